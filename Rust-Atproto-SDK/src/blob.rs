@@ -56,6 +56,8 @@ pub fn detect_mime_type(filename: &str) -> &'static str {
         "image/gif"
     } else if lowercase.ends_with(".webp") {
         "image/webp"
+    } else if lowercase.ends_with(".avif") {
+        "image/avif"
     }
     // Video types
     else if lowercase.ends_with(".mp4") {
@@ -65,6 +67,14 @@ pub fn detect_mime_type(filename: &str) -> &'static str {
     } else if lowercase.ends_with(".webm") {
         "video/webm"
     }
+    // Audio types
+    else if lowercase.ends_with(".mp3") {
+        "audio/mpeg"
+    } else if lowercase.ends_with(".ogg") || lowercase.ends_with(".oga") || lowercase.ends_with(".opus") {
+        "audio/ogg"
+    } else if lowercase.ends_with(".m4a") {
+        "audio/mp4"
+    }
     // Default
     else {
         "application/octet-stream"
@@ -134,11 +144,28 @@ pub fn detect_mime_type_from_data(data: &[u8]) -> Option<&'static str> {
         return Some("image/webp");
     }
 
-    // MP4: Check for "ftyp" box
+    // MP4, AVIF and M4A: Check for "ftyp" box; AVIF's major brand is
+    // "avif" (still) or "avis" (sequence), M4A's is "M4A "
     if data.len() >= 12 && data[4] == b'f' && data[5] == b't' && data[6] == b'y' && data[7] == b'p' {
+        if &data[8..12] == b"avif" || &data[8..12] == b"avis" {
+            return Some("image/avif");
+        }
+        if &data[8..12] == b"M4A " {
+            return Some("audio/mp4");
+        }
         return Some("video/mp4");
     }
 
+    // Ogg: "OggS"
+    if &data[..4] == b"OggS" {
+        return Some("audio/ogg");
+    }
+
+    // MP3: ID3 tag, or an MPEG audio frame sync (11 set bits)
+    if &data[..3] == b"ID3" || (data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+        return Some("audio/mpeg");
+    }
+
     None
 }
 
@@ -231,6 +258,24 @@ mod tests {
         assert_eq!(detect_mime_type_from_data(&webp), Some("image/webp"));
     }
 
+    #[test]
+    fn test_detect_mime_type_from_data_avif() {
+        let avif = b"\x00\x00\x00\x1cftypavif".to_vec();
+        assert_eq!(detect_mime_type_from_data(&avif), Some("image/avif"));
+        let mp4 = b"\x00\x00\x00\x1cftypisom".to_vec();
+        assert_eq!(detect_mime_type_from_data(&mp4), Some("video/mp4"));
+        assert_eq!(detect_mime_type("photo.avif"), "image/avif");
+    }
+
+    #[test]
+    fn test_detect_mime_type_from_data_audio() {
+        assert_eq!(detect_mime_type_from_data(b"ID3\x04\x00"), Some("audio/mpeg"));
+        assert_eq!(detect_mime_type_from_data(&[0xFF, 0xFB, 0x90, 0x64]), Some("audio/mpeg"));
+        assert_eq!(detect_mime_type_from_data(b"OggS\x00\x02"), Some("audio/ogg"));
+        assert_eq!(detect_mime_type_from_data(b"\x00\x00\x00\x20ftypM4A "), Some("audio/mp4"));
+        assert_eq!(detect_mime_type("voice.m4a"), "audio/mp4");
+    }
+
     #[test]
     fn test_detect_mime_type_from_data_too_short() {
         let short = vec![0xFF];
@@ -343,6 +343,31 @@ pub fn is_valid_nsid(nsid: &str) -> bool {
     AtUri::is_valid_nsid(nsid)
 }
 
+/// Validates a record key
+///
+/// Record keys are 1-512 characters from `A-Za-z0-9.-_:~`, and may not be
+/// `.` or `..`.
+///
+/// # Examples
+///
+/// ```
+/// use atproto::syntax::is_valid_rkey;
+///
+/// assert!(is_valid_rkey("3jzfcijpj2z2a"));
+/// assert!(is_valid_rkey("self"));
+/// assert!(!is_valid_rkey(".."));
+/// assert!(!is_valid_rkey("a/b"));
+/// ```
+pub fn is_valid_rkey(rkey: &str) -> bool {
+    !rkey.is_empty()
+        && rkey.len() <= 512
+        && rkey != "."
+        && rkey != ".."
+        && rkey
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | ':' | '~'))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,38 @@
+//! Build script: embeds the git commit hash for the version endpoint.
+//!
+//! `GIT_COMMIT` overrides detection for builds without a `.git` directory
+//! (e.g. Docker builds).
+
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/index");
+
+    let commit = std::env::var("GIT_COMMIT")
+        .ok()
+        .filter(|s| !s.trim().is_empty())
+        .or_else(|| git(&["rev-parse", "HEAD"]))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Uncommitted changes to tracked files mark the build as dirty
+    let dirty = git(&["status", "--porcelain", "--untracked-files=no"])
+        .map(|out| !out.is_empty())
+        .unwrap_or(false);
+
+    println!("cargo:rustc-env=AURORA_GIT_COMMIT={}", commit.trim());
+    println!("cargo:rustc-env=AURORA_GIT_DIRTY={}", dirty);
+    println!(
+        "cargo:rustc-env=AURORA_BUILD_PROFILE={}",
+        std::env::var("PROFILE").unwrap_or_else(|_| "unknown".to_string())
+    );
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -662,6 +662,9 @@ CREATE TABLE IF NOT EXISTS session (
     created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
     expires_at DATETIME NOT NULL,
     app_password_name TEXT,
+    last_used_at DATETIME,
+    ip_address TEXT,
+    user_agent TEXT,
     FOREIGN KEY (did) REFERENCES account(did) ON DELETE CASCADE
 );
 CREATE INDEX idx_session_did ON session(did);
@@ -737,6 +740,7 @@ CREATE TABLE IF NOT EXISTS admin_roles (
     id INTEGER PRIMARY KEY AUTOINCREMENT,
     did TEXT NOT NULL UNIQUE,
     role TEXT NOT NULL CHECK(role IN ('superadmin', 'admin', 'moderator')),
+    domain TEXT,
     granted_by TEXT,
     granted_at TEXT NOT NULL,
     revoked INTEGER NOT NULL DEFAULT 0,
@@ -877,6 +881,434 @@ CREATE INDEX IF NOT EXISTS idx_repo_seq_event_type ON repo_seq(event_type);
 CREATE INDEX IF NOT EXISTS idx_repo_seq_sequenced_at ON repo_seq(sequenced_at);
 CREATE INDEX IF NOT EXISTS idx_repo_seq_seq_invalidated ON repo_seq(seq, invalidated);
 
+-- Mirrored repositories (read-only copies of remote repos)
+CREATE TABLE IF NOT EXISTS mirror_repo (
+    did TEXT PRIMARY KEY NOT NULL,
+    source TEXT NOT NULL,
+    head TEXT,
+    rev TEXT,
+    last_seq INTEGER,
+    status TEXT NOT NULL DEFAULT 'pending',
+    last_error TEXT,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_mirror_repo_source ON mirror_repo(source);
+
+-- Per-account rate limit overrides
+CREATE TABLE IF NOT EXISTS rate_limit_override (
+    did TEXT PRIMARY KEY NOT NULL,
+    multiplier REAL,
+    requests_per_second INTEGER,
+    burst_size INTEGER,
+    reason TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT
+);
+
+-- Account data exports (takeout archives)
+CREATE TABLE IF NOT EXISTS account_export (
+    id TEXT PRIMARY KEY NOT NULL,
+    did TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    progress INTEGER NOT NULL DEFAULT 0,
+    size INTEGER,
+    error TEXT,
+    created_at TEXT NOT NULL,
+    completed_at TEXT,
+    expires_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_account_export_did ON account_export(did, created_at);
+
+-- Reserved and blocked handle patterns (admin-managed)
+CREATE TABLE IF NOT EXISTS reserved_handle (
+    pattern TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL,
+    reason TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+-- Default impersonation targets and profanity patterns
+INSERT OR IGNORE INTO reserved_handle (pattern, kind, reason, created_by, created_at)
+SELECT pattern, kind, reason, 'system', strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+FROM (
+    SELECT 'admin' AS pattern, 'reserved' AS kind, 'impersonation' AS reason
+    UNION ALL SELECT 'administrator', 'reserved', 'impersonation'
+    UNION ALL SELECT 'root', 'reserved', 'impersonation'
+    UNION ALL SELECT 'system', 'reserved', 'impersonation'
+    UNION ALL SELECT 'support', 'reserved', 'impersonation'
+    UNION ALL SELECT 'help', 'reserved', 'impersonation'
+    UNION ALL SELECT 'helpdesk', 'reserved', 'impersonation'
+    UNION ALL SELECT 'moderation', 'reserved', 'impersonation'
+    UNION ALL SELECT 'moderator', 'reserved', 'impersonation'
+    UNION ALL SELECT 'mod', 'reserved', 'impersonation'
+    UNION ALL SELECT 'mods', 'reserved', 'impersonation'
+    UNION ALL SELECT 'staff', 'reserved', 'impersonation'
+    UNION ALL SELECT 'team', 'reserved', 'impersonation'
+    UNION ALL SELECT 'official', 'reserved', 'impersonation'
+    UNION ALL SELECT 'security', 'reserved', 'impersonation'
+    UNION ALL SELECT 'safety', 'reserved', 'impersonation'
+    UNION ALL SELECT 'trust-and-safety', 'reserved', 'impersonation'
+    UNION ALL SELECT 'abuse', 'reserved', 'impersonation'
+    UNION ALL SELECT 'legal', 'reserved', 'impersonation'
+    UNION ALL SELECT 'postmaster', 'reserved', 'impersonation'
+    UNION ALL SELECT 'hostmaster', 'reserved', 'impersonation'
+    UNION ALL SELECT 'webmaster', 'reserved', 'impersonation'
+    UNION ALL SELECT 'noreply', 'reserved', 'impersonation'
+    UNION ALL SELECT 'no-reply', 'reserved', 'impersonation'
+    UNION ALL SELECT 'bsky', 'reserved', 'impersonation'
+    UNION ALL SELECT 'bluesky', 'reserved', 'impersonation'
+    UNION ALL SELECT 'atproto', 'reserved', 'impersonation'
+    UNION ALL SELECT 'api', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'www', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'mail', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'pds', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'status', 'reserved', 'infrastructure'
+    UNION ALL SELECT '*fuck*', 'blocked', 'profanity'
+    UNION ALL SELECT '*shit*', 'blocked', 'profanity'
+    UNION ALL SELECT '*cunt*', 'blocked', 'profanity'
+    UNION ALL SELECT '*whore*', 'blocked', 'profanity'
+);
+
+-- Custom domain handle verification state (re-checked in the background)
+CREATE TABLE IF NOT EXISTS handle_verification (
+    did TEXT PRIMARY KEY NOT NULL,
+    handle TEXT NOT NULL,
+    method TEXT NOT NULL,
+    status TEXT NOT NULL,
+    verified_at TEXT NOT NULL,
+    last_checked_at TEXT NOT NULL,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS handle_verification_checked_idx ON handle_verification(status, last_checked_at);
+
+-- Central index of repository heads (mirrors each actor store's repo_root)
+-- Backs com.atproto.sync.listRepos and getLatestCommit without opening actor databases
+CREATE TABLE IF NOT EXISTS repo_head (
+    did TEXT PRIMARY KEY NOT NULL,
+    cid TEXT NOT NULL,
+    rev TEXT NOT NULL,
+    updated_at TEXT NOT NULL,
+    record_count INTEGER
+);
+
+-- Indexes backing com.atproto.admin.getAuditLog filters and retention pruning
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_timestamp ON admin_audit_log(timestamp);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_admin ON admin_audit_log(admin_did);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_subject ON admin_audit_log(subject_did);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_action ON admin_audit_log(action);
+
+-- External moderation webhooks and their delivery queue
+CREATE TABLE IF NOT EXISTS moderation_webhook (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS moderation_webhook_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER NOT NULL,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    response_status INTEGER,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    delivered_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_delivery_due ON moderation_webhook_delivery(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_webhook_delivery_webhook ON moderation_webhook_delivery(webhook_id);
+
+-- Last processed sequence number per inbound firehose source
+CREATE TABLE IF NOT EXISTS firehose_cursor (
+    source TEXT PRIMARY KEY NOT NULL,
+    cursor INTEGER NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Record counts per repository and collection (mirrors each actor store's collection_stat)
+CREATE TABLE IF NOT EXISTS repo_collection_stat (
+    did TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    record_count INTEGER NOT NULL,
+    PRIMARY KEY (did, collection)
+);
+
+CREATE INDEX IF NOT EXISTS idx_repo_collection_stat_collection ON repo_collection_stat(collection);
+
+-- Handle changes per account
+-- redirect_until keeps the old handle resolving to the DID (and unavailable
+-- to other accounts) until then; NULL once released or taken back
+CREATE TABLE IF NOT EXISTS handle_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    old_handle TEXT NOT NULL,
+    new_handle TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    redirect_until TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_handle_history_did ON handle_history(did, id);
+CREATE INDEX IF NOT EXISTS idx_handle_history_old_handle ON handle_history(old_handle, redirect_until);
+
+-- Resumable blob uploads in progress
+-- Data is appended to <data>/uploads/<id>.part; upload_offset is the number
+-- of bytes received so far
+CREATE TABLE IF NOT EXISTS blob_upload_session (
+    id TEXT PRIMARY KEY,
+    creator_did TEXT NOT NULL,
+    mime_type TEXT,
+    length INTEGER NOT NULL,
+    upload_offset INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_blob_upload_session_creator ON blob_upload_session(creator_did, expires_at);
+CREATE INDEX IF NOT EXISTS idx_blob_upload_session_expires_at ON blob_upload_session(expires_at);
+
+-- Purged accounts, kept after the account row is deleted
+-- account_seq/identity_seq are the #account (deleted) and #identity events
+-- sequenced for the deletion
+CREATE TABLE IF NOT EXISTS account_deletion (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    handle TEXT NOT NULL,
+    deleted_at TEXT NOT NULL,
+    blobs_deleted INTEGER NOT NULL DEFAULT 0,
+    plc_tombstoned BOOLEAN NOT NULL DEFAULT 0,
+    account_seq INTEGER,
+    identity_seq INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_account_deletion_did ON account_deletion(did);
+
+-- Background job state: admin enable/disable overrides and run history
+CREATE TABLE IF NOT EXISTS job_state (
+    name TEXT PRIMARY KEY NOT NULL,
+    enabled BOOLEAN,                -- admin override; NULL follows config
+    last_started_at TEXT,
+    last_finished_at TEXT,
+    last_duration_ms INTEGER,
+    last_status TEXT,               -- 'succeeded' | 'failed'
+    last_result TEXT,               -- summary or error message
+    next_run_at TEXT,
+    run_count INTEGER NOT NULL DEFAULT 0,
+    failure_count INTEGER NOT NULL DEFAULT 0
+);
+
+-- Outbound email queue and delivery status
+CREATE TABLE IF NOT EXISTS email_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    provider TEXT,
+    provider_message_id TEXT,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    sent_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_delivery_due ON email_delivery(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_email_delivery_recipient ON email_delivery(recipient);
+
+-- Record references to each blob, maintained as records are written and deleted
+-- NULL for blobs stored before references were counted; those are never collected
+ALTER TABLE blob_metadata ADD COLUMN ref_count INTEGER;
+-- When ref_count last dropped to zero (or the blob was stored unreferenced)
+ALTER TABLE blob_metadata ADD COLUMN unreferenced_since DATETIME;
+
+CREATE INDEX IF NOT EXISTS idx_blob_unreferenced ON blob_metadata(unreferenced_since) WHERE ref_count = 0;
+
+-- IP, CIDR and ASN blocklist for createAccount and createSession
+CREATE TABLE IF NOT EXISTS ip_blocklist (
+    pattern TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL,
+    note TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT,
+    hit_count INTEGER NOT NULL DEFAULT 0,
+    last_hit_at TEXT
+);
+
+-- Indexes for admin account search: handle prefixes (LIKE is case-insensitive)
+-- and exact email lookups regardless of case
+CREATE INDEX IF NOT EXISTS idx_account_handle_nocase ON account(handle COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS idx_account_email_lower ON account(lower(email));
+
+-- Personal access tokens: scoped bearer tokens for bots and automation.
+-- Only a SHA-256 hash of each token is stored.
+CREATE TABLE IF NOT EXISTS api_token (
+    id TEXT PRIMARY KEY,
+    did TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME,
+    last_used_at DATETIME,
+    UNIQUE (did, name),
+    FOREIGN KEY (did) REFERENCES account(did) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_api_token_did ON api_token(did);
+
+-- JWT signing keys, identified by the `kid` header of the tokens they sign.
+-- `key` is the sealed HMAC secret or ES256 private key (PKCS#8); NULL marks
+-- the configured PDS_JWT_SECRET, which signed tokens without a `kid`.
+-- Retired keys keep validating tokens until the grace period ends.
+CREATE TABLE IF NOT EXISTS jwt_signing_key (
+    kid TEXT PRIMARY KEY,
+    algorithm TEXT NOT NULL,
+    key BLOB,
+    created_at DATETIME NOT NULL,
+    retired_at DATETIME
+);
+
+-- Handle changes waiting for email confirmation
+-- The UNIQUE handle is the reservation: while a change is pending, no other
+-- account can request or take the same handle
+CREATE TABLE IF NOT EXISTS pending_handle_change (
+    did TEXT PRIMARY KEY NOT NULL,
+    handle TEXT UNIQUE NOT NULL,
+    token TEXT UNIQUE NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+
+-- Labels applied and removed by this server's moderators
+-- Append-only: removing a label inserts a negation (neg = 1). The id orders
+-- the com.atproto.label.subscribeLabels stream and is its cursor.
+CREATE TABLE IF NOT EXISTS label (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uri TEXT NOT NULL,
+    cid TEXT,
+    val TEXT NOT NULL,
+    neg BOOLEAN NOT NULL DEFAULT 0,
+    src TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    expires_at TEXT,
+    sig BLOB
+);
+CREATE INDEX IF NOT EXISTS idx_label_uri ON label(uri);
+
+-- Tombstones of deleted records, kept for PDS_TOMBSTONE_RETENTION_DAYS
+-- Written after the deleting commit lands in the actor store; the
+-- tombstone_retention job purges rows past the window.
+CREATE TABLE IF NOT EXISTS record_tombstone (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    uri TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    cid TEXT,
+    rev TEXT NOT NULL,
+    deleted_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_record_tombstone_did ON record_tombstone(did, id);
+CREATE INDEX IF NOT EXISTS idx_record_tombstone_deleted_at ON record_tombstone(deleted_at);
+
+-- Webhooks registered by accounts for events in their own repo, and their
+-- delivery queue (kept separate from the moderation webhooks)
+CREATE TABLE IF NOT EXISTS repo_webhook (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT NOT NULL DEFAULT '',
+    collections TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_repo_webhook_did ON repo_webhook(did);
+
+CREATE TABLE IF NOT EXISTS repo_webhook_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER NOT NULL,
+    did TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    response_status INTEGER,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    delivered_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_repo_webhook_delivery_due ON repo_webhook_delivery(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_repo_webhook_delivery_webhook ON repo_webhook_delivery(webhook_id);
+
+-- ActivityPub bridge (experimental): actors of accounts that turned the
+-- bridge on, their fediverse followers, the notes translated from their
+-- posts, and the outbound activity queue
+CREATE TABLE IF NOT EXISTS activitypub_actor (
+    did TEXT PRIMARY KEY NOT NULL,
+    private_key TEXT NOT NULL,
+    public_key TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS activitypub_follower (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    actor_id TEXT NOT NULL,
+    inbox TEXT NOT NULL,
+    shared_inbox TEXT,
+    created_at TEXT NOT NULL,
+    UNIQUE(did, actor_id)
+);
+
+CREATE TABLE IF NOT EXISTS activitypub_note (
+    did TEXT NOT NULL,
+    rkey TEXT NOT NULL,
+    note TEXT NOT NULL,
+    published TEXT NOT NULL,
+    PRIMARY KEY (did, rkey)
+);
+CREATE INDEX IF NOT EXISTS idx_activitypub_note_published ON activitypub_note(did, published);
+
+CREATE TABLE IF NOT EXISTS activitypub_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    inbox TEXT NOT NULL,
+    activity TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    response_status INTEGER,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    delivered_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_activitypub_delivery_due ON activitypub_delivery(status, next_attempt_at);
+
+-- Whether an image blob is an animated GIF or WebP
+ALTER TABLE blob_metadata ADD COLUMN animated INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE temp_blob_metadata ADD COLUMN animated INTEGER NOT NULL DEFAULT 0;
+
+-- Audio blobs: length and waveform peaks (one byte per bucket) for voice posts
+ALTER TABLE blob_metadata ADD COLUMN duration_ms INTEGER;
+ALTER TABLE blob_metadata ADD COLUMN waveform BLOB;
+ALTER TABLE temp_blob_metadata ADD COLUMN duration_ms INTEGER;
+ALTER TABLE temp_blob_metadata ADD COLUMN waveform BLOB;
+
+-- listReposByCollection pages through one collection's repos in DID order
+CREATE INDEX IF NOT EXISTS idx_repo_collection_stat_collection_did ON repo_collection_stat(collection, did);
+
 -- Migration tracking table
 CREATE TABLE IF NOT EXISTS _sqlx_migrations (
     version BIGINT PRIMARY KEY NOT NULL,
@@ -895,7 +1327,37 @@ VALUES
     (20250106000001, 'admin_moderation', CURRENT_TIMESTAMP, 1, X'00', 0),
     (20250107000001, 'blob_metadata_extensions', CURRENT_TIMESTAMP, 1, X'00', 0),
     (20250108000001, 'temp_blob_table', CURRENT_TIMESTAMP, 1, X'00', 0),
-    (20250109000001, 'plc_keys', CURRENT_TIMESTAMP, 1, X'00', 0);
+    (20250109000001, 'plc_keys', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250110000001, 'session_metadata', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250111000001, 'mirror_repo', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250112000001, 'rate_limit_override', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250113000001, 'account_export', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250114000001, 'reserved_handle', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250115000001, 'handle_verification', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250116000001, 'repo_head', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250117000001, 'admin_audit_log_index', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250118000001, 'moderation_webhook', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250119000001, 'firehose_cursor', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250120000001, 'repo_stats', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250121000001, 'admin_role_domain', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250122000001, 'handle_history', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250123000001, 'blob_upload_session', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250124000001, 'account_deletion', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250125000001, 'job_state', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250126000001, 'email_queue', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250127000001, 'blob_refs', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250128000001, 'ip_blocklist', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250129000001, 'account_search', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250130000001, 'api_tokens', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250131000001, 'jwt_signing_keys', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250201000001, 'pending_handle_change', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250202000001, 'label', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250203000001, 'record_tombstone', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250204000001, 'repo_webhook', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250205000001, 'activitypub', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250206000001, 'blob_animated', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250207000001, 'blob_audio', CURRENT_TIMESTAMP, 1, X'00', 0),
+    (20250208000001, 'repo_collection_stat_by_collection', CURRENT_TIMESTAMP, 1, X'00', 0);
 EOSQL
 
         if [ "$ADMIN_DID" != "__PLACEHOLDER_ADMIN_DID__" ] && [ -n "$ADMIN_DID" ]; then
@@ -0,0 +1,4 @@
+-- Track session usage and the client a session was created from
+ALTER TABLE session ADD COLUMN last_used_at DATETIME;
+ALTER TABLE session ADD COLUMN ip_address TEXT;
+ALTER TABLE session ADD COLUMN user_agent TEXT;
@@ -0,0 +1,12 @@
+-- Mirrored repositories (read-only copies of remote repos)
+CREATE TABLE IF NOT EXISTS mirror_repo (
+    did TEXT PRIMARY KEY NOT NULL,
+    source TEXT NOT NULL,
+    head TEXT,
+    rev TEXT,
+    last_seq INTEGER,
+    status TEXT NOT NULL DEFAULT 'pending',
+    last_error TEXT,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+CREATE INDEX IF NOT EXISTS idx_mirror_repo_source ON mirror_repo(source);
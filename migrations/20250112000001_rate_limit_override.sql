@@ -0,0 +1,11 @@
+-- Per-account rate limit overrides
+CREATE TABLE IF NOT EXISTS rate_limit_override (
+    did TEXT PRIMARY KEY NOT NULL,
+    multiplier REAL,
+    requests_per_second INTEGER,
+    burst_size INTEGER,
+    reason TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT
+);
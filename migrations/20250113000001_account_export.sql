@@ -0,0 +1,13 @@
+-- Account data exports (takeout archives)
+CREATE TABLE IF NOT EXISTS account_export (
+    id TEXT PRIMARY KEY NOT NULL,
+    did TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    progress INTEGER NOT NULL DEFAULT 0,
+    size INTEGER,
+    error TEXT,
+    created_at TEXT NOT NULL,
+    completed_at TEXT,
+    expires_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_account_export_did ON account_export(did, created_at);
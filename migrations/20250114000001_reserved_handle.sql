@@ -0,0 +1,50 @@
+-- Reserved and blocked handle patterns (admin-managed)
+CREATE TABLE IF NOT EXISTS reserved_handle (
+    pattern TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL,
+    reason TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+-- Default impersonation targets and profanity patterns
+INSERT OR IGNORE INTO reserved_handle (pattern, kind, reason, created_by, created_at)
+SELECT pattern, kind, reason, 'system', strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+FROM (
+    SELECT 'admin' AS pattern, 'reserved' AS kind, 'impersonation' AS reason
+    UNION ALL SELECT 'administrator', 'reserved', 'impersonation'
+    UNION ALL SELECT 'root', 'reserved', 'impersonation'
+    UNION ALL SELECT 'system', 'reserved', 'impersonation'
+    UNION ALL SELECT 'support', 'reserved', 'impersonation'
+    UNION ALL SELECT 'help', 'reserved', 'impersonation'
+    UNION ALL SELECT 'helpdesk', 'reserved', 'impersonation'
+    UNION ALL SELECT 'moderation', 'reserved', 'impersonation'
+    UNION ALL SELECT 'moderator', 'reserved', 'impersonation'
+    UNION ALL SELECT 'mod', 'reserved', 'impersonation'
+    UNION ALL SELECT 'mods', 'reserved', 'impersonation'
+    UNION ALL SELECT 'staff', 'reserved', 'impersonation'
+    UNION ALL SELECT 'team', 'reserved', 'impersonation'
+    UNION ALL SELECT 'official', 'reserved', 'impersonation'
+    UNION ALL SELECT 'security', 'reserved', 'impersonation'
+    UNION ALL SELECT 'safety', 'reserved', 'impersonation'
+    UNION ALL SELECT 'trust-and-safety', 'reserved', 'impersonation'
+    UNION ALL SELECT 'abuse', 'reserved', 'impersonation'
+    UNION ALL SELECT 'legal', 'reserved', 'impersonation'
+    UNION ALL SELECT 'postmaster', 'reserved', 'impersonation'
+    UNION ALL SELECT 'hostmaster', 'reserved', 'impersonation'
+    UNION ALL SELECT 'webmaster', 'reserved', 'impersonation'
+    UNION ALL SELECT 'noreply', 'reserved', 'impersonation'
+    UNION ALL SELECT 'no-reply', 'reserved', 'impersonation'
+    UNION ALL SELECT 'bsky', 'reserved', 'impersonation'
+    UNION ALL SELECT 'bluesky', 'reserved', 'impersonation'
+    UNION ALL SELECT 'atproto', 'reserved', 'impersonation'
+    UNION ALL SELECT 'api', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'www', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'mail', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'pds', 'reserved', 'infrastructure'
+    UNION ALL SELECT 'status', 'reserved', 'infrastructure'
+    UNION ALL SELECT '*fuck*', 'blocked', 'profanity'
+    UNION ALL SELECT '*shit*', 'blocked', 'profanity'
+    UNION ALL SELECT '*cunt*', 'blocked', 'profanity'
+    UNION ALL SELECT '*whore*', 'blocked', 'profanity'
+);
@@ -0,0 +1,13 @@
+-- Custom domain handle verification state (re-checked in the background)
+CREATE TABLE IF NOT EXISTS handle_verification (
+    did TEXT PRIMARY KEY NOT NULL,
+    handle TEXT NOT NULL,
+    method TEXT NOT NULL,
+    status TEXT NOT NULL,
+    verified_at TEXT NOT NULL,
+    last_checked_at TEXT NOT NULL,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
+
+CREATE INDEX IF NOT EXISTS handle_verification_checked_idx ON handle_verification(status, last_checked_at);
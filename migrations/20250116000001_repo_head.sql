@@ -0,0 +1,8 @@
+-- Central index of repository heads (mirrors each actor store's repo_root)
+-- Backs com.atproto.sync.listRepos and getLatestCommit without opening actor databases
+CREATE TABLE IF NOT EXISTS repo_head (
+    did TEXT PRIMARY KEY NOT NULL,
+    cid TEXT NOT NULL,
+    rev TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
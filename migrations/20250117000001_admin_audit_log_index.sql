@@ -0,0 +1,5 @@
+-- Indexes backing com.atproto.admin.getAuditLog filters and retention pruning
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_timestamp ON admin_audit_log(timestamp);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_admin ON admin_audit_log(admin_did);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_subject ON admin_audit_log(subject_did);
+CREATE INDEX IF NOT EXISTS idx_admin_audit_log_action ON admin_audit_log(action);
@@ -0,0 +1,6 @@
+-- Last processed sequence number per inbound firehose source
+CREATE TABLE IF NOT EXISTS firehose_cursor (
+    source TEXT PRIMARY KEY NOT NULL,
+    cursor INTEGER NOT NULL,
+    updated_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,12 @@
+-- Record counts per repository and collection (mirrors each actor store's collection_stat)
+-- Backs com.atproto.admin.getRepoStats and getStats without opening actor databases
+ALTER TABLE repo_head ADD COLUMN record_count INTEGER;
+
+CREATE TABLE IF NOT EXISTS repo_collection_stat (
+    did TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    record_count INTEGER NOT NULL,
+    PRIMARY KEY (did, collection)
+);
+
+CREATE INDEX IF NOT EXISTS idx_repo_collection_stat_collection ON repo_collection_stat(collection);
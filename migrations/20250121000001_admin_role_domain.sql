@@ -0,0 +1,17 @@
+-- Domain-scoped admin roles for virtual hosts
+-- A role with a domain only covers accounts whose handle is under that
+-- handle domain; NULL keeps the role server-wide.
+CREATE TABLE IF NOT EXISTS admin_roles (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL UNIQUE,
+    role TEXT NOT NULL CHECK(role IN ('superadmin', 'admin', 'moderator')),
+    granted_by TEXT,
+    granted_at TEXT NOT NULL,
+    revoked INTEGER NOT NULL DEFAULT 0,
+    revoked_at TEXT,
+    revoked_by TEXT,
+    notes TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_admin_role_did ON admin_roles(did);
+
+ALTER TABLE admin_roles ADD COLUMN domain TEXT;
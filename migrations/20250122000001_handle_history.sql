@@ -0,0 +1,13 @@
+-- Handle changes per account
+-- redirect_until keeps the old handle resolving to the DID (and unavailable
+-- to other accounts) until then; NULL once released or taken back
+CREATE TABLE IF NOT EXISTS handle_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    old_handle TEXT NOT NULL,
+    new_handle TEXT NOT NULL,
+    changed_at TEXT NOT NULL,
+    redirect_until TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_handle_history_did ON handle_history(did, id);
+CREATE INDEX IF NOT EXISTS idx_handle_history_old_handle ON handle_history(old_handle, redirect_until);
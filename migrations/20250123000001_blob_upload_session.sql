@@ -0,0 +1,14 @@
+-- Resumable blob uploads in progress
+-- Data is appended to <data>/uploads/<id>.part; upload_offset is the number
+-- of bytes received so far
+CREATE TABLE IF NOT EXISTS blob_upload_session (
+    id TEXT PRIMARY KEY,
+    creator_did TEXT NOT NULL,
+    mime_type TEXT,
+    length INTEGER NOT NULL,
+    upload_offset INTEGER NOT NULL DEFAULT 0,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_blob_upload_session_creator ON blob_upload_session(creator_did, expires_at);
+CREATE INDEX IF NOT EXISTS idx_blob_upload_session_expires_at ON blob_upload_session(expires_at);
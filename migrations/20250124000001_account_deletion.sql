@@ -0,0 +1,14 @@
+-- Purged accounts, kept after the account row is deleted
+-- account_seq/identity_seq are the #account (deleted) and #identity events
+-- sequenced for the deletion
+CREATE TABLE IF NOT EXISTS account_deletion (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    handle TEXT NOT NULL,
+    deleted_at TEXT NOT NULL,
+    blobs_deleted INTEGER NOT NULL DEFAULT 0,
+    plc_tombstoned BOOLEAN NOT NULL DEFAULT 0,
+    account_seq INTEGER,
+    identity_seq INTEGER
+);
+CREATE INDEX IF NOT EXISTS idx_account_deletion_did ON account_deletion(did);
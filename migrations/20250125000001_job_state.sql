@@ -0,0 +1,13 @@
+-- Background job state: admin enable/disable overrides and run history
+CREATE TABLE IF NOT EXISTS job_state (
+    name TEXT PRIMARY KEY NOT NULL,
+    enabled BOOLEAN,                -- admin override; NULL follows config
+    last_started_at TEXT,
+    last_finished_at TEXT,
+    last_duration_ms INTEGER,
+    last_status TEXT,               -- 'succeeded' | 'failed'
+    last_result TEXT,               -- summary or error message
+    next_run_at TEXT,
+    run_count INTEGER NOT NULL DEFAULT 0,
+    failure_count INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,20 @@
+-- Outbound email queue and delivery status
+CREATE TABLE IF NOT EXISTS email_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kind TEXT NOT NULL,
+    recipient TEXT NOT NULL,
+    sender TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    body TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    provider TEXT,
+    provider_message_id TEXT,
+    last_error TEXT,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    sent_at TEXT
+);
+
+CREATE INDEX IF NOT EXISTS idx_email_delivery_due ON email_delivery(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_email_delivery_recipient ON email_delivery(recipient);
@@ -0,0 +1,7 @@
+-- Record references to each blob, maintained as records are written and deleted
+-- NULL for blobs stored before references were counted; those are never collected
+ALTER TABLE blob_metadata ADD COLUMN ref_count INTEGER;
+-- When ref_count last dropped to zero (or the blob was stored unreferenced)
+ALTER TABLE blob_metadata ADD COLUMN unreferenced_since DATETIME;
+
+CREATE INDEX IF NOT EXISTS idx_blob_unreferenced ON blob_metadata(unreferenced_since) WHERE ref_count = 0;
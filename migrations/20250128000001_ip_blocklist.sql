@@ -0,0 +1,11 @@
+-- IP, CIDR and ASN blocklist for createAccount and createSession
+CREATE TABLE IF NOT EXISTS ip_blocklist (
+    pattern TEXT PRIMARY KEY NOT NULL,
+    kind TEXT NOT NULL,
+    note TEXT,
+    created_by TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT,
+    hit_count INTEGER NOT NULL DEFAULT 0,
+    last_hit_at TEXT
+);
@@ -0,0 +1,4 @@
+-- Indexes for admin account search: handle prefixes (LIKE is case-insensitive)
+-- and exact email lookups regardless of case
+CREATE INDEX IF NOT EXISTS idx_account_handle_nocase ON account(handle COLLATE NOCASE);
+CREATE INDEX IF NOT EXISTS idx_account_email_lower ON account(lower(email));
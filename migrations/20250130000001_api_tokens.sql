@@ -0,0 +1,15 @@
+-- Personal access tokens: scoped bearer tokens for bots and automation.
+-- Only a SHA-256 hash of each token is stored.
+CREATE TABLE IF NOT EXISTS api_token (
+    id TEXT PRIMARY KEY,
+    did TEXT NOT NULL,
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    scopes TEXT NOT NULL,
+    created_at DATETIME NOT NULL,
+    expires_at DATETIME,
+    last_used_at DATETIME,
+    UNIQUE (did, name),
+    FOREIGN KEY (did) REFERENCES account(did) ON DELETE CASCADE
+);
+CREATE INDEX IF NOT EXISTS idx_api_token_did ON api_token(did);
@@ -0,0 +1,11 @@
+-- JWT signing keys, identified by the `kid` header of the tokens they sign.
+-- `key` is the sealed HMAC secret or ES256 private key (PKCS#8); NULL marks
+-- the configured PDS_JWT_SECRET, which signed tokens without a `kid`.
+-- Retired keys keep validating tokens until the grace period ends.
+CREATE TABLE IF NOT EXISTS jwt_signing_key (
+    kid TEXT PRIMARY KEY,
+    algorithm TEXT NOT NULL,
+    key BLOB,
+    created_at DATETIME NOT NULL,
+    retired_at DATETIME
+);
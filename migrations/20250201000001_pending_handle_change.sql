@@ -0,0 +1,10 @@
+-- Handle changes waiting for email confirmation
+-- The UNIQUE handle is the reservation: while a change is pending, no other
+-- account can request or take the same handle
+CREATE TABLE IF NOT EXISTS pending_handle_change (
+    did TEXT PRIMARY KEY NOT NULL,
+    handle TEXT UNIQUE NOT NULL,
+    token TEXT UNIQUE NOT NULL,
+    created_at TEXT NOT NULL,
+    expires_at TEXT NOT NULL
+);
@@ -0,0 +1,16 @@
+-- Labels applied and removed by this server's moderators
+-- Append-only: removing a label inserts a negation (neg = 1). The id orders
+-- the com.atproto.label.subscribeLabels stream and is its cursor.
+CREATE TABLE IF NOT EXISTS label (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    uri TEXT NOT NULL,
+    cid TEXT,
+    val TEXT NOT NULL,
+    neg BOOLEAN NOT NULL DEFAULT 0,
+    src TEXT NOT NULL,
+    created_at TEXT NOT NULL,
+    created_by TEXT NOT NULL,
+    expires_at TEXT,
+    sig BLOB
+);
+CREATE INDEX IF NOT EXISTS idx_label_uri ON label(uri);
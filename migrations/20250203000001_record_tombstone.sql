@@ -0,0 +1,14 @@
+-- Tombstones of deleted records, kept for PDS_TOMBSTONE_RETENTION_DAYS
+-- Written after the deleting commit lands in the actor store; the
+-- tombstone_retention job purges rows past the window.
+CREATE TABLE IF NOT EXISTS record_tombstone (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    uri TEXT NOT NULL,
+    collection TEXT NOT NULL,
+    cid TEXT,
+    rev TEXT NOT NULL,
+    deleted_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_record_tombstone_did ON record_tombstone(did, id);
+CREATE INDEX IF NOT EXISTS idx_record_tombstone_deleted_at ON record_tombstone(deleted_at);
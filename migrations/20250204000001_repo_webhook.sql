@@ -0,0 +1,30 @@
+-- Webhooks registered by accounts for events in their own repo, and their
+-- delivery queue (kept separate from the moderation webhooks)
+CREATE TABLE IF NOT EXISTS repo_webhook (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    url TEXT NOT NULL,
+    secret TEXT NOT NULL,
+    events TEXT NOT NULL DEFAULT '',
+    collections TEXT NOT NULL DEFAULT '',
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_repo_webhook_did ON repo_webhook(did);
+
+CREATE TABLE IF NOT EXISTS repo_webhook_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    webhook_id INTEGER NOT NULL,
+    did TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    response_status INTEGER,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    delivered_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_repo_webhook_delivery_due ON repo_webhook_delivery(status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_repo_webhook_delivery_webhook ON repo_webhook_delivery(webhook_id);
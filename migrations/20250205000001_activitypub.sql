@@ -0,0 +1,43 @@
+-- ActivityPub bridge (experimental): actors of accounts that turned the
+-- bridge on, their fediverse followers, the notes translated from their
+-- posts, and the outbound activity queue
+CREATE TABLE IF NOT EXISTS activitypub_actor (
+    did TEXT PRIMARY KEY NOT NULL,
+    private_key TEXT NOT NULL,
+    public_key TEXT NOT NULL,
+    created_at TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS activitypub_follower (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    actor_id TEXT NOT NULL,
+    inbox TEXT NOT NULL,
+    shared_inbox TEXT,
+    created_at TEXT NOT NULL,
+    UNIQUE(did, actor_id)
+);
+
+CREATE TABLE IF NOT EXISTS activitypub_note (
+    did TEXT NOT NULL,
+    rkey TEXT NOT NULL,
+    note TEXT NOT NULL,
+    published TEXT NOT NULL,
+    PRIMARY KEY (did, rkey)
+);
+CREATE INDEX IF NOT EXISTS idx_activitypub_note_published ON activitypub_note(did, published);
+
+CREATE TABLE IF NOT EXISTS activitypub_delivery (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    did TEXT NOT NULL,
+    inbox TEXT NOT NULL,
+    activity TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'pending',
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    response_status INTEGER,
+    created_at TEXT NOT NULL,
+    next_attempt_at TEXT,
+    delivered_at TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_activitypub_delivery_due ON activitypub_delivery(status, next_attempt_at);
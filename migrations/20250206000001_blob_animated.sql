@@ -0,0 +1,3 @@
+-- Whether an image blob is an animated GIF or WebP
+ALTER TABLE blob_metadata ADD COLUMN animated INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE temp_blob_metadata ADD COLUMN animated INTEGER NOT NULL DEFAULT 0;
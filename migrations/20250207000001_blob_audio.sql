@@ -0,0 +1,5 @@
+-- Audio blobs: length and waveform peaks (one byte per bucket) for voice posts
+ALTER TABLE blob_metadata ADD COLUMN duration_ms INTEGER;
+ALTER TABLE blob_metadata ADD COLUMN waveform BLOB;
+ALTER TABLE temp_blob_metadata ADD COLUMN duration_ms INTEGER;
+ALTER TABLE temp_blob_metadata ADD COLUMN waveform BLOB;
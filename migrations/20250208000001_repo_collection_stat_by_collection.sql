@@ -0,0 +1,2 @@
+-- listReposByCollection pages through one collection's repos in DID order
+CREATE INDEX IF NOT EXISTS idx_repo_collection_stat_collection_did ON repo_collection_stat(collection, did);
@@ -137,7 +137,7 @@ PDS_DID_PLC_URL=https://plc.directory
 PDS_SERVICE_HANDLE_DOMAINS=localhost
 
 # Email Configuration (optional)
-# PDS_EMAIL_SMTP_URL=smtp://localhost:1025
+# PDS_EMAIL_SMTP_URL=smtp://localhost:1025?tls=none
 # PDS_EMAIL_FROM_ADDRESS=noreply@localhost
 
 # Invite Configuration
@@ -0,0 +1,197 @@
+/// Personal access tokens
+///
+/// Long-lived bearer tokens for bots and automation. Unlike app passwords
+/// they are sent as-is (no login, no refresh) and carry explicit scopes; the
+/// `enforce_api_token_scopes` middleware refuses requests outside them, and
+/// account management (passwords, sessions, identity, exports) is never
+/// allowed. Only a SHA-256 hash of each token is stored, and a token is
+/// shown once, when it is created.
+use crate::error::{PdsError, PdsResult};
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Prefix identifying a bearer token as an API token
+pub const API_TOKEN_PREFIX: &str = "aurora_pat_";
+
+/// Longest expiry that can be requested
+pub const MAX_EXPIRY_DAYS: u32 = 365;
+
+/// What an API token may do
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ApiTokenScope {
+    /// Read records and repos, and AppView reads on the account's behalf
+    ReadRepo,
+    /// Create, update and delete records and upload blobs
+    WriteRepo,
+    /// Fetch and list blobs
+    ReadBlobs,
+    /// Admin API, for accounts that hold an admin role
+    Admin,
+}
+
+impl ApiTokenScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ReadRepo => "read-repo",
+            Self::WriteRepo => "write-repo",
+            Self::ReadBlobs => "read-blobs",
+            Self::Admin => "admin",
+        }
+    }
+
+    pub fn parse(scope: &str) -> PdsResult<Self> {
+        match scope {
+            "read-repo" => Ok(Self::ReadRepo),
+            "write-repo" => Ok(Self::WriteRepo),
+            "read-blobs" => Ok(Self::ReadBlobs),
+            "admin" => Ok(Self::Admin),
+            other => Err(PdsError::Validation(format!("Unknown API token scope: {}", other))),
+        }
+    }
+
+    /// Parse a space-separated scope list as stored
+    pub fn parse_list(scopes: &str) -> PdsResult<Vec<Self>> {
+        scopes.split_whitespace().map(Self::parse).collect()
+    }
+}
+
+/// What a request needs when it is made with an API token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScopeRequirement {
+    /// Any valid token
+    Any,
+    Scope(ApiTokenScope),
+    /// Not available to API tokens
+    Forbidden,
+}
+
+/// Scope an API token needs for a request
+pub fn required_scope(method: &Method, path: &str) -> ScopeRequirement {
+    use ApiTokenScope::*;
+    use ScopeRequirement::*;
+
+    let read_or_write = if method == Method::GET || method == Method::HEAD {
+        Scope(ReadRepo)
+    } else {
+        Scope(WriteRepo)
+    };
+
+    let Some(nsid) = path.strip_prefix("/xrpc/") else {
+        return match path {
+            p if p.starts_with("/uploads") => Scope(WriteRepo),
+            p if p.starts_with("/blob/") || p.starts_with("/img/") => Scope(ReadBlobs),
+            _ => Any,
+        };
+    };
+
+    match nsid {
+        "com.atproto.server.getSession" => Any,
+        "com.atproto.sync.getBlob" | "com.atproto.sync.listBlobs" | "com.atproto.repo.listMissingBlobs" => {
+            Scope(ReadBlobs)
+        }
+        "com.atproto.repo.uploadBlob" => Scope(WriteRepo),
+        n if n.starts_with("com.atproto.admin.") || n.starts_with("tools.ozone.") => Scope(Admin),
+        n if n.starts_with("com.atproto.repo.")
+            || n.starts_with("com.atproto.sync.")
+            || n.starts_with("app.aurora.sync.")
+            || n.starts_with("app.bsky.")
+            || n.starts_with("chat.bsky.") =>
+        {
+            read_or_write
+        }
+        _ => Forbidden,
+    }
+}
+
+/// Stored form of a token
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// API token details (without the token itself)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiTokenInfo {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<ApiTokenScope>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+/// Create API token request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    pub scopes: Vec<String>,
+    /// Days until the token stops working (never, if unset)
+    pub expires_in_days: Option<u32>,
+}
+
+/// Create API token response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiTokenResponse {
+    #[serde(flatten)]
+    pub info: ApiTokenInfo,
+    /// The token; only returned here
+    pub token: String,
+}
+
+/// List API tokens response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListApiTokensResponse {
+    pub tokens: Vec<ApiTokenInfo>,
+}
+
+/// Revoke API token request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeApiTokenRequest {
+    pub id: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_required_scope() {
+        use ApiTokenScope::*;
+        use ScopeRequirement::*;
+
+        let cases = [
+            (Method::GET, "/xrpc/com.atproto.repo.listRecords", Scope(ReadRepo)),
+            (Method::GET, "/xrpc/app.bsky.feed.getTimeline", Scope(ReadRepo)),
+            (Method::POST, "/xrpc/com.atproto.repo.createRecord", Scope(WriteRepo)),
+            (Method::POST, "/xrpc/com.atproto.repo.uploadBlob", Scope(WriteRepo)),
+            (Method::GET, "/xrpc/com.atproto.sync.getBlob", Scope(ReadBlobs)),
+            (Method::GET, "/blob/bafkrei", Scope(ReadBlobs)),
+            (Method::GET, "/xrpc/com.atproto.admin.getAccountInfo", Scope(Admin)),
+            (Method::GET, "/xrpc/com.atproto.server.getSession", Any),
+            (Method::POST, "/xrpc/com.atproto.server.createAppPassword", Forbidden),
+            (Method::POST, "/xrpc/com.atproto.identity.updateHandle", Forbidden),
+            (Method::GET, "/xrpc/app.aurora.account.downloadExport", Forbidden),
+            (Method::GET, "/health", Any),
+        ];
+        for (method, path, expected) in cases {
+            assert_eq!(required_scope(&method, path), expected, "{} {}", method, path);
+        }
+    }
+
+    #[test]
+    fn test_parse_scopes() {
+        assert_eq!(
+            ApiTokenScope::parse_list("read-repo  read-blobs").unwrap(),
+            vec![ApiTokenScope::ReadRepo, ApiTokenScope::ReadBlobs]
+        );
+        assert!(ApiTokenScope::parse("write").is_err());
+        assert_eq!(serde_json::to_value(ApiTokenScope::WriteRepo).unwrap(), "write-repo");
+    }
+}
@@ -0,0 +1,406 @@
+/// Registration challenges
+///
+/// Signups that need no invite code can be made to pass a challenge first:
+/// a CAPTCHA (hCaptcha or Cloudflare Turnstile, verified server-side with
+/// the provider) or a proof-of-work puzzle issued and checked locally.
+/// Clients fetch the parameters from `getRegistrationChallenge` and send the
+/// solution as `challenge_token` with `createAccount`.
+///
+/// With a signup threshold set, the challenge only kicks in while more
+/// accounts than that were created in the past hour, so a quiet server stays
+/// frictionless and an abuse wave is slowed down.
+///
+/// Other verifiers can be plugged in with `RegistrationChallenge::with_verifier`.
+use crate::{
+    account::AccountManager,
+    config::RegistrationChallengeConfig,
+    error::{PdsError, PdsResult},
+};
+use async_trait::async_trait;
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+const HCAPTCHA_VERIFY_URL: &str = "https://api.hcaptcha.com/siteverify";
+const TURNSTILE_VERIFY_URL: &str = "https://challenges.cloudflare.com/turnstile/v0/siteverify";
+
+/// How long an issued proof-of-work challenge can be solved for
+const POW_CHALLENGE_MINUTES: i64 = 10;
+
+/// What a client needs to solve a challenge
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeParams {
+    /// "hcaptcha", "turnstile", "pow" or a custom verifier's name
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// CAPTCHA widget site key
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_key: Option<String>,
+    /// Proof-of-work challenge to solve
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub challenge: Option<String>,
+    /// Leading zero bits required of `sha256("<challenge>:<nonce>")`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub difficulty: Option<u32>,
+}
+
+/// Whether a signup needs a challenge right now, and how to solve it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChallengeDescription {
+    pub required: bool,
+    #[serde(flatten)]
+    pub params: Option<ChallengeParams>,
+}
+
+/// A registration challenge implementation
+#[async_trait]
+pub trait ChallengeVerifier: Send + Sync {
+    /// Parameters for a new challenge
+    fn issue(&self) -> ChallengeParams;
+
+    /// Check a solution; `client_ip` is passed on to CAPTCHA providers
+    async fn verify(&self, token: &str, client_ip: Option<&str>) -> PdsResult<()>;
+}
+
+/// Registration challenge gate for createAccount
+pub struct RegistrationChallenge {
+    verifier: Option<Arc<dyn ChallengeVerifier>>,
+    signup_threshold: u32,
+    account_manager: Arc<AccountManager>,
+}
+
+impl RegistrationChallenge {
+    /// Build the gate for the configured provider
+    ///
+    /// Proof-of-work challenges are signed with `signing_secret`.
+    pub fn from_config(
+        config: &RegistrationChallengeConfig,
+        signing_secret: &str,
+        account_manager: Arc<AccountManager>,
+    ) -> PdsResult<Self> {
+        let verifier: Option<Arc<dyn ChallengeVerifier>> = match config.provider.as_str() {
+            "none" => None,
+            "hcaptcha" | "turnstile" => Some(Arc::new(CaptchaVerifier::new(config)?)),
+            "pow" => Some(Arc::new(ProofOfWork::new(signing_secret, config.pow_difficulty))),
+            other => {
+                return Err(PdsError::Validation(format!(
+                    "Invalid registration challenge provider: {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            verifier,
+            signup_threshold: config.signup_threshold,
+            account_manager,
+        })
+    }
+
+    /// Use a custom verifier instead of the configured provider
+    pub fn with_verifier(mut self, verifier: Arc<dyn ChallengeVerifier>) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    /// Whether new signups currently have to pass the challenge
+    pub async fn is_required(&self) -> PdsResult<bool> {
+        if self.verifier.is_none() {
+            return Ok(false);
+        }
+        if self.signup_threshold == 0 {
+            return Ok(true);
+        }
+
+        let recent = self
+            .account_manager
+            .count_accounts_since(Utc::now() - Duration::hours(1))
+            .await?;
+        Ok(recent >= self.signup_threshold as i64)
+    }
+
+    /// Describe the challenge a client should solve before signing up
+    pub async fn describe(&self) -> PdsResult<ChallengeDescription> {
+        Ok(ChallengeDescription {
+            required: self.is_required().await?,
+            params: self.verifier.as_ref().map(|v| v.issue()),
+        })
+    }
+
+    /// Refuse a signup without a valid solution while the challenge is required
+    pub async fn check(&self, token: Option<&str>, client_ip: Option<&str>) -> PdsResult<()> {
+        let Some(verifier) = &self.verifier else {
+            return Ok(());
+        };
+        if !self.is_required().await? {
+            return Ok(());
+        }
+
+        match token.filter(|t| !t.is_empty()) {
+            Some(token) => verifier.verify(token, client_ip).await,
+            None => Err(PdsError::Validation(
+                "Registration challenge required (see app.aurora.server.getRegistrationChallenge)".to_string(),
+            )),
+        }
+    }
+}
+
+/// hCaptcha or Turnstile token check
+struct CaptchaVerifier {
+    kind: &'static str,
+    verify_url: &'static str,
+    site_key: String,
+    secret_key: String,
+    http_client: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+    #[serde(rename = "error-codes", default)]
+    error_codes: Vec<String>,
+}
+
+impl CaptchaVerifier {
+    fn new(config: &RegistrationChallengeConfig) -> PdsResult<Self> {
+        let (kind, verify_url) = match config.provider.as_str() {
+            "hcaptcha" => ("hcaptcha", HCAPTCHA_VERIFY_URL),
+            _ => ("turnstile", TURNSTILE_VERIFY_URL),
+        };
+        let missing = || PdsError::Validation(format!("{} requires a site key and a secret key", kind));
+
+        Ok(Self {
+            kind,
+            verify_url,
+            site_key: config.site_key.clone().ok_or_else(missing)?,
+            secret_key: config.secret_key.clone().ok_or_else(missing)?,
+            http_client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        })
+    }
+}
+
+#[async_trait]
+impl ChallengeVerifier for CaptchaVerifier {
+    fn issue(&self) -> ChallengeParams {
+        ChallengeParams {
+            kind: self.kind.to_string(),
+            site_key: Some(self.site_key.clone()),
+            challenge: None,
+            difficulty: None,
+        }
+    }
+
+    async fn verify(&self, token: &str, client_ip: Option<&str>) -> PdsResult<()> {
+        let mut form = vec![("secret", self.secret_key.as_str()), ("response", token)];
+        if let Some(ip) = client_ip {
+            form.push(("remoteip", ip));
+        }
+
+        let response: SiteVerifyResponse = self
+            .http_client
+            .post(self.verify_url)
+            .form(&form)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| PdsError::Internal(format!("{} verification failed: {}", self.kind, e)))?
+            .json()
+            .await
+            .map_err(|e| PdsError::Internal(format!("Invalid {} response: {}", self.kind, e)))?;
+
+        if response.success {
+            Ok(())
+        } else {
+            tracing::info!("Rejected {} token: {:?}", self.kind, response.error_codes);
+            Err(PdsError::Validation("Registration challenge failed".to_string()))
+        }
+    }
+}
+
+/// Stateless proof-of-work puzzle
+///
+/// A challenge is `<expires>.<random>.<mac>`; the client finds a nonce so
+/// that `sha256("<challenge>:<nonce>")` starts with `difficulty` zero bits
+/// and sends `<challenge>:<nonce>`. Solved challenges are remembered until
+/// they expire so each one creates at most one account.
+struct ProofOfWork {
+    secret: Vec<u8>,
+    difficulty: u32,
+    used: Mutex<HashMap<String, i64>>,
+}
+
+impl ProofOfWork {
+    fn new(secret: &str, difficulty: u32) -> Self {
+        Self {
+            secret: secret.as_bytes().to_vec(),
+            difficulty,
+            used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        mac.update(b"registration-pow:");
+        mac.update(payload.as_bytes());
+        mac.update(b":");
+        mac.update(self.difficulty.to_string().as_bytes());
+        mac
+    }
+}
+
+#[async_trait]
+impl ChallengeVerifier for ProofOfWork {
+    fn issue(&self) -> ChallengeParams {
+        let expires = (Utc::now() + Duration::minutes(POW_CHALLENGE_MINUTES)).timestamp();
+        let mut random = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut random);
+        let payload = format!("{}.{}", expires, hex::encode(random));
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+
+        ChallengeParams {
+            kind: "pow".to_string(),
+            site_key: None,
+            challenge: Some(format!("{}.{}", payload, signature)),
+            difficulty: Some(self.difficulty),
+        }
+    }
+
+    async fn verify(&self, token: &str, _client_ip: Option<&str>) -> PdsResult<()> {
+        let invalid = || PdsError::Validation("Registration challenge failed".to_string());
+
+        let (challenge, _nonce) = token.rsplit_once(':').ok_or_else(invalid)?;
+        let (payload, signature) = challenge.rsplit_once('.').ok_or_else(invalid)?;
+        let signature = hex::decode(signature).map_err(|_| invalid())?;
+        self.mac(payload).verify_slice(&signature).map_err(|_| invalid())?;
+
+        let now = Utc::now().timestamp();
+        let expires: i64 = payload
+            .split_once('.')
+            .and_then(|(expires, _)| expires.parse().ok())
+            .ok_or_else(invalid)?;
+        if expires < now {
+            return Err(PdsError::Validation("Registration challenge has expired".to_string()));
+        }
+
+        if leading_zero_bits(&Sha256::digest(token.as_bytes())) < self.difficulty {
+            return Err(invalid());
+        }
+
+        let mut used = self.used.lock().unwrap();
+        used.retain(|_, expires| *expires >= now);
+        if used.insert(challenge.to_string(), expires).is_some() {
+            return Err(PdsError::Validation("Registration challenge was already used".to_string()));
+        }
+
+        Ok(())
+    }
+}
+
+fn leading_zero_bits(hash: &[u8]) -> u32 {
+    let mut bits = 0;
+    for byte in hash {
+        bits += byte.leading_zeros();
+        if *byte != 0 {
+            break;
+        }
+    }
+    bits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn solve(challenge: &str, difficulty: u32) -> String {
+        (0u64..)
+            .map(|nonce| format!("{}:{}", challenge, nonce))
+            .find(|token| leading_zero_bits(&Sha256::digest(token.as_bytes())) >= difficulty)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_leading_zero_bits() {
+        assert_eq!(leading_zero_bits(&[0x00, 0x0f, 0xff]), 12);
+        assert_eq!(leading_zero_bits(&[0x80]), 0);
+        assert_eq!(leading_zero_bits(&[0x00, 0x00]), 16);
+    }
+
+    #[tokio::test]
+    async fn test_proof_of_work() {
+        let pow = ProofOfWork::new("test-secret", 8);
+        let params = pow.issue();
+        assert_eq!(params.kind, "pow");
+        assert_eq!(params.difficulty, Some(8));
+        let challenge = params.challenge.unwrap();
+
+        let token = solve(&challenge, 8);
+        pow.verify(&token, None).await.unwrap();
+
+        // Each challenge is good for one signup
+        let again = solve(&challenge, 9);
+        assert!(pow.verify(&again, None).await.is_err());
+
+        // Unsolved, forged and foreign challenges are refused
+        let challenge = pow.issue().challenge.unwrap();
+        let unsolved = (0u64..)
+            .map(|nonce| format!("{}:{}", challenge, nonce))
+            .find(|token| leading_zero_bits(&Sha256::digest(token.as_bytes())) < 8)
+            .unwrap();
+        assert!(pow.verify(&unsolved, None).await.is_err());
+
+        let forged = format!("9{}", challenge);
+        assert!(pow.verify(&solve(&forged, 8), None).await.is_err());
+
+        let other = ProofOfWork::new("other-secret", 8);
+        assert!(other.verify(&solve(&challenge, 8), None).await.is_err());
+
+        // Expired challenges are refused even when solved
+        let payload = format!("{}.00", Utc::now().timestamp() - 1);
+        let expired = format!("{}.{}", payload, hex::encode(pow.mac(&payload).finalize().into_bytes()));
+        assert!(pow.verify(&solve(&expired, 8), None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_signup_threshold() {
+        let manager = Arc::new(crate::account::manager::tests::create_test_manager().await);
+        let config = RegistrationChallengeConfig {
+            provider: "pow".to_string(),
+            pow_difficulty: 4,
+            signup_threshold: 2,
+            ..Default::default()
+        };
+        let gate = RegistrationChallenge::from_config(&config, "secret", manager.clone()).unwrap();
+
+        // Quiet server: no challenge needed
+        assert!(!gate.describe().await.unwrap().required);
+        gate.check(None, None).await.unwrap();
+
+        for handle in ["challengeone.test", "challengetwo.test"] {
+            manager
+                .create_account(handle.to_string(), None, "password123".to_string(), None)
+                .await
+                .unwrap();
+        }
+
+        let description = gate.describe().await.unwrap();
+        assert!(description.required);
+        assert!(matches!(gate.check(None, None).await, Err(PdsError::Validation(_))));
+        let token = solve(&description.params.unwrap().challenge.unwrap(), 4);
+        gate.check(Some(&token), None).await.unwrap();
+
+        // Disabled provider never challenges
+        let off = RegistrationChallenge::from_config(&RegistrationChallengeConfig::default(), "secret", manager)
+            .unwrap();
+        assert!(!off.describe().await.unwrap().required);
+        assert!(off.describe().await.unwrap().params.is_none());
+    }
+}
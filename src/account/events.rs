@@ -0,0 +1,244 @@
+/// Account lifecycle events for the firehose
+///
+/// Relays and AppViews track accounts through `#identity` and `#account`
+/// events. Every lifecycle transition goes through `AccountEvents` so the
+/// right events are sequenced, in the right order:
+///
+/// - creation: `#identity` with the handle, then `#account` active
+/// - handle change or other identity change: `#identity`
+/// - deactivation, reactivation, takedown, suspension, restore: `#account`
+/// - deletion: `#account` with status `deleted`, then a bare `#identity`
+///
+/// `active` and `status` are never passed in: they are read back from the
+/// account and its moderation actions after the change, so an event always
+/// describes the account as it now is (restoring one of two takedowns still
+/// reports `takendown`).
+use crate::{
+    admin::ModerationManager,
+    error::PdsResult,
+    sequencer::{
+        events::{AccountEvent, AccountStatus, IdentityEvent},
+        Sequencer,
+    },
+};
+use chrono::{DateTime, Utc};
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+
+/// Sequences `#identity` and `#account` events for account changes
+#[derive(Clone)]
+pub struct AccountEvents {
+    db: SqlitePool,
+    sequencer: Arc<Sequencer>,
+    moderation: Arc<ModerationManager>,
+}
+
+impl AccountEvents {
+    pub fn new(db: SqlitePool, sequencer: Arc<Sequencer>, moderation: Arc<ModerationManager>) -> Self {
+        Self { db, sequencer, moderation }
+    }
+
+    /// Current `active` flag and status of an account
+    ///
+    /// Takedowns win over suspensions, which win over a pending deletion;
+    /// an account that no longer exists is `deleted`.
+    pub async fn status(&self, did: &str) -> PdsResult<(bool, Option<AccountStatus>)> {
+        let row = sqlx::query("SELECT taken_down, deactivated_at FROM account WHERE did = ?1")
+            .bind(did)
+            .fetch_optional(&self.db)
+            .await?;
+        let Some(row) = row else {
+            return Ok((false, Some(AccountStatus::Deleted)));
+        };
+        let taken_down: bool = row.try_get("taken_down")?;
+        let deactivated_at: Option<DateTime<Utc>> = row.try_get("deactivated_at")?;
+
+        let status = if taken_down || self.moderation.is_taken_down(did).await? {
+            Some(AccountStatus::Takendown)
+        } else if self.moderation.is_suspended(did).await? {
+            Some(AccountStatus::Suspended)
+        } else if deactivated_at.is_some() {
+            Some(AccountStatus::Deactivated)
+        } else {
+            None
+        };
+        Ok((status.is_none(), status))
+    }
+
+    /// A new account: its identity, then its (active) status
+    pub async fn created(&self, did: &str, handle: &str) -> PdsResult<()> {
+        self.identity_changed(did, Some(handle)).await?;
+        self.status_changed(did).await?;
+        Ok(())
+    }
+
+    /// The account's handle or DID document changed
+    ///
+    /// `handle` is `None` when the account no longer has a valid handle.
+    pub async fn identity_changed(&self, did: &str, handle: Option<&str>) -> PdsResult<i64> {
+        self.sequencer
+            .sequence_identity(IdentityEvent::new(did.to_string(), handle.map(str::to_string)))
+            .await
+    }
+
+    /// The account was deactivated, reactivated, taken down, suspended or
+    /// restored; sequences its current status
+    pub async fn status_changed(&self, did: &str) -> PdsResult<i64> {
+        let (active, status) = self.status(did).await?;
+        self.sequencer
+            .sequence_account(AccountEvent::new(did.to_string(), active, status))
+            .await
+    }
+
+    /// The account is being deleted
+    ///
+    /// Sequenced while the account row still exists so the events carry it
+    /// into the purge record. Both events are attempted; either seq is
+    /// `None` if that one failed.
+    pub async fn deleted(&self, did: &str) -> (Option<i64>, Option<i64>) {
+        let account_seq = match self
+            .sequencer
+            .sequence_account(AccountEvent::new(did.to_string(), false, Some(AccountStatus::Deleted)))
+            .await
+        {
+            Ok(seq) => Some(seq),
+            Err(e) => {
+                tracing::warn!(did = %did, error = %e, "account_deletion_sequence_failed");
+                None
+            }
+        };
+        let identity_seq = match self.identity_changed(did, None).await {
+            Ok(seq) => Some(seq),
+            Err(e) => {
+                tracing::warn!(did = %did, error = %e, "identity_tombstone_sequence_failed");
+                None
+            }
+        };
+        (account_seq, identity_seq)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        admin::moderation::ModerationAction,
+        sequencer::{SeqEvent, SequencerConfig},
+    };
+    use chrono::Duration;
+
+    async fn setup() -> (AccountEvents, Arc<ModerationManager>, SqlitePool) {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(
+            r#"
+            CREATE TABLE account (
+                did TEXT PRIMARY KEY,
+                taken_down INTEGER NOT NULL DEFAULT 0,
+                deactivated_at DATETIME
+            );
+            CREATE TABLE account_moderation (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                did TEXT NOT NULL,
+                action TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                moderated_by TEXT NOT NULL,
+                moderated_at TEXT NOT NULL,
+                expires_at TEXT,
+                reversed INTEGER NOT NULL DEFAULT 0,
+                reversed_at TEXT,
+                reversed_by TEXT,
+                reversal_reason TEXT,
+                report_id INTEGER,
+                notes TEXT
+            );
+            CREATE TABLE repo_seq (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                did TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                event BLOB NOT NULL,
+                invalidated INTEGER NOT NULL DEFAULT 0,
+                sequenced_at TEXT NOT NULL
+            );
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let sequencer = Arc::new(Sequencer::new(db.clone(), SequencerConfig::default()));
+        let moderation = Arc::new(ModerationManager::new(db.clone()));
+        (AccountEvents::new(db.clone(), sequencer, moderation.clone()), moderation, db)
+    }
+
+    /// The latest `#account` event for `did`
+    async fn last_account_event(events: &AccountEvents, did: &str) -> AccountEvent {
+        events
+            .sequencer
+            .get_events_for_did(did, 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .find_map(|evt| match evt {
+                SeqEvent::Account { evt, .. } => Some(evt),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_events() {
+        let (events, moderation, db) = setup().await;
+        let did = "did:plc:alice";
+        sqlx::query("INSERT INTO account (did) VALUES (?1)").bind(did).execute(&db).await.unwrap();
+
+        // Creation: identity first, then an active account
+        events.created(did, "alice.test").await.unwrap();
+        let seq = events.sequencer.get_events_for_did(did, 10).await.unwrap();
+        assert!(matches!(&seq[1], SeqEvent::Identity { evt, .. } if evt.handle.as_deref() == Some("alice.test")));
+        assert!(matches!(&seq[0], SeqEvent::Account { evt, .. } if evt.active && evt.status.is_none()));
+
+        // Suspension, then a takedown on top of it
+        let suspension = moderation
+            .apply_action(did, ModerationAction::Suspend, "spam", "admin", Some(Duration::days(1)), None, None)
+            .await
+            .unwrap();
+        events.status_changed(did).await.unwrap();
+        assert_eq!(last_account_event(&events, did).await.status, Some(AccountStatus::Suspended));
+
+        let takedown = moderation
+            .apply_action(did, ModerationAction::Takedown, "abuse", "admin", None, None, None)
+            .await
+            .unwrap();
+        events.status_changed(did).await.unwrap();
+        let evt = last_account_event(&events, did).await;
+        assert!(!evt.active);
+        assert_eq!(evt.status, Some(AccountStatus::Takendown));
+
+        // Lifting the suspension leaves the takedown in place
+        moderation.reverse_action(suspension.id, "admin", "appeal").await.unwrap();
+        events.status_changed(did).await.unwrap();
+        assert_eq!(last_account_event(&events, did).await.status, Some(AccountStatus::Takendown));
+
+        // Restored, then deactivated pending deletion
+        moderation.reverse_action(takedown.id, "admin", "appeal").await.unwrap();
+        events.status_changed(did).await.unwrap();
+        let evt = last_account_event(&events, did).await;
+        assert!(evt.active);
+        assert_eq!(evt.status, None);
+
+        sqlx::query("UPDATE account SET deactivated_at = ?1 WHERE did = ?2")
+            .bind(Utc::now())
+            .bind(did)
+            .execute(&db)
+            .await
+            .unwrap();
+        events.status_changed(did).await.unwrap();
+        assert_eq!(last_account_event(&events, did).await.status, Some(AccountStatus::Deactivated));
+
+        // Deletion: #account deleted, then a bare #identity
+        let (account_seq, identity_seq) = events.deleted(did).await;
+        assert!(account_seq.unwrap() < identity_seq.unwrap());
+        sqlx::query("DELETE FROM account WHERE did = ?1").bind(did).execute(&db).await.unwrap();
+        assert_eq!(events.status(did).await.unwrap(), (false, Some(AccountStatus::Deleted)));
+    }
+}
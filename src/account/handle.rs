@@ -0,0 +1,227 @@
+/// Handle normalization and syntax validation
+///
+/// Handles are DNS names. Every place that accepts a handle from a client
+/// (registration, updateHandle, login, resolution) runs it through
+/// [`normalize_handle`] so the stored and looked-up forms always agree:
+/// surrounding whitespace, a leading `@` and a trailing root `.` are removed,
+/// internationalized labels are converted to punycode (`xn--`) and the result
+/// is lowercased.
+///
+/// Internationalized labels are only accepted if they can't pass for a
+/// different ASCII handle: labels mixing Latin, Greek and Cyrillic letters,
+/// and labels made up entirely of look-alikes of ASCII letters (e.g. Cyrillic
+/// "раураl" for "paypal"), are rejected.
+use crate::error::{PdsError, PdsResult};
+
+/// Maximum length of a handle (DNS name limit)
+pub const MAX_HANDLE_LENGTH: usize = 253;
+
+/// Maximum length of a single label
+pub const MAX_LABEL_LENGTH: usize = 63;
+
+/// Normalize a client-supplied handle and validate its syntax
+pub fn normalize_handle(input: &str) -> PdsResult<String> {
+    let trimmed = input.trim();
+    let trimmed = trimmed.strip_prefix('@').unwrap_or(trimmed);
+    let trimmed = trimmed.strip_suffix('.').unwrap_or(trimmed);
+
+    if trimmed.is_empty() {
+        return Err(PdsError::Validation("Handle cannot be empty".to_string()));
+    }
+
+    let handle = if trimmed.is_ascii() {
+        trimmed.to_ascii_lowercase()
+    } else {
+        idna::domain_to_ascii(trimmed)
+            .map_err(|_| PdsError::Validation(format!("Invalid internationalized handle: {}", trimmed)))?
+    };
+
+    validate_handle_syntax(&handle)?;
+    check_confusables(&handle)?;
+
+    Ok(handle)
+}
+
+/// Validate the syntax of a normalized (ASCII, lowercase) handle
+///
+/// At least two labels of 1-63 characters from `a-z`, `0-9` and `-`, not
+/// starting or ending with a hyphen; the last label must start with a letter.
+pub fn validate_handle_syntax(handle: &str) -> PdsResult<()> {
+    if handle.len() > MAX_HANDLE_LENGTH {
+        return Err(PdsError::Validation(format!(
+            "Handle too long (max {} characters)",
+            MAX_HANDLE_LENGTH
+        )));
+    }
+
+    let labels: Vec<&str> = handle.split('.').collect();
+    if labels.len() < 2 {
+        return Err(PdsError::Validation(
+            "Handle must be a domain name with at least two labels".to_string(),
+        ));
+    }
+
+    for label in &labels {
+        if label.is_empty() {
+            return Err(PdsError::Validation("Handle contains an empty label".to_string()));
+        }
+        if label.len() > MAX_LABEL_LENGTH {
+            return Err(PdsError::Validation(format!(
+                "Handle label too long (max {} characters): {}",
+                MAX_LABEL_LENGTH, label
+            )));
+        }
+        if !label
+            .bytes()
+            .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-')
+        {
+            return Err(PdsError::Validation(format!(
+                "Handle label contains invalid characters: {}",
+                label
+            )));
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(PdsError::Validation(format!(
+                "Handle label cannot start or end with a hyphen: {}",
+                label
+            )));
+        }
+    }
+
+    let tld = labels[labels.len() - 1];
+    if !tld.starts_with(|c: char| c.is_ascii_lowercase()) {
+        return Err(PdsError::Validation(
+            "Handle top-level domain must start with a letter".to_string(),
+        ));
+    }
+
+    Ok(())
+}
+
+/// Letter scripts that are commonly confused with each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Script {
+    Latin,
+    Greek,
+    Cyrillic,
+    Other,
+}
+
+fn script_of(c: char) -> Option<Script> {
+    match c as u32 {
+        0x30..=0x39 | 0x2d => None,
+        0x61..=0x7a | 0x41..=0x5a | 0xc0..=0x24f | 0x1e00..=0x1eff => Some(Script::Latin),
+        0x370..=0x3ff | 0x1f00..=0x1fff => Some(Script::Greek),
+        0x400..=0x52f => Some(Script::Cyrillic),
+        _ => Some(Script::Other),
+    }
+}
+
+/// Greek and Cyrillic lowercase letters that render like ASCII letters
+///
+/// Only true look-alikes; small-capital forms such as Cyrillic "к" or "м"
+/// are left out so ordinary words in those scripts aren't flagged.
+fn ascii_lookalike(c: char) -> Option<char> {
+    Some(match c {
+        // Cyrillic
+        'а' => 'a',
+        'с' => 'c',
+        'ԁ' => 'd',
+        'е' => 'e',
+        'һ' => 'h',
+        'і' => 'i',
+        'ј' => 'j',
+        'ӏ' => 'l',
+        'о' => 'o',
+        'р' => 'p',
+        'ԛ' => 'q',
+        'ѕ' => 's',
+        'ѵ' => 'v',
+        'ԝ' => 'w',
+        'х' => 'x',
+        'у' => 'y',
+        // Greek
+        'α' => 'a',
+        'ι' => 'i',
+        'ν' => 'v',
+        'ο' => 'o',
+        'ρ' => 'p',
+        'υ' => 'u',
+        'χ' => 'x',
+        _ => return None,
+    })
+}
+
+/// Reject internationalized labels that could impersonate another handle
+fn check_confusables(handle: &str) -> PdsResult<()> {
+    for label in handle.split('.').filter(|l| l.starts_with("xn--")) {
+        let (unicode, result) = idna::domain_to_unicode(label);
+        if result.is_err() {
+            return Err(PdsError::Validation(format!("Invalid punycode label: {}", label)));
+        }
+
+        let mut scripts: Vec<Script> = unicode.chars().filter_map(script_of).collect();
+        scripts.sort_by_key(|s| *s as u8);
+        scripts.dedup();
+        let confusable_scripts = scripts.iter().filter(|s| **s != Script::Other).count();
+        if confusable_scripts > 1 {
+            return Err(PdsError::Validation(format!(
+                "Handle label mixes Latin, Greek or Cyrillic letters: {}",
+                unicode
+            )));
+        }
+
+        let all_lookalikes = unicode
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '-' || ascii_lookalike(c).is_some());
+        if all_lookalikes {
+            let skeleton: String = unicode.chars().map(|c| ascii_lookalike(c).unwrap_or(c)).collect();
+            return Err(PdsError::Validation(format!(
+                "Handle label {} is confusable with \"{}\"",
+                unicode, skeleton
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_handle() {
+        assert_eq!(normalize_handle("  @Alice.Bsky.Social. ").unwrap(), "alice.bsky.social");
+        assert_eq!(normalize_handle("münchen.example").unwrap(), "xn--mnchen-3ya.example");
+        assert_eq!(normalize_handle("xn--mnchen-3ya.example").unwrap(), "xn--mnchen-3ya.example");
+        assert_eq!(normalize_handle("москва.example").unwrap(), "xn--80adxhks.example");
+    }
+
+    #[test]
+    fn test_handle_syntax() {
+        assert!(validate_handle_syntax("alice.test").is_ok());
+        assert!(validate_handle_syntax("a-b.c0.example").is_ok());
+
+        assert!(validate_handle_syntax("alice").is_err());
+        assert!(validate_handle_syntax("alice..test").is_err());
+        assert!(validate_handle_syntax("-alice.test").is_err());
+        assert!(validate_handle_syntax("alice-.test").is_err());
+        assert!(validate_handle_syntax("alice_b.test").is_err());
+        assert!(validate_handle_syntax("alice.123").is_err());
+        assert!(validate_handle_syntax(&format!("{}.test", "a".repeat(64))).is_err());
+        assert!(validate_handle_syntax(&format!("{}.test", "a.".repeat(125))).is_err());
+        assert!(normalize_handle("Alice.test").is_ok());
+        assert!(normalize_handle("@").is_err());
+    }
+
+    #[test]
+    fn test_confusable_handles() {
+        // Cyrillic "раураl" with a Latin "l" mixes scripts
+        assert!(normalize_handle("раураl.example").is_err());
+        // All-Cyrillic look-alike of "paypal" minus the "l"
+        assert!(normalize_handle("раура.example").is_err());
+        // Greek omicron in place of "o"
+        assert!(normalize_handle("gοogle.example").is_err());
+    }
+}
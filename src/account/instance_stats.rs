@@ -0,0 +1,165 @@
+/// Cached instance statistics
+///
+/// The nodeinfo document reports user and post counts to crawlers and
+/// monitoring sites, which fetch it on their own schedule. The counts are
+/// computed by the `instance_stats` job and served from memory, so those
+/// requests never run a COUNT over the account table.
+///
+/// A user is active when their repository has had a commit in the period.
+use crate::{
+    actor_store::RepoIndex,
+    clock::{system_clock, Clock},
+    error::PdsResult,
+};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::SqlitePool;
+use std::sync::{Arc, RwLock};
+
+/// Collection counted as posts
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// Counts as of the last refresh
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstanceStatsSnapshot {
+    /// Accounts that are neither taken down nor deactivated
+    pub total_users: i64,
+    /// Of those, accounts with a commit in the last 30 days
+    pub active_month: i64,
+    /// Of those, accounts with a commit in the last 180 days
+    pub active_halfyear: i64,
+    pub local_posts: i64,
+    pub refreshed_at: DateTime<Utc>,
+}
+
+/// Instance statistics, refreshed in the background
+pub struct InstanceStats {
+    db: SqlitePool,
+    index: RepoIndex,
+    clock: Arc<dyn Clock>,
+    current: RwLock<Option<InstanceStatsSnapshot>>,
+}
+
+impl InstanceStats {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            index: RepoIndex::new(db.clone()),
+            db,
+            clock: system_clock(),
+            current: RwLock::new(None),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// The last computed counts, if any
+    pub fn cached(&self) -> Option<InstanceStatsSnapshot> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// The last computed counts, computing them if this is the first use
+    pub async fn get(&self) -> PdsResult<InstanceStatsSnapshot> {
+        match self.cached() {
+            Some(snapshot) => Ok(snapshot),
+            None => self.refresh().await,
+        }
+    }
+
+    /// Recount and replace the cached counts
+    pub async fn refresh(&self) -> PdsResult<InstanceStatsSnapshot> {
+        let now = self.clock.now();
+
+        let total_users: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM account WHERE taken_down = 0 AND deactivated_at IS NULL")
+                .fetch_one(&self.db)
+                .await?;
+
+        let snapshot = InstanceStatsSnapshot {
+            total_users,
+            active_month: self.active_since(now - Duration::days(30)).await?,
+            active_halfyear: self.active_since(now - Duration::days(180)).await?,
+            local_posts: self.index.collection_total(POST_COLLECTION).await?,
+            refreshed_at: now,
+        };
+
+        *self.current.write().unwrap() = Some(snapshot.clone());
+        Ok(snapshot)
+    }
+
+    /// Accounts counted in `total_users` whose repo head moved after `cutoff`
+    async fn active_since(&self, cutoff: DateTime<Utc>) -> PdsResult<i64> {
+        let count: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM account a
+            JOIN repo_head h ON h.did = a.did
+            WHERE a.taken_down = 0 AND a.deactivated_at IS NULL AND h.updated_at >= ?1
+            "#,
+        )
+        .bind(cutoff.to_rfc3339())
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[tokio::test]
+    async fn test_refresh_counts() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let clock = Arc::new(MockClock::fixed());
+        let stats = InstanceStats::new(db.clone()).with_clock(clock.clone());
+
+        for (did, handle, taken_down, days_since_commit) in [
+            ("did:plc:recent", "recent.test", false, Some(2)),
+            ("did:plc:older", "older.test", false, Some(90)),
+            ("did:plc:idle", "idle.test", false, None),
+            ("did:plc:removed", "removed.test", true, Some(1)),
+        ] {
+            sqlx::query("INSERT INTO account (did, handle, password_hash, taken_down) VALUES (?1, ?2, 'hash', ?3)")
+                .bind(did)
+                .bind(handle)
+                .bind(taken_down)
+                .execute(&db)
+                .await
+                .unwrap();
+            if let Some(days) = days_since_commit {
+                sqlx::query("INSERT INTO repo_head (did, cid, rev, updated_at) VALUES (?1, 'bafyhead', 'rev', ?2)")
+                    .bind(did)
+                    .bind((clock.now() - Duration::days(days)).to_rfc3339())
+                    .execute(&db)
+                    .await
+                    .unwrap();
+            }
+        }
+        sqlx::query(
+            "INSERT INTO repo_collection_stat (did, collection, record_count) VALUES ('did:plc:recent', 'app.bsky.feed.post', 7)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        assert!(stats.cached().is_none());
+        let snapshot = stats.get().await.unwrap();
+        assert_eq!(snapshot.total_users, 3);
+        assert_eq!(snapshot.active_month, 1);
+        assert_eq!(snapshot.active_halfyear, 2);
+        assert_eq!(snapshot.local_posts, 7);
+        assert_eq!(snapshot.refreshed_at, clock.now());
+
+        // Served from the cache until the next refresh
+        sqlx::query("UPDATE account SET taken_down = 1 WHERE did = 'did:plc:older'")
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(stats.get().await.unwrap().total_users, 3);
+        assert_eq!(stats.refresh().await.unwrap().total_users, 2);
+    }
+}
@@ -3,26 +3,200 @@
 /// to avoid needing DATABASE_URL during compilation
 
 use crate::{
-    account::AppPasswordInfo,
+    account::{
+        api_token::{hash_token, MAX_EXPIRY_DAYS},
+        normalize_handle, AccountDeletion, AccountSearch, AccountSearchResult, ActiveSessionInfo,
+        ApiTokenInfo, ApiTokenScope, AppPasswordInfo, API_TOKEN_PREFIX, HandleAvailability, HandleChange, PasswordPolicy, ReservedHandleManager, SessionClientInfo,
+    },
+    clock::{random_ids, system_clock, Clock, IdGenerator},
     config::ServerConfig,
+    crypto::{jwt_keys::JwtKeyring, keys::KeyManager, signing_keys::AccountSigningKeys},
     db::account::{Account, Session},
     error::{PdsError, PdsResult},
 };
 use chrono::{DateTime, Duration, Utc};
 use sqlx::{Row, SqlitePool};
 use std::sync::Arc;
-use uuid::Uuid;
+
+/// Minimum interval between `last_used_at` updates for a session
+const SESSION_LAST_USED_RESOLUTION_SECS: i64 = 60;
+
+/// Window `PDS_HANDLE_CHANGES_PER_MONTH` counts changes over
+const HANDLE_CHANGE_WINDOW_DAYS: i64 = 30;
+
+/// How long an emailed handle change code stays valid
+const HANDLE_CHANGE_CODE_MINUTES: i64 = 60;
+
+/// Whether a query failed on a UNIQUE constraint
+fn is_unique_violation(e: &sqlx::Error) -> bool {
+    e.as_database_error().map_or(false, |db| db.is_unique_violation())
+}
+
+/// Map a UNIQUE violation on the account table to the field that clashed
+///
+/// A clashing DID also means the handle is taken: did:web accounts are
+/// named after their handle.
+fn account_conflict(e: sqlx::Error, handle: &str) -> PdsError {
+    match e.as_database_error() {
+        Some(db) if db.is_unique_violation() && db.message().contains("account.email") => PdsError::EmailTaken,
+        Some(db) if db.is_unique_violation() => PdsError::HandleNotAvailable(handle.to_string()),
+        _ => PdsError::Database(e),
+    }
+}
 
 /// Account manager service
 pub struct AccountManager {
     db: SqlitePool,
     config: Arc<ServerConfig>,
+    password_policy: PasswordPolicy,
+    reserved_handles: ReservedHandleManager,
+    keys: Arc<KeyManager>,
+    signing_keys: Arc<AccountSigningKeys>,
+    jwt_keys: Arc<JwtKeyring>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
+}
+
+/// Identity created for a new account
+struct NewIdentity {
+    did: String,
+    /// PLC rotation key reference and its public key (hex)
+    rotation_key: String,
+    rotation_key_public: String,
+    operation_cid: String,
+    /// Commit signing key reference
+    signing_key: String,
 }
 
 impl AccountManager {
     /// Create a new account manager
     pub fn new(db: SqlitePool, config: Arc<ServerConfig>) -> Self {
-        Self { db, config }
+        let password_policy = PasswordPolicy::new(&config.authentication.password_hash)
+            .unwrap_or_else(|e| {
+                tracing::warn!("{}; falling back to default Argon2 parameters", e);
+                PasswordPolicy::default_policy()
+            });
+
+        let reserved_handles = ReservedHandleManager::new(db.clone());
+        let keys = Arc::new(KeyManager::from_plaintext(
+            &config.authentication.repo_signing_key,
+            &config.authentication.plc_rotation_key,
+        ));
+
+        let signing_keys = Arc::new(AccountSigningKeys::new(
+            keys.clone(),
+            config.storage.actor_store_directory.clone(),
+        ));
+
+        let jwt_keys = Arc::new(JwtKeyring::new(
+            &config.authentication.jwt_secret,
+            config.authentication.jwt_keys.clone(),
+        ));
+
+        Self {
+            db,
+            config,
+            password_policy,
+            reserved_handles,
+            keys,
+            signing_keys,
+            jwt_keys,
+            clock: system_clock(),
+            ids: random_ids(),
+        }
+    }
+
+    /// Use the server's key backend for account PLC rotation and signing keys
+    pub fn with_keys(mut self, keys: Arc<KeyManager>) -> Self {
+        self.signing_keys = Arc::new(AccountSigningKeys::new(
+            keys.clone(),
+            self.config.storage.actor_store_directory.clone(),
+        ));
+        self.keys = keys;
+        self
+    }
+
+    /// Sign session tokens with the server's rotating JWT keys
+    pub fn with_jwt_keys(mut self, jwt_keys: Arc<JwtKeyring>) -> Self {
+        self.jwt_keys = jwt_keys;
+        self
+    }
+
+    /// Use `clock` for timestamps and expiry checks
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Use `ids` for session ids and tokens
+    pub fn with_ids(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Per-account commit signing keys
+    pub fn signing_keys(&self) -> &Arc<AccountSigningKeys> {
+        &self.signing_keys
+    }
+
+    /// Reserved/blocked handle list enforced by handle validation
+    pub fn reserved_handles(&self) -> &ReservedHandleManager {
+        &self.reserved_handles
+    }
+
+    /// Check whether a handle could be registered right now
+    ///
+    /// Format errors are returned as errors; reserved and taken handles are
+    /// reported as unavailable, with alternatives when the handle is taken.
+    pub async fn check_handle_availability(&self, handle: &str) -> PdsResult<HandleAvailability> {
+        let handle = normalize_handle(handle)?;
+
+        if let Some(entry) = self
+            .reserved_handles
+            .check(&handle, &self.config.identity.service_handle_domains)
+        {
+            return Ok(HandleAvailability::Unavailable {
+                reason: entry.rejection().to_string(),
+                suggestions: Vec::new(),
+            });
+        }
+
+        if !self.handle_exists(&handle).await? {
+            return Ok(HandleAvailability::Available);
+        }
+
+        Ok(HandleAvailability::Unavailable {
+            reason: format!("Handle {} already taken", handle),
+            suggestions: self.suggest_handles(&handle, 3).await?,
+        })
+    }
+
+    /// Suggest available alternatives by adding a numeric suffix to the name
+    async fn suggest_handles(&self, handle: &str, count: usize) -> PdsResult<Vec<String>> {
+        use rand::Rng;
+
+        let (name, domain) = match handle.split_once('.') {
+            Some(parts) => parts,
+            None => return Ok(Vec::new()),
+        };
+
+        let mut suggestions = Vec::with_capacity(count);
+        for _ in 0..count * 4 {
+            if suggestions.len() >= count {
+                break;
+            }
+            let suffix: u32 = rand::thread_rng().gen_range(10..10000);
+            let candidate = format!("{}{}.{}", name, suffix, domain);
+
+            if !suggestions.contains(&candidate)
+                && self.validate_handle(&candidate).is_ok()
+                && !self.handle_exists(&candidate).await?
+            {
+                suggestions.push(candidate);
+            }
+        }
+
+        Ok(suggestions)
     }
 
     /// Create a new account
@@ -35,36 +209,95 @@ impl AccountManager {
     ) -> PdsResult<Account> {
         // Note: Invite code validation is handled at the API layer
         // This keeps the AccountManager focused on account creation logic
+        let handle = self.check_new_account(&handle, email.as_deref()).await?;
+
+        // Hash password using the configured Argon2id policy
+        let password_hash = self.password_policy.hash(&password)?;
+
+        // Generate DID with PLC registration
+        let identity = self.generate_plc_did(&handle).await?;
+
+        let account = self
+            .insert_account(
+                identity.did,
+                handle,
+                email,
+                password_hash,
+                Some(identity.rotation_key),
+                Some(identity.rotation_key_public),
+                Some(identity.operation_cid),
+            )
+            .await?;
+        self.signing_keys.store(&account.did, &identity.signing_key).await?;
+
+        Ok(account)
+    }
+
+    /// Create a local did:web account without registering with the PLC directory
+    ///
+    /// Intended for development fixtures (see `seed`), where demo accounts
+    /// must never be published to the public directory.
+    pub async fn create_local_account(
+        &self,
+        handle: String,
+        email: Option<String>,
+        password: String,
+    ) -> PdsResult<Account> {
+        let handle = self.check_new_account(&handle, email.as_deref()).await?;
+
+        let password_hash = self.password_policy.hash(&password)?;
+        let did = format!("did:web:{}", handle);
+        let (signing_key, _) = self.signing_keys.generate().await?;
+
+        let account = self
+            .insert_account(did, handle, email, password_hash, None, None, None)
+            .await?;
+        self.signing_keys.store(&account.did, &signing_key).await?;
 
-        // Validate handle format
-        self.validate_handle(&handle)?;
+        Ok(account)
+    }
+
+    /// Validate handle/email and check neither is already taken
+    ///
+    /// Returns the normalized handle.
+    async fn check_new_account(&self, handle: &str, email: Option<&str>) -> PdsResult<String> {
+        // Normalize and validate handle format
+        let handle = self.validate_handle(handle)?;
 
         // Validate email if provided
-        if let Some(ref email_str) = email {
+        if let Some(email_str) = email {
             self.validate_email(email_str)?;
         }
 
-        // Check if handle already exists
+        // Fail early, before any PLC registration. Redirects and pending
+        // changes are only checked here; for taken handles and emails the
+        // UNIQUE constraints in insert_account are what decides.
         if self.handle_exists(&handle).await? {
-            return Err(PdsError::Conflict(format!("Handle {} already taken", handle)));
+            return Err(PdsError::HandleNotAvailable(handle));
         }
 
-        // Check if email already exists
-        if let Some(ref email_str) = email {
+        if let Some(email_str) = email {
             if self.email_exists(email_str).await? {
-                return Err(PdsError::Conflict("Email already registered".to_string()));
+                return Err(PdsError::EmailTaken);
             }
         }
 
-        // Hash password using SDK's Argon2id implementation
-        let password_hash = atproto::server_auth::PasswordHasher::hash(&password)
-            .map_err(|e| PdsError::Internal(format!("Password hashing failed: {}", e)))?;
-
-        // Generate DID with PLC registration
-        let (did, plc_key, plc_key_public, plc_operation_cid) = self.generate_plc_did(&handle).await?;
+        Ok(handle)
+    }
 
+    /// Insert a new account row
+    async fn insert_account(
+        &self,
+        did: String,
+        handle: String,
+        email: Option<String>,
+        password_hash: String,
+        plc_key: Option<String>,
+        plc_key_public: Option<String>,
+        plc_operation_cid: Option<String>,
+    ) -> PdsResult<Account> {
         // Insert account
-        let now = Utc::now();
+        let now = self.clock.now();
         sqlx::query(
             "INSERT INTO account (did, handle, email, password_hash, created_at, email_confirmed, taken_down, plc_rotation_key, plc_rotation_key_public, plc_last_operation_cid)
              VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)"
@@ -81,7 +314,7 @@ impl AccountManager {
         .bind(&plc_operation_cid)
         .execute(&self.db)
         .await
-        .map_err(|e| PdsError::Database(e))?;
+        .map_err(|e| account_conflict(e, &handle))?;
 
         Ok(Account {
             did,
@@ -93,12 +326,22 @@ impl AccountManager {
             email_confirmed_at: None,
             deactivated_at: None,
             taken_down: false,
-            plc_rotation_key: Some(plc_key),
-            plc_rotation_key_public: Some(plc_key_public),
-            plc_last_operation_cid: Some(plc_operation_cid),
+            plc_rotation_key: plc_key,
+            plc_rotation_key_public: plc_key_public,
+            plc_last_operation_cid: plc_operation_cid,
         })
     }
 
+    /// Number of accounts created since `since`
+    pub async fn count_accounts_since(&self, since: DateTime<Utc>) -> PdsResult<i64> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account WHERE created_at > ?1")
+            .bind(since)
+            .fetch_one(&self.db)
+            .await?;
+
+        Ok(count)
+    }
+
     /// Authenticate account and create session
     pub async fn login(
         &self,
@@ -118,32 +361,69 @@ impl AccountManager {
         }
 
         // Verify password
-        let valid = atproto::server_auth::PasswordHasher::verify(password, &account.password_hash)
-            .map_err(|e| PdsError::Internal(format!("Password verification failed: {}", e)))?;
+        let valid = self.password_policy.verify(password, &account.password_hash)?;
 
         if !valid {
             return Err(PdsError::Authentication("Invalid credentials".to_string()));
         }
 
+        // Upgrade hashes created under a weaker policy
+        if self.password_policy.needs_rehash(&account.password_hash) {
+            if let Err(e) = self.rehash_account_password(&account.did, password).await {
+                tracing::warn!(did = %account.did, error = %e, "password_rehash_failed");
+            }
+        }
+
         // Create session
         let session = self.create_session(&account.did, None).await?;
 
         Ok((account, session))
     }
 
+    /// Re-hash an account password under the current policy
+    async fn rehash_account_password(&self, did: &str, password: &str) -> PdsResult<()> {
+        let password_hash = self.password_policy.hash(password)?;
+
+        sqlx::query("UPDATE account SET password_hash = ?1 WHERE did = ?2")
+            .bind(&password_hash)
+            .bind(did)
+            .execute(&self.db)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+
+        tracing::info!(did = %did, "password_rehashed");
+        Ok(())
+    }
+
+    /// Re-hash an app password under the current policy
+    async fn rehash_app_password(&self, did: &str, name: &str, password: &str) -> PdsResult<()> {
+        let password_hash = self.password_policy.hash(password)?;
+
+        sqlx::query("UPDATE app_password SET password_hash = ?1 WHERE did = ?2 AND name = ?3")
+            .bind(&password_hash)
+            .bind(did)
+            .bind(name)
+            .execute(&self.db)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+
+        tracing::info!(did = %did, name = %name, "app_password_rehashed");
+        Ok(())
+    }
+
     /// Create a session for a DID
     pub async fn create_session(
         &self,
         did: &str,
         app_password_name: Option<String>,
     ) -> PdsResult<Session> {
-        let session_id = Uuid::new_v4().to_string();
+        let session_id = self.ids.uuid().to_string();
 
         // Generate JWT tokens
         let access_token = self.generate_access_token(did, &session_id)?;
         let refresh_token_str = self.generate_refresh_token(did, &session_id)?;
 
-        let now = Utc::now();
+        let now = self.clock.now();
         let expires_at = now + Duration::hours(1); // Access token expires in 1 hour
 
         // Insert session
@@ -163,7 +443,7 @@ impl AccountManager {
         .map_err(|e| PdsError::Database(e))?;
 
         // Store refresh token
-        let refresh_token_id = Uuid::new_v4().to_string();
+        let refresh_token_id = self.ids.uuid().to_string();
         let refresh_expires = now + Duration::days(180); // Refresh token expires in 6 months
 
         sqlx::query(
@@ -193,6 +473,10 @@ impl AccountManager {
 
     /// Validate access token and return session info
     pub async fn validate_access_token(&self, token: &str) -> PdsResult<crate::account::ValidatedSession> {
+        if token.starts_with(API_TOKEN_PREFIX) {
+            return self.validate_api_token(token).await;
+        }
+
         // Find session by access token
         let row = sqlx::query(
             "SELECT id, did, expires_at, app_password_name FROM session WHERE access_token = ?1"
@@ -209,14 +493,28 @@ impl AccountManager {
         let app_password_name: Option<String> = row.get("app_password_name");
 
         // Check expiration
-        if Utc::now() > expires_at {
+        let now = self.clock.now();
+        if now > expires_at {
             return Err(PdsError::Authentication("Session expired".to_string()));
         }
 
+        // Track last use, throttled to avoid a write on every request
+        sqlx::query(
+            "UPDATE session SET last_used_at = ?1
+             WHERE id = ?2 AND (last_used_at IS NULL OR last_used_at < ?3)"
+        )
+        .bind(now)
+        .bind(&session_id)
+        .bind(now - Duration::seconds(SESSION_LAST_USED_RESOLUTION_SECS))
+        .execute(&self.db)
+        .await
+        .map_err(|e| PdsError::Database(e))?;
+
         Ok(crate::account::ValidatedSession {
             did,
             session_id,
             is_app_password: app_password_name.is_some(),
+            scopes: None,
         })
     }
 
@@ -254,20 +552,187 @@ impl AccountManager {
         }
 
         // Check expiration
-        if Utc::now() > expires_at {
+        if self.clock.now() > expires_at {
             return Err(PdsError::Authentication("Refresh token expired".to_string()));
         }
 
         // Mark old refresh token as used
         sqlx::query("UPDATE refresh_token SET used = TRUE, used_at = ?1 WHERE id = ?2")
-            .bind(Utc::now())
+            .bind(self.clock.now())
             .bind(&token_id)
             .execute(&self.db)
             .await
             .map_err(|e| PdsError::Database(e))?;
 
-        // Create new session
-        self.create_session(&did, None).await
+        // Carry the client metadata of the session being refreshed over to the new one
+        let previous = sqlx::query(
+            "SELECT id, app_password_name, ip_address, user_agent FROM session WHERE refresh_token = ?1"
+        )
+        .bind(refresh_token)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| PdsError::Database(e))?;
+
+        let Some(previous) = previous else {
+            return self.create_session(&did, None).await;
+        };
+
+        let previous_id: String = previous.get("id");
+        let app_password_name: Option<String> = previous.get("app_password_name");
+        let client = SessionClientInfo {
+            ip_address: previous.get("ip_address"),
+            user_agent: previous.get("user_agent"),
+        };
+
+        let session = self.create_session(&did, app_password_name).await?;
+        self.record_session_client(&session.id, &client).await?;
+        self.delete_session(&previous_id).await?;
+
+        Ok(session)
+    }
+
+    /// Record the client address and user agent a session was created from
+    pub async fn record_session_client(
+        &self,
+        session_id: &str,
+        client: &SessionClientInfo,
+    ) -> PdsResult<()> {
+        sqlx::query("UPDATE session SET ip_address = ?1, user_agent = ?2 WHERE id = ?3")
+            .bind(&client.ip_address)
+            .bind(&client.user_agent)
+            .bind(session_id)
+            .execute(&self.db)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+
+        Ok(())
+    }
+
+    /// List active sessions for a DID
+    ///
+    /// A session is active while its refresh token is unused and unexpired.
+    /// `current_session_id` marks the session making the request.
+    pub async fn list_sessions(
+        &self,
+        did: &str,
+        current_session_id: &str,
+    ) -> PdsResult<Vec<ActiveSessionInfo>> {
+        let rows = sqlx::query(
+            "SELECT s.id, s.created_at, s.last_used_at, s.app_password_name, s.ip_address, s.user_agent
+             FROM session s
+             JOIN refresh_token r ON r.token = s.refresh_token
+             WHERE s.did = ?1 AND r.used = 0 AND r.expires_at > ?2
+             ORDER BY s.created_at DESC"
+        )
+        .bind(did)
+        .bind(self.clock.now())
+        .fetch_all(&self.db)
+        .await
+        .map_err(|e| PdsError::Database(e))?;
+
+        let mut sessions: Vec<ActiveSessionInfo> = rows
+            .into_iter()
+            .map(|row| {
+                let id: String = row.get("id");
+                ActiveSessionInfo {
+                    current: id == current_session_id,
+                    id,
+                    created_at: row.get("created_at"),
+                    last_used_at: row.get("last_used_at"),
+                    app_password_name: row.get("app_password_name"),
+                    ip_address: row.get("ip_address"),
+                    user_agent: row.get("user_agent"),
+                    api_token_name: None,
+                    scopes: None,
+                    expires_at: None,
+                }
+            })
+            .collect();
+
+        // API tokens are listed (and revoked) alongside sessions
+        let now = self.clock.now();
+        for token in self.list_api_tokens(did).await? {
+            if token.expires_at.is_some_and(|at| at <= now) {
+                continue;
+            }
+            sessions.push(ActiveSessionInfo {
+                current: token.id == current_session_id,
+                id: token.id,
+                created_at: token.created_at,
+                last_used_at: token.last_used_at,
+                app_password_name: None,
+                ip_address: None,
+                user_agent: None,
+                api_token_name: Some(token.name),
+                scopes: Some(token.scopes),
+                expires_at: token.expires_at,
+            });
+        }
+
+        Ok(sessions)
+    }
+
+    /// Revoke a session belonging to a DID
+    ///
+    /// Deletes the session and invalidates its refresh token.
+    pub async fn revoke_session(&self, did: &str, session_id: &str) -> PdsResult<()> {
+        let row = sqlx::query("SELECT refresh_token FROM session WHERE id = ?1 AND did = ?2")
+            .bind(session_id)
+            .bind(did)
+            .fetch_optional(&self.db)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+        let Some(row) = row else {
+            // listSessions also lists API tokens
+            return self
+                .revoke_api_token(did, session_id)
+                .await
+                .map_err(|_| PdsError::NotFound("Session not found".to_string()));
+        };
+
+        let refresh_token: String = row.get("refresh_token");
+
+        sqlx::query("DELETE FROM refresh_token WHERE token = ?1")
+            .bind(&refresh_token)
+            .execute(&self.db)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+
+        self.delete_session(session_id).await?;
+
+        tracing::info!("Revoked session {} for DID: {}", session_id, did);
+
+        Ok(())
+    }
+
+    /// Revoke every session of a DID except `keep_session_id`
+    ///
+    /// Returns the number of sessions revoked.
+    pub async fn revoke_other_sessions(&self, did: &str, keep_session_id: &str) -> PdsResult<u64> {
+        sqlx::query(
+            "DELETE FROM refresh_token WHERE did = ?1 AND token NOT IN
+                (SELECT refresh_token FROM session WHERE id = ?2)"
+        )
+        .bind(did)
+        .bind(keep_session_id)
+        .execute(&self.db)
+        .await
+        .map_err(|e| PdsError::Database(e))?;
+
+        let result = sqlx::query("DELETE FROM session WHERE did = ?1 AND id != ?2")
+            .bind(did)
+            .bind(keep_session_id)
+            .execute(&self.db)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+
+        tracing::info!(
+            "Revoked {} other session(s) for DID: {}",
+            result.rows_affected(),
+            did
+        );
+
+        Ok(result.rows_affected())
     }
 
     /// Get account by DID
@@ -301,9 +766,11 @@ impl AccountManager {
 
     /// Find account by handle or email (public for password reset)
     pub async fn get_account_by_identifier(&self, identifier: &str) -> PdsResult<Account> {
-        // Try handle first
-        if let Ok(account) = self.get_account_by_handle(identifier).await {
-            return Ok(account);
+        // Try handle first, normalized as at registration
+        if let Ok(handle) = normalize_handle(identifier) {
+            if let Ok(account) = self.get_account_by_handle(&handle).await {
+                return Ok(account);
+            }
         }
 
         // Try email
@@ -340,6 +807,72 @@ impl AccountManager {
         })
     }
 
+    /// DID of the active account holding `handle`, if any
+    ///
+    /// A handle that was recently given up still resolves to its old DID
+    /// during the redirect grace period.
+    pub async fn did_for_handle(&self, handle: &str) -> PdsResult<Option<String>> {
+        let did: Option<String> =
+            sqlx::query_scalar("SELECT did FROM account WHERE handle = ?1 AND taken_down = 0")
+                .bind(handle)
+                .fetch_optional(&self.db)
+                .await
+                .map_err(PdsError::Database)?;
+
+        match did {
+            Some(did) => Ok(Some(did)),
+            None => self.handle_redirect(handle).await,
+        }
+    }
+
+    /// DID an old handle still redirects to, if its grace period is running
+    async fn handle_redirect(&self, handle: &str) -> PdsResult<Option<String>> {
+        sqlx::query_scalar(
+            "SELECT h.did FROM handle_history h
+             JOIN account a ON a.did = h.did
+             WHERE h.old_handle = ?1 AND h.redirect_until > ?2 AND a.taken_down = 0
+             ORDER BY h.id DESC LIMIT 1",
+        )
+        .bind(handle)
+        .bind(self.clock.now().to_rfc3339())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(PdsError::Database)
+    }
+
+    /// Handle changes for an account, newest first
+    pub async fn handle_history(&self, did: &str) -> PdsResult<Vec<HandleChange>> {
+        let rows = sqlx::query(
+            "SELECT id, did, old_handle, new_handle, changed_at, redirect_until
+             FROM handle_history WHERE did = ?1 ORDER BY id DESC",
+        )
+        .bind(did)
+        .fetch_all(&self.db)
+        .await
+        .map_err(PdsError::Database)?;
+
+        rows.iter()
+            .map(|row| {
+                let parse = |value: String| {
+                    DateTime::parse_from_rfc3339(&value)
+                        .map(|t| t.with_timezone(&Utc))
+                        .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))
+                };
+                Ok(HandleChange {
+                    id: row.get("id"),
+                    did: row.get("did"),
+                    old_handle: row.get("old_handle"),
+                    new_handle: row.get("new_handle"),
+                    changed_at: parse(row.get("changed_at"))?,
+                    redirect_until: row
+                        .get::<Option<String>, _>("redirect_until")
+                        .map(parse)
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+
     /// Get account by email
     async fn get_account_by_email(&self, email: &str) -> PdsResult<Account> {
         let row = sqlx::query(
@@ -370,24 +903,75 @@ impl AccountManager {
         })
     }
 
-    /// Check if handle exists
+    /// Check if handle exists, is still held by a handle redirect, or is
+    /// reserved by a pending handle change
     async fn handle_exists(&self, handle: &str) -> PdsResult<bool> {
         let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account WHERE handle = ?1")
             .bind(handle)
             .fetch_one(&self.db)
             .await
             .map_err(|e| PdsError::Database(e))?;
+        if count > 0 || self.handle_redirect(handle).await?.is_some() {
+            return Ok(true);
+        }
 
-        Ok(count > 0)
+        let pending: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pending_handle_change WHERE handle = ?1 AND expires_at > ?2",
+        )
+        .bind(handle)
+        .bind(self.clock.now().to_rfc3339())
+        .fetch_one(&self.db)
+        .await
+        .map_err(PdsError::Database)?;
+
+        Ok(pending > 0)
+    }
+
+    /// Refuse a handle change the cooldown or monthly limit doesn't allow yet
+    ///
+    /// The error carries when the next change will be allowed.
+    async fn check_handle_change_limits(&self, did: &str) -> PdsResult<()> {
+        let limits = &self.config.identity.handle_changes;
+        if limits.cooldown_secs == 0 && limits.max_per_month == 0 {
+            return Ok(());
+        }
+
+        let now = self.clock.now();
+        let history = self.handle_history(did).await?;
+        let mut allowed_at = now;
+
+        if let Some(last) = history.first() {
+            allowed_at = allowed_at.max(last.changed_at + Duration::seconds(limits.cooldown_secs as i64));
+        }
+
+        let window_start = now - Duration::days(HANDLE_CHANGE_WINDOW_DAYS);
+        let recent: Vec<_> = history.iter().filter(|c| c.changed_at > window_start).collect();
+        if limits.max_per_month > 0 && recent.len() >= limits.max_per_month as usize {
+            // Newest first: the change that has to age out of the window
+            let oldest_counted = recent[limits.max_per_month as usize - 1];
+            allowed_at = allowed_at.max(oldest_counted.changed_at + Duration::days(HANDLE_CHANGE_WINDOW_DAYS));
+        }
+
+        if allowed_at > now {
+            let retry_after = (allowed_at - now).to_std().unwrap_or_default();
+            return Err(PdsError::RateLimitExceeded { retry_after });
+        }
+        Ok(())
     }
 
     /// Update account handle
     ///
-    /// Updates the handle for a given DID. The new handle must not be taken by another account.
-    /// Returns the old handle that was replaced.
+    /// Updates the handle for a given DID. The new handle must not be taken by another account,
+    /// still be redirecting to one, or be reserved by another account's pending handle change.
+    /// The claim is made by a single guarded UPDATE backed by the UNIQUE handle column, so of
+    /// two concurrent requests for the same handle only one succeeds; the other gets
+    /// `HandleNotAvailable`. Changes are subject to the `PDS_HANDLE_CHANGE_*` limits. The change is
+    /// recorded in the handle history, and with `PDS_HANDLE_REDIRECT_SECS` the old handle keeps
+    /// resolving to this DID for that long. Returns the old handle that was replaced.
     pub async fn update_handle(&self, did: &str, new_handle: &str) -> PdsResult<String> {
-        // Validate new handle format
-        self.validate_handle(new_handle)?;
+        // Normalize and validate new handle format
+        let new_handle = self.validate_handle(new_handle)?;
+        let new_handle = new_handle.as_str();
 
         // Get current account to retrieve old handle
         let account = self.get_account(did).await?;
@@ -398,24 +982,282 @@ impl AccountManager {
             return Ok(old_handle);
         }
 
-        // Check if new handle is already taken by another account
-        if let Ok(existing) = self.get_account_by_handle(new_handle).await {
-            if existing.did != did {
-                return Err(PdsError::Conflict(format!("Handle {} already taken", new_handle)));
-            }
+        self.check_handle_change_limits(did).await?;
+
+        let now = self.clock.now();
+        let redirect_secs = self.config.identity.handle_redirect_secs;
+        let redirect_until = (redirect_secs > 0)
+            .then(|| (now + Duration::seconds(redirect_secs as i64)).to_rfc3339());
+
+        let mut tx = self.db.begin().await.map_err(PdsError::Database)?;
+
+        // An account can reclaim its own old handle, nobody else can during the
+        // grace period or while another account's change to it is pending
+        let claimed = sqlx::query(
+            "UPDATE account SET handle = ?1 WHERE did = ?2
+             AND NOT EXISTS (
+                 SELECT 1 FROM handle_history h JOIN account a ON a.did = h.did
+                 WHERE h.old_handle = ?1 AND h.did != ?2 AND h.redirect_until > ?3 AND a.taken_down = 0
+             )
+             AND NOT EXISTS (
+                 SELECT 1 FROM pending_handle_change WHERE handle = ?1 AND did != ?2 AND expires_at > ?3
+             )",
+        )
+        .bind(new_handle)
+        .bind(did)
+        .bind(now.to_rfc3339())
+        .execute(&mut *tx)
+        .await;
+
+        let claimed = claimed.map_err(|e| account_conflict(e, new_handle))?;
+        if claimed.rows_affected() != 1 {
+            return Err(PdsError::HandleNotAvailable(new_handle.to_string()));
         }
 
-        // Update handle in database
-        sqlx::query("UPDATE account SET handle = ?1 WHERE did = ?2")
+        // Taking the new handle ends any redirect from it
+        sqlx::query("UPDATE handle_history SET redirect_until = NULL WHERE old_handle = ?1")
             .bind(new_handle)
+            .execute(&mut *tx)
+            .await
+            .map_err(PdsError::Database)?;
+
+        // Any change supersedes a pending one
+        sqlx::query("DELETE FROM pending_handle_change WHERE did = ?1")
             .bind(did)
-            .execute(&self.db)
+            .execute(&mut *tx)
             .await
-            .map_err(|e| PdsError::Database(e))?;
+            .map_err(PdsError::Database)?;
+
+        sqlx::query(
+            "INSERT INTO handle_history (did, old_handle, new_handle, changed_at, redirect_until)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(did)
+        .bind(&old_handle)
+        .bind(new_handle)
+        .bind(now.to_rfc3339())
+        .bind(redirect_until)
+        .execute(&mut *tx)
+        .await
+        .map_err(PdsError::Database)?;
+
+        tx.commit().await.map_err(PdsError::Database)?;
 
         Ok(old_handle)
     }
 
+    /// Start a handle change that is confirmed by email
+    ///
+    /// Checks the change limits and reserves `new_handle` for the account
+    /// until the code expires, replacing any earlier pending change. Returns
+    /// the normalized handle, the code to email and when it expires.
+    pub async fn request_handle_change(
+        &self,
+        did: &str,
+        new_handle: &str,
+    ) -> PdsResult<(String, String, DateTime<Utc>)> {
+        let new_handle = self.validate_handle(new_handle)?;
+        self.check_handle_change_limits(did).await?;
+
+        let now = self.clock.now();
+        let expires_at = now + Duration::minutes(HANDLE_CHANGE_CODE_MINUTES);
+        let token = self.ids.uuid().to_string();
+
+        if let Some(holder) = self.did_for_handle(&new_handle).await? {
+            if holder != did {
+                return Err(PdsError::HandleNotAvailable(new_handle));
+            }
+        }
+
+        let mut tx = self.db.begin().await.map_err(PdsError::Database)?;
+
+        sqlx::query("DELETE FROM pending_handle_change WHERE did = ?1 OR expires_at <= ?2")
+            .bind(did)
+            .bind(now.to_rfc3339())
+            .execute(&mut *tx)
+            .await
+            .map_err(PdsError::Database)?;
+
+        // The UNIQUE handle column makes the reservation: a concurrent request
+        // for the same handle fails here
+        let reserved = sqlx::query(
+            "INSERT INTO pending_handle_change (did, handle, token, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )
+        .bind(did)
+        .bind(&new_handle)
+        .bind(&token)
+        .bind(now.to_rfc3339())
+        .bind(expires_at.to_rfc3339())
+        .execute(&mut *tx)
+        .await;
+
+        match reserved {
+            Ok(_) => {}
+            Err(e) if is_unique_violation(&e) => return Err(PdsError::HandleNotAvailable(new_handle)),
+            Err(e) => return Err(PdsError::Database(e)),
+        }
+
+        tx.commit().await.map_err(PdsError::Database)?;
+
+        Ok((new_handle, token, expires_at))
+    }
+
+    /// Handle a pending change for the account would switch to
+    pub async fn pending_handle_change(&self, did: &str, token: &str) -> PdsResult<String> {
+        let row = sqlx::query("SELECT handle, expires_at FROM pending_handle_change WHERE did = ?1 AND token = ?2")
+            .bind(did)
+            .bind(token)
+            .fetch_optional(&self.db)
+            .await
+            .map_err(PdsError::Database)?
+            .ok_or_else(|| PdsError::NotFound("Invalid handle change code".to_string()))?;
+
+        let expires_at: String = row.try_get("expires_at")?;
+        let expires_at = DateTime::parse_from_rfc3339(&expires_at)
+            .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?;
+        if self.clock.now() >= expires_at {
+            return Err(PdsError::Validation("Handle change code has expired".to_string()));
+        }
+
+        Ok(row.try_get("handle")?)
+    }
+
+    /// Apply a pending handle change with its emailed code
+    ///
+    /// Returns the old and the new handle.
+    pub async fn confirm_handle_change(&self, did: &str, token: &str) -> PdsResult<(String, String)> {
+        let new_handle = self.pending_handle_change(did, token).await?;
+        let old_handle = self.update_handle(did, &new_handle).await?;
+        Ok((old_handle, new_handle))
+    }
+
+    /// Whether a handle is under one of this server's service handle domains
+    ///
+    /// Service handles are resolved by the PDS itself; any other handle is a
+    /// custom domain that must be verified via DNS or HTTPS.
+    pub fn is_service_handle(&self, handle: &str) -> bool {
+        let handle = handle.to_lowercase();
+        self.config.identity.service_handle_domains.iter().any(|domain| {
+            let domain = domain.trim_start_matches('.');
+            handle
+                .strip_suffix(domain)
+                .map_or(false, |rest| rest.ends_with('.') && rest.len() > 1)
+        })
+    }
+
+    /// Point a did:plc's alsoKnownAs at a new handle
+    ///
+    /// Builds an update operation on top of the latest PLC operation, signed
+    /// with the account's rotation key. Returns `false` when the account has
+    /// no PLC identity managed by this server.
+    pub async fn update_plc_handle(&self, did: &str, handle: &str) -> PdsResult<bool> {
+        let handle_uri = format!("at://{}", handle);
+        let updated = self
+            .update_plc_operation(did, |last_op| {
+                let mut also_known_as = vec![handle_uri.clone()];
+                if let Some(existing) = last_op.get("alsoKnownAs").and_then(|a| a.as_array()) {
+                    also_known_as.extend(
+                        existing
+                            .iter()
+                            .filter_map(|v| v.as_str())
+                            .filter(|aka| !aka.starts_with("at://"))
+                            .map(String::from),
+                    );
+                }
+                (Some(also_known_as), None)
+            })
+            .await?;
+
+        if updated {
+            tracing::info!(did = %did, handle = %handle, "plc_handle_updated");
+        }
+        Ok(updated)
+    }
+
+    /// Publish a new `atproto` signing key in a did:plc document
+    ///
+    /// Other verification methods are kept. Returns `false` when the account
+    /// has no PLC identity managed by this server.
+    pub async fn update_plc_signing_key(&self, did: &str, signing_key_did_key: &str) -> PdsResult<bool> {
+        let updated = self
+            .update_plc_operation(did, |last_op| {
+                let mut methods = last_op
+                    .get("verificationMethods")
+                    .and_then(|m| m.as_object())
+                    .cloned()
+                    .unwrap_or_default();
+                methods.insert(
+                    "atproto".to_string(),
+                    serde_json::Value::String(signing_key_did_key.to_string()),
+                );
+                (None, Some(serde_json::Value::Object(methods)))
+            })
+            .await?;
+
+        if updated {
+            tracing::info!(did = %did, "plc_signing_key_updated");
+        }
+        Ok(updated)
+    }
+
+    /// Submit a PLC update operation built on the latest one
+    ///
+    /// `change` returns replacement alsoKnownAs and verificationMethods
+    /// (None keeps the current value); rotation keys and services are
+    /// carried over.
+    async fn update_plc_operation<F>(&self, did: &str, change: F) -> PdsResult<bool>
+    where
+        F: FnOnce(&serde_json::Value) -> (Option<Vec<String>>, Option<serde_json::Value>),
+    {
+        use crate::crypto::plc::{fetch_last_plc_operation, register_plc_did, PlcOperationBuilder};
+
+        if !did.starts_with("did:plc:") {
+            return Ok(false);
+        }
+
+        let account = self.get_account(did).await?;
+        let rotation_key = match account.plc_rotation_key.filter(|k| !k.is_empty()) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+
+        let plc_url = self.config.identity.did_plc_url.as_str();
+        let (prev, last_op) = fetch_last_plc_operation(plc_url, did).await?;
+        let (also_known_as, verification_methods) = change(&last_op);
+
+        let also_known_as = also_known_as.unwrap_or_else(|| {
+            last_op
+                .get("alsoKnownAs")
+                .and_then(|a| a.as_array())
+                .map(|akas| akas.iter().filter_map(|v| v.as_str()).map(String::from).collect())
+                .unwrap_or_default()
+        });
+
+        let rotation_keys: Vec<String> = last_op
+            .get("rotationKeys")
+            .and_then(|k| k.as_array())
+            .map(|keys| keys.iter().filter_map(|k| k.as_str()).map(String::from).collect())
+            .unwrap_or_default();
+
+        let mut builder = PlcOperationBuilder::new()
+            .prev(prev)
+            .did(did.to_string())
+            .rotation_keys(rotation_keys)
+            .also_known_as(also_known_as);
+        if let Some(methods) = verification_methods.or_else(|| last_op.get("verificationMethods").cloned()) {
+            builder = builder.verification_methods(methods);
+        }
+        if let Some(services) = last_op.get("services") {
+            builder = builder.services(services.clone());
+        }
+
+        let signed_operation = self.keys.sign_plc_operation(&rotation_key, builder.build()?).await?;
+        register_plc_did(plc_url, signed_operation).await?;
+
+        Ok(true)
+    }
+
     /// Check if email exists
     async fn email_exists(&self, email: &str) -> PdsResult<bool> {
         let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account WHERE email = ?1")
@@ -430,20 +1272,22 @@ impl AccountManager {
     /// Generate DID for handle
     /// Generate a PLC DID and register it with the PLC Directory
     ///
-    /// Returns: (did, rotation_key_hex, rotation_key_public_hex, operation_cid)
-    async fn generate_plc_did(&self, handle: &str) -> PdsResult<(String, String, String, String)> {
-        use crate::crypto::plc::{PlcOperationBuilder, PlcSigner, register_plc_did};
+    /// The rotation and signing keys are created in the key backend; their
+    /// references are what identifies them there (the hex key itself with
+    /// the config backend). Only the signing key is published as the
+    /// `atproto` verification method.
+    async fn generate_plc_did(&self, handle: &str) -> PdsResult<NewIdentity> {
+        use crate::crypto::keys::public_key_multibase;
+        use crate::crypto::plc::{PlcOperationBuilder, register_plc_did};
         use sha2::{Digest, Sha256};
-        use rand::RngCore;
 
-        // Generate a random 32-byte private key for PLC rotation
-        let mut private_key = [0u8; 32];
-        rand::thread_rng().fill_bytes(&mut private_key);
-        let private_key_hex = hex::encode(&private_key);
+        // Create the PLC rotation key
+        let rotation_key_ref = self.keys.generate_account_key().await?;
+        let public_key = self.keys.public_key(&rotation_key_ref).await?;
+        let public_key_hex = hex::encode(public_key.to_encoded_point(true).as_bytes());
 
-        // Create PLC signer
-        let signer = PlcSigner::new(&private_key)?;
-        let public_key_hex = signer.public_key_hex();
+        // And the separate key commits will be signed with
+        let (signing_key_ref, signing_public_key) = self.signing_keys.generate().await?;
 
         // Generate DID from hash of public key (PLC method)
         // did:plc uses base32-encoded hash of the genesis operation
@@ -476,15 +1320,10 @@ impl AccountManager {
         }]);
 
         // Get proper multibase encoding for public key
-        let public_key_multibase = signer.public_key_multibase();
-        let public_key_did_key = signer.public_key_did_key();
-
-        let verification_methods = serde_json::json!([{
-            "id": format!("{}#atproto", did),
-            "type": "Multikey",
-            "controller": did.clone(),
-            "publicKeyMultibase": public_key_multibase
-        }]);
+        let public_key_did_key = format!("did:key:{}", public_key_multibase(&public_key));
+        let signing_key_did_key = format!("did:key:{}", public_key_multibase(&signing_public_key));
+
+        let verification_methods = serde_json::json!({ "atproto": signing_key_did_key });
 
         let also_known_as = vec![format!("at://{}", full_handle)];
 
@@ -497,7 +1336,7 @@ impl AccountManager {
             .build()?;
 
         // Sign the operation
-        let signed_operation = signer.sign_operation(operation)?;
+        let signed_operation = self.keys.sign_plc_operation(&rotation_key_ref, operation).await?;
 
         // Get PLC directory URL from config or use default
         let plc_url = self.config.identity.did_plc_url.as_str();
@@ -516,21 +1355,32 @@ impl AccountManager {
                 let cid_hash = cid_hasher.finalize();
                 let operation_cid = format!("bafyrei{}", hex::encode(&cid_hash[..16]));
 
-                Ok((did, private_key_hex, public_key_hex, operation_cid))
+                Ok(NewIdentity {
+                    did,
+                    rotation_key: rotation_key_ref,
+                    rotation_key_public: public_key_hex,
+                    operation_cid,
+                    signing_key: signing_key_ref,
+                })
             }
             Err(e) => {
                 tracing::warn!("Failed to register DID with PLC directory: {}. Falling back to did:web", e);
                 // Fallback to did:web if PLC registration fails
                 // full_handle is already constructed above (line 463)
                 let did_web = format!("did:web:{}", full_handle);
-                Ok((did_web, private_key_hex, public_key_hex, "".to_string()))
+                Ok(NewIdentity {
+                    did: did_web,
+                    rotation_key: rotation_key_ref,
+                    rotation_key_public: public_key_hex,
+                    operation_cid: String::new(),
+                    signing_key: signing_key_ref,
+                })
             }
         }
     }
 
     /// Generate access JWT token
     fn generate_access_token(&self, did: &str, session_id: &str) -> PdsResult<String> {
-        use jsonwebtoken::{encode, EncodingKey, Header};
         use serde::{Deserialize, Serialize};
 
         #[derive(Debug, Serialize, Deserialize)]
@@ -541,7 +1391,7 @@ impl AccountManager {
             exp: i64,
         }
 
-        let now = Utc::now().timestamp();
+        let now = self.clock.now().timestamp();
         let claims = Claims {
             sub: did.to_string(),
             sid: session_id.to_string(),
@@ -549,19 +1399,11 @@ impl AccountManager {
             exp: now + 3600, // 1 hour
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.config.authentication.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| PdsError::Jwt(format!("Failed to generate token: {}", e)))?;
-
-        Ok(token)
+        self.jwt_keys.sign(&claims)
     }
 
     /// Generate refresh JWT token
     fn generate_refresh_token(&self, did: &str, session_id: &str) -> PdsResult<String> {
-        use jsonwebtoken::{encode, EncodingKey, Header};
         use serde::{Deserialize, Serialize};
 
         #[derive(Debug, Serialize, Deserialize)]
@@ -572,7 +1414,7 @@ impl AccountManager {
             exp: i64,
         }
 
-        let now = Utc::now().timestamp();
+        let now = self.clock.now().timestamp();
         let claims = RefreshClaims {
             sub: did.to_string(),
             sid: session_id.to_string(),
@@ -580,14 +1422,7 @@ impl AccountManager {
             exp: now + (180 * 24 * 3600), // 180 days
         };
 
-        let token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(self.config.authentication.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| PdsError::Jwt(format!("Failed to generate refresh token: {}", e)))?;
-
-        Ok(token)
+        self.jwt_keys.sign(&claims)
     }
 
     /// Cleanup expired sessions and refresh tokens
@@ -597,7 +1432,7 @@ impl AccountManager {
     ///
     /// Returns (sessions_deleted, refresh_tokens_deleted)
     pub async fn cleanup_expired_sessions(&self) -> PdsResult<(u64, u64)> {
-        let now = Utc::now();
+        let now = self.clock.now();
 
         // Delete expired access token sessions
         let sessions_result = sqlx::query("DELETE FROM session WHERE expires_at < ?1")
@@ -635,8 +1470,8 @@ impl AccountManager {
     ///
     /// Creates a verification token that expires in 24 hours
     pub async fn generate_email_verification_token(&self, did: &str) -> PdsResult<String> {
-        let token = Uuid::new_v4().to_string();
-        let now = Utc::now();
+        let token = self.ids.uuid().to_string();
+        let now = self.clock.now();
         let expires_at = now + Duration::hours(24);
 
         sqlx::query(
@@ -662,7 +1497,7 @@ impl AccountManager {
     ///
     /// Marks the email as confirmed if the token is valid and not expired
     pub async fn confirm_email(&self, token: &str) -> PdsResult<String> {
-        let now = Utc::now();
+        let now = self.clock.now();
 
         // Get token info
         let row = sqlx::query(
@@ -757,8 +1592,8 @@ impl AccountManager {
             ));
         }
 
-        let token = Uuid::new_v4().to_string();
-        let now = Utc::now();
+        let token = self.ids.uuid().to_string();
+        let now = self.clock.now();
         let expires_at = now + Duration::hours(1); // Password reset tokens expire in 1 hour
 
         sqlx::query(
@@ -784,7 +1619,7 @@ impl AccountManager {
     ///
     /// Validates the token, updates the password, and invalidates all sessions
     pub async fn reset_password(&self, token: &str, new_password: &str) -> PdsResult<()> {
-        let now = Utc::now();
+        let now = self.clock.now();
 
         // Get token info
         let row = sqlx::query(
@@ -819,8 +1654,7 @@ impl AccountManager {
         }
 
         // Hash new password
-        let password_hash = atproto::server_auth::PasswordHasher::hash(new_password)
-            .map_err(|e| PdsError::Internal(format!("Password hashing failed: {}", e)))?;
+        let password_hash = self.password_policy.hash(new_password)?;
 
         // Update password in database
         sqlx::query("UPDATE account SET password_hash = ?1 WHERE did = ?2")
@@ -865,15 +1699,14 @@ impl AccountManager {
         let account = self.get_account(did).await?;
 
         // Verify password
-        let valid = atproto::server_auth::PasswordHasher::verify(&account.password_hash, password)
-            .map_err(|e| PdsError::Internal(format!("Password verification failed: {}", e)))?;
+        let valid = self.password_policy.verify(password, &account.password_hash)?;
 
         if !valid {
             return Err(PdsError::Authorization("Invalid password".to_string()));
         }
 
         // Mark account for deletion (30 day grace period)
-        let deletion_date = Utc::now() + Duration::days(30);
+        let deletion_date = self.clock.now() + Duration::days(30);
 
         sqlx::query(
             "UPDATE account SET deactivated_at = ?1 WHERE did = ?2"
@@ -933,6 +1766,128 @@ impl AccountManager {
         Ok(())
     }
 
+    /// Tombstone an account's did:plc with its rotation key
+    ///
+    /// Returns `false` when the account has no PLC identity managed by this
+    /// server.
+    pub async fn tombstone_plc(&self, did: &str) -> PdsResult<bool> {
+        use crate::crypto::plc::{fetch_last_plc_operation, submit_plc_tombstone, PlcTombstone};
+
+        if !did.starts_with("did:plc:") {
+            return Ok(false);
+        }
+
+        let rotation_key: Option<String> =
+            sqlx::query_scalar("SELECT plc_rotation_key FROM account WHERE did = ?1")
+                .bind(did)
+                .fetch_optional(&self.db)
+                .await?
+                .flatten();
+        let rotation_key = match rotation_key.filter(|k| !k.is_empty()) {
+            Some(key) => key,
+            None => return Ok(false),
+        };
+
+        let plc_url = self.config.identity.did_plc_url.as_str();
+        let (prev, _) = fetch_last_plc_operation(plc_url, did).await?;
+        let tombstone = self.keys.sign_plc_tombstone(&rotation_key, PlcTombstone::new(prev)).await?;
+        submit_plc_tombstone(plc_url, did, &tombstone).await?;
+
+        tracing::info!(did = %did, "plc_tombstoned");
+        Ok(true)
+    }
+
+    /// Delete an account row and record the deletion
+    ///
+    /// Sessions, tokens and app passwords are deleted with it, in the same
+    /// transaction as the `account_deletion` entry.
+    pub async fn purge_account(
+        &self,
+        did: &str,
+        blobs_deleted: i64,
+        plc_tombstoned: bool,
+        account_seq: Option<i64>,
+        identity_seq: Option<i64>,
+    ) -> PdsResult<AccountDeletion> {
+        let mut tx = self.db.begin().await?;
+
+        let handle: String = sqlx::query_scalar("SELECT handle FROM account WHERE did = ?1")
+            .bind(did)
+            .fetch_optional(&mut *tx)
+            .await?
+            .ok_or_else(|| PdsError::NotFound(format!("Account not found: {}", did)))?;
+
+        for table in ["session", "refresh_token", "email_token", "app_password", "api_token", "account"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE did = ?1", table))
+                .bind(did)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let deleted_at = self.clock.now();
+        let id = sqlx::query(
+            "INSERT INTO account_deletion
+                (did, handle, deleted_at, blobs_deleted, plc_tombstoned, account_seq, identity_seq)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(did)
+        .bind(&handle)
+        .bind(deleted_at.to_rfc3339())
+        .bind(blobs_deleted)
+        .bind(plc_tombstoned)
+        .bind(account_seq)
+        .bind(identity_seq)
+        .execute(&mut *tx)
+        .await?
+        .last_insert_rowid();
+
+        tx.commit().await?;
+
+        Ok(AccountDeletion {
+            id,
+            did: did.to_string(),
+            handle,
+            deleted_at,
+            blobs_deleted,
+            plc_tombstoned,
+            account_seq,
+            identity_seq,
+        })
+    }
+
+    /// Recorded account deletions, newest first
+    pub async fn list_account_deletions(&self, limit: i64, before: Option<i64>) -> PdsResult<Vec<AccountDeletion>> {
+        let rows = sqlx::query(
+            "SELECT id, did, handle, deleted_at, blobs_deleted, plc_tombstoned, account_seq, identity_seq
+             FROM account_deletion
+             WHERE ?1 IS NULL OR id < ?1
+             ORDER BY id DESC
+             LIMIT ?2",
+        )
+        .bind(before)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let deleted_at: String = row.get("deleted_at");
+                Ok(AccountDeletion {
+                    id: row.get("id"),
+                    did: row.get("did"),
+                    handle: row.get("handle"),
+                    deleted_at: DateTime::parse_from_rfc3339(&deleted_at)
+                        .map(|t| t.with_timezone(&Utc))
+                        .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?,
+                    blobs_deleted: row.get("blobs_deleted"),
+                    plc_tombstoned: row.get("plc_tombstoned"),
+                    account_seq: row.get("account_seq"),
+                    identity_seq: row.get("identity_seq"),
+                })
+            })
+            .collect()
+    }
+
     // ==================== App Passwords ====================
 
     /// Create an app password for third-party applications
@@ -978,11 +1933,10 @@ impl AccountManager {
         );
 
         // Hash the password using Argon2id
-        let password_hash = atproto::server_auth::PasswordHasher::hash(&raw_password)
-            .map_err(|e| PdsError::Internal(format!("Password hashing failed: {}", e)))?;
+        let password_hash = self.password_policy.hash(&raw_password)?;
 
         // Store app password
-        let now = Utc::now();
+        let now = self.clock.now();
         sqlx::query(
             "INSERT INTO app_password (did, name, password_hash, created_at, privileged)
              VALUES (?1, ?2, ?3, ?4, ?5)"
@@ -1077,20 +2031,30 @@ impl AccountManager {
         .await
         .map_err(|e| PdsError::Database(e))?;
 
-        let mut matched_name: Option<String> = None;
+        let mut matched: Option<(String, String)> = None;
         for row in rows {
             let name: String = row.get("name");
             let hash: String = row.get("password_hash");
 
-            if let Ok(true) = atproto::server_auth::PasswordHasher::verify(app_password, &hash) {
-                matched_name = Some(name);
+            if let Ok(true) = self.password_policy.verify(app_password, &hash) {
+                matched = Some((name, hash));
                 break;
             }
         }
 
-        let app_password_name = matched_name
+        let (app_password_name, matched_hash) = matched
             .ok_or_else(|| PdsError::Authentication("Invalid app password".to_string()))?;
 
+        // Upgrade hashes created under a weaker policy
+        if self.password_policy.needs_rehash(&matched_hash) {
+            if let Err(e) = self
+                .rehash_app_password(&account.did, &app_password_name, app_password)
+                .await
+            {
+                tracing::warn!(did = %account.did, error = %e, "app_password_rehash_failed");
+            }
+        }
+
         // Create session with app_password_name
         let session = self.create_session(&account.did, Some(app_password_name.clone())).await?;
 
@@ -1112,28 +2076,164 @@ impl AccountManager {
             .collect()
     }
 
-    /// Validate handle format
-    fn validate_handle(&self, handle: &str) -> PdsResult<()> {
-        // Basic validation (detailed validation in Phase 6)
-        if handle.is_empty() {
-            return Err(PdsError::Validation("Handle cannot be empty".to_string()));
+    // ==================== API Tokens ====================
+
+    /// Create a scoped API token; returns its details and the token itself
+    pub async fn create_api_token(
+        &self,
+        did: &str,
+        name: &str,
+        scopes: &[ApiTokenScope],
+        expires_in_days: Option<u32>,
+    ) -> PdsResult<(ApiTokenInfo, String)> {
+        if name.trim().is_empty() {
+            return Err(PdsError::Validation("API token name cannot be empty".to_string()));
+        }
+        if name.len() > 100 {
+            return Err(PdsError::Validation("API token name too long".to_string()));
+        }
+        if scopes.is_empty() {
+            return Err(PdsError::Validation("API token needs at least one scope".to_string()));
+        }
+        if expires_in_days.is_some_and(|days| days == 0 || days > MAX_EXPIRY_DAYS) {
+            return Err(PdsError::Validation(format!(
+                "API token expiry must be between 1 and {} days",
+                MAX_EXPIRY_DAYS
+            )));
         }
 
-        if handle.len() < 3 {
-            return Err(PdsError::Validation("Handle must be at least 3 characters".to_string()));
+        let existing: Option<String> = sqlx::query_scalar("SELECT id FROM api_token WHERE did = ?1 AND name = ?2")
+            .bind(did)
+            .bind(name)
+            .fetch_optional(&self.db)
+            .await?;
+        if existing.is_some() {
+            return Err(PdsError::Conflict(format!("API token '{}' already exists", name)));
         }
 
-        if handle.len() > 253 {
-            return Err(PdsError::Validation("Handle too long".to_string()));
+        let mut unique = Vec::with_capacity(scopes.len());
+        for scope in scopes {
+            if !unique.contains(scope) {
+                unique.push(*scope);
+            }
         }
+        let now = self.clock.now();
+        let info = ApiTokenInfo {
+            id: self.ids.uuid().to_string(),
+            name: name.to_string(),
+            scopes: unique,
+            created_at: now,
+            expires_at: expires_in_days.map(|days| now + Duration::days(days as i64)),
+            last_used_at: None,
+        };
+        let token = format!("{}{}", API_TOKEN_PREFIX, Self::generate_random_string(40));
+        let scope_list: Vec<&str> = info.scopes.iter().map(ApiTokenScope::as_str).collect();
+
+        sqlx::query(
+            "INSERT INTO api_token (id, did, name, token_hash, scopes, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )
+        .bind(&info.id)
+        .bind(did)
+        .bind(&info.name)
+        .bind(hash_token(&token))
+        .bind(scope_list.join(" "))
+        .bind(info.created_at)
+        .bind(info.expires_at)
+        .execute(&self.db)
+        .await?;
+
+        tracing::info!(did = %did, name = %name, scopes = %scope_list.join(" "), "api_token_created");
+        Ok((info, token))
+    }
+
+    /// List a user's API tokens (without the tokens), newest first
+    pub async fn list_api_tokens(&self, did: &str) -> PdsResult<Vec<ApiTokenInfo>> {
+        let rows = sqlx::query(
+            "SELECT id, name, scopes, created_at, expires_at, last_used_at
+             FROM api_token WHERE did = ?1 ORDER BY created_at DESC",
+        )
+        .bind(did)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(ApiTokenInfo {
+                    id: row.get("id"),
+                    name: row.get("name"),
+                    scopes: ApiTokenScope::parse_list(row.get("scopes"))?,
+                    created_at: row.get("created_at"),
+                    expires_at: row.get("expires_at"),
+                    last_used_at: row.get("last_used_at"),
+                })
+            })
+            .collect()
+    }
+
+    /// Revoke one of a user's API tokens
+    pub async fn revoke_api_token(&self, did: &str, id: &str) -> PdsResult<()> {
+        let result = sqlx::query("DELETE FROM api_token WHERE id = ?1 AND did = ?2")
+            .bind(id)
+            .bind(did)
+            .execute(&self.db)
+            .await?;
 
-        if !handle.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '.') {
-            return Err(PdsError::Validation("Handle contains invalid characters".to_string()));
+        if result.rows_affected() == 0 {
+            return Err(PdsError::NotFound("API token not found".to_string()));
         }
 
+        tracing::info!(did = %did, id = %id, "api_token_revoked");
         Ok(())
     }
 
+    /// Validate an API token presented as a bearer token
+    async fn validate_api_token(&self, token: &str) -> PdsResult<crate::account::ValidatedSession> {
+        let row = sqlx::query("SELECT id, did, scopes, expires_at FROM api_token WHERE token_hash = ?1")
+            .bind(hash_token(token))
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or_else(|| PdsError::Authentication("Invalid API token".to_string()))?;
+
+        let id: String = row.get("id");
+        let expires_at: Option<DateTime<Utc>> = row.get("expires_at");
+        let now = self.clock.now();
+        if expires_at.is_some_and(|at| now > at) {
+            return Err(PdsError::Authentication("API token expired".to_string()));
+        }
+
+        sqlx::query(
+            "UPDATE api_token SET last_used_at = ?1
+             WHERE id = ?2 AND (last_used_at IS NULL OR last_used_at < ?3)",
+        )
+        .bind(now)
+        .bind(&id)
+        .bind(now - Duration::seconds(SESSION_LAST_USED_RESOLUTION_SECS))
+        .execute(&self.db)
+        .await?;
+
+        Ok(crate::account::ValidatedSession {
+            did: row.get("did"),
+            session_id: id,
+            is_app_password: true,
+            scopes: Some(ApiTokenScope::parse_list(row.get("scopes"))?),
+        })
+    }
+
+    /// Normalize a handle, validate its syntax and check the reserved handle list
+    fn validate_handle(&self, handle: &str) -> PdsResult<String> {
+        let handle = normalize_handle(handle)?;
+
+        if let Some(entry) = self
+            .reserved_handles
+            .check(&handle, &self.config.identity.service_handle_domains)
+        {
+            return Err(entry.rejection());
+        }
+
+        Ok(handle)
+    }
+
     /// Validate email format
     fn validate_email(&self, email: &str) -> PdsResult<()> {
         // Basic email validation
@@ -1181,10 +2281,109 @@ impl AccountManager {
 
         Ok(accounts)
     }
+
+    /// Search accounts for the admin API
+    ///
+    /// `domain` limits results to handles under a domain (for domain-scoped
+    /// admins). Each result carries the account's active moderation actions
+    /// and blob usage; `repo_bytes` is left for the caller to fill in.
+    pub async fn search_accounts(
+        &self,
+        search: &AccountSearch,
+        domain: Option<&str>,
+        limit: i64,
+        offset: i64,
+    ) -> PdsResult<Vec<AccountSearchResult>> {
+        // (condition, first bind, second bind, ranking)
+        let (condition, first, second, rank) = match search {
+            AccountSearch::Handle(text) => {
+                let text = escape_like(text);
+                (
+                    "a.handle LIKE '%' || ?1 || '%' ESCAPE '\\'",
+                    text.clone(),
+                    text,
+                    // Exact, then prefix (served by the NOCASE handle index), then substring
+                    "CASE WHEN a.handle = ?2 THEN 0 WHEN a.handle LIKE ?2 || '%' ESCAPE '\\' THEN 1 ELSE 2 END",
+                )
+            }
+            AccountSearch::Email(email) => (
+                "lower(a.email) = lower(?1)",
+                email.clone(),
+                String::new(),
+                "0",
+            ),
+            // A range over the primary key; DIDs are ASCII, so 0x7f sorts after any suffix
+            AccountSearch::DidPrefix(prefix) => (
+                "a.did >= ?1 AND a.did < ?2",
+                prefix.clone(),
+                format!("{}\x7f", prefix),
+                "0",
+            ),
+        };
+
+        let sql = format!(
+            "SELECT a.did, a.handle, a.email, a.created_at, a.status, a.taken_down, a.deactivated_at,
+                    (SELECT group_concat(DISTINCT m.action) FROM account_moderation m
+                     WHERE m.did = a.did AND m.reversed = 0) AS moderation,
+                    (SELECT COUNT(*) FROM blob_metadata b WHERE b.creator_did = a.did) AS blob_count,
+                    (SELECT COALESCE(SUM(b.size), 0) FROM blob_metadata b WHERE b.creator_did = a.did) AS blob_bytes
+             FROM account a
+             WHERE {}
+               AND (?3 IS NULL OR a.handle = ?3 OR a.handle LIKE '%.' || ?3)
+             ORDER BY {}, a.handle
+             LIMIT ?4 OFFSET ?5",
+            condition, rank
+        );
+
+        let rows = sqlx::query(&sql)
+            .bind(first)
+            .bind(second)
+            .bind(domain)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&self.db)
+            .await?;
+
+        rows.iter()
+            .map(|row| {
+                let moderation: Option<String> = row.try_get("moderation")?;
+                let mut moderation: Vec<String> = moderation
+                    .map(|actions| actions.split(',').map(str::to_string).collect())
+                    .unwrap_or_default();
+                moderation.sort();
+
+                Ok(AccountSearchResult {
+                    did: row.try_get("did")?,
+                    handle: row.try_get("handle")?,
+                    email: row.try_get("email")?,
+                    created_at: row.try_get("created_at")?,
+                    status: row.try_get("status")?,
+                    taken_down: row.try_get("taken_down")?,
+                    deactivated_at: row.try_get("deactivated_at")?,
+                    moderation,
+                    blob_count: row.try_get("blob_count")?,
+                    blob_bytes: row.try_get("blob_bytes")?,
+                    repo_bytes: None,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Escape `%`, `_` and `\` for a LIKE pattern with `ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
 }
 
 #[cfg(test)]
-mod tests {
+pub(crate) mod tests {
     use super::*;
     use crate::config::*;
     use std::path::PathBuf;
@@ -1193,7 +2392,7 @@ mod tests {
         create_test_manager().await
     }
 
-    async fn create_test_manager() -> AccountManager {
+    pub(crate) async fn create_test_manager() -> AccountManager {
 
         // Create in-memory database
         let db = SqlitePool::connect(":memory:").await.unwrap();
@@ -1228,6 +2427,9 @@ mod tests {
                 created_at DATETIME NOT NULL,
                 expires_at DATETIME NOT NULL,
                 app_password_name TEXT,
+                last_used_at DATETIME,
+                ip_address TEXT,
+                user_agent TEXT,
                 FOREIGN KEY (did) REFERENCES account(did)
             )
             "#,
@@ -1238,15 +2440,98 @@ mod tests {
 
         sqlx::query(
             r#"
-            CREATE TABLE refresh_token (
-                id TEXT PRIMARY KEY,
+            CREATE TABLE refresh_token (
+                id TEXT PRIMARY KEY,
+                did TEXT NOT NULL,
+                token TEXT UNIQUE NOT NULL,
+                created_at DATETIME NOT NULL,
+                expires_at DATETIME NOT NULL,
+                used BOOLEAN NOT NULL DEFAULT 0,
+                used_at DATETIME,
+                FOREIGN KEY (did) REFERENCES account(did)
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE app_password (
+                did TEXT NOT NULL,
+                name TEXT NOT NULL,
+                password_hash TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                privileged BOOLEAN NOT NULL DEFAULT 0,
+                PRIMARY KEY (did, name),
+                FOREIGN KEY (did) REFERENCES account(did)
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE api_token (
+                id TEXT PRIMARY KEY,
+                did TEXT NOT NULL,
+                name TEXT NOT NULL,
+                token_hash TEXT NOT NULL UNIQUE,
+                scopes TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                expires_at DATETIME,
+                last_used_at DATETIME,
+                UNIQUE (did, name)
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE handle_history (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                did TEXT NOT NULL,
+                old_handle TEXT NOT NULL,
+                new_handle TEXT NOT NULL,
+                changed_at TEXT NOT NULL,
+                redirect_until TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE pending_handle_change (
+                did TEXT PRIMARY KEY NOT NULL,
+                handle TEXT UNIQUE NOT NULL,
+                token TEXT UNIQUE NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE email_token (
+                token TEXT PRIMARY KEY NOT NULL,
                 did TEXT NOT NULL,
-                token TEXT UNIQUE NOT NULL,
-                created_at DATETIME NOT NULL,
+                purpose TEXT NOT NULL,
+                created_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
                 expires_at DATETIME NOT NULL,
-                used BOOLEAN NOT NULL DEFAULT 0,
-                used_at DATETIME,
-                FOREIGN KEY (did) REFERENCES account(did)
+                used BOOLEAN NOT NULL DEFAULT 0
             )
             "#,
         )
@@ -1256,14 +2541,15 @@ mod tests {
 
         sqlx::query(
             r#"
-            CREATE TABLE app_password (
+            CREATE TABLE account_deletion (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
                 did TEXT NOT NULL,
-                name TEXT NOT NULL,
-                password_hash TEXT NOT NULL,
-                created_at DATETIME NOT NULL,
-                privileged BOOLEAN NOT NULL DEFAULT 0,
-                PRIMARY KEY (did, name),
-                FOREIGN KEY (did) REFERENCES account(did)
+                handle TEXT NOT NULL,
+                deleted_at TEXT NOT NULL,
+                blobs_deleted INTEGER NOT NULL DEFAULT 0,
+                plc_tombstoned BOOLEAN NOT NULL DEFAULT 0,
+                account_seq INTEGER,
+                identity_seq INTEGER
             )
             "#,
         )
@@ -1279,6 +2565,17 @@ mod tests {
                 service_did: "did:web:localhost".to_string(),
                 version: "0.1.0".to_string(),
                 blob_upload_limit: 5242880,
+                blob_audio_upload_limit: 10485760,
+                blob_account_quota: None,
+                blob_animated_thumbnails: false,
+                blob_temp_ttl_hours: 24,
+                blob_max_staged_per_account: None,
+                listeners: vec![ListenerConfig {
+                    address: "127.0.0.1:2583".to_string(),
+                    role: ListenerRole::All,
+                }],
+                dev_mode: false,
+                pretty_json: false,
             },
             storage: StorageConfig {
                 data_directory: PathBuf::from("./data"),
@@ -1301,12 +2598,19 @@ mod tests {
                     redirect_uri: "http://localhost:3000/oauth/callback".to_string(),
                     pds_url: "http://localhost:3000".to_string(),
                 },
+                admin_network_token: None,
+                password_hash: crate::config::PasswordHashConfig::default(),
+                jwt_keys: crate::config::JwtKeysConfig::default(),
             },
             identity: IdentityConfig {
                 did_plc_url: "https://plc.directory".to_string(),
                 service_handle_domains: vec!["localhost".to_string()],
                 did_cache_stale_ttl: 3600,
                 did_cache_max_ttl: 86400,
+                handle_doh_url: "https://cloudflare-dns.com/dns-query".to_string(),
+                handle_redirect_secs: 0,
+                handle_changes: crate::config::HandleChangeConfig::default(),
+                plc_failover: crate::config::PlcFailoverConfig::default(),
             },
             email: None,
             invites: InviteConfig {
@@ -1320,7 +2624,39 @@ mod tests {
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
+                audit_log_retention_days: None,
+                tombstone_retention_days: None,
             },
+            mirror: MirrorConfig {
+                enabled: false,
+                source_url: "https://bsky.network".to_string(),
+                dids: vec![],
+            },
+            content_policy: ContentPolicyConfig::default(),
+            registration_challenge: RegistrationChallengeConfig::default(),
+            signup_throttle: SignupThrottleConfig::default(),
+            report_service: None,
+            bsky_app_view: None,
+            proxy: ProxyConfig::default(),
+            keys: KeyConfig::default(),
+            encryption: EncryptionConfig::default(),
+            cors: CorsConfig::default(),
+            security_headers: SecurityHeadersConfig::default(),
+            firehose: FirehoseConfig::default(),
+            http_cache: HttpCacheConfig::default(),
+            jobs: JobsConfig::default(),
+            compaction: CompactionConfig::default(),
+            trusted_proxies: TrustedProxyConfig::default(),
+            record_history: RecordHistoryConfig::default(),
+            write_hooks: WriteHooksConfig::default(),
+            nodeinfo: NodeInfoConfig::default(),
+            describe_server: DescribeServerConfig::default(),
+            replica: ReplicaConfig::default(),
+            repo_webhooks: RepoWebhookConfig::default(),
+            activitypub: ActivityPubConfig::default(),
+            feeds: FeedsConfig::default(),
+            body_limits: BodyLimitConfig::default(),
+            virtual_hosts: vec![],
         });
 
         AccountManager::new(db, config)
@@ -1485,7 +2821,7 @@ mod tests {
         // Create test account
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1525,7 +2861,7 @@ mod tests {
 
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1558,7 +2894,7 @@ mod tests {
         // Create test account
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1574,7 +2910,7 @@ mod tests {
 
         // Login with app password using handle
         let (auth_account, session, name) = manager
-            .login_with_app_password("testuser", &app_password)
+            .login_with_app_password("testuser.test", &app_password)
             .await
             .unwrap();
 
@@ -1609,7 +2945,7 @@ mod tests {
         // Create test account
         manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1619,7 +2955,7 @@ mod tests {
 
         // Try to login with invalid app password
         let result = manager
-            .login_with_app_password("testuser", "invalid-password")
+            .login_with_app_password("testuser.test", "invalid-password")
             .await;
 
         assert!(result.is_err());
@@ -1636,7 +2972,7 @@ mod tests {
         // Create test account
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1652,7 +2988,7 @@ mod tests {
 
         // Create session with app password
         manager
-            .login_with_app_password("testuser", &app_password)
+            .login_with_app_password("testuser.test", &app_password)
             .await
             .unwrap();
 
@@ -1695,7 +3031,7 @@ mod tests {
 
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1721,7 +3057,7 @@ mod tests {
 
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1748,7 +3084,7 @@ mod tests {
         // Create test account
         let account = manager
             .create_account(
-                "testuser".to_string(),
+                "testuser.test".to_string(),
                 Some("test@example.com".to_string()),
                 "password123".to_string(),
                 None,
@@ -1763,7 +3099,7 @@ mod tests {
             .unwrap();
 
         let (_account, session, _name) = manager
-            .login_with_app_password("testuser", &app_password)
+            .login_with_app_password("testuser.test", &app_password)
             .await
             .unwrap();
 
@@ -1777,7 +3113,7 @@ mod tests {
         assert_eq!(validated.is_app_password, true);
 
         // Create regular session for comparison
-        let (_account, regular_session) = manager.login("testuser", "password123").await.unwrap();
+        let (_account, regular_session) = manager.login("testuser.test", "password123").await.unwrap();
 
         let validated_regular = manager
             .validate_access_token(&regular_session.access_token)
@@ -1788,33 +3124,249 @@ mod tests {
         assert_eq!(validated_regular.is_app_password, false);
     }
 
+    #[tokio::test]
+    async fn test_list_and_revoke_sessions() {
+        let manager = setup_test_db().await;
+
+        let account = manager
+            .create_account(
+                "testuser.test".to_string(),
+                Some("test@example.com".to_string()),
+                "password123".to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let (_account, first) = manager.login("testuser.test", "password123").await.unwrap();
+        let (_account, second) = manager.login("testuser.test", "password123").await.unwrap();
+        let (_account, third) = manager.login("testuser.test", "password123").await.unwrap();
+
+        manager
+            .record_session_client(
+                &first.id,
+                &SessionClientInfo {
+                    ip_address: Some("203.0.113.7".to_string()),
+                    user_agent: Some("test-agent".to_string()),
+                },
+            )
+            .await
+            .unwrap();
+
+        // Using a session records when it was last used
+        manager.validate_access_token(&first.access_token).await.unwrap();
+
+        let sessions = manager.list_sessions(&account.did, &first.id).await.unwrap();
+        assert_eq!(sessions.len(), 3);
+
+        let current = sessions.iter().find(|s| s.current).unwrap();
+        assert_eq!(current.id, first.id);
+        assert_eq!(current.ip_address.as_deref(), Some("203.0.113.7"));
+        assert_eq!(current.user_agent.as_deref(), Some("test-agent"));
+        assert!(current.last_used_at.is_some());
+
+        // Revoke a single session
+        manager.revoke_session(&account.did, &second.id).await.unwrap();
+        assert!(manager.validate_access_token(&second.access_token).await.is_err());
+        assert!(manager.refresh_session(&second.refresh_token).await.is_err());
+
+        // Revoking another account's session is not allowed
+        assert!(manager.revoke_session("did:plc:other", &third.id).await.is_err());
+
+        // Revoke everything except the current session
+        let revoked = manager
+            .revoke_other_sessions(&account.did, &first.id)
+            .await
+            .unwrap();
+        assert_eq!(revoked, 1);
+
+        let sessions = manager.list_sessions(&account.did, &first.id).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, first.id);
+    }
+
+    #[tokio::test]
+    async fn test_api_tokens() {
+        use crate::clock::MockClock;
+
+        let clock = Arc::new(MockClock::fixed());
+        let manager = setup_test_db().await.with_clock(clock.clone());
+        let account = manager
+            .create_local_account("bot.test".to_string(), None, "password123".to_string())
+            .await
+            .unwrap();
+        let (_account, session) = manager.login("bot.test", "password123").await.unwrap();
+
+        let scopes = [ApiTokenScope::ReadRepo, ApiTokenScope::WriteRepo, ApiTokenScope::ReadRepo];
+        let (info, token) = manager.create_api_token(&account.did, "poster", &scopes, Some(30)).await.unwrap();
+        assert!(token.starts_with(API_TOKEN_PREFIX));
+        assert_eq!(info.scopes, vec![ApiTokenScope::ReadRepo, ApiTokenScope::WriteRepo]);
+        assert_eq!(info.expires_at, Some(clock.now() + Duration::days(30)));
+
+        assert!(manager.create_api_token(&account.did, "poster", &scopes, None).await.is_err());
+        assert!(manager.create_api_token(&account.did, "empty", &[], None).await.is_err());
+        assert!(manager.create_api_token(&account.did, "forever", &scopes, Some(0)).await.is_err());
+
+        // The token authenticates as the account, with its scopes
+        let validated = manager.validate_access_token(&token).await.unwrap();
+        assert_eq!(validated.did, account.did);
+        assert_eq!(validated.session_id, info.id);
+        assert!(validated.is_app_password);
+        assert_eq!(validated.scopes, Some(info.scopes.clone()));
+        assert!(manager.validate_access_token(&format!("{}nope", API_TOKEN_PREFIX)).await.is_err());
+
+        // Listed with the sessions, and revocable from there
+        let sessions = manager.list_sessions(&account.did, &session.id).await.unwrap();
+        assert_eq!(sessions.len(), 2);
+        let listed = sessions.iter().find(|s| s.id == info.id).unwrap();
+        assert_eq!(listed.api_token_name.as_deref(), Some("poster"));
+        assert_eq!(listed.last_used_at, Some(clock.now()));
+
+        clock.advance(Duration::days(31));
+        assert!(manager.validate_access_token(&token).await.is_err());
+        assert_eq!(manager.list_api_tokens(&account.did).await.unwrap().len(), 1);
+
+        let (other, other_token) = manager
+            .create_api_token(&account.did, "reader", &[ApiTokenScope::ReadBlobs], None)
+            .await
+            .unwrap();
+        manager.revoke_session(&account.did, &other.id).await.unwrap();
+        assert!(manager.validate_access_token(&other_token).await.is_err());
+        assert!(manager.revoke_api_token(&account.did, &other.id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_session_expiry_follows_clock() {
+        use crate::clock::{MockClock, SequentialIds};
+
+        let clock = Arc::new(MockClock::fixed());
+        let manager = setup_test_db()
+            .await
+            .with_clock(clock.clone())
+            .with_ids(Arc::new(SequentialIds::new()));
+
+        let account = manager
+            .create_local_account("clock.test".to_string(), None, "password123".to_string())
+            .await
+            .unwrap();
+        assert_eq!(account.created_at, clock.now());
+
+        let session = manager.create_session(&account.did, None).await.unwrap();
+        assert_eq!(session.id, uuid::Uuid::from_u128(1).to_string());
+        assert_eq!(session.expires_at, clock.now() + Duration::hours(1));
+
+        clock.advance(Duration::minutes(59));
+        manager.validate_access_token(&session.access_token).await.unwrap();
+
+        clock.advance(Duration::minutes(2));
+        assert!(manager.validate_access_token(&session.access_token).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_refresh_session_keeps_client_info() {
+        let manager = setup_test_db().await;
+
+        let account = manager
+            .create_account(
+                "testuser.test".to_string(),
+                Some("test@example.com".to_string()),
+                "password123".to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        let (_account, session) = manager.login("testuser.test", "password123").await.unwrap();
+        manager
+            .record_session_client(
+                &session.id,
+                &SessionClientInfo {
+                    ip_address: Some("198.51.100.1".to_string()),
+                    user_agent: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let refreshed = manager.refresh_session(&session.refresh_token).await.unwrap();
+
+        let sessions = manager.list_sessions(&account.did, &refreshed.id).await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, refreshed.id);
+        assert_eq!(sessions[0].ip_address.as_deref(), Some("198.51.100.1"));
+    }
+
+    #[tokio::test]
+    async fn test_login_rehashes_weak_password_hash() {
+        let manager = setup_test_db().await;
+
+        let account = manager
+            .create_account(
+                "testuser.test".to_string(),
+                Some("test@example.com".to_string()),
+                "password123".to_string(),
+                None,
+            )
+            .await
+            .unwrap();
+
+        // Simulate a hash created under an older, weaker policy
+        let weak = PasswordPolicy::new(&crate::config::PasswordHashConfig {
+            memory_kib: 1024,
+            iterations: 1,
+            parallelism: 1,
+        })
+        .unwrap();
+        let weak_hash = weak.hash("password123").unwrap();
+        sqlx::query("UPDATE account SET password_hash = ?1 WHERE did = ?2")
+            .bind(&weak_hash)
+            .bind(&account.did)
+            .execute(&manager.db)
+            .await
+            .unwrap();
+        assert!(manager.password_policy.needs_rehash(&weak_hash));
+
+        manager.login("testuser.test", "password123").await.unwrap();
+
+        let stored: String = sqlx::query_scalar("SELECT password_hash FROM account WHERE did = ?1")
+            .bind(&account.did)
+            .fetch_one(&manager.db)
+            .await
+            .unwrap();
+        assert_ne!(stored, weak_hash);
+        assert!(!manager.password_policy.needs_rehash(&stored));
+
+        // The upgraded hash still authenticates
+        manager.login("testuser.test", "password123").await.unwrap();
+    }
+
     #[tokio::test]
     async fn test_update_handle() {
         let manager = setup_test_db().await;
 
         // Create test account
         let account = manager
-            .create_account("alice".to_string(), None, "password123".to_string(), None)
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
             .await
             .unwrap();
 
-        assert_eq!(account.handle, "alice");
+        assert_eq!(account.handle, "alice.test");
 
         // Update handle to new value
         let old_handle = manager
-            .update_handle(&account.did, "alice-new")
+            .update_handle(&account.did, "alice-new.test")
             .await
             .unwrap();
 
-        assert_eq!(old_handle, "alice");
+        assert_eq!(old_handle, "alice.test");
 
         // Verify handle was updated in database
         let updated_account = manager.get_account(&account.did).await.unwrap();
-        assert_eq!(updated_account.handle, "alice-new");
+        assert_eq!(updated_account.handle, "alice-new.test");
 
         // Verify we can still get account by new handle
         let by_handle = manager
-            .get_account_by_identifier("alice-new")
+            .get_account_by_identifier("alice-new.test")
             .await
             .unwrap();
         assert_eq!(by_handle.did, account.did);
@@ -1826,29 +3378,29 @@ mod tests {
 
         // Create two accounts
         let account1 = manager
-            .create_account("alice".to_string(), None, "password123".to_string(), None)
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
             .await
             .unwrap();
 
         let account2 = manager
-            .create_account("bob".to_string(), None, "password456".to_string(), None)
+            .create_account("bob.test".to_string(), None, "password456".to_string(), None)
             .await
             .unwrap();
 
         // Try to update bob's handle to alice (should fail)
-        let result = manager.update_handle(&account2.did, "alice").await;
+        let result = manager.update_handle(&account2.did, "alice.test").await;
 
         assert!(result.is_err());
         match result {
-            Err(PdsError::Conflict(msg)) => {
-                assert!(msg.contains("already taken"));
+            Err(PdsError::HandleNotAvailable(handle)) => {
+                assert_eq!(handle, "alice.test");
             }
-            _ => panic!("Expected Conflict error"),
+            _ => panic!("Expected HandleNotAvailable error"),
         }
 
         // Verify bob's handle unchanged
         let bob_account = manager.get_account(&account2.did).await.unwrap();
-        assert_eq!(bob_account.handle, "bob");
+        assert_eq!(bob_account.handle, "bob.test");
     }
 
     #[tokio::test]
@@ -1857,21 +3409,21 @@ mod tests {
 
         // Create test account
         let account = manager
-            .create_account("alice".to_string(), None, "password123".to_string(), None)
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
             .await
             .unwrap();
 
         // Update to same handle (should be no-op)
         let old_handle = manager
-            .update_handle(&account.did, "alice")
+            .update_handle(&account.did, "alice.test")
             .await
             .unwrap();
 
-        assert_eq!(old_handle, "alice");
+        assert_eq!(old_handle, "alice.test");
 
         // Verify handle unchanged
         let updated_account = manager.get_account(&account.did).await.unwrap();
-        assert_eq!(updated_account.handle, "alice");
+        assert_eq!(updated_account.handle, "alice.test");
     }
 
     #[tokio::test]
@@ -1880,12 +3432,12 @@ mod tests {
 
         // Create test account
         let account = manager
-            .create_account("alice".to_string(), None, "password123".to_string(), None)
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
             .await
             .unwrap();
 
         // Try invalid handle with special characters
-        let result = manager.update_handle(&account.did, "alice@test").await;
+        let result = manager.update_handle(&account.did, "alice@test.test").await;
         assert!(result.is_err());
 
         // Try handle that's too long
@@ -1895,6 +3447,359 @@ mod tests {
 
         // Verify handle unchanged
         let unchanged_account = manager.get_account(&account.did).await.unwrap();
-        assert_eq!(unchanged_account.handle, "alice");
+        assert_eq!(unchanged_account.handle, "alice.test");
+    }
+
+    #[tokio::test]
+    async fn test_handle_normalized_on_update_and_login() {
+        let manager = setup_test_db().await;
+
+        let account = manager
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
+            .await
+            .unwrap();
+
+        manager.update_handle(&account.did, "@Alice-New.Test.").await.unwrap();
+        let updated = manager.get_account(&account.did).await.unwrap();
+        assert_eq!(updated.handle, "alice-new.test");
+
+        let (found, _) = manager.login("ALICE-NEW.test", "password123").await.unwrap();
+        assert_eq!(found.did, account.did);
+
+        // Confusable internationalized handles are rejected
+        assert!(manager.update_handle(&account.did, "раураl.test").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_handle_history_and_redirect() {
+        let mut manager = setup_test_db().await;
+        let mut config = (*manager.config).clone();
+        config.identity.handle_redirect_secs = 3600;
+        manager.config = Arc::new(config);
+
+        let alice = manager
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
+            .await
+            .unwrap();
+        let bob = manager
+            .create_account("bob.test".to_string(), None, "password456".to_string(), None)
+            .await
+            .unwrap();
+
+        manager.update_handle(&alice.did, "alice-new.test").await.unwrap();
+
+        let history = manager.handle_history(&alice.did).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].old_handle, "alice.test");
+        assert_eq!(history[0].new_handle, "alice-new.test");
+        assert!(history[0].redirect_until.is_some());
+
+        // The old handle still resolves to alice and can't be taken
+        assert_eq!(manager.did_for_handle("alice.test").await.unwrap(), Some(alice.did.clone()));
+        assert!(matches!(
+            manager.update_handle(&bob.did, "alice.test").await,
+            Err(PdsError::HandleNotAvailable(_))
+        ));
+        assert!(matches!(
+            manager.check_handle_availability("alice.test").await.unwrap(),
+            HandleAvailability::Unavailable { .. }
+        ));
+
+        // Alice can take it back, which ends the redirect
+        manager.update_handle(&alice.did, "alice.test").await.unwrap();
+        manager.update_handle(&alice.did, "alice-third.test").await.unwrap();
+        assert_eq!(manager.handle_history(&alice.did).await.unwrap().len(), 3);
+        assert!(manager.handle_redirect("alice-new.test").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_handle_change_limits() {
+        use crate::clock::MockClock;
+
+        let clock = Arc::new(MockClock::fixed());
+        let mut manager = setup_test_db().await.with_clock(clock.clone());
+        let mut config = (*manager.config).clone();
+        config.identity.handle_changes.cooldown_secs = 3600;
+        config.identity.handle_changes.max_per_month = 2;
+        manager.config = Arc::new(config);
+
+        let alice = manager
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
+            .await
+            .unwrap();
+
+        manager.update_handle(&alice.did, "alice-2.test").await.unwrap();
+        match manager.update_handle(&alice.did, "alice-3.test").await {
+            Err(PdsError::RateLimitExceeded { retry_after }) => assert_eq!(retry_after.as_secs(), 3600),
+            other => panic!("expected cooldown, got {:?}", other),
+        }
+
+        clock.advance(Duration::hours(2));
+        manager.update_handle(&alice.did, "alice-3.test").await.unwrap();
+
+        // Two changes in the last 30 days; the first ages out after 30 days
+        clock.advance(Duration::days(1));
+        match manager.update_handle(&alice.did, "alice-4.test").await {
+            Err(PdsError::RateLimitExceeded { retry_after }) => {
+                assert_eq!(retry_after.as_secs() as i64, (Duration::days(29) - Duration::hours(2)).num_seconds())
+            }
+            other => panic!("expected monthly limit, got {:?}", other),
+        }
+
+        clock.advance(Duration::days(29));
+        manager.update_handle(&alice.did, "alice-4.test").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_handle_claims() {
+        let manager = setup_test_db().await;
+        let alice = manager
+            .create_account("alice.test".to_string(), None, "password123".to_string(), None)
+            .await
+            .unwrap();
+        let bob = manager
+            .create_account("bob.test".to_string(), None, "password456".to_string(), None)
+            .await
+            .unwrap();
+
+        let (a, b) = tokio::join!(
+            manager.update_handle(&alice.did, "shared.test"),
+            manager.update_handle(&bob.did, "shared.test"),
+        );
+
+        // Exactly one wins; the loser is told the handle is taken, not a database error
+        assert!(a.is_ok() != b.is_ok(), "{:?} / {:?}", a, b);
+        assert!(matches!(a.err().or(b.err()), Some(PdsError::HandleNotAvailable(_))));
+        let holder = manager.did_for_handle("shared.test").await.unwrap().unwrap();
+        assert_eq!(manager.handle_history(&holder).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_account_creation() {
+        let manager = setup_test_db().await;
+
+        // Many signups racing for one handle
+        let results = futures::future::join_all((0..8).map(|i| {
+            manager.create_account("race.test".to_string(), None, format!("password{}", i), None)
+        }))
+        .await;
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        for result in results.iter().filter(|r| r.is_err()) {
+            assert!(matches!(result, Err(PdsError::HandleNotAvailable(h)) if h == "race.test"), "{:?}", result);
+        }
+
+        // And for one email
+        let results = futures::future::join_all((0..8).map(|i| {
+            manager.create_account(
+                format!("racer{}.test", i),
+                Some("race@example.com".to_string()),
+                "password123".to_string(),
+                None,
+            )
+        }))
+        .await;
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 1);
+        for result in results.iter().filter(|r| r.is_err()) {
+            assert!(matches!(result, Err(PdsError::EmailTaken)), "{:?}", result);
+        }
+
+        let accounts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account")
+            .fetch_one(&manager.db)
+            .await
+            .unwrap();
+        assert_eq!(accounts, 2);
+    }
+
+    #[tokio::test]
+    async fn test_insert_account_maps_constraints() {
+        let manager = setup_test_db().await;
+        manager
+            .insert_account("did:plc:a".into(), "a.test".into(), Some("a@example.com".into()), "x".into(), None, None, None)
+            .await
+            .unwrap();
+
+        // Past the pre-checks, the constraints still say which field clashed
+        assert!(matches!(
+            manager
+                .insert_account("did:plc:b".into(), "a.test".into(), None, "x".into(), None, None, None)
+                .await,
+            Err(PdsError::HandleNotAvailable(_))
+        ));
+        assert!(matches!(
+            manager
+                .insert_account("did:plc:b".into(), "b.test".into(), Some("a@example.com".into()), "x".into(), None, None, None)
+                .await,
+            Err(PdsError::EmailTaken)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_emailed_handle_change() {
+        use crate::clock::MockClock;
+
+        let clock = Arc::new(MockClock::fixed());
+        let manager = setup_test_db().await.with_clock(clock.clone());
+        let alice = manager
+            .create_account("alice.test".to_string(), Some("alice@example.com".to_string()), "password123".to_string(), None)
+            .await
+            .unwrap();
+        let bob = manager
+            .create_account("bob.test".to_string(), None, "password456".to_string(), None)
+            .await
+            .unwrap();
+
+        let (handle, code, _) = manager.request_handle_change(&alice.did, "New-Alice.test").await.unwrap();
+        assert_eq!(handle, "new-alice.test");
+
+        // The pending handle is reserved for alice
+        assert!(matches!(
+            manager.request_handle_change(&bob.did, "new-alice.test").await,
+            Err(PdsError::HandleNotAvailable(_))
+        ));
+        assert!(matches!(
+            manager.update_handle(&bob.did, "new-alice.test").await,
+            Err(PdsError::HandleNotAvailable(_))
+        ));
+        assert!(matches!(
+            manager.check_handle_availability("new-alice.test").await.unwrap(),
+            HandleAvailability::Unavailable { .. }
+        ));
+
+        // Only alice's own code applies it, once
+        assert!(manager.confirm_handle_change(&bob.did, &code).await.is_err());
+        assert!(manager.confirm_handle_change(&alice.did, "wrong").await.is_err());
+        let (old, new) = manager.confirm_handle_change(&alice.did, &code).await.unwrap();
+        assert_eq!((old.as_str(), new.as_str()), ("alice.test", "new-alice.test"));
+        assert!(manager.confirm_handle_change(&alice.did, &code).await.is_err());
+
+        // An expired code releases the reservation
+        let (_, code, _) = manager.request_handle_change(&alice.did, "other-alice.test").await.unwrap();
+        clock.advance(Duration::hours(2));
+        assert!(matches!(
+            manager.confirm_handle_change(&alice.did, &code).await,
+            Err(PdsError::Validation(_))
+        ));
+        manager.update_handle(&bob.did, "other-alice.test").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_purge_account_records_deletion() {
+        let manager = create_test_manager().await;
+        let now = Utc::now();
+        let did = "did:web:purged.localhost";
+
+        sqlx::query(
+            "INSERT INTO account (did, handle, email, password_hash, created_at, email_confirmed, taken_down)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)"
+        )
+        .bind(did)
+        .bind("purged.localhost")
+        .bind("purged@example.com")
+        .bind("hash")
+        .bind(now)
+        .bind(false)
+        .bind(false)
+        .execute(&manager.db)
+        .await
+        .unwrap();
+
+        sqlx::query(
+            "INSERT INTO session (id, did, access_token, refresh_token, created_at, expires_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)"
+        )
+        .bind("purged-session")
+        .bind(did)
+        .bind("purged-access")
+        .bind("purged-refresh")
+        .bind(now)
+        .bind(now + Duration::hours(1))
+        .execute(&manager.db)
+        .await
+        .unwrap();
+
+        // did:web identities have nothing to tombstone
+        assert!(!manager.tombstone_plc(did).await.unwrap());
+
+        let deletion = manager.purge_account(did, 3, false, Some(10), Some(11)).await.unwrap();
+        assert_eq!(deletion.handle, "purged.localhost");
+        assert_eq!(deletion.blobs_deleted, 3);
+
+        let accounts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account WHERE did = ?1")
+            .bind(did)
+            .fetch_one(&manager.db)
+            .await
+            .unwrap();
+        assert_eq!(accounts, 0);
+        let sessions: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM session WHERE did = ?1")
+            .bind(did)
+            .fetch_one(&manager.db)
+            .await
+            .unwrap();
+        assert_eq!(sessions, 0);
+
+        let deletions = manager.list_account_deletions(10, None).await.unwrap();
+        assert_eq!(deletions.len(), 1);
+        assert_eq!(deletions[0].did, did);
+        assert_eq!(deletions[0].account_seq, Some(10));
+        assert_eq!(deletions[0].identity_seq, Some(11));
+        assert!(manager.list_account_deletions(10, Some(deletions[0].id)).await.unwrap().is_empty());
+
+        // Purging again fails rather than recording a second deletion
+        assert!(manager.purge_account(did, 0, false, None, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_search_accounts() {
+        // Moderation and blob usage come from tables only the full schema has
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let manager = AccountManager::new(db, create_test_manager().await.config.clone());
+
+        for (did, handle, email) in [
+            ("did:plc:aaa111", "alice.test", "Alice@Example.com"),
+            ("did:plc:aaa222", "malice.test", "malice@example.com"),
+            ("did:plc:bbb333", "bob.other", "bob@example.com"),
+        ] {
+            sqlx::query("INSERT INTO account (did, handle, email, password_hash) VALUES (?1, ?2, ?3, 'hash')")
+                .bind(did)
+                .bind(handle)
+                .bind(email)
+                .execute(&manager.db)
+                .await
+                .unwrap();
+        }
+        sqlx::query("INSERT INTO account_moderation (did, action) VALUES ('did:plc:aaa222', 'suspend')")
+            .execute(&manager.db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO blob_metadata (cid, mime_type, size, creator_did) VALUES ('bafyblob', 'image/png', 100, 'did:plc:aaa111')",
+        )
+        .execute(&manager.db)
+        .await
+        .unwrap();
+
+        let search = |q: &str| AccountSearch::parse(q).unwrap();
+        let handles = |results: Vec<AccountSearchResult>| results.into_iter().map(|r| r.handle).collect::<Vec<_>>();
+
+        // Prefix matches rank ahead of substring matches
+        let results = manager.search_accounts(&search("@ali"), None, 10, 0).await.unwrap();
+        assert_eq!(results[0].blob_bytes, 100);
+        assert_eq!(handles(results), vec!["alice.test", "malice.test"]);
+
+        let results = manager.search_accounts(&search("alice@example.com"), None, 10, 0).await.unwrap();
+        assert_eq!(handles(results), vec!["alice.test"]);
+
+        let results = manager.search_accounts(&search("did:plc:aaa"), None, 10, 0).await.unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[1].moderation, vec!["suspend"]);
+        assert_eq!(results[1].status, "active");
+
+        // Domain scoping, limits and LIKE wildcards
+        let results = manager.search_accounts(&search("b"), Some("other"), 10, 0).await.unwrap();
+        assert_eq!(handles(results), vec!["bob.other"]);
+        assert_eq!(manager.search_accounts(&search("alice"), None, 1, 1).await.unwrap().len(), 1);
+        assert!(manager.search_accounts(&search("%"), None, 10, 0).await.unwrap().is_empty());
+        assert!(AccountSearch::parse(" @ ").is_err());
     }
 }
@@ -2,12 +2,43 @@
 ///
 /// Handles user account creation, authentication, sessions, and related operations.
 
+mod api_token;
+mod challenge;
+mod events;
+mod handle;
+mod instance_stats;
 mod manager;
+mod password;
+mod repo_webhooks;
+mod reserved;
+mod signup_throttle;
 
+pub use api_token::{
+    required_scope, ApiTokenInfo, ApiTokenScope, CreateApiTokenRequest, CreateApiTokenResponse,
+    ListApiTokensResponse, RevokeApiTokenRequest, ScopeRequirement, API_TOKEN_PREFIX,
+};
+pub use challenge::{ChallengeDescription, ChallengeParams, ChallengeVerifier, RegistrationChallenge};
+pub use events::AccountEvents;
+pub use handle::normalize_handle;
+pub use instance_stats::{InstanceStats, InstanceStatsSnapshot};
 pub use manager::AccountManager;
+pub use password::PasswordPolicy;
+pub use repo_webhooks::{events as repo_webhook_events, RecordEventData, RepoWebhook, RepoWebhookManager};
+pub use reserved::{ReservedHandle, ReservedHandleKind, ReservedHandleManager};
+pub use signup_throttle::{SignupThrottle, SignupThrottleStatus};
 
 use serde::{Deserialize, Serialize};
 
+/// Result of a handle availability check
+#[derive(Debug, Clone, PartialEq)]
+pub enum HandleAvailability {
+    Available,
+    Unavailable {
+        reason: String,
+        suggestions: Vec<String>,
+    },
+}
+
 /// Account creation request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateAccountRequest {
@@ -15,6 +46,8 @@ pub struct CreateAccountRequest {
     pub email: Option<String>,
     pub password: String,
     pub invite_code: Option<String>,
+    /// Solution to the registration challenge, when one is required
+    pub challenge_token: Option<String>,
 }
 
 /// Account creation response
@@ -56,6 +89,127 @@ pub struct SessionInfo {
     pub email_confirmed: Option<bool>,
 }
 
+/// Active session details (for listSessions)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActiveSessionInfo {
+    pub id: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub app_password_name: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Name of the API token this entry stands for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_token_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scopes: Option<Vec<ApiTokenScope>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+/// One recorded handle change (for admin handle history)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandleChange {
+    pub id: i64,
+    pub did: String,
+    pub old_handle: String,
+    pub new_handle: String,
+    pub changed_at: chrono::DateTime<chrono::Utc>,
+    /// Until when the old handle still resolves to this DID
+    pub redirect_until: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// What an admin account search matches on
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountSearch {
+    /// Handles containing the text, exact and prefix matches first
+    Handle(String),
+    /// Exact email address, ignoring case
+    Email(String),
+    /// DIDs starting with the text
+    DidPrefix(String),
+}
+
+impl AccountSearch {
+    /// Pick the match from the query: `did:` prefixes, email addresses,
+    /// otherwise partial handles (a leading `@` is ignored)
+    pub fn parse(query: &str) -> crate::error::PdsResult<Self> {
+        let query = query.trim();
+
+        if query.starts_with("did:") {
+            return Ok(Self::DidPrefix(query.to_string()));
+        }
+        if query.contains('@') && !query.starts_with('@') {
+            return Ok(Self::Email(query.to_string()));
+        }
+        let handle = query.trim_start_matches('@').to_lowercase();
+        if handle.is_empty() {
+            return Err(crate::error::PdsError::Validation("Search query is empty".to_string()));
+        }
+        Ok(Self::Handle(handle))
+    }
+}
+
+/// An account found by admin search, with moderation and storage details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountSearchResult {
+    pub did: String,
+    pub handle: String,
+    pub email: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub status: String,
+    pub taken_down: bool,
+    pub deactivated_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Active (unreversed) moderation actions, e.g. `["suspend"]`
+    pub moderation: Vec<String>,
+    pub blob_count: i64,
+    pub blob_bytes: i64,
+    /// Size of the actor store on disk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repo_bytes: Option<u64>,
+}
+
+/// A purged account, recorded after its data is gone
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountDeletion {
+    pub id: i64,
+    pub did: String,
+    pub handle: String,
+    pub deleted_at: chrono::DateTime<chrono::Utc>,
+    pub blobs_deleted: i64,
+    /// Whether a PLC tombstone was submitted for the DID
+    pub plc_tombstoned: bool,
+    /// Seq of the `#account` (deleted) event
+    pub account_seq: Option<i64>,
+    /// Seq of the `#identity` event announcing the tombstone
+    pub identity_seq: Option<i64>,
+}
+
+/// Client details captured when a session is created
+#[derive(Debug, Clone, Default)]
+pub struct SessionClientInfo {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+/// List sessions response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListSessionsResponse {
+    pub sessions: Vec<ActiveSessionInfo>,
+}
+
+/// Revoke session request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevokeSessionRequest {
+    pub id: String,
+}
+
 /// Token refresh request
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -68,7 +222,11 @@ pub struct RefreshSessionRequest {
 pub struct ValidatedSession {
     pub did: String,
     pub session_id: String,
+    /// Also set for API tokens, which get no more account access than an
+    /// app password
     pub is_app_password: bool,
+    /// Scopes of an API token; `None` for sessions
+    pub scopes: Option<Vec<ApiTokenScope>>,
 }
 
 /// App password info (without the actual password)
@@ -0,0 +1,136 @@
+/// Password hashing policy
+///
+/// Wraps Argon2id with operator-configurable parameters and detects stored
+/// hashes that were produced under a weaker policy so they can be upgraded
+/// on the next successful login.
+use crate::{
+    config::PasswordHashConfig,
+    error::{PdsError, PdsResult},
+};
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Algorithm, Argon2, Params, Version,
+};
+
+/// Argon2id password hashing policy
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    params: Params,
+}
+
+impl PasswordPolicy {
+    /// Build a policy from configuration
+    pub fn new(config: &PasswordHashConfig) -> PdsResult<Self> {
+        let params = Params::new(config.memory_kib, config.iterations, config.parallelism, None)
+            .map_err(|e| PdsError::Validation(format!("Invalid Argon2 parameters: {}", e)))?;
+        Ok(Self { params })
+    }
+
+    /// Policy using the default Argon2id parameters
+    pub fn default_policy() -> Self {
+        Self {
+            params: Params::default(),
+        }
+    }
+
+    fn argon2(&self) -> Argon2<'static> {
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, self.params.clone())
+    }
+
+    /// Hash a password under the current policy
+    pub fn hash(&self, password: &str) -> PdsResult<String> {
+        let salt = SaltString::generate(&mut OsRng);
+        self.argon2()
+            .hash_password(password.as_bytes(), &salt)
+            .map(|hash| hash.to_string())
+            .map_err(|e| PdsError::Internal(format!("Password hashing failed: {}", e)))
+    }
+
+    /// Verify a password against a stored hash
+    ///
+    /// Verification uses the parameters embedded in the stored hash, so
+    /// hashes created under older policies keep working.
+    pub fn verify(&self, password: &str, hash: &str) -> PdsResult<bool> {
+        let parsed = PasswordHash::new(hash)
+            .map_err(|e| PdsError::Internal(format!("Password verification failed: {}", e)))?;
+        Ok(self
+            .argon2()
+            .verify_password(password.as_bytes(), &parsed)
+            .is_ok())
+    }
+
+    /// Whether a stored hash is weaker than the current policy
+    ///
+    /// Unparseable hashes are never flagged; they fail verification instead.
+    pub fn needs_rehash(&self, hash: &str) -> bool {
+        let parsed = match PasswordHash::new(hash) {
+            Ok(parsed) => parsed,
+            Err(_) => return false,
+        };
+
+        if parsed.algorithm != Algorithm::Argon2id.ident()
+            || parsed.version != Some(Version::V0x13.into())
+        {
+            return true;
+        }
+
+        match Params::try_from(&parsed) {
+            Ok(stored) => {
+                stored.m_cost() < self.params.m_cost()
+                    || stored.t_cost() < self.params.t_cost()
+                    || stored.p_cost() < self.params.p_cost()
+            }
+            Err(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(memory_kib: u32, iterations: u32, parallelism: u32) -> PasswordPolicy {
+        PasswordPolicy::new(&PasswordHashConfig {
+            memory_kib,
+            iterations,
+            parallelism,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_hash_and_verify() {
+        let policy = policy(1024, 1, 1);
+        let hash = policy.hash("correct horse").unwrap();
+
+        assert!(hash.starts_with("$argon2id$"));
+        assert!(policy.verify("correct horse", &hash).unwrap());
+        assert!(!policy.verify("battery staple", &hash).unwrap());
+        assert!(!policy.needs_rehash(&hash));
+    }
+
+    #[test]
+    fn test_weaker_hash_needs_rehash() {
+        let old = policy(1024, 1, 1);
+        let hash = old.hash("correct horse").unwrap();
+
+        let stronger = policy(2048, 2, 1);
+        assert!(stronger.needs_rehash(&hash));
+        // Old hashes still verify under the new policy
+        assert!(stronger.verify("correct horse", &hash).unwrap());
+
+        // A weaker policy never downgrades a stronger hash
+        let weaker = policy(512, 1, 1);
+        assert!(!weaker.needs_rehash(&hash));
+    }
+
+    #[test]
+    fn test_rejects_invalid_params() {
+        assert!(PasswordPolicy::new(&PasswordHashConfig {
+            memory_kib: 1,
+            iterations: 1,
+            parallelism: 1,
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,587 @@
+/// Repo event webhooks
+///
+/// Accounts register endpoints that receive events from their own
+/// repository (records created, updated or deleted, blobs uploaded), so
+/// bridges and automation can react without consuming the firehose. Each
+/// webhook can be limited to event types and to collections; filter entries
+/// ending in `.*` match a prefix (`record.*`, `app.bsky.feed.*`).
+///
+/// Record events come from commits as they are sequenced, including ones
+/// too big for the firehose. Deliveries are queued in
+/// `repo_webhook_delivery` and sent by the `repo_webhook_delivery` job,
+/// signed like moderation webhooks (see [`crate::admin::webhooks`]) and
+/// retried by the same [`crate::delivery`] queue; redirects are not followed.
+///
+/// Targets must be https and not on loopback, private or link-local
+/// addresses unless `PDS_REPO_WEBHOOKS_ALLOW_PRIVATE_TARGETS` is set.
+/// Hostnames are checked as written, not resolved.
+use crate::{
+    actor_store::{record_codec::decode_record, ActorStore},
+    admin::webhooks::{matches_filter, post_signed_row},
+    delivery::{parse_time, DeliveryQueue, DeliveryStatus, WebhookDelivery},
+    config::RepoWebhookConfig,
+    error::{PdsError, PdsResult},
+    net::check_public_url,
+    sequencer::events::{CommitEvent, OpAction},
+};
+use chrono::{DateTime, Utc};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    Notify,
+};
+
+/// Event types sent to repo webhooks
+pub mod events {
+    pub const RECORD_CREATED: &str = "record.created";
+    pub const RECORD_UPDATED: &str = "record.updated";
+    pub const RECORD_DELETED: &str = "record.deleted";
+    pub const BLOB_UPLOADED: &str = "blob.uploaded";
+
+    pub const ALL: &[&str] = &[RECORD_CREATED, RECORD_UPDATED, RECORD_DELETED, BLOB_UPLOADED];
+}
+
+/// Registered repo webhook (the secret is only returned at registration)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoWebhook {
+    pub id: i64,
+    pub did: String,
+    pub url: String,
+    /// Event types delivered to this webhook (empty means all)
+    pub events: Vec<String>,
+    /// Collections whose record events are delivered (empty means all)
+    pub collections: Vec<String>,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RepoWebhook {
+    /// Whether this webhook subscribes to an event, in `collection` for record events
+    pub fn wants(&self, event_type: &str, collection: Option<&str>) -> bool {
+        matches_filter(&self.events, event_type)
+            && collection.map_or(true, |collection| matches_filter(&self.collections, collection))
+    }
+}
+
+/// Data of a record event
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordEventData<'a> {
+    pub uri: String,
+    pub collection: &'a str,
+    pub rkey: &'a str,
+    /// Record CID (none for deletes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<&'a str>,
+    /// The record as written (none for deletes)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<serde_json::Value>,
+    pub commit: &'a str,
+    pub rev: &'a str,
+}
+
+/// Signed event body
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RepoEventPayload<'a, T: Serialize> {
+    event: &'a str,
+    did: &'a str,
+    created_at: DateTime<Utc>,
+    data: &'a T,
+}
+
+/// Repo webhook registry and delivery queue
+pub struct RepoWebhookManager {
+    db: SqlitePool,
+    deliveries: DeliveryQueue,
+    config: RepoWebhookConfig,
+    http_client: reqwest::Client,
+    /// Wakes the delivery job when new events are queued
+    notify: Arc<Notify>,
+}
+
+impl RepoWebhookManager {
+    pub fn new(db: SqlitePool, config: RepoWebhookConfig) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            deliveries: DeliveryQueue::new(db.clone(), "repo_webhook_delivery").with_owner("did"),
+            db,
+            config,
+            http_client,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Signal raised whenever deliveries are queued
+    pub fn notifier(&self) -> Arc<Notify> {
+        self.notify.clone()
+    }
+
+    /// Register a webhook for `did`'s repo, returning it with its signing secret
+    ///
+    /// A random secret is generated when none is given.
+    pub async fn register(
+        &self,
+        did: &str,
+        url: &str,
+        events: Vec<String>,
+        collections: Vec<String>,
+        secret: Option<String>,
+    ) -> PdsResult<(RepoWebhook, String)> {
+        if !self.config.enabled {
+            return Err(PdsError::Validation("Repo webhooks are disabled on this server".to_string()));
+        }
+        self.check_target(url)?;
+
+        let registered: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM repo_webhook WHERE did = ?1")
+            .bind(did)
+            .fetch_one(&self.db)
+            .await?;
+        if registered as usize >= self.config.max_per_account {
+            return Err(PdsError::Validation(format!(
+                "At most {} webhooks can be registered per account",
+                self.config.max_per_account
+            )));
+        }
+
+        let secret = match secret {
+            Some(s) if s.len() < 16 => {
+                return Err(PdsError::Validation(
+                    "Webhook secret must be at least 16 characters".to_string(),
+                ))
+            }
+            Some(s) => s,
+            None => {
+                let mut bytes = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                hex::encode(bytes)
+            }
+        };
+
+        let events = clean_filter(events);
+        if let Some(unknown) = events
+            .iter()
+            .find(|e| !events::ALL.iter().any(|known| matches_filter(std::slice::from_ref(e), known)))
+        {
+            return Err(PdsError::Validation(format!("Unknown webhook event: {}", unknown)));
+        }
+        let collections = clean_filter(collections);
+        if let Some(invalid) = collections
+            .iter()
+            .find(|c| !c.chars().all(|ch| ch.is_ascii_alphanumeric() || matches!(ch, '.' | '-' | '*')))
+        {
+            return Err(PdsError::Validation(format!("Invalid collection filter: {}", invalid)));
+        }
+
+        let now = Utc::now();
+        let result = sqlx::query(
+            r#"
+            INSERT INTO repo_webhook (did, url, secret, events, collections, enabled, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, 1, ?6)
+            "#,
+        )
+        .bind(did)
+        .bind(url)
+        .bind(&secret)
+        .bind(events.join(","))
+        .bind(collections.join(","))
+        .bind(now.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        let webhook = RepoWebhook {
+            id: result.last_insert_rowid(),
+            did: did.to_string(),
+            url: url.to_string(),
+            events,
+            collections,
+            enabled: true,
+            created_at: now,
+        };
+
+        Ok((webhook, secret))
+    }
+
+    /// Refuse URLs that aren't https or that point into the server's network
+    fn check_target(&self, url: &str) -> PdsResult<()> {
+        check_public_url(url, "Webhook URL", self.config.allow_private_targets)
+    }
+
+    /// Remove one of `did`'s webhooks and its delivery history
+    pub async fn remove(&self, did: &str, id: i64) -> PdsResult<()> {
+        let result = sqlx::query("DELETE FROM repo_webhook WHERE id = ?1 AND did = ?2")
+            .bind(id)
+            .bind(did)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PdsError::NotFound(format!("Webhook not found: {}", id)));
+        }
+
+        sqlx::query("DELETE FROM repo_webhook_delivery WHERE webhook_id = ?1")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Remove every webhook and delivery of an account (account deletion)
+    pub async fn remove_all(&self, did: &str) -> PdsResult<()> {
+        let mut tx = self.db.begin().await?;
+        for table in ["repo_webhook_delivery", "repo_webhook"] {
+            sqlx::query(&format!("DELETE FROM {} WHERE did = ?1", table))
+                .bind(did)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Webhooks registered for `did`'s repo
+    pub async fn list(&self, did: &str) -> PdsResult<Vec<RepoWebhook>> {
+        let rows = sqlx::query(
+            "SELECT id, did, url, events, collections, enabled, created_at
+             FROM repo_webhook WHERE did = ?1 ORDER BY id",
+        )
+        .bind(did)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let split = |column: &str| -> Vec<String> {
+                    row.get::<String, _>(column)
+                        .split(',')
+                        .filter(|e| !e.is_empty())
+                        .map(String::from)
+                        .collect()
+                };
+
+                Ok(RepoWebhook {
+                    id: row.get("id"),
+                    did: row.get("did"),
+                    url: row.get("url"),
+                    events: split("events"),
+                    collections: split("collections"),
+                    enabled: row.get("enabled"),
+                    created_at: parse_time(&row.get::<String, _>("created_at"))?,
+                })
+            })
+            .collect()
+    }
+
+    /// Queue an event from `did`'s repo for every webhook subscribed to it
+    ///
+    /// Failures are logged rather than returned so that writes never fail
+    /// because of webhook bookkeeping.
+    pub async fn dispatch<T: Serialize>(&self, did: &str, event_type: &str, collection: Option<&str>, data: &T) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let result = async {
+            let webhooks = self.list(did).await?;
+            let targets: Vec<&RepoWebhook> = webhooks
+                .iter()
+                .filter(|w| w.enabled && w.wants(event_type, collection))
+                .collect();
+            self.enqueue(did, event_type, &targets, data).await
+        }
+        .await;
+
+        match result {
+            Ok(0) => {}
+            Ok(_) => self.notify.notify_one(),
+            Err(e) => tracing::warn!("Failed to queue {} repo webhook event for {}: {}", event_type, did, e),
+        }
+    }
+
+    /// Queue record events for the ops of a sequenced commit
+    ///
+    /// Returns the deliveries queued.
+    pub async fn on_commit(&self, store: &ActorStore, evt: &CommitEvent) -> PdsResult<usize> {
+        if evt.ops.is_empty() {
+            return Ok(0);
+        }
+        let webhooks: Vec<RepoWebhook> = self.list(&evt.repo).await?.into_iter().filter(|w| w.enabled).collect();
+        if webhooks.is_empty() {
+            return Ok(0);
+        }
+
+        let mut queued = 0;
+        for op in &evt.ops {
+            let (collection, rkey) = op.path.split_once('/').unwrap_or((op.path.as_str(), ""));
+            let event_type = match op.action {
+                OpAction::Create => events::RECORD_CREATED,
+                OpAction::Update => events::RECORD_UPDATED,
+                OpAction::Delete => events::RECORD_DELETED,
+            };
+            let targets: Vec<&RepoWebhook> =
+                webhooks.iter().filter(|w| w.wants(event_type, Some(collection))).collect();
+            if targets.is_empty() {
+                continue;
+            }
+
+            let record = match &op.cid {
+                Some(cid) => match store.get_block(&evt.repo, cid).await? {
+                    Some(bytes) => Some(decode_record(&bytes)?),
+                    None => None,
+                },
+                None => None,
+            };
+            let data = RecordEventData {
+                uri: format!("at://{}/{}", evt.repo, op.path),
+                collection,
+                rkey,
+                cid: op.cid.as_deref(),
+                record,
+                commit: &evt.commit,
+                rev: &evt.rev,
+            };
+            queued += self.enqueue(&evt.repo, event_type, &targets, &data).await?;
+        }
+
+        if queued > 0 {
+            self.notify.notify_one();
+        }
+        Ok(queued)
+    }
+
+    /// Queue record events for commits in the background until the sequencer goes away
+    pub fn spawn(self: Arc<Self>, store: Arc<ActorStore>, mut commits: broadcast::Receiver<CommitEvent>) {
+        tokio::spawn(async move {
+            loop {
+                match commits.recv().await {
+                    Ok(evt) => {
+                        if let Err(e) = self.on_commit(&store, &evt).await {
+                            tracing::warn!(did = %evt.repo, error = %e, "repo_webhook_dispatch_failed");
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "repo webhooks lagged behind commits");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    async fn enqueue<T: Serialize>(
+        &self,
+        did: &str,
+        event_type: &str,
+        targets: &[&RepoWebhook],
+        data: &T,
+    ) -> PdsResult<usize> {
+        if targets.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now();
+        let payload = serde_json::to_string(&RepoEventPayload {
+            event: event_type,
+            did,
+            created_at: now,
+            data,
+        })
+        .map_err(|e| PdsError::Internal(format!("Failed to serialize webhook payload: {}", e)))?;
+
+        for webhook in targets {
+            sqlx::query(
+                r#"
+                INSERT INTO repo_webhook_delivery
+                (webhook_id, did, event_type, payload, status, attempts, created_at, next_attempt_at)
+                VALUES (?1, ?2, ?3, ?4, 'pending', 0, ?5, ?5)
+                "#,
+            )
+            .bind(webhook.id)
+            .bind(did)
+            .bind(event_type)
+            .bind(&payload)
+            .bind(now.to_rfc3339())
+            .execute(&self.db)
+            .await?;
+        }
+
+        Ok(targets.len())
+    }
+
+    /// List `did`'s deliveries, newest first, optionally for one webhook or status
+    pub async fn list_deliveries(
+        &self,
+        did: &str,
+        webhook_id: Option<i64>,
+        status: Option<DeliveryStatus>,
+        cursor: Option<i64>,
+        limit: i64,
+    ) -> PdsResult<Vec<WebhookDelivery>> {
+        self.deliveries.list(Some(did), webhook_id, status, cursor, limit).await
+    }
+
+    /// Attempt every pending delivery that is due
+    ///
+    /// Returns (delivered, failed attempts).
+    pub async fn deliver_due(&self, limit: i64) -> PdsResult<(u64, u64)> {
+        self.deliveries
+            .deliver_due(
+                "d.event_type, d.payload, w.url, w.secret",
+                "JOIN repo_webhook w ON w.id = d.webhook_id AND w.enabled = 1",
+                limit,
+                |row| post_signed_row(&self.http_client, row),
+            )
+            .await
+    }
+
+    /// Delete delivered entries past the retention window
+    pub async fn prune_deliveries(&self) -> PdsResult<u64> {
+        self.deliveries.prune().await
+    }
+}
+
+/// Trimmed, non-empty filter entries
+fn clean_filter(entries: Vec<String>) -> Vec<String> {
+    entries
+        .into_iter()
+        .map(|e| e.trim().to_string())
+        .filter(|e| !e.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::test_util::temp_store;
+    use crate::sequencer::events::CommitOp;
+
+    async fn create_test_manager(config: RepoWebhookConfig) -> RepoWebhookManager {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        RepoWebhookManager::new(db, config)
+    }
+
+    #[tokio::test]
+    async fn test_register_validation() {
+        let manager = create_test_manager(RepoWebhookConfig {
+            max_per_account: 1,
+            ..Default::default()
+        })
+        .await;
+        let did = "did:plc:alice";
+
+        for url in ["http://hooks.example/x", "https://127.0.0.1:8080/hook", "https://[::1]/hook", "https://localhost/hook"] {
+            assert!(manager.register(did, url, vec![], vec![], None).await.is_err(), "{}", url);
+        }
+        let unknown_event = manager
+            .register(did, "https://hooks.example/x", vec!["commit.created".to_string()], vec![], None)
+            .await;
+        assert!(unknown_event.is_err());
+
+        let (webhook, secret) = manager
+            .register(
+                did,
+                "https://hooks.example/x",
+                vec!["record.*".to_string()],
+                vec![" app.bsky.feed.* ".to_string(), "".to_string()],
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(secret.len(), 64);
+        assert_eq!(webhook.collections, vec!["app.bsky.feed.*"]);
+        assert!(webhook.wants(events::RECORD_CREATED, Some("app.bsky.feed.post")));
+        assert!(!webhook.wants(events::RECORD_CREATED, Some("app.bsky.graph.follow")));
+        assert!(!webhook.wants(events::BLOB_UPLOADED, None));
+
+        // One webhook per account in this configuration
+        assert!(manager.register(did, "https://hooks.example/y", vec![], vec![], None).await.is_err());
+        assert!(manager.remove("did:plc:mallory", webhook.id).await.is_err());
+        manager.remove(did, webhook.id).await.unwrap();
+        assert!(manager.list(did).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_commit_dispatch_and_retry() {
+        let (_dir, store) = temp_store();
+        let manager = create_test_manager(RepoWebhookConfig {
+            allow_private_targets: true,
+            ..Default::default()
+        })
+        .await;
+        let did = "did:plc:alice";
+
+        let (posts, _) = manager
+            .register(
+                did,
+                // Nothing listens here, so delivery fails
+                "http://127.0.0.1:9/hook",
+                vec![],
+                vec!["app.bsky.feed.post".to_string()],
+                None,
+            )
+            .await
+            .unwrap();
+        let (deletes, _) = manager
+            .register(did, "http://127.0.0.1:9/deletes", vec![events::RECORD_DELETED.to_string()], vec![], None)
+            .await
+            .unwrap();
+
+        let op = |action: OpAction, path: &str| CommitOp {
+            action,
+            path: path.to_string(),
+            cid: None,
+        };
+        let commit = CommitEvent::new(
+            did.to_string(),
+            "bafycommit".to_string(),
+            "3rev".to_string(),
+            None,
+            Vec::new(),
+            vec![
+                op(OpAction::Delete, "app.bsky.feed.post/1"),
+                op(OpAction::Delete, "app.bsky.feed.like/2"),
+                op(OpAction::Create, "app.bsky.graph.follow/3"),
+            ],
+        );
+        // The post delete goes to both webhooks, the like delete to one
+        assert_eq!(manager.on_commit(&store, &commit).await.unwrap(), 3);
+
+        // Other repos' commits don't reach these webhooks
+        let mut other = commit.clone();
+        other.repo = "did:plc:bob".to_string();
+        assert_eq!(manager.on_commit(&store, &other).await.unwrap(), 0);
+
+        manager
+            .dispatch(did, events::BLOB_UPLOADED, None, &serde_json::json!({ "cid": "bafyblob" }))
+            .await;
+        let deliveries = manager.list_deliveries(did, Some(posts.id), None, None, 10).await.unwrap();
+        assert_eq!(deliveries.len(), 2);
+        assert_eq!(deliveries[0].event_type, events::BLOB_UPLOADED);
+        assert_eq!(manager.list_deliveries(did, Some(deletes.id), None, None, 10).await.unwrap().len(), 2);
+        assert!(manager.list_deliveries("did:plc:bob", None, None, None, 10).await.unwrap().is_empty());
+
+        assert_eq!(manager.deliver_due(10).await.unwrap(), (0, 4));
+        let delivery = &manager.list_deliveries(did, None, None, None, 10).await.unwrap()[0];
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        assert_eq!(delivery.attempts, 1);
+        assert!(delivery.next_attempt_at.unwrap() > Utc::now());
+
+        // Not due again until the backoff elapses
+        assert_eq!(manager.deliver_due(10).await.unwrap(), (0, 0));
+
+        manager.remove_all(did).await.unwrap();
+        assert!(manager.list(did).await.unwrap().is_empty());
+        assert!(manager.list_deliveries(did, None, None, None, 10).await.unwrap().is_empty());
+    }
+}
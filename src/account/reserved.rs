@@ -0,0 +1,293 @@
+/// Reserved and blocked handles
+///
+/// Entries are persisted in the `reserved_handle` table (seeded with common
+/// impersonation targets and profanity patterns) and managed by admins.
+/// Patterns are lowercase labels where `*` matches any run of characters.
+///
+/// - `reserved` entries apply to the name part of handles under a service
+///   handle domain (`admin.pds.example.com`), leaving custom domains alone.
+/// - `blocked` entries apply to every label of any handle.
+///
+/// Handle validation runs synchronously, so entries are cached in memory and
+/// the cache is refreshed whenever the list changes.
+use crate::error::{PdsError, PdsResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::{Arc, RwLock};
+
+/// How a reserved handle pattern is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReservedHandleKind {
+    /// Unavailable as a name under the service handle domains
+    Reserved,
+    /// Not allowed in any label of any handle
+    Blocked,
+}
+
+impl ReservedHandleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Reserved => "reserved",
+            Self::Blocked => "blocked",
+        }
+    }
+
+    pub fn from_str(s: &str) -> PdsResult<Self> {
+        match s {
+            "reserved" => Ok(Self::Reserved),
+            "blocked" => Ok(Self::Blocked),
+            _ => Err(PdsError::Validation(format!("Invalid reserved handle kind: {}", s))),
+        }
+    }
+}
+
+/// Reserved handle entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReservedHandle {
+    pub pattern: String,
+    pub kind: ReservedHandleKind,
+    pub reason: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ReservedHandle {
+    /// Error returned to clients when a handle matches this entry
+    pub fn rejection(&self) -> PdsError {
+        match self.kind {
+            ReservedHandleKind::Reserved => PdsError::Validation("Handle is reserved".to_string()),
+            ReservedHandleKind::Blocked => PdsError::Validation("Handle is not allowed".to_string()),
+        }
+    }
+}
+
+/// Reserved handle manager
+#[derive(Clone)]
+pub struct ReservedHandleManager {
+    db: SqlitePool,
+    entries: Arc<RwLock<Vec<ReservedHandle>>>,
+}
+
+impl ReservedHandleManager {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            entries: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Reload the in-memory list from the database
+    pub async fn reload(&self) -> PdsResult<usize> {
+        let entries = self.list().await?;
+        let count = entries.len();
+        *self.entries.write().unwrap() = entries;
+        Ok(count)
+    }
+
+    /// Number of entries in the in-memory list
+    pub fn cached_count(&self) -> usize {
+        self.entries.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// List all entries
+    pub async fn list(&self) -> PdsResult<Vec<ReservedHandle>> {
+        let rows = sqlx::query(
+            "SELECT pattern, kind, reason, created_by, created_at FROM reserved_handle ORDER BY kind, pattern",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let created_at: String = row.get("created_at");
+                Ok(ReservedHandle {
+                    pattern: row.get("pattern"),
+                    kind: ReservedHandleKind::from_str(&row.get::<String, _>("kind"))?,
+                    reason: row.get("reason"),
+                    created_by: row.get("created_by"),
+                    created_at: DateTime::parse_from_rfc3339(&created_at)
+                        .map(|dt| dt.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })
+            .collect()
+    }
+
+    /// Add or replace an entry
+    pub async fn add(
+        &self,
+        pattern: &str,
+        kind: ReservedHandleKind,
+        reason: Option<String>,
+        created_by: &str,
+    ) -> PdsResult<ReservedHandle> {
+        let pattern = pattern.trim().to_lowercase();
+        validate_pattern(&pattern)?;
+
+        let now = Utc::now();
+        sqlx::query(
+            r#"
+            INSERT INTO reserved_handle (pattern, kind, reason, created_by, created_at)
+            VALUES (?, ?, ?, ?, ?)
+            ON CONFLICT(pattern) DO UPDATE SET
+                kind = excluded.kind,
+                reason = excluded.reason,
+                created_by = excluded.created_by,
+                created_at = excluded.created_at
+            "#,
+        )
+        .bind(&pattern)
+        .bind(kind.as_str())
+        .bind(&reason)
+        .bind(created_by)
+        .bind(now.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        self.reload().await?;
+
+        Ok(ReservedHandle {
+            pattern,
+            kind,
+            reason,
+            created_by: created_by.to_string(),
+            created_at: now,
+        })
+    }
+
+    /// Remove an entry
+    pub async fn remove(&self, pattern: &str) -> PdsResult<()> {
+        let result = sqlx::query("DELETE FROM reserved_handle WHERE pattern = ?")
+            .bind(pattern.trim().to_lowercase())
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PdsError::NotFound(format!("No reserved handle entry for {}", pattern)));
+        }
+
+        self.reload().await?;
+        Ok(())
+    }
+
+    /// Find the entry (if any) that makes a handle unavailable
+    pub fn check(&self, handle: &str, service_domains: &[String]) -> Option<ReservedHandle> {
+        let entries = self.entries.read().unwrap();
+        find_match(&entries, handle, service_domains).cloned()
+    }
+}
+
+/// Name part of a handle under one of the service handle domains
+fn service_name<'a>(handle: &'a str, service_domains: &[String]) -> Option<&'a str> {
+    service_domains.iter().find_map(|domain| {
+        let domain = domain.trim_start_matches('.');
+        handle
+            .strip_suffix(domain)
+            .and_then(|rest| rest.strip_suffix('.'))
+            .filter(|name| !name.is_empty())
+    })
+}
+
+fn find_match<'a>(
+    entries: &'a [ReservedHandle],
+    handle: &str,
+    service_domains: &[String],
+) -> Option<&'a ReservedHandle> {
+    let handle = handle.to_lowercase();
+    let name = service_name(&handle, service_domains);
+
+    entries.iter().find(|entry| match entry.kind {
+        ReservedHandleKind::Reserved => name.map_or(false, |n| glob_match(&entry.pattern, n)),
+        ReservedHandleKind::Blocked => handle.split('.').any(|label| glob_match(&entry.pattern, label)),
+    })
+}
+
+fn validate_pattern(pattern: &str) -> PdsResult<()> {
+    if pattern.is_empty() || pattern.chars().all(|c| c == '*') {
+        return Err(PdsError::Validation("Pattern must contain at least one character".to_string()));
+    }
+    if !pattern
+        .chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.' || c == '*')
+    {
+        return Err(PdsError::Validation(
+            "Pattern may only contain a-z, 0-9, '-', '.' and '*'".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Match `text` against a pattern where `*` matches any run of characters
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || text.len() < first.len() + last.len() || !text.ends_with(last) {
+        return false;
+    }
+
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(idx) => rest = &rest[idx + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(pattern: &str, kind: ReservedHandleKind) -> ReservedHandle {
+        ReservedHandle {
+            pattern: pattern.to_string(),
+            kind,
+            reason: None,
+            created_by: "system".to_string(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("admin", "admin"));
+        assert!(!glob_match("admin", "admins"));
+        assert!(glob_match("admin*", "administrator"));
+        assert!(glob_match("*slur*", "myslurname"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("a*b*c", "acb"));
+        assert!(!glob_match("ab*ba", "aba"));
+    }
+
+    #[test]
+    fn test_reserved_and_blocked_matching() {
+        let domains = vec![".pds.example.com".to_string()];
+        let entries = vec![
+            entry("support", ReservedHandleKind::Reserved),
+            entry("*slur*", ReservedHandleKind::Blocked),
+        ];
+
+        // Reserved names only apply under the service domains
+        assert!(find_match(&entries, "support.pds.example.com", &domains).is_some());
+        assert!(find_match(&entries, "Support.pds.example.com", &domains).is_some());
+        assert!(find_match(&entries, "support.alice.com", &domains).is_none());
+        assert!(find_match(&entries, "supporter.pds.example.com", &domains).is_none());
+
+        // Blocked patterns apply to any label
+        assert!(find_match(&entries, "myslur.pds.example.com", &domains).is_some());
+        assert!(find_match(&entries, "slur.alice.com", &domains).is_some());
+        assert!(find_match(&entries, "alice.pds.example.com", &domains).is_none());
+
+        assert!(validate_pattern("mod-*").is_ok());
+        assert!(validate_pattern("*").is_err());
+        assert!(validate_pattern("Admin").is_err());
+    }
+}
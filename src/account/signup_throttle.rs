@@ -0,0 +1,273 @@
+/// Signup burst control
+///
+/// Open registration is counted over a sliding hour, server-wide and per
+/// client IP. A client over the per-IP cap is rate limited. When the global
+/// cap is reached the server switches itself to invite-only for a while (a
+/// "lockdown"), so a small instance is not flooded with accounts before an
+/// admin notices; admins are told through the `signup.lockdown` webhook
+/// event and can end the lockdown early.
+///
+/// Counts are kept in memory and start from zero on restart.
+use crate::{
+    admin::webhooks::{events, WebhookManager},
+    clock::{system_clock, Clock},
+    config::SignupThrottleConfig,
+    error::{PdsError, PdsResult},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+/// Window the caps are counted over
+const WINDOW_MINUTES: i64 = 60;
+
+/// Current throttle state (for the admin API)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignupThrottleStatus {
+    pub global_per_hour: u32,
+    pub per_ip_per_hour: u32,
+    /// Open signups in the past hour
+    pub signups_last_hour: usize,
+    /// Invites are required until then
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lockdown_until: Option<DateTime<Utc>>,
+}
+
+/// Payload of the `signup.lockdown` webhook event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LockdownNotice {
+    lockdown_until: DateTime<Utc>,
+    signups_last_hour: usize,
+    global_per_hour: u32,
+}
+
+#[derive(Debug, Default)]
+struct ThrottleState {
+    global: VecDeque<DateTime<Utc>>,
+    per_ip: HashMap<String, VecDeque<DateTime<Utc>>>,
+    lockdown_until: Option<DateTime<Utc>>,
+}
+
+impl ThrottleState {
+    /// Forget signups older than the window and an expired lockdown
+    fn prune(&mut self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::minutes(WINDOW_MINUTES);
+        let expire = |times: &mut VecDeque<DateTime<Utc>>| {
+            while times.front().is_some_and(|t| *t <= cutoff) {
+                times.pop_front();
+            }
+        };
+
+        expire(&mut self.global);
+        self.per_ip.retain(|_, times| {
+            expire(times);
+            !times.is_empty()
+        });
+        if self.lockdown_until.is_some_and(|until| until <= now) {
+            self.lockdown_until = None;
+        }
+    }
+}
+
+/// Sliding-window signup caps with automatic invite-only lockdown
+pub struct SignupThrottle {
+    global_per_hour: u32,
+    per_ip_per_hour: u32,
+    lockdown: Duration,
+    clock: Arc<dyn Clock>,
+    webhooks: Option<Arc<WebhookManager>>,
+    state: Mutex<ThrottleState>,
+}
+
+impl SignupThrottle {
+    pub fn new(config: &SignupThrottleConfig) -> Self {
+        Self {
+            global_per_hour: config.global_per_hour,
+            per_ip_per_hour: config.per_ip_per_hour,
+            lockdown: Duration::minutes(config.lockdown_minutes.min(u32::MAX as u64) as i64),
+            clock: system_clock(),
+            webhooks: None,
+            state: Mutex::new(ThrottleState::default()),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Notify admins through webhooks when a lockdown starts
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookManager>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
+    }
+
+    /// End of the current lockdown, if one is in effect
+    pub fn lockdown_until(&self) -> Option<DateTime<Utc>> {
+        let mut state = self.state.lock().unwrap();
+        state.prune(self.clock.now());
+        state.lockdown_until
+    }
+
+    /// Whether signups currently need an invite code because of a lockdown
+    pub fn in_lockdown(&self) -> bool {
+        self.lockdown_until().is_some()
+    }
+
+    /// Check an open (invite-less) signup from `client_ip` against the caps
+    ///
+    /// `client_ip` must be resolved through the trusted proxies, or clients
+    /// could dodge their cap with a fresh `X-Forwarded-For` per signup.
+    ///
+    /// Fails with a rate limit error when the IP is over its cap. When the
+    /// global cap has been reached, a lockdown starts and the signup fails
+    /// as needing an invite code.
+    pub async fn check(&self, client_ip: Option<&str>) -> PdsResult<()> {
+        let now = self.clock.now();
+        let notice = {
+            let mut state = self.state.lock().unwrap();
+            state.prune(now);
+
+            if let Some(until) = state.lockdown_until {
+                return Err(lockdown_error(until));
+            }
+
+            if let Some(ip) = client_ip.filter(|_| self.per_ip_per_hour > 0) {
+                let times = state.per_ip.get(ip);
+                if times.map_or(0, VecDeque::len) >= self.per_ip_per_hour as usize {
+                    let oldest = times.and_then(|t| t.front()).copied().unwrap_or(now);
+                    let retry_after = (oldest + Duration::minutes(WINDOW_MINUTES) - now)
+                        .to_std()
+                        .unwrap_or_default();
+                    return Err(PdsError::RateLimitExceeded { retry_after });
+                }
+            }
+
+            if self.global_per_hour == 0 || state.global.len() < self.global_per_hour as usize {
+                return Ok(());
+            }
+
+            let until = now + self.lockdown;
+            state.lockdown_until = Some(until);
+            LockdownNotice {
+                lockdown_until: until,
+                signups_last_hour: state.global.len(),
+                global_per_hour: self.global_per_hour,
+            }
+        };
+
+        tracing::warn!(
+            "Signup cap of {} per hour reached; invite codes are required until {}",
+            self.global_per_hour,
+            notice.lockdown_until
+        );
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(events::SIGNUP_LOCKDOWN, &notice).await;
+        }
+
+        Err(lockdown_error(notice.lockdown_until))
+    }
+
+    /// Count a successful open signup
+    pub fn record(&self, client_ip: Option<&str>) {
+        let now = self.clock.now();
+        let mut state = self.state.lock().unwrap();
+        state.prune(now);
+        state.global.push_back(now);
+        if let Some(ip) = client_ip {
+            state.per_ip.entry(ip.to_string()).or_default().push_back(now);
+        }
+    }
+
+    /// End a lockdown early; returns whether one was in effect
+    pub fn end_lockdown(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        state.prune(self.clock.now());
+        // Start counting afresh so the next signup doesn't re-trigger it
+        state.global.clear();
+        state.lockdown_until.take().is_some()
+    }
+
+    pub fn status(&self) -> SignupThrottleStatus {
+        let mut state = self.state.lock().unwrap();
+        state.prune(self.clock.now());
+        SignupThrottleStatus {
+            global_per_hour: self.global_per_hour,
+            per_ip_per_hour: self.per_ip_per_hour,
+            signups_last_hour: state.global.len(),
+            lockdown_until: state.lockdown_until,
+        }
+    }
+}
+
+fn lockdown_error(until: DateTime<Utc>) -> PdsError {
+    PdsError::Validation(format!(
+        "Invite code required: signups are limited until {}",
+        until.to_rfc3339()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn throttle(global: u32, per_ip: u32) -> (SignupThrottle, Arc<MockClock>) {
+        let clock = Arc::new(MockClock::fixed());
+        let config = SignupThrottleConfig {
+            global_per_hour: global,
+            per_ip_per_hour: per_ip,
+            lockdown_minutes: 30,
+        };
+        (SignupThrottle::new(&config).with_clock(clock.clone()), clock)
+    }
+
+    #[tokio::test]
+    async fn test_per_ip_cap() {
+        let (throttle, clock) = throttle(0, 2);
+        for _ in 0..2 {
+            throttle.check(Some("10.0.0.1")).await.unwrap();
+            throttle.record(Some("10.0.0.1"));
+        }
+
+        let err = throttle.check(Some("10.0.0.1")).await.unwrap_err();
+        assert!(matches!(err, PdsError::RateLimitExceeded { .. }));
+        throttle.check(Some("10.0.0.2")).await.unwrap();
+        throttle.check(None).await.unwrap();
+
+        clock.advance(Duration::minutes(61));
+        throttle.check(Some("10.0.0.1")).await.unwrap();
+        assert!(!throttle.in_lockdown());
+    }
+
+    #[tokio::test]
+    async fn test_global_cap_starts_lockdown() {
+        let (throttle, clock) = throttle(3, 0);
+        for i in 0..3 {
+            throttle.check(Some(&format!("10.0.0.{}", i))).await.unwrap();
+            throttle.record(Some(&format!("10.0.0.{}", i)));
+        }
+
+        let err = throttle.check(Some("10.0.0.9")).await.unwrap_err();
+        assert!(err.to_string().contains("Invite code required"));
+        assert_eq!(throttle.lockdown_until(), Some(clock.now() + Duration::minutes(30)));
+        assert_eq!(throttle.status().signups_last_hour, 3);
+
+        // The lockdown lifts on its own once it runs out
+        clock.advance(Duration::minutes(31));
+        assert!(!throttle.in_lockdown());
+
+        // Or when an admin ends it
+        clock.advance(Duration::minutes(30));
+        for _ in 0..3 {
+            throttle.record(None);
+        }
+        assert!(throttle.check(None).await.is_err());
+        assert!(throttle.end_lockdown());
+        assert!(!throttle.end_lockdown());
+        throttle.check(None).await.unwrap();
+    }
+}
@@ -0,0 +1,905 @@
+/// ActivityPub bridge state and delivery
+///
+/// Accounts that turn the bridge on get an actor (with its own RSA key),
+/// the fediverse accounts following it, and the notes translated from the
+/// posts they make afterwards. Follows arrive at the actor's inbox and are
+/// accepted automatically; new, edited and deleted posts are queued as
+/// `Create`, `Update` and `Delete` activities for every follower inbox
+/// (shared inboxes once) and sent by the `activitypub_delivery` job through
+/// the same [`crate::delivery`] queue as webhooks.
+///
+/// Remote actors and inboxes must be https and not on private addresses
+/// unless `PDS_ACTIVITYPUB_ALLOW_PRIVATE_TARGETS` is set. Actor documents
+/// are fetched unsigned, so servers in authorized-fetch mode can't follow.
+///
+/// Actor private keys are never stored in the clear. With a master key
+/// configured (`PDS_ENCRYPTION_MASTER_KEY_ID`) they are wrapped by the key
+/// backend like actor data keys; otherwise they are sealed with a key
+/// derived from `PDS_JWT_SECRET`, like JWT signing keys. Plaintext keys
+/// left by earlier versions are sealed at startup.
+use crate::{
+    activitypub::{
+        signature::{generate_key_pair, sign_post, verify_request, SignatureHeader},
+        translate::{actor_url, note_url, post_to_note, render_html, CONTEXT, POST_COLLECTION, PUBLIC},
+    },
+    actor_store::{record_codec::decode_record, ActorStore},
+    config::ActivityPubConfig,
+    crypto::keys::{cipher_for, open, seal, KeyManager},
+    delivery::DeliveryQueue,
+    error::{PdsError, PdsResult},
+    net::check_public_url,
+    sequencer::events::{CommitEvent, OpAction},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    Notify,
+};
+
+/// Media type of ActivityPub documents
+pub const ACTIVITY_JSON: &str = "application/activity+json";
+
+/// Size of generated actor keys
+const ACTOR_KEY_BITS: usize = 2048;
+
+/// Bridged actors, followers, notes and the outbound queue
+pub struct ActivityPubBridge {
+    db: SqlitePool,
+    deliveries: DeliveryQueue,
+    config: ActivityPubConfig,
+    /// Public URL of this server, without a trailing slash
+    base_url: String,
+    /// Domain of `acct:` addresses
+    domain: String,
+    http_client: reqwest::Client,
+    /// Wakes the delivery job when activities are queued
+    notify: Arc<Notify>,
+    /// Seals actor keys when no master key is configured
+    key_cipher: XChaCha20Poly1305,
+    /// Key backend that wrapped actor keys are opened with
+    keys: Option<Arc<KeyManager>>,
+    /// Master key new actor keys are wrapped with
+    master_key_id: Option<String>,
+}
+
+impl ActivityPubBridge {
+    /// Bridge sealing actor keys with a key derived from `secret`
+    /// (`PDS_JWT_SECRET`)
+    pub fn new(db: SqlitePool, config: ActivityPubConfig, base_url: String, domain: String, secret: &str) -> Self {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(b"aurora-locus activitypub actor keys");
+
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .user_agent(concat!("aurora-locus/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            deliveries: DeliveryQueue::new(db.clone(), "activitypub_delivery"),
+            db,
+            config,
+            base_url,
+            domain,
+            http_client,
+            notify: Arc::new(Notify::new()),
+            key_cipher: cipher_for(&mac.finalize().into_bytes().into()),
+            keys: None,
+            master_key_id: None,
+        }
+    }
+
+    /// Open wrapped actor keys with the key backend, and wrap new ones
+    /// under `master_key_id` instead of sealing them, if given
+    pub fn with_keys(mut self, keys: Arc<KeyManager>, master_key_id: Option<&str>) -> Self {
+        self.keys = Some(keys);
+        self.master_key_id = master_key_id.map(str::to_string);
+        self
+    }
+
+    /// Signal raised whenever activities are queued
+    pub fn notifier(&self) -> Arc<Notify> {
+        self.notify.clone()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.config.enabled
+    }
+
+    pub fn actor_url(&self, did: &str) -> String {
+        actor_url(&self.base_url, did)
+    }
+
+    /// Username of a handle's actor (`alice` for `alice.<domain>`)
+    pub fn username(&self, handle: &str) -> String {
+        handle
+            .strip_suffix(&format!(".{}", self.domain))
+            .unwrap_or(handle)
+            .to_string()
+    }
+
+    /// `acct:` address of a handle's actor
+    pub fn acct(&self, handle: &str) -> String {
+        format!("{}@{}", self.username(handle), self.domain)
+    }
+
+    /// Turn the bridge on for `did`; returns false if it already was
+    pub async fn enable(&self, did: &str) -> PdsResult<bool> {
+        if !self.config.enabled {
+            return Err(PdsError::Validation(
+                "The ActivityPub bridge is disabled on this server".to_string(),
+            ));
+        }
+        if self.is_bridged(did).await? {
+            return Ok(false);
+        }
+
+        let (private_key, public_key) = tokio::task::spawn_blocking(|| generate_key_pair(ACTOR_KEY_BITS))
+            .await
+            .map_err(|e| PdsError::Internal(format!("Actor key generation failed: {}", e)))??;
+
+        let result = sqlx::query(
+            "INSERT OR IGNORE INTO activitypub_actor (did, private_key, public_key, created_at)
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .bind(did)
+        .bind(self.seal_key(did, &private_key).await?)
+        .bind(public_key)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Wrapping context binding a sealed key to its actor
+    fn key_context(did: &str) -> String {
+        format!("aurora-locus:activitypub-key:{}", did)
+    }
+
+    /// Seal an actor's private key PEM for storage
+    ///
+    /// Stored as `wrapped:<master key id>:<base64>` or `sealed:<base64>`.
+    async fn seal_key(&self, did: &str, pem: &str) -> PdsResult<String> {
+        match (&self.keys, &self.master_key_id) {
+            (Some(keys), Some(master_key_id)) => {
+                let wrapped = keys
+                    .wrap_data_key(master_key_id, pem.as_bytes(), &Self::key_context(did))
+                    .await?;
+                Ok(format!("wrapped:{}:{}", master_key_id, STANDARD.encode(wrapped)))
+            }
+            _ => {
+                let sealed = seal(&self.key_cipher, pem.as_bytes(), Self::key_context(did).as_bytes())?;
+                Ok(format!("sealed:{}", STANDARD.encode(sealed)))
+            }
+        }
+    }
+
+    /// Private key PEM of a stored actor key
+    ///
+    /// Keys stored before sealing are PEM already.
+    async fn open_key(&self, did: &str, stored: &str) -> PdsResult<String> {
+        let corrupt = || PdsError::Internal(format!("Corrupt ActivityPub key for {}", did));
+        let pem = if let Some(rest) = stored.strip_prefix("wrapped:") {
+            let (master_key_id, wrapped) = rest.split_once(':').ok_or_else(corrupt)?;
+            let wrapped = STANDARD.decode(wrapped).map_err(|_| corrupt())?;
+            let keys = self.keys.as_ref().ok_or_else(|| {
+                PdsError::Internal(format!("ActivityPub key for {} needs the key backend to open", did))
+            })?;
+            keys.unwrap_data_key(master_key_id, &wrapped, &Self::key_context(did))
+                .await?
+        } else if let Some(sealed) = stored.strip_prefix("sealed:") {
+            let sealed = STANDARD.decode(sealed).map_err(|_| corrupt())?;
+            open(&self.key_cipher, &sealed, Self::key_context(did).as_bytes()).map_err(|_| {
+                PdsError::Internal(format!("ActivityPub key for {} can't be opened with PDS_JWT_SECRET", did))
+            })?
+        } else {
+            return Ok(stored.to_string());
+        };
+        String::from_utf8(pem).map_err(|_| corrupt())
+    }
+
+    /// Seal actor keys stored in the clear by earlier versions, returning
+    /// how many were sealed
+    pub async fn seal_plaintext_keys(&self) -> PdsResult<u64> {
+        let rows = sqlx::query("SELECT did, private_key FROM activitypub_actor WHERE private_key LIKE '-----BEGIN%'")
+            .fetch_all(&self.db)
+            .await?;
+
+        let mut sealed = 0;
+        for row in rows {
+            let did: String = row.get("did");
+            let pem: String = row.get("private_key");
+            let result = sqlx::query("UPDATE activitypub_actor SET private_key = ?2 WHERE did = ?1 AND private_key = ?3")
+                .bind(&did)
+                .bind(self.seal_key(&did, &pem).await?)
+                .bind(&pem)
+                .execute(&self.db)
+                .await?;
+            sealed += result.rows_affected();
+        }
+        Ok(sealed)
+    }
+
+    /// Turn the bridge off, forgetting the actor, its followers and notes
+    pub async fn disable(&self, did: &str) -> PdsResult<()> {
+        let mut tx = self.db.begin().await?;
+        for table in [
+            "activitypub_delivery",
+            "activitypub_note",
+            "activitypub_follower",
+            "activitypub_actor",
+        ] {
+            sqlx::query(&format!("DELETE FROM {} WHERE did = ?1", table))
+                .bind(did)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    pub async fn is_bridged(&self, did: &str) -> PdsResult<bool> {
+        Ok(self.public_key(did).await?.is_some())
+    }
+
+    /// Public key PEM of `did`'s actor, if it is bridged
+    pub async fn public_key(&self, did: &str) -> PdsResult<Option<String>> {
+        Ok(sqlx::query_scalar("SELECT public_key FROM activitypub_actor WHERE did = ?1")
+            .bind(did)
+            .fetch_optional(&self.db)
+            .await?)
+    }
+
+    /// Actor document of `did`, with its profile record if it has one
+    pub async fn actor_document(&self, did: &str, handle: &str, profile: Option<&Value>) -> PdsResult<Option<Value>> {
+        let Some(public_key) = self.public_key(did).await? else {
+            return Ok(None);
+        };
+        let actor = self.actor_url(did);
+
+        let mut doc = json!({
+            "@context": [CONTEXT, "https://w3id.org/security/v1"],
+            "id": actor,
+            "type": "Person",
+            "preferredUsername": self.username(handle),
+            "name": handle,
+            "inbox": format!("{}/inbox", actor),
+            "outbox": format!("{}/outbox", actor),
+            "followers": format!("{}/followers", actor),
+            "url": format!("https://bsky.app/profile/{}", did),
+            "manuallyApprovesFollowers": false,
+            "publicKey": {
+                "id": format!("{}#main-key", actor),
+                "owner": actor,
+                "publicKeyPem": public_key,
+            },
+        });
+
+        if let Some(profile) = profile {
+            if let Some(name) = profile["displayName"].as_str().filter(|n| !n.is_empty()) {
+                doc["name"] = json!(name);
+            }
+            if let Some(description) = profile["description"].as_str() {
+                doc["summary"] = json!(render_html(description, &[]));
+            }
+            for (field, property) in [("avatar", "icon"), ("banner", "image")] {
+                if let Some(cid) = profile[field]["ref"]["$link"].as_str() {
+                    doc[property] = json!({
+                        "type": "Image",
+                        "mediaType": profile[field]["mimeType"].as_str().unwrap_or("image/jpeg"),
+                        "url": format!("{}/xrpc/com.atproto.sync.getBlob?did={}&cid={}", self.base_url, did, cid),
+                    });
+                }
+            }
+        }
+
+        Ok(Some(doc))
+    }
+
+    /// Translated note of the post `rkey`
+    pub async fn note(&self, did: &str, rkey: &str) -> PdsResult<Option<Value>> {
+        let note: Option<String> = sqlx::query_scalar("SELECT note FROM activitypub_note WHERE did = ?1 AND rkey = ?2")
+            .bind(did)
+            .bind(rkey)
+            .fetch_optional(&self.db)
+            .await?;
+        note.map(|n| serde_json::from_str(&n).map_err(|e| PdsError::Internal(format!("Invalid stored note: {}", e))))
+            .transpose()
+    }
+
+    /// Page of `did`'s notes, newest first, as `Create` activities
+    ///
+    /// The cursor is the `published` time of the last item of the previous page.
+    pub async fn outbox_page(&self, did: &str, cursor: Option<&str>, limit: i64) -> PdsResult<(Vec<Value>, Option<String>)> {
+        let rows = sqlx::query(
+            "SELECT note, published FROM activitypub_note
+             WHERE did = ?1 AND (?2 IS NULL OR published < ?2)
+             ORDER BY published DESC LIMIT ?3",
+        )
+        .bind(did)
+        .bind(cursor)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        let cursor = (rows.len() as i64 == limit)
+            .then(|| rows.last().map(|row| row.get::<String, _>("published")))
+            .flatten();
+        let items = rows
+            .iter()
+            .filter_map(|row| serde_json::from_str::<Value>(&row.get::<String, _>("note")).ok())
+            .map(|note| self.activity("Create", did, note))
+            .collect();
+
+        Ok((items, cursor))
+    }
+
+    pub async fn note_count(&self, did: &str) -> PdsResult<i64> {
+        Ok(sqlx::query_scalar("SELECT COUNT(*) FROM activitypub_note WHERE did = ?1")
+            .bind(did)
+            .fetch_one(&self.db)
+            .await?)
+    }
+
+    pub async fn follower_count(&self, did: &str) -> PdsResult<i64> {
+        Ok(sqlx::query_scalar("SELECT COUNT(*) FROM activitypub_follower WHERE did = ?1")
+            .bind(did)
+            .fetch_one(&self.db)
+            .await?)
+    }
+
+    /// Record a follower of `did`'s actor
+    pub async fn add_follower(&self, did: &str, actor_id: &str, inbox: &str, shared_inbox: Option<&str>) -> PdsResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO activitypub_follower (did, actor_id, inbox, shared_inbox, created_at)
+            VALUES (?1, ?2, ?3, ?4, ?5)
+            ON CONFLICT(did, actor_id) DO UPDATE SET inbox = excluded.inbox, shared_inbox = excluded.shared_inbox
+            "#,
+        )
+        .bind(did)
+        .bind(actor_id)
+        .bind(inbox)
+        .bind(shared_inbox)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn remove_follower(&self, did: &str, actor_id: &str) -> PdsResult<()> {
+        sqlx::query("DELETE FROM activitypub_follower WHERE did = ?1 AND actor_id = ?2")
+            .bind(did)
+            .bind(actor_id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Check the HTTP signature of an inbox request, returning the signing actor's document
+    pub async fn verify_inbox_request(
+        &self,
+        method: &str,
+        path_and_query: &str,
+        header: impl Fn(&str) -> Option<String>,
+        body: &[u8],
+    ) -> PdsResult<Value> {
+        let signature = header("signature")
+            .ok_or_else(|| PdsError::Authentication("Inbox requests must be signed".to_string()))?;
+        let signature = SignatureHeader::parse(&signature)?;
+
+        let actor = self.fetch_actor(signature.actor_id()).await?;
+        let keys = match &actor["publicKey"] {
+            Value::Array(keys) => keys.clone(),
+            key => vec![key.clone()],
+        };
+        let public_key = keys
+            .iter()
+            .find(|key| key["id"].as_str() == Some(signature.key_id.as_str()))
+            .or_else(|| keys.first())
+            .and_then(|key| key["publicKeyPem"].as_str())
+            .ok_or_else(|| PdsError::Authentication("Signing actor has no public key".to_string()))?;
+
+        verify_request(&signature, public_key, method, path_and_query, header, body)?;
+        Ok(actor)
+    }
+
+    /// Handle an activity delivered to `did`'s inbox by the (verified) `signer`
+    pub async fn handle_inbox(&self, did: &str, activity: &Value, signer: &Value) -> PdsResult<()> {
+        let signer_id = signer["id"].as_str().unwrap_or_default();
+        if activity["actor"].as_str() != Some(signer_id) {
+            return Err(PdsError::Authorization("Activity actor does not match the signature".to_string()));
+        }
+        let actor = self.actor_url(did);
+
+        match activity["type"].as_str() {
+            Some("Follow") if object_id(&activity["object"]) == Some(actor.as_str()) => {
+                let inbox = signer["inbox"]
+                    .as_str()
+                    .ok_or_else(|| PdsError::Validation("Follower has no inbox".to_string()))?;
+                self.check_target(inbox)?;
+                let shared_inbox = signer["endpoints"]["sharedInbox"]
+                    .as_str()
+                    .filter(|url| self.check_target(url).is_ok());
+                self.add_follower(did, signer_id, inbox, shared_inbox).await?;
+                tracing::info!(did = %did, follower = %signer_id, "activitypub_follow");
+
+                let accept = json!({
+                    "@context": CONTEXT,
+                    "id": format!("{}#accepts/{}", actor, uuid::Uuid::new_v4()),
+                    "type": "Accept",
+                    "actor": actor,
+                    "object": activity,
+                });
+                self.enqueue(did, &[inbox.to_string()], &accept).await?;
+            }
+            Some("Undo") if activity["object"]["type"].as_str() == Some("Follow") => {
+                self.remove_follower(did, signer_id).await?;
+                tracing::info!(did = %did, follower = %signer_id, "activitypub_unfollow");
+            }
+            // The follower's account was deleted
+            Some("Delete") if object_id(&activity["object"]) == Some(signer_id) => {
+                self.remove_follower(did, signer_id).await?;
+            }
+            // Replies, likes and boosts aren't bridged back
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Fetch a remote actor document
+    async fn fetch_actor(&self, url: &str) -> PdsResult<Value> {
+        self.check_target(url)?;
+        let response = self
+            .http_client
+            .get(url)
+            .header(reqwest::header::ACCEPT, ACTIVITY_JSON)
+            .send()
+            .await
+            .map_err(|e| PdsError::Federation(format!("Failed to fetch actor {}: {}", url, e)))?;
+        if !response.status().is_success() {
+            return Err(PdsError::Authentication(format!(
+                "Failed to fetch actor {}: HTTP {}",
+                url,
+                response.status()
+            )));
+        }
+
+        let actor: Value = response
+            .json()
+            .await
+            .map_err(|e| PdsError::Authentication(format!("Invalid actor document at {}: {}", url, e)))?;
+        if actor["id"].as_str() != Some(url) {
+            return Err(PdsError::Authentication(format!("Actor document id does not match {}", url)));
+        }
+        Ok(actor)
+    }
+
+    /// Refuse remote URLs that aren't https or that point into the server's network
+    fn check_target(&self, url: &str) -> PdsResult<()> {
+        check_public_url(url, "Remote URL", self.config.allow_private_targets)
+    }
+
+    /// Translate the post ops of a sequenced commit and queue them for followers
+    ///
+    /// Returns the deliveries queued.
+    pub async fn on_commit(&self, store: &ActorStore, evt: &CommitEvent) -> PdsResult<usize> {
+        let posts: Vec<_> = evt
+            .ops
+            .iter()
+            .filter_map(|op| Some((op, op.path.strip_prefix(POST_COLLECTION)?.strip_prefix('/')?)))
+            .collect();
+        if posts.is_empty() || !self.is_bridged(&evt.repo).await? {
+            return Ok(0);
+        }
+
+        let inboxes = self.inboxes(&evt.repo).await?;
+        let mut queued = 0;
+        for (op, rkey) in posts {
+            let activity = match op.action {
+                OpAction::Create | OpAction::Update => {
+                    let record = match &op.cid {
+                        Some(cid) => match store.get_block(&evt.repo, cid).await? {
+                            Some(bytes) => decode_record(&bytes)?,
+                            None => continue,
+                        },
+                        None => continue,
+                    };
+                    let Some(note) = post_to_note(&self.base_url, &evt.repo, rkey, &record) else {
+                        continue;
+                    };
+
+                    let published = note["published"]
+                        .as_str()
+                        .map(String::from)
+                        .unwrap_or_else(|| Utc::now().to_rfc3339());
+                    sqlx::query(
+                        "INSERT INTO activitypub_note (did, rkey, note, published) VALUES (?1, ?2, ?3, ?4)
+                         ON CONFLICT(did, rkey) DO UPDATE SET note = excluded.note",
+                    )
+                    .bind(&evt.repo)
+                    .bind(rkey)
+                    .bind(note.to_string())
+                    .bind(published)
+                    .execute(&self.db)
+                    .await?;
+
+                    let kind = if op.action == OpAction::Create { "Create" } else { "Update" };
+                    self.activity(kind, &evt.repo, note)
+                }
+                OpAction::Delete => {
+                    let deleted = sqlx::query("DELETE FROM activitypub_note WHERE did = ?1 AND rkey = ?2")
+                        .bind(&evt.repo)
+                        .bind(rkey)
+                        .execute(&self.db)
+                        .await?;
+                    // Never bridged (made before the bridge was on, or a reply to someone else)
+                    if deleted.rows_affected() == 0 {
+                        continue;
+                    }
+
+                    let id = note_url(&self.base_url, &evt.repo, rkey);
+                    self.activity("Delete", &evt.repo, json!({ "id": id, "type": "Tombstone" }))
+                }
+            };
+
+            queued += self.enqueue(&evt.repo, &inboxes, &activity).await?;
+        }
+
+        if queued > 0 {
+            self.notify.notify_one();
+        }
+        Ok(queued)
+    }
+
+    /// Translate commits in the background until the sequencer goes away
+    pub fn spawn(self: Arc<Self>, store: Arc<ActorStore>, mut commits: broadcast::Receiver<CommitEvent>) {
+        tokio::spawn(async move {
+            loop {
+                match commits.recv().await {
+                    Ok(evt) => {
+                        if let Err(e) = self.on_commit(&store, &evt).await {
+                            tracing::warn!(did = %evt.repo, error = %e, "activitypub_translate_failed");
+                        }
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!(skipped, "activitypub bridge lagged behind commits");
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+
+    /// An activity of `did`'s actor wrapping `object`, addressed like a public note
+    fn activity(&self, kind: &str, did: &str, object: Value) -> Value {
+        let actor = self.actor_url(did);
+        let id = object["id"].as_str().unwrap_or(&actor).to_string();
+        let suffix = if kind == "Create" {
+            "/activity".to_string()
+        } else {
+            format!("#{}/{}", kind.to_lowercase(), Utc::now().timestamp_millis())
+        };
+
+        let mut activity = json!({
+            "@context": CONTEXT,
+            "id": format!("{}{}", id, suffix),
+            "type": kind,
+            "actor": actor,
+            "to": [PUBLIC],
+            "cc": [format!("{}/followers", actor)],
+        });
+        if let Some(published) = object.get("published") {
+            activity["published"] = published.clone();
+        }
+        activity["object"] = object;
+        activity
+    }
+
+    /// Distinct inboxes of `did`'s followers (shared inboxes where offered)
+    async fn inboxes(&self, did: &str) -> PdsResult<Vec<String>> {
+        Ok(sqlx::query_scalar(
+            "SELECT DISTINCT COALESCE(shared_inbox, inbox) FROM activitypub_follower WHERE did = ?1",
+        )
+        .bind(did)
+        .fetch_all(&self.db)
+        .await?)
+    }
+
+    async fn enqueue(&self, did: &str, inboxes: &[String], activity: &Value) -> PdsResult<usize> {
+        let now = Utc::now().to_rfc3339();
+        let body = activity.to_string();
+        for inbox in inboxes {
+            sqlx::query(
+                "INSERT INTO activitypub_delivery (did, inbox, activity, status, attempts, created_at, next_attempt_at)
+                 VALUES (?1, ?2, ?3, 'pending', 0, ?4, ?4)",
+            )
+            .bind(did)
+            .bind(inbox)
+            .bind(&body)
+            .bind(&now)
+            .execute(&self.db)
+            .await?;
+        }
+        Ok(inboxes.len())
+    }
+
+    /// Send every queued activity that is due
+    ///
+    /// Returns (delivered, failed attempts).
+    pub async fn deliver_due(&self, limit: i64) -> PdsResult<(u64, u64)> {
+        self.deliveries
+            .deliver_due(
+                "d.did, d.inbox, d.activity, a.private_key",
+                "JOIN activitypub_actor a ON a.did = d.did",
+                limit,
+                |row| async move {
+                    let did: String = row.get("did");
+                    let private_key = match self.open_key(&did, &row.get::<String, _>("private_key")).await {
+                        Ok(pem) => pem,
+                        Err(e) => return (None, Some(e.to_string())),
+                    };
+                    let inbox: String = row.get("inbox");
+                    match self.post(&did, &private_key, &inbox, row.get("activity")).await {
+                        Ok(status) => (Some(status), None),
+                        Err(e) => (None, Some(e.to_string())),
+                    }
+                },
+            )
+            .await
+    }
+
+    /// Delete delivered activities past the retention window
+    pub async fn prune_deliveries(&self) -> PdsResult<u64> {
+        self.deliveries.prune().await
+    }
+
+    /// POST a signed activity to an inbox, returning the response status
+    async fn post(&self, did: &str, private_key: &str, inbox: &str, activity: String) -> PdsResult<i64> {
+        self.check_target(inbox)?;
+        let key_id = format!("{}#main-key", self.actor_url(did));
+        let headers = sign_post(&key_id, private_key, inbox, activity.as_bytes())?;
+
+        let mut request = self
+            .http_client
+            .post(inbox)
+            .header(reqwest::header::CONTENT_TYPE, ACTIVITY_JSON)
+            .body(activity);
+        for (name, value) in headers {
+            request = request.header(name, value);
+        }
+
+        let response = request
+            .send()
+            .await
+            .map_err(|e| PdsError::Federation(format!("Request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(PdsError::Federation(format!("HTTP {}", response.status())));
+        }
+        Ok(response.status().as_u16() as i64)
+    }
+}
+
+/// Id of an activity object given inline or by reference
+fn object_id(object: &Value) -> Option<&str> {
+    object.as_str().or_else(|| object["id"].as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::test_util::temp_store;
+    use crate::sequencer::events::CommitOp;
+
+    const DID: &str = "did:plc:alice";
+
+    async fn create_test_bridge() -> ActivityPubBridge {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        ActivityPubBridge::new(
+            db,
+            ActivityPubConfig {
+                enabled: true,
+                allow_private_targets: false,
+            },
+            "https://pds.example".to_string(),
+            "pds.example".to_string(),
+            "test-secret",
+        )
+    }
+
+    /// Bridge `did` with a small key (full-size keys are slow in debug builds)
+    async fn bridge_account(bridge: &ActivityPubBridge, did: &str) {
+        let (private_key, public_key) = generate_key_pair(1024).unwrap();
+        sqlx::query("INSERT INTO activitypub_actor (did, private_key, public_key, created_at) VALUES (?1, ?2, ?3, ?4)")
+            .bind(did)
+            .bind(bridge.seal_key(did, &private_key).await.unwrap())
+            .bind(public_key)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&bridge.db)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_actor_key_sealing() {
+        let (pem, public_key) = generate_key_pair(1024).unwrap();
+
+        // Keys left in the clear are sealed at startup
+        let bridge = create_test_bridge().await;
+        sqlx::query("INSERT INTO activitypub_actor (did, private_key, public_key, created_at) VALUES (?1, ?2, ?3, ?4)")
+            .bind(DID)
+            .bind(&pem)
+            .bind(&public_key)
+            .bind(Utc::now().to_rfc3339())
+            .execute(&bridge.db)
+            .await
+            .unwrap();
+        assert_eq!(bridge.seal_plaintext_keys().await.unwrap(), 1);
+        assert_eq!(bridge.seal_plaintext_keys().await.unwrap(), 0);
+        let stored: String = sqlx::query_scalar("SELECT private_key FROM activitypub_actor WHERE did = ?1")
+            .bind(DID)
+            .fetch_one(&bridge.db)
+            .await
+            .unwrap();
+        assert!(stored.starts_with("sealed:") && !stored.contains("PRIVATE KEY"));
+        assert_eq!(bridge.open_key(DID, &stored).await.unwrap(), pem);
+
+        // Bound to the actor and the secret
+        assert!(bridge.open_key("did:plc:bob", &stored).await.is_err());
+        let mut other = create_test_bridge().await;
+        other.key_cipher = cipher_for(&[1u8; 32]);
+        assert!(other.open_key(DID, &stored).await.is_err());
+
+        // With a master key the backend wraps them
+        let master = hex::encode([7u8; 32]);
+        let keys = KeyManager::new(
+            Arc::new(crate::crypto::keys::ConfigKeys::new([("master", master.as_str())])),
+            "master",
+            "master",
+        );
+        let keys = Arc::new(keys);
+        let bridge = create_test_bridge().await.with_keys(keys.clone(), Some("master"));
+        let wrapped = bridge.seal_key(DID, &pem).await.unwrap();
+        assert!(wrapped.starts_with("wrapped:master:"));
+        assert_eq!(bridge.open_key(DID, &wrapped).await.unwrap(), pem);
+        assert!(create_test_bridge().await.open_key(DID, &wrapped).await.is_err());
+
+        // and can still open them once new keys are no longer wrapped
+        let bridge = create_test_bridge().await.with_keys(keys, None);
+        assert!(bridge.seal_key(DID, &pem).await.unwrap().starts_with("sealed:"));
+        assert_eq!(bridge.open_key(DID, &wrapped).await.unwrap(), pem);
+    }
+
+    #[tokio::test]
+    async fn test_actor_document() {
+        let bridge = create_test_bridge().await;
+        assert_eq!(bridge.acct("alice.pds.example"), "alice@pds.example");
+        assert_eq!(bridge.acct("alice.com"), "alice.com@pds.example");
+        assert!(bridge.actor_document(DID, "alice.pds.example", None).await.unwrap().is_none());
+
+        bridge_account(&bridge, DID).await;
+        let profile = json!({ "displayName": "Alice", "description": "hello & welcome" });
+        let doc = bridge.actor_document(DID, "alice.pds.example", Some(&profile)).await.unwrap().unwrap();
+        assert_eq!(doc["id"], "https://pds.example/ap/actors/did:plc:alice");
+        assert_eq!(doc["preferredUsername"], "alice");
+        assert_eq!(doc["name"], "Alice");
+        assert_eq!(doc["summary"], "<p>hello &amp; welcome</p>");
+        assert!(doc["publicKey"]["publicKeyPem"].as_str().unwrap().contains("PUBLIC KEY"));
+    }
+
+    #[tokio::test]
+    async fn test_inbox_follow_and_undo() {
+        let bridge = create_test_bridge().await;
+        bridge_account(&bridge, DID).await;
+        let actor = bridge.actor_url(DID);
+        let signer = json!({
+            "id": "https://social.example/users/bob",
+            "inbox": "https://social.example/users/bob/inbox",
+            "endpoints": { "sharedInbox": "https://social.example/inbox" },
+        });
+        let follow = json!({
+            "id": "https://social.example/follows/1",
+            "type": "Follow",
+            "actor": "https://social.example/users/bob",
+            "object": actor,
+        });
+
+        // Activities must come from the signer
+        let mut forged = follow.clone();
+        forged["actor"] = json!("https://social.example/users/carol");
+        assert!(bridge.handle_inbox(DID, &forged, &signer).await.is_err());
+
+        bridge.handle_inbox(DID, &follow, &signer).await.unwrap();
+        assert_eq!(bridge.follower_count(DID).await.unwrap(), 1);
+        let accept: String = sqlx::query_scalar("SELECT activity FROM activitypub_delivery WHERE inbox = ?1")
+            .bind("https://social.example/users/bob/inbox")
+            .fetch_one(&bridge.db)
+            .await
+            .unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&accept).unwrap()["type"], "Accept");
+
+        let undo = json!({ "type": "Undo", "actor": "https://social.example/users/bob", "object": follow });
+        bridge.handle_inbox(DID, &undo, &signer).await.unwrap();
+        assert_eq!(bridge.follower_count(DID).await.unwrap(), 0);
+
+        // Private inboxes are refused
+        let internal = json!({ "id": "https://social.example/users/bob", "inbox": "https://10.0.0.5/inbox" });
+        assert!(bridge.handle_inbox(DID, &follow, &internal).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_commit_translation() {
+        let (_dir, store) = temp_store();
+        let bridge = create_test_bridge().await;
+        bridge_account(&bridge, DID).await;
+        for (actor, shared) in [("bob", true), ("carol", true), ("dave", false)] {
+            let inbox = format!("https://social.example/users/{}/inbox", actor);
+            let shared = shared.then_some("https://social.example/inbox");
+            bridge
+                .add_follower(DID, &format!("https://social.example/users/{}", actor), &inbox, shared)
+                .await
+                .unwrap();
+        }
+
+        let note = post_to_note(
+            &bridge.base_url,
+            DID,
+            "3one",
+            &json!({ "text": "hello", "createdAt": "2025-01-01T00:00:00Z" }),
+        )
+        .unwrap();
+        sqlx::query("INSERT INTO activitypub_note (did, rkey, note, published) VALUES (?1, '3one', ?2, ?3)")
+            .bind(DID)
+            .bind(note.to_string())
+            .bind("2025-01-01T00:00:00Z")
+            .execute(&bridge.db)
+            .await
+            .unwrap();
+        let (items, _) = bridge.outbox_page(DID, None, 20).await.unwrap();
+        assert_eq!(items[0]["type"], "Create");
+        assert_eq!(items[0]["object"]["id"], note["id"]);
+
+        let op = |path: &str| CommitOp {
+            action: OpAction::Delete,
+            path: path.to_string(),
+            cid: None,
+        };
+        let commit = CommitEvent::new(
+            DID.to_string(),
+            "bafycommit".to_string(),
+            "3rev".to_string(),
+            None,
+            Vec::new(),
+            vec![
+                op("app.bsky.feed.post/3one"),
+                // Never bridged, and not a post
+                op("app.bsky.feed.post/3old"),
+                op("app.bsky.feed.like/3like"),
+            ],
+        );
+
+        // One Delete, sent once to the shared inbox and once to dave
+        assert_eq!(bridge.on_commit(&store, &commit).await.unwrap(), 2);
+        assert_eq!(bridge.note_count(DID).await.unwrap(), 0);
+
+        // Unbridged accounts are ignored
+        let mut other = commit.clone();
+        other.repo = "did:plc:bob".to_string();
+        assert_eq!(bridge.on_commit(&store, &other).await.unwrap(), 0);
+
+        bridge.disable(DID).await.unwrap();
+        assert!(!bridge.is_bridged(DID).await.unwrap());
+        assert_eq!(bridge.follower_count(DID).await.unwrap(), 0);
+    }
+}
@@ -0,0 +1,16 @@
+/// ActivityPub bridge (experimental)
+///
+/// Mirrors the public posts of accounts that opt in to an ActivityPub actor
+/// hosted by this server, so fediverse accounts can follow them:
+/// - WebFinger (`acct:<name>@<domain>`) and actor documents
+/// - Outbox and followers collections
+/// - An inbox accepting follows, and signed delivery of new posts
+///
+/// Off unless `PDS_ACTIVITYPUB_ENABLED` is set. The bridge is one-way:
+/// replies, likes and boosts from the fediverse are not brought back.
+
+pub mod bridge;
+pub mod signature;
+pub mod translate;
+
+pub use bridge::{ActivityPubBridge, ACTIVITY_JSON};
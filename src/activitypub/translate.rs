@@ -0,0 +1,220 @@
+/// app.bsky.feed.post -> ActivityStreams translation
+///
+/// Posts become `Note`s addressed to the public and the actor's followers.
+/// Link, mention and tag facets become links in the HTML content, images
+/// become `Document` attachments served by `com.atproto.sync.getBlob`, and
+/// replies keep their thread only when the parent is the author's own post;
+/// replies to other accounts are not bridged, since the parent has no
+/// ActivityPub object to point at.
+use serde_json::{json, Value};
+
+/// Audience of public activities
+pub const PUBLIC: &str = "https://www.w3.org/ns/activitystreams#Public";
+
+/// JSON-LD context of the documents we serve
+pub const CONTEXT: &str = "https://www.w3.org/ns/activitystreams";
+
+/// Collection whose posts are bridged
+pub const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// URL of the ActivityPub actor bridging `did`
+pub fn actor_url(base_url: &str, did: &str) -> String {
+    format!("{}/ap/actors/{}", base_url, did)
+}
+
+/// URL of the note translated from the post `rkey` of `did`
+pub fn note_url(base_url: &str, did: &str, rkey: &str) -> String {
+    format!("{}/notes/{}", actor_url(base_url, did), rkey)
+}
+
+/// Translate a post record into a `Note`, or `None` if it isn't bridged
+pub fn post_to_note(base_url: &str, did: &str, rkey: &str, record: &Value) -> Option<Value> {
+    let actor = actor_url(base_url, did);
+
+    let in_reply_to = match record["reply"]["parent"]["uri"].as_str() {
+        Some(parent) => {
+            let (parent_did, rest) = parent.strip_prefix("at://")?.split_once('/')?;
+            let parent_rkey = rest.strip_prefix(POST_COLLECTION)?.strip_prefix('/')?;
+            if parent_did != did {
+                return None;
+            }
+            Some(note_url(base_url, did, parent_rkey))
+        }
+        None => None,
+    };
+
+    let text = record["text"].as_str().unwrap_or_default();
+    let facets = record["facets"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let attachments = images(&record["embed"])
+        .iter()
+        .filter_map(|image| {
+            let cid = image["image"]["ref"]["$link"]
+                .as_str()
+                .or_else(|| image["image"]["cid"].as_str())?;
+            Some(json!({
+                "type": "Document",
+                "mediaType": image["image"]["mimeType"].as_str().unwrap_or("image/jpeg"),
+                "url": format!("{}/xrpc/com.atproto.sync.getBlob?did={}&cid={}", base_url, did, cid),
+                "name": image["alt"].as_str().unwrap_or_default(),
+            }))
+        })
+        .collect::<Vec<_>>();
+
+    let mut note = json!({
+        "id": note_url(base_url, did, rkey),
+        "type": "Note",
+        "attributedTo": actor,
+        "content": render_html(text, facets),
+        "published": record["createdAt"].as_str(),
+        "to": [PUBLIC],
+        "cc": [format!("{}/followers", actor)],
+        "url": format!("https://bsky.app/profile/{}/post/{}", did, rkey),
+        "attachment": attachments,
+    });
+    if let Some(in_reply_to) = in_reply_to {
+        note["inReplyTo"] = json!(in_reply_to);
+    }
+    if let Some(lang) = record["langs"][0].as_str() {
+        note["contentMap"] = json!({ lang: note["content"].clone() });
+    }
+
+    Some(note)
+}
+
+/// Images of an embed (plain or alongside a quoted record)
+fn images(embed: &Value) -> &[Value] {
+    let images = match embed["$type"].as_str() {
+        Some("app.bsky.embed.images") => &embed["images"],
+        Some("app.bsky.embed.recordWithMedia") => &embed["media"]["images"],
+        _ => &Value::Null,
+    };
+    images.as_array().map(Vec::as_slice).unwrap_or_default()
+}
+
+/// Post text as HTML, with facets as links
+pub fn render_html(text: &str, facets: &[Value]) -> String {
+    let mut links: Vec<(usize, usize, String)> = facets
+        .iter()
+        .filter_map(|facet| {
+            let start = facet["index"]["byteStart"].as_u64()? as usize;
+            let end = facet["index"]["byteEnd"].as_u64()? as usize;
+            if start >= end || !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+                return None;
+            }
+
+            let feature = facet["features"].get(0)?;
+            let href = match feature["$type"].as_str()? {
+                "app.bsky.richtext.facet#link" => feature["uri"]
+                    .as_str()
+                    .filter(|uri| uri.starts_with("https://") || uri.starts_with("http://"))?
+                    .to_string(),
+                "app.bsky.richtext.facet#mention" => {
+                    format!("https://bsky.app/profile/{}", feature["did"].as_str()?)
+                }
+                "app.bsky.richtext.facet#tag" => {
+                    format!("https://bsky.app/hashtag/{}", urlencoding::encode(feature["tag"].as_str()?))
+                }
+                _ => return None,
+            };
+            Some((start, end, href))
+        })
+        .collect();
+    links.sort_by_key(|(start, _, _)| *start);
+
+    let mut html = String::new();
+    let mut pos = 0;
+    for (start, end, href) in links {
+        // Overlapping facets keep the first
+        if start < pos {
+            continue;
+        }
+        html.push_str(&escape(&text[pos..start]));
+        html.push_str(&format!("<a href=\"{}\">{}</a>", escape(&href), escape(&text[start..end])));
+        pos = end;
+    }
+    html.push_str(&escape(&text[pos..]));
+
+    format!("<p>{}</p>", html.replace("\n\n", "</p><p>").replace('\n', "<br>"))
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "https://pds.example";
+    const DID: &str = "did:plc:alice";
+
+    #[test]
+    fn test_post_to_note() {
+        let text = "hi <b> @bob.test\n\nsee example.com";
+        let mention = text.find('@').unwrap();
+        let link = text.find("example.com").unwrap();
+        let record = json!({
+            "$type": "app.bsky.feed.post",
+            "text": text,
+            "createdAt": "2025-01-01T00:00:00Z",
+            "langs": ["en"],
+            "facets": [
+                {
+                    "index": { "byteStart": link, "byteEnd": link + 11 },
+                    "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": "https://example.com" }]
+                },
+                {
+                    "index": { "byteStart": mention, "byteEnd": mention + 9 },
+                    "features": [{ "$type": "app.bsky.richtext.facet#mention", "did": "did:plc:bob" }]
+                },
+                {
+                    // Not a web link; left as text
+                    "index": { "byteStart": 0, "byteEnd": 2 },
+                    "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": "javascript:alert(1)" }]
+                }
+            ],
+            "embed": {
+                "$type": "app.bsky.embed.images",
+                "images": [{
+                    "alt": "a cat",
+                    "image": { "$type": "blob", "ref": { "$link": "bafkreicat" }, "mimeType": "image/png", "size": 10 }
+                }]
+            }
+        });
+
+        let note = post_to_note(BASE, DID, "3post", &record).unwrap();
+        assert_eq!(note["id"], "https://pds.example/ap/actors/did:plc:alice/notes/3post");
+        assert_eq!(note["cc"][0], "https://pds.example/ap/actors/did:plc:alice/followers");
+        assert_eq!(
+            note["content"],
+            "<p>hi &lt;b&gt; <a href=\"https://bsky.app/profile/did:plc:bob\">@bob.test</a></p>\
+             <p>see <a href=\"https://example.com\">example.com</a></p>"
+        );
+        assert_eq!(note["contentMap"]["en"], note["content"]);
+        assert_eq!(
+            note["attachment"][0]["url"],
+            "https://pds.example/xrpc/com.atproto.sync.getBlob?did=did:plc:alice&cid=bafkreicat"
+        );
+        assert_eq!(note["attachment"][0]["name"], "a cat");
+        assert!(note.get("inReplyTo").is_none());
+    }
+
+    #[test]
+    fn test_replies() {
+        let reply_to = |parent: &str| {
+            json!({
+                "text": "reply",
+                "createdAt": "2025-01-01T00:00:00Z",
+                "reply": { "root": { "uri": parent }, "parent": { "uri": parent } }
+            })
+        };
+
+        let thread = post_to_note(BASE, DID, "3two", &reply_to("at://did:plc:alice/app.bsky.feed.post/3one")).unwrap();
+        assert_eq!(thread["inReplyTo"], "https://pds.example/ap/actors/did:plc:alice/notes/3one");
+        assert!(post_to_note(BASE, DID, "3two", &reply_to("at://did:plc:bob/app.bsky.feed.post/3one")).is_none());
+    }
+}
@@ -0,0 +1,78 @@
+/// Blob references held by records
+///
+/// Records point at blobs with `{"$type": "blob", "ref": {"$link": cid}, ...}`
+/// objects anywhere in their body (older records use `{"cid": ..., "mimeType": ...}`).
+/// The CIDs found here are tracked per record in the actor's `record_blob`
+/// table, which backs com.atproto.sync.listBlobs.
+use serde_json::Value;
+use std::collections::BTreeSet;
+
+/// Collect the CIDs of all blobs referenced by a record, deduplicated and sorted
+pub fn find_blob_refs(record: &Value) -> Vec<String> {
+    let mut cids = BTreeSet::new();
+    collect(record, &mut cids);
+    cids.into_iter().collect()
+}
+
+fn collect(value: &Value, cids: &mut BTreeSet<String>) {
+    match value {
+        Value::Object(map) => {
+            if let Some(cid) = blob_cid(map) {
+                cids.insert(cid.to_string());
+                return;
+            }
+            for v in map.values() {
+                collect(v, cids);
+            }
+        }
+        Value::Array(items) => {
+            for v in items {
+                collect(v, cids);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn blob_cid(map: &serde_json::Map<String, Value>) -> Option<&str> {
+    if map.get("$type").and_then(Value::as_str) == Some("blob") {
+        return map.get("ref")?.get("$link")?.as_str();
+    }
+
+    // Legacy blob reference
+    if map.contains_key("mimeType") && !map.contains_key("$type") {
+        return map.get("cid")?.as_str();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_find_blob_refs() {
+        let record = json!({
+            "$type": "app.bsky.feed.post",
+            "text": "two images",
+            "embed": {
+                "$type": "app.bsky.embed.images",
+                "images": [
+                    { "alt": "", "image": { "$type": "blob", "ref": { "$link": "bafkreib" }, "mimeType": "image/png", "size": 10 } },
+                    { "alt": "", "image": { "$type": "blob", "ref": { "$link": "bafkreia" }, "mimeType": "image/png", "size": 10 } },
+                    { "alt": "dup", "image": { "$type": "blob", "ref": { "$link": "bafkreia" }, "mimeType": "image/png", "size": 10 } }
+                ]
+            },
+            "avatar": { "cid": "bafkreilegacy", "mimeType": "image/jpeg" },
+            "facets": [{ "cid": "not-a-blob" }]
+        });
+
+        assert_eq!(
+            find_blob_refs(&record),
+            vec!["bafkreia".to_string(), "bafkreib".to_string(), "bafkreilegacy".to_string()]
+        );
+        assert!(find_blob_refs(&json!({ "text": "no blobs" })).is_empty());
+    }
+}
@@ -0,0 +1,291 @@
+/// Actor store compaction
+///
+/// Every commit adds a commit block, new MST nodes and record blocks, and
+/// nothing removes the ones it replaced, so actor stores grow with write
+/// volume rather than repository size. Compaction deletes blocks that are
+/// no longer reachable from the head commit, keeping those written by the
+/// last `history_depth` revisions, then returns the freed pages to the
+/// filesystem with an incremental VACUUM.
+///
+/// Blocks are deleted in small batches under the actor's commit lock, with
+/// a pause between batches. If the head moves while a repository is being
+/// compacted, the run stops for that repository; the next run picks it up.
+use crate::{
+    actor_store::{mst::Node, verify::decode_commit, ActorStore},
+    error::{PdsError, PdsResult},
+};
+use libipld::Cid;
+use sqlx::SqlitePool;
+use std::{
+    collections::{HashSet, VecDeque},
+    str::FromStr,
+    time::Duration,
+};
+
+/// SQLite `auto_vacuum` mode that allows `incremental_vacuum`
+const AUTO_VACUUM_INCREMENTAL: i64 = 2;
+
+/// How a compaction run deletes and vacuums
+#[derive(Debug, Clone)]
+pub struct CompactionOptions {
+    /// Revisions whose blocks are kept regardless of reachability
+    pub history_depth: usize,
+    /// Blocks deleted (and pages vacuumed) per step
+    pub batch_size: usize,
+    /// Pause between steps
+    pub batch_delay: Duration,
+}
+
+impl From<&crate::config::CompactionConfig> for CompactionOptions {
+    fn from(config: &crate::config::CompactionConfig) -> Self {
+        Self {
+            history_depth: config.history_depth,
+            batch_size: config.batch_size.max(1),
+            batch_delay: Duration::from_millis(config.batch_delay_ms),
+        }
+    }
+}
+
+/// Result of compacting one actor store
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct CompactionOutcome {
+    pub pruned_blocks: u64,
+    /// Bytes the database file shrank by
+    pub reclaimed_bytes: u64,
+    /// The head moved during the run, so pruning stopped early
+    pub interrupted: bool,
+}
+
+/// Prune unreachable blocks from an actor store and vacuum it
+pub async fn compact(store: &ActorStore, did: &str, opts: &CompactionOptions) -> PdsResult<CompactionOutcome> {
+    let pool = store.open_db(did).await?;
+    let size_before = database_size(&pool).await?;
+    let mut outcome = CompactionOutcome::default();
+
+    let (head, candidates) = {
+        let _commit_lock = store.lock_commits(did).await;
+        let head = store.get_repo_root(did).await?.cid;
+        let live = live_blocks(store, did, &head).await?;
+        (head, prunable_blocks(&pool, &live, opts.history_depth).await?)
+    };
+
+    for batch in candidates.chunks(opts.batch_size) {
+        {
+            let _commit_lock = store.lock_commits(did).await;
+            // A new commit may have re-used a block we were about to delete
+            if store.get_repo_root(did).await?.cid != head {
+                outcome.interrupted = true;
+                break;
+            }
+
+            let mut tx = pool.begin().await?;
+            for cid in batch {
+                sqlx::query("DELETE FROM repo_block WHERE cid = ?1")
+                    .bind(cid)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            tx.commit().await?;
+        }
+        outcome.pruned_blocks += batch.len() as u64;
+        tokio::time::sleep(opts.batch_delay).await;
+    }
+
+    vacuum(store, did, &pool, opts).await?;
+    outcome.reclaimed_bytes = size_before.saturating_sub(database_size(&pool).await?);
+
+    Ok(outcome)
+}
+
+/// CIDs of every block reachable from `head`: the commit, its MST nodes and
+/// their records, plus any record the index points at
+///
+/// Fails rather than returning a partial set if a reachable block is
+/// missing or undecodable, so a damaged repo is never pruned further.
+async fn live_blocks(store: &ActorStore, did: &str, head: &str) -> PdsResult<HashSet<String>> {
+    let mut live: HashSet<String> = store
+        .list_all_records(did)
+        .await?
+        .into_iter()
+        .map(|record| record.cid)
+        .collect();
+    live.insert(head.to_string());
+
+    // A fresh repository's root has no commit block yet
+    let Some(commit) = store.get_block(did, head).await? else {
+        return Ok(live);
+    };
+    let (commit, _) = decode_commit(&commit)?;
+
+    let mut queue = VecDeque::from([commit.data]);
+    let mut seen: HashSet<Cid> = HashSet::new();
+    while let Some(cid) = queue.pop_front() {
+        if !seen.insert(cid) {
+            continue;
+        }
+        let bytes = store
+            .get_block(did, &cid.to_string())
+            .await?
+            .ok_or_else(|| PdsError::Internal(format!("MST node {} is missing; not compacting {}", cid, did)))?;
+        let node = Node::decode(&bytes)?;
+        queue.extend(node.children());
+        live.extend(node.entries.iter().map(|entry| entry.value.to_string()));
+        live.insert(cid.to_string());
+    }
+
+    Ok(live)
+}
+
+/// Blocks outside `live` and older than the last `history_depth` revisions
+async fn prunable_blocks(pool: &SqlitePool, live: &HashSet<String>, history_depth: usize) -> PdsResult<Vec<String>> {
+    // Oldest revision still kept; blocks without a rev predate tracking
+    let oldest_kept: Option<String> = if history_depth == 0 {
+        None
+    } else {
+        sqlx::query_scalar(
+            "SELECT DISTINCT repo_rev FROM repo_block WHERE repo_rev IS NOT NULL
+             ORDER BY repo_rev DESC LIMIT 1 OFFSET ?1",
+        )
+        .bind(history_depth as i64 - 1)
+        .fetch_optional(pool)
+        .await?
+    };
+    if history_depth > 0 && oldest_kept.is_none() {
+        // Fewer revisions than the depth: only untracked blocks qualify
+        let cids: Vec<String> = sqlx::query_scalar("SELECT cid FROM repo_block WHERE repo_rev IS NULL ORDER BY rowid")
+            .fetch_all(pool)
+            .await?;
+        return Ok(cids.into_iter().filter(|cid| !live.contains(cid)).collect());
+    }
+
+    let cids: Vec<String> = sqlx::query_scalar(
+        "SELECT cid FROM repo_block WHERE repo_rev IS NULL OR ?1 IS NULL OR repo_rev < ?1 ORDER BY rowid",
+    )
+    .bind(oldest_kept)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(cids
+        .into_iter()
+        .filter(|cid| !live.contains(cid) && Cid::from_str(cid).is_ok())
+        .collect())
+}
+
+/// Return free pages to the filesystem
+///
+/// Stores are created with incremental auto-vacuum. Older ones are switched
+/// over with one full VACUUM, under the commit lock so writers wait for the
+/// rewrite instead of failing on a locked database; after that, free pages
+/// are released a batch at a time.
+async fn vacuum(store: &ActorStore, did: &str, pool: &SqlitePool, opts: &CompactionOptions) -> PdsResult<()> {
+    let mut conn = pool.acquire().await?;
+    let mode: i64 = sqlx::query_scalar("PRAGMA auto_vacuum").fetch_one(&mut *conn).await?;
+    if mode != AUTO_VACUUM_INCREMENTAL {
+        let _commit_lock = store.lock_commits(did).await;
+        sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&mut *conn).await?;
+        sqlx::query("VACUUM").execute(&mut *conn).await?;
+        return Ok(());
+    }
+
+    loop {
+        let free: i64 = sqlx::query_scalar("PRAGMA freelist_count").fetch_one(&mut *conn).await?;
+        if free == 0 {
+            return Ok(());
+        }
+        sqlx::query(&format!("PRAGMA incremental_vacuum({})", opts.batch_size))
+            .execute(&mut *conn)
+            .await?;
+        tokio::time::sleep(opts.batch_delay).await;
+    }
+}
+
+/// Size of the database file in bytes
+async fn database_size(pool: &SqlitePool) -> PdsResult<u64> {
+    let pages: i64 = sqlx::query_scalar("PRAGMA page_count").fetch_one(pool).await?;
+    let page_size: i64 = sqlx::query_scalar("PRAGMA page_size").fetch_one(pool).await?;
+    Ok((pages * page_size).max(0) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::{
+        mst::block_cid,
+        test_util::{dummy_signer, temp_store},
+        RepositoryManager, WriteOp, WriteOpAction,
+    };
+
+    fn options(history_depth: usize) -> CompactionOptions {
+        CompactionOptions {
+            history_depth,
+            batch_size: 3,
+            batch_delay: Duration::ZERO,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_compaction_prunes_replaced_blocks() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:compacttest";
+        store.create(did).await.unwrap();
+        let repo = RepositoryManager::new(did.to_string(), store.clone());
+
+        for i in 0..5 {
+            let write = WriteOp {
+                action: if i == 0 { WriteOpAction::Create } else { WriteOpAction::Update },
+                collection: "app.bsky.actor.profile".to_string(),
+                rkey: "self".to_string(),
+                value: Some(serde_json::json!({ "$type": "app.bsky.actor.profile", "displayName": format!("v{}", i) })),
+                swap_cid: None,
+                validate: Some(false),
+            };
+            repo.apply_writes(vec![write], None, dummy_signer).await.unwrap();
+        }
+        let orphan = block_cid(b"orphan");
+        store.put_block(did, &orphan.to_string(), b"orphan").await.unwrap();
+        let before = store.get_all_blocks(did).await.unwrap().len();
+
+        // Keeping the last two revisions leaves older history
+        let kept = compact(&store, did, &options(2)).await.unwrap();
+        assert!(kept.pruned_blocks > 0);
+        assert!(store.get_block(did, &orphan.to_string()).await.unwrap().is_none());
+
+        let full = compact(&store, did, &options(0)).await.unwrap();
+        assert!(full.pruned_blocks > 0);
+        assert!(!full.interrupted);
+        assert!(store.get_all_blocks(did).await.unwrap().len() < before - 1);
+
+        // The current repo is intact
+        let report = crate::actor_store::verify::verify_local(&store, did, None).await.unwrap();
+        assert!(report.is_valid(), "{}", report.summary());
+        assert_eq!(compact(&store, did, &options(0)).await.unwrap().pruned_blocks, 0);
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_reused_blocks() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:compactreuse";
+        store.create(did).await.unwrap();
+        let repo = RepositoryManager::new(did.to_string(), store.clone());
+        let uri = format!("at://{}/app.bsky.actor.profile/self", did);
+
+        let mut reused = None;
+        for (i, name) in ["x", "y", "x", "z"].into_iter().enumerate() {
+            let write = WriteOp {
+                action: if i == 0 { WriteOpAction::Create } else { WriteOpAction::Update },
+                collection: "app.bsky.actor.profile".to_string(),
+                rkey: "self".to_string(),
+                value: Some(serde_json::json!({ "$type": "app.bsky.actor.profile", "displayName": name })),
+                swap_cid: None,
+                validate: Some(false),
+            };
+            repo.apply_writes(vec![write], None, dummy_signer).await.unwrap();
+            reused.get_or_insert(store.get_record(did, &uri).await.unwrap().unwrap().cid);
+        }
+
+        // The first record block came back in the third revision, which is
+        // within the last two kept
+        compact(&store, did, &options(2)).await.unwrap();
+        assert!(store.get_block(did, &reused.unwrap()).await.unwrap().is_some());
+    }
+}
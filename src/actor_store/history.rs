@@ -0,0 +1,442 @@
+/// Repository commit history
+///
+/// Every commit applied to an actor store is logged in `repo_commit` with
+/// its CID, revision, number of record operations and time. The log is kept
+/// for the life of the repository; the blocks behind it are not. Compaction
+/// removes blocks older than the last `PDS_COMPACTION_HISTORY_DEPTH`
+/// revisions that the head no longer uses, so an old commit is listed as not
+/// `retained` once its commit block is gone, and reading its records fails
+/// with `NotFound` when a block it needs has been pruned.
+///
+/// Commits made before the log existed are not listed.
+///
+/// With record history on, a record's previous versions are also copied out
+/// of the block store as it is updated or deleted, so they outlive
+/// compaction. Only the last `PDS_RECORD_HISTORY_DEPTH` are kept per record.
+/// A kept version holds references to the blobs it uses until it is dropped,
+/// so rolling back to it never finds them collected.
+use crate::{
+    actor_store::{mst::Node, proof::commit_data, record_codec::decode_record, ActorStore},
+    error::{PdsError, PdsResult},
+};
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use serde::Serialize;
+use sqlx::Row;
+use std::str::FromStr;
+
+/// Most commits returned by `commit_path`
+pub const MAX_COMMIT_PATH: i64 = 10_000;
+
+/// One commit in a repository's history
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitInfo {
+    pub cid: String,
+    pub rev: String,
+    /// Record creates, updates and deletes in the commit
+    pub op_count: i64,
+    pub created_at: DateTime<Utc>,
+    /// Whether the commit block is still stored
+    pub retained: bool,
+}
+
+/// A record as of a past commit
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoricalRecord {
+    pub uri: String,
+    pub cid: String,
+    pub value: serde_json::Value,
+}
+
+/// A previous version of a record
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordVersion {
+    pub cid: String,
+    /// Revision of the commit that replaced or deleted this version
+    pub replaced_rev: String,
+    pub replaced_at: DateTime<Utc>,
+    pub value: serde_json::Value,
+}
+
+/// Previous versions of the record `uri`, newest first
+pub async fn record_versions(store: &ActorStore, did: &str, uri: &str) -> PdsResult<Vec<RecordVersion>> {
+    let pool = store.open_db(did).await?;
+    let rows = sqlx::query(
+        "SELECT cid, replaced_rev, replaced_at, content FROM record_version WHERE uri = ?1 ORDER BY id DESC",
+    )
+    .bind(uri)
+    .fetch_all(&pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            let content: Vec<u8> = row.try_get("content")?;
+            Ok(RecordVersion {
+                cid: row.try_get("cid")?,
+                replaced_rev: row.try_get("replaced_rev")?,
+                replaced_at: row.try_get("replaced_at")?,
+                value: decode_record(&content)?,
+            })
+        })
+        .collect()
+}
+
+/// Value of the kept version `cid` of the record `uri`
+pub async fn record_version(store: &ActorStore, did: &str, uri: &str, cid: &str) -> PdsResult<serde_json::Value> {
+    let pool = store.open_db(did).await?;
+    let content: Vec<u8> =
+        sqlx::query_scalar("SELECT content FROM record_version WHERE uri = ?1 AND cid = ?2 ORDER BY id DESC LIMIT 1")
+            .bind(uri)
+            .bind(cid)
+            .fetch_optional(&pool)
+            .await?
+            .ok_or_else(|| PdsError::NotFound(format!("No kept version {} of {}", cid, uri)))?;
+    decode_record(&content)
+}
+
+/// Commits newest first, starting below revision `before`
+pub async fn list_commits(
+    store: &ActorStore,
+    did: &str,
+    before: Option<&str>,
+    limit: i64,
+) -> PdsResult<Vec<CommitInfo>> {
+    let pool = store.open_db(did).await?;
+    let rows = sqlx::query(
+        r#"
+        SELECT c.rev, c.cid, c.op_count, c.committed_at,
+               EXISTS (SELECT 1 FROM repo_block b WHERE b.cid = c.cid) AS retained
+        FROM repo_commit c
+        WHERE ?1 IS NULL OR c.rev < ?1
+        ORDER BY c.rev DESC
+        LIMIT ?2
+        "#,
+    )
+    .bind(before)
+    .bind(limit)
+    .fetch_all(&pool)
+    .await?;
+
+    rows.into_iter()
+        .map(|row| {
+            Ok(CommitInfo {
+                cid: row.try_get("cid")?,
+                rev: row.try_get("rev")?,
+                op_count: row.try_get("op_count")?,
+                created_at: row.try_get("committed_at")?,
+                retained: row.try_get("retained")?,
+            })
+        })
+        .collect()
+}
+
+/// Revision of the logged commit `cid`
+async fn commit_rev(store: &ActorStore, did: &str, cid: &str) -> PdsResult<String> {
+    let pool = store.open_db(did).await?;
+    sqlx::query_scalar("SELECT rev FROM repo_commit WHERE cid = ?1 ORDER BY rev DESC LIMIT 1")
+        .bind(cid)
+        .fetch_optional(&pool)
+        .await?
+        .ok_or_else(|| PdsError::NotFound(format!("Commit {} is not in the history of {}", cid, did)))
+}
+
+/// CIDs of the commits after `earliest` up to and including `latest`
+/// (default: the head), oldest first
+///
+/// Without `earliest`, the path starts at the oldest logged commit.
+pub async fn commit_path(
+    store: &ActorStore,
+    did: &str,
+    latest: Option<&str>,
+    earliest: Option<&str>,
+) -> PdsResult<Vec<String>> {
+    let latest_rev = match latest {
+        Some(cid) => commit_rev(store, did, cid).await?,
+        None => store.get_repo_root(did).await?.rev,
+    };
+    let earliest_rev = match earliest {
+        Some(cid) => Some(commit_rev(store, did, cid).await?),
+        None => None,
+    };
+
+    let pool = store.open_db(did).await?;
+    let cids: Vec<String> = sqlx::query_scalar(
+        "SELECT cid FROM repo_commit WHERE rev <= ?1 AND (?2 IS NULL OR rev > ?2) ORDER BY rev ASC LIMIT ?3",
+    )
+    .bind(&latest_rev)
+    .bind(&earliest_rev)
+    .bind(MAX_COMMIT_PATH + 1)
+    .fetch_all(&pool)
+    .await?;
+
+    if cids.len() as i64 > MAX_COMMIT_PATH {
+        return Err(PdsError::Validation(format!(
+            "More than {} commits in range; pass a later earliest commit",
+            MAX_COMMIT_PATH
+        )));
+    }
+    Ok(cids)
+}
+
+/// Records as of commit `commit`, in key order
+///
+/// Only records in `collection` are listed when it is given. `cursor` is the
+/// `collection/rkey` key of the last record already seen. Records taken
+/// down since are left out unless `include_taken_down` is set. Returns the
+/// commit's revision, the records, and the cursor for the next page.
+pub async fn records_at_commit(
+    store: &ActorStore,
+    did: &str,
+    commit: &str,
+    collection: Option<&str>,
+    cursor: Option<&str>,
+    limit: usize,
+    include_taken_down: bool,
+) -> PdsResult<(String, Vec<HistoricalRecord>, Option<String>)> {
+    let rev = commit_rev(store, did, commit).await?;
+    let pool = store.open_db(did).await?;
+    let pruned = |what: &str| {
+        PdsError::NotFound(format!(
+            "{} of commit {} is no longer retained; older history has been compacted",
+            what, commit
+        ))
+    };
+
+    let commit_bytes = store.get_block(did, commit).await?.ok_or_else(|| pruned("The commit block"))?;
+    let root = commit_data(&commit_bytes)?;
+
+    let prefix = collection.map(|c| format!("{}/", c));
+    // Keys at or before `after` are skipped
+    let after = [cursor.map(str::to_string), prefix.clone()].into_iter().flatten().max();
+    let past = |key: &str| after.as_deref().map_or(true, |after| key > after);
+
+    enum Pending {
+        Node(Cid),
+        Entry(String, Cid),
+    }
+
+    let limit = limit.max(1);
+    let mut records = Vec::new();
+    let mut last_key: Option<String> = None;
+    let mut stack = vec![Pending::Node(root)];
+    while let Some(item) = stack.pop() {
+        match item {
+            Pending::Node(cid) => {
+                let bytes = store
+                    .get_block(did, &cid.to_string())
+                    .await?
+                    .ok_or_else(|| pruned("An MST node"))?;
+                let node = Node::decode(&bytes)?;
+                // Pushed in reverse so keys come off the stack in order; a
+                // subtree is skipped when every key in it is before `after`
+                for (i, entry) in node.entries.iter().enumerate().rev() {
+                    if let Some(right) = entry.right {
+                        let next_key = node.entries.get(i + 1).map(|e| e.key.as_str());
+                        if next_key.map_or(true, &past) {
+                            stack.push(Pending::Node(right));
+                        }
+                    }
+                    stack.push(Pending::Entry(entry.key.clone(), entry.value));
+                }
+                if let Some(left) = node.left {
+                    if node.entries.first().map_or(true, |e| past(&e.key)) {
+                        stack.push(Pending::Node(left));
+                    }
+                }
+            }
+            Pending::Entry(key, value) => {
+                if !past(&key) {
+                    continue;
+                }
+                if let Some(prefix) = &prefix {
+                    if !key.starts_with(prefix.as_str()) {
+                        break;
+                    }
+                }
+                let uri = format!("at://{}/{}", did, key);
+                if !include_taken_down {
+                    let taken_down: Option<bool> =
+                        sqlx::query_scalar("SELECT takedown_ref IS NOT NULL FROM record WHERE uri = ?1")
+                            .bind(&uri)
+                            .fetch_optional(&pool)
+                            .await?;
+                    if taken_down == Some(true) {
+                        continue;
+                    }
+                }
+                // Another record exists, so there is a next page
+                if records.len() == limit {
+                    return Ok((rev, records, last_key));
+                }
+
+                let bytes = store
+                    .get_block(did, &value.to_string())
+                    .await?
+                    .ok_or_else(|| pruned(&format!("Record {}", key)))?;
+                records.push(HistoricalRecord {
+                    uri,
+                    cid: value.to_string(),
+                    value: decode_record(&bytes)?,
+                });
+                last_key = Some(key);
+            }
+        }
+    }
+
+    Ok((rev, records, None))
+}
+
+/// Commit CID in canonical form, or a validation error
+pub fn parse_commit_cid(cid: &str) -> PdsResult<String> {
+    Cid::from_str(cid)
+        .map(|cid| cid.to_string())
+        .map_err(|e| PdsError::Validation(format!("Invalid commit CID {}: {}", cid, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::{
+        compaction::{compact, CompactionOptions},
+        test_util::{dummy_signer, temp_store},
+        RepositoryManager, WriteOp, WriteOpAction,
+    };
+    use std::time::Duration;
+
+    fn write(action: WriteOpAction, collection: &str, rkey: &str, text: &str) -> WriteOp {
+        WriteOp {
+            action,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            value: Some(serde_json::json!({ "$type": collection, "text": text })),
+            swap_cid: None,
+            validate: Some(false),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_commit_history() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:historytest";
+        store.create(did).await.unwrap();
+        let repo = RepositoryManager::new(did.to_string(), store.clone());
+
+        let post = "app.bsky.feed.post";
+        repo.apply_writes(
+            vec![write(WriteOpAction::Create, post, "a", "first"), write(WriteOpAction::Create, post, "b", "second")],
+            None,
+            dummy_signer,
+        )
+        .await
+        .unwrap();
+        repo.apply_writes(vec![write(WriteOpAction::Create, "app.bsky.feed.like", "c", "like")], None, dummy_signer)
+            .await
+            .unwrap();
+        repo.apply_writes(vec![write(WriteOpAction::Update, post, "a", "edited")], None, dummy_signer)
+            .await
+            .unwrap();
+
+        let commits = list_commits(&store, did, None, 10).await.unwrap();
+        assert_eq!(commits.len(), 3);
+        assert_eq!(commits[0].cid, store.get_repo_root(did).await.unwrap().cid);
+        assert_eq!(commits.iter().map(|c| c.op_count).collect::<Vec<_>>(), vec![1, 1, 2]);
+        assert!(commits.iter().all(|c| c.retained));
+        let older = list_commits(&store, did, Some(&commits[0].rev), 1).await.unwrap();
+        assert_eq!(older[0].cid, commits[1].cid);
+
+        let path = commit_path(&store, did, None, Some(&commits[2].cid)).await.unwrap();
+        assert_eq!(path, vec![commits[1].cid.clone(), commits[0].cid.clone()]);
+        assert_eq!(commit_path(&store, did, Some(&commits[1].cid), None).await.unwrap().len(), 2);
+        assert!(commit_path(&store, did, Some("bafyreiunknown"), None).await.is_err());
+
+        // The first commit still has the original post text
+        let (rev, records, cursor) =
+            records_at_commit(&store, did, &commits[2].cid, None, None, 10, false).await.unwrap();
+        assert_eq!(rev, commits[2].rev);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].value["text"], "first");
+        assert!(cursor.is_none());
+
+        // Paged and filtered by collection at the head
+        let (_, page, cursor) =
+            records_at_commit(&store, did, &commits[0].cid, Some(post), None, 1, false).await.unwrap();
+        assert_eq!(page[0].value["text"], "edited");
+        let cursor = cursor.unwrap();
+        assert_eq!(cursor, format!("{}/a", post));
+        let (_, page, cursor) =
+            records_at_commit(&store, did, &commits[0].cid, Some(post), Some(&cursor), 1, false).await.unwrap();
+        assert_eq!(page[0].uri, format!("at://{}/{}/b", did, post));
+        assert!(cursor.is_none());
+
+        // A record taken down since is only listed for admins
+        sqlx::query("UPDATE record SET takedown_ref = 'takedown-1' WHERE uri = ?1")
+            .bind(format!("at://{}/{}/b", did, post))
+            .execute(&store.open_db(did).await.unwrap())
+            .await
+            .unwrap();
+        let (_, records, _) = records_at_commit(&store, did, &commits[2].cid, None, None, 10, false).await.unwrap();
+        assert_eq!(records.iter().map(|r| r.value["text"].as_str().unwrap()).collect::<Vec<_>>(), vec!["first"]);
+        let (_, records, _) = records_at_commit(&store, did, &commits[2].cid, None, None, 10, true).await.unwrap();
+        assert_eq!(records.len(), 2);
+
+        // Compaction keeps the log but not the old blocks
+        let opts = CompactionOptions {
+            history_depth: 0,
+            batch_size: 100,
+            batch_delay: Duration::ZERO,
+        };
+        compact(&store, did, &opts).await.unwrap();
+        let commits = list_commits(&store, did, None, 10).await.unwrap();
+        assert_eq!(commits.iter().map(|c| c.retained).collect::<Vec<_>>(), vec![true, false, false]);
+        assert!(matches!(
+            records_at_commit(&store, did, &commits[2].cid, None, None, 10, false).await,
+            Err(PdsError::NotFound(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_record_versions() {
+        let (_dir, store) = temp_store();
+        let store = store.with_record_history(2);
+        let did = "did:plc:versiontest";
+        store.create(did).await.unwrap();
+        let repo = RepositoryManager::new(did.to_string(), store.clone());
+
+        let profile = "app.bsky.actor.profile";
+        let uri = format!("at://{}/{}/self", did, profile);
+        for text in ["original", "vandalised", "vandalised", "worse"] {
+            repo.apply_writes(vec![write(WriteOpAction::Update, profile, "self", text)], None, dummy_signer)
+                .await
+                .unwrap();
+        }
+
+        // Unchanged rewrites are not versions, and only two are kept
+        let versions = record_versions(&store, did, &uri).await.unwrap();
+        assert_eq!(
+            versions.iter().map(|v| v.value["text"].as_str().unwrap()).collect::<Vec<_>>(),
+            vec!["vandalised", "original"]
+        );
+        assert_eq!(versions[0].replaced_rev, store.get_repo_root(did).await.unwrap().rev);
+
+        // Kept versions outlive compaction and deletion
+        let opts = CompactionOptions {
+            history_depth: 0,
+            batch_size: 100,
+            batch_delay: Duration::ZERO,
+        };
+        compact(&store, did, &opts).await.unwrap();
+        repo.apply_writes(vec![write(WriteOpAction::Delete, profile, "self", "")], None, dummy_signer)
+            .await
+            .unwrap();
+        let versions = record_versions(&store, did, &uri).await.unwrap();
+        assert_eq!(versions[0].value["text"], "worse");
+        let original = record_version(&store, did, &uri, &versions[1].cid).await.unwrap();
+        assert_eq!(original["text"], "vandalised");
+        assert!(matches!(
+            record_version(&store, did, &uri, "bafyreiunknown").await,
+            Err(PdsError::NotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,548 @@
+/// Record write hooks
+///
+/// Hooks are compiled-in plugins that see every record create, update and
+/// delete in their collections before `RepositoryManager::apply_writes`
+/// commits it. A hook can rewrite the record, reject the whole batch, or
+/// attach a note to the write; after the commit, each hook is called again
+/// with the committed writes and its notes, e.g. to label records or push
+/// copies elsewhere.
+///
+/// Built-in hooks are enabled by name through `PDS_WRITE_HOOKS` (see
+/// [`WriteHooksConfig`]) and run in the listed order, before the content
+/// policy and record validation, so rewritten records are still checked.
+/// Instance-specific hooks can be added with [`WriteHooks::with_hook`].
+use crate::{
+    actor_store::repository::{WriteOp, WriteOpAction},
+    admin::LabelManager,
+    config::{WriteHookConfig, WriteHooksConfig},
+    error::{PdsError, PdsResult},
+};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::Arc;
+
+/// Names accepted in `PDS_WRITE_HOOKS`
+pub const BUILT_IN_HOOKS: &[&str] = &["hashtag_ban", "auto_label"];
+
+/// A record write as seen by a hook
+pub struct HookWrite<'a> {
+    pub did: &'a str,
+    pub action: &'a WriteOpAction,
+    pub collection: &'a str,
+    pub rkey: &'a str,
+    /// The record for creates and updates; hooks may change it in place
+    pub record: Option<&'a mut Value>,
+}
+
+/// What a hook decided about a write
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    /// Let the write through, possibly rewritten
+    Continue,
+    /// Let the write through and pass this note to `after_commit`
+    Annotate(Value),
+    /// Refuse the whole batch with this reason
+    Reject(String),
+}
+
+/// A write in a hook's collections, after it was committed
+#[derive(Debug, Clone)]
+pub struct CommittedWrite {
+    pub uri: String,
+    pub action: WriteOpAction,
+    /// CID of the committed record; `None` for deletes
+    pub cid: Option<String>,
+    /// The note the hook attached before the commit
+    pub note: Option<Value>,
+}
+
+/// A write hook
+#[async_trait]
+pub trait WriteHook: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// Called for each write before the commit
+    async fn before_write(&self, write: &mut HookWrite<'_>) -> HookOutcome;
+
+    /// Called once the writes it saw are committed
+    ///
+    /// Failures here can't undo the commit, so hooks log them.
+    async fn after_commit(&self, _did: &str, _writes: &[CommittedWrite]) {}
+}
+
+struct ScopedHook {
+    hook: Arc<dyn WriteHook>,
+    /// NSIDs, or prefixes ending in `*`; empty means every collection
+    collections: Vec<String>,
+}
+
+impl ScopedHook {
+    fn applies_to(&self, collection: &str) -> bool {
+        self.collections.is_empty()
+            || self.collections.iter().any(|scope| match scope.strip_suffix('*') {
+                Some(prefix) => collection.starts_with(prefix),
+                None => scope == collection,
+            })
+    }
+}
+
+/// Notes hooks attached to writes, keyed by (hook, write) position
+#[derive(Debug, Default)]
+pub struct HookNotes(HashMap<(usize, usize), Value>);
+
+/// The configured hooks, in the order they run
+#[derive(Default)]
+pub struct WriteHooks {
+    hooks: Vec<ScopedHook>,
+}
+
+impl WriteHooks {
+    /// Build the built-in hooks listed in `config`
+    pub fn from_config(
+        config: &WriteHooksConfig,
+        label_manager: Arc<LabelManager>,
+        service_did: String,
+    ) -> PdsResult<Self> {
+        let mut hooks = Self::default();
+        for hook_config in &config.hooks {
+            let hook: Arc<dyn WriteHook> = match hook_config.name.as_str() {
+                "hashtag_ban" => Arc::new(HashtagBanHook::from_config(hook_config)?),
+                "auto_label" => Arc::new(AutoLabelHook::from_config(
+                    hook_config,
+                    label_manager.clone(),
+                    service_did.clone(),
+                )?),
+                other => return Err(PdsError::Validation(format!("Unknown write hook: {}", other))),
+            };
+            hooks.hooks.push(ScopedHook {
+                hook,
+                collections: hook_config.collections.clone(),
+            });
+        }
+        Ok(hooks)
+    }
+
+    /// Add a hook for `collections` (empty for all) to the end of the pipeline
+    pub fn with_hook(mut self, hook: impl WriteHook + 'static, collections: Vec<String>) -> Self {
+        self.hooks.push(ScopedHook {
+            hook: Arc::new(hook),
+            collections,
+        });
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Run every hook over a batch of writes, rewriting them in place
+    ///
+    /// Fails if any hook rejects any write.
+    pub async fn before_commit(&self, did: &str, writes: &mut [WriteOp]) -> PdsResult<HookNotes> {
+        let mut notes = HookNotes::default();
+        for (hook_index, scoped) in self.hooks.iter().enumerate() {
+            for (write_index, write) in writes.iter_mut().enumerate() {
+                if !scoped.applies_to(&write.collection) {
+                    continue;
+                }
+
+                let mut hook_write = HookWrite {
+                    did,
+                    action: &write.action,
+                    collection: &write.collection,
+                    rkey: &write.rkey,
+                    record: write.value.as_mut(),
+                };
+                match scoped.hook.before_write(&mut hook_write).await {
+                    HookOutcome::Continue => {}
+                    HookOutcome::Annotate(note) => {
+                        notes.0.insert((hook_index, write_index), note);
+                    }
+                    HookOutcome::Reject(reason) => {
+                        let name = scoped.hook.name();
+                        tracing::info!(did, hook = name, "Write rejected by hook: {}", reason);
+                        return Err(PdsError::Validation(format!(
+                            "Rejected by write hook ({}): {}",
+                            name, reason
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(notes)
+    }
+
+    /// Hand each hook the committed writes from its collections
+    ///
+    /// `cids` maps `collection/rkey` to the committed record CID.
+    pub async fn after_commit(
+        &self,
+        did: &str,
+        writes: &[WriteOp],
+        mut notes: HookNotes,
+        cids: &HashMap<String, String>,
+    ) {
+        for (hook_index, scoped) in self.hooks.iter().enumerate() {
+            let committed: Vec<CommittedWrite> = writes
+                .iter()
+                .enumerate()
+                .filter(|(_, write)| scoped.applies_to(&write.collection))
+                .map(|(write_index, write)| CommittedWrite {
+                    uri: format!("at://{}/{}/{}", did, write.collection, write.rkey),
+                    action: write.action.clone(),
+                    cid: cids.get(&format!("{}/{}", write.collection, write.rkey)).cloned(),
+                    note: notes.0.remove(&(hook_index, write_index)),
+                })
+                .collect();
+            if !committed.is_empty() {
+                scoped.hook.after_commit(did, &committed).await;
+            }
+        }
+    }
+}
+
+/// Comma-separated option values, trimmed, lowercased and without `#`
+fn list_option(options: &BTreeMap<String, String>, key: &str) -> Vec<String> {
+    options
+        .get(key)
+        .map(|value| {
+            value
+                .split(',')
+                .map(|item| item.trim().trim_start_matches('#').to_lowercase())
+                .filter(|item| !item.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Hashtags of a post: its `tags`, tag facets and `#words` in the text, lowercased
+pub fn record_hashtags(record: &Value) -> HashSet<String> {
+    let mut tags: HashSet<String> = HashSet::new();
+
+    if let Some(list) = record.get("tags").and_then(Value::as_array) {
+        tags.extend(list.iter().filter_map(Value::as_str).map(str::to_lowercase));
+    }
+    for facet in record.get("facets").and_then(Value::as_array).into_iter().flatten() {
+        for feature in facet.get("features").and_then(Value::as_array).into_iter().flatten() {
+            if let Some(tag) = feature.get("tag").and_then(Value::as_str) {
+                tags.insert(tag.to_lowercase());
+            }
+        }
+    }
+    if let Some(text) = record.get("text").and_then(Value::as_str) {
+        for word in text.split_whitespace() {
+            if let Some(tag) = word.strip_prefix('#') {
+                let tag = tag.trim_end_matches(|c: char| !c.is_alphanumeric() && c != '_');
+                if !tag.is_empty() {
+                    tags.insert(tag.to_lowercase());
+                }
+            }
+        }
+    }
+
+    tags
+}
+
+/// Rejects records using banned hashtags, or strips the tags from them
+///
+/// Options: `tags` (comma-separated, required) and `action` (`reject`, the
+/// default, or `strip`). Stripping removes the tags from the record's `tags`
+/// and tag facets; the text is left alone.
+pub struct HashtagBanHook {
+    banned: HashSet<String>,
+    strip: bool,
+}
+
+impl HashtagBanHook {
+    pub fn new(banned: impl IntoIterator<Item = String>, strip: bool) -> Self {
+        Self {
+            banned: banned.into_iter().map(|tag| tag.trim_start_matches('#').to_lowercase()).collect(),
+            strip,
+        }
+    }
+
+    fn from_config(config: &WriteHookConfig) -> PdsResult<Self> {
+        let banned = list_option(&config.options, "tags");
+        if banned.is_empty() {
+            return Err(PdsError::Validation("PDS_WRITE_HOOK_HASHTAG_BAN_TAGS is required".to_string()));
+        }
+        let strip = match config.options.get("action").map(String::as_str) {
+            None | Some("reject") => false,
+            Some("strip") => true,
+            Some(other) => {
+                return Err(PdsError::Validation(format!(
+                    "Invalid PDS_WRITE_HOOK_HASHTAG_BAN_ACTION: {} (expected reject or strip)",
+                    other
+                )))
+            }
+        };
+        Ok(Self::new(banned, strip))
+    }
+
+    fn is_banned(&self, tag: &str) -> bool {
+        self.banned.contains(&tag.to_lowercase())
+    }
+
+    fn strip_tags(&self, record: &mut Value) {
+        if let Some(list) = record.get_mut("tags").and_then(Value::as_array_mut) {
+            list.retain(|tag| !tag.as_str().is_some_and(|tag| self.is_banned(tag)));
+        }
+        if let Some(facets) = record.get_mut("facets").and_then(Value::as_array_mut) {
+            for facet in facets.iter_mut() {
+                if let Some(features) = facet.get_mut("features").and_then(Value::as_array_mut) {
+                    features.retain(|f| !f.get("tag").and_then(Value::as_str).is_some_and(|tag| self.is_banned(tag)));
+                }
+            }
+            facets.retain(|facet| {
+                facet.get("features").and_then(Value::as_array).map_or(true, |f| !f.is_empty())
+            });
+        }
+    }
+}
+
+#[async_trait]
+impl WriteHook for HashtagBanHook {
+    fn name(&self) -> &'static str {
+        "hashtag_ban"
+    }
+
+    async fn before_write(&self, write: &mut HookWrite<'_>) -> HookOutcome {
+        let Some(record) = write.record.as_deref_mut() else {
+            return HookOutcome::Continue;
+        };
+        let mut found: Vec<String> = record_hashtags(record)
+            .into_iter()
+            .filter(|tag| self.banned.contains(tag))
+            .collect();
+        if found.is_empty() {
+            return HookOutcome::Continue;
+        }
+
+        if self.strip {
+            self.strip_tags(record);
+            return HookOutcome::Continue;
+        }
+        found.sort();
+        HookOutcome::Reject(format!("banned hashtag #{}", found[0]))
+    }
+}
+
+/// Labels records that use given hashtags or words
+///
+/// Options: `label` (required) plus `tags` and/or `words` (comma-separated;
+/// words match anywhere in the text, ignoring case). Labels are applied by
+/// the service once the record is committed.
+pub struct AutoLabelHook {
+    label: String,
+    tags: Vec<String>,
+    words: Vec<String>,
+    label_manager: Arc<LabelManager>,
+    service_did: String,
+}
+
+impl AutoLabelHook {
+    pub fn new(
+        label: String,
+        tags: Vec<String>,
+        words: Vec<String>,
+        label_manager: Arc<LabelManager>,
+        service_did: String,
+    ) -> Self {
+        Self {
+            label,
+            tags,
+            words,
+            label_manager,
+            service_did,
+        }
+    }
+
+    fn from_config(config: &WriteHookConfig, label_manager: Arc<LabelManager>, service_did: String) -> PdsResult<Self> {
+        let label = config
+            .options
+            .get("label")
+            .map(|label| label.trim().to_string())
+            .filter(|label| !label.is_empty())
+            .ok_or_else(|| PdsError::Validation("PDS_WRITE_HOOK_AUTO_LABEL_LABEL is required".to_string()))?;
+        let tags = list_option(&config.options, "tags");
+        let words = list_option(&config.options, "words");
+        if tags.is_empty() && words.is_empty() {
+            return Err(PdsError::Validation(
+                "PDS_WRITE_HOOK_AUTO_LABEL_TAGS or PDS_WRITE_HOOK_AUTO_LABEL_WORDS is required".to_string(),
+            ));
+        }
+        Ok(Self::new(label, tags, words, label_manager, service_did))
+    }
+
+    fn matches(&self, record: &Value) -> bool {
+        let hashtags = record_hashtags(record);
+        if self.tags.iter().any(|tag| hashtags.contains(tag)) {
+            return true;
+        }
+        let text = record.get("text").and_then(Value::as_str).unwrap_or_default().to_lowercase();
+        self.words.iter().any(|word| text.contains(word.as_str()))
+    }
+}
+
+#[async_trait]
+impl WriteHook for AutoLabelHook {
+    fn name(&self) -> &'static str {
+        "auto_label"
+    }
+
+    async fn before_write(&self, write: &mut HookWrite<'_>) -> HookOutcome {
+        match write.record.as_deref() {
+            Some(record) if self.matches(record) => HookOutcome::Annotate(Value::from(self.label.clone())),
+            _ => HookOutcome::Continue,
+        }
+    }
+
+    async fn after_commit(&self, _did: &str, writes: &[CommittedWrite]) {
+        for write in writes.iter().filter(|w| w.note.is_some()) {
+            if let Err(e) = self
+                .label_manager
+                .apply_label(&write.uri, write.cid.as_deref(), &self.label, &self.service_did, None)
+                .await
+            {
+                tracing::warn!("Failed to label {} from write hook: {}", write.uri, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::{
+        test_util::{dummy_signer, temp_store},
+        RepositoryManager,
+    };
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Adds a field, rejects one rkey and records what it saw after commit
+    struct TestHook {
+        committed: Arc<Mutex<Vec<CommittedWrite>>>,
+    }
+
+    #[async_trait]
+    impl WriteHook for TestHook {
+        fn name(&self) -> &'static str {
+            "test"
+        }
+
+        async fn before_write(&self, write: &mut HookWrite<'_>) -> HookOutcome {
+            if write.rkey == "forbidden" {
+                return HookOutcome::Reject("not this one".to_string());
+            }
+            match write.record.as_deref_mut() {
+                Some(record) => {
+                    record["via"] = json!("hook");
+                    HookOutcome::Annotate(json!(write.rkey))
+                }
+                None => HookOutcome::Continue,
+            }
+        }
+
+        async fn after_commit(&self, _did: &str, writes: &[CommittedWrite]) {
+            self.committed.lock().unwrap().extend_from_slice(writes);
+        }
+    }
+
+    fn write(action: WriteOpAction, collection: &str, rkey: &str, value: Option<Value>) -> WriteOp {
+        WriteOp {
+            action,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            value,
+            validate: Some(false),
+            swap_cid: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hashtag_ban() {
+        let post = json!({
+            "text": "hello #Spam and #ok",
+            "tags": ["spam", "fine"],
+            "facets": [{
+                "index": { "byteStart": 6, "byteEnd": 11 },
+                "features": [{ "$type": "app.bsky.richtext.facet#tag", "tag": "spam" }]
+            }]
+        });
+        assert_eq!(
+            record_hashtags(&post),
+            ["spam", "ok", "fine"].into_iter().map(String::from).collect()
+        );
+
+        let hooks = WriteHooks::default().with_hook(HashtagBanHook::new(vec!["#SPAM".to_string()], false), vec![]);
+        let mut writes = vec![write(WriteOpAction::Create, "app.bsky.feed.post", "1", Some(post.clone()))];
+        let err = hooks.before_commit("did:plc:alice", &mut writes).await.unwrap_err();
+        assert!(err.to_string().contains("banned hashtag #spam"));
+
+        let hooks = WriteHooks::default().with_hook(HashtagBanHook::new(vec!["spam".to_string()], true), vec![]);
+        hooks.before_commit("did:plc:alice", &mut writes).await.unwrap();
+        let stripped = writes[0].value.as_ref().unwrap();
+        assert_eq!(stripped["tags"], json!(["fine"]));
+        assert_eq!(stripped["facets"], json!([]));
+        assert_eq!(stripped["text"], post["text"]);
+    }
+
+    #[tokio::test]
+    async fn test_hooks_rewrite_reject_and_annotate() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:hooktest";
+        store.create(did).await.unwrap();
+
+        let committed = Arc::new(Mutex::new(Vec::new()));
+        let hooks = WriteHooks::default().with_hook(
+            TestHook {
+                committed: committed.clone(),
+            },
+            vec!["app.bsky.feed.*".to_string()],
+        );
+        let repo = RepositoryManager::new(did.to_string(), store.clone()).with_write_hooks(Some(Arc::new(hooks)));
+
+        let post = json!({ "$type": "app.bsky.feed.post", "text": "hi" });
+        let writes = vec![
+            write(WriteOpAction::Create, "app.bsky.feed.post", "1", Some(post.clone())),
+            write(WriteOpAction::Create, "app.bsky.actor.profile", "self", Some(json!({}))),
+        ];
+        repo.apply_writes(writes, None, dummy_signer).await.unwrap();
+
+        // Only the in-scope record was rewritten and reported back
+        let stored = repo.get_record(&format!("at://{}/app.bsky.feed.post/1", did)).await.unwrap().unwrap();
+        assert_eq!(stored["value"]["via"], "hook");
+        let profile = repo.get_record(&format!("at://{}/app.bsky.actor.profile/self", did)).await.unwrap().unwrap();
+        assert!(profile["value"].get("via").is_none());
+
+        let seen = committed.lock().unwrap().clone();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].cid.as_deref(), stored["cid"].as_str());
+        assert_eq!(seen[0].note, Some(json!("1")));
+
+        // A rejection stops the whole batch
+        let writes = vec![
+            write(WriteOpAction::Create, "app.bsky.feed.post", "2", Some(post.clone())),
+            write(WriteOpAction::Create, "app.bsky.feed.post", "forbidden", Some(post)),
+        ];
+        assert!(repo.apply_writes(writes, None, dummy_signer).await.is_err());
+        assert!(repo.get_record(&format!("at://{}/app.bsky.feed.post/2", did)).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_hook() {
+        let config = WriteHooksConfig {
+            hooks: vec![WriteHookConfig {
+                name: "nope".to_string(),
+                ..Default::default()
+            }],
+        };
+        let labels = Arc::new(LabelManager::new(
+            sqlx::SqlitePool::connect_lazy("sqlite::memory:").unwrap(),
+            "did:web:localhost".to_string(),
+        ));
+        assert!(WriteHooks::from_config(&config, labels, "did:web:localhost".to_string()).is_err());
+    }
+}
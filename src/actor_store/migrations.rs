@@ -0,0 +1,207 @@
+/// Actor store schema migration fan-out
+///
+/// Every account has its own SQLite database, upgraded in place the first
+/// time the server opens it after a release changes the actor schema. An
+/// account that stays idle keeps the old schema until then, so this runner
+/// walks the actor store directory and upgrades every database ahead of
+/// time, a few at a time. A store that fails is logged and reported, and the
+/// rest carry on.
+///
+/// Runs as the `actor_store_migration` background job and as
+/// `aurora-locus migrate actor-stores [--concurrency N]`.
+use crate::{
+    actor_store::{store::ACTOR_SCHEMA_VERSION, ActorStore},
+    error::PdsResult,
+};
+use futures::stream::{self, StreamExt};
+use sqlx::SqlitePool;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+/// Stores upgraded at once by default
+pub const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Log progress every this many stores
+const PROGRESS_EVERY: usize = 100;
+
+/// Outcome of a fan-out run
+#[derive(Debug, Default)]
+pub struct ActorMigrationReport {
+    /// Actor databases found on disk
+    pub discovered: usize,
+    /// Stores whose schema changed
+    pub upgraded: usize,
+    /// Stores that were already current
+    pub current: usize,
+    /// Stores still below the latest version because their records await
+    /// re-encoding by the `record_encoding_migration` job
+    pub awaiting_reencode: usize,
+    /// Stores that could not be upgraded, with the error
+    pub failed: Vec<(String, String)>,
+    /// Databases with no matching account; left alone
+    pub orphaned: Vec<PathBuf>,
+    pub elapsed: Duration,
+}
+
+impl ActorMigrationReport {
+    pub fn summary(&self) -> String {
+        let mut out = format!(
+            "{} actor store(s): {} upgraded, {} already current, {} failed, {} orphaned in {:.1}s\n",
+            self.discovered,
+            self.upgraded,
+            self.current,
+            self.failed.len(),
+            self.orphaned.len(),
+            self.elapsed.as_secs_f64()
+        );
+        if self.awaiting_reencode > 0 {
+            out.push_str(&format!(
+                "{} store(s) still hold JSON records; the record_encoding_migration job re-encodes them\n",
+                self.awaiting_reencode
+            ));
+        }
+        for (did, error) in &self.failed {
+            out.push_str(&format!("  failed {}: {}\n", did, error));
+        }
+        for path in &self.orphaned {
+            out.push_str(&format!("  orphaned {}\n", path.display()));
+        }
+        out
+    }
+}
+
+/// Upgrade every actor store under `store`'s directory
+///
+/// Databases are matched to accounts in `account_db` by their location;
+/// `concurrency` stores are upgraded at a time.
+pub async fn migrate_all(
+    store: &ActorStore,
+    account_db: &SqlitePool,
+    concurrency: usize,
+) -> PdsResult<ActorMigrationReport> {
+    let started = Instant::now();
+    let mut report = ActorMigrationReport::default();
+
+    let dids: Vec<String> = sqlx::query_scalar("SELECT did FROM account").fetch_all(account_db).await?;
+    let mut by_location: HashMap<PathBuf, String> = dids
+        .into_iter()
+        .map(|did| (store.get_location(&did).db_location, did))
+        .collect();
+
+    let mut targets = Vec::new();
+    for path in store.discover_databases().await? {
+        match by_location.remove(&path) {
+            Some(did) => targets.push(did),
+            None => report.orphaned.push(path),
+        }
+    }
+    report.discovered = targets.len() + report.orphaned.len();
+    let total = targets.len();
+    tracing::info!(stores = total, concurrency, "Starting actor store migration");
+
+    let mut results = stream::iter(targets)
+        .map(|did| async move {
+            let result = store.upgrade_schema(&did).await;
+            (did, result)
+        })
+        .buffer_unordered(concurrency.max(1));
+
+    let mut done = 0;
+    while let Some((did, result)) = results.next().await {
+        done += 1;
+        match result {
+            Ok((changed, version)) => {
+                if changed {
+                    report.upgraded += 1;
+                } else {
+                    report.current += 1;
+                }
+                if version < ACTOR_SCHEMA_VERSION {
+                    report.awaiting_reencode += 1;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(did = %did, error = %e, "actor_store_migration_failed");
+                report.failed.push((did, e.to_string()));
+            }
+        }
+
+        if done % PROGRESS_EVERY == 0 || done == total {
+            tracing::info!(
+                done,
+                total,
+                upgraded = report.upgraded,
+                failed = report.failed.len(),
+                "Actor store migration progress"
+            );
+        }
+    }
+
+    report.failed.sort();
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::ActorStoreConfig;
+    use tempfile::TempDir;
+
+    fn store(dir: &TempDir) -> ActorStore {
+        ActorStore::new(ActorStoreConfig {
+            base_directory: dir.path().to_path_buf(),
+            cache_size: 10,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_migrate_all() {
+        let dir = TempDir::new().unwrap();
+        let account_db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query("CREATE TABLE account (did TEXT PRIMARY KEY)").execute(&account_db).await.unwrap();
+
+        let creator = store(&dir);
+        for did in ["did:plc:current", "did:plc:old", "did:plc:broken", "did:plc:orphan"] {
+            creator.create(did).await.unwrap();
+            if did != "did:plc:orphan" {
+                sqlx::query("INSERT INTO account (did) VALUES (?1)")
+                    .bind(did)
+                    .execute(&account_db)
+                    .await
+                    .unwrap();
+            }
+        }
+
+        // A store from before collection stats, and one that can't be opened
+        let old = creator.open_db("did:plc:old").await.unwrap();
+        sqlx::raw_sql("DROP TRIGGER record_stat_insert; DROP TRIGGER record_stat_delete; DROP TABLE collection_stat; PRAGMA user_version = 1")
+            .execute(&old)
+            .await
+            .unwrap();
+        old.close().await;
+        let broken = creator.get_location("did:plc:broken").db_location;
+        std::fs::write(&broken, b"not a database").unwrap();
+
+        // A fresh store, as after a restart
+        let store = store(&dir);
+        let report = migrate_all(&store, &account_db, 2).await.unwrap();
+        assert_eq!(report.discovered, 4);
+        assert_eq!(report.upgraded, 1);
+        assert_eq!(report.current, 1);
+        assert_eq!(report.awaiting_reencode, 1);
+        assert_eq!(report.failed.len(), 1);
+        assert_eq!(report.failed[0].0, "did:plc:broken");
+        assert_eq!(report.orphaned, vec![creator.get_location("did:plc:orphan").db_location]);
+
+        assert_eq!(store.schema_version("did:plc:old").await.unwrap(), 2);
+        let stats = store.collection_stats("did:plc:old").await.unwrap();
+        assert!(stats.is_empty());
+
+        // Nothing left to do
+        let again = migrate_all(&store, &account_db, 2).await.unwrap();
+        assert_eq!(again.upgraded, 0);
+        assert_eq!(again.current, 2);
+    }
+}
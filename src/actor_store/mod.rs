@@ -3,9 +3,23 @@
 /// Each user (actor) has their own SQLite database containing their repository data.
 /// This module manages the lifecycle and operations on these per-user databases.
 
+pub mod blob_refs;
+pub mod compaction;
+pub mod export;
+pub mod history;
+pub mod hooks;
+pub mod migrations;
 pub mod models;
+pub mod mst;
+pub mod proof;
+pub mod record_codec;
+pub mod repo_index;
 pub mod repository;
 pub mod store;
+#[cfg(test)]
+pub mod test_util;
+pub mod tombstones;
+pub mod verify;
 
 // Re-export commonly used types (allow unused for now as they're part of the public API)
 #[allow(unused_imports)]
@@ -13,7 +27,9 @@ pub use models::*;
 pub use repository::{RepositoryManager, WriteOp};
 #[allow(unused_imports)]
 pub use repository::WriteOpAction;
+pub use repo_index::{CollectionCount, RepoHead, RepoIndex, RepoStats};
 pub use store::{ActorStore, ActorStoreConfig};
+pub use tombstones::{RecordTombstone, TombstoneLog, TombstoneQuery};
 
 use std::path::PathBuf;
 
@@ -36,11 +52,13 @@ pub fn get_actor_location(base_dir: &PathBuf, did: &str) -> ActorLocation {
     let directory = base_dir.join(&shard).join(&safe_did);
     let db_location = directory.join("store.sqlite");
     let key_location = directory.join("key");
+    let signing_key_location = directory.join("signing_key");
 
     ActorLocation {
         directory,
         db_location,
         key_location,
+        signing_key_location,
     }
 }
 
@@ -49,5 +67,8 @@ pub fn get_actor_location(base_dir: &PathBuf, did: &str) -> ActorLocation {
 pub struct ActorLocation {
     pub directory: PathBuf,
     pub db_location: PathBuf,
+    /// Data encryption keys (see `crypto::data_keys`)
     pub key_location: PathBuf,
+    /// Commit signing key reference (see `crypto::signing_keys`)
+    pub signing_key_location: PathBuf,
 }
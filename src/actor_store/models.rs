@@ -96,3 +96,78 @@ pub struct PreparedWrite {
     pub swap_cid: Option<String>,
     pub validate: Option<bool>,
 }
+
+/// A record index change made by a commit
+#[derive(Debug, Clone)]
+pub enum RecordChange {
+    Put {
+        uri: String,
+        cid: String,
+        collection: String,
+        rkey: String,
+        /// Blobs the record references
+        blobs: Vec<String>,
+    },
+    Delete {
+        uri: String,
+    },
+}
+
+/// Blob references kept record versions took over and gave up in a commit,
+/// one entry per reference
+///
+/// Returned by `ActorStore::apply_commit` for the caller to claim and
+/// release in the blob store, as it does for the records themselves.
+#[derive(Debug, Clone, Default)]
+pub struct VersionBlobRefs {
+    /// Blobs referenced by versions kept in the commit
+    pub kept: Vec<String>,
+    /// Blobs referenced by versions dropped beyond the history depth
+    pub pruned: Vec<String>,
+}
+
+/// Everything one commit writes to an actor store
+///
+/// Built up while a commit is prepared and applied with
+/// `ActorStore::apply_commit` in a single transaction.
+#[derive(Debug, Clone, Default)]
+pub struct CommitBatch {
+    /// Head the commit builds on; the batch is rejected if it has moved
+    pub prev: Option<String>,
+    pub blocks: Vec<(String, Vec<u8>)>,
+    /// Record index changes, applied in order
+    pub changes: Vec<RecordChange>,
+    /// Blocks no longer referenced, removed after the record changes
+    pub dropped_blocks: Vec<String>,
+}
+
+impl CommitBatch {
+    pub fn new(prev: Option<String>) -> Self {
+        Self {
+            prev,
+            ..Default::default()
+        }
+    }
+
+    pub fn put_block(&mut self, cid: impl ToString, content: Vec<u8>) {
+        self.blocks.push((cid.to_string(), content));
+    }
+
+    pub fn put_record(&mut self, uri: String, cid: String, collection: &str, rkey: &str, blobs: Vec<String>) {
+        self.changes.push(RecordChange::Put {
+            uri,
+            cid,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            blobs,
+        });
+    }
+
+    pub fn delete_record(&mut self, uri: String) {
+        self.changes.push(RecordChange::Delete { uri });
+    }
+
+    pub fn delete_block(&mut self, cid: String) {
+        self.dropped_blocks.push(cid);
+    }
+}
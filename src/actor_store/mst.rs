@@ -0,0 +1,591 @@
+/// Incremental Merkle Search Tree over the actor store
+///
+/// Repository MSTs are kept as content-addressed nodes in `repo_block`, in
+/// the atproto repository format (`{l, e: [{p, k, v, t}]}` with prefix
+/// compressed keys). A write loads only the nodes on the path to the keys it
+/// touches and records the nodes it creates, so a commit stores just its new
+/// blocks and its cost does not grow with the size of the repository.
+///
+/// Tree shape is canonical: a key's layer is the number of leading zero bit
+/// pairs in the SHA-256 of the key, a node at layer L holds exactly the keys
+/// of layer L in its range, and every non-empty gap between them is a node
+/// at layer L - 1 (possibly with no entries of its own).
+use crate::{
+    actor_store::ActorStore,
+    error::{PdsError, PdsResult},
+};
+use atproto::mst::calculate_key_layer;
+use libipld::{
+    cbor::DagCborCodec,
+    codec::Codec,
+    multihash::{Code, MultihashDigest},
+    Cid, Ipld,
+};
+use std::collections::{BTreeMap, HashMap};
+
+/// DAG-CBOR multicodec
+const DAG_CBOR: u64 = 0x71;
+
+/// CID of a DAG-CBOR block
+pub fn block_cid(bytes: &[u8]) -> Cid {
+    Cid::new_v1(DAG_CBOR, Code::Sha2_256.digest(bytes))
+}
+
+/// Leaf entry of a node, with the subtree to its right
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    pub key: String,
+    pub value: Cid,
+    pub right: Option<Cid>,
+}
+
+/// MST node: a left subtree followed by entries
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Node {
+    pub left: Option<Cid>,
+    pub entries: Vec<Entry>,
+}
+
+impl Node {
+    /// Subtree before entry `i` (`i == entries.len()` is the last gap)
+    fn gap(&self, i: usize) -> Option<Cid> {
+        if i == 0 {
+            self.left
+        } else {
+            self.entries[i - 1].right
+        }
+    }
+
+    fn set_gap(&mut self, i: usize, cid: Option<Cid>) {
+        if i == 0 {
+            self.left = cid;
+        } else {
+            self.entries[i - 1].right = cid;
+        }
+    }
+
+    /// Index of the first entry not less than `key`
+    fn position(&self, key: &str) -> usize {
+        self.entries.partition_point(|e| e.key.as_str() < key)
+    }
+
+    /// Subtrees referenced by this node
+    pub fn children(&self) -> impl Iterator<Item = Cid> + '_ {
+        self.left
+            .into_iter()
+            .chain(self.entries.iter().filter_map(|e| e.right))
+    }
+
+    /// Look up `key` in this node
+    ///
+    /// Returns the value if the key is here, otherwise the subtree that
+    /// would contain it.
+    pub fn find(&self, key: &str) -> Result<Cid, Option<Cid>> {
+        let i = self.position(key);
+        match self.entries.get(i) {
+            Some(entry) if entry.key == key => Ok(entry.value),
+            _ => Err(self.gap(i)),
+        }
+    }
+
+    /// Encode as DAG-CBOR
+    pub fn encode(&self) -> PdsResult<Vec<u8>> {
+        let link = |cid: Option<Cid>| cid.map_or(Ipld::Null, Ipld::Link);
+
+        let mut entries = Vec::with_capacity(self.entries.len());
+        let mut last_key: &[u8] = &[];
+        for entry in &self.entries {
+            let key = entry.key.as_bytes();
+            let prefix = last_key.iter().zip(key).take_while(|(a, b)| a == b).count();
+
+            let mut map = BTreeMap::new();
+            map.insert("p".to_string(), Ipld::Integer(prefix as i128));
+            map.insert("k".to_string(), Ipld::Bytes(key[prefix..].to_vec()));
+            map.insert("v".to_string(), Ipld::Link(entry.value));
+            map.insert("t".to_string(), link(entry.right));
+            entries.push(Ipld::Map(map));
+            last_key = key;
+        }
+
+        let mut map = BTreeMap::new();
+        map.insert("l".to_string(), link(self.left));
+        map.insert("e".to_string(), Ipld::List(entries));
+
+        DagCborCodec
+            .encode(&Ipld::Map(map))
+            .map_err(|e| PdsError::Internal(format!("Failed to encode MST node: {}", e)))
+    }
+
+    /// Decode from DAG-CBOR
+    pub fn decode(bytes: &[u8]) -> PdsResult<Self> {
+        let invalid = |what: &str| PdsError::Internal(format!("Invalid MST node: {}", what));
+        let link = |ipld: Option<&Ipld>| match ipld {
+            Some(Ipld::Link(cid)) => Ok(Some(*cid)),
+            Some(Ipld::Null) | None => Ok(None),
+            _ => Err(invalid("bad subtree link")),
+        };
+
+        let ipld: Ipld = DagCborCodec
+            .decode(bytes)
+            .map_err(|e| PdsError::Internal(format!("Invalid MST node: {}", e)))?;
+        let Ipld::Map(map) = ipld else {
+            return Err(invalid("not a map"));
+        };
+        let Some(Ipld::List(list)) = map.get("e") else {
+            return Err(invalid("missing entries"));
+        };
+
+        let mut entries = Vec::with_capacity(list.len());
+        let mut last_key: Vec<u8> = Vec::new();
+        for entry in list {
+            let Ipld::Map(entry) = entry else {
+                return Err(invalid("entry is not a map"));
+            };
+            let prefix = match entry.get("p") {
+                Some(Ipld::Integer(p)) if *p >= 0 && (*p as usize) <= last_key.len() => *p as usize,
+                _ => return Err(invalid("bad key prefix")),
+            };
+            let Some(Ipld::Bytes(suffix)) = entry.get("k") else {
+                return Err(invalid("bad key"));
+            };
+            let Some(Ipld::Link(value)) = entry.get("v") else {
+                return Err(invalid("bad value"));
+            };
+
+            let mut key = last_key[..prefix].to_vec();
+            key.extend_from_slice(suffix);
+            entries.push(Entry {
+                key: String::from_utf8(key.clone()).map_err(|_| invalid("key is not UTF-8"))?,
+                value: *value,
+                right: link(entry.get("t"))?,
+            });
+            last_key = key;
+        }
+
+        Ok(Node {
+            left: link(map.get("l"))?,
+            entries,
+        })
+    }
+}
+
+/// Why a tree operation could not complete
+enum TreeError {
+    /// The node must be loaded from storage first
+    Missing(Cid),
+    Other(PdsError),
+}
+
+impl From<PdsError> for TreeError {
+    fn from(e: PdsError) -> Self {
+        TreeError::Other(e)
+    }
+}
+
+type TreeResult<T> = Result<T, TreeError>;
+
+/// A repository MST being edited
+///
+/// Operations run against the nodes loaded so far; when one needs a node
+/// that isn't loaded it is fetched and the operation retried, so only the
+/// nodes on the touched paths are ever read.
+pub struct RepoTree<'a> {
+    store: &'a ActorStore,
+    did: &'a str,
+    root: Option<Cid>,
+    /// Layer of `root` (0 for an empty tree)
+    layer: u32,
+    nodes: HashMap<Cid, Node>,
+    /// Encoded nodes created by this edit
+    created: HashMap<Cid, Vec<u8>>,
+}
+
+impl<'a> RepoTree<'a> {
+    /// Start from an empty tree
+    pub fn empty(store: &'a ActorStore, did: &'a str) -> Self {
+        Self {
+            store,
+            did,
+            root: None,
+            layer: 0,
+            nodes: HashMap::new(),
+            created: HashMap::new(),
+        }
+    }
+
+    /// Open the tree rooted at `root`
+    ///
+    /// Returns `None` if the root block is missing or not in the atproto node
+    /// format (repositories committed before incremental storage).
+    pub async fn open(store: &'a ActorStore, did: &'a str, root: Cid) -> PdsResult<Option<Self>> {
+        let Some(bytes) = store.get_block(did, &root.to_string()).await? else {
+            return Ok(None);
+        };
+        let Ok(node) = Node::decode(&bytes) else {
+            return Ok(None);
+        };
+
+        let mut tree = Self::empty(store, did);
+        if node.left.is_none() && node.entries.is_empty() {
+            return Ok(Some(tree));
+        }
+        tree.nodes.insert(root, node);
+        tree.root = Some(root);
+        tree.layer = tree.run(|t| t.layer_of(root)).await?;
+        Ok(Some(tree))
+    }
+
+    /// Build a tree from every (key, value) of a repository
+    pub fn build(
+        store: &'a ActorStore,
+        did: &'a str,
+        entries: impl IntoIterator<Item = (String, Cid)>,
+    ) -> PdsResult<Self> {
+        let mut tree = Self::empty(store, did);
+        for (key, value) in entries {
+            // Every node is created in memory, so nothing can be missing
+            match tree.try_put(&key, value) {
+                Ok(()) => {}
+                Err(TreeError::Other(e)) => return Err(e),
+                Err(TreeError::Missing(cid)) => {
+                    return Err(PdsError::Internal(format!("MST node {} missing during build", cid)))
+                }
+            }
+        }
+        Ok(tree)
+    }
+
+    /// Value stored under `key`
+    pub async fn get(&mut self, key: &str) -> PdsResult<Option<Cid>> {
+        self.run(|t| {
+            let mut cursor = t.root;
+            while let Some(cid) = cursor {
+                match t.node(cid)?.find(key) {
+                    Ok(value) => return Ok(Some(value)),
+                    Err(next) => cursor = next,
+                }
+            }
+            Ok(None)
+        })
+        .await
+    }
+
+    /// Insert or replace `key`
+    pub async fn put(&mut self, key: &str, value: Cid) -> PdsResult<()> {
+        self.run(|t| t.try_put(key, value)).await
+    }
+
+    /// Remove `key` if present
+    pub async fn delete(&mut self, key: &str) -> PdsResult<()> {
+        self.run(|t| {
+            let Some(root) = t.root else { return Ok(()) };
+            let mut root = t.delete_from(Some(root), t.layer, key)?;
+            let mut layer = t.layer;
+
+            // Drop top layers left without entries of their own
+            while let Some(cid) = root {
+                let node = t.node(cid)?;
+                if !node.entries.is_empty() || layer == 0 {
+                    break;
+                }
+                root = node.left;
+                layer -= 1;
+            }
+
+            t.root = root;
+            t.layer = if root.is_some() { layer } else { 0 };
+            Ok(())
+        })
+        .await
+    }
+
+    /// CID of the root node (the commit's `data`)
+    pub fn root_cid(&mut self) -> PdsResult<Cid> {
+        match self.root {
+            Some(cid) => Ok(cid),
+            None => {
+                let bytes = Node::default().encode()?;
+                let cid = block_cid(&bytes);
+                self.created.insert(cid, bytes);
+                Ok(cid)
+            }
+        }
+    }
+
+    /// Nodes created by this edit that are part of the current tree
+    pub fn new_blocks(&mut self) -> PdsResult<Vec<(Cid, Vec<u8>)>> {
+        let mut blocks = Vec::new();
+        let mut stack = vec![self.root_cid()?];
+
+        while let Some(cid) = stack.pop() {
+            // Anything not created here is unchanged, along with its subtree
+            let Some(bytes) = self.created.get(&cid) else { continue };
+            if let Some(node) = self.nodes.get(&cid) {
+                stack.extend(node.children());
+            }
+            blocks.push((cid, bytes.clone()));
+        }
+
+        Ok(blocks)
+    }
+
+    /// Run an operation, loading missing nodes until it completes
+    async fn run<T>(&mut self, mut op: impl FnMut(&mut Self) -> TreeResult<T>) -> PdsResult<T> {
+        loop {
+            match op(self) {
+                Ok(value) => return Ok(value),
+                Err(TreeError::Other(e)) => return Err(e),
+                Err(TreeError::Missing(cid)) => {
+                    let bytes = self
+                        .store
+                        .get_block(self.did, &cid.to_string())
+                        .await?
+                        .ok_or_else(|| PdsError::Internal(format!("MST node {} missing", cid)))?;
+                    self.nodes.insert(cid, Node::decode(&bytes)?);
+                }
+            }
+        }
+    }
+
+    fn node(&self, cid: Cid) -> TreeResult<&Node> {
+        self.nodes.get(&cid).ok_or(TreeError::Missing(cid))
+    }
+
+    /// Store a node, returning `None` for an empty one
+    fn save(&mut self, node: Node) -> TreeResult<Option<Cid>> {
+        if node.left.is_none() && node.entries.is_empty() {
+            return Ok(None);
+        }
+        let bytes = node.encode()?;
+        let cid = block_cid(&bytes);
+        self.created.insert(cid, bytes);
+        self.nodes.insert(cid, node);
+        Ok(Some(cid))
+    }
+
+    fn layer_of(&self, cid: Cid) -> TreeResult<u32> {
+        let node = self.node(cid)?;
+        match (node.entries.first(), node.left) {
+            (Some(entry), _) => Ok(calculate_key_layer(&entry.key)),
+            (None, Some(left)) => Ok(self.layer_of(left)? + 1),
+            (None, None) => Ok(0),
+        }
+    }
+
+    fn try_put(&mut self, key: &str, value: Cid) -> TreeResult<()> {
+        let key_layer = calculate_key_layer(key);
+        let mut root = self.root;
+        let mut layer = self.layer;
+
+        if root.is_none() {
+            layer = key_layer;
+        }
+        // Grow the tree until the key's layer exists
+        while layer < key_layer {
+            root = self.save(Node {
+                left: root,
+                entries: Vec::new(),
+            })?;
+            layer += 1;
+        }
+
+        let root = self.put_into(root, layer, key, key_layer, value)?;
+        self.root = Some(root);
+        self.layer = layer;
+        Ok(())
+    }
+
+    fn put_into(
+        &mut self,
+        tree: Option<Cid>,
+        layer: u32,
+        key: &str,
+        key_layer: u32,
+        value: Cid,
+    ) -> TreeResult<Cid> {
+        let mut node = match tree {
+            Some(cid) => self.node(cid)?.clone(),
+            None => Node::default(),
+        };
+        let i = node.position(key);
+
+        if key_layer == layer {
+            match node.entries.get_mut(i) {
+                Some(entry) if entry.key == key => entry.value = value,
+                _ => {
+                    let (lower, upper) = self.split(node.gap(i), layer.saturating_sub(1), key)?;
+                    node.set_gap(i, lower);
+                    node.entries.insert(
+                        i,
+                        Entry {
+                            key: key.to_string(),
+                            value,
+                            right: upper,
+                        },
+                    );
+                }
+            }
+        } else {
+            let child = self.put_into(node.gap(i), layer - 1, key, key_layer, value)?;
+            node.set_gap(i, Some(child));
+        }
+
+        Ok(self.save(node)?.expect("node has an entry or subtree"))
+    }
+
+    /// Split a subtree into the keys below and above `key` (which it must not contain)
+    fn split(&mut self, tree: Option<Cid>, layer: u32, key: &str) -> TreeResult<(Option<Cid>, Option<Cid>)> {
+        let Some(cid) = tree else { return Ok((None, None)) };
+        let node = self.node(cid)?.clone();
+        let i = node.position(key);
+        let (lower, upper) = self.split(node.gap(i), layer.saturating_sub(1), key)?;
+
+        let mut left = Node {
+            left: node.left,
+            entries: node.entries[..i].to_vec(),
+        };
+        left.set_gap(i, lower);
+        let right = Node {
+            left: upper,
+            entries: node.entries[i..].to_vec(),
+        };
+
+        Ok((self.save(left)?, self.save(right)?))
+    }
+
+    /// Join two adjacent subtrees of the same layer (all of `a` before `b`)
+    fn merge(&mut self, a: Option<Cid>, b: Option<Cid>, layer: u32) -> TreeResult<Option<Cid>> {
+        let (a, b) = match (a, b) {
+            (None, other) | (other, None) => return Ok(other),
+            (Some(a), Some(b)) => (self.node(a)?.clone(), self.node(b)?.clone()),
+        };
+
+        let last = a.entries.len();
+        let middle = self.merge(a.gap(last), b.left, layer.saturating_sub(1))?;
+        let mut merged = a;
+        merged.set_gap(last, middle);
+        merged.entries.extend(b.entries);
+
+        self.save(merged)
+    }
+
+    fn delete_from(&mut self, tree: Option<Cid>, layer: u32, key: &str) -> TreeResult<Option<Cid>> {
+        let Some(cid) = tree else { return Ok(None) };
+        let mut node = self.node(cid)?.clone();
+        let i = node.position(key);
+
+        match node.entries.get(i) {
+            Some(entry) if entry.key == key => {
+                let removed = node.entries.remove(i);
+                let merged = self.merge(node.gap(i), removed.right, layer.saturating_sub(1))?;
+                node.set_gap(i, merged);
+            }
+            _ => {
+                let gap = node.gap(i);
+                if gap.is_none() {
+                    return Ok(Some(cid));
+                }
+                let child = self.delete_from(gap, layer.saturating_sub(1), key)?;
+                if child == gap {
+                    return Ok(Some(cid));
+                }
+                node.set_gap(i, child);
+            }
+        }
+
+        self.save(node)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::test_util::temp_store;
+
+    fn keys(n: usize) -> Vec<(String, Cid)> {
+        (0..n)
+            .map(|i| {
+                let key = format!("app.bsky.feed.post/3k{:06}", i);
+                let value = block_cid(key.as_bytes());
+                (key, value)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_node_roundtrip() {
+        let value = block_cid(b"record");
+        let node = Node {
+            left: Some(block_cid(b"left")),
+            entries: vec![
+                Entry {
+                    key: "app.bsky.feed.post/3k2a".to_string(),
+                    value,
+                    right: None,
+                },
+                Entry {
+                    key: "app.bsky.feed.post/3k2b".to_string(),
+                    value,
+                    right: Some(block_cid(b"right")),
+                },
+            ],
+        };
+        assert_eq!(Node::decode(&node.encode().unwrap()).unwrap(), node);
+    }
+
+    #[test]
+    fn test_empty_root_is_canonical() {
+        // The well-known CID of an empty repository's MST root
+        let (_dir, store) = temp_store();
+        let mut tree = RepoTree::empty(&store, "did:plc:mst");
+        assert_eq!(
+            tree.root_cid().unwrap().to_string(),
+            "bafyreie5737gdxlw5i64vzichcalba3z2v5n6icifvx5xytvske7mr3hpm"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_shape_is_independent_of_history() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:mst";
+        let all = keys(300);
+
+        let mut forward = RepoTree::build(&store, did, all.clone()).unwrap();
+        let mut backward = RepoTree::build(&store, did, all.iter().rev().cloned()).unwrap();
+        assert_eq!(forward.root_cid().unwrap(), backward.root_cid().unwrap());
+
+        // Deleting keys gives the same tree as never inserting them
+        for (key, _) in all.iter().skip(100) {
+            forward.delete(key).await.unwrap();
+        }
+        let mut expected = RepoTree::build(&store, did, all[..100].to_vec()).unwrap();
+        assert_eq!(forward.root_cid().unwrap(), expected.root_cid().unwrap());
+
+        for (key, value) in &all[..100] {
+            assert_eq!(forward.get(key).await.unwrap(), Some(*value));
+        }
+        assert_eq!(forward.get(&all[200].0).await.unwrap(), None);
+
+        for (key, _) in &all[..100] {
+            forward.delete(key).await.unwrap();
+        }
+        let mut empty = RepoTree::empty(&store, did);
+        assert_eq!(forward.root_cid().unwrap(), empty.root_cid().unwrap());
+    }
+
+    #[test]
+    fn test_new_blocks_cover_only_changes() {
+        let (_dir, store) = temp_store();
+        let mut tree = RepoTree::build(&store, "did:plc:mst", keys(200)).unwrap();
+        let all = tree.new_blocks().unwrap();
+
+        // Reopening with every node loaded but none created
+        tree.created.clear();
+        assert!(tree.try_put("app.bsky.feed.post/3kzzzzzz", block_cid(b"new")).is_ok());
+        let changed = tree.new_blocks().unwrap();
+
+        assert!(!changed.is_empty());
+        assert!(changed.len() < all.len());
+        assert_eq!(changed[0].0, tree.root_cid().unwrap());
+    }
+}
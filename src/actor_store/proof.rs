@@ -0,0 +1,165 @@
+/// Record inclusion proofs for com.atproto.sync.getRecord
+///
+/// A proof is the set of blocks a verifier needs to check a record against
+/// the signed head commit: the commit, every MST node on the path from the
+/// root to the record's key, and the record itself. When the record does not
+/// exist, the path nodes alone show that the key is absent.
+///
+/// Commit and MST node blocks are written to `repo_block` on every commit
+/// (see `RepoTree`).
+use crate::{
+    actor_store::{mst::Node, ActorStore},
+    error::{PdsError, PdsResult},
+};
+use atproto::mst::MstNode;
+use libipld::{cbor::DagCborCodec, codec::Codec, Cid, Ipld};
+use std::str::FromStr;
+
+/// Inclusion (or exclusion) proof for a record
+pub struct RecordProof {
+    /// Head commit CID (CAR root)
+    pub commit: Cid,
+    /// Commit, MST path nodes and (if present) the record block
+    pub blocks: Vec<(Cid, Vec<u8>)>,
+    /// CID of the record, if it exists
+    pub record_cid: Option<Cid>,
+}
+
+/// Build a proof for `collection/rkey` against the current head commit
+pub async fn record_proof(
+    store: &ActorStore,
+    did: &str,
+    collection: &str,
+    rkey: &str,
+) -> PdsResult<RecordProof> {
+    let root = store.get_repo_root(did).await?;
+    let commit_cid = Cid::from_str(&root.cid)
+        .map_err(|e| PdsError::Internal(format!("Invalid root CID: {}", e)))?;
+
+    let commit_bytes = load_block(store, did, &commit_cid).await?.ok_or_else(|| {
+        PdsError::NotFound(format!(
+            "Commit block {} is not available; proofs are served for commits made after block persistence was enabled",
+            commit_cid
+        ))
+    })?;
+    let data = commit_data(&commit_bytes)?;
+
+    let key = format!("{}/{}", collection, rkey);
+    let mut blocks = vec![(commit_cid, commit_bytes)];
+    let (path, record_cid) = find_key_path(store, did, &data, &key).await?;
+    blocks.extend(path);
+
+    if let Some(cid) = &record_cid {
+        let record = load_block(store, did, cid)
+            .await?
+            .ok_or_else(|| PdsError::Internal(format!("Record block {} missing", cid)))?;
+        blocks.push((*cid, record));
+    }
+
+    Ok(RecordProof {
+        commit: commit_cid,
+        blocks,
+        record_cid,
+    })
+}
+
+async fn load_block(store: &ActorStore, did: &str, cid: &Cid) -> PdsResult<Option<Vec<u8>>> {
+    store.get_block(did, &cid.to_string()).await
+}
+
+/// Extract the MST root CID from a signed commit block
+pub(crate) fn commit_data(bytes: &[u8]) -> PdsResult<Cid> {
+    let ipld: Ipld = DagCborCodec
+        .decode(bytes)
+        .map_err(|e| PdsError::Internal(format!("Invalid commit block: {}", e)))?;
+
+    match ipld {
+        Ipld::Map(map) => match map.get("data") {
+            Some(Ipld::Link(cid)) => Ok(*cid),
+            _ => Err(PdsError::Internal("Commit missing data".to_string())),
+        },
+        _ => Err(PdsError::Internal("Commit block is not a map".to_string())),
+    }
+}
+
+/// Find the chain of MST nodes leading to `key`
+///
+/// Returns the nodes on the path (root first) and the value CID if the key
+/// exists. For a missing key, the path ends at the node whose gap would hold it.
+async fn find_key_path(
+    store: &ActorStore,
+    did: &str,
+    root: &Cid,
+    key: &str,
+) -> PdsResult<(Vec<(Cid, Vec<u8>)>, Option<Cid>)> {
+    let mut path: Vec<(Cid, Vec<u8>)> = Vec::new();
+    let mut cursor = Some(*root);
+
+    while let Some(cid) = cursor {
+        let bytes = load_block(store, did, &cid)
+            .await?
+            .ok_or_else(|| PdsError::NotFound(format!("MST node {} is not available", cid)))?;
+
+        let next = match Node::decode(&bytes) {
+            Ok(node) => node.find(key),
+            // Heads committed before incremental storage use a single flat node
+            Err(_) if path.is_empty() => {
+                let node = MstNode::from_cbor(&bytes)
+                    .map_err(|e| PdsError::Internal(format!("Invalid MST node {}: {}", cid, e)))?;
+                node.entries
+                    .iter()
+                    .find(|e| e.key == key)
+                    .map(|e| e.value_cid)
+                    .ok_or(None)
+            }
+            Err(e) => return Err(e),
+        };
+        path.push((cid, bytes));
+
+        match next {
+            Ok(value) => return Ok((path, Some(value))),
+            Err(subtree) => cursor = subtree,
+        }
+    }
+
+    Ok((path, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::{
+        mst::{block_cid, RepoTree},
+        test_util::temp_store,
+    };
+
+    #[tokio::test]
+    async fn test_record_proof_path() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:prooftest";
+        store.create(did).await.unwrap();
+
+        let entries: Vec<(String, Cid)> = (0..100)
+            .map(|i| {
+                let key = format!("app.bsky.feed.post/3k{:06}", i);
+                (key.clone(), block_cid(key.as_bytes()))
+            })
+            .collect();
+        let mut tree = RepoTree::build(&store, did, entries.clone()).unwrap();
+        let root = tree.root_cid().unwrap();
+        for (cid, bytes) in tree.new_blocks().unwrap() {
+            store.put_block(did, &cid.to_string(), &bytes).await.unwrap();
+        }
+
+        let (key, value) = &entries[42];
+        let (path, found) = find_key_path(&store, did, &root, key).await.unwrap();
+        assert_eq!(found, Some(*value));
+        assert_eq!(path[0].0, root);
+
+        let (path, found) = find_key_path(&store, did, &root, "app.bsky.feed.post/3kzzzzzz")
+            .await
+            .unwrap();
+        assert_eq!(found, None);
+        assert!(!path.is_empty());
+    }
+}
@@ -0,0 +1,164 @@
+/// Record block encoding
+///
+/// Records are stored as DAG-CBOR blocks, as ATProto repositories require,
+/// and converted from and to the JSON data model at the API edge: a
+/// `{"$link": cid}` object is a CID link and a `{"$bytes": base64}` object is
+/// a byte string. The data model has no floats, so they are rejected.
+///
+/// Blocks written before records were stored as DAG-CBOR hold JSON. They are
+/// still read until the `record_encoding_migration` job re-encodes them.
+use crate::error::{PdsError, PdsResult};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use libipld::{cbor::DagCborCodec, codec::Codec, Cid, Ipld};
+use serde_json::{Map, Number, Value};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// Encode a JSON record as canonical DAG-CBOR
+pub fn encode_record(value: &Value) -> PdsResult<Vec<u8>> {
+    if !value.is_object() {
+        return Err(PdsError::Validation("Record must be an object".to_string()));
+    }
+    DagCborCodec
+        .encode(&json_to_ipld(value)?)
+        .map_err(|e| PdsError::Internal(format!("Failed to encode record: {}", e)))
+}
+
+/// Decode a record block into JSON
+pub fn decode_record(bytes: &[u8]) -> PdsResult<Value> {
+    match DagCborCodec.decode::<Ipld>(bytes) {
+        Ok(ipld @ Ipld::Map(_)) => Ok(ipld_to_json(&ipld)),
+        _ => legacy_json(bytes).ok_or_else(|| PdsError::Internal("Record block is not DAG-CBOR".to_string())),
+    }
+}
+
+/// The record, if the block is a legacy JSON record
+pub fn legacy_json(bytes: &[u8]) -> Option<Value> {
+    if matches!(DagCborCodec.decode::<Ipld>(bytes), Ok(Ipld::Map(_))) {
+        return None;
+    }
+    serde_json::from_slice::<Value>(bytes).ok().filter(Value::is_object)
+}
+
+fn json_to_ipld(value: &Value) -> PdsResult<Ipld> {
+    Ok(match value {
+        Value::Null => Ipld::Null,
+        Value::Bool(b) => Ipld::Bool(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => Ipld::Integer(i.into()),
+            None => match n.as_u64() {
+                Some(u) => Ipld::Integer(u.into()),
+                None => {
+                    return Err(PdsError::Validation(format!(
+                        "Floats are not allowed in records: {}",
+                        n
+                    )))
+                }
+            },
+        },
+        Value::String(s) => Ipld::String(s.clone()),
+        Value::Array(items) => Ipld::List(items.iter().map(json_to_ipld).collect::<PdsResult<_>>()?),
+        Value::Object(map) => {
+            if let Some(link) = single_key(map, "$link") {
+                let cid = link
+                    .as_str()
+                    .and_then(|s| Cid::from_str(s).ok())
+                    .ok_or_else(|| PdsError::Validation(format!("Invalid $link: {}", link)))?;
+                return Ok(Ipld::Link(cid));
+            }
+            if let Some(bytes) = single_key(map, "$bytes") {
+                let data = bytes
+                    .as_str()
+                    .and_then(|s| STANDARD_NO_PAD.decode(s.trim_end_matches('=')).ok())
+                    .ok_or_else(|| PdsError::Validation("Invalid $bytes".to_string()))?;
+                return Ok(Ipld::Bytes(data));
+            }
+            Ipld::Map(
+                map.iter()
+                    .map(|(k, v)| Ok((k.clone(), json_to_ipld(v)?)))
+                    .collect::<PdsResult<BTreeMap<_, _>>>()?,
+            )
+        }
+    })
+}
+
+fn single_key<'a>(map: &'a Map<String, Value>, key: &str) -> Option<&'a Value> {
+    if map.len() == 1 {
+        map.get(key)
+    } else {
+        None
+    }
+}
+
+fn ipld_to_json(ipld: &Ipld) -> Value {
+    match ipld {
+        Ipld::Null => Value::Null,
+        Ipld::Bool(b) => Value::Bool(*b),
+        Ipld::Integer(i) => i64::try_from(*i)
+            .map(Value::from)
+            .or_else(|_| u64::try_from(*i).map(Value::from))
+            .unwrap_or(Value::Null),
+        Ipld::Float(f) => Number::from_f64(*f).map(Value::Number).unwrap_or(Value::Null),
+        Ipld::String(s) => Value::String(s.clone()),
+        Ipld::Bytes(bytes) => serde_json::json!({ "$bytes": STANDARD_NO_PAD.encode(bytes) }),
+        Ipld::List(items) => Value::Array(items.iter().map(ipld_to_json).collect()),
+        Ipld::Map(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), ipld_to_json(v))).collect()),
+        Ipld::Link(cid) => serde_json::json!({ "$link": cid.to_string() }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::mst::block_cid;
+
+    #[test]
+    fn test_round_trip() {
+        let blob = block_cid(b"image").to_string();
+        let record = serde_json::json!({
+            "$type": "app.bsky.feed.post",
+            "text": "hello",
+            "langs": ["en"],
+            "count": -3,
+            "reply": null,
+            "embed": {
+                "$type": "app.bsky.embed.images",
+                "images": [{
+                    "alt": "",
+                    "image": {"$type": "blob", "ref": {"$link": blob}, "mimeType": "image/png", "size": 1024},
+                }],
+            },
+            "sig": {"$bytes": "AQID"},
+        });
+
+        let bytes = encode_record(&record).unwrap();
+        assert_eq!(decode_record(&bytes).unwrap(), record);
+
+        // Links are real CBOR links, and key order doesn't change the bytes
+        let ipld: Ipld = DagCborCodec.decode(&bytes).unwrap();
+        let Ipld::Map(map) = &ipld else { panic!("not a map") };
+        assert!(matches!(map.get("sig"), Some(Ipld::Bytes(b)) if b == &vec![1, 2, 3]));
+        let reordered: Value = serde_json::from_str(r#"{"text":"a","$type":"t"}"#).unwrap();
+        let ordered: Value = serde_json::from_str(r#"{"$type":"t","text":"a"}"#).unwrap();
+        assert_eq!(encode_record(&reordered).unwrap(), encode_record(&ordered).unwrap());
+    }
+
+    #[test]
+    fn test_rejects_invalid_records() {
+        assert!(matches!(encode_record(&serde_json::json!({"n": 1.5})), Err(PdsError::Validation(_))));
+        assert!(matches!(encode_record(&serde_json::json!({"l": {"$link": "nope"}})), Err(PdsError::Validation(_))));
+        assert!(matches!(encode_record(&serde_json::json!([1])), Err(PdsError::Validation(_))));
+    }
+
+    #[test]
+    fn test_legacy_json_blocks() {
+        let record = serde_json::json!({"text": "old"});
+        let json = serde_json::to_vec(&record).unwrap();
+        assert_eq!(legacy_json(&json), Some(record.clone()));
+        assert_eq!(decode_record(&json).unwrap(), record);
+
+        let cbor = encode_record(&record).unwrap();
+        assert_eq!(legacy_json(&cbor), None);
+        assert!(decode_record(b"\xff\x00").is_err());
+    }
+}
@@ -0,0 +1,457 @@
+/// Central index of repository heads
+///
+/// Each repo's current commit lives in its own actor database, which makes
+/// server-wide listings (com.atproto.sync.listRepos) expensive. The actor store
+/// mirrors every repo_root change into the `repo_head` table in the account
+/// database so these can be served with a single indexed query.
+///
+/// Per-collection record counts are mirrored the same way (from each actor
+/// store's trigger-maintained `collection_stat` table) for repo statistics.
+use crate::error::PdsResult;
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+
+/// Repository head with the hosting account's status
+#[derive(Debug, Clone)]
+pub struct RepoHead {
+    pub did: String,
+    pub cid: String,
+    pub rev: String,
+    /// "takendown", "deactivated", or None when active
+    pub status: Option<String>,
+}
+
+impl RepoHead {
+    pub fn active(&self) -> bool {
+        self.status.is_none()
+    }
+
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Self {
+        let taken_down: bool = row.get("taken_down");
+        let deactivated: bool = row.get("deactivated");
+
+        let status = if taken_down {
+            Some("takendown".to_string())
+        } else if deactivated {
+            Some("deactivated".to_string())
+        } else {
+            None
+        };
+
+        Self {
+            did: row.get("did"),
+            cid: row.get("cid"),
+            rev: row.get("rev"),
+            status,
+        }
+    }
+}
+
+/// Record count for one collection
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionCount {
+    pub collection: String,
+    pub count: i64,
+}
+
+/// Record counts for one repository, or summed over all of them
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepoStats {
+    /// Repositories counted
+    pub repos: i64,
+    pub records: i64,
+    /// Ordered by collection NSID
+    pub collections: Vec<CollectionCount>,
+}
+
+/// Repo head index (account database)
+#[derive(Clone)]
+pub struct RepoIndex {
+    db: SqlitePool,
+}
+
+impl RepoIndex {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Record the current head of a repository
+    pub async fn upsert(&self, did: &str, cid: &str, rev: &str) -> PdsResult<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO repo_head (did, cid, rev, updated_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(did) DO UPDATE SET
+                cid = excluded.cid,
+                rev = excluded.rev,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(did)
+        .bind(cid)
+        .bind(rev)
+        .bind(chrono::Utc::now().to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Remove a repository (and its record counts) from the index
+    pub async fn remove(&self, did: &str) -> PdsResult<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query("DELETE FROM repo_head WHERE did = ?1")
+            .bind(did)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM repo_collection_stat WHERE did = ?1")
+            .bind(did)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Replace a repository's per-collection record counts
+    pub async fn set_stats(&self, did: &str, collections: &[CollectionCount]) -> PdsResult<()> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query("DELETE FROM repo_collection_stat WHERE did = ?1")
+            .bind(did)
+            .execute(&mut *tx)
+            .await?;
+        for entry in collections.iter().filter(|c| c.count > 0) {
+            sqlx::query(
+                "INSERT INTO repo_collection_stat (did, collection, record_count) VALUES (?1, ?2, ?3)",
+            )
+            .bind(did)
+            .bind(&entry.collection)
+            .bind(entry.count)
+            .execute(&mut *tx)
+            .await?;
+        }
+        let total: i64 = collections.iter().map(|c| c.count).sum();
+        sqlx::query("UPDATE repo_head SET record_count = ?1 WHERE did = ?2")
+            .bind(total)
+            .bind(did)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Record counts of one repository, if it has been summarized
+    pub async fn stats(&self, did: &str) -> PdsResult<Option<RepoStats>> {
+        let total: Option<Option<i64>> =
+            sqlx::query_scalar("SELECT record_count FROM repo_head WHERE did = ?1")
+                .bind(did)
+                .fetch_optional(&self.db)
+                .await?;
+        let Some(Some(records)) = total else {
+            return Ok(None);
+        };
+
+        let rows = sqlx::query(
+            "SELECT collection, record_count FROM repo_collection_stat WHERE did = ?1 ORDER BY collection",
+        )
+        .bind(did)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(Some(RepoStats {
+            repos: 1,
+            records,
+            collections: rows.iter().map(collection_count).collect(),
+        }))
+    }
+
+    /// Record counts summed over every indexed repository
+    pub async fn global_stats(&self) -> PdsResult<RepoStats> {
+        let rows = sqlx::query(
+            r#"
+            SELECT collection, SUM(record_count) AS record_count
+            FROM repo_collection_stat
+            GROUP BY collection
+            ORDER BY collection
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let repos: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM repo_head")
+            .fetch_one(&self.db)
+            .await?;
+
+        let collections: Vec<CollectionCount> = rows.iter().map(collection_count).collect();
+        Ok(RepoStats {
+            repos,
+            records: collections.iter().map(|c| c.count).sum(),
+            collections,
+        })
+    }
+
+    /// Records in one collection across every indexed repository
+    pub async fn collection_total(&self, collection: &str) -> PdsResult<i64> {
+        let total: Option<i64> = sqlx::query_scalar(
+            "SELECT SUM(record_count) FROM repo_collection_stat WHERE collection = ?1",
+        )
+        .bind(collection)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(total.unwrap_or(0))
+    }
+
+    /// Indexed repositories whose record counts have not been mirrored yet
+    pub async fn unsummarized_dids(&self) -> PdsResult<Vec<String>> {
+        let dids = sqlx::query_scalar("SELECT did FROM repo_head WHERE record_count IS NULL")
+            .fetch_all(&self.db)
+            .await?;
+
+        Ok(dids)
+    }
+
+    /// Get the head of a hosted repository
+    pub async fn get(&self, did: &str) -> PdsResult<Option<RepoHead>> {
+        let row = sqlx::query(
+            r#"
+            SELECT h.did, h.cid, h.rev, a.taken_down, a.deactivated_at IS NOT NULL AS deactivated
+            FROM repo_head h
+            JOIN account a ON a.did = h.did
+            WHERE h.did = ?1
+            "#,
+        )
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(row.as_ref().map(RepoHead::from_row))
+    }
+
+    /// List hosted repositories ordered by DID, starting after `cursor`
+    pub async fn list(&self, cursor: Option<&str>, limit: i64) -> PdsResult<Vec<RepoHead>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT h.did, h.cid, h.rev, a.taken_down, a.deactivated_at IS NOT NULL AS deactivated
+            FROM repo_head h
+            JOIN account a ON a.did = h.did
+            WHERE h.did > ?1
+            ORDER BY h.did
+            LIMIT ?2
+            "#,
+        )
+        .bind(cursor.unwrap_or(""))
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows.iter().map(RepoHead::from_row).collect())
+    }
+
+    /// Active hosted repositories with records in `collection`, ordered by
+    /// DID, starting after `cursor`, with their record counts
+    pub async fn list_by_collection(
+        &self,
+        collection: &str,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> PdsResult<Vec<(String, i64)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT s.did, s.record_count
+            FROM repo_collection_stat s
+            JOIN account a ON a.did = s.did
+            WHERE s.collection = ?1 AND s.did > ?2
+              AND a.taken_down = 0 AND a.deactivated_at IS NULL
+            ORDER BY s.did
+            LIMIT ?3
+            "#,
+        )
+        .bind(collection)
+        .bind(cursor.unwrap_or(""))
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(rows.iter().map(|row| (row.get("did"), row.get("record_count"))).collect())
+    }
+
+    /// Active hosted repositories with records in `collection`
+    pub async fn count_by_collection(&self, collection: &str) -> PdsResult<i64> {
+        let count = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM repo_collection_stat s
+            JOIN account a ON a.did = s.did
+            WHERE s.collection = ?1 AND a.taken_down = 0 AND a.deactivated_at IS NULL
+            "#,
+        )
+        .bind(collection)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// Hosted accounts that have no indexed head yet
+    pub async fn unindexed_dids(&self) -> PdsResult<Vec<String>> {
+        let dids = sqlx::query_scalar(
+            "SELECT a.did FROM account a LEFT JOIN repo_head h ON h.did = a.did WHERE h.did IS NULL",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        Ok(dids)
+    }
+}
+
+fn collection_count(row: &sqlx::sqlite::SqliteRow) -> CollectionCount {
+    CollectionCount {
+        collection: row.get("collection"),
+        count: row.get("record_count"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_test_index() -> RepoIndex {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE account (
+                did TEXT PRIMARY KEY,
+                taken_down INTEGER NOT NULL DEFAULT 0,
+                deactivated_at TEXT
+            );
+            CREATE TABLE repo_head (
+                did TEXT PRIMARY KEY NOT NULL,
+                cid TEXT NOT NULL,
+                rev TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                record_count INTEGER
+            );
+            CREATE TABLE repo_collection_stat (
+                did TEXT NOT NULL,
+                collection TEXT NOT NULL,
+                record_count INTEGER NOT NULL,
+                PRIMARY KEY (did, collection)
+            );
+            INSERT INTO account (did, taken_down, deactivated_at) VALUES
+                ('did:plc:a', 0, NULL),
+                ('did:plc:b', 1, NULL),
+                ('did:plc:c', 0, '2025-01-01T00:00:00Z'),
+                ('did:plc:d', 0, NULL);
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        RepoIndex::new(db)
+    }
+
+    #[tokio::test]
+    async fn test_list_and_status() {
+        let index = create_test_index().await;
+        for did in ["did:plc:a", "did:plc:b", "did:plc:c"] {
+            index.upsert(did, "bafyold", "rev1").await.unwrap();
+        }
+        index.upsert("did:plc:a", "bafynew", "rev2").await.unwrap();
+        // Not a hosted account (e.g. a mirrored repo)
+        index.upsert("did:plc:mirror", "bafymirror", "rev1").await.unwrap();
+
+        let page = index.list(None, 2).await.unwrap();
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0].did, "did:plc:a");
+        assert_eq!(page[0].cid, "bafynew");
+        assert!(page[0].active());
+        assert_eq!(page[1].status.as_deref(), Some("takendown"));
+
+        let rest = index.list(Some("did:plc:b"), 2).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        assert_eq!(rest[0].status.as_deref(), Some("deactivated"));
+
+        assert_eq!(index.unindexed_dids().await.unwrap(), vec!["did:plc:d".to_string()]);
+
+        index.remove("did:plc:a").await.unwrap();
+        assert!(index.get("did:plc:a").await.unwrap().is_none());
+    }
+
+    fn count(collection: &str, count: i64) -> CollectionCount {
+        CollectionCount {
+            collection: collection.to_string(),
+            count,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_repo_stats() {
+        let index = create_test_index().await;
+        index.upsert("did:plc:a", "bafya", "rev1").await.unwrap();
+        index.upsert("did:plc:b", "bafyb", "rev1").await.unwrap();
+
+        assert!(index.stats("did:plc:a").await.unwrap().is_none());
+        assert_eq!(index.unsummarized_dids().await.unwrap().len(), 2);
+
+        index
+            .set_stats("did:plc:a", &[count("app.bsky.feed.post", 3), count("app.bsky.graph.follow", 2)])
+            .await
+            .unwrap();
+        index
+            .set_stats("did:plc:b", &[count("app.bsky.feed.post", 4), count("app.bsky.feed.like", 0)])
+            .await
+            .unwrap();
+
+        let a = index.stats("did:plc:a").await.unwrap().unwrap();
+        assert_eq!(a.records, 5);
+        assert_eq!(a.collections[0], count("app.bsky.feed.post", 3));
+
+        // Empty collections are not stored
+        let b = index.stats("did:plc:b").await.unwrap().unwrap();
+        assert_eq!(b.collections, vec![count("app.bsky.feed.post", 4)]);
+
+        let global = index.global_stats().await.unwrap();
+        assert_eq!(global.repos, 2);
+        assert_eq!(global.records, 9);
+        assert_eq!(
+            global.collections,
+            vec![count("app.bsky.feed.post", 7), count("app.bsky.graph.follow", 2)]
+        );
+        assert_eq!(index.collection_total("app.bsky.feed.post").await.unwrap(), 7);
+        assert!(index.unsummarized_dids().await.unwrap().is_empty());
+
+        index.remove("did:plc:a").await.unwrap();
+        assert_eq!(index.collection_total("app.bsky.feed.post").await.unwrap(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_list_by_collection() {
+        let index = create_test_index().await;
+        let generator = "app.bsky.feed.generator";
+        for did in ["did:plc:a", "did:plc:b", "did:plc:c", "did:plc:d"] {
+            index.upsert(did, "bafy", "rev1").await.unwrap();
+            index.set_stats(did, &[count(generator, 2), count("app.bsky.feed.post", 1)]).await.unwrap();
+        }
+        index.set_stats("did:plc:d", &[count(generator, 1)]).await.unwrap();
+        index.set_stats("did:plc:a", &[count("app.bsky.feed.post", 5)]).await.unwrap();
+
+        // Taken down (b) and deactivated (c) accounts are left out
+        assert_eq!(index.count_by_collection(generator).await.unwrap(), 1);
+        assert_eq!(
+            index.list_by_collection(generator, None, 10).await.unwrap(),
+            vec![("did:plc:d".to_string(), 1)]
+        );
+        assert_eq!(index.count_by_collection("app.bsky.feed.post").await.unwrap(), 1);
+
+        let page = index.list_by_collection("app.bsky.feed.post", None, 1).await.unwrap();
+        assert_eq!(page, vec![("did:plc:a".to_string(), 5)]);
+        assert!(index
+            .list_by_collection("app.bsky.feed.post", Some("did:plc:a"), 1)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+}
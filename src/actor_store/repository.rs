@@ -1,22 +1,42 @@
 /// Repository manager - integrates SDK MST with persistent storage
 ///
 /// This module bridges the SDK's in-memory MST implementation with
-/// our SQLite-based persistent storage system.
+/// our SQLite-based persistent storage system. Writes go through the
+/// incremental `RepoTree`, so a commit only touches the MST nodes on the
+/// paths to the changed records.
 
 use crate::{
-    actor_store::ActorStore,
+    actor_store::{
+        blob_refs::find_blob_refs,
+        hooks::WriteHooks,
+        models::{CommitBatch, RecordChange, VersionBlobRefs},
+        mst::{block_cid, RepoTree},
+        proof,
+        record_codec::{decode_record, encode_record, legacy_json},
+        verify::VerificationReport,
+        ActorStore,
+    },
+    car::{verify_block, CarEncoder},
+    admin::ContentPolicy,
+    blob_store::BlobStore,
     error::{PdsError, PdsResult},
     sequencer::{events::{CommitEvent, CommitOp, OpAction}, Sequencer},
     validation::{RecordValidator, validation_errors_to_pds_error},
 };
 use atproto::{
-    repo::Repository as SdkRepo,
-    tid::Tid,
+    repo::{Repository as SdkRepo, SignedCommit, UnsignedCommit},
     types::Did,
 };
+use libipld::Cid;
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::str::FromStr;
 use std::sync::Arc;
 
+/// Repository format version written in commits
+const REPO_VERSION: u32 = 3;
+
 /// Write operation action
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -51,6 +71,9 @@ pub struct RepositoryManager {
     store: ActorStore,
     validator: RecordValidator,
     sequencer: Option<Arc<Sequencer>>,
+    content_policy: Option<Arc<ContentPolicy>>,
+    write_hooks: Option<Arc<WriteHooks>>,
+    blob_store: Option<Arc<BlobStore>>,
 }
 
 impl RepositoryManager {
@@ -61,6 +84,9 @@ impl RepositoryManager {
             store,
             validator: RecordValidator::new(),
             sequencer: None,
+            content_policy: None,
+            write_hooks: None,
+            blob_store: None,
         }
     }
 
@@ -71,9 +97,34 @@ impl RepositoryManager {
             store,
             validator: RecordValidator::new(),
             sequencer: Some(sequencer),
+            content_policy: None,
+            write_hooks: None,
+            blob_store: None,
         }
     }
 
+    /// Check writes against the content policy before committing them
+    pub fn with_content_policy(mut self, policy: Option<Arc<ContentPolicy>>) -> Self {
+        self.content_policy = policy;
+        self
+    }
+
+    /// Run write hooks over each batch, before the content policy
+    pub fn with_write_hooks(mut self, hooks: Option<Arc<WriteHooks>>) -> Self {
+        self.write_hooks = hooks;
+        self
+    }
+
+    /// Track blob references as records are written and deleted
+    ///
+    /// Blobs a write references are claimed (and committed from temp storage)
+    /// before the commit; references dropped by updates and deletes are
+    /// released after it.
+    pub fn with_blob_store(mut self, blob_store: Option<Arc<BlobStore>>) -> Self {
+        self.blob_store = blob_store;
+        self
+    }
+
     /// Initialize a new repository for an actor
     pub async fn initialize(&self) -> PdsResult<()> {
         // Create the actor's database and directory structure
@@ -145,29 +196,68 @@ impl RepositoryManager {
     /// # Arguments
     ///
     /// * `writes` - List of write operations to apply
+    /// * `swap_commit` - Expected head commit CID; the writes are rejected
+    ///   with `InvalidSwap` if the repository has moved on
     /// * `sign_fn` - Function to sign the commit
     ///
+    /// Each write's `swap_cid` must match the current record CID. All swaps
+    /// are checked before anything is written, under the actor's commit lock
+    /// so the head can't move between the check and the new commit.
+    ///
     /// # Returns
     ///
     /// Returns the new commit CID and revision TID
-    pub async fn apply_writes<F>(
+    pub async fn apply_writes<F, Fut>(
         &self,
         writes: Vec<WriteOp>,
+        swap_commit: Option<&str>,
         sign_fn: F,
     ) -> PdsResult<(String, String)>
     where
-        F: FnOnce(&[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError>,
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
     {
-        // Load current repository state
-        let mut repo = self.load_repo().await?;
+        // Hooks may rewrite records, so they run before anything checks them
+        let mut writes = writes;
+        let hook_notes = match &self.write_hooks {
+            Some(hooks) if !hooks.is_empty() => Some(hooks.before_commit(&self.did, &mut writes).await?),
+            _ => None,
+        };
 
-        // Track operations for commit event
+        // Content policy runs first so rejected writes leave no trace
+        let flagged = match &self.content_policy {
+            Some(policy) => policy.check_writes(&self.did, &writes).await?,
+            None => Vec::new(),
+        };
+
+        // Held until the commit is sequenced, so events leave in commit order
+        let _commit_lock = self.store.lock_commits(&self.did).await;
+
+        let head = self.store.get_repo_root(&self.did).await?;
+        if let Some(expected) = swap_commit {
+            if head.cid != expected {
+                return Err(PdsError::InvalidSwap(format!("Commit was at {}", head.cid)));
+            }
+        }
+        self.check_record_swaps(&writes).await?;
+
+        let mut tree = self.open_tree(&head.cid).await?;
+
+        // Records written by this commit carry its revision
+        let rev = self.store.ids().tid()?.to_string();
+
+        // Nothing is stored until the whole commit is applied at once
+        let mut batch = CommitBatch::new(Some(head.cid.clone()));
+
+        // Track operations and new record blocks for the commit event
         let mut commit_ops: Vec<CommitOp> = Vec::new();
+        let mut record_blocks: Vec<(Cid, Vec<u8>)> = Vec::new();
 
         // Apply each write operation to the MST
         for write in writes.clone() {
             let collection = &write.collection;
             let rkey = &write.rkey;
+            let key = format!("{}/{}", collection, rkey);
 
             match write.action {
                 WriteOpAction::Create | WriteOpAction::Update => {
@@ -183,86 +273,89 @@ impl RepositoryManager {
                     }
 
                     // Serialize record to DAG-CBOR bytes
-                    let record_bytes = serde_json::to_vec(&value)
-                        .map_err(|e| PdsError::Internal(format!("Failed to serialize record: {}", e)))?;
-
-                    // Insert into MST
-                    let record_cid = repo.put_record(collection, rkey, record_bytes.clone())
-                        .map_err(|e| PdsError::Internal(format!("MST insert failed: {}", e)))?;
+                    let record_bytes = encode_record(&value)?;
+                    let record_cid = block_cid(&record_bytes);
 
-                    // Store block content first (to satisfy foreign key constraint)
-                    self.store.put_block(&self.did, &record_cid.to_string(), &record_bytes).await?;
+                    let existed = tree.get(&key).await?.is_some();
+                    tree.put(&key, record_cid).await?;
 
-                    // Store record metadata in database
+                    // Record block and index entry
                     let uri = format!("at://{}/{}/{}", self.did, collection, rkey);
-                    let new_rev = Tid::next()
-                        .map_err(|e| PdsError::Internal(format!("Failed to generate TID: {}", e)))?;
-
-                    self.store.put_record(
-                        &self.did,
-                        &uri,
-                        &record_cid.to_string(),
-                        collection,
-                        rkey,
-                        &new_rev.to_string(),
-                    ).await?;
+                    batch.put_block(record_cid, record_bytes.clone());
+                    batch.put_record(uri, record_cid.to_string(), collection, rkey, find_blob_refs(&value));
 
                     // Track operation for commit event
                     commit_ops.push(CommitOp {
-                        action: OpAction::Create,
-                        path: format!("{}/{}", collection, rkey),
+                        action: if existed { OpAction::Update } else { OpAction::Create },
+                        path: key,
                         cid: Some(record_cid.to_string()),
                     });
+                    record_blocks.push((record_cid, record_bytes));
                 }
                 WriteOpAction::Delete => {
-                    // Delete from MST
-                    repo.delete_record(collection, rkey);
-
-                    // Delete from database
-                    let uri = format!("at://{}/{}/{}", self.did, collection, rkey);
-                    self.store.delete_record(&self.did, &uri).await?;
+                    // Delete from MST and the record index
+                    tree.delete(&key).await?;
+                    batch.delete_record(format!("at://{}/{}/{}", self.did, collection, rkey));
 
                     // Track operation for commit event
                     commit_ops.push(CommitOp {
                         action: OpAction::Delete,
-                        path: format!("{}/{}", collection, rkey),
+                        path: key,
                         cid: None,
                     });
                 }
             }
         }
 
-        // Get previous commit CID before creating new commit
-        let prev_commit_cid = repo.head().map(|cid| cid.to_string());
+        // Claimed before the commit so a blob can't be collected while a
+        // record points at it; on failure the claims are given back
+        let (claimed, released) = match &self.blob_store {
+            Some(blobs) => {
+                let changes = self.blob_ref_changes(&batch).await?;
+                blobs.claim_refs(&self.did, &changes.0).await?;
+                changes
+            }
+            None => (Vec::new(), Vec::new()),
+        };
 
-        // Create signed commit
-        let commit_cid = repo.commit(sign_fn)
-            .map_err(|e| PdsError::Internal(format!("Commit creation failed: {}", e)))?;
+        let committed = self.commit_tree(&mut tree, &rev, batch, sign_fn).await;
 
-        let rev = repo.rev()
-            .ok_or_else(|| PdsError::Internal("No revision after commit".to_string()))?;
+        if let Some(blobs) = &self.blob_store {
+            let unused = if committed.is_ok() { &released } else { &claimed };
+            if let Err(e) = blobs.release_refs(unused).await {
+                tracing::warn!("Failed to release blob references for {}: {}", self.did, e);
+            }
+        }
+        let (commit_cid, commit_bytes, mst_blocks) = committed?;
 
-        // Export repository to CAR format to get all blocks
-        let car_bytes = repo.export_car()
-            .map_err(|e| PdsError::Internal(format!("CAR export failed: {}", e)))?;
+        let cids: HashMap<String, String> = commit_ops
+            .iter()
+            .filter_map(|op| op.cid.clone().map(|cid| (op.path.clone(), cid)))
+            .collect();
 
-        // Store commit blocks to database
-        // Update the repo_root
-        self.store.update_repo_root(
-            &self.did,
-            &commit_cid.to_string(),
-            &rev.to_string(),
-        ).await?;
+        // Label/report records the content policy flagged
+        if let (Some(policy), false) = (&self.content_policy, flagged.is_empty()) {
+            policy.enforce(&self.did, &flagged, &cids).await;
+        }
 
-        // Emit commit event to sequencer for firehose
+        // Emit commit event to sequencer for firehose. This happens only once
+        // the commit is durable, so the firehose never announces a commit
+        // that was rolled back; a crash in between loses just the event and
+        // consumers catch up from the new head.
         if let Some(ref sequencer) = self.sequencer {
+            // The diff: commit, new MST nodes and new records
+            let mut car = CarEncoder::new(&commit_cid)?;
+            car.add_block(&commit_cid, &commit_bytes)?;
+            car.add_blocks(mst_blocks)?;
+            car.add_blocks(record_blocks)?;
+
             // Create commit event
             let commit_event = CommitEvent::new(
                 self.did.clone(),
                 commit_cid.to_string(),
-                rev.to_string(),
-                prev_commit_cid,
-                car_bytes.clone(),
+                rev.clone(),
+                Some(head.rev),
+                car.finalize(),
                 commit_ops,
             );
 
@@ -276,29 +369,317 @@ impl RepositoryManager {
                 .ok();
         }
 
-        Ok((commit_cid.to_string(), rev.to_string()))
+        // Hook follow-up work (labels, copies elsewhere) doesn't hold up the next commit
+        drop(_commit_lock);
+        if let (Some(hooks), Some(notes)) = (&self.write_hooks, hook_notes) {
+            hooks.after_commit(&self.did, &writes, notes, &cids).await;
+        }
+
+        Ok((commit_cid.to_string(), rev))
+    }
+
+    /// Blob references a commit adds and drops, one entry per record reference
+    async fn blob_ref_changes(&self, batch: &CommitBatch) -> PdsResult<(Vec<String>, Vec<String>)> {
+        let (mut added, mut dropped) = (Vec::new(), Vec::new());
+        for change in &batch.changes {
+            let (uri, new) = match change {
+                RecordChange::Put { uri, blobs, .. } => (uri, blobs.as_slice()),
+                RecordChange::Delete { uri } => (uri, &[][..]),
+            };
+            let old = self.store.get_record_blobs(&self.did, uri).await?;
+            added.extend(new.iter().filter(|cid| !old.contains(cid)).cloned());
+            dropped.extend(old.iter().filter(|cid| !new.contains(cid)).cloned());
+        }
+        Ok((added, dropped))
+    }
+
+    /// Check every write's `swap_cid` against the record it replaces
+    async fn check_record_swaps(&self, writes: &[WriteOp]) -> PdsResult<()> {
+        for write in writes {
+            let Some(expected) = &write.swap_cid else {
+                continue;
+            };
+            let uri = format!("at://{}/{}/{}", self.did, write.collection, write.rkey);
+            let current = self.store.get_record(&self.did, &uri).await?.map(|r| r.cid);
+            if current.as_deref() != Some(expected.as_str()) {
+                return Err(PdsError::InvalidSwap(format!(
+                    "Record was at {}",
+                    current.as_deref().unwrap_or("null")
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Open the MST of the commit `head`
+    ///
+    /// Repositories whose head predates incremental storage (or has no stored
+    /// commit block) are rebuilt once from the record index; the next commit
+    /// then persists the tree.
+    async fn open_tree<'a>(&'a self, head: &str) -> PdsResult<RepoTree<'a>> {
+        if let Ok(commit_cid) = Cid::from_str(head) {
+            if let Some(commit) = self.store.get_block(&self.did, &commit_cid.to_string()).await? {
+                if let Ok(data) = proof::commit_data(&commit) {
+                    if let Some(tree) = RepoTree::open(&self.store, &self.did, data).await? {
+                        return Ok(tree);
+                    }
+                }
+            }
+        }
+
+        tracing::info!("Rebuilding MST for {} from the record index", self.did);
+        let records = self.store.list_all_records(&self.did).await?;
+        let entries = records
+            .into_iter()
+            .map(|r| {
+                let cid = Cid::from_str(&r.cid)
+                    .map_err(|e| PdsError::Internal(format!("Invalid record CID {}: {}", r.cid, e)))?;
+                Ok((format!("{}/{}", r.collection, r.rkey), cid))
+            })
+            .collect::<PdsResult<Vec<_>>>()?;
+
+        RepoTree::build(&self.store, &self.did, entries)
+    }
+
+    /// Sign a commit over `tree` and make it the repository head
+    ///
+    /// Adds the MST nodes the tree created and the commit block to `batch`
+    /// and applies it in one transaction. Returns the commit CID and bytes
+    /// and the stored MST blocks.
+    async fn commit_tree<F, Fut>(
+        &self,
+        tree: &mut RepoTree<'_>,
+        rev: &str,
+        mut batch: CommitBatch,
+        sign_fn: F,
+    ) -> PdsResult<(Cid, Vec<u8>, Vec<(Cid, Vec<u8>)>)>
+    where
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
+    {
+        // Create signed commit over the new MST root
+        let unsigned = UnsignedCommit {
+            did: self.did.clone(),
+            version: REPO_VERSION,
+            data: tree.root_cid()?,
+            rev: rev.to_string(),
+            prev: None,
+        };
+        let signing_hash = unsigned.signing_hash()
+            .map_err(|e| PdsError::Internal(format!("Commit creation failed: {}", e)))?;
+        let sig = sign_fn(signing_hash)
+            .await
+            .map_err(|e| PdsError::Internal(format!("Commit creation failed: {}", e)))?;
+        let commit = SignedCommit { commit: unsigned, sig };
+        let commit_bytes = commit.to_cbor()
+            .map_err(|e| PdsError::Internal(format!("Commit creation failed: {}", e)))?;
+        let commit_cid = block_cid(&commit_bytes);
+
+        // Store only the blocks this commit added
+        let mst_blocks = tree.new_blocks()?;
+        for (cid, bytes) in &mst_blocks {
+            batch.put_block(cid, bytes.clone());
+        }
+        batch.put_block(commit_cid, commit_bytes.clone());
+
+        let version_refs = self.store
+            .apply_commit(&self.did, &batch, &commit_cid.to_string(), rev)
+            .await?;
+        self.settle_version_refs(version_refs).await;
+
+        Ok((commit_cid, commit_bytes, mst_blocks))
+    }
+
+    /// Claim the blob references kept record versions took over and release
+    /// those of pruned versions
+    ///
+    /// Runs before a write releases the references its records dropped, so
+    /// a blob passed from a record to its kept version is never left
+    /// unreferenced. Failures are logged: the commit has already happened.
+    async fn settle_version_refs(&self, refs: VersionBlobRefs) {
+        let Some(blobs) = &self.blob_store else {
+            return;
+        };
+        // One at a time, so a blob that has gone doesn't cost the others their claim
+        for cid in &refs.kept {
+            if let Err(e) = blobs.claim_refs(&self.did, std::slice::from_ref(cid)).await {
+                tracing::warn!("Failed to claim blob {} for a kept version in {}: {}", cid, self.did, e);
+            }
+        }
+        if let Err(e) = blobs.release_refs(&refs.pruned).await {
+            tracing::warn!("Failed to release blob references of pruned versions in {}: {}", self.did, e);
+        }
+    }
+
+    /// Rebuild the MST from the record index and sign a fresh commit
+    ///
+    /// Index entries whose record block is missing or does not match its CID
+    /// are dropped first; their URIs are returned with the new commit CID and
+    /// revision. Used by `aurora-locus repair --fix`. No firehose event is
+    /// emitted - consumers see the new head on their next sync.
+    pub async fn rebuild<F, Fut>(&self, sign_fn: F) -> PdsResult<(String, String, Vec<String>)>
+    where
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
+    {
+        let _commit_lock = self.store.lock_commits(&self.did).await;
+
+        let mut batch = CommitBatch::new(None);
+        let mut entries = Vec::new();
+        let mut dropped = Vec::new();
+        for record in self.store.list_all_records(&self.did).await? {
+            let intact = match Cid::from_str(&record.cid) {
+                Ok(cid) => match self.store.get_block(&self.did, &record.cid).await? {
+                    Some(bytes) if verify_block(&cid, &bytes).is_ok() => Some(cid),
+                    _ => None,
+                },
+                Err(_) => None,
+            };
+
+            match intact {
+                Some(cid) => entries.push((format!("{}/{}", record.collection, record.rkey), cid)),
+                None => {
+                    batch.delete_record(record.uri.clone());
+                    dropped.push(record.uri);
+                }
+            }
+        }
+
+        let rev = self.store.ids().tid()?.to_string();
+        let mut tree = RepoTree::build(&self.store, &self.did, entries)?;
+        let (commit_cid, _, _) = self.commit_tree(&mut tree, &rev, batch, sign_fn).await?;
+
+        Ok((commit_cid.to_string(), rev, dropped))
+    }
+
+    /// Re-encode records stored as JSON into DAG-CBOR
+    ///
+    /// Each legacy record gets a new block and CID, the old block is removed,
+    /// and the MST is rebuilt over the new CIDs in a fresh commit. Returns the
+    /// commit CID, revision and number of records re-encoded, or `None` when
+    /// there was nothing to do. As with `rebuild`, no firehose event is
+    /// emitted. Records that can't be expressed in the data model (floats)
+    /// are left as they are.
+    pub async fn migrate_record_encoding<F, Fut>(&self, sign_fn: F) -> PdsResult<Option<(String, String, usize)>>
+    where
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
+    {
+        let _commit_lock = self.store.lock_commits(&self.did).await;
+        let head = self.store.get_repo_root(&self.did).await?;
+
+        let mut batch = CommitBatch::new(Some(head.cid));
+        let mut entries = Vec::new();
+        let mut migrated = 0;
+        for record in self.store.list_all_records(&self.did).await? {
+            let mut cid = Cid::from_str(&record.cid)
+                .map_err(|e| PdsError::Internal(format!("Invalid record CID {}: {}", record.cid, e)))?;
+
+            let legacy = self.store.get_block(&self.did, &record.cid).await?.and_then(|b| legacy_json(&b));
+            if let Some(value) = legacy {
+                match encode_record(&value) {
+                    Ok(bytes) => {
+                        cid = block_cid(&bytes);
+                        batch.put_block(cid, bytes);
+                        batch.put_record(record.uri, cid.to_string(), &record.collection, &record.rkey, find_blob_refs(&value));
+                        batch.delete_block(record.cid);
+                        migrated += 1;
+                    }
+                    Err(e) => tracing::warn!("Leaving {} encoded as JSON: {}", record.uri, e),
+                }
+            }
+            entries.push((format!("{}/{}", record.collection, record.rkey), cid));
+        }
+
+        if migrated == 0 {
+            return Ok(None);
+        }
+
+        let rev = self.store.ids().tid()?.to_string();
+        let mut tree = RepoTree::build(&self.store, &self.did, entries)?;
+        let (commit_cid, _, _) = self.commit_tree(&mut tree, &rev, batch, sign_fn).await?;
+
+        Ok(Some((commit_cid.to_string(), rev, migrated)))
+    }
+
+    /// Replace the repository with a verified CAR import
+    ///
+    /// `report` must come from verifying `blocks` for this DID; every block
+    /// is stored, the record index is rewritten from the MST, and the
+    /// imported commit becomes the head.
+    pub async fn import(
+        &self,
+        blocks: &HashMap<Cid, Vec<u8>>,
+        report: &VerificationReport,
+    ) -> PdsResult<()> {
+        if !report.is_valid() || report.did != self.did {
+            return Err(PdsError::Validation(format!(
+                "Refusing to import unverified repository for {}",
+                self.did
+            )));
+        }
+        let rev = report
+            .rev
+            .clone()
+            .ok_or_else(|| PdsError::Validation("Imported commit has no rev".to_string()))?;
+
+        if !self.store.exists(&self.did).await {
+            self.store.create(&self.did).await?;
+        }
+        let _commit_lock = self.store.lock_commits(&self.did).await;
+
+        // Blocks, record index and head are replaced in one transaction
+        let mut batch = CommitBatch::new(None);
+        for (cid, data) in blocks {
+            batch.put_block(cid, data.clone());
+        }
+
+        let mut wanted = HashSet::new();
+        for (key, cid) in &report.entries {
+            let (collection, rkey) = key
+                .split_once('/')
+                .ok_or_else(|| PdsError::Validation(format!("Invalid MST key: {}", key)))?;
+            let uri = format!("at://{}/{}/{}", self.did, collection, rkey);
+
+            // Blob references are indexed for records the data model can read
+            let blobs = blocks
+                .get(cid)
+                .and_then(|b| decode_record(b).ok())
+                .map(|value| find_blob_refs(&value))
+                .unwrap_or_default();
+            batch.put_record(uri.clone(), cid.to_string(), collection, rkey, blobs);
+            wanted.insert(uri);
+        }
+
+        for record in self.store.list_all_records(&self.did).await? {
+            if !wanted.contains(&record.uri) {
+                batch.delete_record(record.uri);
+            }
+        }
+
+        let version_refs = self.store.apply_commit(&self.did, &batch, &report.commit, &rev).await?;
+        self.settle_version_refs(version_refs).await;
+        Ok(())
     }
 
     /// Create a single record
-    pub async fn create_record<F>(
+    pub async fn create_record<F, Fut>(
         &self,
         collection: &str,
         rkey: Option<&str>,
         value: serde_json::Value,
         validate: Option<bool>,
+        swap_commit: Option<&str>,
         sign_fn: F,
     ) -> PdsResult<(String, String, String)> // (uri, cid, rev)
     where
-        F: FnOnce(&[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError>,
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
     {
         // Generate rkey if not provided
         let rkey = match rkey {
             Some(k) => k.to_string(),
-            None => {
-                let tid = Tid::next()
-                    .map_err(|e| PdsError::Internal(format!("Failed to generate TID: {}", e)))?;
-                tid.to_string()
-            }
+            None => self.store.ids().tid()?.to_string(),
         };
 
         // Apply as a single write operation
@@ -311,23 +692,28 @@ impl RepositoryManager {
             swap_cid: None, // Creates don't use swap CID
         }];
 
-        let (commit_cid, rev) = self.apply_writes(writes, sign_fn).await?;
+        let (commit_cid, rev) = self.apply_writes(writes, swap_commit, sign_fn).await?;
 
         let uri = format!("at://{}/{}/{}", self.did, collection, rkey);
         Ok((uri, commit_cid, rev))
     }
 
     /// Update a record
-    pub async fn update_record<F>(
+    ///
+    /// `swap_record` is the CID the record must currently have.
+    pub async fn update_record<F, Fut>(
         &self,
         collection: &str,
         rkey: &str,
         value: serde_json::Value,
         validate: Option<bool>,
+        swap_record: Option<&str>,
+        swap_commit: Option<&str>,
         sign_fn: F,
     ) -> PdsResult<(String, String)> // (cid, rev)
     where
-        F: FnOnce(&[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError>,
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
     {
         let writes = vec![WriteOp {
             action: WriteOpAction::Update,
@@ -335,21 +721,26 @@ impl RepositoryManager {
             rkey: rkey.to_string(),
             value: Some(value),
             validate,
-            swap_cid: None, // Can be added later if needed
+            swap_cid: swap_record.map(str::to_string),
         }];
 
-        self.apply_writes(writes, sign_fn).await
+        self.apply_writes(writes, swap_commit, sign_fn).await
     }
 
     /// Delete a record
-    pub async fn delete_record<F>(
+    ///
+    /// `swap_record` is the CID the record must currently have.
+    pub async fn delete_record<F, Fut>(
         &self,
         collection: &str,
         rkey: &str,
+        swap_record: Option<&str>,
+        swap_commit: Option<&str>,
         sign_fn: F,
     ) -> PdsResult<(String, String)> // (cid, rev)
     where
-        F: FnOnce(&[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError>,
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
     {
         let writes = vec![WriteOp {
             action: WriteOpAction::Delete,
@@ -357,10 +748,10 @@ impl RepositoryManager {
             rkey: rkey.to_string(),
             value: None,
             validate: None, // Validation not needed for deletes
-            swap_cid: None, // Can be added later if needed
+            swap_cid: swap_record.map(str::to_string),
         }];
 
-        self.apply_writes(writes, sign_fn).await
+        self.apply_writes(writes, swap_commit, sign_fn).await
     }
 
     /// Get a record by AT-URI
@@ -372,8 +763,7 @@ impl RepositoryManager {
             // Load actual record content from blocks
             if let Some(content) = self.store.get_block(&self.did, &rec.cid).await? {
                 // Deserialize the record content from bytes
-                let value: serde_json::Value = serde_json::from_slice(&content)
-                    .map_err(|e| PdsError::Internal(format!("Failed to deserialize record: {}", e)))?;
+                let value = decode_record(&content)?;
 
                 Ok(Some(serde_json::json!({
                     "uri": rec.uri,
@@ -404,8 +794,7 @@ impl RepositoryManager {
             // Load actual record content from blocks
             if let Some(content) = self.store.get_block(&self.did, &rec.cid).await? {
                 // Deserialize the record content from bytes
-                let value: serde_json::Value = serde_json::from_slice(&content)
-                    .map_err(|e| PdsError::Internal(format!("Failed to deserialize record: {}", e)))?;
+                let value = decode_record(&content)?;
 
                 results.push(serde_json::json!({
                     "uri": rec.uri,
@@ -483,13 +872,9 @@ impl RepositoryManager {
         }))
     }
 
-    /// Export repository to CAR file
+    /// Export repository to CAR file, optionally only blocks after rev `since`
     pub async fn export_car(&self, since: Option<&str>) -> PdsResult<Vec<u8>> {
-        let repo = self.load_repo().await?;
-
-        // Use SDK's CAR export
-        repo.export_car()
-            .map_err(|e| PdsError::Internal(format!("CAR export failed: {}", e)))
+        super::export::export_repo_car(self.store.clone(), &self.did, since).await
     }
 
     // ==================== Batch Operations ====================
@@ -597,38 +982,13 @@ impl RepositoryManager {
                 }
             }
 
-            // Validate swap CID for optimistic concurrency (Update/Delete only)
-            if let Some(ref swap_cid) = write.swap_cid {
-                match write.action {
-                    WriteOpAction::Update | WriteOpAction::Delete => {
-                        // Construct URI and check current record CID
-                        let uri = format!("at://{}/{}/{}", self.did, write.collection, write.rkey);
-
-                        match self.store.get_record(&self.did, &uri).await? {
-                            Some(current_record) => {
-                                if current_record.cid != *swap_cid {
-                                    return Err(PdsError::Validation(format!(
-                                        "Swap CID mismatch for {}/{}: expected '{}', found '{}'",
-                                        write.collection, write.rkey, swap_cid, current_record.cid
-                                    )));
-                                }
-                            }
-                            None => {
-                                return Err(PdsError::NotFound(format!(
-                                    "Cannot swap CID - record not found: {}/{}",
-                                    write.collection, write.rkey
-                                )));
-                            }
-                        }
-                    }
-                    WriteOpAction::Create => {
-                        // swap_cid doesn't make sense for Create operations
-                        return Err(PdsError::Validation(format!(
-                            "swap_cid cannot be used with Create action for {}/{}",
-                            write.collection, write.rkey
-                        )));
-                    }
-                }
+            // Record swaps only make sense for Update/Delete; apply_writes
+            // checks them against the current record
+            if write.swap_cid.is_some() && matches!(write.action, WriteOpAction::Create) {
+                return Err(PdsError::Validation(format!(
+                    "swap_cid cannot be used with Create action for {}/{}",
+                    write.collection, write.rkey
+                )));
             }
         }
 
@@ -637,14 +997,17 @@ impl RepositoryManager {
 
     /// Apply batch writes atomically
     ///
-    /// All operations succeed or all fail together
-    pub async fn apply_batch_writes<F>(
+    /// All operations succeed or all fail together. With `swap_commit`, the
+    /// whole batch is rejected if the repository head is no longer that commit.
+    pub async fn apply_batch_writes<F, Fut>(
         &self,
         writes: Vec<crate::actor_store::models::PreparedWrite>,
+        swap_commit: Option<&str>,
         sign_fn: F,
     ) -> PdsResult<(String, String)> // (commit_cid, rev)
     where
-        F: FnOnce(&[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError>,
+        F: FnOnce([u8; 32]) -> Fut,
+        Fut: Future<Output = Result<Vec<u8>, atproto::repo::RepoError>>,
     {
         use crate::actor_store::models::WriteOpAction as ModelAction;
 
@@ -670,7 +1033,7 @@ impl RepositoryManager {
         }).collect();
 
         // Apply all operations atomically
-        self.apply_writes(ops, sign_fn).await
+        self.apply_writes(ops, swap_commit, sign_fn).await
     }
 }
 
@@ -678,25 +1041,12 @@ impl RepositoryManager {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::actor_store::ActorStoreConfig;
-    use std::path::PathBuf;
-
-    fn test_store() -> ActorStore {
-        let config = ActorStoreConfig {
-            base_directory: PathBuf::from("./test_data/repos"),
-            cache_size: 10,
-        };
-        ActorStore::new(config)
-    }
-
-    fn test_dummy_signer(_hash: &[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError> {
-        // Return a dummy 64-byte signature for testing
-        Ok(vec![0u8; 64])
-    }
+    use crate::actor_store::verify::{verify_local, IssueKind};
+    use crate::actor_store::test_util::{dummy_signer, temp_store};
 
     #[tokio::test]
     async fn test_repository_initialization() {
-        let store = test_store();
+        let (_dir, store) = temp_store();
         let repo_mgr = RepositoryManager::new("did:plc:test123".to_string(), store);
 
         let result = repo_mgr.initialize().await;
@@ -705,7 +1055,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_create_record() {
-        let store = test_store();
+        let (_dir, store) = temp_store();
         let repo_mgr = RepositoryManager::new("did:plc:test456".to_string(), store);
 
         repo_mgr.initialize().await.unwrap();
@@ -720,7 +1070,8 @@ mod tests {
             None,
             value,
             None, // validate
-            test_dummy_signer,
+            None, // swap_commit
+            dummy_signer,
         ).await;
 
         assert!(result.is_ok());
@@ -732,7 +1083,7 @@ mod tests {
 
     #[tokio::test]
     async fn test_apply_writes() {
-        let store = test_store();
+        let (_dir, store) = temp_store();
         let repo_mgr = RepositoryManager::new("did:plc:test789".to_string(), store);
 
         repo_mgr.initialize().await.unwrap();
@@ -756,7 +1107,435 @@ mod tests {
             },
         ];
 
-        let result = repo_mgr.apply_writes(writes, test_dummy_signer).await;
+        let result = repo_mgr.apply_writes(writes, None, dummy_signer).await;
         assert!(result.is_ok());
     }
+
+    #[tokio::test]
+    async fn test_swap_record_and_commit() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:testswap";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone());
+        repo_mgr.initialize().await.unwrap();
+
+        let (uri, commit, _) = repo_mgr
+            .create_record("app.bsky.feed.post", Some("post1"), serde_json::json!({"text": "v1"}), Some(false), None, dummy_signer)
+            .await
+            .unwrap();
+        let record_cid = store.get_record(did, &uri).await.unwrap().unwrap().cid;
+
+        // A stale record CID is rejected and nothing changes
+        let stale = block_cid(b"stale").to_string();
+        let result = repo_mgr
+            .update_record("app.bsky.feed.post", "post1", serde_json::json!({"text": "v2"}), Some(false), Some(&stale), None, dummy_signer)
+            .await;
+        assert!(matches!(result, Err(PdsError::InvalidSwap(_))));
+        assert_eq!(store.get_repo_root(did).await.unwrap().cid, commit);
+
+        // Matching swaps go through
+        let (commit, _) = repo_mgr
+            .update_record("app.bsky.feed.post", "post1", serde_json::json!({"text": "v2"}), Some(false), Some(&record_cid), Some(&commit), dummy_signer)
+            .await
+            .unwrap();
+
+        // The head moved, so the old commit no longer swaps
+        let result = repo_mgr
+            .delete_record("app.bsky.feed.post", "post1", None, Some(&record_cid), dummy_signer)
+            .await;
+        assert!(matches!(result, Err(PdsError::InvalidSwap(_))));
+        let result = repo_mgr
+            .delete_record("app.bsky.feed.post", "missing", Some(&record_cid), None, dummy_signer)
+            .await;
+        assert!(matches!(result, Err(PdsError::InvalidSwap(_))));
+
+        let new_cid = store.get_record(did, &uri).await.unwrap().unwrap().cid;
+        repo_mgr
+            .delete_record("app.bsky.feed.post", "post1", Some(&new_cid), Some(&commit), dummy_signer)
+            .await
+            .unwrap();
+        assert!(store.get_record(did, &uri).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_swap_commit_is_atomic() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:testbatchswap";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone());
+        repo_mgr.initialize().await.unwrap();
+        let head = store.get_repo_root(did).await.unwrap().cid;
+
+        let batch = |rkey: &str| {
+            repo_mgr
+                .prepare_writes(vec![WriteOp {
+                    action: WriteOpAction::Create,
+                    collection: "app.bsky.feed.post".to_string(),
+                    rkey: rkey.to_string(),
+                    value: Some(serde_json::json!({"text": rkey})),
+                    validate: Some(false),
+                    swap_cid: None,
+                }])
+                .unwrap()
+        };
+
+        // Two batches racing on the same head: exactly one wins
+        let other = RepositoryManager::new(did.to_string(), store.clone());
+        let (a, b) = tokio::join!(
+            repo_mgr.apply_batch_writes(batch("a"), Some(&head), dummy_signer),
+            other.apply_batch_writes(batch("b"), Some(&head), dummy_signer),
+        );
+        let (winner, loser) = if a.is_ok() { (a, b) } else { (b, a) };
+        let (commit, _) = winner.unwrap();
+        assert!(matches!(loser, Err(PdsError::InvalidSwap(_))));
+        assert_eq!(store.get_repo_root(did).await.unwrap().cid, commit);
+        assert_eq!(store.list_all_records(did).await.unwrap().len(), 1);
+
+        repo_mgr
+            .apply_batch_writes(batch("c"), Some(&commit), dummy_signer)
+            .await
+            .unwrap();
+    }
+
+    async fn failing_signer(_hash: [u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError> {
+        Err(atproto::repo::RepoError::Signing("signer unavailable".to_string()))
+    }
+
+    #[tokio::test]
+    async fn test_failed_commit_leaves_no_trace() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:testcrash";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone());
+        repo_mgr.initialize().await.unwrap();
+        let (uri, _, _) = repo_mgr
+            .create_record("app.bsky.feed.post", Some("post1"), serde_json::json!({"text": "kept"}), Some(false), None, dummy_signer)
+            .await
+            .unwrap();
+        let head = store.get_repo_root(did).await.unwrap();
+        let blocks = store.get_all_blocks(did).await.unwrap().len();
+
+        // Failing after the MST is built but before anything is stored
+        let writes = vec![
+            WriteOp {
+                action: WriteOpAction::Create,
+                collection: "app.bsky.feed.post".to_string(),
+                rkey: "post2".to_string(),
+                value: Some(serde_json::json!({"text": "lost"})),
+                validate: Some(false),
+                swap_cid: None,
+            },
+            WriteOp {
+                action: WriteOpAction::Delete,
+                collection: "app.bsky.feed.post".to_string(),
+                rkey: "post1".to_string(),
+                value: None,
+                validate: None,
+                swap_cid: None,
+            },
+        ];
+        assert!(repo_mgr.apply_writes(writes, None, failing_signer).await.is_err());
+
+        // Failing inside the transaction: the record's block is missing, so
+        // the foreign key rejects it after the other rows were written
+        let mut batch = CommitBatch::new(Some(head.cid.clone()));
+        batch.put_block(block_cid(b"orphan"), b"orphan".to_vec());
+        batch.delete_record(uri.clone());
+        batch.put_record(
+            format!("at://{}/app.bsky.feed.post/post3", did),
+            block_cid(b"missing").to_string(),
+            "app.bsky.feed.post",
+            "post3",
+            Vec::new(),
+        );
+        let commit = block_cid(b"commit").to_string();
+        assert!(store.apply_commit(did, &batch, &commit, "3kzzzzzzzzz2a").await.is_err());
+
+        // Neither attempt changed the head, blocks or record index
+        let after = store.get_repo_root(did).await.unwrap();
+        assert_eq!((after.cid, after.rev), (head.cid.clone(), head.rev));
+        assert_eq!(store.get_all_blocks(did).await.unwrap().len(), blocks);
+        let records = store.list_all_records(did).await.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].uri, uri);
+        assert!(verify_local(&store, did, None).await.unwrap().is_valid());
+
+        // A batch built on a stale head is rejected too
+        batch.prev = Some(commit);
+        assert!(matches!(
+            store.apply_commit(did, &batch, &head.cid, "3kzzzzzzzzz2a").await,
+            Err(PdsError::InvalidSwap(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_migrate_record_encoding() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:testlegacy";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone());
+        repo_mgr.initialize().await.unwrap();
+        repo_mgr
+            .create_record("app.bsky.feed.post", Some("new"), serde_json::json!({"text": "cbor"}), Some(false), None, dummy_signer)
+            .await
+            .unwrap();
+        assert!(repo_mgr.migrate_record_encoding(dummy_signer).await.unwrap().is_none());
+
+        // A record written by an older version, as JSON
+        let value = serde_json::json!({"text": "json"});
+        let json = serde_json::to_vec(&value).unwrap();
+        let old_cid = block_cid(&json).to_string();
+        let uri = format!("at://{}/app.bsky.feed.post/old", did);
+        store.put_block(did, &old_cid, &json).await.unwrap();
+        store.put_record(did, &uri, &old_cid, "app.bsky.feed.post", "old", "3jzfcijpj2z2a").await.unwrap();
+        assert_eq!(repo_mgr.get_record(&uri).await.unwrap().unwrap()["value"], value);
+
+        let (commit, _, count) = repo_mgr.migrate_record_encoding(dummy_signer).await.unwrap().unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(store.get_repo_root(did).await.unwrap().cid, commit);
+
+        let record = store.get_record(did, &uri).await.unwrap().unwrap();
+        let block = store.get_block(did, &record.cid).await.unwrap().unwrap();
+        assert_eq!(block, encode_record(&value).unwrap());
+        assert!(store.get_block(did, &old_cid).await.unwrap().is_none());
+        assert_eq!(repo_mgr.get_record(&uri).await.unwrap().unwrap()["value"], value);
+        assert!(verify_local(&store, did, None).await.unwrap().is_valid());
+    }
+
+    async fn test_blob_store(dir: &std::path::Path) -> Arc<BlobStore> {
+        use crate::blob_store::{BlobBackendType, BlobStorageConfig, BlobStoreConfig};
+
+        let db = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        for table in ["blob_metadata", "temp_blob_metadata"] {
+            sqlx::query(&format!(
+                "CREATE TABLE {} (cid TEXT PRIMARY KEY, mime_type TEXT NOT NULL, size INTEGER NOT NULL,
+                 creator_did TEXT NOT NULL, created_at DATETIME NOT NULL, width INTEGER, height INTEGER,
+                 alt_text TEXT, thumbnail_cid TEXT, ref_count INTEGER, unreferenced_since DATETIME,
+                 animated INTEGER NOT NULL DEFAULT 0, duration_ms INTEGER, waveform BLOB)",
+                table
+            ))
+            .execute(&db)
+            .await
+            .unwrap();
+        }
+        let config = BlobStoreConfig {
+            storage: BlobStorageConfig {
+                backend: BlobBackendType::Disk { location: dir.join("blobs") },
+                max_blob_size: 1024 * 1024,
+                max_audio_size: 10 * 1024 * 1024,
+                account_quota: None,
+                animated_thumbnails: false,
+                temp_ttl_hours: 24,
+                max_staged_per_account: None,
+                temp_dir: dir.join("tmp"),
+            },
+        };
+        Arc::new(BlobStore::new(config, db).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_deletes_leave_tombstones() {
+        let (_dir, store) = temp_store();
+        let db = sqlx::SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let tombstones = crate::actor_store::TombstoneLog::new(db);
+        let store = store.with_tombstone_log(tombstones.clone());
+        let did = "did:plc:testtombstones";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store);
+        repo_mgr.initialize().await.unwrap();
+
+        let (uri, cid, _) = repo_mgr
+            .create_record("app.bsky.feed.post", Some("post1"), serde_json::json!({"text": "gone soon"}), None, None, dummy_signer)
+            .await
+            .unwrap();
+        let (_, rev) = repo_mgr
+            .delete_record("app.bsky.feed.post", "post1", None, None, dummy_signer)
+            .await
+            .unwrap();
+
+        let logged = tombstones
+            .list(&crate::actor_store::TombstoneQuery { limit: 10, ..Default::default() })
+            .await
+            .unwrap();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(logged[0].uri, uri);
+        assert_eq!(logged[0].cid.as_deref(), Some(cid.as_str()));
+        assert_eq!(logged[0].rev, rev);
+        assert_eq!(logged[0].collection, "app.bsky.feed.post");
+    }
+
+    #[tokio::test]
+    async fn test_blob_references_follow_records() {
+        let (dir, store) = temp_store();
+        let blobs = test_blob_store(dir.path()).await;
+        let did = "did:plc:testblobs";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone()).with_blob_store(Some(blobs.clone()));
+        repo_mgr.initialize().await.unwrap();
+
+        let staged = blobs.stage_blob(b"picture".to_vec(), Some("image/png"), did).await.unwrap();
+        let post = |cid: &str| {
+            serde_json::json!({
+                "text": "with image",
+                "image": {"$type": "blob", "ref": {"$link": cid}, "mimeType": "image/png", "size": 7},
+            })
+        };
+
+        // Unknown blobs are rejected before anything is committed
+        let head = store.get_repo_root(did).await.unwrap().cid;
+        let missing = block_cid(b"missing").to_string();
+        let result = repo_mgr
+            .create_record("app.bsky.feed.post", Some("bad"), post(&missing), Some(false), None, dummy_signer)
+            .await;
+        assert!(matches!(result, Err(PdsError::Validation(_))));
+        assert_eq!(store.get_repo_root(did).await.unwrap().cid, head);
+
+        // Writing the record commits the staged blob
+        repo_mgr
+            .create_record("app.bsky.feed.post", Some("a"), post(&staged.cid), Some(false), None, dummy_signer)
+            .await
+            .unwrap();
+        repo_mgr
+            .create_record("app.bsky.feed.post", Some("b"), post(&staged.cid), Some(false), None, dummy_signer)
+            .await
+            .unwrap();
+        assert!(blobs.get_metadata(&staged.cid).await.unwrap().is_some());
+
+        // Still referenced by one record, so it survives collection
+        repo_mgr.delete_record("app.bsky.feed.post", "a", None, None, dummy_signer).await.unwrap();
+        assert_eq!(blobs.delete_unreferenced(-1).await.unwrap(), 0);
+
+        // Updating away the last reference releases it
+        repo_mgr
+            .update_record("app.bsky.feed.post", "b", serde_json::json!({"text": "no image"}), Some(false), None, None, dummy_signer)
+            .await
+            .unwrap();
+        assert_eq!(blobs.delete_unreferenced(-1).await.unwrap(), 1);
+        assert!(blobs.get_metadata(&staged.cid).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_kept_versions_hold_blob_references() {
+        use crate::actor_store::history::record_version;
+
+        let (dir, store) = temp_store();
+        let store = store.with_record_history(1);
+        let blobs = test_blob_store(dir.path()).await;
+        let did = "did:plc:testversionblobs";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone()).with_blob_store(Some(blobs.clone()));
+        repo_mgr.initialize().await.unwrap();
+
+        let staged = blobs.stage_blob(b"avatar".to_vec(), Some("image/png"), did).await.unwrap();
+        let profile = "app.bsky.actor.profile";
+        let uri = format!("at://{}/{}/self", did, profile);
+        let (_, kept_cid, _) = repo_mgr
+            .create_record(
+                profile,
+                Some("self"),
+                serde_json::json!({
+                    "displayName": "Alice",
+                    "avatar": {"$type": "blob", "ref": {"$link": &staged.cid}, "mimeType": "image/png", "size": 6},
+                }),
+                Some(false),
+                None,
+                dummy_signer,
+            )
+            .await
+            .unwrap();
+
+        // The kept version keeps the avatar through blob collection
+        let text_only = |name: &str| serde_json::json!({ "displayName": name });
+        repo_mgr
+            .update_record(profile, "self", text_only("vandalised"), Some(false), None, None, dummy_signer)
+            .await
+            .unwrap();
+        assert_eq!(blobs.delete_unreferenced(-1).await.unwrap(), 0);
+
+        // so it can be rolled back to
+        let value = record_version(&store, did, &uri, &kept_cid).await.unwrap();
+        repo_mgr
+            .update_record(profile, "self", value, Some(false), None, None, dummy_signer)
+            .await
+            .unwrap();
+        assert_eq!(store.get_record(did, &uri).await.unwrap().unwrap().cid, kept_cid);
+
+        // Once no record or kept version uses it, it is collected
+        for name in ["first", "second"] {
+            repo_mgr
+                .update_record(profile, "self", text_only(name), Some(false), None, None, dummy_signer)
+                .await
+                .unwrap();
+        }
+        assert_eq!(blobs.delete_unreferenced(-1).await.unwrap(), 1);
+        assert!(blobs.get_metadata(&staged.cid).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_verify_and_rebuild() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:testverify";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone());
+        repo_mgr.initialize().await.unwrap();
+
+        for i in 0..5 {
+            repo_mgr
+                .create_record(
+                    "app.bsky.feed.post",
+                    Some(&format!("post{}", i)),
+                    serde_json::json!({"text": format!("Post {}", i)}),
+                    Some(false),
+                    None,
+                    dummy_signer,
+                )
+                .await
+                .unwrap();
+        }
+        let report = verify_local(&store, did, None).await.unwrap();
+        assert!(report.is_valid(), "{}", report.summary());
+        assert_eq!(report.records, 5);
+
+        // An index row without its block is dropped by a rebuild
+        let uri = format!("at://{}/app.bsky.feed.post/ghost", did);
+        store
+            .put_record(did, &uri, &block_cid(b"ghost").to_string(), "app.bsky.feed.post", "ghost", "3jzfcijpj2z2a")
+            .await
+            .unwrap();
+        let report = verify_local(&store, did, None).await.unwrap();
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::IndexMismatch);
+
+        let (_, _, dropped) = repo_mgr.rebuild(dummy_signer).await.unwrap();
+        assert_eq!(dropped, vec![uri]);
+        assert!(verify_local(&store, did, None).await.unwrap().is_valid());
+    }
+
+    #[tokio::test]
+    async fn test_collection_stats() {
+        let (_dir, store) = temp_store();
+        let did = "did:plc:teststats";
+        let repo_mgr = RepositoryManager::new(did.to_string(), store.clone());
+        repo_mgr.initialize().await.unwrap();
+
+        let write = |action, collection: &str, rkey: &str| WriteOp {
+            action,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            value: (action != WriteOpAction::Delete).then(|| serde_json::json!({"text": rkey})),
+            validate: Some(false),
+            swap_cid: None,
+        };
+        let writes = vec![
+            write(WriteOpAction::Create, "app.bsky.feed.post", "post1"),
+            write(WriteOpAction::Create, "app.bsky.feed.post", "post2"),
+            write(WriteOpAction::Create, "app.bsky.graph.follow", "follow1"),
+        ];
+        repo_mgr.apply_writes(writes, None, dummy_signer).await.unwrap();
+
+        // Updates keep the count, deletes decrement it
+        let writes = vec![
+            write(WriteOpAction::Update, "app.bsky.feed.post", "post1"),
+            write(WriteOpAction::Delete, "app.bsky.graph.follow", "follow1"),
+        ];
+        repo_mgr.apply_writes(writes, None, dummy_signer).await.unwrap();
+
+        let stats = store.collection_stats(did).await.unwrap();
+        assert_eq!(stats.len(), 1);
+        assert_eq!(stats[0].collection, "app.bsky.feed.post");
+        assert_eq!(stats[0].count, 2);
+    }
 }
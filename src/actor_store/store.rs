@@ -1,13 +1,105 @@
 /// Actor Store Manager - Handles per-user repository databases
 use crate::{
-    actor_store::{get_actor_location, models::*, ActorLocation},
+    actor_store::{
+        blob_refs::find_blob_refs,
+        get_actor_location,
+        record_codec::decode_record,
+        models::*,
+        repo_index::{CollectionCount, RepoIndex},
+        tombstones::TombstoneLog,
+        ActorLocation,
+    },
+    clock::{random_ids, IdGenerator},
+    crypto::data_keys::{is_plaintext_db, sqlcipher_available, DataKey, DataKeyManager},
     error::{PdsError, PdsResult},
 };
 use sqlx::{Row, SqlitePool};
 use std::collections::HashMap;
-use std::path::PathBuf;
-use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{Mutex as AsyncMutex, OwnedMutexGuard, RwLock};
+
+/// Tables added after the initial actor schema. Applied whenever a store is
+/// opened so existing actor databases pick them up.
+const ACTOR_SCHEMA_UPGRADES: &str = r#"
+    CREATE TABLE IF NOT EXISTS account_pref (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        name TEXT NOT NULL,
+        value_json TEXT NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_account_pref_name ON account_pref(name);
+
+    CREATE TABLE IF NOT EXISTS record_blob (
+        blob_cid TEXT NOT NULL,
+        record_uri TEXT NOT NULL,
+        PRIMARY KEY (blob_cid, record_uri)
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_record_blob_record ON record_blob(record_uri);
+
+    CREATE TABLE IF NOT EXISTS collection_stat (
+        collection TEXT PRIMARY KEY NOT NULL,
+        record_count INTEGER NOT NULL
+    );
+
+    CREATE TRIGGER IF NOT EXISTS record_stat_insert AFTER INSERT ON record
+    BEGIN
+        INSERT INTO collection_stat (collection, record_count) VALUES (new.collection, 1)
+        ON CONFLICT(collection) DO UPDATE SET record_count = record_count + 1;
+    END;
+
+    CREATE TRIGGER IF NOT EXISTS record_stat_delete AFTER DELETE ON record
+    BEGIN
+        UPDATE collection_stat SET record_count = record_count - 1 WHERE collection = old.collection;
+    END;
+
+    CREATE TABLE IF NOT EXISTS repo_commit (
+        rev TEXT PRIMARY KEY NOT NULL,
+        cid TEXT NOT NULL,
+        op_count INTEGER NOT NULL,
+        committed_at DATETIME NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_repo_commit_cid ON repo_commit(cid);
+
+    CREATE TABLE IF NOT EXISTS record_version (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        uri TEXT NOT NULL,
+        cid TEXT NOT NULL,
+        replaced_rev TEXT NOT NULL,
+        content BLOB NOT NULL,
+        replaced_at DATETIME NOT NULL
+    );
+
+    CREATE INDEX IF NOT EXISTS idx_record_version_uri ON record_version(uri, id);
+
+    CREATE TABLE IF NOT EXISTS record_version_blob (
+        version_id INTEGER NOT NULL,
+        blob_cid TEXT NOT NULL,
+        PRIMARY KEY (version_id, blob_cid)
+    );
+"#;
+
+/// `PRAGMA user_version` once existing records have been scanned into `record_blob`
+const RECORD_BLOB_BACKFILLED: i64 = 1;
+
+/// `PRAGMA user_version` once existing records have been counted into `collection_stat`
+const COLLECTION_STATS_BACKFILLED: i64 = 2;
+
+/// `PRAGMA user_version` once legacy JSON record blocks have been re-encoded as DAG-CBOR
+const RECORDS_DAG_CBOR: i64 = 3;
+
+/// `PRAGMA user_version` of a fully upgraded store
+pub const ACTOR_SCHEMA_VERSION: i64 = RECORDS_DAG_CBOR;
+
+/// Whether a preference `$type` belongs to a namespace such as "app.bsky"
+pub fn pref_in_namespace(name: &str, namespace: &str) -> bool {
+    name == namespace
+        || name
+            .strip_prefix(namespace)
+            .map_or(false, |rest| rest.starts_with('.'))
+}
 
 /// Configuration for the actor store
 #[derive(Debug, Clone)]
@@ -31,6 +123,21 @@ pub struct ActorStore {
     config: ActorStoreConfig,
     // Cache of open database connections (LRU-style)
     db_cache: Arc<RwLock<HashMap<String, SqlitePool>>>,
+    // Central repo head index kept in sync with repo_root
+    repo_index: Option<RepoIndex>,
+    // Tombstones of deleted records, when they are retained
+    tombstones: Option<TombstoneLog>,
+    // Per-actor data keys for encrypted stores
+    data_keys: Option<Arc<DataKeyManager>>,
+    // Whether new stores are created encrypted
+    encrypt_new: bool,
+    // Per-actor commit locks, so a head read and the commit built on it
+    // can't interleave with another commit
+    commit_locks: Arc<Mutex<HashMap<String, Arc<AsyncMutex<()>>>>>,
+    // Source of commit revisions and record keys
+    ids: Arc<dyn IdGenerator>,
+    // Previous versions kept per record, when record history is on
+    record_history: Option<usize>,
 }
 
 impl ActorStore {
@@ -39,9 +146,105 @@ impl ActorStore {
         Self {
             config,
             db_cache: Arc::new(RwLock::new(HashMap::new())),
+            repo_index: None,
+            tombstones: None,
+            data_keys: None,
+            encrypt_new: false,
+            commit_locks: Arc::new(Mutex::new(HashMap::new())),
+            ids: random_ids(),
+            record_history: None,
         }
     }
 
+    /// Open encrypted stores with per-actor data keys, and optionally
+    /// create new stores encrypted
+    pub fn with_encryption(mut self, data_keys: Arc<DataKeyManager>, encrypt_new: bool) -> Self {
+        self.data_keys = Some(data_keys);
+        self.encrypt_new = encrypt_new;
+        self
+    }
+
+    /// Mirror repo_root changes into a central repo head index
+    pub fn with_repo_index(mut self, repo_index: RepoIndex) -> Self {
+        self.repo_index = Some(repo_index);
+        self
+    }
+
+    /// Log a tombstone for every record a commit deletes
+    pub fn with_tombstone_log(mut self, tombstones: TombstoneLog) -> Self {
+        self.tombstones = Some(tombstones);
+        self
+    }
+
+    /// Generate commit revisions and record keys with `ids`
+    pub fn with_ids(mut self, ids: Arc<dyn IdGenerator>) -> Self {
+        self.ids = ids;
+        self
+    }
+
+    /// Keep the last `depth` versions of each record when it is updated or
+    /// deleted
+    pub fn with_record_history(mut self, depth: usize) -> Self {
+        self.record_history = Some(depth);
+        self
+    }
+
+    /// Source of commit revisions and record keys
+    pub fn ids(&self) -> &Arc<dyn IdGenerator> {
+        &self.ids
+    }
+
+    /// Central repo head index, if configured
+    pub fn repo_index(&self) -> Option<&RepoIndex> {
+        self.repo_index.as_ref()
+    }
+
+    /// Deleted-record tombstone log, if configured
+    pub fn tombstone_log(&self) -> Option<&TombstoneLog> {
+        self.tombstones.as_ref()
+    }
+
+    /// Index hosted repositories that predate the repo head index, and
+    /// mirror record counts of repositories that predate repo stats
+    pub async fn backfill_repo_index(&self) -> PdsResult<usize> {
+        let index = match &self.repo_index {
+            Some(index) => index,
+            None => return Ok(0),
+        };
+
+        let mut count = 0;
+        for did in index.unindexed_dids().await? {
+            if !self.exists(&did).await {
+                continue;
+            }
+            let root = self.get_repo_root(&did).await?;
+            index.upsert(&did, &root.cid, &root.rev).await?;
+            count += 1;
+        }
+
+        for did in index.unsummarized_dids().await? {
+            if self.exists(&did).await {
+                index.set_stats(&did, &self.collection_stats(&did).await?).await?;
+            }
+        }
+
+        Ok(count)
+    }
+
+    /// Hold the actor's commit lock until the guard is dropped
+    ///
+    /// Every commit reads the head, builds on it and moves it while holding
+    /// this lock.
+    pub async fn lock_commits(&self, did: &str) -> OwnedMutexGuard<()> {
+        let lock = {
+            let mut locks = self.commit_locks.lock().unwrap_or_else(|e| e.into_inner());
+            // Drop locks nobody holds or waits on
+            locks.retain(|_, lock| Arc::strong_count(lock) > 1);
+            Arc::clone(locks.entry(did.to_string()).or_default())
+        };
+        lock.lock_owned().await
+    }
+
     /// Get the location information for a DID
     pub fn get_location(&self, did: &str) -> ActorLocation {
         get_actor_location(&self.config.base_directory, did)
@@ -53,6 +256,21 @@ impl ActorStore {
         location.db_location.exists()
     }
 
+    /// Bytes an actor's store takes on disk, including its write-ahead log
+    pub async fn disk_usage(&self, did: &str) -> PdsResult<u64> {
+        let location = self.get_location(did);
+        let mut total = 0;
+        for suffix in ["", "-wal"] {
+            let path = location.directory.join(format!("store.sqlite{}", suffix));
+            match tokio::fs::metadata(&path).await {
+                Ok(meta) => total += meta.len(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(total)
+    }
+
     /// Create a new actor repository
     pub async fn create(&self, did: &str) -> PdsResult<()> {
         let location = self.get_location(did);
@@ -60,17 +278,16 @@ impl ActorStore {
         // Create directory structure
         tokio::fs::create_dir_all(&location.directory).await?;
 
-        // Create the database file connection
-        let pool = SqlitePool::connect_with(
-            sqlx::sqlite::SqliteConnectOptions::new()
-                .filename(&location.db_location)
-                .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-                .foreign_keys(true)
-                .create_if_missing(true)
-                .busy_timeout(std::time::Duration::from_secs(5)),
-        )
-        .await
-        .map_err(|e| PdsError::Database(e))?;
+        // Create the database file connection, encrypted if configured
+        let key = match &self.data_keys {
+            Some(data_keys) if self.encrypt_new => Some(data_keys.get_or_create(did).await?.current),
+            _ => None,
+        };
+        let pool = Self::connect(&location.db_location, true, key.as_ref()).await?;
+
+        // Only takes effect before the first table is created; compaction
+        // switches older stores over
+        sqlx::query("PRAGMA auto_vacuum = INCREMENTAL").execute(&pool).await?;
 
         // Create actor repository schema inline
         sqlx::query(
@@ -85,7 +302,8 @@ impl ActorStore {
             CREATE TABLE IF NOT EXISTS repo_block (
                 cid TEXT PRIMARY KEY NOT NULL,
                 content BLOB NOT NULL,
-                indexed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                indexed_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                repo_rev TEXT
             );
 
             CREATE TABLE IF NOT EXISTS record (
@@ -106,18 +324,33 @@ impl ActorStore {
         .execute(&pool)
         .await?;
 
+        sqlx::query(ACTOR_SCHEMA_UPGRADES).execute(&pool).await?;
+        Self::add_block_revs(&pool).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", RECORDS_DAG_CBOR))
+            .execute(&pool)
+            .await?;
+
         // Initialize empty repository root
+        let (root_cid, root_rev) = (
+            "bafyreihk5ztsfapt6g2cnxbxgbxb7dltipq5pufb4jtwmqrxrxqaygceyq", // Empty MST root
+            "3jzfcijpj2z2a", // Initial TID
+        );
         sqlx::query(
             "INSERT INTO repo_root (did, cid, rev, indexed_at)
              VALUES (?1, ?2, ?3, ?4)"
         )
         .bind(did)
-        .bind("bafyreihk5ztsfapt6g2cnxbxgbxb7dltipq5pufb4jtwmqrxrxqaygceyq") // Empty MST root
-        .bind("3jzfcijpj2z2a") // Initial TID
+        .bind(root_cid)
+        .bind(root_rev)
         .bind(chrono::Utc::now())
         .execute(&pool)
         .await?;
 
+        if let Some(index) = &self.repo_index {
+            index.upsert(did, root_cid, root_rev).await?;
+            index.set_stats(did, &[]).await?;
+        }
+
         // Add to cache
         {
             let mut cache = self.db_cache.write().await;
@@ -144,15 +377,8 @@ impl ActorStore {
             return Err(PdsError::NotFound(format!("Actor repository not found for {}", did)));
         }
 
-        let pool = SqlitePool::connect_with(
-            sqlx::sqlite::SqliteConnectOptions::new()
-                .filename(&location.db_location)
-                .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
-                .foreign_keys(true)
-                .busy_timeout(std::time::Duration::from_secs(5)),
-        )
-        .await
-        .map_err(|e| PdsError::Database(e))?;
+        let pool = self.open_pool(did, &location.db_location).await?;
+        Self::apply_upgrades(&pool).await?;
 
         // Add to cache
         {
@@ -171,6 +397,312 @@ impl ActorStore {
         Ok(pool)
     }
 
+    /// Bring an open actor database up to the current schema
+    async fn apply_upgrades(pool: &SqlitePool) -> PdsResult<()> {
+        sqlx::query(ACTOR_SCHEMA_UPGRADES).execute(pool).await?;
+        Self::add_block_revs(pool).await?;
+        Self::backfill_record_blobs(pool).await?;
+        Self::backfill_collection_stats(pool).await?;
+        Ok(())
+    }
+
+    /// Apply pending schema upgrades to an actor store without caching it
+    ///
+    /// Stores are also upgraded when first opened; this brings one up to
+    /// date ahead of time. Returns whether anything changed, judged by
+    /// `PRAGMA user_version` and SQLite's schema cookie, and the version after.
+    pub async fn upgrade_schema(&self, did: &str) -> PdsResult<(bool, i64)> {
+        let cached = self.db_cache.read().await.get(did).cloned();
+        if let Some(pool) = cached {
+            // Upgraded when it was opened
+            let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await?;
+            return Ok((false, version));
+        }
+
+        let location = self.get_location(did);
+        if !location.db_location.exists() {
+            return Err(PdsError::NotFound(format!("Actor repository not found for {}", did)));
+        }
+        let pool = self.open_pool(did, &location.db_location).await?;
+        let result = async {
+            let before = Self::schema_versions(&pool).await?;
+            Self::apply_upgrades(&pool).await?;
+            let after = Self::schema_versions(&pool).await?;
+            PdsResult::Ok((before != after, after.0))
+        }
+        .await;
+        pool.close().await;
+        result
+    }
+
+    /// (`PRAGMA user_version`, `PRAGMA schema_version`) of a database; the
+    /// latter changes with every DDL statement
+    async fn schema_versions(pool: &SqlitePool) -> PdsResult<(i64, i64)> {
+        let user: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+        let schema: i64 = sqlx::query_scalar("PRAGMA schema_version").fetch_one(pool).await?;
+        Ok((user, schema))
+    }
+
+    /// Paths of every actor database under the store directory
+    pub async fn discover_databases(&self) -> PdsResult<Vec<PathBuf>> {
+        let mut found = Vec::new();
+        let mut shards = match tokio::fs::read_dir(&self.config.base_directory).await {
+            Ok(shards) => shards,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(found),
+            Err(e) => return Err(e.into()),
+        };
+        while let Some(shard) = shards.next_entry().await? {
+            if !shard.file_type().await?.is_dir() {
+                continue;
+            }
+            let mut actors = tokio::fs::read_dir(shard.path()).await?;
+            while let Some(actor) = actors.next_entry().await? {
+                let db = actor.path().join("store.sqlite");
+                if db.is_file() {
+                    found.push(db);
+                }
+            }
+        }
+        found.sort();
+        Ok(found)
+    }
+
+    /// Open an actor database, plaintext or encrypted
+    async fn open_pool(&self, did: &str, path: &Path) -> PdsResult<SqlitePool> {
+        if is_plaintext_db(path)? {
+            return Self::connect(path, false, None).await;
+        }
+
+        let keys = match &self.data_keys {
+            Some(data_keys) => data_keys.actor_keys(did).await?,
+            None => None,
+        }
+        .ok_or_else(|| PdsError::Internal(format!("Actor store for {} is encrypted but has no data key", did)))?;
+
+        // Mid-rotation the store may still be under the previous key
+        let mut last_err = None;
+        for key in keys.candidates() {
+            match Self::connect(path, false, Some(key)).await {
+                Ok(pool) => return Ok(pool),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| PdsError::Internal(format!("Cannot open actor store for {}", did))))
+    }
+
+    /// Connect to an actor database, keyed with SQLCipher if `key` is set
+    async fn connect(path: &Path, create: bool, key: Option<&DataKey>) -> PdsResult<SqlitePool> {
+        let mut options = sqlx::sqlite::SqliteConnectOptions::new()
+            .filename(path)
+            .journal_mode(sqlx::sqlite::SqliteJournalMode::Wal)
+            .foreign_keys(true)
+            .create_if_missing(create)
+            .busy_timeout(std::time::Duration::from_secs(5));
+
+        if let Some(key) = key {
+            // Without SQLCipher the key pragma is ignored and data would be
+            // written in plaintext
+            if !sqlcipher_available().await? {
+                return Err(PdsError::Internal(
+                    "Encrypted actor stores require a build with the `sqlcipher` feature".to_string(),
+                ));
+            }
+            // sqlx applies `key` before any other pragma
+            options = options.pragma("key", key.sqlcipher_key());
+        }
+
+        let pool = SqlitePool::connect_with(options)
+            .await
+            .map_err(|e| PdsError::Database(e))?;
+
+        if key.is_some() {
+            // A wrong key only shows up on first read
+            sqlx::query("SELECT count(*) FROM sqlite_master").fetch_one(&pool).await?;
+        }
+
+        Ok(pool)
+    }
+
+    /// Re-encrypt an actor store under `key` (plaintext stores become encrypted)
+    ///
+    /// The store is exported to a new file with `sqlcipher_export` and swapped
+    /// in place, so this must not run while the server is serving the actor.
+    pub async fn reencrypt(&self, did: &str, key: &DataKey) -> PdsResult<()> {
+        let location = self.get_location(did);
+        let pool = self.open_db(did).await?;
+        let tmp = location.directory.join("store.sqlite.rekey");
+        let _ = tokio::fs::remove_file(&tmp).await;
+
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await?;
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)").execute(&pool).await?;
+        {
+            let mut conn = pool.acquire().await?;
+            let tmp_path = tmp.to_string_lossy().replace('\'', "''");
+            sqlx::query(&format!(
+                "ATTACH DATABASE '{}' AS rekeyed KEY {}",
+                tmp_path,
+                key.sqlcipher_key()
+            ))
+            .execute(&mut *conn)
+            .await?;
+            sqlx::query("SELECT sqlcipher_export('rekeyed')").execute(&mut *conn).await?;
+            sqlx::query(&format!("PRAGMA rekeyed.user_version = {}", version))
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("DETACH DATABASE rekeyed").execute(&mut *conn).await?;
+        }
+
+        self.db_cache.write().await.remove(did);
+        pool.close().await;
+
+        for suffix in ["-wal", "-shm"] {
+            let _ = tokio::fs::remove_file(location.directory.join(format!("store.sqlite{}", suffix))).await;
+        }
+        tokio::fs::rename(&tmp, &location.db_location).await?;
+
+        Ok(())
+    }
+
+    /// Whether an actor store is encrypted
+    pub fn is_encrypted(&self, did: &str) -> PdsResult<bool> {
+        Ok(!is_plaintext_db(&self.get_location(did).db_location)?)
+    }
+
+    /// Add `repo_block.repo_rev` to stores created before it existed
+    ///
+    /// Blocks written since record the rev of the commit that added them, so
+    /// partial exports can select them. Older blocks keep a NULL rev.
+    async fn add_block_revs(pool: &SqlitePool) -> PdsResult<()> {
+        let present: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM pragma_table_info('repo_block') WHERE name = 'repo_rev'"
+        )
+        .fetch_one(pool)
+        .await?;
+        if present == 0 {
+            sqlx::query("ALTER TABLE repo_block ADD COLUMN repo_rev TEXT")
+                .execute(pool)
+                .await?;
+        }
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_repo_block_rev ON repo_block(repo_rev)")
+            .execute(pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Index blob references of records written before `record_blob` existed
+    async fn backfill_record_blobs(pool: &SqlitePool) -> PdsResult<()> {
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+        if version >= RECORD_BLOB_BACKFILLED {
+            return Ok(());
+        }
+
+        let rows = sqlx::query(
+            "SELECT r.uri, b.content FROM record r JOIN repo_block b ON b.cid = r.cid"
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut tx = pool.begin().await?;
+        for row in rows {
+            let uri: String = row.get("uri");
+            let content: Vec<u8> = row.get("content");
+            let Ok(value) = decode_record(&content) else {
+                continue;
+            };
+            for cid in find_blob_refs(&value) {
+                sqlx::query("INSERT OR IGNORE INTO record_blob (blob_cid, record_uri) VALUES (?1, ?2)")
+                    .bind(&cid)
+                    .bind(&uri)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        sqlx::query(&format!("PRAGMA user_version = {}", RECORD_BLOB_BACKFILLED))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// `PRAGMA user_version` of an actor store, read without upgrading it
+    pub async fn schema_version(&self, did: &str) -> PdsResult<i64> {
+        let cached = self.db_cache.read().await.get(did).cloned();
+        if let Some(pool) = cached {
+            return Ok(sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await?);
+        }
+
+        let location = self.get_location(did);
+        if !location.db_location.exists() {
+            return Err(PdsError::NotFound(format!("Actor repository not found for {}", did)));
+        }
+        let pool = self.open_pool(did, &location.db_location).await?;
+        let version = sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await;
+        pool.close().await;
+        Ok(version?)
+    }
+
+    /// Whether the store may still hold records encoded as JSON
+    pub async fn has_legacy_records(&self, did: &str) -> PdsResult<bool> {
+        let pool = self.open_db(did).await?;
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(&pool).await?;
+        Ok(version < RECORDS_DAG_CBOR)
+    }
+
+    /// Record that every record block in the store is DAG-CBOR
+    pub async fn mark_records_migrated(&self, did: &str) -> PdsResult<()> {
+        let pool = self.open_db(did).await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", RECORDS_DAG_CBOR))
+            .execute(&pool)
+            .await?;
+        Ok(())
+    }
+
+    /// Count records written before `collection_stat` existed
+    async fn backfill_collection_stats(pool: &SqlitePool) -> PdsResult<()> {
+        let version: i64 = sqlx::query_scalar("PRAGMA user_version").fetch_one(pool).await?;
+        if version >= COLLECTION_STATS_BACKFILLED {
+            return Ok(());
+        }
+
+        let mut tx = pool.begin().await?;
+        sqlx::query("DELETE FROM collection_stat").execute(&mut *tx).await?;
+        sqlx::query(
+            "INSERT INTO collection_stat (collection, record_count)
+             SELECT collection, COUNT(*) FROM record GROUP BY collection"
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(&format!("PRAGMA user_version = {}", COLLECTION_STATS_BACKFILLED))
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Record counts per collection, ordered by collection
+    pub async fn collection_stats(&self, did: &str) -> PdsResult<Vec<CollectionCount>> {
+        let pool = self.open_db(did).await?;
+
+        let rows = sqlx::query(
+            "SELECT collection, record_count FROM collection_stat
+             WHERE record_count > 0
+             ORDER BY collection"
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| CollectionCount {
+                collection: row.get("collection"),
+                count: row.get("record_count"),
+            })
+            .collect())
+    }
+
     /// Get the current repository root
     pub async fn get_repo_root(&self, did: &str) -> PdsResult<RepoRoot> {
         let pool = self.open_db(did).await?;
@@ -205,6 +737,237 @@ impl ActorStore {
         .execute(&pool)
         .await?;
 
+        // Every write path ends with a new root, so counts are mirrored here
+        if let Some(index) = &self.repo_index {
+            index.upsert(did, cid, rev).await?;
+            index.set_stats(did, &self.collection_stats(did).await?).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply a commit's blocks, record changes and new head in one transaction
+    ///
+    /// Either everything in `batch` is stored, the commit is added to the
+    /// commit history and the head becomes `cid`/`rev`, or nothing is. If
+    /// `batch.prev` is set and the head is no longer that commit, the batch
+    /// is rejected with `InvalidSwap`. With record history on, the versions
+    /// the batch replaces are kept in the same transaction, and the blob
+    /// references they take over and give up are returned. The repo head
+    /// index and the tombstone log live in another database and are updated
+    /// after the transaction commits.
+    pub async fn apply_commit(
+        &self,
+        did: &str,
+        batch: &CommitBatch,
+        cid: &str,
+        rev: &str,
+    ) -> PdsResult<VersionBlobRefs> {
+        let pool = self.open_db(did).await?;
+        let now = chrono::Utc::now();
+        // Dropping the transaction on an early return rolls it back
+        let mut tx = pool.begin().await?;
+        let mut deleted: Vec<(String, Option<String>)> = Vec::new();
+        let mut version_refs = VersionBlobRefs::default();
+
+        for (block_cid, content) in &batch.blocks {
+            sqlx::query(
+                "INSERT INTO repo_block (cid, content, indexed_at, repo_rev)
+                 VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(cid) DO UPDATE SET repo_rev = excluded.repo_rev"
+            )
+            .bind(block_cid)
+            .bind(content)
+            .bind(now)
+            .bind(rev)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        for change in &batch.changes {
+            let uri = match change {
+                RecordChange::Put { uri, cid, collection, rkey, .. } => {
+                    if self.record_history.is_some() {
+                        let old_cid: Option<String> = sqlx::query_scalar("SELECT cid FROM record WHERE uri = ?1")
+                            .bind(uri)
+                            .fetch_optional(&mut *tx)
+                            .await?;
+                        if let Some(old_cid) = old_cid.filter(|old| old != cid) {
+                            self.keep_record_version(&mut tx, uri, &old_cid, rev, now, &mut version_refs)
+                                .await?;
+                        }
+                    }
+                    sqlx::query(
+                        "INSERT INTO record (uri, cid, collection, rkey, repo_rev, indexed_at, takedown_ref)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, NULL)
+                         ON CONFLICT(uri) DO UPDATE SET
+                            cid = excluded.cid,
+                            repo_rev = excluded.repo_rev,
+                            indexed_at = excluded.indexed_at"
+                    )
+                    .bind(uri)
+                    .bind(cid)
+                    .bind(collection)
+                    .bind(rkey)
+                    .bind(rev)
+                    .bind(now)
+                    .execute(&mut *tx)
+                    .await?;
+                    uri
+                }
+                RecordChange::Delete { uri } => {
+                    let old_cid: Option<String> = sqlx::query_scalar("DELETE FROM record WHERE uri = ?1 RETURNING cid")
+                        .bind(uri)
+                        .fetch_optional(&mut *tx)
+                        .await?;
+                    if let Some(old_cid) = &old_cid {
+                        self.keep_record_version(&mut tx, uri, old_cid, rev, now, &mut version_refs)
+                            .await?;
+                    }
+                    if old_cid.is_some() {
+                        deleted.push((uri.clone(), old_cid));
+                    }
+                    uri
+                }
+            };
+
+            sqlx::query("DELETE FROM record_blob WHERE record_uri = ?1")
+                .bind(uri)
+                .execute(&mut *tx)
+                .await?;
+            if let RecordChange::Put { blobs, .. } = change {
+                for blob_cid in blobs {
+                    sqlx::query("INSERT OR IGNORE INTO record_blob (blob_cid, record_uri) VALUES (?1, ?2)")
+                        .bind(blob_cid)
+                        .bind(uri)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+        }
+
+        sqlx::query(
+            "INSERT INTO repo_commit (rev, cid, op_count, committed_at) VALUES (?1, ?2, ?3, ?4)
+             ON CONFLICT(rev) DO UPDATE SET cid = excluded.cid, op_count = excluded.op_count"
+        )
+        .bind(rev)
+        .bind(cid)
+        .bind(batch.changes.len() as i64)
+        .bind(now)
+        .execute(&mut *tx)
+        .await?;
+
+        for block_cid in &batch.dropped_blocks {
+            sqlx::query("DELETE FROM repo_block WHERE cid = ?1")
+                .bind(block_cid)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        let updated = sqlx::query(
+            "UPDATE repo_root SET cid = ?1, rev = ?2, indexed_at = ?3
+             WHERE did = ?4 AND (?5 IS NULL OR cid = ?5)"
+        )
+        .bind(cid)
+        .bind(rev)
+        .bind(now)
+        .bind(did)
+        .bind(&batch.prev)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+        if updated == 0 {
+            return Err(match &batch.prev {
+                Some(prev) => PdsError::InvalidSwap(format!("Commit was no longer at {}", prev)),
+                None => PdsError::NotFound("Repository root not found".to_string()),
+            });
+        }
+
+        tx.commit().await?;
+
+        // The commit is durable by now, so a failed tombstone write is only logged
+        if let (Some(tombstones), false) = (&self.tombstones, deleted.is_empty()) {
+            if let Err(e) = tombstones.record(did, rev, &deleted).await {
+                tracing::warn!("Failed to log tombstones for {}: {}", did, e);
+            }
+        }
+
+        if let Some(index) = &self.repo_index {
+            index.upsert(did, cid, rev).await?;
+            index.set_stats(did, &self.collection_stats(did).await?).await?;
+        }
+
+        Ok(version_refs)
+    }
+
+    /// Keep the record block `cid` as a previous version of `uri`, replaced
+    /// in revision `rev`, and drop versions beyond the configured depth
+    ///
+    /// Runs inside the commit transaction, before the commit can drop the
+    /// block or the record's blob references. The version takes over those
+    /// references, so a rollback can still use the blobs; they are added to
+    /// `refs.kept`, and those of dropped versions to `refs.pruned`. Does
+    /// nothing when record history is off or the block is already gone.
+    async fn keep_record_version(
+        &self,
+        tx: &mut sqlx::Transaction<'_, sqlx::Sqlite>,
+        uri: &str,
+        cid: &str,
+        rev: &str,
+        now: chrono::DateTime<chrono::Utc>,
+        refs: &mut VersionBlobRefs,
+    ) -> PdsResult<()> {
+        let Some(depth) = self.record_history else {
+            return Ok(());
+        };
+        let content: Option<Vec<u8>> = sqlx::query_scalar("SELECT content FROM repo_block WHERE cid = ?1")
+            .bind(cid)
+            .fetch_optional(&mut **tx)
+            .await?;
+        let Some(content) = content else {
+            return Ok(());
+        };
+
+        let version_id: i64 = sqlx::query_scalar(
+            "INSERT INTO record_version (uri, cid, replaced_rev, content, replaced_at)
+             VALUES (?1, ?2, ?3, ?4, ?5)
+             RETURNING id"
+        )
+        .bind(uri)
+        .bind(cid)
+        .bind(rev)
+        .bind(content)
+        .bind(now)
+        .fetch_one(&mut **tx)
+        .await?;
+        let kept: Vec<String> = sqlx::query_scalar(
+            "INSERT INTO record_version_blob (version_id, blob_cid)
+             SELECT ?1, blob_cid FROM record_blob WHERE record_uri = ?2
+             RETURNING blob_cid"
+        )
+        .bind(version_id)
+        .bind(uri)
+        .fetch_all(&mut **tx)
+        .await?;
+        refs.kept.extend(kept);
+
+        let pruned: Vec<i64> = sqlx::query_scalar(
+            "DELETE FROM record_version WHERE uri = ?1 AND id NOT IN
+             (SELECT id FROM record_version WHERE uri = ?1 ORDER BY id DESC LIMIT ?2)
+             RETURNING id"
+        )
+        .bind(uri)
+        .bind(depth as i64)
+        .fetch_all(&mut **tx)
+        .await?;
+        for version_id in pruned {
+            let released: Vec<String> =
+                sqlx::query_scalar("DELETE FROM record_version_blob WHERE version_id = ?1 RETURNING blob_cid")
+                    .bind(version_id)
+                    .fetch_all(&mut **tx)
+                    .await?;
+            refs.pruned.extend(released);
+        }
         Ok(())
     }
 
@@ -287,6 +1050,38 @@ impl ActorStore {
         Ok(records)
     }
 
+    /// Newest records of a collection (by rkey), leaving out taken-down ones
+    pub async fn list_recent_records(&self, did: &str, collection: &str, limit: i64) -> PdsResult<Vec<Record>> {
+        let pool = self.open_db(did).await?;
+
+        let rows = sqlx::query(
+            "SELECT uri, cid, collection, rkey, repo_rev, indexed_at, takedown_ref
+             FROM record
+             WHERE collection = ?1 AND takedown_ref IS NULL
+             ORDER BY rkey DESC
+             LIMIT ?2"
+        )
+        .bind(collection)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        let records = rows
+            .into_iter()
+            .map(|row| Record {
+                uri: row.get("uri"),
+                cid: row.get("cid"),
+                collection: row.get("collection"),
+                rkey: row.get("rkey"),
+                repo_rev: row.get("repo_rev"),
+                indexed_at: row.get("indexed_at"),
+                takedown_ref: row.get("takedown_ref"),
+            })
+            .collect();
+
+        Ok(records)
+    }
+
     /// Get all distinct collections for a DID
     pub async fn get_collections(&self, did: &str) -> PdsResult<Vec<String>> {
         let pool = self.open_db(did).await?;
@@ -342,9 +1137,91 @@ impl ActorStore {
             .execute(&pool)
             .await?;
 
+        sqlx::query("DELETE FROM record_blob WHERE record_uri = ?1")
+            .bind(uri)
+            .execute(&pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replace the set of blobs a record references
+    pub async fn set_record_blobs(&self, did: &str, uri: &str, blob_cids: &[String]) -> PdsResult<()> {
+        let pool = self.open_db(did).await?;
+        let mut tx = pool.begin().await?;
+
+        sqlx::query("DELETE FROM record_blob WHERE record_uri = ?1")
+            .bind(uri)
+            .execute(&mut *tx)
+            .await?;
+
+        for cid in blob_cids {
+            sqlx::query("INSERT OR IGNORE INTO record_blob (blob_cid, record_uri) VALUES (?1, ?2)")
+                .bind(cid)
+                .bind(uri)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// CIDs of the blobs a record references
+    pub async fn get_record_blobs(&self, did: &str, uri: &str) -> PdsResult<Vec<String>> {
+        let pool = self.open_db(did).await?;
+
+        let cids = sqlx::query_scalar("SELECT blob_cid FROM record_blob WHERE record_uri = ?1 ORDER BY blob_cid")
+            .bind(uri)
+            .fetch_all(&pool)
+            .await?;
+
+        Ok(cids)
+    }
+
+    /// Number of records referencing a blob
+    pub async fn count_blob_refs(&self, did: &str, blob_cid: &str) -> PdsResult<usize> {
+        let pool = self.open_db(did).await?;
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM record_blob WHERE blob_cid = ?1")
+            .bind(blob_cid)
+            .fetch_one(&pool)
+            .await?;
+
+        Ok(count as usize)
+    }
+
+    /// List CIDs of blobs referenced by the repository, ordered by CID
+    ///
+    /// With `since`, only blobs referenced by records written after that
+    /// revision are returned. Paginates on CID, starting after `cursor`.
+    pub async fn list_blobs(
+        &self,
+        did: &str,
+        since: Option<&str>,
+        cursor: Option<&str>,
+        limit: i64,
+    ) -> PdsResult<Vec<String>> {
+        let pool = self.open_db(did).await?;
+
+        let cids = sqlx::query_scalar(
+            "SELECT DISTINCT rb.blob_cid
+             FROM record_blob rb
+             JOIN record r ON r.uri = rb.record_uri
+             WHERE (?1 IS NULL OR r.repo_rev > ?1)
+               AND rb.blob_cid > ?2
+             ORDER BY rb.blob_cid
+             LIMIT ?3"
+        )
+        .bind(since)
+        .bind(cursor.unwrap_or(""))
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(cids)
+    }
+
     /// Count records in a collection
     pub async fn count_records(&self, did: &str, collection: &str) -> PdsResult<i64> {
         let pool = self.open_db(did).await?;
@@ -404,6 +1281,35 @@ impl ActorStore {
         Ok(blocks)
     }
 
+    /// One page of blocks, in storage order, after row `after`
+    ///
+    /// With `since`, only blocks written by commits after that rev are
+    /// returned, plus blocks that predate rev tracking. Returns
+    /// `(rowid, cid, content)` so the next page can start after the last row.
+    pub async fn list_blocks_page(
+        &self,
+        did: &str,
+        since: Option<&str>,
+        after: i64,
+        limit: i64,
+    ) -> PdsResult<Vec<(i64, String, Vec<u8>)>> {
+        let pool = self.open_db(did).await?;
+
+        let blocks: Vec<(i64, String, Vec<u8>)> = sqlx::query_as(
+            "SELECT rowid, cid, content FROM repo_block
+             WHERE rowid > ?1 AND (?2 IS NULL OR repo_rev IS NULL OR repo_rev > ?2)
+             ORDER BY rowid
+             LIMIT ?3"
+        )
+        .bind(after)
+        .bind(since)
+        .bind(limit)
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(blocks)
+    }
+
     /// Get specific blocks by CIDs
     pub async fn get_blocks_by_cids(&self, did: &str, cids: &[String]) -> PdsResult<Vec<(String, Vec<u8>)>> {
         let pool = self.open_db(did).await?;
@@ -446,6 +1352,89 @@ impl ActorStore {
         Ok(records)
     }
 
+    /// List records written after a repo revision, oldest first
+    pub async fn list_records_since(&self, did: &str, rev: &str) -> PdsResult<Vec<Record>> {
+        let pool = self.open_db(did).await?;
+
+        let rows = sqlx::query(
+            "SELECT uri, cid, collection, rkey, repo_rev, indexed_at, takedown_ref
+             FROM record
+             WHERE repo_rev > ?1
+             ORDER BY repo_rev"
+        )
+        .bind(rev)
+        .fetch_all(&pool)
+        .await?;
+
+        let records = rows
+            .into_iter()
+            .map(|row| Record {
+                uri: row.get("uri"),
+                cid: row.get("cid"),
+                collection: row.get("collection"),
+                rkey: row.get("rkey"),
+                repo_rev: row.get("repo_rev"),
+                indexed_at: row.get("indexed_at"),
+                takedown_ref: row.get("takedown_ref"),
+            })
+            .collect();
+
+        Ok(records)
+    }
+
+    /// Get stored preferences in a namespace, in the order they were written
+    pub async fn get_preferences(&self, did: &str, namespace: &str) -> PdsResult<Vec<AccountPref>> {
+        let pool = self.open_db(did).await?;
+
+        let prefs = sqlx::query_as::<_, AccountPref>(
+            "SELECT id, name, value_json FROM account_pref ORDER BY id"
+        )
+        .fetch_all(&pool)
+        .await?;
+
+        Ok(prefs
+            .into_iter()
+            .filter(|pref| pref_in_namespace(&pref.name, namespace))
+            .collect())
+    }
+
+    /// Replace preferences in a namespace
+    ///
+    /// Existing preferences for which `replaceable` returns false are kept,
+    /// so callers with a restricted scope can't clear preferences they can't see.
+    /// `values` are `(name, value_json)` pairs.
+    pub async fn put_preferences(
+        &self,
+        did: &str,
+        namespace: &str,
+        values: Vec<(String, String)>,
+        replaceable: impl Fn(&str) -> bool,
+    ) -> PdsResult<()> {
+        let pool = self.open_db(did).await?;
+        let existing = self.get_preferences(did, namespace).await?;
+
+        let mut tx = pool.begin().await?;
+
+        for pref in existing.iter().filter(|pref| replaceable(&pref.name)) {
+            sqlx::query("DELETE FROM account_pref WHERE id = ?1")
+                .bind(pref.id)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        for (name, value_json) in values {
+            sqlx::query("INSERT INTO account_pref (name, value_json) VALUES (?1, ?2)")
+                .bind(name)
+                .bind(value_json)
+                .execute(&mut *tx)
+                .await?;
+        }
+
+        tx.commit().await?;
+
+        Ok(())
+    }
+
     /// Destroy an actor's repository (delete all data)
     pub async fn destroy(&self, did: &str) -> PdsResult<()> {
         let location = self.get_location(did);
@@ -456,10 +1445,17 @@ impl ActorStore {
             cache.remove(did);
         }
 
-        // Delete directory
+        // Delete directory (including the actor's data key)
         if location.directory.exists() {
             tokio::fs::remove_dir_all(&location.directory).await?;
         }
+        if let Some(data_keys) = &self.data_keys {
+            data_keys.forget(did);
+        }
+
+        if let Some(index) = &self.repo_index {
+            index.remove(did).await?;
+        }
 
         Ok(())
     }
@@ -0,0 +1,23 @@
+/// Fixtures shared by tests that need an actor store
+use crate::actor_store::{ActorStore, ActorStoreConfig};
+use tempfile::TempDir;
+
+/// An empty actor store in a fresh temporary directory
+///
+/// Stores live in `actors/` under the directory, leaving the rest free for
+/// whatever else a test keeps on disk; everything is removed when the
+/// `TempDir` is dropped.
+pub fn temp_store() -> (TempDir, ActorStore) {
+    let dir = tempfile::tempdir().unwrap();
+    let store = ActorStore::new(ActorStoreConfig {
+        base_directory: dir.path().join("actors"),
+        cache_size: 10,
+    });
+    (dir, store)
+}
+
+/// Commit signer that returns a zeroed signature, for tests that never
+/// verify one
+pub async fn dummy_signer(_hash: [u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError> {
+    Ok(vec![0u8; 64])
+}
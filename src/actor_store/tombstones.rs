@@ -0,0 +1,181 @@
+/// Deleted-record tombstone log
+///
+/// With `PDS_TOMBSTONE_RETENTION_DAYS` set, the actor store writes a
+/// tombstone (uri, cid, revision, deletion time) to the `record_tombstone`
+/// table in the account database for every record a commit deletes. Admins
+/// read them back for abuse investigations and for reconciling clients that
+/// missed the deletes; the `tombstone_retention` job purges them once they
+/// are past the window.
+use crate::error::PdsResult;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+
+/// A record deleted from a repository
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordTombstone {
+    pub id: i64,
+    pub did: String,
+    pub uri: String,
+    pub collection: String,
+    /// CID of the record when it was deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    /// Revision of the deleting commit
+    pub rev: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Filters for reading tombstones
+///
+/// Results are newest first; `cursor` is the id of the last tombstone of
+/// the previous page.
+#[derive(Debug, Clone, Default)]
+pub struct TombstoneQuery {
+    pub did: Option<String>,
+    pub collection: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub cursor: Option<i64>,
+    pub limit: i64,
+}
+
+/// Tombstone log (account database)
+#[derive(Clone)]
+pub struct TombstoneLog {
+    db: SqlitePool,
+}
+
+impl TombstoneLog {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Record the `(uri, cid)` pairs deleted by the commit `rev` of `did`
+    pub async fn record(&self, did: &str, rev: &str, deleted: &[(String, Option<String>)]) -> PdsResult<()> {
+        let now = Utc::now();
+        let mut tx = self.db.begin().await?;
+
+        for (uri, cid) in deleted {
+            let collection = uri
+                .trim_start_matches("at://")
+                .split('/')
+                .nth(1)
+                .unwrap_or_default();
+            sqlx::query(
+                "INSERT INTO record_tombstone (did, uri, collection, cid, rev, deleted_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )
+            .bind(did)
+            .bind(uri)
+            .bind(collection)
+            .bind(cid)
+            .bind(rev)
+            .bind(now.to_rfc3339())
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Tombstones matching a query, newest first
+    pub async fn list(&self, query: &TombstoneQuery) -> PdsResult<Vec<RecordTombstone>> {
+        let rows = sqlx::query(
+            "SELECT id, did, uri, collection, cid, rev, deleted_at
+             FROM record_tombstone
+             WHERE (?1 IS NULL OR did = ?1)
+               AND (?2 IS NULL OR collection = ?2)
+               AND (?3 IS NULL OR deleted_at >= ?3)
+               AND (?4 IS NULL OR id < ?4)
+             ORDER BY id DESC
+             LIMIT ?5",
+        )
+        .bind(&query.did)
+        .bind(&query.collection)
+        .bind(query.since.map(|t| t.to_rfc3339()))
+        .bind(query.cursor)
+        .bind(query.limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter()
+            .map(|row| -> PdsResult<RecordTombstone> {
+                let deleted_at: String = row.try_get("deleted_at")?;
+                Ok(RecordTombstone {
+                    id: row.try_get("id")?,
+                    did: row.try_get("did")?,
+                    uri: row.try_get("uri")?,
+                    collection: row.try_get("collection")?,
+                    cid: row.try_get("cid")?,
+                    rev: row.try_get("rev")?,
+                    deleted_at: DateTime::parse_from_rfc3339(&deleted_at)
+                        .map(|t| t.with_timezone(&Utc))
+                        .unwrap_or_else(|_| Utc::now()),
+                })
+            })
+            .collect()
+    }
+
+    /// Delete tombstones of records deleted before `cutoff`
+    pub async fn prune(&self, cutoff: DateTime<Utc>) -> PdsResult<u64> {
+        let result = sqlx::query("DELETE FROM record_tombstone WHERE deleted_at < ?1")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.db)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_record_list_and_prune() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let log = TombstoneLog::new(db.clone());
+
+        log.record(
+            "did:plc:alice",
+            "3rev1",
+            &[
+                ("at://did:plc:alice/app.bsky.feed.post/1".to_string(), Some("bafyone".to_string())),
+                ("at://did:plc:alice/app.bsky.feed.like/2".to_string(), None),
+            ],
+        )
+        .await
+        .unwrap();
+        log.record("did:plc:bob", "3rev2", &[("at://did:plc:bob/app.bsky.feed.post/3".to_string(), None)])
+            .await
+            .unwrap();
+
+        let all = log.list(&TombstoneQuery { limit: 10, ..Default::default() }).await.unwrap();
+        assert_eq!(all.iter().map(|t| t.id).collect::<Vec<_>>(), vec![3, 2, 1]);
+        assert_eq!(all[2].collection, "app.bsky.feed.post");
+        assert_eq!(all[2].cid.as_deref(), Some("bafyone"));
+
+        let posts = TombstoneQuery {
+            did: Some("did:plc:alice".to_string()),
+            collection: Some("app.bsky.feed.post".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(log.list(&posts).await.unwrap().len(), 1);
+
+        let page = TombstoneQuery { cursor: Some(2), limit: 10, ..Default::default() };
+        assert_eq!(log.list(&page).await.unwrap()[0].id, 1);
+
+        // Backdate the first tombstone past the window
+        let old = (Utc::now() - chrono::Duration::days(60)).to_rfc3339();
+        sqlx::query("UPDATE record_tombstone SET deleted_at = ?1 WHERE id = 1")
+            .bind(&old)
+            .execute(&db)
+            .await
+            .unwrap();
+        assert_eq!(log.prune(Utc::now() - chrono::Duration::days(30)).await.unwrap(), 1);
+        assert_eq!(log.list(&TombstoneQuery { limit: 10, ..Default::default() }).await.unwrap().len(), 2);
+    }
+}
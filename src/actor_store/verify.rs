@@ -0,0 +1,681 @@
+/// Repository verification
+///
+/// Checks a repository - stored locally or read from a CAR file - end to end:
+/// the head commit decodes and is signed by the account's `#atproto` key,
+/// the MST is well formed and in canonical shape, and every block hashes to
+/// its CID. Problems are collected into a [`VerificationReport`] instead of
+/// stopping at the first one, so com.atproto.repo.importRepo can say why it
+/// rejected a CAR and `aurora-locus repair` can show what it would fix.
+use crate::{
+    actor_store::{mst::Node, ActorStore},
+    car::verify_block,
+    error::{PdsError, PdsResult},
+    identity::IdentityResolver,
+};
+use async_trait::async_trait;
+use atproto::{mst::calculate_key_layer, repo::UnsignedCommit};
+use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature, VerifyingKey};
+use libipld::{cbor::DagCborCodec, codec::Codec, Cid, Ipld};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr;
+
+/// Multicodec prefix for secp256k1 public keys in multikey encoding
+pub const SECP256K1_MULTICODEC: [u8; 2] = [0xe7, 0x01];
+
+/// Where blocks are read from
+#[async_trait]
+pub trait BlockSource: Send + Sync {
+    async fn get_block(&self, cid: &Cid) -> PdsResult<Option<Vec<u8>>>;
+}
+
+/// Blocks of a CAR file
+#[async_trait]
+impl BlockSource for HashMap<Cid, Vec<u8>> {
+    async fn get_block(&self, cid: &Cid) -> PdsResult<Option<Vec<u8>>> {
+        Ok(self.get(cid).cloned())
+    }
+}
+
+/// Blocks of a repository in the actor store
+pub struct StoreBlocks<'a> {
+    pub store: &'a ActorStore,
+    pub did: &'a str,
+}
+
+#[async_trait]
+impl BlockSource for StoreBlocks<'_> {
+    async fn get_block(&self, cid: &Cid) -> PdsResult<Option<Vec<u8>>> {
+        self.store.get_block(self.did, &cid.to_string()).await
+    }
+}
+
+/// Kind of problem found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IssueKind {
+    /// A referenced block is not present
+    MissingBlock,
+    /// Block contents do not hash to the CID they are stored under
+    CidMismatch,
+    /// The commit block cannot be decoded
+    InvalidCommit,
+    /// The commit is for a different DID
+    DidMismatch,
+    /// The commit signature does not verify against the signing key
+    InvalidSignature,
+    /// An MST node cannot be decoded, or has an invalid key or empty node
+    InvalidMstNode,
+    /// MST keys are not in strictly increasing order
+    UnsortedKeys,
+    /// A key or node sits at the wrong tree layer
+    WrongLayer,
+    /// The record index disagrees with the MST (local repositories only)
+    IndexMismatch,
+}
+
+/// A single problem
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationIssue {
+    pub kind: IssueKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cid: Option<String>,
+    /// MST key (`collection/rkey`) the problem concerns
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    pub message: String,
+}
+
+/// Outcome of verifying a repository
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerificationReport {
+    pub did: String,
+    pub commit: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rev: Option<String>,
+    /// Whether the commit signature was checked and is valid
+    pub signature_verified: bool,
+    pub mst_nodes: usize,
+    pub records: usize,
+    pub issues: Vec<VerificationIssue>,
+    /// Every (key, record CID) in the MST, in key order
+    #[serde(skip)]
+    pub entries: Vec<(String, Cid)>,
+}
+
+impl VerificationReport {
+    fn new(did: &str, commit: &Cid) -> Self {
+        Self {
+            did: did.to_string(),
+            commit: commit.to_string(),
+            rev: None,
+            signature_verified: false,
+            mst_nodes: 0,
+            records: 0,
+            issues: Vec::new(),
+            entries: Vec::new(),
+        }
+    }
+
+    /// Whether no problems were found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// One line per issue, for logs and error messages
+    pub fn summary(&self) -> String {
+        if self.is_valid() {
+            return format!(
+                "{}: ok ({} records, {} MST nodes)",
+                self.did, self.records, self.mst_nodes
+            );
+        }
+
+        let mut out = format!("{}: {} issue(s)", self.did, self.issues.len());
+        for issue in &self.issues {
+            out.push_str(&format!("\n  {:?}: {}", issue.kind, issue.message));
+        }
+        out
+    }
+
+    fn issue(&mut self, kind: IssueKind, cid: Option<&Cid>, key: Option<&str>, message: String) {
+        self.issues.push(VerificationIssue {
+            kind,
+            cid: cid.map(Cid::to_string),
+            key: key.map(String::from),
+            message,
+        });
+    }
+}
+
+/// Verify the repository whose head commit is `commit_cid`
+///
+/// The signature is only checked when `key` is given. Errors are returned
+/// only when the block source itself fails; everything wrong with the
+/// repository ends up in the report.
+pub async fn verify_repo<S: BlockSource + ?Sized>(
+    source: &S,
+    did: &str,
+    commit_cid: &Cid,
+    key: Option<&VerifyingKey>,
+) -> PdsResult<VerificationReport> {
+    let mut report = VerificationReport::new(did, commit_cid);
+
+    let Some(bytes) = load(source, commit_cid, &mut report).await? else {
+        return Ok(report);
+    };
+    let (commit, sig) = match decode_commit(&bytes) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            report.issue(IssueKind::InvalidCommit, Some(commit_cid), None, e.to_string());
+            return Ok(report);
+        }
+    };
+    report.rev = Some(commit.rev.clone());
+
+    if commit.did != did {
+        report.issue(
+            IssueKind::DidMismatch,
+            Some(commit_cid),
+            None,
+            format!("Commit is for {}", commit.did),
+        );
+    }
+
+    if let Some(key) = key {
+        match check_signature(&commit, &sig, key) {
+            Ok(()) => report.signature_verified = true,
+            Err(message) => report.issue(IssueKind::InvalidSignature, Some(commit_cid), None, message),
+        }
+    }
+
+    let nodes = load_tree(source, &commit.data, &mut report).await?;
+    report.mst_nodes = nodes.len();
+    if nodes.contains_key(&commit.data) {
+        let mut last_key = None;
+        check_node(&nodes, &commit.data, None, true, &mut last_key, &mut report);
+    }
+
+    let entries = std::mem::take(&mut report.entries);
+    for (key, cid) in &entries {
+        if let Some(bytes) = source.get_block(cid).await? {
+            if verify_block(cid, &bytes).is_err() {
+                report.issue(
+                    IssueKind::CidMismatch,
+                    Some(cid),
+                    Some(key),
+                    "Record bytes do not match the record CID".to_string(),
+                );
+            }
+        } else {
+            report.issue(IssueKind::MissingBlock, Some(cid), Some(key), "Record block is missing".to_string());
+        }
+    }
+    report.records = entries.len();
+    report.entries = entries;
+
+    Ok(report)
+}
+
+/// Verify a local repository, including its record index
+pub async fn verify_local(
+    store: &ActorStore,
+    did: &str,
+    key: Option<&VerifyingKey>,
+) -> PdsResult<VerificationReport> {
+    let head = store.get_repo_root(did).await?;
+    let commit_cid = Cid::from_str(&head.cid)
+        .map_err(|e| PdsError::Internal(format!("Invalid repo root {}: {}", head.cid, e)))?;
+
+    let mut report = verify_repo(&StoreBlocks { store, did }, did, &commit_cid, key).await?;
+    if report.rev.as_deref().map_or(false, |rev| rev != head.rev) {
+        report.issue(
+            IssueKind::IndexMismatch,
+            Some(&commit_cid),
+            None,
+            format!("Stored root rev {} does not match the commit", head.rev),
+        );
+    }
+
+    // Only compare against a tree that could be read completely
+    let tree_readable = !report.issues.iter().any(|i| {
+        i.key.is_none()
+            && matches!(
+                i.kind,
+                IssueKind::MissingBlock | IssueKind::CidMismatch | IssueKind::InvalidCommit | IssueKind::InvalidMstNode
+            )
+    });
+    if !tree_readable {
+        return Ok(report);
+    }
+
+    let mut in_tree: HashMap<String, Cid> = report.entries.iter().cloned().collect();
+    for record in store.list_all_records(did).await? {
+        let key = format!("{}/{}", record.collection, record.rkey);
+        match in_tree.remove(&key) {
+            Some(cid) if cid.to_string() == record.cid => {}
+            Some(cid) => report.issue(
+                IssueKind::IndexMismatch,
+                Some(&cid),
+                Some(&key),
+                format!("Record index has CID {}", record.cid),
+            ),
+            None => report.issue(
+                IssueKind::IndexMismatch,
+                None,
+                Some(&key),
+                "Indexed record is not in the MST".to_string(),
+            ),
+        }
+    }
+
+    let mut missing: Vec<_> = in_tree.into_iter().collect();
+    missing.sort();
+    for (key, cid) in missing {
+        report.issue(
+            IssueKind::IndexMismatch,
+            Some(&cid),
+            Some(&key),
+            "MST record is not in the record index".to_string(),
+        );
+    }
+
+    Ok(report)
+}
+
+/// Fetch a block and check it against its CID
+async fn load<S: BlockSource + ?Sized>(
+    source: &S,
+    cid: &Cid,
+    report: &mut VerificationReport,
+) -> PdsResult<Option<Vec<u8>>> {
+    let Some(bytes) = source.get_block(cid).await? else {
+        report.issue(IssueKind::MissingBlock, Some(cid), None, format!("Block {} is missing", cid));
+        return Ok(None);
+    };
+
+    if let Err(e) = verify_block(cid, &bytes) {
+        report.issue(IssueKind::CidMismatch, Some(cid), None, e.to_string());
+        return Ok(None);
+    }
+
+    Ok(Some(bytes))
+}
+
+/// Load every reachable MST node
+async fn load_tree<S: BlockSource + ?Sized>(
+    source: &S,
+    root: &Cid,
+    report: &mut VerificationReport,
+) -> PdsResult<HashMap<Cid, Node>> {
+    let mut nodes = HashMap::new();
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::from([*root]);
+
+    while let Some(cid) = queue.pop_front() {
+        if !seen.insert(cid) {
+            continue;
+        }
+        let Some(bytes) = load(source, &cid, report).await? else {
+            continue;
+        };
+
+        match Node::decode(&bytes) {
+            Ok(node) => {
+                queue.extend(node.children());
+                nodes.insert(cid, node);
+            }
+            Err(e) => report.issue(IssueKind::InvalidMstNode, Some(&cid), None, e.to_string()),
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// Check a subtree in key order
+///
+/// `layer` is the layer the node must be at (unknown for the root).
+fn check_node(
+    nodes: &HashMap<Cid, Node>,
+    cid: &Cid,
+    layer: Option<u32>,
+    is_root: bool,
+    last_key: &mut Option<String>,
+    report: &mut VerificationReport,
+) {
+    // Missing or undecodable nodes were reported while loading
+    let Some(node) = nodes.get(cid) else {
+        return;
+    };
+
+    let node_layer = match node.entries.first() {
+        Some(first) => Some(calculate_key_layer(first.key.as_bytes())),
+        None if is_root && node.left.is_none() => return,
+        None if is_root => {
+            report.issue(
+                IssueKind::WrongLayer,
+                Some(cid),
+                None,
+                "Root node has no entries of its own".to_string(),
+            );
+            None
+        }
+        None if node.left.is_none() => {
+            report.issue(IssueKind::InvalidMstNode, Some(cid), None, "Empty subtree node".to_string());
+            return;
+        }
+        None => layer,
+    };
+
+    if let (Some(expected), Some(actual)) = (layer, node_layer) {
+        if expected != actual {
+            report.issue(
+                IssueKind::WrongLayer,
+                Some(cid),
+                None,
+                format!("Node is at layer {} but belongs at layer {}", actual, expected),
+            );
+        }
+    }
+
+    let child_layer = match node_layer {
+        Some(0) => {
+            if node.children().next().is_some() {
+                report.issue(
+                    IssueKind::WrongLayer,
+                    Some(cid),
+                    None,
+                    "Layer 0 node has subtrees".to_string(),
+                );
+            }
+            None
+        }
+        other => other.map(|l| l - 1),
+    };
+
+    if let Some(left) = &node.left {
+        check_node(nodes, left, child_layer, false, last_key, report);
+    }
+
+    for entry in &node.entries {
+        if !valid_key(&entry.key) {
+            report.issue(
+                IssueKind::InvalidMstNode,
+                Some(cid),
+                Some(&entry.key),
+                "Key is not collection/rkey".to_string(),
+            );
+        }
+        if last_key.as_deref().map_or(false, |last| entry.key.as_str() <= last) {
+            report.issue(
+                IssueKind::UnsortedKeys,
+                Some(cid),
+                Some(&entry.key),
+                "Key is out of order".to_string(),
+            );
+        }
+        let entry_layer = calculate_key_layer(entry.key.as_bytes());
+        if node_layer.map_or(false, |l| l != entry_layer) {
+            report.issue(
+                IssueKind::WrongLayer,
+                Some(cid),
+                Some(&entry.key),
+                format!("Key belongs at layer {}", entry_layer),
+            );
+        }
+
+        report.entries.push((entry.key.clone(), entry.value));
+        *last_key = Some(entry.key.clone());
+
+        if let Some(right) = &entry.right {
+            check_node(nodes, right, child_layer, false, last_key, report);
+        }
+    }
+}
+
+/// `collection/rkey` with both parts non-empty
+fn valid_key(key: &str) -> bool {
+    match key.split_once('/') {
+        Some((collection, rkey)) => !collection.is_empty() && !rkey.is_empty() && !rkey.contains('/'),
+        None => false,
+    }
+}
+
+fn check_signature(commit: &UnsignedCommit, sig: &[u8], key: &VerifyingKey) -> Result<(), String> {
+    let hash = commit
+        .signing_hash()
+        .map_err(|e| format!("Failed to hash commit: {}", e))?;
+    let signature = Signature::from_slice(sig).map_err(|_| "Malformed commit signature".to_string())?;
+
+    key.verify_prehash(&hash, &signature)
+        .map_err(|_| "Commit signature does not match the signing key".to_string())
+}
+
+/// Resolve the `#atproto` signing key from a DID document
+pub async fn account_signing_key(resolver: &IdentityResolver, did: &str) -> PdsResult<VerifyingKey> {
+    let doc = resolver.resolve_did(did).await?;
+    let multibase = doc
+        .get_signing_key()
+        .and_then(|vm| vm.public_key_multibase.clone())
+        .ok_or_else(|| PdsError::DidResolution(format!("No #atproto signing key for {}", did)))?;
+
+    parse_multikey(&multibase)
+}
+
+/// Decode a signed commit block into its unsigned form and signature
+pub fn decode_commit(bytes: &[u8]) -> PdsResult<(UnsignedCommit, Vec<u8>)> {
+    let ipld: Ipld = DagCborCodec
+        .decode(bytes)
+        .map_err(|e| PdsError::Validation(format!("Invalid commit block: {}", e)))?;
+
+    let Ipld::Map(map) = ipld else {
+        return Err(PdsError::Validation("Commit block is not a map".to_string()));
+    };
+
+    let did = match map.get("did") {
+        Some(Ipld::String(did)) => did.clone(),
+        _ => return Err(PdsError::Validation("Commit missing did".to_string())),
+    };
+    let version = match map.get("version") {
+        Some(Ipld::Integer(3)) => 3,
+        _ => return Err(PdsError::Validation("Unsupported commit version".to_string())),
+    };
+    let data = match map.get("data") {
+        Some(Ipld::Link(cid)) => *cid,
+        _ => return Err(PdsError::Validation("Commit missing data".to_string())),
+    };
+    let rev = match map.get("rev") {
+        Some(Ipld::String(rev)) => rev.clone(),
+        _ => return Err(PdsError::Validation("Commit missing rev".to_string())),
+    };
+    let prev = match map.get("prev") {
+        Some(Ipld::Link(cid)) => Some(*cid),
+        Some(Ipld::Null) | None => None,
+        _ => return Err(PdsError::Validation("Invalid commit prev".to_string())),
+    };
+    let sig = match map.get("sig") {
+        Some(Ipld::Bytes(sig)) => sig.clone(),
+        _ => return Err(PdsError::Validation("Commit missing sig".to_string())),
+    };
+
+    Ok((
+        UnsignedCommit {
+            did,
+            version,
+            data,
+            rev,
+            prev,
+        },
+        sig,
+    ))
+}
+
+/// Parse a multibase public key (`z` + base58btc)
+///
+/// Accepts multikey-encoded secp256k1 keys and bare compressed keys.
+pub fn parse_multikey(multibase: &str) -> PdsResult<VerifyingKey> {
+    let encoded = multibase
+        .strip_prefix('z')
+        .ok_or_else(|| PdsError::DidResolution("Unsupported multibase encoding".to_string()))?;
+    let bytes = bs58::decode(encoded)
+        .into_vec()
+        .map_err(|e| PdsError::DidResolution(format!("Invalid multibase key: {}", e)))?;
+
+    let key_bytes = match bytes.strip_prefix(&SECP256K1_MULTICODEC) {
+        Some(key) => key,
+        None if bytes.len() == 33 => &bytes[..],
+        None => {
+            return Err(PdsError::DidResolution(
+                "Only secp256k1 signing keys are supported".to_string(),
+            ))
+        }
+    };
+
+    VerifyingKey::from_sec1_bytes(key_bytes)
+        .map_err(|e| PdsError::DidResolution(format!("Invalid signing key: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::{
+        mst::{block_cid, Entry, RepoTree},
+        test_util::temp_store,
+    };
+    use atproto::repo::SignedCommit;
+    use k256::ecdsa::{signature::hazmat::PrehashSigner, SigningKey};
+
+    /// A signed repository with `n` records, as CAR blocks
+    fn repo(did: &str, n: usize, key: &SigningKey) -> (Cid, HashMap<Cid, Vec<u8>>) {
+        let (_dir, store) = temp_store();
+        let mut blocks = HashMap::new();
+        let mut entries = Vec::new();
+        for i in 0..n {
+            let record = format!("{{\"text\":\"post {}\"}}", i).into_bytes();
+            let cid = block_cid(&record);
+            entries.push((format!("app.bsky.feed.post/3k{:06}", i), cid));
+            blocks.insert(cid, record);
+        }
+
+        let mut tree = RepoTree::build(&store, did, entries).unwrap();
+        let data = tree.root_cid().unwrap();
+        blocks.extend(tree.new_blocks().unwrap());
+
+        let commit = sign(did, data, key);
+        let commit_cid = block_cid(&commit);
+        blocks.insert(commit_cid, commit);
+        (commit_cid, blocks)
+    }
+
+    fn sign(did: &str, data: Cid, key: &SigningKey) -> Vec<u8> {
+        let unsigned = UnsignedCommit {
+            did: did.to_string(),
+            version: 3,
+            data,
+            rev: "3jzfcijpj2z2a".to_string(),
+            prev: None,
+        };
+        let sig: Signature = key.sign_prehash(&unsigned.signing_hash().unwrap()).unwrap();
+        SignedCommit {
+            commit: unsigned,
+            sig: sig.to_bytes().to_vec(),
+        }
+        .to_cbor()
+        .unwrap()
+    }
+
+    fn kinds(report: &VerificationReport) -> Vec<IssueKind> {
+        report.issues.iter().map(|i| i.kind).collect()
+    }
+
+    #[tokio::test]
+    async fn test_valid_repo() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let (commit, blocks) = repo("did:plc:verify", 50, &key);
+
+        let report = verify_repo(&blocks, "did:plc:verify", &commit, Some(key.verifying_key()))
+            .await
+            .unwrap();
+        assert!(report.is_valid(), "{}", report.summary());
+        assert!(report.signature_verified);
+        assert_eq!(report.records, 50);
+        assert!(report.entries.windows(2).all(|w| w[0].0 < w[1].0));
+
+        // Wrong key and wrong DID
+        let other = SigningKey::from_slice(&[8u8; 32]).unwrap();
+        let report = verify_repo(&blocks, "did:plc:other", &commit, Some(other.verifying_key()))
+            .await
+            .unwrap();
+        assert_eq!(kinds(&report), vec![IssueKind::DidMismatch, IssueKind::InvalidSignature]);
+    }
+
+    #[tokio::test]
+    async fn test_corrupt_blocks_are_reported() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let (commit, mut blocks) = repo("did:plc:verify", 10, &key);
+
+        let record = block_cid(b"{\"text\":\"post 3\"}");
+        blocks.insert(record, b"tampered".to_vec());
+        let removed = block_cid(b"{\"text\":\"post 4\"}");
+        blocks.remove(&removed);
+
+        let report = verify_repo(&blocks, "did:plc:verify", &commit, None).await.unwrap();
+        assert!(!report.signature_verified);
+        assert_eq!(kinds(&report), vec![IssueKind::CidMismatch, IssueKind::MissingBlock]);
+        assert_eq!(report.issues[0].key.as_deref(), Some("app.bsky.feed.post/3k000003"));
+        assert_eq!(report.issues[1].key.as_deref(), Some("app.bsky.feed.post/3k000004"));
+    }
+
+    #[tokio::test]
+    async fn test_non_canonical_tree_is_reported() {
+        let key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let value = block_cid(b"{}");
+        let entry = |key: &str| Entry {
+            key: key.to_string(),
+            value,
+            right: None,
+        };
+
+        // Out of order, and a key that is not collection/rkey
+        let node = Node {
+            left: None,
+            entries: vec![entry("app.bsky.feed.post/b"), entry("app.bsky.feed.post/a"), entry("nokey")],
+        };
+        let node_bytes = node.encode().unwrap();
+        let data = block_cid(&node_bytes);
+        let commit = sign("did:plc:verify", data, &key);
+        let commit_cid = block_cid(&commit);
+
+        let mut blocks = HashMap::from([(data, node_bytes), (commit_cid, commit), (value, b"{}".to_vec())]);
+        let report = verify_repo(&blocks, "did:plc:verify", &commit_cid, Some(key.verifying_key()))
+            .await
+            .unwrap();
+        let found = kinds(&report);
+        assert!(found.contains(&IssueKind::UnsortedKeys));
+        assert!(found.contains(&IssueKind::InvalidMstNode));
+
+        // Missing MST node
+        blocks.remove(&data);
+        let report = verify_repo(&blocks, "did:plc:verify", &commit_cid, None).await.unwrap();
+        assert_eq!(kinds(&report), vec![IssueKind::MissingBlock]);
+    }
+
+    #[test]
+    fn test_commit_signature_roundtrip() {
+        let signing_key = SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let data = block_cid(b"data");
+
+        let bytes = sign("did:plc:abc123", data, &signing_key);
+        let (decoded, decoded_sig) = decode_commit(&bytes).unwrap();
+        assert_eq!(decoded.data, data);
+        assert!(check_signature(&decoded, &decoded_sig, signing_key.verifying_key()).is_ok());
+
+        // Multikey round trip
+        let mut multikey = SECP256K1_MULTICODEC.to_vec();
+        multikey.extend_from_slice(signing_key.verifying_key().to_encoded_point(true).as_bytes());
+        let encoded = format!("z{}", bs58::encode(multikey).into_string());
+        assert_eq!(&parse_multikey(&encoded).unwrap(), signing_key.verifying_key());
+    }
+}
@@ -0,0 +1,423 @@
+/// Network Blocklist for Registration and Login
+///
+/// Admins can block single IP addresses, CIDR ranges or autonomous systems
+/// from `createAccount` and `createSession`, to cut off signup abuse waves
+/// without touching existing sessions. Entries live in the account database
+/// and can expire on their own.
+///
+/// The client IP is resolved through the trusted proxies (see
+/// `net::client_ip`), so a forged `X-Forwarded-For` can't dodge an entry. ASNs are
+/// only known when the reverse proxy looks them up and passes them on in the
+/// header named by `PDS_BLOCKLIST_ASN_HEADER`; without it ASN entries never
+/// match.
+use crate::{
+    error::{PdsError, PdsResult},
+    net::{in_network, max_prefix, network},
+};
+use axum::http::HeaderMap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::net::IpAddr;
+
+/// What a blocklist pattern matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockKind {
+    /// A single address
+    Ip,
+    /// An address range, e.g. `203.0.113.0/24`
+    Cidr,
+    /// An autonomous system, e.g. `AS64496`
+    Asn,
+}
+
+impl BlockKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockKind::Ip => "ip",
+            BlockKind::Cidr => "cidr",
+            BlockKind::Asn => "asn",
+        }
+    }
+
+    fn from_str(s: &str) -> PdsResult<Self> {
+        match s {
+            "ip" => Ok(BlockKind::Ip),
+            "cidr" => Ok(BlockKind::Cidr),
+            "asn" => Ok(BlockKind::Asn),
+            _ => Err(PdsError::Internal(format!("Invalid blocklist kind: {}", s))),
+        }
+    }
+}
+
+/// A parsed blocklist pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rule {
+    Ip(IpAddr),
+    Cidr(IpAddr, u8),
+    Asn(u32),
+}
+
+impl Rule {
+    /// Parse an admin-supplied pattern
+    ///
+    /// CIDR ranges are stored by their network address, so `10.1.2.3/8`
+    /// and `10.0.0.0/8` are the same entry.
+    fn parse(pattern: &str) -> PdsResult<Self> {
+        let pattern = pattern.trim();
+        let invalid = || PdsError::Validation(format!("Invalid blocklist pattern: {}", pattern));
+
+        if let Some(number) = pattern.strip_prefix("AS").or_else(|| pattern.strip_prefix("as")) {
+            return number.parse().map(Rule::Asn).map_err(|_| invalid());
+        }
+
+        match pattern.split_once('/') {
+            Some((addr, prefix)) => {
+                let addr: IpAddr = addr.parse().map_err(|_| invalid())?;
+                let addr = addr.to_canonical();
+                let prefix: u8 = prefix.parse().map_err(|_| invalid())?;
+                if prefix > max_prefix(&addr) {
+                    return Err(invalid());
+                }
+                Ok(Rule::Cidr(network(addr, prefix), prefix))
+            }
+            None => pattern
+                .parse::<IpAddr>()
+                .map(|addr| Rule::Ip(addr.to_canonical()))
+                .map_err(|_| invalid()),
+        }
+    }
+
+    fn kind(&self) -> BlockKind {
+        match self {
+            Rule::Ip(_) => BlockKind::Ip,
+            Rule::Cidr(..) => BlockKind::Cidr,
+            Rule::Asn(_) => BlockKind::Asn,
+        }
+    }
+
+    fn pattern(&self) -> String {
+        match self {
+            Rule::Ip(addr) => addr.to_string(),
+            Rule::Cidr(addr, prefix) => format!("{}/{}", addr, prefix),
+            Rule::Asn(asn) => format!("AS{}", asn),
+        }
+    }
+
+    fn matches(&self, ip: Option<IpAddr>, asn: Option<u32>) -> bool {
+        match (self, ip, asn) {
+            (Rule::Ip(addr), Some(ip), _) => *addr == ip,
+            (Rule::Cidr(addr, prefix), Some(ip), _) => in_network(ip, (*addr, *prefix)),
+            (Rule::Asn(blocked), _, Some(asn)) => *blocked == asn,
+            _ => false,
+        }
+    }
+}
+
+/// Blocklist entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlocklistEntry {
+    pub pattern: String,
+    pub kind: BlockKind,
+    pub note: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    /// Attempts refused because of this entry
+    pub hit_count: i64,
+    pub last_hit_at: Option<DateTime<Utc>>,
+}
+
+impl BlocklistEntry {
+    /// Whether the entry has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.map(|t| t <= Utc::now()).unwrap_or(false)
+    }
+}
+
+/// Blocklist manager
+#[derive(Clone)]
+pub struct IpBlocklist {
+    db: SqlitePool,
+    /// Request header carrying the client's ASN, set by the reverse proxy
+    asn_header: Option<String>,
+}
+
+impl IpBlocklist {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db, asn_header: None }
+    }
+
+    /// Read the client ASN from this request header
+    pub fn with_asn_header(mut self, header: Option<String>) -> Self {
+        self.asn_header = header.filter(|h| !h.is_empty());
+        self
+    }
+
+    /// Block a pattern, replacing the note and expiry of an existing entry
+    pub async fn add(
+        &self,
+        pattern: &str,
+        note: Option<String>,
+        created_by: &str,
+        expires_in: Option<chrono::Duration>,
+    ) -> PdsResult<BlocklistEntry> {
+        let rule = Rule::parse(pattern)?;
+        let now = Utc::now();
+        let expires_at = expires_in.map(|d| now + d);
+
+        sqlx::query(
+            r#"
+            INSERT INTO ip_blocklist (pattern, kind, note, created_by, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT(pattern) DO UPDATE SET
+                note = excluded.note,
+                created_by = excluded.created_by,
+                created_at = excluded.created_at,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(rule.pattern())
+        .bind(rule.kind().as_str())
+        .bind(&note)
+        .bind(created_by)
+        .bind(now.to_rfc3339())
+        .bind(expires_at.map(|dt| dt.to_rfc3339()))
+        .execute(&self.db)
+        .await?;
+
+        self.get(&rule.pattern())
+            .await?
+            .ok_or_else(|| PdsError::Internal("Blocklist entry vanished".to_string()))
+    }
+
+    /// Unblock a pattern
+    pub async fn remove(&self, pattern: &str) -> PdsResult<()> {
+        let rule = Rule::parse(pattern)?;
+        let result = sqlx::query("DELETE FROM ip_blocklist WHERE pattern = ?")
+            .bind(rule.pattern())
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PdsError::NotFound(format!("{} is not blocked", rule.pattern())));
+        }
+
+        Ok(())
+    }
+
+    async fn get(&self, pattern: &str) -> PdsResult<Option<BlocklistEntry>> {
+        let row = sqlx::query(
+            r#"
+            SELECT pattern, kind, note, created_by, created_at, expires_at, hit_count, last_hit_at
+            FROM ip_blocklist
+            WHERE pattern = ?
+            "#,
+        )
+        .bind(pattern)
+        .fetch_optional(&self.db)
+        .await?;
+
+        row.as_ref().map(Self::row_to_entry).transpose()
+    }
+
+    /// List all active (non-expired) entries
+    pub async fn list(&self) -> PdsResult<Vec<BlocklistEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT pattern, kind, note, created_by, created_at, expires_at, hit_count, last_hit_at
+            FROM ip_blocklist
+            ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut entries = Vec::new();
+        for row in rows {
+            let entry = Self::row_to_entry(&row)?;
+            if !entry.is_expired() {
+                entries.push(entry);
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Refuse the request if its client IP or ASN is blocked
+    ///
+    /// `endpoint` labels the blocked-attempt metric.
+    pub async fn check_request(&self, endpoint: &str, ip: Option<IpAddr>, headers: &HeaderMap) -> PdsResult<()> {
+        let asn = self
+            .asn_header
+            .as_ref()
+            .and_then(|name| headers.get(name.as_str()))
+            .and_then(|h| h.to_str().ok())
+            .and_then(|s| {
+                let s = s.trim();
+                s.strip_prefix("AS").unwrap_or(s).parse().ok()
+            });
+
+        self.check(endpoint, ip, asn).await
+    }
+
+    /// Refuse a client by IP address and ASN
+    pub async fn check(&self, endpoint: &str, ip: Option<IpAddr>, asn: Option<u32>) -> PdsResult<()> {
+        let ip = ip.map(|ip| ip.to_canonical());
+        if ip.is_none() && asn.is_none() {
+            return Ok(());
+        }
+
+        let matched = self
+            .list()
+            .await?
+            .into_iter()
+            .find(|entry| Rule::parse(&entry.pattern).map(|r| r.matches(ip, asn)).unwrap_or(false));
+
+        let Some(entry) = matched else {
+            return Ok(());
+        };
+
+        crate::metrics::record_blocklist_block(endpoint, entry.kind.as_str());
+        tracing::warn!(
+            "Refused {} from {:?} (AS{:?}): blocked by {}",
+            endpoint,
+            ip,
+            asn,
+            entry.pattern
+        );
+
+        if let Err(e) = sqlx::query(
+            "UPDATE ip_blocklist SET hit_count = hit_count + 1, last_hit_at = ? WHERE pattern = ?",
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(&entry.pattern)
+        .execute(&self.db)
+        .await
+        {
+            tracing::warn!("Failed to count blocklist hit: {}", e);
+        }
+
+        Err(PdsError::Authorization(
+            "Requests from this network are not allowed".to_string(),
+        ))
+    }
+
+    fn row_to_entry(row: &sqlx::sqlite::SqliteRow) -> PdsResult<BlocklistEntry> {
+        let parse = |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))
+        };
+
+        Ok(BlocklistEntry {
+            pattern: row.get("pattern"),
+            kind: BlockKind::from_str(&row.get::<String, _>("kind"))?,
+            note: row.get("note"),
+            created_by: row.get("created_by"),
+            created_at: parse(row.get("created_at"))?,
+            expires_at: row.get::<Option<String>, _>("expires_at").map(parse).transpose()?,
+            hit_count: row.get("hit_count"),
+            last_hit_at: row.get::<Option<String>, _>("last_hit_at").map(parse).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_db() -> SqlitePool {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE ip_blocklist (
+                pattern TEXT PRIMARY KEY,
+                kind TEXT NOT NULL,
+                note TEXT,
+                created_by TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT,
+                hit_count INTEGER NOT NULL DEFAULT 0,
+                last_hit_at TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        db
+    }
+
+    #[test]
+    fn test_parse_and_match_patterns() {
+        let ip = |s: &str| Some(s.parse::<IpAddr>().unwrap());
+
+        let rule = Rule::parse("203.0.113.77/24").unwrap();
+        assert_eq!(rule.pattern(), "203.0.113.0/24");
+        assert!(rule.matches(ip("203.0.113.1"), None));
+        assert!(!rule.matches(ip("203.0.114.1"), None));
+        assert!(!rule.matches(ip("::1"), None));
+
+        let rule = Rule::parse("2001:db8::/32").unwrap();
+        assert!(rule.matches(ip("2001:db8:1::5"), None));
+        assert!(!rule.matches(ip("2001:db9::5"), None));
+
+        assert!(Rule::parse("0.0.0.0/0").unwrap().matches(ip("198.51.100.1"), None));
+        assert!(Rule::parse("::ffff:198.51.100.1").unwrap().matches(ip("198.51.100.1"), None));
+
+        let rule = Rule::parse("as64496").unwrap();
+        assert_eq!(rule.pattern(), "AS64496");
+        assert!(rule.matches(None, Some(64496)));
+        assert!(!rule.matches(ip("198.51.100.1"), None));
+
+        for bad in ["", "10.0.0.0/33", "::/129", "ASx", "example.com", "10.0.0.1/"] {
+            assert!(Rule::parse(bad).is_err(), "{} should not parse", bad);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_blocklist_enforcement() {
+        let blocklist = IpBlocklist::new(setup_db().await).with_asn_header(Some("x-client-asn".to_string()));
+
+        blocklist
+            .add("198.51.100.0/24", Some("signup wave".to_string()), "did:plc:admin", None)
+            .await
+            .unwrap();
+        blocklist.add("AS64496", None, "did:plc:admin", None).await.unwrap();
+        blocklist
+            .add("192.0.2.1", None, "did:plc:admin", Some(chrono::Duration::seconds(-1)))
+            .await
+            .unwrap();
+
+        // Expired entries neither list nor block
+        assert_eq!(blocklist.list().await.unwrap().len(), 2);
+        let ip = |s: &str| Some(s.parse::<IpAddr>().unwrap());
+        let mut headers = HeaderMap::new();
+        assert!(blocklist.check_request("createSession", ip("192.0.2.1"), &headers).await.is_ok());
+
+        assert!(matches!(
+            blocklist.check_request("createAccount", ip("198.51.100.9"), &headers).await,
+            Err(PdsError::Authorization(_))
+        ));
+
+        assert!(blocklist.check_request("createSession", ip("203.0.113.5"), &headers).await.is_ok());
+        headers.insert("x-client-asn", "AS64496".parse().unwrap());
+        assert!(blocklist.check_request("createSession", ip("203.0.113.5"), &headers).await.is_err());
+
+        let entries = blocklist.list().await.unwrap();
+        let range = entries.iter().find(|e| e.pattern == "198.51.100.0/24").unwrap();
+        assert_eq!(range.kind, BlockKind::Cidr);
+        assert_eq!(range.hit_count, 1);
+        assert!(range.last_hit_at.is_some());
+
+        // Removal accepts any spelling of the same range
+        blocklist.remove("198.51.100.200/24").await.unwrap();
+        assert!(matches!(blocklist.remove("198.51.100.0/24").await, Err(PdsError::NotFound(_))));
+        assert!(blocklist.check("createAccount", "198.51.100.9".parse().ok(), None).await.is_ok());
+    }
+}
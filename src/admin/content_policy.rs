@@ -0,0 +1,588 @@
+/// Automated content policy for record writes
+///
+/// Every create/update passing through `RepositoryManager::apply_writes` is
+/// checked against a list of rules before anything is committed. A matching
+/// rule either rejects the whole write, or lets it through and afterwards
+/// labels the record or files a report into the moderation queue on behalf of
+/// the service.
+///
+/// The built-in rules cover post rate, repeated identical posts, blocked link
+/// domains and links from brand-new accounts; further rules can be added with
+/// `ContentPolicy::with_rule`. On config reload the built-in rules are
+/// rebuilt from the new settings (custom rules are dropped).
+use crate::{
+    account::AccountManager,
+    actor_store::{WriteOp, WriteOpAction},
+    admin::{LabelManager, ReportManager, ReportReason},
+    config::ContentPolicyConfig,
+    error::{PdsError, PdsResult},
+};
+use serde_json::Value;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+
+/// What happens when a rule matches
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolicyAction {
+    /// Refuse the write
+    Reject,
+    /// Accept the write and label the record
+    Label,
+    /// Accept the write and file a report for moderators
+    Report,
+}
+
+impl PolicyAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PolicyAction::Reject => "reject",
+            PolicyAction::Label => "label",
+            PolicyAction::Report => "report",
+        }
+    }
+
+    pub fn from_str(s: &str) -> PdsResult<Self> {
+        match s.to_lowercase().as_str() {
+            "reject" => Ok(PolicyAction::Reject),
+            "label" => Ok(PolicyAction::Label),
+            "report" => Ok(PolicyAction::Report),
+            _ => Err(PdsError::Validation(format!("Invalid content policy action: {}", s))),
+        }
+    }
+}
+
+/// A record write as seen by policy rules
+pub struct PolicyWrite<'a> {
+    pub did: &'a str,
+    pub collection: &'a str,
+    pub rkey: &'a str,
+    pub record: &'a Value,
+    /// Age of the writing account, if known
+    pub account_age: Option<chrono::Duration>,
+}
+
+/// A rule that matched a write
+#[derive(Debug, Clone)]
+pub struct PolicyMatch {
+    pub rule: &'static str,
+    pub action: PolicyAction,
+    /// Label value applied for `PolicyAction::Label`
+    pub label: &'static str,
+    pub reason_type: ReportReason,
+    pub reason: String,
+}
+
+/// A content policy rule
+///
+/// Rules may keep in-memory state (e.g. recent activity per account); every
+/// call to `check` counts as an attempted write.
+pub trait ContentRule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn check(&self, write: &PolicyWrite<'_>) -> Option<PolicyMatch>;
+}
+
+/// A non-rejecting match, tied to the record it applies to
+#[derive(Debug, Clone)]
+pub struct FlaggedWrite {
+    pub collection: String,
+    pub rkey: String,
+    pub matched: PolicyMatch,
+}
+
+/// Content policy pipeline
+pub struct ContentPolicy {
+    /// Swapped as a whole on reload; checks work on a snapshot
+    rules: RwLock<Arc<Vec<Arc<dyn ContentRule>>>>,
+    config: RwLock<ContentPolicyConfig>,
+    account_manager: Arc<AccountManager>,
+    label_manager: Arc<LabelManager>,
+    report_manager: Arc<ReportManager>,
+    service_did: String,
+}
+
+impl ContentPolicy {
+    /// Build the pipeline with the built-in rules enabled in `config`
+    pub fn from_config(
+        config: &ContentPolicyConfig,
+        account_manager: Arc<AccountManager>,
+        label_manager: Arc<LabelManager>,
+        report_manager: Arc<ReportManager>,
+        service_did: String,
+    ) -> PdsResult<Self> {
+        Ok(Self {
+            rules: RwLock::new(Arc::new(Self::built_in_rules(config)?)),
+            config: RwLock::new(config.clone()),
+            account_manager,
+            label_manager,
+            report_manager,
+            service_did,
+        })
+    }
+
+    fn built_in_rules(config: &ContentPolicyConfig) -> PdsResult<Vec<Arc<dyn ContentRule>>> {
+        let mut rules: Vec<Arc<dyn ContentRule>> = Vec::new();
+        if !config.enabled {
+            return Ok(rules);
+        }
+
+        if config.max_posts_per_minute > 0 {
+            rules.push(Arc::new(PostRateRule::new(
+                config.max_posts_per_minute,
+                PolicyAction::from_str(&config.rate_action)?,
+            )));
+        }
+        if config.duplicate_limit > 0 {
+            rules.push(Arc::new(DuplicateTextRule::new(
+                config.duplicate_limit,
+                Duration::from_secs(config.duplicate_window),
+                PolicyAction::from_str(&config.duplicate_action)?,
+            )));
+        }
+        if !config.blocked_domains.is_empty() {
+            rules.push(Arc::new(LinkDomainRule::new(
+                config.blocked_domains.clone(),
+                PolicyAction::from_str(&config.blocked_domain_action)?,
+            )));
+        }
+        if config.new_account_hours > 0 {
+            rules.push(Arc::new(NewAccountLinkRule::new(
+                chrono::Duration::hours(config.new_account_hours as i64),
+                PolicyAction::from_str(&config.new_account_action)?,
+            )));
+        }
+
+        Ok(rules)
+    }
+
+    /// Add a rule to the end of the pipeline
+    pub fn with_rule(mut self, rule: impl ContentRule + 'static) -> Self {
+        let rules = self.rules.get_mut().unwrap_or_else(|e| e.into_inner());
+        Arc::make_mut(rules).push(Arc::new(rule));
+        self
+    }
+
+    /// Settings the built-in rules were last built from
+    pub fn config(&self) -> ContentPolicyConfig {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Rebuild the built-in rules from new settings
+    ///
+    /// Does nothing (and keeps rule state such as post counters) when the
+    /// settings are unchanged. Returns whether the rules were replaced.
+    pub fn reconfigure(&self, config: &ContentPolicyConfig) -> PdsResult<bool> {
+        if self.config() == *config {
+            return Ok(false);
+        }
+
+        let rules = Self::built_in_rules(config)?;
+        *self.rules.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(rules);
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(true)
+    }
+
+    /// Check a batch of writes before it is committed
+    ///
+    /// Fails if any rule rejects any write; otherwise returns the writes to
+    /// label or report once the commit succeeds.
+    pub async fn check_writes(&self, did: &str, writes: &[WriteOp]) -> PdsResult<Vec<FlaggedWrite>> {
+        let rules = self.rules.read().unwrap_or_else(|e| e.into_inner()).clone();
+        if rules.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let account_age = match self.account_manager.get_account(did).await {
+            Ok(account) => Some(chrono::Utc::now() - account.created_at),
+            Err(_) => None,
+        };
+
+        let mut flagged = Vec::new();
+        for write in writes {
+            let record = match (&write.action, &write.value) {
+                (WriteOpAction::Create | WriteOpAction::Update, Some(record)) => record,
+                _ => continue,
+            };
+            let policy_write = PolicyWrite {
+                did,
+                collection: &write.collection,
+                rkey: &write.rkey,
+                record,
+                account_age,
+            };
+
+            for matched in rules.iter().filter_map(|rule| rule.check(&policy_write)) {
+                if matched.action == PolicyAction::Reject {
+                    tracing::info!(did, rule = matched.rule, "Write rejected by content policy: {}", matched.reason);
+                    return Err(PdsError::Validation(format!(
+                        "Rejected by content policy ({}): {}",
+                        matched.rule, matched.reason
+                    )));
+                }
+                flagged.push(FlaggedWrite {
+                    collection: write.collection.clone(),
+                    rkey: write.rkey.clone(),
+                    matched,
+                });
+            }
+        }
+
+        Ok(flagged)
+    }
+
+    /// Label or report committed records flagged by `check_writes`
+    ///
+    /// `cids` maps `collection/rkey` to the committed record CID. Failures are
+    /// logged; the write has already been committed.
+    pub async fn enforce(&self, did: &str, flagged: &[FlaggedWrite], cids: &HashMap<String, String>) {
+        for flag in flagged {
+            let uri = format!("at://{}/{}/{}", did, flag.collection, flag.rkey);
+            let cid = cids.get(&format!("{}/{}", flag.collection, flag.rkey)).map(String::as_str);
+            let matched = &flag.matched;
+
+            let result = match matched.action {
+                PolicyAction::Label => self
+                    .label_manager
+                    .apply_label(&uri, cid, matched.label, &self.service_did, None)
+                    .await
+                    .map(|_| ()),
+                PolicyAction::Report => self
+                    .report_manager
+                    .submit_report(
+                        Some(did),
+                        Some(&uri),
+                        cid,
+                        matched.reason_type,
+                        Some(&format!("[{}] {}", matched.rule, matched.reason)),
+                        &self.service_did,
+                    )
+                    .await
+                    .map(|_| ()),
+                PolicyAction::Reject => Ok(()),
+            };
+
+            match result {
+                Ok(()) => tracing::info!(
+                    uri = %uri,
+                    rule = matched.rule,
+                    action = matched.action.as_str(),
+                    "Content policy flagged record: {}",
+                    matched.reason
+                ),
+                Err(e) => tracing::warn!("Failed to {} {} for content policy: {}", matched.action.as_str(), uri, e),
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Built-in rules
+// ============================================================================
+
+/// Drop timestamps older than `window` and return how many remain
+fn prune(times: &mut VecDeque<Instant>, window: Duration, now: Instant) -> usize {
+    while times.front().map_or(false, |t| now.duration_since(*t) > window) {
+        times.pop_front();
+    }
+    times.len()
+}
+
+/// Limits how many posts an account may create per minute
+pub struct PostRateRule {
+    max_per_minute: u32,
+    action: PolicyAction,
+    recent: Mutex<HashMap<String, VecDeque<Instant>>>,
+}
+
+impl PostRateRule {
+    pub fn new(max_per_minute: u32, action: PolicyAction) -> Self {
+        Self {
+            max_per_minute,
+            action,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ContentRule for PostRateRule {
+    fn name(&self) -> &'static str {
+        "post-rate"
+    }
+
+    fn check(&self, write: &PolicyWrite<'_>) -> Option<PolicyMatch> {
+        if write.collection != POST_COLLECTION {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|_, times| prune(times, Duration::from_secs(60), now) > 0);
+
+        let times = recent.entry(write.did.to_string()).or_default();
+        times.push_back(now);
+
+        (times.len() > self.max_per_minute as usize).then(|| PolicyMatch {
+            rule: self.name(),
+            action: self.action,
+            label: "spam",
+            reason_type: ReportReason::Spam,
+            reason: format!("More than {} posts per minute", self.max_per_minute),
+        })
+    }
+}
+
+/// Flags accounts repeating the same post text
+pub struct DuplicateTextRule {
+    max_repeats: u32,
+    window: Duration,
+    action: PolicyAction,
+    recent: Mutex<HashMap<String, VecDeque<(Instant, u64)>>>,
+}
+
+impl DuplicateTextRule {
+    /// Texts shorter than this (after normalization) are not tracked
+    const MIN_TEXT_LEN: usize = 10;
+
+    pub fn new(max_repeats: u32, window: Duration, action: PolicyAction) -> Self {
+        Self {
+            max_repeats,
+            window,
+            action,
+            recent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Hash of the text, ignoring case and whitespace differences
+    fn fingerprint(text: &str) -> Option<u64> {
+        let normalized = text.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+        if normalized.chars().count() < Self::MIN_TEXT_LEN {
+            return None;
+        }
+
+        let mut hasher = DefaultHasher::new();
+        normalized.hash(&mut hasher);
+        Some(hasher.finish())
+    }
+}
+
+impl ContentRule for DuplicateTextRule {
+    fn name(&self) -> &'static str {
+        "duplicate-text"
+    }
+
+    fn check(&self, write: &PolicyWrite<'_>) -> Option<PolicyMatch> {
+        if write.collection != POST_COLLECTION {
+            return None;
+        }
+        let fingerprint = Self::fingerprint(write.record.get("text")?.as_str()?)?;
+
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|_, entries| {
+            while entries.front().map_or(false, |(t, _)| now.duration_since(*t) > self.window) {
+                entries.pop_front();
+            }
+            !entries.is_empty()
+        });
+
+        let entries = recent.entry(write.did.to_string()).or_default();
+        entries.push_back((now, fingerprint));
+        let repeats = entries.iter().filter(|(_, f)| *f == fingerprint).count();
+
+        (repeats > self.max_repeats as usize).then(|| PolicyMatch {
+            rule: self.name(),
+            action: self.action,
+            label: "spam",
+            reason_type: ReportReason::Spam,
+            reason: format!("Same text posted {} times within {}s", repeats, self.window.as_secs()),
+        })
+    }
+}
+
+/// Hosts of every http(s) URL appearing in a record (text, facets, embeds)
+pub fn link_hosts(record: &Value) -> Vec<String> {
+    fn walk(value: &Value, hosts: &mut Vec<String>) {
+        match value {
+            Value::String(s) => {
+                for token in s.split_whitespace() {
+                    let token = token.trim_matches(|c: char| matches!(c, '(' | ')' | '<' | '>' | '"' | '\''));
+                    if !(token.starts_with("http://") || token.starts_with("https://")) {
+                        continue;
+                    }
+                    if let Some(host) = reqwest::Url::parse(token).ok().and_then(|u| u.host_str().map(str::to_lowercase)) {
+                        if !hosts.contains(&host) {
+                            hosts.push(host);
+                        }
+                    }
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| walk(v, hosts)),
+            Value::Object(map) => map.values().for_each(|v| walk(v, hosts)),
+            _ => {}
+        }
+    }
+
+    let mut hosts = Vec::new();
+    walk(record, &mut hosts);
+    hosts
+}
+
+/// Blocks links to listed domains (and their subdomains)
+pub struct LinkDomainRule {
+    blocked: Vec<String>,
+    action: PolicyAction,
+}
+
+impl LinkDomainRule {
+    pub fn new(blocked: Vec<String>, action: PolicyAction) -> Self {
+        let blocked = blocked
+            .into_iter()
+            .map(|d| d.trim().trim_start_matches("*.").trim_end_matches('.').to_lowercase())
+            .filter(|d| !d.is_empty())
+            .collect();
+        Self { blocked, action }
+    }
+
+    fn is_blocked(&self, host: &str) -> bool {
+        self.blocked.iter().any(|domain| {
+            host == domain
+                || host
+                    .strip_suffix(domain.as_str())
+                    .map_or(false, |rest| rest.ends_with('.'))
+        })
+    }
+}
+
+impl ContentRule for LinkDomainRule {
+    fn name(&self) -> &'static str {
+        "blocked-domain"
+    }
+
+    fn check(&self, write: &PolicyWrite<'_>) -> Option<PolicyMatch> {
+        let host = link_hosts(write.record).into_iter().find(|h| self.is_blocked(h))?;
+
+        Some(PolicyMatch {
+            rule: self.name(),
+            action: self.action,
+            label: "blocked-link",
+            reason_type: ReportReason::Violation,
+            reason: format!("Links to blocked domain {}", host),
+        })
+    }
+}
+
+/// Restricts links in posts from accounts younger than `min_age`
+pub struct NewAccountLinkRule {
+    min_age: chrono::Duration,
+    action: PolicyAction,
+}
+
+impl NewAccountLinkRule {
+    pub fn new(min_age: chrono::Duration, action: PolicyAction) -> Self {
+        Self { min_age, action }
+    }
+}
+
+impl ContentRule for NewAccountLinkRule {
+    fn name(&self) -> &'static str {
+        "new-account-link"
+    }
+
+    fn check(&self, write: &PolicyWrite<'_>) -> Option<PolicyMatch> {
+        if write.collection != POST_COLLECTION || write.account_age? >= self.min_age {
+            return None;
+        }
+        if link_hosts(write.record).is_empty() {
+            return None;
+        }
+
+        Some(PolicyMatch {
+            rule: self.name(),
+            action: self.action,
+            label: "new-account-link",
+            reason_type: ReportReason::Spam,
+            reason: format!("Link posted by an account younger than {}h", self.min_age.num_hours()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn post<'a>(did: &'a str, record: &'a Value) -> PolicyWrite<'a> {
+        PolicyWrite {
+            did,
+            collection: POST_COLLECTION,
+            rkey: "3k",
+            record,
+            account_age: Some(chrono::Duration::days(30)),
+        }
+    }
+
+    #[test]
+    fn test_post_rate_rule() {
+        let rule = PostRateRule::new(2, PolicyAction::Reject);
+        let record = json!({ "text": "hi" });
+
+        assert!(rule.check(&post("did:plc:a", &record)).is_none());
+        assert!(rule.check(&post("did:plc:a", &record)).is_none());
+        let matched = rule.check(&post("did:plc:a", &record)).unwrap();
+        assert_eq!(matched.action, PolicyAction::Reject);
+
+        // Other accounts have their own budget
+        assert!(rule.check(&post("did:plc:b", &record)).is_none());
+    }
+
+    #[test]
+    fn test_duplicate_text_rule() {
+        let rule = DuplicateTextRule::new(2, Duration::from_secs(3600), PolicyAction::Report);
+        let first = json!({ "text": "Buy cheap followers now" });
+        let spaced = json!({ "text": "buy  cheap followers   NOW" });
+        let short = json!({ "text": "lol" });
+
+        assert!(rule.check(&post("did:plc:a", &first)).is_none());
+        assert!(rule.check(&post("did:plc:a", &spaced)).is_none());
+        assert!(rule.check(&post("did:plc:a", &first)).is_some());
+
+        for _ in 0..5 {
+            assert!(rule.check(&post("did:plc:a", &short)).is_none());
+        }
+    }
+
+    #[test]
+    fn test_link_domain_rule() {
+        let rule = LinkDomainRule::new(vec!["spam.example".to_string()], PolicyAction::Reject);
+        let facet = json!({
+            "text": "see this",
+            "facets": [{ "features": [{ "$type": "app.bsky.richtext.facet#link", "uri": "https://www.spam.example/x" }] }]
+        });
+        let lookalike = json!({ "text": "https://notspam.example is fine" });
+
+        assert!(rule.check(&post("did:plc:a", &facet)).is_some());
+        assert!(rule.check(&post("did:plc:a", &lookalike)).is_none());
+    }
+
+    #[test]
+    fn test_new_account_link_rule() {
+        let rule = NewAccountLinkRule::new(chrono::Duration::hours(24), PolicyAction::Label);
+        let record = json!({ "text": "check (https://example.com/page)" });
+        assert_eq!(link_hosts(&record), vec!["example.com".to_string()]);
+
+        let mut write = post("did:plc:a", &record);
+        assert!(rule.check(&write).is_none());
+
+        write.account_age = Some(chrono::Duration::hours(2));
+        assert_eq!(rule.check(&write).unwrap().label, "new-account-link");
+
+        let no_link = json!({ "text": "hello" });
+        write.record = &no_link;
+        assert!(rule.check(&write).is_none());
+    }
+}
@@ -26,6 +26,7 @@ where
 
 /// Invite code
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct InviteCode {
     pub code: String,
     pub available: i32,
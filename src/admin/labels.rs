@@ -1,11 +1,14 @@
 /// Label Management System
+use crate::admin::webhooks::{events, WebhookManager};
 use crate::error::{PdsError, PdsResult};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use sqlx::{Row, SqlitePool};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use std::sync::Arc;
 
 /// Content label
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Label {
     pub id: i64,
     pub uri: String,  // AT-URI
@@ -24,11 +27,22 @@ pub struct Label {
 pub struct LabelManager {
     db: SqlitePool,
     server_did: String,
+    webhooks: Option<Arc<WebhookManager>>,
 }
 
 impl LabelManager {
     pub fn new(db: SqlitePool, server_did: String) -> Self {
-        Self { db, server_did }
+        Self {
+            db,
+            server_did,
+            webhooks: None,
+        }
+    }
+
+    /// Send applied and removed labels to moderation webhooks
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookManager>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
     }
 
     /// Apply label to content or account
@@ -59,7 +73,7 @@ impl LabelManager {
         .execute(&self.db)
         .await?;
 
-        Ok(Label {
+        let label = Label {
             id: result.last_insert_rowid(),
             uri: uri.to_string(),
             cid: cid.map(String::from),
@@ -70,7 +84,13 @@ impl LabelManager {
             created_by: created_by.to_string(),
             expires_at,
             sig: None,
-        })
+        };
+
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(events::LABEL_APPLIED, &label).await;
+        }
+
+        Ok(label)
     }
 
     /// Remove label (create negative label)
@@ -98,7 +118,7 @@ impl LabelManager {
         .execute(&self.db)
         .await?;
 
-        Ok(Label {
+        let label = Label {
             id: result.last_insert_rowid(),
             uri: uri.to_string(),
             cid: cid.map(String::from),
@@ -109,7 +129,13 @@ impl LabelManager {
             created_by: created_by.to_string(),
             expires_at: None,
             sig: None,
-        })
+        };
+
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(events::LABEL_REMOVED, &label).await;
+        }
+
+        Ok(label)
     }
 
     /// Get all labels for a URI
@@ -126,33 +152,85 @@ impl LabelManager {
         .fetch_all(&self.db)
         .await?;
 
-        let mut labels = Vec::new();
-        for row in rows {
-            let created_at_str: String = row.get("created_at");
-            let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-                .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?
-                .with_timezone(&Utc);
-
-            let expires_at = row
-                .try_get::<String, _>("expires_at")
-                .ok()
-                .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
-                .map(|dt| dt.with_timezone(&Utc));
-
-            labels.push(Label {
-                id: row.get("id"),
-                uri: row.get("uri"),
-                cid: row.get("cid"),
-                val: row.get("val"),
-                neg: row.get("neg"),
-                src: row.get("src"),
-                created_at,
-                created_by: row.get("created_by"),
-                expires_at,
-                sig: row.get("sig"),
-            });
-        }
+        rows.iter().map(label_from_row).collect()
+    }
+
+    /// Labels and negations written after `after`, oldest first
+    ///
+    /// Label ids only grow, so they double as the label stream's seq.
+    pub async fn labels_after(&self, after: i64, limit: i64) -> PdsResult<Vec<Label>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, uri, cid, val, neg, src, created_at, created_by, expires_at, sig
+            FROM label
+            WHERE id > ?
+            ORDER BY id
+            LIMIT ?
+            "#,
+        )
+        .bind(after)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter().map(label_from_row).collect()
+    }
+
+    /// Id of the newest label (0 if there are none)
+    pub async fn latest_id(&self) -> PdsResult<i64> {
+        let id: Option<i64> = sqlx::query_scalar("SELECT MAX(id) FROM label")
+            .fetch_one(&self.db)
+            .await?;
+        Ok(id.unwrap_or(0))
+    }
+}
+
+fn label_from_row(row: &SqliteRow) -> PdsResult<Label> {
+    let created_at_str: String = row.get("created_at");
+    let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+        .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?
+        .with_timezone(&Utc);
+
+    let expires_at = row
+        .try_get::<String, _>("expires_at")
+        .ok()
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc));
+
+    Ok(Label {
+        id: row.get("id"),
+        uri: row.get("uri"),
+        cid: row.get("cid"),
+        val: row.get("val"),
+        neg: row.get("neg"),
+        src: row.get("src"),
+        created_at,
+        created_by: row.get("created_by"),
+        expires_at,
+        sig: row.get("sig"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_labels_after() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let labels = LabelManager::new(db, "did:web:pds.test".to_string());
+        assert_eq!(labels.latest_id().await.unwrap(), 0);
+
+        let uri = "at://did:plc:alice/app.bsky.feed.post/1";
+        let applied = labels.apply_label(uri, None, "spam", "did:plc:admin", None).await.unwrap();
+        let removed = labels.remove_label(uri, None, "spam", "did:plc:admin").await.unwrap();
+        assert_eq!(labels.latest_id().await.unwrap(), removed.id);
 
-        Ok(labels)
+        let all = labels.labels_after(0, 10).await.unwrap();
+        assert_eq!(all.iter().map(|l| (l.id, l.neg)).collect::<Vec<_>>(), vec![(applied.id, false), (removed.id, true)]);
+        assert_eq!(labels.labels_after(applied.id, 10).await.unwrap().len(), 1);
+        assert_eq!(labels.labels_after(0, 1).await.unwrap()[0].id, applied.id);
+        assert_eq!(labels.get_labels(uri).await.unwrap().len(), 2);
     }
 }
@@ -8,18 +8,27 @@ pub mod moderation;
 pub mod labels;
 pub mod invites;
 pub mod reports;
+pub mod rate_limits;
+pub mod content_policy;
+pub mod webhooks;
+pub mod blocklist;
 
-pub use roles::{AdminRoleManager, Role};
+pub use roles::{AdminRole, AdminRoleManager, Role};
 pub use moderation::{ModerationAction, ModerationManager, ModerationRecord};
 pub use labels::{Label, LabelManager};
 pub use invites::{InviteCode, InviteCodeManager};
 pub use reports::{Report, ReportManager, ReportReason, ReportStatus};
+pub use rate_limits::{RateLimitOverride, RateLimitOverrideManager};
+pub use content_policy::ContentPolicy;
+pub use webhooks::{DeliveryStatus, Webhook, WebhookDelivery, WebhookManager};
+pub use blocklist::{BlockKind, BlocklistEntry, IpBlocklist};
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// Admin action audit log entry
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AuditLogEntry {
     pub id: i64,
     pub admin_did: String,
@@ -29,3 +38,18 @@ pub struct AuditLogEntry {
     pub timestamp: DateTime<Utc>,
     pub ip_address: Option<String>,
 }
+
+/// Filters for reading the admin audit log
+///
+/// Results are newest first; `cursor` is the id of the last entry of the
+/// previous page.
+#[derive(Debug, Clone, Default)]
+pub struct AuditLogQuery {
+    pub admin_did: Option<String>,
+    pub action: Option<String>,
+    pub subject_did: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub cursor: Option<i64>,
+    pub limit: i64,
+}
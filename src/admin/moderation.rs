@@ -1,8 +1,10 @@
 /// Account Moderation System
+use crate::admin::webhooks::{events, WebhookManager};
 use crate::error::{PdsError, PdsResult};
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
 
 /// Moderation action types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -45,6 +47,7 @@ impl ModerationAction {
 
 /// Moderation record
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ModerationRecord {
     pub id: i64,
     pub did: String,
@@ -65,11 +68,18 @@ pub struct ModerationRecord {
 #[derive(Clone)]
 pub struct ModerationManager {
     db: SqlitePool,
+    webhooks: Option<Arc<WebhookManager>>,
 }
 
 impl ModerationManager {
     pub fn new(db: SqlitePool) -> Self {
-        Self { db }
+        Self { db, webhooks: None }
+    }
+
+    /// Send account actions (`account.takedown`, ...) to moderation webhooks
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookManager>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
     }
 
     /// Apply moderation action to an account
@@ -106,7 +116,7 @@ impl ModerationManager {
 
         let id = result.last_insert_rowid();
 
-        Ok(ModerationRecord {
+        let record = ModerationRecord {
             id,
             did: did.to_string(),
             action,
@@ -120,7 +130,14 @@ impl ModerationManager {
             reversal_reason: None,
             report_id,
             notes,
-        })
+        };
+
+        if let Some(webhooks) = &self.webhooks {
+            let event_type = format!("{}{}", events::ACCOUNT_PREFIX, action.as_str());
+            webhooks.dispatch(&event_type, &record).await;
+        }
+
+        Ok(record)
     }
 
     /// Reverse a moderation action
@@ -215,10 +232,12 @@ impl ModerationManager {
     }
 
     /// Cleanup expired suspensions
-    pub async fn cleanup_expired(&self) -> PdsResult<u64> {
+    ///
+    /// Returns the suspended DIDs (one per expired suspension).
+    pub async fn cleanup_expired(&self) -> PdsResult<Vec<String>> {
         let now = Utc::now();
 
-        let result = sqlx::query(
+        let dids = sqlx::query_scalar(
             r#"
             UPDATE account_moderation
             SET reversed = 1,
@@ -229,14 +248,15 @@ impl ModerationManager {
               AND reversed = 0
               AND expires_at IS NOT NULL
               AND expires_at < ?
+            RETURNING did
             "#,
         )
         .bind(now.to_rfc3339())
         .bind(now.to_rfc3339())
-        .execute(&self.db)
+        .fetch_all(&self.db)
         .await?;
 
-        Ok(result.rows_affected())
+        Ok(dids)
     }
 
     /// Parse database rows into ModerationRecord objects
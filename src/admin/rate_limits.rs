@@ -0,0 +1,302 @@
+/// Per-account Rate Limit Overrides
+///
+/// Admins can raise the budget for high-volume bots or clamp down on
+/// abusive-but-not-bannable accounts. Overrides are persisted and loaded
+/// into the runtime limiter at startup.
+use crate::error::{PdsError, PdsResult};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+/// Per-account rate limit override
+///
+/// Either an explicit budget (`requests_per_second` / `burst_size`) or a
+/// `multiplier` applied to the authenticated defaults. Explicit values win.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitOverride {
+    pub did: String,
+    pub multiplier: Option<f64>,
+    pub requests_per_second: Option<u32>,
+    pub burst_size: Option<u32>,
+    pub reason: Option<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitOverride {
+    /// Resolve the effective (requests per second, burst) given the defaults
+    pub fn effective_quota(&self, default_rps: u32, default_burst: u32) -> (u32, u32) {
+        let scale = |base: u32| -> u32 {
+            match self.multiplier {
+                Some(m) => ((base as f64) * m).round().max(1.0) as u32,
+                None => base,
+            }
+        };
+
+        let rps = self.requests_per_second.unwrap_or_else(|| scale(default_rps)).max(1);
+        let burst = self.burst_size.unwrap_or_else(|| scale(default_burst)).max(1);
+        (rps, burst)
+    }
+
+    /// Whether the override has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.map(|t| t <= Utc::now()).unwrap_or(false)
+    }
+}
+
+/// Rate limit override manager
+#[derive(Clone)]
+pub struct RateLimitOverrideManager {
+    db: SqlitePool,
+}
+
+impl RateLimitOverrideManager {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Create or replace the override for an account
+    pub async fn set_override(
+        &self,
+        did: &str,
+        multiplier: Option<f64>,
+        requests_per_second: Option<u32>,
+        burst_size: Option<u32>,
+        reason: Option<String>,
+        created_by: &str,
+        expires_in: Option<chrono::Duration>,
+    ) -> PdsResult<RateLimitOverride> {
+        if multiplier.is_none() && requests_per_second.is_none() && burst_size.is_none() {
+            return Err(PdsError::Validation(
+                "Override requires a multiplier or an explicit budget".to_string(),
+            ));
+        }
+        if let Some(m) = multiplier {
+            if !m.is_finite() || m <= 0.0 {
+                return Err(PdsError::Validation(
+                    "Multiplier must be a positive number".to_string(),
+                ));
+            }
+        }
+        if requests_per_second == Some(0) || burst_size == Some(0) {
+            return Err(PdsError::Validation(
+                "Explicit budgets must be greater than zero".to_string(),
+            ));
+        }
+
+        let now = Utc::now();
+        let expires_at = expires_in.map(|d| now + d);
+
+        sqlx::query(
+            r#"
+            INSERT INTO rate_limit_override
+                (did, multiplier, requests_per_second, burst_size, reason, created_by, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(did) DO UPDATE SET
+                multiplier = excluded.multiplier,
+                requests_per_second = excluded.requests_per_second,
+                burst_size = excluded.burst_size,
+                reason = excluded.reason,
+                created_by = excluded.created_by,
+                created_at = excluded.created_at,
+                expires_at = excluded.expires_at
+            "#,
+        )
+        .bind(did)
+        .bind(multiplier)
+        .bind(requests_per_second.map(|v| v as i64))
+        .bind(burst_size.map(|v| v as i64))
+        .bind(&reason)
+        .bind(created_by)
+        .bind(now.to_rfc3339())
+        .bind(expires_at.map(|dt| dt.to_rfc3339()))
+        .execute(&self.db)
+        .await?;
+
+        Ok(RateLimitOverride {
+            did: did.to_string(),
+            multiplier,
+            requests_per_second,
+            burst_size,
+            reason,
+            created_by: created_by.to_string(),
+            created_at: now,
+            expires_at,
+        })
+    }
+
+    /// Remove the override for an account
+    pub async fn remove_override(&self, did: &str) -> PdsResult<()> {
+        let result = sqlx::query("DELETE FROM rate_limit_override WHERE did = ?")
+            .bind(did)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PdsError::NotFound(format!("No rate limit override for {}", did)));
+        }
+
+        Ok(())
+    }
+
+    /// Get the active override for an account
+    pub async fn get_override(&self, did: &str) -> PdsResult<Option<RateLimitOverride>> {
+        let row = sqlx::query(
+            r#"
+            SELECT did, multiplier, requests_per_second, burst_size, reason, created_by, created_at, expires_at
+            FROM rate_limit_override
+            WHERE did = ?
+            "#,
+        )
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?;
+
+        match row {
+            Some(row) => {
+                let entry = Self::row_to_override(&row)?;
+                Ok(if entry.is_expired() { None } else { Some(entry) })
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// List all active (non-expired) overrides
+    pub async fn list_overrides(&self) -> PdsResult<Vec<RateLimitOverride>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT did, multiplier, requests_per_second, burst_size, reason, created_by, created_at, expires_at
+            FROM rate_limit_override
+            ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut overrides = Vec::new();
+        for row in rows {
+            let entry = Self::row_to_override(&row)?;
+            if !entry.is_expired() {
+                overrides.push(entry);
+            }
+        }
+
+        Ok(overrides)
+    }
+
+    fn row_to_override(row: &sqlx::sqlite::SqliteRow) -> PdsResult<RateLimitOverride> {
+        let created_at_str: String = row.get("created_at");
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?
+            .with_timezone(&Utc);
+
+        let expires_at = match row.get::<Option<String>, _>("expires_at") {
+            Some(s) => Some(
+                DateTime::parse_from_rfc3339(&s)
+                    .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+
+        Ok(RateLimitOverride {
+            did: row.get("did"),
+            multiplier: row.get("multiplier"),
+            requests_per_second: row
+                .get::<Option<i64>, _>("requests_per_second")
+                .map(|v| v as u32),
+            burst_size: row.get::<Option<i64>, _>("burst_size").map(|v| v as u32),
+            reason: row.get("reason"),
+            created_by: row.get("created_by"),
+            created_at,
+            expires_at,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup_db() -> SqlitePool {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE rate_limit_override (
+                did TEXT PRIMARY KEY,
+                multiplier REAL,
+                requests_per_second INTEGER,
+                burst_size INTEGER,
+                reason TEXT,
+                created_by TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                expires_at TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        db
+    }
+
+    #[tokio::test]
+    async fn test_set_get_remove_override() {
+        let manager = RateLimitOverrideManager::new(setup_db().await);
+
+        manager
+            .set_override("did:plc:bot", Some(4.0), None, None, Some("feed bot".to_string()), "did:plc:admin", None)
+            .await
+            .unwrap();
+
+        let entry = manager.get_override("did:plc:bot").await.unwrap().unwrap();
+        assert_eq!(entry.multiplier, Some(4.0));
+        assert_eq!(entry.effective_quota(100, 50), (400, 200));
+
+        // Replacing keeps a single row
+        manager
+            .set_override("did:plc:bot", None, Some(5), Some(2), None, "did:plc:admin", None)
+            .await
+            .unwrap();
+        assert_eq!(manager.list_overrides().await.unwrap().len(), 1);
+        let entry = manager.get_override("did:plc:bot").await.unwrap().unwrap();
+        assert_eq!(entry.effective_quota(100, 50), (5, 2));
+
+        manager.remove_override("did:plc:bot").await.unwrap();
+        assert!(manager.get_override("did:plc:bot").await.unwrap().is_none());
+        assert!(manager.remove_override("did:plc:bot").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_override_validation_and_expiry() {
+        let manager = RateLimitOverrideManager::new(setup_db().await);
+
+        assert!(manager
+            .set_override("did:plc:x", None, None, None, None, "did:plc:admin", None)
+            .await
+            .is_err());
+        assert!(manager
+            .set_override("did:plc:x", Some(0.0), None, None, None, "did:plc:admin", None)
+            .await
+            .is_err());
+
+        manager
+            .set_override(
+                "did:plc:x",
+                Some(0.5),
+                None,
+                None,
+                None,
+                "did:plc:admin",
+                Some(chrono::Duration::seconds(-1)),
+            )
+            .await
+            .unwrap();
+        assert!(manager.get_override("did:plc:x").await.unwrap().is_none());
+        assert!(manager.list_overrides().await.unwrap().is_empty());
+    }
+}
@@ -1,8 +1,10 @@
 /// Report Management System
+use crate::admin::webhooks::{events, WebhookManager};
 use crate::error::{PdsError, PdsResult};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
 
 /// Report reason types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -39,6 +41,33 @@ impl ReportReason {
             _ => Err(PdsError::Validation(format!("Invalid report reason: {}", s))),
         }
     }
+
+    /// Parse a `com.atproto.moderation.defs#reason*` token
+    ///
+    /// Reason types are an open set; unknown tokens (e.g. appeals or
+    /// Ozone-specific reasons) are kept as `Other`.
+    pub fn from_lexicon(s: &str) -> Self {
+        match s.strip_prefix("com.atproto.moderation.defs#") {
+            Some("reasonSpam") => ReportReason::Spam,
+            Some("reasonViolation") => ReportReason::Violation,
+            Some("reasonMisleading") => ReportReason::Misleading,
+            Some("reasonSexual") => ReportReason::Sexual,
+            Some("reasonRude") => ReportReason::Rude,
+            _ => ReportReason::Other,
+        }
+    }
+
+    /// The `com.atproto.moderation.defs#reason*` token for this reason
+    pub fn to_lexicon(&self) -> &'static str {
+        match self {
+            ReportReason::Spam => "com.atproto.moderation.defs#reasonSpam",
+            ReportReason::Violation => "com.atproto.moderation.defs#reasonViolation",
+            ReportReason::Misleading => "com.atproto.moderation.defs#reasonMisleading",
+            ReportReason::Sexual => "com.atproto.moderation.defs#reasonSexual",
+            ReportReason::Rude => "com.atproto.moderation.defs#reasonRude",
+            ReportReason::Other => "com.atproto.moderation.defs#reasonOther",
+        }
+    }
 }
 
 /// Report status
@@ -74,6 +103,7 @@ impl ReportStatus {
 
 /// Report record
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Report {
     pub id: i64,
     pub subject_did: Option<String>,
@@ -93,11 +123,18 @@ pub struct Report {
 #[derive(Clone)]
 pub struct ReportManager {
     db: SqlitePool,
+    webhooks: Option<Arc<WebhookManager>>,
 }
 
 impl ReportManager {
     pub fn new(db: SqlitePool) -> Self {
-        Self { db }
+        Self { db, webhooks: None }
+    }
+
+    /// Send new reports to moderation webhooks
+    pub fn with_webhooks(mut self, webhooks: Arc<WebhookManager>) -> Self {
+        self.webhooks = Some(webhooks);
+        self
     }
 
     /// Submit a report
@@ -135,7 +172,7 @@ impl ReportManager {
         .execute(&self.db)
         .await?;
 
-        Ok(Report {
+        let report = Report {
             id: result.last_insert_rowid(),
             subject_did: subject_did.map(String::from),
             subject_uri: subject_uri.map(String::from),
@@ -148,7 +185,13 @@ impl ReportManager {
             reviewed_by: None,
             reviewed_at: None,
             resolution: None,
-        })
+        };
+
+        if let Some(webhooks) = &self.webhooks {
+            webhooks.dispatch(events::REPORT_CREATED, &report).await;
+        }
+
+        Ok(report)
     }
 
     /// Update report status
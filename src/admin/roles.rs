@@ -1,5 +1,8 @@
 /// Admin Role Management
-use crate::error::{PdsError, PdsResult};
+use crate::{
+    admin::{AuditLogEntry, AuditLogQuery},
+    error::{PdsError, PdsResult},
+};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sqlx::{Row, SqlitePool};
@@ -42,10 +45,13 @@ impl Role {
 
 /// Admin role record
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct AdminRole {
     pub id: i64,
     pub did: String,
     pub role: Role,
+    /// Handle domain the role is limited to (None = server-wide)
+    pub domain: Option<String>,
     pub granted_by: Option<String>,
     pub granted_at: DateTime<Utc>,
     pub revoked: bool,
@@ -66,10 +72,14 @@ impl AdminRoleManager {
     }
 
     /// Grant admin role to a DID
+    ///
+    /// With a `domain` the role only covers accounts whose handle is under
+    /// that handle domain.
     pub async fn grant_role(
         &self,
         did: &str,
         role: Role,
+        domain: Option<String>,
         granted_by: &str,
         notes: Option<String>,
     ) -> PdsResult<AdminRole> {
@@ -87,12 +97,13 @@ impl AdminRoleManager {
 
         let result = sqlx::query(
             r#"
-            INSERT INTO admin_roles (did, role, granted_by, granted_at, notes)
-            VALUES (?, ?, ?, ?, ?)
+            INSERT INTO admin_roles (did, role, domain, granted_by, granted_at, notes)
+            VALUES (?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(did)
         .bind(role.as_str())
+        .bind(&domain)
         .bind(granted_by)
         .bind(now.to_rfc3339())
         .bind(&notes)
@@ -105,6 +116,7 @@ impl AdminRoleManager {
             id,
             did: did.to_string(),
             role,
+            domain,
             granted_by: Some(granted_by.to_string()),
             granted_at: now,
             revoked: false,
@@ -151,7 +163,7 @@ impl AdminRoleManager {
     pub async fn get_role(&self, did: &str) -> PdsResult<Option<AdminRole>> {
         let row = sqlx::query(
             r#"
-            SELECT id, did, role, granted_by, granted_at, revoked, revoked_at, revoked_by, notes
+            SELECT id, did, role, domain, granted_by, granted_at, revoked, revoked_at, revoked_by, notes
             FROM admin_roles
             WHERE did = ? AND revoked = 0
             ORDER BY granted_at DESC
@@ -181,6 +193,7 @@ impl AdminRoleManager {
                 id: row.get("id"),
                 did: row.get("did"),
                 role,
+                domain: row.get("domain"),
                 granted_by: row.get("granted_by"),
                 granted_at,
                 revoked: row.get("revoked"),
@@ -206,7 +219,7 @@ impl AdminRoleManager {
     pub async fn list_active_roles(&self) -> PdsResult<Vec<AdminRole>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, did, role, granted_by, granted_at, revoked, revoked_at, revoked_by, notes
+            SELECT id, did, role, domain, granted_by, granted_at, revoked, revoked_at, revoked_by, notes
             FROM admin_roles
             WHERE revoked = 0
             ORDER BY granted_at DESC
@@ -235,6 +248,7 @@ impl AdminRoleManager {
                 id: row.get("id"),
                 did: row.get("did"),
                 role,
+                domain: row.get("domain"),
                 granted_by: row.get("granted_by"),
                 granted_at,
                 revoked: row.get("revoked"),
@@ -275,6 +289,96 @@ impl AdminRoleManager {
 
         Ok(())
     }
+
+    /// Log several admin actions in one transaction
+    ///
+    /// Entries are `(action, subject_did, details)`; either all of them are
+    /// written or none is.
+    pub async fn log_actions(
+        &self,
+        admin_did: &str,
+        entries: &[(&str, Option<&str>, Option<&str>)],
+        ip_address: Option<&str>,
+    ) -> PdsResult<()> {
+        let now = Utc::now().to_rfc3339();
+        let mut tx = self.db.begin().await?;
+
+        for (action, subject_did, details) in entries {
+            sqlx::query(
+                r#"
+                INSERT INTO admin_audit_log (admin_did, action, subject_did, details, timestamp, ip_address)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(admin_did)
+            .bind(action)
+            .bind(subject_did)
+            .bind(details)
+            .bind(&now)
+            .bind(ip_address)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Read audit log entries matching a query, newest first
+    pub async fn query_audit_log(&self, query: &AuditLogQuery) -> PdsResult<Vec<AuditLogEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, admin_did, action, subject_did, details, timestamp, ip_address
+            FROM admin_audit_log
+            WHERE (?1 IS NULL OR admin_did = ?1)
+              AND (?2 IS NULL OR action = ?2)
+              AND (?3 IS NULL OR subject_did = ?3)
+              AND (?4 IS NULL OR timestamp >= ?4)
+              AND (?5 IS NULL OR timestamp < ?5)
+              AND (?6 IS NULL OR id < ?6)
+            ORDER BY id DESC
+            LIMIT ?7
+            "#,
+        )
+        .bind(&query.admin_did)
+        .bind(&query.action)
+        .bind(&query.subject_did)
+        .bind(query.since.map(|t| t.to_rfc3339()))
+        .bind(query.until.map(|t| t.to_rfc3339()))
+        .bind(query.cursor)
+        .bind(query.limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                let timestamp_str: String = row.get("timestamp");
+                let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
+                    .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))?
+                    .with_timezone(&Utc);
+
+                Ok(AuditLogEntry {
+                    id: row.get("id"),
+                    admin_did: row.get("admin_did"),
+                    action: row.get("action"),
+                    subject_did: row.get("subject_did"),
+                    details: row.get("details"),
+                    timestamp,
+                    ip_address: row.get("ip_address"),
+                })
+            })
+            .collect()
+    }
+
+    /// Delete audit log entries older than `cutoff`
+    pub async fn prune_audit_log(&self, cutoff: DateTime<Utc>) -> PdsResult<u64> {
+        let result = sqlx::query("DELETE FROM admin_audit_log WHERE timestamp < ?1")
+            .bind(cutoff.to_rfc3339())
+            .execute(&self.db)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
 }
 
 #[cfg(test)]
@@ -315,6 +419,7 @@ mod tests {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 did TEXT NOT NULL UNIQUE,
                 role TEXT NOT NULL,
+                domain TEXT,
                 granted_by TEXT,
                 granted_at TEXT NOT NULL,
                 revoked INTEGER NOT NULL DEFAULT 0,
@@ -352,6 +457,7 @@ mod tests {
             .grant_role(
                 "did:plc:alice",
                 Role::Admin,
+                Some("example.com".to_string()),
                 "did:plc:superadmin",
                 Some("First admin".to_string()),
             )
@@ -365,6 +471,7 @@ mod tests {
         // Get role
         let retrieved = manager.get_role("did:plc:alice").await.unwrap().unwrap();
         assert_eq!(retrieved.role, Role::Admin);
+        assert_eq!(retrieved.domain.as_deref(), Some("example.com"));
 
         // Check role
         assert!(manager.has_role("did:plc:alice", Role::Admin).await.unwrap());
@@ -388,6 +495,7 @@ mod tests {
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
                 did TEXT NOT NULL UNIQUE,
                 role TEXT NOT NULL,
+                domain TEXT,
                 granted_by TEXT,
                 granted_at TEXT NOT NULL,
                 revoked INTEGER NOT NULL DEFAULT 0,
@@ -422,7 +530,7 @@ mod tests {
 
         // Grant then revoke
         manager
-            .grant_role("did:plc:bob", Role::Moderator, "did:plc:admin", None)
+            .grant_role("did:plc:bob", Role::Moderator, None, "did:plc:admin", None)
             .await
             .unwrap();
 
@@ -438,4 +546,130 @@ mod tests {
             .await
             .unwrap());
     }
+
+    #[tokio::test]
+    async fn test_query_and_prune_audit_log() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE admin_audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                admin_did TEXT NOT NULL,
+                action TEXT NOT NULL,
+                subject_did TEXT,
+                details TEXT,
+                timestamp TEXT NOT NULL,
+                ip_address TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let manager = AdminRoleManager::new(db.clone());
+        manager
+            .log_action("did:plc:admin", "account.takedown", Some("did:plc:bob"), None, None)
+            .await
+            .unwrap();
+        manager
+            .log_action("did:plc:admin", "role.grant", Some("did:plc:carol"), None, None)
+            .await
+            .unwrap();
+        manager
+            .log_action("did:plc:other", "account.takedown", Some("did:plc:dave"), None, None)
+            .await
+            .unwrap();
+
+        // Backdate the first entry
+        let old = (Utc::now() - chrono::Duration::days(400)).to_rfc3339();
+        sqlx::query("UPDATE admin_audit_log SET timestamp = ?1 WHERE id = 1")
+            .bind(&old)
+            .execute(&db)
+            .await
+            .unwrap();
+
+        let all = AuditLogQuery { limit: 10, ..Default::default() };
+        let entries = manager.query_audit_log(&all).await.unwrap();
+        assert_eq!(entries.iter().map(|e| e.id).collect::<Vec<_>>(), vec![3, 2, 1]);
+
+        let takedowns = AuditLogQuery {
+            action: Some("account.takedown".to_string()),
+            admin_did: Some("did:plc:admin".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        let entries = manager.query_audit_log(&takedowns).await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].subject_did.as_deref(), Some("did:plc:bob"));
+
+        let recent = AuditLogQuery {
+            since: Some(Utc::now() - chrono::Duration::days(1)),
+            limit: 1,
+            ..Default::default()
+        };
+        let page = manager.query_audit_log(&recent).await.unwrap();
+        assert_eq!(page[0].id, 3);
+        let next = AuditLogQuery { cursor: Some(page[0].id), ..recent };
+        let page = manager.query_audit_log(&next).await.unwrap();
+        assert_eq!(page[0].id, 2);
+
+        let pruned = manager
+            .prune_audit_log(Utc::now() - chrono::Duration::days(365))
+            .await
+            .unwrap();
+        assert_eq!(pruned, 1);
+        assert_eq!(manager.query_audit_log(&all).await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_log_actions_is_atomic() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE admin_audit_log (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                admin_did TEXT NOT NULL,
+                action TEXT NOT NULL CHECK (action != 'invalid'),
+                subject_did TEXT,
+                details TEXT,
+                timestamp TEXT NOT NULL,
+                ip_address TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let manager = AdminRoleManager::new(db);
+        manager
+            .log_actions(
+                "did:plc:admin",
+                &[
+                    ("account.takedown", Some("did:plc:bob"), None),
+                    ("moderation.bulk", None, Some("takedown of 1 subject(s)")),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+
+        // One bad entry writes nothing
+        let result = manager
+            .log_actions(
+                "did:plc:admin",
+                &[("account.suspend", Some("did:plc:carol"), None), ("invalid", None, None)],
+                None,
+            )
+            .await;
+        assert!(result.is_err());
+
+        let all = AuditLogQuery { limit: 10, ..Default::default() };
+        let entries = manager.query_audit_log(&all).await.unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].action, "moderation.bulk");
+    }
 }
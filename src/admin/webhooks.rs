@@ -0,0 +1,444 @@
+/// Moderation webhooks
+///
+/// Operators register HTTPS endpoints that receive moderation events (new
+/// reports, labels, account actions) as signed JSON, so external tooling can
+/// react without polling the admin API.
+///
+/// Events are queued in `moderation_webhook_delivery` and sent by a
+/// background job, which retries failures with exponential backoff. Each
+/// request carries:
+///
+/// - `X-Aurora-Event`: event type (e.g. `report.created`)
+/// - `X-Aurora-Delivery`: delivery id
+/// - `X-Aurora-Timestamp`: unix seconds when the request was signed
+/// - `X-Aurora-Signature`: `sha256=<hex>` HMAC of `"{timestamp}.{body}"`
+///   keyed with the webhook secret
+use crate::{
+    delivery::{Attempt, DeliveryQueue},
+    error::{PdsError, PdsResult},
+};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+pub use crate::delivery::{parse_time, DeliveryStatus, WebhookDelivery};
+
+/// Event types sent to webhooks
+pub mod events {
+    pub const REPORT_CREATED: &str = "report.created";
+    pub const LABEL_APPLIED: &str = "label.applied";
+    pub const LABEL_REMOVED: &str = "label.removed";
+    /// Prefix for account moderation actions (`account.takedown`, `account.suspend`, ...)
+    pub const ACCOUNT_PREFIX: &str = "account.";
+    /// Open signups hit the hourly cap and invite codes are now required
+    pub const SIGNUP_LOCKDOWN: &str = "signup.lockdown";
+}
+
+/// Registered webhook (the secret is only returned at registration)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Webhook {
+    pub id: i64,
+    pub url: String,
+    /// Event types delivered to this webhook (empty means all)
+    pub events: Vec<String>,
+    pub enabled: bool,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    /// Whether this webhook subscribes to an event type
+    ///
+    /// Entries ending in `.` or `.*` match a whole prefix, e.g. `account.*`.
+    pub fn wants(&self, event_type: &str) -> bool {
+        matches_filter(&self.events, event_type)
+    }
+}
+
+/// Signed event body
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WebhookPayload<'a, T: Serialize> {
+    event: &'a str,
+    created_at: DateTime<Utc>,
+    data: &'a T,
+}
+
+/// Webhook registry and delivery queue
+pub struct WebhookManager {
+    db: SqlitePool,
+    deliveries: DeliveryQueue,
+    http_client: reqwest::Client,
+    /// Wakes the delivery job when new events are queued
+    notify: Arc<Notify>,
+}
+
+impl WebhookManager {
+    pub fn new(db: SqlitePool) -> Self {
+        let http_client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .unwrap_or_default();
+
+        Self {
+            deliveries: DeliveryQueue::new(db.clone(), "moderation_webhook_delivery"),
+            db,
+            http_client,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Signal raised whenever deliveries are queued
+    pub fn notifier(&self) -> Arc<Notify> {
+        self.notify.clone()
+    }
+
+    /// Register a webhook, returning it with its signing secret
+    ///
+    /// A random secret is generated when none is given.
+    pub async fn register(
+        &self,
+        url: &str,
+        events: Vec<String>,
+        secret: Option<String>,
+        created_by: &str,
+    ) -> PdsResult<(Webhook, String)> {
+        let parsed = reqwest::Url::parse(url)
+            .map_err(|e| PdsError::Validation(format!("Invalid webhook URL: {}", e)))?;
+        if !matches!(parsed.scheme(), "https" | "http") {
+            return Err(PdsError::Validation("Webhook URL must be an http(s) URL".to_string()));
+        }
+
+        let secret = match secret {
+            Some(s) if s.len() < 16 => {
+                return Err(PdsError::Validation(
+                    "Webhook secret must be at least 16 characters".to_string(),
+                ))
+            }
+            Some(s) => s,
+            None => {
+                let mut bytes = [0u8; 32];
+                rand::thread_rng().fill_bytes(&mut bytes);
+                hex::encode(bytes)
+            }
+        };
+
+        let events: Vec<String> = events
+            .into_iter()
+            .map(|e| e.trim().to_string())
+            .filter(|e| !e.is_empty())
+            .collect();
+        let now = Utc::now();
+
+        let result = sqlx::query(
+            r#"
+            INSERT INTO moderation_webhook (url, secret, events, enabled, created_by, created_at)
+            VALUES (?1, ?2, ?3, 1, ?4, ?5)
+            "#,
+        )
+        .bind(url)
+        .bind(&secret)
+        .bind(events.join(","))
+        .bind(created_by)
+        .bind(now.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        let webhook = Webhook {
+            id: result.last_insert_rowid(),
+            url: url.to_string(),
+            events,
+            enabled: true,
+            created_by: created_by.to_string(),
+            created_at: now,
+        };
+
+        Ok((webhook, secret))
+    }
+
+    /// Remove a webhook and its delivery history
+    pub async fn remove(&self, id: i64) -> PdsResult<()> {
+        let result = sqlx::query("DELETE FROM moderation_webhook WHERE id = ?1")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(PdsError::NotFound(format!("Webhook not found: {}", id)));
+        }
+
+        sqlx::query("DELETE FROM moderation_webhook_delivery WHERE webhook_id = ?1")
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// List registered webhooks
+    pub async fn list(&self) -> PdsResult<Vec<Webhook>> {
+        let rows = sqlx::query(
+            "SELECT id, url, events, enabled, created_by, created_at FROM moderation_webhook ORDER BY id",
+        )
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter().map(Self::webhook_from_row).collect()
+    }
+
+    fn webhook_from_row(row: &sqlx::sqlite::SqliteRow) -> PdsResult<Webhook> {
+        let events: String = row.get("events");
+
+        Ok(Webhook {
+            id: row.get("id"),
+            url: row.get("url"),
+            events: events
+                .split(',')
+                .filter(|e| !e.is_empty())
+                .map(String::from)
+                .collect(),
+            enabled: row.get("enabled"),
+            created_by: row.get("created_by"),
+            created_at: parse_time(&row.get::<String, _>("created_at"))?,
+        })
+    }
+
+    /// Queue an event for every enabled webhook subscribed to it
+    ///
+    /// Failures are logged rather than returned so that moderation actions
+    /// never fail because of webhook bookkeeping.
+    pub async fn dispatch<T: Serialize>(&self, event_type: &str, data: &T) {
+        if let Err(e) = self.enqueue(event_type, data).await {
+            tracing::warn!("Failed to queue {} webhook event: {}", event_type, e);
+        }
+    }
+
+    async fn enqueue<T: Serialize>(&self, event_type: &str, data: &T) -> PdsResult<usize> {
+        let webhooks: Vec<Webhook> = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|w| w.enabled && w.wants(event_type))
+            .collect();
+        if webhooks.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now();
+        let payload = serde_json::to_string(&WebhookPayload {
+            event: event_type,
+            created_at: now,
+            data,
+        })
+        .map_err(|e| PdsError::Internal(format!("Failed to serialize webhook payload: {}", e)))?;
+
+        for webhook in &webhooks {
+            sqlx::query(
+                r#"
+                INSERT INTO moderation_webhook_delivery
+                (webhook_id, event_type, payload, status, attempts, created_at, next_attempt_at)
+                VALUES (?1, ?2, ?3, 'pending', 0, ?4, ?4)
+                "#,
+            )
+            .bind(webhook.id)
+            .bind(event_type)
+            .bind(&payload)
+            .bind(now.to_rfc3339())
+            .execute(&self.db)
+            .await?;
+        }
+
+        self.notify.notify_one();
+        Ok(webhooks.len())
+    }
+
+    /// List deliveries, newest first, optionally for one webhook or status
+    pub async fn list_deliveries(
+        &self,
+        webhook_id: Option<i64>,
+        status: Option<DeliveryStatus>,
+        cursor: Option<i64>,
+        limit: i64,
+    ) -> PdsResult<Vec<WebhookDelivery>> {
+        self.deliveries.list(None, webhook_id, status, cursor, limit).await
+    }
+
+    /// Attempt every pending delivery that is due
+    ///
+    /// Returns (delivered, failed attempts).
+    pub async fn deliver_due(&self, limit: i64) -> PdsResult<(u64, u64)> {
+        self.deliveries
+            .deliver_due(
+                "d.event_type, d.payload, w.url, w.secret",
+                "JOIN moderation_webhook w ON w.id = d.webhook_id AND w.enabled = 1",
+                limit,
+                |row| post_signed_row(&self.http_client, row),
+            )
+            .await
+    }
+
+    /// Delete delivered entries past the retention window
+    pub async fn prune_deliveries(&self) -> PdsResult<u64> {
+        self.deliveries.prune().await
+    }
+}
+
+/// Whether `event_type` passes a subscription filter
+///
+/// An empty filter matches everything; entries ending in `.` or `.*` match
+/// a whole prefix, e.g. `account.*`.
+pub fn matches_filter(filter: &[String], event_type: &str) -> bool {
+    filter.is_empty()
+        || filter.iter().any(|e| {
+            let prefix = e.strip_suffix('*').unwrap_or(e);
+            if prefix.ends_with('.') {
+                event_type.starts_with(prefix)
+            } else {
+                event_type == e
+            }
+        })
+}
+
+/// POST a signed delivery, returning the response status and the error, if
+/// it failed
+pub(crate) async fn post_signed(
+    client: &reqwest::Client,
+    url: &str,
+    secret: &str,
+    event_type: &str,
+    delivery_id: i64,
+    payload: String,
+) -> (Option<i64>, Option<String>) {
+    let timestamp = Utc::now().timestamp();
+    let result = client
+        .post(url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header("X-Aurora-Event", event_type)
+        .header("X-Aurora-Delivery", delivery_id.to_string())
+        .header("X-Aurora-Timestamp", timestamp.to_string())
+        .header("X-Aurora-Signature", sign_payload(secret, timestamp, &payload))
+        .body(payload)
+        .send()
+        .await;
+
+    match result {
+        Ok(response) if response.status().is_success() => (Some(response.status().as_u16() as i64), None),
+        Ok(response) => (
+            Some(response.status().as_u16() as i64),
+            Some(format!("Endpoint returned {}", response.status())),
+        ),
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+/// POST the due delivery in `row` (`d.id`, `d.event_type`, `d.payload`,
+/// `w.url` and `w.secret`) to its webhook
+pub(crate) async fn post_signed_row(client: &reqwest::Client, row: SqliteRow) -> Attempt {
+    let url: String = row.get("url");
+    post_signed(
+        client,
+        &url,
+        &row.get::<String, _>("secret"),
+        &row.get::<String, _>("event_type"),
+        row.get("id"),
+        row.get("payload"),
+    )
+    .await
+}
+
+/// `sha256=<hex>` signature of a payload sent at `timestamp`
+pub fn sign_payload(secret: &str, timestamp: i64, payload: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(payload.as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn create_test_manager() -> WebhookManager {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(include_str!("../../migrations/20250118000001_moderation_webhook.sql"))
+            .execute(&db)
+            .await
+            .unwrap();
+        WebhookManager::new(db)
+    }
+
+    #[test]
+    fn test_event_filter() {
+        let mut webhook = Webhook {
+            id: 1,
+            url: "https://mod.example/hook".to_string(),
+            events: vec![],
+            enabled: true,
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+        };
+        assert!(webhook.wants("report.created"));
+
+        webhook.events = vec!["report.created".to_string(), "account.*".to_string()];
+        assert!(webhook.wants("report.created"));
+        assert!(webhook.wants("account.takedown"));
+        assert!(!webhook.wants("label.applied"));
+    }
+
+    #[test]
+    fn test_signature() {
+        let signature = sign_payload("secret", 1700000000, r#"{"event":"report.created"}"#);
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_ne!(signature, sign_payload("secret", 1700000001, r#"{"event":"report.created"}"#));
+    }
+
+    #[tokio::test]
+    async fn test_dispatch_and_retry() {
+        let manager = create_test_manager().await;
+        let (webhook, secret) = manager
+            .register(
+                // Nothing listens here, so delivery fails
+                "http://127.0.0.1:9/hook",
+                vec!["report.created".to_string()],
+                None,
+                "did:plc:admin",
+            )
+            .await
+            .unwrap();
+        assert_eq!(secret.len(), 64);
+        assert!(manager.register("ftp://x", vec![], None, "did:plc:admin").await.is_err());
+
+        manager.dispatch(events::LABEL_APPLIED, &serde_json::json!({})).await;
+        manager
+            .dispatch(events::REPORT_CREATED, &serde_json::json!({ "id": 1 }))
+            .await;
+
+        let deliveries = manager.list_deliveries(Some(webhook.id), None, None, 10).await.unwrap();
+        assert_eq!(deliveries.len(), 1);
+        assert_eq!(deliveries[0].status, DeliveryStatus::Pending);
+
+        let (delivered, failed) = manager.deliver_due(10).await.unwrap();
+        assert_eq!((delivered, failed), (0, 1));
+
+        let delivery = &manager.list_deliveries(None, None, None, 10).await.unwrap()[0];
+        assert_eq!(delivery.status, DeliveryStatus::Pending);
+        assert_eq!(delivery.attempts, 1);
+        assert!(delivery.last_error.is_some());
+        assert!(delivery.next_attempt_at.unwrap() > Utc::now());
+
+        // Not due again until the backoff elapses
+        assert_eq!(manager.deliver_due(10).await.unwrap(), (0, 0));
+
+        manager.remove(webhook.id).await.unwrap();
+        assert!(manager.list_deliveries(None, None, None, 10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,272 @@
+/// ActivityPub bridge endpoints (experimental)
+///
+/// Fediverse-facing documents of bridged accounts:
+/// - /ap/actors/:did: actor document
+/// - /ap/actors/:did/outbox: translated posts, newest first
+/// - /ap/actors/:did/followers: follower count
+/// - /ap/actors/:did/notes/:rkey: a translated post
+/// - /ap/actors/:did/inbox: signed follows and unfollows
+///
+/// and the account's own controls:
+/// - app.aurora.activitypub.getBridgeStatus
+/// - app.aurora.activitypub.enableBridge
+/// - app.aurora.activitypub.disableBridge
+
+use crate::{
+    activitypub::{translate::CONTEXT, ACTIVITY_JSON},
+    actor_store::record_codec::decode_record,
+    api::middleware,
+    context::AppContext,
+    db::account::Account,
+    error::{PdsError, PdsResult},
+};
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Notes per outbox page
+const OUTBOX_PAGE_SIZE: i64 = 20;
+
+/// Build ActivityPub routes
+pub fn routes() -> Router<AppContext> {
+    Router::new()
+        .route("/ap/actors/:did", get(actor))
+        .route("/ap/actors/:did/outbox", get(outbox))
+        .route("/ap/actors/:did/followers", get(followers))
+        .route("/ap/actors/:did/notes/:rkey", get(note))
+        .route("/ap/actors/:did/inbox", post(inbox))
+        .route("/xrpc/app.aurora.activitypub.getBridgeStatus", get(get_bridge_status))
+        .route("/xrpc/app.aurora.activitypub.enableBridge", post(enable_bridge))
+        .route("/xrpc/app.aurora.activitypub.disableBridge", post(disable_bridge))
+}
+
+/// ActivityPub JSON response
+fn activity_json(doc: Value) -> Response {
+    ([(header::CONTENT_TYPE, ACTIVITY_JSON)], Json(doc)).into_response()
+}
+
+/// Active local account whose bridge is on
+async fn bridged_account(ctx: &AppContext, did: &str) -> PdsResult<Account> {
+    let not_found = || PdsError::NotFound(format!("No ActivityPub actor for {}", did));
+    if !ctx.activitypub.enabled() {
+        return Err(not_found());
+    }
+
+    let account = ctx.account_manager.get_account(did).await.map_err(|_| not_found())?;
+    if account.taken_down || account.deactivated_at.is_some() || !ctx.activitypub.is_bridged(did).await? {
+        return Err(not_found());
+    }
+    Ok(account)
+}
+
+/// GET /ap/actors/:did
+async fn actor(State(ctx): State<AppContext>, Path(did): Path<String>) -> PdsResult<Response> {
+    let account = bridged_account(&ctx, &did).await?;
+
+    let profile_uri = format!("at://{}/app.bsky.actor.profile/self", did);
+    let profile = match ctx.actor_store.get_record(&did, &profile_uri).await? {
+        Some(record) => match ctx.actor_store.get_block(&did, &record.cid).await? {
+            Some(bytes) => decode_record(&bytes).ok(),
+            None => None,
+        },
+        None => None,
+    };
+
+    let doc = ctx
+        .activitypub
+        .actor_document(&did, &account.handle, profile.as_ref())
+        .await?
+        .ok_or_else(|| PdsError::NotFound(format!("No ActivityPub actor for {}", did)))?;
+    Ok(activity_json(doc))
+}
+
+#[derive(Debug, Deserialize)]
+struct OutboxParams {
+    #[serde(default)]
+    page: bool,
+    cursor: Option<String>,
+}
+
+/// GET /ap/actors/:did/outbox
+///
+/// The collection links to its first page (`?page=true`); pages link on
+/// with a `cursor`.
+async fn outbox(
+    State(ctx): State<AppContext>,
+    Path(did): Path<String>,
+    Query(params): Query<OutboxParams>,
+) -> PdsResult<Response> {
+    bridged_account(&ctx, &did).await?;
+    let outbox = format!("{}/outbox", ctx.activitypub.actor_url(&did));
+
+    if !params.page {
+        return Ok(activity_json(json!({
+            "@context": CONTEXT,
+            "id": outbox,
+            "type": "OrderedCollection",
+            "totalItems": ctx.activitypub.note_count(&did).await?,
+            "first": format!("{}?page=true", outbox),
+        })));
+    }
+
+    let (items, next) = ctx
+        .activitypub
+        .outbox_page(&did, params.cursor.as_deref(), OUTBOX_PAGE_SIZE)
+        .await?;
+    let page_url = |cursor: Option<&str>| match cursor {
+        Some(cursor) => format!("{}?page=true&cursor={}", outbox, urlencoding::encode(cursor)),
+        None => format!("{}?page=true", outbox),
+    };
+
+    let mut page = json!({
+        "@context": CONTEXT,
+        "id": page_url(params.cursor.as_deref()),
+        "type": "OrderedCollectionPage",
+        "partOf": outbox,
+        "orderedItems": items,
+    });
+    if let Some(next) = next {
+        page["next"] = json!(page_url(Some(&next)));
+    }
+    Ok(activity_json(page))
+}
+
+/// GET /ap/actors/:did/followers (the count only; followers aren't listed)
+async fn followers(State(ctx): State<AppContext>, Path(did): Path<String>) -> PdsResult<Response> {
+    bridged_account(&ctx, &did).await?;
+    Ok(activity_json(json!({
+        "@context": CONTEXT,
+        "id": format!("{}/followers", ctx.activitypub.actor_url(&did)),
+        "type": "OrderedCollection",
+        "totalItems": ctx.activitypub.follower_count(&did).await?,
+    })))
+}
+
+/// GET /ap/actors/:did/notes/:rkey
+async fn note(State(ctx): State<AppContext>, Path((did, rkey)): Path<(String, String)>) -> PdsResult<Response> {
+    bridged_account(&ctx, &did).await?;
+    let mut note = ctx
+        .activitypub
+        .note(&did, &rkey)
+        .await?
+        .ok_or_else(|| PdsError::NotFound(format!("No note {}", rkey)))?;
+    note["@context"] = json!(CONTEXT);
+    Ok(activity_json(note))
+}
+
+/// POST /ap/actors/:did/inbox
+///
+/// Requests must carry an HTTP signature by the activity's actor.
+async fn inbox(
+    State(ctx): State<AppContext>,
+    Path(did): Path<String>,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> PdsResult<StatusCode> {
+    bridged_account(&ctx, &did).await?;
+
+    let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok()).map(String::from);
+    let path = uri.path_and_query().map(|p| p.as_str()).unwrap_or_else(|| uri.path());
+    let signer = ctx
+        .activitypub
+        .verify_inbox_request(method.as_str(), path, header, &body)
+        .await?;
+
+    let activity: Value = serde_json::from_slice(&body)
+        .map_err(|e| PdsError::Validation(format!("Invalid activity: {}", e)))?;
+    ctx.activitypub.handle_inbox(&did, &activity, &signer).await?;
+
+    Ok(StatusCode::ACCEPTED)
+}
+
+/// Bridge status of the caller's account
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BridgeStatusResponse {
+    /// Whether the server offers the bridge
+    available: bool,
+    /// Whether the caller's posts are bridged
+    enabled: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    actor: Option<String>,
+    /// Address fediverse users follow, e.g. `alice@pds.example`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    acct: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    followers: Option<i64>,
+}
+
+async fn bridge_status(ctx: &AppContext, did: &str) -> PdsResult<BridgeStatusResponse> {
+    let enabled = ctx.activitypub.is_bridged(did).await?;
+    if !enabled {
+        return Ok(BridgeStatusResponse {
+            available: ctx.activitypub.enabled(),
+            enabled,
+            actor: None,
+            acct: None,
+            followers: None,
+        });
+    }
+
+    let account = ctx.account_manager.get_account(did).await?;
+    Ok(BridgeStatusResponse {
+        available: ctx.activitypub.enabled(),
+        enabled,
+        actor: Some(ctx.activitypub.actor_url(did)),
+        acct: Some(ctx.activitypub.acct(&account.handle)),
+        followers: Some(ctx.activitypub.follower_count(did).await?),
+    })
+}
+
+/// Whether the caller's posts are bridged, and where
+async fn get_bridge_status(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<BridgeStatusResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+    Ok(Json(bridge_status(&ctx, &validated.did).await?))
+}
+
+/// Start mirroring the caller's new posts to an ActivityPub actor
+async fn enable_bridge(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<BridgeStatusResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+    if validated.is_app_password {
+        return Err(PdsError::Authorization(
+            "Cannot change the ActivityPub bridge using app password authentication".to_string(),
+        ));
+    }
+
+    if ctx.activitypub.enable(&validated.did).await? {
+        tracing::info!(did = %validated.did, "activitypub_bridge_enabled");
+    }
+    Ok(Json(bridge_status(&ctx, &validated.did).await?))
+}
+
+/// Stop bridging, dropping the actor, its followers and translated posts
+async fn disable_bridge(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<BridgeStatusResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+    if validated.is_app_password {
+        return Err(PdsError::Authorization(
+            "Cannot change the ActivityPub bridge using app password authentication".to_string(),
+        ));
+    }
+
+    ctx.activitypub.disable(&validated.did).await?;
+    tracing::info!(did = %validated.did, "activitypub_bridge_disabled");
+    Ok(Json(bridge_status(&ctx, &validated.did).await?))
+}
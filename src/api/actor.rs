@@ -0,0 +1,170 @@
+/// app.bsky.actor.* endpoints served by the PDS
+///
+/// Preferences are private to the account and stored in its actor store.
+/// App-password sessions cannot read or write full-access-only preferences
+/// (e.g. personal details such as birth date).
+use crate::{
+    actor_store::store::pref_in_namespace,
+    api::middleware,
+    context::AppContext,
+    error::{PdsError, PdsResult},
+};
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Namespace clients may store preferences under
+const PREFERENCES_NAMESPACE: &str = "app.bsky";
+
+/// Preference types that require a full-access (non app password) session
+const FULL_ACCESS_ONLY_PREFS: &[&str] = &["app.bsky.actor.defs#personalDetailsPref"];
+
+/// Build actor routes
+pub fn routes() -> Router<AppContext> {
+    Router::new()
+        .route("/xrpc/app.bsky.actor.getPreferences", get(get_preferences))
+        .route("/xrpc/app.bsky.actor.putPreferences", post(put_preferences))
+}
+
+/// Preferences request/response body
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Preferences {
+    pub preferences: Vec<Value>,
+}
+
+/// Whether a session with the given access may see or change a preference type
+fn pref_in_scope(name: &str, full_access: bool) -> bool {
+    full_access || !FULL_ACCESS_ONLY_PREFS.contains(&name)
+}
+
+/// Validate preferences for storage, returning `(type, json)` pairs
+fn validate_preferences(
+    preferences: &[Value],
+    full_access: bool,
+) -> PdsResult<Vec<(String, String)>> {
+    let mut values = Vec::with_capacity(preferences.len());
+
+    for pref in preferences {
+        let name = pref
+            .get("$type")
+            .and_then(|t| t.as_str())
+            .filter(|t| !t.is_empty())
+            .ok_or_else(|| PdsError::Validation("Preference is missing a $type".to_string()))?;
+
+        if !pref_in_namespace(name, PREFERENCES_NAMESPACE) {
+            return Err(PdsError::Validation(format!(
+                "Some preferences are not in the {} namespace",
+                PREFERENCES_NAMESPACE
+            )));
+        }
+
+        if !pref_in_scope(name, full_access) {
+            return Err(PdsError::Authorization(format!(
+                "Do not have authorization to set preferences: {}",
+                name
+            )));
+        }
+
+        let json = serde_json::to_string(pref)
+            .map_err(|e| PdsError::Internal(format!("Failed to encode preference: {}", e)))?;
+        values.push((name.to_string(), json));
+    }
+
+    Ok(values)
+}
+
+/// Load the preferences visible to a session
+pub async fn load_preferences(ctx: &AppContext, did: &str, full_access: bool) -> PdsResult<Vec<Value>> {
+    let stored = ctx
+        .actor_store
+        .get_preferences(did, PREFERENCES_NAMESPACE)
+        .await?;
+
+    Ok(stored
+        .into_iter()
+        .filter(|pref| pref_in_scope(&pref.name, full_access))
+        .filter_map(|pref| serde_json::from_str(&pref.value_json).ok())
+        .collect())
+}
+
+/// Get private preferences
+///
+/// Implements app.bsky.actor.getPreferences
+async fn get_preferences(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<Preferences>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    let preferences = load_preferences(&ctx, &validated.did, !validated.is_app_password).await?;
+
+    Ok(Json(Preferences { preferences }))
+}
+
+/// Replace private preferences
+///
+/// Implements app.bsky.actor.putPreferences
+async fn put_preferences(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<Preferences>,
+) -> PdsResult<Json<Value>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+    let full_access = !validated.is_app_password;
+
+    let values = validate_preferences(&req.preferences, full_access)?;
+
+    ctx.actor_store
+        .put_preferences(&validated.did, PREFERENCES_NAMESPACE, values, |name| {
+            pref_in_scope(name, full_access)
+        })
+        .await?;
+
+    tracing::info!(did = %validated.did, count = req.preferences.len(), "preferences_updated");
+
+    Ok(Json(serde_json::json!({})))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pref_namespace_and_scope() {
+        assert!(pref_in_namespace("app.bsky.actor.defs#adultContentPref", "app.bsky"));
+        assert!(!pref_in_namespace("app.bskyx.actor.defs#pref", "app.bsky"));
+        assert!(!pref_in_namespace("com.example.pref", "app.bsky"));
+
+        assert!(pref_in_scope("app.bsky.actor.defs#personalDetailsPref", true));
+        assert!(!pref_in_scope("app.bsky.actor.defs#personalDetailsPref", false));
+        assert!(pref_in_scope("app.bsky.actor.defs#savedFeedsPref", false));
+    }
+
+    #[test]
+    fn test_validate_preferences() {
+        let prefs = vec![
+            json!({ "$type": "app.bsky.actor.defs#adultContentPref", "enabled": false }),
+            json!({ "$type": "app.bsky.actor.defs#personalDetailsPref", "birthDate": "1990-01-01T00:00:00Z" }),
+        ];
+
+        let values = validate_preferences(&prefs, true).unwrap();
+        assert_eq!(values.len(), 2);
+        assert_eq!(values[0].0, "app.bsky.actor.defs#adultContentPref");
+
+        // App passwords can't set personal details
+        assert!(matches!(
+            validate_preferences(&prefs, false),
+            Err(PdsError::Authorization(_))
+        ));
+
+        assert!(validate_preferences(&[json!({ "enabled": true })], true).is_err());
+        assert!(validate_preferences(&[json!({ "$type": "com.example.pref" })], true).is_err());
+    }
+}
@@ -1,27 +1,48 @@
 /// Admin API Endpoints
 /// Implements com.atproto.admin.* endpoints for server administration
 use crate::{
-    admin::InviteCode,
+    account::{
+        AccountDeletion, AccountSearch, AccountSearchResult, HandleChange, ReservedHandle,
+        ReservedHandleKind, SignupThrottleStatus,
+    },
+    actor_store::{CollectionCount, RecordTombstone, TombstoneQuery},
+    admin::{
+        AdminRole, AuditLogEntry, AuditLogQuery, BlocklistEntry, DeliveryStatus, InviteCode,
+        Label, ModerationRecord, RateLimitOverride, Report, Webhook, WebhookDelivery,
+    },
     auth::AdminAuthContext,
+    blob_store::{AccountBlob, BlobUsage, BlobUsageSort},
+    crypto::jwt_keys::JwtKeyInfo,
+    jobs::JobInfo,
+    mailer::{EmailDelivery, EmailStatus},
+    reload::ReloadReport,
+    sequencer::{ConsumerInfo, InspectedEvent, SequencerHealth},
     AppContext,
 };
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
-use chrono::Duration;
-use serde::Deserialize;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
 
 /// Build admin API routes
 pub fn routes() -> Router<AppContext> {
     Router::new()
         // Admin stats and data
         .route("/xrpc/com.atproto.admin.getStats", get(get_stats))
+        .route("/xrpc/com.atproto.admin.getRepoStats", get(get_repo_stats))
         .route("/xrpc/com.atproto.admin.getUsers", get(get_users))
+        .route("/xrpc/com.atproto.admin.searchAccounts", get(search_accounts))
         .route("/xrpc/com.atproto.admin.listAccounts", get(get_users)) // Alias for frontend compatibility
         .route("/xrpc/com.atproto.admin.getAccount", get(get_account))
+        .route("/xrpc/com.atproto.admin.getHandleHistory", get(get_handle_history))
+        .route("/xrpc/com.atproto.admin.listAccountDeletions", get(list_account_deletions))
+        .route("/xrpc/com.atproto.admin.listBlobsForAccount", get(list_blobs_for_account))
+        .route("/xrpc/com.atproto.admin.getBlobStorageReport", get(get_blob_storage_report))
         .route("/xrpc/com.atproto.admin.updateSubjectStatus", post(update_subject_status))
         // Invite codes
         .route("/xrpc/com.atproto.admin.createInviteCode", post(create_invite_code))
@@ -35,6 +56,7 @@ pub fn routes() -> Router<AppContext> {
         // Account moderation
         .route("/xrpc/com.atproto.admin.takedownAccount", post(takedown_account))
         .route("/xrpc/com.atproto.admin.suspendAccount", post(suspend_account))
+        .route("/xrpc/com.atproto.admin.bulkModerate", post(bulk_moderate))
         .route("/xrpc/com.atproto.admin.restoreAccount", post(restore_account))
         .route("/xrpc/com.atproto.admin.getModerationHistory", get(get_moderation_history))
         .route("/xrpc/com.atproto.admin.getModerationQueue", get(get_moderation_queue))
@@ -45,12 +67,553 @@ pub fn routes() -> Router<AppContext> {
         .route("/xrpc/com.atproto.admin.submitReport", post(submit_report))
         .route("/xrpc/com.atproto.admin.updateReportStatus", post(update_report_status))
         .route("/xrpc/com.atproto.admin.listReports", get(list_reports))
+        // Rate limit overrides
+        .route("/xrpc/com.atproto.admin.setRateLimitOverride", post(set_rate_limit_override))
+        .route("/xrpc/com.atproto.admin.removeRateLimitOverride", post(remove_rate_limit_override))
+        .route("/xrpc/com.atproto.admin.listRateLimitOverrides", get(list_rate_limit_overrides))
+        // Reserved handles
+        .route("/xrpc/com.atproto.admin.addReservedHandle", post(add_reserved_handle))
+        .route("/xrpc/com.atproto.admin.removeReservedHandle", post(remove_reserved_handle))
+        .route("/xrpc/com.atproto.admin.listReservedHandles", get(list_reserved_handles))
+        // Network blocklist
+        .route("/xrpc/com.atproto.admin.addBlocklistEntry", post(add_blocklist_entry))
+        .route("/xrpc/com.atproto.admin.removeBlocklistEntry", post(remove_blocklist_entry))
+        .route("/xrpc/com.atproto.admin.listBlocklist", get(list_blocklist))
+        // Signup burst control
+        .route("/xrpc/com.atproto.admin.getSignupThrottle", get(get_signup_throttle))
+        .route("/xrpc/com.atproto.admin.endSignupLockdown", post(end_signup_lockdown))
+        // Audit log
+        .route("/xrpc/com.atproto.admin.getAuditLog", get(get_audit_log))
+        // Deleted-record tombstones
+        .route("/xrpc/com.atproto.admin.listRecordTombstones", get(list_record_tombstones))
+        // Moderation webhooks
+        .route("/xrpc/com.atproto.admin.registerWebhook", post(register_webhook))
+        .route("/xrpc/com.atproto.admin.removeWebhook", post(remove_webhook))
+        .route("/xrpc/com.atproto.admin.listWebhooks", get(list_webhooks))
+        .route("/xrpc/com.atproto.admin.listWebhookDeliveries", get(list_webhook_deliveries))
+        // Sequencer integrity
+        .route("/xrpc/com.atproto.admin.getSequencerHealth", get(get_sequencer_health))
+        .route("/xrpc/com.atproto.admin.reemitEvents", post(reemit_events))
+        .route("/xrpc/com.atproto.admin.getSequencerEvents", get(get_sequencer_events))
+        .route("/xrpc/com.atproto.admin.replaySequencerEvents", post(replay_sequencer_events))
+        // Firehose consumers
+        .route("/xrpc/com.atproto.admin.listFirehoseConsumers", get(list_firehose_consumers))
+        .route("/xrpc/com.atproto.admin.disconnectFirehoseConsumer", post(disconnect_firehose_consumer))
+        // Configuration
+        .route("/xrpc/com.atproto.admin.reloadConfig", post(reload_config))
+        .route("/xrpc/com.atproto.admin.getMigrationStatus", get(get_migration_status))
+        .route("/xrpc/com.atproto.admin.listJwtKeys", get(list_jwt_keys))
+        .route("/xrpc/com.atproto.admin.rotateJwtKey", post(rotate_jwt_key))
+        // Background jobs
+        .route("/xrpc/com.atproto.admin.listJobs", get(list_jobs))
+        .route("/xrpc/com.atproto.admin.runJob", post(run_job))
+        .route("/xrpc/com.atproto.admin.updateJob", post(update_job))
+        // Outbound email
+        .route("/xrpc/com.atproto.admin.listEmailDeliveries", get(list_email_deliveries))
+        .route("/xrpc/com.atproto.admin.retryEmailDelivery", post(retry_email_delivery))
+        .route("/xrpc/com.atproto.admin.sendTestEmail", post(send_test_email))
+}
+
+// ============================================================================
+// Response Types
+//
+// Every admin response is a typed struct serialized with camelCase keys, as
+// lexicons require. Avoid hand-built `json!` maps so casing can't drift.
+// ============================================================================
+
+/// Generic acknowledgement for actions on an account
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuccessResponse {
+    pub success: bool,
+    pub did: String,
+}
+
+impl SuccessResponse {
+    fn new(did: String) -> Self {
+        Self { success: true, did }
+    }
+}
+
+/// An account's stored blobs
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListBlobsForAccountResponse {
+    pub did: String,
+    pub blobs: Vec<AccountBlob>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Blob storage by account, largest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobStorageReportResponse {
+    pub total_blobs: i64,
+    pub total_bytes: i64,
+    pub accounts: Vec<BlobUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Server statistics
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StatsResponse {
+    pub total_users: i64,
+    pub total_posts: i64,
+    pub active_sessions: i64,
+    pub pending_reports: i64,
+}
+
+/// Record counts for one repository, or across all of them
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RepoStatsResponse {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+    pub repos: i64,
+    pub total_records: i64,
+    pub collections: Vec<CollectionCount>,
+}
+
+/// Events sequenced to re-announce a repository
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReemitEventsResponse {
+    pub did: String,
+    pub seqs: Vec<i64>,
+}
+
+/// Decoded events from the sequencer log
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencerEventsResponse {
+    pub events: Vec<InspectedEvent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<i64>,
+}
+
+/// An event sequenced again by a replay
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayedEvent {
+    pub original_seq: i64,
+    pub seq: i64,
+}
+
+/// Events re-emitted by replaySequencerEvents
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayEventsResponse {
+    pub replayed: Vec<ReplayedEvent>,
+}
+
+/// Connected firehose consumers, most behind first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListFirehoseConsumersResponse {
+    /// Latest seq in the event log, lag is measured from
+    pub head: i64,
+    pub consumers: Vec<ConsumerInfo>,
+}
+
+/// Result of disconnecting a firehose consumer
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisconnectFirehoseConsumerResponse {
+    /// Whether the consumer was connected
+    pub disconnected: bool,
+}
+
+/// JWT signing keys, the signing key first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListJwtKeysResponse {
+    pub keys: Vec<JwtKeyInfo>,
+}
+
+/// Result of a JWT signing key rotation
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateJwtKeyResponse {
+    /// Id of the new signing key
+    pub kid: String,
+}
+
+/// Account summary in user listings
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserView {
+    pub did: String,
+    pub handle: String,
+    pub email: Option<String>,
+    pub created_at: String,
+    pub status: String,
+}
+
+/// Paginated user listing
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUsersResponse {
+    pub users: Vec<UserView>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Accounts matching an admin search
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchAccountsResponse {
+    pub accounts: Vec<AccountSearchResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Single account details
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountView {
+    pub did: String,
+    pub handle: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub email_confirmed: bool,
+    pub takedown: bool,
+}
+
+/// Handle changes for an account, newest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandleHistoryResponse {
+    pub did: String,
+    pub history: Vec<HandleChange>,
+}
+
+/// Purged accounts, newest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListAccountDeletionsResponse {
+    pub deletions: Vec<AccountDeletion>,
+    pub cursor: Option<String>,
+}
+
+/// Background jobs with their schedules and last runs
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListJobsResponse {
+    pub jobs: Vec<JobInfo>,
+}
+
+/// Acknowledgement of a run-now request
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunJobResponse {
+    pub name: String,
+    pub triggered: bool,
+}
+
+/// Page of queued email, newest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListEmailDeliveriesResponse {
+    pub deliveries: Vec<EmailDelivery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Outcome of a test email sent straight through the provider
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SendTestEmailResponse {
+    pub success: bool,
+    pub provider: String,
+    pub message_id: Option<String>,
+}
+
+/// Result of granting a role
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantRoleResponse {
+    pub success: bool,
+    pub did: String,
+    pub role: String,
+    pub admin_role: AdminRole,
+}
+
+/// Role lookup: a single account's role, or every active assignment
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum ListRolesResponse {
+    #[serde(rename_all = "camelCase")]
+    Account { did: String, role: Option<AdminRole> },
+    #[serde(rename_all = "camelCase")]
+    All { roles: Vec<AdminRole> },
+}
+
+/// Result of a moderation action
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationActionResponse {
+    pub success: bool,
+    pub did: String,
+    pub action: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Outcome of a bulk moderation action for one subject
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkModerationResult {
+    pub subject: String,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub moderation_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_id: Option<i64>,
+    /// Sequence number of the account event the action produced
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seq: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of a bulk moderation action
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BulkModerationResponse {
+    pub action: String,
+    pub succeeded: usize,
+    pub failed: usize,
+    pub results: Vec<BulkModerationResult>,
+}
+
+/// Moderation history for an account
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationHistoryResponse {
+    pub did: String,
+    pub history: Vec<ModerationRecord>,
+}
+
+/// Open reports awaiting review
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModerationQueueResponse {
+    pub queue: Vec<Report>,
+    pub count: usize,
+}
+
+/// Result of applying or removing a label
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelResponse {
+    pub success: bool,
+    pub label: Label,
+}
+
+/// Result of submitting a report
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReportResponse {
+    pub success: bool,
+    pub report: Report,
+}
+
+/// Result of a report status change
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateReportStatusResponse {
+    pub success: bool,
+    pub report_id: i64,
+    pub status: String,
+}
+
+/// Report listing
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListReportsResponse {
+    pub reports: Vec<Report>,
+}
+
+/// Result of disabling an invite code
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisableInviteCodeResponse {
+    pub success: bool,
+    pub code: String,
+}
+
+/// Result of setting a rate limit override
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitOverrideResponse {
+    pub success: bool,
+    #[serde(rename = "override")]
+    pub rate_limit_override: RateLimitOverride,
+}
+
+/// Active rate limit overrides
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRateLimitOverridesResponse {
+    pub overrides: Vec<RateLimitOverride>,
+    pub count: usize,
+}
+
+/// Result of adding a reserved handle pattern
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReservedHandleResponse {
+    pub success: bool,
+    pub entry: ReservedHandle,
+}
+
+/// Result of removing a reserved handle pattern
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveReservedHandleResponse {
+    pub success: bool,
+    pub pattern: String,
+}
+
+/// Reserved and blocked handle patterns
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListReservedHandlesResponse {
+    pub entries: Vec<ReservedHandle>,
+    pub count: usize,
+}
+
+/// Result of adding a blocklist entry
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlocklistEntryResponse {
+    pub success: bool,
+    pub entry: BlocklistEntry,
+}
+
+/// Result of removing a blocklist entry
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveBlocklistEntryResponse {
+    pub success: bool,
+    pub pattern: String,
+}
+
+/// Active blocklist entries
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListBlocklistResponse {
+    pub entries: Vec<BlocklistEntry>,
+    pub count: usize,
+}
+
+/// Result of ending a signup lockdown
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndSignupLockdownResponse {
+    /// Whether a lockdown was in effect
+    pub ended: bool,
+}
+
+/// Page of admin audit log entries, newest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetAuditLogResponse {
+    pub entries: Vec<AuditLogEntry>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Page of deleted-record tombstones, newest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListRecordTombstonesResponse {
+    pub tombstones: Vec<RecordTombstone>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Newly registered webhook; the secret is only ever returned here
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterWebhookResponse {
+    pub webhook: Webhook,
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoveWebhookResponse {
+    pub success: bool,
+    pub id: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListWebhooksResponse {
+    pub webhooks: Vec<Webhook>,
+}
+
+/// Page of webhook deliveries, newest first
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListWebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
 }
 
 // ============================================================================
 // Admin Endpoints (OAuth Authentication via AdminAuthContext)
 // ============================================================================
 
+/// Reject admins whose role is limited to one handle domain
+///
+/// Used by endpoints that span the whole server (invites, roles, reports,
+/// webhooks, server stats).
+fn require_server_wide(auth: &AdminAuthContext) -> Result<(), (StatusCode, String)> {
+    match &auth.domain {
+        None => Ok(()),
+        Some(domain) => Err((
+            StatusCode::FORBIDDEN,
+            format!("Admin role is limited to {}", domain),
+        )),
+    }
+}
+
+/// Reject actions on accounts outside a domain-scoped admin's domain
+async fn require_account_scope(
+    ctx: &AppContext,
+    auth: &AdminAuthContext,
+    did: &str,
+) -> Result<(), (StatusCode, String)> {
+    if auth.domain.is_none() {
+        return Ok(());
+    }
+
+    let account = ctx.account_manager
+        .get_account(did)
+        .await
+        .map_err(|_| (StatusCode::NOT_FOUND, format!("Account not found: {}", did)))?;
+    if !auth.covers_handle(&account.handle) {
+        return Err((StatusCode::FORBIDDEN, format!("{} is outside your domain", did)));
+    }
+
+    Ok(())
+}
+
+/// DID of the repo an AT-URI points into
+fn uri_did(uri: &str) -> &str {
+    uri.trim_start_matches("at://").split('/').next().unwrap_or("")
+}
+
 #[derive(Deserialize)]
 struct CreateInviteCodeRequest {
     uses: Option<i32>,
@@ -65,6 +628,8 @@ async fn create_invite_code(
     auth: AdminAuthContext,
     Json(req): Json<CreateInviteCodeRequest>,
 ) -> Result<Json<InviteCode>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
     // Create invite code
     let uses = req.uses.unwrap_or(1);
     let expires_in = req.expires_days.map(Duration::days);
@@ -89,7 +654,8 @@ struct GetInviteCodesQuery {
     include_disabled: bool,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct GetInviteCodesResponse {
     codes: Vec<InviteCode>,
 }
@@ -97,9 +663,11 @@ struct GetInviteCodesResponse {
 /// Get all invite codes
 async fn get_invite_codes(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(query): Query<GetInviteCodesQuery>,
 ) -> Result<Json<GetInviteCodesResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
     // Get all invite codes
     let codes = ctx
         .invite_manager
@@ -118,7 +686,8 @@ struct ListInviteCodesQuery {
     cursor: Option<String>,
 }
 
-#[derive(Debug, serde::Serialize)]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
 struct ListInviteCodesResponse {
     codes: Vec<InviteCode>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -128,9 +697,11 @@ struct ListInviteCodesResponse {
 /// List invite codes (ATProto standard endpoint)
 async fn list_invite_codes(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(_query): Query<ListInviteCodesQuery>,
 ) -> Result<Json<ListInviteCodesResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
     // Get all invite codes (ignore cursor for now, return all)
     let codes = ctx
         .invite_manager
@@ -144,8 +715,9 @@ async fn list_invite_codes(
 /// Get server statistics
 async fn get_stats(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+    auth: AdminAuthContext,
+) -> Result<Json<StatsResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
 
     // Get statistics from database
     let total_users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM account")
@@ -153,9 +725,14 @@ async fn get_stats(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    // Note: Post count would require querying all actor databases - expensive
-    // Set to 0 for now, can be improved later
-    let total_posts: i64 = 0;
+    // Mirrored from each actor store's collection_stat on every commit
+    let total_posts: i64 = match ctx.actor_store.repo_index() {
+        Some(index) => index
+            .collection_total("app.bsky.feed.post")
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+        None => 0,
+    };
 
     let active_sessions: i64 = sqlx::query_scalar(
         "SELECT COUNT(*) FROM session WHERE expires_at > datetime('now')"
@@ -171,12 +748,52 @@ async fn get_stats(
     .await
     .unwrap_or(0);
 
-    Ok(Json(serde_json::json!({
-        "totalUsers": total_users,
-        "totalPosts": total_posts,
-        "activeSessions": active_sessions,
-        "pendingReports": pending_reports,
-    })))
+    Ok(Json(StatsResponse {
+        total_users,
+        total_posts,
+        active_sessions,
+        pending_reports,
+    }))
+}
+
+#[derive(Deserialize)]
+struct GetRepoStatsQuery {
+    did: Option<String>,
+}
+
+/// Record counts by collection for one repository (`did`) or the whole server
+async fn get_repo_stats(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<GetRepoStatsQuery>,
+) -> Result<Json<RepoStatsResponse>, (StatusCode, String)> {
+    match &query.did {
+        Some(did) => require_account_scope(&ctx, &auth, did).await?,
+        None => require_server_wide(&auth)?,
+    }
+
+    let index = ctx.actor_store.repo_index().ok_or_else(|| {
+        (StatusCode::INTERNAL_SERVER_ERROR, "Repo index is not configured".to_string())
+    })?;
+
+    let stats = match &query.did {
+        Some(did) => index
+            .stats(did)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+            .ok_or_else(|| (StatusCode::NOT_FOUND, format!("No repo stats for {}", did)))?,
+        None => index
+            .global_stats()
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?,
+    };
+
+    Ok(Json(RepoStatsResponse {
+        did: query.did,
+        repos: stats.repos,
+        total_records: stats.records,
+        collections: stats.collections,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -186,50 +803,94 @@ struct GetUsersParams {
 }
 
 /// Get list of users
+///
+/// Domain-scoped admins only see accounts under their handle domain.
 async fn get_users(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(params): Query<GetUsersParams>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<GetUsersResponse>, (StatusCode, String)> {
 
     let limit = params.limit.unwrap_or(50).min(100);
 
-    let users: Vec<serde_json::Value> = if let Some(cursor) = params.cursor {
+    let users: Vec<UserView> = if let Some(cursor) = params.cursor {
         sqlx::query_as::<_, (String, String, Option<String>, String, String)>(
-            "SELECT did, handle, email, created_at, status FROM account WHERE did > ? ORDER BY did LIMIT ?"
+            "SELECT did, handle, email, created_at, status FROM account
+             WHERE did > ?1 AND (?2 IS NULL OR handle = ?2 OR handle LIKE '%.' || ?2)
+             ORDER BY did LIMIT ?3"
         )
         .bind(cursor)
+        .bind(&auth.domain)
         .bind(limit)
         .fetch_all(&ctx.account_db)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
     } else {
         sqlx::query_as::<_, (String, String, Option<String>, String, String)>(
-            "SELECT did, handle, email, created_at, status FROM account ORDER BY did LIMIT ?"
+            "SELECT did, handle, email, created_at, status FROM account
+             WHERE ?1 IS NULL OR handle = ?1 OR handle LIKE '%.' || ?1
+             ORDER BY did LIMIT ?2"
         )
+        .bind(&auth.domain)
         .bind(limit)
         .fetch_all(&ctx.account_db)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
     }
     .into_iter()
-    .map(|(did, handle, email, created_at, status)| {
-        serde_json::json!({
-            "did": did,
-            "handle": handle,
-            "email": email,
-            "createdAt": created_at,
-            "status": status,
-        })
+    .map(|(did, handle, email, created_at, status)| UserView {
+        did,
+        handle,
+        email,
+        created_at,
+        status,
     })
     .collect();
 
-    let cursor = users.last().and_then(|u| u.get("did")).and_then(|d| d.as_str());
+    let cursor = users.last().map(|u| u.did.clone());
+
+    Ok(Json(GetUsersResponse { users, cursor }))
+}
+
+#[derive(Deserialize)]
+struct SearchAccountsParams {
+    /// Partial handle, email address or DID prefix
+    q: String,
+    limit: Option<i64>,
+    cursor: Option<String>,
+}
+
+/// Search accounts by partial handle, exact email or DID prefix
+///
+/// Results include active moderation actions and storage usage. Domain-scoped
+/// admins only find accounts under their handle domain.
+async fn search_accounts(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(params): Query<SearchAccountsParams>,
+) -> Result<Json<SearchAccountsResponse>, (StatusCode, String)> {
+    let search = AccountSearch::parse(&params.q).map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let limit = params.limit.unwrap_or(25).clamp(1, 100);
+    let offset = params
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?
+        .unwrap_or(0)
+        .max(0);
+
+    let mut accounts = ctx.account_manager
+        .search_accounts(&search, auth.domain.as_deref(), limit, offset)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    for account in &mut accounts {
+        account.repo_bytes = ctx.actor_store.disk_usage(&account.did).await.ok();
+    }
+
+    let cursor = (accounts.len() as i64 == limit).then(|| (offset + limit).to_string());
 
-    Ok(Json(serde_json::json!({
-        "users": users,
-        "cursor": cursor,
-    })))
+    Ok(Json(SearchAccountsResponse { accounts, cursor }))
 }
 
 // ============================================================================
@@ -240,6 +901,9 @@ async fn get_users(
 struct GrantRoleRequest {
     did: String,
     role: String,
+    /// Limit the role to accounts under this handle domain
+    #[serde(default)]
+    domain: Option<String>,
 }
 
 /// Grant admin role to a user
@@ -247,16 +911,33 @@ async fn grant_role(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<GrantRoleRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<GrantRoleResponse>, (StatusCode, String)> {
     use crate::admin::roles::Role;
 
+    require_server_wide(&auth)?;
+
     // Parse role
     let role = Role::from_str(&req.role)
         .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
 
+    // Domain scopes must name one of the virtual hosts
+    let domain = match &req.domain {
+        Some(domain) => {
+            let domain = domain.trim().trim_start_matches('.').to_lowercase();
+            if !ctx.config.virtual_hosts.iter().any(|v| v.domain == domain) {
+                return Err((StatusCode::BAD_REQUEST, format!("Unknown handle domain: {}", domain)));
+            }
+            if role == Role::SuperAdmin {
+                return Err((StatusCode::BAD_REQUEST, "Superadmin roles cannot be domain-scoped".to_string()));
+            }
+            Some(domain)
+        }
+        None => None,
+    };
+
     // Grant role
     let admin_role = ctx.admin_role_manager
-        .grant_role(&req.did, role, &auth.did, None)
+        .grant_role(&req.did, role, domain, &auth.did, None)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
@@ -265,12 +946,12 @@ async fn grant_role(
         .log_action(&auth.did, "role.grant", Some(&req.did), Some(&req.role), None)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "did": req.did,
-        "role": req.role,
-        "admin_role": admin_role,
-    })))
+    Ok(Json(GrantRoleResponse {
+        success: true,
+        did: req.did,
+        role: req.role,
+        admin_role,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -285,7 +966,9 @@ async fn revoke_role(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<RevokeRoleRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<SuccessResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
     // Revoke role (revoke_role doesn't take a specific role, revokes the active role)
     ctx.admin_role_manager
         .revoke_role(&req.did, &auth.did, req.reason.clone())
@@ -297,10 +980,7 @@ async fn revoke_role(
         .log_action(&auth.did, "role.revoke", Some(&req.did), req.reason.as_deref(), None)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "did": req.did,
-    })))
+    Ok(Json(SuccessResponse::new(req.did)))
 }
 
 #[derive(Deserialize)]
@@ -311,9 +991,11 @@ struct ListRolesQuery {
 /// List admin roles
 async fn list_roles(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(query): Query<ListRolesQuery>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ListRolesResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
     if let Some(did) = query.did {
         // Get role for specific user
         let role_record = ctx.admin_role_manager
@@ -321,10 +1003,10 @@ async fn list_roles(
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-        Ok(Json(serde_json::json!({
-            "did": did,
-            "role": role_record,
-        })))
+        Ok(Json(ListRolesResponse::Account {
+            did,
+            role: role_record,
+        }))
     } else {
         // List all active role assignments
         let assignments = ctx.admin_role_manager
@@ -332,9 +1014,7 @@ async fn list_roles(
             .await
             .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-        Ok(Json(serde_json::json!({
-            "roles": assignments,
-        })))
+        Ok(Json(ListRolesResponse::All { roles: assignments }))
     }
 }
 
@@ -355,9 +1035,11 @@ async fn takedown_account(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<TakedownAccountRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ModerationActionResponse>, (StatusCode, String)> {
     use crate::admin::moderation::ModerationAction;
 
+    require_account_scope(&ctx, &auth, &req.did).await?;
+
     // Apply takedown action
     let record = ctx.moderation_manager
         .apply_action(
@@ -371,18 +1053,24 @@ async fn takedown_account(
         )
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    ctx.account_events
+        .status_changed(&req.did)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Log action
     let _ = ctx.admin_role_manager
         .log_action(&auth.did, "account.takedown", Some(&req.did), Some(&req.reason), None)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "moderation_id": record.id,
-        "did": req.did,
-        "action": "takedown",
-    })))
+    Ok(Json(ModerationActionResponse {
+        success: true,
+        did: req.did,
+        action: "takedown".to_string(),
+        moderation_id: Some(record.id),
+        expires_at: None,
+        message: None,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -400,9 +1088,11 @@ async fn suspend_account(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<SuspendAccountRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ModerationActionResponse>, (StatusCode, String)> {
     use crate::admin::moderation::ModerationAction;
 
+    require_account_scope(&ctx, &auth, &req.did).await?;
+
     let expires_in = req.duration_days.map(Duration::days);
 
     // Apply suspension
@@ -418,19 +1108,208 @@ async fn suspend_account(
         )
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    ctx.account_events
+        .status_changed(&req.did)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Log action
     let _ = ctx.admin_role_manager
         .log_action(&auth.did, "account.suspend", Some(&req.did), Some(&req.reason), None)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "moderation_id": record.id,
-        "did": req.did,
-        "action": "suspend",
-        "expires_at": record.expires_at,
-    })))
+    Ok(Json(ModerationActionResponse {
+        success: true,
+        did: req.did,
+        action: "suspend".to_string(),
+        moderation_id: Some(record.id),
+        expires_at: record.expires_at,
+        message: None,
+    }))
+}
+
+/// Most subjects a single bulk moderation request may name
+const MAX_BULK_SUBJECTS: usize = 500;
+
+/// Account events sequenced before pausing, so a large batch doesn't flood
+/// firehose consumers
+const BULK_SEQUENCE_CHUNK: usize = 50;
+const BULK_SEQUENCE_PAUSE_MS: u64 = 100;
+
+#[derive(Deserialize)]
+struct BulkModerationRequest {
+    /// "label", "suspend" or "takedown"
+    action: String,
+    /// DIDs, or AT-URIs for labels
+    subjects: Vec<String>,
+    reason: String,
+    /// Label value (labels only)
+    #[serde(default)]
+    val: Option<String>,
+    /// Suspension or label lifetime; permanent if unset
+    #[serde(default)]
+    duration_days: Option<i64>,
+    #[serde(default)]
+    notes: Option<String>,
+}
+
+/// Apply one moderation action to many subjects
+///
+/// Each subject succeeds or fails on its own and gets its own entry in the
+/// results. Successful items and a summary row are written to the audit log
+/// in a single transaction, and account events for suspensions and
+/// takedowns are sequenced in small chunks.
+async fn bulk_moderate(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<BulkModerationRequest>,
+) -> Result<Json<BulkModerationResponse>, (StatusCode, String)> {
+    use crate::admin::moderation::ModerationAction;
+    use crate::admin::roles::Role;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let (action, audit_action) = match req.action.as_str() {
+        "takedown" => (Some(ModerationAction::Takedown), "account.takedown"),
+        "suspend" => (Some(ModerationAction::Suspend), "account.suspend"),
+        "label" => (None, "label.apply"),
+        other => return Err((StatusCode::BAD_REQUEST, format!("Invalid action: {}", other))),
+    };
+    let val = match (&action, req.val.as_deref()) {
+        (None, Some(val)) if !val.is_empty() => val,
+        (None, _) => return Err((StatusCode::BAD_REQUEST, "Labels require a val".to_string())),
+        (Some(_), _) => "",
+    };
+    if req.subjects.is_empty() || req.subjects.len() > MAX_BULK_SUBJECTS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Between 1 and {} subjects are required", MAX_BULK_SUBJECTS),
+        ));
+    }
+
+    let mut subjects: Vec<&str> = Vec::with_capacity(req.subjects.len());
+    for subject in &req.subjects {
+        if !subjects.contains(&subject.as_str()) {
+            subjects.push(subject);
+        }
+    }
+
+    let mut results = Vec::with_capacity(subjects.len());
+    for subject in subjects {
+        let mut result = BulkModerationResult {
+            subject: subject.to_string(),
+            success: false,
+            moderation_id: None,
+            label_id: None,
+            seq: None,
+            error: None,
+        };
+        match bulk_moderate_one(&ctx, &auth, &req, action, val, subject).await {
+            Ok((moderation_id, label_id)) => {
+                result.success = true;
+                result.moderation_id = moderation_id;
+                result.label_id = label_id;
+            }
+            Err(e) => result.error = Some(e),
+        }
+        results.push(result);
+    }
+
+    // Sequence account events once the actions are in, pausing between chunks
+    if action.is_some() {
+        let mut sequenced = 0;
+        for result in results.iter_mut().filter(|r| r.success) {
+            if sequenced > 0 && sequenced % BULK_SEQUENCE_CHUNK == 0 {
+                tokio::time::sleep(std::time::Duration::from_millis(BULK_SEQUENCE_PAUSE_MS)).await;
+            }
+            sequenced += 1;
+
+            match ctx.account_events.status_changed(&result.subject).await {
+                Ok(seq) => result.seq = Some(seq),
+                Err(e) => tracing::warn!(did = %result.subject, error = %e, "bulk_moderation_sequence_failed"),
+            }
+        }
+    }
+
+    let succeeded = results.iter().filter(|r| r.success).count();
+    let failed = results.len() - succeeded;
+
+    // Audit every applied action plus a summary, all or nothing
+    let summary = format!(
+        "{} of {} subject(s): {}{}",
+        req.action,
+        results.len(),
+        req.reason,
+        if val.is_empty() { String::new() } else { format!(" (val: {})", val) }
+    );
+    let applied: Vec<(&str, String)> = results
+        .iter()
+        .filter(|r| r.success)
+        .map(|r| match action {
+            Some(_) => (r.subject.as_str(), req.reason.clone()),
+            None => (uri_did(&r.subject), format!("{} on {}", val, r.subject)),
+        })
+        .collect();
+    let mut entries: Vec<(&str, Option<&str>, Option<&str>)> = applied
+        .iter()
+        .map(|(did, details)| (audit_action, Some(*did), Some(details.as_str())))
+        .collect();
+    entries.push(("moderation.bulk", None, Some(summary.as_str())));
+    ctx.admin_role_manager
+        .log_actions(&auth.did, &entries, None)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to write audit log: {}", e)))?;
+
+    Ok(Json(BulkModerationResponse {
+        action: req.action,
+        succeeded,
+        failed,
+        results,
+    }))
+}
+
+/// Apply a bulk moderation action to one subject, returning the moderation
+/// record or label id
+async fn bulk_moderate_one(
+    ctx: &AppContext,
+    auth: &AdminAuthContext,
+    req: &BulkModerationRequest,
+    action: Option<crate::admin::moderation::ModerationAction>,
+    val: &str,
+    subject: &str,
+) -> Result<(Option<i64>, Option<i64>), String> {
+    let did = if subject.starts_with("did:") {
+        subject
+    } else if action.is_none() && subject.starts_with("at://") {
+        uri_did(subject)
+    } else {
+        return Err("Invalid subject format".to_string());
+    };
+    require_account_scope(ctx, auth, did).await.map_err(|(_, e)| e)?;
+
+    let expires_in = req.duration_days.map(Duration::days);
+    match action {
+        Some(action) => {
+            ctx.account_manager
+                .get_account(did)
+                .await
+                .map_err(|_| format!("Account not found: {}", did))?;
+            let record = ctx.moderation_manager
+                .apply_action(did, action, &req.reason, &auth.did, expires_in, None, req.notes.clone())
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok((Some(record.id), None))
+        }
+        None => {
+            let label = ctx.label_manager
+                .apply_label(subject, None, val, &auth.did, expires_in)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok((None, Some(label.id)))
+        }
+    }
 }
 
 #[derive(Deserialize)]
@@ -445,22 +1324,38 @@ async fn restore_account(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<RestoreAccountRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<SuccessResponse>, (StatusCode, String)> {
+    if auth.domain.is_some() {
+        require_account_scope(&ctx, &auth, &req.did).await?;
+
+        let history = ctx.moderation_manager
+            .get_history(&req.did)
+            .await
+            .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+        if !history.iter().any(|record| record.id == req.moderation_id) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                format!("Moderation action {} is not on {}", req.moderation_id, req.did),
+            ));
+        }
+    }
+
     // Reverse moderation action
     ctx.moderation_manager
         .reverse_action(req.moderation_id, &auth.did, &req.reason)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    ctx.account_events
+        .status_changed(&req.did)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
     // Log action
     let _ = ctx.admin_role_manager
         .log_action(&auth.did, "account.restore", Some(&req.did), Some(&req.reason), None)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "did": req.did,
-    })))
+    Ok(Json(SuccessResponse::new(req.did)))
 }
 
 #[derive(Deserialize)]
@@ -471,18 +1366,20 @@ struct GetModerationHistoryQuery {
 /// Get moderation history for an account
 async fn get_moderation_history(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(query): Query<GetModerationHistoryQuery>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ModerationHistoryResponse>, (StatusCode, String)> {
+    require_account_scope(&ctx, &auth, &query.did).await?;
+
     let history = ctx.moderation_manager
         .get_history(&query.did)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(serde_json::json!({
-        "did": query.did,
-        "history": history,
-    })))
+    Ok(Json(ModerationHistoryResponse {
+        did: query.did,
+        history,
+    }))
 }
 
 // ============================================================================
@@ -504,7 +1401,9 @@ async fn apply_label(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<ApplyLabelRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<LabelResponse>, (StatusCode, String)> {
+    require_account_scope(&ctx, &auth, uri_did(&req.uri)).await?;
+
     let expires_in = req.expires_days.map(Duration::days);
 
     let label = ctx.label_manager
@@ -523,10 +1422,10 @@ async fn apply_label(
         .log_action(&auth.did, "label.apply", None, Some(&req.val), Some(&req.uri))
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "label": label,
-    })))
+    Ok(Json(LabelResponse {
+        success: true,
+        label,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -542,7 +1441,9 @@ async fn remove_label(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<RemoveLabelRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<LabelResponse>, (StatusCode, String)> {
+    require_account_scope(&ctx, &auth, uri_did(&req.uri)).await?;
+
     let label = ctx.label_manager
         .remove_label(
             &req.uri,
@@ -558,10 +1459,10 @@ async fn remove_label(
         .log_action(&auth.did, "label.remove", None, Some(&req.val), Some(&req.uri))
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "label": label,
-    })))
+    Ok(Json(LabelResponse {
+        success: true,
+        label,
+    }))
 }
 
 // ============================================================================
@@ -586,7 +1487,7 @@ async fn submit_report(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<SubmitReportRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ReportResponse>, (StatusCode, String)> {
     use crate::admin::reports::ReportReason;
 
     // Parse reason type
@@ -606,10 +1507,10 @@ async fn submit_report(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "report": report,
-    })))
+    Ok(Json(ReportResponse {
+        success: true,
+        report,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -625,9 +1526,11 @@ async fn update_report_status(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<UpdateReportStatusRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<UpdateReportStatusResponse>, (StatusCode, String)> {
     use crate::admin::reports::ReportStatus;
 
+    require_server_wide(&auth)?;
+
     // Parse status
     let status = ReportStatus::from_str(&req.status)
         .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
@@ -648,11 +1551,11 @@ async fn update_report_status(
         .log_action(&auth.did, "report.update", None, Some(&req.status), None)
         .await;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "report_id": req.report_id,
-        "status": req.status,
-    })))
+    Ok(Json(UpdateReportStatusResponse {
+        success: true,
+        report_id: req.report_id,
+        status: req.status,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -666,11 +1569,13 @@ struct ListReportsQuery {
 /// List reports
 async fn list_reports(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(query): Query<ListReportsQuery>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ListReportsResponse>, (StatusCode, String)> {
     use crate::admin::reports::ReportStatus;
 
+    require_server_wide(&auth)?;
+
     // Parse status filter if provided
     let status_filter = if let Some(status_str) = query.status {
         Some(ReportStatus::from_str(&status_str)
@@ -685,9 +1590,7 @@ async fn list_reports(
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(serde_json::json!({
-        "reports": reports,
-    })))
+    Ok(Json(ListReportsResponse { reports }))
 }
 
 // ============================================================================
@@ -702,22 +1605,169 @@ struct GetAccountQuery {
 /// Get single account details
 async fn get_account(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(query): Query<GetAccountQuery>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<AccountView>, (StatusCode, String)> {
+    require_account_scope(&ctx, &auth, &query.did).await?;
+
     let account = ctx.account_manager
         .get_account(&query.did)
         .await
         .map_err(|e| (StatusCode::NOT_FOUND, format!("Account not found: {}", e)))?;
 
-    Ok(Json(serde_json::json!({
-        "did": account.did,
-        "handle": account.handle,
-        "email": account.email,
-        "created_at": account.created_at,
-        "email_confirmed": account.email_confirmed,
-        "takedown": account.taken_down,
-    })))
+    Ok(Json(AccountView {
+        did: account.did,
+        handle: account.handle,
+        email: account.email,
+        created_at: account.created_at,
+        email_confirmed: account.email_confirmed,
+        takedown: account.taken_down,
+    }))
+}
+
+/// Handle changes for an account, newest first
+async fn get_handle_history(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<GetAccountQuery>,
+) -> Result<Json<HandleHistoryResponse>, (StatusCode, String)> {
+    require_account_scope(&ctx, &auth, &query.did).await?;
+
+    let history = ctx.account_manager
+        .handle_history(&query.did)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(HandleHistoryResponse {
+        did: query.did,
+        history,
+    }))
+}
+
+#[derive(Deserialize)]
+struct ListAccountDeletionsQuery {
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// List accounts permanently purged after their deletion grace period (Admin or higher)
+async fn list_account_deletions(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<ListAccountDeletionsQuery>,
+) -> Result<Json<ListAccountDeletionsResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+
+    let deletions = ctx.account_manager
+        .list_account_deletions(limit, cursor)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let cursor = if deletions.len() as i64 == limit {
+        deletions.last().map(|d| d.id.to_string())
+    } else {
+        None
+    };
+
+    Ok(Json(ListAccountDeletionsResponse { deletions, cursor }))
+}
+
+#[derive(Deserialize)]
+struct ListBlobsForAccountQuery {
+    did: String,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// List the blobs an account has uploaded, with their reference counts
+async fn list_blobs_for_account(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<ListBlobsForAccountQuery>,
+) -> Result<Json<ListBlobsForAccountResponse>, (StatusCode, String)> {
+    require_account_scope(&ctx, &auth, &query.did).await?;
+
+    let limit = query.limit.unwrap_or(100).clamp(1, 1000);
+    let blobs = ctx.blob_store
+        .list_for_account(&query.did, limit, query.cursor.as_deref())
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let cursor = if blobs.len() as i64 == limit {
+        blobs.last().map(|b| b.cid.clone())
+    } else {
+        None
+    };
+
+    Ok(Json(ListBlobsForAccountResponse {
+        did: query.did,
+        blobs,
+        cursor,
+    }))
+}
+
+#[derive(Deserialize)]
+struct GetBlobStorageReportQuery {
+    /// "bytes" (default) or "count"
+    #[serde(default)]
+    sort: BlobUsageSort,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// Blob storage per account, largest first (Admin or higher)
+async fn get_blob_storage_report(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<GetBlobStorageReportQuery>,
+) -> Result<Json<BlobStorageReportResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let offset = query
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?
+        .unwrap_or(0)
+        .max(0);
+
+    let internal = |e: crate::error::PdsError| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let accounts = ctx.blob_store
+        .usage_by_account(query.sort, limit, offset)
+        .await
+        .map_err(internal)?;
+    let (total_blobs, total_bytes) = ctx.blob_store.total_usage().await.map_err(internal)?;
+
+    let cursor = (accounts.len() as i64 == limit).then(|| (offset + limit).to_string());
+
+    Ok(Json(BlobStorageReportResponse {
+        total_blobs,
+        total_bytes,
+        accounts,
+        cursor,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -734,7 +1784,7 @@ async fn update_subject_status(
     State(ctx): State<AppContext>,
     auth: AdminAuthContext,
     Json(req): Json<UpdateSubjectStatusRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ModerationActionResponse>, (StatusCode, String)> {
     use crate::admin::moderation::ModerationAction;
 
     // Extract DID from subject (handle both DID and AT-URI)
@@ -742,15 +1792,11 @@ async fn update_subject_status(
         req.subject.clone()
     } else if req.subject.starts_with("at://") {
         // Extract DID from AT-URI (format: at://did:plc:xyz/...)
-        req.subject
-            .trim_start_matches("at://")
-            .split('/')
-            .next()
-            .unwrap_or("")
-            .to_string()
+        uri_did(&req.subject).to_string()
     } else {
         return Err((StatusCode::BAD_REQUEST, "Invalid subject format".to_string()));
     };
+    require_account_scope(&ctx, &auth, &did).await?;
 
     let action = match req.action.as_str() {
         "suspend" => ModerationAction::Suspend,
@@ -758,12 +1804,17 @@ async fn update_subject_status(
         "restore" => {
             // For restore, we reverse existing moderation actions
             // This is a simplified implementation - in production you'd want to track specific actions to reverse
-            return Ok(Json(serde_json::json!({
-                "success": true,
-                "did": did,
-                "action": "restore",
-                "message": "To restore, use the reverse_action endpoint with the specific moderation_id"
-            })));
+            return Ok(Json(ModerationActionResponse {
+                success: true,
+                did,
+                action: "restore".to_string(),
+                moderation_id: None,
+                expires_at: None,
+                message: Some(
+                    "To restore, use com.atproto.admin.restoreAccount with the specific moderation_id"
+                        .to_string(),
+                ),
+            }));
         }
         _ => return Err((StatusCode::BAD_REQUEST, "Invalid action".to_string())),
     };
@@ -783,12 +1834,19 @@ async fn update_subject_status(
         )
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    ctx.account_events
+        .status_changed(&did)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "did": did,
-        "action": req.action,
-    })))
+    Ok(Json(ModerationActionResponse {
+        success: true,
+        did,
+        action: req.action,
+        moderation_id: None,
+        expires_at: None,
+        message: None,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -800,21 +1858,23 @@ struct GetModerationQueueQuery {
 /// Get moderation queue (reports needing review)
 async fn get_moderation_queue(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Query(query): Query<GetModerationQueueQuery>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<ModerationQueueResponse>, (StatusCode, String)> {
     use crate::admin::reports::ReportStatus;
 
+    require_server_wide(&auth)?;
+
     // Get open reports as the moderation queue
     let reports = ctx.report_manager
         .list_reports(Some(ReportStatus::Open), query.limit)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(serde_json::json!({
-        "queue": reports,
-        "count": reports.len(),
-    })))
+    Ok(Json(ModerationQueueResponse {
+        count: reports.len(),
+        queue: reports,
+    }))
 }
 
 #[derive(Deserialize)]
@@ -825,16 +1885,2132 @@ struct DisableInviteCodeRequest {
 /// Disable an invite code
 async fn disable_invite_code(
     State(ctx): State<AppContext>,
-    _auth: AdminAuthContext,
+    auth: AdminAuthContext,
     Json(req): Json<DisableInviteCodeRequest>,
-) -> Result<Json<serde_json::Value>, (StatusCode, String)> {
+) -> Result<Json<DisableInviteCodeResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
     ctx.invite_manager
         .disable_code(&req.code)
         .await
         .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
 
-    Ok(Json(serde_json::json!({
-        "success": true,
-        "code": req.code,
-    })))
+    Ok(Json(DisableInviteCodeResponse {
+        success: true,
+        code: req.code,
+    }))
+}
+
+// ============================================================================
+// Rate Limit Override Endpoints
+// ============================================================================
+
+#[derive(Deserialize)]
+struct SetRateLimitOverrideRequest {
+    did: String,
+    #[serde(default)]
+    multiplier: Option<f64>,
+    #[serde(default)]
+    requests_per_second: Option<u32>,
+    #[serde(default)]
+    burst_size: Option<u32>,
+    #[serde(default)]
+    reason: Option<String>,
+    #[serde(default)]
+    expires_hours: Option<i64>,
+}
+
+/// Set a per-account rate limit override (Admin or higher)
+async fn set_rate_limit_override(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<SetRateLimitOverrideRequest>,
+) -> Result<Json<RateLimitOverrideResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_account_scope(&ctx, &auth, &req.did).await?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let entry = ctx.rate_limit_override_manager
+        .set_override(
+            &req.did,
+            req.multiplier,
+            req.requests_per_second,
+            req.burst_size,
+            req.reason.clone(),
+            &auth.did,
+            req.expires_hours.map(Duration::hours),
+        )
+        .await
+        .map_err(|e| match e {
+            PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Apply immediately to the running limiter
+    ctx.rate_limiter.set_account_override(&entry);
+
+    // Log action
+    let details = serde_json::json!({
+        "multiplier": entry.multiplier,
+        "requests_per_second": entry.requests_per_second,
+        "burst_size": entry.burst_size,
+        "reason": entry.reason,
+    })
+    .to_string();
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "ratelimit.set", Some(&req.did), Some(&details), None)
+        .await;
+
+    Ok(Json(RateLimitOverrideResponse {
+        success: true,
+        rate_limit_override: entry,
+    }))
+}
+
+#[derive(Deserialize)]
+struct RemoveRateLimitOverrideRequest {
+    did: String,
+}
+
+/// Remove a per-account rate limit override (Admin or higher)
+async fn remove_rate_limit_override(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RemoveRateLimitOverrideRequest>,
+) -> Result<Json<SuccessResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_account_scope(&ctx, &auth, &req.did).await?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    ctx.rate_limit_override_manager
+        .remove_override(&req.did)
+        .await
+        .map_err(|e| match e {
+            PdsError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    ctx.rate_limiter.remove_account_override(&req.did);
+
+    // Log action
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "ratelimit.remove", Some(&req.did), None, None)
+        .await;
+
+    Ok(Json(SuccessResponse::new(req.did)))
+}
+
+/// List active per-account rate limit overrides
+async fn list_rate_limit_overrides(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListRateLimitOverridesResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
+    let overrides = ctx.rate_limit_override_manager
+        .list_overrides()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ListRateLimitOverridesResponse {
+        count: overrides.len(),
+        overrides,
+    }))
+}
+
+// ============================================================================
+// Network Blocklist Endpoints
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AddBlocklistEntryRequest {
+    /// IP address, CIDR range or ASN (`AS64496`)
+    pattern: String,
+    #[serde(default)]
+    note: Option<String>,
+    #[serde(default)]
+    expires_hours: Option<i64>,
+}
+
+/// Block an IP, range or ASN from registration and login (Admin or higher)
+async fn add_blocklist_entry(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<AddBlocklistEntryRequest>,
+) -> Result<Json<BlocklistEntryResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let entry = ctx.ip_blocklist
+        .add(&req.pattern, req.note.clone(), &auth.did, req.expires_hours.map(Duration::hours))
+        .await
+        .map_err(|e| match e {
+            PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Log action
+    let details = serde_json::json!({
+        "pattern": entry.pattern,
+        "note": entry.note,
+        "expires_at": entry.expires_at,
+    })
+    .to_string();
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "blocklist.add", None, Some(&details), None)
+        .await;
+
+    Ok(Json(BlocklistEntryResponse {
+        success: true,
+        entry,
+    }))
+}
+
+#[derive(Deserialize)]
+struct RemoveBlocklistEntryRequest {
+    pattern: String,
+}
+
+/// Remove a blocklist entry (Admin or higher)
+async fn remove_blocklist_entry(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RemoveBlocklistEntryRequest>,
+) -> Result<Json<RemoveBlocklistEntryResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    ctx.ip_blocklist
+        .remove(&req.pattern)
+        .await
+        .map_err(|e| match e {
+            PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            PdsError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Log action
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "blocklist.remove", None, Some(&req.pattern), None)
+        .await;
+
+    Ok(Json(RemoveBlocklistEntryResponse {
+        success: true,
+        pattern: req.pattern,
+    }))
+}
+
+/// List active blocklist entries with their hit counts
+async fn list_blocklist(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListBlocklistResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
+    let entries = ctx.ip_blocklist
+        .list()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ListBlocklistResponse {
+        count: entries.len(),
+        entries,
+    }))
+}
+
+// ============================================================================
+// Signup Throttle Endpoints
+// ============================================================================
+
+/// Signup caps, recent open signups and any lockdown in effect
+async fn get_signup_throttle(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<SignupThrottleStatus>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
+    Ok(Json(ctx.signup_throttle.status()))
+}
+
+/// Lift a signup lockdown before it runs out
+async fn end_signup_lockdown(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<EndSignupLockdownResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let ended = ctx.signup_throttle.end_lockdown();
+    if ended {
+        let _ = ctx.admin_role_manager
+            .log_action(&auth.did, "signup.end_lockdown", None, None, None)
+            .await;
+    }
+
+    Ok(Json(EndSignupLockdownResponse { ended }))
+}
+
+// ============================================================================
+// Reserved Handle Endpoints
+// ============================================================================
+
+#[derive(Deserialize)]
+struct AddReservedHandleRequest {
+    pattern: String,
+    /// "reserved" (default) or "blocked"
+    #[serde(default)]
+    kind: Option<String>,
+    #[serde(default)]
+    reason: Option<String>,
+}
+
+/// Reserve or block a handle pattern (Admin or higher)
+async fn add_reserved_handle(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<AddReservedHandleRequest>,
+) -> Result<Json<ReservedHandleResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let kind = ReservedHandleKind::from_str(req.kind.as_deref().unwrap_or("reserved"))
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+
+    let entry = ctx.account_manager
+        .reserved_handles()
+        .add(&req.pattern, kind, req.reason.clone(), &auth.did)
+        .await
+        .map_err(|e| match e {
+            PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Log action
+    let details = format!("{} {}", entry.kind.as_str(), entry.pattern);
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "handle.reserve", None, Some(&details), None)
+        .await;
+
+    Ok(Json(ReservedHandleResponse {
+        success: true,
+        entry,
+    }))
+}
+
+#[derive(Deserialize)]
+struct RemoveReservedHandleRequest {
+    pattern: String,
+}
+
+/// Remove a reserved or blocked handle pattern (Admin or higher)
+async fn remove_reserved_handle(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RemoveReservedHandleRequest>,
+) -> Result<Json<RemoveReservedHandleResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    ctx.account_manager
+        .reserved_handles()
+        .remove(&req.pattern)
+        .await
+        .map_err(|e| match e {
+            PdsError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Log action
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "handle.unreserve", None, Some(&req.pattern), None)
+        .await;
+
+    Ok(Json(RemoveReservedHandleResponse {
+        success: true,
+        pattern: req.pattern,
+    }))
+}
+
+/// List reserved and blocked handle patterns
+async fn list_reserved_handles(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListReservedHandlesResponse>, (StatusCode, String)> {
+    require_server_wide(&auth)?;
+
+    let entries = ctx.account_manager
+        .reserved_handles()
+        .list()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ListReservedHandlesResponse {
+        count: entries.len(),
+        entries,
+    }))
+}
+
+// ============================================================================
+// Audit Log Endpoints
+// ============================================================================
+
+/// Largest page for the paginated JSON view
+const AUDIT_LOG_MAX_PAGE: i64 = 100;
+/// Largest page for CSV/JSON file exports
+const AUDIT_LOG_MAX_EXPORT: i64 = 10_000;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GetAuditLogQuery {
+    admin_did: Option<String>,
+    action: Option<String>,
+    /// Subject DID
+    subject: Option<String>,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+    /// "csv" or "json" to download the page as a file
+    format: Option<String>,
+}
+
+/// Query the admin audit log (Admin or higher)
+///
+/// Filters by acting admin, action, subject DID and time range, newest
+/// first. With `format=csv` or `format=json` the page is returned as a file
+/// download and may be up to `AUDIT_LOG_MAX_EXPORT` entries.
+async fn get_audit_log(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<GetAuditLogQuery>,
+) -> Result<Response, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let export = match query.format.as_deref() {
+        None => None,
+        Some(format @ ("csv" | "json")) => Some(format),
+        Some(other) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("Unsupported format: {} (expected csv or json)", other),
+            ))
+        }
+    };
+
+    let max = if export.is_some() { AUDIT_LOG_MAX_EXPORT } else { AUDIT_LOG_MAX_PAGE };
+    let limit = query.limit.unwrap_or(50).clamp(1, max);
+
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+
+    let entries = ctx.admin_role_manager
+        .query_audit_log(&AuditLogQuery {
+            admin_did: query.admin_did,
+            action: query.action,
+            subject_did: query.subject,
+            since: query.since,
+            until: query.until,
+            cursor,
+            limit,
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // There may be more results when the page is full
+    let cursor = if entries.len() as i64 == limit {
+        entries.last().map(|e| e.id.to_string())
+    } else {
+        None
+    };
+    let response = GetAuditLogResponse { entries, cursor };
+
+    let filename = format!("audit-log-{}", Utc::now().format("%Y%m%dT%H%M%SZ"));
+    match export {
+        None => Ok(Json(response).into_response()),
+        Some("csv") => Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.csv\"", filename)),
+            ],
+            audit_log_csv(&response.entries),
+        )
+            .into_response()),
+        Some(_) => Ok((
+            [(header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}.json\"", filename))],
+            Json(response),
+        )
+            .into_response()),
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListRecordTombstonesQuery {
+    did: Option<String>,
+    collection: Option<String>,
+    since: Option<DateTime<Utc>>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// List tombstones of deleted records (Admin or higher)
+///
+/// Only available while `PDS_TOMBSTONE_RETENTION_DAYS` is set. Domain-scoped
+/// admins must name an account (`did`) in their domain.
+async fn list_record_tombstones(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<ListRecordTombstonesQuery>,
+) -> Result<Json<ListRecordTombstonesResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    match &query.did {
+        Some(did) => require_account_scope(&ctx, &auth, did).await?,
+        None => require_server_wide(&auth)?,
+    }
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let tombstones = ctx.actor_store.tombstone_log().ok_or_else(|| {
+        (
+            StatusCode::BAD_REQUEST,
+            "Record tombstones are not kept (set PDS_TOMBSTONE_RETENTION_DAYS)".to_string(),
+        )
+    })?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+
+    let tombstones = tombstones
+        .list(&TombstoneQuery {
+            did: query.did,
+            collection: query.collection,
+            since: query.since,
+            cursor,
+            limit,
+        })
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    // There may be more results when the page is full
+    let cursor = if tombstones.len() as i64 == limit {
+        tombstones.last().map(|t| t.id.to_string())
+    } else {
+        None
+    };
+
+    Ok(Json(ListRecordTombstonesResponse { tombstones, cursor }))
+}
+
+// ============================================================================
+// Moderation Webhook Endpoints
+// ============================================================================
+
+#[derive(Deserialize)]
+struct RegisterWebhookRequest {
+    url: String,
+    /// Event types to receive (e.g. "report.created", "account.*"); all when empty
+    #[serde(default)]
+    events: Vec<String>,
+    /// Signing secret; generated when omitted
+    #[serde(default)]
+    secret: Option<String>,
+}
+
+/// Register a moderation webhook (Admin or higher)
+async fn register_webhook(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RegisterWebhookRequest>,
+) -> Result<Json<RegisterWebhookResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let (webhook, secret) = ctx.webhook_manager
+        .register(&req.url, req.events, req.secret, &auth.did)
+        .await
+        .map_err(|e| match e {
+            PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Log action
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "webhook.register", None, Some(&webhook.url), None)
+        .await;
+
+    Ok(Json(RegisterWebhookResponse { webhook, secret }))
+}
+
+#[derive(Deserialize)]
+struct RemoveWebhookRequest {
+    id: i64,
+}
+
+/// Remove a moderation webhook (Admin or higher)
+async fn remove_webhook(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RemoveWebhookRequest>,
+) -> Result<Json<RemoveWebhookResponse>, (StatusCode, String)> {
+    use crate::{admin::roles::Role, error::PdsError};
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    ctx.webhook_manager
+        .remove(req.id)
+        .await
+        .map_err(|e| match e {
+            PdsError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    // Log action
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "webhook.remove", None, Some(&req.id.to_string()), None)
+        .await;
+
+    Ok(Json(RemoveWebhookResponse {
+        success: true,
+        id: req.id,
+    }))
+}
+
+/// List moderation webhooks (Admin or higher)
+async fn list_webhooks(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListWebhooksResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let webhooks = ctx.webhook_manager
+        .list()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(ListWebhooksResponse { webhooks }))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListWebhookDeliveriesQuery {
+    webhook_id: Option<i64>,
+    /// "pending", "delivered" or "failed"
+    status: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// List webhook deliveries and their status (Admin or higher)
+async fn list_webhook_deliveries(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<ListWebhookDeliveriesQuery>,
+) -> Result<Json<ListWebhookDeliveriesResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let status = query
+        .status
+        .as_deref()
+        .map(DeliveryStatus::from_str)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+
+    let deliveries = ctx.webhook_manager
+        .list_deliveries(query.webhook_id, status, cursor, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let cursor = if deliveries.len() as i64 == limit {
+        deliveries.last().map(|d| d.id.to_string())
+    } else {
+        None
+    };
+
+    Ok(Json(ListWebhookDeliveriesResponse { deliveries, cursor }))
+}
+
+// ============================================================================
+// Sequencer Integrity Endpoints
+// ============================================================================
+
+/// Check the sequencer log for gaps and duplicate commits (Admin or higher)
+///
+/// Read-only; the hourly integrity job invalidates duplicates.
+async fn get_sequencer_health(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<SequencerHealth>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let health = ctx.sequencer
+        .check_integrity(false)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(health))
+}
+
+#[derive(Deserialize)]
+struct ReemitEventsRequest {
+    did: String,
+}
+
+/// Re-announce a repository after events were lost (Admin or higher)
+///
+/// Sequences identity and account events with the current handle and status,
+/// and a `tooBig` commit for the current head so consumers re-sync the repo
+/// with getRepo instead of relying on the events they missed.
+async fn reemit_events(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<ReemitEventsRequest>,
+) -> Result<Json<ReemitEventsResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+    use crate::sequencer::events::CommitEvent;
+
+    require_account_scope(&ctx, &auth, &req.did).await?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let internal = |e: crate::error::PdsError| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+
+    let handle: Option<String> = sqlx::query_scalar("SELECT handle FROM account WHERE did = ?1")
+        .bind(&req.did)
+        .fetch_optional(&ctx.account_db)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    let handle = handle.ok_or_else(|| (StatusCode::NOT_FOUND, format!("Account not found: {}", req.did)))?;
+
+    let head = match ctx.actor_store.repo_index() {
+        Some(index) => index.get(&req.did).await.map_err(internal)?,
+        None => None,
+    };
+
+    let mut seqs = vec![ctx.account_events
+        .identity_changed(&req.did, Some(&handle))
+        .await
+        .map_err(internal)?];
+
+    if let Some(head) = head {
+        let (active, _) = ctx.account_events.status(&req.did).await.map_err(internal)?;
+        seqs.push(ctx.account_events.status_changed(&req.did).await.map_err(internal)?);
+
+        if active {
+            let mut commit = CommitEvent::new(req.did.clone(), head.cid, head.rev, None, Vec::new(), Vec::new());
+            commit.too_big = true;
+            seqs.push(ctx.sequencer.sequence_commit(commit).await.map_err(internal)?);
+        }
+    }
+
+    // Log action
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "sequencer.reemit", Some(&req.did), None, None)
+        .await;
+
+    Ok(Json(ReemitEventsResponse { did: req.did, seqs }))
+}
+
+/// Most events one replay may re-emit
+const MAX_REPLAY_EVENTS: i64 = 500;
+
+#[derive(Deserialize)]
+struct GetSequencerEventsParams {
+    /// Return events after this seq
+    cursor: Option<i64>,
+    /// Up to and including this seq
+    until: Option<i64>,
+    did: Option<String>,
+    limit: Option<i64>,
+    /// Check each commit's blocks
+    verify: Option<bool>,
+}
+
+/// Inspect the event log, decoded to JSON (Admin or higher)
+///
+/// Includes invalidated events. With `verify=true`, each commit's CAR slice
+/// is checked against its commit and ops.
+async fn get_sequencer_events(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(params): Query<GetSequencerEventsParams>,
+) -> Result<Json<SequencerEventsResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    let rows = ctx.sequencer
+        .list_rows(params.cursor.unwrap_or(0), params.until, params.did.as_deref(), limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let cursor = if rows.len() as i64 == limit {
+        rows.last().map(|row| row.seq)
+    } else {
+        None
+    };
+    let verify = params.verify.unwrap_or(false);
+    let events = rows.iter().map(|row| InspectedEvent::from_row(row, verify)).collect();
+
+    Ok(Json(SequencerEventsResponse { events, cursor }))
+}
+
+#[derive(Deserialize)]
+struct ReplaySequencerEventsRequest {
+    /// Replay events after this seq
+    after: i64,
+    /// Up to and including this seq
+    until: i64,
+    did: Option<String>,
+}
+
+/// Re-emit a range of events to firehose subscribers (Admin or higher)
+///
+/// Valid events in the range are sequenced again, unchanged, under new seqs.
+/// At most 500 events per call.
+async fn replay_sequencer_events(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<ReplaySequencerEventsRequest>,
+) -> Result<Json<ReplayEventsResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    if req.until <= req.after {
+        return Err((StatusCode::BAD_REQUEST, "until must be greater than after".to_string()));
+    }
+
+    let internal = |e: crate::error::PdsError| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string());
+    let rows = ctx.sequencer
+        .list_rows(req.after, Some(req.until), req.did.as_deref(), MAX_REPLAY_EVENTS + 1)
+        .await
+        .map_err(internal)?;
+    if rows.len() as i64 > MAX_REPLAY_EVENTS {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            format!("Range holds more than {} events; narrow it", MAX_REPLAY_EVENTS),
+        ));
+    }
+
+    let replayed: Vec<ReplayedEvent> = ctx.sequencer
+        .replay(&rows)
+        .await
+        .map_err(internal)?
+        .into_iter()
+        .map(|(original_seq, seq)| ReplayedEvent { original_seq, seq })
+        .collect();
+
+    let details = serde_json::json!({
+        "after": req.after,
+        "until": req.until,
+        "did": req.did,
+        "count": replayed.len(),
+    });
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "sequencer.replay", req.did.as_deref(), Some(&details.to_string()), None)
+        .await;
+
+    Ok(Json(ReplayEventsResponse { replayed }))
+}
+
+/// Connected firehose consumers with their cursor and lag (Admin or higher)
+async fn list_firehose_consumers(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListFirehoseConsumersResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let head = ctx.sequencer
+        .current_seq()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+        .unwrap_or(0);
+
+    Ok(Json(ListFirehoseConsumersResponse {
+        head,
+        consumers: ctx.firehose_consumers.list(head),
+    }))
+}
+
+#[derive(Deserialize)]
+struct DisconnectFirehoseConsumerRequest {
+    id: u64,
+}
+
+/// Close a firehose consumer's connection (Admin or higher)
+///
+/// The consumer gets an `Error` info frame first; nothing stops it from
+/// reconnecting, so pair with a blocklist entry for abusive clients.
+async fn disconnect_firehose_consumer(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<DisconnectFirehoseConsumerRequest>,
+) -> Result<Json<DisconnectFirehoseConsumerResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let disconnected = ctx.firehose_consumers.disconnect(req.id);
+    if disconnected {
+        let details = serde_json::json!({ "id": req.id });
+        let _ = ctx.admin_role_manager
+            .log_action(&auth.did, "firehose.disconnect", None, Some(&details.to_string()), None)
+            .await;
+    }
+
+    Ok(Json(DisconnectFirehoseConsumerResponse { disconnected }))
+}
+
+/// Re-read configuration and apply the dynamic settings (Superadmin only)
+///
+/// Same as sending the server SIGHUP. Invalid configuration is rejected
+/// without changing anything.
+async fn reload_config(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ReloadReport>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::SuperAdmin) {
+        return Err((StatusCode::FORBIDDEN, "Requires superadmin role".to_string()));
+    }
+
+    let report = crate::reload::reload(&ctx, &auth.did)
+        .await
+        .map_err(|e| match e {
+            crate::error::PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    Ok(Json(report))
+}
+
+/// Applied vs pending migrations of each database, and actor store schema
+/// versions (Admin or higher)
+///
+/// Read-only; pending migrations are applied with `aurora-locus migrate upgrade`
+/// while the server is stopped.
+async fn get_migration_status(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<crate::db::migrations::MigrationStatus>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let status = crate::db::migrations::status(&ctx.config, &ctx.account_db, &ctx.actor_store)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    Ok(Json(status))
+}
+
+/// List JWT signing keys that still validate tokens (Admin or higher)
+async fn list_jwt_keys(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListJwtKeysResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    Ok(Json(ListJwtKeysResponse { keys: ctx.jwt_keys.list() }))
+}
+
+/// Rotate the JWT signing key now (Superadmin only)
+///
+/// The previous key keeps validating tokens for `PDS_JWT_RETIRED_KEY_HOURS`.
+async fn rotate_jwt_key(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<RotateJwtKeyResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::SuperAdmin) {
+        return Err((StatusCode::FORBIDDEN, "Requires superadmin role".to_string()));
+    }
+
+    let kid = ctx.jwt_keys
+        .rotate()
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let details = serde_json::json!({ "kid": kid });
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "jwt_key.rotate", None, Some(&details.to_string()), None)
+        .await;
+
+    Ok(Json(RotateJwtKeyResponse { kid }))
+}
+
+/// Map job registry and email queue errors to admin API errors
+fn job_error(e: crate::error::PdsError) -> (StatusCode, String) {
+    use crate::error::PdsError;
+
+    match e {
+        PdsError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+        PdsError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+        e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// List background jobs with schedule, enabled flag and run history (Admin or higher)
+async fn list_jobs(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+) -> Result<Json<ListJobsResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let jobs = ctx.jobs.list().await.map_err(job_error)?;
+    Ok(Json(ListJobsResponse { jobs }))
+}
+
+#[derive(Deserialize)]
+struct RunJobRequest {
+    name: String,
+}
+
+/// Run a job now, even if it is disabled (Admin or higher)
+async fn run_job(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RunJobRequest>,
+) -> Result<Json<RunJobResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    ctx.jobs.trigger(&req.name).map_err(job_error)?;
+
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "job.run", None, Some(&req.name), None)
+        .await;
+
+    Ok(Json(RunJobResponse {
+        name: req.name,
+        triggered: true,
+    }))
+}
+
+#[derive(Deserialize)]
+struct UpdateJobRequest {
+    name: String,
+    /// `null` returns the job to its configured flag
+    enabled: Option<bool>,
+}
+
+/// Enable or disable a job (Admin or higher)
+async fn update_job(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<UpdateJobRequest>,
+) -> Result<Json<JobInfo>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let job = ctx.jobs.set_enabled(&req.name, req.enabled).await.map_err(job_error)?;
+
+    let details = format!(
+        "{} {}",
+        req.name,
+        match req.enabled {
+            Some(true) => "enabled",
+            Some(false) => "disabled",
+            None => "reset to config",
+        }
+    );
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "job.update", None, Some(&details), None)
+        .await;
+
+    Ok(Json(job))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListEmailDeliveriesQuery {
+    /// "pending", "sent" or "failed"
+    status: Option<String>,
+    recipient: Option<String>,
+    cursor: Option<String>,
+    limit: Option<i64>,
+}
+
+/// List queued email and its delivery status (Admin or higher)
+async fn list_email_deliveries(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Query(query): Query<ListEmailDeliveriesQuery>,
+) -> Result<Json<ListEmailDeliveriesResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let status = query
+        .status
+        .as_deref()
+        .map(EmailStatus::from_str)
+        .transpose()
+        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+    let cursor = query
+        .cursor
+        .as_deref()
+        .map(|c| c.parse::<i64>())
+        .transpose()
+        .map_err(|_| (StatusCode::BAD_REQUEST, "Invalid cursor".to_string()))?;
+    let limit = query.limit.unwrap_or(50).clamp(1, 100);
+
+    let deliveries = ctx.mailer
+        .queue()
+        .list(status, query.recipient.as_deref(), cursor, limit)
+        .await
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+    let cursor = if deliveries.len() as i64 == limit {
+        deliveries.last().map(|d| d.id.to_string())
+    } else {
+        None
+    };
+
+    Ok(Json(ListEmailDeliveriesResponse { deliveries, cursor }))
+}
+
+#[derive(Deserialize)]
+struct RetryEmailDeliveryRequest {
+    id: i64,
+}
+
+/// Re-queue a failed email with a fresh set of attempts (Admin or higher)
+async fn retry_email_delivery(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<RetryEmailDeliveryRequest>,
+) -> Result<Json<EmailDelivery>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let delivery = ctx.mailer.queue().retry(req.id).await.map_err(job_error)?;
+
+    let details = format!("email {} to {}", delivery.id, delivery.recipient);
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "email.retry", None, Some(&details), None)
+        .await;
+
+    Ok(Json(delivery))
+}
+
+#[derive(Deserialize)]
+struct SendTestEmailRequest {
+    to: String,
+}
+
+/// Send a test email through the configured provider, bypassing the queue (Admin or higher)
+///
+/// Provider errors are returned as 502 so a broken mail setup is visible
+/// without digging through the delivery log.
+async fn send_test_email(
+    State(ctx): State<AppContext>,
+    auth: AdminAuthContext,
+    Json(req): Json<SendTestEmailRequest>,
+) -> Result<Json<SendTestEmailResponse>, (StatusCode, String)> {
+    use crate::admin::roles::Role;
+    use crate::error::PdsError;
+
+    require_server_wide(&auth)?;
+
+    if !auth.role.can_act_as(Role::Admin) {
+        return Err((StatusCode::FORBIDDEN, "Requires admin role".to_string()));
+    }
+
+    let (provider, message_id) = ctx.mailer
+        .send_test_email(&req.to)
+        .await
+        .map_err(|e| match e {
+            PdsError::Validation(msg) => (StatusCode::BAD_REQUEST, msg),
+            PdsError::Mail(msg) => (StatusCode::BAD_GATEWAY, msg),
+            e => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        })?;
+
+    let _ = ctx.admin_role_manager
+        .log_action(&auth.did, "email.test", None, Some(&req.to), None)
+        .await;
+
+    Ok(Json(SendTestEmailResponse {
+        success: true,
+        provider: provider.to_string(),
+        message_id,
+    }))
+}
+
+/// Render audit log entries as CSV (RFC 4180 quoting)
+fn audit_log_csv(entries: &[AuditLogEntry]) -> String {
+    fn field(value: &str) -> String {
+        if value.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        } else {
+            value.to_string()
+        }
+    }
+
+    let mut csv = String::from("id,timestamp,adminDid,action,subjectDid,details,ipAddress\r\n");
+    for entry in entries {
+        let row = [
+            entry.id.to_string(),
+            entry.timestamp.to_rfc3339(),
+            field(&entry.admin_did),
+            field(&entry.action),
+            field(entry.subject_did.as_deref().unwrap_or("")),
+            field(entry.details.as_deref().unwrap_or("")),
+            field(entry.ip_address.as_deref().unwrap_or("")),
+        ];
+        csv.push_str(&row.join(","));
+        csv.push_str("\r\n");
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::admin::{ModerationAction, ReportReason, ReportStatus, Role};
+    use crate::api::labels::{LabelView, QueryLabelsResponse};
+    use serde_json::Value;
+
+    /// Compact, order-independent description of a JSON value's keys
+    fn shape(value: &Value) -> String {
+        match value {
+            Value::Object(map) => {
+                let mut keys: Vec<_> = map.iter().collect();
+                keys.sort_by(|a, b| a.0.cmp(b.0));
+                let fields: Vec<String> =
+                    keys.into_iter().map(|(k, v)| format!("{}{}", k, shape(v))).collect();
+                format!("{{{}}}", fields.join(","))
+            }
+            Value::Array(items) => format!("[{}]", items.first().map(shape).unwrap_or_default()),
+            _ => String::new(),
+        }
+    }
+
+    fn snapshot<T: Serialize>(response: &T) -> String {
+        shape(&serde_json::to_value(response).unwrap())
+    }
+
+    fn invite() -> InviteCode {
+        InviteCode {
+            code: "abc123".to_string(),
+            available: 1,
+            disabled: false,
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: Some(Utc::now()),
+            note: Some("note".to_string()),
+            for_account: Some("did:plc:user".to_string()),
+        }
+    }
+
+    fn label() -> Label {
+        Label {
+            id: 1,
+            uri: "at://did:plc:user/app.bsky.feed.post/1".to_string(),
+            cid: Some("bafyrei".to_string()),
+            val: "spam".to_string(),
+            neg: true,
+            src: "did:plc:labeler".to_string(),
+            created_at: Utc::now(),
+            created_by: "did:plc:admin".to_string(),
+            expires_at: Some(Utc::now()),
+            sig: Some(vec![1, 2, 3]),
+        }
+    }
+
+    fn report() -> Report {
+        Report {
+            id: 1,
+            subject_did: Some("did:plc:user".to_string()),
+            subject_uri: Some("at://did:plc:user/app.bsky.feed.post/1".to_string()),
+            subject_cid: Some("bafyrei".to_string()),
+            reason_type: ReportReason::Spam,
+            reason: Some("spam".to_string()),
+            reported_by: "did:plc:reporter".to_string(),
+            reported_at: Utc::now(),
+            status: ReportStatus::Open,
+            reviewed_by: Some("did:plc:admin".to_string()),
+            reviewed_at: Some(Utc::now()),
+            resolution: Some("resolved".to_string()),
+        }
+    }
+
+    fn moderation_record() -> ModerationRecord {
+        ModerationRecord {
+            id: 1,
+            did: "did:plc:user".to_string(),
+            action: ModerationAction::Suspend,
+            reason: "spam".to_string(),
+            moderated_by: "did:plc:admin".to_string(),
+            moderated_at: Utc::now(),
+            expires_at: Some(Utc::now()),
+            reversed: true,
+            reversed_at: Some(Utc::now()),
+            reversed_by: Some("did:plc:admin".to_string()),
+            reversal_reason: Some("appeal".to_string()),
+            report_id: Some(1),
+            notes: Some("notes".to_string()),
+        }
+    }
+
+    fn admin_role() -> AdminRole {
+        AdminRole {
+            id: 1,
+            did: "did:plc:user".to_string(),
+            role: Role::Moderator,
+            domain: Some("example.com".to_string()),
+            granted_by: Some("did:plc:admin".to_string()),
+            granted_at: Utc::now(),
+            revoked: false,
+            revoked_at: Some(Utc::now()),
+            revoked_by: Some("did:plc:admin".to_string()),
+            notes: Some("notes".to_string()),
+        }
+    }
+
+    fn rate_limit_override() -> RateLimitOverride {
+        RateLimitOverride {
+            did: "did:plc:user".to_string(),
+            multiplier: Some(2.0),
+            requests_per_second: Some(20),
+            burst_size: Some(40),
+            reason: Some("bot".to_string()),
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: Some(Utc::now()),
+        }
+    }
+
+    fn audit_entry() -> AuditLogEntry {
+        AuditLogEntry {
+            id: 1,
+            admin_did: "did:plc:admin".to_string(),
+            action: "account.takedown".to_string(),
+            subject_did: Some("did:plc:user".to_string()),
+            details: Some("spam".to_string()),
+            timestamp: Utc::now(),
+            ip_address: Some("127.0.0.1".to_string()),
+        }
+    }
+
+    fn moderation_action() -> ModerationActionResponse {
+        ModerationActionResponse {
+            success: true,
+            did: "did:plc:user".to_string(),
+            action: "suspend".to_string(),
+            moderation_id: Some(1),
+            expires_at: Some(Utc::now()),
+            message: Some("message".to_string()),
+        }
+    }
+
+    /// Snapshot of every admin and label route's response shape.
+    ///
+    /// Lexicons require camelCase keys; a failure here means a response
+    /// changed shape or casing. Update the snapshot only for intended changes.
+    #[test]
+    fn test_response_shape_snapshots() {
+        let invite_shape = "{available,code,createdAt,createdBy,disabled,expiresAt,forAccount,note}";
+        let label_shape = "{cid,createdAt,createdBy,expiresAt,id,neg,sig[],src,uri,val}";
+        let report_shape = "{id,reason,reasonType,reportedAt,reportedBy,resolution,reviewedAt,reviewedBy,status,subjectCid,subjectDid,subjectUri}";
+        let record_shape = "{action,did,expiresAt,id,moderatedAt,moderatedBy,notes,reason,reportId,reversalReason,reversed,reversedAt,reversedBy}";
+        let role_shape = "{did,domain,grantedAt,grantedBy,id,notes,revoked,revokedAt,revokedBy,role}";
+        let override_shape = "{burstSize,createdAt,createdBy,did,expiresAt,multiplier,reason,requestsPerSecond}";
+        let action_shape = "{action,did,expiresAt,message,moderationId,success}";
+        let reserved_shape = "{createdAt,createdBy,kind,pattern,reason}";
+        let blocklist_shape = "{createdAt,createdBy,expiresAt,hitCount,kind,lastHitAt,note,pattern}";
+        let webhook_shape = "{createdAt,createdBy,enabled,events[],id,url}";
+        let webhook = || Webhook {
+            id: 1,
+            url: "https://mod.example/hook".to_string(),
+            events: vec!["report.created".to_string()],
+            enabled: true,
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+        };
+        let reserved = || ReservedHandle {
+            pattern: "support".to_string(),
+            kind: ReservedHandleKind::Reserved,
+            reason: Some("impersonation".to_string()),
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+        };
+
+        let blocklist_entry = || BlocklistEntry {
+            pattern: "198.51.100.0/24".to_string(),
+            kind: crate::admin::BlockKind::Cidr,
+            note: Some("signup wave".to_string()),
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+            hit_count: 3,
+            last_hit_at: Some(Utc::now()),
+        };
+
+        let cases: Vec<(&str, String, String)> = vec![
+            (
+                "getStats",
+                snapshot(&StatsResponse {
+                    total_users: 1,
+                    total_posts: 0,
+                    active_sessions: 1,
+                    pending_reports: 0,
+                }),
+                "{activeSessions,pendingReports,totalPosts,totalUsers}".to_string(),
+            ),
+            (
+                "getRepoStats",
+                snapshot(&RepoStatsResponse {
+                    did: Some("did:plc:user".to_string()),
+                    repos: 1,
+                    total_records: 3,
+                    collections: vec![CollectionCount {
+                        collection: "app.bsky.feed.post".to_string(),
+                        count: 3,
+                    }],
+                }),
+                "{collections[{collection,count}],did,repos,totalRecords}".to_string(),
+            ),
+            (
+                "getUsers",
+                snapshot(&GetUsersResponse {
+                    users: vec![UserView {
+                        did: "did:plc:user".to_string(),
+                        handle: "user.test".to_string(),
+                        email: Some("user@example.com".to_string()),
+                        created_at: Utc::now().to_rfc3339(),
+                        status: "active".to_string(),
+                    }],
+                    cursor: Some("did:plc:user".to_string()),
+                }),
+                "{cursor,users[{createdAt,did,email,handle,status}]}".to_string(),
+            ),
+            (
+                "getAccount",
+                snapshot(&AccountView {
+                    did: "did:plc:user".to_string(),
+                    handle: "user.test".to_string(),
+                    email: Some("user@example.com".to_string()),
+                    created_at: Utc::now(),
+                    email_confirmed: true,
+                    takedown: false,
+                }),
+                "{createdAt,did,email,emailConfirmed,handle,takedown}".to_string(),
+            ),
+            (
+                "updateSubjectStatus/takedownAccount/suspendAccount",
+                snapshot(&moderation_action()),
+                action_shape.to_string(),
+            ),
+            (
+                "listRecordTombstones",
+                snapshot(&ListRecordTombstonesResponse {
+                    tombstones: vec![RecordTombstone {
+                        id: 1,
+                        did: "did:plc:user".to_string(),
+                        uri: "at://did:plc:user/app.bsky.feed.post/1".to_string(),
+                        collection: "app.bsky.feed.post".to_string(),
+                        cid: Some("bafyrecord".to_string()),
+                        rev: "3rev".to_string(),
+                        deleted_at: Utc::now(),
+                    }],
+                    cursor: Some("1".to_string()),
+                }),
+                "{cursor,tombstones[{cid,collection,deletedAt,did,id,rev,uri}]}".to_string(),
+            ),
+            (
+                "bulkModerate",
+                snapshot(&BulkModerationResponse {
+                    action: "takedown".to_string(),
+                    succeeded: 1,
+                    failed: 0,
+                    results: vec![BulkModerationResult {
+                        subject: "did:plc:user".to_string(),
+                        success: true,
+                        moderation_id: Some(1),
+                        label_id: Some(1),
+                        seq: Some(7),
+                        error: Some("error".to_string()),
+                    }],
+                }),
+                "{action,failed,results[{error,labelId,moderationId,seq,subject,success}],succeeded}".to_string(),
+            ),
+            (
+                "createInviteCode",
+                snapshot(&invite()),
+                invite_shape.to_string(),
+            ),
+            (
+                "getInviteCodes",
+                snapshot(&GetInviteCodesResponse { codes: vec![invite()] }),
+                format!("{{codes[{}]}}", invite_shape),
+            ),
+            (
+                "listInviteCodes",
+                snapshot(&ListInviteCodesResponse {
+                    codes: vec![invite()],
+                    cursor: Some("1".to_string()),
+                }),
+                format!("{{codes[{}],cursor}}", invite_shape),
+            ),
+            (
+                "disableInviteCode",
+                snapshot(&DisableInviteCodeResponse {
+                    success: true,
+                    code: "abc123".to_string(),
+                }),
+                "{code,success}".to_string(),
+            ),
+            (
+                "grantRole",
+                snapshot(&GrantRoleResponse {
+                    success: true,
+                    did: "did:plc:user".to_string(),
+                    role: "moderator".to_string(),
+                    admin_role: admin_role(),
+                }),
+                format!("{{adminRole{},did,role,success}}", role_shape),
+            ),
+            (
+                "revokeRole/restoreAccount/removeRateLimitOverride",
+                snapshot(&SuccessResponse::new("did:plc:user".to_string())),
+                "{did,success}".to_string(),
+            ),
+            (
+                "listRoles?did",
+                snapshot(&ListRolesResponse::Account {
+                    did: "did:plc:user".to_string(),
+                    role: Some(admin_role()),
+                }),
+                format!("{{did,role{}}}", role_shape),
+            ),
+            (
+                "listRoles",
+                snapshot(&ListRolesResponse::All { roles: vec![admin_role()] }),
+                format!("{{roles[{}]}}", role_shape),
+            ),
+            (
+                "getModerationHistory",
+                snapshot(&ModerationHistoryResponse {
+                    did: "did:plc:user".to_string(),
+                    history: vec![moderation_record()],
+                }),
+                format!("{{did,history[{}]}}", record_shape),
+            ),
+            (
+                "getModerationQueue",
+                snapshot(&ModerationQueueResponse {
+                    queue: vec![report()],
+                    count: 1,
+                }),
+                format!("{{count,queue[{}]}}", report_shape),
+            ),
+            (
+                "applyLabel/removeLabel",
+                snapshot(&LabelResponse {
+                    success: true,
+                    label: label(),
+                }),
+                format!("{{label{},success}}", label_shape),
+            ),
+            (
+                "submitReport",
+                snapshot(&ReportResponse {
+                    success: true,
+                    report: report(),
+                }),
+                format!("{{report{},success}}", report_shape),
+            ),
+            (
+                "updateReportStatus",
+                snapshot(&UpdateReportStatusResponse {
+                    success: true,
+                    report_id: 1,
+                    status: "resolved".to_string(),
+                }),
+                "{reportId,status,success}".to_string(),
+            ),
+            (
+                "listReports",
+                snapshot(&ListReportsResponse { reports: vec![report()] }),
+                format!("{{reports[{}]}}", report_shape),
+            ),
+            (
+                "setRateLimitOverride",
+                snapshot(&RateLimitOverrideResponse {
+                    success: true,
+                    rate_limit_override: rate_limit_override(),
+                }),
+                format!("{{override{},success}}", override_shape),
+            ),
+            (
+                "listRateLimitOverrides",
+                snapshot(&ListRateLimitOverridesResponse {
+                    overrides: vec![rate_limit_override()],
+                    count: 1,
+                }),
+                format!("{{count,overrides[{}]}}", override_shape),
+            ),
+            (
+                "addReservedHandle",
+                snapshot(&ReservedHandleResponse {
+                    success: true,
+                    entry: reserved(),
+                }),
+                format!("{{entry{},success}}", reserved_shape),
+            ),
+            (
+                "removeReservedHandle",
+                snapshot(&RemoveReservedHandleResponse {
+                    success: true,
+                    pattern: "support".to_string(),
+                }),
+                "{pattern,success}".to_string(),
+            ),
+            (
+                "listReservedHandles",
+                snapshot(&ListReservedHandlesResponse {
+                    entries: vec![reserved()],
+                    count: 1,
+                }),
+                format!("{{count,entries[{}]}}", reserved_shape),
+            ),
+            (
+                "addBlocklistEntry",
+                snapshot(&BlocklistEntryResponse {
+                    success: true,
+                    entry: blocklist_entry(),
+                }),
+                format!("{{entry{},success}}", blocklist_shape),
+            ),
+            (
+                "removeBlocklistEntry",
+                snapshot(&RemoveBlocklistEntryResponse {
+                    success: true,
+                    pattern: "198.51.100.0/24".to_string(),
+                }),
+                "{pattern,success}".to_string(),
+            ),
+            (
+                "listBlocklist",
+                snapshot(&ListBlocklistResponse {
+                    entries: vec![blocklist_entry()],
+                    count: 1,
+                }),
+                format!("{{count,entries[{}]}}", blocklist_shape),
+            ),
+            (
+                "getAuditLog",
+                snapshot(&GetAuditLogResponse {
+                    entries: vec![audit_entry()],
+                    cursor: Some("1".to_string()),
+                }),
+                "{cursor,entries[{action,adminDid,details,id,ipAddress,subjectDid,timestamp}]}".to_string(),
+            ),
+            (
+                "registerWebhook",
+                snapshot(&RegisterWebhookResponse {
+                    webhook: webhook(),
+                    secret: "s3cret".to_string(),
+                }),
+                format!("{{secret,webhook{}}}", webhook_shape),
+            ),
+            (
+                "removeWebhook",
+                snapshot(&RemoveWebhookResponse { success: true, id: 1 }),
+                "{id,success}".to_string(),
+            ),
+            (
+                "listWebhooks",
+                snapshot(&ListWebhooksResponse { webhooks: vec![webhook()] }),
+                format!("{{webhooks[{}]}}", webhook_shape),
+            ),
+            (
+                "listWebhookDeliveries",
+                snapshot(&ListWebhookDeliveriesResponse {
+                    deliveries: vec![WebhookDelivery {
+                        id: 1,
+                        webhook_id: 1,
+                        event_type: "report.created".to_string(),
+                        status: DeliveryStatus::Pending,
+                        attempts: 1,
+                        last_error: Some("Endpoint returned 503".to_string()),
+                        response_status: Some(503),
+                        created_at: Utc::now(),
+                        next_attempt_at: Some(Utc::now()),
+                        delivered_at: None,
+                    }],
+                    cursor: None,
+                }),
+                "{deliveries[{attempts,createdAt,deliveredAt,eventType,id,lastError,nextAttemptAt,responseStatus,status,webhookId}]}".to_string(),
+            ),
+            (
+                "getSequencerHealth",
+                snapshot(&SequencerHealth {
+                    first_seq: Some(1),
+                    last_seq: Some(10),
+                    event_count: 8,
+                    invalidated_count: 1,
+                    gaps: vec![crate::sequencer::SeqGap { after: 3, before: 6, missing: 2 }],
+                    missing_events: 2,
+                    duplicates: vec![crate::sequencer::DuplicateEvent {
+                        did: "did:plc:user".to_string(),
+                        original_seq: 7,
+                        duplicate_seqs: vec![8],
+                    }],
+                    invalidated_duplicates: 0,
+                    checked_at: Utc::now(),
+                }),
+                "{checkedAt,duplicates[{did,duplicateSeqs[],originalSeq}],eventCount,firstSeq,gaps[{after,before,missing}],invalidatedCount,invalidatedDuplicates,lastSeq,missingEvents}".to_string(),
+            ),
+            (
+                "reemitEvents",
+                snapshot(&ReemitEventsResponse {
+                    did: "did:plc:user".to_string(),
+                    seqs: vec![11, 12, 13],
+                }),
+                "{did,seqs[]}".to_string(),
+            ),
+            (
+                "getSequencerEvents",
+                snapshot(&SequencerEventsResponse {
+                    events: vec![InspectedEvent {
+                        seq: 5,
+                        did: "did:plc:user".to_string(),
+                        event_type: "identity".to_string(),
+                        invalidated: false,
+                        sequenced_at: Utc::now(),
+                        payload_bytes: 40,
+                        event: Some(serde_json::json!({"did": "did:plc:user", "handle": "user.test"})),
+                        decode_error: None,
+                        integrity: Some(crate::sequencer::CommitIntegrity {
+                            valid: false,
+                            blocks: 2,
+                            issues: vec!["Commit block is missing".to_string()],
+                        }),
+                    }],
+                    cursor: Some(5),
+                }),
+                "{cursor,events[{did,event{did,handle},eventType,integrity{blocks,issues[],valid},invalidated,payloadBytes,seq,sequencedAt}]}".to_string(),
+            ),
+            (
+                "listBlobsForAccount",
+                snapshot(&ListBlobsForAccountResponse {
+                    did: "did:plc:user".to_string(),
+                    blobs: vec![AccountBlob {
+                        cid: "bafkreiblob".to_string(),
+                        mime_type: "image/png".to_string(),
+                        size: 1024,
+                        created_at: Utc::now(),
+                        ref_count: Some(1),
+                        thumbnail_cid: Some("bafkreithumb".to_string()),
+                    }],
+                    cursor: Some("bafkreiblob".to_string()),
+                }),
+                "{blobs[{cid,createdAt,mimeType,refCount,size,thumbnailCid}],cursor,did}".to_string(),
+            ),
+            (
+                "getBlobStorageReport",
+                snapshot(&BlobStorageReportResponse {
+                    total_blobs: 10,
+                    total_bytes: 4096,
+                    accounts: vec![BlobUsage {
+                        did: "did:plc:user".to_string(),
+                        blobs: 3,
+                        bytes: 2048,
+                        unreferenced_blobs: 1,
+                        unreferenced_bytes: 512,
+                        last_upload_at: Some(Utc::now()),
+                    }],
+                    cursor: Some("50".to_string()),
+                }),
+                "{accounts[{blobs,bytes,did,lastUploadAt,unreferencedBlobs,unreferencedBytes}],cursor,totalBlobs,totalBytes}".to_string(),
+            ),
+            (
+                "searchAccounts",
+                snapshot(&SearchAccountsResponse {
+                    accounts: vec![AccountSearchResult {
+                        did: "did:plc:user".to_string(),
+                        handle: "user.test".to_string(),
+                        email: Some("user@example.com".to_string()),
+                        created_at: Utc::now(),
+                        status: "active".to_string(),
+                        taken_down: false,
+                        deactivated_at: None,
+                        moderation: vec!["warn".to_string()],
+                        blob_count: 2,
+                        blob_bytes: 2048,
+                        repo_bytes: Some(65536),
+                    }],
+                    cursor: Some("25".to_string()),
+                }),
+                "{accounts[{blobBytes,blobCount,createdAt,deactivatedAt,did,email,handle,moderation[],repoBytes,status,takenDown}],cursor}".to_string(),
+            ),
+            (
+                "getSignupThrottle",
+                snapshot(&SignupThrottleStatus {
+                    global_per_hour: 50,
+                    per_ip_per_hour: 3,
+                    signups_last_hour: 50,
+                    lockdown_until: Some(Utc::now()),
+                }),
+                "{globalPerHour,lockdownUntil,perIpPerHour,signupsLastHour}".to_string(),
+            ),
+            (
+                "endSignupLockdown",
+                snapshot(&EndSignupLockdownResponse { ended: true }),
+                "{ended}".to_string(),
+            ),
+            (
+                "listFirehoseConsumers",
+                snapshot(&ListFirehoseConsumersResponse {
+                    head: 120,
+                    consumers: vec![ConsumerInfo {
+                        id: 1,
+                        remote_addr: Some("10.0.0.1".to_string()),
+                        user_agent: Some("relay/1.0".to_string()),
+                        did: None,
+                        cursor: 100,
+                        frames_sent: 40,
+                        bytes_sent: 4096,
+                        lag: Some(20),
+                        connected_at: Utc::now(),
+                    }],
+                }),
+                "{consumers[{bytesSent,connectedAt,cursor,framesSent,id,lag,remoteAddr,userAgent}],head}".to_string(),
+            ),
+            (
+                "getMigrationStatus",
+                snapshot(&crate::db::migrations::MigrationStatus {
+                    databases: vec![crate::db::migrations::DatabaseStatus {
+                        name: "account".to_string(),
+                        path: "data/account.sqlite".to_string(),
+                        shared_with: Some("account".to_string()),
+                        applied: 27,
+                        latest_applied: Some(20250204000001),
+                        pending: vec![crate::db::migrations::MigrationInfo {
+                            version: 20250205000001,
+                            description: "activitypub".to_string(),
+                        }],
+                        unknown: vec![],
+                        failed: vec![],
+                    }],
+                    actor_stores: crate::db::migrations::ActorStoreStatus {
+                        schema_version: 3,
+                        stores: 2,
+                        behind: 1,
+                        unreadable: 0,
+                        versions: vec![crate::db::migrations::StoreVersionCount { version: 2, stores: 1 }],
+                    },
+                }),
+                "{actorStores{behind,schemaVersion,stores,unreadable,versions[{stores,version}]},databases[{applied,failed[],latestApplied,name,path,pending[{description,version}],sharedWith,unknown[]}]}".to_string(),
+            ),
+            (
+                "disconnectFirehoseConsumer",
+                snapshot(&DisconnectFirehoseConsumerResponse { disconnected: true }),
+                "{disconnected}".to_string(),
+            ),
+            (
+                "listJwtKeys",
+                snapshot(&ListJwtKeysResponse {
+                    keys: vec![JwtKeyInfo {
+                        kid: "20250131-0a1b2c3d4e5f6a7b".to_string(),
+                        algorithm: crate::config::JwtAlgorithm::Es256,
+                        active: false,
+                        created_at: Utc::now(),
+                        retired_at: Some(Utc::now()),
+                        expires_at: Some(Utc::now()),
+                    }],
+                }),
+                "{keys[{active,algorithm,createdAt,expiresAt,kid,retiredAt}]}".to_string(),
+            ),
+            (
+                "rotateJwtKey",
+                snapshot(&RotateJwtKeyResponse { kid: "20250131-0a1b2c3d4e5f6a7b".to_string() }),
+                "{kid}".to_string(),
+            ),
+            (
+                "replaySequencerEvents",
+                snapshot(&ReplayEventsResponse {
+                    replayed: vec![ReplayedEvent { original_seq: 5, seq: 14 }],
+                }),
+                "{replayed[{originalSeq,seq}]}".to_string(),
+            ),
+            (
+                "getHandleHistory",
+                snapshot(&HandleHistoryResponse {
+                    did: "did:plc:user".to_string(),
+                    history: vec![HandleChange {
+                        id: 1,
+                        did: "did:plc:user".to_string(),
+                        old_handle: "old.test".to_string(),
+                        new_handle: "new.test".to_string(),
+                        changed_at: Utc::now(),
+                        redirect_until: Some(Utc::now()),
+                    }],
+                }),
+                "{did,history[{changedAt,did,id,newHandle,oldHandle,redirectUntil}]}".to_string(),
+            ),
+            (
+                "listJobs",
+                snapshot(&ListJobsResponse {
+                    jobs: vec![JobInfo {
+                        name: "session_cleanup".to_string(),
+                        description: "Delete expired sessions and refresh tokens".to_string(),
+                        schedule: "0 * * * *".to_string(),
+                        enabled: true,
+                        running: false,
+                        last_started_at: Some(Utc::now()),
+                        last_finished_at: Some(Utc::now()),
+                        last_duration_ms: Some(12),
+                        last_status: Some("succeeded".to_string()),
+                        last_result: None,
+                        next_run_at: Some(Utc::now()),
+                        run_count: 4,
+                        failure_count: 0,
+                    }],
+                }),
+                "{jobs[{description,enabled,failureCount,lastDurationMs,lastFinishedAt,lastResult,lastStartedAt,lastStatus,name,nextRunAt,runCount,running,schedule}]}".to_string(),
+            ),
+            (
+                "runJob",
+                snapshot(&RunJobResponse {
+                    name: "session_cleanup".to_string(),
+                    triggered: true,
+                }),
+                "{name,triggered}".to_string(),
+            ),
+            (
+                "listEmailDeliveries",
+                snapshot(&ListEmailDeliveriesResponse {
+                    deliveries: vec![EmailDelivery {
+                        id: 7,
+                        kind: "verification".to_string(),
+                        recipient: "alice@example.com".to_string(),
+                        subject: "Verify your email address".to_string(),
+                        status: EmailStatus::Pending,
+                        attempts: 2,
+                        provider: Some("smtp".to_string()),
+                        provider_message_id: None,
+                        last_error: Some("connection reset".to_string()),
+                        created_at: Utc::now(),
+                        next_attempt_at: Some(Utc::now()),
+                        sent_at: None,
+                    }],
+                    cursor: None,
+                }),
+                "{deliveries[{attempts,createdAt,id,kind,lastError,nextAttemptAt,provider,providerMessageId,recipient,sentAt,status,subject}]}".to_string(),
+            ),
+            (
+                "sendTestEmail",
+                snapshot(&SendTestEmailResponse {
+                    success: true,
+                    provider: "ses".to_string(),
+                    message_id: Some("0100018d".to_string()),
+                }),
+                "{messageId,provider,success}".to_string(),
+            ),
+            (
+                "listAccountDeletions",
+                snapshot(&ListAccountDeletionsResponse {
+                    deletions: vec![AccountDeletion {
+                        id: 3,
+                        did: "did:plc:gone".to_string(),
+                        handle: "gone.test".to_string(),
+                        deleted_at: Utc::now(),
+                        blobs_deleted: 12,
+                        plc_tombstoned: true,
+                        account_seq: Some(40),
+                        identity_seq: Some(41),
+                    }],
+                    cursor: None,
+                }),
+                "{cursor,deletions[{accountSeq,blobsDeleted,deletedAt,did,handle,id,identitySeq,plcTombstoned}]}".to_string(),
+            ),
+            (
+                "label.queryLabels",
+                snapshot(&QueryLabelsResponse {
+                    labels: vec![LabelView::from(label())],
+                    cursor: Some("50".to_string()),
+                }),
+                "{cursor,labels[{cid,cts,exp,neg,sig,src,uri,val}]}".to_string(),
+            ),
+        ];
+
+        for (route, actual, expected) in cases {
+            assert!(
+                !actual.contains('_'),
+                "{} response has snake_case keys: {}",
+                route,
+                actual
+            );
+            assert_eq!(actual, expected, "{} response shape changed", route);
+        }
+    }
+
+    #[test]
+    fn test_audit_log_csv() {
+        let mut entry = audit_entry();
+        entry.details = Some("reason: \"spam, bots\"".to_string());
+        entry.ip_address = None;
+
+        let csv = audit_log_csv(&[entry.clone()]);
+        let lines: Vec<&str> = csv.split("\r\n").collect();
+        assert_eq!(lines[0], "id,timestamp,adminDid,action,subjectDid,details,ipAddress");
+        assert_eq!(
+            lines[1],
+            format!(
+                "1,{},did:plc:admin,account.takedown,did:plc:user,\"reason: \"\"spam, bots\"\"\",",
+                entry.timestamp.to_rfc3339()
+            )
+        );
+        assert_eq!(lines[2], "");
+    }
 }
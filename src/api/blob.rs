@@ -1,24 +1,65 @@
 /// com.atproto.repo.uploadBlob and blob serving endpoints
 use crate::{
-    api::middleware,
-    blob_store::{BlobUploadResponse},
+    account::repo_webhook_events,
+    api::{conditional, middleware},
+    blob_store::{BlobProcessingState, BlobProcessingStatus, BlobUploadResponse, ImagePreset, TempBlob, UploadSession},
     context::AppContext,
     error::{PdsError, PdsResult},
 };
 use axum::{
     body::Bytes,
-    extract::{Path, State},
-    http::{header, HeaderMap, StatusCode},
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{header, HeaderMap, HeaderName, StatusCode},
     response::{IntoResponse, Response},
-    routing::{get, post},
+    routing::{get, patch, post},
     Json, Router,
 };
+use serde::{Deserialize, Serialize};
+
+/// Protocol version reported on resumable upload responses
+const TUS_VERSION: &str = "1.0.0";
+
+/// Cache lifetime for image variants; short enough that takedowns and
+/// deletions stop being served from shared caches within a day
+const IMAGE_CACHE_CONTROL: &str = "public, max-age=86400";
+
+/// Largest chunk accepted by a single resumable upload PATCH
+const MAX_CHUNK_BYTES: usize = 8 * 1024 * 1024;
+
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+
+// Upload feedback headers on uploadBlob and finalize responses
+const BLOB_MAX_SIZE: HeaderName = HeaderName::from_static("x-blob-max-size");
+const BLOB_QUOTA_USED: HeaderName = HeaderName::from_static("x-blob-quota-used");
+const BLOB_QUOTA_LIMIT: HeaderName = HeaderName::from_static("x-blob-quota-limit");
+const BLOB_QUOTA_REMAINING: HeaderName = HeaderName::from_static("x-blob-quota-remaining");
+const BLOB_WIDTH: HeaderName = HeaderName::from_static("x-blob-width");
+const BLOB_HEIGHT: HeaderName = HeaderName::from_static("x-blob-height");
+const BLOB_STATE: HeaderName = HeaderName::from_static("x-blob-processing-state");
+const BLOB_ANIMATED: HeaderName = HeaderName::from_static("x-blob-animated");
+const BLOB_DURATION: HeaderName = HeaderName::from_static("x-blob-duration-ms");
 
 /// Build blob routes
 pub fn routes() -> Router<AppContext> {
     Router::new()
         .route("/xrpc/com.atproto.repo.uploadBlob", post(upload_blob))
         .route("/blob/:cid", get(get_blob))
+        .route("/xrpc/com.atproto.sync.getBlob", get(sync_get_blob))
+        .route("/img/:preset/:did/:cid", get(get_image))
+        // Resumable uploads
+        .route("/uploads", post(create_upload))
+        .route(
+            "/uploads/:id",
+            patch(append_upload)
+                .head(upload_status)
+                .delete(cancel_upload)
+                .layer(DefaultBodyLimit::max(MAX_CHUNK_BYTES)),
+        )
+        .route("/uploads/:id/finalize", post(finalize_upload))
+        .route("/xrpc/app.aurora.blob.getProcessingStatus", get(get_processing_status))
+        .route("/xrpc/app.aurora.blob.getWaveform", get(get_waveform))
 }
 
 /// Upload a blob (Two-phase upload)
@@ -26,12 +67,16 @@ pub fn routes() -> Router<AppContext> {
 /// Phase 1: Stages blob in temporary storage and returns blob reference.
 /// Phase 2: Blob is committed to permanent storage when used in a record.
 ///
-/// Accepts raw binary data in the request body with Content-Type header
+/// A blob that records already point at (imported before their blobs were
+/// uploaded, as in account migration) is committed straight away.
+///
+/// Accepts raw binary data in the request body with Content-Type header.
+/// The response carries upload feedback headers (see `uploaded_blob_response`).
 async fn upload_blob(
     State(ctx): State<AppContext>,
     headers: HeaderMap,
     body: Bytes,
-) -> PdsResult<impl IntoResponse> {
+) -> PdsResult<Response> {
     // Require authentication
     let session = middleware::require_auth(State(ctx.clone()), headers.clone()).await?;
 
@@ -50,17 +95,245 @@ async fn upload_blob(
         .stage_blob(data, mime_type.as_deref(), &session.did)
         .await?;
 
-    // Return blob reference
-    let blob_ref = crate::blob_store::BlobRef::new(
-        temp_blob.cid,
-        temp_blob.mime_type,
-        temp_blob.size,
-    );
+    if ctx.blob_store.get_metadata(&temp_blob.cid).await?.is_none() && ctx.actor_store.exists(&session.did).await {
+        let refs = ctx.actor_store.count_blob_refs(&session.did, &temp_blob.cid).await?;
+        if refs > 0 {
+            ctx.blob_store
+                .claim_refs(&session.did, &vec![temp_blob.cid.clone(); refs])
+                .await?;
+        }
+    }
+
+    notify_blob_uploaded(&ctx, &session.did, &temp_blob).await;
+
+    uploaded_blob_response(&ctx, &session.did, temp_blob).await
+}
+
+/// Queue `blob.uploaded` repo webhook events for a staged blob
+async fn notify_blob_uploaded(ctx: &AppContext, did: &str, blob: &TempBlob) {
+    let data = serde_json::json!({
+        "cid": blob.cid,
+        "mimeType": blob.mime_type,
+        "size": blob.size,
+    });
+    ctx.repo_webhooks
+        .dispatch(did, repo_webhook_events::BLOB_UPLOADED, None, &data)
+        .await;
+}
+
+/// Blob reference response with upload feedback headers
+///
+/// - `X-Blob-Max-Size`: largest blob of this type accepted, in bytes
+/// - `X-Blob-Quota-Used`: bytes the account stores, this blob included
+/// - `X-Blob-Quota-Limit`, `X-Blob-Quota-Remaining`: when a quota is set
+/// - `X-Blob-Width`, `X-Blob-Height`: for images
+/// - `X-Blob-Animated`: `true` for animated GIFs and WebPs
+/// - `X-Blob-Duration-Ms`: for audio
+/// - `X-Blob-Processing-State`: `staged` until a record uses the blob
+async fn uploaded_blob_response(ctx: &AppContext, did: &str, blob: TempBlob) -> PdsResult<Response> {
+    let status = ctx.blob_store.processing_status(did, &blob.cid).await?;
+    let state = status.map_or(BlobProcessingState::Staged, |s| s.state);
+    let used = ctx.blob_store.account_usage(did).await?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(BLOB_MAX_SIZE, ctx.blob_store.size_limit(&blob.mime_type).into());
+    headers.insert(BLOB_QUOTA_USED, used.into());
+    if let Some(quota) = ctx.blob_store.account_quota() {
+        headers.insert(BLOB_QUOTA_LIMIT, quota.into());
+        headers.insert(BLOB_QUOTA_REMAINING, (quota as i64 - used).max(0).into());
+    }
+    if let (Some(width), Some(height)) = (blob.width, blob.height) {
+        headers.insert(BLOB_WIDTH, width.into());
+        headers.insert(BLOB_HEIGHT, height.into());
+    }
+    if blob.animated {
+        headers.insert(BLOB_ANIMATED, "true".parse().unwrap());
+    }
+    if let Some(duration_ms) = blob.duration_ms {
+        headers.insert(BLOB_DURATION, duration_ms.into());
+    }
+    headers.insert(BLOB_STATE, state_name(state).parse().unwrap());
+
+    let TempBlob { cid, mime_type, size, .. } = blob;
+    let body = BlobUploadResponse {
+        blob: crate::blob_store::BlobRef::new(cid, mime_type, size),
+    };
+    Ok((StatusCode::OK, headers, Json(body)).into_response())
+}
+
+/// Header value of a processing state
+fn state_name(state: BlobProcessingState) -> &'static str {
+    match state {
+        BlobProcessingState::Staged => "staged",
+        BlobProcessingState::Ready => "ready",
+    }
+}
+
+/// Read a non-negative integer header
+fn int_header(headers: &HeaderMap, name: &HeaderName) -> PdsResult<i64> {
+    headers
+        .get(name)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|v| *v >= 0)
+        .ok_or_else(|| PdsError::Validation(format!("Missing or invalid {} header", name)))
+}
+
+/// Response carrying a session's progress headers
+fn upload_response(status: StatusCode, session: &UploadSession) -> Response {
+    let mut response = status.into_response();
+    let headers = response.headers_mut();
+    headers.insert(TUS_RESUMABLE, TUS_VERSION.parse().unwrap());
+    headers.insert(UPLOAD_OFFSET, session.offset.into());
+    headers.insert(UPLOAD_LENGTH, session.length.into());
+    headers.insert(header::CACHE_CONTROL, "no-store".parse().unwrap());
+    response
+}
+
+/// Start a resumable upload
+///
+/// POST /uploads with `Upload-Length` set to the full blob size and the
+/// blob's Content-Type. Returns 201 with the session URL in `Location`.
+async fn create_upload(State(ctx): State<AppContext>, headers: HeaderMap) -> PdsResult<Response> {
+    let session = middleware::require_auth(State(ctx.clone()), headers.clone()).await?;
+
+    let length = int_header(&headers, &UPLOAD_LENGTH)?;
+    let mime_type = headers.get(header::CONTENT_TYPE).and_then(|v| v.to_str().ok());
+
+    let upload = ctx
+        .upload_manager
+        .create(&session.did, length, mime_type)
+        .await?;
+
+    let mut response = upload_response(StatusCode::CREATED, &upload);
+    let location = format!("/uploads/{}", upload.id)
+        .parse()
+        .map_err(|_| PdsError::Internal("Invalid upload location".to_string()))?;
+    response.headers_mut().insert(header::LOCATION, location);
+    Ok(response)
+}
+
+/// Report how much of an upload has been received
+///
+/// HEAD /uploads/:id. Clients resume from the returned `Upload-Offset`.
+async fn upload_status(
+    State(ctx): State<AppContext>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> PdsResult<Response> {
+    let session = middleware::require_auth(State(ctx.clone()), headers.clone()).await?;
+    let upload = ctx.upload_manager.get(&id, &session.did).await?;
+    Ok(upload_response(StatusCode::OK, &upload))
+}
+
+/// Append a chunk to an upload
+///
+/// PATCH /uploads/:id with `Upload-Offset` equal to the bytes already
+/// received. A stale offset gets 409 Conflict.
+async fn append_upload(
+    State(ctx): State<AppContext>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> PdsResult<Response> {
+    let session = middleware::require_auth(State(ctx.clone()), headers.clone()).await?;
+    let offset = int_header(&headers, &UPLOAD_OFFSET)?;
+
+    let upload = ctx
+        .upload_manager
+        .append(&id, &session.did, offset, &body)
+        .await?;
+    Ok(upload_response(StatusCode::NO_CONTENT, &upload))
+}
+
+/// Finish an upload and stage the blob
+///
+/// POST /uploads/:id/finalize once every byte has been sent. Returns the
+/// same blob reference and headers as uploadBlob.
+async fn finalize_upload(
+    State(ctx): State<AppContext>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> PdsResult<Response> {
+    let session = middleware::require_auth(State(ctx.clone()), headers.clone()).await?;
+    let temp_blob = ctx.upload_manager.finalize(&id, &session.did).await?;
+    notify_blob_uploaded(&ctx, &session.did, &temp_blob).await;
+    uploaded_blob_response(&ctx, &session.did, temp_blob).await
+}
+
+/// Abandon an upload
+///
+/// DELETE /uploads/:id
+async fn cancel_upload(
+    State(ctx): State<AppContext>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+) -> PdsResult<StatusCode> {
+    let session = middleware::require_auth(State(ctx.clone()), headers.clone()).await?;
+    ctx.upload_manager.cancel(&id, &session.did).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
 
-    Ok((
-        StatusCode::OK,
-        Json(BlobUploadResponse { blob: blob_ref }),
-    ))
+/// Query parameters for app.aurora.blob.getProcessingStatus
+#[derive(Debug, Deserialize)]
+struct ProcessingStatusQuery {
+    cid: String,
+}
+
+/// app.aurora.blob.getProcessingStatus
+///
+/// Where one of the caller's uploads is in its processing, with its
+/// dimensions and thumbnail once known. Clients poll until `ready`.
+async fn get_processing_status(
+    State(ctx): State<AppContext>,
+    Query(query): Query<ProcessingStatusQuery>,
+    headers: HeaderMap,
+) -> PdsResult<Json<BlobProcessingStatus>> {
+    let session = middleware::require_auth(State(ctx.clone()), headers).await?;
+    let status = ctx
+        .blob_store
+        .processing_status(&session.did, &query.cid)
+        .await?
+        .ok_or_else(|| PdsError::NotFound(format!("Blob not found: {}", query.cid)))?;
+    Ok(Json(status))
+}
+
+/// app.aurora.blob.getWaveform response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WaveformResponse {
+    cid: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<i64>,
+    /// Peak amplitudes, 0-255, spread evenly over the recording
+    peaks: Vec<u8>,
+}
+
+/// app.aurora.blob.getWaveform
+///
+/// Waveform of an audio blob uploaded by `did`, for drawing voice posts
+/// before they play. Public, like com.atproto.sync.getBlob.
+async fn get_waveform(
+    State(ctx): State<AppContext>,
+    Query(query): Query<GetBlobQuery>,
+) -> PdsResult<Json<WaveformResponse>> {
+    let not_found = || PdsError::NotFound(format!("No waveform for blob {}", query.cid));
+    let metadata = ctx
+        .blob_store
+        .get_metadata(&query.cid)
+        .await?
+        .filter(|m| m.creator_did == query.did)
+        .ok_or_else(not_found)?;
+    if ctx.moderation_manager.is_taken_down(&query.did).await? {
+        return Err(not_found());
+    }
+
+    let peaks = metadata.waveform.ok_or_else(not_found)?;
+    Ok(Json(WaveformResponse {
+        cid: metadata.cid,
+        duration_ms: metadata.duration_ms,
+        peaks,
+    }))
 }
 
 /// Get a blob by CID
@@ -71,33 +344,53 @@ async fn get_blob(
     Path(cid): Path<String>,
     headers: HeaderMap,
 ) -> PdsResult<Response> {
+    serve_blob(&ctx, &cid, &headers).await
+}
+
+/// Query parameters for com.atproto.sync.getBlob
+#[derive(Debug, Deserialize)]
+struct GetBlobQuery {
+    did: String,
+    cid: String,
+}
+
+/// com.atproto.sync.getBlob
+///
+/// Same as /blob/:cid, but only for blobs uploaded by `did`.
+async fn sync_get_blob(
+    State(ctx): State<AppContext>,
+    Query(query): Query<GetBlobQuery>,
+    headers: HeaderMap,
+) -> PdsResult<Response> {
+    ctx.blob_store
+        .get_metadata(&query.cid)
+        .await?
+        .filter(|m| m.creator_did == query.did)
+        .ok_or_else(|| PdsError::NotFound(format!("Blob not found: {}", query.cid)))?;
+
+    serve_blob(&ctx, &query.cid, &headers).await
+}
+
+/// Blob response with ETag, Cache-Control and Range handling
+async fn serve_blob(ctx: &AppContext, cid: &str, headers: &HeaderMap) -> PdsResult<Response> {
+    // CIDs are content-addressed, so the CID is the validator and the
+    // check needs no blob read
+    let etag = conditional::etag(cid);
+    let cache_control = blob_cache_control(ctx.config.http_cache.blob_max_age);
+    if conditional::if_none_match(headers, &etag) {
+        return Ok(conditional::not_modified(&etag, &cache_control));
+    }
+
     // Get blob from store
     let blob_data = ctx
         .blob_store
-        .get(&cid)
+        .get(cid)
         .await?
         .ok_or_else(|| PdsError::NotFound(format!("Blob not found: {}", cid)))?;
 
     let (data, mime_type) = blob_data;
     let total_size = data.len();
 
-    // Calculate ETag from CID (CID is already content-addressed)
-    let etag = format!("\"{}\"", cid);
-
-    // Check If-None-Match header for 304 Not Modified
-    if let Some(if_none_match) = headers.get(header::IF_NONE_MATCH) {
-        if let Ok(if_none_match_str) = if_none_match.to_str() {
-            if if_none_match_str == etag {
-                return Ok(Response::builder()
-                    .status(StatusCode::NOT_MODIFIED)
-                    .header(header::ETAG, etag)
-                    .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
-                    .body(axum::body::Body::empty())
-                    .unwrap());
-            }
-        }
-    }
-
     // Check for Range header
     if let Some(range_header) = headers.get(header::RANGE) {
         if let Ok(range_str) = range_header.to_str() {
@@ -116,7 +409,7 @@ async fn get_blob(
                         format!("bytes {}-{}/{}", start, end, total_size),
                     )
                     .header(header::ETAG, etag)
-                    .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+                    .header(header::CACHE_CONTROL, cache_control)
                     .header(header::ACCEPT_RANGES, "bytes")
                     .body(axum::body::Body::from(partial_data))
                     .unwrap());
@@ -130,12 +423,89 @@ async fn get_blob(
         .header(header::CONTENT_TYPE, mime_type)
         .header(header::CONTENT_LENGTH, total_size.to_string())
         .header(header::ETAG, etag)
-        .header(header::CACHE_CONTROL, "public, max-age=31536000, immutable")
+        .header(header::CACHE_CONTROL, cache_control)
         .header(header::ACCEPT_RANGES, "bytes")
         .body(axum::body::Body::from(data))
         .unwrap())
 }
 
+/// Cache-Control for blob bodies; long lifetimes are marked immutable
+fn blob_cache_control(max_age: u64) -> String {
+    if max_age >= 86_400 {
+        format!("{}, immutable", conditional::cache_control(max_age))
+    } else {
+        conditional::cache_control(max_age)
+    }
+}
+
+/// Serve a resized image variant
+///
+/// GET /img/:preset/:did/:cid, where preset is one of avatar,
+/// avatar_thumbnail, banner, feed_thumbnail or feed_fullsize. Variants are
+/// JPEGs rendered on first request and cached. A trailing `@jpeg` on the
+/// CID is accepted for compatibility with CDN-style URLs.
+async fn get_image(
+    State(ctx): State<AppContext>,
+    Path((preset, did, cid)): Path<(String, String, String)>,
+    headers: HeaderMap,
+) -> PdsResult<Response> {
+    let preset = ImagePreset::from_name(&preset)
+        .ok_or_else(|| PdsError::NotFound(format!("Unknown image preset: {}", preset)))?;
+    let cid = cid.strip_suffix("@jpeg").unwrap_or(&cid).to_string();
+
+    // The blob must belong to the DID in the URL and its account must be visible
+    let metadata = ctx
+        .blob_store
+        .get_metadata(&cid)
+        .await?
+        .filter(|m| m.creator_did == did)
+        .ok_or_else(|| PdsError::NotFound(format!("Blob not found: {}", cid)))?;
+    if !metadata.mime_type.starts_with("image/") {
+        return Err(PdsError::Validation(format!("Blob {} is not an image", cid)));
+    }
+    if ctx.moderation_manager.is_taken_down(&did).await? {
+        return Err(PdsError::NotFound(format!("Blob not found: {}", cid)));
+    }
+
+    let etag = conditional::etag(&format!("{}-{}", cid, preset.name()));
+    if conditional::if_none_match(&headers, &etag) {
+        return Ok(conditional::not_modified(&etag, IMAGE_CACHE_CONTROL));
+    }
+
+    let data = match ctx.image_cache.get(preset, &cid).await {
+        Ok(Some(data)) => data,
+        cached => {
+            if let Err(e) = cached {
+                tracing::warn!("Image cache read failed for {}: {}", cid, e);
+            }
+
+            let (source, _) = ctx
+                .blob_store
+                .get(&cid)
+                .await?
+                .ok_or_else(|| PdsError::NotFound(format!("Blob not found: {}", cid)))?;
+            let rendered = tokio::task::spawn_blocking(move || preset.render(&source))
+                .await
+                .map_err(|e| PdsError::Internal(format!("Image transform failed: {}", e)))??;
+
+            if let Err(e) = ctx.image_cache.put(preset, &cid, &rendered).await {
+                tracing::warn!("Image cache write failed for {}: {}", cid, e);
+            }
+            rendered
+        }
+    };
+
+    Ok(Response::builder()
+        .status(StatusCode::OK)
+        .header(header::CONTENT_TYPE, "image/jpeg")
+        .header(header::CONTENT_LENGTH, data.len().to_string())
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, IMAGE_CACHE_CONTROL)
+        .header(header::X_CONTENT_TYPE_OPTIONS, "nosniff")
+        .body(axum::body::Body::from(data))
+        .unwrap())
+}
+
 /// Parse HTTP Range header
 ///
 /// Returns (start, end) inclusive byte positions, or None if invalid
@@ -0,0 +1,106 @@
+/// Conditional GET support
+///
+/// Read endpoints whose responses are determined by a CID or repo rev send
+/// that as an ETag and answer a matching `If-None-Match` with 304, so
+/// polling clients only pay for the body when something changed.
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Quote a validator as a strong ETag
+pub fn etag(value: &str) -> String {
+    format!("\"{}\"", value)
+}
+
+/// Short stable digest for ETags built from mutable response parts
+pub fn digest(value: &impl Serialize) -> String {
+    let bytes = serde_json::to_vec(value).unwrap_or_default();
+    hex::encode(&Sha256::digest(&bytes)[..8])
+}
+
+/// Cache-Control value for a max-age in seconds
+///
+/// Zero still allows storing but makes clients revalidate every time.
+pub fn cache_control(max_age: u64) -> String {
+    if max_age == 0 {
+        "no-cache".to_string()
+    } else {
+        format!("public, max-age={}", max_age)
+    }
+}
+
+/// Whether `If-None-Match` matches `etag`
+///
+/// Uses the weak comparison GET requires (RFC 9110 section 13.1.2), so
+/// `W/` prefixes are ignored on both sides.
+pub fn if_none_match(headers: &HeaderMap, etag: &str) -> bool {
+    let ours = etag.trim_start_matches("W/");
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == ours)
+}
+
+/// 304 response carrying the validator and caching headers
+pub fn not_modified(etag: &str, cache_control: &str) -> Response {
+    Response::builder()
+        .status(StatusCode::NOT_MODIFIED)
+        .header(header::ETAG, etag)
+        .header(header::CACHE_CONTROL, cache_control)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// JSON response with an ETag, or 304 if the client already has it
+pub fn json<T: Serialize>(headers: &HeaderMap, etag: &str, cache_control: &str, body: T) -> Response {
+    if if_none_match(headers, etag) {
+        return not_modified(etag, cache_control);
+    }
+
+    let mut response = Json(body).into_response();
+    if let (Ok(etag), Ok(cache_control)) = (etag.parse(), cache_control.parse()) {
+        response.headers_mut().insert(header::ETAG, etag);
+        response.headers_mut().insert(header::CACHE_CONTROL, cache_control);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::IF_NONE_MATCH, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let tag = etag("bafyabc");
+        assert!(if_none_match(&headers("\"bafyabc\""), &tag));
+        assert!(if_none_match(&headers("\"other\", W/\"bafyabc\""), &tag));
+        assert!(if_none_match(&headers("*"), &tag));
+        assert!(!if_none_match(&headers("\"bafyab\""), &tag));
+        assert!(!if_none_match(&HeaderMap::new(), &tag));
+    }
+
+    #[test]
+    fn test_json_not_modified() {
+        let tag = etag("3kabc");
+        let response = json(&headers("\"3kabc\""), &tag, &cache_control(60), "body");
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], "\"3kabc\"");
+
+        let response = json(&HeaderMap::new(), &tag, &cache_control(0), "body");
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CACHE_CONTROL], "no-cache");
+    }
+}
@@ -0,0 +1,363 @@
+/// RSS and Atom feeds of an account's posts
+///
+/// `GET /feeds/<handle>.rss` (RSS 2.0) and `GET /feeds/<handle>.atom`
+/// list the newest `app.bsky.feed.post` records of an active account.
+/// Deleted and taken-down posts are left out, as are replies to other
+/// accounts; feeds of taken-down or deactivated accounts are not served.
+///
+/// The ETag follows the repo revision, so readers polling with
+/// `If-None-Match` get a 304 until the account posts again, and rendered
+/// feeds are kept in Redis (when configured) for `PDS_FEEDS_CACHE_SECONDS`.
+use crate::{
+    account::normalize_handle,
+    actor_store::{record_codec::decode_record, RepositoryManager},
+    api::conditional,
+    context::AppContext,
+    error::{PdsError, PdsResult},
+};
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+/// Redis category of rendered feeds
+const CACHE_CATEGORY: &str = "feed:";
+
+/// Longest entry title taken from the post text
+const TITLE_CHARS: usize = 80;
+
+/// Build feed routes
+pub fn routes() -> Router<AppContext> {
+    Router::new().route("/feeds/:file", get(feed))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            Self::Rss => "application/rss+xml; charset=utf-8",
+            Self::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Self::Rss => "rss",
+            Self::Atom => "atom",
+        }
+    }
+}
+
+/// Feed-level details
+#[derive(Debug)]
+struct FeedInfo {
+    did: String,
+    handle: String,
+    title: String,
+    description: String,
+    /// URL of the account's profile in the web app
+    profile_url: String,
+    /// URL of this feed
+    self_url: String,
+}
+
+/// One post of a feed
+#[derive(Debug)]
+struct FeedItem {
+    uri: String,
+    link: String,
+    text: String,
+    published: DateTime<Utc>,
+}
+
+/// GET /feeds/:handle.rss or /feeds/:handle.atom
+async fn feed(State(ctx): State<AppContext>, Path(file): Path<String>, headers: HeaderMap) -> PdsResult<Response> {
+    let config = &ctx.config.feeds;
+    let not_found = || PdsError::NotFound(format!("No feed {}", file));
+    if !config.enabled {
+        return Err(not_found());
+    }
+
+    let (handle, format) = match (file.strip_suffix(".rss"), file.strip_suffix(".atom")) {
+        (Some(handle), _) => (handle, FeedFormat::Rss),
+        (_, Some(handle)) => (handle, FeedFormat::Atom),
+        _ => return Err(not_found()),
+    };
+    let handle = normalize_handle(handle).map_err(|_| not_found())?;
+    let did = ctx.account_manager.did_for_handle(&handle).await?.ok_or_else(not_found)?;
+    let account = ctx.account_manager.get_account(&did).await?;
+    if account.taken_down || account.deactivated_at.is_some() {
+        return Err(not_found());
+    }
+
+    let rev = ctx.actor_store.get_repo_root(&did).await?.rev;
+    let version = conditional::digest(&(&rev, &handle, format.extension(), config.max_items, &config.link_base));
+    let etag = conditional::etag(&version);
+    let cache_control = conditional::cache_control(config.cache_seconds);
+    if conditional::if_none_match(&headers, &etag) {
+        return Ok(conditional::not_modified(&etag, &cache_control));
+    }
+
+    let cache_key = format!("{}:{}", did, version);
+    let cached = match &ctx.cache {
+        Some(cache) => cache.get::<String>(CACHE_CATEGORY, &cache_key).await.unwrap_or_default(),
+        None => None,
+    };
+    let body = match cached {
+        Some(body) => body,
+        None => {
+            let body = build_feed(&ctx, &did, &handle, format).await?;
+            if let Some(cache) = &ctx.cache {
+                if let Err(e) = cache
+                    .set(CACHE_CATEGORY, &cache_key, &body, Some(config.cache_seconds.max(1)))
+                    .await
+                {
+                    tracing::debug!("Failed to cache feed of {}: {}", did, e);
+                }
+            }
+            body
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, cache_control),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+/// Render the feed of `did` from its newest posts
+async fn build_feed(ctx: &AppContext, did: &str, handle: &str, format: FeedFormat) -> PdsResult<String> {
+    let config = &ctx.config.feeds;
+    let repo = RepositoryManager::new(did.to_string(), (*ctx.actor_store).clone());
+    let profile = repo
+        .get_record(&format!("at://{}/app.bsky.actor.profile/self", did))
+        .await?
+        .map(|record| record["value"].clone())
+        .unwrap_or_default();
+
+    let profile_url = format!("{}/profile/{}", config.link_base, handle);
+    let display_name = profile["displayName"].as_str().filter(|n| !n.trim().is_empty());
+    let info = FeedInfo {
+        did: did.to_string(),
+        handle: handle.to_string(),
+        title: match display_name {
+            Some(name) => format!("{} (@{})", name.trim(), handle),
+            None => format!("@{}", handle),
+        },
+        description: profile["description"]
+            .as_str()
+            .map(String::from)
+            .unwrap_or_else(|| format!("Posts by @{}", handle)),
+        profile_url: profile_url.clone(),
+        self_url: format!("{}/feeds/{}.{}", ctx.public_url(), handle, format.extension()),
+    };
+
+    let mut items = Vec::new();
+    for record in ctx
+        .actor_store
+        .list_recent_records(did, "app.bsky.feed.post", config.max_items)
+        .await?
+    {
+        let Some(block) = ctx.actor_store.get_block(did, &record.cid).await? else {
+            continue;
+        };
+        let post = decode_record(&block)?;
+        if !is_feed_post(did, &post) {
+            continue;
+        }
+
+        items.push(FeedItem {
+            link: format!("{}/post/{}", profile_url, record.rkey),
+            text: post["text"].as_str().unwrap_or_default().to_string(),
+            published: post["createdAt"]
+                .as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc))
+                .unwrap_or(record.indexed_at),
+            uri: record.uri,
+        });
+    }
+
+    Ok(match format {
+        FeedFormat::Rss => render_rss(&info, &items),
+        FeedFormat::Atom => render_atom(&info, &items),
+    })
+}
+
+/// Whether a post belongs in its author's feed (not a reply to someone else)
+fn is_feed_post(did: &str, post: &Value) -> bool {
+    match post["reply"]["parent"]["uri"].as_str() {
+        Some(parent) => parent
+            .strip_prefix("at://")
+            .and_then(|rest| rest.split('/').next())
+            .is_some_and(|parent_did| parent_did == did),
+        None => true,
+    }
+}
+
+fn render_rss(info: &FeedInfo, items: &[FeedItem]) -> String {
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<rss version=\"2.0\" xmlns:atom=\"http://www.w3.org/2005/Atom\">\n<channel>\n");
+    xml.push_str(&format!("<title>{}</title>\n", escape(&info.title)));
+    xml.push_str(&format!("<link>{}</link>\n", escape(&info.profile_url)));
+    xml.push_str(&format!("<description>{}</description>\n", escape(&info.description)));
+    xml.push_str(&format!(
+        "<atom:link href=\"{}\" rel=\"self\" type=\"application/rss+xml\"/>\n",
+        escape(&info.self_url)
+    ));
+    if let Some(latest) = items.iter().map(|item| item.published).max() {
+        xml.push_str(&format!("<lastBuildDate>{}</lastBuildDate>\n", latest.to_rfc2822()));
+    }
+
+    for item in items {
+        xml.push_str("<item>\n");
+        xml.push_str(&format!("<link>{}</link>\n", escape(&item.link)));
+        xml.push_str(&format!("<guid isPermaLink=\"false\">{}</guid>\n", escape(&item.uri)));
+        xml.push_str(&format!("<pubDate>{}</pubDate>\n", item.published.to_rfc2822()));
+        xml.push_str(&format!("<description>{}</description>\n", escape(&item.text)));
+        xml.push_str("</item>\n");
+    }
+
+    xml.push_str("</channel>\n</rss>\n");
+    xml
+}
+
+fn render_atom(info: &FeedInfo, items: &[FeedItem]) -> String {
+    let updated = items
+        .iter()
+        .map(|item| item.published)
+        .max()
+        .unwrap_or(DateTime::UNIX_EPOCH);
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str("<feed xmlns=\"http://www.w3.org/2005/Atom\">\n");
+    xml.push_str(&format!("<id>at://{}</id>\n", escape(&info.did)));
+    xml.push_str(&format!("<title>{}</title>\n", escape(&info.title)));
+    xml.push_str(&format!("<subtitle>{}</subtitle>\n", escape(&info.description)));
+    xml.push_str(&format!("<link rel=\"alternate\" href=\"{}\"/>\n", escape(&info.profile_url)));
+    xml.push_str(&format!("<link rel=\"self\" href=\"{}\"/>\n", escape(&info.self_url)));
+    xml.push_str(&format!("<updated>{}</updated>\n", updated.to_rfc3339()));
+    xml.push_str(&format!(
+        "<author><name>@{}</name><uri>{}</uri></author>\n",
+        escape(&info.handle),
+        escape(&info.profile_url)
+    ));
+
+    for item in items {
+        xml.push_str("<entry>\n");
+        xml.push_str(&format!("<id>{}</id>\n", escape(&item.uri)));
+        xml.push_str(&format!("<title>{}</title>\n", escape(&entry_title(&item.text))));
+        xml.push_str(&format!("<link rel=\"alternate\" href=\"{}\"/>\n", escape(&item.link)));
+        xml.push_str(&format!("<published>{}</published>\n", item.published.to_rfc3339()));
+        xml.push_str(&format!("<updated>{}</updated>\n", item.published.to_rfc3339()));
+        xml.push_str(&format!("<content type=\"text\">{}</content>\n", escape(&item.text)));
+        xml.push_str("</entry>\n");
+    }
+
+    xml.push_str("</feed>\n");
+    xml
+}
+
+/// First line of a post, shortened, as an Atom entry title
+fn entry_title(text: &str) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    if line.is_empty() {
+        return "Post".to_string();
+    }
+    if line.chars().count() <= TITLE_CHARS {
+        return line.to_string();
+    }
+    let short: String = line.chars().take(TITLE_CHARS - 1).collect();
+    format!("{}…", short.trim_end())
+}
+
+/// Escape text for XML, dropping characters XML 1.0 can't carry
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| matches!(c, '\t' | '\n' | '\r') || *c >= ' ')
+        .fold(String::with_capacity(text.len()), |mut out, c| {
+            match c {
+                '&' => out.push_str("&amp;"),
+                '<' => out.push_str("&lt;"),
+                '>' => out.push_str("&gt;"),
+                '"' => out.push_str("&quot;"),
+                '\'' => out.push_str("&apos;"),
+                c => out.push(c),
+            }
+            out
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> (FeedInfo, Vec<FeedItem>) {
+        let info = FeedInfo {
+            did: "did:plc:alice".to_string(),
+            handle: "alice.test".to_string(),
+            title: "Alice & co (@alice.test)".to_string(),
+            description: "Posts by @alice.test".to_string(),
+            profile_url: "https://bsky.app/profile/alice.test".to_string(),
+            self_url: "https://pds.test/feeds/alice.test.rss".to_string(),
+        };
+        let items = vec![FeedItem {
+            uri: "at://did:plc:alice/app.bsky.feed.post/3abc".to_string(),
+            link: "https://bsky.app/profile/alice.test/post/3abc".to_string(),
+            text: "<hello>\u{0}\nsecond line".to_string(),
+            published: DateTime::parse_from_rfc3339("2025-01-02T03:04:05Z").unwrap().with_timezone(&Utc),
+        }];
+        (info, items)
+    }
+
+    #[test]
+    fn test_render_rss() {
+        let (info, items) = sample();
+        let xml = render_rss(&info, &items);
+        assert!(xml.contains("<title>Alice &amp; co (@alice.test)</title>"));
+        assert!(xml.contains("<guid isPermaLink=\"false\">at://did:plc:alice/app.bsky.feed.post/3abc</guid>"));
+        assert!(xml.contains("<pubDate>Thu, 2 Jan 2025 03:04:05 +0000</pubDate>"));
+        assert!(xml.contains("<description>&lt;hello&gt;\nsecond line</description>"));
+        assert!(!xml.contains('\u{0}'));
+    }
+
+    #[test]
+    fn test_render_atom() {
+        let (info, items) = sample();
+        let xml = render_atom(&info, &items);
+        assert!(xml.contains("<id>at://did:plc:alice</id>"));
+        assert!(xml.contains("<updated>2025-01-02T03:04:05+00:00</updated>"));
+        assert!(xml.contains("<title>&lt;hello&gt;</title>"));
+
+        let empty = render_atom(&info, &[]);
+        assert!(empty.contains("<updated>1970-01-01T00:00:00+00:00</updated>"));
+        assert!(!empty.contains("<entry>"));
+    }
+
+    #[test]
+    fn test_feed_posts_and_titles() {
+        let reply = |parent: &str| json!({ "text": "hi", "reply": { "parent": { "uri": parent } } });
+        assert!(is_feed_post("did:plc:alice", &json!({ "text": "hi" })));
+        assert!(is_feed_post("did:plc:alice", &reply("at://did:plc:alice/app.bsky.feed.post/1")));
+        assert!(!is_feed_post("did:plc:alice", &reply("at://did:plc:bob/app.bsky.feed.post/1")));
+
+        assert_eq!(entry_title(""), "Post");
+        assert_eq!(entry_title(&"a".repeat(100)).chars().count(), TITLE_CHARS);
+    }
+}
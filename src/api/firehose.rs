@@ -19,6 +19,32 @@
 /// - Graceful shutdown on producer failures
 /// - Detailed error messages sent to clients before disconnect
 ///
+/// ## Filtering
+/// - `wantedDids` and `wantedCollections` query parameters (repeated or
+///   comma-separated) restrict the stream to some repos and/or collections
+/// - Collections match exactly or by NSID prefix (`app.bsky.feed.*`)
+/// - Applied by the producer, so filtered events never enter the client buffer
+///
+/// ## Own-Repo Subscriptions
+/// - `app.aurora.sync.subscribeOwnRepo` streams only the authenticated
+///   account's commit, identity and account events, for backup tools and
+///   multi-device sync
+/// - Reads the account's events directly from the log, so a cursor is never
+///   outdated while its events are retained; `wantedCollections` still applies
+///
+/// ## Compression and Frame Size
+/// - permessage-deflate (RFC 7692) when the client offers it and
+///   `PDS_FIREHOSE_COMPRESSION` is on; each message is compressed on its own
+/// - Commits whose frame would exceed `PDS_FIREHOSE_MAX_FRAME_BYTES` are sent
+///   as `tooBig` commits without blocks or ops, for the client to fetch the repo
+///   (commits over the sequencer's block/op budget are already stored that way)
+/// - Bytes sent per connection are reported in metrics
+///
+/// ## Consumer Monitoring
+/// - Each connection is listed in `AppContext::firehose_consumers` with its
+///   address, cursor, frames and bytes sent, for the admin API and metrics
+/// - Admins can disconnect a consumer by id
+///
 /// ## Connection Health
 /// - Ping/pong every 30 seconds to detect dead connections
 /// - Activity tracking to optimize keepalive messages
@@ -35,38 +61,46 @@
 /// - `#commit`: Repository commit with operations
 /// - `#identity`: Handle changes
 /// - `#account`: Account status changes
-/// - `#info`: Control messages (connection status, errors)
+/// - `#info`: Control messages (connection status, errors, and `SequenceGap`
+///   when seqs between two delivered events are missing from the log)
 ///
 /// Each frame includes a monotonically increasing `seq` number for cursor tracking.
 
 use crate::{
+    api::{
+        middleware::{self, ClientIp},
+        websocket::{Deflater, WsSender, WsStream, WsUpgrade},
+    },
     context::AppContext,
     error::{PdsError, PdsResult},
+    metrics,
     sequencer::events::{AccountEvent, CommitEvent, IdentityEvent},
 };
 use axum::{
-    extract::{
-        ws::{Message, WebSocket, WebSocketUpgrade},
-        Query, State,
-    },
-    response::Response,
+    extract::{Query, RawQuery, State},
+    http::HeaderMap,
+    response::{IntoResponse, Response},
     routing::get,
     Router,
 };
 use base64::{Engine as _, engine::general_purpose};
-use futures::{sink::SinkExt, stream::StreamExt};
+use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, net::IpAddr};
 use tokio::{
     sync::mpsc,
-    time::{interval, timeout, Duration, Instant},
+    time::{interval, sleep, timeout, Duration, Instant},
 };
+use tokio_tungstenite::tungstenite::Message;
 
 /// Firehose configuration constants
 const BUFFER_SIZE: usize = 100; // Size of the event buffer for backpressure
-const POLL_INTERVAL_MS: u64 = 100; // How often to poll for new events
+const POLL_INTERVAL_MS: u64 = 100; // First idle re-check of the log; doubles up to the configured max
 const SEND_TIMEOUT_MS: u64 = 5000; // Timeout for sending a message
 const PING_INTERVAL_SECS: u64 = 30; // Send ping every 30 seconds
 const MAX_CATCHUP_EVENTS: i64 = 1000; // Max events to send in catch-up mode
+const MAX_WANTED_DIDS: usize = 10_000; // Max DIDs in a subscription filter
+const MAX_WANTED_COLLECTIONS: usize = 100; // Max collections in a subscription filter
 
 /// Request parameters for subscribeRepos
 #[derive(Debug, Deserialize)]
@@ -75,6 +109,135 @@ pub struct SubscribeReposParams {
     pub cursor: Option<i64>,
 }
 
+/// Subscription filter from `wantedDids` / `wantedCollections`
+///
+/// Empty sets match everything. Identity and account events pass the
+/// collection filter; commits keep only matching ops and are dropped if none
+/// match (their `blocks` are left whole).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FirehoseFilter {
+    pub dids: HashSet<String>,
+    /// Exact NSIDs, or prefixes ending in "." for `nsid.*` patterns
+    pub collections: Vec<String>,
+}
+
+impl FirehoseFilter {
+    /// Parse filters from the raw query string
+    pub fn from_query(query: Option<&str>) -> PdsResult<Self> {
+        let mut filter = Self::default();
+
+        for pair in query.unwrap_or("").split('&') {
+            let Some((key, value)) = pair.split_once('=') else {
+                continue;
+            };
+            let value = urlencoding::decode(&value.replace('+', " "))
+                .map_err(|_| PdsError::Validation(format!("Invalid {} parameter", key)))?
+                .into_owned();
+            let values = value.split(',').map(str::trim).filter(|v| !v.is_empty());
+
+            match key {
+                "wantedDids" => {
+                    for did in values {
+                        if !did.starts_with("did:") {
+                            return Err(PdsError::Validation(format!("Invalid DID in wantedDids: {}", did)));
+                        }
+                        filter.dids.insert(did.to_string());
+                    }
+                }
+                "wantedCollections" => {
+                    for collection in values {
+                        let pattern = match collection.strip_suffix('*') {
+                            Some(prefix) if prefix.ends_with('.') => prefix.to_string(),
+                            Some(_) => {
+                                return Err(PdsError::Validation(format!(
+                                    "Invalid collection pattern: {} (wildcards must follow a '.')",
+                                    collection
+                                )))
+                            }
+                            None => collection.to_string(),
+                        };
+                        if pattern.contains('*') || pattern.split('.').count() < 2 {
+                            return Err(PdsError::Validation(format!("Invalid collection: {}", collection)));
+                        }
+                        if !filter.collections.contains(&pattern) {
+                            filter.collections.push(pattern);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if filter.dids.len() > MAX_WANTED_DIDS {
+            return Err(PdsError::Validation(format!(
+                "Too many wantedDids (max {})",
+                MAX_WANTED_DIDS
+            )));
+        }
+        if filter.collections.len() > MAX_WANTED_COLLECTIONS {
+            return Err(PdsError::Validation(format!(
+                "Too many wantedCollections (max {})",
+                MAX_WANTED_COLLECTIONS
+            )));
+        }
+
+        Ok(filter)
+    }
+
+    /// Whether the filter passes everything
+    pub fn is_empty(&self) -> bool {
+        self.dids.is_empty() && self.collections.is_empty()
+    }
+
+    fn wants_did(&self, did: &str) -> bool {
+        self.dids.is_empty() || self.dids.contains(did)
+    }
+
+    fn wants_path(&self, path: &str) -> bool {
+        if self.collections.is_empty() {
+            return true;
+        }
+        let collection = path.split('/').next().unwrap_or(path);
+        self.collections.iter().any(|pattern| {
+            if pattern.ends_with('.') {
+                collection.starts_with(pattern.as_str())
+            } else {
+                collection == pattern
+            }
+        })
+    }
+
+    /// Apply the filter to a frame, returning what should be sent
+    pub fn apply(&self, frame: FirehoseFrame) -> Option<FirehoseFrame> {
+        match frame {
+            FirehoseFrame::Commit(mut commit) => {
+                if !self.wants_did(&commit.repo) {
+                    return None;
+                }
+                if !self.collections.is_empty() {
+                    commit.ops.retain(|op| self.wants_path(&op.path));
+                    if commit.ops.is_empty() {
+                        return None;
+                    }
+                }
+                Some(FirehoseFrame::Commit(commit))
+            }
+            FirehoseFrame::Identity(ref identity) if !self.wants_did(&identity.did) => None,
+            FirehoseFrame::Account(ref account) if !self.wants_did(&account.did) => None,
+            other => Some(other),
+        }
+    }
+}
+
+/// Which part of the event log a subscription reads
+#[derive(Debug, Clone, PartialEq)]
+enum EventScope {
+    /// Every repo (subscribeRepos)
+    All,
+    /// One account's own repo (subscribeOwnRepo)
+    Repo(String),
+}
+
 /// Firehose event frame
 #[derive(Debug, Serialize)]
 #[serde(tag = "$type")]
@@ -89,6 +252,18 @@ pub enum FirehoseFrame {
     Info(FirehoseInfo),
 }
 
+impl FirehoseFrame {
+    /// Seq of the event the frame carries (info frames have none)
+    fn seq(&self) -> Option<i64> {
+        match self {
+            FirehoseFrame::Commit(commit) => Some(commit.seq),
+            FirehoseFrame::Identity(identity) => Some(identity.seq),
+            FirehoseFrame::Account(account) => Some(account.seq),
+            FirehoseFrame::Info(_) => None,
+        }
+    }
+}
+
 /// Commit event for firehose
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -147,20 +322,88 @@ pub struct FirehoseInfo {
 
 /// WebSocket handler for subscribeRepos
 pub async fn subscribe_repos(
-    ws: WebSocketUpgrade,
+    ws: WsUpgrade,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Query(params): Query<SubscribeReposParams>,
+    RawQuery(query): RawQuery,
     State(ctx): State<AppContext>,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_subscription(socket, params, ctx))
+    // Reject bad filters before upgrading so the client gets a 400
+    let filter = match FirehoseFilter::from_query(query.as_deref()) {
+        Ok(filter) => filter,
+        Err(e) => return e.into_response(),
+    };
+
+    let client = ConsumerClient::from_request(ip, &headers);
+    let config = ctx.config.firehose.clone();
+    ws.on_upgrade(config.compression, config.compression_level, move |socket, deflater| {
+        handle_subscription(socket, deflater, params, filter, EventScope::All, client, ctx)
+    })
+}
+
+/// WebSocket handler for subscribeOwnRepo
+///
+/// Requires an access token (app passwords are accepted) and streams the
+/// caller's own repo events; `wantedDids` is ignored.
+pub async fn subscribe_own_repo(
+    ws: WsUpgrade,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Query(params): Query<SubscribeReposParams>,
+    RawQuery(query): RawQuery,
+    State(ctx): State<AppContext>,
+) -> Response {
+    let client = ConsumerClient::from_request(ip, &headers);
+    // Authenticate and validate before upgrading so errors get a status code
+    let session = match middleware::require_auth(State(ctx.clone()), headers).await {
+        Ok(session) => session,
+        Err(e) => return e.into_response(),
+    };
+    let mut filter = match FirehoseFilter::from_query(query.as_deref()) {
+        Ok(filter) => filter,
+        Err(e) => return e.into_response(),
+    };
+    filter.dids = HashSet::from([session.did.clone()]);
+
+    let config = ctx.config.firehose.clone();
+    ws.on_upgrade(config.compression, config.compression_level, move |socket, deflater| {
+        handle_subscription(socket, deflater, params, filter, EventScope::Repo(session.did), client, ctx)
+    })
+}
+
+/// Who is connecting, for the consumer registry
+struct ConsumerClient {
+    remote_addr: Option<String>,
+    user_agent: Option<String>,
+}
+
+impl ConsumerClient {
+    fn from_request(ip: Option<IpAddr>, headers: &HeaderMap) -> Self {
+        Self {
+            remote_addr: ip.map(|ip| ip.to_string()),
+            user_agent: headers
+                .get(axum::http::header::USER_AGENT)
+                .and_then(|h| h.to_str().ok())
+                .map(str::to_string),
+        }
+    }
 }
 
 /// Handle WebSocket subscription with backpressure and error recovery
 async fn handle_subscription(
-    socket: WebSocket,
+    socket: WsStream,
+    deflater: Option<Deflater>,
     params: SubscribeReposParams,
+    filter: FirehoseFilter,
+    scope: EventScope,
+    client: ConsumerClient,
     ctx: AppContext,
 ) {
-    let (mut sender, mut receiver) = socket.split();
+    let (sink, mut receiver) = socket.split();
+    let mut sender = WsSender::new(sink, deflater);
+    let max_frame_bytes = ctx.config.firehose.max_frame_bytes;
+    let head = ctx.sequencer.watch_head();
 
     // Validate cursor and get current sequence
     let current_seq = match ctx.sequencer.current_seq().await {
@@ -177,8 +420,9 @@ async fn handle_subscription(
     let requested_cursor = params.cursor.unwrap_or(0);
     let mut cursor = requested_cursor;
 
-    // Check if cursor is too old (backfill limit)
-    if requested_cursor > 0 && current_seq - requested_cursor > MAX_CATCHUP_EVENTS {
+    // Check if cursor is too old (backfill limit). Own-repo subscriptions
+    // only read one account's events, so they may resume from any cursor.
+    if scope == EventScope::All && requested_cursor > 0 && current_seq - requested_cursor > MAX_CATCHUP_EVENTS {
         // Cursor too old, send info message
         let info = FirehoseFrame::Info(FirehoseInfo {
             name: "OutdatedCursor".to_string(),
@@ -195,10 +439,29 @@ async fn handle_subscription(
         cursor = current_seq - MAX_CATCHUP_EVENTS;
     }
 
+    let did = match &scope {
+        EventScope::All => None,
+        EventScope::Repo(did) => Some(did.clone()),
+    };
+    let consumer = ctx
+        .firehose_consumers
+        .register(client.remote_addr, client.user_agent, did, cursor);
+
     // Send initial info message
     let info = FirehoseFrame::Info(FirehoseInfo {
         name: "Connected".to_string(),
-        message: Some(format!("Firehose subscription started at seq {}", cursor)),
+        message: Some(if let EventScope::Repo(did) = &scope {
+            format!("Subscription to {} started at seq {}", did, cursor)
+        } else if filter.is_empty() {
+            format!("Firehose subscription started at seq {}", cursor)
+        } else {
+            format!(
+                "Firehose subscription started at seq {} (filtered: {} DID(s), {} collection(s))",
+                cursor,
+                filter.dids.len(),
+                filter.collections.len()
+            )
+        }),
     });
     if send_frame(&mut sender, &info).await.is_err() {
         return;
@@ -210,7 +473,7 @@ async fn handle_subscription(
     // Spawn event producer task
     let producer_ctx = ctx.clone();
     let producer = tokio::spawn(async move {
-        produce_events(producer_ctx, cursor, event_tx).await
+        produce_events(producer_ctx, cursor, filter, scope, event_tx).await
     });
 
     // Create ping interval
@@ -222,9 +485,16 @@ async fn handle_subscription(
         tokio::select! {
             // Send events from buffer
             Some(frame) = event_rx.recv() => {
-                match send_frame_with_timeout(&mut sender, &frame).await {
+                let seq = frame.seq();
+                let before = sender.bytes_sent();
+                match send_frame_with_timeout(&mut sender, frame, max_frame_bytes).await {
                     Ok(_) => {
                         last_activity = Instant::now();
+                        consumer.record_sent(seq, sender.bytes_sent() - before);
+                        let latest = *head.borrow();
+                        if let Some(latest) = latest {
+                            consumer.report_lag(latest);
+                        }
                     }
                     Err(SendError::Timeout) => {
                         tracing::warn!("Send timeout, client may be slow");
@@ -239,6 +509,13 @@ async fn handle_subscription(
                 }
             }
 
+            // Disconnected through the admin API
+            _ = consumer.disconnected() => {
+                tracing::info!(consumer = consumer.id(), "Firehose consumer disconnected by an admin");
+                let _ = send_error(&mut sender, "Disconnected by the server administrator").await;
+                break;
+            }
+
             // Send periodic pings
             _ = ping_interval.tick() => {
                 if last_activity.elapsed() > Duration::from_secs(PING_INTERVAL_SECS) {
@@ -280,29 +557,60 @@ async fn handle_subscription(
 
     // Cancel producer task
     producer.abort();
+
+    metrics::record_firehose_connection(sender.bytes_sent());
+    tracing::debug!(
+        bytes_sent = sender.bytes_sent(),
+        uncompressed_bytes = sender.uncompressed_bytes(),
+        compressed = sender.compressed(),
+        "firehose_connection_closed"
+    );
 }
 
 /// Produce events from sequencer and send to channel
+///
+/// Events the subscription filter rejects are skipped here, so they take no
+/// buffer space. Once caught up, the producer sleeps until the sequencer
+/// reports a new event, re-checking the log with exponential backoff (up to
+/// `PDS_FIREHOSE_MAX_POLL_INTERVAL_MS`) for events written by another process.
 async fn produce_events(
     ctx: AppContext,
     mut cursor: i64,
+    filter: FirehoseFilter,
+    scope: EventScope,
     tx: mpsc::Sender<FirehoseFrame>,
 ) {
-    let mut tick = interval(Duration::from_millis(POLL_INTERVAL_MS));
+    let min_idle = Duration::from_millis(POLL_INTERVAL_MS);
+    let max_idle = Duration::from_millis(ctx.config.firehose.max_poll_interval_ms).max(min_idle);
+    let mut idle = min_idle;
+    let mut head = ctx.sequencer.watch_head();
     let mut error_count = 0;
     const MAX_ERRORS: u32 = 5;
 
     loop {
-        tick.tick().await;
-
         // Get next event from sequencer
-        match ctx.sequencer.next_event(cursor).await {
+        let next = match &scope {
+            EventScope::All => ctx.sequencer.next_event(cursor).await,
+            EventScope::Repo(did) => ctx.sequencer.next_event_for_did(did, cursor).await,
+        };
+        match next {
             Ok(Some(event)) => {
                 error_count = 0; // Reset error count on success
+                idle = min_idle;
+
+                // Tell the client when events it would have received are gone
+                // (an own-repo stream skips other repos' seqs by design)
+                if scope == EventScope::All && cursor > 0 && event.seq > cursor + 1 {
+                    if let Some(info) = gap_info(&ctx, cursor, event.seq).await {
+                        if tx.send(info).await.is_err() {
+                            break;
+                        }
+                    }
+                }
                 cursor = event.seq;
 
-                // Convert to firehose frame
-                if let Some(frame) = event_to_frame(event) {
+                // Convert to firehose frame and apply the subscription filter
+                if let Some(frame) = event_to_frame(event).and_then(|f| filter.apply(f)) {
                     // Try to send to channel (with backpressure)
                     if tx.send(frame).await.is_err() {
                         // Channel closed, consumer disconnected
@@ -311,8 +619,16 @@ async fn produce_events(
                 }
             }
             Ok(None) => {
-                // No new events, continue polling
+                // Caught up; wait for the next event or the backoff timer
                 error_count = 0;
+                tokio::select! {
+                    Ok(()) = head.changed() => metrics::record_firehose_wakeup("event"),
+                    _ = sleep(idle) => {
+                        metrics::record_firehose_wakeup("timer");
+                        idle = next_poll_interval(idle, max_idle);
+                    }
+                    _ = tx.closed() => break,
+                }
             }
             Err(e) => {
                 // Error reading events
@@ -325,12 +641,42 @@ async fn produce_events(
                 }
 
                 // Exponential backoff
-                tokio::time::sleep(Duration::from_millis(100 * 2_u64.pow(error_count))).await;
+                sleep(Duration::from_millis(100 * 2_u64.pow(error_count))).await;
             }
         }
     }
 }
 
+/// Idle re-check interval after `current` passed without an event
+fn next_poll_interval(current: Duration, max: Duration) -> Duration {
+    current.saturating_mul(2).min(max)
+}
+
+/// `SequenceGap` info frame if seqs between two events are missing from the log
+///
+/// Jumps over invalidated events are normal and produce nothing.
+async fn gap_info(ctx: &AppContext, after: i64, before: i64) -> Option<FirehoseFrame> {
+    let missing = match ctx.sequencer.missing_between(after, before).await {
+        Ok(missing) => missing,
+        Err(e) => {
+            tracing::warn!("Failed to check for sequence gap after {}: {}", after, e);
+            return None;
+        }
+    };
+    if missing == 0 {
+        return None;
+    }
+
+    tracing::warn!(after, before, missing, "firehose_sequence_gap");
+    Some(FirehoseFrame::Info(FirehoseInfo {
+        name: "SequenceGap".to_string(),
+        message: Some(format!(
+            "{} event(s) between seq {} and {} are missing from the log; resync affected repos",
+            missing, after, before
+        )),
+    }))
+}
+
 /// Convert SeqRow to FirehoseFrame
 fn event_to_frame(event: crate::sequencer::SeqRow) -> Option<FirehoseFrame> {
     match event.event_type.as_str() {
@@ -403,17 +749,60 @@ enum SendError {
     Disconnected,
 }
 
+/// Serialize a frame, sending commits over `max_bytes` as `tooBig`
+///
+/// A `tooBig` commit keeps its repo, commit and rev but drops blocks, ops
+/// and blobs; consumers fetch the repo instead.
+fn encode_frame(frame: FirehoseFrame, max_bytes: usize) -> PdsResult<String> {
+    let encode = |frame: &FirehoseFrame| {
+        serde_json::to_string(frame)
+            .map_err(|e| PdsError::Internal(format!("Failed to encode firehose frame: {}", e)))
+    };
+
+    let json = encode(&frame)?;
+    if json.len() <= max_bytes {
+        return Ok(json);
+    }
+
+    match frame {
+        FirehoseFrame::Commit(mut commit) => {
+            tracing::debug!(
+                seq = commit.seq,
+                repo = %commit.repo,
+                bytes = json.len(),
+                "firehose_commit_too_big"
+            );
+            metrics::record_firehose_too_big();
+            commit.too_big = true;
+            commit.blocks = String::new();
+            commit.ops.clear();
+            commit.blobs.clear();
+            encode(&FirehoseFrame::Commit(commit))
+        }
+        other => encode(&other),
+    }
+}
+
+/// Send an encoded frame, counting the bytes that went out
+async fn send_text(sender: &mut WsSender, json: String) -> PdsResult<()> {
+    let before = sender.bytes_sent();
+    let result = sender.send_text(json).await;
+    metrics::record_firehose_bytes(sender.compressed(), sender.bytes_sent() - before);
+    result
+}
+
 /// Send a frame with timeout
 async fn send_frame_with_timeout(
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
-    frame: &FirehoseFrame,
+    sender: &mut WsSender,
+    frame: FirehoseFrame,
+    max_frame_bytes: usize,
 ) -> Result<(), SendError> {
-    let json = serde_json::to_string(frame)
+    let json = encode_frame(frame, max_frame_bytes)
         .map_err(|_| SendError::Disconnected)?;
 
     match timeout(
         Duration::from_millis(SEND_TIMEOUT_MS),
-        sender.send(Message::Text(json))
+        send_text(sender, json)
     ).await {
         Ok(Ok(_)) => Ok(()),
         Ok(Err(_)) => Err(SendError::Disconnected),
@@ -423,17 +812,17 @@ async fn send_frame_with_timeout(
 
 /// Send a frame without timeout
 async fn send_frame(
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    sender: &mut WsSender,
     frame: &FirehoseFrame,
 ) -> Result<(), ()> {
     let json = serde_json::to_string(frame)
         .map_err(|_| ())?;
-    sender.send(Message::Text(json)).await.map_err(|_| ())
+    send_text(sender, json).await.map_err(|_| ())
 }
 
 /// Send error message and close connection
 async fn send_error(
-    sender: &mut futures::stream::SplitSink<WebSocket, Message>,
+    sender: &mut WsSender,
     message: &str,
 ) -> Result<(), ()> {
     let error_frame = FirehoseFrame::Info(FirehoseInfo {
@@ -446,10 +835,15 @@ async fn send_error(
 
 /// Build firehose routes
 pub fn routes() -> Router<AppContext> {
-    Router::new().route(
-        "/xrpc/com.atproto.sync.subscribeRepos",
-        get(subscribe_repos),
-    )
+    Router::new()
+        .route(
+            "/xrpc/com.atproto.sync.subscribeRepos",
+            get(subscribe_repos),
+        )
+        .route(
+            "/xrpc/app.aurora.sync.subscribeOwnRepo",
+            get(subscribe_own_repo),
+        )
 }
 
 #[cfg(test)]
@@ -609,4 +1003,110 @@ mod tests {
         assert!(PING_INTERVAL_SECS >= 10); // At least 10 seconds
         assert!(MAX_CATCHUP_EVENTS > 100); // Reasonable catchup window
     }
+
+    #[test]
+    fn test_next_poll_interval() {
+        let max = Duration::from_millis(5_000);
+        let mut idle = Duration::from_millis(POLL_INTERVAL_MS);
+        let mut steps = Vec::new();
+        while idle < max {
+            idle = next_poll_interval(idle, max);
+            steps.push(idle.as_millis());
+        }
+        assert_eq!(steps, vec![200, 400, 800, 1600, 3200, 5000]);
+        assert_eq!(next_poll_interval(max, max), max);
+    }
+
+    fn commit_frame(repo: &str, paths: &[&str]) -> FirehoseFrame {
+        FirehoseFrame::Commit(FirehoseCommit {
+            seq: 1,
+            rebase: false,
+            too_big: false,
+            repo: repo.to_string(),
+            commit: "cid123".to_string(),
+            rev: "rev123".to_string(),
+            since: None,
+            blocks: "".to_string(),
+            ops: paths
+                .iter()
+                .map(|path| FirehoseOp {
+                    action: "create".to_string(),
+                    path: path.to_string(),
+                    cid: None,
+                })
+                .collect(),
+            blobs: vec![],
+            time: Utc::now(),
+        })
+    }
+
+    #[test]
+    fn test_encode_frame_too_big() {
+        let frame = commit_frame("did:plc:alice", &["app.bsky.feed.post/1"]);
+        let json = encode_frame(frame, 1 << 20).unwrap();
+        assert!(json.contains("\"tooBig\":false"));
+
+        let FirehoseFrame::Commit(mut commit) = commit_frame("did:plc:alice", &["app.bsky.feed.post/1"]) else {
+            unreachable!()
+        };
+        commit.blocks = "A".repeat(8192);
+        let json = encode_frame(FirehoseFrame::Commit(commit), 4096).unwrap();
+        assert!(json.len() < 4096);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["tooBig"], true);
+        assert_eq!(value["blocks"], "");
+        assert_eq!(value["ops"].as_array().unwrap().len(), 0);
+        assert_eq!(value["repo"], "did:plc:alice");
+    }
+
+    #[test]
+    fn test_filter_from_query() {
+        let filter = FirehoseFilter::from_query(Some(
+            "cursor=5&wantedDids=did:plc:a&wantedDids=did%3Aplc%3Ab,did:plc:c&wantedCollections=app.bsky.feed.*",
+        ))
+        .unwrap();
+        assert_eq!(filter.dids.len(), 3);
+        assert!(filter.dids.contains("did:plc:b"));
+        assert_eq!(filter.collections, vec!["app.bsky.feed.".to_string()]);
+
+        assert!(FirehoseFilter::from_query(None).unwrap().is_empty());
+        assert!(FirehoseFilter::from_query(Some("wantedDids=alice.test")).is_err());
+        assert!(FirehoseFilter::from_query(Some("wantedCollections=app.bsky*")).is_err());
+        assert!(FirehoseFilter::from_query(Some("wantedCollections=post")).is_err());
+    }
+
+    #[test]
+    fn test_filter_apply() {
+        let filter =
+            FirehoseFilter::from_query(Some("wantedDids=did:plc:a&wantedCollections=app.bsky.feed.*")).unwrap();
+
+        // Other repos are dropped
+        assert!(filter.apply(commit_frame("did:plc:b", &["app.bsky.feed.post/1"])).is_none());
+
+        // Only matching ops are kept; commits with none are dropped
+        match filter.apply(commit_frame("did:plc:a", &["app.bsky.feed.post/1", "app.bsky.graph.follow/2"])) {
+            Some(FirehoseFrame::Commit(commit)) => {
+                assert_eq!(commit.ops.len(), 1);
+                assert_eq!(commit.ops[0].path, "app.bsky.feed.post/1");
+            }
+            _ => panic!("Expected Commit frame"),
+        }
+        assert!(filter.apply(commit_frame("did:plc:a", &["app.bsky.graph.follow/2"])).is_none());
+
+        // Identity events ignore the collection filter
+        let identity = |did: &str| {
+            FirehoseFrame::Identity(FirehoseIdentity {
+                seq: 2,
+                did: did.to_string(),
+                time: Utc::now(),
+                handle: None,
+            })
+        };
+        assert!(filter.apply(identity("did:plc:a")).is_some());
+        assert!(filter.apply(identity("did:plc:b")).is_none());
+
+        let exact = FirehoseFilter::from_query(Some("wantedCollections=app.bsky.feed.post")).unwrap();
+        assert!(exact.apply(commit_frame("did:plc:z", &["app.bsky.feed.postgate/1"])).is_none());
+        assert!(exact.apply(commit_frame("did:plc:z", &["app.bsky.feed.post/1"])).is_some());
+    }
 }
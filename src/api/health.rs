@@ -142,6 +142,9 @@ pub async fn health_detailed(
     // Check sequencer
     checks.push(check_sequencer_detailed(&ctx).await);
 
+    // Check PLC directories
+    checks.push(check_plc_directories_detailed(&ctx));
+
     // Determine overall status
     let overall_status = determine_overall_status(&checks);
 
@@ -275,16 +278,47 @@ async fn check_background_jobs_detailed(ctx: &AppContext) -> ComponentHealth {
 async fn check_sequencer_detailed(ctx: &AppContext) -> ComponentHealth {
     let start = Instant::now();
 
-    // Check if sequencer is accessible
-    let _ = &ctx.sequencer;
+    // Gaps or leftover duplicates from the last integrity check degrade it
+    let (status, integrity) = match ctx.sequencer.last_health().await {
+        Some(health) => (
+            if health.is_healthy() { "healthy" } else { "degraded" },
+            serde_json::json!({
+                "gaps": health.gaps.len(),
+                "missingEvents": health.missing_events,
+                "checkedAt": health.checked_at,
+            }),
+        ),
+        None => ("healthy", serde_json::Value::Null),
+    };
 
     ComponentHealth {
         name: "sequencer".to_string(),
-        status: "healthy".to_string(),
+        status: status.to_string(),
         response_time_ms: Some(start.elapsed().as_millis() as u64),
         error: None,
         details: Some(serde_json::json!({
             "type": "event_stream",
+            "integrity": integrity,
+        })),
+    }
+}
+
+/// Check PLC directory health as seen by DID resolution
+///
+/// Degraded while any directory is being passed over after repeated
+/// failures; resolution still works as long as one answers.
+fn check_plc_directories_detailed(ctx: &AppContext) -> ComponentHealth {
+    let mirrors = ctx.identity_resolver.plc_mirror_status();
+    let down = mirrors.iter().filter(|m| !m.healthy).count();
+
+    ComponentHealth {
+        name: "plc_directory".to_string(),
+        status: if down == 0 { "healthy" } else { "degraded" }.to_string(),
+        response_time_ms: None,
+        error: (down == mirrors.len() && down > 0).then(|| "All PLC directories are failing".to_string()),
+        details: Some(serde_json::json!({
+            "mirrors": mirrors,
+            "offlineMode": ctx.config.identity.plc_failover.offline_mode,
         })),
     }
 }
@@ -1,16 +1,25 @@
 /// Identity API endpoints
 /// Implements com.atproto.identity.* endpoints for handle and DID resolution
 use crate::{
+    account::{normalize_handle, HandleAvailability},
+    api::{conditional, middleware::ClientIp},
     auth::AuthContext,
-    crypto::plc::{PlcOperationBuilder, PlcSigner},
+    crypto::{keys::public_key_multibase, plc::PlcOperationBuilder},
     error::{PdsError, PdsResult},
+    identity::HandleVerificationMethod,
+    mailer::Branding,
+    rate_limit::ResolveClient,
     AppContext,
 };
+use atproto::did_doc::DidDocument;
 use axum::{
     extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
 /// com.atproto.identity.resolveHandle
@@ -31,17 +40,82 @@ pub struct ResolveHandleResponse {
 
 pub async fn resolve_handle(
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Query(params): Query<ResolveHandleParams>,
-) -> PdsResult<Json<ResolveHandleResponse>> {
-    // Validate handle format
-    if params.handle.is_empty() {
-        return Err(PdsError::Validation("Handle cannot be empty".to_string()));
+) -> PdsResult<Response> {
+    let client = ResolveClient::from_request(ip, &headers, &ctx.jwt_keys);
+    let budget = ctx.resolve_limiter.check(&client).await?;
+
+    // Validate and normalize handle
+    let handle = normalize_handle(&params.handle)?;
+
+    // Fast path: a fresh resolution in memory or Redis, without SQLite
+    let did = match ctx.identity_resolver.cached_handle(&handle).await {
+        Some(did) => did,
+        // Local accounts, including old handles within their redirect period
+        None => match ctx.account_manager.did_for_handle(&handle).await? {
+            Some(did) => {
+                ctx.identity_resolver.remember_handle(&handle, &did);
+                did
+            }
+            // Resolve via identity resolver (with caching)
+            None => ctx.identity_resolver.resolve_handle(&handle).await?,
+        },
+    };
+
+    Ok(resolution_response(
+        &headers,
+        budget,
+        ctx.config.http_cache.handle_max_age,
+        ResolveHandleResponse { did },
+    ))
+}
+
+/// com.atproto.identity.resolveDid
+///
+/// Resolve a DID to its DID document
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveDidParams {
+    pub did: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveDidResponse {
+    pub did_doc: DidDocument,
+}
+
+pub async fn resolve_did(
+    State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
+    Query(params): Query<ResolveDidParams>,
+) -> PdsResult<Response> {
+    let client = ResolveClient::from_request(ip, &headers, &ctx.jwt_keys);
+    let budget = ctx.resolve_limiter.check(&client).await?;
+
+    if !params.did.starts_with("did:plc:") && !params.did.starts_with("did:web:") {
+        return Err(PdsError::Validation(format!("Unsupported DID: {}", params.did)));
     }
 
-    // Resolve via identity resolver (with caching)
-    let did = ctx.identity_resolver.resolve_handle(&params.handle).await?;
+    let did_doc = ctx.identity_resolver.resolve_did(&params.did).await?;
+
+    Ok(resolution_response(
+        &headers,
+        budget,
+        ctx.config.http_cache.did_doc_max_age,
+        ResolveDidResponse { did_doc },
+    ))
+}
 
-    Ok(Json(ResolveHandleResponse { did }))
+/// Cacheable resolution response carrying the caller's rate limit budget
+fn resolution_response<T: Serialize>(headers: &HeaderMap, budget: u32, max_age: u64, body: T) -> Response {
+    let etag = conditional::etag(&conditional::digest(&body));
+    let mut response = conditional::json(headers, &etag, &conditional::cache_control(max_age), body);
+    response.headers_mut().insert("X-RateLimit-Limit", budget.into());
+    response
 }
 
 /// com.atproto.identity.updateHandle
@@ -54,57 +128,146 @@ pub struct UpdateHandleRequest {
     pub handle: String,
 }
 
+/// Handle change waiting for the emailed code
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingHandleChangeResponse {
+    pub handle: String,
+    pub confirmation_required: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
 pub async fn update_handle(
     State(ctx): State<AppContext>,
     auth: AuthContext,
     Json(req): Json<UpdateHandleRequest>,
-) -> PdsResult<Json<()>> {
+) -> PdsResult<Response> {
     let did = auth.did;
 
-    // Validate handle format
-    if req.handle.is_empty() {
-        return Err(PdsError::Validation("Handle cannot be empty".to_string()));
+    // Validate and normalize handle (lowercase, punycode)
+    let new_handle = normalize_handle(&req.handle)?;
+
+    let method = verify_new_handle(&ctx, &did, &new_handle).await?;
+
+    // With PDS_HANDLE_CHANGE_REQUIRE_EMAIL the change waits for a code sent to
+    // the account's email (accounts without one change directly)
+    let account = ctx.account_manager.get_account(&did).await?;
+    if let (true, Some(email), false) = (
+        ctx.config.identity.handle_changes.require_email_confirmation,
+        account.email.as_deref(),
+        account.handle == new_handle,
+    ) {
+        let (handle, code, expires_at) = ctx
+            .account_manager
+            .request_handle_change(&did, &new_handle)
+            .await?;
+
+        let branding = Branding::for_handle(&ctx.config, &account.handle, &ctx.service_url());
+        ctx.mailer
+            .send_handle_change_email(email, &account.handle, &handle, &code, &branding)
+            .await?;
+        tracing::info!(did = %did, handle = %handle, "handle_change_requested");
+
+        let pending = PendingHandleChangeResponse {
+            handle,
+            confirmation_required: true,
+            expires_at,
+        };
+        return Ok((StatusCode::ACCEPTED, Json(pending)).into_response());
     }
 
-    // Basic handle validation (lowercase, alphanumeric + dots/hyphens)
-    if !req.handle.chars().all(|c| c.is_alphanumeric() || c == '.' || c == '-') {
-        return Err(PdsError::Validation(
-            "Handle contains invalid characters".to_string(),
-        ));
-    }
+    // Update account table with new handle
+    let old_handle = ctx.account_manager
+        .update_handle(&did, &new_handle)
+        .await?;
 
-    // Check handle length (max 253 chars for DNS compatibility)
-    if req.handle.len() > 253 {
-        return Err(PdsError::Validation("Handle too long (max 253 characters)".to_string()));
-    }
+    finish_handle_change(&ctx, &did, &old_handle, &new_handle, method).await?;
 
-    // Normalize handle to lowercase
-    let new_handle = req.handle.to_lowercase();
+    Ok(Json(()).into_response())
+}
 
-    // Update handle via identity resolver
-    // This will verify the handle resolves to this DID
-    ctx.identity_resolver
-        .update_handle(&did, &new_handle)
-        .await?;
+/// app.aurora.identity.confirmHandleChange
+///
+/// Apply a handle change with the code emailed by updateHandle
+#[derive(Debug, Deserialize)]
+pub struct ConfirmHandleChangeRequest {
+    pub token: String,
+}
 
-    // Update account table with new handle
-    let old_handle = ctx.account_manager
-        .update_handle(&did, &new_handle)
+pub async fn confirm_handle_change(
+    State(ctx): State<AppContext>,
+    auth: AuthContext,
+    Json(req): Json<ConfirmHandleChangeRequest>,
+) -> PdsResult<Json<()>> {
+    let did = auth.did;
+
+    // The domain may have changed hands since the code was sent
+    let new_handle = ctx.account_manager.pending_handle_change(&did, &req.token).await?;
+    let method = verify_new_handle(&ctx, &did, &new_handle).await?;
+
+    let (old_handle, new_handle) = ctx
+        .account_manager
+        .confirm_handle_change(&did, &req.token)
         .await?;
 
+    finish_handle_change(&ctx, &did, &old_handle, &new_handle, method).await?;
+
+    Ok(Json(()))
+}
+
+/// Custom domains must point back at this DID via DNS TXT or HTTPS well-known
+///
+/// Returns how a custom domain was verified (None for service handles).
+async fn verify_new_handle(
+    ctx: &AppContext,
+    did: &str,
+    new_handle: &str,
+) -> PdsResult<Option<HandleVerificationMethod>> {
+    if ctx.account_manager.is_service_handle(new_handle) {
+        Ok(None)
+    } else {
+        Ok(Some(ctx.identity_resolver.verify_handle(new_handle, did).await?))
+    }
+}
+
+/// Everything that follows a handle change: verification tracking, caches,
+/// the PLC document and the identity event
+async fn finish_handle_change(
+    ctx: &AppContext,
+    did: &str,
+    old_handle: &str,
+    new_handle: &str,
+    method: Option<HandleVerificationMethod>,
+) -> PdsResult<()> {
+    // Track custom domains for background re-verification
+    match method {
+        Some(method) => {
+            ctx.handle_verification_manager
+                .record_verified(did, new_handle, method)
+                .await?;
+            tracing::info!(did = %did, handle = %new_handle, method = method.as_str(), "custom_handle_verified");
+        }
+        None => ctx.handle_verification_manager.remove(did).await?,
+    }
+
     // Invalidate old handle in cache (force re-resolution)
-    ctx.identity_resolver
-        .invalidate_handle(&old_handle)
-        .await?;
+    if old_handle != new_handle {
+        ctx.identity_resolver
+            .invalidate_handle(old_handle)
+            .await?;
+    }
+
+    // Point the PLC document's alsoKnownAs at the new handle. The handle
+    // change itself has already succeeded, so directory failures only warn.
+    if let Err(e) = ctx.account_manager.update_plc_handle(did, new_handle).await {
+        tracing::warn!(did = %did, handle = %new_handle, error = %e, "plc_handle_update_failed");
+    }
+    ctx.identity_resolver.invalidate_did(did).await?;
 
     // Emit identity event to sequencer for firehose consumers
-    use crate::sequencer::events::IdentityEvent;
-    let identity_event = IdentityEvent::new(did.clone(), Some(new_handle.clone()));
-    ctx.sequencer
-        .sequence_identity(identity_event)
-        .await?;
+    ctx.account_events.identity_changed(did, Some(new_handle)).await?;
 
-    Ok(Json(()))
+    Ok(())
 }
 
 /// com.atproto.identity.getRecommendedDidCredentials
@@ -151,31 +314,25 @@ pub async fn get_recommended_did_credentials(
     // Fetch current DID document
     let doc = ctx.identity_resolver.resolve_did(&did).await?;
 
-    // Extract rotation keys
-    let rotation_keys: Vec<String> = doc
-        .verification_method
-        .iter()
-        .filter_map(|vm| {
-            // Only include keys that can be used for rotation
-            if vm.id.contains("#atproto") {
-                vm.public_key_multibase.clone()
-            } else {
-                None
-            }
-        })
+    // The account's PLC rotation key, if this server holds one
+    let account = ctx.account_manager.get_account(&did).await?;
+    let rotation_keys: Vec<String> = account
+        .plc_rotation_key_public
+        .as_deref()
+        .and_then(|hex_key| hex::decode(hex_key).ok())
+        .and_then(|bytes| k256::ecdsa::VerifyingKey::from_sec1_bytes(&bytes).ok())
+        .map(|key| format!("did:key:{}", public_key_multibase(&key)))
+        .into_iter()
         .collect();
 
-    // Map verification methods
-    let verification_methods: Vec<VerificationMethod> = doc
-        .verification_method
-        .iter()
-        .map(|vm| VerificationMethod {
-            id: vm.id.clone(),
-            method_type: vm.key_type.clone(),
-            controller: vm.controller.clone(),
-            public_key_multibase: vm.public_key_multibase.clone().unwrap_or_default(),
-        })
-        .collect();
+    // The key this server signs the account's commits with
+    let signing_key = ctx.signing_keys.public_key(&did).await?;
+    let verification_methods = vec![VerificationMethod {
+        id: format!("{}#atproto", did),
+        method_type: "Multikey".to_string(),
+        controller: did.clone(),
+        public_key_multibase: public_key_multibase(&signing_key),
+    }];
 
     // Map services
     let services: Vec<Service> = doc
@@ -196,6 +353,61 @@ pub async fn get_recommended_did_credentials(
     }))
 }
 
+/// com.atproto.identity.rotateSigningKey
+///
+/// Replace the account's commit signing key. The new key is published as
+/// the `atproto` verification method first - with a PLC operation for
+/// did:plc accounts whose rotation key this server holds, or in the served
+/// document for local did:web accounts - and commits made afterwards are
+/// signed with it. The PLC rotation key is not changed.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateSigningKeyResponse {
+    pub did: String,
+    /// New signing key as a did:key
+    pub signing_key: String,
+}
+
+pub async fn rotate_signing_key(
+    State(ctx): State<AppContext>,
+    auth: AuthContext,
+) -> PdsResult<Json<RotateSigningKeyResponse>> {
+    let did = auth.did;
+    let account = ctx.account_manager.get_account(&did).await?;
+
+    let (key_ref, public_key) = ctx.signing_keys.generate().await?;
+    let signing_key = format!("did:key:{}", public_key_multibase(&public_key));
+
+    if did.starts_with("did:plc:") {
+        if !ctx.account_manager.update_plc_signing_key(&did, &signing_key).await? {
+            return Err(PdsError::Validation(
+                "This server does not hold the PLC rotation key for this DID; update it with signPlcOperation"
+                    .to_string(),
+            ));
+        }
+    } else {
+        let served_here = did
+            .strip_prefix("did:web:")
+            .is_some_and(|host| ctx.config.virtual_host(host).is_some());
+        if !served_here {
+            return Err(PdsError::Validation(
+                "The DID document for this account is not served by this server".to_string(),
+            ));
+        }
+    }
+
+    ctx.signing_keys.store(&did, &key_ref).await?;
+    ctx.identity_resolver.invalidate_did(&did).await?;
+    tracing::info!(did = %did, "signing_key_rotated");
+
+    // Consumers re-resolve the DID document on identity events
+    ctx.account_events
+        .identity_changed(&did, Some(&account.handle))
+        .await?;
+
+    Ok(Json(RotateSigningKeyResponse { did, signing_key }))
+}
+
 /// com.atproto.identity.signPlcOperation
 ///
 /// Sign a PLC operation for DID:PLC update
@@ -235,10 +447,6 @@ pub async fn sign_plc_operation(
         ));
     }
 
-    // Load the PLC rotation key from config
-    let plc_key_hex = &ctx.config.authentication.plc_rotation_key;
-    let signer = PlcSigner::from_hex(plc_key_hex)?;
-
     // Get current DID document to extract previous operation CID
     let _did_doc = ctx.identity_resolver.resolve_did(&did).await?;
     // In production, extract prev CID from DID doc metadata
@@ -279,8 +487,11 @@ pub async fn sign_plc_operation(
     // Build unsigned operation
     let operation = builder.build()?;
 
-    // Sign the operation
-    let signed_operation = signer.sign_operation(operation)?;
+    // Sign with the server PLC rotation key
+    let signed_operation = ctx
+        .keys
+        .sign_plc_operation(ctx.keys.plc_rotation_key_id(), operation)
+        .await?;
 
     // Convert to JSON value
     let operation_json = serde_json::to_value(&signed_operation).map_err(|e| {
@@ -363,13 +574,13 @@ pub async fn submit_plc_operation(
         .json(&req.operation)
         .send()
         .await
-        .map_err(|e| PdsError::Internal(format!("Failed to submit to PLC directory: {}", e)))?;
+        .map_err(|e| PdsError::Plc(format!("Failed to submit to PLC directory: {}", e)))?;
 
     // Check response status
     if !response.status().is_success() {
         let status = response.status();
         let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        return Err(PdsError::Internal(format!(
+        return Err(PdsError::Plc(format!(
             "PLC directory returned error {}: {}",
             status, error_body
         )));
@@ -416,10 +627,10 @@ pub async fn request_plc_operation_signature(
         .get(&token_endpoint)
         .send()
         .await
-        .map_err(|e| PdsError::Internal(format!("Failed to contact PLC directory: {}", e)))?;
+        .map_err(|e| PdsError::Plc(format!("Failed to contact PLC directory: {}", e)))?;
 
     if !response.status().is_success() {
-        return Err(PdsError::Internal(format!(
+        return Err(PdsError::Plc(format!(
             "PLC directory returned error: {}",
             response.status()
         )));
@@ -427,7 +638,7 @@ pub async fn request_plc_operation_signature(
 
     // Parse response to extract token (last operation CID)
     let audit_log: serde_json::Value = response.json().await.map_err(|e| {
-        PdsError::Internal(format!("Failed to parse PLC response: {}", e))
+        PdsError::Plc(format!("Failed to parse PLC response: {}", e))
     })?;
 
     // Extract the last operation CID as token
@@ -436,7 +647,7 @@ pub async fn request_plc_operation_signature(
             if let Some(cid) = last_op.get("cid").and_then(|v| v.as_str()) {
                 cid.to_string()
             } else {
-                return Err(PdsError::Internal(
+                return Err(PdsError::Plc(
                     "PLC audit log missing CID".to_string(),
                 ));
             }
@@ -445,7 +656,7 @@ pub async fn request_plc_operation_signature(
             String::new()
         }
     } else {
-        return Err(PdsError::Internal(
+        return Err(PdsError::Plc(
             "Invalid PLC audit log format".to_string(),
         ));
     };
@@ -453,6 +664,68 @@ pub async fn request_plc_operation_signature(
     Ok(Json(RequestPlcOperationSignatureResponse { token }))
 }
 
+/// com.atproto.temp.checkHandleAvailability
+///
+/// Check whether a handle can be registered, so signup forms can give
+/// immediate feedback. Reserved and taken handles are reported as unavailable.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckHandleAvailabilityParams {
+    pub handle: String,
+    /// Accepted for lexicon compatibility; not used for suggestions
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub birth_date: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CheckHandleAvailabilityResponse {
+    pub handle: String,
+    pub result: HandleAvailabilityResult,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "$type")]
+pub enum HandleAvailabilityResult {
+    #[serde(rename = "com.atproto.temp.checkHandleAvailability#resultAvailable")]
+    Available {},
+    #[serde(rename = "com.atproto.temp.checkHandleAvailability#resultUnavailable")]
+    Unavailable {
+        suggestions: Vec<HandleSuggestion>,
+    },
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandleSuggestion {
+    pub handle: String,
+    pub method: String,
+}
+
+pub async fn check_handle_availability(
+    State(ctx): State<AppContext>,
+    Query(params): Query<CheckHandleAvailabilityParams>,
+) -> PdsResult<Json<CheckHandleAvailabilityResponse>> {
+    let handle = normalize_handle(&params.handle)?;
+
+    let result = match ctx.account_manager.check_handle_availability(&handle).await? {
+        HandleAvailability::Available => HandleAvailabilityResult::Available {},
+        HandleAvailability::Unavailable { suggestions, .. } => HandleAvailabilityResult::Unavailable {
+            suggestions: suggestions
+                .into_iter()
+                .map(|handle| HandleSuggestion {
+                    handle,
+                    method: "numeric_suffix".to_string(),
+                })
+                .collect(),
+        },
+    };
+
+    Ok(Json(CheckHandleAvailabilityResponse { handle, result }))
+}
+
 /// Build identity API routes
 pub fn routes() -> Router<AppContext> {
     Router::new()
@@ -461,11 +734,23 @@ pub fn routes() -> Router<AppContext> {
             "/xrpc/com.atproto.identity.resolveHandle",
             get(resolve_handle),
         )
+        .route(
+            "/xrpc/com.atproto.identity.resolveDid",
+            get(resolve_did),
+        )
+        .route(
+            "/xrpc/com.atproto.temp.checkHandleAvailability",
+            get(check_handle_availability),
+        )
         // Authenticated endpoints
         .route(
             "/xrpc/com.atproto.identity.updateHandle",
             post(update_handle),
         )
+        .route(
+            "/xrpc/app.aurora.identity.confirmHandleChange",
+            post(confirm_handle_change),
+        )
         .route(
             "/xrpc/com.atproto.identity.getRecommendedDidCredentials",
             get(get_recommended_did_credentials),
@@ -482,10 +767,38 @@ pub fn routes() -> Router<AppContext> {
             "/xrpc/com.atproto.identity.submitPlcOperation",
             post(submit_plc_operation),
         )
+        .route(
+            "/xrpc/com.atproto.identity.rotateSigningKey",
+            post(rotate_signing_key),
+        )
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handle_availability_result_serialization() {
+        let available = serde_json::to_value(HandleAvailabilityResult::Available {}).unwrap();
+        assert_eq!(
+            available,
+            serde_json::json!({ "$type": "com.atproto.temp.checkHandleAvailability#resultAvailable" })
+        );
+
+        let unavailable = serde_json::to_value(HandleAvailabilityResult::Unavailable {
+            suggestions: vec![HandleSuggestion {
+                handle: "alice42.test".to_string(),
+                method: "numeric_suffix".to_string(),
+            }],
+        })
+        .unwrap();
+        assert_eq!(
+            unavailable["$type"],
+            "com.atproto.temp.checkHandleAvailability#resultUnavailable"
+        );
+        assert_eq!(unavailable["suggestions"][0]["handle"], "alice42.test");
+    }
+
     #[test]
     fn test_handle_validation() {
         // Valid handles
@@ -1,16 +1,36 @@
 /// com.atproto.label.* endpoints
+///
+/// Besides queryLabels, `subscribeLabels` streams labels as moderators apply
+/// and remove them: each label (or negation) goes out in its own `#labels`
+/// frame whose `seq` is the label's id, so a consumer resumes with
+/// `cursor=<last seq>`. Without a cursor only new labels are sent; `cursor=0`
+/// replays every label first. Frames are JSON, like the firehose's.
 use crate::{
     admin::labels::Label,
+    api::websocket::{WsSender, WsStream, WsUpgrade},
     context::AppContext,
-    error::PdsResult,
+    error::{PdsError, PdsResult},
 };
 use axum::{
     extract::{Query, State},
+    response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
 use chrono::{DateTime, Utc};
+use futures::stream::StreamExt;
 use serde::{Deserialize, Serialize};
+use tokio::time::{interval, timeout, Duration, Instant};
+use tokio_tungstenite::tungstenite::Message;
+
+/// How often the label table is checked for new labels
+const LABEL_POLL_INTERVAL_MS: u64 = 250;
+/// Labels read per poll
+const LABEL_BATCH_SIZE: i64 = 100;
+/// A consumer that can't take a frame within this long is disconnected
+const LABEL_SEND_TIMEOUT_MS: u64 = 5000;
+/// Keepalive ping when nothing was sent for this long
+const LABEL_PING_INTERVAL_SECS: u64 = 30;
 
 /// Request parameters for queryLabels
 #[derive(Debug, Deserialize)]
@@ -138,10 +158,142 @@ pub async fn query_labels(
     Ok(Json(QueryLabelsResponse { labels, cursor }))
 }
 
+/// Request parameters for subscribeLabels
+#[derive(Debug, Deserialize)]
+pub struct SubscribeLabelsParams {
+    /// Last seq the consumer has seen
+    pub cursor: Option<i64>,
+}
+
+/// subscribeLabels frame
+#[derive(Debug, Serialize)]
+#[serde(tag = "$type")]
+pub enum LabelFrame {
+    #[serde(rename = "#labels")]
+    Labels { seq: i64, labels: Vec<LabelView> },
+    #[serde(rename = "#info")]
+    Info {
+        name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+}
+
+impl From<Label> for LabelFrame {
+    fn from(label: Label) -> Self {
+        Self::Labels {
+            seq: label.id,
+            labels: vec![label.into()],
+        }
+    }
+}
+
+/// WebSocket handler for subscribeLabels
+///
+/// Implements com.atproto.label.subscribeLabels
+pub async fn subscribe_labels(
+    ws: WsUpgrade,
+    Query(params): Query<SubscribeLabelsParams>,
+    State(ctx): State<AppContext>,
+) -> Response {
+    // Check the cursor before upgrading so the client gets a 400
+    let latest = match ctx.label_manager.latest_id().await {
+        Ok(latest) => latest,
+        Err(e) => return e.into_response(),
+    };
+    let cursor = params.cursor.unwrap_or(latest);
+    if cursor > latest {
+        return PdsError::Validation(format!("FutureCursor: cursor {} is ahead of the stream ({})", cursor, latest))
+            .into_response();
+    }
+
+    let config = ctx.config.firehose.clone();
+    ws.on_upgrade(config.compression, config.compression_level, move |socket, deflater| async move {
+        let (sink, receiver) = socket.split();
+        stream_labels(WsSender::new(sink, deflater), receiver, cursor, ctx).await
+    })
+}
+
+/// Send labels after `cursor` until the client goes away
+async fn stream_labels(
+    mut sender: WsSender,
+    mut receiver: futures::stream::SplitStream<WsStream>,
+    mut cursor: i64,
+    ctx: AppContext,
+) {
+    let mut poll = interval(Duration::from_millis(LABEL_POLL_INTERVAL_MS));
+    let mut ping = interval(Duration::from_secs(LABEL_PING_INTERVAL_SECS));
+    let mut last_activity = Instant::now();
+
+    loop {
+        tokio::select! {
+            _ = poll.tick() => {
+                let labels = match ctx.label_manager.labels_after(cursor, LABEL_BATCH_SIZE).await {
+                    Ok(labels) => labels,
+                    Err(e) => {
+                        tracing::warn!("Failed to read labels after {}: {}", cursor, e);
+                        continue;
+                    }
+                };
+
+                for label in labels {
+                    cursor = label.id;
+                    if let Err(e) = send_label_frame(&mut sender, &LabelFrame::from(label)).await {
+                        tracing::debug!("Label stream closed: {}", e);
+                        return;
+                    }
+                    last_activity = Instant::now();
+                }
+            }
+
+            _ = ping.tick() => {
+                if last_activity.elapsed() > Duration::from_secs(LABEL_PING_INTERVAL_SECS)
+                    && sender.send(Message::Ping(vec![])).await.is_err()
+                {
+                    return;
+                }
+            }
+
+            msg = receiver.next() => {
+                match msg {
+                    Some(Ok(Message::Ping(data))) => {
+                        if sender.send(Message::Pong(data)).await.is_err() {
+                            return;
+                        }
+                    }
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Send a frame, giving up on consumers that stopped reading
+async fn send_label_frame(sender: &mut WsSender, frame: &LabelFrame) -> PdsResult<()> {
+    let json = serde_json::to_string(frame)
+        .map_err(|e| PdsError::Internal(format!("Failed to encode label frame: {}", e)))?;
+
+    match timeout(Duration::from_millis(LABEL_SEND_TIMEOUT_MS), sender.send_text(json)).await {
+        Ok(result) => result,
+        Err(_) => {
+            let info = LabelFrame::Info {
+                name: "Error".to_string(),
+                message: Some("Client processing too slow".to_string()),
+            };
+            if let Ok(json) = serde_json::to_string(&info) {
+                let _ = sender.send_text(json).await;
+            }
+            Err(PdsError::Internal("Label consumer too slow".to_string()))
+        }
+    }
+}
+
 /// Build labels API routes
 pub fn routes() -> Router<AppContext> {
     Router::new()
         .route("/xrpc/com.atproto.label.queryLabels", get(query_labels))
+        .route("/xrpc/com.atproto.label.subscribeLabels", get(subscribe_labels))
 }
 
 #[cfg(test)]
@@ -170,6 +322,28 @@ mod tests {
         assert!(json.contains("at://did:plc:test"));
     }
 
+    #[test]
+    fn test_label_frame_serialization() {
+        let label = Label {
+            id: 7,
+            uri: "did:plc:test".to_string(),
+            cid: None,
+            val: "!hide".to_string(),
+            neg: true,
+            src: "did:plc:labeler".to_string(),
+            created_at: Utc::now(),
+            created_by: "did:plc:admin".to_string(),
+            expires_at: None,
+            sig: None,
+        };
+
+        let json = serde_json::to_value(LabelFrame::from(label)).unwrap();
+        assert_eq!(json["$type"], "#labels");
+        assert_eq!(json["seq"], 7);
+        assert_eq!(json["labels"][0]["val"], "!hide");
+        assert_eq!(json["labels"][0]["neg"], true);
+    }
+
     #[test]
     fn test_query_params_deserialization() {
         let json = r#"{"uriPatterns":["at://did:plc:test/*"],"sources":["did:plc:labeler"],"limit":100}"#;
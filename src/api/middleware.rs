@@ -1,17 +1,24 @@
 /// Authentication and authorization middleware
 use crate::{
-    account::ValidatedSession,
+    account::{required_scope, ScopeRequirement, SessionClientInfo, ValidatedSession},
     context::AppContext,
     error::{PdsError, PdsResult},
     metrics,
 };
 use axum::{
-    extract::{Request, State},
-    http::HeaderMap,
+    async_trait,
+    body::Body,
+    extract::{ConnectInfo, FromRequestParts, Request, State},
+    http::{header, request::Parts, HeaderMap, HeaderValue, Method},
     middleware::Next,
     response::Response,
 };
-use std::time::Instant;
+use futures::StreamExt;
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    time::Instant,
+};
 use tracing::{error, info, warn};
 
 /// Extract bearer token from Authorization header
@@ -28,6 +35,63 @@ pub fn extract_bearer_token(headers: &HeaderMap) -> Option<String> {
         })
 }
 
+/// The client's IP address, as resolved by `resolve_client_ip`
+///
+/// `None` when the server wasn't started with connection info.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ClientIp(pub Option<IpAddr>);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts.extensions.get::<ClientIp>().copied().unwrap_or_default())
+    }
+}
+
+/// Resolve the client IP through the trusted proxies and add it to extensions
+pub async fn resolve_client_ip(State(ctx): State<AppContext>, mut req: Request, next: Next) -> Response {
+    let ip = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| crate::net::client_ip(req.headers(), peer.ip(), &ctx.config.trusted_proxies));
+    req.extensions_mut().insert(ClientIp(ip));
+    next.run(req).await
+}
+
+/// Host name the request was addressed to, lowercased and without the port
+///
+/// Prefers `X-Forwarded-Host` so virtual hosts keep working behind a proxy.
+pub fn request_host(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get("x-forwarded-host")
+        .or_else(|| headers.get(header::HOST))
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.split(',').next())
+        .map(|s| {
+            let s = s.trim();
+            // Strip the port, keeping bracketed IPv6 literals whole
+            let host = match s.find(']') {
+                Some(end) if s.starts_with('[') => &s[..=end],
+                _ => s.split(':').next().unwrap_or(s),
+            };
+            host.trim_end_matches('.').to_ascii_lowercase()
+        })
+        .filter(|s| !s.is_empty())
+}
+
+/// Client details to record against a new session
+pub fn session_client_info(ip: Option<IpAddr>, headers: &HeaderMap) -> SessionClientInfo {
+    SessionClientInfo {
+        ip_address: ip.map(|ip| ip.to_string()),
+        user_agent: headers
+            .get("user-agent")
+            .and_then(|h| h.to_str().ok())
+            .map(|s| s.chars().take(512).collect()),
+    }
+}
+
 /// Authenticate request and add session to extensions
 pub async fn authenticate(
     State(ctx): State<AppContext>,
@@ -163,6 +227,202 @@ pub async fn check_account_moderation(
     Ok(next.run(req).await)
 }
 
+/// API token scope enforcement
+///
+/// Requests made with an API token must fall within the token's scopes
+/// (see `account::required_scope`). Runs inside `check_account_moderation`,
+/// which leaves the validated session in the request extensions; requests
+/// without one (no token, or an invalid one) pass through to the endpoint.
+pub async fn enforce_api_token_scopes(req: Request, next: Next) -> Result<Response, PdsError> {
+    let scopes = req
+        .extensions()
+        .get::<ValidatedSession>()
+        .and_then(|session| session.scopes.as_ref());
+
+    if let Some(scopes) = scopes {
+        match required_scope(req.method(), req.uri().path()) {
+            ScopeRequirement::Any => {}
+            ScopeRequirement::Scope(scope) if scopes.contains(&scope) => {}
+            ScopeRequirement::Scope(scope) => {
+                return Err(PdsError::Authorization(format!(
+                    "API token lacks the {} scope",
+                    scope.as_str()
+                )));
+            }
+            ScopeRequirement::Forbidden => {
+                return Err(PdsError::Authorization(
+                    "Not available to API tokens".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Refuse request bodies over the route's size limit (`BodyLimitConfig`)
+///
+/// A declared `Content-Length` over the limit is refused before anything is
+/// read. Bodies without one are read here, up to the limit, so handlers never
+/// buffer more than it.
+pub async fn enforce_body_limit(
+    State(ctx): State<AppContext>,
+    req: Request,
+    next: Next,
+) -> Result<Response, PdsError> {
+    if matches!(*req.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(next.run(req).await);
+    }
+
+    let limit = ctx.config.body_limits.limit_for(req.uri().path());
+    let too_large = || PdsError::PayloadTooLarge(format!("Request body is over the {} byte limit", limit));
+    let declared = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok());
+
+    match declared {
+        Some(length) if length > limit as u64 => Err(too_large()),
+        Some(_) => Ok(next.run(req).await),
+        None => {
+            let (parts, body) = req.into_parts();
+            let mut stream = body.into_data_stream();
+            let mut buffered = Vec::new();
+            while let Some(chunk) = stream.next().await {
+                let chunk = chunk.map_err(|e| PdsError::Validation(format!("Failed to read request body: {}", e)))?;
+                if buffered.len() + chunk.len() > limit {
+                    return Err(too_large());
+                }
+                buffered.extend_from_slice(&chunk);
+            }
+            Ok(next.run(Request::from_parts(parts, Body::from(buffered))).await)
+        }
+    }
+}
+
+/// Header carrying the admin network token
+pub const ADMIN_NETWORK_TOKEN_HEADER: &str = "x-admin-network-token";
+
+/// Admin network token middleware
+///
+/// When `PDS_ADMIN_NETWORK_TOKEN` is configured, requests to admin routes must
+/// present it in the `X-Admin-Network-Token` header in addition to normal
+/// admin authentication. Intended for deployments where a reverse proxy or
+/// internal network injects the token.
+pub async fn require_admin_network_token(
+    State(ctx): State<AppContext>,
+    req: Request,
+    next: Next,
+) -> Result<Response, PdsError> {
+    if let Some(expected) = &ctx.config.authentication.admin_network_token {
+        let presented = req
+            .headers()
+            .get(ADMIN_NETWORK_TOKEN_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("");
+
+        if !constant_time_eq(presented.as_bytes(), expected.as_bytes()) {
+            warn!(
+                path = %req.uri().path(),
+                "admin_network_token_rejected"
+            );
+            metrics::record_error("AdminNetworkTokenRejected", "middleware");
+            // Don't reveal that the admin API exists on this listener
+            return Err(PdsError::NotFound("Endpoint not found".to_string()));
+        }
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// Pretty-print JSON response bodies when `PDS_PRETTY_JSON` is enabled
+///
+/// Intended for development and debugging; responses are buffered and
+/// re-serialized, so leave it off in production.
+pub async fn pretty_json(State(ctx): State<AppContext>, req: Request, next: Next) -> Response {
+    let response = next.run(req).await;
+    if !ctx.config.service.pretty_json {
+        return response;
+    }
+
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.starts_with("application/json"))
+        .unwrap_or(false);
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to buffer JSON response for pretty-printing: {}", e);
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+
+    let pretty = serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .and_then(|value| serde_json::to_vec_pretty(&value).ok());
+
+    match pretty {
+        Some(pretty) => {
+            parts.headers.insert(header::CONTENT_LENGTH, HeaderValue::from(pretty.len()));
+            Response::from_parts(parts, Body::from(pretty))
+        }
+        None => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
+
+/// Add the configured security headers (HSTS, frame-ancestors, Referrer-Policy)
+///
+/// Headers a handler already set are left alone, except that
+/// `frame-ancestors` is appended to an existing Content-Security-Policy that
+/// lacks it.
+pub async fn security_headers(State(ctx): State<AppContext>, req: Request, next: Next) -> Response {
+    let mut response = next.run(req).await;
+    let config = &ctx.config.security_headers;
+    let headers = response.headers_mut();
+
+    headers
+        .entry(header::X_CONTENT_TYPE_OPTIONS)
+        .or_insert(HeaderValue::from_static("nosniff"));
+
+    if let Some(value) = config.hsts_value().and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.entry(header::STRICT_TRANSPORT_SECURITY).or_insert(value);
+    }
+
+    if let Some(value) = config.referrer_policy.as_deref().and_then(|v| HeaderValue::from_str(v).ok()) {
+        headers.entry(header::REFERRER_POLICY).or_insert(value);
+    }
+
+    if let Some(ancestors) = &config.frame_ancestors {
+        let directive = format!("frame-ancestors {}", ancestors);
+        let csp = match headers.get(header::CONTENT_SECURITY_POLICY).and_then(|v| v.to_str().ok()) {
+            Some(existing) if existing.contains("frame-ancestors") => None,
+            Some(existing) => Some(format!("{}; {}", existing.trim_end_matches(';'), directive)),
+            None => Some(directive),
+        };
+        if let Some(value) = csp.and_then(|v| HeaderValue::from_str(&v).ok()) {
+            headers.insert(header::CONTENT_SECURITY_POLICY, value);
+        }
+    }
+
+    response
+}
+
+/// Compare two byte strings without short-circuiting on the first mismatch
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b.iter()).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
 /// Request ID for tracing
 #[derive(Debug, Clone)]
 pub struct RequestId(pub String);
@@ -182,7 +442,7 @@ impl Default for RequestId {
 /// Request logging middleware with request IDs and metrics
 ///
 /// Features:
-/// - Assigns unique request ID to each request
+/// - Tags each request with its X-Request-Id (or a fresh ID if none was set)
 /// - Logs request/response with structured data
 /// - Tracks request duration
 /// - Logs slow requests (>1s)
@@ -193,7 +453,11 @@ pub async fn request_logging(
     mut req: Request,
     next: Next,
 ) -> Result<Response, PdsError> {
-    let request_id = RequestId::new();
+    // Reuse the ID assigned (or forwarded) by the request ID layer
+    let request_id = match crate::telemetry::request_id(&req) {
+        "" => RequestId::new(),
+        id => RequestId(id.to_string()),
+    };
     let method = req.method().to_string();
     let path = req.uri().path().to_string();
     let start = Instant::now();
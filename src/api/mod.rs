@@ -1,36 +1,68 @@
 /// API routes and handlers
+pub mod activitypub;
+pub mod actor;
 pub mod admin;
 pub mod blob;
+pub mod conditional;
+pub mod feeds;
 pub mod firehose;
 pub mod health;
 pub mod identity;
 pub mod labels;
 pub mod middleware;
+pub mod moderation;
 pub mod oauth_admin;
+pub mod proxy;
+pub mod record_history;
+pub mod replica;
 pub mod repo;
 pub mod server;
 pub mod sync;
+pub mod takeout;
+pub mod version;
+pub mod webhooks;
+pub mod websocket;
 pub mod well_known;
 
 use crate::context::AppContext;
 use axum::Router;
 
-/// Build API routes
+/// Build API routes (public and admin)
 pub fn routes() -> Router<AppContext> {
-    // Create OAuth state store (in-memory for now)
-    let oauth_state_store = oauth_admin::OAuthStateStore::new();
+    Router::new().merge(public_routes()).merge(admin_routes())
+}
 
+/// Build public API routes (everything except com.atproto.admin.* and admin OAuth)
+pub fn public_routes() -> Router<AppContext> {
     Router::new()
         .merge(well_known::routes())
         .merge(server::routes())
         .merge(repo::routes())
+        .merge(actor::routes())
         .merge(blob::routes())
         .merge(identity::routes())
-        .merge(admin::routes())
         .merge(sync::routes())
         .merge(firehose::routes())
         .merge(labels::routes())
+        .merge(moderation::routes())
         .merge(health::routes())
+        .merge(version::routes())
+        .merge(takeout::routes())
+        .merge(webhooks::routes())
+        .merge(record_history::routes())
+        .merge(activitypub::routes())
+        .merge(feeds::routes())
+        // Catch-all for XRPC methods without an explicit route
+        .merge(proxy::routes())
+}
+
+/// Build admin API routes (com.atproto.admin.* and admin OAuth login)
+pub fn admin_routes() -> Router<AppContext> {
+    // Create OAuth state store (in-memory for now)
+    let oauth_state_store = oauth_admin::OAuthStateStore::new();
+
+    Router::new()
+        .merge(admin::routes())
         // OAuth admin routes with their own state
         .merge(oauth_admin::routes(oauth_state_store))
 }
@@ -0,0 +1,252 @@
+/// com.atproto.moderation.* endpoints
+///
+/// Reports are stored in the local moderation queue, or — when
+/// `PDS_REPORT_SERVICE_DID` is configured — forwarded to that moderation
+/// service (e.g. Ozone) with a service auth token issued for the reporter.
+use crate::{
+    admin::ReportReason,
+    auth::AuthContext,
+    config::ReportServiceConfig,
+    context::AppContext,
+    crypto::service_auth::{create_service_jwt, ServiceJwtClaims},
+    error::{PdsError, PdsResult},
+    telemetry::inject_trace_context,
+};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+const CREATE_REPORT_NSID: &str = "com.atproto.moderation.createReport";
+
+/// Build moderation routes
+pub fn routes() -> Router<AppContext> {
+    Router::new().route("/xrpc/com.atproto.moderation.createReport", post(create_report))
+}
+
+/// createReport input
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReportInput {
+    /// `com.atproto.moderation.defs#reason*` token
+    pub reason_type: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// `com.atproto.admin.defs#repoRef` or `com.atproto.repo.strongRef`
+    pub subject: Value,
+}
+
+/// createReport output
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateReportOutput {
+    pub id: i64,
+    pub reason_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub subject: Value,
+    pub reported_by: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Subject of a report
+#[derive(Debug, PartialEq)]
+enum ReportSubject {
+    Repo { did: String },
+    Record { uri: String, cid: String },
+}
+
+impl ReportSubject {
+    fn parse(subject: &Value) -> PdsResult<Self> {
+        let field = |name: &str| subject.get(name).and_then(Value::as_str).map(String::from);
+
+        match subject.get("$type").and_then(Value::as_str) {
+            Some("com.atproto.admin.defs#repoRef") => Ok(ReportSubject::Repo {
+                did: field("did")
+                    .ok_or_else(|| PdsError::Validation("repoRef subject requires did".to_string()))?,
+            }),
+            Some("com.atproto.repo.strongRef") => match (field("uri"), field("cid")) {
+                (Some(uri), Some(cid)) => Ok(ReportSubject::Record { uri, cid }),
+                _ => Err(PdsError::Validation(
+                    "strongRef subject requires uri and cid".to_string(),
+                )),
+            },
+            other => Err(PdsError::Validation(format!(
+                "Unsupported report subject type: {}",
+                other.unwrap_or("(none)")
+            ))),
+        }
+    }
+}
+
+/// Report an account or record (com.atproto.moderation.createReport)
+async fn create_report(
+    State(ctx): State<AppContext>,
+    auth: AuthContext,
+    Json(input): Json<CreateReportInput>,
+) -> PdsResult<Response> {
+    let subject = ReportSubject::parse(&input.subject)?;
+
+    if let Some(service) = &ctx.config.report_service {
+        return forward_report(&ctx, service, &auth.did, &input).await;
+    }
+
+    let (subject_did, subject_uri, subject_cid) = match &subject {
+        ReportSubject::Repo { did } => (Some(did.as_str()), None, None),
+        ReportSubject::Record { uri, cid } => (None, Some(uri.as_str()), Some(cid.as_str())),
+    };
+
+    let report = ctx
+        .report_manager
+        .submit_report(
+            subject_did,
+            subject_uri,
+            subject_cid,
+            ReportReason::from_lexicon(&input.reason_type),
+            input.reason.as_deref(),
+            &auth.did,
+        )
+        .await?;
+
+    Ok(Json(CreateReportOutput {
+        id: report.id,
+        // Echo the caller's token so unknown reason types round-trip
+        reason_type: input.reason_type,
+        reason: report.reason,
+        subject: input.subject,
+        reported_by: report.reported_by,
+        created_at: report.reported_at,
+    })
+    .into_response())
+}
+
+/// Forward a report to the configured moderation service
+///
+/// The upstream status and body are returned unchanged.
+async fn forward_report(
+    ctx: &AppContext,
+    service: &ReportServiceConfig,
+    reporter: &str,
+    input: &CreateReportInput,
+) -> PdsResult<Response> {
+    let url = match &service.url {
+        Some(url) => url.clone(),
+        None => resolve_labeler_endpoint(ctx, &service.did).await?,
+    };
+
+    let token = create_service_jwt(
+        &ctx.signing_keys,
+        &ServiceJwtClaims::new(reporter, &service.did, Some(CREATE_REPORT_NSID)),
+    )
+    .await?;
+
+    let client = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(15))
+        .build()
+        .map_err(|e| PdsError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+    let endpoint = format!("{}/xrpc/{}", url.trim_end_matches('/'), CREATE_REPORT_NSID);
+
+    let response = inject_trace_context(client.post(&endpoint))
+        .bearer_auth(token)
+        .json(input)
+        .send()
+        .await
+        .map_err(|e| {
+            tracing::warn!("Failed to forward report to {}: {}", endpoint, e);
+            PdsError::Upstream(format!("Moderation service unavailable: {}", e))
+        })?;
+
+    let status = StatusCode::from_u16(response.status().as_u16())
+        .unwrap_or(StatusCode::BAD_GATEWAY);
+    let body: Value = response.json().await.map_err(|e| {
+        PdsError::Upstream(format!("Invalid response from moderation service: {}", e))
+    })?;
+
+    if !status.is_success() {
+        tracing::warn!("Moderation service {} rejected report: {} {}", service.did, status, body);
+    }
+
+    Ok((status, Json(body)).into_response())
+}
+
+/// Find the `#atproto_labeler` service endpoint of a moderation service DID
+async fn resolve_labeler_endpoint(ctx: &AppContext, did: &str) -> PdsResult<String> {
+    let doc = ctx.identity_resolver.resolve_did(did).await?;
+    let doc = serde_json::to_value(&doc)
+        .map_err(|e| PdsError::Internal(format!("Invalid DID document: {}", e)))?;
+
+    labeler_endpoint(&doc).ok_or_else(|| {
+        PdsError::DidResolution(format!("No #atproto_labeler service in DID document for {}", did))
+    })
+}
+
+fn labeler_endpoint(doc: &Value) -> Option<String> {
+    doc.get("service")?
+        .as_array()?
+        .iter()
+        .find(|s| {
+            s.get("id")
+                .and_then(Value::as_str)
+                .map_or(false, |id| id.ends_with("#atproto_labeler"))
+        })?
+        .get("serviceEndpoint")?
+        .as_str()
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_subject() {
+        assert_eq!(
+            ReportSubject::parse(&json!({ "$type": "com.atproto.admin.defs#repoRef", "did": "did:plc:abc" }))
+                .unwrap(),
+            ReportSubject::Repo { did: "did:plc:abc".to_string() }
+        );
+        assert_eq!(
+            ReportSubject::parse(&json!({
+                "$type": "com.atproto.repo.strongRef",
+                "uri": "at://did:plc:abc/app.bsky.feed.post/1",
+                "cid": "bafyrei"
+            }))
+            .unwrap(),
+            ReportSubject::Record {
+                uri: "at://did:plc:abc/app.bsky.feed.post/1".to_string(),
+                cid: "bafyrei".to_string()
+            }
+        );
+        assert!(ReportSubject::parse(&json!({ "$type": "com.atproto.repo.strongRef", "uri": "at://x" })).is_err());
+        assert!(ReportSubject::parse(&json!({ "did": "did:plc:abc" })).is_err());
+
+        assert_eq!(
+            ReportReason::from_lexicon("com.atproto.moderation.defs#reasonSpam"),
+            ReportReason::Spam
+        );
+        assert_eq!(
+            ReportReason::from_lexicon("com.atproto.moderation.defs#reasonAppeal"),
+            ReportReason::Other
+        );
+    }
+
+    #[test]
+    fn test_labeler_endpoint() {
+        let doc = json!({
+            "id": "did:plc:mod",
+            "service": [
+                { "id": "#atproto_pds", "type": "AtprotoPersonalDataServer", "serviceEndpoint": "https://pds.example" },
+                { "id": "#atproto_labeler", "type": "AtprotoLabeler", "serviceEndpoint": "https://mod.example" }
+            ]
+        });
+        assert_eq!(labeler_endpoint(&doc), Some("https://mod.example".to_string()));
+        assert_eq!(labeler_endpoint(&json!({ "service": [] })), None);
+    }
+}
@@ -304,7 +304,6 @@ async fn handle_oauth_callback(
         (session.access_token, session.refresh_token)
     } else {
         // Create temporary admin-only JWT tokens
-        use jsonwebtoken::{encode, EncodingKey, Header};
         use serde_json::json;
 
         let now = chrono::Utc::now().timestamp();
@@ -315,12 +314,7 @@ async fn handle_oauth_callback(
             "scope": "admin",
         });
 
-        let access_token = encode(
-            &Header::default(),
-            &claims,
-            &EncodingKey::from_secret(ctx.config.authentication.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| {
+        let access_token = ctx.jwt_keys.sign(&claims).map_err(|e| {
             tracing::error!("Failed to create JWT: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -335,12 +329,7 @@ async fn handle_oauth_callback(
             "scope": "refresh",
         });
 
-        let refresh_token = encode(
-            &Header::default(),
-            &refresh_claims,
-            &EncodingKey::from_secret(ctx.config.authentication.jwt_secret.as_bytes()),
-        )
-        .map_err(|e| {
+        let refresh_token = ctx.jwt_keys.sign(&refresh_claims).map_err(|e| {
             tracing::error!("Failed to create refresh token: {}", e);
             (
                 StatusCode::INTERNAL_SERVER_ERROR,
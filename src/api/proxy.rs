@@ -0,0 +1,672 @@
+/// XRPC proxying (`atproto-proxy`), with read-after-write for the AppView
+///
+/// XRPC methods this PDS does not implement itself are forwarded to another
+/// service with a service auth token issued for the requesting account. The
+/// target is named by an `atproto-proxy: <did>#<service_id>` header and
+/// resolved through the DID document's service entries; without the header,
+/// `app.bsky.*` methods go to the configured AppView
+/// (`PDS_BSKY_APP_VIEW_URL`). Targets are subject to the
+/// `PDS_PROXY_ALLOWED_DIDS` / `PDS_PROXY_DENIED_DIDS` lists and responses are
+/// streamed back.
+///
+/// Only the configured AppView serves anonymous callers. Any other target
+/// needs an authenticated account, and the endpoint its DID document names
+/// must be https and not on a private or loopback address (see
+/// [`crate::net`]) unless `PDS_PROXY_ALLOW_PRIVATE_TARGETS` is set.
+/// Upstream redirects are passed back, not followed.
+///
+/// The AppView reports how far it has indexed the requester's repo in the
+/// `atproto-repo-rev` response header. Profile, feed and thread responses
+/// are patched with records the user wrote locally after that revision, so
+/// their own writes show up immediately; `atproto-upstream-lag` reports the
+/// indexing lag in milliseconds.
+use crate::{
+    actor_store::record_codec::decode_record,
+    auth::OptionalAuthContext,
+    context::AppContext,
+    crypto::service_auth::{create_service_jwt, ServiceJwtClaims},
+    error::{PdsError, PdsResult},
+    net::check_public_url,
+    telemetry::inject_trace_context,
+};
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
+    response::Response,
+    routing::get,
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Request header naming the service to proxy to (`did#service_id`)
+pub const PROXY_HEADER: &str = "atproto-proxy";
+
+/// Response header carrying the requester's repo revision as indexed upstream
+pub const REPO_REV_HEADER: &str = "atproto-repo-rev";
+
+/// Response header reporting read-after-write lag in milliseconds
+pub const UPSTREAM_LAG_HEADER: &str = "atproto-upstream-lag";
+
+/// Request headers passed through to the upstream service
+const FORWARDED_REQUEST_HEADERS: &[&str] = &[
+    "accept-language",
+    "atproto-accept-labelers",
+    "content-type",
+    "if-none-match",
+    "x-bsky-topics",
+];
+
+/// Response headers passed back to the client
+const FORWARDED_RESPONSE_HEADERS: &[&str] = &[
+    "content-type",
+    "content-language",
+    "atproto-content-labelers",
+    "atproto-repo-rev",
+    "cache-control",
+    "etag",
+    "retry-after",
+];
+
+/// Service id of the AppView in `atproto-proxy` headers
+const APP_VIEW_SERVICE_ID: &str = "bsky_appview";
+
+/// AppView methods whose responses get local writes overlaid
+const READ_AFTER_WRITE_METHODS: &[&str] = &[
+    "app.bsky.actor.getProfile",
+    "app.bsky.actor.getProfiles",
+    "app.bsky.feed.getTimeline",
+    "app.bsky.feed.getAuthorFeed",
+    "app.bsky.feed.getPostThread",
+];
+
+const POST_COLLECTION: &str = "app.bsky.feed.post";
+const PROFILE_COLLECTION: &str = "app.bsky.actor.profile";
+
+/// Methods that are never proxied, even with an explicit `atproto-proxy`
+///
+/// These act on the account itself and must only be handled by its PDS.
+const PROTECTED_METHODS: &[&str] = &[
+    "com.atproto.identity.requestPlcOperationSignature",
+    "com.atproto.identity.signPlcOperation",
+    "com.atproto.server.activateAccount",
+    "com.atproto.server.createAccount",
+    "com.atproto.server.createAppPassword",
+    "com.atproto.server.deactivateAccount",
+    "com.atproto.server.deleteAccount",
+    "com.atproto.server.getServiceAuth",
+    "com.atproto.server.requestAccountDelete",
+    "com.atproto.server.requestEmailUpdate",
+    "com.atproto.server.requestPasswordReset",
+    "com.atproto.server.resetPassword",
+    "com.atproto.server.updateEmail",
+];
+
+/// Build proxy routes
+///
+/// Matches any XRPC method; explicitly routed methods take precedence.
+pub fn routes() -> Router<AppContext> {
+    Router::new().route("/xrpc/:method", get(proxy_xrpc).post(proxy_xrpc))
+}
+
+/// Resolved upstream for a proxied call
+#[derive(Debug, Clone, PartialEq)]
+struct ProxyTarget {
+    /// Service DID (the service auth audience)
+    did: String,
+    /// Base URL of the service
+    url: String,
+    /// Whether this is the configured AppView (enables read-after-write)
+    is_app_view: bool,
+}
+
+/// Forward an XRPC call this PDS doesn't serve itself
+async fn proxy_xrpc(
+    State(ctx): State<AppContext>,
+    Path(nsid): Path<String>,
+    auth: OptionalAuthContext,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Bytes,
+) -> PdsResult<Response> {
+    let target = proxy_target(&ctx, &nsid, &headers, auth.auth.is_some()).await?;
+
+    let mut url = format!("{}/xrpc/{}", target.url.trim_end_matches('/'), nsid);
+    if let Some(query) = uri.query() {
+        url.push('?');
+        url.push_str(query);
+    }
+
+    let mut request = inject_trace_context(ctx.proxy_client.request(method.clone(), &url));
+    for name in FORWARDED_REQUEST_HEADERS {
+        if let Some(value) = headers.get(*name) {
+            request = request.header(*name, value);
+        }
+    }
+    if let Some(auth) = &auth.auth {
+        let token = create_service_jwt(
+            &ctx.signing_keys,
+            &ServiceJwtClaims::new(&auth.did, &target.did, Some(&nsid)),
+        )
+        .await?;
+        request = request.bearer_auth(token);
+    }
+    if method == Method::POST {
+        request = request.body(body);
+    }
+
+    // The timeout covers the response headers; streamed bodies are only
+    // bounded by the client going away
+    let timeout = Duration::from_secs(ctx.config.proxy.timeout_secs);
+    let upstream = match tokio::time::timeout(timeout, request.send()).await {
+        Ok(Ok(upstream)) => upstream,
+        Ok(Err(e)) => {
+            tracing::warn!("Proxied request to {} failed: {}", url, e);
+            return Err(PdsError::Upstream(format!("Upstream request failed: {}", e)));
+        }
+        Err(_) => {
+            tracing::warn!("Proxied request to {} timed out", url);
+            return Err(PdsError::UpstreamTimeout("Upstream request timed out".to_string()));
+        }
+    };
+
+    let mut status = upstream.status();
+    let mut response_headers = HeaderMap::new();
+    for name in FORWARDED_RESPONSE_HEADERS {
+        if let Some(value) = upstream.headers().get(*name) {
+            response_headers.insert(HeaderName::from_static(name), value.clone());
+        }
+    }
+
+    // Read-after-write needs the whole body; everything else is streamed
+    let overlay = match &auth.auth {
+        Some(auth) if target.is_app_view && READ_AFTER_WRITE_METHODS.contains(&nsid.as_str()) => Some(auth),
+        _ => None,
+    };
+    let body = match overlay {
+        None => Body::from_stream(upstream.bytes_stream()),
+        Some(auth) => {
+            let bytes = tokio::time::timeout(timeout, upstream.bytes())
+                .await
+                .map_err(|_| PdsError::UpstreamTimeout("Upstream response timed out".to_string()))?
+                .map_err(|e| PdsError::Upstream(format!("Failed to read upstream response: {}", e)))?;
+
+            let params = query_params(uri.query().unwrap_or(""));
+            let upstream_rev = response_headers
+                .get(REPO_REV_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(String::from);
+
+            match read_after_write(&ctx, &auth.did, &nsid, &params, upstream_rev.as_deref(), status, &bytes).await {
+                Ok(Some(patched)) => {
+                    status = StatusCode::OK;
+                    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+                    if let Some(lag) = patched.lag_ms {
+                        response_headers.insert(HeaderName::from_static(UPSTREAM_LAG_HEADER), HeaderValue::from(lag));
+                    }
+                    Body::from(patched.body.to_string())
+                }
+                Ok(None) => Body::from(bytes),
+                Err(e) => {
+                    tracing::warn!("Read-after-write for {} failed: {}", nsid, e);
+                    Body::from(bytes)
+                }
+            }
+        }
+    };
+
+    let mut response = Response::new(body);
+    *response.status_mut() = status;
+    *response.headers_mut() = response_headers;
+    Ok(response)
+}
+
+/// Pick the upstream for a method, honoring `atproto-proxy`
+///
+/// Targets other than the AppView are only resolved for `authenticated`
+/// callers.
+async fn proxy_target(
+    ctx: &AppContext,
+    nsid: &str,
+    headers: &HeaderMap,
+    authenticated: bool,
+) -> PdsResult<ProxyTarget> {
+    let not_implemented = || PdsError::NotFound(format!("Method not implemented: {}", nsid));
+    // Admin and account management methods are served only by this PDS
+    if nsid.starts_with("com.atproto.admin.") || PROTECTED_METHODS.contains(&nsid) {
+        return Err(not_implemented());
+    }
+    let app_view = ctx.config.bsky_app_view.as_ref();
+
+    let Some(value) = headers.get(PROXY_HEADER) else {
+        return match app_view {
+            Some(app_view) if nsid.starts_with("app.bsky.") => Ok(ProxyTarget {
+                did: app_view.did.clone(),
+                url: app_view.url.clone(),
+                is_app_view: true,
+            }),
+            _ => Err(not_implemented()),
+        };
+    };
+
+    let value = value
+        .to_str()
+        .map_err(|_| PdsError::Validation("Invalid atproto-proxy header".to_string()))?;
+    let (did, service_id) = parse_proxy_header(value)?;
+
+    if !ctx.config.proxy.permits(did) {
+        return Err(PdsError::Authorization(format!("Proxying to {} is not allowed", did)));
+    }
+
+    // The configured AppView doesn't need a DID lookup
+    if let Some(app_view) = app_view.filter(|a| a.did == did && service_id == APP_VIEW_SERVICE_ID) {
+        return Ok(ProxyTarget {
+            did: app_view.did.clone(),
+            url: app_view.url.clone(),
+            is_app_view: true,
+        });
+    }
+
+    if !authenticated {
+        return Err(PdsError::Authentication(format!(
+            "Proxying to {} requires authentication",
+            did
+        )));
+    }
+
+    let doc = ctx.identity_resolver.resolve_did(did).await?;
+    let doc = serde_json::to_value(&doc)
+        .map_err(|e| PdsError::Internal(format!("Invalid DID document: {}", e)))?;
+    let url = service_endpoint(&doc, service_id, ctx.config.proxy.allow_private_targets).ok_or_else(|| {
+        PdsError::Validation(format!("No #{} service in DID document for {}", service_id, did))
+    })?;
+    check_public_url(&url, "Proxy service endpoint", ctx.config.proxy.allow_private_targets)?;
+
+    Ok(ProxyTarget {
+        did: did.to_string(),
+        url,
+        is_app_view: false,
+    })
+}
+
+/// Split an `atproto-proxy` value into DID and service id
+fn parse_proxy_header(value: &str) -> PdsResult<(&str, &str)> {
+    match value.split_once('#') {
+        Some((did, service_id)) if did.starts_with("did:") && !service_id.is_empty() => Ok((did, service_id)),
+        _ => Err(PdsError::Validation(
+            "atproto-proxy must be <did>#<service_id>".to_string(),
+        )),
+    }
+}
+
+/// Find the https endpoint of a DID document service entry (or http, with
+/// `allow_http`)
+fn service_endpoint(doc: &Value, service_id: &str, allow_http: bool) -> Option<String> {
+    let did = doc.get("id").and_then(Value::as_str).unwrap_or("");
+    let relative = format!("#{}", service_id);
+    let absolute = format!("{}#{}", did, service_id);
+
+    doc.get("service")?
+        .as_array()?
+        .iter()
+        .find(|s| {
+            s.get("id")
+                .and_then(Value::as_str)
+                .map_or(false, |id| id == relative || id == absolute)
+        })?
+        .get("serviceEndpoint")?
+        .as_str()
+        .filter(|url| url.starts_with("https://") || (allow_http && url.starts_with("http://")))
+        .map(String::from)
+}
+
+/// Parse a query string into (name, value) pairs, keeping repeated names
+fn query_params(query: &str) -> Vec<(String, String)> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let decode = |s: &str| {
+                urlencoding::decode(&s.replace('+', " "))
+                    .map(|s| s.into_owned())
+                    .unwrap_or_else(|_| s.to_string())
+            };
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+fn param<'a>(params: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    params.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+}
+
+/// A record written locally but possibly not yet indexed upstream
+#[derive(Debug, Clone)]
+struct LocalRecord {
+    uri: String,
+    cid: String,
+    value: Value,
+    indexed_at: DateTime<Utc>,
+}
+
+/// Writes the AppView has not seen yet
+#[derive(Debug, Default)]
+struct LocalWrites {
+    handle: String,
+    profile: Option<LocalRecord>,
+    /// Newest first
+    posts: Vec<LocalRecord>,
+}
+
+impl LocalWrites {
+    fn is_empty(&self) -> bool {
+        self.profile.is_none() && self.posts.is_empty()
+    }
+
+    /// Milliseconds since the oldest unindexed write
+    fn lag_ms(&self) -> Option<i64> {
+        self.profile
+            .iter()
+            .chain(self.posts.iter())
+            .map(|r| r.indexed_at)
+            .min()
+            .map(|oldest| (Utc::now() - oldest).num_milliseconds().max(0))
+    }
+
+    fn is_self(&self, did: &str, actor: &str) -> bool {
+        actor == did || actor.eq_ignore_ascii_case(&self.handle)
+    }
+}
+
+/// Patched response body
+struct Patched {
+    body: Value,
+    lag_ms: Option<i64>,
+}
+
+/// Overlay local writes onto a proxied response, if any apply
+async fn read_after_write(
+    ctx: &AppContext,
+    did: &str,
+    nsid: &str,
+    params: &[(String, String)],
+    upstream_rev: Option<&str>,
+    status: reqwest::StatusCode,
+    body: &[u8],
+) -> PdsResult<Option<Patched>> {
+    // Without a revision from upstream, only a missing own post can be patched
+    let since = match (upstream_rev, nsid) {
+        (Some(rev), _) => rev.to_string(),
+        (None, "app.bsky.feed.getPostThread") => String::new(),
+        (None, _) => return Ok(None),
+    };
+
+    let local = local_writes(ctx, did, &since).await?;
+    if local.is_empty() {
+        return Ok(None);
+    }
+    let author = author_view(did, &local);
+
+    let body = if status.is_success() {
+        let mut body: Value = match serde_json::from_slice(body) {
+            Ok(body) => body,
+            Err(_) => return Ok(None),
+        };
+        let changed = match nsid {
+            "app.bsky.actor.getProfile" => {
+                let is_self = param(params, "actor").map_or(false, |a| local.is_self(did, a));
+                is_self && local.profile.as_ref().map_or(false, |p| apply_profile(&mut body, &p.value))
+            }
+            "app.bsky.actor.getProfiles" => match (&local.profile, body.get_mut("profiles").and_then(Value::as_array_mut)) {
+                (Some(profile), Some(profiles)) => profiles
+                    .iter_mut()
+                    .filter(|p| p.get("did").and_then(Value::as_str) == Some(did))
+                    .fold(false, |changed, p| apply_profile(p, &profile.value) || changed),
+                _ => false,
+            },
+            "app.bsky.feed.getTimeline" => insert_posts(&mut body, &author, &local.posts),
+            "app.bsky.feed.getAuthorFeed" => {
+                let is_self = param(params, "actor").map_or(false, |a| local.is_self(did, a));
+                // Cursors page into older posts; only the first page gets new ones
+                is_self && param(params, "cursor").is_none() && insert_posts(&mut body, &author, &local.posts)
+            }
+            _ => false,
+        };
+        if !changed {
+            return Ok(None);
+        }
+        body
+    } else if nsid == "app.bsky.feed.getPostThread" {
+        // The AppView doesn't know our post yet
+        let Some(uri) = param(params, "uri") else { return Ok(None) };
+        match local.posts.iter().find(|p| p.uri == uri) {
+            Some(post) => json!({
+                "thread": {
+                    "$type": "app.bsky.feed.defs#threadViewPost",
+                    "post": post_view(&author, post),
+                    "replies": [],
+                }
+            }),
+            None => return Ok(None),
+        }
+    } else {
+        return Ok(None);
+    };
+
+    Ok(Some(Patched {
+        body,
+        lag_ms: local.lag_ms(),
+    }))
+}
+
+/// Load profile and post records written after `since`
+async fn local_writes(ctx: &AppContext, did: &str, since: &str) -> PdsResult<LocalWrites> {
+    let mut local = LocalWrites::default();
+    if !ctx.actor_store.exists(did).await {
+        return Ok(local);
+    }
+
+    for record in ctx.actor_store.list_records_since(did, since).await? {
+        if record.collection != POST_COLLECTION && record.collection != PROFILE_COLLECTION {
+            continue;
+        }
+        let Some(block) = ctx.actor_store.get_block(did, &record.cid).await? else {
+            continue;
+        };
+        let Ok(value) = decode_record(&block) else {
+            continue;
+        };
+
+        let local_record = LocalRecord {
+            uri: record.uri,
+            cid: record.cid,
+            value,
+            indexed_at: record.indexed_at,
+        };
+        if record.collection == PROFILE_COLLECTION {
+            local.profile = Some(local_record);
+        } else {
+            local.posts.push(local_record);
+        }
+    }
+
+    if local.is_empty() {
+        return Ok(local);
+    }
+    local.posts.reverse();
+    local.handle = ctx.account_manager.get_account(did).await?.handle;
+
+    Ok(local)
+}
+
+/// Minimal profileViewBasic for the requester
+fn author_view(did: &str, local: &LocalWrites) -> Value {
+    let mut author = json!({ "did": did, "handle": local.handle });
+    if let Some(name) = local
+        .profile
+        .as_ref()
+        .and_then(|p| p.value.get("displayName"))
+    {
+        author["displayName"] = name.clone();
+    }
+    author
+}
+
+/// Apply a local profile record to a profile view, returning whether it changed
+fn apply_profile(view: &mut Value, profile: &Value) -> bool {
+    let Some(view) = view.as_object_mut() else { return false };
+
+    for field in ["displayName", "description"] {
+        match profile.get(field) {
+            Some(value) => {
+                view.insert(field.to_string(), value.clone());
+            }
+            None => {
+                view.remove(field);
+            }
+        }
+    }
+    true
+}
+
+/// postView for a local post (engagement counts start at zero)
+fn post_view(author: &Value, post: &LocalRecord) -> Value {
+    json!({
+        "uri": post.uri,
+        "cid": post.cid,
+        "author": author,
+        "record": post.value,
+        "replyCount": 0,
+        "repostCount": 0,
+        "likeCount": 0,
+        "quoteCount": 0,
+        "indexedAt": post.indexed_at.to_rfc3339(),
+        "labels": [],
+    })
+}
+
+/// Prepend local posts missing from a feed, returning whether any were added
+fn insert_posts(body: &mut Value, author: &Value, posts: &[LocalRecord]) -> bool {
+    let Some(feed) = body.get_mut("feed").and_then(Value::as_array_mut) else {
+        return false;
+    };
+
+    let missing: Vec<Value> = posts
+        .iter()
+        .filter(|post| {
+            !feed.iter().any(|item| {
+                item.get("post").and_then(|p| p.get("uri")).and_then(Value::as_str) == Some(post.uri.as_str())
+            })
+        })
+        .map(|post| json!({ "post": post_view(author, post) }))
+        .collect();
+
+    if missing.is_empty() {
+        return false;
+    }
+    feed.splice(0..0, missing);
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_post(rkey: &str, text: &str) -> LocalRecord {
+        LocalRecord {
+            uri: format!("at://did:plc:me/app.bsky.feed.post/{}", rkey),
+            cid: format!("bafy{}", rkey),
+            value: json!({ "$type": POST_COLLECTION, "text": text, "createdAt": "2025-01-01T00:00:00Z" }),
+            indexed_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_parse_proxy_header() {
+        assert_eq!(
+            parse_proxy_header("did:web:api.bsky.chat#bsky_chat").unwrap(),
+            ("did:web:api.bsky.chat", "bsky_chat")
+        );
+        assert!(parse_proxy_header("did:web:api.bsky.chat").is_err());
+        assert!(parse_proxy_header("did:web:api.bsky.chat#").is_err());
+        assert!(parse_proxy_header("api.bsky.chat#bsky_chat").is_err());
+    }
+
+    #[test]
+    fn test_service_endpoint() {
+        let doc = json!({
+            "id": "did:web:feed.example",
+            "service": [
+                { "id": "#bsky_fg", "type": "BskyFeedGenerator", "serviceEndpoint": "https://feed.example" },
+                { "id": "did:web:feed.example#bsky_chat", "type": "BskyChatService", "serviceEndpoint": "https://chat.example" },
+                { "id": "#odd", "type": "Other", "serviceEndpoint": "ftp://feed.example" },
+                { "id": "#plain", "type": "Other", "serviceEndpoint": "http://feed.example" }
+            ]
+        });
+        assert_eq!(service_endpoint(&doc, "bsky_fg", false), Some("https://feed.example".to_string()));
+        assert_eq!(service_endpoint(&doc, "bsky_chat", false), Some("https://chat.example".to_string()));
+        assert_eq!(service_endpoint(&doc, "odd", false), None);
+        assert_eq!(service_endpoint(&doc, "missing", false), None);
+        assert_eq!(service_endpoint(&doc, "plain", false), None);
+        assert_eq!(service_endpoint(&doc, "plain", true), Some("http://feed.example".to_string()));
+    }
+
+    #[test]
+    fn test_query_params() {
+        assert_eq!(
+            query_params("actors=alice.test&actors=did%3Aplc%3Abob&q=hello+world"),
+            vec![
+                ("actors".to_string(), "alice.test".to_string()),
+                ("actors".to_string(), "did:plc:bob".to_string()),
+                ("q".to_string(), "hello world".to_string()),
+            ]
+        );
+        assert!(query_params("").is_empty());
+    }
+
+    #[test]
+    fn test_insert_posts_skips_indexed() {
+        let author = json!({ "did": "did:plc:me", "handle": "me.test" });
+        let posts = vec![local_post("3", "newest"), local_post("2", "already indexed")];
+        let mut body = json!({
+            "feed": [{ "post": { "uri": "at://did:plc:me/app.bsky.feed.post/2" } }],
+            "cursor": "abc"
+        });
+
+        assert!(insert_posts(&mut body, &author, &posts));
+        let feed = body["feed"].as_array().unwrap();
+        assert_eq!(feed.len(), 2);
+        assert_eq!(feed[0]["post"]["record"]["text"], "newest");
+        assert_eq!(feed[0]["post"]["author"]["handle"], "me.test");
+
+        // Nothing left to add
+        assert!(!insert_posts(&mut body, &author, &posts));
+    }
+
+    #[test]
+    fn test_apply_profile() {
+        let mut view = json!({ "did": "did:plc:me", "displayName": "Old", "description": "old bio", "followersCount": 3 });
+        assert!(apply_profile(&mut view, &json!({ "displayName": "New" })));
+        assert_eq!(view["displayName"], "New");
+        assert!(view.get("description").is_none());
+        assert_eq!(view["followersCount"], 3);
+    }
+
+    #[test]
+    fn test_lag() {
+        let mut post = local_post("1", "hi");
+        post.indexed_at = Utc::now() - chrono::Duration::seconds(2);
+        let local = LocalWrites {
+            handle: "me.test".to_string(),
+            profile: None,
+            posts: vec![post],
+        };
+        assert!(local.lag_ms().unwrap() >= 2000);
+        assert!(local.is_self("did:plc:me", "ME.test"));
+        assert!(LocalWrites::default().lag_ms().is_none());
+    }
+}
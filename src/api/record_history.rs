@@ -0,0 +1,139 @@
+/// Record version endpoints
+///
+/// - app.aurora.repo.listRecordVersions: a record's kept previous versions
+/// - app.aurora.repo.rollbackRecord: write a kept version back as the record
+///
+/// Versions are only kept with `PDS_RECORD_HISTORY_ENABLED`. Both endpoints
+/// act on the caller's own repo, and accept app passwords, so a profile
+/// vandalised through a leaked password can be restored from another one.
+/// A rollback is an ordinary write: it goes through the content policy and
+/// write hooks and is sequenced as a new commit.
+
+use crate::{
+    actor_store::{
+        history::{record_version, record_versions, RecordVersion},
+        RepositoryManager,
+    },
+    api::middleware,
+    context::AppContext,
+    error::{PdsError, PdsResult},
+};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+/// Build record version routes
+pub fn routes() -> Router<AppContext> {
+    Router::new()
+        .route("/xrpc/app.aurora.repo.listRecordVersions", get(list_record_versions))
+        .route("/xrpc/app.aurora.repo.rollbackRecord", post(rollback_record))
+}
+
+/// Caller's DID, if `repo` is their own repo
+async fn own_repo(ctx: &AppContext, headers: HeaderMap, repo: &str) -> PdsResult<String> {
+    if !ctx.config.record_history.enabled {
+        return Err(PdsError::NotFound("Record history is not enabled on this server".to_string()));
+    }
+
+    let session = middleware::require_auth(State(ctx.clone()), headers).await?;
+    if repo != session.did {
+        return Err(PdsError::Authorization(
+            "Cannot access record versions in another user's repo".to_string(),
+        ));
+    }
+    Ok(session.did)
+}
+
+#[derive(Debug, Deserialize)]
+struct ListRecordVersionsQuery {
+    repo: String,
+    collection: String,
+    rkey: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ListRecordVersionsResponse {
+    uri: String,
+    /// Current CID, absent if the record is deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cid: Option<String>,
+    /// Newest first
+    versions: Vec<RecordVersion>,
+}
+
+/// List a record's previous versions
+async fn list_record_versions(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(query): Query<ListRecordVersionsQuery>,
+) -> PdsResult<Json<ListRecordVersionsResponse>> {
+    let did = own_repo(&ctx, headers, &query.repo).await?;
+    let uri = format!("at://{}/{}/{}", did, query.collection, query.rkey);
+
+    let cid = ctx.actor_store.get_record(&did, &uri).await?.map(|record| record.cid);
+    let versions = record_versions(&ctx.actor_store, &did, &uri).await?;
+
+    Ok(Json(ListRecordVersionsResponse { uri, cid, versions }))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RollbackRecordRequest {
+    repo: String,
+    collection: String,
+    rkey: String,
+    /// CID of the kept version to restore
+    cid: String,
+    /// CID the record must currently have
+    swap_record: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct RollbackRecordResponse {
+    uri: String,
+    /// CID of the restored record
+    cid: String,
+    /// The commit that restored it
+    commit: CommitMeta,
+}
+
+#[derive(Debug, Serialize)]
+struct CommitMeta {
+    cid: String,
+    rev: String,
+}
+
+/// Write a kept version back as the record, recreating it if deleted
+async fn rollback_record(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<RollbackRecordRequest>,
+) -> PdsResult<Json<RollbackRecordResponse>> {
+    let did = own_repo(&ctx, headers, &req.repo).await?;
+    let uri = format!("at://{}/{}/{}", did, req.collection, req.rkey);
+    let value = record_version(&ctx.actor_store, &did, &uri, &req.cid).await?;
+
+    let repo_mgr = RepositoryManager::with_sequencer(did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone())
+        .with_content_policy(Some(ctx.content_policy.clone()))
+        .with_write_hooks(Some(ctx.write_hooks.clone()))
+        .with_blob_store(Some(ctx.blob_store.clone()));
+
+    let signer = ctx.signing_keys.commit_signer(&did).await?;
+
+    // The kept version was valid when written, but lexicons may have moved on
+    let (commit_cid, rev) = repo_mgr
+        .update_record(&req.collection, &req.rkey, value, None, req.swap_record.as_deref(), None, signer)
+        .await?;
+    tracing::info!(did = %did, uri = %uri, restored = %req.cid, "record_rolled_back");
+
+    // Re-encoding a kept version gives back the same block, and so its CID
+    Ok(Json(RollbackRecordResponse {
+        uri,
+        cid: req.cid,
+        commit: CommitMeta { cid: commit_cid, rev },
+    }))
+}
@@ -0,0 +1,140 @@
+/// Read replica request routing
+///
+/// On an instance started with `PDS_REPLICA_PRIMARY_URL`, blob, image and
+/// sync reads (getBlob, getRepo, getRecord, subscribeRepos, ...) are served
+/// from the local copy of the replicated storage. Every other request is
+/// forwarded to the primary unchanged (method, path, headers and body), so
+/// clients can point at the nearest region and still log in and write.
+/// The primary's response is streamed back as-is.
+use crate::{
+    context::AppContext,
+    error::{PdsError, PdsResult},
+    telemetry::inject_trace_context,
+};
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, Method},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::time::Duration;
+
+/// XRPC namespaces a replica answers from its own storage
+const LOCAL_XRPC_PREFIXES: &[&str] = &["com.atproto.sync.", "app.aurora.sync."];
+
+/// Non-XRPC paths that are still forwarded (logins and resumable uploads)
+const FORWARDED_PATHS: &[&str] = &["/oauth/", "/uploads"];
+
+/// Connection-level headers that must not be copied between hops
+const HOP_BY_HOP_HEADERS: &[header::HeaderName] = &[
+    header::CONNECTION,
+    header::HOST,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// Serve replicated reads locally and forward everything else to the primary
+pub async fn replica_routing(State(ctx): State<AppContext>, request: Request, next: Next) -> Response {
+    let primary = match ctx.config.replica.primary_url.as_deref() {
+        Some(primary) if !serves_locally(request.method(), request.uri().path()) => primary.to_string(),
+        _ => return next.run(request).await,
+    };
+
+    match forward(&ctx, &primary, request).await {
+        Ok(response) => response,
+        Err(e) => e.into_response(),
+    }
+}
+
+/// Whether a replica handles the request itself
+fn serves_locally(method: &Method, path: &str) -> bool {
+    if method != Method::GET && method != Method::HEAD {
+        return false;
+    }
+
+    match path.strip_prefix("/xrpc/") {
+        Some(nsid) => LOCAL_XRPC_PREFIXES.iter().any(|prefix| nsid.starts_with(prefix)),
+        None => !FORWARDED_PATHS.iter().any(|prefix| path.starts_with(prefix)),
+    }
+}
+
+/// Send the request to the primary and stream its response back
+async fn forward(ctx: &AppContext, primary: &str, request: Request) -> PdsResult<Response> {
+    let (parts, body) = request.into_parts();
+    let url = match parts.uri.path_and_query() {
+        Some(path) => format!("{}{}", primary, path),
+        None => primary.to_string(),
+    };
+
+    // The timeout covers the response headers; streamed bodies are only
+    // bounded by the client going away
+    let client = reqwest::Client::builder()
+        .connect_timeout(Duration::from_secs(ctx.config.proxy.connect_timeout_secs))
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| PdsError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+    let mut headers = without_hop_by_hop(&parts.headers);
+    // Keep the original host so virtual hosts resolve on the primary
+    if let (false, Some(host)) = (headers.contains_key("x-forwarded-host"), parts.headers.get(header::HOST)) {
+        headers.insert("x-forwarded-host", host.clone());
+    }
+
+    let mut request = inject_trace_context(client.request(parts.method.clone(), &url).headers(headers));
+    if parts.method != Method::GET && parts.method != Method::HEAD {
+        request = request.body(reqwest::Body::wrap_stream(body.into_data_stream()));
+    }
+
+    let timeout = Duration::from_secs(ctx.config.proxy.timeout_secs);
+    let upstream = match tokio::time::timeout(timeout, request.send()).await {
+        Ok(Ok(upstream)) => upstream,
+        Ok(Err(e)) => {
+            tracing::warn!("Forwarding {} {} to the primary failed: {}", parts.method, url, e);
+            return Err(PdsError::Upstream(format!("Primary request failed: {}", e)));
+        }
+        Err(_) => {
+            tracing::warn!("Forwarding {} {} to the primary timed out", parts.method, url);
+            return Err(PdsError::UpstreamTimeout("Primary request timed out".to_string()));
+        }
+    };
+
+    let status = upstream.status();
+    let headers = without_hop_by_hop(upstream.headers());
+    let mut response = Response::new(Body::from_stream(upstream.bytes_stream()));
+    *response.status_mut() = status;
+    *response.headers_mut() = headers;
+    Ok(response)
+}
+
+fn without_hop_by_hop(headers: &HeaderMap) -> HeaderMap {
+    let mut headers = headers.clone();
+    for name in HOP_BY_HOP_HEADERS {
+        headers.remove(name);
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_serves_locally() {
+        assert!(serves_locally(&Method::GET, "/xrpc/com.atproto.sync.getBlob"));
+        assert!(serves_locally(&Method::GET, "/xrpc/com.atproto.sync.subscribeRepos"));
+        assert!(serves_locally(&Method::GET, "/img/avatar/did:plc:abc/bafkrei"));
+        assert!(serves_locally(&Method::HEAD, "/blob/bafkrei"));
+        assert!(serves_locally(&Method::GET, "/.well-known/did.json"));
+
+        assert!(!serves_locally(&Method::POST, "/xrpc/com.atproto.repo.createRecord"));
+        assert!(!serves_locally(&Method::POST, "/xrpc/com.atproto.sync.requestCrawl"));
+        assert!(!serves_locally(&Method::GET, "/xrpc/com.atproto.server.getSession"));
+        assert!(!serves_locally(&Method::GET, "/xrpc/app.bsky.feed.getTimeline"));
+        assert!(!serves_locally(&Method::GET, "/oauth/admin/login"));
+        assert!(!serves_locally(&Method::PATCH, "/uploads/abc"));
+    }
+}
@@ -1,13 +1,19 @@
 /// com.atproto.repo.* endpoints
 use crate::{
-    actor_store::{RepositoryManager, WriteOp},
-    api::{labels::LabelView, middleware},
+    actor_store::{
+        verify::{account_signing_key, verify_repo},
+        RepositoryManager, WriteOp,
+    },
+    api::{conditional, labels::LabelView, middleware},
+    car::read_car,
     context::AppContext,
     error::{PdsError, PdsResult},
 };
 use axum::{
+    body::Bytes,
     extract::{Query, State},
-    http::HeaderMap,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
@@ -23,6 +29,7 @@ pub fn routes() -> Router<AppContext> {
         .route("/xrpc/com.atproto.repo.listRecords", get(list_records))
         .route("/xrpc/com.atproto.repo.describeRepo", get(describe_repo))
         .route("/xrpc/com.atproto.repo.applyWrites", post(apply_writes))
+        .route("/xrpc/com.atproto.repo.importRepo", post(import_repo))
 }
 
 /// Request to create a record
@@ -175,19 +182,6 @@ struct ApplyWritesRequest {
     swap_commit: Option<String>,
 }
 
-/// Creates a proper signing function using the repository's stored private key
-///
-/// Uses PlcSigner with the repo_signing_key from configuration to sign repository commits
-fn create_repo_signer(
-    repo_key_hex: &str,
-) -> impl Fn(&[u8; 32]) -> Result<Vec<u8>, atproto::repo::RepoError> + '_ {
-    move |hash: &[u8; 32]| {
-        let signer = crate::crypto::plc::PlcSigner::from_hex(repo_key_hex)
-            .map_err(|e| atproto::repo::RepoError::Signing(format!("Failed to create signer: {}", e)))?;
-        Ok(signer.sign(hash))
-    }
-}
-
 /// Create a new record
 async fn create_record(
     State(ctx): State<AppContext>,
@@ -218,15 +212,24 @@ async fn create_record(
         session.did.clone(),
         (*ctx.actor_store).clone(),
         ctx.sequencer.clone(),
-    );
+    )
+    .with_content_policy(Some(ctx.content_policy.clone()))
+    .with_write_hooks(Some(ctx.write_hooks.clone()))
+    .with_blob_store(Some(ctx.blob_store.clone()));
 
-    // Create signer from repo key
-    let signer = create_repo_signer(&ctx.config.authentication.repo_signing_key);
+    let signer = ctx.signing_keys.commit_signer(&session.did).await?;
 
     // Create the record
     tracing::debug!("create_record: Calling repo_mgr.create_record");
     let (uri, cid, _rev) = repo_mgr
-        .create_record(&req.collection, req.rkey.as_deref(), req.record, req.validate, signer)
+        .create_record(
+            &req.collection,
+            req.rkey.as_deref(),
+            req.record,
+            req.validate,
+            req.swap_commit.as_deref(),
+            signer,
+        )
         .await
         .map_err(|e| {
             tracing::error!("create_record: Failed to create record: {}", e);
@@ -254,14 +257,24 @@ async fn put_record(
     }
 
     // Create repository manager
-    let repo_mgr = RepositoryManager::with_sequencer(session.did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone());
+    let repo_mgr = RepositoryManager::with_sequencer(session.did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone())
+        .with_content_policy(Some(ctx.content_policy.clone()))
+        .with_write_hooks(Some(ctx.write_hooks.clone()))
+        .with_blob_store(Some(ctx.blob_store.clone()));
 
-    // Create signer from repo key
-    let signer = create_repo_signer(&ctx.config.authentication.repo_signing_key);
+    let signer = ctx.signing_keys.commit_signer(&session.did).await?;
 
     // Update the record
     let (cid, _rev) = repo_mgr
-        .update_record(&req.collection, &req.rkey, req.record, req.validate, signer)
+        .update_record(
+            &req.collection,
+            &req.rkey,
+            req.record,
+            req.validate,
+            req.swap_record.as_deref(),
+            req.swap_commit.as_deref(),
+            signer,
+        )
         .await?;
 
     let uri = format!("at://{}/{}/{}", session.did, req.collection, req.rkey);
@@ -286,24 +299,34 @@ async fn delete_record(
     }
 
     // Create repository manager
-    let repo_mgr = RepositoryManager::with_sequencer(session.did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone());
+    let repo_mgr = RepositoryManager::with_sequencer(session.did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone())
+        .with_write_hooks(Some(ctx.write_hooks.clone()))
+        .with_blob_store(Some(ctx.blob_store.clone()));
 
-    // Create signer from repo key
-    let signer = create_repo_signer(&ctx.config.authentication.repo_signing_key);
+    let signer = ctx.signing_keys.commit_signer(&session.did).await?;
 
     // Delete the record
     repo_mgr
-        .delete_record(&req.collection, &req.rkey, signer)
+        .delete_record(
+            &req.collection,
+            &req.rkey,
+            req.swap_record.as_deref(),
+            req.swap_commit.as_deref(),
+            signer,
+        )
         .await?;
 
     Ok(Json(serde_json::json!({})))
 }
 
 /// Get a record
+///
+/// The ETag is the record CID, plus a digest of its labels when it has any.
 async fn get_record(
     State(ctx): State<AppContext>,
     Query(query): Query<GetRecordQuery>,
-) -> PdsResult<Json<GetRecordResponse>> {
+    headers: HeaderMap,
+) -> PdsResult<Response> {
     // Get the DID (could be handle resolution in the future)
     let did = &query.repo;
 
@@ -326,16 +349,29 @@ async fn get_record(
             let record_value = value.get("value").cloned().unwrap_or(serde_json::Value::Null);
 
             // Fetch labels for this record
-            let labels = ctx.label_manager.get_labels(&uri).await
+            let labels: Option<Vec<LabelView>> = ctx.label_manager.get_labels(&uri).await
                 .ok()
                 .map(|lbls| lbls.into_iter().map(LabelView::from).collect());
 
-            Ok(Json(GetRecordResponse {
-                uri,
-                cid,
-                value: record_value,
-                labels,
-            }))
+            let etag = match labels.as_deref() {
+                Some(labels) if !labels.is_empty() => {
+                    conditional::etag(&format!("{}-{}", cid, conditional::digest(&labels)))
+                }
+                _ => conditional::etag(&cid),
+            };
+            let cache_control = conditional::cache_control(ctx.config.http_cache.record_max_age);
+
+            Ok(conditional::json(
+                &headers,
+                &etag,
+                &cache_control,
+                GetRecordResponse {
+                    uri,
+                    cid,
+                    value: record_value,
+                    labels,
+                },
+            ))
         }
         None => Err(PdsError::NotFound(format!("Record not found: {}", uri))),
     }
@@ -400,10 +436,14 @@ async fn list_records(
 }
 
 /// Describe a repository
+///
+/// The ETag is the repo rev plus a digest of the description, since the
+/// handle and DID document can change without a new commit.
 async fn describe_repo(
     State(ctx): State<AppContext>,
     Query(query): Query<DescribeRepoQuery>,
-) -> PdsResult<Json<DescribeRepoResponse>> {
+    headers: HeaderMap,
+) -> PdsResult<Response> {
     // Get the DID
     let did = &query.repo;
 
@@ -416,7 +456,7 @@ async fn describe_repo(
         Some(&ctx.identity_resolver),
     ).await?;
 
-    Ok(Json(DescribeRepoResponse {
+    let response = DescribeRepoResponse {
         did: desc.get("did").and_then(|v| v.as_str()).unwrap_or("").to_string(),
         handle: desc.get("handle").and_then(|v| v.as_str()).unwrap_or("").to_string(),
         did_doc: desc.get("didDoc").cloned(),
@@ -429,7 +469,18 @@ async fn describe_repo(
             .get("handleIsCorrect")
             .and_then(|v| v.as_bool())
             .unwrap_or(true),
-    }))
+    };
+
+    let rev = ctx
+        .actor_store
+        .get_repo_root(did)
+        .await
+        .map(|root| root.rev)
+        .unwrap_or_default();
+    let etag = conditional::etag(&format!("{}-{}", rev, conditional::digest(&response)));
+    let cache_control = conditional::cache_control(ctx.config.http_cache.describe_repo_max_age);
+
+    Ok(conditional::json(&headers, &etag, &cache_control, response))
 }
 
 /// Apply writes (batch operations with validation)
@@ -439,6 +490,7 @@ async fn describe_repo(
 /// - Duplicate detection
 /// - Size limit enforcement
 /// - All-or-nothing atomicity
+/// - `swapCommit`: rejected with `InvalidSwap` if the head moved
 async fn apply_writes(
     State(ctx): State<AppContext>,
     headers: HeaderMap,
@@ -455,7 +507,10 @@ async fn apply_writes(
     }
 
     // Create repository manager
-    let repo_mgr = RepositoryManager::with_sequencer(session.did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone());
+    let repo_mgr = RepositoryManager::with_sequencer(session.did.clone(), (*ctx.actor_store).clone(), ctx.sequencer.clone())
+        .with_content_policy(Some(ctx.content_policy.clone()))
+        .with_write_hooks(Some(ctx.write_hooks.clone()))
+        .with_blob_store(Some(ctx.blob_store.clone()));
 
     // Prepare writes (converts to PreparedWrite format)
     let prepared = repo_mgr.prepare_writes(req.writes)?;
@@ -466,12 +521,11 @@ async fn apply_writes(
         session.did
     );
 
-    // Create signer from repo key
-    let signer = create_repo_signer(&ctx.config.authentication.repo_signing_key);
+    let signer = ctx.signing_keys.commit_signer(&session.did).await?;
 
     // Apply batch atomically (includes validation)
     let (commit_cid, rev) = repo_mgr
-        .apply_batch_writes(prepared, signer)
+        .apply_batch_writes(prepared, req.swap_commit.as_deref(), signer)
         .await?;
 
     tracing::info!(
@@ -487,3 +541,51 @@ async fn apply_writes(
         }
     })))
 }
+
+/// Import a repository CAR (com.atproto.repo.importRepo)
+///
+/// Used when migrating an account here: the CAR must be the caller's own
+/// repository, signed with the key in their DID document. The MST and every
+/// block are checked before anything is stored, and a rejected import
+/// returns the verification report.
+async fn import_repo(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> PdsResult<Response> {
+    let session = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    let car = read_car(&body)?;
+    let commit = *car
+        .roots
+        .first()
+        .ok_or_else(|| PdsError::Validation("Repository CAR has no root".to_string()))?;
+
+    let key = account_signing_key(&ctx.identity_resolver, &session.did).await?;
+    let report = verify_repo(&car.blocks, &session.did, &commit, Some(&key)).await?;
+    if !report.is_valid() {
+        tracing::warn!("Rejected repo import: {}", report.summary());
+        return Ok((
+            StatusCode::BAD_REQUEST,
+            Json(serde_json::json!({
+                "error": "InvalidRepo",
+                "message": format!("Repository failed verification with {} issue(s)", report.issues.len()),
+                "report": report,
+            })),
+        )
+            .into_response());
+    }
+
+    RepositoryManager::new(session.did.clone(), (*ctx.actor_store).clone())
+        .import(&car.blocks, &report)
+        .await?;
+
+    tracing::info!(
+        "Imported repo for {} (rev: {}, {} records)",
+        session.did,
+        report.rev.as_deref().unwrap_or_default(),
+        report.records
+    );
+
+    Ok(StatusCode::OK.into_response())
+}
@@ -1,13 +1,16 @@
 /// com.atproto.server.* endpoints
 use crate::{
     account::{
-        CreateAccountRequest, CreateAccountResponse, CreateAppPasswordRequest,
+        ApiTokenScope, ChallengeDescription, CreateApiTokenRequest, CreateApiTokenResponse,
+        ListApiTokensResponse, RevokeApiTokenRequest, CreateAccountRequest, CreateAccountResponse, CreateAppPasswordRequest,
         CreateAppPasswordResponse, CreateSessionRequest, ListAppPasswordsResponse,
-        RefreshSessionRequest, RevokeAppPasswordRequest, SessionInfo, SessionResponse,
+        ListSessionsResponse, RefreshSessionRequest, RevokeAppPasswordRequest,
+        RevokeSessionRequest, SessionInfo, SessionResponse,
     },
-    api::middleware,
+    api::middleware::{self, ClientIp},
     context::AppContext,
     error::PdsResult,
+    mailer::Branding,
 };
 use axum::{
     extract::State,
@@ -20,6 +23,7 @@ use axum::{
 pub fn routes() -> Router<AppContext> {
     Router::new()
         .route("/xrpc/com.atproto.server.createAccount", post(create_account))
+        .route("/xrpc/app.aurora.server.getRegistrationChallenge", get(get_registration_challenge))
         .route("/xrpc/com.atproto.server.createSession", post(create_session))
         .route("/xrpc/com.atproto.server.getSession", get(get_session))
         .route("/xrpc/com.atproto.server.deleteSession", post(delete_session))
@@ -32,29 +36,50 @@ pub fn routes() -> Router<AppContext> {
         .route("/xrpc/com.atproto.server.createAppPassword", post(create_app_password))
         .route("/xrpc/com.atproto.server.listAppPasswords", get(list_app_passwords))
         .route("/xrpc/com.atproto.server.revokeAppPassword", post(revoke_app_password))
+        .route("/xrpc/app.aurora.server.listSessions", get(list_sessions))
+        .route("/xrpc/app.aurora.server.revokeSession", post(revoke_session))
+        .route("/xrpc/app.aurora.server.revokeOtherSessions", post(revoke_other_sessions))
+        .route("/xrpc/app.aurora.server.createApiToken", post(create_api_token))
+        .route("/xrpc/app.aurora.server.listApiTokens", get(list_api_tokens))
+        .route("/xrpc/app.aurora.server.revokeApiToken", post(revoke_api_token))
 }
 
 /// Create account endpoint
 async fn create_account(
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(req): Json<CreateAccountRequest>,
 ) -> PdsResult<Json<CreateAccountResponse>> {
     tracing::info!("create_account: Starting account creation for handle: {}", req.handle);
+    ctx.ip_blocklist.check_request("createAccount", ip, &headers).await?;
+
+    let handle = crate::account::normalize_handle(&req.handle)?;
 
-    // Validate and use invite code if required
-    if ctx.config.invites.required {
+    // Validate and use invite code if the handle's domain requires one, or
+    // while open signups are locked down after a burst
+    let client_ip = ip.map(|ip| ip.to_string());
+    let invite_required = ctx.config.invite_required_for(&handle) || ctx.signup_throttle.in_lockdown();
+    if invite_required {
         tracing::debug!("create_account: Invite code required, validating");
         let code = req.invite_code.as_ref().ok_or_else(|| {
             crate::error::PdsError::Validation("Invite code required".to_string())
         })?;
 
         // Validate and mark code as used
-        ctx.invite_manager.use_code(code, &req.handle).await
+        ctx.invite_manager.use_code(code, &handle).await
             .map_err(|e| {
                 tracing::error!("create_account: Failed to use invite code: {}", e);
                 e
             })?;
         tracing::debug!("create_account: Invite code validated successfully");
+    } else {
+        // Open registration is capped per hour and may have to pass a
+        // CAPTCHA or proof of work
+        ctx.signup_throttle.check(client_ip.as_deref()).await?;
+        ctx.registration_challenge
+            .check(req.challenge_token.as_deref(), client_ip.as_deref())
+            .await?;
     }
 
     // Create account (pass None for invite_code since we already validated it)
@@ -62,13 +87,16 @@ async fn create_account(
     let email = req.email.clone();
     let account = ctx
         .account_manager
-        .create_account(req.handle.clone(), req.email, req.password, None)
+        .create_account(handle, req.email, req.password, None)
         .await
         .map_err(|e| {
             tracing::error!("create_account: Failed to create account in database: {}", e);
             e
         })?;
     tracing::info!("create_account: Account created successfully, DID: {}", account.did);
+    if !invite_required {
+        ctx.signup_throttle.record(client_ip.as_deref());
+    }
 
     // Initialize repository for the new account
     tracing::debug!("create_account: Initializing repository for DID: {}", account.did);
@@ -81,17 +109,20 @@ async fn create_account(
         })?;
     tracing::info!("create_account: Repository initialized successfully");
 
+    // Announce the new account to relays
+    ctx.account_events.created(&account.did, &account.handle).await?;
+
     // Generate and send email verification token if email was provided
     if email.is_some() && ctx.mailer.is_configured() {
         match ctx.account_manager.generate_email_verification_token(&account.did).await {
             Ok(token) => {
                 // Send verification email
-                let base_url = ctx.service_url();
+                let branding = Branding::for_handle(&ctx.config, &account.handle, &ctx.service_url());
                 if let Err(e) = ctx.mailer.send_verification_email(
                     email.as_ref().unwrap(),
                     &account.handle,
                     &token,
-                    &base_url
+                    &branding
                 ).await {
                     tracing::warn!("Failed to send verification email: {}", e);
                     // Don't fail account creation if email fails
@@ -120,11 +151,26 @@ async fn create_account(
     }))
 }
 
+/// Registration challenge for open signups
+///
+/// Tells clients whether `createAccount` currently needs a
+/// `challenge_token`, with the CAPTCHA site key or a fresh proof-of-work
+/// challenge to solve.
+async fn get_registration_challenge(
+    State(ctx): State<AppContext>,
+) -> PdsResult<Json<ChallengeDescription>> {
+    Ok(Json(ctx.registration_challenge.describe().await?))
+}
+
 /// Create session (login) endpoint
 async fn create_session(
     State(ctx): State<AppContext>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(req): Json<CreateSessionRequest>,
 ) -> PdsResult<Json<SessionResponse>> {
+    ctx.ip_blocklist.check_request("createSession", ip, &headers).await?;
+
     // Try regular password authentication first
     let (account, session) = match ctx
         .account_manager
@@ -141,6 +187,11 @@ async fn create_session(
         }
     };
 
+    // Remember where the session was created from for listSessions
+    ctx.account_manager
+        .record_session_client(&session.id, &middleware::session_client_info(ip, &headers))
+        .await?;
+
     Ok(Json(SessionResponse {
         did: account.did,
         handle: account.handle,
@@ -237,13 +288,13 @@ async fn request_email_confirmation(
 
     // Send verification email if mailer is configured
     if ctx.mailer.is_configured() {
-        let base_url = ctx.service_url();
+        let branding = Branding::for_handle(&ctx.config, &account.handle, &ctx.service_url());
         ctx.mailer
             .send_verification_email(
                 account.email.as_ref().unwrap(),
                 &account.handle,
                 &token,
-                &base_url,
+                &branding,
             )
             .await?;
     } else {
@@ -294,9 +345,9 @@ async fn request_password_reset(
 
     // Send password reset email if mailer is configured
     if ctx.mailer.is_configured() {
-        let base_url = ctx.service_url();
+        let branding = Branding::for_handle(&ctx.config, &account.handle, &ctx.service_url());
         ctx.mailer
-            .send_password_reset_email(&email, &account.handle, &token, &base_url)
+            .send_password_reset_email(&email, &account.handle, &token, &branding)
             .await?;
     } else {
         tracing::warn!("Email not configured, reset token generated but not sent");
@@ -347,6 +398,7 @@ async fn delete_account(
     ctx.account_manager
         .request_account_deletion(&validated.did, &req.password)
         .await?;
+    ctx.account_events.status_changed(&validated.did).await?;
 
     Ok(Json(serde_json::json!({
         "message": "Account marked for deletion. You have 30 days to cancel this request by logging in again."
@@ -426,3 +478,143 @@ async fn revoke_app_password(
 
     Ok(Json(serde_json::json!({})))
 }
+
+/// List active sessions endpoint
+async fn list_sessions(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<ListSessionsResponse>> {
+    // Require authentication
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    let sessions = ctx
+        .account_manager
+        .list_sessions(&validated.did, &validated.session_id)
+        .await?;
+
+    Ok(Json(ListSessionsResponse { sessions }))
+}
+
+/// Revoke session endpoint
+///
+/// Deletes one of the caller's sessions and invalidates its refresh token.
+async fn revoke_session(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<RevokeSessionRequest>,
+) -> PdsResult<Json<serde_json::Value>> {
+    // Require authentication
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    // App passwords may only end their own session
+    if validated.is_app_password && req.id != validated.session_id {
+        return Err(crate::error::PdsError::Authorization(
+            "Cannot revoke other sessions using app password authentication".to_string(),
+        ));
+    }
+
+    ctx.account_manager
+        .revoke_session(&validated.did, &req.id)
+        .await?;
+
+    Ok(Json(serde_json::json!({})))
+}
+
+/// Revoke other sessions endpoint
+///
+/// Signs out every session of the caller except the one making the request.
+async fn revoke_other_sessions(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<serde_json::Value>> {
+    // Require authentication
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    if validated.is_app_password {
+        return Err(crate::error::PdsError::Authorization(
+            "Cannot revoke other sessions using app password authentication".to_string(),
+        ));
+    }
+
+    let revoked = ctx
+        .account_manager
+        .revoke_other_sessions(&validated.did, &validated.session_id)
+        .await?;
+
+    Ok(Json(serde_json::json!({ "revoked": revoked })))
+}
+
+/// Create API token endpoint
+///
+/// Creates a scoped personal access token for bots and automation. Needs a
+/// full session; the `admin` scope is only granted to accounts holding an
+/// admin role. The token is only shown once in the response.
+async fn create_api_token(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<CreateApiTokenRequest>,
+) -> PdsResult<Json<CreateApiTokenResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    if validated.is_app_password {
+        return Err(crate::error::PdsError::Authorization(
+            "Cannot create API tokens using app password or API token authentication".to_string(),
+        ));
+    }
+
+    let scopes = req
+        .scopes
+        .iter()
+        .map(|scope| ApiTokenScope::parse(scope))
+        .collect::<PdsResult<Vec<_>>>()?;
+
+    if scopes.contains(&ApiTokenScope::Admin) {
+        let is_admin = ctx.config.authentication.admin_dids.contains(&validated.did)
+            || ctx.admin_role_manager.get_role(&validated.did).await?.is_some();
+        if !is_admin {
+            return Err(crate::error::PdsError::Authorization(
+                "The admin scope requires an admin role".to_string(),
+            ));
+        }
+    }
+
+    let (info, token) = ctx
+        .account_manager
+        .create_api_token(&validated.did, &req.name, &scopes, req.expires_in_days)
+        .await?;
+
+    Ok(Json(CreateApiTokenResponse { info, token }))
+}
+
+/// List API tokens endpoint (without the tokens themselves)
+async fn list_api_tokens(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<ListApiTokensResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    let tokens = ctx.account_manager.list_api_tokens(&validated.did).await?;
+
+    Ok(Json(ListApiTokensResponse { tokens }))
+}
+
+/// Revoke API token endpoint
+async fn revoke_api_token(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<RevokeApiTokenRequest>,
+) -> PdsResult<Json<serde_json::Value>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    if validated.is_app_password {
+        return Err(crate::error::PdsError::Authorization(
+            "Cannot revoke API tokens using app password or API token authentication".to_string(),
+        ));
+    }
+
+    ctx.account_manager
+        .revoke_api_token(&validated.did, &req.id)
+        .await?;
+
+    Ok(Json(serde_json::json!({})))
+}
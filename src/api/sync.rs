@@ -3,6 +3,14 @@
 /// Implements com.atproto.sync.* endpoints for federation and repository export
 
 use crate::{
+    actor_store::{
+        export,
+        history::{self, CommitInfo, HistoricalRecord},
+        proof::record_proof,
+        RepoHead,
+    },
+    admin::Role,
+    api::middleware,
     car::CarEncoder,
     context::AppContext,
     error::{PdsError, PdsResult},
@@ -11,7 +19,7 @@ use libipld::Cid;
 use axum::{
     body::Body,
     extract::{Query, State},
-    http::{header, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::Response,
     routing::get,
     Json, Router,
@@ -24,7 +32,7 @@ use std::str::FromStr;
 pub struct GetRepoParams {
     /// DID of the repository
     pub did: String,
-    /// Optional: commit CID to retrieve specific version
+    /// Optional revision; only blocks written by later commits are included
     pub since: Option<String>,
 }
 
@@ -52,6 +60,56 @@ pub struct GetBlocksParams {
     pub cids: Vec<String>,
 }
 
+impl GetBlocksParams {
+    /// Parse from query pairs (`cids` is repeated: `?did=...&cids=a&cids=b`)
+    fn from_pairs(pairs: Vec<(String, String)>) -> PdsResult<Self> {
+        let mut did = None;
+        let mut cids = Vec::new();
+        for (key, value) in pairs {
+            match key.as_str() {
+                "did" => did = Some(value),
+                "cids" => cids.push(value),
+                _ => {}
+            }
+        }
+
+        let did = did.ok_or_else(|| PdsError::Validation("Missing did parameter".to_string()))?;
+        Ok(Self { did, cids })
+    }
+}
+
+/// Request parameters for getRecord
+#[derive(Debug, Deserialize)]
+pub struct GetRecordParams {
+    /// DID of the repository
+    pub did: String,
+    /// Collection NSID
+    pub collection: String,
+    /// Record key
+    pub rkey: String,
+}
+
+/// Request parameters for listBlobs
+#[derive(Debug, Deserialize)]
+pub struct ListBlobsParams {
+    /// DID of the repository
+    pub did: String,
+    /// Optional revision; only blobs referenced by later writes are listed
+    pub since: Option<String>,
+    /// Optional limit (default: 500, max: 1000)
+    pub limit: Option<i64>,
+    /// Optional cursor for pagination
+    pub cursor: Option<String>,
+}
+
+/// Response for listBlobs
+#[derive(Debug, Serialize)]
+pub struct ListBlobsResponse {
+    pub cids: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
 /// Request parameters for listRepos
 #[derive(Debug, Deserialize)]
 pub struct ListReposParams {
@@ -69,17 +127,155 @@ pub struct ListReposResponse {
     pub cursor: Option<String>,
 }
 
+/// Request parameters for listReposByCollection
+#[derive(Debug, Deserialize)]
+pub struct ListReposByCollectionParams {
+    /// Collection NSID the repos must have records in
+    pub collection: String,
+    /// Optional limit (default: 500, max: 2000)
+    pub limit: Option<i64>,
+    /// Optional cursor for pagination
+    pub cursor: Option<String>,
+}
+
+/// Request parameters for getCommitPath
+#[derive(Debug, Deserialize)]
+pub struct GetCommitPathParams {
+    /// DID of the repository
+    pub did: String,
+    /// Last commit in the path (default: the head)
+    pub latest: Option<String>,
+    /// Commit the path starts after (default: the oldest logged commit)
+    pub earliest: Option<String>,
+}
+
+/// Response for getCommitPath
+#[derive(Debug, Serialize)]
+pub struct CommitPathResponse {
+    /// Commit CIDs, oldest first
+    pub commits: Vec<String>,
+}
+
+/// Request parameters for listCommits
+#[derive(Debug, Deserialize)]
+pub struct ListCommitsParams {
+    /// DID of the repository
+    pub did: String,
+    /// Optional limit (default: 50, max: 100)
+    pub limit: Option<i64>,
+    /// Optional cursor (a revision) for pagination
+    pub cursor: Option<String>,
+}
+
+/// Response for listCommits
+#[derive(Debug, Serialize)]
+pub struct ListCommitsResponse {
+    /// Commits, newest first
+    pub commits: Vec<CommitInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Request parameters for listRecordsAtCommit
+#[derive(Debug, Deserialize)]
+pub struct ListRecordsAtCommitParams {
+    /// DID of the repository
+    pub did: String,
+    /// CID of the commit
+    pub commit: String,
+    /// Optional collection NSID to list
+    pub collection: Option<String>,
+    /// Optional limit (default: 50, max: 100)
+    pub limit: Option<i64>,
+    /// Optional cursor for pagination
+    pub cursor: Option<String>,
+}
+
+/// Response for listRecordsAtCommit
+#[derive(Debug, Serialize)]
+pub struct ListRecordsAtCommitResponse {
+    pub commit: String,
+    pub rev: String,
+    pub records: Vec<HistoricalRecord>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+}
+
+/// Response for listReposByCollection
+#[derive(Debug, Serialize)]
+pub struct ListReposByCollectionResponse {
+    pub repos: Vec<CollectionRepo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<String>,
+    /// Repos with records in the collection, across all pages
+    pub total: i64,
+}
+
+/// A repository with records in the requested collection
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectionRepo {
+    pub did: String,
+    /// Records the repo holds in the collection
+    pub record_count: i64,
+}
+
 /// Repository information
 #[derive(Debug, Serialize)]
 pub struct RepoInfo {
     pub did: String,
     pub head: String,
     pub rev: String,
+    pub active: bool,
+    /// Why the repo is inactive ("takendown" or "deactivated")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// Header flagging responses served from a read-only mirrored repository
+///
+/// Carries the URL of the PDS or relay the repository is mirrored from.
+pub const MIRRORED_FROM_HEADER: &str = "x-mirrored-from";
+
+/// Build response headers flagging a mirrored repository (empty if not mirrored)
+async fn mirror_headers(ctx: &AppContext, did: &str) -> PdsResult<HeaderMap> {
+    let mut headers = HeaderMap::new();
+
+    if let Some(mirror_manager) = &ctx.mirror_manager {
+        if let Some(mirrored) = mirror_manager.get_mirrored(did).await? {
+            if let Ok(value) = HeaderValue::from_str(&mirrored.source) {
+                headers.insert(MIRRORED_FROM_HEADER, value);
+            }
+        }
+    }
+
+    Ok(headers)
+}
+
+/// Reject repositories whose account is taken down or deactivated
+fn check_repo_status(head: &RepoHead) -> PdsResult<()> {
+    match head.status.as_deref() {
+        Some("takendown") => Err(PdsError::AccountTakenDown(format!(
+            "Repo has been taken down: {}",
+            head.did
+        ))),
+        Some(_) => Err(PdsError::NotFound(format!(
+            "Repo has been deactivated: {}",
+            head.did
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Encode a full repository as CAR bytes rooted at the current commit
+pub async fn export_repo_car(ctx: &AppContext, did: &str) -> PdsResult<Vec<u8>> {
+    export::export_repo_car((*ctx.actor_store).clone(), did, None).await
 }
 
 /// Get a repository as a CAR file export
 ///
-/// Implements com.atproto.sync.getRepo
+/// Implements com.atproto.sync.getRepo. The CAR is streamed from the actor
+/// store in chunks rather than built in memory.
 pub async fn get_repo(
     State(ctx): State<AppContext>,
     Query(params): Query<GetRepoParams>,
@@ -92,39 +288,28 @@ pub async fn get_repo(
         )));
     }
 
-    // Get the repository root CID
-    let repo_root = ctx.actor_store.get_repo_root(&params.did).await?;
-    let root_cid = Cid::from_str(&repo_root.cid)
-        .map_err(|e| PdsError::Internal(format!("Invalid root CID: {}", e)))?;
-
-    // Create CAR encoder
-    let mut encoder = CarEncoder::new(&root_cid)?;
-
-    // Get all blocks for this repository
-    let block_data = ctx.actor_store.get_all_blocks(&params.did).await?;
-
-    // Convert to (Cid, Vec<u8>) format
-    let blocks: Vec<(Cid, Vec<u8>)> = block_data
-        .into_iter()
-        .filter_map(|(cid_str, content)| {
-            Cid::from_str(&cid_str).ok().map(|cid| (cid, content))
-        })
-        .collect();
-
-    encoder.add_blocks(blocks)?;
-
-    let car_bytes = encoder.finalize();
+    let car = export::stream_repo_car(
+        (*ctx.actor_store).clone(),
+        &params.did,
+        params.since.as_deref(),
+    )
+    .await?;
 
     // Return CAR file as application/vnd.ipld.car
-    Ok(Response::builder()
+    let mut response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, "application/vnd.ipld.car")
         .header(
             header::CONTENT_DISPOSITION,
             format!("attachment; filename=\"{}.car\"", params.did),
         )
-        .body(Body::from(car_bytes))
-        .unwrap())
+        .body(Body::from_stream(car))
+        .unwrap();
+    response
+        .headers_mut()
+        .extend(mirror_headers(&ctx, &params.did).await?);
+
+    Ok(response)
 }
 
 /// Get the latest commit for a repository
@@ -133,7 +318,22 @@ pub async fn get_repo(
 pub async fn get_latest_commit(
     State(ctx): State<AppContext>,
     Query(params): Query<GetLatestCommitParams>,
-) -> PdsResult<Json<LatestCommitResponse>> {
+) -> PdsResult<(HeaderMap, Json<LatestCommitResponse>)> {
+    // Hosted repos are answered from the repo head index
+    if let Some(index) = ctx.actor_store.repo_index() {
+        if let Some(head) = index.get(&params.did).await? {
+            check_repo_status(&head)?;
+
+            return Ok((
+                mirror_headers(&ctx, &params.did).await?,
+                Json(LatestCommitResponse {
+                    cid: head.cid,
+                    rev: head.rev,
+                }),
+            ));
+        }
+    }
+
     // Validate DID exists
     if !ctx.actor_store.exists(&params.did).await {
         return Err(PdsError::NotFound(format!(
@@ -145,10 +345,13 @@ pub async fn get_latest_commit(
     // Get the repository root CID (latest commit)
     let repo_root = ctx.actor_store.get_repo_root(&params.did).await?;
 
-    Ok(Json(LatestCommitResponse {
-        cid: repo_root.cid,
-        rev: repo_root.rev,
-    }))
+    Ok((
+        mirror_headers(&ctx, &params.did).await?,
+        Json(LatestCommitResponse {
+            cid: repo_root.cid,
+            rev: repo_root.rev,
+        }),
+    ))
 }
 
 /// Get specific blocks from a repository
@@ -156,8 +359,10 @@ pub async fn get_latest_commit(
 /// Implements com.atproto.sync.getBlocks
 pub async fn get_blocks(
     State(ctx): State<AppContext>,
-    Query(params): Query<GetBlocksParams>,
+    Query(pairs): Query<Vec<(String, String)>>,
 ) -> PdsResult<Response> {
+    let params = GetBlocksParams::from_pairs(pairs)?;
+
     // Validate DID exists
     if !ctx.actor_store.exists(&params.did).await {
         return Err(PdsError::NotFound(format!(
@@ -198,16 +403,104 @@ pub async fn get_blocks(
         })
         .collect();
 
+    // Every requested block must be present
+    let missing: Vec<String> = cids
+        .iter()
+        .filter(|cid| !blocks.iter().any(|(found, _)| found == *cid))
+        .map(|cid| cid.to_string())
+        .collect();
+    if !missing.is_empty() {
+        return Err(PdsError::NotFound(format!(
+            "Could not find blocks: {}",
+            missing.join(", ")
+        )));
+    }
+
     encoder.add_blocks(blocks)?;
 
-    let car_bytes = encoder.finalize();
+    car_response(encoder.finalize(), mirror_headers(&ctx, &params.did).await?)
+}
 
-    // Return CAR file
-    Ok(Response::builder()
+/// Get a record with its MST inclusion proof
+///
+/// Implements com.atproto.sync.getRecord. The CAR is rooted at the head
+/// commit and contains the commit, the MST nodes on the path to the record,
+/// and the record block (omitted when the record does not exist, in which
+/// case the path proves its absence).
+pub async fn get_record(
+    State(ctx): State<AppContext>,
+    Query(params): Query<GetRecordParams>,
+) -> PdsResult<Response> {
+    if !ctx.actor_store.exists(&params.did).await {
+        return Err(PdsError::NotFound(format!(
+            "Repository not found for DID: {}",
+            params.did
+        )));
+    }
+
+    let proof = record_proof(&ctx.actor_store, &params.did, &params.collection, &params.rkey).await?;
+
+    let mut encoder = CarEncoder::new(&proof.commit)?;
+    encoder.add_blocks(proof.blocks)?;
+
+    car_response(encoder.finalize(), mirror_headers(&ctx, &params.did).await?)
+}
+
+/// Build a CAR file response
+fn car_response(car_bytes: Vec<u8>, headers: HeaderMap) -> PdsResult<Response> {
+    let mut response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, "application/vnd.ipld.car")
         .body(Body::from(car_bytes))
-        .unwrap())
+        .map_err(|e| PdsError::Internal(format!("Failed to build response: {}", e)))?;
+    response.headers_mut().extend(headers);
+
+    Ok(response)
+}
+
+/// List blobs referenced by a repository
+///
+/// Implements com.atproto.sync.listBlobs
+pub async fn list_blobs(
+    State(ctx): State<AppContext>,
+    Query(params): Query<ListBlobsParams>,
+) -> PdsResult<(HeaderMap, Json<ListBlobsResponse>)> {
+    let limit = params.limit.unwrap_or(500).clamp(1, 1000);
+
+    if let Some(index) = ctx.actor_store.repo_index() {
+        if let Some(head) = index.get(&params.did).await? {
+            check_repo_status(&head)?;
+        }
+    }
+
+    if !ctx.actor_store.exists(&params.did).await {
+        return Err(PdsError::NotFound(format!(
+            "Repository not found for DID: {}",
+            params.did
+        )));
+    }
+
+    let cids = ctx
+        .actor_store
+        .list_blobs(
+            &params.did,
+            params.since.as_deref(),
+            params.cursor.as_deref(),
+            limit,
+        )
+        .await?;
+
+    // There may be more results when the page is full
+    let cursor = if cids.len() as i64 == limit {
+        cids.last().cloned()
+    } else {
+        None
+    };
+
+    Ok((
+        mirror_headers(&ctx, &params.did).await?,
+        Json(ListBlobsResponse { cids, cursor }),
+    ))
 }
 
 /// List all repositories on this PDS
@@ -217,36 +510,191 @@ pub async fn list_repos(
     State(ctx): State<AppContext>,
     Query(params): Query<ListReposParams>,
 ) -> PdsResult<Json<ListReposResponse>> {
-    let limit = params.limit.unwrap_or(500).min(1000);
+    let limit = params.limit.unwrap_or(500).clamp(1, 1000);
+
+    let index = ctx
+        .actor_store
+        .repo_index()
+        .ok_or_else(|| PdsError::Internal("Repo head index is not configured".to_string()))?;
+
+    // Single indexed query over the central repo head table
+    let heads = index.list(params.cursor.as_deref(), limit).await?;
+
+    // There may be more results when the page is full
+    let cursor = if heads.len() as i64 == limit {
+        heads.last().map(|h| h.did.clone())
+    } else {
+        None
+    };
 
-    // Get list of all accounts with pagination
-    let accounts = ctx
-        .account_manager
-        .list_accounts(params.cursor.as_deref(), limit)
+    let repos = heads
+        .into_iter()
+        .map(|head| RepoInfo {
+            active: head.active(),
+            did: head.did,
+            head: head.cid,
+            rev: head.rev,
+            status: head.status,
+        })
+        .collect();
+
+    Ok(Json(ListReposResponse { repos, cursor }))
+}
+
+/// List active repositories with records in a collection
+///
+/// Implements com.atproto.sync.listReposByCollection, so crawlers that only
+/// care about e.g. feed generators can skip every other repo. Served from
+/// the per-repo collection counts in the repo head index, which every commit
+/// updates; taken down and deactivated accounts are left out.
+pub async fn list_repos_by_collection(
+    State(ctx): State<AppContext>,
+    Query(params): Query<ListReposByCollectionParams>,
+) -> PdsResult<Json<ListReposByCollectionResponse>> {
+    if !atproto::syntax::is_valid_nsid(&params.collection) {
+        return Err(PdsError::Validation(format!("Invalid collection NSID: {}", params.collection)));
+    }
+    let limit = params.limit.unwrap_or(500).clamp(1, 2000);
+
+    let index = ctx
+        .actor_store
+        .repo_index()
+        .ok_or_else(|| PdsError::Internal("Repo head index is not configured".to_string()))?;
+
+    let page = index
+        .list_by_collection(&params.collection, params.cursor.as_deref(), limit)
         .await?;
+    let total = index.count_by_collection(&params.collection).await?;
 
-    // Build repository info for each account
-    let mut repos = Vec::new();
-    for account in &accounts {
-        // Get the repository root for this DID
-        if let Ok(repo_root) = ctx.actor_store.get_repo_root(&account.did).await {
-            repos.push(RepoInfo {
-                did: account.did.clone(),
-                head: repo_root.cid,
-                rev: repo_root.rev,
-            });
+    // There may be more results when the page is full
+    let cursor = if page.len() as i64 == limit {
+        page.last().map(|(did, _)| did.clone())
+    } else {
+        None
+    };
+
+    let repos = page
+        .into_iter()
+        .map(|(did, record_count)| CollectionRepo { did, record_count })
+        .collect();
+
+    Ok(Json(ListReposByCollectionResponse { repos, cursor, total }))
+}
+
+/// Check that `did` is a hosted repository that may be read
+async fn check_hosted_repo(ctx: &AppContext, did: &str) -> PdsResult<()> {
+    if let Some(index) = ctx.actor_store.repo_index() {
+        if let Some(head) = index.get(did).await? {
+            check_repo_status(&head)?;
         }
     }
 
-    // Determine next cursor
-    let cursor = if repos.len() as i64 == limit {
-        // There may be more results, return the last DID as cursor
-        accounts.last().map(|a| a.did.clone())
+    if !ctx.actor_store.exists(did).await {
+        return Err(PdsError::NotFound(format!("Repository not found for DID: {}", did)));
+    }
+    Ok(())
+}
+
+/// List a repository's commits, newest first
+///
+/// Implements app.aurora.sync.listCommits. Each commit carries its CID,
+/// revision, record operation count and time, and whether its blocks are
+/// still retained (see `actor_store::history`).
+pub async fn list_commits(
+    State(ctx): State<AppContext>,
+    Query(params): Query<ListCommitsParams>,
+) -> PdsResult<Json<ListCommitsResponse>> {
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+    check_hosted_repo(&ctx, &params.did).await?;
+
+    let commits = history::list_commits(&ctx.actor_store, &params.did, params.cursor.as_deref(), limit).await?;
+    let cursor = if commits.len() as i64 == limit {
+        commits.last().map(|c| c.rev.clone())
     } else {
         None
     };
 
-    Ok(Json(ListReposResponse { repos, cursor }))
+    Ok(Json(ListCommitsResponse { commits, cursor }))
+}
+
+/// CIDs of the commits between two commits, oldest first
+///
+/// Implements app.aurora.sync.getCommitPath: the commits after `earliest`
+/// up to and including `latest`.
+pub async fn get_commit_path(
+    State(ctx): State<AppContext>,
+    Query(params): Query<GetCommitPathParams>,
+) -> PdsResult<Json<CommitPathResponse>> {
+    let latest = params.latest.as_deref().map(history::parse_commit_cid).transpose()?;
+    let earliest = params.earliest.as_deref().map(history::parse_commit_cid).transpose()?;
+    check_hosted_repo(&ctx, &params.did).await?;
+
+    let commits = history::commit_path(&ctx.actor_store, &params.did, latest.as_deref(), earliest.as_deref()).await?;
+    Ok(Json(CommitPathResponse { commits }))
+}
+
+/// Whether the caller may read `did`'s past records, and if so whether as
+/// an admin
+///
+/// Old commits still hold records deleted or taken down since, so only the
+/// repo owner and server admins can list them.
+async fn history_reader(ctx: &AppContext, headers: HeaderMap, did: &str) -> PdsResult<bool> {
+    let session = middleware::require_auth(State(ctx.clone()), headers).await?;
+    if session.did == did {
+        return Ok(false);
+    }
+
+    let is_admin = ctx.config.authentication.admin_dids.contains(&session.did)
+        || ctx
+            .admin_role_manager
+            .get_role(&session.did)
+            .await?
+            .is_some_and(|role| role.domain.is_none() && role.role.can_act_as(Role::Admin));
+    if !is_admin {
+        return Err(PdsError::Authorization(
+            "Only the repo owner and server admins can list past records".to_string(),
+        ));
+    }
+    Ok(true)
+}
+
+/// A repository's records as of a past commit
+///
+/// Implements app.aurora.sync.listRecordsAtCommit for the repo owner and
+/// server admins; records taken down since are only listed for admins.
+/// Fails with `NotFound` once compaction has pruned blocks the commit needs.
+pub async fn list_records_at_commit(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(params): Query<ListRecordsAtCommitParams>,
+) -> PdsResult<Json<ListRecordsAtCommitResponse>> {
+    let commit = history::parse_commit_cid(&params.commit)?;
+    if let Some(collection) = &params.collection {
+        if !atproto::syntax::is_valid_nsid(collection) {
+            return Err(PdsError::Validation(format!("Invalid collection NSID: {}", collection)));
+        }
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, 100) as usize;
+    let is_admin = history_reader(&ctx, headers, &params.did).await?;
+    check_hosted_repo(&ctx, &params.did).await?;
+
+    let (rev, records, cursor) = history::records_at_commit(
+        &ctx.actor_store,
+        &params.did,
+        &commit,
+        params.collection.as_deref(),
+        params.cursor.as_deref(),
+        limit,
+        is_admin,
+    )
+    .await?;
+
+    Ok(Json(ListRecordsAtCommitResponse {
+        commit,
+        rev,
+        records,
+        cursor,
+    }))
 }
 
 /// Build sync API routes
@@ -264,10 +712,34 @@ pub fn routes() -> Router<AppContext> {
             "/xrpc/com.atproto.sync.getBlocks",
             get(get_blocks),
         )
+        .route(
+            "/xrpc/com.atproto.sync.getRecord",
+            get(get_record),
+        )
+        .route(
+            "/xrpc/com.atproto.sync.listBlobs",
+            get(list_blobs),
+        )
         .route(
             "/xrpc/com.atproto.sync.listRepos",
             get(list_repos),
         )
+        .route(
+            "/xrpc/com.atproto.sync.listReposByCollection",
+            get(list_repos_by_collection),
+        )
+        .route(
+            "/xrpc/app.aurora.sync.getCommitPath",
+            get(get_commit_path),
+        )
+        .route(
+            "/xrpc/app.aurora.sync.listCommits",
+            get(list_commits),
+        )
+        .route(
+            "/xrpc/app.aurora.sync.listRecordsAtCommit",
+            get(list_records_at_commit),
+        )
 }
 
 #[cfg(test)]
@@ -282,6 +754,39 @@ mod tests {
         assert!(params.since.is_some());
     }
 
+    #[test]
+    fn test_get_blocks_params_from_pairs() {
+        let uri: axum::http::Uri = "/xrpc/com.atproto.sync.getBlocks?did=did:plc:test&cids=bafya&cids=bafyb"
+            .parse()
+            .unwrap();
+        let Query(pairs) = Query::<Vec<(String, String)>>::try_from_uri(&uri).unwrap();
+        let params = GetBlocksParams::from_pairs(pairs).unwrap();
+        assert_eq!(params.did, "did:plc:test");
+        assert_eq!(params.cids, vec!["bafya", "bafyb"]);
+
+        assert!(GetBlocksParams::from_pairs(vec![("cids".to_string(), "bafya".to_string())]).is_err());
+    }
+
+    #[test]
+    fn test_list_blobs_params_and_response() {
+        let uri: axum::http::Uri = "/xrpc/com.atproto.sync.listBlobs?did=did:plc:test&since=3l4example&limit=2"
+            .parse()
+            .unwrap();
+        let Query(params) = Query::<ListBlobsParams>::try_from_uri(&uri).unwrap();
+        assert_eq!(params.did, "did:plc:test");
+        assert_eq!(params.since.as_deref(), Some("3l4example"));
+        assert_eq!(params.limit, Some(2));
+        assert!(params.cursor.is_none());
+
+        let last_page = ListBlobsResponse {
+            cids: vec!["bafkreia".to_string()],
+            cursor: None,
+        };
+        let json = serde_json::to_value(&last_page).unwrap();
+        assert_eq!(json["cids"][0], "bafkreia");
+        assert!(json.get("cursor").is_none());
+    }
+
     #[test]
     fn test_latest_commit_response_serialize() {
         let response = LatestCommitResponse {
@@ -292,4 +797,26 @@ mod tests {
         assert!(json.contains("cid"));
         assert!(json.contains("rev"));
     }
+
+    #[test]
+    fn test_repo_info_serialize() {
+        let active = RepoInfo {
+            did: "did:plc:test".to_string(),
+            head: "bafyreie5cvv4h45feadgeuwhbcutmh6t2ceseocckahdoe6uat64zmz454".to_string(),
+            rev: "3l4example".to_string(),
+            active: true,
+            status: None,
+        };
+        let json = serde_json::to_value(&active).unwrap();
+        assert_eq!(json["active"], true);
+        assert!(json.get("status").is_none());
+
+        let taken_down = RepoInfo {
+            active: false,
+            status: Some("takendown".to_string()),
+            ..active
+        };
+        let json = serde_json::to_value(&taken_down).unwrap();
+        assert_eq!(json["status"], "takendown");
+    }
 }
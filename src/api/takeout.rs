@@ -0,0 +1,164 @@
+/// Account data export endpoints
+///
+/// - app.aurora.account.requestExport: start an export (or return the active one)
+/// - app.aurora.account.getExportStatus: progress, plus a signed download link when complete
+/// - app.aurora.account.downloadExport: download an archive via a signed link
+
+use crate::{
+    api::middleware,
+    context::AppContext,
+    error::{PdsError, PdsResult},
+    takeout::{self, AccountExport, ExportStatus},
+};
+use axum::{
+    body::Body,
+    extract::{Query, Request, State},
+    http::{header, HeaderMap, HeaderValue},
+    response::Response,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+
+/// Build export routes
+pub fn routes() -> Router<AppContext> {
+    Router::new()
+        .route("/xrpc/app.aurora.account.requestExport", post(request_export))
+        .route("/xrpc/app.aurora.account.getExportStatus", get(get_export_status))
+        .route("/xrpc/app.aurora.account.downloadExport", get(download_export))
+}
+
+/// Export status response
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportStatusResponse {
+    #[serde(flatten)]
+    pub export: AccountExport,
+    /// Signed download link (only when complete)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub download_url_expires_at: Option<String>,
+}
+
+impl ExportStatusResponse {
+    fn new(ctx: &AppContext, export: AccountExport) -> Self {
+        let (download_url, download_url_expires_at) = if export.status == ExportStatus::Complete {
+            let expires = (chrono::Utc::now()
+                + chrono::Duration::seconds(takeout::manager::DOWNLOAD_LINK_TTL_SECS))
+            .min(export.expires_at.unwrap_or_else(chrono::Utc::now));
+            let sig = ctx.takeout_manager.sign_download(&export.id, expires.timestamp());
+            (
+                Some(format!(
+                    "https://{}/xrpc/app.aurora.account.downloadExport?id={}&expires={}&sig={}",
+                    ctx.config.service.hostname,
+                    export.id,
+                    expires.timestamp(),
+                    sig
+                )),
+                Some(expires.to_rfc3339()),
+            )
+        } else {
+            (None, None)
+        };
+
+        Self {
+            export,
+            download_url,
+            download_url_expires_at,
+        }
+    }
+}
+
+/// Request a full account export
+async fn request_export(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+) -> PdsResult<Json<ExportStatusResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    // Exports include private account data
+    if validated.is_app_password {
+        return Err(PdsError::Authorization(
+            "Cannot request an account export using app password authentication".to_string(),
+        ));
+    }
+
+    let (export, created) = ctx.takeout_manager.request_export(&validated.did).await?;
+    if created {
+        tracing::info!(did = %validated.did, id = %export.id, "account_export_requested");
+        takeout::spawn_export(ctx.clone(), export.clone());
+    }
+
+    Ok(Json(ExportStatusResponse::new(&ctx, export)))
+}
+
+#[derive(Debug, Deserialize)]
+struct ExportStatusParams {
+    /// Export ID (defaults to the most recent export)
+    id: Option<String>,
+}
+
+/// Get export progress and, when complete, a signed download link
+async fn get_export_status(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(params): Query<ExportStatusParams>,
+) -> PdsResult<Json<ExportStatusResponse>> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    if validated.is_app_password {
+        return Err(PdsError::Authorization(
+            "Cannot access account exports using app password authentication".to_string(),
+        ));
+    }
+
+    let export = match params.id {
+        Some(id) => ctx.takeout_manager.get_export(&validated.did, &id).await?,
+        None => ctx.takeout_manager.latest_export(&validated.did).await?,
+    }
+    .ok_or_else(|| PdsError::NotFound("Export not found".to_string()))?;
+
+    Ok(Json(ExportStatusResponse::new(&ctx, export)))
+}
+
+#[derive(Debug, Deserialize)]
+struct DownloadParams {
+    id: String,
+    expires: i64,
+    sig: String,
+}
+
+/// Download an export archive (authorized by the link signature)
+async fn download_export(
+    State(ctx): State<AppContext>,
+    Query(params): Query<DownloadParams>,
+    request: Request,
+) -> PdsResult<Response> {
+    ctx.takeout_manager
+        .verify_download(&params.id, params.expires, &params.sig)?;
+
+    let export = ctx
+        .takeout_manager
+        .get_export_by_id(&params.id)
+        .await?
+        .filter(|e| e.status == ExportStatus::Complete)
+        .ok_or_else(|| PdsError::NotFound("Export not found".to_string()))?;
+
+    let response = ServeFile::new(ctx.takeout_manager.archive_path(&export.id))
+        .oneshot(request)
+        .await
+        .map_err(|e| PdsError::Internal(format!("Failed to serve export: {}", e)))?;
+
+    let mut response = response.map(Body::new);
+    if let Ok(value) = HeaderValue::from_str(&format!(
+        "attachment; filename=\"aurora-export-{}.tar.gz\"",
+        export.created_at.format("%Y%m%d")
+    )) {
+        response.headers_mut().insert(header::CONTENT_DISPOSITION, value);
+    }
+
+    Ok(response)
+}
@@ -0,0 +1,87 @@
+/// Build and version information endpoint
+///
+/// Reports exactly what is running: crate version, the git commit embedded
+/// at build time, and which optional backends are enabled. Useful for
+/// operators and for bug reports.
+
+use crate::{
+    cache::CacheConfig,
+    config::{BlobstoreConfig, ServerConfig},
+    context::AppContext,
+};
+use axum::{extract::State, response::Json, routing::get, Router};
+use serde::Serialize;
+
+/// Git commit hash embedded by build.rs
+pub const GIT_COMMIT: &str = env!("AURORA_GIT_COMMIT");
+
+/// Build information
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    pub commit: &'static str,
+    /// Built from a working tree with uncommitted changes
+    pub dirty: bool,
+    pub profile: &'static str,
+    pub features: FeatureSet,
+}
+
+/// Enabled features and backends
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeatureSet {
+    /// "disk" or "s3"
+    pub blobstore: &'static str,
+    pub redis: bool,
+    pub admin_oauth: bool,
+    /// Admin routes served only on an internal listener
+    pub admin_internal_only: bool,
+    pub federation: bool,
+    pub mirror: bool,
+    pub email: bool,
+    pub invites_required: bool,
+}
+
+impl BuildInfo {
+    /// Collect build information for the running server
+    pub fn collect(config: &ServerConfig) -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            commit: GIT_COMMIT,
+            dirty: env!("AURORA_GIT_DIRTY") == "true",
+            profile: env!("AURORA_BUILD_PROFILE"),
+            features: FeatureSet::from_config(config),
+        }
+    }
+}
+
+impl FeatureSet {
+    fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            blobstore: match config.storage.blobstore {
+                BlobstoreConfig::Disk { .. } => "disk",
+                BlobstoreConfig::S3 { .. } => "s3",
+            },
+            redis: CacheConfig::from_env().enabled,
+            admin_oauth: !config.authentication.oauth.client_id.is_empty(),
+            admin_internal_only: config.admin_listener_only(),
+            federation: config.federation.enabled,
+            mirror: config.mirror.enabled,
+            email: config.email.is_some(),
+            invites_required: config.invites.required,
+        }
+    }
+}
+
+/// Build version routes
+pub fn routes() -> Router<AppContext> {
+    Router::new().route("/version", get(version))
+}
+
+/// Report build and feature information
+async fn version(State(ctx): State<AppContext>) -> Json<BuildInfo> {
+    Json(BuildInfo::collect(&ctx.config))
+}
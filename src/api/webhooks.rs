@@ -0,0 +1,186 @@
+/// Repo event webhook endpoints
+///
+/// - app.aurora.repo.registerWebhook: register an endpoint for events in a repo
+/// - app.aurora.repo.listWebhooks: webhooks registered for a repo
+/// - app.aurora.repo.removeWebhook: remove a webhook and its delivery log
+/// - app.aurora.repo.listWebhookDeliveries: delivery log, newest first
+///
+/// Accounts manage their own repo's webhooks; server-wide admins may pass
+/// `repo` to manage another account's.
+
+use crate::{
+    account::RepoWebhook,
+    admin::{DeliveryStatus, Role, WebhookDelivery},
+    api::middleware,
+    context::AppContext,
+    error::{PdsError, PdsResult},
+};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+/// Build repo webhook routes
+pub fn routes() -> Router<AppContext> {
+    Router::new()
+        .route("/xrpc/app.aurora.repo.registerWebhook", post(register_webhook))
+        .route("/xrpc/app.aurora.repo.listWebhooks", get(list_webhooks))
+        .route("/xrpc/app.aurora.repo.removeWebhook", post(remove_webhook))
+        .route("/xrpc/app.aurora.repo.listWebhookDeliveries", get(list_webhook_deliveries))
+}
+
+/// Repo whose webhooks a request manages
+///
+/// Defaults to the caller's own; another repo needs a server-wide admin role.
+async fn target_repo(ctx: &AppContext, headers: HeaderMap, repo: Option<String>, manage: bool) -> PdsResult<String> {
+    let validated = middleware::require_auth(State(ctx.clone()), headers).await?;
+
+    // Webhook secrets and targets are account settings
+    if manage && validated.is_app_password {
+        return Err(PdsError::Authorization(
+            "Cannot manage webhooks using app password authentication".to_string(),
+        ));
+    }
+
+    let repo = match repo {
+        Some(repo) if repo != validated.did => repo,
+        _ => return Ok(validated.did),
+    };
+
+    let is_admin = ctx.config.authentication.admin_dids.contains(&validated.did)
+        || ctx
+            .admin_role_manager
+            .get_role(&validated.did)
+            .await?
+            .is_some_and(|role| role.domain.is_none() && role.role.can_act_as(Role::Admin));
+    if !is_admin {
+        return Err(PdsError::Authorization(
+            "Only server admins can manage another account's webhooks".to_string(),
+        ));
+    }
+
+    Ok(ctx.account_manager.get_account(&repo).await?.did)
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterWebhookRequest {
+    /// DID of the repo (defaults to the caller's)
+    repo: Option<String>,
+    url: String,
+    /// Event types, e.g. `record.created` or `record.*` (empty means all)
+    #[serde(default)]
+    events: Vec<String>,
+    /// Collections whose record events are sent, e.g. `app.bsky.feed.*` (empty means all)
+    #[serde(default)]
+    collections: Vec<String>,
+    /// Signing secret (generated if unset)
+    secret: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RegisterWebhookResponse {
+    #[serde(flatten)]
+    webhook: RepoWebhook,
+    /// Signing secret; only returned here
+    secret: String,
+}
+
+/// Register a webhook for a repo
+async fn register_webhook(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<RegisterWebhookRequest>,
+) -> PdsResult<Json<RegisterWebhookResponse>> {
+    let did = target_repo(&ctx, headers, req.repo, true).await?;
+
+    let (webhook, secret) = ctx
+        .repo_webhooks
+        .register(&did, &req.url, req.events, req.collections, req.secret)
+        .await?;
+    tracing::info!(did = %did, id = webhook.id, url = %webhook.url, "repo_webhook_registered");
+
+    Ok(Json(RegisterWebhookResponse { webhook, secret }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RepoParams {
+    repo: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListWebhooksResponse {
+    webhooks: Vec<RepoWebhook>,
+}
+
+/// List a repo's webhooks
+async fn list_webhooks(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(params): Query<RepoParams>,
+) -> PdsResult<Json<ListWebhooksResponse>> {
+    let did = target_repo(&ctx, headers, params.repo, false).await?;
+    let webhooks = ctx.repo_webhooks.list(&did).await?;
+    Ok(Json(ListWebhooksResponse { webhooks }))
+}
+
+#[derive(Debug, Deserialize)]
+struct RemoveWebhookRequest {
+    repo: Option<String>,
+    id: i64,
+}
+
+/// Remove a webhook
+async fn remove_webhook(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Json(req): Json<RemoveWebhookRequest>,
+) -> PdsResult<Json<serde_json::Value>> {
+    let did = target_repo(&ctx, headers, req.repo, true).await?;
+    ctx.repo_webhooks.remove(&did, req.id).await?;
+    tracing::info!(did = %did, id = req.id, "repo_webhook_removed");
+    Ok(Json(serde_json::json!({})))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListDeliveriesParams {
+    repo: Option<String>,
+    webhook_id: Option<i64>,
+    /// pending, delivered or failed
+    status: Option<String>,
+    cursor: Option<i64>,
+    limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+struct ListDeliveriesResponse {
+    deliveries: Vec<WebhookDelivery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cursor: Option<String>,
+}
+
+/// List a repo's webhook deliveries, newest first
+async fn list_webhook_deliveries(
+    State(ctx): State<AppContext>,
+    headers: HeaderMap,
+    Query(params): Query<ListDeliveriesParams>,
+) -> PdsResult<Json<ListDeliveriesResponse>> {
+    let did = target_repo(&ctx, headers, params.repo, false).await?;
+    let status = params.status.as_deref().map(DeliveryStatus::from_str).transpose()?;
+    let limit = params.limit.unwrap_or(50).clamp(1, 100);
+
+    let deliveries = ctx
+        .repo_webhooks
+        .list_deliveries(&did, params.webhook_id, status, params.cursor, limit)
+        .await?;
+    let cursor = (deliveries.len() as i64 == limit)
+        .then(|| deliveries.last().map(|d| d.id.to_string()))
+        .flatten();
+
+    Ok(Json(ListDeliveriesResponse { deliveries, cursor }))
+}
@@ -0,0 +1,347 @@
+/// WebSocket upgrades with permessage-deflate (RFC 7692)
+///
+/// axum's `WebSocketUpgrade` can't negotiate extensions, so the firehose does
+/// its own handshake and runs tungstenite directly on the upgraded connection.
+///
+/// Compression is outbound only and uses no context takeover: every message
+/// is deflated independently, so memory per connection stays flat and a
+/// slow client never pins a compression window. Small messages are sent
+/// uncompressed. Clients are told not to compress either; a compressed
+/// frame from a client ends the connection.
+use crate::error::{PdsError, PdsResult};
+use axum::{
+    async_trait,
+    body::Body,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderMap, HeaderValue, StatusCode},
+    response::Response,
+};
+use flate2::{Compress, Compression, FlushCompress};
+use futures::{sink::SinkExt, stream::SplitSink};
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper_util::rt::TokioIo;
+use std::future::Future;
+use tokio_tungstenite::{
+    tungstenite::{
+        handshake::derive_accept_key,
+        protocol::{
+            frame::coding::{Data, OpCode},
+            Frame, Role, WebSocketConfig,
+        },
+        Message,
+    },
+    WebSocketStream,
+};
+
+/// Extension parameters sent back when permessage-deflate is accepted
+const DEFLATE_RESPONSE: &str =
+    "permessage-deflate; server_no_context_takeover; client_no_context_takeover";
+
+/// Messages shorter than this are not worth compressing
+const MIN_COMPRESS_BYTES: usize = 256;
+
+/// Largest message accepted from a client
+const MAX_INBOUND_MESSAGE_BYTES: usize = 64 * 1024;
+
+/// Trailer that ends every sync-flushed deflate block (RFC 7692 section 7.2.1)
+const DEFLATE_TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// An upgraded WebSocket connection
+pub type WsStream = WebSocketStream<TokioIo<Upgraded>>;
+
+/// WebSocket upgrade request
+pub struct WsUpgrade {
+    key: HeaderValue,
+    on_upgrade: OnUpgrade,
+    offers_deflate: bool,
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for WsUpgrade {
+    type Rejection = PdsError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let headers = &parts.headers;
+        let has_token = |name: header::HeaderName, token: &str| {
+            headers.get_all(name).iter().any(|value| {
+                value
+                    .to_str()
+                    .map(|v| v.split(',').any(|t| t.trim().eq_ignore_ascii_case(token)))
+                    .unwrap_or(false)
+            })
+        };
+
+        if !has_token(header::CONNECTION, "upgrade") || !has_token(header::UPGRADE, "websocket") {
+            return Err(PdsError::Validation("Expected a WebSocket upgrade request".to_string()));
+        }
+        if headers.get(header::SEC_WEBSOCKET_VERSION).map(|v| v.as_bytes()) != Some(b"13") {
+            return Err(PdsError::Validation("Unsupported WebSocket version".to_string()));
+        }
+        let key = headers
+            .get(header::SEC_WEBSOCKET_KEY)
+            .cloned()
+            .ok_or_else(|| PdsError::Validation("Missing Sec-WebSocket-Key".to_string()))?;
+        let on_upgrade = parts
+            .extensions
+            .remove::<OnUpgrade>()
+            .ok_or_else(|| PdsError::Internal("Connection cannot be upgraded".to_string()))?;
+
+        Ok(Self {
+            key,
+            on_upgrade,
+            offers_deflate: accepts_deflate_offer(headers),
+        })
+    }
+}
+
+impl WsUpgrade {
+    /// Finish the handshake and run `callback` on the connection
+    ///
+    /// `compression` enables permessage-deflate at `level` if the client
+    /// offered it.
+    pub fn on_upgrade<F, Fut>(self, compression: bool, level: u32, callback: F) -> Response
+    where
+        F: FnOnce(WsStream, Option<Deflater>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        let deflate = compression && self.offers_deflate;
+
+        let mut builder = Response::builder()
+            .status(StatusCode::SWITCHING_PROTOCOLS)
+            .header(header::CONNECTION, "upgrade")
+            .header(header::UPGRADE, "websocket")
+            .header(header::SEC_WEBSOCKET_ACCEPT, derive_accept_key(self.key.as_bytes()));
+        if deflate {
+            builder = builder.header(header::SEC_WEBSOCKET_EXTENSIONS, DEFLATE_RESPONSE);
+        }
+
+        let on_upgrade = self.on_upgrade;
+        tokio::spawn(async move {
+            let upgraded = match on_upgrade.await {
+                Ok(upgraded) => upgraded,
+                Err(e) => {
+                    tracing::debug!("WebSocket upgrade failed: {}", e);
+                    return;
+                }
+            };
+
+            let config = WebSocketConfig {
+                max_message_size: Some(MAX_INBOUND_MESSAGE_BYTES),
+                max_frame_size: Some(MAX_INBOUND_MESSAGE_BYTES),
+                ..Default::default()
+            };
+            let stream = WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, Some(config)).await;
+            callback(stream, deflate.then(|| Deflater::new(level))).await;
+        });
+
+        builder.body(Body::empty()).unwrap_or_else(|_| {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            response
+        })
+    }
+}
+
+/// Whether any permessage-deflate offer can be accepted as-is
+///
+/// Offers asking for a smaller server window than the default 15 bits, or
+/// with unknown or repeated parameters, are declined.
+fn accepts_deflate_offer(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(acceptable_offer)
+}
+
+fn acceptable_offer(offer: &str) -> bool {
+    let mut parts = offer.split(';').map(str::trim);
+    if parts.next() != Some("permessage-deflate") {
+        return false;
+    }
+
+    let mut seen = Vec::new();
+    for param in parts.filter(|p| !p.is_empty()) {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        if seen.contains(&name) {
+            return false;
+        }
+        seen.push(name);
+
+        let ok = match name {
+            "server_no_context_takeover" | "client_no_context_takeover" => value.is_none(),
+            "client_max_window_bits" => value.map_or(true, valid_window_bits),
+            "server_max_window_bits" => value == Some("15"),
+            _ => false,
+        };
+        if !ok {
+            return false;
+        }
+    }
+
+    true
+}
+
+fn valid_window_bits(value: &str) -> bool {
+    matches!(value.parse::<u8>(), Ok(8..=15))
+}
+
+/// Per-message deflate without context takeover
+pub struct Deflater {
+    compress: Compress,
+}
+
+impl Deflater {
+    pub fn new(level: u32) -> Self {
+        Self {
+            compress: Compress::new(Compression::new(level.min(9)), false),
+        }
+    }
+
+    /// Compress one message payload (raw deflate, trailer removed)
+    pub fn compress(&mut self, data: &[u8]) -> PdsResult<Vec<u8>> {
+        self.compress.reset();
+
+        let mut out = Vec::with_capacity(data.len() / 2 + 64);
+        let mut input = data;
+        loop {
+            if out.capacity() - out.len() < 64 {
+                out.reserve(out.capacity().max(1024));
+            }
+            let before = self.compress.total_in();
+            self.compress
+                .compress_vec(input, &mut out, FlushCompress::Sync)
+                .map_err(|e| PdsError::Internal(format!("Deflate failed: {}", e)))?;
+            input = &input[(self.compress.total_in() - before) as usize..];
+
+            // The flush is complete once all input is consumed and output space is left over
+            if input.is_empty() && out.len() < out.capacity() {
+                break;
+            }
+        }
+
+        if out.ends_with(&DEFLATE_TRAILER) {
+            out.truncate(out.len() - DEFLATE_TRAILER.len());
+        }
+        Ok(out)
+    }
+}
+
+/// Sending half of an upgraded connection, compressing when negotiated
+pub struct WsSender {
+    sink: SplitSink<WsStream, Message>,
+    deflater: Option<Deflater>,
+    /// Bytes of message payload put on the wire
+    bytes_sent: u64,
+    /// Payload bytes before compression
+    uncompressed_bytes: u64,
+}
+
+impl WsSender {
+    pub fn new(sink: SplitSink<WsStream, Message>, deflater: Option<Deflater>) -> Self {
+        Self {
+            sink,
+            deflater,
+            bytes_sent: 0,
+            uncompressed_bytes: 0,
+        }
+    }
+
+    /// Whether permessage-deflate is in use
+    pub fn compressed(&self) -> bool {
+        self.deflater.is_some()
+    }
+
+    /// Send a text message, deflating it if negotiated and worthwhile
+    pub async fn send_text(&mut self, text: String) -> PdsResult<()> {
+        self.uncompressed_bytes += text.len() as u64;
+
+        let message = match &mut self.deflater {
+            Some(deflater) if text.len() >= MIN_COMPRESS_BYTES => {
+                let payload = deflater.compress(text.as_bytes())?;
+                self.bytes_sent += payload.len() as u64;
+                let mut frame = Frame::message(payload, OpCode::Data(Data::Text), true);
+                frame.header_mut().rsv1 = true;
+                Message::Frame(frame)
+            }
+            _ => {
+                self.bytes_sent += text.len() as u64;
+                Message::Text(text)
+            }
+        };
+
+        self.send(message).await
+    }
+
+    /// Send a message as-is (control frames)
+    pub async fn send(&mut self, message: Message) -> PdsResult<()> {
+        self.sink
+            .send(message)
+            .await
+            .map_err(|e| PdsError::Internal(format!("WebSocket send failed: {}", e)))
+    }
+
+    /// Payload bytes sent, after compression
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    /// Payload bytes sent, before compression
+    pub fn uncompressed_bytes(&self) -> u64 {
+        self.uncompressed_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::{Decompress, FlushDecompress};
+
+    fn offer(value: &str) -> bool {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::SEC_WEBSOCKET_EXTENSIONS, HeaderValue::from_str(value).unwrap());
+        accepts_deflate_offer(&headers)
+    }
+
+    #[test]
+    fn test_deflate_negotiation() {
+        assert!(offer("permessage-deflate"));
+        assert!(offer("permessage-deflate; client_max_window_bits"));
+        assert!(offer("permessage-deflate; server_no_context_takeover; client_max_window_bits=10"));
+        // A declined offer falls through to the next one
+        assert!(offer("permessage-deflate; server_max_window_bits=10, permessage-deflate"));
+
+        assert!(!offer("x-webkit-deflate-frame"));
+        assert!(!offer("permessage-deflate; server_max_window_bits=10"));
+        assert!(!offer("permessage-deflate; client_max_window_bits=20"));
+        assert!(!offer("permessage-deflate; unknown_param"));
+        assert!(!offer("permessage-deflate; server_no_context_takeover; server_no_context_takeover"));
+        assert!(!accepts_deflate_offer(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn test_deflate_round_trip() {
+        let mut deflater = Deflater::new(6);
+        let message = r##"{"$type":"#commit","repo":"did:plc:abc","ops":[]}"##.repeat(50);
+
+        // Same output each time: no context carries over between messages
+        let first = deflater.compress(message.as_bytes()).unwrap();
+        let second = deflater.compress(message.as_bytes()).unwrap();
+        assert_eq!(first, second);
+        assert!(first.len() < message.len());
+        assert!(!first.ends_with(&DEFLATE_TRAILER));
+
+        // Receivers append the trailer before inflating
+        let mut input = first.clone();
+        input.extend_from_slice(&DEFLATE_TRAILER);
+        let mut output = Vec::with_capacity(message.len() * 2);
+        Decompress::new(false)
+            .decompress_vec(&input, &mut output, FlushDecompress::Sync)
+            .unwrap();
+        assert_eq!(output, message.as_bytes());
+    }
+}
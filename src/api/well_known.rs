@@ -1,38 +1,104 @@
 /// Well-known endpoints
 /// Handles /.well-known/* endpoints for DID resolution and other standards
 use crate::{
+    api::{conditional, middleware::request_host},
     context::AppContext,
-    crypto::plc::PlcSigner,
     error::{PdsError, PdsResult},
 };
+use k256::ecdsa::VerifyingKey;
 use atproto::did_doc::{DidDocument, Service, VerificationMethod};
 use axum::{
-    extract::State,
-    http::{header, StatusCode},
-    response::{Json, Response},
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Json, Response},
     routing::get,
     Router,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use serde::Deserialize;
+use serde_json::json;
+
+/// Schema identifier of the nodeinfo document we serve
+const NODEINFO_SCHEMA: &str = "http://nodeinfo.diaspora.software/ns/schema/2.1";
 
 /// Build well-known routes
 pub fn routes() -> Router<AppContext> {
     Router::new()
         .route("/.well-known/atproto-did", get(atproto_did))
         .route("/.well-known/did.json", get(did_document))
+        .route("/.well-known/nodeinfo", get(nodeinfo_links))
+        .route("/nodeinfo/2.1", get(nodeinfo))
+        .route("/.well-known/webfinger", get(webfinger))
+        .route("/.well-known/jwks.json", get(jwks))
+}
+
+/// How long clients may cache the JWKS document
+///
+/// Short, because a rotation signs with the new key right away; verifiers
+/// should also refetch when they see an unknown `kid`.
+const JWKS_MAX_AGE_SECS: u64 = 300;
+
+/// /.well-known/jwks.json
+///
+/// Public keys that verify tokens this server signs: the service DID's
+/// atproto key, which signs its ES256K service auth tokens, and the ES256
+/// JWT signing keys behind access and admin OAuth tokens, including retired
+/// keys still in their grace period. HS256 keys are never published. The
+/// document is built from the key backend and the JWT keyring on each
+/// request, so it follows rotations without a restart.
+pub async fn jwks(State(ctx): State<AppContext>, headers: HeaderMap) -> PdsResult<Response> {
+    let service_did = ctx.service_did();
+    let service_key = ctx.signing_keys.public_key(service_did).await?;
+
+    let mut keys = vec![secp256k1_jwk(&service_key, &format!("{}#atproto", service_did))];
+    keys.extend(ctx.jwt_keys.public_jwks());
+    let doc = json!({ "keys": keys });
+
+    let etag = conditional::etag(&conditional::digest(&doc));
+    Ok(conditional::json(&headers, &etag, &conditional::cache_control(JWKS_MAX_AGE_SECS), doc))
+}
+
+/// JWK of a secp256k1 verification key
+fn secp256k1_jwk(key: &VerifyingKey, kid: &str) -> serde_json::Value {
+    let point = key.to_encoded_point(false);
+    let coordinate = |c: Option<&k256::FieldBytes>| c.map(|c| URL_SAFE_NO_PAD.encode(c)).unwrap_or_default();
+    json!({
+        "kty": "EC",
+        "crv": "secp256k1",
+        "x": coordinate(point.x()),
+        "y": coordinate(point.y()),
+        "use": "sig",
+        "alg": "ES256K",
+        "kid": kid,
+    })
 }
 
 /// /.well-known/atproto-did
 ///
-/// Returns the DID for this PDS server in plain text
-/// Used for did:web resolution
-pub async fn atproto_did(State(ctx): State<AppContext>) -> PdsResult<Response> {
-    let did = ctx.service_did();
+/// Returns the DID for the requested host in plain text. A request for the
+/// service hostname gets the service DID (did:web resolution); a request
+/// for an account handle under one of the service handle domains gets that
+/// account's DID (HTTPS handle resolution).
+pub async fn atproto_did(State(ctx): State<AppContext>, headers: HeaderMap) -> PdsResult<Response> {
+    let host = request_host(&headers);
+    let did = match host {
+        Some(host) if host != ctx.config.service.hostname.to_ascii_lowercase() => {
+            if ctx.config.virtual_host(&host).is_none() {
+                return Err(PdsError::NotFound(format!("Unknown host: {}", host)));
+            }
+            ctx.account_manager
+                .did_for_handle(&host)
+                .await?
+                .ok_or_else(|| PdsError::NotFound(format!("No account for handle: {}", host)))?
+        }
+        _ => ctx.service_did().to_string(),
+    };
 
     // Return plain text DID
     let response = Response::builder()
         .status(StatusCode::OK)
         .header(header::CONTENT_TYPE, "text/plain")
-        .body(did.to_string().into())
+        .body(did.into())
         .map_err(|e| {
             crate::error::PdsError::Internal(format!("Failed to build response: {}", e))
         })?;
@@ -42,34 +108,211 @@ pub async fn atproto_did(State(ctx): State<AppContext>) -> PdsResult<Response> {
 
 /// /.well-known/did.json
 ///
-/// Returns the full DID document for this PDS server
-/// Used for did:web DID resolution
-pub async fn did_document(State(ctx): State<AppContext>) -> PdsResult<Json<DidDocument>> {
-    let did = ctx.service_did().to_string();
-
-    // Generate the DID document with verification methods and services
-    let doc = generate_did_document(&ctx, &did).await?;
+/// Returns the DID document for did:web resolution. A request for the
+/// service hostname gets the server's own document, generated from config:
+/// the `#atproto_pds` endpoint and the key that service auth tokens issued
+/// by the service DID are signed with. It is only served when the service
+/// DID is the did:web of the hostname. A request for the handle host of a
+/// local `did:web:<handle>` account gets that account's document, with its
+/// own signing key.
+pub async fn did_document(State(ctx): State<AppContext>, headers: HeaderMap) -> PdsResult<Json<DidDocument>> {
+    let host = request_host(&headers);
+    let doc = match host {
+        Some(host) if host != ctx.config.service.hostname.to_ascii_lowercase() => {
+            let did = format!("did:web:{}", host);
+            if ctx.config.virtual_host(&host).is_none()
+                || ctx.account_manager.did_for_handle(&host).await?.as_deref() != Some(did.as_str())
+            {
+                return Err(PdsError::NotFound(format!("No did:web account for host: {}", host)));
+            }
+            let key = ctx.signing_keys.public_key(&did).await?;
+            generate_did_document(&ctx, &did, &key, vec![format!("at://{}", host)])?
+        }
+        _ => {
+            let did = ctx.service_did().to_string();
+            // Any other service DID (e.g. did:plc) is published elsewhere
+            let hostname = ctx.config.service.hostname.to_ascii_lowercase();
+            let with_port = format!("{}:{}", hostname, ctx.config.service.port);
+            if !did_web_host(&did).is_some_and(|host| host == hostname || host == with_port) {
+                return Err(PdsError::NotFound(format!("Service DID {} is not a did:web for this host", did)));
+            }
+            let key = ctx.signing_keys.public_key(&did).await?;
+            generate_did_document(&ctx, &did, &key, vec![])?
+        }
+    };
 
     Ok(Json(doc))
 }
 
+/// Host a did:web DID without a path resolves at (`%3A` decoded to a port)
+fn did_web_host(did: &str) -> Option<String> {
+    let id = did.strip_prefix("did:web:")?;
+    if id.contains(':') {
+        return None;
+    }
+    Some(id.replace("%3A", ":").replace("%3a", ":").to_ascii_lowercase())
+}
+
+/// /.well-known/nodeinfo
+///
+/// Points nodeinfo clients (fediverse crawlers, instance monitors) at the
+/// nodeinfo 2.1 document.
+pub async fn nodeinfo_links(State(ctx): State<AppContext>) -> PdsResult<Json<serde_json::Value>> {
+    if !ctx.config.nodeinfo.enabled {
+        return Err(PdsError::NotFound("nodeinfo is disabled".to_string()));
+    }
+
+    Ok(Json(json!({
+        "links": [{
+            "rel": NODEINFO_SCHEMA,
+            "href": format!("{}/nodeinfo/2.1", ctx.service_url()),
+        }]
+    })))
+}
+
+/// /nodeinfo/2.1
+///
+/// Software, usage and registration status. Counts come from the cached
+/// instance stats, so they can be up to one `instance_stats` run old.
+pub async fn nodeinfo(State(ctx): State<AppContext>) -> PdsResult<Response> {
+    if !ctx.config.nodeinfo.enabled {
+        return Err(PdsError::NotFound("nodeinfo is disabled".to_string()));
+    }
+
+    let stats = ctx.instance_stats.get().await?;
+    let open_registrations = !ctx.signup_throttle.in_lockdown()
+        && (!ctx.config.invites.required
+            || ctx.config.virtual_hosts.iter().any(|vhost| vhost.invite_required == Some(false)));
+
+    let doc = json!({
+        "version": "2.1",
+        "software": {
+            "name": "aurora-locus",
+            "version": env!("CARGO_PKG_VERSION"),
+        },
+        "protocols": ["atproto"],
+        "services": { "inbound": [], "outbound": [] },
+        "openRegistrations": open_registrations,
+        "usage": {
+            "users": {
+                "total": stats.total_users,
+                "activeMonth": stats.active_month,
+                "activeHalfyear": stats.active_halfyear,
+            },
+            "localPosts": stats.local_posts,
+        },
+        "metadata": {
+            "did": ctx.service_did(),
+            "availableUserDomains": ctx.config.identity.service_handle_domains,
+            "statsUpdatedAt": stats.refreshed_at.to_rfc3339(),
+        },
+    });
+
+    let content_type = format!("application/json; profile=\"{}#\"", NODEINFO_SCHEMA);
+    Ok(([(header::CONTENT_TYPE, content_type)], Json(doc)).into_response())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct WebFingerQuery {
+    resource: String,
+}
+
+/// /.well-known/webfinger
+///
+/// Resolves `acct:<handle>` (or `acct:<name>@<domain>` for the handle
+/// `<name>.<domain>`) to the account's DID. Off unless
+/// `PDS_NODEINFO_WEBFINGER` is set, except for accounts bridged to
+/// ActivityPub, whose `acct:<name>@<hostname>` addresses (where `<name>`
+/// may be a whole custom-domain handle) resolve to their actor.
+pub async fn webfinger(
+    State(ctx): State<AppContext>,
+    Query(query): Query<WebFingerQuery>,
+) -> PdsResult<Response> {
+    if !ctx.config.nodeinfo.webfinger && !ctx.activitypub.enabled() {
+        return Err(PdsError::NotFound("WebFinger is disabled".to_string()));
+    }
+
+    let handle = webfinger_handle(&query.resource)
+        .ok_or_else(|| PdsError::Validation(format!("Unsupported resource: {}", query.resource)))?;
+    let mut found = ctx.account_manager.did_for_handle(&handle).await?.map(|did| (did, handle.clone()));
+    if found.is_none() {
+        if let Some(local) = webfinger_local_handle(&query.resource, &ctx.config.service.hostname) {
+            found = ctx.account_manager.did_for_handle(&local).await?.map(|did| (did, local));
+        }
+    }
+    let (did, handle) = found.ok_or_else(|| PdsError::NotFound(format!("No account for handle: {}", handle)))?;
+
+    let bridged = ctx.activitypub.enabled() && ctx.activitypub.is_bridged(&did).await?;
+    if !bridged && !ctx.config.nodeinfo.webfinger {
+        return Err(PdsError::NotFound(format!("No account for handle: {}", handle)));
+    }
+
+    let mut jrd = json!({
+        "subject": format!("acct:{}", handle),
+        "aliases": [did, format!("at://{}", did)],
+        "links": [{
+            "rel": "self",
+            "type": "application/json",
+            "href": format!("{}/xrpc/com.atproto.repo.describeRepo?repo={}", ctx.service_url(), did),
+        }],
+    });
+    if bridged {
+        let actor = ctx.activitypub.actor_url(&did);
+        jrd["subject"] = json!(format!("acct:{}", ctx.activitypub.acct(&handle)));
+        jrd["aliases"] = json!([actor, did, format!("at://{}", did)]);
+        jrd["links"] = json!([
+            { "rel": "self", "type": crate::activitypub::ACTIVITY_JSON, "href": actor },
+            jrd["links"][0].clone(),
+        ]);
+    }
+
+    Ok(([(header::CONTENT_TYPE, "application/jrd+json")], Json(jrd)).into_response())
+}
+
+/// Handle named by a WebFinger `acct:` resource
+fn webfinger_handle(resource: &str) -> Option<String> {
+    let account = resource.trim().strip_prefix("acct:")?;
+    let account = account.strip_prefix('@').unwrap_or(account);
+    let handle = match account.split_once('@') {
+        Some((name, domain)) => format!("{}.{}", name, domain),
+        None => account.to_string(),
+    };
+    crate::account::normalize_handle(&handle).ok()
+}
+
+/// Whole handle named by `acct:<handle>@<hostname>`, the ActivityPub address
+/// of handles outside the server's domain
+fn webfinger_local_handle(resource: &str, hostname: &str) -> Option<String> {
+    let account = resource.trim().strip_prefix("acct:")?;
+    let (name, domain) = account.strip_prefix('@').unwrap_or(account).rsplit_once('@')?;
+    if !domain.eq_ignore_ascii_case(hostname) || !name.contains('.') {
+        return None;
+    }
+    crate::account::normalize_handle(name).ok()
+}
+
 /// Generate a complete DID document for a did:web DID
 ///
 /// Creates a DID document containing:
 /// - Service endpoints (PDS URL)
 /// - Verification methods (signing keys)
 /// - Also known as (handles)
-async fn generate_did_document(ctx: &AppContext, did: &str) -> PdsResult<DidDocument> {
+fn generate_did_document(
+    ctx: &AppContext,
+    did: &str,
+    signing_key: &VerifyingKey,
+    also_known_as: Vec<String>,
+) -> PdsResult<DidDocument> {
     // Build service endpoint
-    let service_url = ctx.service_url();
+    let service_url = ctx.public_url();
     let service = Service {
         id: format!("{}#atproto_pds", did),
         service_type: "AtprotoPersonalDataServer".to_string(),
         service_endpoint: service_url,
     };
 
-    // Build verification method from repo signing key
-    let verification_method = generate_verification_method(ctx, did)?;
+    // Build verification method from the signing key
+    let verification_method = generate_verification_method(did, signing_key)?;
 
     // Build DID document
     let doc = DidDocument {
@@ -79,7 +322,7 @@ async fn generate_did_document(ctx: &AppContext, did: &str) -> PdsResult<DidDocu
             "https://w3id.org/security/suites/secp256k1-2019/v1"
         ])),
         id: did.to_string(),
-        also_known_as: vec![], // Could be populated with service handle
+        also_known_as,
         service: vec![service],
         verification_method: vec![verification_method],
     };
@@ -87,14 +330,10 @@ async fn generate_did_document(ctx: &AppContext, did: &str) -> PdsResult<DidDocu
     Ok(doc)
 }
 
-/// Generate verification method from repository signing key
-fn generate_verification_method(ctx: &AppContext, did: &str) -> PdsResult<VerificationMethod> {
-    // Load repo signing key from config
-    let repo_key_hex = &ctx.config.authentication.repo_signing_key;
-    let signer = PlcSigner::from_hex(repo_key_hex)?;
-
+/// Generate the `#atproto` verification method for a signing key
+fn generate_verification_method(did: &str, verifying_key: &VerifyingKey) -> PdsResult<VerificationMethod> {
     // Get public key in multibase format
-    let public_key_multibase = generate_multibase_key(&signer)?;
+    let public_key_multibase = generate_multibase_key(verifying_key)?;
 
     Ok(VerificationMethod {
         id: format!("{}#atproto", did),
@@ -104,15 +343,10 @@ fn generate_verification_method(ctx: &AppContext, did: &str) -> PdsResult<Verifi
     })
 }
 
-/// Generate multibase-encoded public key from signer
+/// Generate multibase-encoded public key
 ///
 /// Uses base58btc encoding with 'z' prefix (multibase format)
-fn generate_multibase_key(signer: &PlcSigner) -> PdsResult<String> {
-    use k256::ecdsa::VerifyingKey;
-
-    // Get the verifying key from the signing key
-    let verifying_key: VerifyingKey = signer.verifying_key();
-
+fn generate_multibase_key(verifying_key: &VerifyingKey) -> PdsResult<String> {
     // Get compressed public key (33 bytes: 1 byte prefix + 32 bytes X coordinate)
     let public_key_bytes = verifying_key.to_encoded_point(true);
     let compressed_bytes = public_key_bytes.as_bytes();
@@ -128,6 +362,7 @@ fn generate_multibase_key(signer: &PlcSigner) -> PdsResult<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::crypto::plc::PlcSigner;
     use crate::config::*;
     use std::path::PathBuf;
 
@@ -137,6 +372,44 @@ mod tests {
         assert_eq!("/.well-known/atproto-did", "/.well-known/atproto-did");
     }
 
+    #[test]
+    fn test_secp256k1_jwk() {
+        let key = k256::ecdsa::SigningKey::from_slice(&[7u8; 32]).unwrap();
+        let jwk = secp256k1_jwk(key.verifying_key(), "did:web:localhost#atproto");
+
+        assert_eq!(jwk["crv"], "secp256k1");
+        assert_eq!(jwk["alg"], "ES256K");
+        assert_eq!(jwk["kid"], "did:web:localhost#atproto");
+        for coordinate in ["x", "y"] {
+            let bytes = URL_SAFE_NO_PAD.decode(jwk[coordinate].as_str().unwrap()).unwrap();
+            assert_eq!(bytes.len(), 32);
+        }
+    }
+
+    #[test]
+    fn test_did_web_host() {
+        assert_eq!(did_web_host("did:web:pds.example.com").as_deref(), Some("pds.example.com"));
+        assert_eq!(did_web_host("did:web:Localhost%3A2583").as_deref(), Some("localhost:2583"));
+        assert_eq!(did_web_host("did:web:example.com:user:alice"), None);
+        assert_eq!(did_web_host("did:plc:abc123"), None);
+    }
+
+    #[test]
+    fn test_webfinger_handle() {
+        assert_eq!(webfinger_handle("acct:alice.example.com").as_deref(), Some("alice.example.com"));
+        assert_eq!(webfinger_handle("acct:Alice@example.com").as_deref(), Some("alice.example.com"));
+        assert_eq!(webfinger_handle("acct:@alice.example.com").as_deref(), Some("alice.example.com"));
+        assert_eq!(webfinger_handle("https://example.com/alice"), None);
+        assert_eq!(webfinger_handle("acct:"), None);
+
+        assert_eq!(
+            webfinger_local_handle("acct:alice.com@pds.example", "pds.example").as_deref(),
+            Some("alice.com")
+        );
+        assert_eq!(webfinger_local_handle("acct:alice@pds.example", "pds.example"), None);
+        assert_eq!(webfinger_local_handle("acct:alice.com@other.example", "pds.example"), None);
+    }
+
     fn create_test_config() -> ServerConfig {
         ServerConfig {
             service: ServiceConfig {
@@ -145,6 +418,17 @@ mod tests {
                 service_did: "did:web:localhost".to_string(),
                 version: "0.1.0".to_string(),
                 blob_upload_limit: 5242880,
+                blob_audio_upload_limit: 10485760,
+                blob_account_quota: None,
+                blob_animated_thumbnails: false,
+                blob_temp_ttl_hours: 24,
+                blob_max_staged_per_account: None,
+                listeners: vec![ListenerConfig {
+                    address: "127.0.0.1:2583".to_string(),
+                    role: ListenerRole::All,
+                }],
+                dev_mode: false,
+                pretty_json: false,
             },
             storage: StorageConfig {
                 data_directory: PathBuf::from("./data"),
@@ -162,12 +446,19 @@ mod tests {
                 admin_password: "test_password".to_string(),
                 repo_signing_key: "a".repeat(64), // Valid hex key
                 plc_rotation_key: "b".repeat(64), // Valid hex key
+                admin_network_token: None,
+                password_hash: crate::config::PasswordHashConfig::default(),
+                jwt_keys: crate::config::JwtKeysConfig::default(),
             },
             identity: IdentityConfig {
                 did_plc_url: "https://plc.directory".to_string(),
                 service_handle_domains: vec![".localhost".to_string()],
                 did_cache_stale_ttl: 3600,
                 did_cache_max_ttl: 86400,
+                handle_doh_url: "https://cloudflare-dns.com/dns-query".to_string(),
+                handle_redirect_secs: 0,
+                handle_changes: crate::config::HandleChangeConfig::default(),
+                plc_failover: crate::config::PlcFailoverConfig::default(),
             },
             email: None,
             invites: InviteConfig {
@@ -181,7 +472,39 @@ mod tests {
             },
             logging: LoggingConfig {
                 level: "info".to_string(),
+                audit_log_retention_days: None,
+                tombstone_retention_days: None,
+            },
+            mirror: MirrorConfig {
+                enabled: false,
+                source_url: "https://bsky.network".to_string(),
+                dids: vec![],
             },
+            content_policy: ContentPolicyConfig::default(),
+            registration_challenge: RegistrationChallengeConfig::default(),
+            signup_throttle: SignupThrottleConfig::default(),
+            report_service: None,
+            bsky_app_view: None,
+            proxy: ProxyConfig::default(),
+            keys: KeyConfig::default(),
+            encryption: EncryptionConfig::default(),
+            cors: CorsConfig::default(),
+            security_headers: SecurityHeadersConfig::default(),
+            firehose: FirehoseConfig::default(),
+            http_cache: HttpCacheConfig::default(),
+            jobs: JobsConfig::default(),
+            compaction: CompactionConfig::default(),
+            trusted_proxies: TrustedProxyConfig::default(),
+            record_history: RecordHistoryConfig::default(),
+            write_hooks: WriteHooksConfig::default(),
+            nodeinfo: NodeInfoConfig::default(),
+            describe_server: DescribeServerConfig::default(),
+            replica: ReplicaConfig::default(),
+            repo_webhooks: RepoWebhookConfig::default(),
+            activitypub: ActivityPubConfig::default(),
+            feeds: FeedsConfig::default(),
+            body_limits: BodyLimitConfig::default(),
+            virtual_hosts: vec![],
         }
     }
 
@@ -191,7 +514,7 @@ mod tests {
         let test_key = vec![0x42u8; 32]; // Valid 32-byte key
         let signer = PlcSigner::new(&test_key).unwrap();
 
-        let result = generate_multibase_key(&signer);
+        let result = generate_multibase_key(&signer.verifying_key());
 
         assert!(result.is_ok());
         let multibase = result.unwrap();
@@ -214,8 +537,8 @@ mod tests {
         let signer1 = PlcSigner::new(&test_key).unwrap();
         let signer2 = PlcSigner::new(&test_key).unwrap();
 
-        let multibase1 = generate_multibase_key(&signer1).unwrap();
-        let multibase2 = generate_multibase_key(&signer2).unwrap();
+        let multibase1 = generate_multibase_key(&signer1.verifying_key()).unwrap();
+        let multibase2 = generate_multibase_key(&signer2.verifying_key()).unwrap();
 
         assert_eq!(multibase1, multibase2);
     }
@@ -227,7 +550,7 @@ mod tests {
 
         // Test verification method generation with config
         let signer = PlcSigner::from_hex(&config.authentication.repo_signing_key).unwrap();
-        let multibase = generate_multibase_key(&signer).unwrap();
+        let multibase = generate_multibase_key(&signer.verifying_key()).unwrap();
 
         // Verify multibase format
         assert!(multibase.starts_with('z'));
@@ -4,6 +4,7 @@ use crate::{
     admin::Role,
     api::middleware::extract_bearer_token,
     context::AppContext,
+    crypto::jwt_keys::JwtKeyring,
     error::PdsError,
 };
 use axum::{
@@ -84,6 +85,21 @@ pub struct AdminAuthContext {
     pub did: String,
     pub session: ValidatedSession,
     pub role: Role,
+    /// Handle domain this admin is limited to (None = server-wide)
+    pub domain: Option<String>,
+}
+
+impl AdminAuthContext {
+    /// Whether this admin may act on accounts with `handle`
+    pub fn covers_handle(&self, handle: &str) -> bool {
+        match &self.domain {
+            None => true,
+            Some(domain) => {
+                let handle = handle.to_ascii_lowercase();
+                handle == *domain || handle.ends_with(&format!(".{}", domain))
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -108,7 +124,7 @@ impl FromRequestParts<AppContext> for AdminAuthContext {
                 // Session validation failed, try JWT validation for admin-only tokens
                 tracing::debug!("AdminAuthContext: Session validation failed, trying JWT validation");
 
-                let token_data = verify_jwt_token(&token, &state.config.authentication.jwt_secret)?;
+                let token_data = verify_jwt_token(&token, &state.jwt_keys)?;
 
                 // Extract DID from JWT claims
                 let claims = &token_data.claims;
@@ -131,6 +147,7 @@ impl FromRequestParts<AppContext> for AdminAuthContext {
                     did: did.clone(),
                     session_id: format!("jwt-{}", Uuid::new_v4()),
                     is_app_password: false,
+                    scopes: None,
                 };
 
                 (did, session)
@@ -143,14 +160,14 @@ impl FromRequestParts<AppContext> for AdminAuthContext {
         let is_configured_admin = state.config.authentication.admin_dids.contains(&did);
 
         // Try to get role from database
-        let role = if let Some(admin_role) = state.admin_role_manager.get_role(&did).await? {
+        let (role, domain) = if let Some(admin_role) = state.admin_role_manager.get_role(&did).await? {
             // User has a role in the database
             tracing::info!("AdminAuthContext: User {} has role {} from database", did, admin_role.role.as_str());
-            admin_role.role
+            (admin_role.role, admin_role.domain)
         } else if is_configured_admin {
             // User is in configured admin DIDs, grant SuperAdmin
             tracing::info!("AdminAuthContext: User {} is configured admin, granting SuperAdmin", did);
-            Role::SuperAdmin
+            (Role::SuperAdmin, None)
         } else {
             // User is not an admin
             tracing::warn!("AdminAuthContext: User {} is not an admin", did);
@@ -163,6 +180,7 @@ impl FromRequestParts<AppContext> for AdminAuthContext {
             did,
             session,
             role,
+            domain,
         })
     }
 }
@@ -184,37 +202,21 @@ macro_rules! require_admin_role {
 /// Verify a JWT token with full validation
 ///
 /// This performs:
-/// 1. JWT signature verification
+/// 1. JWT signature verification (with the signing key named by `kid`)
 /// 2. Expiration checking
 /// 3. Claims validation
-pub fn verify_jwt_token(token: &str, jwt_secret: &str) -> Result<jsonwebtoken::TokenData<serde_json::Value>, PdsError> {
-    use jsonwebtoken::{decode, DecodingKey, Validation, Algorithm};
-
-    let decoding_key = DecodingKey::from_secret(jwt_secret.as_bytes());
-    let mut validation = Validation::new(Algorithm::HS256);
-    // Allow some clock skew (5 minutes)
-    validation.leeway = 300;
-
-    decode::<serde_json::Value>(token, &decoding_key, &validation)
-        .map_err(|e| {
-            tracing::warn!("JWT verification failed: {}", e);
-            match e.kind() {
-                jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
-                    PdsError::Authentication("Token has expired".to_string())
-                }
-                jsonwebtoken::errors::ErrorKind::InvalidSignature => {
-                    PdsError::Authentication("Invalid token signature".to_string())
-                }
-                _ => PdsError::Authentication(format!("Invalid token: {}", e))
-            }
-        })
+pub fn verify_jwt_token(token: &str, keys: &JwtKeyring) -> Result<jsonwebtoken::TokenData<serde_json::Value>, PdsError> {
+    keys.verify(token).map_err(|e| {
+        tracing::warn!("JWT verification failed: {}", e);
+        e
+    })
 }
 
 /// Simplified admin token verification for admin panel
 /// This is a basic check - for more secure verification, use AdminAuthContext extractor
-pub fn verify_admin_token(token: &str, jwt_secret: &str) -> Result<(), PdsError> {
+pub fn verify_admin_token(token: &str, keys: &JwtKeyring) -> Result<(), PdsError> {
     // Perform full JWT verification
-    verify_jwt_token(token, jwt_secret)?;
+    verify_jwt_token(token, keys)?;
 
     // Token is valid
     Ok(())
@@ -126,7 +126,7 @@ impl BackupScheduler {
         } else {
             // Default to scripts/backup.sh or scripts/backup.ps1 depending on OS
             let project_root = std::env::current_dir()
-                .map_err(|e| PdsError::Internal(format!("Failed to get current dir: {}", e)))?;
+                .map_err(|e| PdsError::Backup(format!("Failed to get current dir: {}", e)))?;
 
             if cfg!(windows) {
                 project_root.join("scripts").join("backup.ps1")
@@ -136,7 +136,7 @@ impl BackupScheduler {
         };
 
         if !script_path.exists() {
-            return Err(PdsError::Internal(format!(
+            return Err(PdsError::Backup(format!(
                 "Backup script not found: {:?}",
                 script_path
             )));
@@ -158,11 +158,11 @@ impl BackupScheduler {
                 .arg(&self.config.backup_dir)
                 .output()
         }
-        .map_err(|e| PdsError::Internal(format!("Failed to execute backup script: {}", e)))?;
+        .map_err(|e| PdsError::Backup(format!("Failed to execute backup script: {}", e)))?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
-            return Err(PdsError::Internal(format!(
+            return Err(PdsError::Backup(format!(
                 "Backup script failed: {}",
                 stderr
             )));
@@ -217,10 +217,10 @@ pub fn list_backups(backup_dir: &Path) -> PdsResult<Vec<BackupMetadata>> {
     let mut backups = Vec::new();
 
     for entry in std::fs::read_dir(backup_dir)
-        .map_err(|e| PdsError::Internal(format!("Failed to read backup dir: {}", e)))?
+        .map_err(|e| PdsError::Backup(format!("Failed to read backup dir: {}", e)))?
     {
         let entry = entry
-            .map_err(|e| PdsError::Internal(format!("Failed to read dir entry: {}", e)))?;
+            .map_err(|e| PdsError::Backup(format!("Failed to read dir entry: {}", e)))?;
         let path = entry.path();
 
         if path.is_dir() && path.file_name().unwrap_or_default().to_string_lossy().starts_with("backup_") {
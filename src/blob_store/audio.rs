@@ -0,0 +1,177 @@
+/// Audio blob analysis for voice posts
+///
+/// Decodes MP3, Ogg Vorbis and MP4/AAC uploads once, at staging, for their
+/// duration and a coarse waveform clients draw before playing. The waveform
+/// is `WAVEFORM_BUCKETS` peak amplitudes (0-255, full scale 255) spread
+/// evenly over the recording. Streams that can be read but not decoded (Ogg
+/// Opus) still get a duration from their headers, but no waveform.
+use std::io::Cursor;
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error,
+    formats::FormatOptions,
+    io::MediaSourceStream,
+    meta::MetadataOptions,
+    probe::Hint,
+};
+
+/// Peaks in a waveform
+pub const WAVEFORM_BUCKETS: usize = 100;
+
+/// Peak windows per second of audio, reduced to buckets once the length
+/// is known
+const WINDOWS_PER_SECOND: u32 = 100;
+
+/// What analysis found in an audio blob
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AudioInfo {
+    pub duration_ms: Option<i64>,
+    pub waveform: Option<Vec<u8>>,
+}
+
+/// Duration and waveform of `data`, or `None` if it isn't readable audio
+pub fn analyze(data: &[u8], mime_type: &str) -> Option<AudioInfo> {
+    let source = MediaSourceStream::new(Box::new(Cursor::new(data.to_vec())), Default::default());
+    let mut hint = Hint::new();
+    hint.mime_type(mime_type);
+
+    let probed = symphonia::default::get_probe()
+        .format(&hint, source, &FormatOptions::default(), &MetadataOptions::default())
+        .ok()?;
+    let mut format = probed.format;
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)?
+        .clone();
+    let sample_rate = track.codec_params.sample_rate?;
+    let declared_ms = track
+        .codec_params
+        .n_frames
+        .map(|frames| (frames * 1000 / sample_rate as u64) as i64);
+
+    let mut decoder = match symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default()) {
+        Ok(decoder) => decoder,
+        Err(_) => {
+            return Some(AudioInfo {
+                duration_ms: declared_ms,
+                waveform: None,
+            })
+        }
+    };
+
+    let window = (sample_rate / WINDOWS_PER_SECOND).max(1) as usize;
+    let mut windows: Vec<f32> = Vec::new();
+    let (mut current, mut in_window, mut frames) = (0f32, 0usize, 0u64);
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(_) => break,
+        };
+        if packet.track_id() != track.id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet; the rest may still play
+            Err(Error::DecodeError(_)) => continue,
+            Err(_) => break,
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut samples = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+
+        for frame in samples.samples().chunks(channels) {
+            let peak = frame.iter().fold(0f32, |peak, s| peak.max(s.abs()));
+            current = current.max(peak);
+            in_window += 1;
+            frames += 1;
+            if in_window == window {
+                windows.push(current);
+                current = 0.0;
+                in_window = 0;
+            }
+        }
+    }
+    if in_window > 0 {
+        windows.push(current);
+    }
+
+    if frames == 0 {
+        return Some(AudioInfo {
+            duration_ms: declared_ms,
+            waveform: None,
+        });
+    }
+    Some(AudioInfo {
+        duration_ms: Some((frames * 1000 / sample_rate as u64) as i64),
+        waveform: Some(reduce(&windows)),
+    })
+}
+
+/// Fold per-window peaks into at most `WAVEFORM_BUCKETS` peaks
+fn reduce(windows: &[f32]) -> Vec<u8> {
+    let buckets = windows.len().min(WAVEFORM_BUCKETS);
+    (0..buckets)
+        .map(|i| {
+            let range = &windows[i * windows.len() / buckets..(i + 1) * windows.len() / buckets];
+            let peak = range.iter().fold(0f32, |peak, p| peak.max(*p));
+            (peak.clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// One second of 8kHz mono 16-bit PCM: silence, then a full-scale square wave
+    fn wav() -> Vec<u8> {
+        let samples: Vec<i16> = (0..8000)
+            .map(|i| if i < 4000 { 0 } else if i % 2 == 0 { i16::MAX } else { -i16::MAX })
+            .collect();
+        let data_len = (samples.len() * 2) as u32;
+
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+        wav.extend_from_slice(&8000u32.to_le_bytes());
+        wav.extend_from_slice(&16000u32.to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        for sample in samples {
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+        wav
+    }
+
+    #[test]
+    fn test_analyze() {
+        let info = analyze(&wav(), "audio/wav").unwrap();
+        assert_eq!(info.duration_ms, Some(1000));
+
+        let waveform = info.waveform.unwrap();
+        assert_eq!(waveform.len(), WAVEFORM_BUCKETS);
+        assert!(waveform[..50].iter().all(|p| *p == 0));
+        assert!(waveform[50..].iter().all(|p| *p >= 254));
+
+        assert!(analyze(b"not audio at all", "audio/mpeg").is_none());
+    }
+
+    #[test]
+    fn test_reduce() {
+        assert_eq!(reduce(&[0.5, 1.0]), vec![128, 255]);
+        let long: Vec<f32> = (0..1000).map(|i| if i % 10 == 0 { 1.0 } else { 0.0 }).collect();
+        assert_eq!(reduce(&long), vec![255; WAVEFORM_BUCKETS]);
+        assert!(reduce(&[]).is_empty());
+    }
+}
@@ -40,7 +40,7 @@ impl DiskBlobBackend {
         let blob_path = self.get_blob_path(cid);
         if let Some(parent) = blob_path.parent() {
             fs::create_dir_all(parent).await.map_err(|e| {
-                PdsError::BlobStorage(format!("Failed to create blob directory: {}", e))
+                PdsError::BlobBackend(format!("Failed to create blob directory: {}", e))
             })?;
         }
         Ok(blob_path)
@@ -53,7 +53,7 @@ impl BlobBackend for DiskBlobBackend {
         let blob_path = self.ensure_blob_dir(cid).await?;
 
         fs::write(&blob_path, data).await.map_err(|e| {
-            PdsError::BlobStorage(format!("Failed to write blob {}: {}", cid, e))
+            PdsError::BlobBackend(format!("Failed to write blob {}: {}", cid, e))
         })?;
 
         Ok(())
@@ -65,7 +65,7 @@ impl BlobBackend for DiskBlobBackend {
         match fs::read(&blob_path).await {
             Ok(data) => Ok(Some(data)),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(PdsError::BlobStorage(format!(
+            Err(e) => Err(PdsError::BlobBackend(format!(
                 "Failed to read blob {}: {}",
                 cid, e
             ))),
@@ -78,7 +78,7 @@ impl BlobBackend for DiskBlobBackend {
         match fs::remove_file(&blob_path).await {
             Ok(()) => Ok(()),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
-            Err(e) => Err(PdsError::BlobStorage(format!(
+            Err(e) => Err(PdsError::BlobBackend(format!(
                 "Failed to delete blob {}: {}",
                 cid, e
             ))),
@@ -96,7 +96,7 @@ impl BlobBackend for DiskBlobBackend {
         match fs::metadata(&blob_path).await {
             Ok(metadata) => Ok(Some(metadata.len())),
             Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
-            Err(e) => Err(PdsError::BlobStorage(format!(
+            Err(e) => Err(PdsError::BlobBackend(format!(
                 "Failed to get blob size {}: {}",
                 cid, e
             ))),
@@ -0,0 +1,198 @@
+/// Image decoding helpers for the thumbnailer
+///
+/// Animated GIF and WebP blobs are thumbnailed from their first frame,
+/// composited onto the full canvas, and anything with transparency is
+/// flattened onto white since JPEG has no alpha channel. Built with the
+/// `animated-thumbnails` feature, and with `PDS_BLOB_ANIMATED_THUMBNAILS`
+/// on, animations get a small animated WebP thumbnail instead, falling back
+/// to the still one when it would be too large.
+///
+/// AVIF blobs are stored and served, but there is no AVIF decoder here, so
+/// only their dimensions are read and they get no thumbnail.
+use crate::blob_store::ImageDimensions;
+use image::{
+    codecs::{gif::GifDecoder, webp::WebPDecoder},
+    AnimationDecoder, DynamicImage, ImageResult, Rgb, RgbImage,
+};
+use std::io::Cursor;
+
+/// Frames kept in an animated thumbnail
+pub const MAX_ANIMATED_FRAMES: usize = 60;
+
+/// Largest animated thumbnail kept, in bytes
+pub const MAX_ANIMATED_THUMBNAIL_BYTES: usize = 512 * 1024;
+
+/// How far into an AVIF file to look for its dimensions
+const AVIF_HEADER_SCAN: usize = 64 * 1024;
+
+/// Whether `data` is a GIF or WebP with more than one frame
+pub fn is_animated(data: &[u8], mime_type: &str) -> bool {
+    match mime_type {
+        "image/gif" => GifDecoder::new(Cursor::new(data))
+            .map(|decoder| decoder.into_frames().take(2).filter(Result::is_ok).count() > 1)
+            .unwrap_or(false),
+        "image/webp" => WebPDecoder::new(Cursor::new(data))
+            .map(|decoder| decoder.has_animation())
+            .unwrap_or(false),
+        _ => false,
+    }
+}
+
+/// The image as first shown: the first frame of an animation, composited
+/// onto the canvas, or the whole image otherwise
+pub fn first_frame(data: &[u8], mime_type: &str) -> ImageResult<DynamicImage> {
+    let frames = match mime_type {
+        "image/gif" => Some(GifDecoder::new(Cursor::new(data))?.into_frames()),
+        "image/webp" => {
+            let decoder = WebPDecoder::new(Cursor::new(data))?;
+            decoder.has_animation().then(|| decoder.into_frames())
+        }
+        _ => None,
+    };
+
+    match frames.and_then(|mut frames| frames.next()) {
+        Some(frame) => Ok(DynamicImage::ImageRgba8(frame?.into_buffer())),
+        None => image::load_from_memory(data),
+    }
+}
+
+/// Drop the alpha channel, compositing onto white
+pub fn flatten(img: &DynamicImage) -> RgbImage {
+    if !img.color().has_alpha() {
+        return img.to_rgb8();
+    }
+
+    let rgba = img.to_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        Rgb([blend(r), blend(g), blend(b)])
+    })
+}
+
+/// Dimensions of an AVIF image, from its `ispe` (image spatial extents)
+/// property
+///
+/// The property sits in the `meta` box at the start of the file, so a scan
+/// of the header finds it without parsing the box tree.
+pub fn avif_dimensions(data: &[u8]) -> Option<ImageDimensions> {
+    let head = &data[..data.len().min(AVIF_HEADER_SCAN)];
+    let at = head.windows(4).position(|w| w == b"ispe")?;
+    // Box type, version and flags, then width and height
+    let fields = head.get(at + 8..at + 16)?;
+    let width = u32::from_be_bytes(fields[..4].try_into().ok()?);
+    let height = u32::from_be_bytes(fields[4..].try_into().ok()?);
+    (width > 0 && height > 0).then_some(ImageDimensions { width, height })
+}
+
+/// Animated WebP thumbnail of an animated GIF or WebP, at most `max_size`
+/// on a side
+///
+/// Keeps the first `MAX_ANIMATED_FRAMES` frames; `None` if the animation
+/// can't be decoded or the result is over `MAX_ANIMATED_THUMBNAIL_BYTES`.
+#[cfg(feature = "animated-thumbnails")]
+pub fn animated_thumbnail(data: &[u8], mime_type: &str, max_size: u32) -> Option<Vec<u8>> {
+    use image::imageops::{self, FilterType};
+    use webp_animation::{Encoder, EncoderOptions, EncodingConfig, EncodingType, LossyEncodingConfig};
+
+    let frames = match mime_type {
+        "image/gif" => GifDecoder::new(Cursor::new(data)).ok()?.into_frames(),
+        "image/webp" => WebPDecoder::new(Cursor::new(data)).ok()?.into_frames(),
+        _ => return None,
+    };
+
+    let mut encoder: Option<Encoder> = None;
+    let mut size = (0, 0);
+    let mut timestamp = 0i32;
+    for frame in frames.take(MAX_ANIMATED_FRAMES) {
+        let frame = frame.ok()?;
+        let (numer, denom) = frame.delay().numer_denom_ms();
+        let buffer = frame.into_buffer();
+
+        if encoder.is_none() {
+            let scale = (max_size as f64 / buffer.width().max(buffer.height()) as f64).min(1.0);
+            size = (
+                ((buffer.width() as f64 * scale).round() as u32).max(1),
+                ((buffer.height() as f64 * scale).round() as u32).max(1),
+            );
+            let options = EncoderOptions {
+                encoding_config: Some(EncodingConfig {
+                    encoding_type: EncodingType::Lossy(LossyEncodingConfig::default()),
+                    quality: 75.0,
+                    method: 4,
+                }),
+                ..Default::default()
+            };
+            encoder = Some(Encoder::new_with_options(size, options).ok()?);
+        }
+
+        let resized = imageops::resize(&buffer, size.0, size.1, FilterType::Triangle);
+        encoder.as_mut()?.add_frame(resized.as_raw(), timestamp).ok()?;
+        // Browsers play delays under 20ms at 100ms, as the source would have been shown
+        let delay = numer / denom.max(1);
+        timestamp += if delay < 20 { 100 } else { delay as i32 };
+    }
+
+    let webp = encoder?.finalize(timestamp).ok()?;
+    (webp.len() <= MAX_ANIMATED_THUMBNAIL_BYTES).then(|| webp.to_vec())
+}
+
+/// Animated thumbnails need the `animated-thumbnails` feature
+#[cfg(not(feature = "animated-thumbnails"))]
+pub fn animated_thumbnail(_data: &[u8], _mime_type: &str, _max_size: u32) -> Option<Vec<u8>> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{codecs::gif::GifEncoder, Delay, Frame, ImageFormat, Rgba, RgbaImage};
+
+    fn animated_gif() -> Vec<u8> {
+        let mut buf = Vec::new();
+        {
+            let mut encoder = GifEncoder::new(&mut buf);
+            let frames = [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 255])].map(|color| {
+                Frame::from_parts(RgbaImage::from_pixel(40, 20, color), 0, 0, Delay::from_numer_denom_ms(100, 1))
+            });
+            encoder.encode_frames(frames).unwrap();
+        }
+        buf
+    }
+
+    #[test]
+    fn test_first_frame_of_animated_gif() {
+        let gif = animated_gif();
+        assert!(is_animated(&gif, "image/gif"));
+
+        let frame = first_frame(&gif, "image/gif").unwrap();
+        assert_eq!((frame.width(), frame.height()), (40, 20));
+        assert_eq!(frame.to_rgba8().get_pixel(10, 10).0, [255, 0, 0, 255]);
+
+        let mut png = Vec::new();
+        RgbaImage::new(4, 4).write_to(&mut Cursor::new(&mut png), ImageFormat::Png).unwrap();
+        assert!(!is_animated(&png, "image/png"));
+        assert!(!is_animated(&png, "image/gif"));
+    }
+
+    #[test]
+    fn test_flatten_onto_white() {
+        let mut img = RgbaImage::from_pixel(2, 1, Rgba([0, 0, 0, 0]));
+        img.put_pixel(1, 0, Rgba([0, 0, 0, 255]));
+        let flat = flatten(&DynamicImage::ImageRgba8(img));
+        assert_eq!(flat.get_pixel(0, 0).0, [255, 255, 255]);
+        assert_eq!(flat.get_pixel(1, 0).0, [0, 0, 0]);
+    }
+
+    #[test]
+    fn test_avif_dimensions() {
+        let mut avif = b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00avifmif1".to_vec();
+        avif.extend_from_slice(b"\x00\x00\x00\x14ispe\x00\x00\x00\x00");
+        avif.extend_from_slice(&640u32.to_be_bytes());
+        avif.extend_from_slice(&480u32.to_be_bytes());
+
+        let dims = avif_dimensions(&avif).unwrap();
+        assert_eq!((dims.width, dims.height), (640, 480));
+        assert!(avif_dimensions(&avif[..40]).is_none());
+    }
+}
@@ -3,15 +3,21 @@
 /// Handles binary file storage for images, videos, and other media.
 /// Supports multiple backend implementations (disk, S3, etc.)
 
+pub mod audio;
 pub mod disk;
+pub mod media;
 pub mod models;
 // Temporarily disabled due to AWS SDK build issues on Windows
 // pub mod s3;
+pub mod resumable;
 pub mod store;
+pub mod transform;
 
 pub use models::*;
 // pub use s3::{S3BlobBackend, S3Config};
+pub use resumable::{ResumableUploadManager, UploadSession};
 pub use store::{BlobStore, BlobStoreConfig};
+pub use transform::{ImagePreset, ImageVariantCache};
 
 use crate::error::PdsResult;
 use async_trait::async_trait;
@@ -47,6 +53,23 @@ pub struct BlobStorageConfig {
     /// Maximum blob size in bytes (default: 5MB)
     pub max_blob_size: usize,
 
+    /// Maximum audio blob size in bytes (default: 10MB)
+    pub max_audio_size: usize,
+
+    /// Blob bytes each account may store, staged uploads included (None: unlimited)
+    pub account_quota: Option<usize>,
+
+    /// Thumbnail animated GIFs and WebPs as animated WebPs (needs the
+    /// `animated-thumbnails` feature)
+    pub animated_thumbnails: bool,
+
+    /// Hours a staged blob or incomplete resumable upload is kept before the
+    /// cleanup job removes it (default: 24)
+    pub temp_ttl_hours: i64,
+
+    /// Blobs an account may have staged but not committed at once (None: unlimited)
+    pub max_staged_per_account: Option<usize>,
+
     /// Temporary upload directory
     pub temp_dir: PathBuf,
 }
@@ -58,6 +81,11 @@ impl Default for BlobStorageConfig {
                 location: PathBuf::from("./data/blobs"),
             },
             max_blob_size: 5 * 1024 * 1024, // 5MB
+            max_audio_size: 10 * 1024 * 1024, // 10MB
+            account_quota: None,
+            animated_thumbnails: false,
+            temp_ttl_hours: 24,
+            max_staged_per_account: None,
             temp_dir: PathBuf::from("./data/tmp"),
         }
     }
@@ -14,6 +14,15 @@ pub struct BlobMetadata {
     pub height: Option<i64>,
     pub alt_text: Option<String>,
     pub thumbnail_cid: Option<String>,
+    /// Animated GIF or WebP
+    #[serde(default)]
+    pub animated: bool,
+    /// Length of an audio blob
+    #[serde(default)]
+    pub duration_ms: Option<i64>,
+    /// Peak amplitudes of an audio blob (see `audio::WAVEFORM_BUCKETS`)
+    #[serde(default)]
+    pub waveform: Option<Vec<u8>>,
 }
 
 /// Image dimensions
@@ -72,4 +81,80 @@ pub struct TempBlob {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub width: Option<i64>,
     pub height: Option<i64>,
+    pub animated: bool,
+    pub duration_ms: Option<i64>,
+    pub waveform: Option<Vec<u8>>,
+}
+
+/// A stored blob as listed for an account
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountBlob {
+    pub cid: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub created_at: DateTime<Utc>,
+    /// Records referencing the blob; `None` for blobs stored before
+    /// references were counted
+    pub ref_count: Option<i64>,
+    /// Thumbnail generated for the blob, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_cid: Option<String>,
+}
+
+/// Blob storage used by one account
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobUsage {
+    pub did: String,
+    pub blobs: i64,
+    pub bytes: i64,
+    /// Blobs no record references (thumbnails excluded), awaiting cleanup
+    pub unreferenced_blobs: i64,
+    pub unreferenced_bytes: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_upload_at: Option<DateTime<Utc>>,
+}
+
+/// Ordering of a blob usage report, largest first
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlobUsageSort {
+    #[default]
+    Bytes,
+    Count,
+}
+
+/// Where an uploaded blob is in its processing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlobProcessingState {
+    /// Uploaded and awaiting a record; derived media (thumbnails) is made
+    /// once a record references it
+    Staged,
+    /// Stored permanently with its derived media
+    Ready,
+}
+
+/// Processing status of an uploaded blob
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BlobProcessingStatus {
+    pub cid: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub state: BlobProcessingState,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub width: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub height: Option<i64>,
+    /// Animated GIF or WebP
+    pub animated: bool,
+    /// Length of an audio blob
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    /// Thumbnail generated for the blob, if any; an animated WebP for
+    /// animations when the server makes them, otherwise a JPEG
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thumbnail_cid: Option<String>,
 }
@@ -0,0 +1,479 @@
+/// Resumable blob uploads
+///
+/// A tus-style protocol for large blobs over unreliable connections: the
+/// client creates an upload session with the total size, sends the data in
+/// chunks at explicit offsets (resuming from the server's offset after a
+/// dropped connection), then finalizes. Finalizing hashes the assembled data
+/// and stages it like a regular uploadBlob, so the two-phase commit applies
+/// unchanged.
+///
+/// Partial data lives in `<directory>/<id>.part` until finalized. Sessions
+/// not touched within the blob store's temp TTL (`PDS_BLOB_TEMP_TTL_HOURS`)
+/// are removed by the temp blob cleanup job.
+use crate::{
+    blob_store::{BlobStore, TempBlob},
+    clock::{random_ids, system_clock, Clock, IdGenerator},
+    error::{PdsError, PdsResult},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::{
+    collections::HashSet,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+use tokio::{fs, io::AsyncWriteExt};
+
+/// Maximum unfinished upload sessions per account
+const MAX_OPEN_SESSIONS: i64 = 5;
+
+/// State of a resumable upload
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadSession {
+    pub id: String,
+    #[serde(skip)]
+    pub creator_did: String,
+    pub mime_type: Option<String>,
+    /// Total size declared at creation
+    pub length: i64,
+    /// Bytes received so far
+    pub offset: i64,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    pub fn is_complete(&self) -> bool {
+        self.offset == self.length
+    }
+}
+
+/// Creates, appends to, finalizes and expires upload sessions
+pub struct ResumableUploadManager {
+    db: SqlitePool,
+    directory: PathBuf,
+    blob_store: Arc<BlobStore>,
+    /// Sessions with a chunk being written; a second writer gets a conflict
+    busy: Mutex<HashSet<String>>,
+    clock: Arc<dyn Clock>,
+    ids: Arc<dyn IdGenerator>,
+}
+
+/// Marks a session busy until dropped
+struct BusyGuard<'a> {
+    busy: &'a Mutex<HashSet<String>>,
+    id: String,
+}
+
+impl Drop for BusyGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut busy) = self.busy.lock() {
+            busy.remove(&self.id);
+        }
+    }
+}
+
+impl ResumableUploadManager {
+    pub fn new(db: SqlitePool, directory: PathBuf, blob_store: Arc<BlobStore>) -> Self {
+        Self {
+            db,
+            directory,
+            blob_store,
+            busy: Mutex::new(HashSet::new()),
+            clock: system_clock(),
+            ids: random_ids(),
+        }
+    }
+
+    /// Use `clock` for session expiry and `ids` for session ids
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>, ids: Arc<dyn IdGenerator>) -> Self {
+        self.clock = clock;
+        self.ids = ids;
+        self
+    }
+
+    fn part_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{}.part", id))
+    }
+
+    fn lock(&self, id: &str) -> PdsResult<BusyGuard<'_>> {
+        let mut busy = self
+            .busy
+            .lock()
+            .map_err(|_| PdsError::Internal("Upload lock poisoned".to_string()))?;
+        if !busy.insert(id.to_string()) {
+            return Err(PdsError::Conflict(format!("Upload {} is busy", id)));
+        }
+        Ok(BusyGuard {
+            busy: &self.busy,
+            id: id.to_string(),
+        })
+    }
+
+    /// Start an upload of `length` bytes
+    pub async fn create(
+        &self,
+        creator_did: &str,
+        length: i64,
+        mime_type: Option<&str>,
+    ) -> PdsResult<UploadSession> {
+        let max = self.blob_store.size_limit(mime_type.unwrap_or_default()) as i64;
+        if length <= 0 || length > max {
+            return Err(PdsError::Validation(format!(
+                "Upload length must be between 1 and {} bytes",
+                max
+            )));
+        }
+        self.blob_store.check_quota(creator_did, length, None).await?;
+        self.blob_store.check_staged_limit(creator_did, None).await?;
+
+        let open: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM blob_upload_session WHERE creator_did = ?1 AND expires_at > ?2",
+        )
+        .bind(creator_did)
+        .bind(self.clock.now().to_rfc3339())
+        .fetch_one(&self.db)
+        .await
+        .map_err(PdsError::Database)?;
+        if open >= MAX_OPEN_SESSIONS {
+            return Err(PdsError::Validation(format!(
+                "Too many unfinished uploads (max {})",
+                MAX_OPEN_SESSIONS
+            )));
+        }
+
+        fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to create upload directory: {}", e)))?;
+
+        let now = self.clock.now();
+        let session = UploadSession {
+            id: self.ids.uuid().simple().to_string(),
+            creator_did: creator_did.to_string(),
+            mime_type: mime_type.map(String::from),
+            length,
+            offset: 0,
+            created_at: now,
+            expires_at: now + Duration::hours(self.blob_store.temp_ttl_hours()),
+        };
+
+        fs::write(self.part_path(&session.id), b"")
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to create upload file: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO blob_upload_session (id, creator_did, mime_type, length, upload_offset, created_at, expires_at)
+            VALUES (?1, ?2, ?3, ?4, 0, ?5, ?6)
+            "#,
+        )
+        .bind(&session.id)
+        .bind(&session.creator_did)
+        .bind(&session.mime_type)
+        .bind(session.length)
+        .bind(session.created_at.to_rfc3339())
+        .bind(session.expires_at.to_rfc3339())
+        .execute(&self.db)
+        .await
+        .map_err(PdsError::Database)?;
+
+        tracing::info!(id = %session.id, did = %creator_did, length, "upload_session_created");
+        Ok(session)
+    }
+
+    /// Look up a live session owned by `creator_did`
+    pub async fn get(&self, id: &str, creator_did: &str) -> PdsResult<UploadSession> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, creator_did, mime_type, length, upload_offset, created_at, expires_at
+            FROM blob_upload_session
+            WHERE id = ?1 AND creator_did = ?2 AND expires_at > ?3
+            "#,
+        )
+        .bind(id)
+        .bind(creator_did)
+        .bind(self.clock.now().to_rfc3339())
+        .fetch_optional(&self.db)
+        .await
+        .map_err(PdsError::Database)?
+        .ok_or_else(|| PdsError::NotFound(format!("Upload not found: {}", id)))?;
+
+        let parse = |value: String| {
+            DateTime::parse_from_rfc3339(&value)
+                .map(|t| t.with_timezone(&Utc))
+                .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))
+        };
+
+        Ok(UploadSession {
+            id: row.get("id"),
+            creator_did: row.get("creator_did"),
+            mime_type: row.get("mime_type"),
+            length: row.get("length"),
+            offset: row.get("upload_offset"),
+            created_at: parse(row.get("created_at"))?,
+            expires_at: parse(row.get("expires_at"))?,
+        })
+    }
+
+    /// Write a chunk at `offset`, which must equal the bytes received so far
+    ///
+    /// A mismatched offset is a conflict; the client should re-read the
+    /// session's offset and resume from there.
+    pub async fn append(
+        &self,
+        id: &str,
+        creator_did: &str,
+        offset: i64,
+        chunk: &[u8],
+    ) -> PdsResult<UploadSession> {
+        let _guard = self.lock(id)?;
+        let mut session = self.get(id, creator_did).await?;
+
+        if offset != session.offset {
+            return Err(PdsError::Conflict(format!(
+                "Upload offset is {}, not {}",
+                session.offset, offset
+            )));
+        }
+        if offset + chunk.len() as i64 > session.length {
+            return Err(PdsError::Validation(format!(
+                "Chunk exceeds the declared upload length of {} bytes",
+                session.length
+            )));
+        }
+
+        // Drop anything past the recorded offset left by an interrupted write
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .open(self.part_path(id))
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to open upload file: {}", e)))?;
+        file.set_len(offset as u64)
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to truncate upload file: {}", e)))?;
+        drop(file);
+
+        let mut file = fs::OpenOptions::new()
+            .append(true)
+            .open(self.part_path(id))
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to open upload file: {}", e)))?;
+        file.write_all(chunk)
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to write upload chunk: {}", e)))?;
+        file.sync_data()
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to sync upload chunk: {}", e)))?;
+
+        session.offset += chunk.len() as i64;
+        session.expires_at = self.clock.now() + Duration::hours(self.blob_store.temp_ttl_hours());
+        sqlx::query("UPDATE blob_upload_session SET upload_offset = ?1, expires_at = ?2 WHERE id = ?3")
+            .bind(session.offset)
+            .bind(session.expires_at.to_rfc3339())
+            .bind(id)
+            .execute(&self.db)
+            .await
+            .map_err(PdsError::Database)?;
+
+        Ok(session)
+    }
+
+    /// Hash the completed upload and stage it as a temp blob
+    pub async fn finalize(&self, id: &str, creator_did: &str) -> PdsResult<TempBlob> {
+        let _guard = self.lock(id)?;
+        let session = self.get(id, creator_did).await?;
+
+        if !session.is_complete() {
+            return Err(PdsError::Validation(format!(
+                "Upload incomplete: {} of {} bytes received",
+                session.offset, session.length
+            )));
+        }
+
+        let data = fs::read(self.part_path(id))
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to read upload file: {}", e)))?;
+        if data.len() as i64 != session.length {
+            return Err(PdsError::BlobBackend(format!(
+                "Upload file holds {} bytes, expected {}",
+                data.len(),
+                session.length
+            )));
+        }
+
+        let temp_blob = self
+            .blob_store
+            .stage_blob(data, session.mime_type.as_deref(), creator_did)
+            .await?;
+        self.remove(id).await?;
+
+        tracing::info!(id = %id, cid = %temp_blob.cid, "upload_session_finalized");
+        Ok(temp_blob)
+    }
+
+    /// Abandon an upload
+    pub async fn cancel(&self, id: &str, creator_did: &str) -> PdsResult<()> {
+        let _guard = self.lock(id)?;
+        self.get(id, creator_did).await?;
+        self.remove(id).await
+    }
+
+    async fn remove(&self, id: &str) -> PdsResult<()> {
+        match fs::remove_file(self.part_path(id)).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                return Err(PdsError::BlobBackend(format!("Failed to delete upload file: {}", e)))
+            }
+        }
+
+        sqlx::query("DELETE FROM blob_upload_session WHERE id = ?1")
+            .bind(id)
+            .execute(&self.db)
+            .await
+            .map_err(PdsError::Database)?;
+
+        Ok(())
+    }
+
+    /// Delete sessions past their expiry, returning how many were removed
+    pub async fn expire(&self) -> PdsResult<u64> {
+        let ids: Vec<String> =
+            sqlx::query_scalar("SELECT id FROM blob_upload_session WHERE expires_at <= ?1")
+                .bind(self.clock.now().to_rfc3339())
+                .fetch_all(&self.db)
+                .await
+                .map_err(PdsError::Database)?;
+
+        let mut removed = 0;
+        for id in ids {
+            // Skip sessions with a chunk in flight; the next run gets them
+            let Ok(_guard) = self.lock(&id) else { continue };
+            match self.remove(&id).await {
+                Ok(()) => removed += 1,
+                Err(e) => tracing::warn!("Failed to expire upload session {}: {}", id, e),
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blob_store::{BlobBackendType, BlobStorageConfig, BlobStoreConfig};
+    use crate::clock::{MockClock, SequentialIds};
+    use tempfile::tempdir;
+
+    async fn setup() -> (ResumableUploadManager, tempfile::TempDir, Arc<MockClock>) {
+        let dir = tempdir().unwrap();
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+
+        for ddl in [
+            r#"
+            CREATE TABLE temp_blob_metadata (
+                cid TEXT PRIMARY KEY,
+                mime_type TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                creator_did TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                width INTEGER,
+                height INTEGER,
+                animated INTEGER NOT NULL DEFAULT 0,
+                duration_ms INTEGER,
+                waveform BLOB
+            )
+            "#,
+            r#"
+            CREATE TABLE blob_upload_session (
+                id TEXT PRIMARY KEY,
+                creator_did TEXT NOT NULL,
+                mime_type TEXT,
+                length INTEGER NOT NULL,
+                upload_offset INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                expires_at TEXT NOT NULL
+            )
+            "#,
+        ] {
+            sqlx::query(ddl).execute(&db).await.unwrap();
+        }
+
+        let config = BlobStoreConfig {
+            storage: BlobStorageConfig {
+                backend: BlobBackendType::Disk {
+                    location: dir.path().join("blobs"),
+                },
+                max_blob_size: 1024,
+                max_audio_size: 10 * 1024 * 1024,
+                account_quota: None,
+                animated_thumbnails: false,
+                temp_ttl_hours: 24,
+                max_staged_per_account: None,
+                temp_dir: dir.path().join("tmp"),
+            },
+        };
+        let blob_store = Arc::new(BlobStore::new(config, db.clone()).unwrap());
+        let clock = Arc::new(MockClock::fixed());
+        let manager = ResumableUploadManager::new(db, dir.path().join("uploads"), blob_store)
+            .with_clock(clock.clone(), Arc::new(SequentialIds::new()));
+        (manager, dir, clock)
+    }
+
+    #[tokio::test]
+    async fn test_resumable_upload() {
+        let (manager, _dir, _clock) = setup().await;
+        let did = "did:plc:alice";
+        let data = b"0123456789abcdefghij".to_vec();
+
+        let session = manager.create(did, 20, Some("video/mp4")).await.unwrap();
+        assert_eq!(session.offset, 0);
+
+        manager.append(&session.id, did, 0, &data[..8]).await.unwrap();
+
+        // A retried or out-of-order chunk is rejected with the real offset
+        assert!(matches!(
+            manager.append(&session.id, did, 4, &data[4..12]).await,
+            Err(PdsError::Conflict(_))
+        ));
+        // Other accounts can't see the session
+        assert!(manager.get(&session.id, "did:plc:bob").await.is_err());
+        // Finalizing early fails
+        assert!(manager.finalize(&session.id, did).await.is_err());
+
+        let resumed = manager.get(&session.id, did).await.unwrap();
+        let session = manager
+            .append(&session.id, did, resumed.offset, &data[8..])
+            .await
+            .unwrap();
+        assert!(session.is_complete());
+
+        let blob = manager.finalize(&session.id, did).await.unwrap();
+        assert_eq!(blob.size, 20);
+        assert_eq!(blob.mime_type, "video/mp4");
+        assert!(manager.get(&session.id, did).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_upload_limits_and_expiry() {
+        let (manager, _dir, clock) = setup().await;
+        let did = "did:plc:alice";
+
+        assert!(manager.create(did, 0, None).await.is_err());
+        assert!(manager.create(did, 4096, None).await.is_err());
+
+        let session = manager.create(did, 10, None).await.unwrap();
+        assert!(manager.append(&session.id, did, 0, &[0u8; 11]).await.is_err());
+
+        // Still live just before the TTL runs out
+        clock.advance(Duration::hours(manager.blob_store.temp_ttl_hours()) - Duration::minutes(1));
+        assert_eq!(manager.expire().await.unwrap(), 0);
+
+        clock.advance(Duration::minutes(2));
+        assert_eq!(manager.expire().await.unwrap(), 1);
+        assert!(!manager.part_path(&session.id).exists());
+    }
+}
@@ -95,7 +95,7 @@ impl S3BlobBackend {
         // Verify bucket exists (optional, can be expensive)
         // Uncomment if you want to verify on startup:
         // client.head_bucket().bucket(&config.bucket).send().await
-        //     .map_err(|e| PdsError::Internal(format!("S3 bucket not accessible: {}", e)))?;
+        //     .map_err(|e| PdsError::BlobBackend(format!("S3 bucket not accessible: {}", e)))?;
 
         info!("✓ S3 blob storage initialized");
 
@@ -146,7 +146,7 @@ impl BlobBackend for S3BlobBackend {
             .await
             .map_err(|e| {
                 error!("Failed to upload blob to S3: {}", e);
-                PdsError::Internal(format!("S3 upload failed: {}", e))
+                PdsError::BlobBackend(format!("S3 upload failed: {}", e))
             })?;
 
         debug!("✓ Blob uploaded to S3: {}", key);
@@ -173,7 +173,7 @@ impl BlobBackend for S3BlobBackend {
                     .await
                     .map_err(|e| {
                         error!("Failed to read S3 object body: {}", e);
-                        PdsError::Internal(format!("Failed to read S3 object: {}", e))
+                        PdsError::BlobBackend(format!("Failed to read S3 object: {}", e))
                     })?
                     .into_bytes()
                     .to_vec();
@@ -189,7 +189,7 @@ impl BlobBackend for S3BlobBackend {
                     Ok(None)
                 } else {
                     error!("Failed to download blob from S3: {}", e);
-                    Err(PdsError::Internal(format!("S3 download failed: {}", e)))
+                    Err(PdsError::BlobBackend(format!("S3 download failed: {}", e)))
                 }
             }
         }
@@ -208,7 +208,7 @@ impl BlobBackend for S3BlobBackend {
             .await
             .map_err(|e| {
                 error!("Failed to delete blob from S3: {}", e);
-                PdsError::Internal(format!("S3 delete failed: {}", e))
+                PdsError::BlobBackend(format!("S3 delete failed: {}", e))
             })?;
 
         debug!("✓ Blob deleted from S3: {}", key);
@@ -233,7 +233,7 @@ impl BlobBackend for S3BlobBackend {
                     Ok(false)
                 } else {
                     error!("Failed to check blob existence in S3: {}", e);
-                    Err(PdsError::Internal(format!(
+                    Err(PdsError::BlobBackend(format!(
                         "S3 head object failed: {}",
                         e
                     )))
@@ -260,7 +260,7 @@ impl BlobBackend for S3BlobBackend {
                     Ok(None)
                 } else {
                     error!("Failed to get blob size from S3: {}", e);
-                    Err(PdsError::Internal(format!(
+                    Err(PdsError::BlobBackend(format!(
                         "S3 head object failed: {}",
                         e
                     )))
@@ -2,16 +2,26 @@
 ///
 /// Coordinates blob storage backends with database metadata tracking
 use crate::{
-    blob_store::{disk::DiskBlobBackend, BlobBackend, BlobBackendType, BlobMetadata, BlobRef, BlobStorageConfig, ImageDimensions, TempBlob},
+    blob_store::{
+        audio::{self, AudioInfo},
+        disk::DiskBlobBackend,
+        media, AccountBlob, BlobBackend, BlobBackendType, BlobMetadata, BlobProcessingState,
+        BlobProcessingStatus, BlobRef, BlobStorageConfig, BlobUsage, BlobUsageSort, ImageDimensions, TempBlob,
+    },
+    clock::{system_clock, Clock},
+    crypto::data_keys::{is_sealed_blob, ActorKeys, DataKeyManager},
     error::{PdsError, PdsResult},
+    metrics,
 };
-use chrono::Utc;
 use image::ImageFormat;
 use sha2::{Digest, Sha256};
 use sqlx::{Row, SqlitePool};
 use std::sync::Arc;
 use tokio::fs;
 
+/// Longest side of a generated thumbnail
+const THUMBNAIL_SIZE: u32 = 256;
+
 /// Blob store configuration
 #[derive(Debug, Clone)]
 pub struct BlobStoreConfig {
@@ -32,6 +42,12 @@ pub struct BlobStore {
     config: BlobStoreConfig,
     backend: Arc<dyn BlobBackend>,
     db: SqlitePool,
+    // Per-actor data keys for sealed blob contents
+    data_keys: Option<Arc<DataKeyManager>>,
+    // Whether new blob contents are sealed
+    encrypt_new: bool,
+    // Source of upload and reference timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl BlobStore {
@@ -46,7 +62,151 @@ impl BlobStore {
             }
         };
 
-        Ok(Self { config, backend, db })
+        Ok(Self {
+            config,
+            backend,
+            db,
+            data_keys: None,
+            encrypt_new: false,
+            clock: system_clock(),
+        })
+    }
+
+    /// Decrypt sealed blobs with per-actor data keys, and optionally seal
+    /// new blob contents
+    pub fn with_encryption(mut self, data_keys: Arc<DataKeyManager>, encrypt_new: bool) -> Self {
+        self.data_keys = Some(data_keys);
+        self.encrypt_new = encrypt_new;
+        self
+    }
+
+    /// Use `clock` for upload times and reference cutoffs
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Largest blob accepted, in bytes
+    pub fn max_blob_size(&self) -> usize {
+        self.config.storage.max_blob_size
+    }
+
+    /// Largest blob of `mime_type` accepted, in bytes
+    ///
+    /// Audio has its own limit; everything else shares `max_blob_size`.
+    pub fn size_limit(&self, mime_type: &str) -> usize {
+        if mime_type.starts_with("audio/") {
+            self.config.storage.max_audio_size
+        } else {
+            self.config.storage.max_blob_size
+        }
+    }
+
+    /// Blob bytes each account may store, if limited
+    pub fn account_quota(&self) -> Option<usize> {
+        self.config.storage.account_quota
+    }
+
+    /// Blob bytes stored by `did`, staged uploads included
+    pub async fn account_usage(&self, did: &str) -> PdsResult<i64> {
+        self.usage_excluding(did, "").await
+    }
+
+    /// Blob bytes stored by `did`, leaving out blob `cid`
+    async fn usage_excluding(&self, did: &str, cid: &str) -> PdsResult<i64> {
+        let bytes: i64 = sqlx::query_scalar(
+            r#"
+            SELECT (SELECT COALESCE(SUM(size), 0) FROM blob_metadata WHERE creator_did = ?1 AND cid != ?2)
+                 + (SELECT COALESCE(SUM(size), 0) FROM temp_blob_metadata
+                    WHERE creator_did = ?1 AND cid != ?2 AND cid NOT IN (SELECT cid FROM blob_metadata))
+            "#,
+        )
+        .bind(did)
+        .bind(cid)
+        .fetch_one(&self.db)
+        .await?;
+        Ok(bytes)
+    }
+
+    /// Fail if storing `size` more bytes would take `did` past its quota
+    ///
+    /// An upload of a blob the account already has (`cid`) isn't counted twice.
+    pub async fn check_quota(&self, did: &str, size: i64, cid: Option<&str>) -> PdsResult<()> {
+        let Some(quota) = self.config.storage.account_quota else {
+            return Ok(());
+        };
+        let used = self.usage_excluding(did, cid.unwrap_or("")).await?;
+        if used + size > quota as i64 {
+            return Err(PdsError::Validation(format!(
+                "Blob storage quota exceeded: {} of {} bytes used",
+                used, quota
+            )));
+        }
+        Ok(())
+    }
+
+    /// Hours staged blobs and incomplete uploads are kept
+    pub fn temp_ttl_hours(&self) -> i64 {
+        self.config.storage.temp_ttl_hours
+    }
+
+    /// Fail if `did` already has as many blobs staged as it may
+    ///
+    /// Staging a blob that is already staged (`cid`) doesn't count as another.
+    pub async fn check_staged_limit(&self, did: &str, cid: Option<&str>) -> PdsResult<()> {
+        let Some(limit) = self.config.storage.max_staged_per_account else {
+            return Ok(());
+        };
+        let staged: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM temp_blob_metadata
+            WHERE creator_did = ?1 AND cid != ?2 AND cid NOT IN (SELECT cid FROM blob_metadata)
+            "#,
+        )
+        .bind(did)
+        .bind(cid.unwrap_or(""))
+        .fetch_one(&self.db)
+        .await?;
+        if staged >= limit as i64 {
+            metrics::record_blob_staging_rejected();
+            return Err(PdsError::Validation(format!(
+                "Too many uploads awaiting use: {} blobs staged, limit is {}",
+                staged, limit
+            )));
+        }
+        Ok(())
+    }
+
+    /// Blobs staged but never committed, and their total size
+    pub async fn staged_stats(&self) -> PdsResult<(i64, i64)> {
+        let row = sqlx::query(
+            r#"
+            SELECT COUNT(*) AS count, COALESCE(SUM(size), 0) AS bytes FROM temp_blob_metadata
+            WHERE cid NOT IN (SELECT cid FROM blob_metadata)
+            "#,
+        )
+        .fetch_one(&self.db)
+        .await?;
+        Ok((row.try_get("count")?, row.try_get("bytes")?))
+    }
+
+    /// Seal blob contents with the creator's data key, if configured
+    async fn seal(&self, creator_did: &str, cid: &str, data: Vec<u8>) -> PdsResult<Vec<u8>> {
+        match &self.data_keys {
+            Some(data_keys) if self.encrypt_new => data_keys.seal_blob(creator_did, cid, &data).await,
+            _ => Ok(data),
+        }
+    }
+
+    /// Decrypt blob contents sealed with the creator's data key
+    async fn unseal(&self, creator_did: &str, cid: &str, data: Vec<u8>) -> PdsResult<Vec<u8>> {
+        if !is_sealed_blob(&data) {
+            return Ok(data);
+        }
+        match &self.data_keys {
+            Some(data_keys) => data_keys.open_blob(creator_did, cid, data).await,
+            None => Err(PdsError::BlobBackend(format!("Blob {} is encrypted", cid))),
+        }
     }
 
     /// Extract image dimensions from data
@@ -55,6 +215,9 @@ impl BlobStore {
         if !mime_type.starts_with("image/") {
             return None;
         }
+        if mime_type == "image/avif" {
+            return media::avif_dimensions(data);
+        }
 
         // Try to load the image
         match image::load_from_memory(data) {
@@ -70,23 +233,27 @@ impl BlobStore {
     }
 
     /// Generate thumbnail for an image
+    ///
+    /// Animations are thumbnailed from their first frame. AVIF can't be
+    /// decoded, so gets none.
     fn generate_thumbnail(data: &[u8], mime_type: &str, max_size: u32) -> Option<Vec<u8>> {
         // Only process images
-        if !mime_type.starts_with("image/") {
+        if !mime_type.starts_with("image/") || mime_type == "image/avif" {
             return None;
         }
 
         // Try to load and resize the image
-        match image::load_from_memory(data) {
+        match media::first_frame(data, mime_type) {
             Ok(img) => {
                 // Resize to thumbnail (preserving aspect ratio)
                 let thumb = img.thumbnail(max_size, max_size);
 
-                // Encode as JPEG (good balance of size/quality for thumbnails)
+                // Encode as JPEG (good balance of size/quality for thumbnails),
+                // which can't carry transparency
                 let mut buf = Vec::new();
                 let mut cursor = std::io::Cursor::new(&mut buf);
 
-                match thumb.write_to(&mut cursor, ImageFormat::Jpeg) {
+                match media::flatten(&thumb).write_to(&mut cursor, ImageFormat::Jpeg) {
                     Ok(_) => Some(buf),
                     Err(e) => {
                         tracing::warn!("Failed to encode thumbnail: {}", e);
@@ -101,6 +268,39 @@ impl BlobStore {
         }
     }
 
+    /// Thumbnail for a blob, with its MIME type
+    ///
+    /// An animated WebP for animations when enabled and small enough,
+    /// otherwise a still JPEG.
+    fn make_thumbnail(&self, data: &[u8], mime_type: &str, animated: bool) -> Option<(Vec<u8>, &'static str)> {
+        if animated && self.config.storage.animated_thumbnails {
+            if let Some(thumb) = media::animated_thumbnail(data, mime_type, THUMBNAIL_SIZE) {
+                return Some((thumb, "image/webp"));
+            }
+        }
+        Self::generate_thumbnail(data, mime_type, THUMBNAIL_SIZE).map(|thumb| (thumb, "image/jpeg"))
+    }
+
+    /// Duration and waveform of an audio blob, decoded off the async runtime
+    async fn analyze_audio(data: &[u8], mime_type: &str) -> AudioInfo {
+        if !mime_type.starts_with("audio/") {
+            return AudioInfo::default();
+        }
+
+        let (data, mime) = (data.to_vec(), mime_type.to_string());
+        match tokio::task::spawn_blocking(move || audio::analyze(&data, &mime)).await {
+            Ok(Some(info)) => info,
+            Ok(None) => {
+                tracing::warn!("Failed to read {} audio", mime_type);
+                AudioInfo::default()
+            }
+            Err(e) => {
+                tracing::warn!("Audio analysis failed: {}", e);
+                AudioInfo::default()
+            }
+        }
+    }
+
     /// Get temp blob file path
     fn get_temp_blob_path(&self, cid: &str) -> std::path::PathBuf {
         self.config.storage.temp_dir.join(cid)
@@ -110,11 +310,6 @@ impl BlobStore {
     ///
     /// Returns TempBlob with metadata for later commitment
     pub async fn stage_blob(&self, data: Vec<u8>, mime_type: Option<&str>, creator_did: &str) -> PdsResult<TempBlob> {
-        // Validate size
-        let size = data.len();
-        atproto::blob::validate_blob_size(size, self.config.storage.max_blob_size)
-            .map_err(|e| PdsError::Validation(e))?;
-
         // Detect MIME type from data if not provided
         let mime_type = mime_type
             .map(String::from)
@@ -126,38 +321,52 @@ impl BlobStore {
         // Validate MIME type is allowed
         self.validate_mime_type(&mime_type)?;
 
+        // Validate size against the limit for the type
+        let size = data.len();
+        atproto::blob::validate_blob_size(size, self.size_limit(&mime_type))
+            .map_err(|e| PdsError::Validation(e))?;
+
         // Calculate CID
         let cid = self.calculate_cid(&data);
+        self.check_quota(creator_did, size as i64, Some(&cid)).await?;
+        self.check_staged_limit(creator_did, Some(&cid)).await?;
 
         // Extract image dimensions if this is an image
         let dimensions = Self::extract_image_dimensions(&data, &mime_type);
         let (width, height) = dimensions
             .map(|d| (Some(d.width as i64), Some(d.height as i64)))
             .unwrap_or((None, None));
+        let animated = media::is_animated(&data, &mime_type);
+        let audio = Self::analyze_audio(&data, &mime_type).await;
 
         // Ensure temp directory exists
         fs::create_dir_all(&self.config.storage.temp_dir)
             .await
-            .map_err(|e| PdsError::BlobStorage(format!("Failed to create temp directory: {}", e)))?;
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to create temp directory: {}", e)))?;
 
         // Write to temp location
         let temp_path = self.get_temp_blob_path(&cid);
-        fs::write(&temp_path, &data)
+        let stored = self.seal(creator_did, &cid, data).await?;
+        fs::write(&temp_path, &stored)
             .await
-            .map_err(|e| PdsError::BlobStorage(format!("Failed to write temp blob: {}", e)))?;
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to write temp blob: {}", e)))?;
 
         let temp_blob = TempBlob {
             cid: cid.clone(),
             mime_type,
             size: size as i64,
             creator_did: creator_did.to_string(),
-            created_at: Utc::now(),
+            created_at: self.clock.now(),
             width,
             height,
+            animated,
+            duration_ms: audio.duration_ms,
+            waveform: audio.waveform,
         };
 
         // Store temp blob metadata in database
         self.store_temp_blob_metadata(&temp_blob).await?;
+        metrics::record_blob_staged();
 
         tracing::info!("Staged blob {} in temp storage", cid);
 
@@ -178,11 +387,12 @@ impl BlobStore {
         // Read temp blob data
         let data = fs::read(&temp_path)
             .await
-            .map_err(|e| PdsError::BlobStorage(format!("Failed to read temp blob: {}", e)))?;
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to read temp blob: {}", e)))?;
 
         // Get metadata from database (should have been stored during stage)
         let metadata = self.get_temp_blob_metadata(cid).await?
             .ok_or_else(|| PdsError::NotFound(format!("Temp blob metadata not found: {}", cid)))?;
+        let data = self.unseal(&metadata.creator_did, cid, data).await?;
 
         // Extract dimensions for thumbnail generation
         let dimensions = if let (Some(w), Some(h)) = (metadata.width, metadata.height) {
@@ -195,20 +405,23 @@ impl BlobStore {
         };
 
         // Generate thumbnail if this is an image
-        let thumbnail_cid = if let Some(thumb_data) = Self::generate_thumbnail(&data, &metadata.mime_type, 256) {
+        let thumbnail = self.make_thumbnail(&data, &metadata.mime_type, metadata.animated);
+        let thumbnail_cid = if let Some((thumb_data, thumb_mime)) = thumbnail {
             let thumb_cid = self.calculate_cid(&thumb_data);
 
             if !self.backend.exists(&thumb_cid).await? {
-                self.backend.put(&thumb_cid, thumb_data.clone(), "image/jpeg").await?;
+                let stored = self.seal(&metadata.creator_did, &thumb_cid, thumb_data.clone()).await?;
+                self.backend.put(&thumb_cid, stored, thumb_mime).await?;
 
-                let thumb_dimensions = Self::extract_image_dimensions(&thumb_data, "image/jpeg");
+                let thumb_dimensions = Self::extract_image_dimensions(&thumb_data, thumb_mime);
                 self.store_metadata_full(
                     &thumb_cid,
-                    "image/jpeg",
+                    thumb_mime,
                     thumb_data.len() as i64,
                     &metadata.creator_did,
                     thumb_dimensions.as_ref(),
                     None,
+                    media::is_animated(&thumb_data, thumb_mime),
                 ).await?;
             }
 
@@ -218,7 +431,8 @@ impl BlobStore {
         };
 
         // Move to permanent storage
-        self.backend.put(cid, data, &metadata.mime_type).await?;
+        let stored = self.seal(&metadata.creator_did, cid, data).await?;
+        self.backend.put(cid, stored, &metadata.mime_type).await?;
 
         // Store permanent metadata
         self.store_metadata_full(
@@ -228,12 +442,18 @@ impl BlobStore {
             &metadata.creator_did,
             dimensions.as_ref(),
             thumbnail_cid.as_deref(),
+            metadata.animated,
         ).await?;
+        let audio = AudioInfo {
+            duration_ms: metadata.duration_ms,
+            waveform: metadata.waveform,
+        };
+        self.store_audio_metadata(cid, &audio).await?;
 
         // Delete temp file
         fs::remove_file(&temp_path)
             .await
-            .map_err(|e| PdsError::BlobStorage(format!("Failed to delete temp blob: {}", e)))?;
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to delete temp blob: {}", e)))?;
 
         // Delete temp metadata
         self.delete_temp_blob_metadata(cid).await?;
@@ -247,11 +467,6 @@ impl BlobStore {
     ///
     /// Returns the blob metadata and reference
     pub async fn upload(&self, data: Vec<u8>, mime_type: Option<&str>, creator_did: &str) -> PdsResult<BlobRef> {
-        // Validate size
-        let size = data.len();
-        atproto::blob::validate_blob_size(size, self.config.storage.max_blob_size)
-            .map_err(|e| PdsError::Validation(e))?;
-
         // Detect MIME type from data if not provided
         let mime_type = mime_type
             .map(String::from)
@@ -263,32 +478,43 @@ impl BlobStore {
         // Validate MIME type is allowed
         self.validate_mime_type(&mime_type)?;
 
+        // Validate size against the limit for the type
+        let size = data.len();
+        atproto::blob::validate_blob_size(size, self.size_limit(&mime_type))
+            .map_err(|e| PdsError::Validation(e))?;
+
         // Calculate CID (using SHA-256 hash)
         let cid = self.calculate_cid(&data);
+        self.check_quota(creator_did, size as i64, Some(&cid)).await?;
 
         // Extract image dimensions if this is an image
         let dimensions = Self::extract_image_dimensions(&data, &mime_type);
 
+        let animated = media::is_animated(&data, &mime_type);
+        let audio = Self::analyze_audio(&data, &mime_type).await;
+
         // Generate thumbnail if this is an image (256x256 max)
-        let thumbnail_cid = if let Some(thumb_data) = Self::generate_thumbnail(&data, &mime_type, 256) {
+        let thumbnail_cid = if let Some((thumb_data, thumb_mime)) = self.make_thumbnail(&data, &mime_type, animated) {
             // Calculate thumbnail CID
             let thumb_cid = self.calculate_cid(&thumb_data);
 
             // Store thumbnail blob
             if !self.backend.exists(&thumb_cid).await? {
-                self.backend.put(&thumb_cid, thumb_data.clone(), "image/jpeg").await?;
+                let stored = self.seal(creator_did, &thumb_cid, thumb_data.clone()).await?;
+                self.backend.put(&thumb_cid, stored, thumb_mime).await?;
 
                 // Extract dimensions from thumbnail
-                let thumb_dimensions = Self::extract_image_dimensions(&thumb_data, "image/jpeg");
+                let thumb_dimensions = Self::extract_image_dimensions(&thumb_data, thumb_mime);
 
                 // Store thumbnail metadata with dimensions
                 self.store_metadata_full(
                     &thumb_cid,
-                    "image/jpeg",
+                    thumb_mime,
                     thumb_data.len() as i64,
                     creator_did,
                     thumb_dimensions.as_ref(),
                     None, // thumbnails don't have their own thumbnails
+                    media::is_animated(&thumb_data, thumb_mime),
                 ).await?;
             }
 
@@ -304,7 +530,8 @@ impl BlobStore {
         }
 
         // Store blob in backend
-        self.backend.put(&cid, data, &mime_type).await?;
+        let stored = self.seal(creator_did, &cid, data).await?;
+        self.backend.put(&cid, stored, &mime_type).await?;
 
         // Store metadata in database with dimensions and thumbnail
         self.store_metadata_full(
@@ -314,7 +541,9 @@ impl BlobStore {
             creator_did,
             dimensions.as_ref(),
             thumbnail_cid.as_deref(),
+            animated,
         ).await?;
+        self.store_audio_metadata(&cid, &audio).await?;
 
         Ok(BlobRef::new(cid, mime_type, size as i64))
     }
@@ -325,8 +554,15 @@ impl BlobStore {
         let data = self.backend.get(cid).await?;
 
         if let Some(data) = data {
-            // Get MIME type from database
+            // Get MIME type (and creator, for sealed blobs) from database
             let metadata = self.get_metadata(cid).await?;
+            let data = match &metadata {
+                Some(m) => self.unseal(&m.creator_did, cid, data).await?,
+                None if is_sealed_blob(&data) => {
+                    return Err(PdsError::BlobBackend(format!("No owner recorded for encrypted blob {}", cid)))
+                }
+                None => data,
+            };
             let mime_type = metadata
                 .map(|m| m.mime_type)
                 .unwrap_or_else(|| "application/octet-stream".to_string());
@@ -337,6 +573,47 @@ impl BlobStore {
         }
     }
 
+    /// Seal an actor's blobs with `keys.current`
+    ///
+    /// Blobs sealed with the previous key are re-sealed; plaintext blobs are
+    /// encrypted only if `seal_plaintext` is set. Returns the number of blobs
+    /// rewritten.
+    pub async fn reencrypt_for_creator(
+        &self,
+        did: &str,
+        keys: &ActorKeys,
+        seal_plaintext: bool,
+    ) -> PdsResult<usize> {
+        let cids: Vec<String> = sqlx::query_scalar("SELECT cid FROM blob_metadata WHERE creator_did = ?1")
+            .bind(did)
+            .fetch_all(&self.db)
+            .await?;
+
+        let mut rewritten = 0;
+        for cid in cids {
+            let Some(data) = self.backend.get(&cid).await? else {
+                continue;
+            };
+            let plaintext = if is_sealed_blob(&data) {
+                DataKeyManager::open_with(keys, &cid, &data)?
+            } else if seal_plaintext {
+                data
+            } else {
+                continue;
+            };
+            let sealed = DataKeyManager::seal_with(&keys.current, &cid, &plaintext)?;
+            let mime_type = self
+                .get_metadata(&cid)
+                .await?
+                .map(|m| m.mime_type)
+                .unwrap_or_else(|| "application/octet-stream".to_string());
+            self.backend.put(&cid, sealed, &mime_type).await?;
+            rewritten += 1;
+        }
+
+        Ok(rewritten)
+    }
+
     /// Delete a blob
     pub async fn delete(&self, cid: &str) -> PdsResult<()> {
         // Delete from backend
@@ -348,6 +625,105 @@ impl BlobStore {
         Ok(())
     }
 
+    /// Claim a record reference to each blob in `cids` for a write by `did`
+    ///
+    /// Blobs `did` uploaded but hasn't used yet are committed from temp
+    /// storage first. If a blob doesn't exist the references already claimed
+    /// are released and the write is rejected.
+    pub async fn claim_refs(&self, did: &str, cids: &[String]) -> PdsResult<()> {
+        for (i, cid) in cids.iter().enumerate() {
+            if let Err(e) = self.claim_ref(did, cid).await {
+                if let Err(release_err) = self.release_refs(&cids[..i]).await {
+                    tracing::warn!("Failed to release blob references: {}", release_err);
+                }
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    async fn claim_ref(&self, did: &str, cid: &str) -> PdsResult<()> {
+        if let Some(temp) = self.get_temp_blob_metadata(cid).await? {
+            if temp.creator_did == did {
+                self.commit_blob(cid).await?;
+            }
+        }
+
+        // Counts stay NULL for blobs stored before references were counted
+        let claimed = sqlx::query(
+            "UPDATE blob_metadata SET ref_count = ref_count + 1, unreferenced_since = NULL WHERE cid = ?1",
+        )
+        .bind(cid)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+
+        if claimed == 0 {
+            return Err(PdsError::Validation(format!("Could not find blob: {}", cid)));
+        }
+        Ok(())
+    }
+
+    /// Release one record reference to each blob in `cids`
+    ///
+    /// A blob left with no references is deleted by `delete_unreferenced`
+    /// once its grace period has passed.
+    pub async fn release_refs(&self, cids: &[String]) -> PdsResult<()> {
+        let now = self.clock.now();
+        for cid in cids {
+            sqlx::query(
+                "UPDATE blob_metadata SET
+                    ref_count = MAX(ref_count - 1, 0),
+                    unreferenced_since = CASE WHEN ref_count <= 1 THEN ?2 ELSE unreferenced_since END
+                 WHERE cid = ?1",
+            )
+            .bind(cid)
+            .bind(now)
+            .execute(&self.db)
+            .await?;
+        }
+        Ok(())
+    }
+
+    /// Delete blobs no record has referenced for `grace_hours`
+    ///
+    /// Thumbnails go once the blob they belong to is gone. Returns the
+    /// number of blobs deleted.
+    pub async fn delete_unreferenced(&self, grace_hours: i64) -> PdsResult<u64> {
+        let cutoff = self.clock.now() - chrono::Duration::hours(grace_hours);
+
+        let cids: Vec<String> = sqlx::query_scalar(
+            "SELECT cid FROM blob_metadata
+             WHERE ref_count = 0 AND unreferenced_since < ?1
+               AND cid NOT IN (SELECT thumbnail_cid FROM blob_metadata WHERE thumbnail_cid IS NOT NULL)
+             ORDER BY unreferenced_since
+             LIMIT 500",
+        )
+        .bind(cutoff)
+        .fetch_all(&self.db)
+        .await?;
+
+        let mut deleted = 0;
+        for cid in cids {
+            // Re-checked so a blob claimed in the meantime is kept
+            let removed = sqlx::query(
+                "DELETE FROM blob_metadata WHERE cid = ?1 AND ref_count = 0 AND unreferenced_since < ?2",
+            )
+            .bind(&cid)
+            .bind(cutoff)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+
+            if removed > 0 {
+                self.backend.delete(&cid).await?;
+                deleted += 1;
+            }
+        }
+
+        Ok(deleted)
+    }
+
     /// Calculate CID for data using SHA-256
     fn calculate_cid(&self, data: &[u8]) -> String {
         let hash = Sha256::digest(data);
@@ -362,6 +738,10 @@ impl BlobStore {
             "image/png",
             "image/gif",
             "image/webp",
+            "image/avif",
+            "audio/mpeg",
+            "audio/ogg",
+            "audio/mp4",
             "video/mp4",
             "video/quicktime",
             "video/webm",
@@ -381,8 +761,8 @@ impl BlobStore {
     async fn store_metadata(&self, cid: &str, mime_type: &str, size: i64, creator_did: &str) -> PdsResult<()> {
         sqlx::query(
             r#"
-            INSERT INTO blob_metadata (cid, mime_type, size, creator_did, created_at)
-            VALUES (?1, ?2, ?3, ?4, ?5)
+            INSERT INTO blob_metadata (cid, mime_type, size, creator_did, created_at, ref_count, unreferenced_since)
+            VALUES (?1, ?2, ?3, ?4, ?5, 0, ?5)
             ON CONFLICT(cid) DO NOTHING
             "#,
         )
@@ -390,7 +770,7 @@ impl BlobStore {
         .bind(mime_type)
         .bind(size)
         .bind(creator_did)
-        .bind(Utc::now())
+        .bind(self.clock.now())
         .execute(&self.db)
         .await
         .map_err(|e| PdsError::Database(e))?;
@@ -407,6 +787,7 @@ impl BlobStore {
         creator_did: &str,
         dimensions: Option<&ImageDimensions>,
         thumbnail_cid: Option<&str>,
+        animated: bool,
     ) -> PdsResult<()> {
         let (width, height) = dimensions
             .map(|d| (Some(d.width as i64), Some(d.height as i64)))
@@ -414,22 +795,25 @@ impl BlobStore {
 
         sqlx::query(
             r#"
-            INSERT INTO blob_metadata (cid, mime_type, size, creator_did, created_at, width, height, thumbnail_cid)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)
+            INSERT INTO blob_metadata
+                (cid, mime_type, size, creator_did, created_at, width, height, thumbnail_cid, animated, ref_count, unreferenced_since)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, 0, ?5)
             ON CONFLICT(cid) DO UPDATE SET
                 width = excluded.width,
                 height = excluded.height,
-                thumbnail_cid = excluded.thumbnail_cid
+                thumbnail_cid = excluded.thumbnail_cid,
+                animated = excluded.animated
             "#,
         )
         .bind(cid)
         .bind(mime_type)
         .bind(size)
         .bind(creator_did)
-        .bind(Utc::now())
+        .bind(self.clock.now())
         .bind(width)
         .bind(height)
         .bind(thumbnail_cid)
+        .bind(animated)
         .execute(&self.db)
         .await
         .map_err(|e| PdsError::Database(e))?;
@@ -437,17 +821,35 @@ impl BlobStore {
         Ok(())
     }
 
+    /// Record an audio blob's duration and waveform
+    async fn store_audio_metadata(&self, cid: &str, audio: &AudioInfo) -> PdsResult<()> {
+        if audio.duration_ms.is_none() && audio.waveform.is_none() {
+            return Ok(());
+        }
+        sqlx::query("UPDATE blob_metadata SET duration_ms = ?2, waveform = ?3 WHERE cid = ?1")
+            .bind(cid)
+            .bind(audio.duration_ms)
+            .bind(&audio.waveform)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
     /// Store temp blob metadata in database
     async fn store_temp_blob_metadata(&self, temp_blob: &TempBlob) -> PdsResult<()> {
         sqlx::query(
             r#"
-            INSERT INTO temp_blob_metadata (cid, mime_type, size, creator_did, created_at, width, height)
-            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+            INSERT INTO temp_blob_metadata
+                (cid, mime_type, size, creator_did, created_at, width, height, animated, duration_ms, waveform)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
             ON CONFLICT(cid) DO UPDATE SET
                 mime_type = excluded.mime_type,
                 size = excluded.size,
                 width = excluded.width,
-                height = excluded.height
+                height = excluded.height,
+                animated = excluded.animated,
+                duration_ms = excluded.duration_ms,
+                waveform = excluded.waveform
             "#,
         )
         .bind(&temp_blob.cid)
@@ -457,6 +859,9 @@ impl BlobStore {
         .bind(temp_blob.created_at)
         .bind(temp_blob.width)
         .bind(temp_blob.height)
+        .bind(temp_blob.animated)
+        .bind(temp_blob.duration_ms)
+        .bind(&temp_blob.waveform)
         .execute(&self.db)
         .await
         .map_err(|e| PdsError::Database(e))?;
@@ -468,7 +873,7 @@ impl BlobStore {
     async fn get_temp_blob_metadata(&self, cid: &str) -> PdsResult<Option<TempBlob>> {
         let result = sqlx::query(
             r#"
-            SELECT cid, mime_type, size, creator_did, created_at, width, height
+            SELECT cid, mime_type, size, creator_did, created_at, width, height, animated, duration_ms, waveform
             FROM temp_blob_metadata
             WHERE cid = ?1
             "#,
@@ -487,6 +892,9 @@ impl BlobStore {
                 created_at: row.try_get("created_at")?,
                 width: row.try_get("width")?,
                 height: row.try_get("height")?,
+                animated: row.try_get("animated")?,
+                duration_ms: row.try_get("duration_ms")?,
+                waveform: row.try_get("waveform")?,
             }))
         } else {
             Ok(None)
@@ -506,7 +914,7 @@ impl BlobStore {
 
     /// List orphaned temp blobs (older than ttl)
     pub async fn list_orphaned_temp_blobs(&self, ttl_hours: i64) -> PdsResult<Vec<String>> {
-        let cutoff = Utc::now() - chrono::Duration::hours(ttl_hours);
+        let cutoff = self.clock.now() - chrono::Duration::hours(ttl_hours);
 
         let rows = sqlx::query(
             r#"
@@ -536,7 +944,7 @@ impl BlobStore {
         if temp_path.exists() {
             fs::remove_file(&temp_path)
                 .await
-                .map_err(|e| PdsError::BlobStorage(format!("Failed to delete temp blob file: {}", e)))?;
+                .map_err(|e| PdsError::BlobBackend(format!("Failed to delete temp blob file: {}", e)))?;
         }
 
         // Delete metadata
@@ -549,7 +957,8 @@ impl BlobStore {
     pub async fn get_metadata(&self, cid: &str) -> PdsResult<Option<BlobMetadata>> {
         let result = sqlx::query(
             r#"
-            SELECT cid, mime_type, size, creator_did, created_at, width, height, alt_text, thumbnail_cid
+            SELECT cid, mime_type, size, creator_did, created_at, width, height, alt_text, thumbnail_cid, animated,
+                   duration_ms, waveform
             FROM blob_metadata
             WHERE cid = ?1
             "#,
@@ -570,12 +979,48 @@ impl BlobStore {
                 height: row.try_get("height")?,
                 alt_text: row.try_get("alt_text")?,
                 thumbnail_cid: row.try_get("thumbnail_cid")?,
+                animated: row.try_get("animated")?,
+                duration_ms: row.try_get("duration_ms")?,
+                waveform: row.try_get("waveform")?,
             }))
         } else {
             Ok(None)
         }
     }
 
+    /// Processing status of a blob uploaded by `did`
+    pub async fn processing_status(&self, did: &str, cid: &str) -> PdsResult<Option<BlobProcessingStatus>> {
+        if let Some(blob) = self.get_metadata(cid).await?.filter(|m| m.creator_did == did) {
+            return Ok(Some(BlobProcessingStatus {
+                cid: blob.cid,
+                mime_type: blob.mime_type,
+                size: blob.size,
+                state: BlobProcessingState::Ready,
+                width: blob.width,
+                height: blob.height,
+                animated: blob.animated,
+                duration_ms: blob.duration_ms,
+                thumbnail_cid: blob.thumbnail_cid,
+            }));
+        }
+
+        Ok(self
+            .get_temp_blob_metadata(cid)
+            .await?
+            .filter(|t| t.creator_did == did)
+            .map(|temp| BlobProcessingStatus {
+                cid: temp.cid,
+                mime_type: temp.mime_type,
+                size: temp.size,
+                state: BlobProcessingState::Staged,
+                width: temp.width,
+                height: temp.height,
+                animated: temp.animated,
+                duration_ms: temp.duration_ms,
+                thumbnail_cid: None,
+            }))
+    }
+
     /// Delete blob metadata from database
     async fn delete_metadata(&self, cid: &str) -> PdsResult<()> {
         sqlx::query("DELETE FROM blob_metadata WHERE cid = ?1")
@@ -587,11 +1032,96 @@ impl BlobStore {
         Ok(())
     }
 
+    /// List an account's blobs, ordered by CID, after `cursor`
+    pub async fn list_for_account(&self, did: &str, limit: i64, cursor: Option<&str>) -> PdsResult<Vec<AccountBlob>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT cid, mime_type, size, created_at, ref_count, thumbnail_cid
+            FROM blob_metadata
+            WHERE creator_did = ?1 AND (?2 IS NULL OR cid > ?2)
+            ORDER BY cid
+            LIMIT ?3
+            "#,
+        )
+        .bind(did)
+        .bind(cursor)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(AccountBlob {
+                    cid: row.try_get("cid")?,
+                    mime_type: row.try_get("mime_type")?,
+                    size: row.try_get("size")?,
+                    created_at: row.try_get("created_at")?,
+                    ref_count: row.try_get("ref_count")?,
+                    thumbnail_cid: row.try_get("thumbnail_cid")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Blob storage per account, largest first by `sort`
+    ///
+    /// `offset` pages through the ranking; it shifts as accounts upload, so
+    /// treat later pages as approximate.
+    pub async fn usage_by_account(&self, sort: BlobUsageSort, limit: i64, offset: i64) -> PdsResult<Vec<BlobUsage>> {
+        let order = match sort {
+            BlobUsageSort::Bytes => "bytes DESC, blobs DESC",
+            BlobUsageSort::Count => "blobs DESC, bytes DESC",
+        };
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT b.creator_did AS did,
+                   COUNT(*) AS blobs,
+                   COALESCE(SUM(b.size), 0) AS bytes,
+                   COALESCE(SUM(CASE WHEN b.ref_count = 0 AND t.cid IS NULL THEN 1 ELSE 0 END), 0) AS unreferenced_blobs,
+                   COALESCE(SUM(CASE WHEN b.ref_count = 0 AND t.cid IS NULL THEN b.size ELSE 0 END), 0) AS unreferenced_bytes,
+                   MAX(b.created_at) AS last_upload_at
+            FROM blob_metadata b
+            LEFT JOIN (SELECT DISTINCT thumbnail_cid AS cid FROM blob_metadata WHERE thumbnail_cid IS NOT NULL) t
+                   ON t.cid = b.cid
+            GROUP BY b.creator_did
+            ORDER BY {}, did
+            LIMIT ?1 OFFSET ?2
+            "#,
+            order
+        ))
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(BlobUsage {
+                    did: row.try_get("did")?,
+                    blobs: row.try_get("blobs")?,
+                    bytes: row.try_get("bytes")?,
+                    unreferenced_blobs: row.try_get("unreferenced_blobs")?,
+                    unreferenced_bytes: row.try_get("unreferenced_bytes")?,
+                    last_upload_at: row.try_get("last_upload_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Blob count and bytes across every account
+    pub async fn total_usage(&self) -> PdsResult<(i64, i64)> {
+        let totals: (i64, i64) = sqlx::query_as("SELECT COUNT(*), COALESCE(SUM(size), 0) FROM blob_metadata")
+            .fetch_one(&self.db)
+            .await?;
+        Ok(totals)
+    }
+
     /// List blobs for a user
     pub async fn list_for_user(&self, did: &str, limit: i64) -> PdsResult<Vec<BlobMetadata>> {
         let rows = sqlx::query(
             r#"
-            SELECT cid, mime_type, size, creator_did, created_at, width, height, alt_text, thumbnail_cid
+            SELECT cid, mime_type, size, creator_did, created_at, width, height, alt_text, thumbnail_cid, animated,
+                   duration_ms, waveform
             FROM blob_metadata
             WHERE creator_did = ?1
             ORDER BY created_at DESC
@@ -616,6 +1146,9 @@ impl BlobStore {
                 height: row.try_get("height")?,
                 alt_text: row.try_get("alt_text")?,
                 thumbnail_cid: row.try_get("thumbnail_cid")?,
+                animated: row.try_get("animated")?,
+                duration_ms: row.try_get("duration_ms")?,
+                waveform: row.try_get("waveform")?,
             });
         }
 
@@ -636,6 +1169,11 @@ mod tests {
                     location: dir.path().to_path_buf(),
                 },
                 max_blob_size: 1024 * 1024,
+                max_audio_size: 10 * 1024 * 1024,
+                account_quota: None,
+                animated_thumbnails: false,
+                temp_ttl_hours: 24,
+                max_staged_per_account: None,
                 temp_dir: dir.path().join("tmp"),
             },
         };
@@ -655,7 +1193,31 @@ mod tests {
                 width INTEGER,
                 height INTEGER,
                 alt_text TEXT,
-                thumbnail_cid TEXT
+                thumbnail_cid TEXT,
+                ref_count INTEGER,
+                unreferenced_since DATETIME,
+                animated INTEGER NOT NULL DEFAULT 0,
+                duration_ms INTEGER,
+                waveform BLOB
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE temp_blob_metadata (
+                cid TEXT PRIMARY KEY,
+                mime_type TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                creator_did TEXT NOT NULL,
+                created_at DATETIME NOT NULL,
+                width INTEGER,
+                height INTEGER,
+                animated INTEGER NOT NULL DEFAULT 0,
+                duration_ms INTEGER,
+                waveform BLOB
             )
             "#,
         )
@@ -682,6 +1244,33 @@ mod tests {
         assert_eq!(mime_type, "image/png");
     }
 
+    #[tokio::test]
+    async fn test_encrypted_blob_roundtrip() {
+        use crate::crypto::keys::{ConfigKeys, KeyManager};
+
+        let key_dir = tempdir().unwrap();
+        let master = hex::encode([5u8; 32]);
+        let keys = KeyManager::new(Arc::new(ConfigKeys::new([("master", master.as_str())])), "master", "master");
+        let data_keys = Arc::new(DataKeyManager::new(Arc::new(keys), "master", key_dir.path().to_path_buf()));
+        let store = create_test_store().await.with_encryption(data_keys.clone(), true);
+
+        let data = b"secret image data".to_vec();
+        let blob_ref = store.upload(data.clone(), Some("image/png"), "did:plc:owner").await.unwrap();
+
+        // Sealed in the backend, plaintext through the store
+        let stored = store.backend.get(&blob_ref.r#ref.link).await.unwrap().unwrap();
+        assert!(is_sealed_blob(&stored));
+        let (retrieved, _) = store.get(&blob_ref.r#ref.link).await.unwrap().unwrap();
+        assert_eq!(retrieved, data);
+
+        // Re-sealing under a rotated key keeps the blob readable
+        let rotated = data_keys.begin_rotation("did:plc:owner").await.unwrap();
+        assert_eq!(store.reencrypt_for_creator("did:plc:owner", &rotated, false).await.unwrap(), 1);
+        data_keys.finish_rotation("did:plc:owner").await.unwrap();
+        let (retrieved, _) = store.get(&blob_ref.r#ref.link).await.unwrap().unwrap();
+        assert_eq!(retrieved, data);
+    }
+
     #[tokio::test]
     async fn test_upload_duplicate_blob() {
         let store = create_test_store().await;
@@ -696,6 +1285,38 @@ mod tests {
         assert_eq!(blob_ref1.r#ref.link, blob_ref2.r#ref.link);
     }
 
+    #[tokio::test]
+    async fn test_usage_by_account() {
+        let store = create_test_store().await;
+
+        let mut alice = Vec::new();
+        for size in [10, 20, 30] {
+            let blob = store.upload(vec![size as u8; size], Some("image/png"), "did:plc:alice").await.unwrap();
+            alice.push(blob.r#ref.link);
+        }
+        store.upload(vec![1u8; 100], Some("image/png"), "did:plc:bob").await.unwrap();
+        store.claim_refs("did:plc:alice", &alice[..1]).await.unwrap();
+
+        let by_bytes = store.usage_by_account(BlobUsageSort::Bytes, 10, 0).await.unwrap();
+        assert_eq!(by_bytes.iter().map(|u| u.did.as_str()).collect::<Vec<_>>(), vec!["did:plc:bob", "did:plc:alice"]);
+        let usage = &by_bytes[1];
+        assert_eq!((usage.blobs, usage.bytes), (3, 60));
+        assert_eq!((usage.unreferenced_blobs, usage.unreferenced_bytes), (2, 50));
+        assert!(usage.last_upload_at.is_some());
+
+        let by_count = store.usage_by_account(BlobUsageSort::Count, 1, 0).await.unwrap();
+        assert_eq!(by_count[0].did, "did:plc:alice");
+        assert_eq!(store.usage_by_account(BlobUsageSort::Count, 1, 1).await.unwrap()[0].did, "did:plc:bob");
+        assert_eq!(store.total_usage().await.unwrap(), (4, 160));
+
+        let page = store.list_for_account("did:plc:alice", 2, None).await.unwrap();
+        assert_eq!(page.len(), 2);
+        let rest = store.list_for_account("did:plc:alice", 2, Some(&page[1].cid)).await.unwrap();
+        assert_eq!(rest.len(), 1);
+        let claimed = page.iter().chain(&rest).find(|b| b.cid == alice[0]).unwrap();
+        assert_eq!(claimed.ref_count, Some(1));
+    }
+
     #[tokio::test]
     async fn test_upload_oversized_blob() {
         let store = create_test_store().await;
@@ -797,4 +1418,126 @@ mod tests {
         assert_eq!(metadata.size, 9);
         assert_eq!(metadata.creator_did, "did:plc:test");
     }
+
+    #[tokio::test]
+    async fn test_blob_references() {
+        let store = create_test_store().await;
+        let ref_count = |cid: String| {
+            let db = store.db.clone();
+            async move {
+                sqlx::query_scalar::<_, Option<i64>>("SELECT ref_count FROM blob_metadata WHERE cid = ?1")
+                    .bind(cid)
+                    .fetch_optional(&db)
+                    .await
+                    .unwrap()
+                    .flatten()
+            }
+        };
+
+        // Only the uploader's own staged blobs are committed by a write
+        let temp = store.stage_blob(b"staged".to_vec(), Some("image/png"), "did:plc:owner").await.unwrap();
+        let cids = vec![temp.cid.clone()];
+        assert!(matches!(store.claim_refs("did:plc:other", &cids).await, Err(PdsError::Validation(_))));
+        store.claim_refs("did:plc:owner", &cids).await.unwrap();
+        assert!(store.get_metadata(&temp.cid).await.unwrap().is_some());
+        assert_eq!(ref_count(temp.cid.clone()).await, Some(1));
+
+        // A missing blob rejects the whole claim
+        let uploaded = store.upload(b"uploaded".to_vec(), Some("image/png"), "did:plc:owner").await.unwrap();
+        let uploaded = uploaded.r#ref.link;
+        let cids = vec![temp.cid.clone(), "bafyreimissing".to_string()];
+        assert!(store.claim_refs("did:plc:owner", &cids).await.is_err());
+        assert_eq!(ref_count(temp.cid.clone()).await, Some(1));
+
+        // Referenced blobs survive collection, released ones don't
+        store.release_refs(&[temp.cid.clone()]).await.unwrap();
+        store.claim_refs("did:plc:owner", &[uploaded.clone()]).await.unwrap();
+        // A negative grace period collects everything unreferenced now
+        assert_eq!(store.delete_unreferenced(-1).await.unwrap(), 1);
+        assert!(store.get_metadata(&temp.cid).await.unwrap().is_none());
+        assert!(store.get(&uploaded).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_account_quota_and_processing_status() {
+        let mut store = create_test_store().await;
+        store.config.storage.account_quota = Some(20);
+
+        let temp = store.stage_blob(vec![1u8; 12], Some("image/png"), "did:plc:owner").await.unwrap();
+        assert_eq!(store.account_usage("did:plc:owner").await.unwrap(), 12);
+        let status = store.processing_status("did:plc:owner", &temp.cid).await.unwrap().unwrap();
+        assert_eq!(status.state, BlobProcessingState::Staged);
+        assert!(store.processing_status("did:plc:other", &temp.cid).await.unwrap().is_none());
+
+        // Over quota, but the same blob again is fine, as are other accounts
+        let over = store.stage_blob(vec![2u8; 12], Some("image/png"), "did:plc:owner").await;
+        assert!(matches!(over, Err(PdsError::Validation(_))));
+        store.stage_blob(vec![1u8; 12], Some("image/png"), "did:plc:owner").await.unwrap();
+        store.stage_blob(vec![2u8; 12], Some("image/png"), "did:plc:other").await.unwrap();
+        assert!(store.check_quota("did:plc:owner", 8, None).await.is_ok());
+        assert!(store.check_quota("did:plc:owner", 9, None).await.is_err());
+
+        store.commit_blob(&temp.cid).await.unwrap();
+        assert_eq!(store.account_usage("did:plc:owner").await.unwrap(), 12);
+        let status = store.processing_status("did:plc:owner", &temp.cid).await.unwrap().unwrap();
+        assert_eq!(status.state, BlobProcessingState::Ready);
+    }
+
+    #[tokio::test]
+    async fn test_animated_and_avif_blobs() {
+        use image::{codecs::gif::GifEncoder, Delay, Frame, Rgba, RgbaImage};
+
+        let store = create_test_store().await;
+
+        let mut gif = Vec::new();
+        {
+            let mut encoder = GifEncoder::new_with_speed(&mut gif, 10);
+            for color in [Rgba([255, 0, 0, 255]), Rgba([0, 0, 255, 0])] {
+                let frame = Frame::from_parts(RgbaImage::from_pixel(600, 300, color), 0, 0, Delay::from_numer_denom_ms(80, 1));
+                encoder.encode_frame(frame).unwrap();
+            }
+        }
+        let temp = store.stage_blob(gif, Some("image/gif"), "did:plc:test").await.unwrap();
+        assert!(temp.animated);
+        store.commit_blob(&temp.cid).await.unwrap();
+
+        // A still JPEG of the first frame
+        let metadata = store.get_metadata(&temp.cid).await.unwrap().unwrap();
+        assert!(metadata.animated);
+        assert_eq!((metadata.width, metadata.height), (Some(600), Some(300)));
+        let thumb = store.get_metadata(&metadata.thumbnail_cid.unwrap()).await.unwrap().unwrap();
+        assert_eq!(thumb.mime_type, "image/jpeg");
+        assert!(!thumb.animated);
+        assert_eq!((thumb.width, thumb.height), (Some(256), Some(128)));
+
+        // AVIF is accepted, sniffed and measured, without a thumbnail
+        let mut avif = b"\x00\x00\x00\x1cftypavif\x00\x00\x00\x00avifmif1".to_vec();
+        avif.extend_from_slice(b"\x00\x00\x00\x14ispe\x00\x00\x00\x00");
+        avif.extend_from_slice(&800u32.to_be_bytes());
+        avif.extend_from_slice(&600u32.to_be_bytes());
+        let temp = store.stage_blob(avif, None, "did:plc:test").await.unwrap();
+        assert_eq!(temp.mime_type, "image/avif");
+        assert_eq!((temp.width, temp.height), (Some(800), Some(600)));
+        assert!(!temp.animated);
+        store.commit_blob(&temp.cid).await.unwrap();
+        assert!(store.get_metadata(&temp.cid).await.unwrap().unwrap().thumbnail_cid.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_audio_size_limit() {
+        let store = create_test_store().await;
+        assert_eq!(store.size_limit("audio/mpeg"), 10 * 1024 * 1024);
+        assert_eq!(store.size_limit("image/png"), 1024 * 1024);
+
+        // Over the image limit, under the audio one
+        let mut mp3 = b"ID3\x04\x00\x00\x00\x00\x00\x00".to_vec();
+        mp3.resize(2 * 1024 * 1024, 0);
+        let temp = store.stage_blob(mp3.clone(), None, "did:plc:test").await.unwrap();
+        assert_eq!(temp.mime_type, "audio/mpeg");
+        // Nothing decodable, so nothing to show
+        assert_eq!((temp.duration_ms, temp.waveform), (None, None));
+
+        let result = store.stage_blob(mp3, Some("image/png"), "did:plc:test").await;
+        assert!(result.unwrap_err().to_string().contains("exceeds maximum"));
+    }
 }
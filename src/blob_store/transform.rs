@@ -0,0 +1,246 @@
+/// On-demand image variants
+///
+/// Serves resized, re-encoded copies of image blobs for the presets clients
+/// render (avatars, feed thumbnails, full-size feed images, banners), so a
+/// self-hosted PDS doesn't need an image CDN in front of it. Variants are
+/// generated on first request and cached in Redis when it is configured,
+/// otherwise on disk under `<directory>/<preset>/<cid>.jpg`.
+///
+/// Every variant is a JPEG and never larger than its source image.
+use crate::{
+    blob_store::media,
+    cache::CacheClient,
+    error::{PdsError, PdsResult},
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageReader, Limits};
+use std::{io::Cursor, path::PathBuf};
+use tokio::fs;
+
+/// Largest source image dimension accepted for transforms
+const MAX_SOURCE_DIMENSION: u32 = 12_000;
+
+/// Decoder memory ceiling for one source image
+const MAX_DECODE_BYTES: u64 = 256 * 1024 * 1024;
+
+/// JPEG quality for variants
+const JPEG_QUALITY: u8 = 85;
+
+/// Redis TTL for cached variants
+const REDIS_TTL_SECS: u64 = 86_400;
+
+/// How a variant is sized
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Fit {
+    /// Fill the box exactly, cropping the overflow
+    Cover(u32, u32),
+    /// Fit inside the box, keeping the aspect ratio
+    Contain(u32, u32),
+}
+
+/// Named image variant
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImagePreset {
+    Avatar,
+    AvatarThumbnail,
+    Banner,
+    FeedThumbnail,
+    FeedFullsize,
+}
+
+impl ImagePreset {
+    /// Parse the preset segment of an image URL
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "avatar" => Some(Self::Avatar),
+            "avatar_thumbnail" => Some(Self::AvatarThumbnail),
+            "banner" => Some(Self::Banner),
+            "feed_thumbnail" => Some(Self::FeedThumbnail),
+            "feed_fullsize" | "full" => Some(Self::FeedFullsize),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Avatar => "avatar",
+            Self::AvatarThumbnail => "avatar_thumbnail",
+            Self::Banner => "banner",
+            Self::FeedThumbnail => "feed_thumbnail",
+            Self::FeedFullsize => "feed_fullsize",
+        }
+    }
+
+    fn fit(&self) -> Fit {
+        match self {
+            Self::Avatar => Fit::Cover(1000, 1000),
+            Self::AvatarThumbnail => Fit::Cover(128, 128),
+            Self::Banner => Fit::Cover(3000, 1000),
+            Self::FeedThumbnail => Fit::Contain(1000, 1000),
+            Self::FeedFullsize => Fit::Contain(2000, 2000),
+        }
+    }
+
+    /// Resize and re-encode `data` as a JPEG for this preset
+    pub fn render(&self, data: &[u8]) -> PdsResult<Vec<u8>> {
+        let mut limits = Limits::default();
+        limits.max_image_width = Some(MAX_SOURCE_DIMENSION);
+        limits.max_image_height = Some(MAX_SOURCE_DIMENSION);
+        limits.max_alloc = Some(MAX_DECODE_BYTES);
+
+        let mut reader = ImageReader::new(Cursor::new(data))
+            .with_guessed_format()
+            .map_err(|e| PdsError::Validation(format!("Unreadable image: {}", e)))?;
+        reader.limits(limits);
+        let img = reader
+            .decode()
+            .map_err(|e| PdsError::Validation(format!("Unreadable image: {}", e)))?;
+
+        let resized = resize(img, self.fit());
+
+        let mut buf = Vec::new();
+        JpegEncoder::new_with_quality(&mut buf, JPEG_QUALITY)
+            .encode_image(&media::flatten(&resized))
+            .map_err(|e| PdsError::Internal(format!("Failed to encode image: {}", e)))?;
+        Ok(buf)
+    }
+}
+
+/// Apply a fit without ever upscaling
+fn resize(img: DynamicImage, fit: Fit) -> DynamicImage {
+    let (width, height) = (img.width(), img.height());
+    match fit {
+        Fit::Contain(max_w, max_h) => {
+            if width <= max_w && height <= max_h {
+                img
+            } else {
+                img.resize(max_w, max_h, FilterType::Lanczos3)
+            }
+        }
+        Fit::Cover(box_w, box_h) => {
+            // Shrink the box until the source covers it, keeping its shape
+            let scale = (width as f64 / box_w as f64)
+                .min(height as f64 / box_h as f64)
+                .min(1.0);
+            let target_w = ((box_w as f64 * scale).round() as u32).max(1);
+            let target_h = ((box_h as f64 * scale).round() as u32).max(1);
+            img.resize_to_fill(target_w, target_h, FilterType::Lanczos3)
+        }
+    }
+}
+
+/// Cache for rendered variants
+pub struct ImageVariantCache {
+    directory: PathBuf,
+    redis: Option<CacheClient>,
+}
+
+impl ImageVariantCache {
+    pub fn new(directory: PathBuf, redis: Option<CacheClient>) -> Self {
+        Self { directory, redis }
+    }
+
+    fn path(&self, preset: ImagePreset, cid: &str) -> PathBuf {
+        self.directory.join(preset.name()).join(format!("{}.jpg", cid))
+    }
+
+    fn redis_key(preset: ImagePreset, cid: &str) -> String {
+        format!("{}:{}", preset.name(), cid)
+    }
+
+    /// Cached variant, if any
+    pub async fn get(&self, preset: ImagePreset, cid: &str) -> PdsResult<Option<Vec<u8>>> {
+        if let Some(redis) = &self.redis {
+            let cached: Option<String> = redis.get("img:", &Self::redis_key(preset, cid)).await?;
+            return Ok(cached.and_then(|encoded| BASE64.decode(encoded).ok()));
+        }
+
+        match fs::read(self.path(preset, cid)).await {
+            Ok(data) => Ok(Some(data)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(PdsError::BlobBackend(format!("Failed to read image cache: {}", e))),
+        }
+    }
+
+    /// Store a rendered variant
+    pub async fn put(&self, preset: ImagePreset, cid: &str, data: &[u8]) -> PdsResult<()> {
+        if let Some(redis) = &self.redis {
+            return redis
+                .set("img:", &Self::redis_key(preset, cid), &BASE64.encode(data), Some(REDIS_TTL_SECS))
+                .await;
+        }
+
+        let path = self.path(preset, cid);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .map_err(|e| PdsError::BlobBackend(format!("Failed to create image cache: {}", e)))?;
+        }
+
+        // Write then rename so readers never see a partial file
+        let tmp = path.with_extension("jpg.tmp");
+        fs::write(&tmp, data)
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to write image cache: {}", e)))?;
+        fs::rename(&tmp, &path)
+            .await
+            .map_err(|e| PdsError::BlobBackend(format!("Failed to write image cache: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, RgbImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let img = DynamicImage::ImageRgb8(RgbImage::new(width, height));
+        let mut buf = Vec::new();
+        img.write_to(&mut Cursor::new(&mut buf), ImageFormat::Png).unwrap();
+        buf
+    }
+
+    fn dimensions(jpeg: &[u8]) -> (u32, u32) {
+        let img = image::load_from_memory(jpeg).unwrap();
+        (img.width(), img.height())
+    }
+
+    #[test]
+    fn test_preset_names() {
+        assert_eq!(ImagePreset::from_name("avatar"), Some(ImagePreset::Avatar));
+        assert_eq!(ImagePreset::from_name("full"), Some(ImagePreset::FeedFullsize));
+        assert_eq!(ImagePreset::from_name("original"), None);
+        assert_eq!(ImagePreset::from_name(ImagePreset::Banner.name()), Some(ImagePreset::Banner));
+    }
+
+    #[test]
+    fn test_render_sizes() {
+        // Cover crops to the preset's shape
+        let avatar = ImagePreset::Avatar.render(&png(1600, 1200)).unwrap();
+        assert_eq!(dimensions(&avatar), (1000, 1000));
+
+        // Contain keeps the aspect ratio
+        let thumb = ImagePreset::FeedThumbnail.render(&png(2000, 1000)).unwrap();
+        assert_eq!(dimensions(&thumb), (1000, 500));
+
+        // Small sources are never upscaled
+        let small = ImagePreset::FeedFullsize.render(&png(300, 200)).unwrap();
+        assert_eq!(dimensions(&small), (300, 200));
+        let small_avatar = ImagePreset::Avatar.render(&png(300, 200)).unwrap();
+        assert_eq!(dimensions(&small_avatar), (200, 200));
+
+        assert!(ImagePreset::Avatar.render(b"not an image").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ImageVariantCache::new(dir.path().to_path_buf(), None);
+
+        assert!(cache.get(ImagePreset::Avatar, "bafyabc").await.unwrap().is_none());
+        cache.put(ImagePreset::Avatar, "bafyabc", b"jpeg").await.unwrap();
+        assert_eq!(cache.get(ImagePreset::Avatar, "bafyabc").await.unwrap().unwrap(), b"jpeg");
+        assert!(cache.get(ImagePreset::Banner, "bafyabc").await.unwrap().is_none());
+    }
+}
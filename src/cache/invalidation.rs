@@ -0,0 +1,125 @@
+/// Cache invalidation driven by sequenced events
+///
+/// Identity and account events are the server's record of handle changes,
+/// takedowns, deactivations and deletions. This subscriber listens to them
+/// as the sequencer writes them and drops whatever the caches hold for the
+/// affected DID: the DID document and handle in every identity tier, plus
+/// session and repository metadata entries in Redis. A handle change also
+/// warms the caches with the new handle, so the first lookup after the
+/// change doesn't go to the network.
+///
+/// Failures are logged and skipped; the cache TTLs still bound staleness.
+use crate::{
+    cache::{categories, CacheClient},
+    error::PdsResult,
+    identity::IdentityResolver,
+    sequencer::events::{AccountEvent, IdentityEvent},
+};
+use std::sync::Arc;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Invalidates caches as identity and account events are sequenced
+pub struct CacheInvalidator {
+    identity: Arc<IdentityResolver>,
+    redis: Option<CacheClient>,
+}
+
+impl CacheInvalidator {
+    pub fn new(identity: Arc<IdentityResolver>, redis: Option<CacheClient>) -> Self {
+        Self { identity, redis }
+    }
+
+    /// Consume events in the background until the sequencer goes away
+    pub fn spawn(
+        self,
+        mut identity_events: broadcast::Receiver<IdentityEvent>,
+        mut account_events: broadcast::Receiver<AccountEvent>,
+    ) {
+        tokio::spawn(async move {
+            loop {
+                let result = tokio::select! {
+                    evt = identity_events.recv() => match evt {
+                        Ok(evt) => self.on_identity(&evt).await.map_err(|e| (evt.did, e)),
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, "cache invalidation lagged behind identity events");
+                            Ok(())
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                    evt = account_events.recv() => match evt {
+                        Ok(evt) => self.on_account(&evt).await.map_err(|e| (evt.did, e)),
+                        Err(RecvError::Lagged(skipped)) => {
+                            tracing::warn!(skipped, "cache invalidation lagged behind account events");
+                            Ok(())
+                        }
+                        Err(RecvError::Closed) => break,
+                    },
+                };
+
+                if let Err((did, e)) = result {
+                    tracing::warn!(did = %did, error = %e, "cache_invalidation_failed");
+                }
+            }
+        });
+    }
+
+    /// Handle change or identity refresh: drop the old identity, warm the new handle
+    pub async fn on_identity(&self, evt: &IdentityEvent) -> PdsResult<()> {
+        self.identity.invalidate_identity(&evt.did, evt.handle.as_deref()).await?;
+        if let Some(handle) = &evt.handle {
+            self.identity.warm_handle(handle, &evt.did).await?;
+        }
+        Ok(())
+    }
+
+    /// Status change (takedown, deactivation, deletion, reactivation)
+    pub async fn on_account(&self, evt: &AccountEvent) -> PdsResult<()> {
+        self.identity.invalidate_identity(&evt.did, None).await?;
+
+        if let Some(redis) = &self.redis {
+            for category in [categories::SESSION, categories::REPO_META] {
+                redis.delete(category, &evt.did).await?;
+                redis.flush_pattern(&format!("{}{}:*", category, evt.did)).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identity::{DidCache, IdentityResolverConfig};
+    use crate::sequencer::events::AccountStatus;
+    use sqlx::SqlitePool;
+
+    #[tokio::test]
+    async fn test_targeted_invalidation() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let cache = DidCache::new(db);
+        let resolver = Arc::new(IdentityResolver::new(cache.clone(), IdentityResolverConfig::default()).unwrap());
+        let invalidator = CacheInvalidator::new(resolver.clone(), None);
+
+        cache.cache_handle("alice.test", "did:plc:alice").await.unwrap();
+        cache.cache_handle("bob.test", "did:plc:bob").await.unwrap();
+        resolver.remember_handle("alice.test", "did:plc:alice");
+
+        // A handle change drops the old handle and serves the new one from cache
+        invalidator
+            .on_identity(&IdentityEvent::new("did:plc:alice".to_string(), Some("Alice2.test".to_string())))
+            .await
+            .unwrap();
+        assert!(resolver.cached_handle("alice.test").await.is_none());
+        assert!(cache.get_handle("alice.test").await.unwrap().is_none());
+        assert_eq!(resolver.resolve_handle("alice2.test").await.unwrap(), "did:plc:alice");
+
+        // A takedown only touches the account it names
+        invalidator
+            .on_account(&AccountEvent::new("did:plc:bob".to_string(), false, Some(AccountStatus::Takendown)))
+            .await
+            .unwrap();
+        assert!(cache.get_handle("bob.test").await.unwrap().is_none());
+        assert!(cache.get_handle("alice2.test").await.unwrap().is_some());
+    }
+}
@@ -7,6 +7,10 @@
 /// - Repository metadata
 /// - Rate limit counters (for distributed rate limiting)
 
+pub mod invalidation;
+
+pub use invalidation::CacheInvalidator;
+
 use crate::error::{PdsError, PdsResult};
 use redis::aio::ConnectionManager;
 use redis::{AsyncCommands, Client};
@@ -105,12 +109,12 @@ impl CacheClient {
 
         let client = Client::open(config.redis_url.as_str()).map_err(|e| {
             error!("Failed to create Redis client: {}", e);
-            PdsError::Internal(format!("Redis client creation failed: {}", e))
+            PdsError::Cache(format!("Redis client creation failed: {}", e))
         })?;
 
         let connection = ConnectionManager::new(client).await.map_err(|e| {
             error!("Failed to connect to Redis: {}", e);
-            PdsError::Internal(format!("Redis connection failed: {}", e))
+            PdsError::Cache(format!("Redis connection failed: {}", e))
         })?;
 
         info!("✓ Redis connection established");
@@ -118,6 +122,11 @@ impl CacheClient {
         Ok(Self { connection, config })
     }
 
+    /// Cache configuration (TTLs per category)
+    pub fn config(&self) -> &CacheConfig {
+        &self.config
+    }
+
     /// Build a cache key with prefix
     fn build_key(&self, category: &str, key: &str) -> String {
         format!("{}{}{}", self.config.key_prefix, category, key)
@@ -132,7 +141,7 @@ impl CacheClient {
         let mut conn = self.connection.clone();
         let result: Option<String> = conn.get(&cache_key).await.map_err(|e| {
             warn!("Redis GET failed for {}: {}", cache_key, e);
-            PdsError::Internal(format!("Cache get failed: {}", e))
+            PdsError::Cache(format!("Cache get failed: {}", e))
         })?;
 
         match result {
@@ -170,7 +179,7 @@ impl CacheClient {
 
         let json = serde_json::to_string(value).map_err(|e| {
             error!("Failed to serialize value for cache: {}", e);
-            PdsError::Internal(format!("Cache serialization failed: {}", e))
+            PdsError::Cache(format!("Cache serialization failed: {}", e))
         })?;
 
         let mut conn = self.connection.clone();
@@ -178,7 +187,7 @@ impl CacheClient {
             .await
             .map_err(|e| {
                 warn!("Redis SET failed for {}: {}", cache_key, e);
-                PdsError::Internal(format!("Cache set failed: {}", e))
+                PdsError::Cache(format!("Cache set failed: {}", e))
             })?;
 
         debug!("Cache SET successful: {}", cache_key);
@@ -194,7 +203,7 @@ impl CacheClient {
         let mut conn = self.connection.clone();
         conn.del(&cache_key).await.map_err(|e| {
             warn!("Redis DELETE failed for {}: {}", cache_key, e);
-            PdsError::Internal(format!("Cache delete failed: {}", e))
+            PdsError::Cache(format!("Cache delete failed: {}", e))
         })?;
 
         Ok(())
@@ -207,7 +216,7 @@ impl CacheClient {
         let mut conn = self.connection.clone();
         conn.exists(&cache_key).await.map_err(|e| {
             warn!("Redis EXISTS failed for {}: {}", cache_key, e);
-            PdsError::Internal(format!("Cache exists check failed: {}", e))
+            PdsError::Cache(format!("Cache exists check failed: {}", e))
         })
     }
 
@@ -222,7 +231,7 @@ impl CacheClient {
         // Increment counter
         let count: i64 = conn.incr(&cache_key, 1).await.map_err(|e| {
             warn!("Redis INCR failed for {}: {}", cache_key, e);
-            PdsError::Internal(format!("Cache increment failed: {}", e))
+            PdsError::Cache(format!("Cache increment failed: {}", e))
         })?;
 
         // Set TTL if this is a new key (count == 1)
@@ -231,7 +240,7 @@ impl CacheClient {
                 .await
                 .map_err(|e| {
                     warn!("Redis EXPIRE failed for {}: {}", cache_key, e);
-                    PdsError::Internal(format!("Cache expire failed: {}", e))
+                    PdsError::Cache(format!("Cache expire failed: {}", e))
                 })?;
         }
 
@@ -245,7 +254,7 @@ impl CacheClient {
         let mut conn = self.connection.clone();
         conn.ttl(&cache_key).await.map_err(|e| {
             warn!("Redis TTL failed for {}: {}", cache_key, e);
-            PdsError::Internal(format!("Cache TTL check failed: {}", e))
+            PdsError::Cache(format!("Cache TTL check failed: {}", e))
         })
     }
 
@@ -260,7 +269,7 @@ impl CacheClient {
         // Get all keys matching pattern
         let keys: Vec<String> = conn.keys(&cache_pattern).await.map_err(|e| {
             error!("Redis KEYS failed: {}", e);
-            PdsError::Internal(format!("Cache keys lookup failed: {}", e))
+            PdsError::Cache(format!("Cache keys lookup failed: {}", e))
         })?;
 
         if keys.is_empty() {
@@ -270,7 +279,7 @@ impl CacheClient {
         // Delete all keys
         let deleted: u64 = conn.del(&keys).await.map_err(|e| {
             error!("Redis DELETE multiple keys failed: {}", e);
-            PdsError::Internal(format!("Cache flush failed: {}", e))
+            PdsError::Cache(format!("Cache flush failed: {}", e))
         })?;
 
         info!("Cache flushed {} keys matching {}", deleted, cache_pattern);
@@ -282,11 +291,11 @@ impl CacheClient {
         let mut conn = self.connection.clone();
         let pong: String = redis::cmd("PING").query_async(&mut conn).await.map_err(|e| {
             error!("Redis PING failed: {}", e);
-            PdsError::Internal(format!("Cache ping failed: {}", e))
+            PdsError::Cache(format!("Cache ping failed: {}", e))
         })?;
 
         if pong != "PONG" {
-            return Err(PdsError::Internal(
+            return Err(PdsError::Cache(
                 "Unexpected Redis PING response".to_string(),
             ));
         }
@@ -304,7 +313,7 @@ impl CacheClient {
             .await
             .map_err(|e| {
                 error!("Redis INFO failed: {}", e);
-                PdsError::Internal(format!("Cache stats failed: {}", e))
+                PdsError::Cache(format!("Cache stats failed: {}", e))
             })?;
 
         // Parse basic stats
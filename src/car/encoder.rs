@@ -1,12 +1,12 @@
 use crate::error::{PdsError, PdsResult};
+use atproto::car::CarHeader;
 use libipld::Cid;
-use serde_cbor;
 
 /// CARv1 file encoder for ATProto repositories
 ///
 /// CAR (Content Addressable aRchive) format specification:
-/// - Header: CBOR-encoded { version: 1, roots: [CID] }
-/// - Blocks: Repeated { varint(cid_len), cid_bytes, varint(block_len), block_bytes }
+/// - Header: varint(len), DAG-CBOR { version: 1, roots: [CID link] }
+/// - Blocks: Repeated { varint(cid_len + block_len), cid_bytes, block_bytes }
 pub struct CarEncoder {
     buffer: Vec<u8>,
 }
@@ -14,35 +14,12 @@ pub struct CarEncoder {
 impl CarEncoder {
     /// Create a new CAR encoder with the given root CID
     pub fn new(root: &Cid) -> PdsResult<Self> {
-        let mut buffer = Vec::new();
-
-        // Encode CAR header
-        let header = serde_json::json!({
-            "version": 1,
-            "roots": [root.to_string()]
-        });
-
-        let header_bytes = serde_cbor::to_vec(&header)
-            .map_err(|e| PdsError::Internal(format!("Failed to encode CAR header: {}", e)))?;
-
-        // Write header length as varint
-        write_varint(&mut buffer, header_bytes.len() as u64);
-        buffer.extend_from_slice(&header_bytes);
-
-        Ok(Self { buffer })
+        Ok(Self { buffer: encode_header(root)? })
     }
 
     /// Add a block to the CAR file
     pub fn add_block(&mut self, cid: &Cid, data: &[u8]) -> PdsResult<()> {
-        // Write CID
-        let cid_bytes = cid.to_bytes();
-        write_varint(&mut self.buffer, cid_bytes.len() as u64);
-        self.buffer.extend_from_slice(&cid_bytes);
-
-        // Write block data
-        write_varint(&mut self.buffer, data.len() as u64);
-        self.buffer.extend_from_slice(data);
-
+        encode_block(&mut self.buffer, cid, data);
         Ok(())
     }
 
@@ -60,6 +37,29 @@ impl CarEncoder {
     }
 }
 
+/// Encode the CAR header for a single root, length-prefixed
+///
+/// Together with [`encode_block`] this lets a CAR be written in pieces, e.g.
+/// streamed to a response without holding the whole file.
+pub fn encode_header(root: &Cid) -> PdsResult<Vec<u8>> {
+    let header_bytes = CarHeader::new(vec![*root])
+        .to_cbor()
+        .map_err(|e| PdsError::Internal(format!("Failed to encode CAR header: {}", e)))?;
+
+    let mut buffer = Vec::with_capacity(header_bytes.len() + 2);
+    write_varint(&mut buffer, header_bytes.len() as u64);
+    buffer.extend_from_slice(&header_bytes);
+    Ok(buffer)
+}
+
+/// Append one block section: varint length, CID bytes, block bytes
+pub fn encode_block(buffer: &mut Vec<u8>, cid: &Cid, data: &[u8]) {
+    let cid_bytes = cid.to_bytes();
+    write_varint(buffer, (cid_bytes.len() + data.len()) as u64);
+    buffer.extend_from_slice(&cid_bytes);
+    buffer.extend_from_slice(data);
+}
+
 /// Write an unsigned varint to a buffer
 fn write_varint(buffer: &mut Vec<u8>, mut value: u64) {
     while value >= 0x80 {
@@ -92,6 +92,19 @@ mod tests {
         assert_eq!(buf, vec![0xAC, 0x02]);
     }
 
+    #[test]
+    fn test_encoded_car_reads_back() {
+        let root = crate::actor_store::mst::block_cid(b"root");
+        let leaf = crate::actor_store::mst::block_cid(b"leaf");
+        let mut encoder = CarEncoder::new(&root).unwrap();
+        encoder.add_block(&root, b"root").unwrap();
+        encoder.add_block(&leaf, b"leaf").unwrap();
+
+        let car = crate::car::read_verified_car(&encoder.finalize()).unwrap();
+        assert_eq!(car.roots, vec![root]);
+        assert_eq!(car.blocks[&leaf], b"leaf".to_vec());
+    }
+
     #[test]
     fn test_car_encoder_creation() {
         let cid = Cid::try_from("bafyreie5cvv4h45feadgeuwhbcutmh6t2ceseocckahdoe6uat64zmz454").unwrap();
@@ -1,3 +1,5 @@
 pub mod encoder;
+pub mod reader;
 
 pub use encoder::CarEncoder;
+pub use reader::{read_car, read_verified_car, verify_block, CarContents};
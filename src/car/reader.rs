@@ -0,0 +1,73 @@
+use crate::error::{PdsError, PdsResult};
+use atproto::car::CarReader;
+use libipld::Cid;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Multihash code for SHA-256
+const SHA2_256_CODE: u64 = 0x12;
+
+/// Blocks of a CAR file, keyed by CID
+#[derive(Debug, Default)]
+pub struct CarContents {
+    pub roots: Vec<Cid>,
+    pub blocks: HashMap<Cid, Vec<u8>>,
+}
+
+/// Read a CAR file without checking block contents
+///
+/// Callers that need to report on bad blocks rather than reject the whole
+/// file check them with [`verify_block`].
+pub fn read_car(bytes: &[u8]) -> PdsResult<CarContents> {
+    let reader =
+        CarReader::new(bytes).map_err(|e| PdsError::Validation(format!("Invalid CAR: {}", e)))?;
+    let roots = reader.roots().to_vec();
+
+    let mut blocks = HashMap::new();
+    for block in reader.blocks() {
+        let (cid, data) =
+            block.map_err(|e| PdsError::Validation(format!("Invalid CAR block: {}", e)))?;
+        blocks.insert(cid, data);
+    }
+
+    Ok(CarContents { roots, blocks })
+}
+
+/// Read a CAR file, checking every block against its CID
+pub fn read_verified_car(bytes: &[u8]) -> PdsResult<CarContents> {
+    let contents = read_car(bytes)?;
+    for (cid, data) in &contents.blocks {
+        verify_block(cid, data)?;
+    }
+    Ok(contents)
+}
+
+/// Check that block data hashes to its CID
+pub fn verify_block(cid: &Cid, data: &[u8]) -> PdsResult<()> {
+    let hash = cid.hash();
+    if hash.code() != SHA2_256_CODE {
+        return Err(PdsError::Validation(format!(
+            "Unsupported hash function in CID {}",
+            cid
+        )));
+    }
+
+    if hash.digest() != Sha256::digest(data).as_slice() {
+        return Err(PdsError::Validation(format!("Block does not match CID {}", cid)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::mst::block_cid;
+
+    #[test]
+    fn test_verify_block() {
+        let cid = block_cid(b"hello");
+        assert!(verify_block(&cid, b"hello").is_ok());
+        assert!(verify_block(&cid, b"tampered").is_err());
+    }
+}
@@ -0,0 +1,179 @@
+/// Time and identifier sources
+///
+/// Code that stamps, expires or orders data asks a [`Clock`] for the time and
+/// an [`IdGenerator`] for UUIDs and TIDs instead of calling `Utc::now`,
+/// `Uuid::new_v4` or `Tid::next` directly. The server uses [`SystemClock`]
+/// and [`RandomIds`]; tests swap in [`MockClock`] and [`SequentialIds`] to
+/// control expiry and get reproducible ordering.
+use crate::error::{PdsError, PdsResult};
+use atproto::tid::Tid;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// Source of unique identifiers
+pub trait IdGenerator: Send + Sync {
+    /// A new UUID (session ids, tokens, upload ids)
+    fn uuid(&self) -> Uuid;
+
+    /// A new TID, greater than every TID this generator returned before
+    fn tid(&self) -> PdsResult<Tid>;
+}
+
+/// The system clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// Random UUIDs and TIDs from the process-wide TID clock
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RandomIds;
+
+impl IdGenerator for RandomIds {
+    fn uuid(&self) -> Uuid {
+        Uuid::new_v4()
+    }
+
+    fn tid(&self) -> PdsResult<Tid> {
+        Tid::next().map_err(|e| PdsError::Internal(format!("Failed to generate TID: {}", e)))
+    }
+}
+
+/// The clock used when none is injected
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// The id generator used when none is injected
+pub fn random_ids() -> Arc<dyn IdGenerator> {
+    Arc::new(RandomIds)
+}
+
+/// A clock that only moves when told to
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<DateTime<Utc>>,
+}
+
+impl MockClock {
+    pub fn new(start: DateTime<Utc>) -> Self {
+        Self { now: Mutex::new(start) }
+    }
+
+    /// A clock stopped at 2025-01-01T00:00:00Z
+    pub fn fixed() -> Self {
+        Self::new(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+    }
+
+    pub fn set(&self, now: DateTime<Utc>) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        *self.now.lock().unwrap() += by;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> DateTime<Utc> {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// Identifiers from a counter: UUIDs 1, 2, 3... and TIDs one microsecond apart
+#[derive(Debug)]
+pub struct SequentialIds {
+    next: AtomicU64,
+    /// Microseconds since the epoch of the first TID
+    tid_base: u64,
+}
+
+impl SequentialIds {
+    pub fn new() -> Self {
+        Self::starting_at(Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap())
+    }
+
+    /// TIDs start at `time`
+    pub fn starting_at(time: DateTime<Utc>) -> Self {
+        Self {
+            next: AtomicU64::new(1),
+            tid_base: time.timestamp_micros().max(0) as u64,
+        }
+    }
+
+    fn next(&self) -> u64 {
+        self.next.fetch_add(1, Ordering::SeqCst)
+    }
+}
+
+impl Default for SequentialIds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl IdGenerator for SequentialIds {
+    fn uuid(&self) -> Uuid {
+        Uuid::from_u128(self.next() as u128)
+    }
+
+    fn tid(&self) -> PdsResult<Tid> {
+        Tid::from_timestamp(self.tid_base + self.next(), 0)
+            .map_err(|e| PdsError::Internal(format!("Failed to generate TID: {}", e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = MockClock::fixed();
+        let start = clock.now();
+        assert_eq!(clock.now(), start);
+
+        clock.advance(Duration::minutes(5));
+        assert_eq!(clock.now(), start + Duration::minutes(5));
+
+        clock.set(start);
+        assert_eq!(clock.now(), start);
+    }
+
+    #[test]
+    fn test_sequential_ids() {
+        let ids = SequentialIds::new();
+        assert_eq!(ids.uuid(), Uuid::from_u128(1));
+        assert_eq!(ids.uuid(), Uuid::from_u128(2));
+
+        let a = ids.tid().unwrap();
+        let b = ids.tid().unwrap();
+        assert!(a.to_string() < b.to_string());
+        assert_eq!(b.timestamp() - a.timestamp(), 1);
+
+        // Every generator produces the same sequence
+        let again = SequentialIds::new();
+        again.uuid();
+        again.uuid();
+        assert_eq!(again.tid().unwrap().to_string(), a.to_string());
+    }
+
+    #[test]
+    fn test_random_ids() {
+        let ids = RandomIds;
+        assert_ne!(ids.uuid(), ids.uuid());
+        let a = ids.tid().unwrap();
+        assert!(ids.tid().unwrap().to_string() > a.to_string());
+    }
+}
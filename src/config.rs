@@ -16,6 +16,33 @@ pub struct ServerConfig {
     pub rate_limit: RateLimitConfig,
     pub logging: LoggingConfig,
     pub federation: FederationConfig,
+    pub mirror: MirrorConfig,
+    pub content_policy: ContentPolicyConfig,
+    pub registration_challenge: RegistrationChallengeConfig,
+    pub signup_throttle: SignupThrottleConfig,
+    pub report_service: Option<ReportServiceConfig>,
+    pub bsky_app_view: Option<AppViewConfig>,
+    pub proxy: ProxyConfig,
+    pub trusted_proxies: TrustedProxyConfig,
+    pub keys: KeyConfig,
+    pub encryption: EncryptionConfig,
+    pub cors: CorsConfig,
+    pub security_headers: SecurityHeadersConfig,
+    pub firehose: FirehoseConfig,
+    pub http_cache: HttpCacheConfig,
+    pub jobs: JobsConfig,
+    pub compaction: CompactionConfig,
+    pub record_history: RecordHistoryConfig,
+    pub write_hooks: WriteHooksConfig,
+    pub nodeinfo: NodeInfoConfig,
+    pub describe_server: DescribeServerConfig,
+    pub replica: ReplicaConfig,
+    pub repo_webhooks: RepoWebhookConfig,
+    pub activitypub: ActivityPubConfig,
+    pub feeds: FeedsConfig,
+    pub body_limits: BodyLimitConfig,
+    /// Per-domain settings for the service handle domains
+    pub virtual_hosts: Vec<VirtualHostConfig>,
 }
 
 /// Service-level configuration
@@ -26,6 +53,69 @@ pub struct ServiceConfig {
     pub service_did: String,
     pub version: String,
     pub blob_upload_limit: usize,
+    /// Largest audio blob accepted, in bytes
+    pub blob_audio_upload_limit: usize,
+    /// Blob bytes each account may store (None: unlimited)
+    pub blob_account_quota: Option<usize>,
+    /// Animated WebP thumbnails for animated images (`animated-thumbnails` builds)
+    pub blob_animated_thumbnails: bool,
+    /// Hours staged blobs and incomplete resumable uploads are kept
+    pub blob_temp_ttl_hours: i64,
+    /// Blobs each account may have staged but uncommitted (None: unlimited)
+    pub blob_max_staged_per_account: Option<usize>,
+    /// Listeners to bind (defaults to a single all-routes listener on `port`)
+    pub listeners: Vec<ListenerConfig>,
+    /// Development mode (enables dev-only tooling such as `seed`)
+    pub dev_mode: bool,
+    /// Pretty-print JSON response bodies
+    pub pretty_json: bool,
+}
+
+/// A single network listener and the routes it serves
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListenerConfig {
+    /// Socket address to bind (e.g., "0.0.0.0:2583" or "[::]:2583")
+    pub address: String,
+    /// Route set served on this listener
+    pub role: ListenerRole,
+}
+
+/// Route set exposed by a listener
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListenerRole {
+    /// Every route, including metrics
+    All,
+    /// API, well-known and admin routes, without the metrics endpoint
+    Api,
+    /// Only the Prometheus metrics endpoint
+    Metrics,
+    /// Only the admin API, admin OAuth and admin panel
+    ///
+    /// When any admin listener is configured, `all` and `api` listeners
+    /// stop serving admin routes.
+    Admin,
+}
+
+impl ListenerRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListenerRole::All => "all",
+            ListenerRole::Api => "api",
+            ListenerRole::Metrics => "metrics",
+            ListenerRole::Admin => "admin",
+        }
+    }
+
+    pub fn from_str(s: &str) -> PdsResult<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "all" => Ok(ListenerRole::All),
+            "api" => Ok(ListenerRole::Api),
+            "metrics" => Ok(ListenerRole::Metrics),
+            "admin" => Ok(ListenerRole::Admin),
+            other => Err(PdsError::Validation(format!("Invalid listener role: {}", other))),
+        }
+    }
 }
 
 /// Storage configuration
@@ -39,60 +129,1367 @@ pub struct StorageConfig {
     pub blobstore: BlobstoreConfig,
 }
 
-/// Blob storage configuration
+/// Blob storage configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum BlobstoreConfig {
+    Disk {
+        location: PathBuf,
+        tmp_location: PathBuf,
+    },
+    S3 {
+        bucket: String,
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+        endpoint: Option<String>,
+    },
+}
+
+/// Authentication configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    pub repo_signing_key: String,
+    pub plc_rotation_key: String,
+    /// DID(s) allowed to access admin panel (comma-separated)
+    pub admin_dids: Vec<String>,
+    /// OAuth configuration for admin login
+    pub oauth: OAuthConfig,
+    /// Shared token required in the `X-Admin-Network-Token` header on admin routes
+    pub admin_network_token: Option<String>,
+    /// Argon2id parameters for password hashing
+    pub password_hash: PasswordHashConfig,
+    /// Signing keys for access and admin tokens
+    pub jwt_keys: JwtKeysConfig,
+}
+
+/// Argon2id password hashing parameters
+///
+/// Stored hashes using weaker parameters are transparently upgraded on the
+/// next successful login.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PasswordHashConfig {
+    /// Memory cost in KiB
+    pub memory_kib: u32,
+    /// Number of iterations (time cost)
+    pub iterations: u32,
+    /// Degree of parallelism (lanes)
+    pub parallelism: u32,
+}
+
+impl Default for PasswordHashConfig {
+    fn default() -> Self {
+        // argon2 crate defaults (OWASP minimum for Argon2id)
+        Self {
+            memory_kib: 19_456,
+            iterations: 2,
+            parallelism: 1,
+        }
+    }
+}
+
+/// Algorithm of JWT signing keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum JwtAlgorithm {
+    /// HMAC-SHA256 with a random server-side secret
+    Hs256,
+    /// ECDSA P-256, verifiable by other services with the public key
+    Es256,
+}
+
+impl JwtAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Hs256 => "HS256",
+            Self::Es256 => "ES256",
+        }
+    }
+}
+
+/// JWT signing key rotation
+///
+/// Tokens carry the `kid` of the key that signed them. After a rotation the
+/// retired key keeps validating tokens for `retired_key_hours`, so tokens
+/// issued just before the rotation don't stop working.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JwtKeysConfig {
+    /// Algorithm of newly created signing keys
+    pub algorithm: JwtAlgorithm,
+    /// Rotate the signing key once it is this many days old (never, if unset)
+    pub rotation_days: Option<u32>,
+    /// How long a retired key still validates tokens
+    pub retired_key_hours: u32,
+}
+
+impl Default for JwtKeysConfig {
+    fn default() -> Self {
+        Self {
+            algorithm: JwtAlgorithm::Hs256,
+            rotation_days: None,
+            // Admin refresh tokens last 30 days
+            retired_key_hours: 720,
+        }
+    }
+}
+
+impl JwtKeysConfig {
+    /// Load from `PDS_JWT_ALGORITHM`, `PDS_JWT_ROTATION_DAYS` and `PDS_JWT_RETIRED_KEY_HOURS`
+    fn from_env() -> PdsResult<Self> {
+        let defaults = Self::default();
+
+        let algorithm = match env::var("PDS_JWT_ALGORITHM").map(|s| s.to_uppercase()).as_deref() {
+            Err(_) | Ok("HS256") => JwtAlgorithm::Hs256,
+            Ok("ES256") => JwtAlgorithm::Es256,
+            Ok(other) => {
+                return Err(PdsError::Validation(format!(
+                    "Unknown PDS_JWT_ALGORITHM: {} (expected HS256 or ES256)",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            algorithm,
+            rotation_days: env::var("PDS_JWT_ROTATION_DAYS")
+                .ok()
+                .and_then(|s| s.parse().ok()),
+            retired_key_hours: env::var("PDS_JWT_RETIRED_KEY_HOURS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.retired_key_hours),
+        })
+    }
+}
+
+/// OAuth configuration for admin authentication
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OAuthConfig {
+    /// OAuth client ID (URL to client metadata)
+    pub client_id: String,
+    /// OAuth redirect URI
+    pub redirect_uri: String,
+    /// PDS URL for OAuth (e.g., https://bsky.social)
+    pub pds_url: String,
+}
+
+/// Identity configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IdentityConfig {
+    pub did_plc_url: String,
+    pub service_handle_domains: Vec<String>,
+    pub did_cache_stale_ttl: u64,
+    pub did_cache_max_ttl: u64,
+    /// DNS-over-HTTPS endpoint used to verify custom domain handles
+    pub handle_doh_url: String,
+    /// How long a replaced handle keeps resolving to its old DID and stays
+    /// unavailable to other accounts (0 = released immediately)
+    pub handle_redirect_secs: u64,
+    /// Limits on how often accounts may change their handle
+    pub handle_changes: HandleChangeConfig,
+    /// Extra PLC directories to resolve from, and what to do when all are down
+    pub plc_failover: PlcFailoverConfig,
+}
+
+/// Handle change limits
+///
+/// A stolen session can hand an account's handle to someone else in one
+/// request. These settings slow that down: a minimum gap between changes, a
+/// cap per 30 days, and optionally a code emailed to the account that has to
+/// be submitted before the change is applied. Admin changes are not limited.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HandleChangeConfig {
+    /// Minimum time between two handle changes (0 = none)
+    pub cooldown_secs: u64,
+    /// Handle changes allowed per rolling 30 days (0 = unlimited)
+    pub max_per_month: u32,
+    /// Require confirming a change with a code sent to the account's email
+    pub require_email_confirmation: bool,
+}
+
+impl HandleChangeConfig {
+    /// Load from `PDS_HANDLE_CHANGE_COOLDOWN_SECS`, `PDS_HANDLE_CHANGES_PER_MONTH`
+    /// and `PDS_HANDLE_CHANGE_REQUIRE_EMAIL`
+    fn from_env() -> Self {
+        Self {
+            cooldown_secs: env::var("PDS_HANDLE_CHANGE_COOLDOWN_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+            max_per_month: env::var("PDS_HANDLE_CHANGES_PER_MONTH")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(0),
+            require_email_confirmation: env::var("PDS_HANDLE_CHANGE_REQUIRE_EMAIL")
+                .map(|s| s == "true" || s == "1")
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// PLC directory failover
+///
+/// DID resolution tries `did_plc_url` and then each mirror, skipping ones
+/// that recently failed. PLC operations are still only submitted to
+/// `did_plc_url`. In offline mode, a DID document past the max cache TTL is
+/// kept for `offline_max_age` and served, with a warning, when no directory
+/// answers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlcFailoverConfig {
+    /// Read-only PLC mirrors, tried in order after `did_plc_url`
+    pub mirrors: Vec<String>,
+    /// Serve expired cached DID documents when every directory is down
+    pub offline_mode: bool,
+    /// Oldest cached DID document served in offline mode (seconds)
+    pub offline_max_age: u64,
+}
+
+impl Default for PlcFailoverConfig {
+    fn default() -> Self {
+        Self {
+            mirrors: Vec::new(),
+            offline_mode: false,
+            offline_max_age: 604800,
+        }
+    }
+}
+
+impl PlcFailoverConfig {
+    /// Load from `PDS_DID_PLC_MIRRORS` (comma-separated), `PDS_DID_OFFLINE_MODE`
+    /// and `PDS_DID_OFFLINE_MAX_AGE`
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        Self {
+            mirrors: env::var("PDS_DID_PLC_MIRRORS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|url| url.trim().trim_end_matches('/').to_string())
+                        .filter(|url| !url.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+            offline_mode: env::var("PDS_DID_OFFLINE_MODE")
+                .map(|s| s == "true" || s == "1")
+                .unwrap_or(false),
+            offline_max_age: env::var("PDS_DID_OFFLINE_MAX_AGE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.offline_max_age),
+        }
+    }
+}
+
+/// Settings for one service handle domain
+///
+/// Unset fields fall back to the server-wide settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VirtualHostConfig {
+    /// Handle domain without the leading dot, e.g. "example.com"
+    pub domain: String,
+    /// Overrides `PDS_INVITE_REQUIRED` for handles under this domain
+    pub invite_required: Option<bool>,
+    /// Name used in emails, e.g. "Example Social"
+    pub brand_name: Option<String>,
+    /// Sender for emails to accounts under this domain
+    pub email_from: Option<String>,
+    /// Base URL for links in emails, e.g. "https://example.com"
+    pub public_url: Option<String>,
+    /// Directory with `verification.txt` / `password_reset.txt` templates
+    pub email_template_dir: Option<PathBuf>,
+}
+
+impl VirtualHostConfig {
+    /// Load `PDS_VHOST_<DOMAIN>_*` for a handle domain
+    ///
+    /// `<DOMAIN>` is the domain uppercased with every other character
+    /// replaced by `_`, so `example.com` reads `PDS_VHOST_EXAMPLE_COM_*`.
+    fn from_env(domain: &str) -> Self {
+        let domain = domain.trim().trim_start_matches('.').to_lowercase();
+        let prefix = format!("PDS_VHOST_{}", env_key(&domain));
+        let var = |name: &str| {
+            env::var(format!("{}_{}", prefix, name))
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+        };
+
+        Self {
+            invite_required: var("INVITE_REQUIRED").map(|v| v == "true" || v == "1"),
+            brand_name: var("BRAND_NAME"),
+            email_from: var("EMAIL_FROM"),
+            public_url: var("PUBLIC_URL").map(|u| u.trim_end_matches('/').to_string()),
+            email_template_dir: var("EMAIL_TEMPLATE_DIR").map(PathBuf::from),
+            domain,
+        }
+    }
+
+    /// Whether a handle or host name is this domain or under it
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('.');
+        name.eq_ignore_ascii_case(&self.domain)
+            || name
+                .len()
+                .checked_sub(self.domain.len() + 1)
+                .map_or(false, |split| {
+                    name.as_bytes()[split] == b'.' && name[split + 1..].eq_ignore_ascii_case(&self.domain)
+                })
+    }
+}
+
+/// Environment variable key for a domain (`example.com` -> `EXAMPLE_COM`)
+pub(crate) fn env_key(domain: &str) -> String {
+    domain
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// Email configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub provider: EmailProviderConfig,
+    pub from_address: String,
+    /// Send attempts before a queued email is marked failed
+    pub max_attempts: u32,
+}
+
+/// Service outgoing email is handed to (see `mailer::provider`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum EmailProviderConfig {
+    /// SMTP relay, `smtp[s]://[user:pass@]host[:port][?tls=...]`
+    Smtp { smtp_url: String },
+    /// AWS SES v2 API
+    Ses {
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+        endpoint: Option<String>,
+    },
+    /// Mailgun HTTP API
+    Mailgun {
+        domain: String,
+        api_key: String,
+        /// `https://api.eu.mailgun.net` for EU domains
+        base_url: String,
+    },
+    /// SendGrid HTTP API
+    Sendgrid { api_key: String, base_url: String },
+}
+
+impl EmailConfig {
+    /// Load from `PDS_EMAIL_PROVIDER` and the provider's variables
+    ///
+    /// Email is disabled when neither `PDS_EMAIL_PROVIDER` nor
+    /// `PDS_EMAIL_SMTP_URL` is set.
+    fn from_env(hostname: &str) -> PdsResult<Option<Self>> {
+        let required = |name: &str| {
+            env::var(name).map_err(|_| PdsError::Validation(format!("{} is required", name)))
+        };
+
+        let provider = match env::var("PDS_EMAIL_PROVIDER").as_deref() {
+            Err(_) if env::var("PDS_EMAIL_SMTP_URL").is_err() => return Ok(None),
+            Err(_) | Ok("smtp") => EmailProviderConfig::Smtp {
+                smtp_url: required("PDS_EMAIL_SMTP_URL")?,
+            },
+            Ok("ses") => EmailProviderConfig::Ses {
+                region: env::var("PDS_EMAIL_SES_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                access_key_id: required("PDS_EMAIL_SES_ACCESS_KEY_ID")?,
+                secret_access_key: required("PDS_EMAIL_SES_SECRET_ACCESS_KEY")?,
+                session_token: env::var("PDS_EMAIL_SES_SESSION_TOKEN").ok(),
+                endpoint: env::var("PDS_EMAIL_SES_ENDPOINT").ok(),
+            },
+            Ok("mailgun") => EmailProviderConfig::Mailgun {
+                domain: required("PDS_EMAIL_MAILGUN_DOMAIN")?,
+                api_key: required("PDS_EMAIL_MAILGUN_API_KEY")?,
+                base_url: env::var("PDS_EMAIL_MAILGUN_BASE_URL")
+                    .unwrap_or_else(|_| "https://api.mailgun.net".to_string()),
+            },
+            Ok("sendgrid") => EmailProviderConfig::Sendgrid {
+                api_key: required("PDS_EMAIL_SENDGRID_API_KEY")?,
+                base_url: env::var("PDS_EMAIL_SENDGRID_BASE_URL")
+                    .unwrap_or_else(|_| "https://api.sendgrid.com".to_string()),
+            },
+            Ok(other) => {
+                return Err(PdsError::Validation(format!(
+                    "Unknown PDS_EMAIL_PROVIDER: {} (expected smtp, ses, mailgun or sendgrid)",
+                    other
+                )))
+            }
+        };
+
+        Ok(Some(Self {
+            provider,
+            from_address: env::var("PDS_EMAIL_FROM_ADDRESS")
+                .unwrap_or_else(|_| format!("noreply@{}", hostname)),
+            max_attempts: env::var("PDS_EMAIL_MAX_ATTEMPTS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(6),
+        }))
+    }
+}
+
+/// External moderation service that receives user reports
+///
+/// When set, com.atproto.moderation.createReport is forwarded to this
+/// service with service auth instead of being stored locally.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportServiceConfig {
+    /// Moderation service DID (the service auth audience)
+    pub did: String,
+    /// Service URL; resolved from the DID's `#atproto_labeler` entry when unset
+    pub url: Option<String>,
+}
+
+/// AppView that unknown app.bsky.* queries are proxied to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppViewConfig {
+    /// AppView URL (e.g., https://api.bsky.app)
+    pub url: String,
+    /// AppView service DID (the service auth audience)
+    pub did: String,
+}
+
+/// Controls for `atproto-proxy` forwarding to arbitrary services
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyConfig {
+    /// If non-empty, only these service DIDs may be proxied to
+    pub allowed_dids: Vec<String>,
+    /// Service DIDs that are never proxied to
+    pub denied_dids: Vec<String>,
+    /// Total time allowed for the upstream response headers (seconds)
+    pub timeout_secs: u64,
+    /// Time allowed to establish the upstream connection (seconds)
+    pub connect_timeout_secs: u64,
+    /// Allow service endpoints on loopback, private and link-local
+    /// addresses, and plain http (for development)
+    pub allow_private_targets: bool,
+}
+
+impl Default for ProxyConfig {
+    fn default() -> Self {
+        Self {
+            allowed_dids: Vec::new(),
+            denied_dids: Vec::new(),
+            timeout_secs: 30,
+            connect_timeout_secs: 5,
+            allow_private_targets: false,
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Load from `PDS_PROXY_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_PROXY_{}", name)).ok();
+        let dids = |s: String| {
+            s.split(',')
+                .map(|d| d.trim().to_string())
+                .filter(|d| !d.is_empty())
+                .collect()
+        };
+
+        Self {
+            allowed_dids: var("ALLOWED_DIDS").map(dids).unwrap_or(defaults.allowed_dids),
+            denied_dids: var("DENIED_DIDS").map(dids).unwrap_or(defaults.denied_dids),
+            timeout_secs: var("TIMEOUT_SECS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.timeout_secs),
+            connect_timeout_secs: var("CONNECT_TIMEOUT_SECS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.connect_timeout_secs),
+            allow_private_targets: var("ALLOW_PRIVATE_TARGETS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.allow_private_targets),
+        }
+    }
+
+    /// Whether requests may be proxied to `did`
+    pub fn permits(&self, did: &str) -> bool {
+        !self.denied_dids.iter().any(|d| d == did)
+            && (self.allowed_dids.is_empty() || self.allowed_dids.iter().any(|d| d == did))
+    }
+}
+
+/// Where signing keys are held (see `crypto::keys`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyConfig {
+    pub backend: KeyBackend,
+    /// Id of the repository signing key in the backend
+    pub repo_key_id: String,
+    /// Id of the server PLC rotation key in the backend
+    pub plc_rotation_key_id: String,
+}
+
+/// Key storage backend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum KeyBackend {
+    /// Plaintext hex keys from the environment
+    Config,
+    /// Passphrase-encrypted keystore file
+    Local { path: PathBuf, passphrase: String },
+    /// AWS KMS asymmetric secp256k1 keys
+    AwsKms {
+        region: String,
+        access_key_id: String,
+        secret_access_key: String,
+        session_token: Option<String>,
+        endpoint: Option<String>,
+    },
+    /// HashiCorp Vault KV v2 secrets
+    Vault {
+        addr: String,
+        token: String,
+        mount: String,
+        path: String,
+    },
+}
+
+impl Default for KeyConfig {
+    fn default() -> Self {
+        Self {
+            backend: KeyBackend::Config,
+            repo_key_id: "repo".to_string(),
+            plc_rotation_key_id: "plc-rotation".to_string(),
+        }
+    }
+}
+
+impl KeyConfig {
+    /// Load from `PDS_KEY_BACKEND` and the backend's variables
+    fn from_env(data_directory: &std::path::Path) -> PdsResult<Self> {
+        let defaults = Self::default();
+        let required = |name: &str| {
+            env::var(name).map_err(|_| PdsError::Validation(format!("{} is required", name)))
+        };
+
+        let backend = match env::var("PDS_KEY_BACKEND").as_deref() {
+            Err(_) | Ok("config") => KeyBackend::Config,
+            Ok("local") => KeyBackend::Local {
+                path: env::var("PDS_KEYSTORE_PATH")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|_| data_directory.join("keystore.json")),
+                passphrase: required("PDS_KEYSTORE_PASSPHRASE")?,
+            },
+            Ok("aws-kms") => KeyBackend::AwsKms {
+                region: env::var("PDS_KMS_REGION").unwrap_or_else(|_| "us-east-1".to_string()),
+                access_key_id: required("PDS_KMS_ACCESS_KEY_ID")?,
+                secret_access_key: required("PDS_KMS_SECRET_ACCESS_KEY")?,
+                session_token: env::var("PDS_KMS_SESSION_TOKEN").ok(),
+                endpoint: env::var("PDS_KMS_ENDPOINT").ok(),
+            },
+            Ok("vault") => KeyBackend::Vault {
+                addr: required("PDS_VAULT_ADDR")?,
+                token: required("PDS_VAULT_TOKEN")?,
+                mount: env::var("PDS_VAULT_MOUNT").unwrap_or_else(|_| "secret".to_string()),
+                path: env::var("PDS_VAULT_PATH").unwrap_or_else(|_| "aurora-locus".to_string()),
+            },
+            Ok(other) => {
+                return Err(PdsError::Validation(format!(
+                    "Unknown PDS_KEY_BACKEND: {} (expected config, local, aws-kms or vault)",
+                    other
+                )))
+            }
+        };
+
+        Ok(Self {
+            backend,
+            repo_key_id: env::var("PDS_REPO_KEY_ID").unwrap_or(defaults.repo_key_id),
+            plc_rotation_key_id: env::var("PDS_PLC_ROTATION_KEY_ID")
+                .unwrap_or(defaults.plc_rotation_key_id),
+        })
+    }
+}
+
+/// Encryption at rest (see `crypto::data_keys`)
+///
+/// Each actor gets a data key wrapped by the master key in the key backend.
+/// Turning a flag off only affects new data; anything already encrypted is
+/// still decrypted transparently.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptionConfig {
+    /// Create new actor stores encrypted (requires the `sqlcipher` build)
+    pub actor_stores: bool,
+    /// Encrypt new blob contents
+    pub blobs: bool,
+    /// Id of the master (key-wrapping) key in the key backend
+    pub master_key_id: String,
+    /// Plaintext master key hex for the config backend, imported into the
+    /// local and vault backends on first start if set
+    pub master_key_hex: String,
+}
+
+impl Default for EncryptionConfig {
+    fn default() -> Self {
+        Self {
+            actor_stores: false,
+            blobs: false,
+            master_key_id: "master".to_string(),
+            master_key_hex: String::new(),
+        }
+    }
+}
+
+impl EncryptionConfig {
+    /// Load from `PDS_ENCRYPTION_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_ENCRYPTION_{}", name)).ok();
+        let flag = |name: &str| var(name).map(|v| v == "true" || v == "1").unwrap_or(false);
+
+        Self {
+            actor_stores: flag("ACTOR_STORES"),
+            blobs: flag("BLOBS"),
+            master_key_id: var("MASTER_KEY_ID").unwrap_or(defaults.master_key_id),
+            master_key_hex: var("MASTER_KEY_HEX").unwrap_or(defaults.master_key_hex),
+        }
+    }
+
+    /// Whether anything new is being encrypted
+    pub fn enabled(&self) -> bool {
+        self.actor_stores || self.blobs
+    }
+}
+
+/// Cross-origin policy for browser clients
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorsConfig {
+    /// Origins allowed to make requests ("*" allows any origin)
+    pub allowed_origins: Vec<String>,
+    /// Allow cookies and authorization headers on cross-origin requests
+    pub allow_credentials: bool,
+    /// How long browsers may cache preflight responses (seconds)
+    pub max_age_secs: Option<u64>,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        Self {
+            allowed_origins: vec!["*".to_string()],
+            allow_credentials: false,
+            max_age_secs: None,
+        }
+    }
+}
+
+impl CorsConfig {
+    /// Load from `PDS_CORS_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_CORS_{}", name)).ok();
+
+        Self {
+            allowed_origins: var("ALLOWED_ORIGINS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|o| o.trim().trim_end_matches('/').to_string())
+                        .filter(|o| !o.is_empty())
+                        .collect()
+                })
+                .unwrap_or(defaults.allowed_origins),
+            allow_credentials: var("ALLOW_CREDENTIALS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.allow_credentials),
+            max_age_secs: var("MAX_AGE").and_then(|s| s.parse().ok()),
+        }
+    }
+
+    /// Whether any origin is allowed
+    pub fn allows_any_origin(&self) -> bool {
+        self.allowed_origins.iter().any(|o| o == "*")
+    }
+}
+
+/// Security headers added to every response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SecurityHeadersConfig {
+    /// `Strict-Transport-Security` max-age (seconds); unset sends no HSTS header
+    pub hsts_max_age_secs: Option<u64>,
+    /// Add `includeSubDomains` to HSTS
+    pub hsts_include_subdomains: bool,
+    /// CSP `frame-ancestors` sources, e.g. "'none'" or "'self' https://app.example"
+    pub frame_ancestors: Option<String>,
+    /// `Referrer-Policy` value
+    pub referrer_policy: Option<String>,
+}
+
+impl Default for SecurityHeadersConfig {
+    fn default() -> Self {
+        Self {
+            hsts_max_age_secs: None,
+            hsts_include_subdomains: false,
+            frame_ancestors: Some("'none'".to_string()),
+            referrer_policy: Some("strict-origin-when-cross-origin".to_string()),
+        }
+    }
+}
+
+impl SecurityHeadersConfig {
+    /// Load from `PDS_HSTS_*`, `PDS_FRAME_ANCESTORS` and `PDS_REFERRER_POLICY`
+    ///
+    /// Setting `PDS_FRAME_ANCESTORS` or `PDS_REFERRER_POLICY` to an empty
+    /// string disables that header.
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let optional = |name: &str, default: Option<String>| match env::var(name) {
+            Ok(v) if v.trim().is_empty() => None,
+            Ok(v) => Some(v.trim().to_string()),
+            Err(_) => default,
+        };
+
+        Self {
+            hsts_max_age_secs: env::var("PDS_HSTS_MAX_AGE").ok().and_then(|s| s.parse().ok()),
+            hsts_include_subdomains: env::var("PDS_HSTS_INCLUDE_SUBDOMAINS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.hsts_include_subdomains),
+            frame_ancestors: optional("PDS_FRAME_ANCESTORS", defaults.frame_ancestors),
+            referrer_policy: optional("PDS_REFERRER_POLICY", defaults.referrer_policy),
+        }
+    }
+
+    /// `Strict-Transport-Security` header value, if enabled
+    pub fn hsts_value(&self) -> Option<String> {
+        self.hsts_max_age_secs.map(|max_age| {
+            if self.hsts_include_subdomains {
+                format!("max-age={}; includeSubDomains", max_age)
+            } else {
+                format!("max-age={}", max_age)
+            }
+        })
+    }
+}
+
+/// subscribeRepos WebSocket settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirehoseConfig {
+    /// Accept permessage-deflate when clients offer it
+    pub compression: bool,
+    /// Deflate level (0-9)
+    pub compression_level: u32,
+    /// Largest frame sent to a client (bytes); commits over it go out as `tooBig`
+    pub max_frame_bytes: usize,
+    /// Longest an idle subscription waits between checks of the event log
+    /// (ms). New events wake subscriptions at once; the checks only pick up
+    /// events written by another process.
+    pub max_poll_interval_ms: u64,
+}
+
+impl Default for FirehoseConfig {
+    fn default() -> Self {
+        Self {
+            compression: true,
+            compression_level: 6,
+            max_frame_bytes: 2 * 1024 * 1024,
+            max_poll_interval_ms: 5_000,
+        }
+    }
+}
+
+impl FirehoseConfig {
+    /// Load from `PDS_FIREHOSE_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_FIREHOSE_{}", name)).ok();
+
+        Self {
+            compression: var("COMPRESSION")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.compression),
+            compression_level: var("COMPRESSION_LEVEL")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.compression_level),
+            max_frame_bytes: var("MAX_FRAME_BYTES")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_frame_bytes),
+            max_poll_interval_ms: var("MAX_POLL_INTERVAL_MS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_poll_interval_ms),
+        }
+    }
+}
+
+/// Cache-Control lifetimes for public read endpoints (seconds; 0 sends
+/// `no-cache`, so clients still revalidate with the ETag)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpCacheConfig {
+    /// getRecord
+    pub record_max_age: u64,
+    /// describeRepo
+    pub describe_repo_max_age: u64,
+    /// Blob downloads (content-addressed, so safe to cache for long)
+    pub blob_max_age: u64,
+    /// resolveHandle
+    pub handle_max_age: u64,
+    /// resolveDid
+    pub did_doc_max_age: u64,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        Self {
+            record_max_age: 60,
+            describe_repo_max_age: 60,
+            blob_max_age: 31_536_000,
+            handle_max_age: 300,
+            did_doc_max_age: 300,
+        }
+    }
+}
+
+impl HttpCacheConfig {
+    /// Load from `PDS_HTTP_CACHE_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_HTTP_CACHE_{}", name)).ok();
+
+        Self {
+            record_max_age: var("RECORD_MAX_AGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.record_max_age),
+            describe_repo_max_age: var("DESCRIBE_REPO_MAX_AGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.describe_repo_max_age),
+            blob_max_age: var("BLOB_MAX_AGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.blob_max_age),
+            handle_max_age: var("HANDLE_MAX_AGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.handle_max_age),
+            did_doc_max_age: var("DID_DOC_MAX_AGE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.did_doc_max_age),
+        }
+    }
+}
+
+/// Background job scheduling
+///
+/// Jobs run on their built-in cron schedules unless overridden with
+/// `PDS_JOB_<NAME>_SCHEDULE`, and can be switched off with
+/// `PDS_JOB_<NAME>_ENABLED=false` (`<NAME>` is the job name uppercased).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobsConfig {
+    /// Upper bound (seconds) of the random delay before startup runs
+    pub startup_jitter_secs: u64,
+    /// Per-job overrides keyed by job name
+    pub overrides: std::collections::BTreeMap<String, JobOverride>,
+}
+
+/// Configured settings for one job
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobOverride {
+    pub schedule: Option<String>,
+    pub enabled: Option<bool>,
+}
+
+impl JobsConfig {
+    /// Load from `PDS_JOBS_*` and `PDS_JOB_<NAME>_*` environment variables
+    fn from_env() -> Self {
+        let mut overrides: std::collections::BTreeMap<String, JobOverride> = Default::default();
+        for (key, value) in env::vars() {
+            let Some(rest) = key.strip_prefix("PDS_JOB_") else {
+                continue;
+            };
+            if let Some(name) = rest.strip_suffix("_SCHEDULE") {
+                overrides.entry(name.to_lowercase()).or_default().schedule = Some(value);
+            } else if let Some(name) = rest.strip_suffix("_ENABLED") {
+                overrides.entry(name.to_lowercase()).or_default().enabled = value.parse().ok();
+            }
+        }
+
+        Self {
+            startup_jitter_secs: env::var("PDS_JOBS_STARTUP_JITTER_SECS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(30),
+            overrides,
+        }
+    }
+
+    /// Whether the job runs on its schedule
+    pub fn enabled(&self, name: &str) -> bool {
+        self.overrides.get(name).and_then(|o| o.enabled).unwrap_or(true)
+    }
+
+    /// Configured schedule, if overridden
+    pub fn schedule(&self, name: &str) -> Option<&str> {
+        self.overrides.get(name).and_then(|o| o.schedule.as_deref())
+    }
+}
+
+/// Record write hooks (see `actor_store::hooks`)
+///
+/// `PDS_WRITE_HOOKS` lists the hooks to run, in order. Each hook is limited
+/// to `PDS_WRITE_HOOK_<NAME>_COLLECTIONS` (comma-separated NSIDs or `prefix.*`,
+/// all collections when unset) and reads its own options from
+/// `PDS_WRITE_HOOK_<NAME>_<OPTION>`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WriteHooksConfig {
+    pub hooks: Vec<WriteHookConfig>,
+}
+
+/// One enabled write hook
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct WriteHookConfig {
+    pub name: String,
+    pub collections: Vec<String>,
+    /// Hook-specific options keyed by lowercased option name
+    pub options: std::collections::BTreeMap<String, String>,
+}
+
+impl WriteHooksConfig {
+    /// Load from `PDS_WRITE_HOOKS` and `PDS_WRITE_HOOK_<NAME>_*` environment variables
+    fn from_env() -> Self {
+        let names = env::var("PDS_WRITE_HOOKS").unwrap_or_default();
+        let hooks = names
+            .split(',')
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                let prefix = format!("PDS_WRITE_HOOK_{}_", env_key(&name));
+                let mut hook = WriteHookConfig {
+                    name,
+                    ..Default::default()
+                };
+                for (key, value) in env::vars() {
+                    let Some(option) = key.strip_prefix(&prefix) else {
+                        continue;
+                    };
+                    if option == "COLLECTIONS" {
+                        hook.collections = value
+                            .split(',')
+                            .map(|c| c.trim().to_string())
+                            .filter(|c| !c.is_empty() && c != "*")
+                            .collect();
+                    } else {
+                        hook.options.insert(option.to_lowercase(), value);
+                    }
+                }
+                hook
+            })
+            .collect();
+
+        Self { hooks }
+    }
+}
+
+/// Adaptive signup throttle for open registration
+///
+/// Caps are counted over a sliding hour; 0 disables a cap.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SignupThrottleConfig {
+    /// Open signups per hour across the server before invites are required
+    pub global_per_hour: u32,
+    /// Open signups per hour from one client IP
+    pub per_ip_per_hour: u32,
+    /// How long invites stay required once the global cap is hit
+    pub lockdown_minutes: u64,
+}
+
+impl Default for SignupThrottleConfig {
+    fn default() -> Self {
+        Self {
+            global_per_hour: 0,
+            per_ip_per_hour: 0,
+            lockdown_minutes: 60,
+        }
+    }
+}
+
+impl SignupThrottleConfig {
+    /// Load from `PDS_SIGNUP_THROTTLE_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_SIGNUP_THROTTLE_{}", name)).ok();
+
+        Self {
+            global_per_hour: var("GLOBAL_PER_HOUR")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.global_per_hour),
+            per_ip_per_hour: var("PER_IP_PER_HOUR")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.per_ip_per_hour),
+            lockdown_minutes: var("LOCKDOWN_MINUTES")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.lockdown_minutes),
+        }
+    }
+}
+
+/// Signup metadata advertised by `com.atproto.server.describeServer`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DescribeServerConfig {
+    pub privacy_policy_url: Option<String>,
+    pub terms_of_service_url: Option<String>,
+    /// Where users can reach the operator
+    pub contact_email: Option<String>,
+    /// Tell clients signup needs a verified phone number
+    pub phone_verification_required: bool,
+}
+
+impl DescribeServerConfig {
+    /// Load from `PDS_PRIVACY_POLICY_URL`, `PDS_TERMS_OF_SERVICE_URL`,
+    /// `PDS_CONTACT_EMAIL_ADDRESS` and `PDS_PHONE_VERIFICATION_REQUIRED`
+    fn from_env() -> Self {
+        let var = |name: &str| env::var(format!("PDS_{}", name)).ok().filter(|s| !s.is_empty());
+
+        Self {
+            privacy_policy_url: var("PRIVACY_POLICY_URL"),
+            terms_of_service_url: var("TERMS_OF_SERVICE_URL"),
+            contact_email: var("CONTACT_EMAIL_ADDRESS"),
+            phone_verification_required: var("PHONE_VERIFICATION_REQUIRED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// Read replica mode
+///
+/// A replica serves blob, image and sync reads from storage replicated from
+/// the primary (a shared S3 bucket or a copied blob directory, plus account
+/// database and actor-store snapshots) and forwards every other request,
+/// including all writes, to the primary.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ReplicaConfig {
+    /// Base URL of the primary; replica mode is on when set
+    pub primary_url: Option<String>,
+}
+
+impl ReplicaConfig {
+    /// Load from `PDS_REPLICA_PRIMARY_URL`
+    fn from_env() -> Self {
+        Self {
+            primary_url: env::var("PDS_REPLICA_PRIMARY_URL")
+                .ok()
+                .map(|url| url.trim_end_matches('/').to_string())
+                .filter(|url| !url.is_empty()),
+        }
+    }
+
+    /// Whether this instance is a read replica
+    pub fn enabled(&self) -> bool {
+        self.primary_url.is_some()
+    }
+}
+
+/// Webhooks accounts register for events in their own repo
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RepoWebhookConfig {
+    pub enabled: bool,
+    /// Webhooks a single account may register
+    pub max_per_account: usize,
+    /// Allow targets on loopback, private and link-local addresses (for
+    /// development; otherwise any account could reach internal services)
+    pub allow_private_targets: bool,
+}
+
+impl Default for RepoWebhookConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_per_account: 10,
+            allow_private_targets: false,
+        }
+    }
+}
+
+impl RepoWebhookConfig {
+    /// Load from `PDS_REPO_WEBHOOKS_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_REPO_WEBHOOKS_{}", name)).ok();
+
+        Self {
+            enabled: var("ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            max_per_account: var("MAX_PER_ACCOUNT")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.max_per_account),
+            allow_private_targets: var("ALLOW_PRIVATE_TARGETS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.allow_private_targets),
+        }
+    }
+}
+
+/// ActivityPub bridge (experimental)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActivityPubConfig {
+    /// Let accounts mirror their posts to an ActivityPub actor
+    pub enabled: bool,
+    /// Allow remote actors and inboxes on loopback, private and link-local
+    /// addresses (for development)
+    pub allow_private_targets: bool,
+}
+
+impl ActivityPubConfig {
+    /// Load from `PDS_ACTIVITYPUB_*` environment variables
+    fn from_env() -> Self {
+        let var = |name: &str| env::var(format!("PDS_ACTIVITYPUB_{}", name)).ok();
+
+        Self {
+            enabled: var("ENABLED").map(|v| v == "true" || v == "1").unwrap_or(false),
+            allow_private_targets: var("ALLOW_PRIVATE_TARGETS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+        }
+    }
+}
+
+/// RSS/Atom feeds of accounts' posts (`/feeds/<handle>.rss`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedsConfig {
+    pub enabled: bool,
+    /// Posts per feed
+    pub max_items: i64,
+    /// How long readers and the Redis cache may keep a feed
+    pub cache_seconds: u64,
+    /// Web app that item links point to
+    pub link_base: String,
+}
+
+impl Default for FeedsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_items: 50,
+            cache_seconds: 300,
+            link_base: "https://bsky.app".to_string(),
+        }
+    }
+}
+
+impl FeedsConfig {
+    /// Load from `PDS_FEEDS_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_FEEDS_{}", name)).ok();
+
+        Self {
+            enabled: var("ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            max_items: var("MAX_ITEMS")
+                .and_then(|v| v.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(defaults.max_items),
+            cache_seconds: var("CACHE_SECONDS")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(defaults.cache_seconds),
+            link_base: var("LINK_BASE")
+                .map(|v| v.trim_end_matches('/').to_string())
+                .unwrap_or(defaults.link_base),
+        }
+    }
+}
+
+/// Request body size limits, by kind of route
+///
+/// Checked against `Content-Length` before a handler runs, and while reading
+/// bodies sent without one. Resumable upload chunks are also capped at 8MB
+/// each whatever `blob` is.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BodyLimitConfig {
+    /// XRPC procedures and every other route
+    pub json: usize,
+    /// uploadBlob and resumable upload chunks
+    pub blob: usize,
+    /// Repository CARs for importRepo
+    pub car: usize,
+}
+
+impl Default for BodyLimitConfig {
+    fn default() -> Self {
+        Self {
+            json: 1024 * 1024,
+            blob: 10 * 1024 * 1024,
+            car: 256 * 1024 * 1024,
+        }
+    }
+}
+
+impl BodyLimitConfig {
+    /// Load from `PDS_BODY_LIMIT_*` environment variables; the blob limit
+    /// defaults to the largest blob accepted
+    fn from_env(max_blob_size: usize) -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| {
+            env::var(format!("PDS_BODY_LIMIT_{}", name))
+                .ok()
+                .and_then(|v| v.parse::<usize>().ok())
+                .filter(|limit| *limit > 0)
+        };
+
+        Self {
+            json: var("JSON").unwrap_or(defaults.json),
+            blob: var("BLOB").unwrap_or(max_blob_size),
+            car: var("CAR").unwrap_or(defaults.car),
+        }
+    }
+
+    /// Largest body accepted on `path`
+    pub fn limit_for(&self, path: &str) -> usize {
+        match path {
+            "/xrpc/com.atproto.repo.importRepo" => self.car,
+            "/xrpc/com.atproto.repo.uploadBlob" => self.blob,
+            _ if path == "/uploads" || path.starts_with("/uploads/") => self.blob,
+            _ => self.json,
+        }
+    }
+}
+
+/// Instance discovery documents (nodeinfo and WebFinger)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeInfoConfig {
+    /// Serve `/.well-known/nodeinfo` and the nodeinfo 2.1 document
+    pub enabled: bool,
+    /// Serve `/.well-known/webfinger` for `acct:<handle>` lookups
+    pub webfinger: bool,
+}
+
+impl Default for NodeInfoConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            webfinger: false,
+        }
+    }
+}
+
+impl NodeInfoConfig {
+    /// Load from `PDS_NODEINFO_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_NODEINFO_{}", name)).ok();
+
+        Self {
+            enabled: var("ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            webfinger: var("WEBFINGER")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.webfinger),
+        }
+    }
+}
+
+/// Reverse proxies whose `X-Forwarded-For` entries are believed
+///
+/// Client IPs feed the blocklist, signup caps and rate limits, so they are
+/// taken from the right-most `X-Forwarded-For` entry not added by one of
+/// these proxies, and from the socket address when there are none.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustedProxyConfig {
+    /// Proxies directly in front of the server, counting from the socket
+    pub hops: usize,
+    /// Proxy addresses, as IPs or CIDR ranges
+    pub cidrs: Vec<String>,
+}
+
+impl TrustedProxyConfig {
+    /// Load from `PDS_TRUSTED_PROXY_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_TRUSTED_PROXY_{}", name)).ok();
+
+        Self {
+            hops: var("HOPS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.hops),
+            cidrs: var("CIDRS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|c| c.trim().to_string())
+                        .filter(|c| !c.is_empty())
+                        .collect()
+                })
+                .unwrap_or(defaults.cidrs),
+        }
+    }
+}
+
+/// Actor store compaction (the `repo_compaction` job)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(tag = "type")]
-pub enum BlobstoreConfig {
-    Disk {
-        location: PathBuf,
-        tmp_location: PathBuf,
-    },
-    S3 {
-        bucket: String,
-        region: String,
-        access_key_id: String,
-        secret_access_key: String,
-        endpoint: Option<String>,
-    },
+pub struct CompactionConfig {
+    /// Revisions whose blocks are kept even once unreferenced, so recent
+    /// `since` exports stay complete
+    pub history_depth: usize,
+    /// Blocks deleted per transaction
+    pub batch_size: usize,
+    /// Pause between batches (milliseconds), letting queued writes through
+    pub batch_delay_ms: u64,
 }
 
-/// Authentication configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AuthConfig {
-    pub jwt_secret: String,
-    pub repo_signing_key: String,
-    pub plc_rotation_key: String,
-    /// DID(s) allowed to access admin panel (comma-separated)
-    pub admin_dids: Vec<String>,
-    /// OAuth configuration for admin login
-    pub oauth: OAuthConfig,
+impl Default for CompactionConfig {
+    fn default() -> Self {
+        Self {
+            history_depth: 10,
+            batch_size: 500,
+            batch_delay_ms: 50,
+        }
+    }
 }
 
-/// OAuth configuration for admin authentication
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct OAuthConfig {
-    /// OAuth client ID (URL to client metadata)
-    pub client_id: String,
-    /// OAuth redirect URI
-    pub redirect_uri: String,
-    /// PDS URL for OAuth (e.g., https://bsky.social)
-    pub pds_url: String,
+impl CompactionConfig {
+    /// Load from `PDS_COMPACTION_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_COMPACTION_{}", name)).ok();
+
+        Self {
+            history_depth: var("HISTORY_DEPTH")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.history_depth),
+            batch_size: var("BATCH_SIZE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.batch_size),
+            batch_delay_ms: var("BATCH_DELAY_MS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.batch_delay_ms),
+        }
+    }
 }
 
-/// Identity configuration
+/// Previous versions of records, kept so an owner can see and roll back
+/// edits (`app.aurora.repo.listRecordVersions` and `rollbackRecord`)
 #[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct IdentityConfig {
-    pub did_plc_url: String,
-    pub service_handle_domains: Vec<String>,
-    pub did_cache_stale_ttl: u64,
-    pub did_cache_max_ttl: u64,
+pub struct RecordHistoryConfig {
+    pub enabled: bool,
+    /// Versions kept per record, oldest dropped first
+    pub depth: usize,
 }
 
-/// Email configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct EmailConfig {
-    pub smtp_url: String,
-    pub from_address: String,
+impl Default for RecordHistoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            depth: 10,
+        }
+    }
+}
+
+impl RecordHistoryConfig {
+    /// Load from `PDS_RECORD_HISTORY_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_RECORD_HISTORY_{}", name)).ok();
+
+        Self {
+            enabled: var("ENABLED")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(defaults.enabled),
+            depth: var("DEPTH")
+                .and_then(|s| s.parse().ok())
+                .filter(|n| *n > 0)
+                .unwrap_or(defaults.depth),
+        }
+    }
 }
 
 /// Invite system configuration
@@ -114,6 +1511,11 @@ pub struct RateLimitConfig {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LoggingConfig {
     pub level: String,
+    /// Days to keep admin audit log entries (None keeps them forever)
+    pub audit_log_retention_days: Option<u32>,
+    /// Days to keep a tombstone (uri, cid, deletion time) for each deleted
+    /// record (None keeps no tombstones)
+    pub tombstone_retention_days: Option<u32>,
 }
 
 /// Federation configuration for Bluesky network integration
@@ -133,6 +1535,146 @@ pub struct FederationConfig {
     pub auto_stream_events: bool,
 }
 
+/// Mirror mode configuration
+///
+/// When enabled, the PDS follows a remote firehose and keeps read-only,
+/// signature-verified copies of the listed repositories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorConfig {
+    /// Enable mirror mode
+    pub enabled: bool,
+    /// PDS or relay to mirror from (e.g., https://bsky.network)
+    pub source_url: String,
+    /// DIDs whose repositories are mirrored
+    pub dids: Vec<String>,
+}
+
+/// Automated content policy applied to record writes
+///
+/// Actions are "reject", "label" or "report". A limit of 0 (or an empty
+/// domain list) disables the corresponding rule.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContentPolicyConfig {
+    pub enabled: bool,
+    /// Maximum posts an account may create per minute
+    pub max_posts_per_minute: u32,
+    pub rate_action: String,
+    /// Identical posts allowed within `duplicate_window` seconds
+    pub duplicate_limit: u32,
+    pub duplicate_window: u64,
+    pub duplicate_action: String,
+    /// Link domains (and their subdomains) that may not appear in records
+    pub blocked_domains: Vec<String>,
+    pub blocked_domain_action: String,
+    /// Accounts younger than this many hours are restricted from posting links
+    pub new_account_hours: u64,
+    pub new_account_action: String,
+}
+
+impl Default for ContentPolicyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_posts_per_minute: 30,
+            rate_action: "reject".to_string(),
+            duplicate_limit: 3,
+            duplicate_window: 3600,
+            duplicate_action: "report".to_string(),
+            blocked_domains: Vec::new(),
+            blocked_domain_action: "reject".to_string(),
+            new_account_hours: 24,
+            new_account_action: "label".to_string(),
+        }
+    }
+}
+
+impl ContentPolicyConfig {
+    /// Load from `PDS_CONTENT_POLICY_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| env::var(format!("PDS_CONTENT_POLICY_{}", name)).ok();
+
+        Self {
+            enabled: var("ENABLED").and_then(|s| s.parse().ok()).unwrap_or(defaults.enabled),
+            max_posts_per_minute: var("MAX_POSTS_PER_MINUTE")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_posts_per_minute),
+            rate_action: var("RATE_ACTION").unwrap_or(defaults.rate_action),
+            duplicate_limit: var("DUPLICATE_LIMIT")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.duplicate_limit),
+            duplicate_window: var("DUPLICATE_WINDOW")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.duplicate_window),
+            duplicate_action: var("DUPLICATE_ACTION").unwrap_or(defaults.duplicate_action),
+            blocked_domains: var("BLOCKED_DOMAINS")
+                .map(|s| {
+                    s.split(',')
+                        .map(|d| d.trim().to_lowercase())
+                        .filter(|d| !d.is_empty())
+                        .collect()
+                })
+                .unwrap_or(defaults.blocked_domains),
+            blocked_domain_action: var("BLOCKED_DOMAIN_ACTION").unwrap_or(defaults.blocked_domain_action),
+            new_account_hours: var("NEW_ACCOUNT_HOURS")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.new_account_hours),
+            new_account_action: var("NEW_ACCOUNT_ACTION").unwrap_or(defaults.new_account_action),
+        }
+    }
+}
+
+/// Challenge for `createAccount` on handles that need no invite code
+///
+/// `provider` is "none", "hcaptcha", "turnstile" (the token is checked with
+/// the provider using `secret_key`) or "pow" (a proof-of-work puzzle with
+/// `pow_difficulty` leading zero bits). Signups only have to pass it once
+/// `signup_threshold` accounts were created in the past hour (0 = always).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RegistrationChallengeConfig {
+    pub provider: String,
+    pub site_key: Option<String>,
+    pub secret_key: Option<String>,
+    pub pow_difficulty: u32,
+    pub signup_threshold: u32,
+}
+
+impl Default for RegistrationChallengeConfig {
+    fn default() -> Self {
+        Self {
+            provider: "none".to_string(),
+            site_key: None,
+            secret_key: None,
+            pow_difficulty: 20,
+            signup_threshold: 0,
+        }
+    }
+}
+
+impl RegistrationChallengeConfig {
+    /// Load from `PDS_REGISTRATION_CHALLENGE_*` environment variables
+    fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str| {
+            env::var(format!("PDS_REGISTRATION_CHALLENGE_{}", name))
+                .ok()
+                .filter(|s| !s.is_empty())
+        };
+
+        Self {
+            provider: var("PROVIDER").map(|s| s.to_lowercase()).unwrap_or(defaults.provider),
+            site_key: var("SITE_KEY"),
+            secret_key: var("SECRET_KEY"),
+            pow_difficulty: var("POW_DIFFICULTY")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.pow_difficulty),
+            signup_threshold: var("SIGNUP_THRESHOLD")
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.signup_threshold),
+        }
+    }
+}
+
 impl ServerConfig {
     /// Load configuration from environment variables
     pub fn from_env() -> PdsResult<Self> {
@@ -151,6 +1693,45 @@ impl ServerConfig {
             .unwrap_or_else(|_| "5242880".to_string())
             .parse()
             .unwrap_or(5242880);
+        let blob_audio_upload_limit = env::var("PDS_BLOB_AUDIO_UPLOAD_LIMIT")
+            .unwrap_or_else(|_| "10485760".to_string())
+            .parse()
+            .unwrap_or(10485760);
+        let blob_account_quota = env::var("PDS_BLOB_ACCOUNT_QUOTA")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|quota| *quota > 0);
+        let blob_animated_thumbnails = env::var("PDS_BLOB_ANIMATED_THUMBNAILS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        let blob_temp_ttl_hours = env::var("PDS_BLOB_TEMP_TTL_HOURS")
+            .ok()
+            .and_then(|v| v.parse::<i64>().ok())
+            .filter(|hours| *hours > 0)
+            .unwrap_or(24);
+        let blob_max_staged_per_account = env::var("PDS_BLOB_MAX_STAGED_PER_ACCOUNT")
+            .ok()
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|limit| *limit > 0);
+        let dev_mode = env::var("PDS_DEV_MODE")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        let pretty_json = env::var("PDS_PRETTY_JSON")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+
+        // Listeners: comma-separated "address=role" pairs, e.g.
+        // "0.0.0.0:2583=api,[::]:2583=api,127.0.0.1:9090=metrics"
+        let listeners = match env::var("PDS_LISTENERS") {
+            Ok(spec) if !spec.trim().is_empty() => parse_listeners(&spec)?,
+            _ => vec![ListenerConfig {
+                address: format!("0.0.0.0:{}", port),
+                role: ListenerRole::All,
+            }],
+        };
 
         let data_directory: PathBuf = env::var("PDS_DATA_DIRECTORY")
             .unwrap_or_else(|_| "./data".to_string())
@@ -192,10 +1773,12 @@ impl ServerConfig {
 
         let jwt_secret = env::var("PDS_JWT_SECRET")
             .map_err(|_| PdsError::Validation("JWT secret required".to_string()))?;
-        let repo_signing_key = env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX")
-            .map_err(|_| PdsError::Validation("Repo signing key required".to_string()))?;
-        let plc_rotation_key = env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX")
-            .map_err(|_| PdsError::Validation("PLC rotation key required".to_string()))?;
+        // Only required with the config key backend; other backends import
+        // them on first start if present
+        let repo_signing_key =
+            env::var("PDS_REPO_SIGNING_KEY_K256_PRIVATE_KEY_HEX").unwrap_or_default();
+        let plc_rotation_key =
+            env::var("PDS_PLC_ROTATION_KEY_K256_PRIVATE_KEY_HEX").unwrap_or_default();
 
         // Parse admin DIDs from comma-separated list
         let admin_dids = env::var("PDS_ADMIN_DIDS")
@@ -205,6 +1788,28 @@ impl ServerConfig {
             .filter(|s| !s.is_empty())
             .collect::<Vec<String>>();
 
+        // Optional network-level token gating the admin API
+        let admin_network_token = env::var("PDS_ADMIN_NETWORK_TOKEN")
+            .ok()
+            .filter(|s| !s.trim().is_empty());
+
+        // Argon2id password hashing parameters
+        let hash_defaults = PasswordHashConfig::default();
+        let password_hash = PasswordHashConfig {
+            memory_kib: env::var("PDS_ARGON2_MEMORY_KIB")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(hash_defaults.memory_kib),
+            iterations: env::var("PDS_ARGON2_ITERATIONS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(hash_defaults.iterations),
+            parallelism: env::var("PDS_ARGON2_PARALLELISM")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(hash_defaults.parallelism),
+        };
+
         // OAuth configuration for admin login
         let oauth_client_id = env::var("PDS_OAUTH_CLIENT_ID")
             .unwrap_or_else(|_| format!("https://{}/oauth/client-metadata.json", hostname));
@@ -215,7 +1820,7 @@ impl ServerConfig {
 
         let did_plc_url = env::var("PDS_DID_PLC_URL")
             .unwrap_or_else(|_| "https://plc.directory".to_string());
-        let service_handle_domains = env::var("PDS_SERVICE_HANDLE_DOMAINS")
+        let service_handle_domains: Vec<String> = env::var("PDS_SERVICE_HANDLE_DOMAINS")
             .unwrap_or_else(|_| format!(".{}", hostname))
             .split(',')
             .map(|s| s.trim().to_string())
@@ -228,16 +1833,14 @@ impl ServerConfig {
             .unwrap_or_else(|_| "86400".to_string())
             .parse()
             .unwrap_or(86400);
+        let handle_doh_url = env::var("PDS_HANDLE_DOH_URL")
+            .unwrap_or_else(|_| "https://cloudflare-dns.com/dns-query".to_string());
+        let handle_redirect_secs = env::var("PDS_HANDLE_REDIRECT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(0);
 
-        let email = if let Ok(smtp_url) = env::var("PDS_EMAIL_SMTP_URL") {
-            Some(EmailConfig {
-                smtp_url,
-                from_address: env::var("PDS_EMAIL_FROM_ADDRESS")
-                    .unwrap_or_else(|_| format!("noreply@{}", hostname)),
-            })
-        } else {
-            None
-        };
+        let email = EmailConfig::from_env(&hostname)?;
 
         let invite_required = env::var("PDS_INVITE_REQUIRED")
             .unwrap_or_else(|_| "false".to_string())
@@ -260,6 +1863,14 @@ impl ServerConfig {
             .unwrap_or(3000);
 
         let log_level = env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string());
+        let audit_log_retention_days = env::var("PDS_AUDIT_LOG_RETENTION_DAYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|days| *days > 0);
+        let tombstone_retention_days = env::var("PDS_TOMBSTONE_RETENTION_DAYS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .filter(|days| *days > 0);
 
         // Federation configuration
         let federation_enabled = env::var("PDS_FEDERATION_ENABLED")
@@ -285,6 +1896,50 @@ impl ServerConfig {
             .parse()
             .unwrap_or(false);
 
+        // Mirror mode configuration
+        let mirror_enabled = env::var("PDS_MIRROR_ENABLED")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse()
+            .unwrap_or(false);
+        let mirror_source_url = env::var("PDS_MIRROR_SOURCE_URL")
+            .unwrap_or_else(|_| "https://bsky.network".to_string());
+        let mirror_dids = env::var("PDS_MIRROR_DIDS")
+            .unwrap_or_else(|_| String::new())
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<String>>();
+
+        // External moderation service for user reports
+        let report_service = env::var("PDS_REPORT_SERVICE_DID")
+            .ok()
+            .filter(|s| !s.trim().is_empty())
+            .map(|did| ReportServiceConfig {
+                did: did.trim().to_string(),
+                url: env::var("PDS_REPORT_SERVICE_URL")
+                    .ok()
+                    .filter(|s| !s.trim().is_empty()),
+            });
+
+        // AppView for proxied app.bsky.* reads
+        let bsky_app_view = match env::var("PDS_BSKY_APP_VIEW_URL") {
+            Ok(url) if !url.trim().is_empty() => Some(AppViewConfig {
+                url: url.trim().to_string(),
+                did: env::var("PDS_BSKY_APP_VIEW_DID").map_err(|_| {
+                    PdsError::Validation("PDS_BSKY_APP_VIEW_DID required with an AppView URL".to_string())
+                })?,
+            }),
+            _ => None,
+        };
+
+        let keys = KeyConfig::from_env(&data_directory)?;
+
+        let virtual_hosts = service_handle_domains
+            .iter()
+            .filter(|d| !d.trim_start_matches('.').is_empty())
+            .map(|d| VirtualHostConfig::from_env(d))
+            .collect();
+
         Ok(ServerConfig {
             service: ServiceConfig {
                 hostname,
@@ -292,6 +1947,14 @@ impl ServerConfig {
                 service_did,
                 version,
                 blob_upload_limit,
+                blob_audio_upload_limit,
+                blob_account_quota,
+                blob_animated_thumbnails,
+                blob_temp_ttl_hours,
+                blob_max_staged_per_account,
+                listeners,
+                dev_mode,
+                pretty_json,
             },
             storage: StorageConfig {
                 data_directory,
@@ -311,12 +1974,19 @@ impl ServerConfig {
                     redirect_uri: oauth_redirect_uri,
                     pds_url: oauth_pds_url,
                 },
+                admin_network_token,
+                password_hash,
+                jwt_keys: JwtKeysConfig::from_env()?,
             },
             identity: IdentityConfig {
                 did_plc_url,
                 service_handle_domains,
                 did_cache_stale_ttl,
                 did_cache_max_ttl,
+                handle_doh_url,
+                handle_redirect_secs,
+                handle_changes: HandleChangeConfig::from_env(),
+                plc_failover: PlcFailoverConfig::from_env(),
             },
             email,
             invites: InviteConfig {
@@ -328,7 +1998,11 @@ impl ServerConfig {
                 enabled: rate_limit_enabled,
                 global_requests_per_minute: rate_limit_requests,
             },
-            logging: LoggingConfig { level: log_level },
+            logging: LoggingConfig {
+                level: log_level,
+                audit_log_retention_days,
+                tombstone_retention_days,
+            },
             federation: FederationConfig {
                 enabled: federation_enabled,
                 relay_urls,
@@ -337,23 +2011,482 @@ impl ServerConfig {
                 public_url,
                 auto_stream_events,
             },
+            mirror: MirrorConfig {
+                enabled: mirror_enabled,
+                source_url: mirror_source_url,
+                dids: mirror_dids,
+            },
+            content_policy: ContentPolicyConfig::from_env(),
+            registration_challenge: RegistrationChallengeConfig::from_env(),
+            signup_throttle: SignupThrottleConfig::from_env(),
+            report_service,
+            bsky_app_view,
+            proxy: ProxyConfig::from_env(),
+            keys,
+            encryption: EncryptionConfig::from_env(),
+            cors: CorsConfig::from_env(),
+            security_headers: SecurityHeadersConfig::from_env(),
+            firehose: FirehoseConfig::from_env(),
+            http_cache: HttpCacheConfig::from_env(),
+            jobs: JobsConfig::from_env(),
+            compaction: CompactionConfig::from_env(),
+            trusted_proxies: TrustedProxyConfig::from_env(),
+            record_history: RecordHistoryConfig::from_env(),
+            write_hooks: WriteHooksConfig::from_env(),
+            nodeinfo: NodeInfoConfig::from_env(),
+            describe_server: DescribeServerConfig::from_env(),
+            replica: ReplicaConfig::from_env(),
+            repo_webhooks: RepoWebhookConfig::from_env(),
+            activitypub: ActivityPubConfig::from_env(),
+            feeds: FeedsConfig::from_env(),
+            body_limits: BodyLimitConfig::from_env(blob_upload_limit.max(blob_audio_upload_limit)),
+            virtual_hosts,
         })
     }
 
-    /// Validate configuration
+    /// Validate configuration, reporting every problem at once
     pub fn validate(&self) -> PdsResult<()> {
+        let errors = self.validation_errors();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(PdsError::Validation(errors.join("; ")))
+        }
+    }
+
+    /// Every configuration problem found
+    pub fn validation_errors(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+
         if self.service.hostname.is_empty() {
-            return Err(PdsError::Validation("Hostname cannot be empty".to_string()));
+            errors.push("Hostname cannot be empty".to_string());
         }
 
         if self.authentication.jwt_secret.len() < 32 {
-            return Err(PdsError::Validation(
-                "JWT secret must be at least 32 characters".to_string(),
-            ));
+            errors.push("JWT secret must be at least 32 characters".to_string());
         }
 
         // Admin password removed - OAuth uses DID-based authentication
 
-        Ok(())
+        if matches!(self.keys.backend, KeyBackend::Config) {
+            if self.authentication.repo_signing_key.is_empty() {
+                errors.push("Repo signing key required".to_string());
+            }
+            if self.authentication.plc_rotation_key.is_empty() {
+                errors.push("PLC rotation key required".to_string());
+            }
+        }
+        if self.keys.repo_key_id.is_empty() || self.keys.plc_rotation_key_id.is_empty() {
+            errors.push("Key ids cannot be empty".to_string());
+        }
+        if self.encryption.enabled() {
+            if self.encryption.master_key_id.is_empty() {
+                errors.push("Encryption master key id cannot be empty".to_string());
+            }
+            if matches!(self.keys.backend, KeyBackend::Config) && self.encryption.master_key_hex.is_empty() {
+                errors.push("PDS_ENCRYPTION_MASTER_KEY_HEX is required with the config key backend".to_string());
+            }
+        }
+
+        for origin in &self.cors.allowed_origins {
+            let valid = origin == "*"
+                || ((origin.starts_with("https://") || origin.starts_with("http://"))
+                    && axum::http::HeaderValue::from_str(origin).is_ok());
+            if !valid {
+                errors.push(format!("Invalid CORS origin: {}", origin));
+            }
+        }
+        if self.cors.allow_credentials && self.cors.allows_any_origin() {
+            errors.push("PDS_CORS_ALLOW_CREDENTIALS requires explicit PDS_CORS_ALLOWED_ORIGINS".to_string());
+        }
+        let headers = &self.security_headers;
+        for value in [&headers.frame_ancestors, &headers.referrer_policy].into_iter().flatten() {
+            if axum::http::HeaderValue::from_str(value).is_err() {
+                errors.push(format!("Invalid security header value: {}", value));
+            }
+        }
+
+        let max_blob_size = self.service.blob_upload_limit.max(self.service.blob_audio_upload_limit);
+        if self.body_limits.blob < max_blob_size {
+            errors.push(format!(
+                "PDS_BODY_LIMIT_BLOB ({}) is below the largest blob upload limit ({})",
+                self.body_limits.blob, max_blob_size
+            ));
+        }
+
+        if self.firehose.compression_level > 9 {
+            errors.push("PDS_FIREHOSE_COMPRESSION_LEVEL must be between 0 and 9".to_string());
+        }
+        if self.firehose.max_frame_bytes < 4096 {
+            errors.push("PDS_FIREHOSE_MAX_FRAME_BYTES must be at least 4096".to_string());
+        }
+        if self.firehose.max_poll_interval_ms < 100 {
+            errors.push("PDS_FIREHOSE_MAX_POLL_INTERVAL_MS must be at least 100".to_string());
+        }
+
+        let links = [
+            ("PDS_PRIVACY_POLICY_URL", &self.describe_server.privacy_policy_url),
+            ("PDS_TERMS_OF_SERVICE_URL", &self.describe_server.terms_of_service_url),
+        ];
+        for (name, url) in links {
+            if let Some(url) = url {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    errors.push(format!("{} must be an http(s) URL: {}", name, url));
+                }
+            }
+        }
+        if let Some(email) = &self.describe_server.contact_email {
+            if !email.contains('@') {
+                errors.push(format!("Invalid PDS_CONTACT_EMAIL_ADDRESS: {}", email));
+            }
+        }
+
+        if let Some(url) = &self.replica.primary_url {
+            if !url.starts_with("https://") && !url.starts_with("http://") {
+                errors.push(format!("PDS_REPLICA_PRIMARY_URL must be an http(s) URL: {}", url));
+            }
+            if self.mirror.enabled {
+                errors.push("Mirror mode cannot run on a read replica".to_string());
+            }
+        }
+
+        for cidr in &self.trusted_proxies.cidrs {
+            if crate::net::parse_cidr(cidr).is_none() {
+                errors.push(format!("Invalid address in PDS_TRUSTED_PROXY_CIDRS: {}", cidr));
+            }
+        }
+
+        if self.compaction.batch_size == 0 {
+            errors.push("PDS_COMPACTION_BATCH_SIZE must be at least 1".to_string());
+        }
+        if self.signup_throttle.global_per_hour > 0 && self.signup_throttle.lockdown_minutes == 0 {
+            errors.push("PDS_SIGNUP_THROTTLE_LOCKDOWN_MINUTES must be at least 1".to_string());
+        }
+
+        for hook in &self.write_hooks.hooks {
+            if !crate::actor_store::hooks::BUILT_IN_HOOKS.contains(&hook.name.as_str()) {
+                errors.push(format!("Unknown write hook in PDS_WRITE_HOOKS: {}", hook.name));
+            }
+        }
+
+        for (name, job) in &self.jobs.overrides {
+            if !crate::jobs::JOBS.iter().any(|j| j.name == name) {
+                errors.push(format!("Unknown job in PDS_JOB_{}_*: {}", env_key(name), name));
+                continue;
+            }
+            if let Some(schedule) = &job.schedule {
+                if let Err(e) = crate::jobs::schedule::JobSchedule::parse(schedule) {
+                    errors.push(format!("PDS_JOB_{}_SCHEDULE: {}", env_key(name), e));
+                }
+            }
+        }
+
+        if let Some(email) = &self.email {
+            if email.max_attempts == 0 {
+                errors.push("PDS_EMAIL_MAX_ATTEMPTS must be at least 1".to_string());
+            }
+            if let EmailProviderConfig::Smtp { smtp_url } = &email.provider {
+                if let Err(PdsError::Validation(e)) = crate::mailer::provider::SmtpSettings::parse(smtp_url) {
+                    errors.push(e);
+                }
+            }
+            let base_url = match &email.provider {
+                EmailProviderConfig::Mailgun { base_url, .. } | EmailProviderConfig::Sendgrid { base_url, .. } => {
+                    Some(base_url)
+                }
+                EmailProviderConfig::Ses { endpoint, .. } => endpoint.as_ref(),
+                EmailProviderConfig::Smtp { .. } => None,
+            };
+            if let Some(url) = base_url {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    errors.push(format!("Email provider URL must be an http(s) URL: {}", url));
+                }
+            }
+        }
+
+        for vhost in &self.virtual_hosts {
+            if let Some(url) = &vhost.public_url {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    errors.push(format!(
+                        "Public URL for {} must be an http(s) URL",
+                        vhost.domain
+                    ));
+                }
+            }
+            if let Some(dir) = &vhost.email_template_dir {
+                if !dir.is_dir() {
+                    errors.push(format!(
+                        "Email template directory for {} does not exist: {}",
+                        vhost.domain,
+                        dir.display()
+                    ));
+                }
+            }
+        }
+
+        if self.service.listeners.is_empty() {
+            errors.push("At least one listener must be configured".to_string());
+        }
+
+        for listener in &self.service.listeners {
+            if listener.address.parse::<std::net::SocketAddr>().is_err() {
+                errors.push(format!("Invalid listener address: {}", listener.address));
+            }
+        }
+
+        if self.mirror.enabled {
+            if !self.mirror.source_url.starts_with("http://")
+                && !self.mirror.source_url.starts_with("https://")
+            {
+                errors.push("Mirror source URL must be an http(s) URL".to_string());
+            }
+
+            for did in &self.mirror.dids {
+                if !did.starts_with("did:") {
+                    errors.push(format!("Invalid mirror DID: {}", did));
+                }
+            }
+        }
+
+        if self.identity.did_cache_stale_ttl > self.identity.did_cache_max_ttl {
+            errors.push("DID cache stale TTL cannot exceed the max TTL".to_string());
+        }
+
+        if !self.identity.handle_doh_url.starts_with("https://")
+            && !self.identity.handle_doh_url.starts_with("http://")
+        {
+            errors.push("Handle DNS-over-HTTPS URL must be an http(s) URL".to_string());
+        }
+
+        if let Some(token) = &self.authentication.admin_network_token {
+            if token.len() < 32 {
+                errors.push("Admin network token must be at least 32 characters".to_string());
+            }
+        }
+
+        if self.content_policy.enabled {
+            let policy = &self.content_policy;
+            for action in [
+                &policy.rate_action,
+                &policy.duplicate_action,
+                &policy.blocked_domain_action,
+                &policy.new_account_action,
+            ] {
+                if !matches!(action.as_str(), "reject" | "label" | "report") {
+                    errors.push(format!(
+                        "Invalid content policy action: {} (expected reject, label or report)",
+                        action
+                    ));
+                }
+            }
+        }
+
+        let challenge = &self.registration_challenge;
+        match challenge.provider.as_str() {
+            "none" => {}
+            "hcaptcha" | "turnstile" => {
+                if challenge.site_key.is_none() || challenge.secret_key.is_none() {
+                    errors.push(format!(
+                        "PDS_REGISTRATION_CHALLENGE_PROVIDER={} requires PDS_REGISTRATION_CHALLENGE_SITE_KEY and PDS_REGISTRATION_CHALLENGE_SECRET_KEY",
+                        challenge.provider
+                    ));
+                }
+            }
+            "pow" => {
+                if !(1..=32).contains(&challenge.pow_difficulty) {
+                    errors.push("PDS_REGISTRATION_CHALLENGE_POW_DIFFICULTY must be between 1 and 32".to_string());
+                }
+            }
+            other => errors.push(format!(
+                "Invalid registration challenge provider: {} (expected none, hcaptcha, turnstile or pow)",
+                other
+            )),
+        }
+
+        if let Some(report_service) = &self.report_service {
+            if !report_service.did.starts_with("did:") {
+                errors.push(format!(
+                    "Invalid report service DID: {}",
+                    report_service.did
+                ));
+            }
+            if let Some(url) = &report_service.url {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    errors.push("Report service URL must be an http(s) URL".to_string());
+                }
+            }
+        }
+
+        if let Some(app_view) = &self.bsky_app_view {
+            if !app_view.url.starts_with("https://") && !app_view.url.starts_with("http://") {
+                errors.push("AppView URL must be an http(s) URL".to_string());
+            }
+            if !app_view.did.starts_with("did:") {
+                errors.push(format!("Invalid AppView DID: {}", app_view.did));
+            }
+        }
+
+        if self.proxy.timeout_secs == 0 || self.proxy.connect_timeout_secs == 0 {
+            errors.push("Proxy timeouts must be at least 1 second".to_string());
+        }
+        if let Some(did) = self.proxy.allowed_dids.iter().chain(&self.proxy.denied_dids).find(|d| !d.starts_with("did:")) {
+            errors.push(format!("Invalid DID in proxy allow/deny list: {}", did));
+        }
+
+        let hashing = &self.authentication.password_hash;
+        if hashing.iterations == 0 || hashing.parallelism == 0 {
+            errors.push("Argon2 iterations and parallelism must be at least 1".to_string());
+        }
+        if hashing.memory_kib < 8 * hashing.parallelism {
+            errors.push("Argon2 memory must be at least 8 KiB per lane".to_string());
+        }
+
+        let jwt_keys = &self.authentication.jwt_keys;
+        if jwt_keys.rotation_days == Some(0) {
+            errors.push("PDS_JWT_ROTATION_DAYS must be at least 1".to_string());
+        }
+        if jwt_keys.retired_key_hours < 24 {
+            errors.push("PDS_JWT_RETIRED_KEY_HOURS must be at least 24 (the admin token lifetime)".to_string());
+        }
+
+        errors
+    }
+
+    /// Virtual host a handle or host name belongs to
+    ///
+    /// The most specific domain wins when handle domains are nested.
+    pub fn virtual_host(&self, name: &str) -> Option<&VirtualHostConfig> {
+        self.virtual_hosts
+            .iter()
+            .filter(|vhost| vhost.matches(name))
+            .max_by_key(|vhost| vhost.domain.len())
+    }
+
+    /// Whether registering `handle` needs an invite code
+    pub fn invite_required_for(&self, handle: &str) -> bool {
+        self.virtual_host(handle)
+            .and_then(|vhost| vhost.invite_required)
+            .unwrap_or(self.invites.required)
+    }
+
+    /// Whether admin routes are served only on dedicated admin listeners
+    pub fn admin_listener_only(&self) -> bool {
+        self.service
+            .listeners
+            .iter()
+            .any(|l| l.role == ListenerRole::Admin)
+    }
+}
+
+/// Parse a listener specification ("addr=role,addr=role,...")
+///
+/// A listener without an explicit role serves every route.
+pub fn parse_listeners(spec: &str) -> PdsResult<Vec<ListenerConfig>> {
+    spec.split(',')
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|entry| {
+            let (address, role) = match entry.rsplit_once('=') {
+                Some((address, role)) => (address.trim(), ListenerRole::from_str(role)?),
+                None => (entry, ListenerRole::All),
+            };
+
+            address.parse::<std::net::SocketAddr>().map_err(|_| {
+                PdsError::Validation(format!("Invalid listener address: {}", address))
+            })?;
+
+            Ok(ListenerConfig {
+                address: address.to_string(),
+                role,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_listeners() {
+        let listeners =
+            parse_listeners("0.0.0.0:2583=api, [::]:2583=api,127.0.0.1:9090=metrics").unwrap();
+        assert_eq!(listeners.len(), 3);
+        assert_eq!(listeners[1].address, "[::]:2583");
+        assert_eq!(listeners[1].role, ListenerRole::Api);
+        assert_eq!(listeners[2].role, ListenerRole::Metrics);
+    }
+
+    #[test]
+    fn test_parse_listeners_admin_role() {
+        let listeners = parse_listeners("0.0.0.0:2583=api,127.0.0.1:2584=admin").unwrap();
+        assert_eq!(listeners[1].role, ListenerRole::Admin);
+    }
+
+    #[test]
+    fn test_parse_listeners_default_role() {
+        let listeners = parse_listeners("127.0.0.1:2583").unwrap();
+        assert_eq!(listeners[0].role, ListenerRole::All);
+    }
+
+    #[test]
+    fn test_parse_listeners_invalid() {
+        assert!(parse_listeners("not-an-address=api").is_err());
+        assert!(parse_listeners("127.0.0.1:2583=bogus").is_err());
+    }
+
+    #[test]
+    fn test_proxy_permits() {
+        let mut proxy = ProxyConfig::default();
+        assert!(proxy.permits("did:web:feed.example"));
+
+        proxy.denied_dids = vec!["did:web:bad.example".to_string()];
+        assert!(!proxy.permits("did:web:bad.example"));
+
+        proxy.allowed_dids = vec!["did:web:feed.example".to_string()];
+        assert!(proxy.permits("did:web:feed.example"));
+        assert!(!proxy.permits("did:web:other.example"));
+    }
+
+    #[test]
+    fn test_body_limit_routes() {
+        let limits = BodyLimitConfig::default();
+        assert_eq!(limits.limit_for("/xrpc/com.atproto.repo.importRepo"), limits.car);
+        assert_eq!(limits.limit_for("/xrpc/com.atproto.repo.uploadBlob"), limits.blob);
+        assert_eq!(limits.limit_for("/uploads/abc123"), limits.blob);
+        assert_eq!(limits.limit_for("/xrpc/com.atproto.repo.applyWrites"), limits.json);
+        assert_eq!(limits.limit_for("/uploadsx"), limits.json);
+    }
+
+    #[test]
+    fn test_hsts_value() {
+        let mut headers = SecurityHeadersConfig::default();
+        assert_eq!(headers.hsts_value(), None);
+
+        headers.hsts_max_age_secs = Some(31536000);
+        assert_eq!(headers.hsts_value().as_deref(), Some("max-age=31536000"));
+
+        headers.hsts_include_subdomains = true;
+        assert_eq!(
+            headers.hsts_value().as_deref(),
+            Some("max-age=31536000; includeSubDomains")
+        );
+    }
+
+    #[test]
+    fn test_virtual_host_matches() {
+        let vhost = VirtualHostConfig {
+            domain: "example.com".to_string(),
+            ..Default::default()
+        };
+        assert!(vhost.matches("alice.example.com"));
+        assert!(vhost.matches("Alice.Example.com."));
+        assert!(vhost.matches("example.com"));
+        assert!(!vhost.matches("alice.badexample.com"));
+        assert!(!vhost.matches("alice.example.com.evil"));
+        assert!(!vhost.matches("com"));
+
+        assert_eq!(env_key("social.example.com"), "SOCIAL_EXAMPLE_COM");
+        assert_eq!(env_key("my-pds.test"), "MY_PDS_TEST");
     }
 }
@@ -1,19 +1,32 @@
 /// Application context and dependency injection
 use crate::{
-    account::AccountManager,
-    actor_store::{ActorStore, ActorStoreConfig},
+    account::{AccountEvents, AccountManager, InstanceStats, RegistrationChallenge, RepoWebhookManager, SignupThrottle},
+    activitypub::ActivityPubBridge,
+    actor_store::{hooks::WriteHooks, ActorStore, ActorStoreConfig, RepoIndex, TombstoneLog},
     admin::{
-        AdminRoleManager, InviteCodeManager, LabelManager, ModerationManager, ReportManager,
+        AdminRoleManager, ContentPolicy, InviteCodeManager, IpBlocklist, LabelManager,
+        ModerationManager, RateLimitOverrideManager, ReportManager, WebhookManager,
+    },
+    blob_store::{BlobBackendType, BlobStore, BlobStoreConfig, ImageVariantCache, ResumableUploadManager},
+    cache::{CacheClient, CacheConfig, CacheInvalidator},
+    clock::{random_ids, system_clock, Clock, IdGenerator},
+    config::{BlobstoreConfig, ServerConfig},
+    crypto::{
+        data_keys::{sqlcipher_available, DataKeyManager},
+        jwt_keys::JwtKeyring,
+        keys::KeyManager,
+        signing_keys::AccountSigningKeys,
     },
-    blob_store::{BlobStore, BlobStoreConfig},
-    config::ServerConfig,
     db,
     error::{PdsError, PdsResult},
     federation::{RelayClient, RelayConfig},
-    identity::{DidCache, IdentityResolver, IdentityResolverConfig},
+    identity::{DidCache, HandleVerificationManager, IdentityResolver, IdentityResolverConfig},
+    jobs::JobRegistry,
     mailer::Mailer,
-    rate_limit::{RateLimiter, RateLimitConfig},
-    sequencer::{Sequencer, SequencerConfig},
+    mirror::MirrorManager,
+    rate_limit::{RateLimiter, RateLimitConfig, ResolveRateLimitConfig, ResolveRateLimiter},
+    sequencer::{FirehoseConsumers, Sequencer, SequencerConfig},
+    takeout::TakeoutManager,
 };
 use sqlx::SqlitePool;
 use std::sync::Arc;
@@ -22,30 +35,89 @@ use std::sync::Arc;
 #[derive(Clone)]
 pub struct AppContext {
     pub config: Arc<ServerConfig>,
+    // Time and UUID/TID sources (mockable in tests)
+    pub clock: Arc<dyn Clock>,
+    pub ids: Arc<dyn IdGenerator>,
+    // Signing keys (server repo key, service auth, PLC operations)
+    pub keys: Arc<KeyManager>,
+    // Per-account commit signing keys
+    pub signing_keys: Arc<AccountSigningKeys>,
+    // Rotating JWT signing keys
+    pub jwt_keys: Arc<JwtKeyring>,
+    // Per-actor data keys for encryption at rest
+    pub data_keys: Arc<DataKeyManager>,
     pub account_db: SqlitePool,
     pub account_manager: Arc<AccountManager>,
     pub actor_store: Arc<ActorStore>,
     pub blob_store: Arc<BlobStore>,
+    pub upload_manager: Arc<ResumableUploadManager>,
+    pub image_cache: Arc<ImageVariantCache>,
     pub identity_resolver: Arc<IdentityResolver>,
+    // Redis cache layer (optional - only if CACHE_ENABLED)
+    pub cache: Option<CacheClient>,
+    pub handle_verification_manager: Arc<HandleVerificationManager>,
     // Admin & Moderation
     pub admin_role_manager: Arc<AdminRoleManager>,
     pub moderation_manager: Arc<ModerationManager>,
     pub label_manager: Arc<LabelManager>,
     pub invite_manager: Arc<InviteCodeManager>,
     pub report_manager: Arc<ReportManager>,
+    pub rate_limit_override_manager: Arc<RateLimitOverrideManager>,
+    /// IP/CIDR/ASN blocklist for createAccount and createSession
+    pub ip_blocklist: Arc<IpBlocklist>,
+    /// CAPTCHA or proof-of-work gate for open registration
+    pub registration_challenge: Arc<RegistrationChallenge>,
+    /// Hourly caps on open registration, with automatic invite-only lockdown
+    pub signup_throttle: Arc<SignupThrottle>,
+    /// User and post counts for nodeinfo, refreshed by the `instance_stats` job
+    pub instance_stats: Arc<InstanceStats>,
+    // External moderation webhooks
+    pub webhook_manager: Arc<WebhookManager>,
+    // Per-account repo event webhooks
+    pub repo_webhooks: Arc<RepoWebhookManager>,
+    // ActivityPub bridge (experimental)
+    pub activitypub: Arc<ActivityPubBridge>,
+    // Automated spam/abuse rules on record writes (reloadable)
+    pub content_policy: Arc<ContentPolicy>,
+    // Per-collection record write hooks (PDS_WRITE_HOOKS)
+    pub write_hooks: Arc<WriteHooks>,
     // Sequencer for event streaming
     pub sequencer: Arc<Sequencer>,
+    // #identity/#account events for account lifecycle changes
+    pub account_events: Arc<AccountEvents>,
+    // Connected subscribeRepos / subscribeOwnRepo clients
+    pub firehose_consumers: Arc<FirehoseConsumers>,
+    // Client for atproto-proxy forwarding (redirects are not followed)
+    pub proxy_client: reqwest::Client,
     // Relay client for federation
     pub relay_client: Option<Arc<tokio::sync::Mutex<RelayClient>>>,
     // Rate limiter
     pub rate_limiter: Arc<RateLimiter>,
+    // Per-client budgets for resolveHandle/resolveDid
+    pub resolve_limiter: Arc<ResolveRateLimiter>,
     // Email mailer
     pub mailer: Arc<Mailer>,
+    // Mirror mode (read-only copies of remote repos)
+    pub mirror_manager: Option<Arc<MirrorManager>>,
+    // Account data exports
+    pub takeout_manager: Arc<TakeoutManager>,
+    // Background job schedules and run history
+    pub jobs: Arc<JobRegistry>,
 }
 
 impl AppContext {
     /// Create a new application context from configuration
     pub async fn new(config: ServerConfig) -> PdsResult<Self> {
+        Self::new_with_clock(config, system_clock(), random_ids()).await
+    }
+
+    /// Create an application context that takes time and identifiers from
+    /// `clock` and `ids`
+    pub async fn new_with_clock(
+        config: ServerConfig,
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+    ) -> PdsResult<Self> {
         // Validate configuration
         config.validate()?;
 
@@ -56,43 +128,216 @@ impl AppContext {
         let account_db = db::create_pool(&config.storage.account_db, db::DatabaseOptions::default()).await?;
 
         // NOTE: Database schema is set up by install.sh during installation
-        // We do NOT run migrations at startup to avoid checksum mismatches
+        // We do NOT run migrations at startup to avoid checksum mismatches;
+        // `aurora-locus migrate upgrade` applies them with a backup
 
 
         // Test connection
         db::test_connection(&account_db).await?;
 
+        match db::migrations::database_status(
+            "account",
+            &config.storage.account_db,
+            &account_db,
+            &db::migrations::MIGRATOR,
+        )
+        .await
+        {
+            Ok(status) if !status.pending.is_empty() => tracing::warn!(
+                "The account database has {} pending migration(s); stop the server and run `aurora-locus migrate upgrade`",
+                status.pending.len()
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to check database migrations: {}", e),
+        }
+
+        // Open the signing key backend; fails fast if the server keys are unusable
+        let keys = Arc::new(KeyManager::from_config(&config).await?);
+
+        // JWT signing keys; without the key table, tokens stay signed with
+        // PDS_JWT_SECRET
+        let jwt_keys = Arc::new(
+            JwtKeyring::new(&config.authentication.jwt_secret, config.authentication.jwt_keys.clone())
+                .with_db(account_db.clone())
+                .with_clock(clock.clone()),
+        );
+        if let Err(e) = jwt_keys.load().await {
+            tracing::warn!("Failed to load JWT signing keys: {}", e);
+        }
+
         // Initialize account manager
-        let account_manager = Arc::new(AccountManager::new(account_db.clone(), Arc::new(config.clone())));
+        let account_manager = Arc::new(
+            AccountManager::new(account_db.clone(), Arc::new(config.clone()))
+                .with_keys(keys.clone())
+                .with_jwt_keys(jwt_keys.clone())
+                .with_clock(clock.clone())
+                .with_ids(ids.clone()),
+        );
+        let signing_keys = account_manager.signing_keys().clone();
+
+        // Load the reserved handle list enforced by handle validation
+        match account_manager.reserved_handles().reload().await {
+            Ok(count) => tracing::info!("Loaded {} reserved handle pattern(s)", count),
+            Err(e) => tracing::warn!("Failed to load reserved handles: {}", e),
+        }
 
         // Initialize actor store
         let actor_store_config = ActorStoreConfig {
             base_directory: config.storage.actor_store_directory.clone(),
             cache_size: 100,
         };
-        let actor_store = Arc::new(ActorStore::new(actor_store_config));
+        // Encrypted stores and blobs are always readable; the flags only
+        // decide whether new data is encrypted
+        let data_keys = Arc::new(DataKeyManager::new(
+            keys.clone(),
+            &config.encryption.master_key_id,
+            config.storage.actor_store_directory.clone(),
+        ));
+        if config.encryption.actor_stores && !sqlcipher_available().await? {
+            return Err(PdsError::Validation(
+                "PDS_ENCRYPTION_ACTOR_STORES requires a build with the `sqlcipher` feature".to_string(),
+            ));
+        }
+        let mut actor_store = ActorStore::new(actor_store_config)
+            .with_repo_index(RepoIndex::new(account_db.clone()))
+            .with_encryption(data_keys.clone(), config.encryption.actor_stores)
+            .with_ids(ids.clone());
+        if config.logging.tombstone_retention_days.is_some() {
+            actor_store = actor_store.with_tombstone_log(TombstoneLog::new(account_db.clone()));
+        }
+        if config.record_history.enabled {
+            actor_store = actor_store.with_record_history(config.record_history.depth);
+        }
+        let actor_store = Arc::new(actor_store);
+
+        // Index repositories created before the repo head index existed
+        match actor_store.backfill_repo_index().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Indexed {} existing repository head(s)", count),
+            Err(e) => tracing::warn!("Failed to backfill repo head index: {}", e),
+        }
 
         // Initialize blob store
-        let blob_store_config = BlobStoreConfig::default();
-        let blob_store = Arc::new(BlobStore::new(blob_store_config, account_db.clone())?);
+        let mut blob_store_config = BlobStoreConfig::default();
+        blob_store_config.storage.max_blob_size = config.service.blob_upload_limit;
+        blob_store_config.storage.max_audio_size = config.service.blob_audio_upload_limit;
+        blob_store_config.storage.account_quota = config.service.blob_account_quota;
+        blob_store_config.storage.animated_thumbnails = config.service.blob_animated_thumbnails;
+        blob_store_config.storage.temp_ttl_hours = config.service.blob_temp_ttl_hours;
+        blob_store_config.storage.max_staged_per_account = config.service.blob_max_staged_per_account;
+        if config.service.blob_animated_thumbnails && !cfg!(feature = "animated-thumbnails") {
+            tracing::warn!("PDS_BLOB_ANIMATED_THUMBNAILS needs the animated-thumbnails feature; animated images get still thumbnails");
+        }
+        if let BlobstoreConfig::Disk { location, tmp_location } = &config.storage.blobstore {
+            blob_store_config.storage.backend = BlobBackendType::Disk { location: location.clone() };
+            blob_store_config.storage.temp_dir = tmp_location.clone();
+        }
+        let blob_store = Arc::new(
+            BlobStore::new(blob_store_config, account_db.clone())?
+                .with_encryption(data_keys.clone(), config.encryption.blobs)
+                .with_clock(clock.clone()),
+        );
+
+        // Resumable uploads assemble chunks next to the temp blobs
+        let upload_manager = Arc::new(
+            ResumableUploadManager::new(
+                account_db.clone(),
+                config.storage.data_directory.join("uploads"),
+                blob_store.clone(),
+            )
+            .with_clock(clock.clone(), ids.clone()),
+        );
+
+        // Initialize Redis cache layer (optional - falls back to SQLite-only caching)
+        let cache_config = CacheConfig::from_env();
+        let cache = if cache_config.enabled {
+            match CacheClient::new(cache_config).await {
+                Ok(client) => Some(client),
+                Err(e) => {
+                    tracing::warn!("Redis cache unavailable, continuing without it: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        // Resized image variants for /img
+        let image_cache = Arc::new(ImageVariantCache::new(
+            config.storage.data_directory.join("image_cache"),
+            cache.clone(),
+        ));
 
         // Initialize identity resolver
         // Note: Using account_db for now; could be separate database in future
-        let did_cache = DidCache::new(account_db.clone());
-        let identity_config = IdentityResolverConfig::default();
+        // DID documents are served until the max TTL and refreshed after the stale TTL
+        let failover = &config.identity.plc_failover;
+        let mut did_cache = DidCache::new(account_db.clone()).with_ttls(
+            chrono::Duration::seconds(config.identity.did_cache_max_ttl as i64),
+            chrono::Duration::minutes(5),
+        );
+        if failover.offline_mode {
+            did_cache = did_cache.with_offline_retention(chrono::Duration::seconds(failover.offline_max_age as i64));
+        }
+        let identity_config = IdentityResolverConfig {
+            doh_url: config.identity.handle_doh_url.clone(),
+            did_stale_ttl: config.identity.did_cache_stale_ttl,
+            plc_urls: std::iter::once(config.identity.did_plc_url.clone())
+                .chain(failover.mirrors.iter().cloned())
+                .collect(),
+            offline_mode: failover.offline_mode,
+            ..IdentityResolverConfig::default()
+        };
         let identity_resolver = Arc::new(
-            IdentityResolver::new(did_cache, identity_config)?
+            IdentityResolver::new(did_cache, identity_config)?.with_redis(cache.clone())
         );
+        let handle_verification_manager = Arc::new(HandleVerificationManager::new(account_db.clone()));
 
         // Initialize admin & moderation managers
+        let webhook_manager = Arc::new(WebhookManager::new(account_db.clone()));
         let admin_role_manager = Arc::new(AdminRoleManager::new(account_db.clone()));
-        let moderation_manager = Arc::new(ModerationManager::new(account_db.clone()));
-        let label_manager = Arc::new(LabelManager::new(
-            account_db.clone(),
-            config.service.service_did.clone(),
-        ));
+        let moderation_manager = Arc::new(
+            ModerationManager::new(account_db.clone()).with_webhooks(webhook_manager.clone()),
+        );
+        let label_manager = Arc::new(
+            LabelManager::new(account_db.clone(), config.service.service_did.clone())
+                .with_webhooks(webhook_manager.clone()),
+        );
         let invite_manager = Arc::new(InviteCodeManager::new(account_db.clone()));
-        let report_manager = Arc::new(ReportManager::new(account_db.clone()));
+        let report_manager = Arc::new(
+            ReportManager::new(account_db.clone()).with_webhooks(webhook_manager.clone()),
+        );
+        let rate_limit_override_manager = Arc::new(RateLimitOverrideManager::new(account_db.clone()));
+        let ip_blocklist = Arc::new(
+            IpBlocklist::new(account_db.clone())
+                .with_asn_header(std::env::var("PDS_BLOCKLIST_ASN_HEADER").ok()),
+        );
+        let registration_challenge = Arc::new(RegistrationChallenge::from_config(
+            &config.registration_challenge,
+            &config.authentication.jwt_secret,
+            account_manager.clone(),
+        )?);
+        let signup_throttle = Arc::new(
+            SignupThrottle::new(&config.signup_throttle)
+                .with_clock(clock.clone())
+                .with_webhooks(webhook_manager.clone()),
+        );
+        let instance_stats = Arc::new(InstanceStats::new(account_db.clone()).with_clock(clock.clone()));
+
+        // Initialize content policy (no rules unless PDS_CONTENT_POLICY_ENABLED;
+        // always built so a config reload can turn it on)
+        let content_policy = Arc::new(ContentPolicy::from_config(
+            &config.content_policy,
+            account_manager.clone(),
+            label_manager.clone(),
+            report_manager.clone(),
+            config.service.service_did.clone(),
+        )?);
+        let write_hooks = Arc::new(WriteHooks::from_config(
+            &config.write_hooks,
+            label_manager.clone(),
+            config.service.service_did.clone(),
+        )?);
 
         // Initialize relay client first (optional - only if relay servers configured and federation enabled)
         let relay_client = if config.federation.enabled && !config.federation.relay_urls.is_empty() {
@@ -111,34 +356,157 @@ impl AppContext {
         };
 
         // Initialize sequencer with relay client (using account_db for now, could be separate database)
-        let sequencer = Arc::new(Sequencer::with_relay(
+        let sequencer = Arc::new(
+            Sequencer::with_relay(account_db.clone(), SequencerConfig::from_env(), relay_client.clone())
+                .with_clock(clock.clone()),
+        );
+
+        let account_events = Arc::new(AccountEvents::new(
             account_db.clone(),
-            SequencerConfig::default(),
-            relay_client.clone()
+            sequencer.clone(),
+            moderation_manager.clone(),
         ));
 
+        let firehose_consumers = Arc::new(FirehoseConsumers::new().with_clock(clock.clone()));
+
+        // Drop cached identity, session and repo data as identity and account events are sequenced
+        CacheInvalidator::new(identity_resolver.clone(), cache.clone())
+            .spawn(sequencer.subscribe_identity(), sequencer.subscribe_account());
+
+        // Queue repo webhook events as commits are sequenced
+        let repo_webhooks = Arc::new(RepoWebhookManager::new(account_db.clone(), config.repo_webhooks.clone()));
+        if config.repo_webhooks.enabled {
+            repo_webhooks.clone().spawn(actor_store.clone(), sequencer.subscribe_commits());
+        }
+
+        // Translate bridged accounts' posts as commits are sequenced
+        let public_url = match &config.federation.public_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("http://{}:{}", config.service.hostname, config.service.port),
+        };
+        let activitypub = ActivityPubBridge::new(
+            account_db.clone(),
+            config.activitypub.clone(),
+            public_url,
+            config.service.hostname.clone(),
+            &config.authentication.jwt_secret,
+        )
+        .with_keys(
+            keys.clone(),
+            config.encryption.enabled().then_some(config.encryption.master_key_id.as_str()),
+        );
+        match activitypub.seal_plaintext_keys().await {
+            Ok(0) => {}
+            Ok(count) => tracing::info!("Sealed {} plaintext ActivityPub actor key(s)", count),
+            Err(e) => tracing::warn!("Failed to seal ActivityPub actor keys: {}", e),
+        }
+        let activitypub = Arc::new(activitypub);
+        if config.activitypub.enabled {
+            activitypub.clone().spawn(actor_store.clone(), sequencer.subscribe_commits());
+        }
+
         // Initialize rate limiter
-        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig::default()));
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig::from_env()));
+
+        // Apply persisted per-account overrides
+        match rate_limit_override_manager.list_overrides().await {
+            Ok(overrides) => {
+                for entry in &overrides {
+                    rate_limiter.set_account_override(entry);
+                }
+                if !overrides.is_empty() {
+                    tracing::info!("Loaded {} rate limit override(s)", overrides.len());
+                }
+            }
+            Err(e) => tracing::warn!("Failed to load rate limit overrides: {}", e),
+        }
+
+        let resolve_limiter = Arc::new(ResolveRateLimiter::new(
+            ResolveRateLimitConfig::from_env(),
+            cache.clone(),
+        ));
 
         // Initialize mailer
-        let mailer = Arc::new(Mailer::new(config.email.clone())?);
+        let mailer = Arc::new(Mailer::new(config.email.clone(), account_db.clone())?);
+
+        // Initialize mirror manager (optional - only if mirror mode enabled)
+        let mirror_manager = if config.mirror.enabled {
+            tracing::info!(
+                "Mirror mode enabled: {} repo(s) from {}",
+                config.mirror.dids.len(),
+                config.mirror.source_url
+            );
+            Some(Arc::new(MirrorManager::new(
+                account_db.clone(),
+                actor_store.clone(),
+                identity_resolver.clone(),
+                config.mirror.clone(),
+            )))
+        } else {
+            None
+        };
+
+        // Initialize account export manager
+        let takeout_manager = Arc::new(TakeoutManager::new(
+            account_db.clone(),
+            config.storage.data_directory.join("exports"),
+            config.authentication.jwt_secret.clone(),
+        ));
+
+        // Job registry (the scheduler itself is started by main)
+        let jobs = Arc::new(JobRegistry::new(account_db.clone(), &config.jobs)?);
+
+        // Shared by every proxied request; a redirect could otherwise lead
+        // past the target checks
+        let proxy_client = reqwest::Client::builder()
+            .connect_timeout(std::time::Duration::from_secs(config.proxy.connect_timeout_secs))
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| PdsError::Internal(format!("Failed to build HTTP client: {}", e)))?;
 
         Ok(Self {
             config: Arc::new(config),
+            clock,
+            ids,
+            keys,
+            signing_keys,
+            jwt_keys,
+            data_keys,
             account_db,
             account_manager,
             actor_store,
             blob_store,
+            upload_manager,
+            image_cache,
             identity_resolver,
+            cache,
+            handle_verification_manager,
             admin_role_manager,
             moderation_manager,
             label_manager,
             invite_manager,
             report_manager,
+            rate_limit_override_manager,
+            ip_blocklist,
+            registration_challenge,
+            signup_throttle,
+            instance_stats,
+            webhook_manager,
+            repo_webhooks,
+            activitypub,
+            content_policy,
+            write_hooks,
             sequencer,
+            account_events,
+            firehose_consumers,
+            proxy_client,
             relay_client,
             rate_limiter,
+            resolve_limiter,
             mailer,
+            mirror_manager,
+            takeout_manager,
+            jobs,
         })
     }
 
@@ -178,6 +546,15 @@ impl AppContext {
         )
     }
 
+    /// URL this server is reached at from the internet (`PDS_PUBLIC_URL`),
+    /// falling back to the service URL
+    pub fn public_url(&self) -> String {
+        match &self.config.federation.public_url {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => self.service_url(),
+        }
+    }
+
     /// Get service DID
     pub fn service_did(&self) -> &str {
         &self.config.service.service_did
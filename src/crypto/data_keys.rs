@@ -0,0 +1,412 @@
+/// Encryption at rest
+///
+/// Every actor gets a random 256-bit data encryption key (DEK), kept in the
+/// actor's `key` file wrapped by the server master key from the key backend.
+/// The DEK is the SQLCipher key of an encrypted actor store and seals the
+/// contents of blobs the actor uploads (XChaCha20-Poly1305, bound to the
+/// blob CID).
+///
+/// Encrypted and plaintext data coexist: actor stores are recognised by
+/// their file header and sealed blobs by a magic prefix, so enabling
+/// encryption only affects new data until `aurora-locus encryption encrypt`
+/// is run. During a DEK rotation the key file holds both the new and the
+/// previous key, and reads fall back to the previous one.
+///
+/// Removing an actor's directory destroys its DEK, and with it every
+/// encrypted blob the actor created.
+use crate::{
+    actor_store::get_actor_location,
+    crypto::keys::{cipher_for, open, seal, KeyManager},
+    error::{PdsError, PdsResult},
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Prefix of sealed blob contents
+const BLOB_MAGIC: &[u8] = b"ALENC\x01";
+
+/// Header of a plaintext SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// A data encryption key
+#[derive(Clone)]
+pub struct DataKey([u8; 32]);
+
+impl DataKey {
+    fn generate() -> Self {
+        let mut key = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut key);
+        Self(key)
+    }
+
+    fn from_slice(bytes: &[u8]) -> PdsResult<Self> {
+        let key: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| PdsError::Validation("Data key must be 32 bytes".to_string()))?;
+        Ok(Self(key))
+    }
+
+    /// Value for `PRAGMA key` / `ATTACH ... KEY` (a raw key, no KDF)
+    pub fn sqlcipher_key(&self) -> String {
+        format!("\"x'{}'\"", hex::encode(self.0))
+    }
+
+    fn seal_blob(&self, cid: &str, data: &[u8]) -> PdsResult<Vec<u8>> {
+        let mut sealed = BLOB_MAGIC.to_vec();
+        sealed.extend(seal(&cipher_for(&self.0), data, cid.as_bytes())?);
+        Ok(sealed)
+    }
+
+    fn open_blob(&self, cid: &str, data: &[u8]) -> PdsResult<Vec<u8>> {
+        open(&cipher_for(&self.0), &data[BLOB_MAGIC.len()..], cid.as_bytes())
+    }
+}
+
+impl std::fmt::Debug for DataKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DataKey(..)")
+    }
+}
+
+/// An actor's current DEK and, mid-rotation, the one it replaces
+#[derive(Debug, Clone)]
+pub struct ActorKeys {
+    pub current: DataKey,
+    pub previous: Option<DataKey>,
+}
+
+impl ActorKeys {
+    /// Current key first, then the previous one
+    pub fn candidates(&self) -> impl Iterator<Item = &DataKey> {
+        std::iter::once(&self.current).chain(self.previous.as_ref())
+    }
+}
+
+/// Contents of an actor's `key` file
+#[derive(Debug, Serialize, Deserialize)]
+struct KeyFile {
+    version: u32,
+    /// Master key the DEKs are wrapped with
+    master_key_id: String,
+    /// Wrapped current DEK (base64)
+    current: String,
+    /// Wrapped previous DEK while a rotation is in progress
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<String>,
+}
+
+/// Whether blob contents were sealed with a DEK
+pub fn is_sealed_blob(data: &[u8]) -> bool {
+    data.starts_with(BLOB_MAGIC)
+}
+
+/// Whether a database file is a plaintext SQLite database
+pub fn is_plaintext_db(path: &Path) -> PdsResult<bool> {
+    use std::io::Read;
+
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path)?;
+    match file.read_exact(&mut header) {
+        Ok(()) => Ok(header == SQLITE_HEADER),
+        // Empty or truncated files are fresh plaintext databases
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Whether SQLite was built with SQLCipher (the `sqlcipher` feature)
+pub async fn sqlcipher_available() -> PdsResult<bool> {
+    let pool = sqlx::SqlitePool::connect("sqlite::memory:").await?;
+    let version: Option<String> = sqlx::query_scalar("PRAGMA cipher_version")
+        .fetch_optional(&pool)
+        .await?;
+    pool.close().await;
+    Ok(version.is_some())
+}
+
+/// Per-actor data keys, wrapped by the master key
+pub struct DataKeyManager {
+    keys: Arc<KeyManager>,
+    master_key_id: String,
+    actor_directory: PathBuf,
+    cache: RwLock<HashMap<String, ActorKeys>>,
+    // Serializes key creation so concurrent writers agree on one DEK
+    create_lock: tokio::sync::Mutex<()>,
+}
+
+impl DataKeyManager {
+    pub fn new(keys: Arc<KeyManager>, master_key_id: &str, actor_directory: PathBuf) -> Self {
+        Self {
+            keys,
+            master_key_id: master_key_id.to_string(),
+            actor_directory,
+            cache: RwLock::new(HashMap::new()),
+            create_lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Master key new DEKs are wrapped with
+    pub fn master_key_id(&self) -> &str {
+        &self.master_key_id
+    }
+
+    fn key_path(&self, did: &str) -> PathBuf {
+        get_actor_location(&self.actor_directory, did).key_location
+    }
+
+    /// Wrapping context binding a DEK to its actor
+    fn context(did: &str) -> String {
+        format!("aurora-locus:actor-key:{}", did)
+    }
+
+    /// Master key the actor's DEKs are currently wrapped with
+    pub async fn wrapping_key_id(&self, did: &str) -> PdsResult<Option<String>> {
+        Ok(self.read_file(did).await?.map(|file| file.master_key_id))
+    }
+
+    /// The actor's keys, if it has any
+    pub async fn actor_keys(&self, did: &str) -> PdsResult<Option<ActorKeys>> {
+        if let Some(keys) = self.cache.read().unwrap().get(did) {
+            return Ok(Some(keys.clone()));
+        }
+
+        let file = match self.read_file(did).await? {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+        let keys = ActorKeys {
+            current: self.unwrap(did, &file.master_key_id, &file.current).await?,
+            previous: match &file.previous {
+                Some(wrapped) => Some(self.unwrap(did, &file.master_key_id, wrapped).await?),
+                None => None,
+            },
+        };
+
+        self.cache.write().unwrap().insert(did.to_string(), keys.clone());
+        Ok(Some(keys))
+    }
+
+    /// The actor's keys, creating a DEK if it has none
+    pub async fn get_or_create(&self, did: &str) -> PdsResult<ActorKeys> {
+        if let Some(keys) = self.actor_keys(did).await? {
+            return Ok(keys);
+        }
+
+        let _guard = self.create_lock.lock().await;
+        if let Some(keys) = self.actor_keys(did).await? {
+            return Ok(keys);
+        }
+
+        let keys = ActorKeys {
+            current: DataKey::generate(),
+            previous: None,
+        };
+        self.write_keys(did, &keys).await?;
+        Ok(keys)
+    }
+
+    /// Re-wrap an actor's DEKs under the configured master key
+    ///
+    /// Returns `false` if they already were. The previous master key must
+    /// still be available in the backend.
+    pub async fn rewrap(&self, did: &str) -> PdsResult<bool> {
+        let file = match self.read_file(did).await? {
+            Some(file) => file,
+            None => return Ok(false),
+        };
+        if file.master_key_id == self.master_key_id {
+            return Ok(false);
+        }
+
+        let keys = self
+            .actor_keys(did)
+            .await?
+            .ok_or_else(|| PdsError::NotFound(format!("No data key for {}", did)))?;
+        self.write_keys(did, &keys).await?;
+        Ok(true)
+    }
+
+    /// Start a DEK rotation: a new current key, keeping the old one as previous
+    ///
+    /// Resumes an interrupted rotation instead of starting another.
+    pub async fn begin_rotation(&self, did: &str) -> PdsResult<ActorKeys> {
+        let old = self.get_or_create(did).await?;
+        if old.previous.is_some() {
+            return Ok(old);
+        }
+
+        let keys = ActorKeys {
+            current: DataKey::generate(),
+            previous: Some(old.current),
+        };
+        self.write_keys(did, &keys).await?;
+        Ok(keys)
+    }
+
+    /// Drop the previous DEK once everything is encrypted with the new one
+    pub async fn finish_rotation(&self, did: &str) -> PdsResult<()> {
+        if let Some(keys) = self.actor_keys(did).await? {
+            if keys.previous.is_some() {
+                let keys = ActorKeys {
+                    current: keys.current,
+                    previous: None,
+                };
+                self.write_keys(did, &keys).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Drop cached keys (after the actor's directory is removed)
+    pub fn forget(&self, did: &str) {
+        self.cache.write().unwrap().remove(did);
+    }
+
+    /// Seal blob contents with the actor's DEK
+    pub async fn seal_blob(&self, did: &str, cid: &str, data: &[u8]) -> PdsResult<Vec<u8>> {
+        self.get_or_create(did).await?.current.seal_blob(cid, data)
+    }
+
+    /// Decrypt blob contents if they were sealed; plaintext passes through
+    pub async fn open_blob(&self, did: &str, cid: &str, data: Vec<u8>) -> PdsResult<Vec<u8>> {
+        if !is_sealed_blob(&data) {
+            return Ok(data);
+        }
+
+        let keys = self
+            .actor_keys(did)
+            .await?
+            .ok_or_else(|| PdsError::BlobBackend(format!("No data key to decrypt blob {}", cid)))?;
+        Self::open_with(&keys, cid, &data)
+    }
+
+    /// Decrypt sealed blob contents with any of the given keys
+    pub fn open_with(keys: &ActorKeys, cid: &str, data: &[u8]) -> PdsResult<Vec<u8>> {
+        let mut last_err = None;
+        for key in keys.candidates() {
+            match key.open_blob(cid, data) {
+                Ok(plaintext) => return Ok(plaintext),
+                Err(e) => last_err = Some(e),
+            }
+        }
+        Err(last_err.unwrap_or_else(|| PdsError::BlobBackend(format!("Cannot decrypt blob {}", cid))))
+    }
+
+    /// Seal blob contents with a specific key (used when re-encrypting)
+    pub fn seal_with(key: &DataKey, cid: &str, data: &[u8]) -> PdsResult<Vec<u8>> {
+        key.seal_blob(cid, data)
+    }
+
+    async fn unwrap(&self, did: &str, master_key_id: &str, wrapped: &str) -> PdsResult<DataKey> {
+        let wrapped = STANDARD
+            .decode(wrapped)
+            .map_err(|_| PdsError::Validation(format!("Corrupt key file for {}", did)))?;
+        let key = self
+            .keys
+            .unwrap_data_key(master_key_id, &wrapped, &Self::context(did))
+            .await?;
+        DataKey::from_slice(&key)
+    }
+
+    async fn wrap(&self, did: &str, key: &DataKey) -> PdsResult<String> {
+        let wrapped = self
+            .keys
+            .wrap_data_key(&self.master_key_id, &key.0, &Self::context(did))
+            .await?;
+        Ok(STANDARD.encode(wrapped))
+    }
+
+    async fn read_file(&self, did: &str) -> PdsResult<Option<KeyFile>> {
+        let bytes = match tokio::fs::read(self.key_path(did)).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| PdsError::Validation(format!("Corrupt key file for {}: {}", did, e)))
+    }
+
+    /// Wrap and write an actor's keys atomically
+    async fn write_keys(&self, did: &str, keys: &ActorKeys) -> PdsResult<()> {
+        let file = KeyFile {
+            version: 1,
+            master_key_id: self.master_key_id.clone(),
+            current: self.wrap(did, &keys.current).await?,
+            previous: match &keys.previous {
+                Some(key) => Some(self.wrap(did, key).await?),
+                None => None,
+            },
+        };
+        let bytes = serde_json::to_vec_pretty(&file)
+            .map_err(|e| PdsError::Internal(format!("Failed to encode key file: {}", e)))?;
+
+        let path = self.key_path(did);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        tokio::fs::rename(&tmp, &path).await?;
+
+        self.cache.write().unwrap().insert(did.to_string(), keys.clone());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manager(name: &str, master_key_id: &str) -> DataKeyManager {
+        let master = hex::encode([9u8; 32]);
+        let other = hex::encode([8u8; 32]);
+        let provider = crate::crypto::keys::ConfigKeys::new([
+            ("repo", master.as_str()),
+            ("master", master.as_str()),
+            ("master-2", other.as_str()),
+        ]);
+        let keys = KeyManager::new(Arc::new(provider), "repo", "repo");
+        let dir = PathBuf::from(format!("./test_data/data_keys/{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        DataKeyManager::new(Arc::new(keys), master_key_id, dir)
+    }
+
+    #[tokio::test]
+    async fn test_blob_sealing_and_rotation() {
+        let did = "did:plc:sealer";
+        let keys = manager("rotation", "master");
+
+        let sealed = keys.seal_blob(did, "bafyblob", b"image bytes").await.unwrap();
+        assert!(is_sealed_blob(&sealed));
+        assert_eq!(keys.open_blob(did, "bafyblob", sealed.clone()).await.unwrap(), b"image bytes");
+        // Bound to the CID, and plaintext passes through untouched
+        assert!(keys.open_blob(did, "bafyother", sealed.clone()).await.is_err());
+        assert_eq!(keys.open_blob(did, "bafyplain", b"plain".to_vec()).await.unwrap(), b"plain");
+
+        // Mid-rotation, data under the previous key still opens
+        let rotated = keys.begin_rotation(did).await.unwrap();
+        assert!(rotated.previous.is_some());
+        assert_eq!(keys.open_blob(did, "bafyblob", sealed.clone()).await.unwrap(), b"image bytes");
+        keys.finish_rotation(did).await.unwrap();
+        assert!(keys.open_blob(did, "bafyblob", sealed).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rewrap_under_new_master() {
+        let did = "did:plc:rewrap";
+        let first = manager("rewrap", "master");
+        let sealed = first.seal_blob(did, "bafyblob", b"data").await.unwrap();
+
+        let second = DataKeyManager::new(first.keys.clone(), "master-2", first.actor_directory.clone());
+        assert!(second.rewrap(did).await.unwrap());
+        assert!(!second.rewrap(did).await.unwrap());
+
+        let reloaded = DataKeyManager::new(first.keys.clone(), "master-2", first.actor_directory.clone());
+        assert_eq!(reloaded.open_blob(did, "bafyblob", sealed).await.unwrap(), b"data");
+    }
+}
@@ -0,0 +1,502 @@
+/// JWT signing keys
+///
+/// Access, refresh and admin tokens are signed with the newest key and carry
+/// its id in the `kid` header. A rotation creates a new key and retires the
+/// previous one, which keeps validating tokens for
+/// `PDS_JWT_RETIRED_KEY_HOURS` and is deleted after that. Keys are random
+/// HS256 secrets or, with `PDS_JWT_ALGORITHM=ES256`, P-256 key pairs whose
+/// public half other services can verify tokens with.
+///
+/// Keys are kept in the `jwt_signing_key` table, sealed with a key derived
+/// from `PDS_JWT_SECRET`. The configured secret itself is the `legacy` key:
+/// it signed every token issued before keys had ids, validates tokens
+/// without a `kid`, and is retired like any other key when the first
+/// generated key replaces it.
+use crate::{
+    clock::{system_clock, Clock},
+    config::{JwtAlgorithm, JwtKeysConfig},
+    crypto::keys::{cipher_for, open, seal},
+    error::{PdsError, PdsResult},
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chacha20poly1305::XChaCha20Poly1305;
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation};
+use p256::{
+    elliptic_curve::sec1::ToEncodedPoint,
+    pkcs8::{DecodePrivateKey, EncodePrivateKey},
+};
+use rand::RngCore;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use sha2::Sha256;
+use sqlx::{Row, SqlitePool};
+use std::sync::{Arc, RwLock};
+
+/// Key id of the configured `PDS_JWT_SECRET`
+pub const LEGACY_KID: &str = "legacy";
+
+/// Allowed clock skew when checking token expiry
+const LEEWAY_SECS: u64 = 300;
+
+impl From<JwtAlgorithm> for Algorithm {
+    fn from(algorithm: JwtAlgorithm) -> Self {
+        match algorithm {
+            JwtAlgorithm::Hs256 => Algorithm::HS256,
+            JwtAlgorithm::Es256 => Algorithm::ES256,
+        }
+    }
+}
+
+struct JwtKey {
+    kid: String,
+    algorithm: JwtAlgorithm,
+    encoding: EncodingKey,
+    decoding: DecodingKey,
+    /// Public JWK of an ES256 key
+    public_jwk: Option<serde_json::Value>,
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+}
+
+/// A signing key, as reported to admins
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JwtKeyInfo {
+    pub kid: String,
+    #[serde(serialize_with = "serialize_algorithm")]
+    pub algorithm: JwtAlgorithm,
+    /// Whether new tokens are signed with this key
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retired_at: Option<DateTime<Utc>>,
+    /// When a retired key stops validating tokens
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn serialize_algorithm<S: Serializer>(algorithm: &JwtAlgorithm, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(algorithm.as_str())
+}
+
+/// Signing and validation keys for the server's JWTs
+pub struct JwtKeyring {
+    db: Option<SqlitePool>,
+    legacy_secret: Vec<u8>,
+    cipher: XChaCha20Poly1305,
+    config: JwtKeysConfig,
+    /// The signing key first, then retired keys, newest first
+    keys: RwLock<Vec<JwtKey>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl JwtKeyring {
+    /// Keyring holding only the configured secret, which signs tokens
+    /// without a `kid` until keys are loaded from a database
+    pub fn new(secret: &str, config: JwtKeysConfig) -> Self {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(b"aurora-locus jwt signing keys");
+
+        let clock = system_clock();
+        let legacy = legacy_key(secret.as_bytes(), clock.now(), None);
+        Self {
+            db: None,
+            legacy_secret: secret.as_bytes().to_vec(),
+            cipher: cipher_for(&mac.finalize().into_bytes().into()),
+            config,
+            keys: RwLock::new(vec![legacy]),
+            clock,
+        }
+    }
+
+    /// Keep keys in `db`; they are used once `load`ed
+    pub fn with_db(mut self, db: SqlitePool) -> Self {
+        self.db = Some(db);
+        self
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Read the stored keys, creating a signing key on first start or when
+    /// `PDS_JWT_ALGORITHM` no longer matches the current one
+    pub async fn load(&self) -> PdsResult<()> {
+        let Some(db) = &self.db else {
+            return Ok(());
+        };
+
+        // The configured secret signed every token issued before key ids
+        sqlx::query(
+            "INSERT INTO jwt_signing_key (kid, algorithm, key, created_at) VALUES (?1, ?2, NULL, ?3)
+             ON CONFLICT (kid) DO NOTHING",
+        )
+        .bind(LEGACY_KID)
+        .bind(JwtAlgorithm::Hs256.as_str())
+        .bind(self.clock.now())
+        .execute(db)
+        .await?;
+
+        self.reload(db).await?;
+
+        let needs_key = match self.keys.read().unwrap().first() {
+            Some(key) => key.retired_at.is_some() || key.kid == LEGACY_KID || key.algorithm != self.config.algorithm,
+            None => true,
+        };
+        if needs_key {
+            self.rotate().await?;
+        }
+        Ok(())
+    }
+
+    async fn reload(&self, db: &SqlitePool) -> PdsResult<()> {
+        let rows = sqlx::query(
+            "SELECT kid, algorithm, key, created_at, retired_at FROM jwt_signing_key
+             ORDER BY retired_at IS NOT NULL, created_at DESC",
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut keys = Vec::with_capacity(rows.len());
+        for row in rows {
+            let kid: String = row.try_get("kid")?;
+            let created_at: DateTime<Utc> = row.try_get("created_at")?;
+            let retired_at: Option<DateTime<Utc>> = row.try_get("retired_at")?;
+            let sealed: Option<Vec<u8>> = row.try_get("key")?;
+
+            let key = match sealed {
+                None => legacy_key(&self.legacy_secret, created_at, retired_at),
+                Some(sealed) => {
+                    let material = open(&self.cipher, &sealed, kid.as_bytes()).map_err(|_| {
+                        PdsError::Internal(format!("JWT signing key {} can't be opened with PDS_JWT_SECRET", kid))
+                    })?;
+                    let algorithm = match row.try_get::<String, _>("algorithm")?.as_str() {
+                        "HS256" => JwtAlgorithm::Hs256,
+                        "ES256" => JwtAlgorithm::Es256,
+                        other => {
+                            return Err(PdsError::Internal(format!(
+                                "JWT signing key {} has unknown algorithm {}",
+                                kid, other
+                            )))
+                        }
+                    };
+                    build_key(kid, algorithm, &material, created_at, retired_at)?
+                }
+            };
+            keys.push(key);
+        }
+
+        *self.keys.write().unwrap() = keys;
+        Ok(())
+    }
+
+    /// Create a new signing key and retire the current one
+    ///
+    /// Returns the new key's id.
+    pub async fn rotate(&self) -> PdsResult<String> {
+        let db = self
+            .db
+            .as_ref()
+            .ok_or_else(|| PdsError::Internal("JWT key rotation needs a database".to_string()))?;
+
+        let now = self.clock.now();
+        let mut id = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut id);
+        let kid = format!("{}-{}", now.format("%Y%m%d"), hex::encode(id));
+
+        let material = match self.config.algorithm {
+            JwtAlgorithm::Hs256 => {
+                let mut secret = vec![0u8; 64];
+                rand::thread_rng().fill_bytes(&mut secret);
+                secret
+            }
+            JwtAlgorithm::Es256 => p256::SecretKey::random(&mut rand::rngs::OsRng)
+                .to_pkcs8_der()
+                .map_err(|e| PdsError::Internal(format!("Failed to encode ES256 key: {}", e)))?
+                .as_bytes()
+                .to_vec(),
+        };
+        let sealed = seal(&self.cipher, &material, kid.as_bytes())?;
+
+        let mut tx = db.begin().await?;
+        sqlx::query("UPDATE jwt_signing_key SET retired_at = ?1 WHERE retired_at IS NULL")
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("INSERT INTO jwt_signing_key (kid, algorithm, key, created_at) VALUES (?1, ?2, ?3, ?4)")
+            .bind(&kid)
+            .bind(self.config.algorithm.as_str())
+            .bind(&sealed)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        self.reload(db).await?;
+        tracing::info!(kid = %kid, algorithm = self.config.algorithm.as_str(), "Rotated JWT signing key");
+        Ok(kid)
+    }
+
+    /// Rotate if the signing key is older than `PDS_JWT_ROTATION_DAYS`
+    pub async fn rotate_if_due(&self) -> PdsResult<Option<String>> {
+        let Some(days) = self.config.rotation_days else {
+            return Ok(None);
+        };
+        let due = match self.keys.read().unwrap().first() {
+            Some(key) => key.created_at + Duration::days(days as i64) <= self.clock.now(),
+            None => true,
+        };
+        if !due {
+            return Ok(None);
+        }
+        self.rotate().await.map(Some)
+    }
+
+    /// Delete retired keys past the grace period
+    pub async fn prune(&self) -> PdsResult<u64> {
+        let Some(db) = &self.db else {
+            return Ok(0);
+        };
+
+        let cutoff = self.clock.now() - self.grace();
+        let result = sqlx::query("DELETE FROM jwt_signing_key WHERE retired_at IS NOT NULL AND retired_at <= ?1")
+            .bind(cutoff)
+            .execute(db)
+            .await?;
+        if result.rows_affected() > 0 {
+            self.reload(db).await?;
+        }
+        Ok(result.rows_affected())
+    }
+
+    /// Sign `claims` with the current key
+    pub fn sign<T: Serialize>(&self, claims: &T) -> PdsResult<String> {
+        let keys = self.keys.read().unwrap();
+        let key = keys
+            .iter()
+            .find(|key| key.retired_at.is_none())
+            .ok_or_else(|| PdsError::Jwt("No active JWT signing key".to_string()))?;
+
+        let mut header = Header::new(key.algorithm.into());
+        if key.kid != LEGACY_KID {
+            header.kid = Some(key.kid.clone());
+        }
+        encode(&header, claims, &key.encoding).map_err(|e| PdsError::Jwt(format!("Failed to sign token: {}", e)))
+    }
+
+    /// Verify a token's signature (with the key its `kid` names) and expiry
+    pub fn verify(&self, token: &str) -> PdsResult<TokenData<serde_json::Value>> {
+        let header =
+            decode_header(token).map_err(|e| PdsError::Authentication(format!("Invalid token: {}", e)))?;
+        let kid = header.kid.as_deref().unwrap_or(LEGACY_KID);
+
+        let keys = self.keys.read().unwrap();
+        let key = keys
+            .iter()
+            .find(|key| key.kid == kid && self.validates(key))
+            .ok_or_else(|| PdsError::Authentication("Token signed with an unknown or expired key".to_string()))?;
+
+        let mut validation = Validation::new(key.algorithm.into());
+        validation.leeway = LEEWAY_SECS;
+        decode::<serde_json::Value>(token, &key.decoding, &validation).map_err(|e| {
+            tracing::debug!("JWT verification failed: {}", e);
+            match e.kind() {
+                jsonwebtoken::errors::ErrorKind::ExpiredSignature => {
+                    PdsError::Authentication("Token has expired".to_string())
+                }
+                jsonwebtoken::errors::ErrorKind::InvalidSignature => {
+                    PdsError::Authentication("Invalid token signature".to_string())
+                }
+                _ => PdsError::Authentication(format!("Invalid token: {}", e)),
+            }
+        })
+    }
+
+    /// Keys that sign or still validate tokens
+    pub fn list(&self) -> Vec<JwtKeyInfo> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|key| self.validates(key))
+            .map(|key| JwtKeyInfo {
+                kid: key.kid.clone(),
+                algorithm: key.algorithm,
+                active: key.retired_at.is_none(),
+                created_at: key.created_at,
+                retired_at: key.retired_at,
+                expires_at: key.retired_at.map(|at| at + self.grace()),
+            })
+            .collect()
+    }
+
+    /// Public JWKs of the ES256 keys that sign or still validate tokens
+    ///
+    /// HS256 keys are secret and never published.
+    pub fn public_jwks(&self) -> Vec<serde_json::Value> {
+        self.keys
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|key| self.validates(key))
+            .filter_map(|key| key.public_jwk.clone())
+            .collect()
+    }
+
+    fn grace(&self) -> Duration {
+        Duration::hours(self.config.retired_key_hours as i64)
+    }
+
+    fn validates(&self, key: &JwtKey) -> bool {
+        key.retired_at.map_or(true, |at| at + self.grace() > self.clock.now())
+    }
+}
+
+fn legacy_key(secret: &[u8], created_at: DateTime<Utc>, retired_at: Option<DateTime<Utc>>) -> JwtKey {
+    JwtKey {
+        kid: LEGACY_KID.to_string(),
+        algorithm: JwtAlgorithm::Hs256,
+        encoding: EncodingKey::from_secret(secret),
+        decoding: DecodingKey::from_secret(secret),
+        public_jwk: None,
+        created_at,
+        retired_at,
+    }
+}
+
+fn build_key(
+    kid: String,
+    algorithm: JwtAlgorithm,
+    material: &[u8],
+    created_at: DateTime<Utc>,
+    retired_at: Option<DateTime<Utc>>,
+) -> PdsResult<JwtKey> {
+    let (encoding, decoding, public_jwk) = match algorithm {
+        JwtAlgorithm::Hs256 => (EncodingKey::from_secret(material), DecodingKey::from_secret(material), None),
+        JwtAlgorithm::Es256 => {
+            let secret = p256::SecretKey::from_pkcs8_der(material)
+                .map_err(|e| PdsError::Internal(format!("Invalid ES256 key {}: {}", kid, e)))?;
+            let point = secret.public_key().to_encoded_point(false);
+            let (x, y) = match (point.x(), point.y()) {
+                (Some(x), Some(y)) => (URL_SAFE_NO_PAD.encode(x), URL_SAFE_NO_PAD.encode(y)),
+                _ => return Err(PdsError::Internal(format!("Invalid ES256 public key {}", kid))),
+            };
+            let decoding = DecodingKey::from_ec_components(&x, &y)
+                .map_err(|e| PdsError::Internal(format!("Invalid ES256 public key {}: {}", kid, e)))?;
+            let jwk = json!({
+                "kty": "EC",
+                "crv": "P-256",
+                "x": x,
+                "y": y,
+                "use": "sig",
+                "alg": "ES256",
+                "kid": kid,
+            });
+            (EncodingKey::from_ec_der(material), decoding, Some(jwk))
+        }
+    };
+
+    Ok(JwtKey {
+        kid,
+        algorithm,
+        encoding,
+        decoding,
+        public_jwk,
+        created_at,
+        retired_at,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    async fn keyring(algorithm: JwtAlgorithm, clock: Arc<MockClock>) -> (JwtKeyring, SqlitePool) {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&db).await.unwrap();
+        let config = JwtKeysConfig {
+            algorithm,
+            rotation_days: Some(30),
+            retired_key_hours: 48,
+        };
+        let keyring = JwtKeyring::new("test-secret-key-for-testing-only", config)
+            .with_db(db.clone())
+            .with_clock(clock);
+        (keyring, db)
+    }
+
+    fn claims(clock: &MockClock) -> serde_json::Value {
+        json!({ "sub": "did:plc:alice", "exp": clock.now().timestamp() + 3600 })
+    }
+
+    #[tokio::test]
+    async fn test_rotation_and_retired_keys() {
+        let clock = Arc::new(MockClock::fixed());
+        let (keyring, _db) = keyring(JwtAlgorithm::Hs256, clock.clone()).await;
+
+        // Before loading, tokens are signed with the configured secret and no kid
+        let legacy_token = keyring.sign(&claims(&clock)).unwrap();
+        assert!(decode_header(&legacy_token).unwrap().kid.is_none());
+
+        keyring.load().await.unwrap();
+        let first = keyring.sign(&claims(&clock)).unwrap();
+        assert!(decode_header(&first).unwrap().kid.is_some());
+        assert_eq!(keyring.verify(&legacy_token).unwrap().claims["sub"], "did:plc:alice");
+        assert!(keyring.public_jwks().is_empty());
+        assert!(keyring.rotate_if_due().await.unwrap().is_none());
+
+        // A due rotation retires the key, which still validates its tokens
+        clock.advance(Duration::days(30));
+        let second_kid = keyring.rotate_if_due().await.unwrap().unwrap();
+        let second = keyring.sign(&claims(&clock)).unwrap();
+        assert_eq!(decode_header(&second).unwrap().kid.unwrap(), second_kid);
+        assert!(keyring.verify(&first).is_ok());
+        assert_eq!(keyring.list().len(), 2);
+        assert!(keyring.list()[0].active);
+
+        // Past the grace period the retired keys are pruned
+        clock.advance(Duration::hours(49));
+        assert!(keyring.verify(&first).is_err());
+        assert_eq!(keyring.prune().await.unwrap(), 2);
+        assert!(keyring.verify(&keyring.sign(&claims(&clock)).unwrap()).is_ok());
+        assert!(keyring.verify(&legacy_token).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_es256_keys() {
+        let clock = Arc::new(MockClock::fixed());
+        let (keyring, db) = keyring(JwtAlgorithm::Es256, clock.clone()).await;
+        keyring.load().await.unwrap();
+
+        let token = keyring.sign(&claims(&clock)).unwrap();
+        assert_eq!(decode_header(&token).unwrap().alg, Algorithm::ES256);
+        assert!(keyring.verify(&token).is_ok());
+
+        // The published key verifies the token on its own
+        let jwks = keyring.public_jwks();
+        assert_eq!(jwks.len(), 1);
+        assert_eq!(jwks[0]["kid"], decode_header(&token).unwrap().kid.unwrap());
+        let jwk: jsonwebtoken::jwk::Jwk = serde_json::from_value(jwks[0].clone()).unwrap();
+        let validation = Validation::new(Algorithm::ES256);
+        assert!(decode::<serde_json::Value>(&token, &DecodingKey::from_jwk(&jwk).unwrap(), &validation).is_ok());
+
+        // Stored keys reload, and a tampered token fails
+        let reloaded = JwtKeyring::new("test-secret-key-for-testing-only", keyring.config.clone()).with_db(db);
+        reloaded.load().await.unwrap();
+        assert!(reloaded.verify(&token).is_ok());
+        let mut tampered = token.clone();
+        tampered.pop();
+        assert!(reloaded.verify(&tampered).is_err());
+
+        // An HS256 token claiming the ES256 key's kid is rejected
+        let kid = decode_header(&token).unwrap().kid;
+        let mut header = Header::new(Algorithm::HS256);
+        header.kid = kid;
+        let forged = encode(&header, &claims(&clock), &EncodingKey::from_secret(b"guess")).unwrap();
+        assert!(reloaded.verify(&forged).is_err());
+    }
+}
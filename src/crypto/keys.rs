@@ -0,0 +1,1136 @@
+/// Key management
+///
+/// Private keys used for repository commits, service auth tokens and PLC
+/// operations are held by a `KeyProvider` and only ever addressed by id:
+///
+/// - `config`: plaintext hex from `PDS_*_PRIVATE_KEY_HEX` (the original
+///   behaviour, and the default)
+/// - `local`: a keystore file encrypted with a passphrase from
+///   `PDS_KEYSTORE_PASSPHRASE` (Argon2id + XChaCha20-Poly1305)
+/// - `aws-kms`: asymmetric `ECC_SECG_P256K1` keys in AWS KMS; private keys
+///   never leave KMS
+/// - `vault`: keys kept in a HashiCorp Vault KV v2 mount and loaded into
+///   memory on first use (Vault's transit engine has no secp256k1 support)
+///
+/// Account PLC rotation keys are stored in the account table as references:
+/// a provider key id, or - for accounts created with the `config` backend -
+/// the plaintext hex key itself, which keeps working with every backend.
+/// Account commit signing keys are referenced the same way from the actor
+/// directory (see `crypto::signing_keys`).
+///
+/// Providers also wrap data encryption keys for encryption at rest (see
+/// `crypto::data_keys`) under a master key: a symmetric KMS key, or a random
+/// secret kept alongside the signing keys in the other backends.
+use crate::{
+    config::{KeyBackend, ServerConfig},
+    crypto::plc::{operation_signing_digest, PlcOperation, PlcSigner, PlcTombstone},
+    error::{PdsError, PdsResult},
+};
+use argon2::Argon2;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    XChaCha20Poly1305, XNonce,
+};
+use futures::future::BoxFuture;
+use hmac::{Hmac, Mac};
+use k256::{
+    ecdsa::{Signature, VerifyingKey},
+    pkcs8::DecodePublicKey,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+/// Default id of the repository signing key
+pub const DEFAULT_REPO_KEY_ID: &str = "repo";
+
+/// Default id of the server PLC rotation key
+pub const DEFAULT_PLC_ROTATION_KEY_ID: &str = "plc-rotation";
+
+/// Future returned by commit signers
+pub type SignFuture = BoxFuture<'static, Result<Vec<u8>, atproto::repo::RepoError>>;
+
+/// A backend holding secp256k1 private keys and key-wrapping keys
+#[async_trait]
+pub trait KeyProvider: Send + Sync {
+    /// Backend name, for logs
+    fn backend(&self) -> &'static str;
+
+    /// Public half of `key_id` (`PdsError::NotFound` if there is no such key)
+    async fn public_key(&self, key_id: &str) -> PdsResult<VerifyingKey>;
+
+    /// Sign a SHA-256 digest as-is, returning a 64-byte low-S signature
+    async fn sign_prehash(&self, key_id: &str, digest: &[u8; 32]) -> PdsResult<Vec<u8>>;
+
+    /// Create a key and return the reference to store for it
+    async fn generate_key(&self, key_id: &str) -> PdsResult<String>;
+
+    /// Store an existing private key under `key_id`
+    async fn import_key(&self, key_id: &str, _secret: &[u8]) -> PdsResult<()> {
+        Err(PdsError::Validation(format!(
+            "The {} key backend cannot import key {}",
+            self.backend(),
+            key_id
+        )))
+    }
+
+    /// Create a key-wrapping key under `key_id`
+    async fn generate_wrapping_key(&self, key_id: &str) -> PdsResult<()> {
+        Err(PdsError::Validation(format!(
+            "The {} key backend cannot create key {}; create it in the backend",
+            self.backend(),
+            key_id
+        )))
+    }
+
+    /// Encrypt a data key under `key_id`, bound to `context`
+    async fn wrap_key(&self, key_id: &str, plaintext: &[u8], context: &str) -> PdsResult<Vec<u8>>;
+
+    /// Decrypt a data key wrapped by `wrap_key`
+    async fn unwrap_key(&self, key_id: &str, wrapped: &[u8], context: &str) -> PdsResult<Vec<u8>>;
+}
+
+/// Signing front end used by the rest of the server
+pub struct KeyManager {
+    provider: Arc<dyn KeyProvider>,
+    repo_key_id: String,
+    plc_rotation_key_id: String,
+}
+
+impl KeyManager {
+    /// Wrap a provider
+    pub fn new(provider: Arc<dyn KeyProvider>, repo_key_id: &str, plc_rotation_key_id: &str) -> Self {
+        Self {
+            provider,
+            repo_key_id: repo_key_id.to_string(),
+            plc_rotation_key_id: plc_rotation_key_id.to_string(),
+        }
+    }
+
+    /// Plaintext keys from configuration (`config` backend)
+    pub fn from_plaintext(repo_key_hex: &str, plc_rotation_key_hex: &str) -> Self {
+        let provider = ConfigKeys::new([
+            (DEFAULT_REPO_KEY_ID, repo_key_hex),
+            (DEFAULT_PLC_ROTATION_KEY_ID, plc_rotation_key_hex),
+        ]);
+        Self::new(Arc::new(provider), DEFAULT_REPO_KEY_ID, DEFAULT_PLC_ROTATION_KEY_ID)
+    }
+
+    /// Open the configured backend
+    ///
+    /// Backends that can import keys pick up plaintext keys still present
+    /// in the configuration the first time they start, so existing
+    /// deployments can move off plaintext keys. Fails if the server keys
+    /// can't be loaded.
+    pub async fn from_config(config: &ServerConfig) -> PdsResult<Self> {
+        let auth = &config.authentication;
+        let keys = &config.keys;
+
+        let provider: Arc<dyn KeyProvider> = match &keys.backend {
+            KeyBackend::Config => Arc::new(ConfigKeys::new([
+                (keys.repo_key_id.as_str(), auth.repo_signing_key.as_str()),
+                (keys.plc_rotation_key_id.as_str(), auth.plc_rotation_key.as_str()),
+                (
+                    config.encryption.master_key_id.as_str(),
+                    config.encryption.master_key_hex.as_str(),
+                ),
+            ])),
+            KeyBackend::Local { path, passphrase } => Arc::new(LocalKeystore::open(path, passphrase)?),
+            KeyBackend::AwsKms {
+                region,
+                access_key_id,
+                secret_access_key,
+                session_token,
+                endpoint,
+            } => Arc::new(AwsKms::new(
+                region,
+                AwsCredentials {
+                    access_key_id: access_key_id.clone(),
+                    secret_access_key: secret_access_key.clone(),
+                    session_token: session_token.clone(),
+                },
+                endpoint.as_deref(),
+            )?),
+            KeyBackend::Vault { addr, token, mount, path } => {
+                Arc::new(VaultKv::new(addr, token, mount, path)?)
+            }
+        };
+
+        let manager = Self::new(provider, &keys.repo_key_id, &keys.plc_rotation_key_id);
+        for (key_id, plaintext) in [
+            (&keys.repo_key_id, &auth.repo_signing_key),
+            (&keys.plc_rotation_key_id, &auth.plc_rotation_key),
+        ] {
+            manager.ensure_key(key_id, plaintext).await?;
+        }
+        if config.encryption.enabled() {
+            manager
+                .ensure_wrapping_key(&config.encryption.master_key_id, &config.encryption.master_key_hex)
+                .await?;
+        }
+
+        tracing::info!("Signing keys loaded from the {} backend", manager.backend());
+        Ok(manager)
+    }
+
+    /// Check a server key exists, importing its plaintext form if given
+    async fn ensure_key(&self, key_id: &str, plaintext: &str) -> PdsResult<()> {
+        match self.provider.public_key(key_id).await {
+            Ok(_) => Ok(()),
+            Err(PdsError::NotFound(_)) if !plaintext.is_empty() => {
+                let secret = hex::decode(plaintext)
+                    .map_err(|_| PdsError::Validation(format!("Invalid hex for key {}", key_id)))?;
+                self.provider.import_key(key_id, &secret).await?;
+                tracing::warn!(
+                    "Imported plaintext key {} into the {} backend; remove it from the environment",
+                    key_id,
+                    self.backend()
+                );
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Check a key-wrapping key exists, importing or creating it if not
+    async fn ensure_wrapping_key(&self, key_id: &str, plaintext: &str) -> PdsResult<()> {
+        match self.provider.wrap_key(key_id, &[0u8; 32], "probe").await {
+            Ok(_) => Ok(()),
+            Err(PdsError::NotFound(_)) if !plaintext.is_empty() => {
+                let secret = hex::decode(plaintext)
+                    .map_err(|_| PdsError::Validation(format!("Invalid hex for key {}", key_id)))?;
+                self.provider.import_key(key_id, &secret).await
+            }
+            Err(PdsError::NotFound(_)) => {
+                self.provider.generate_wrapping_key(key_id).await?;
+                tracing::info!("Created master key {} in the {} backend", key_id, self.backend());
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Backend name
+    pub fn backend(&self) -> &'static str {
+        self.provider.backend()
+    }
+
+    /// Id of the server PLC rotation key
+    pub fn plc_rotation_key_id(&self) -> &str {
+        &self.plc_rotation_key_id
+    }
+
+    /// Public repository signing key
+    pub async fn repo_public_key(&self) -> PdsResult<VerifyingKey> {
+        self.provider.public_key(&self.repo_key_id).await
+    }
+
+    /// Sign a commit hash with the repository key
+    pub async fn sign_commit(&self, hash: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        self.provider.sign_prehash(&self.repo_key_id, hash).await
+    }
+
+    /// Commit signer for `RepositoryManager` writes, using the server repo key
+    pub fn commit_signer(self: &Arc<Self>) -> impl FnOnce([u8; 32]) -> SignFuture + Send + 'static {
+        self.commit_signer_for(None)
+    }
+
+    /// Commit signer using a stored key reference, or the server repo key
+    pub fn commit_signer_for(
+        self: &Arc<Self>,
+        key_ref: Option<String>,
+    ) -> impl FnOnce([u8; 32]) -> SignFuture + Send + 'static {
+        let keys = Arc::clone(self);
+        move |hash: [u8; 32]| -> SignFuture {
+            Box::pin(async move {
+                let sig = match key_ref {
+                    Some(key_ref) => keys.sign_prehash(&key_ref, &hash).await,
+                    None => keys.sign_commit(&hash).await,
+                };
+                sig.map_err(|e| atproto::repo::RepoError::Signing(e.to_string()))
+            })
+        }
+    }
+
+    /// Public key for a stored key reference
+    pub async fn public_key(&self, key_ref: &str) -> PdsResult<VerifyingKey> {
+        match plaintext_key(key_ref) {
+            Some(signer) => Ok(signer.verifying_key()),
+            None => self.provider.public_key(key_ref).await,
+        }
+    }
+
+    /// Sign a digest with a stored key reference
+    pub async fn sign_prehash(&self, key_ref: &str, digest: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        match plaintext_key(key_ref) {
+            Some(signer) => signer.sign_prehash(digest),
+            None => self.provider.sign_prehash(key_ref, digest).await,
+        }
+    }
+
+    /// Sign a PLC operation with a stored key reference
+    pub async fn sign_plc_operation(&self, key_ref: &str, mut operation: PlcOperation) -> PdsResult<PlcOperation> {
+        operation.sig = None;
+        let digest = operation_signing_digest(&operation)?;
+        let sig = self.sign_prehash(key_ref, &digest).await?;
+        operation.sig = Some(hex::encode(sig));
+        Ok(operation)
+    }
+
+    /// Sign a PLC tombstone with a stored key reference
+    pub async fn sign_plc_tombstone(&self, key_ref: &str, mut tombstone: PlcTombstone) -> PdsResult<PlcTombstone> {
+        tombstone.sig = None;
+        let digest = operation_signing_digest(&tombstone)?;
+        let sig = self.sign_prehash(key_ref, &digest).await?;
+        tombstone.sig = Some(hex::encode(sig));
+        Ok(tombstone)
+    }
+
+    /// Wrap a data encryption key under a master key
+    pub async fn wrap_data_key(&self, master_key_id: &str, key: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        self.provider.wrap_key(master_key_id, key, context).await
+    }
+
+    /// Unwrap a data encryption key
+    pub async fn unwrap_data_key(&self, master_key_id: &str, wrapped: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        self.provider.unwrap_key(master_key_id, wrapped, context).await
+    }
+
+    /// Create a key for an account (PLC rotation or commit signing),
+    /// returning its reference
+    pub async fn generate_account_key(&self) -> PdsResult<String> {
+        let key_id = format!("account-{}", uuid::Uuid::new_v4());
+        self.provider.generate_key(&key_id).await
+    }
+}
+
+/// A key reference that is a plaintext hex key (config backend accounts)
+fn plaintext_key(key_ref: &str) -> Option<PlcSigner> {
+    if key_ref.len() == 64 && key_ref.bytes().all(|b| b.is_ascii_hexdigit()) {
+        PlcSigner::from_hex(key_ref).ok()
+    } else {
+        None
+    }
+}
+
+/// Multibase (`z` + base58btc) compressed public key, as used in DID documents
+pub fn public_key_multibase(key: &VerifyingKey) -> String {
+    format!("z{}", bs58::encode(key.to_encoded_point(true).as_bytes()).into_string())
+}
+
+/// XChaCha20-Poly1305 cipher for a 32-byte key
+pub fn cipher_for(key: &[u8; 32]) -> XChaCha20Poly1305 {
+    XChaCha20Poly1305::new(&(*key).into())
+}
+
+/// Encrypt with a random nonce, returning `nonce || ciphertext`
+pub fn seal(cipher: &XChaCha20Poly1305, plaintext: &[u8], aad: &[u8]) -> PdsResult<Vec<u8>> {
+    let mut nonce = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = cipher
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: plaintext, aad })
+        .map_err(|_| PdsError::Internal("Encryption failed".to_string()))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    Ok(sealed)
+}
+
+/// Decrypt `nonce || ciphertext` produced by [`seal`]
+pub fn open(cipher: &XChaCha20Poly1305, sealed: &[u8], aad: &[u8]) -> PdsResult<Vec<u8>> {
+    if sealed.len() < 24 {
+        return Err(PdsError::Validation("Encrypted data is truncated".to_string()));
+    }
+    let (nonce, ciphertext) = sealed.split_at(24);
+    cipher
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| PdsError::Authentication("Decryption failed (wrong key or corrupt data)".to_string()))
+}
+
+/// Cipher for wrapping data keys with a stored secret
+///
+/// The secret is run through HMAC first so the same bytes are never used
+/// directly as both an ECDSA and an AEAD key.
+fn wrapping_cipher(secret: &[u8]) -> XChaCha20Poly1305 {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(secret).expect("HMAC accepts any key length");
+    mac.update(b"aurora-locus key wrapping");
+    cipher_for(&mac.finalize().into_bytes().into())
+}
+
+fn random_secret() -> [u8; 32] {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    secret
+}
+
+fn unknown_key(backend: &str, key_id: &str) -> PdsError {
+    PdsError::NotFound(format!("Key {} not found in the {} backend", key_id, backend))
+}
+
+/// Plaintext hex keys from configuration
+///
+/// Nothing is persisted: generated keys are returned as their hex form,
+/// which is what gets stored in the account table.
+pub struct ConfigKeys {
+    keys: HashMap<String, String>,
+}
+
+impl ConfigKeys {
+    pub fn new<'a>(keys: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        Self {
+            keys: keys
+                .into_iter()
+                .filter(|(_, hex)| !hex.is_empty())
+                .map(|(id, hex)| (id.to_string(), hex.to_string()))
+                .collect(),
+        }
+    }
+
+    fn secret(&self, key_id: &str) -> PdsResult<Vec<u8>> {
+        let hex = self.keys.get(key_id).ok_or_else(|| unknown_key(self.backend(), key_id))?;
+        hex::decode(hex).map_err(|_| PdsError::Validation(format!("Invalid hex for key {}", key_id)))
+    }
+
+    fn signer(&self, key_id: &str) -> PdsResult<PlcSigner> {
+        PlcSigner::new(&self.secret(key_id)?)
+    }
+}
+
+#[async_trait]
+impl KeyProvider for ConfigKeys {
+    fn backend(&self) -> &'static str {
+        "config"
+    }
+
+    async fn public_key(&self, key_id: &str) -> PdsResult<VerifyingKey> {
+        Ok(self.signer(key_id)?.verifying_key())
+    }
+
+    async fn sign_prehash(&self, key_id: &str, digest: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        self.signer(key_id)?.sign_prehash(digest)
+    }
+
+    async fn generate_key(&self, _key_id: &str) -> PdsResult<String> {
+        Ok(hex::encode(random_secret()))
+    }
+
+    async fn wrap_key(&self, key_id: &str, plaintext: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        seal(&wrapping_cipher(&self.secret(key_id)?), plaintext, context.as_bytes())
+    }
+
+    async fn unwrap_key(&self, key_id: &str, wrapped: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        open(&wrapping_cipher(&self.secret(key_id)?), wrapped, context.as_bytes())
+    }
+}
+
+/// On-disk keystore format
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct KeystoreFile {
+    version: u32,
+    /// Argon2id salt (base64)
+    salt: String,
+    keys: BTreeMap<String, SealedKey>,
+}
+
+/// A private key encrypted under the keystore key, bound to its id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SealedKey {
+    nonce: String,
+    ciphertext: String,
+}
+
+/// Passphrase-encrypted keystore file
+pub struct LocalKeystore {
+    path: PathBuf,
+    cipher: XChaCha20Poly1305,
+    file: RwLock<KeystoreFile>,
+    secrets: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl LocalKeystore {
+    /// Open (or create) the keystore at `path`
+    ///
+    /// Every key is decrypted up front, so a wrong passphrase fails here.
+    pub fn open(path: &Path, passphrase: &str) -> PdsResult<Self> {
+        if passphrase.is_empty() {
+            return Err(PdsError::Validation("Keystore passphrase is empty".to_string()));
+        }
+
+        let file = if path.exists() {
+            let bytes = std::fs::read(path)?;
+            serde_json::from_slice(&bytes)
+                .map_err(|e| PdsError::Validation(format!("Invalid keystore {}: {}", path.display(), e)))?
+        } else {
+            let mut salt = [0u8; 16];
+            rand::thread_rng().fill_bytes(&mut salt);
+            KeystoreFile {
+                version: 1,
+                salt: STANDARD.encode(salt),
+                keys: BTreeMap::new(),
+            }
+        };
+
+        let salt = STANDARD
+            .decode(&file.salt)
+            .map_err(|_| PdsError::Validation("Invalid keystore salt".to_string()))?;
+        let mut key = [0u8; 32];
+        Argon2::default()
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut key)
+            .map_err(|e| PdsError::Internal(format!("Keystore key derivation failed: {}", e)))?;
+        let cipher = XChaCha20Poly1305::new(&key.into());
+
+        let mut secrets = HashMap::new();
+        for (id, sealed) in &file.keys {
+            secrets.insert(id.clone(), open_sealed(&cipher, id, sealed)?);
+        }
+
+        let keystore = Self {
+            path: path.to_path_buf(),
+            cipher,
+            file: RwLock::new(file),
+            secrets: RwLock::new(secrets),
+        };
+        if !path.exists() {
+            keystore.save()?;
+        }
+        Ok(keystore)
+    }
+
+    fn secret(&self, key_id: &str) -> PdsResult<Vec<u8>> {
+        self.secrets
+            .read()
+            .unwrap()
+            .get(key_id)
+            .cloned()
+            .ok_or_else(|| unknown_key("local", key_id))
+    }
+
+    fn signer(&self, key_id: &str) -> PdsResult<PlcSigner> {
+        PlcSigner::new(&self.secret(key_id)?)
+    }
+
+    fn store(&self, key_id: &str, secret: &[u8]) -> PdsResult<()> {
+        if secret.len() != 32 {
+            return Err(PdsError::Validation("Keys must be exactly 32 bytes".to_string()));
+        }
+        if self.secrets.read().unwrap().contains_key(key_id) {
+            return Err(PdsError::Conflict(format!("Key {} already exists", key_id)));
+        }
+
+        let mut nonce = [0u8; 24];
+        rand::thread_rng().fill_bytes(&mut nonce);
+        let ciphertext = self
+            .cipher
+            .encrypt(
+                XNonce::from_slice(&nonce),
+                Payload {
+                    msg: secret,
+                    aad: key_id.as_bytes(),
+                },
+            )
+            .map_err(|_| PdsError::Internal("Keystore encryption failed".to_string()))?;
+
+        self.file.write().unwrap().keys.insert(
+            key_id.to_string(),
+            SealedKey {
+                nonce: STANDARD.encode(nonce),
+                ciphertext: STANDARD.encode(ciphertext),
+            },
+        );
+        self.save()?;
+        self.secrets.write().unwrap().insert(key_id.to_string(), secret.to_vec());
+        Ok(())
+    }
+
+    /// Write the keystore atomically, readable only by the server user
+    fn save(&self) -> PdsResult<()> {
+        let bytes = serde_json::to_vec_pretty(&*self.file.read().unwrap())
+            .map_err(|e| PdsError::Internal(format!("Failed to encode keystore: {}", e)))?;
+        let tmp = self.path.with_extension("tmp");
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&tmp, bytes)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600))?;
+        }
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+}
+
+fn open_sealed(cipher: &XChaCha20Poly1305, key_id: &str, sealed: &SealedKey) -> PdsResult<Vec<u8>> {
+    let invalid = || PdsError::Validation(format!("Keystore entry {} is corrupt", key_id));
+    let nonce = STANDARD.decode(&sealed.nonce).map_err(|_| invalid())?;
+    let ciphertext = STANDARD.decode(&sealed.ciphertext).map_err(|_| invalid())?;
+    if nonce.len() != 24 {
+        return Err(invalid());
+    }
+
+    cipher
+        .decrypt(
+            XNonce::from_slice(&nonce),
+            Payload {
+                msg: &ciphertext,
+                aad: key_id.as_bytes(),
+            },
+        )
+        .map_err(|_| {
+            PdsError::Authentication(format!(
+                "Cannot decrypt keystore entry {} (wrong passphrase?)",
+                key_id
+            ))
+        })
+}
+
+#[async_trait]
+impl KeyProvider for LocalKeystore {
+    fn backend(&self) -> &'static str {
+        "local"
+    }
+
+    async fn public_key(&self, key_id: &str) -> PdsResult<VerifyingKey> {
+        Ok(self.signer(key_id)?.verifying_key())
+    }
+
+    async fn sign_prehash(&self, key_id: &str, digest: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        self.signer(key_id)?.sign_prehash(digest)
+    }
+
+    async fn generate_key(&self, key_id: &str) -> PdsResult<String> {
+        self.store(key_id, &random_secret())?;
+        Ok(key_id.to_string())
+    }
+
+    async fn import_key(&self, key_id: &str, secret: &[u8]) -> PdsResult<()> {
+        self.store(key_id, secret)
+    }
+
+    async fn generate_wrapping_key(&self, key_id: &str) -> PdsResult<()> {
+        self.store(key_id, &random_secret())
+    }
+
+    async fn wrap_key(&self, key_id: &str, plaintext: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        seal(&wrapping_cipher(&self.secret(key_id)?), plaintext, context.as_bytes())
+    }
+
+    async fn unwrap_key(&self, key_id: &str, wrapped: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        open(&wrapping_cipher(&self.secret(key_id)?), wrapped, context.as_bytes())
+    }
+}
+
+/// AWS credentials for request signing
+#[derive(Debug, Clone)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+}
+
+/// AWS KMS asymmetric keys (`ECC_SECG_P256K1`, `SIGN_VERIFY`)
+///
+/// Key ids are KMS key ids, ARNs or aliases. KMS can't import asymmetric
+/// keys, so the server keys must be created in KMS and their ids configured.
+pub struct AwsKms {
+    client: reqwest::Client,
+    endpoint: reqwest::Url,
+    region: String,
+    credentials: AwsCredentials,
+    public_keys: RwLock<HashMap<String, VerifyingKey>>,
+}
+
+impl AwsKms {
+    pub fn new(region: &str, credentials: AwsCredentials, endpoint: Option<&str>) -> PdsResult<Self> {
+        let endpoint = endpoint
+            .map(String::from)
+            .unwrap_or_else(|| format!("https://kms.{}.amazonaws.com/", region));
+        let endpoint = reqwest::Url::parse(&endpoint)
+            .map_err(|e| PdsError::Validation(format!("Invalid KMS endpoint {}: {}", endpoint, e)))?;
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|e| PdsError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            region: region.to_string(),
+            credentials,
+            public_keys: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Call a KMS JSON API action
+    async fn call(&self, action: &str, body: Value) -> PdsResult<Value> {
+        let payload = serde_json::to_vec(&body)
+            .map_err(|e| PdsError::Internal(format!("Failed to encode KMS request: {}", e)))?;
+        let host = self
+            .endpoint
+            .host_str()
+            .ok_or_else(|| PdsError::Validation("KMS endpoint has no host".to_string()))?;
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        };
+        let amz_date = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut headers = vec![
+            ("content-type", "application/x-amz-json-1.1".to_string()),
+            ("host", host),
+            ("x-amz-date", amz_date.clone()),
+            ("x-amz-target", format!("TrentService.{}", action)),
+        ];
+        if let Some(token) = &self.credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let authorization = sigv4_authorization(
+            &self.credentials,
+            &self.region,
+            "kms",
+            "POST",
+            self.endpoint.path(),
+            "",
+            &headers,
+            &payload,
+            &amz_date,
+        );
+
+        let mut request = self.client.post(self.endpoint.clone()).body(payload);
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, value);
+            }
+        }
+        let response = request
+            .header("authorization", authorization)
+            .send()
+            .await
+            .map_err(|e| PdsError::Internal(format!("KMS {} failed: {}", action, e)))?;
+
+        let status = response.status();
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| PdsError::Internal(format!("Invalid KMS {} response: {}", action, e)))?;
+        if status.is_success() {
+            return Ok(body);
+        }
+
+        let kind = body.get("__type").and_then(Value::as_str).unwrap_or_default();
+        let message = body
+            .get("message")
+            .or_else(|| body.get("Message"))
+            .and_then(Value::as_str)
+            .unwrap_or_default();
+        if kind.ends_with("NotFoundException") {
+            return Err(PdsError::NotFound(format!("KMS key not found: {}", message)));
+        }
+        Err(PdsError::Internal(format!("KMS {} returned {}: {} {}", action, status, kind, message)))
+    }
+
+    fn decode_field(body: &Value, field: &str) -> PdsResult<Vec<u8>> {
+        body.get(field)
+            .and_then(Value::as_str)
+            .and_then(|b| STANDARD.decode(b).ok())
+            .ok_or_else(|| PdsError::Internal(format!("KMS response missing {}", field)))
+    }
+}
+
+#[async_trait]
+impl KeyProvider for AwsKms {
+    fn backend(&self) -> &'static str {
+        "aws-kms"
+    }
+
+    async fn public_key(&self, key_id: &str) -> PdsResult<VerifyingKey> {
+        if let Some(key) = self.public_keys.read().unwrap().get(key_id) {
+            return Ok(*key);
+        }
+
+        let body = self.call("GetPublicKey", json!({ "KeyId": key_id })).await?;
+        if body.get("KeySpec").and_then(Value::as_str) != Some("ECC_SECG_P256K1") {
+            return Err(PdsError::Validation(format!("KMS key {} is not a secp256k1 key", key_id)));
+        }
+        let der = Self::decode_field(&body, "PublicKey")?;
+        let key = VerifyingKey::from_public_key_der(&der)
+            .map_err(|e| PdsError::Internal(format!("Invalid KMS public key: {}", e)))?;
+
+        self.public_keys.write().unwrap().insert(key_id.to_string(), key);
+        Ok(key)
+    }
+
+    async fn sign_prehash(&self, key_id: &str, digest: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        let body = self
+            .call(
+                "Sign",
+                json!({
+                    "KeyId": key_id,
+                    "Message": STANDARD.encode(digest),
+                    "MessageType": "DIGEST",
+                    "SigningAlgorithm": "ECDSA_SHA_256",
+                }),
+            )
+            .await?;
+
+        // KMS returns DER and doesn't normalize S; atproto requires low-S
+        let der = Self::decode_field(&body, "Signature")?;
+        let signature = Signature::from_der(&der)
+            .map_err(|e| PdsError::Internal(format!("Invalid KMS signature: {}", e)))?;
+        let signature = signature.normalize_s().unwrap_or(signature);
+        Ok(signature.to_bytes().to_vec())
+    }
+
+    async fn generate_key(&self, key_id: &str) -> PdsResult<String> {
+        let body = self
+            .call(
+                "CreateKey",
+                json!({
+                    "KeySpec": "ECC_SECG_P256K1",
+                    "KeyUsage": "SIGN_VERIFY",
+                    "Description": key_id,
+                }),
+            )
+            .await?;
+
+        body.pointer("/KeyMetadata/KeyId")
+            .and_then(Value::as_str)
+            .map(String::from)
+            .ok_or_else(|| PdsError::Internal("KMS CreateKey response missing KeyId".to_string()))
+    }
+
+    /// `key_id` must be a symmetric (`SYMMETRIC_DEFAULT`) KMS key
+    async fn wrap_key(&self, key_id: &str, plaintext: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        let body = self
+            .call(
+                "Encrypt",
+                json!({
+                    "KeyId": key_id,
+                    "Plaintext": STANDARD.encode(plaintext),
+                    "EncryptionContext": { "context": context },
+                }),
+            )
+            .await?;
+        Self::decode_field(&body, "CiphertextBlob")
+    }
+
+    async fn unwrap_key(&self, key_id: &str, wrapped: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        let body = self
+            .call(
+                "Decrypt",
+                json!({
+                    "KeyId": key_id,
+                    "CiphertextBlob": STANDARD.encode(wrapped),
+                    "EncryptionContext": { "context": context },
+                }),
+            )
+            .await?;
+        Self::decode_field(&body, "Plaintext")
+    }
+}
+
+/// AWS Signature Version 4 `Authorization` header
+///
+/// `headers` are the lowercase names and values to sign, sorted by name.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn sigv4_authorization(
+    credentials: &AwsCredentials,
+    region: &str,
+    service: &str,
+    method: &str,
+    path: &str,
+    query: &str,
+    headers: &[(&str, String)],
+    payload: &[u8],
+    amz_date: &str,
+) -> String {
+    let date = &amz_date[..8];
+    let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    let canonical_headers: String = headers
+        .iter()
+        .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+        .collect();
+    let canonical_request = format!(
+        "{}\n{}\n{}\n{}\n{}\n{}",
+        method,
+        path,
+        query,
+        canonical_headers,
+        signed_headers,
+        hex::encode(Sha256::digest(payload))
+    );
+
+    let scope = format!("{}/{}/{}/aws4_request", date, region, service);
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+        amz_date,
+        scope,
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+
+    let hmac = |key: &[u8], data: &str| {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key).expect("HMAC accepts any key length");
+        mac.update(data.as_bytes());
+        mac.finalize().into_bytes().to_vec()
+    };
+    let k_date = hmac(format!("AWS4{}", credentials.secret_access_key).as_bytes(), date);
+    let k_region = hmac(&k_date, region);
+    let k_service = hmac(&k_region, service);
+    let k_signing = hmac(&k_service, "aws4_request");
+    let signature = hex::encode(hmac(&k_signing, &string_to_sign));
+
+    format!(
+        "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+        credentials.access_key_id, scope, signed_headers, signature
+    )
+}
+
+/// Keys stored in a HashiCorp Vault KV v2 mount
+///
+/// Each key is a secret at `<mount>/data/<path>/<key id>` with a hex
+/// `private_key` field. Keys are read once and cached in memory.
+pub struct VaultKv {
+    client: reqwest::Client,
+    addr: String,
+    token: String,
+    mount: String,
+    path: String,
+    secrets: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl VaultKv {
+    pub fn new(addr: &str, token: &str, mount: &str, path: &str) -> PdsResult<Self> {
+        let client = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()
+            .map_err(|e| PdsError::Internal(format!("Failed to build HTTP client: {}", e)))?;
+
+        Ok(Self {
+            client,
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            mount: mount.trim_matches('/').to_string(),
+            path: path.trim_matches('/').to_string(),
+            secrets: RwLock::new(HashMap::new()),
+        })
+    }
+
+    fn url(&self, key_id: &str) -> String {
+        format!(
+            "{}/v1/{}/data/{}/{}",
+            self.addr,
+            self.mount,
+            self.path,
+            urlencoding::encode(key_id)
+        )
+    }
+
+    async fn signer(&self, key_id: &str) -> PdsResult<PlcSigner> {
+        PlcSigner::new(&self.secret(key_id).await?)
+    }
+
+    async fn secret(&self, key_id: &str) -> PdsResult<Vec<u8>> {
+        if let Some(secret) = self.secrets.read().unwrap().get(key_id) {
+            return Ok(secret.clone());
+        }
+
+        let response = self
+            .client
+            .get(self.url(key_id))
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .map_err(|e| PdsError::Internal(format!("Vault request failed: {}", e)))?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Err(unknown_key("vault", key_id));
+        }
+        if !response.status().is_success() {
+            return Err(PdsError::Internal(format!("Vault returned {} for key {}", response.status(), key_id)));
+        }
+
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| PdsError::Internal(format!("Invalid Vault response: {}", e)))?;
+        let hex = body
+            .pointer("/data/data/private_key")
+            .and_then(Value::as_str)
+            .ok_or_else(|| PdsError::Internal(format!("Vault secret for {} has no private_key", key_id)))?;
+        let secret = hex::decode(hex)
+            .map_err(|_| PdsError::Internal(format!("Vault secret for {} is not hex", key_id)))?;
+
+        self.secrets.write().unwrap().insert(key_id.to_string(), secret.clone());
+        Ok(secret)
+    }
+
+    /// Write a key, refusing to overwrite an existing one
+    async fn store(&self, key_id: &str, secret: &[u8]) -> PdsResult<()> {
+        if secret.len() != 32 {
+            return Err(PdsError::Validation("Keys must be exactly 32 bytes".to_string()));
+        }
+        let response = self
+            .client
+            .post(self.url(key_id))
+            .header("X-Vault-Token", &self.token)
+            .json(&json!({
+                "options": { "cas": 0 },
+                "data": { "private_key": hex::encode(secret) },
+            }))
+            .send()
+            .await
+            .map_err(|e| PdsError::Internal(format!("Vault request failed: {}", e)))?;
+        if !response.status().is_success() {
+            return Err(PdsError::Internal(format!(
+                "Vault refused to store key {}: {}",
+                key_id,
+                response.status()
+            )));
+        }
+
+        self.secrets.write().unwrap().insert(key_id.to_string(), secret.to_vec());
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl KeyProvider for VaultKv {
+    fn backend(&self) -> &'static str {
+        "vault"
+    }
+
+    async fn public_key(&self, key_id: &str) -> PdsResult<VerifyingKey> {
+        Ok(self.signer(key_id).await?.verifying_key())
+    }
+
+    async fn sign_prehash(&self, key_id: &str, digest: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        self.signer(key_id).await?.sign_prehash(digest)
+    }
+
+    async fn generate_key(&self, key_id: &str) -> PdsResult<String> {
+        self.store(key_id, &random_secret()).await?;
+        Ok(key_id.to_string())
+    }
+
+    async fn import_key(&self, key_id: &str, secret: &[u8]) -> PdsResult<()> {
+        self.store(key_id, secret).await
+    }
+
+    async fn generate_wrapping_key(&self, key_id: &str) -> PdsResult<()> {
+        self.store(key_id, &random_secret()).await
+    }
+
+    async fn wrap_key(&self, key_id: &str, plaintext: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        seal(&wrapping_cipher(&self.secret(key_id).await?), plaintext, context.as_bytes())
+    }
+
+    async fn unwrap_key(&self, key_id: &str, wrapped: &[u8], context: &str) -> PdsResult<Vec<u8>> {
+        open(&wrapping_cipher(&self.secret(key_id).await?), wrapped, context.as_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+    fn keystore_path(name: &str) -> PathBuf {
+        let path = PathBuf::from(format!("./test_data/keys/{}.json", name));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn test_local_keystore_roundtrip() {
+        let path = keystore_path("roundtrip");
+        let keystore = LocalKeystore::open(&path, "correct horse").unwrap();
+        keystore.import_key("repo", &[1u8; 32]).await.unwrap();
+        let generated = keystore.generate_key("account-1").await.unwrap();
+        assert!(keystore.import_key("repo", &[2u8; 32]).await.is_err());
+
+        // The file never holds plaintext keys
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&hex::encode([1u8; 32])));
+
+        let reopened = LocalKeystore::open(&path, "correct horse").unwrap();
+        assert_eq!(
+            reopened.public_key("repo").await.unwrap(),
+            PlcSigner::new(&[1u8; 32]).unwrap().verifying_key()
+        );
+        let digest: [u8; 32] = Sha256::digest(b"commit").into();
+        let sig = reopened.sign_prehash(&generated, &digest).await.unwrap();
+        let key = reopened.public_key(&generated).await.unwrap();
+        assert!(key.verify_prehash(&digest, &Signature::from_slice(&sig).unwrap()).is_ok());
+
+        assert!(matches!(reopened.public_key("missing").await, Err(PdsError::NotFound(_))));
+        assert!(LocalKeystore::open(&path, "wrong").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_key_manager_plaintext_refs() {
+        let repo_hex = hex::encode([3u8; 32]);
+        let keys = KeyManager::from_plaintext(&repo_hex, &hex::encode([4u8; 32]));
+
+        // Config-backend account keys are stored as their hex form
+        let account_key = keys.generate_account_key().await.unwrap();
+        assert!(plaintext_key(&account_key).is_some());
+
+        let digest: [u8; 32] = Sha256::digest(b"op").into();
+        let sig = keys.sign_prehash(&account_key, &digest).await.unwrap();
+        let public = keys.public_key(&account_key).await.unwrap();
+        assert!(public.verify_prehash(&digest, &Signature::from_slice(&sig).unwrap()).is_ok());
+
+        // PLC operations match the signature PlcSigner produces
+        let op = crate::crypto::plc::PlcOperationBuilder::new()
+            .did("did:plc:abc123".to_string())
+            .build()
+            .unwrap();
+        let signer = PlcSigner::from_hex(&repo_hex).unwrap();
+        assert_eq!(
+            keys.sign_plc_operation(&repo_hex, op.clone()).await.unwrap().sig,
+            signer.sign_operation(op).unwrap().sig
+        );
+        assert_eq!(
+            keys.repo_public_key().await.unwrap(),
+            signer.verifying_key()
+        );
+    }
+
+    #[test]
+    fn test_sigv4_reference_signature() {
+        // Example request from the AWS Signature Version 4 documentation
+        let credentials = AwsCredentials {
+            access_key_id: "AKIDEXAMPLE".to_string(),
+            secret_access_key: "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY".to_string(),
+            session_token: None,
+        };
+        let headers = vec![
+            ("content-type", "application/x-www-form-urlencoded; charset=utf-8".to_string()),
+            ("host", "iam.amazonaws.com".to_string()),
+            ("x-amz-date", "20150830T123600Z".to_string()),
+        ];
+        let authorization = sigv4_authorization(
+            &credentials,
+            "us-east-1",
+            "iam",
+            "GET",
+            "/",
+            "Action=ListUsers&Version=2010-05-08",
+            &headers,
+            b"",
+            "20150830T123600Z",
+        );
+        assert_eq!(
+            authorization,
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+}
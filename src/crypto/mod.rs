@@ -1,5 +1,12 @@
 /// Cryptography module for PLC operations and key management
 ///
-/// Handles secp256k1 signing for DID:PLC operations
+/// Handles secp256k1 signing for DID:PLC operations and service auth tokens,
+/// per-account commit signing keys, the data keys used for encryption at
+/// rest, and the rotating keys that sign the server's JWTs
 
+pub mod data_keys;
+pub mod jwt_keys;
+pub mod keys;
 pub mod plc;
+pub mod service_auth;
+pub mod signing_keys;
@@ -2,9 +2,12 @@
 ///
 /// Implements secp256k1-based signing for DID:PLC update operations
 
-use crate::error::{PdsError, PdsResult};
+use crate::{
+    error::{PdsError, PdsResult},
+    telemetry::inject_trace_context,
+};
 use k256::{
-    ecdsa::{signature::Signer, Signature, SigningKey},
+    ecdsa::SigningKey,
     SecretKey,
 };
 use serde::{Deserialize, Serialize};
@@ -46,6 +49,35 @@ pub struct PlcOperation {
     pub sig: Option<String>,
 }
 
+/// PLC tombstone, permanently deactivating a DID
+///
+/// Signed by one of the DID's rotation keys like any other operation; the
+/// directory resolves the DID to nothing afterwards.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlcTombstone {
+    /// Always "plc_tombstone"
+    #[serde(rename = "type")]
+    pub op_type: String,
+
+    /// CID of the operation being tombstoned
+    pub prev: String,
+
+    /// Signature over the tombstone
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sig: Option<String>,
+}
+
+impl PlcTombstone {
+    /// Unsigned tombstone on top of `prev`
+    pub fn new(prev: String) -> Self {
+        Self {
+            op_type: "plc_tombstone".to_string(),
+            prev,
+            sig: None,
+        }
+    }
+}
+
 /// Builder for PLC operations
 #[derive(Debug, Default)]
 pub struct PlcOperationBuilder {
@@ -126,6 +158,7 @@ impl PlcOperationBuilder {
 }
 
 /// PLC Signer - handles signing of PLC operations
+#[derive(Clone)]
 pub struct PlcSigner {
     signing_key: SigningKey,
 }
@@ -157,7 +190,7 @@ impl PlcSigner {
         Self::new(&key_bytes)
     }
 
-    /// Sign raw bytes (for repository commits)
+    /// Sign raw bytes (hashed with SHA-256 before signing)
     ///
     /// Returns a 64-byte signature
     pub fn sign(&self, data: &[u8]) -> Vec<u8> {
@@ -166,6 +199,19 @@ impl PlcSigner {
         signature.to_bytes().to_vec()
     }
 
+    /// Sign a SHA-256 digest as-is (for repository commits)
+    ///
+    /// Commit signatures cover the commit hash itself; `sign` would hash it
+    /// a second time. Returns a 64-byte signature.
+    pub fn sign_prehash(&self, hash: &[u8; 32]) -> PdsResult<Vec<u8>> {
+        use k256::ecdsa::signature::hazmat::PrehashSigner;
+        let signature: k256::ecdsa::Signature = self
+            .signing_key
+            .sign_prehash(hash)
+            .map_err(|e| PdsError::Internal(format!("Failed to sign commit: {}", e)))?;
+        Ok(signature.to_bytes().to_vec())
+    }
+
     /// Sign a PLC operation
     ///
     /// This creates a deterministic signature over the canonical JSON representation
@@ -174,21 +220,12 @@ impl PlcSigner {
         // Ensure sig field is not set
         operation.sig = None;
 
-        // Serialize to canonical JSON (sorted keys)
-        let canonical_json = serde_json::to_vec(&operation).map_err(|e| {
-            PdsError::Internal(format!("Failed to serialize operation: {}", e))
-        })?;
-
-        // Hash the JSON
-        let mut hasher = Sha256::new();
-        hasher.update(&canonical_json);
-        let hash = hasher.finalize();
-
-        // Sign the hash
-        let signature: Signature = self.signing_key.sign(&hash);
+        // Sign the operation digest
+        let digest = operation_signing_digest(&operation)?;
+        let signature = self.sign_prehash(&digest)?;
 
         // Encode signature as hex
-        let sig_hex = hex::encode(signature.to_bytes());
+        let sig_hex = hex::encode(signature);
 
         // Add signature to operation
         operation.sig = Some(sig_hex);
@@ -228,6 +265,17 @@ impl PlcSigner {
     }
 }
 
+/// Digest signed for a PLC operation
+///
+/// The canonical JSON (without `sig`) is hashed, and the signer hashes that
+/// hash once more. Key backends that sign digests directly sign this value.
+pub fn operation_signing_digest<T: Serialize>(operation: &T) -> PdsResult<[u8; 32]> {
+    let canonical_json = serde_json::to_vec(operation)
+        .map_err(|e| PdsError::Internal(format!("Failed to serialize operation: {}", e)))?;
+    let hash = Sha256::digest(&canonical_json);
+    Ok(Sha256::digest(hash).into())
+}
+
 /// Validate a PLC operation structure
 pub fn validate_plc_operation(operation: &PlcOperation) -> PdsResult<()> {
     // Check type
@@ -266,6 +314,7 @@ pub fn validate_plc_operation(operation: &PlcOperation) -> PdsResult<()> {
 /// Register a PLC DID with the PLC Directory
 ///
 /// Submits a signed PLC operation to the directory to create or update a DID
+#[tracing::instrument(skip(operation), fields(did = %operation.did))]
 pub async fn register_plc_did(
     plc_url: &str,
     operation: PlcOperation,
@@ -281,12 +330,11 @@ pub async fn register_plc_did(
     let endpoint = format!("{}/{}", plc_url.trim_end_matches('/'), operation.did);
 
     // Submit operation to PLC directory
-    let response = client
-        .post(&endpoint)
+    let response = inject_trace_context(client.post(&endpoint))
         .json(&operation)
         .send()
         .await
-        .map_err(|e| PdsError::Internal(format!("PLC registration request failed: {}", e)))?;
+        .map_err(|e| PdsError::Plc(format!("PLC registration request failed: {}", e)))?;
 
     if response.status().is_success() {
         // Return the DID
@@ -294,16 +342,92 @@ pub async fn register_plc_did(
     } else {
         let status = response.status();
         let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-        Err(PdsError::Internal(format!(
+        Err(PdsError::Plc(format!(
             "PLC directory returned error {}: {}",
             status, error_body
         )))
     }
 }
 
+/// Submit a signed tombstone for `did` to the PLC Directory
+#[tracing::instrument(skip(tombstone))]
+pub async fn submit_plc_tombstone(plc_url: &str, did: &str, tombstone: &PlcTombstone) -> PdsResult<()> {
+    if tombstone.sig.is_none() {
+        return Err(PdsError::Validation("Tombstone must be signed".to_string()));
+    }
+
+    let endpoint = format!("{}/{}", plc_url.trim_end_matches('/'), did);
+    let response = inject_trace_context(reqwest::Client::new().post(&endpoint))
+        .json(tombstone)
+        .send()
+        .await
+        .map_err(|e| PdsError::Plc(format!("PLC tombstone request failed: {}", e)))?;
+
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        let status = response.status();
+        let error_body = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+        Err(PdsError::Plc(format!(
+            "PLC directory returned error {}: {}",
+            status, error_body
+        )))
+    }
+}
+
+/// Fetch the most recent (non-nullified) operation for a DID from the PLC audit log
+///
+/// Returns the operation CID (used as `prev` for the next update) and the operation itself.
+#[tracing::instrument]
+pub async fn fetch_last_plc_operation(
+    plc_url: &str,
+    did: &str,
+) -> PdsResult<(String, serde_json::Value)> {
+    let endpoint = format!("{}/{}/log/audit", plc_url.trim_end_matches('/'), did);
+
+    let response = inject_trace_context(reqwest::Client::new().get(&endpoint))
+        .send()
+        .await
+        .map_err(|e| PdsError::Plc(format!("Failed to contact PLC directory: {}", e)))?;
+
+    if !response.status().is_success() {
+        return Err(PdsError::Plc(format!(
+            "PLC directory returned error: {}",
+            response.status()
+        )));
+    }
+
+    let audit_log: serde_json::Value = response
+        .json()
+        .await
+        .map_err(|e| PdsError::Plc(format!("Failed to parse PLC audit log: {}", e)))?;
+
+    let last = audit_log
+        .as_array()
+        .and_then(|entries| {
+            entries
+                .iter()
+                .rev()
+                .find(|e| !e.get("nullified").and_then(|n| n.as_bool()).unwrap_or(false))
+        })
+        .ok_or_else(|| PdsError::Plc(format!("No PLC operations found for {}", did)))?;
+
+    let cid = last
+        .get("cid")
+        .and_then(|c| c.as_str())
+        .ok_or_else(|| PdsError::Plc("PLC audit log missing CID".to_string()))?;
+    let operation = last
+        .get("operation")
+        .cloned()
+        .ok_or_else(|| PdsError::Plc("PLC audit log missing operation".to_string()))?;
+
+    Ok((cid.to_string(), operation))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use k256::ecdsa::Signature;
 
     #[test]
     fn test_plc_signer_creation() {
@@ -328,6 +452,20 @@ mod tests {
         assert!(signer.is_ok());
     }
 
+    #[test]
+    fn test_sign_prehash_verifies_against_digest() {
+        use k256::ecdsa::signature::hazmat::PrehashVerifier;
+
+        let signer = PlcSigner::new(&[1u8; 32]).unwrap();
+        let hash: [u8; 32] = Sha256::digest(b"commit").into();
+        let sig = Signature::from_slice(&signer.sign_prehash(&hash).unwrap()).unwrap();
+
+        assert!(signer.verifying_key().verify_prehash(&hash, &sig).is_ok());
+        // `sign` hashes again, so its signature does not cover the digest
+        let double = Signature::from_slice(&signer.sign(&hash)).unwrap();
+        assert!(signer.verifying_key().verify_prehash(&hash, &double).is_err());
+    }
+
     #[test]
     fn test_plc_operation_builder() {
         let operation = PlcOperationBuilder::new()
@@ -436,4 +574,12 @@ mod tests {
         // Signatures should be identical for identical operations
         assert_eq!(signed1.sig, signed2.sig);
     }
+    #[test]
+    fn test_tombstone_shape() {
+        let tombstone = PlcTombstone::new("bafyprev".to_string());
+        assert_eq!(
+            serde_json::to_value(&tombstone).unwrap(),
+            serde_json::json!({ "type": "plc_tombstone", "prev": "bafyprev" })
+        );
+    }
 }
@@ -0,0 +1,113 @@
+/// Inter-service authentication tokens
+///
+/// Service auth JWTs are signed by the account's atproto signing key (ES256K)
+/// and let another service (AppView, moderation service, ...) verify that a
+/// request was made on behalf of `iss`. `aud` is the receiving service DID and
+/// `lxm` binds the token to a single XRPC method.
+use super::signing_keys::AccountSigningKeys;
+use crate::error::{PdsError, PdsResult};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// Default token lifetime in seconds
+pub const SERVICE_JWT_TTL_SECS: i64 = 60;
+
+/// Service auth JWT claims
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ServiceJwtClaims {
+    pub iss: String,
+    pub aud: String,
+    pub exp: i64,
+    pub iat: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lxm: Option<String>,
+    pub jti: String,
+}
+
+impl ServiceJwtClaims {
+    /// Claims for a token valid for `SERVICE_JWT_TTL_SECS`
+    pub fn new(iss: &str, aud: &str, lxm: Option<&str>) -> Self {
+        let now = chrono::Utc::now().timestamp();
+        let mut jti = [0u8; 16];
+        rand::thread_rng().fill_bytes(&mut jti);
+
+        Self {
+            iss: iss.to_string(),
+            aud: aud.to_string(),
+            exp: now + SERVICE_JWT_TTL_SECS,
+            iat: now,
+            lxm: lxm.map(String::from),
+            jti: hex::encode(jti),
+        }
+    }
+}
+
+/// Sign service auth claims as a compact ES256K JWT with the `iss` account's key
+pub async fn create_service_jwt(keys: &AccountSigningKeys, claims: &ServiceJwtClaims) -> PdsResult<String> {
+    let header = serde_json::json!({ "typ": "JWT", "alg": "ES256K" });
+    let signing_input = format!("{}.{}", encode_segment(&header)?, encode_segment(claims)?);
+    let signature = keys.sign(&claims.iss, signing_input.as_bytes()).await?;
+
+    Ok(format!("{}.{}", signing_input, URL_SAFE_NO_PAD.encode(signature)))
+}
+
+fn encode_segment<T: Serialize>(value: &T) -> PdsResult<String> {
+    let bytes = serde_json::to_vec(value)
+        .map_err(|e| PdsError::Internal(format!("Failed to encode service JWT: {}", e)))?;
+    Ok(URL_SAFE_NO_PAD.encode(bytes))
+}
+
+/// Decode the claims of a service JWT without verifying it
+pub fn decode_service_jwt_claims(token: &str) -> PdsResult<ServiceJwtClaims> {
+    let payload = token
+        .split('.')
+        .nth(1)
+        .ok_or_else(|| PdsError::Authentication("Malformed service JWT".to_string()))?;
+    let bytes = URL_SAFE_NO_PAD
+        .decode(payload)
+        .map_err(|_| PdsError::Authentication("Malformed service JWT".to_string()))?;
+
+    serde_json::from_slice(&bytes)
+        .map_err(|e| PdsError::Authentication(format!("Invalid service JWT claims: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::{keys::KeyManager, plc::PlcSigner};
+    use std::{path::PathBuf, sync::Arc};
+    use k256::ecdsa::{signature::Verifier, Signature};
+
+    #[tokio::test]
+    async fn test_service_jwt_roundtrip() {
+        let key_hex = hex::encode([7u8; 32]);
+        // No signing_key file, so the server repo key signs
+        let keys = AccountSigningKeys::new(
+            Arc::new(KeyManager::from_plaintext(&key_hex, &key_hex)),
+            PathBuf::from("./test_data/service_auth"),
+        );
+        let signer = PlcSigner::from_hex(&key_hex).unwrap();
+        let claims = ServiceJwtClaims::new(
+            "did:plc:user",
+            "did:plc:modservice",
+            Some("com.atproto.moderation.createReport"),
+        );
+
+        let token = create_service_jwt(&keys, &claims).await.unwrap();
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let header: serde_json::Value =
+            serde_json::from_slice(&URL_SAFE_NO_PAD.decode(parts[0]).unwrap()).unwrap();
+        assert_eq!(header["alg"], "ES256K");
+        assert_eq!(decode_service_jwt_claims(&token).unwrap(), claims);
+
+        let signature = Signature::from_slice(&URL_SAFE_NO_PAD.decode(parts[2]).unwrap()).unwrap();
+        let signing_input = format!("{}.{}", parts[0], parts[1]);
+        assert!(signer
+            .verifying_key()
+            .verify(signing_input.as_bytes(), &signature)
+            .is_ok());
+    }
+}
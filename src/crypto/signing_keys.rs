@@ -0,0 +1,197 @@
+/// Per-account commit signing keys
+///
+/// Each account signs its repository commits with its own secp256k1 key,
+/// the one published as `#atproto` in its DID document. It is unrelated to
+/// the account's PLC rotation key, which only ever signs PLC operations.
+///
+/// Keys are created in the key backend like rotation keys, and the
+/// reference (a provider key id, or the hex key with the `config` backend)
+/// is kept in the actor's `signing_key` file. Accounts created before
+/// per-account keys have no file and keep signing with the server repo key
+/// until they rotate.
+use crate::{
+    actor_store::get_actor_location,
+    crypto::keys::{KeyManager, SignFuture},
+    error::{PdsError, PdsResult},
+};
+use chrono::{DateTime, Utc};
+use k256::ecdsa::VerifyingKey;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+/// Contents of an actor's `signing_key` file
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SigningKeyFile {
+    version: u32,
+    /// Reference of the key new commits are signed with
+    current: String,
+    /// Key replaced by the last rotation, kept for auditing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    previous: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rotated_at: Option<DateTime<Utc>>,
+}
+
+/// Commit signing keys of local accounts
+pub struct AccountSigningKeys {
+    keys: Arc<KeyManager>,
+    actor_directory: PathBuf,
+    /// DID -> current key reference
+    cache: RwLock<HashMap<String, String>>,
+}
+
+impl AccountSigningKeys {
+    pub fn new(keys: Arc<KeyManager>, actor_directory: PathBuf) -> Self {
+        Self {
+            keys,
+            actor_directory,
+            cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    fn key_path(&self, did: &str) -> PathBuf {
+        get_actor_location(&self.actor_directory, did).signing_key_location
+    }
+
+    /// Create a key in the backend without assigning it to an account
+    ///
+    /// The key only takes effect once `store`d, so it can be published in
+    /// the DID document first.
+    pub async fn generate(&self) -> PdsResult<(String, VerifyingKey)> {
+        let key_ref = self.keys.generate_account_key().await?;
+        let public_key = self.keys.public_key(&key_ref).await?;
+        Ok((key_ref, public_key))
+    }
+
+    /// Sign the account's future commits with `key_ref`
+    pub async fn store(&self, did: &str, key_ref: &str) -> PdsResult<()> {
+        let previous = self.read_file(did).await?.map(|file| file.current);
+        let file = SigningKeyFile {
+            version: 1,
+            current: key_ref.to_string(),
+            rotated_at: previous.as_ref().map(|_| Utc::now()),
+            previous,
+        };
+        let bytes = serde_json::to_vec_pretty(&file)
+            .map_err(|e| PdsError::Internal(format!("Failed to encode signing key file: {}", e)))?;
+
+        // Readable only by the server user: with the config backend the
+        // reference is the private key itself
+        let path = self.key_path(did);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, bytes).await?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            tokio::fs::set_permissions(&tmp, std::fs::Permissions::from_mode(0o600)).await?;
+        }
+        tokio::fs::rename(&tmp, &path).await?;
+
+        self.cache.write().unwrap().insert(did.to_string(), key_ref.to_string());
+        Ok(())
+    }
+
+    /// The account's key reference, if it has its own key
+    pub async fn key_ref(&self, did: &str) -> PdsResult<Option<String>> {
+        if let Some(key_ref) = self.cache.read().unwrap().get(did) {
+            return Ok(Some(key_ref.clone()));
+        }
+
+        let key_ref = self.read_file(did).await?.map(|file| file.current);
+        if let Some(key_ref) = &key_ref {
+            self.cache.write().unwrap().insert(did.to_string(), key_ref.clone());
+        }
+        Ok(key_ref)
+    }
+
+    /// Public key the account's commits are signed with
+    pub async fn public_key(&self, did: &str) -> PdsResult<VerifyingKey> {
+        match self.key_ref(did).await? {
+            Some(key_ref) => self.keys.public_key(&key_ref).await,
+            None => self.keys.repo_public_key().await,
+        }
+    }
+
+    /// Sign arbitrary bytes (hashed with SHA-256) with the account's key
+    pub async fn sign(&self, did: &str, data: &[u8]) -> PdsResult<Vec<u8>> {
+        let digest: [u8; 32] = Sha256::digest(data).into();
+        match self.key_ref(did).await? {
+            Some(key_ref) => self.keys.sign_prehash(&key_ref, &digest).await,
+            None => self.keys.sign_commit(&digest).await,
+        }
+    }
+
+    /// Commit signer for the account's `RepositoryManager` writes
+    ///
+    /// Commits are signed with the account's signing key, or the server
+    /// commit key for accounts that don't have one.
+    pub async fn commit_signer(
+        &self,
+        did: &str,
+    ) -> PdsResult<impl FnOnce([u8; 32]) -> SignFuture + Send + 'static> {
+        Ok(self.keys.commit_signer_for(self.key_ref(did).await?))
+    }
+
+    async fn read_file(&self, did: &str) -> PdsResult<Option<SigningKeyFile>> {
+        let bytes = match tokio::fs::read(self.key_path(did)).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&bytes)
+            .map(Some)
+            .map_err(|e| PdsError::Validation(format!("Corrupt signing key file for {}: {}", did, e)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature};
+
+    fn manager(name: &str) -> AccountSigningKeys {
+        let keys = KeyManager::from_plaintext(&hex::encode([5u8; 32]), &hex::encode([6u8; 32]));
+        let dir = PathBuf::from(format!("./test_data/signing_keys/{}", name));
+        let _ = std::fs::remove_dir_all(&dir);
+        AccountSigningKeys::new(Arc::new(keys), dir)
+    }
+
+    #[tokio::test]
+    async fn test_account_key_and_rotation() {
+        let did = "did:plc:signer";
+        let keys = manager("rotation");
+        let server_key = keys.keys.repo_public_key().await.unwrap();
+
+        // Without its own key an account signs with the server key
+        assert!(keys.key_ref(did).await.unwrap().is_none());
+        assert_eq!(keys.public_key(did).await.unwrap(), server_key);
+
+        let (first, first_public) = keys.generate().await.unwrap();
+        keys.store(did, &first).await.unwrap();
+        assert_eq!(keys.public_key(did).await.unwrap(), first_public);
+        assert_ne!(first_public, server_key);
+
+        let hash = [7u8; 32];
+        let sig = keys.commit_signer(did).await.unwrap()(hash).await.unwrap();
+        let sig = Signature::from_slice(&sig).unwrap();
+        assert!(first_public.verify_prehash(&hash, &sig).is_ok());
+
+        // Rotation switches future signatures and survives a restart
+        let (second, second_public) = keys.generate().await.unwrap();
+        keys.store(did, &second).await.unwrap();
+        let reopened = AccountSigningKeys::new(keys.keys.clone(), keys.actor_directory.clone());
+        assert_eq!(reopened.public_key(did).await.unwrap(), second_public);
+        let file = reopened.read_file(did).await.unwrap().unwrap();
+        assert_eq!(file.previous.as_deref(), Some(first.as_str()));
+        assert!(file.rotated_at.is_some());
+    }
+}
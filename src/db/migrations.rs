@@ -0,0 +1,686 @@
+/// Migration status and guarded upgrades
+///
+/// The installer creates the account database from `install.sh` and records
+/// every migration it contains in `_sqlx_migrations`, with placeholder
+/// checksums that sqlx's own runner rejects, so the server never migrates
+/// at startup. Instead:
+///
+/// - `aurora-locus migrate status [--json]` compares the recorded versions
+///   with the migrations compiled into the binary
+/// - `aurora-locus migrate upgrade [--backup-dir DIR]` copies the database
+///   with `VACUUM INTO`, checks the copy, then applies the pending
+///   migrations one transaction each; on failure it stops and prints how to
+///   restore the copy
+/// - `aurora-locus migrate actor-stores [--concurrency N]` upgrades every
+///   actor store (see `actor_store::migrations`)
+///
+/// The sequencer log and DID cache are tables in the account database and
+/// share its migrations. Actor stores upgrade themselves when opened, or all
+/// at once with `migrate actor-stores`; their `PRAGMA user_version` is
+/// reported alongside.
+use crate::{
+    actor_store::{migrations as actor_migrations, store::ACTOR_SCHEMA_VERSION, ActorStore, ActorStoreConfig},
+    config::ServerConfig,
+    crypto::{data_keys::DataKeyManager, keys::KeyManager},
+    error::{PdsError, PdsResult},
+};
+use serde::Serialize;
+use sqlx::{
+    migrate::{Migration, Migrator},
+    sqlite::SqliteConnectOptions,
+    Row, SqlitePool,
+};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Migrations of the account database, embedded at compile time
+pub static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
+/// Tables whose schema lives in the account database besides its own
+const SHARED_DATABASES: [&str; 2] = ["sequencer", "did_cache"];
+
+/// A migration, by version
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationInfo {
+    pub version: i64,
+    pub description: String,
+}
+
+impl From<&Migration> for MigrationInfo {
+    fn from(migration: &Migration) -> Self {
+        Self {
+            version: migration.version,
+            description: migration.description.to_string(),
+        }
+    }
+}
+
+/// Applied and pending migrations of one database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DatabaseStatus {
+    pub name: String,
+    pub path: String,
+    /// Database whose file, and so migrations, this one shares
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shared_with: Option<String>,
+    pub applied: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_applied: Option<i64>,
+    pub pending: Vec<MigrationInfo>,
+    /// Recorded versions this binary doesn't know (upgraded by a newer release)
+    pub unknown: Vec<i64>,
+    /// Recorded versions marked as failed
+    pub failed: Vec<i64>,
+}
+
+/// Actor stores by schema version
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ActorStoreStatus {
+    /// Version stores are brought to
+    pub schema_version: i64,
+    pub stores: usize,
+    /// Stores below `schema_version`; they upgrade when next opened
+    pub behind: usize,
+    /// Stores that could not be opened
+    pub unreadable: usize,
+    /// Store count per version, oldest first
+    pub versions: Vec<StoreVersionCount>,
+}
+
+/// Actor stores at one schema version
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StoreVersionCount {
+    pub version: i64,
+    pub stores: usize,
+}
+
+/// Migration status of every database
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MigrationStatus {
+    pub databases: Vec<DatabaseStatus>,
+    pub actor_stores: ActorStoreStatus,
+}
+
+impl MigrationStatus {
+    /// Pending migrations across databases, counting shared files once
+    pub fn pending(&self) -> usize {
+        self.databases
+            .iter()
+            .filter(|db| db.shared_with.is_none())
+            .map(|db| db.pending.len())
+            .sum()
+    }
+
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for db in &self.databases {
+            if let Some(owner) = &db.shared_with {
+                out.push_str(&format!("{}: stored in the {} database\n", db.name, owner));
+                continue;
+            }
+
+            out.push_str(&format!(
+                "{} ({}): {} applied (latest {}), {} pending\n",
+                db.name,
+                db.path,
+                db.applied,
+                db.latest_applied.map_or_else(|| "none".to_string(), |v| v.to_string()),
+                db.pending.len()
+            ));
+            for migration in &db.pending {
+                out.push_str(&format!("  pending {} {}\n", migration.version, migration.description));
+            }
+            for version in &db.failed {
+                out.push_str(&format!("  FAILED  {} (restore a backup before upgrading)\n", version));
+            }
+            if !db.unknown.is_empty() {
+                out.push_str(&format!(
+                    "  {} migration(s) from a newer release: {:?}\n",
+                    db.unknown.len(),
+                    db.unknown
+                ));
+            }
+        }
+
+        let stores = &self.actor_stores;
+        out.push_str(&format!(
+            "actor stores: {} at version {}, {} behind (upgraded when opened or by `migrate actor-stores`), {} unreadable\n",
+            stores.stores - stores.behind - stores.unreadable,
+            stores.schema_version,
+            stores.behind,
+            stores.unreadable
+        ));
+        if self.pending() > 0 {
+            out.push_str("Run `aurora-locus migrate upgrade` with the server stopped to apply pending migrations\n");
+        }
+        out
+    }
+}
+
+/// Versions recorded in `_sqlx_migrations`, with whether they succeeded
+async fn recorded_versions(pool: &SqlitePool) -> PdsResult<Vec<(i64, bool)>> {
+    let tracked: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = '_sqlx_migrations'")
+            .fetch_one(pool)
+            .await?;
+    if tracked == 0 {
+        return Ok(Vec::new());
+    }
+
+    let rows = sqlx::query("SELECT version, success FROM _sqlx_migrations ORDER BY version")
+        .fetch_all(pool)
+        .await?;
+    Ok(rows
+        .iter()
+        .map(|row| (row.get::<i64, _>("version"), row.get::<bool, _>("success")))
+        .collect())
+}
+
+/// Up migrations of `migrator`, in version order
+fn up_migrations(migrator: &Migrator) -> impl Iterator<Item = &Migration> {
+    migrator.iter().filter(|m| !m.migration_type.is_down_migration())
+}
+
+/// Compare a database's recorded migrations with `migrator`
+pub async fn database_status(
+    name: &str,
+    path: &Path,
+    pool: &SqlitePool,
+    migrator: &Migrator,
+) -> PdsResult<DatabaseStatus> {
+    let recorded: HashMap<i64, bool> = recorded_versions(pool).await?.into_iter().collect();
+    let known: Vec<i64> = up_migrations(migrator).map(|m| m.version).collect();
+
+    let mut unknown: Vec<i64> = recorded.keys().filter(|v| !known.contains(v)).copied().collect();
+    unknown.sort_unstable();
+    let mut failed: Vec<i64> = recorded.iter().filter(|(_, ok)| !**ok).map(|(v, _)| *v).collect();
+    failed.sort_unstable();
+
+    Ok(DatabaseStatus {
+        name: name.to_string(),
+        path: path.display().to_string(),
+        shared_with: None,
+        applied: recorded.values().filter(|ok| **ok).count(),
+        latest_applied: recorded.iter().filter(|(_, ok)| **ok).map(|(v, _)| *v).max(),
+        pending: up_migrations(migrator)
+            .filter(|m| !recorded.contains_key(&m.version))
+            .map(MigrationInfo::from)
+            .collect(),
+        unknown,
+        failed,
+    })
+}
+
+/// Count actor stores by `PRAGMA user_version`
+pub async fn actor_store_status(account_db: &SqlitePool, actor_store: &ActorStore) -> PdsResult<ActorStoreStatus> {
+    let dids: Vec<String> = sqlx::query_scalar("SELECT did FROM account ORDER BY did")
+        .fetch_all(account_db)
+        .await?;
+
+    let mut status = ActorStoreStatus {
+        schema_version: ACTOR_SCHEMA_VERSION,
+        stores: 0,
+        behind: 0,
+        unreadable: 0,
+        versions: Vec::new(),
+    };
+    let mut versions: BTreeMap<i64, usize> = BTreeMap::new();
+    for did in dids {
+        if !actor_store.exists(&did).await {
+            continue;
+        }
+        status.stores += 1;
+        match actor_store.schema_version(&did).await {
+            Ok(version) => {
+                *versions.entry(version).or_default() += 1;
+                if version < ACTOR_SCHEMA_VERSION {
+                    status.behind += 1;
+                }
+            }
+            Err(e) => {
+                tracing::warn!(did = %did, error = %e, "Failed to read actor store schema version");
+                status.unreadable += 1;
+            }
+        }
+    }
+
+    status.versions = versions
+        .into_iter()
+        .map(|(version, stores)| StoreVersionCount { version, stores })
+        .collect();
+    Ok(status)
+}
+
+/// Migration status of the account database (and the databases stored in
+/// it) and of the actor stores
+pub async fn status(config: &ServerConfig, account_db: &SqlitePool, actor_store: &ActorStore) -> PdsResult<MigrationStatus> {
+    let account = database_status("account", &config.storage.account_db, account_db, &MIGRATOR).await?;
+
+    let mut databases = vec![account.clone()];
+    for name in SHARED_DATABASES {
+        databases.push(DatabaseStatus {
+            name: name.to_string(),
+            shared_with: Some(account.name.clone()),
+            ..account.clone()
+        });
+    }
+
+    Ok(MigrationStatus {
+        databases,
+        actor_stores: actor_store_status(account_db, actor_store).await?,
+    })
+}
+
+/// Outcome of a guarded upgrade
+#[derive(Debug, Default)]
+pub struct UpgradeReport {
+    /// Copy of the database taken before applying anything
+    pub backup: Option<PathBuf>,
+    pub applied: Vec<MigrationInfo>,
+}
+
+impl UpgradeReport {
+    pub fn summary(&self) -> String {
+        if self.applied.is_empty() {
+            return "No pending migrations; nothing to do\n".to_string();
+        }
+
+        let mut out = String::new();
+        if let Some(backup) = &self.backup {
+            out.push_str(&format!("Backup: {}\n", backup.display()));
+        }
+        for migration in &self.applied {
+            out.push_str(&format!("applied {} {}\n", migration.version, migration.description));
+        }
+        out.push_str(&format!("{} migration(s) applied\n", self.applied.len()));
+        out
+    }
+}
+
+/// Copy a database to `backup` and check the copy
+async fn backup_database(pool: &SqlitePool, backup: &Path) -> PdsResult<()> {
+    if backup.exists() {
+        return Err(PdsError::Validation(format!("Backup {} already exists", backup.display())));
+    }
+    if let Some(parent) = backup.parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+
+    sqlx::query("VACUUM INTO ?1")
+        .bind(backup.to_string_lossy().to_string())
+        .execute(pool)
+        .await?;
+
+    let copy = SqlitePool::connect_with(SqliteConnectOptions::new().filename(backup).read_only(true)).await?;
+    let check: String = sqlx::query_scalar("PRAGMA quick_check").fetch_one(&copy).await?;
+    copy.close().await;
+    if check != "ok" {
+        return Err(PdsError::Internal(format!(
+            "Backup {} failed its integrity check ({}); nothing was migrated",
+            backup.display(),
+            check
+        )));
+    }
+    Ok(())
+}
+
+/// How to undo a failed upgrade
+fn rollback_instructions(db_path: &Path, backup: &Path) -> String {
+    let db = db_path.display();
+    format!(
+        "To roll back, stop the server and restore the backup taken before the upgrade:\n  \
+         cp '{}' '{}'\n  rm -f '{}-wal' '{}-shm'",
+        backup.display(),
+        db,
+        db,
+        db
+    )
+}
+
+/// Apply one migration and record it
+async fn apply(pool: &SqlitePool, migration: &Migration) -> PdsResult<()> {
+    let started = Instant::now();
+    let record = "INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time) \
+                  VALUES (?1, ?2, 1, ?3, ?4)";
+
+    if migration.no_tx {
+        sqlx::raw_sql(&migration.sql).execute(pool).await?;
+        sqlx::query(record)
+            .bind(migration.version)
+            .bind(&*migration.description)
+            .bind(&*migration.checksum)
+            .bind(started.elapsed().as_nanos() as i64)
+            .execute(pool)
+            .await?;
+        return Ok(());
+    }
+
+    let mut tx = pool.begin().await?;
+    sqlx::raw_sql(&migration.sql).execute(&mut *tx).await?;
+    sqlx::query(record)
+        .bind(migration.version)
+        .bind(&*migration.description)
+        .bind(&*migration.checksum)
+        .bind(started.elapsed().as_nanos() as i64)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Back up a database, then apply its pending migrations in order
+///
+/// Stops at the first failure; that migration's transaction is rolled back,
+/// and the error explains how to restore the backup to also undo the ones
+/// applied before it.
+pub async fn upgrade(pool: &SqlitePool, db_path: &Path, backup: &Path, migrator: &Migrator) -> PdsResult<UpgradeReport> {
+    let status = database_status("database", db_path, pool, migrator).await?;
+    if let Some(version) = status.failed.first() {
+        return Err(PdsError::Validation(format!(
+            "Migration {} is recorded as failed; restore a backup of {} before upgrading",
+            version,
+            db_path.display()
+        )));
+    }
+    if status.pending.is_empty() {
+        return Ok(UpgradeReport::default());
+    }
+
+    backup_database(pool, backup).await?;
+    tracing::info!(backup = %backup.display(), "Backed up {} before migrating", db_path.display());
+
+    sqlx::raw_sql(
+        "CREATE TABLE IF NOT EXISTS _sqlx_migrations (
+            version BIGINT PRIMARY KEY NOT NULL,
+            description TEXT NOT NULL,
+            installed_on TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+            success BOOLEAN NOT NULL,
+            checksum BLOB NOT NULL,
+            execution_time BIGINT NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await?;
+
+    let mut report = UpgradeReport {
+        backup: Some(backup.to_path_buf()),
+        applied: Vec::new(),
+    };
+    for migration in up_migrations(migrator).filter(|m| status.pending.iter().any(|p| p.version == m.version)) {
+        if let Err(e) = apply(pool, migration).await {
+            let undone = if migration.no_tx {
+                "It ran outside a transaction and may be partly applied."
+            } else {
+                "Its changes were rolled back."
+            };
+            return Err(PdsError::Internal(format!(
+                "Migration {} ({}) failed: {}. {} {} earlier migration(s) from this run stay applied. {}",
+                migration.version,
+                migration.description,
+                e,
+                undone,
+                report.applied.len(),
+                rollback_instructions(db_path, backup)
+            )));
+        }
+        tracing::info!(version = migration.version, "Applied migration {}", migration.description);
+        report.applied.push(MigrationInfo::from(migration));
+    }
+
+    Ok(report)
+}
+
+/// What `aurora-locus migrate` should do
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrateCommand {
+    Status { json: bool },
+    Upgrade { backup_dir: Option<PathBuf> },
+    ActorStores { concurrency: usize },
+}
+
+/// Options of `aurora-locus migrate [status [--json] | upgrade [--backup-dir DIR] | actor-stores [--concurrency N]]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrateOptions {
+    pub command: MigrateCommand,
+}
+
+impl MigrateOptions {
+    /// Parse options from the arguments following `migrate`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let (command, flags) = match args.split_first() {
+            Some((command, flags)) if !command.starts_with("--") => (command.as_str(), flags),
+            _ => ("status", args),
+        };
+
+        let mut json = false;
+        let mut backup_dir = None;
+        let mut concurrency = actor_migrations::DEFAULT_CONCURRENCY;
+        let mut iter = flags.iter();
+        while let Some(flag) = iter.next() {
+            match (command, flag.as_str()) {
+                ("status", "--json") => json = true,
+                ("upgrade", "--backup-dir") => {
+                    let dir = iter
+                        .next()
+                        .ok_or_else(|| PdsError::Validation("Missing value for --backup-dir".to_string()))?;
+                    backup_dir = Some(PathBuf::from(dir));
+                }
+                ("actor-stores", "--concurrency") => {
+                    concurrency = iter
+                        .next()
+                        .and_then(|n| n.parse().ok())
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| PdsError::Validation("--concurrency needs a positive number".to_string()))?;
+                }
+                (_, other) => {
+                    return Err(PdsError::Validation(format!("Unknown migrate {} option: {}", command, other)))
+                }
+            }
+        }
+
+        let command = match command {
+            "status" => MigrateCommand::Status { json },
+            "upgrade" => MigrateCommand::Upgrade { backup_dir },
+            "actor-stores" => MigrateCommand::ActorStores { concurrency },
+            other => {
+                return Err(PdsError::Validation(format!(
+                    "Unknown migrate command: {} (expected status, upgrade or actor-stores)",
+                    other
+                )))
+            }
+        };
+        Ok(Self { command })
+    }
+}
+
+/// Actor store able to open encrypted stores, for the CLI
+async fn open_actor_store(config: &ServerConfig) -> PdsResult<ActorStore> {
+    let keys = Arc::new(KeyManager::from_config(config).await?);
+    let data_keys = DataKeyManager::new(
+        keys,
+        &config.encryption.master_key_id,
+        config.storage.actor_store_directory.clone(),
+    );
+    Ok(ActorStore::new(ActorStoreConfig {
+        base_directory: config.storage.actor_store_directory.clone(),
+        cache_size: 1,
+    })
+    .with_encryption(Arc::new(data_keys), false))
+}
+
+/// Run `aurora-locus migrate`, returning what to print
+///
+/// Runs before the server opens its databases, so an upgrade never races
+/// the server's own writes as long as the server is stopped.
+pub async fn run(config: &ServerConfig, opts: &MigrateOptions) -> PdsResult<String> {
+    let account_path = &config.storage.account_db;
+    if !account_path.exists() {
+        return Err(PdsError::NotFound(format!(
+            "Account database {} does not exist; run install.sh first",
+            account_path.display()
+        )));
+    }
+    let account_db = super::create_pool(account_path, super::DatabaseOptions::default()).await?;
+
+    let output = match &opts.command {
+        MigrateCommand::Status { json } => {
+            let actor_store = open_actor_store(config).await?;
+            let status = status(config, &account_db, &actor_store).await?;
+            if *json {
+                serde_json::to_string_pretty(&status)
+                    .map_err(|e| PdsError::Internal(format!("Failed to encode status: {}", e)))?
+                    + "\n"
+            } else {
+                status.summary()
+            }
+        }
+        MigrateCommand::Upgrade { backup_dir } => {
+            let dir = backup_dir
+                .clone()
+                .unwrap_or_else(|| config.storage.data_directory.join("backups"));
+            let file_name = account_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_else(|| "account.sqlite".to_string());
+            let backup = dir.join(format!(
+                "pre-migrate-{}-{}",
+                chrono::Utc::now().format("%Y%m%dT%H%M%SZ"),
+                file_name
+            ));
+            upgrade(&account_db, account_path, &backup, &MIGRATOR).await?.summary()
+        }
+        MigrateCommand::ActorStores { concurrency } => {
+            let actor_store = open_actor_store(config).await?;
+            actor_migrations::migrate_all(&actor_store, &account_db, *concurrency)
+                .await?
+                .summary()
+        }
+    };
+
+    account_db.close().await;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    async fn file_pool(dir: &TempDir, name: &str) -> (SqlitePool, PathBuf) {
+        let path = dir.path().join(name);
+        let pool = super::super::create_pool(&path, super::super::DatabaseOptions::default())
+            .await
+            .unwrap();
+        (pool, path)
+    }
+
+    #[tokio::test]
+    async fn test_installer_schema_is_up_to_date() {
+        let pool = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::raw_sql(crate::testing::account_schema()).execute(&pool).await.unwrap();
+
+        let status = database_status("account", Path::new(":memory:"), &pool, &MIGRATOR).await.unwrap();
+        assert!(status.pending.is_empty(), "install.sh lacks {:?}", status.pending);
+        assert!(status.unknown.is_empty());
+        assert_eq!(status.applied, up_migrations(&MIGRATOR).count());
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_backs_up_and_applies() {
+        let dir = TempDir::new().unwrap();
+        let migrations = dir.path().join("migrations");
+        std::fs::create_dir(&migrations).unwrap();
+        std::fs::write(migrations.join("1_first.sql"), "CREATE TABLE first (id INTEGER);").unwrap();
+        std::fs::write(migrations.join("2_second.sql"), "CREATE TABLE second (id INTEGER);").unwrap();
+        let migrator = Migrator::new(migrations.as_path()).await.unwrap();
+
+        let (pool, path) = file_pool(&dir, "db.sqlite").await;
+        sqlx::query("CREATE TABLE existing (id INTEGER)").execute(&pool).await.unwrap();
+        let before = database_status("db", &path, &pool, &migrator).await.unwrap();
+        assert_eq!(before.pending.iter().map(|m| m.version).collect::<Vec<_>>(), vec![1, 2]);
+
+        let backup = dir.path().join("backups/db.sqlite");
+        let report = upgrade(&pool, &path, &backup, &migrator).await.unwrap();
+        assert_eq!(report.applied.len(), 2);
+        assert_eq!(report.backup.as_deref(), Some(backup.as_path()));
+
+        // The backup predates the migrations
+        let copy = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&backup)).await.unwrap();
+        let tables: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&copy)
+            .await
+            .unwrap();
+        assert_eq!(tables, vec!["existing"]);
+
+        let after = database_status("db", &path, &pool, &migrator).await.unwrap();
+        assert!(after.pending.is_empty());
+        assert_eq!(after.latest_applied, Some(2));
+
+        // Nothing pending: no second backup
+        let again = upgrade(&pool, &path, &dir.path().join("backups/again.sqlite"), &migrator).await.unwrap();
+        assert!(again.applied.is_empty() && again.backup.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_failed_upgrade_stops_with_instructions() {
+        let dir = TempDir::new().unwrap();
+        let migrations = dir.path().join("migrations");
+        std::fs::create_dir(&migrations).unwrap();
+        std::fs::write(migrations.join("1_ok.sql"), "CREATE TABLE ok (id INTEGER);").unwrap();
+        std::fs::write(
+            migrations.join("2_broken.sql"),
+            "CREATE TABLE partial (id INTEGER); INSERT INTO missing VALUES (1);",
+        )
+        .unwrap();
+        std::fs::write(migrations.join("3_later.sql"), "CREATE TABLE later (id INTEGER);").unwrap();
+        let migrator = Migrator::new(migrations.as_path()).await.unwrap();
+
+        let (pool, path) = file_pool(&dir, "db.sqlite").await;
+        let backup = dir.path().join("backup.sqlite");
+        let err = upgrade(&pool, &path, &backup, &migrator).await.unwrap_err().to_string();
+        assert!(err.contains("Migration 2 (broken) failed"), "{}", err);
+        assert!(err.contains(&format!("cp '{}' '{}'", backup.display(), path.display())), "{}", err);
+        assert!(backup.exists());
+
+        // The failed migration rolled back; the later one never ran
+        let status = database_status("db", &path, &pool, &migrator).await.unwrap();
+        assert_eq!(status.latest_applied, Some(1));
+        assert_eq!(status.pending.iter().map(|m| m.version).collect::<Vec<_>>(), vec![2, 3]);
+        let partial: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM sqlite_master WHERE name = 'partial'")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(partial, 0);
+    }
+
+    #[test]
+    fn test_migrate_options() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+
+        assert_eq!(MigrateOptions::from_args(&[]).unwrap().command, MigrateCommand::Status { json: false });
+        assert_eq!(
+            MigrateOptions::from_args(&args("--json")).unwrap().command,
+            MigrateCommand::Status { json: true }
+        );
+        assert_eq!(
+            MigrateOptions::from_args(&args("upgrade --backup-dir /tmp/b")).unwrap().command,
+            MigrateCommand::Upgrade {
+                backup_dir: Some(PathBuf::from("/tmp/b"))
+            }
+        );
+        assert_eq!(
+            MigrateOptions::from_args(&args("actor-stores --concurrency 16")).unwrap().command,
+            MigrateCommand::ActorStores { concurrency: 16 }
+        );
+        assert!(MigrateOptions::from_args(&args("actor-stores --concurrency 0")).is_err());
+        assert!(MigrateOptions::from_args(&args("upgrade --json")).is_err());
+        assert!(MigrateOptions::from_args(&args("downgrade")).is_err());
+    }
+}
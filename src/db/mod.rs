@@ -4,6 +4,7 @@
 /// to the account, sequencer, and DID cache databases.
 
 pub mod account;
+pub mod migrations;
 pub mod postgres;
 
 use crate::error::{PdsError, PdsResult};
@@ -0,0 +1,281 @@
+/// Outbound delivery queue
+///
+/// Moderation webhooks, repo webhooks and the ActivityPub bridge queue
+/// their outgoing requests in a table each, with the same bookkeeping
+/// columns (`status`, `attempts`, `last_error`, `response_status`,
+/// `created_at`, `next_attempt_at`, `delivered_at`). A `DeliveryQueue`
+/// names one of those tables and does the rest: picking due rows, recording
+/// the outcome with exponential backoff, listing and pruning. What to join
+/// in and how to send is up to the owner.
+use crate::error::{PdsError, PdsResult};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{sqlite::SqliteRow, Row, SqlitePool};
+use std::future::Future;
+
+/// Deliveries are abandoned after this many failed attempts
+pub const MAX_DELIVERY_ATTEMPTS: i64 = 8;
+
+/// Delay before the first retry; doubles with each further attempt
+const RETRY_BASE_SECS: i64 = 30;
+
+/// Upper bound on the retry delay
+const RETRY_MAX_SECS: i64 = 6 * 3600;
+
+/// Delivered entries are kept this long for inspection
+const DELIVERY_RETENTION_DAYS: i64 = 7;
+
+/// Delivery status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus {
+    Pending,
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Pending => "pending",
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> PdsResult<Self> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(DeliveryStatus::Pending),
+            "delivered" => Ok(DeliveryStatus::Delivered),
+            "failed" => Ok(DeliveryStatus::Failed),
+            _ => Err(PdsError::Validation(format!("Invalid delivery status: {}", s))),
+        }
+    }
+}
+
+/// Delivery of one event to one webhook
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDelivery {
+    pub id: i64,
+    pub webhook_id: i64,
+    pub event_type: String,
+    pub status: DeliveryStatus,
+    pub attempts: i64,
+    pub last_error: Option<String>,
+    pub response_status: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+/// Response status of an attempt and the error, if it failed
+pub type Attempt = (Option<i64>, Option<String>);
+
+/// One delivery table
+#[derive(Clone)]
+pub struct DeliveryQueue {
+    db: SqlitePool,
+    /// Delivery table, aliased `d` in queries
+    table: &'static str,
+    /// Column naming the account that owns a delivery, if any
+    owner: Option<&'static str>,
+}
+
+impl DeliveryQueue {
+    pub fn new(db: SqlitePool, table: &'static str) -> Self {
+        Self { db, table, owner: None }
+    }
+
+    /// Deliveries belong to the account in `column`, which `list` can filter by
+    pub fn with_owner(mut self, column: &'static str) -> Self {
+        self.owner = Some(column);
+        self
+    }
+
+    /// List webhook deliveries, newest first, optionally for one owner,
+    /// webhook or status
+    pub async fn list(
+        &self,
+        owner: Option<&str>,
+        webhook_id: Option<i64>,
+        status: Option<DeliveryStatus>,
+        cursor: Option<i64>,
+        limit: i64,
+    ) -> PdsResult<Vec<WebhookDelivery>> {
+        let owner_filter = match self.owner {
+            Some(column) => format!("(?1 IS NULL OR {} = ?1)", column),
+            None => "?1 IS NULL".to_string(),
+        };
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT id, webhook_id, event_type, status, attempts, last_error, response_status,
+                   created_at, next_attempt_at, delivered_at
+            FROM {}
+            WHERE {}
+              AND (?2 IS NULL OR webhook_id = ?2)
+              AND (?3 IS NULL OR status = ?3)
+              AND (?4 IS NULL OR id < ?4)
+            ORDER BY id DESC
+            LIMIT ?5
+            "#,
+            self.table, owner_filter
+        ))
+        .bind(owner)
+        .bind(webhook_id)
+        .bind(status.map(|s| s.as_str()))
+        .bind(cursor)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter()
+            .map(|row| {
+                let optional_time = |column: &str| -> PdsResult<Option<DateTime<Utc>>> {
+                    row.get::<Option<String>, _>(column)
+                        .map(|s| parse_time(&s))
+                        .transpose()
+                };
+
+                Ok(WebhookDelivery {
+                    id: row.get("id"),
+                    webhook_id: row.get("webhook_id"),
+                    event_type: row.get("event_type"),
+                    status: DeliveryStatus::from_str(&row.get::<String, _>("status"))?,
+                    attempts: row.get("attempts"),
+                    last_error: row.get("last_error"),
+                    response_status: row.get("response_status"),
+                    created_at: parse_time(&row.get::<String, _>("created_at"))?,
+                    next_attempt_at: optional_time("next_attempt_at")?,
+                    delivered_at: optional_time("delivered_at")?,
+                })
+            })
+            .collect()
+    }
+
+    /// Attempt every pending delivery that is due
+    ///
+    /// Each row holds `d.id`, `d.attempts` and `columns`, which may come
+    /// from tables added by `join` (which can also drop rows, e.g. of
+    /// disabled webhooks). `send` makes one attempt. Returns (delivered,
+    /// failed attempts).
+    pub async fn deliver_due<F, Fut>(&self, columns: &str, join: &str, limit: i64, send: F) -> PdsResult<(u64, u64)>
+    where
+        F: Fn(SqliteRow) -> Fut,
+        Fut: Future<Output = Attempt>,
+    {
+        let rows = sqlx::query(&format!(
+            r#"
+            SELECT d.id, d.attempts, {}
+            FROM {} d
+            {}
+            WHERE d.status = 'pending' AND d.next_attempt_at <= ?1
+            ORDER BY d.next_attempt_at
+            LIMIT ?2
+            "#,
+            columns, self.table, join
+        ))
+        .bind(Utc::now().to_rfc3339())
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        let (mut delivered, mut failed) = (0, 0);
+        for row in rows {
+            let id: i64 = row.get("id");
+            let attempts: i64 = row.get::<i64, _>("attempts") + 1;
+
+            let (status_code, error) = send(row).await;
+
+            match error {
+                None => {
+                    delivered += 1;
+                    sqlx::query(&format!(
+                        r#"
+                        UPDATE {}
+                        SET status = 'delivered', attempts = ?2, response_status = ?3,
+                            last_error = NULL, next_attempt_at = NULL, delivered_at = ?4
+                        WHERE id = ?1
+                        "#,
+                        self.table
+                    ))
+                    .bind(id)
+                    .bind(attempts)
+                    .bind(status_code)
+                    .bind(Utc::now().to_rfc3339())
+                    .execute(&self.db)
+                    .await?;
+                }
+                Some(error) => {
+                    failed += 1;
+                    let next_attempt = retry_delay(attempts).map(|delay| Utc::now() + delay);
+                    if next_attempt.is_some() {
+                        tracing::debug!("Delivery {} in {} failed (attempt {}): {}", id, self.table, attempts, error);
+                    } else {
+                        tracing::warn!("Giving up delivery {} in {} after {} attempts: {}", id, self.table, attempts, error);
+                    }
+                    sqlx::query(&format!(
+                        r#"
+                        UPDATE {}
+                        SET status = ?2, attempts = ?3, response_status = ?4,
+                            last_error = ?5, next_attempt_at = ?6
+                        WHERE id = ?1
+                        "#,
+                        self.table
+                    ))
+                    .bind(id)
+                    .bind(if next_attempt.is_some() { "pending" } else { "failed" })
+                    .bind(attempts)
+                    .bind(status_code)
+                    .bind(&error)
+                    .bind(next_attempt.map(|t| t.to_rfc3339()))
+                    .execute(&self.db)
+                    .await?;
+                }
+            }
+        }
+
+        Ok((delivered, failed))
+    }
+
+    /// Delete delivered entries past the retention window
+    pub async fn prune(&self) -> PdsResult<u64> {
+        let cutoff = Utc::now() - Duration::days(DELIVERY_RETENTION_DAYS);
+        let result = sqlx::query(&format!(
+            "DELETE FROM {} WHERE status = 'delivered' AND delivered_at < ?1",
+            self.table
+        ))
+        .bind(cutoff.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Delay before retrying after `attempts` failures, or None to give up
+pub fn retry_delay(attempts: i64) -> Option<Duration> {
+    if attempts >= MAX_DELIVERY_ATTEMPTS {
+        return None;
+    }
+    let secs = RETRY_BASE_SECS.saturating_mul(1 << (attempts - 1).clamp(0, 20));
+    Some(Duration::seconds(secs.min(RETRY_MAX_SECS)))
+}
+
+pub fn parse_time(s: &str) -> PdsResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        assert_eq!(retry_delay(1), Some(Duration::seconds(30)));
+        assert_eq!(retry_delay(3), Some(Duration::seconds(120)));
+        assert_eq!(retry_delay(MAX_DELIVERY_ATTEMPTS), None);
+    }
+}
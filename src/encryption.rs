@@ -0,0 +1,235 @@
+/// Encryption-at-rest tooling
+///
+/// `aurora-locus encryption <command> [--did DID]`, run while the server is
+/// stopped:
+///
+/// - `status`: which actor stores are encrypted and which master key wraps
+///   each actor's data key
+/// - `encrypt`: encrypt existing plaintext actor stores and/or blobs, per
+///   `PDS_ENCRYPTION_ACTOR_STORES` / `PDS_ENCRYPTION_BLOBS`
+/// - `rotate-master`: re-wrap data keys under the configured master key
+///   (`PDS_ENCRYPTION_MASTER_KEY_ID`); the old master key must still exist
+/// - `rotate-keys`: give actors new data keys and re-encrypt their
+///   encrypted stores and blobs
+///
+/// An interrupted `rotate-keys` leaves both keys in the actor's key file and
+/// is resumed by running it again.
+
+use crate::{context::AppContext, error::{PdsError, PdsResult}};
+
+/// Encryption subcommand
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EncryptionCommand {
+    Status,
+    Encrypt,
+    RotateMaster,
+    RotateKeys,
+}
+
+/// Encryption options (`aurora-locus encryption <command> [--did DID]`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncryptionOptions {
+    pub command: EncryptionCommand,
+    /// Only process this actor
+    pub did: Option<String>,
+}
+
+impl EncryptionOptions {
+    /// Parse options from the arguments following `encryption`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let command = match args.first().map(String::as_str) {
+            Some("status") => EncryptionCommand::Status,
+            Some("encrypt") => EncryptionCommand::Encrypt,
+            Some("rotate-master") => EncryptionCommand::RotateMaster,
+            Some("rotate-keys") => EncryptionCommand::RotateKeys,
+            Some(other) => {
+                return Err(PdsError::Validation(format!(
+                    "Unknown encryption command: {} (expected status, encrypt, rotate-master or rotate-keys)",
+                    other
+                )))
+            }
+            None => {
+                return Err(PdsError::Validation(
+                    "Missing encryption command (status, encrypt, rotate-master or rotate-keys)".to_string(),
+                ))
+            }
+        };
+
+        let mut opts = Self { command, did: None };
+        let mut iter = args[1..].iter();
+        while let Some(flag) = iter.next() {
+            match flag.as_str() {
+                "--did" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+                    if !value.starts_with("did:") {
+                        return Err(PdsError::Validation(format!("Invalid value for --did: {}", value)));
+                    }
+                    opts.did = Some(value.clone());
+                }
+                other => {
+                    return Err(PdsError::Validation(format!(
+                        "Unknown encryption option: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Result for one actor
+#[derive(Debug)]
+pub struct ActorOutcome {
+    pub did: String,
+    pub store_encrypted: bool,
+    /// Master key wrapping the actor's data key, if it has one
+    pub master_key_id: Option<String>,
+    /// What was done, e.g. "store encrypted, 3 blob(s) sealed"
+    pub actions: Vec<String>,
+}
+
+/// Summary of an encryption run
+#[derive(Debug, Default)]
+pub struct EncryptionReport {
+    pub actors: Vec<ActorOutcome>,
+    /// Actors that could not be processed, with the error
+    pub errors: Vec<(String, String)>,
+}
+
+impl EncryptionReport {
+    /// Human-readable summary
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+
+        for actor in &self.actors {
+            out.push_str(&format!(
+                "{}: store {}, key {}",
+                actor.did,
+                if actor.store_encrypted { "encrypted" } else { "plaintext" },
+                actor.master_key_id.as_deref().map_or("none".to_string(), |id| format!("wrapped by {}", id)),
+            ));
+            if !actor.actions.is_empty() {
+                out.push_str(&format!(" ({})", actor.actions.join(", ")));
+            }
+            out.push('\n');
+        }
+        for (did, error) in &self.errors {
+            out.push_str(&format!("{}: error: {}\n", did, error));
+        }
+
+        let encrypted = self.actors.iter().filter(|a| a.store_encrypted).count();
+        let changed = self.actors.iter().filter(|a| !a.actions.is_empty()).count();
+        out.push_str(&format!(
+            "{} actor(s): {} encrypted store(s), {} changed, {} error(s)\n",
+            self.actors.len(),
+            encrypted,
+            changed,
+            self.errors.len()
+        ));
+
+        out
+    }
+}
+
+/// Run an encryption command over local actors
+pub async fn run(ctx: &AppContext, opts: &EncryptionOptions) -> PdsResult<EncryptionReport> {
+    let dids: Vec<String> = match &opts.did {
+        Some(did) => vec![did.clone()],
+        None => sqlx::query_scalar("SELECT did FROM account ORDER BY did")
+            .fetch_all(&ctx.account_db)
+            .await?,
+    };
+
+    let mut report = EncryptionReport::default();
+    for did in dids {
+        if !ctx.actor_store.exists(&did).await {
+            continue;
+        }
+
+        match process(ctx, &did, opts.command).await {
+            Ok(outcome) => report.actors.push(outcome),
+            Err(e) => report.errors.push((did, e.to_string())),
+        }
+    }
+
+    Ok(report)
+}
+
+async fn process(ctx: &AppContext, did: &str, command: EncryptionCommand) -> PdsResult<ActorOutcome> {
+    let encryption = &ctx.config.encryption;
+    let mut actions = Vec::new();
+
+    match command {
+        EncryptionCommand::Status => {}
+        EncryptionCommand::Encrypt => {
+            let store_pending = encryption.actor_stores && !ctx.actor_store.is_encrypted(did)?;
+            if store_pending || encryption.blobs {
+                let keys = ctx.data_keys.get_or_create(did).await?;
+                if store_pending {
+                    ctx.actor_store.reencrypt(did, &keys.current).await?;
+                    actions.push("store encrypted".to_string());
+                }
+                if encryption.blobs {
+                    let sealed = ctx.blob_store.reencrypt_for_creator(did, &keys, true).await?;
+                    actions.push(format!("{} blob(s) sealed", sealed));
+                }
+            }
+        }
+        EncryptionCommand::RotateMaster => {
+            if ctx.data_keys.rewrap(did).await? {
+                actions.push(format!("re-wrapped under {}", ctx.data_keys.master_key_id()));
+            }
+        }
+        EncryptionCommand::RotateKeys => {
+            if ctx.data_keys.actor_keys(did).await?.is_some() {
+                let keys = ctx.data_keys.begin_rotation(did).await?;
+                if ctx.actor_store.is_encrypted(did)? {
+                    ctx.actor_store.reencrypt(did, &keys.current).await?;
+                    actions.push("store re-keyed".to_string());
+                }
+                // Only blobs already sealed get the new key; plaintext ones
+                // are left for `encrypt`
+                let sealed = ctx.blob_store.reencrypt_for_creator(did, &keys, false).await?;
+                actions.push(format!("{} blob(s) re-sealed", sealed));
+                ctx.data_keys.finish_rotation(did).await?;
+                actions.push("new data key".to_string());
+            }
+        }
+    }
+
+    Ok(ActorOutcome {
+        did: did.to_string(),
+        store_encrypted: ctx.actor_store.is_encrypted(did)?,
+        master_key_id: ctx.data_keys.wrapping_key_id(did).await?,
+        actions,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_encryption_options() {
+        let opts = EncryptionOptions::from_args(&args(&["status"])).unwrap();
+        assert_eq!(opts.command, EncryptionCommand::Status);
+        assert_eq!(opts.did, None);
+
+        let opts = EncryptionOptions::from_args(&args(&["rotate-keys", "--did", "did:plc:abc"])).unwrap();
+        assert_eq!(opts.command, EncryptionCommand::RotateKeys);
+        assert_eq!(opts.did.as_deref(), Some("did:plc:abc"));
+
+        assert!(EncryptionOptions::from_args(&[]).is_err());
+        assert!(EncryptionOptions::from_args(&args(&["decrypt"])).is_err());
+        assert!(EncryptionOptions::from_args(&args(&["encrypt", "--did", "alice.test"])).is_err());
+        assert!(EncryptionOptions::from_args(&args(&["encrypt", "--force"])).is_err());
+    }
+}
@@ -156,7 +156,7 @@ impl FederationAuthenticator {
 
         let response = self.http_client.get(&url).send().await.map_err(|e| {
             warn!("Failed to fetch remote profile: {}", e);
-            PdsError::Internal(format!("Failed to fetch profile: {}", e))
+            PdsError::Federation(format!("Failed to fetch profile: {}", e))
         })?;
 
         if !response.status().is_success() {
@@ -165,7 +165,7 @@ impl FederationAuthenticator {
 
         let profile: RemoteProfile = response.json().await.map_err(|e| {
             warn!("Failed to parse profile response: {}", e);
-            PdsError::Internal(format!("Invalid profile response: {}", e))
+            PdsError::Federation(format!("Invalid profile response: {}", e))
         })?;
 
         debug!("Fetched profile for {}: @{}", did, profile.handle);
@@ -95,11 +95,11 @@ impl PdsDiscovery {
         debug!("Fetching PDS list from relay: {}", url);
 
         let response = self.http_client.get(&url).send().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to connect to relay: {}", e))
+            PdsError::Federation(format!("Failed to connect to relay: {}", e))
         })?;
 
         if !response.status().is_success() {
-            return Err(PdsError::Internal(format!(
+            return Err(PdsError::Federation(format!(
                 "Relay returned error: {}",
                 response.status()
             )));
@@ -107,7 +107,7 @@ impl PdsDiscovery {
 
         // Parse response
         let relay_response: RelayResponse = response.json().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to parse relay response: {}", e))
+            PdsError::Federation(format!("Failed to parse relay response: {}", e))
         })?;
 
         // Convert to PdsInstance format
@@ -143,7 +143,7 @@ impl PdsDiscovery {
         })?;
 
         let did = response.text().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to read response: {}", e))
+            PdsError::Federation(format!("Failed to read response: {}", e))
         })?;
 
         // Fetch PDS info
@@ -156,11 +156,11 @@ impl PdsDiscovery {
         let url = format!("{}/xrpc/com.atproto.server.describeServer", pds_url);
 
         let response = self.http_client.get(&url).send().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to fetch PDS info: {}", e))
+            PdsError::Federation(format!("Failed to fetch PDS info: {}", e))
         })?;
 
         let info: ServerDescription = response.json().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to parse PDS info: {}", e))
+            PdsError::Federation(format!("Failed to parse PDS info: {}", e))
         })?;
 
         Ok(PdsInstance {
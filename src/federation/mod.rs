@@ -13,7 +13,9 @@ pub mod search;
 
 pub use authentication::FederationAuthenticator;
 pub use discovery::{PdsDiscovery, PdsInstance};
-pub use relay::{RelayClient, RelayConfig};
+pub use relay::{
+    CursorStore, FirehoseEvent, FirehoseHandler, FirehoseSubscription, RelayClient, RelayConfig,
+};
 pub use search::FederatedSearch;
 
 use serde::{Deserialize, Serialize};
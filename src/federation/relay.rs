@@ -6,11 +6,24 @@
 /// - Firehose aggregation
 /// - Network-wide event distribution
 
-use crate::error::{PdsError, PdsResult};
+use crate::{
+    error::{PdsError, PdsResult},
+    metrics,
+    mirror::validation::{
+        decode_frame, frame_seq, validate_commit_frame, validate_info_frame, CommitFrame,
+        MirrorValidationError,
+    },
+    telemetry::inject_trace_context,
+};
+use async_trait::async_trait;
+use chrono::Utc;
+use libipld::Ipld;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use sqlx::SqlitePool;
+use std::ops::ControlFlow;
 use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration, Instant};
 use tokio_tungstenite::{connect_async, tungstenite::Message};
 use tracing::{debug, error, info, warn};
 use futures_util::{SinkExt, StreamExt};
@@ -46,7 +59,7 @@ impl Default for RelayConfig {
 pub struct RelayClient {
     config: RelayConfig,
     http_client: Client,
-    event_sender: Option<mpsc::Sender<RelayEvent>>,
+    event_sender: Option<mpsc::Sender<FirehoseEvent>>,
 }
 
 impl RelayClient {
@@ -62,120 +75,37 @@ impl RelayClient {
         }
     }
 
-    /// Subscribe to relay firehose
-    pub async fn subscribe_firehose(&mut self) -> PdsResult<mpsc::Receiver<RelayEvent>> {
+    /// Subscribe to the firehose of every configured relay
+    ///
+    /// Each relay is followed by a `FirehoseSubscription` that validates
+    /// frames and reconnects with backoff; validated events from all relays
+    /// are merged into the returned channel.
+    pub async fn subscribe_firehose(&mut self) -> PdsResult<mpsc::Receiver<FirehoseEvent>> {
         info!("Subscribing to relay firehose...");
 
         let (tx, rx) = mpsc::channel(self.config.buffer_size);
         self.event_sender = Some(tx.clone());
 
         for relay_url in &self.config.servers {
-            let relay_url = relay_url.clone();
-            let tx = tx.clone();
-            let reconnect_interval = self.config.reconnect_interval;
+            let subscription = FirehoseSubscription::new(relay_url.clone())
+                .with_reconnect_delay(Duration::from_secs(self.config.reconnect_interval.max(1)));
+            let handler = ChannelHandler(tx.clone());
 
-            tokio::spawn(async move {
-                Self::connect_to_relay(relay_url, tx, reconnect_interval).await;
-            });
+            tokio::spawn(async move { subscription.run(&handler).await });
         }
 
         Ok(rx)
     }
 
-    /// Connect to a relay server and stream events
-    async fn connect_to_relay(relay_url: String, tx: mpsc::Sender<RelayEvent>, reconnect_interval: u64) {
-        loop {
-            info!("Connecting to relay: {}", relay_url);
-
-            // Convert HTTP URL to WebSocket URL
-            let ws_url = relay_url.replace("https://", "wss://").replace("http://", "ws://");
-            let ws_url = format!("{}/xrpc/com.atproto.sync.subscribeRepos", ws_url);
-
-            match connect_async(&ws_url).await {
-                Ok((mut ws_stream, _)) => {
-                    info!("✓ Connected to relay: {}", relay_url);
-
-                    // Read events from WebSocket
-                    while let Some(msg) = ws_stream.next().await {
-                        match msg {
-                            Ok(Message::Binary(data)) => {
-                                // Parse relay event
-                                match Self::parse_relay_event(&data) {
-                                    Ok(event) => {
-                                        if tx.send(event).await.is_err() {
-                                            warn!("Event channel closed");
-                                            break;
-                                        }
-                                    }
-                                    Err(e) => {
-                                        warn!("Failed to parse relay event: {}", e);
-                                    }
-                                }
-                            }
-                            Ok(Message::Text(text)) => {
-                                debug!("Received text message: {}", text);
-                            }
-                            Ok(Message::Close(_)) => {
-                                info!("Relay closed connection: {}", relay_url);
-                                break;
-                            }
-                            Ok(Message::Ping(data)) => {
-                                if let Err(e) = ws_stream.send(Message::Pong(data)).await {
-                                    error!("Failed to send pong: {}", e);
-                                    break;
-                                }
-                            }
-                            Ok(_) => {}
-                            Err(e) => {
-                                error!("WebSocket error: {}", e);
-                                break;
-                            }
-                        }
-                    }
-
-                    info!("Disconnected from relay: {}", relay_url);
-                }
-                Err(e) => {
-                    error!("Failed to connect to relay {}: {}", relay_url, e);
-                }
-            }
-
-            // Wait before reconnecting
-            info!("Reconnecting in {} seconds...", reconnect_interval);
-            tokio::time::sleep(std::time::Duration::from_secs(reconnect_interval)).await;
-        }
-    }
-
-    /// Parse relay event from binary data
-    fn parse_relay_event(data: &[u8]) -> PdsResult<RelayEvent> {
-        // In a real implementation, this would parse CAR files and CBOR data
-        // For now, we'll create a simple event structure
-
-        // Try to parse as JSON (simplified for this implementation)
-        if let Ok(text) = std::str::from_utf8(data) {
-            if let Ok(event) = serde_json::from_str::<RelayEvent>(text) {
-                return Ok(event);
-            }
-        }
-
-        // Fallback: create a raw event
-        Ok(RelayEvent {
-            event_type: "raw".to_string(),
-            did: String::new(),
-            seq: 0,
-            commit: None,
-            time: chrono::Utc::now().to_rfc3339(),
-        })
-    }
-
     /// Publish event to relay servers
+    #[tracing::instrument(skip(self, event), fields(did = %event.did, seq = event.seq))]
     pub async fn publish_event(&self, event: &RelayEvent) -> PdsResult<()> {
         debug!("Publishing event to {} relay servers", self.config.servers.len());
 
         for relay_url in &self.config.servers {
             let url = format!("{}/xrpc/com.atproto.repo.uploadBlob", relay_url);
 
-            match self.http_client.post(&url).json(event).send().await {
+            match inject_trace_context(self.http_client.post(&url)).json(event).send().await {
                 Ok(response) => {
                     if response.status().is_success() {
                         debug!("✓ Event published to {}", relay_url);
@@ -193,17 +123,18 @@ impl RelayClient {
     }
 
     /// Fetch repository from relay
+    #[tracing::instrument(skip(self))]
     pub async fn fetch_repo(&self, did: &str) -> PdsResult<Vec<u8>> {
         info!("Fetching repository from relay: {}", did);
 
         for relay_url in &self.config.servers {
             let url = format!("{}/xrpc/com.atproto.sync.getRepo?did={}", relay_url, did);
 
-            match self.http_client.get(&url).send().await {
+            match inject_trace_context(self.http_client.get(&url)).send().await {
                 Ok(response) => {
                     if response.status().is_success() {
                         let data = response.bytes().await.map_err(|e| {
-                            PdsError::Internal(format!("Failed to read response: {}", e))
+                            PdsError::Federation(format!("Failed to read response: {}", e))
                         })?;
                         return Ok(data.to_vec());
                     }
@@ -238,6 +169,367 @@ pub struct RelayStats {
     pub last_event_time: Option<String>,
 }
 
+// ============================================================================
+// Inbound firehose subscription
+// ============================================================================
+
+/// Upper bound on the reconnect delay
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(60);
+
+/// Persist the cursor after this many events...
+const CURSOR_SAVE_EVENTS: u64 = 100;
+
+/// ...or after this long, whichever comes first
+const CURSOR_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Validated event from an inbound firehose
+#[derive(Debug, Clone)]
+pub enum FirehoseEvent {
+    /// Repository commit
+    Commit(CommitFrame),
+    /// Any other sequenced message (`#identity`, `#account`, `#sync`, ...)
+    Other { kind: String, seq: i64, body: Ipld },
+}
+
+impl FirehoseEvent {
+    pub fn seq(&self) -> i64 {
+        match self {
+            FirehoseEvent::Commit(frame) => frame.seq,
+            FirehoseEvent::Other { seq, .. } => *seq,
+        }
+    }
+}
+
+/// Receives validated events from a `FirehoseSubscription`
+#[async_trait]
+pub trait FirehoseHandler: Send + Sync {
+    /// Handle one event; `Break` ends the subscription
+    async fn handle_event(&self, event: FirehoseEvent) -> ControlFlow<()>;
+}
+
+/// Forwards events into a channel, stopping once the receiver is dropped
+struct ChannelHandler(mpsc::Sender<FirehoseEvent>);
+
+#[async_trait]
+impl FirehoseHandler for ChannelHandler {
+    async fn handle_event(&self, event: FirehoseEvent) -> ControlFlow<()> {
+        match self.0.send(event).await {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => {
+                warn!("Event channel closed");
+                ControlFlow::Break(())
+            }
+        }
+    }
+}
+
+/// Persisted firehose cursors, keyed by source URL
+#[derive(Clone)]
+pub struct CursorStore {
+    db: SqlitePool,
+}
+
+impl CursorStore {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Last processed sequence number for a source
+    pub async fn load(&self, source: &str) -> PdsResult<Option<i64>> {
+        let cursor = sqlx::query_scalar("SELECT cursor FROM firehose_cursor WHERE source = ?1")
+            .bind(source)
+            .fetch_optional(&self.db)
+            .await?;
+
+        Ok(cursor)
+    }
+
+    pub async fn save(&self, source: &str, cursor: i64) -> PdsResult<()> {
+        sqlx::query(
+            "INSERT INTO firehose_cursor (source, cursor, updated_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(source) DO UPDATE SET
+                cursor = excluded.cursor,
+                updated_at = excluded.updated_at"
+        )
+        .bind(source)
+        .bind(cursor)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn clear(&self, source: &str) -> PdsResult<()> {
+        sqlx::query("DELETE FROM firehose_cursor WHERE source = ?1")
+            .bind(source)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+}
+
+/// What to do after a frame
+#[derive(Debug, PartialEq)]
+enum FrameFlow {
+    Continue,
+    Reconnect,
+    Stop,
+}
+
+/// Client for another PDS or relay's com.atproto.sync.subscribeRepos
+///
+/// Every frame is validated before it reaches the handler. The cursor (the
+/// highest sequence number seen) is persisted when a `CursorStore` is
+/// attached, so the stream resumes where it left off after a reconnect or
+/// restart. Reconnects back off exponentially up to a minute.
+pub struct FirehoseSubscription {
+    source_url: String,
+    cursor_store: Option<CursorStore>,
+    initial_cursor: Option<i64>,
+    reconnect_delay: Duration,
+}
+
+impl FirehoseSubscription {
+    pub fn new(source_url: impl Into<String>) -> Self {
+        Self {
+            source_url: source_url.into(),
+            cursor_store: None,
+            initial_cursor: None,
+            reconnect_delay: Duration::from_secs(1),
+        }
+    }
+
+    /// Persist the cursor so the stream resumes across restarts
+    pub fn with_cursor_store(mut self, store: CursorStore) -> Self {
+        self.cursor_store = Some(store);
+        self
+    }
+
+    /// Cursor to start from when none has been persisted
+    pub fn with_initial_cursor(mut self, cursor: Option<i64>) -> Self {
+        self.initial_cursor = cursor;
+        self
+    }
+
+    /// Delay before the first reconnect attempt (doubles on each failure)
+    pub fn with_reconnect_delay(mut self, delay: Duration) -> Self {
+        self.reconnect_delay = delay;
+        self
+    }
+
+    pub fn source_url(&self) -> &str {
+        &self.source_url
+    }
+
+    /// Follow the firehose until the handler stops the subscription
+    pub async fn run<H: FirehoseHandler>(&self, handler: &H) {
+        let mut cursor = match &self.cursor_store {
+            Some(store) => match store.load(&self.source_url).await {
+                Ok(stored) => stored.or(self.initial_cursor),
+                Err(e) => {
+                    warn!(error = %e, "firehose_cursor_load_failed");
+                    self.initial_cursor
+                }
+            },
+            None => self.initial_cursor,
+        };
+        let mut delay = self.reconnect_delay;
+
+        loop {
+            let (connected, flow) = self.consume(handler, &mut cursor).await;
+            if flow == FrameFlow::Stop {
+                info!("Firehose subscription to {} stopped", self.source_url);
+                return;
+            }
+
+            // Back off only while the source is unreachable
+            if connected {
+                delay = self.reconnect_delay;
+            }
+            info!("Reconnecting to {} in {:?}", self.source_url, delay);
+            sleep(delay).await;
+            delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+        }
+    }
+
+    fn subscribe_url(&self, cursor: Option<i64>) -> String {
+        let base = self
+            .source_url
+            .trim_end_matches('/')
+            .replace("https://", "wss://")
+            .replace("http://", "ws://");
+        match cursor {
+            Some(cursor) => format!("{}/xrpc/com.atproto.sync.subscribeRepos?cursor={}", base, cursor),
+            None => format!("{}/xrpc/com.atproto.sync.subscribeRepos", base),
+        }
+    }
+
+    /// Consume the stream until it closes, returning whether it connected
+    async fn consume<H: FirehoseHandler>(
+        &self,
+        handler: &H,
+        cursor: &mut Option<i64>,
+    ) -> (bool, FrameFlow) {
+        let ws_url = self.subscribe_url(*cursor);
+        info!("Connecting to firehose: {}", ws_url);
+
+        let mut ws_stream = match connect_async(&ws_url).await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                error!("Failed to connect to {}: {}", ws_url, e);
+                return (false, FrameFlow::Reconnect);
+            }
+        };
+        info!("✓ Connected to firehose: {}", self.source_url);
+
+        let mut saved = *cursor;
+        let mut unsaved_events = 0u64;
+        let mut last_save = Instant::now();
+        let mut flow = FrameFlow::Reconnect;
+
+        while let Some(msg) = ws_stream.next().await {
+            match msg {
+                Ok(Message::Binary(data)) => {
+                    let before = *cursor;
+                    match self.handle_frame(&data, handler, cursor).await {
+                        FrameFlow::Continue => {}
+                        other => {
+                            flow = other;
+                            break;
+                        }
+                    }
+                    if *cursor != before {
+                        unsaved_events += 1;
+                    }
+
+                    if unsaved_events >= CURSOR_SAVE_EVENTS || last_save.elapsed() >= CURSOR_SAVE_INTERVAL {
+                        self.save_cursor(*cursor, &mut saved).await;
+                        unsaved_events = 0;
+                        last_save = Instant::now();
+                    }
+                }
+                Ok(Message::Ping(data)) => {
+                    if let Err(e) = ws_stream.send(Message::Pong(data)).await {
+                        error!("Failed to send pong: {}", e);
+                        break;
+                    }
+                }
+                Ok(Message::Close(_)) => {
+                    info!("Firehose source closed connection: {}", self.source_url);
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Firehose WebSocket error: {}", e);
+                    break;
+                }
+            }
+        }
+
+        self.save_cursor(*cursor, &mut saved).await;
+        (true, flow)
+    }
+
+    async fn save_cursor(&self, cursor: Option<i64>, saved: &mut Option<i64>) {
+        let (Some(store), Some(seq)) = (&self.cursor_store, cursor) else {
+            return;
+        };
+        if *saved == Some(seq) {
+            return;
+        }
+
+        match store.save(&self.source_url, seq).await {
+            Ok(()) => *saved = Some(seq),
+            Err(e) => warn!(error = %e, "firehose_cursor_save_failed"),
+        }
+    }
+
+    /// Validate one frame, hand it to the handler and advance the cursor
+    async fn handle_frame<H: FirehoseHandler>(
+        &self,
+        data: &[u8],
+        handler: &H,
+        cursor: &mut Option<i64>,
+    ) -> FrameFlow {
+        let (header, body) = match decode_frame(data) {
+            Ok(frame) => frame,
+            Err(MirrorValidationError::UpstreamError(message)) => {
+                warn!(source = %self.source_url, error = %message, "firehose_error_frame");
+                // The source is behind our cursor (e.g. it was reset); start from live
+                if message.starts_with("FutureCursor") {
+                    *cursor = None;
+                    if let Some(store) = &self.cursor_store {
+                        let _ = store.clear(&self.source_url).await;
+                    }
+                }
+                return FrameFlow::Reconnect;
+            }
+            Err(e) => {
+                warn!(error = %e, "firehose_frame_rejected");
+                metrics::record_error("FirehoseFrameRejected", "relay");
+                return FrameFlow::Continue;
+            }
+        };
+
+        let kind = header.message_type.unwrap_or_default();
+        let event = match kind.as_str() {
+            "#info" => {
+                match validate_info_frame(&body) {
+                    Ok((name, message)) if name == "OutdatedCursor" => warn!(
+                        "Cursor for {} is older than its backfill window; events were missed: {}",
+                        self.source_url,
+                        message.unwrap_or_default()
+                    ),
+                    Ok((name, message)) => info!("Firehose info from {}: {} {}", self.source_url, name, message.unwrap_or_default()),
+                    Err(e) => warn!(error = %e, "firehose_frame_rejected"),
+                }
+                return FrameFlow::Continue;
+            }
+            "#commit" => match validate_commit_frame(&body) {
+                Ok(frame) => FirehoseEvent::Commit(frame),
+                Err(e) => {
+                    warn!(error = %e, "firehose_commit_rejected");
+                    metrics::record_error("FirehoseCommitRejected", "relay");
+                    // Skip past it so a bad frame isn't replayed on reconnect
+                    advance(cursor, frame_seq(&body));
+                    return FrameFlow::Continue;
+                }
+            },
+            _ => match frame_seq(&body) {
+                Some(seq) => FirehoseEvent::Other { kind, seq, body },
+                None => {
+                    debug!("Ignoring unsequenced {} frame", kind);
+                    return FrameFlow::Continue;
+                }
+            },
+        };
+
+        let seq = event.seq();
+        if cursor.map_or(false, |c| seq <= c) {
+            debug!("Skipping already processed event {}", seq);
+            return FrameFlow::Continue;
+        }
+
+        let flow = handler.handle_event(event).await;
+        advance(cursor, Some(seq));
+
+        match flow {
+            ControlFlow::Continue(()) => FrameFlow::Continue,
+            ControlFlow::Break(()) => FrameFlow::Stop,
+        }
+    }
+}
+
+fn advance(cursor: &mut Option<i64>, seq: Option<i64>) {
+    if let Some(seq) = seq {
+        if cursor.map_or(true, |c| seq > c) {
+            *cursor = Some(seq);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -277,4 +569,78 @@ mod tests {
         let client = RelayClient::new(config.clone());
         assert_eq!(client.config.servers.len(), 1);
     }
+
+    fn frame(header: &[(&str, Ipld)], body: &[(&str, Ipld)]) -> Vec<u8> {
+        use libipld::{cbor::DagCborCodec, codec::Codec};
+
+        let map = |fields: &[(&str, Ipld)]| {
+            Ipld::Map(fields.iter().map(|(k, v)| (k.to_string(), v.clone())).collect())
+        };
+        let mut bytes = DagCborCodec.encode(&map(header)).unwrap();
+        bytes.extend(DagCborCodec.encode(&map(body)).unwrap());
+        bytes
+    }
+
+    #[derive(Default)]
+    struct RecordingHandler(std::sync::Mutex<Vec<i64>>);
+
+    #[async_trait]
+    impl FirehoseHandler for RecordingHandler {
+        async fn handle_event(&self, event: FirehoseEvent) -> ControlFlow<()> {
+            self.0.lock().unwrap().push(event.seq());
+            ControlFlow::Continue(())
+        }
+    }
+
+    #[test]
+    fn test_subscribe_url() {
+        let subscription = FirehoseSubscription::new("https://relay.example.com/");
+        assert_eq!(
+            subscription.subscribe_url(None),
+            "wss://relay.example.com/xrpc/com.atproto.sync.subscribeRepos"
+        );
+        assert_eq!(
+            subscription.subscribe_url(Some(42)),
+            "wss://relay.example.com/xrpc/com.atproto.sync.subscribeRepos?cursor=42"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_frame_handling_and_cursor_persistence() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(include_str!("../../migrations/20250119000001_firehose_cursor.sql"))
+            .execute(&db)
+            .await
+            .unwrap();
+        let store = CursorStore::new(db);
+        let subscription = FirehoseSubscription::new("https://relay.example.com")
+            .with_cursor_store(store.clone());
+        let handler = RecordingHandler::default();
+        let mut cursor = None;
+
+        let identity = |seq: i128| {
+            frame(
+                &[("op", Ipld::Integer(1)), ("t", Ipld::String("#identity".to_string()))],
+                &[("seq", Ipld::Integer(seq)), ("did", Ipld::String("did:plc:abc".to_string()))],
+            )
+        };
+
+        assert_eq!(subscription.handle_frame(&identity(5), &handler, &mut cursor).await, FrameFlow::Continue);
+        // Replayed events are skipped
+        assert_eq!(subscription.handle_frame(&identity(5), &handler, &mut cursor).await, FrameFlow::Continue);
+        // Garbage is rejected without ending the stream
+        assert_eq!(subscription.handle_frame(b"garbage", &handler, &mut cursor).await, FrameFlow::Continue);
+        assert_eq!(*handler.0.lock().unwrap(), vec![5]);
+        assert_eq!(cursor, Some(5));
+
+        let mut saved = None;
+        subscription.save_cursor(cursor, &mut saved).await;
+        assert_eq!(store.load("https://relay.example.com").await.unwrap(), Some(5));
+
+        // FutureCursor resets to live
+        let error = frame(&[("op", Ipld::Integer(-1))], &[("error", Ipld::String("FutureCursor".to_string()))]);
+        assert_eq!(subscription.handle_frame(&error, &handler, &mut cursor).await, FrameFlow::Reconnect);
+        assert_eq!(cursor, None);
+        assert_eq!(store.load("https://relay.example.com").await.unwrap(), None);
+    }
 }
@@ -123,7 +123,7 @@ impl FederatedSearch {
         );
 
         let response = client.get(&url).send().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to search PDS {}: {}", instance.did, e))
+            PdsError::Federation(format!("Failed to search PDS {}: {}", instance.did, e))
         })?;
 
         if !response.status().is_success() {
@@ -131,7 +131,7 @@ impl FederatedSearch {
         }
 
         let search_response: ActorSearchResponse = response.json().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to parse search response: {}", e))
+            PdsError::Federation(format!("Failed to parse search response: {}", e))
         })?;
 
         Ok(search_response.actors)
@@ -152,7 +152,7 @@ impl FederatedSearch {
         );
 
         let response = client.get(&url).send().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to search PDS {}: {}", instance.did, e))
+            PdsError::Federation(format!("Failed to search PDS {}: {}", instance.did, e))
         })?;
 
         if !response.status().is_success() {
@@ -160,7 +160,7 @@ impl FederatedSearch {
         }
 
         let search_response: PostSearchResponse = response.json().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to parse search response: {}", e))
+            PdsError::Federation(format!("Failed to parse search response: {}", e))
         })?;
 
         Ok(search_response.posts)
@@ -215,7 +215,7 @@ impl FederatedSearch {
         );
 
         let response = client.get(&url).send().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to fetch feed: {}", e))
+            PdsError::Federation(format!("Failed to fetch feed: {}", e))
         })?;
 
         if !response.status().is_success() {
@@ -223,7 +223,7 @@ impl FederatedSearch {
         }
 
         let feed_response: FeedResponse = response.json().await.map_err(|e| {
-            PdsError::Internal(format!("Failed to parse feed response: {}", e))
+            PdsError::Federation(format!("Failed to parse feed response: {}", e))
         })?;
 
         Ok(feed_response
@@ -14,6 +14,8 @@ pub struct DidCache {
     did_doc_ttl: Duration,
     /// TTL for handle cache (default: 5 minutes)
     handle_ttl: Duration,
+    /// How long expired DID documents are kept for offline mode
+    offline_retention: Option<Duration>,
 }
 
 impl DidCache {
@@ -23,6 +25,7 @@ impl DidCache {
             db,
             did_doc_ttl: Duration::hours(1),
             handle_ttl: Duration::minutes(5),
+            offline_retention: None,
         }
     }
 
@@ -33,8 +36,52 @@ impl DidCache {
         self
     }
 
+    /// Keep DID documents past their TTL, up to `max_age` old, so they can
+    /// still be served while every PLC directory is down
+    pub fn with_offline_retention(mut self, max_age: Duration) -> Self {
+        self.offline_retention = Some(max_age);
+        self
+    }
+
+    /// How long DID documents stay in the table
+    fn did_doc_retention(&self) -> Duration {
+        self.offline_retention
+            .map_or(self.did_doc_ttl, |retention| retention.max(self.did_doc_ttl))
+    }
+
     /// Get cached DID document
     pub async fn get_did_doc(&self, did: &str) -> PdsResult<Option<CachedDidDoc>> {
+        let Some(cached_doc) = self.read_did_doc(did).await? else {
+            return Ok(None);
+        };
+
+        // Check if cache is still valid
+        if Utc::now() - cached_doc.cached_at < self.did_doc_ttl {
+            Ok(Some(cached_doc))
+        } else {
+            // Cache expired; delete it unless it is kept for offline mode
+            if Utc::now() - cached_doc.cached_at >= self.did_doc_retention() {
+                self.delete_did_doc(did).await?;
+            }
+            Ok(None)
+        }
+    }
+
+    /// Get a DID document past its TTL but within the offline retention
+    ///
+    /// Only for when the document can't be fetched; `None` unless offline
+    /// retention is enabled.
+    pub async fn get_stale_did_doc(&self, did: &str) -> PdsResult<Option<CachedDidDoc>> {
+        if self.offline_retention.is_none() {
+            return Ok(None);
+        }
+        Ok(self
+            .read_did_doc(did)
+            .await?
+            .filter(|cached| Utc::now() - cached.cached_at < self.did_doc_retention()))
+    }
+
+    async fn read_did_doc(&self, did: &str) -> PdsResult<Option<CachedDidDoc>> {
         let result = sqlx::query(
             r#"
             SELECT did, doc, updated_at, cached_at
@@ -47,25 +94,15 @@ impl DidCache {
         .await
         .map_err(|e| PdsError::Database(e))?;
 
-        if let Some(row) = result {
-            let cached_doc = CachedDidDoc {
+        match result {
+            Some(row) => Ok(Some(CachedDidDoc {
                 did: row.try_get("did")?,
                 doc: row.try_get("doc")?,
                 updated_at: parse_timestamp(&row.try_get::<String, _>("updated_at")?)?,
                 cached_at: parse_timestamp(&row.try_get::<String, _>("cached_at")?)?,
-            };
-
-            // Check if cache is still valid
-            if Utc::now() - cached_doc.cached_at < self.did_doc_ttl {
-                return Ok(Some(cached_doc));
-            } else {
-                // Cache expired, delete it
-                self.delete_did_doc(did).await?;
-                return Ok(None);
-            }
+            })),
+            None => Ok(None),
         }
-
-        Ok(None)
     }
 
     /// Cache DID document
@@ -205,7 +242,7 @@ impl DidCache {
 
     /// Clean up expired cache entries
     pub async fn cleanup_expired(&self) -> PdsResult<()> {
-        let did_doc_cutoff = (Utc::now() - self.did_doc_ttl).to_rfc3339();
+        let did_doc_cutoff = (Utc::now() - self.did_doc_retention()).to_rfc3339();
         let handle_cutoff = (Utc::now() - self.handle_ttl).to_rfc3339();
 
         // Delete expired DID documents
@@ -318,4 +355,42 @@ mod tests {
         let handle = cache.get_did_handle("did:plc:bob").await.unwrap();
         assert_eq!(handle, Some("bob.test".to_string()));
     }
+
+    #[tokio::test]
+    async fn test_offline_retention() {
+        let cache = create_test_cache()
+            .await
+            .with_ttls(Duration::hours(1), Duration::minutes(5))
+            .with_offline_retention(Duration::days(7));
+
+        let backdate = |did: &'static str, age: Duration| {
+            let db = cache.db.clone();
+            async move {
+                sqlx::query("UPDATE did_doc SET cached_at = ?1 WHERE did = ?2")
+                    .bind((Utc::now() - age).to_rfc3339())
+                    .bind(did)
+                    .execute(&db)
+                    .await
+                    .unwrap();
+            }
+        };
+        cache.cache_did_doc("did:plc:expired", "{}").await.unwrap();
+        cache.cache_did_doc("did:plc:ancient", "{}").await.unwrap();
+        backdate("did:plc:expired", Duration::hours(2)).await;
+        backdate("did:plc:ancient", Duration::days(8)).await;
+
+        // Past the TTL but kept for offline use
+        assert!(cache.get_did_doc("did:plc:expired").await.unwrap().is_none());
+        assert!(cache.get_stale_did_doc("did:plc:expired").await.unwrap().is_some());
+
+        // Past the retention too
+        assert!(cache.get_stale_did_doc("did:plc:ancient").await.unwrap().is_none());
+        cache.cleanup_expired().await.unwrap();
+        assert!(cache.read_did_doc("did:plc:ancient").await.unwrap().is_none());
+        assert!(cache.read_did_doc("did:plc:expired").await.unwrap().is_some());
+
+        // Without offline mode stale documents are never served
+        let online = DidCache::new(cache.db.clone());
+        assert!(online.get_stale_did_doc("did:plc:expired").await.unwrap().is_none());
+    }
 }
@@ -0,0 +1,157 @@
+/// PLC directory mirrors with failover
+///
+/// Resolution tries directories in configured order, moving a directory to
+/// the back of the line after `FAILURE_THRESHOLD` consecutive failures. It
+/// stays there for a backoff that doubles with each further failure (up to
+/// `MAX_BACKOFF`), then gets tried in its normal place again; one success
+/// clears it. Directories in backoff are still tried last, so a request
+/// only fails when every directory does.
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Consecutive failures before a directory is passed over
+const FAILURE_THRESHOLD: u32 = 3;
+
+/// Backoff after reaching the threshold
+const BASE_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Longest a directory is passed over for
+const MAX_BACKOFF: Duration = Duration::from_secs(600);
+
+#[derive(Debug)]
+struct MirrorState {
+    url: String,
+    consecutive_failures: u32,
+    down_until: Option<Instant>,
+    last_error: Option<String>,
+    last_success: Option<DateTime<Utc>>,
+}
+
+/// Health of one PLC directory, for the health endpoint
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlcMirrorStatus {
+    pub url: String,
+    pub healthy: bool,
+    pub consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_success: Option<DateTime<Utc>>,
+}
+
+/// Shared health tracking for the configured PLC directories
+#[derive(Debug, Clone)]
+pub struct PlcMirrors {
+    mirrors: Arc<Mutex<Vec<MirrorState>>>,
+}
+
+impl PlcMirrors {
+    /// Track `urls`, in the order they should be tried; duplicates are dropped
+    pub fn new(urls: &[String]) -> Self {
+        let mut mirrors: Vec<MirrorState> = Vec::new();
+        for url in urls {
+            let url = url.trim_end_matches('/');
+            if !url.is_empty() && !mirrors.iter().any(|m| m.url == url) {
+                mirrors.push(MirrorState {
+                    url: url.to_string(),
+                    consecutive_failures: 0,
+                    down_until: None,
+                    last_error: None,
+                    last_success: None,
+                });
+            }
+        }
+        Self {
+            mirrors: Arc::new(Mutex::new(mirrors)),
+        }
+    }
+
+    /// Directories to try, healthy ones first in configured order, then the
+    /// rest by how soon their backoff ends
+    pub fn order(&self) -> Vec<String> {
+        let now = Instant::now();
+        let mirrors = self.mirrors.lock().unwrap();
+        let (mut up, mut down): (Vec<&MirrorState>, Vec<&MirrorState>) = mirrors
+            .iter()
+            .partition(|m| m.down_until.map_or(true, |until| until <= now));
+        down.sort_by_key(|m| m.down_until);
+        up.append(&mut down);
+        up.into_iter().map(|m| m.url.clone()).collect()
+    }
+
+    pub fn record_success(&self, url: &str) {
+        let mut mirrors = self.mirrors.lock().unwrap();
+        if let Some(mirror) = mirrors.iter_mut().find(|m| m.url == url) {
+            mirror.consecutive_failures = 0;
+            mirror.down_until = None;
+            mirror.last_error = None;
+            mirror.last_success = Some(Utc::now());
+        }
+    }
+
+    pub fn record_failure(&self, url: &str, error: &str) {
+        let mut mirrors = self.mirrors.lock().unwrap();
+        if let Some(mirror) = mirrors.iter_mut().find(|m| m.url == url) {
+            mirror.consecutive_failures += 1;
+            mirror.last_error = Some(error.to_string());
+            if mirror.consecutive_failures >= FAILURE_THRESHOLD {
+                let doublings = (mirror.consecutive_failures - FAILURE_THRESHOLD).min(5);
+                let backoff = (BASE_BACKOFF * 2u32.pow(doublings)).min(MAX_BACKOFF);
+                if mirror.down_until.is_none() {
+                    tracing::warn!(url = %url, error = %error, "plc_mirror_marked_down");
+                }
+                mirror.down_until = Some(Instant::now() + backoff);
+            }
+        }
+    }
+
+    pub fn status(&self) -> Vec<PlcMirrorStatus> {
+        let now = Instant::now();
+        self.mirrors
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|m| PlcMirrorStatus {
+                url: m.url.clone(),
+                healthy: m.down_until.map_or(true, |until| until <= now),
+                consecutive_failures: m.consecutive_failures,
+                last_error: m.last_error.clone(),
+                last_success: m.last_success,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_failover_order() {
+        let mirrors = PlcMirrors::new(&[
+            "https://plc.directory".to_string(),
+            "https://plc.mirror.example/".to_string(),
+            "https://plc.directory/".to_string(),
+        ]);
+        assert_eq!(mirrors.order(), vec!["https://plc.directory", "https://plc.mirror.example"]);
+
+        // Failures below the threshold keep the order
+        mirrors.record_failure("https://plc.directory", "timeout");
+        mirrors.record_failure("https://plc.directory", "timeout");
+        assert_eq!(mirrors.order()[0], "https://plc.directory");
+
+        mirrors.record_failure("https://plc.directory", "timeout");
+        assert_eq!(mirrors.order(), vec!["https://plc.mirror.example", "https://plc.directory"]);
+        let status = mirrors.status();
+        assert!(!status[0].healthy);
+        assert_eq!(status[0].consecutive_failures, 3);
+        assert_eq!(status[0].last_error.as_deref(), Some("timeout"));
+
+        mirrors.record_success("https://plc.directory");
+        assert_eq!(mirrors.order()[0], "https://plc.directory");
+        assert!(mirrors.status().iter().all(|m| m.healthy));
+    }
+}
@@ -4,10 +4,14 @@
 /// for efficient cross-server identity lookups.
 
 pub mod cache;
+pub mod mirrors;
 pub mod resolver;
+pub mod verification;
 
 pub use cache::DidCache;
+pub use mirrors::{PlcMirrorStatus, PlcMirrors};
 pub use resolver::{IdentityResolver, IdentityResolverConfig};
+pub use verification::{HandleVerificationManager, HandleVerificationMethod, HandleVerifier};
 
 use chrono::{DateTime, Utc};
 use serde::Serialize;
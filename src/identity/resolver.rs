@@ -1,36 +1,88 @@
 /// Identity Resolver - Orchestrates handle and DID resolution with caching
+///
+/// Lookups go Redis (when the cache layer is enabled) → SQLite → network.
+/// Handles additionally get a short-lived in-process tier, which is what
+/// the `resolveHandle` fast path serves from.
+/// DID documents older than the stale TTL are still served, but trigger a
+/// background refresh (stale-while-revalidate); documents past the max TTL
+/// are evicted from SQLite and re-fetched. Redis failures are logged and
+/// treated as misses so resolution never depends on Redis being up.
+/// did:plc documents come from the first PLC directory that answers (see
+/// `PlcMirrors`); in offline mode, an expired document is served with a
+/// warning when none do.
 use crate::{
+    account::normalize_handle,
+    cache::{categories, CacheClient},
     error::{PdsError, PdsResult},
-    identity::DidCache,
+    identity::{
+        mirrors::{PlcMirrorStatus, PlcMirrors},
+        verification::HandleVerificationMethod,
+        DidCache, HandleVerifier,
+    },
+    metrics,
+    telemetry::inject_trace_context,
 };
-use atproto::{did_doc::DidDocument, handle::HandleResolver};
-use std::sync::Arc;
+use atproto::did_doc::DidDocument;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 /// Identity resolution configuration
 #[derive(Debug, Clone)]
 pub struct IdentityResolverConfig {
     /// User-Agent header for HTTP requests
     pub user_agent: String,
-    /// Enable DNS-over-HTTPS for handle resolution
-    pub use_doh: bool,
+    /// DNS-over-HTTPS (JSON API) endpoint used for `_atproto` TXT lookups
+    pub doh_url: String,
+    /// Age (seconds) after which a cached DID document is refreshed in the background
+    pub did_stale_ttl: u64,
+    /// PLC directories to resolve did:plc from, in failover order
+    pub plc_urls: Vec<String>,
+    /// Serve expired cached DID documents when they can't be fetched
+    pub offline_mode: bool,
 }
 
 impl Default for IdentityResolverConfig {
     fn default() -> Self {
         Self {
             user_agent: "Aurora-Locus/0.1".to_string(),
-            use_doh: false,
+            doh_url: "https://cloudflare-dns.com/dns-query".to_string(),
+            did_stale_ttl: 3600,
+            plc_urls: vec!["https://plc.directory".to_string()],
+            offline_mode: false,
         }
     }
 }
 
-/// Main identity resolver - combines caching with SDK resolution
+/// How long a handle resolution stays in the in-process tier
+const HOT_HANDLE_TTL: Duration = Duration::from_secs(60);
+
+/// Entries kept in the in-process handle tier
+const HOT_HANDLE_CAPACITY: usize = 10_000;
+
+/// DID document entry stored in Redis
+#[derive(Debug, Serialize, Deserialize)]
+struct RedisDidDoc {
+    doc: DidDocument,
+    /// Unix timestamp the document was fetched from its source
+    cached_at: i64,
+}
+
+/// Main identity resolver - combines caching with DNS/HTTPS handle verification
 #[derive(Clone)]
 pub struct IdentityResolver {
     cache: DidCache,
-    handle_resolver: Arc<HandleResolver>,
+    redis: Option<CacheClient>,
+    handle_verifier: HandleVerifier,
     http_client: reqwest::Client,
     config: IdentityResolverConfig,
+    plc_mirrors: PlcMirrors,
+    /// DIDs with a background refresh in flight
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    /// Recently resolved handles: handle -> (DID, resolved at)
+    hot_handles: Arc<Mutex<HashMap<String, (String, Instant)>>>,
 }
 
 impl IdentityResolver {
@@ -46,17 +98,27 @@ impl IdentityResolver {
             .build()
             .map_err(|e| PdsError::Internal(format!("Failed to create HTTP client: {}", e)))?;
 
-        // Create handle resolver from SDK
-        let handle_resolver = Arc::new(HandleResolver::new());
+        let handle_verifier = HandleVerifier::new(http_client.clone(), config.doh_url.clone());
+        let plc_mirrors = PlcMirrors::new(&config.plc_urls);
 
         Ok(Self {
             cache,
-            handle_resolver,
+            redis: None,
+            handle_verifier,
             http_client,
             config,
+            plc_mirrors,
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+            hot_handles: Arc::new(Mutex::new(HashMap::new())),
         })
     }
 
+    /// Put the Redis cache layer in front of the SQLite cache
+    pub fn with_redis(mut self, redis: Option<CacheClient>) -> Self {
+        self.redis = redis;
+        self
+    }
+
     /// Resolve handle to DID with caching
     ///
     /// Resolution order:
@@ -65,48 +127,231 @@ impl IdentityResolver {
     /// 3. Try HTTPS well-known resolution
     /// 4. Cache successful resolution
     pub async fn resolve_handle(&self, handle: &str) -> PdsResult<String> {
-        let normalized = handle.to_lowercase();
+        let normalized = normalize_handle(handle)?;
 
-        // Check cache first
+        // Check memory and Redis, then SQLite
+        if let Some(did) = self.cached_handle(&normalized).await {
+            return Ok(did);
+        }
         if let Some(cached) = self.cache.get_handle(&normalized).await? {
+            self.redis_set_handle(&normalized, &cached.did).await;
+            self.remember_handle(&normalized, &cached.did);
             return Ok(cached.did);
         }
 
-        // Cache miss - resolve via SDK
-        let did = self.handle_resolver
+        // Cache miss - resolve via DNS TXT, then HTTPS well-known
+        let (did, _) = self.handle_verifier
             .resolve(&normalized)
-            .await
-            .map_err(|e| PdsError::IdentityResolution(format!("Failed to resolve handle: {}", e)))?;
-
-        let did_str = did.as_str().to_string();
+            .await?
+            .ok_or_else(|| PdsError::IdentityResolution(format!("Unable to resolve handle: {}", handle)))?;
 
         // Cache the successful resolution
-        self.cache.cache_handle(&normalized, &did_str).await?;
+        self.store_handle(&normalized, &did).await?;
+
+        Ok(did)
+    }
+
+    /// Resolve a handle from memory or Redis only
+    ///
+    /// Never touches SQLite or the network, so a miss just means the caller
+    /// should take the slow path.
+    pub async fn cached_handle(&self, handle: &str) -> Option<String> {
+        let normalized = handle.to_lowercase();
+        if let Some(did) = self.hot_handle(&normalized) {
+            return Some(did);
+        }
+
+        let did = self.redis_get::<String>(categories::HANDLE, &normalized).await?;
+        self.remember_handle(&normalized, &did);
+        Some(did)
+    }
+
+    /// Keep a resolution in the in-process tier for `HOT_HANDLE_TTL`
+    pub fn remember_handle(&self, handle: &str, did: &str) {
+        let mut hot = self.hot_handles.lock().unwrap();
+        if hot.len() >= HOT_HANDLE_CAPACITY {
+            hot.retain(|_, (_, at)| at.elapsed() < HOT_HANDLE_TTL);
+            if hot.len() >= HOT_HANDLE_CAPACITY {
+                hot.clear();
+            }
+        }
+        hot.insert(handle.to_lowercase(), (did.to_string(), Instant::now()));
+    }
+
+    /// Store a handle known to be current in every cache tier
+    ///
+    /// Used for handles of local accounts, which need no verification.
+    pub async fn warm_handle(&self, handle: &str, did: &str) -> PdsResult<()> {
+        let normalized = handle.to_lowercase();
+        self.remember_handle(&normalized, did);
+        self.redis_set_handle(&normalized, did).await;
+        self.cache.cache_handle(&normalized, did).await
+    }
+
+    fn hot_handle(&self, handle: &str) -> Option<String> {
+        let mut hot = self.hot_handles.lock().unwrap();
+        match hot.get(handle) {
+            Some((did, at)) if at.elapsed() < HOT_HANDLE_TTL => Some(did.clone()),
+            Some(_) => {
+                hot.remove(handle);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Verify that a handle currently points at `did`, bypassing the cache
+    ///
+    /// On success the resolution is cached and the matching method returned.
+    pub async fn verify_handle(&self, handle: &str, did: &str) -> PdsResult<HandleVerificationMethod> {
+        let normalized = normalize_handle(handle)?;
 
-        Ok(did_str)
+        let method = self.handle_verifier.verify(&normalized, did).await?;
+        self.store_handle(&normalized, did).await?;
+
+        Ok(method)
     }
 
     /// Resolve DID to DID document with caching
     ///
     /// Supports did:plc and did:web methods
     pub async fn resolve_did(&self, did: &str) -> PdsResult<DidDocument> {
-        // Check cache first
+        // Redis tier
+        if let Some(entry) = self.redis_get::<RedisDidDoc>(categories::DID_DOC, did).await {
+            self.refresh_if_stale(did, entry.cached_at);
+            return Ok(entry.doc);
+        }
+
+        // SQLite tier (entries past the max TTL are already evicted)
         if let Some(cached) = self.cache.get_did_doc(did).await? {
             // Parse cached document
             let doc: DidDocument = serde_json::from_str(&cached.doc)
                 .map_err(|e| PdsError::Internal(format!("Invalid cached DID document: {}", e)))?;
+
+            self.redis_set_did_doc(did, &doc, cached.cached_at.timestamp()).await;
+            self.refresh_if_stale(did, cached.cached_at.timestamp());
             return Ok(doc);
         }
 
         // Cache miss - fetch DID document
-        let doc = self.fetch_did_document(did).await?;
+        let doc = match self.fetch_did_document(did).await {
+            Ok(doc) => doc,
+            Err(e) => return self.offline_did_doc(did).await?.ok_or(e),
+        };
+        self.store_did_doc(did, &doc).await?;
+
+        Ok(doc)
+    }
 
-        // Cache the document
-        let doc_json = serde_json::to_string(&doc)
+    /// An expired DID document to serve when the real one can't be fetched
+    ///
+    /// Only in offline mode; the document is not copied back into Redis so
+    /// the next lookup tries the network again.
+    async fn offline_did_doc(&self, did: &str) -> PdsResult<Option<DidDocument>> {
+        if !self.config.offline_mode {
+            return Ok(None);
+        }
+        let Some(cached) = self.cache.get_stale_did_doc(did).await? else {
+            return Ok(None);
+        };
+        let doc: DidDocument = serde_json::from_str(&cached.doc)
+            .map_err(|e| PdsError::Internal(format!("Invalid cached DID document: {}", e)))?;
+
+        tracing::warn!(
+            did = %did,
+            age_secs = (Utc::now() - cached.cached_at).num_seconds(),
+            "serving_stale_did_doc"
+        );
+        metrics::record_stale_did_doc_served();
+        Ok(Some(doc))
+    }
+
+    /// Health of each configured PLC directory
+    pub fn plc_mirror_status(&self) -> Vec<PlcMirrorStatus> {
+        self.plc_mirrors.status()
+    }
+
+    /// Refresh a DID document in the background once it is older than the stale TTL
+    fn refresh_if_stale(&self, did: &str, cached_at: i64) {
+        if Utc::now().timestamp() - cached_at < self.config.did_stale_ttl as i64 {
+            return;
+        }
+
+        // Only one refresh per DID at a time
+        if !self.refreshing.lock().unwrap().insert(did.to_string()) {
+            return;
+        }
+
+        let resolver = self.clone();
+        let did = did.to_string();
+        tokio::spawn(async move {
+            match resolver.fetch_did_document(&did).await {
+                Ok(doc) => {
+                    if let Err(e) = resolver.store_did_doc(&did, &doc).await {
+                        tracing::warn!(did = %did, error = %e, "did_doc_refresh_store_failed");
+                    }
+                }
+                // Keep serving the stale document until it reaches the max TTL
+                Err(e) => tracing::debug!(did = %did, error = %e, "did_doc_refresh_failed"),
+            }
+            resolver.refreshing.lock().unwrap().remove(&did);
+        });
+    }
+
+    /// Write a freshly fetched DID document to both cache tiers
+    async fn store_did_doc(&self, did: &str, doc: &DidDocument) -> PdsResult<()> {
+        let doc_json = serde_json::to_string(doc)
             .map_err(|e| PdsError::Internal(format!("Failed to serialize DID document: {}", e)))?;
         self.cache.cache_did_doc(did, &doc_json).await?;
+        self.redis_set_did_doc(did, doc, Utc::now().timestamp()).await;
+        Ok(())
+    }
 
-        Ok(doc)
+    /// Write a handle mapping to both cache tiers
+    async fn store_handle(&self, handle: &str, did: &str) -> PdsResult<()> {
+        self.cache.cache_handle(handle, did).await?;
+        self.redis_set_handle(handle, did).await;
+        self.remember_handle(handle, did);
+        Ok(())
+    }
+
+    /// Read from Redis, treating errors as a miss
+    async fn redis_get<T: serde::de::DeserializeOwned>(&self, category: &str, key: &str) -> Option<T> {
+        let redis = self.redis.as_ref()?;
+        match redis.get(category, key).await {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::debug!(key = %key, error = %e, "identity_redis_get_failed");
+                None
+            }
+        }
+    }
+
+    async fn redis_set_did_doc(&self, did: &str, doc: &DidDocument, cached_at: i64) {
+        if let Some(redis) = &self.redis {
+            let entry = RedisDidDoc { doc: doc.clone(), cached_at };
+            let ttl = redis.config().did_doc_ttl;
+            if let Err(e) = redis.set(categories::DID_DOC, did, &entry, Some(ttl)).await {
+                tracing::debug!(did = %did, error = %e, "identity_redis_set_failed");
+            }
+        }
+    }
+
+    async fn redis_set_handle(&self, handle: &str, did: &str) {
+        if let Some(redis) = &self.redis {
+            let ttl = redis.config().handle_ttl;
+            if let Err(e) = redis.set(categories::HANDLE, handle, &did, Some(ttl)).await {
+                tracing::debug!(handle = %handle, error = %e, "identity_redis_set_failed");
+            }
+        }
+    }
+
+    async fn redis_delete(&self, category: &str, key: &str) {
+        if let Some(redis) = &self.redis {
+            if let Err(e) = redis.delete(category, key).await {
+                tracing::warn!(key = %key, error = %e, "identity_redis_delete_failed");
+            }
+        }
     }
 
     /// Fetch DID document from source
@@ -120,31 +365,65 @@ impl IdentityResolver {
         }
     }
 
-    /// Fetch DID document from PLC directory
+    /// Fetch DID document from the first PLC directory that answers
+    ///
+    /// Connection errors, 5xx and 429 responses, and unparseable bodies
+    /// count against a directory and move on to the next one. Any other
+    /// error status (e.g. 404 for an unknown DID) is an answer, and is
+    /// returned as is.
+    #[tracing::instrument(skip(self))]
     async fn fetch_plc_document(&self, did: &str) -> PdsResult<DidDocument> {
-        let plc_url = format!("https://plc.directory/{}", did);
+        let mut last_error = None;
+        for base in self.plc_mirrors.order() {
+            match self.fetch_plc_document_from(&base, did).await {
+                Ok(doc) => {
+                    self.plc_mirrors.record_success(&base);
+                    return Ok(doc);
+                }
+                Err((e, true)) => {
+                    tracing::debug!(url = %base, did = %did, error = %e, "plc_directory_failed");
+                    self.plc_mirrors.record_failure(&base, &e.to_string());
+                    last_error = Some(e);
+                }
+                Err((e, false)) => {
+                    self.plc_mirrors.record_success(&base);
+                    return Err(e);
+                }
+            }
+        }
 
-        let response = self.http_client
-            .get(&plc_url)
+        Err(last_error.unwrap_or_else(|| {
+            PdsError::IdentityResolution("No PLC directory configured".to_string())
+        }))
+    }
+
+    /// Fetch a DID document from one PLC directory; the flag on errors says
+    /// whether another directory should be tried
+    async fn fetch_plc_document_from(&self, base: &str, did: &str) -> Result<DidDocument, (PdsError, bool)> {
+        let plc_url = format!("{}/{}", base, did);
+
+        let response = inject_trace_context(self.http_client.get(&plc_url))
             .send()
             .await
-            .map_err(|e| PdsError::IdentityResolution(format!("Failed to fetch PLC document: {}", e)))?;
-
-        if !response.status().is_success() {
-            return Err(PdsError::IdentityResolution(
-                format!("PLC directory returned error: {}", response.status())
+            .map_err(|e| (PdsError::IdentityResolution(format!("Failed to fetch PLC document: {}", e)), true))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let retry = status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS;
+            return Err((
+                PdsError::IdentityResolution(format!("PLC directory returned error: {}", status)),
+                retry,
             ));
         }
 
-        let doc: DidDocument = response
+        response
             .json()
             .await
-            .map_err(|e| PdsError::IdentityResolution(format!("Invalid PLC document: {}", e)))?;
-
-        Ok(doc)
+            .map_err(|e| (PdsError::IdentityResolution(format!("Invalid PLC document: {}", e)), true))
     }
 
     /// Fetch DID document from did:web
+    #[tracing::instrument(skip(self))]
     async fn fetch_web_document(&self, did: &str) -> PdsResult<DidDocument> {
         // did:web:example.com -> https://example.com/.well-known/did.json
         // did:web:example.com:user:alice -> https://example.com/user/alice/did.json
@@ -162,8 +441,7 @@ impl IdentityResolver {
             format!("https://{}/{}/did.json", domain, path)
         };
 
-        let response = self.http_client
-            .get(&url)
+        let response = inject_trace_context(self.http_client.get(&url))
             .send()
             .await
             .map_err(|e| PdsError::IdentityResolution(format!("Failed to fetch did:web document: {}", e)))?;
@@ -182,26 +460,6 @@ impl IdentityResolver {
         Ok(doc)
     }
 
-    /// Update handle for a DID
-    ///
-    /// This updates the cache and should be called when a user changes their handle
-    pub async fn update_handle(&self, did: &str, handle: &str) -> PdsResult<()> {
-        let normalized = handle.to_lowercase();
-
-        // Verify the handle resolves to this DID
-        let resolved_did = self.resolve_handle(&normalized).await?;
-        if resolved_did != did {
-            return Err(PdsError::IdentityResolution(
-                format!("Handle {} does not resolve to DID {}", handle, did)
-            ));
-        }
-
-        // Update cache
-        self.cache.cache_handle(&normalized, did).await?;
-
-        Ok(())
-    }
-
     /// Get handle for a DID (reverse lookup)
     ///
     /// First checks cache, then falls back to examining DID document's alsoKnownAs
@@ -218,7 +476,7 @@ impl IdentityResolver {
         for aka in &doc.also_known_as {
             if let Some(handle) = aka.strip_prefix("at://") {
                 // Cache this mapping
-                self.cache.cache_handle(handle, did).await?;
+                self.store_handle(handle, did).await?;
                 return Ok(Some(handle.to_string()));
             }
         }
@@ -228,14 +486,36 @@ impl IdentityResolver {
 
     /// Invalidate cached handle (force re-resolution)
     pub async fn invalidate_handle(&self, handle: &str) -> PdsResult<()> {
-        self.cache.delete_handle(handle).await
+        let normalized = handle.to_lowercase();
+        self.hot_handles.lock().unwrap().remove(&normalized);
+        self.redis_delete(categories::HANDLE, &normalized).await;
+        self.cache.delete_handle(&normalized).await
     }
 
     /// Invalidate cached DID document (force re-fetch)
     pub async fn invalidate_did(&self, did: &str) -> PdsResult<()> {
+        self.redis_delete(categories::DID_DOC, did).await;
         self.cache.delete_did_doc(did).await
     }
 
+    /// Invalidate everything cached for a DID after an identity change
+    ///
+    /// Drops the DID document, the previously cached handle for the DID, and
+    /// the new handle (if any) so all of them are re-resolved.
+    pub async fn invalidate_identity(&self, did: &str, handle: Option<&str>) -> PdsResult<()> {
+        self.invalidate_did(did).await?;
+        self.hot_handles.lock().unwrap().retain(|_, (cached, _)| cached != did);
+
+        if let Some(old_handle) = self.cache.get_did_handle(did).await? {
+            self.invalidate_handle(&old_handle).await?;
+        }
+        if let Some(handle) = handle {
+            self.invalidate_handle(handle).await?;
+        }
+
+        Ok(())
+    }
+
     /// Clean up expired cache entries
     pub async fn cleanup_cache(&self) -> PdsResult<()> {
         self.cache.cleanup_expired().await
@@ -247,7 +527,7 @@ mod tests {
     use super::*;
     use sqlx::SqlitePool;
 
-    async fn create_test_resolver() -> IdentityResolver {
+    async fn create_test_db() -> SqlitePool {
         let db = SqlitePool::connect(":memory:").await.unwrap();
 
         // Create cache tables
@@ -279,7 +559,11 @@ mod tests {
         .await
         .unwrap();
 
-        let cache = DidCache::new(db);
+        db
+    }
+
+    async fn create_test_resolver() -> IdentityResolver {
+        let cache = DidCache::new(create_test_db().await);
         IdentityResolver::new(cache, IdentityResolverConfig::default()).unwrap()
     }
 
@@ -302,6 +586,23 @@ mod tests {
         assert_eq!(did_upper, "did:plc:alice123");
     }
 
+    #[tokio::test]
+    async fn test_hot_handle_tier() {
+        let resolver = create_test_resolver().await;
+
+        assert!(resolver.cached_handle("carol.test").await.is_none());
+        resolver.remember_handle("Carol.Test", "did:plc:carol");
+        assert_eq!(resolver.cached_handle("carol.test").await.as_deref(), Some("did:plc:carol"));
+
+        // Identity events drop every handle pointing at the DID
+        resolver.invalidate_identity("did:plc:carol", None).await.unwrap();
+        assert!(resolver.cached_handle("carol.test").await.is_none());
+
+        resolver.remember_handle("dave.test", "did:plc:dave");
+        resolver.invalidate_handle("DAVE.test").await.unwrap();
+        assert!(resolver.cached_handle("dave.test").await.is_none());
+    }
+
     #[tokio::test]
     async fn test_get_handle_for_did() {
         let resolver = create_test_resolver().await;
@@ -339,6 +640,63 @@ mod tests {
         assert!(cached_after.is_none());
     }
 
+    #[tokio::test]
+    async fn test_invalidate_identity() {
+        let resolver = create_test_resolver().await;
+
+        let doc = serde_json::json!({
+            "id": "did:plc:dave",
+            "alsoKnownAs": ["at://dave.test"]
+        });
+        resolver.cache.cache_did_doc("did:plc:dave", &doc.to_string()).await.unwrap();
+        resolver.cache.cache_handle("dave.test", "did:plc:dave").await.unwrap();
+        resolver.cache.cache_handle("dave.example.com", "did:plc:someone-else").await.unwrap();
+
+        // Handle change to dave.example.com drops the doc, old handle and new handle
+        resolver
+            .invalidate_identity("did:plc:dave", Some("dave.example.com"))
+            .await
+            .unwrap();
+
+        assert!(resolver.cache.get_did_doc("did:plc:dave").await.unwrap().is_none());
+        assert!(resolver.cache.get_handle("dave.test").await.unwrap().is_none());
+        assert!(resolver.cache.get_handle("dave.example.com").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_offline_mode_serves_stale_did_doc() {
+        // Nothing listens on these, and every cached document is already expired
+        let config = IdentityResolverConfig {
+            plc_urls: vec!["http://127.0.0.1:9".to_string(), "http://127.0.0.1:9/".to_string(), "http://127.0.0.1:7".to_string()],
+            offline_mode: true,
+            ..IdentityResolverConfig::default()
+        };
+        let cache = DidCache::new(create_test_db().await)
+            .with_ttls(chrono::Duration::zero(), chrono::Duration::minutes(5))
+            .with_offline_retention(chrono::Duration::days(7));
+        let resolver = IdentityResolver::new(cache, config.clone()).unwrap();
+
+        let doc = serde_json::json!({"id": "did:plc:erin", "alsoKnownAs": ["at://erin.test"]});
+        resolver.cache.cache_did_doc("did:plc:erin", &doc.to_string()).await.unwrap();
+
+        let resolved = resolver.resolve_did("did:plc:erin").await.unwrap();
+        assert_eq!(resolved.id, "did:plc:erin");
+        assert!(resolver.resolve_did("did:plc:unknown").await.is_err());
+
+        // Both directories were tried on each lookup
+        let status = resolver.plc_mirror_status();
+        assert_eq!(status.len(), 2);
+        assert!(status.iter().all(|m| m.consecutive_failures == 2 && m.last_error.is_some()));
+
+        // Without offline mode the expired document is not served
+        let online = IdentityResolver::new(
+            resolver.cache.clone(),
+            IdentityResolverConfig { offline_mode: false, ..config },
+        )
+        .unwrap();
+        assert!(online.resolve_did("did:plc:erin").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_did_web_url_parsing() {
         let resolver = create_test_resolver().await;
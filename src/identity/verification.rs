@@ -0,0 +1,417 @@
+/// Handle domain verification
+///
+/// A handle is valid for a DID when the domain points back at it through either:
+/// - a `_atproto.<handle>` DNS TXT record `did=<did>` (queried over DNS-over-HTTPS), or
+/// - `https://<handle>/.well-known/atproto-did` returning the DID as plain text.
+///
+/// Verified custom-domain handles are recorded in `handle_verification` so a
+/// background job can re-check them and flag handles whose domain stopped
+/// pointing at the account.
+use crate::error::{PdsError, PdsResult};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+
+/// Consecutive failed re-checks before a handle is marked invalid
+pub const MAX_VERIFICATION_FAILURES: i64 = 3;
+
+/// DNS record type for TXT
+const DNS_TYPE_TXT: u64 = 16;
+
+/// How a handle was verified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HandleVerificationMethod {
+    Dns,
+    Https,
+}
+
+impl HandleVerificationMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Dns => "dns",
+            Self::Https => "https",
+        }
+    }
+}
+
+/// Resolves handles via DNS TXT and HTTPS well-known
+#[derive(Clone)]
+pub struct HandleVerifier {
+    http_client: reqwest::Client,
+    doh_url: String,
+}
+
+impl HandleVerifier {
+    pub fn new(http_client: reqwest::Client, doh_url: String) -> Self {
+        Self { http_client, doh_url }
+    }
+
+    /// Resolve a handle to a DID, trying DNS first and then HTTPS
+    pub async fn resolve(&self, handle: &str) -> PdsResult<Option<(String, HandleVerificationMethod)>> {
+        match self.resolve_dns(handle).await {
+            Ok(Some(did)) => return Ok(Some((did, HandleVerificationMethod::Dns))),
+            Ok(None) => {}
+            Err(e) => tracing::debug!(handle = %handle, error = %e, "handle_dns_lookup_failed"),
+        }
+
+        match self.resolve_https(handle).await {
+            Ok(Some(did)) => Ok(Some((did, HandleVerificationMethod::Https))),
+            Ok(None) => Ok(None),
+            Err(e) => {
+                tracing::debug!(handle = %handle, error = %e, "handle_https_lookup_failed");
+                Ok(None)
+            }
+        }
+    }
+
+    /// Verify that a handle points at `did`, returning the method that matched
+    pub async fn verify(&self, handle: &str, did: &str) -> PdsResult<HandleVerificationMethod> {
+        let dns = self.resolve_dns(handle).await.ok().flatten();
+        if dns.as_deref() == Some(did) {
+            return Ok(HandleVerificationMethod::Dns);
+        }
+
+        let https = self.resolve_https(handle).await.ok().flatten();
+        if https.as_deref() == Some(did) {
+            return Ok(HandleVerificationMethod::Https);
+        }
+
+        let found = dns.or(https);
+        Err(PdsError::Validation(match found {
+            Some(other) => format!("Handle {} resolves to {}, not {}", handle, other, did),
+            None => format!(
+                "Handle {} could not be verified: add a TXT record \"did={}\" at _atproto.{} \
+                 or serve the DID at https://{}/.well-known/atproto-did",
+                handle, did, handle, handle
+            ),
+        }))
+    }
+
+    /// Look up `_atproto.<handle>` TXT records over DNS-over-HTTPS
+    async fn resolve_dns(&self, handle: &str) -> PdsResult<Option<String>> {
+        let response = self
+            .http_client
+            .get(&self.doh_url)
+            .query(&[("name", format!("_atproto.{}", handle)), ("type", "TXT".to_string())])
+            .header("accept", "application/dns-json")
+            .send()
+            .await
+            .map_err(|e| PdsError::IdentityResolution(format!("DNS query failed: {}", e)))?;
+
+        if !response.status().is_success() {
+            return Err(PdsError::IdentityResolution(format!(
+                "DNS resolver returned {}",
+                response.status()
+            )));
+        }
+
+        let body: serde_json::Value = response
+            .json()
+            .await
+            .map_err(|e| PdsError::IdentityResolution(format!("Invalid DNS response: {}", e)))?;
+
+        Ok(parse_doh_txt(&body))
+    }
+
+    /// Fetch `https://<handle>/.well-known/atproto-did`
+    async fn resolve_https(&self, handle: &str) -> PdsResult<Option<String>> {
+        let url = format!("https://{}/.well-known/atproto-did", handle);
+
+        let response = self
+            .http_client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| PdsError::IdentityResolution(format!("Failed to fetch {}: {}", url, e)))?;
+
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+
+        let body = response
+            .text()
+            .await
+            .map_err(|e| PdsError::IdentityResolution(format!("Failed to read {}: {}", url, e)))?;
+
+        Ok(parse_well_known(&body))
+    }
+}
+
+/// Extract the DID from a DNS JSON (RFC 8484 JSON API) TXT answer
+///
+/// Exactly one distinct `did=` value is required; conflicting records are ignored.
+fn parse_doh_txt(body: &serde_json::Value) -> Option<String> {
+    let answers = body.get("Answer")?.as_array()?;
+
+    let mut dids: Vec<String> = answers
+        .iter()
+        .filter(|a| a.get("type").and_then(|t| t.as_u64()) == Some(DNS_TYPE_TXT))
+        .filter_map(|a| a.get("data").and_then(|d| d.as_str()))
+        .map(|data| {
+            // TXT data may be split into quoted character-strings
+            data.split('"')
+                .enumerate()
+                .filter(|(i, _)| i % 2 == 1)
+                .map(|(_, s)| s)
+                .collect::<String>()
+        })
+        .filter_map(|txt| txt.strip_prefix("did=").map(|d| d.trim().to_string()))
+        .filter(|did| did.starts_with("did:"))
+        .collect();
+
+    dids.sort();
+    dids.dedup();
+
+    match dids.len() {
+        1 => dids.pop(),
+        _ => None,
+    }
+}
+
+/// Extract the DID from a well-known atproto-did body
+fn parse_well_known(body: &str) -> Option<String> {
+    let did = body.lines().next()?.trim();
+    if did.starts_with("did:") && did.len() <= 2048 && !did.contains(char::is_whitespace) {
+        Some(did.to_string())
+    } else {
+        None
+    }
+}
+
+/// Verification state of a custom-domain handle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HandleVerification {
+    pub did: String,
+    pub handle: String,
+    pub method: HandleVerificationMethod,
+    /// "verified" or "invalid"
+    pub status: String,
+    pub verified_at: DateTime<Utc>,
+    pub last_checked_at: DateTime<Utc>,
+    pub consecutive_failures: i64,
+    pub last_error: Option<String>,
+}
+
+/// Persistence for custom-domain handle verification
+#[derive(Clone)]
+pub struct HandleVerificationManager {
+    db: SqlitePool,
+}
+
+impl HandleVerificationManager {
+    pub fn new(db: SqlitePool) -> Self {
+        Self { db }
+    }
+
+    /// Record a successful verification (resets failure tracking)
+    pub async fn record_verified(
+        &self,
+        did: &str,
+        handle: &str,
+        method: HandleVerificationMethod,
+    ) -> PdsResult<()> {
+        let now = Utc::now().to_rfc3339();
+
+        sqlx::query(
+            r#"
+            INSERT INTO handle_verification
+                (did, handle, method, status, verified_at, last_checked_at, consecutive_failures, last_error)
+            VALUES (?, ?, ?, 'verified', ?, ?, 0, NULL)
+            ON CONFLICT(did) DO UPDATE SET
+                handle = excluded.handle,
+                method = excluded.method,
+                status = 'verified',
+                verified_at = excluded.verified_at,
+                last_checked_at = excluded.last_checked_at,
+                consecutive_failures = 0,
+                last_error = NULL
+            "#,
+        )
+        .bind(did)
+        .bind(handle)
+        .bind(method.as_str())
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Record a failed re-check, returning the new consecutive failure count
+    ///
+    /// The handle is marked invalid once `MAX_VERIFICATION_FAILURES` is reached.
+    pub async fn record_failure(&self, did: &str, error: &str) -> PdsResult<i64> {
+        let failures: i64 = sqlx::query_scalar(
+            r#"
+            UPDATE handle_verification SET
+                consecutive_failures = consecutive_failures + 1,
+                last_checked_at = ?,
+                last_error = ?,
+                status = CASE WHEN consecutive_failures + 1 >= ? THEN 'invalid' ELSE status END
+            WHERE did = ?
+            RETURNING consecutive_failures
+            "#,
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(error)
+        .bind(MAX_VERIFICATION_FAILURES)
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?
+        .unwrap_or(0);
+
+        Ok(failures)
+    }
+
+    /// Stop tracking a DID (e.g. after switching to a service handle)
+    pub async fn remove(&self, did: &str) -> PdsResult<()> {
+        sqlx::query("DELETE FROM handle_verification WHERE did = ?")
+            .bind(did)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Get the verification state for a DID
+    pub async fn get(&self, did: &str) -> PdsResult<Option<HandleVerification>> {
+        let row = sqlx::query(
+            "SELECT did, handle, method, status, verified_at, last_checked_at, consecutive_failures, last_error
+             FROM handle_verification WHERE did = ?",
+        )
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?;
+
+        row.map(|row| row_to_verification(&row)).transpose()
+    }
+
+    /// Handles not checked within `max_age`, oldest first
+    pub async fn due_for_recheck(&self, max_age: Duration, limit: i64) -> PdsResult<Vec<HandleVerification>> {
+        let cutoff = (Utc::now() - max_age).to_rfc3339();
+
+        let rows = sqlx::query(
+            "SELECT did, handle, method, status, verified_at, last_checked_at, consecutive_failures, last_error
+             FROM handle_verification
+             WHERE status = 'verified' AND last_checked_at < ?
+             ORDER BY last_checked_at
+             LIMIT ?",
+        )
+        .bind(cutoff)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter().map(row_to_verification).collect()
+    }
+}
+
+fn row_to_verification(row: &sqlx::sqlite::SqliteRow) -> PdsResult<HandleVerification> {
+    let parse_time = |s: String| {
+        DateTime::parse_from_rfc3339(&s)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| PdsError::Internal(format!("Invalid timestamp in handle_verification: {}", e)))
+    };
+
+    let method = match row.get::<String, _>("method").as_str() {
+        "dns" => HandleVerificationMethod::Dns,
+        _ => HandleVerificationMethod::Https,
+    };
+
+    Ok(HandleVerification {
+        did: row.get("did"),
+        handle: row.get("handle"),
+        method,
+        status: row.get("status"),
+        verified_at: parse_time(row.get("verified_at"))?,
+        last_checked_at: parse_time(row.get("last_checked_at"))?,
+        consecutive_failures: row.get("consecutive_failures"),
+        last_error: row.get("last_error"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_doh_txt() {
+        let body = json!({
+            "Status": 0,
+            "Answer": [
+                { "name": "_atproto.alice.com", "type": 16, "data": "\"did=did:plc:abc123\"" },
+                { "name": "_atproto.alice.com", "type": 16, "data": "\"v=spf1 -all\"" }
+            ]
+        });
+        assert_eq!(parse_doh_txt(&body), Some("did:plc:abc123".to_string()));
+
+        // Split character-strings are joined
+        let split = json!({ "Answer": [{ "type": 16, "data": "\"did=did:plc:\" \"abc123\"" }] });
+        assert_eq!(parse_doh_txt(&split), Some("did:plc:abc123".to_string()));
+
+        // Conflicting records are rejected
+        let conflicting = json!({ "Answer": [
+            { "type": 16, "data": "\"did=did:plc:one\"" },
+            { "type": 16, "data": "\"did=did:plc:two\"" }
+        ]});
+        assert_eq!(parse_doh_txt(&conflicting), None);
+
+        assert_eq!(parse_doh_txt(&json!({ "Status": 3 })), None);
+    }
+
+    #[test]
+    fn test_parse_well_known() {
+        assert_eq!(parse_well_known("did:plc:abc123\n"), Some("did:plc:abc123".to_string()));
+        assert_eq!(parse_well_known("  did:web:alice.com  "), Some("did:web:alice.com".to_string()));
+        assert_eq!(parse_well_known("<html>not found</html>"), None);
+        assert_eq!(parse_well_known(""), None);
+    }
+
+    #[tokio::test]
+    async fn test_verification_failures_mark_invalid() {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(
+            r#"
+            CREATE TABLE handle_verification (
+                did TEXT PRIMARY KEY NOT NULL,
+                handle TEXT NOT NULL,
+                method TEXT NOT NULL,
+                status TEXT NOT NULL,
+                verified_at TEXT NOT NULL,
+                last_checked_at TEXT NOT NULL,
+                consecutive_failures INTEGER NOT NULL DEFAULT 0,
+                last_error TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        let manager = HandleVerificationManager::new(db);
+        manager
+            .record_verified("did:plc:alice", "alice.com", HandleVerificationMethod::Dns)
+            .await
+            .unwrap();
+
+        for expected in 1..=MAX_VERIFICATION_FAILURES {
+            assert_eq!(manager.record_failure("did:plc:alice", "no record").await.unwrap(), expected);
+        }
+
+        let state = manager.get("did:plc:alice").await.unwrap().unwrap();
+        assert_eq!(state.status, "invalid");
+        assert_eq!(state.last_error.as_deref(), Some("no record"));
+
+        // Re-verifying resets the state
+        manager
+            .record_verified("did:plc:alice", "alice.com", HandleVerificationMethod::Https)
+            .await
+            .unwrap();
+        let state = manager.get("did:plc:alice").await.unwrap().unwrap();
+        assert_eq!(state.status, "verified");
+        assert_eq!(state.consecutive_failures, 0);
+    }
+}
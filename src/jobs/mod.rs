@@ -1,16 +1,239 @@
-use std::sync::Arc;
-use tokio::time::{interval, Duration};
-use tracing::{error, info};
+use crate::{context::AppContext, error::PdsResult};
+use chrono::Utc;
+use rand::Rng;
+use std::{future::Future, pin::Pin, sync::Arc};
+use tokio::{
+    sync::Notify,
+    time::{sleep, Duration},
+};
+use tracing::{debug, error, info, warn};
 
+pub mod registry;
+pub mod schedule;
 pub mod tasks;
 
+pub use registry::{JobInfo, JobRegistry};
+
+/// Runs of a job failing with a retryable error, including the first
+const JOB_ATTEMPTS: u32 = 3;
+
+/// Delay before retrying a job, doubled for each further attempt
+const JOB_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+/// Future returned by a job run; `Some` summary is logged and recorded
+pub type JobFuture = Pin<Box<dyn Future<Output = PdsResult<Option<String>>> + Send>>;
+
+/// A named background job
+pub struct JobDefinition {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Default cron schedule (UTC), overridable with `PDS_JOB_<NAME>_SCHEDULE`
+    pub schedule: &'static str,
+    /// Also run shortly after startup (after the startup jitter)
+    pub run_at_startup: bool,
+    pub run: fn(Arc<AppContext>) -> JobFuture,
+    /// Extra wake-up signal besides the schedule
+    pub wake: Option<fn(&AppContext) -> Arc<Notify>>,
+}
+
+/// Every background job, in the order they are listed
+pub static JOBS: &[JobDefinition] = &[
+    JobDefinition {
+        name: "session_cleanup",
+        description: "Delete expired sessions and refresh tokens",
+        schedule: "0 * * * *",
+        run_at_startup: false,
+        run: session_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "jwt_key_rotation",
+        description: "Rotate the JWT signing key when due and delete expired retired keys",
+        schedule: "20 * * * *",
+        run_at_startup: true,
+        run: jwt_key_rotation,
+        wake: None,
+    },
+    JobDefinition {
+        name: "suspension_cleanup",
+        description: "Lift expired suspensions",
+        schedule: "*/15 * * * *",
+        run_at_startup: false,
+        run: suspension_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "identity_cache_cleanup",
+        description: "Evict expired identity cache entries",
+        schedule: "*/30 * * * *",
+        run_at_startup: false,
+        run: identity_cache_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "account_deletion",
+        description: "Purge accounts whose deletion grace period has ended",
+        schedule: "0 3 * * *",
+        run_at_startup: false,
+        run: account_deletion,
+        wake: None,
+    },
+    JobDefinition {
+        name: "temp_blob_cleanup",
+        description: "Delete uploaded blobs never referenced by a record",
+        schedule: "0 */6 * * *",
+        run_at_startup: false,
+        run: temp_blob_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "account_export_cleanup",
+        description: "Remove expired account exports",
+        schedule: "30 */6 * * *",
+        run_at_startup: false,
+        run: account_export_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "handle_reverification",
+        description: "Re-verify custom domain handles",
+        schedule: "15 * * * *",
+        run_at_startup: false,
+        run: handle_reverification,
+        wake: None,
+    },
+    JobDefinition {
+        name: "audit_log_retention",
+        description: "Prune admin audit log entries past the retention window",
+        schedule: "0 4 * * *",
+        run_at_startup: false,
+        run: audit_log_retention,
+        wake: None,
+    },
+    JobDefinition {
+        name: "tombstone_retention",
+        description: "Purge deleted-record tombstones past the retention window",
+        schedule: "15 4 * * *",
+        run_at_startup: false,
+        run: tombstone_retention,
+        wake: None,
+    },
+    JobDefinition {
+        name: "webhook_delivery",
+        description: "Send queued moderation webhook deliveries",
+        schedule: "*/15 * * * * *",
+        run_at_startup: true,
+        run: webhook_delivery,
+        wake: Some(webhook_notifier),
+    },
+    JobDefinition {
+        name: "repo_webhook_delivery",
+        description: "Send queued repo event webhook deliveries",
+        schedule: "*/15 * * * * *",
+        run_at_startup: true,
+        run: repo_webhook_delivery,
+        wake: Some(repo_webhook_notifier),
+    },
+    JobDefinition {
+        name: "activitypub_delivery",
+        description: "Send queued ActivityPub activities to follower inboxes",
+        schedule: "*/30 * * * * *",
+        run_at_startup: true,
+        run: activitypub_delivery,
+        wake: Some(activitypub_notifier),
+    },
+    JobDefinition {
+        name: "webhook_delivery_cleanup",
+        description: "Prune old delivered webhook events and ActivityPub activities",
+        schedule: "30 4 * * *",
+        run_at_startup: false,
+        run: webhook_delivery_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "email_delivery",
+        description: "Send queued email",
+        schedule: "*/30 * * * * *",
+        run_at_startup: true,
+        run: email_delivery,
+        wake: Some(email_notifier),
+    },
+    JobDefinition {
+        name: "email_queue_cleanup",
+        description: "Prune old sent and failed email",
+        schedule: "45 4 * * *",
+        run_at_startup: false,
+        run: email_queue_cleanup,
+        wake: None,
+    },
+    JobDefinition {
+        name: "record_encoding_migration",
+        description: "Re-encode records stored as JSON into DAG-CBOR",
+        schedule: "0 5 * * *",
+        run_at_startup: true,
+        run: record_encoding_migration,
+        wake: None,
+    },
+    JobDefinition {
+        name: "repo_compaction",
+        description: "Prune unreachable repository blocks and vacuum actor stores",
+        schedule: "30 2 * * *",
+        run_at_startup: false,
+        run: repo_compaction,
+        wake: None,
+    },
+    JobDefinition {
+        name: "actor_store_migration",
+        description: "Apply pending schema upgrades to every actor store",
+        schedule: "15 4 * * *",
+        run_at_startup: true,
+        run: actor_store_migration,
+        wake: None,
+    },
+    JobDefinition {
+        name: "sequencer_integrity",
+        description: "Check the sequencer log for gaps and duplicates",
+        schedule: "45 * * * *",
+        run_at_startup: true,
+        run: sequencer_integrity,
+        wake: None,
+    },
+    JobDefinition {
+        name: "firehose_lag",
+        description: "Refresh the firehose consumer lag metrics",
+        schedule: "* * * * *",
+        run_at_startup: false,
+        run: firehose_lag,
+        wake: None,
+    },
+    JobDefinition {
+        name: "instance_stats",
+        description: "Recount users and posts for the nodeinfo document",
+        schedule: "*/15 * * * *",
+        run_at_startup: true,
+        run: instance_stats,
+        wake: None,
+    },
+    JobDefinition {
+        name: "health_check",
+        description: "Verify database connectivity",
+        schedule: "*/5 * * * *",
+        run_at_startup: true,
+        run: health_check,
+        wake: None,
+    },
+];
+
 /// Job scheduler for background tasks
+///
+/// Runs every job in `JOBS` on its cron schedule, skipping disabled jobs,
+/// and runs a job immediately when `JobRegistry::trigger` asks for it.
 pub struct JobScheduler {
-    context: Arc<crate::context::AppContext>,
+    context: Arc<AppContext>,
 }
 
 impl JobScheduler {
-    pub fn new(context: Arc<crate::context::AppContext>) -> Self {
+    pub fn new(context: Arc<AppContext>) -> Self {
         Self { context }
     }
 
@@ -18,131 +241,320 @@ impl JobScheduler {
     pub fn start(self: Arc<Self>) {
         info!("Starting background job scheduler");
 
-        // Spawn cleanup tasks
-        tokio::spawn(Self::expired_session_cleanup_job(Arc::clone(&self)));
-        tokio::spawn(Self::expired_suspension_cleanup_job(Arc::clone(&self)));
-        tokio::spawn(Self::identity_cache_cleanup_job(Arc::clone(&self)));
-        tokio::spawn(Self::account_deletion_job(Arc::clone(&self)));
-        tokio::spawn(Self::temp_blob_cleanup_job(Arc::clone(&self)));
-
-        // Spawn monitoring tasks
-        tokio::spawn(Self::health_check_job(Arc::clone(&self)));
+        for job in JOBS {
+            tokio::spawn(Self::run_loop(Arc::clone(&self), job));
+        }
 
-        info!("Background jobs started");
+        info!("{} background jobs started", JOBS.len());
     }
 
-    /// Cleanup expired sessions (runs every hour)
-    async fn expired_session_cleanup_job(scheduler: Arc<Self>) {
-        let mut interval = interval(Duration::from_secs(3600)); // Every hour
+    /// Wait for each scheduled run (or a run-now request) and run the job
+    async fn run_loop(scheduler: Arc<Self>, job: &'static JobDefinition) {
+        let registry = Arc::clone(&scheduler.context.jobs);
+        let runtime = match registry.runtime(job.name) {
+            Ok(runtime) => runtime,
+            Err(e) => {
+                error!("Job {} not registered: {}", job.name, e);
+                return;
+            }
+        };
+        let wake = job.wake.map(|wake| wake(&scheduler.context));
 
-        loop {
-            interval.tick().await;
-            info!("Running expired session cleanup");
-
-            match tasks::cleanup_expired_sessions(&scheduler.context).await {
-                Ok(count) => {
-                    if count > 0 {
-                        info!("Cleaned up {} expired tokens (sessions + refresh tokens)", count);
-                    } else {
-                        info!("Session cleanup: no expired tokens found");
-                    }
-                }
-                Err(e) => error!("Failed to cleanup expired sessions: {}", e),
+        if job.run_at_startup {
+            // Spread startup runs so instances restarted together don't align
+            let jitter = registry.startup_jitter_secs();
+            if jitter > 0 {
+                let delay = rand::thread_rng().gen_range(0..=jitter);
+                sleep(Duration::from_secs(delay)).await;
+            }
+            if registry.is_enabled(job.name).await.unwrap_or(true) {
+                scheduler.run_once(job).await;
             }
         }
-    }
-
-    /// Cleanup expired suspensions (runs every 15 minutes)
-    async fn expired_suspension_cleanup_job(scheduler: Arc<Self>) {
-        let mut interval = interval(Duration::from_secs(900)); // Every 15 minutes
 
         loop {
-            interval.tick().await;
-            info!("Running expired suspension cleanup");
-
-            match tasks::cleanup_expired_suspensions(&scheduler.context).await {
-                Ok(count) => {
-                    if count > 0 {
-                        info!("Cleaned up {} expired suspensions", count);
-                    }
-                }
-                Err(e) => error!("Failed to cleanup expired suspensions: {}", e),
+            let next = runtime.schedule.next_after(Utc::now());
+            if let Err(e) = registry.record_next_run(job.name, next).await {
+                warn!("Failed to record next run of {}: {}", job.name, e);
             }
-        }
-    }
+            let delay = next
+                .and_then(|t| (t - Utc::now()).to_std().ok())
+                .unwrap_or(Duration::ZERO);
 
-    /// Cleanup expired identity cache entries (runs every 30 minutes)
-    async fn identity_cache_cleanup_job(scheduler: Arc<Self>) {
-        let mut interval = interval(Duration::from_secs(1800)); // Every 30 minutes
+            // A schedule with no future runs only runs on demand
+            let manual = tokio::select! {
+                _ = sleep(delay), if next.is_some() => false,
+                _ = runtime.trigger.notified() => true,
+                _ = woken(wake.as_deref()) => false,
+            };
 
-        loop {
-            interval.tick().await;
-            info!("Running identity cache cleanup");
-
-            match tasks::cleanup_identity_cache(&scheduler.context).await {
-                Ok(_) => {
-                    // Silent success
-                }
-                Err(e) => error!("Failed to cleanup identity cache: {}", e),
+            if manual || registry.is_enabled(job.name).await.unwrap_or(true) {
+                scheduler.run_once(job).await;
             }
         }
     }
 
-    /// Purge deleted accounts after grace period (runs daily)
-    async fn account_deletion_job(scheduler: Arc<Self>) {
-        let mut interval = interval(Duration::from_secs(86400)); // Every 24 hours
-
-        loop {
-            interval.tick().await;
-            info!("Running account deletion job");
-
-            match tasks::purge_deleted_accounts(&scheduler.context).await {
-                Ok(count) => {
-                    if count > 0 {
-                        info!("Purged {} accounts after grace period", count);
-                    } else {
-                        info!("Account deletion: no accounts ready for purge");
-                    }
-                }
-                Err(e) => error!("Failed to purge deleted accounts: {}", e),
-            }
+    /// Run a job once, recording its duration and outcome
+    async fn run_once(&self, job: &'static JobDefinition) {
+        let registry = &self.context.jobs;
+        if !registry.try_start(job.name) {
+            warn!("Job {} is still running, skipping this run", job.name);
+            return;
         }
-    }
 
-    /// Cleanup orphaned temp blobs (runs every 6 hours)
-    async fn temp_blob_cleanup_job(scheduler: Arc<Self>) {
-        let mut interval = interval(Duration::from_secs(21600)); // Every 6 hours
+        let started_at = Utc::now();
+        if let Err(e) = registry.record_start(job.name, started_at).await {
+            warn!("Failed to record start of {}: {}", job.name, e);
+        }
 
-        loop {
-            interval.tick().await;
-            info!("Running temp blob cleanup job");
-
-            match tasks::cleanup_orphaned_temp_blobs(&scheduler.context).await {
-                Ok(count) => {
-                    if count > 0 {
-                        info!("Cleaned up {} orphaned temp blobs", count);
-                    } else {
-                        info!("Temp blob cleanup: no orphaned blobs found");
-                    }
+        // Spawned so a panicking job is recorded as failed instead of
+        // staying "running" forever. Retryable failures (an unreachable
+        // upstream, a busy database) are tried again with backoff.
+        let mut attempt = 1;
+        let result = loop {
+            match tokio::spawn((job.run)(Arc::clone(&self.context))).await {
+                Ok(Err(e)) if e.is_retryable() && attempt < JOB_ATTEMPTS => {
+                    let delay = JOB_RETRY_DELAY * 2u32.pow(attempt - 1);
+                    warn!(
+                        job = job.name,
+                        "Job failed (attempt {}), retrying in {}s: {}",
+                        attempt,
+                        delay.as_secs(),
+                        e
+                    );
+                    sleep(delay).await;
+                    attempt += 1;
                 }
-                Err(e) => error!("Failed to cleanup orphaned temp blobs: {}", e),
+                Ok(result) => break result.map_err(|e| e.to_string()),
+                Err(e) => break Err(format!("Job panicked: {}", e)),
             }
+        };
+
+        match &result {
+            Ok(Some(summary)) => info!(job = job.name, "{}", summary),
+            Ok(None) => debug!(job = job.name, "Job finished"),
+            Err(e) => error!(job = job.name, "Job failed: {}", e),
         }
+
+        if let Err(e) = registry.record_finish(job.name, started_at, &result).await {
+            warn!("Failed to record run of {}: {}", job.name, e);
+        }
+    }
+}
+
+/// Resolve when the optional wake-up signal fires
+async fn woken(wake: Option<&Notify>) {
+    match wake {
+        Some(notify) => notify.notified().await,
+        None => std::future::pending().await,
     }
+}
 
-    /// Health check job (runs every 5 minutes)
-    async fn health_check_job(scheduler: Arc<Self>) {
-        let mut interval = interval(Duration::from_secs(300)); // Every 5 minutes
+fn webhook_notifier(ctx: &AppContext) -> Arc<Notify> {
+    ctx.webhook_manager.notifier()
+}
 
-        loop {
-            interval.tick().await;
+fn repo_webhook_notifier(ctx: &AppContext) -> Arc<Notify> {
+    ctx.repo_webhooks.notifier()
+}
 
-            match tasks::health_check(&scheduler.context).await {
-                Ok(_) => {
-                    // Silent success - health is good
-                }
-                Err(e) => error!("Health check failed: {}", e),
-            }
+fn activitypub_notifier(ctx: &AppContext) -> Arc<Notify> {
+    ctx.activitypub.notifier()
+}
+
+fn email_notifier(ctx: &AppContext) -> Arc<Notify> {
+    ctx.mailer.queue().notifier()
+}
+
+fn session_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::cleanup_expired_sessions(&ctx).await?;
+        Ok((count > 0).then(|| format!("Cleaned up {} expired tokens (sessions + refresh tokens)", count)))
+    })
+}
+
+fn jwt_key_rotation(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let rotated = ctx.jwt_keys.rotate_if_due().await?;
+        let pruned = ctx.jwt_keys.prune().await?;
+        Ok(match rotated {
+            Some(kid) => Some(format!("Rotated JWT signing key to {}, deleted {} expired key(s)", kid, pruned)),
+            None => (pruned > 0).then(|| format!("Deleted {} expired JWT signing key(s)", pruned)),
+        })
+    })
+}
+
+fn suspension_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::cleanup_expired_suspensions(&ctx).await?;
+        Ok((count > 0).then(|| format!("Cleaned up {} expired suspensions", count)))
+    })
+}
+
+fn identity_cache_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        tasks::cleanup_identity_cache(&ctx).await?;
+        Ok(None)
+    })
+}
+
+fn account_deletion(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::purge_deleted_accounts(&ctx).await?;
+        Ok((count > 0).then(|| format!("Purged {} accounts after grace period", count)))
+    })
+}
+
+fn temp_blob_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::cleanup_orphaned_temp_blobs(&ctx).await?;
+        Ok((count > 0).then(|| format!("Cleaned up {} orphaned temp blobs", count)))
+    })
+}
+
+fn account_export_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::cleanup_account_exports(&ctx).await?;
+        Ok((count > 0).then(|| format!("Removed {} expired account exports", count)))
+    })
+}
+
+fn handle_reverification(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (checked, invalidated) = tasks::reverify_handles(&ctx).await?;
+        Ok((checked > 0)
+            .then(|| format!("Re-verified {} custom handle(s), {} marked invalid", checked, invalidated)))
+    })
+}
+
+fn audit_log_retention(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::prune_audit_log(&ctx).await?;
+        Ok((count > 0).then(|| format!("Pruned {} admin audit log entries", count)))
+    })
+}
+
+fn tombstone_retention(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = tasks::prune_tombstones(&ctx).await?;
+        Ok((count > 0).then(|| format!("Purged {} record tombstones", count)))
+    })
+}
+
+fn webhook_delivery(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (delivered, failed) = tasks::deliver_webhooks(&ctx).await?;
+        Ok((delivered > 0 || failed > 0)
+            .then(|| format!("Delivered {} webhook event(s), {} attempt(s) failed", delivered, failed)))
+    })
+}
+
+fn repo_webhook_delivery(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (delivered, failed) = tasks::deliver_repo_webhooks(&ctx).await?;
+        Ok((delivered > 0 || failed > 0)
+            .then(|| format!("Delivered {} repo webhook event(s), {} attempt(s) failed", delivered, failed)))
+    })
+}
+
+fn activitypub_delivery(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (delivered, failed) = tasks::deliver_activitypub(&ctx).await?;
+        Ok((delivered > 0 || failed > 0)
+            .then(|| format!("Delivered {} ActivityPub activities, {} attempt(s) failed", delivered, failed)))
+    })
+}
+
+fn webhook_delivery_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = ctx.webhook_manager.prune_deliveries().await?
+            + ctx.repo_webhooks.prune_deliveries().await?
+            + ctx.activitypub.prune_deliveries().await?;
+        Ok((count > 0).then(|| format!("Pruned {} delivered webhook events and activities", count)))
+    })
+}
+
+fn email_delivery(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (sent, failed) = tasks::deliver_email(&ctx).await?;
+        Ok((sent > 0 || failed > 0).then(|| format!("Sent {} email(s), {} attempt(s) failed", sent, failed)))
+    })
+}
+
+fn email_queue_cleanup(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let count = ctx.mailer.queue().prune().await?;
+        Ok((count > 0).then(|| format!("Pruned {} old queued email(s)", count)))
+    })
+}
+
+fn record_encoding_migration(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (repos, records) = tasks::migrate_record_encoding(&ctx).await?;
+        Ok((repos > 0).then(|| format!("Re-encoded {} record(s) in {} repo(s) as DAG-CBOR", records, repos)))
+    })
+}
+
+fn actor_store_migration(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let report = tasks::migrate_actor_stores(&ctx).await?;
+        Ok((report.upgraded > 0 || !report.failed.is_empty()).then(|| {
+            format!(
+                "Upgraded {} of {} actor store(s), {} failed",
+                report.upgraded,
+                report.discovered,
+                report.failed.len()
+            )
+        }))
+    })
+}
+
+fn repo_compaction(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let (repos, blocks, bytes) = tasks::compact_actor_stores(&ctx).await?;
+        Ok((repos > 0).then(|| format!("Compacted {} repo(s): {} block(s) pruned, {} byte(s) reclaimed", repos, blocks, bytes)))
+    })
+}
+
+fn sequencer_integrity(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let health = tasks::check_sequencer_integrity(&ctx).await?;
+        if !health.is_healthy() || health.invalidated_duplicates > 0 {
+            let summary = format!(
+                "Sequencer integrity: {} gap(s) ({} missing event(s)), {} duplicate commit(s) invalidated",
+                health.gaps.len(),
+                health.missing_events,
+                health.invalidated_duplicates
+            );
+            warn!("{}", summary);
+            return Ok(Some(summary));
         }
-    }
+        Ok(None)
+    })
+}
+
+fn firehose_lag(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let head = ctx.sequencer.current_seq().await?.unwrap_or(0);
+        ctx.firehose_consumers.list(head);
+        Ok(None)
+    })
+}
+
+fn instance_stats(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        let stats = ctx.instance_stats.refresh().await?;
+        debug!(
+            "Instance stats: {} users ({} active this month), {} posts",
+            stats.total_users, stats.active_month, stats.local_posts
+        );
+        Ok(None)
+    })
+}
+
+fn health_check(ctx: Arc<AppContext>) -> JobFuture {
+    Box::pin(async move {
+        tasks::health_check(&ctx).await?;
+        Ok(None)
+    })
 }
@@ -0,0 +1,341 @@
+/// Job registry: schedules, enable flags and run history
+///
+/// Holds the runtime state of every job in `JOBS` (its schedule, a trigger
+/// for run-now requests and whether it is running) and persists run history
+/// in the `job_state` table so it survives restarts. An admin enable/disable
+/// stored there takes precedence over `PDS_JOB_<NAME>_ENABLED`.
+use crate::{
+    config::JobsConfig,
+    error::{PdsError, PdsResult},
+    jobs::{schedule::JobSchedule, JOBS},
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::sync::Notify;
+
+/// Runtime state of one job
+pub struct JobRuntime {
+    pub schedule: JobSchedule,
+    config_enabled: bool,
+    /// Signalled by run-now requests
+    pub trigger: Notify,
+    running: AtomicBool,
+}
+
+/// A job as reported by the admin API
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JobInfo {
+    pub name: String,
+    pub description: String,
+    pub schedule: String,
+    pub enabled: bool,
+    pub running: bool,
+    pub last_started_at: Option<DateTime<Utc>>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    pub last_duration_ms: Option<i64>,
+    /// "succeeded" or "failed"
+    pub last_status: Option<String>,
+    /// Summary of the last run, or its error
+    pub last_result: Option<String>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub run_count: i64,
+    pub failure_count: i64,
+}
+
+/// Registry of background jobs
+pub struct JobRegistry {
+    db: SqlitePool,
+    startup_jitter_secs: u64,
+    jobs: HashMap<&'static str, JobRuntime>,
+}
+
+impl JobRegistry {
+    /// Build the registry, applying configured schedules and enable flags
+    pub fn new(db: SqlitePool, config: &JobsConfig) -> PdsResult<Self> {
+        let mut jobs = HashMap::new();
+        for job in JOBS {
+            let schedule = JobSchedule::parse(config.schedule(job.name).unwrap_or(job.schedule))?;
+            jobs.insert(
+                job.name,
+                JobRuntime {
+                    schedule,
+                    config_enabled: config.enabled(job.name),
+                    trigger: Notify::new(),
+                    running: AtomicBool::new(false),
+                },
+            );
+        }
+
+        Ok(Self {
+            db,
+            startup_jitter_secs: config.startup_jitter_secs,
+            jobs,
+        })
+    }
+
+    pub fn startup_jitter_secs(&self) -> u64 {
+        self.startup_jitter_secs
+    }
+
+    /// Runtime state of a registered job
+    pub fn runtime(&self, name: &str) -> PdsResult<&JobRuntime> {
+        self.jobs
+            .get(name)
+            .ok_or_else(|| PdsError::NotFound(format!("Unknown job: {}", name)))
+    }
+
+    /// Whether the job runs on its schedule
+    pub async fn is_enabled(&self, name: &str) -> PdsResult<bool> {
+        let runtime = self.runtime(name)?;
+        let stored: Option<Option<bool>> = sqlx::query_scalar("SELECT enabled FROM job_state WHERE name = ?1")
+            .bind(name)
+            .fetch_optional(&self.db)
+            .await?;
+        Ok(stored.flatten().unwrap_or(runtime.config_enabled))
+    }
+
+    /// Enable or disable a job; `None` goes back to the configured flag
+    pub async fn set_enabled(&self, name: &str, enabled: Option<bool>) -> PdsResult<JobInfo> {
+        self.runtime(name)?;
+        sqlx::query(
+            "INSERT INTO job_state (name, enabled) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET enabled = excluded.enabled",
+        )
+        .bind(name)
+        .bind(enabled)
+        .execute(&self.db)
+        .await?;
+        self.get(name).await
+    }
+
+    /// Ask the scheduler to run a job now, even if it is disabled
+    pub fn trigger(&self, name: &str) -> PdsResult<()> {
+        let runtime = self.runtime(name)?;
+        if runtime.running.load(Ordering::SeqCst) {
+            return Err(PdsError::Conflict(format!("Job {} is already running", name)));
+        }
+        runtime.trigger.notify_one();
+        Ok(())
+    }
+
+    /// Mark a job as running; false if it already is
+    pub fn try_start(&self, name: &str) -> bool {
+        self.jobs
+            .get(name)
+            .map(|r| !r.running.swap(true, Ordering::SeqCst))
+            .unwrap_or(false)
+    }
+
+    /// Record the start of a run
+    pub async fn record_start(&self, name: &str, started_at: DateTime<Utc>) -> PdsResult<()> {
+        sqlx::query(
+            "INSERT INTO job_state (name, last_started_at) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET last_started_at = excluded.last_started_at",
+        )
+        .bind(name)
+        .bind(started_at.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Record the outcome of a run and clear the running flag
+    pub async fn record_finish(
+        &self,
+        name: &str,
+        started_at: DateTime<Utc>,
+        result: &Result<Option<String>, String>,
+    ) -> PdsResult<()> {
+        if let Some(runtime) = self.jobs.get(name) {
+            runtime.running.store(false, Ordering::SeqCst);
+        }
+
+        let finished_at = Utc::now();
+        let (status, detail, failed) = match result {
+            Ok(summary) => ("succeeded", summary.clone(), 0),
+            Err(e) => ("failed", Some(e.clone()), 1),
+        };
+
+        sqlx::query(
+            "INSERT INTO job_state
+                (name, last_started_at, last_finished_at, last_duration_ms, last_status, last_result,
+                 run_count, failure_count)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)
+             ON CONFLICT(name) DO UPDATE SET
+                last_finished_at = excluded.last_finished_at,
+                last_duration_ms = excluded.last_duration_ms,
+                last_status = excluded.last_status,
+                last_result = excluded.last_result,
+                run_count = run_count + 1,
+                failure_count = failure_count + excluded.failure_count",
+        )
+        .bind(name)
+        .bind(started_at.to_rfc3339())
+        .bind(finished_at.to_rfc3339())
+        .bind((finished_at - started_at).num_milliseconds())
+        .bind(status)
+        .bind(detail)
+        .bind(failed)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Record when the job will next run on its schedule
+    pub async fn record_next_run(&self, name: &str, next_run_at: Option<DateTime<Utc>>) -> PdsResult<()> {
+        sqlx::query(
+            "INSERT INTO job_state (name, next_run_at) VALUES (?1, ?2)
+             ON CONFLICT(name) DO UPDATE SET next_run_at = excluded.next_run_at",
+        )
+        .bind(name)
+        .bind(next_run_at.map(|t| t.to_rfc3339()))
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// One job with its run history
+    pub async fn get(&self, name: &str) -> PdsResult<JobInfo> {
+        let job = JOBS
+            .iter()
+            .find(|j| j.name == name)
+            .ok_or_else(|| PdsError::NotFound(format!("Unknown job: {}", name)))?;
+        let runtime = self.runtime(name)?;
+
+        let row = sqlx::query(
+            "SELECT enabled, last_started_at, last_finished_at, last_duration_ms, last_status,
+                    last_result, next_run_at, run_count, failure_count
+             FROM job_state WHERE name = ?1",
+        )
+        .bind(name)
+        .fetch_optional(&self.db)
+        .await?;
+
+        let mut info = JobInfo {
+            name: job.name.to_string(),
+            description: job.description.to_string(),
+            schedule: runtime.schedule.as_str().to_string(),
+            enabled: runtime.config_enabled,
+            running: runtime.running.load(Ordering::SeqCst),
+            last_started_at: None,
+            last_finished_at: None,
+            last_duration_ms: None,
+            last_status: None,
+            last_result: None,
+            next_run_at: None,
+            run_count: 0,
+            failure_count: 0,
+        };
+
+        if let Some(row) = row {
+            let time = |column: &str| -> Option<DateTime<Utc>> {
+                row.get::<Option<String>, _>(column)
+                    .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+                    .map(|t| t.with_timezone(&Utc))
+            };
+            if let Some(enabled) = row.get::<Option<bool>, _>("enabled") {
+                info.enabled = enabled;
+            }
+            info.last_started_at = time("last_started_at");
+            info.last_finished_at = time("last_finished_at");
+            info.next_run_at = time("next_run_at");
+            info.last_duration_ms = row.get("last_duration_ms");
+            info.last_status = row.get("last_status");
+            info.last_result = row.get("last_result");
+            info.run_count = row.get("run_count");
+            info.failure_count = row.get("failure_count");
+        }
+
+        Ok(info)
+    }
+
+    /// Every job, in registration order
+    pub async fn list(&self) -> PdsResult<Vec<JobInfo>> {
+        let mut jobs = Vec::with_capacity(JOBS.len());
+        for job in JOBS {
+            jobs.push(self.get(job.name).await?);
+        }
+        Ok(jobs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::JobOverride;
+
+    async fn registry(config: JobsConfig) -> JobRegistry {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(include_str!("../../migrations/20250125000001_job_state.sql"))
+            .execute(&db)
+            .await
+            .unwrap();
+        JobRegistry::new(db, &config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_config_and_admin_overrides() {
+        let mut config = JobsConfig::default();
+        config.overrides.insert(
+            "health_check".to_string(),
+            JobOverride {
+                schedule: Some("*/10 * * * *".to_string()),
+                enabled: Some(false),
+            },
+        );
+        let registry = registry(config).await;
+
+        let info = registry.get("health_check").await.unwrap();
+        assert_eq!(info.schedule, "*/10 * * * *");
+        assert!(!info.enabled);
+        assert!(!registry.is_enabled("health_check").await.unwrap());
+
+        // The admin flag wins until cleared
+        assert!(registry.set_enabled("health_check", Some(true)).await.unwrap().enabled);
+        assert!(registry.is_enabled("health_check").await.unwrap());
+        assert!(!registry.set_enabled("health_check", None).await.unwrap().enabled);
+
+        assert!(registry.is_enabled("session_cleanup").await.unwrap());
+        assert!(matches!(registry.get("nope").await, Err(PdsError::NotFound(_))));
+        assert_eq!(registry.list().await.unwrap().len(), JOBS.len());
+    }
+
+    #[tokio::test]
+    async fn test_run_history() {
+        let registry = registry(JobsConfig::default()).await;
+        let started = Utc::now();
+
+        assert!(registry.try_start("session_cleanup"));
+        assert!(!registry.try_start("session_cleanup"));
+        assert!(matches!(registry.trigger("session_cleanup"), Err(PdsError::Conflict(_))));
+        registry.record_start("session_cleanup", started).await.unwrap();
+        assert!(registry.get("session_cleanup").await.unwrap().running);
+
+        registry
+            .record_finish("session_cleanup", started, &Ok(Some("3 removed".to_string())))
+            .await
+            .unwrap();
+        assert!(registry.try_start("session_cleanup"));
+        registry
+            .record_finish("session_cleanup", started, &Err("database locked".to_string()))
+            .await
+            .unwrap();
+        registry.record_next_run("session_cleanup", Some(started)).await.unwrap();
+
+        let info = registry.get("session_cleanup").await.unwrap();
+        assert!(!info.running);
+        assert_eq!(info.run_count, 2);
+        assert_eq!(info.failure_count, 1);
+        assert_eq!(info.last_status.as_deref(), Some("failed"));
+        assert_eq!(info.last_result.as_deref(), Some("database locked"));
+        assert!(info.last_finished_at.is_some());
+        assert!(info.next_run_at.is_some());
+        assert!(registry.trigger("session_cleanup").is_ok());
+    }
+}
@@ -0,0 +1,87 @@
+/// Cron-style job schedules
+///
+/// Accepts standard five-field expressions (`minute hour day month weekday`),
+/// six fields with a leading seconds field for sub-minute jobs, and the
+/// `@hourly`/`@daily`/`@weekly`/`@monthly`/`@yearly` shorthands. Weekdays
+/// are best written by name (`MON-FRI`). All times are UTC.
+use crate::error::{PdsError, PdsResult};
+use chrono::{DateTime, Utc};
+use std::str::FromStr;
+
+/// Parsed job schedule
+#[derive(Debug, Clone)]
+pub struct JobSchedule {
+    expression: String,
+    schedule: cron::Schedule,
+}
+
+impl JobSchedule {
+    pub fn parse(expression: &str) -> PdsResult<Self> {
+        let expression = expression.trim();
+        let fields = expression.split_whitespace().count();
+
+        // The cron crate always wants a seconds field
+        let normalized = match fields {
+            _ if expression.starts_with('@') => expression.to_string(),
+            5 => format!("0 {}", expression),
+            6 => expression.to_string(),
+            _ => {
+                return Err(PdsError::Validation(format!(
+                    "Invalid schedule \"{}\": expected 5 or 6 fields",
+                    expression
+                )))
+            }
+        };
+
+        let schedule = cron::Schedule::from_str(&normalized)
+            .map_err(|e| PdsError::Validation(format!("Invalid schedule \"{}\": {}", expression, e)))?;
+
+        Ok(Self {
+            expression: expression.to_string(),
+            schedule,
+        })
+    }
+
+    /// The expression as configured
+    pub fn as_str(&self) -> &str {
+        &self.expression
+    }
+
+    /// First run strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.after(&after).next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_and_next_run() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 10, 12, 7, 30).unwrap();
+
+        let hourly = JobSchedule::parse("15 * * * *").unwrap();
+        assert_eq!(hourly.next_after(at), Some(Utc.with_ymd_and_hms(2025, 1, 10, 12, 15, 0).unwrap()));
+
+        let daily = JobSchedule::parse("0 3 * * *").unwrap();
+        assert_eq!(daily.next_after(at), Some(Utc.with_ymd_and_hms(2025, 1, 11, 3, 0, 0).unwrap()));
+
+        // Six fields include seconds
+        let seconds = JobSchedule::parse("*/15 * * * * *").unwrap();
+        assert_eq!(seconds.next_after(at), Some(Utc.with_ymd_and_hms(2025, 1, 10, 12, 7, 45).unwrap()));
+
+        let shorthand = JobSchedule::parse("@hourly").unwrap();
+        assert_eq!(shorthand.next_after(at), Some(Utc.with_ymd_and_hms(2025, 1, 10, 13, 0, 0).unwrap()));
+        assert_eq!(shorthand.as_str(), "@hourly");
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid() {
+        assert!(JobSchedule::parse("").is_err());
+        assert!(JobSchedule::parse("* * *").is_err());
+        assert!(JobSchedule::parse("61 * * * *").is_err());
+        assert!(JobSchedule::parse("@sometimes").is_err());
+    }
+}
@@ -10,9 +10,20 @@ pub async fn cleanup_expired_sessions(ctx: &AppContext) -> PdsResult<u64> {
     Ok(sessions_deleted + refresh_tokens_deleted)
 }
 
-/// Cleanup expired suspensions
+/// Cleanup expired suspensions, announcing each account's new status
 pub async fn cleanup_expired_suspensions(ctx: &AppContext) -> PdsResult<u64> {
-    ctx.moderation_manager.cleanup_expired().await
+    let mut dids = ctx.moderation_manager.cleanup_expired().await?;
+    let count = dids.len() as u64;
+
+    dids.sort();
+    dids.dedup();
+    for did in dids {
+        if let Err(e) = ctx.account_events.status_changed(&did).await {
+            tracing::warn!(did = %did, error = %e, "suspension_expiry_sequence_failed");
+        }
+    }
+
+    Ok(count)
 }
 
 /// Cleanup expired identity cache entries
@@ -31,9 +42,16 @@ pub async fn health_check(ctx: &AppContext) -> PdsResult<()> {
     Ok(())
 }
 
+/// Blobs deleted per page while purging an account
+const PURGE_BLOB_PAGE: i64 = 1000;
+
 /// Purge accounts marked for deletion after grace period
 ///
-/// GDPR-compliant permanent deletion of account data after 30-day grace period
+/// GDPR-compliant permanent deletion of account data after 30-day grace period.
+/// Relays and AppViews learn about it from an `#account` event with status
+/// `deleted` followed by an `#identity` event; did:plc identities whose
+/// rotation key we hold are tombstoned in the PLC directory. Each purge is
+/// recorded in `account_deletion`.
 pub async fn purge_deleted_accounts(ctx: &AppContext) -> PdsResult<u64> {
     use chrono::Utc;
     use sqlx::Row;
@@ -60,50 +78,68 @@ pub async fn purge_deleted_accounts(ctx: &AppContext) -> PdsResult<u64> {
 
         tracing::info!("Purging account: {} ({})", handle, did);
 
+        // Revoke the DID while we still have the rotation key
+        let plc_tombstoned = match ctx.account_manager.tombstone_plc(&did).await {
+            Ok(tombstoned) => tombstoned,
+            Err(e) => {
+                tracing::warn!("Failed to tombstone {} in the PLC directory: {}", did, e);
+                false
+            }
+        };
+
         // Delete all blobs for this user
-        match ctx.blob_store.list_for_user(&did, 1000).await {
-            Ok(blobs) => {
-                let blob_count = blobs.len();
-                for blob in blobs {
-                    if let Err(e) = ctx.blob_store.delete(&blob.cid).await {
-                        tracing::warn!("Failed to delete blob {}: {}", blob.cid, e);
-                    }
+        let mut blobs_deleted = 0i64;
+        loop {
+            let blobs = match ctx.blob_store.list_for_user(&did, PURGE_BLOB_PAGE).await {
+                Ok(blobs) => blobs,
+                Err(e) => {
+                    tracing::warn!("Failed to list blobs for {}: {}", did, e);
+                    break;
                 }
-                tracing::info!("Deleted {} blobs for {}", blob_count, did);
+            };
+            if blobs.is_empty() {
+                break;
             }
-            Err(e) => {
-                tracing::warn!("Failed to list blobs for {}: {}", did, e);
+
+            let mut page_deleted = 0;
+            for blob in blobs {
+                match ctx.blob_store.delete(&blob.cid).await {
+                    Ok(()) => page_deleted += 1,
+                    Err(e) => tracing::warn!("Failed to delete blob {}: {}", blob.cid, e),
+                }
+            }
+            blobs_deleted += page_deleted;
+
+            // Stop rather than spin on blobs that can't be deleted
+            if page_deleted == 0 {
+                break;
             }
         }
+        tracing::info!("Deleted {} blobs for {}", blobs_deleted, did);
 
         // Delete actor repository data
-        // Note: ActorStore.destroy() would be used here when implemented
-        // For now, we'll log and continue
-        tracing::info!("Actor store cleanup for {} (not yet implemented)", did);
-
-        // Delete all sessions and refresh tokens
-        sqlx::query("DELETE FROM session WHERE did = ?1")
-            .bind(&did)
-            .execute(&ctx.account_db)
-            .await?;
+        if let Err(e) = ctx.actor_store.destroy(&did).await {
+            tracing::warn!("Failed to destroy actor store for {}: {}", did, e);
+        }
 
-        sqlx::query("DELETE FROM refresh_token WHERE did = ?1")
-            .bind(&did)
-            .execute(&ctx.account_db)
-            .await?;
+        // Tell relays and AppViews the account is gone
+        let (account_seq, identity_seq) = ctx.account_events.deleted(&did).await;
 
-        // Delete all email tokens
-        sqlx::query("DELETE FROM email_token WHERE did = ?1")
-            .bind(&did)
-            .execute(&ctx.account_db)
-            .await?;
+        if let Err(e) = ctx.repo_webhooks.remove_all(&did).await {
+            tracing::warn!("Failed to remove repo webhooks of {}: {}", did, e);
+        }
+        if let Err(e) = ctx.activitypub.disable(&did).await {
+            tracing::warn!("Failed to remove ActivityPub actor of {}: {}", did, e);
+        }
 
-        // Delete account record (permanent)
-        sqlx::query("DELETE FROM account WHERE did = ?1")
-            .bind(&did)
-            .execute(&ctx.account_db)
+        // Delete account record, sessions and tokens (permanent)
+        ctx.account_manager
+            .purge_account(&did, blobs_deleted, plc_tombstoned, account_seq, identity_seq)
             .await?;
 
+        let _ = ctx.identity_resolver.invalidate_did(&did).await;
+        let _ = ctx.identity_resolver.invalidate_handle(&handle).await;
+
         deleted_count += 1;
 
         tracing::info!(
@@ -122,12 +158,15 @@ pub async fn purge_deleted_accounts(ctx: &AppContext) -> PdsResult<u64> {
 
 /// Cleanup orphaned temp blobs
 ///
-/// Deletes temporary blobs that have been staged but not committed within TTL (24 hours)
+/// Deletes temporary blobs that have been staged but not committed within the
+/// temp TTL (`PDS_BLOB_TEMP_TTL_HOURS`, default 24 hours), and committed blobs
+/// no record has referenced for as long. What is still staged afterwards is
+/// reported in metrics.
 pub async fn cleanup_orphaned_temp_blobs(ctx: &AppContext) -> PdsResult<u64> {
-    const TTL_HOURS: i64 = 24;
+    let ttl_hours = ctx.blob_store.temp_ttl_hours();
 
-    // Get list of orphaned blobs (older than 24 hours)
-    let orphaned_cids = ctx.blob_store.list_orphaned_temp_blobs(TTL_HOURS).await?;
+    // Get list of orphaned blobs (older than the TTL)
+    let orphaned_cids = ctx.blob_store.list_orphaned_temp_blobs(ttl_hours).await?;
 
     let mut deleted_count = 0;
 
@@ -146,6 +185,212 @@ pub async fn cleanup_orphaned_temp_blobs(ctx: &AppContext) -> PdsResult<u64> {
     if deleted_count > 0 {
         tracing::info!("Cleaned up {} orphaned temp blobs", deleted_count);
     }
+    match ctx.blob_store.staged_stats().await {
+        Ok((count, bytes)) => crate::metrics::record_staged_blob_cleanup(deleted_count, count, bytes),
+        Err(e) => tracing::warn!("Failed to read staged blob stats: {}", e),
+    }
+
+    // Committed blobs whose last referencing record was deleted
+    match ctx.blob_store.delete_unreferenced(ttl_hours).await {
+        Ok(0) => {}
+        Ok(deleted) => {
+            tracing::info!("Deleted {} unreferenced blobs", deleted);
+            deleted_count += deleted;
+        }
+        Err(e) => tracing::warn!("Failed to delete unreferenced blobs: {}", e),
+    }
+
+    // Resumable uploads that stopped receiving chunks
+    match ctx.upload_manager.expire().await {
+        Ok(0) => {}
+        Ok(expired) => {
+            tracing::info!("Expired {} incomplete upload sessions", expired);
+            deleted_count += expired;
+        }
+        Err(e) => tracing::warn!("Failed to expire upload sessions: {}", e),
+    }
 
     Ok(deleted_count)
 }
+
+/// Delete expired account export archives and fail interrupted exports
+pub async fn cleanup_account_exports(ctx: &AppContext) -> PdsResult<u64> {
+    ctx.takeout_manager.cleanup().await
+}
+
+/// Delete admin audit log entries older than the configured retention
+///
+/// Does nothing when no retention is configured.
+pub async fn prune_audit_log(ctx: &AppContext) -> PdsResult<u64> {
+    let Some(days) = ctx.config.logging.audit_log_retention_days else {
+        return Ok(0);
+    };
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
+    ctx.admin_role_manager.prune_audit_log(cutoff).await
+}
+
+/// Delete record tombstones older than the configured retention
+///
+/// Does nothing when no retention is configured.
+pub async fn prune_tombstones(ctx: &AppContext) -> PdsResult<u64> {
+    let (Some(days), Some(tombstones)) = (
+        ctx.config.logging.tombstone_retention_days,
+        ctx.actor_store.tombstone_log(),
+    ) else {
+        return Ok(0);
+    };
+
+    let cutoff = chrono::Utc::now() - chrono::Duration::days(days as i64);
+    tombstones.prune(cutoff).await
+}
+
+/// Send moderation webhook deliveries that are due
+pub async fn deliver_webhooks(ctx: &AppContext) -> PdsResult<(u64, u64)> {
+    ctx.webhook_manager.deliver_due(100).await
+}
+
+/// Send repo event webhook deliveries that are due
+pub async fn deliver_repo_webhooks(ctx: &AppContext) -> PdsResult<(u64, u64)> {
+    ctx.repo_webhooks.deliver_due(100).await
+}
+
+/// Send queued ActivityPub activities that are due
+pub async fn deliver_activitypub(ctx: &AppContext) -> PdsResult<(u64, u64)> {
+    ctx.activitypub.deliver_due(100).await
+}
+
+/// Send queued email that is due
+pub async fn deliver_email(ctx: &AppContext) -> PdsResult<(u64, u64)> {
+    ctx.mailer.deliver_due(100).await
+}
+
+/// Re-encode records that older versions stored as JSON into DAG-CBOR
+///
+/// Each repository is migrated once and then marked, so later runs only read
+/// a pragma per repository. Returns the repositories rewritten and records
+/// re-encoded; a repository that fails is retried on the next run.
+pub async fn migrate_record_encoding(ctx: &AppContext) -> PdsResult<(u64, u64)> {
+    use crate::actor_store::RepositoryManager;
+
+    let dids: Vec<String> = sqlx::query_scalar("SELECT did FROM account ORDER BY did")
+        .fetch_all(&ctx.account_db)
+        .await?;
+
+    let (mut repos, mut records) = (0u64, 0u64);
+    for did in dids {
+        if !ctx.actor_store.exists(&did).await || !ctx.actor_store.has_legacy_records(&did).await? {
+            continue;
+        }
+
+        let repo_mgr = RepositoryManager::new(did.clone(), (*ctx.actor_store).clone());
+        let result = match ctx.signing_keys.commit_signer(&did).await {
+            Ok(signer) => repo_mgr.migrate_record_encoding(signer).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(migrated) => {
+                if let Some((commit, _, count)) = migrated {
+                    tracing::info!(did = %did, commit = %commit, "Re-encoded {} record(s) as DAG-CBOR", count);
+                    repos += 1;
+                    records += count as u64;
+                }
+                ctx.actor_store.mark_records_migrated(&did).await?;
+            }
+            Err(e) => tracing::warn!(did = %did, error = %e, "record_encoding_migration_failed"),
+        }
+    }
+
+    Ok((repos, records))
+}
+
+/// Upgrade the schema of every actor store ahead of first use
+pub async fn migrate_actor_stores(
+    ctx: &AppContext,
+) -> PdsResult<crate::actor_store::migrations::ActorMigrationReport> {
+    use crate::actor_store::migrations::{migrate_all, DEFAULT_CONCURRENCY};
+
+    migrate_all(&ctx.actor_store, &ctx.account_db, DEFAULT_CONCURRENCY).await
+}
+
+/// Compact every local actor store, one at a time
+///
+/// Returns (repos compacted, blocks pruned, bytes reclaimed). A store that
+/// fails is logged and skipped.
+pub async fn compact_actor_stores(ctx: &AppContext) -> PdsResult<(u64, u64, u64)> {
+    use crate::actor_store::compaction::{compact, CompactionOptions};
+
+    let opts = CompactionOptions::from(&ctx.config.compaction);
+    let dids: Vec<String> = sqlx::query_scalar("SELECT did FROM account ORDER BY did")
+        .fetch_all(&ctx.account_db)
+        .await?;
+
+    let (mut repos, mut blocks, mut bytes) = (0u64, 0u64, 0u64);
+    for did in dids {
+        if !ctx.actor_store.exists(&did).await {
+            continue;
+        }
+
+        match compact(&ctx.actor_store, &did, &opts).await {
+            Ok(outcome) => {
+                crate::metrics::record_repo_compaction(outcome.pruned_blocks, outcome.reclaimed_bytes);
+                if outcome.pruned_blocks > 0 || outcome.reclaimed_bytes > 0 {
+                    repos += 1;
+                    blocks += outcome.pruned_blocks;
+                    bytes += outcome.reclaimed_bytes;
+                }
+            }
+            Err(e) => tracing::warn!(did = %did, error = %e, "repo_compaction_failed"),
+        }
+        tokio::time::sleep(opts.batch_delay).await;
+    }
+
+    Ok((repos, blocks, bytes))
+}
+
+/// Check the sequencer log for gaps and invalidate duplicate commits
+pub async fn check_sequencer_integrity(ctx: &AppContext) -> PdsResult<crate::sequencer::SequencerHealth> {
+    ctx.sequencer.check_integrity(true).await
+}
+
+/// Re-verify custom domain handles that haven't been checked in the last day
+///
+/// Handles that fail `MAX_VERIFICATION_FAILURES` checks in a row are marked
+/// invalid: the cached resolution is dropped and an identity event is emitted
+/// so downstream services re-resolve the account.
+pub async fn reverify_handles(ctx: &AppContext) -> PdsResult<(u64, u64)> {
+    use crate::identity::verification::MAX_VERIFICATION_FAILURES;
+
+    let due = ctx
+        .handle_verification_manager
+        .due_for_recheck(chrono::Duration::hours(24), 100)
+        .await?;
+
+    let (mut checked, mut invalidated) = (0u64, 0u64);
+    for entry in due {
+        checked += 1;
+
+        match ctx.identity_resolver.verify_handle(&entry.handle, &entry.did).await {
+            Ok(method) => {
+                ctx.handle_verification_manager
+                    .record_verified(&entry.did, &entry.handle, method)
+                    .await?;
+            }
+            Err(e) => {
+                let failures = ctx
+                    .handle_verification_manager
+                    .record_failure(&entry.did, &e.to_string())
+                    .await?;
+                tracing::warn!(did = %entry.did, handle = %entry.handle, failures, error = %e, "handle_reverification_failed");
+
+                if failures >= MAX_VERIFICATION_FAILURES {
+                    invalidated += 1;
+                    ctx.identity_resolver.invalidate_handle(&entry.handle).await?;
+                    ctx.account_events.identity_changed(&entry.did, None).await?;
+                }
+            }
+        }
+    }
+
+    Ok((checked, invalidated))
+}
@@ -0,0 +1,500 @@
+/// Sequencer / firehose load test harness
+///
+/// `aurora-locus loadtest` writes synthetic commit events into the sequencer
+/// at a fixed rate while N simulated subscribers follow
+/// com.atproto.sync.subscribeRepos, then reports throughput, end-to-end
+/// latency (sequenced -> received), drops, and disconnects.
+///
+/// By default the firehose is served in-process on an ephemeral port; pass
+/// `--url` to target a running server sharing the same database instead.
+/// Only available with `PDS_DEV_MODE=true`. Synthetic events use
+/// `did:web:loadtest-*` repos and are invalidated afterwards unless
+/// `--keep-events` is given; they are never published to relays.
+
+use crate::{
+    context::AppContext,
+    error::{PdsError, PdsResult},
+    sequencer::{
+        events::{CommitEvent, CommitOp, OpAction},
+        Sequencer, SequencerConfig,
+    },
+};
+use futures_util::{SinkExt, StreamExt};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::time::{interval, sleep, timeout, Duration, Instant, MissedTickBehavior};
+use tokio_tungstenite::{connect_async, tungstenite::Message};
+use tracing::{info, warn};
+
+/// DID prefix for synthetic repos
+const LOADTEST_DID_PREFIX: &str = "did:web:loadtest-";
+
+/// How long subscribers keep reading after the generator stops
+const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Load test options (`aurora-locus loadtest [--rate N] [--duration S] ...`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadTestOptions {
+    /// Commits per second
+    pub rate: u32,
+    /// Generation time in seconds
+    pub duration_secs: u64,
+    /// Number of simulated subscribers
+    pub subscribers: usize,
+    /// How many of the subscribers read slowly
+    pub slow_subscribers: usize,
+    /// Per-frame delay for slow subscribers
+    pub slow_delay_ms: u64,
+    /// Operations per synthetic commit
+    pub ops_per_commit: usize,
+    /// Size of the synthetic CAR payload per commit
+    pub block_bytes: usize,
+    /// Number of distinct synthetic repos
+    pub repos: usize,
+    /// External server base URL (ws:// or http://); in-process when unset
+    pub url: Option<String>,
+    /// Keep synthetic events in the sequencer after the run
+    pub keep_events: bool,
+}
+
+impl Default for LoadTestOptions {
+    fn default() -> Self {
+        Self {
+            rate: 100,
+            duration_secs: 30,
+            subscribers: 10,
+            slow_subscribers: 0,
+            slow_delay_ms: 50,
+            ops_per_commit: 3,
+            block_bytes: 1024,
+            repos: 50,
+            url: None,
+            keep_events: false,
+        }
+    }
+}
+
+impl LoadTestOptions {
+    /// Parse options from the arguments following `loadtest`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let mut opts = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            if flag == "--keep-events" {
+                opts.keep_events = true;
+                continue;
+            }
+
+            let value = iter
+                .next()
+                .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+            let invalid = || PdsError::Validation(format!("Invalid value for {}: {}", flag, value));
+
+            match flag.as_str() {
+                "--rate" => opts.rate = value.parse().map_err(|_| invalid())?,
+                "--duration" => opts.duration_secs = value.parse().map_err(|_| invalid())?,
+                "--subscribers" => opts.subscribers = value.parse().map_err(|_| invalid())?,
+                "--slow-subscribers" => {
+                    opts.slow_subscribers = value.parse().map_err(|_| invalid())?
+                }
+                "--slow-delay-ms" => opts.slow_delay_ms = value.parse().map_err(|_| invalid())?,
+                "--ops" => opts.ops_per_commit = value.parse().map_err(|_| invalid())?,
+                "--block-bytes" => opts.block_bytes = value.parse().map_err(|_| invalid())?,
+                "--repos" => opts.repos = value.parse().map_err(|_| invalid())?,
+                "--url" => opts.url = Some(value.clone()),
+                other => {
+                    return Err(PdsError::Validation(format!(
+                        "Unknown loadtest option: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        if opts.rate == 0 || opts.repos == 0 {
+            return Err(PdsError::Validation(
+                "--rate and --repos must be greater than zero".to_string(),
+            ));
+        }
+        if opts.slow_subscribers > opts.subscribers {
+            return Err(PdsError::Validation(
+                "--slow-subscribers cannot exceed --subscribers".to_string(),
+            ));
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Result for a single simulated subscriber
+#[derive(Debug, Default)]
+pub struct SubscriberReport {
+    pub slow: bool,
+    pub received: usize,
+    /// Generated events never received
+    pub dropped: usize,
+    /// Connection closed before all events arrived
+    pub disconnected: bool,
+    /// Latencies in microseconds
+    pub latencies_us: Vec<u64>,
+}
+
+/// Summary of a load test run
+#[derive(Debug, Default)]
+pub struct LoadTestReport {
+    pub generated: usize,
+    pub generate_errors: usize,
+    pub elapsed: Duration,
+    pub subscribers: Vec<SubscriberReport>,
+}
+
+impl LoadTestReport {
+    /// Human-readable summary
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        let secs = self.elapsed.as_secs_f64().max(f64::EPSILON);
+
+        out.push_str(&format!(
+            "generated {} commits in {:.1}s ({:.1}/s, {} errors)\n",
+            self.generated,
+            secs,
+            self.generated as f64 / secs,
+            self.generate_errors
+        ));
+
+        let mut all: Vec<u64> = self
+            .subscribers
+            .iter()
+            .flat_map(|s| s.latencies_us.iter().copied())
+            .collect();
+        all.sort_unstable();
+
+        out.push_str(&format!(
+            "latency (all subscribers): p50 {} p95 {} p99 {} max {}\n",
+            format_us(percentile(&all, 50.0)),
+            format_us(percentile(&all, 95.0)),
+            format_us(percentile(&all, 99.0)),
+            format_us(all.last().copied()),
+        ));
+
+        for (i, sub) in self.subscribers.iter().enumerate() {
+            let mut lat = sub.latencies_us.clone();
+            lat.sort_unstable();
+            out.push_str(&format!(
+                "subscriber {:>3}{}: received {} dropped {}{} p50 {} p99 {}\n",
+                i,
+                if sub.slow { " (slow)" } else { "" },
+                sub.received,
+                sub.dropped,
+                if sub.disconnected { " DISCONNECTED" } else { "" },
+                format_us(percentile(&lat, 50.0)),
+                format_us(percentile(&lat, 99.0)),
+            ));
+        }
+
+        out
+    }
+}
+
+/// Nearest-rank percentile of sorted values
+fn percentile(sorted: &[u64], pct: f64) -> Option<u64> {
+    if sorted.is_empty() {
+        return None;
+    }
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
+
+fn format_us(value: Option<u64>) -> String {
+    match value {
+        Some(us) if us >= 1000 => format!("{:.1}ms", us as f64 / 1000.0),
+        Some(us) => format!("{}us", us),
+        None => "-".to_string(),
+    }
+}
+
+/// Build a synthetic commit event
+fn synthetic_commit(n: usize, opts: &LoadTestOptions) -> CommitEvent {
+    let repo = format!("{}{}", LOADTEST_DID_PREFIX, n % opts.repos);
+    let ops = (0..opts.ops_per_commit)
+        .map(|i| CommitOp {
+            action: OpAction::Create,
+            path: format!("app.bsky.feed.post/loadtest{}x{}", n, i),
+            cid: Some(format!("bafyreiloadtest{}x{}", n, i)),
+        })
+        .collect();
+
+    CommitEvent::new(
+        repo,
+        format!("bafyreiloadtestcommit{}", n),
+        format!("loadtest{:012}", n),
+        None,
+        vec![0u8; opts.block_bytes],
+        ops,
+    )
+}
+
+/// Sequence timestamps shared between the generator and subscribers
+type SentTimes = Arc<Mutex<HashMap<i64, Instant>>>;
+
+/// Run the load test
+pub async fn run(ctx: &AppContext, opts: &LoadTestOptions) -> PdsResult<LoadTestReport> {
+    if !ctx.config.service.dev_mode {
+        return Err(PdsError::Validation(
+            "loadtest is only available with PDS_DEV_MODE=true".to_string(),
+        ));
+    }
+
+    // Firehose endpoint: external server, or serve in-process
+    let (base_url, server) = match &opts.url {
+        Some(url) => (url.clone(), None),
+        None => {
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+            let addr = listener.local_addr()?;
+            let app = crate::server::build_router(ctx.clone())
+                .into_make_service_with_connect_info::<std::net::SocketAddr>();
+            let handle = tokio::spawn(async move {
+                let _ = axum::serve(listener, app).await;
+            });
+            (format!("ws://{}", addr), Some(handle))
+        }
+    };
+    let ws_url = format!(
+        "{}/xrpc/com.atproto.sync.subscribeRepos",
+        base_url
+            .trim_end_matches('/')
+            .replace("https://", "wss://")
+            .replace("http://", "ws://")
+    );
+
+    // Same database as the firehose, but never published to relays
+    let sequencer = Sequencer::new(ctx.account_db.clone(), SequencerConfig::default());
+
+    let start_seq = sequencer.current_seq().await?.unwrap_or(0);
+    let sent: SentTimes = Arc::new(Mutex::new(HashMap::new()));
+    let (done_tx, done_rx) = tokio::sync::watch::channel(false);
+
+    // Subscribers
+    let mut subscriber_handles = Vec::with_capacity(opts.subscribers);
+    for i in 0..opts.subscribers {
+        let slow = i < opts.slow_subscribers;
+        let delay = Duration::from_millis(if slow { opts.slow_delay_ms } else { 0 });
+        subscriber_handles.push(tokio::spawn(subscribe(
+            format!("{}?cursor={}", ws_url, start_seq),
+            sent.clone(),
+            done_rx.clone(),
+            slow,
+            delay,
+        )));
+    }
+
+    // Give subscribers a moment to connect before generating
+    sleep(Duration::from_millis(500)).await;
+    info!(
+        "loadtest: generating {} commits/s for {}s with {} subscriber(s) on {}",
+        opts.rate, opts.duration_secs, opts.subscribers, ws_url
+    );
+
+    // Generator
+    let mut report = LoadTestReport::default();
+    let started = Instant::now();
+    let deadline = started + Duration::from_secs(opts.duration_secs);
+    let mut tick = interval(Duration::from_secs_f64(1.0 / opts.rate as f64));
+    tick.set_missed_tick_behavior(MissedTickBehavior::Burst);
+
+    let mut n = 0usize;
+    while Instant::now() < deadline {
+        tick.tick().await;
+        let generated_at = Instant::now();
+
+        match sequencer.sequence_commit(synthetic_commit(n, opts)).await {
+            Ok(seq) => {
+                sent.lock().unwrap().insert(seq, generated_at);
+                report.generated += 1;
+            }
+            Err(e) => {
+                warn!("loadtest: failed to sequence commit: {}", e);
+                report.generate_errors += 1;
+            }
+        }
+        n += 1;
+    }
+    report.elapsed = started.elapsed();
+    let _ = done_tx.send(true);
+
+    let expected = report.generated;
+    for handle in subscriber_handles {
+        match handle.await {
+            Ok(mut sub) => {
+                sub.dropped = expected.saturating_sub(sub.received);
+                report.subscribers.push(sub);
+            }
+            Err(e) => warn!("loadtest: subscriber task failed: {}", e),
+        }
+    }
+
+    if let Some(handle) = server {
+        handle.abort();
+    }
+
+    if !opts.keep_events {
+        sqlx::query("UPDATE repo_seq SET invalidated = 1 WHERE did LIKE ? AND seq > ?")
+            .bind(format!("{}%", LOADTEST_DID_PREFIX))
+            .bind(start_seq)
+            .execute(&ctx.account_db)
+            .await?;
+    }
+
+    Ok(report)
+}
+
+/// A single simulated subscriber
+async fn subscribe(
+    url: String,
+    sent: SentTimes,
+    mut done: tokio::sync::watch::Receiver<bool>,
+    slow: bool,
+    delay: Duration,
+) -> SubscriberReport {
+    let mut report = SubscriberReport {
+        slow,
+        ..Default::default()
+    };
+
+    let mut stream = match connect_async(&url).await {
+        Ok((stream, _)) => stream,
+        Err(e) => {
+            warn!("loadtest: subscriber failed to connect to {}: {}", url, e);
+            report.disconnected = true;
+            return report;
+        }
+    };
+
+    let mut drain_deadline: Option<Instant> = None;
+
+    loop {
+        // After the generator stops, read until caught up or the drain timeout
+        if *done.borrow() && drain_deadline.is_none() {
+            drain_deadline = Some(Instant::now() + DRAIN_TIMEOUT);
+        }
+        if let Some(deadline) = drain_deadline {
+            if report.received >= sent.lock().unwrap().len() || Instant::now() >= deadline {
+                break;
+            }
+        }
+
+        let next = tokio::select! {
+            msg = timeout(Duration::from_millis(250), stream.next()) => msg,
+            _ = done.changed(), if drain_deadline.is_none() => continue,
+        };
+
+        let msg = match next {
+            Err(_) => continue, // poll timeout; re-check drain state
+            Ok(None) => {
+                report.disconnected = true;
+                break;
+            }
+            Ok(Some(Err(_))) => {
+                report.disconnected = true;
+                break;
+            }
+            Ok(Some(Ok(msg))) => msg,
+        };
+
+        let received_at = Instant::now();
+        match msg {
+            Message::Text(text) => {
+                let frame: serde_json::Value = match serde_json::from_str(&text) {
+                    Ok(frame) => frame,
+                    Err(_) => continue,
+                };
+                if frame.get("$type").and_then(|t| t.as_str()) != Some("#commit") {
+                    continue;
+                }
+                let seq = match frame.get("seq").and_then(|s| s.as_i64()) {
+                    Some(seq) => seq,
+                    None => continue,
+                };
+
+                // Only count events this run generated
+                let generated_at = sent.lock().unwrap().get(&seq).copied();
+                if let Some(generated_at) = generated_at {
+                    report.received += 1;
+                    report
+                        .latencies_us
+                        .push(received_at.duration_since(generated_at).as_micros() as u64);
+                }
+
+                if !delay.is_zero() {
+                    sleep(delay).await;
+                }
+            }
+            Message::Ping(data) => {
+                let _ = stream.send(Message::Pong(data)).await;
+            }
+            Message::Close(_) => {
+                report.disconnected = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let _ = stream.close(None).await;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_loadtest_options() {
+        let opts = LoadTestOptions::from_args(&args(
+            "--rate 500 --duration 5 --subscribers 4 --slow-subscribers 1 --keep-events",
+        ))
+        .unwrap();
+        assert_eq!(opts.rate, 500);
+        assert_eq!(opts.duration_secs, 5);
+        assert_eq!(opts.subscribers, 4);
+        assert_eq!(opts.slow_subscribers, 1);
+        assert!(opts.keep_events);
+        assert!(opts.url.is_none());
+
+        assert!(LoadTestOptions::from_args(&args("--rate 0")).is_err());
+        assert!(LoadTestOptions::from_args(&args("--subscribers 1 --slow-subscribers 2")).is_err());
+        assert!(LoadTestOptions::from_args(&args("--unknown 1")).is_err());
+    }
+
+    #[test]
+    fn test_percentile() {
+        let values: Vec<u64> = (1..=100).collect();
+        assert_eq!(percentile(&values, 50.0), Some(50));
+        assert_eq!(percentile(&values, 99.0), Some(99));
+        assert_eq!(percentile(&values, 100.0), Some(100));
+        assert_eq!(percentile(&[7], 50.0), Some(7));
+        assert_eq!(percentile(&[], 50.0), None);
+    }
+
+    #[test]
+    fn test_synthetic_commit() {
+        let opts = LoadTestOptions {
+            repos: 3,
+            ops_per_commit: 2,
+            block_bytes: 16,
+            ..Default::default()
+        };
+
+        let evt = synthetic_commit(4, &opts);
+        assert_eq!(evt.repo, "did:web:loadtest-1");
+        assert_eq!(evt.ops.len(), 2);
+        assert_eq!(evt.blocks.len(), 16);
+    }
+}
@@ -0,0 +1,476 @@
+/// Outbound email queue
+///
+/// Emails are written to `email_delivery` and sent by the `email_delivery`
+/// background job, so a slow or unavailable provider never holds up the
+/// request that triggered the email. Transient failures are retried with
+/// exponential backoff; permanent ones (and emails out of attempts) are
+/// marked failed and can be re-queued by an admin.
+///
+/// Bodies contain single-use links, so they are cleared once an email is
+/// sent and only kept for emails that may still be (re)sent.
+use crate::{
+    error::{PdsError, PdsResult},
+    mailer::provider::{EmailProvider, OutgoingEmail},
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Row, SqlitePool};
+use std::sync::Arc;
+use tokio::sync::Notify;
+
+/// Delay before the first retry; doubles with each further attempt
+const RETRY_BASE_SECS: i64 = 60;
+
+/// Upper bound on the retry delay
+const RETRY_MAX_SECS: i64 = 3600;
+
+/// Sent and failed entries are kept this long for inspection
+const DELIVERY_RETENTION_DAYS: i64 = 14;
+
+/// Email kinds
+pub mod kinds {
+    pub const VERIFICATION: &str = "verification";
+    pub const PASSWORD_RESET: &str = "password_reset";
+    pub const HANDLE_CHANGE: &str = "handle_change";
+}
+
+/// Delivery status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailStatus {
+    Pending,
+    Sent,
+    Failed,
+}
+
+impl EmailStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailStatus::Pending => "pending",
+            EmailStatus::Sent => "sent",
+            EmailStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> PdsResult<Self> {
+        match s.to_lowercase().as_str() {
+            "pending" => Ok(EmailStatus::Pending),
+            "sent" => Ok(EmailStatus::Sent),
+            "failed" => Ok(EmailStatus::Failed),
+            _ => Err(PdsError::Validation(format!("Invalid email status: {}", s))),
+        }
+    }
+}
+
+/// A queued email and its delivery status (the body is never returned)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmailDelivery {
+    pub id: i64,
+    pub kind: String,
+    pub recipient: String,
+    pub subject: String,
+    pub status: EmailStatus,
+    pub attempts: i64,
+    /// Provider of the last attempt
+    pub provider: Option<String>,
+    pub provider_message_id: Option<String>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: Option<DateTime<Utc>>,
+    pub sent_at: Option<DateTime<Utc>>,
+}
+
+/// Persistent queue of outgoing email
+#[derive(Clone)]
+pub struct EmailQueue {
+    db: SqlitePool,
+    /// Wakes the delivery job when email is queued
+    notify: Arc<Notify>,
+}
+
+impl EmailQueue {
+    pub fn new(db: SqlitePool) -> Self {
+        Self {
+            db,
+            notify: Arc::new(Notify::new()),
+        }
+    }
+
+    /// Signal raised whenever email is queued
+    pub fn notifier(&self) -> Arc<Notify> {
+        self.notify.clone()
+    }
+
+    /// Queue an email for immediate delivery, returning its id
+    pub async fn enqueue(&self, kind: &str, email: &OutgoingEmail) -> PdsResult<i64> {
+        let now = Utc::now().to_rfc3339();
+        let id = sqlx::query(
+            r#"
+            INSERT INTO email_delivery
+                (kind, recipient, sender, subject, body, status, attempts, created_at, next_attempt_at)
+            VALUES (?1, ?2, ?3, ?4, ?5, 'pending', 0, ?6, ?6)
+            "#,
+        )
+        .bind(kind)
+        .bind(&email.to)
+        .bind(&email.from)
+        .bind(&email.subject)
+        .bind(&email.body)
+        .bind(&now)
+        .execute(&self.db)
+        .await?
+        .last_insert_rowid();
+
+        self.notify.notify_one();
+        Ok(id)
+    }
+
+    /// Send every pending email that is due
+    ///
+    /// Returns (sent, failed attempts).
+    pub async fn deliver_due(
+        &self,
+        provider: &dyn EmailProvider,
+        max_attempts: i64,
+        limit: i64,
+    ) -> PdsResult<(u64, u64)> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, recipient, sender, subject, body, attempts
+            FROM email_delivery
+            WHERE status = 'pending' AND next_attempt_at <= ?1
+            ORDER BY next_attempt_at
+            LIMIT ?2
+            "#,
+        )
+        .bind(Utc::now().to_rfc3339())
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        let (mut sent, mut failed) = (0, 0);
+        for row in rows {
+            let id: i64 = row.get("id");
+            let attempts: i64 = row.get::<i64, _>("attempts") + 1;
+            let email = OutgoingEmail {
+                from: row.get("sender"),
+                to: row.get("recipient"),
+                subject: row.get("subject"),
+                body: row.get("body"),
+            };
+
+            match provider.send(&email).await {
+                Ok(message_id) => {
+                    sent += 1;
+                    tracing::info!("Sent email {} to {}: {}", id, email.to, email.subject);
+                    sqlx::query(
+                        r#"
+                        UPDATE email_delivery
+                        SET status = 'sent', body = '', attempts = ?2, provider = ?3,
+                            provider_message_id = ?4, last_error = NULL, next_attempt_at = NULL,
+                            sent_at = ?5
+                        WHERE id = ?1
+                        "#,
+                    )
+                    .bind(id)
+                    .bind(attempts)
+                    .bind(provider.name())
+                    .bind(message_id)
+                    .bind(Utc::now().to_rfc3339())
+                    .execute(&self.db)
+                    .await?;
+                }
+                Err(error) => {
+                    failed += 1;
+                    let next_attempt = if error.is_permanent() {
+                        None
+                    } else {
+                        retry_delay(attempts, max_attempts).map(|delay| Utc::now() + delay)
+                    };
+                    tracing::warn!(
+                        "Email {} to {} failed (attempt {}{}): {}",
+                        id,
+                        email.to,
+                        attempts,
+                        if next_attempt.is_none() { ", giving up" } else { "" },
+                        error
+                    );
+                    sqlx::query(
+                        r#"
+                        UPDATE email_delivery
+                        SET status = ?2, attempts = ?3, provider = ?4, last_error = ?5,
+                            next_attempt_at = ?6
+                        WHERE id = ?1
+                        "#,
+                    )
+                    .bind(id)
+                    .bind(if next_attempt.is_some() { "pending" } else { "failed" })
+                    .bind(attempts)
+                    .bind(provider.name())
+                    .bind(error.to_string())
+                    .bind(next_attempt.map(|t| t.to_rfc3339()))
+                    .execute(&self.db)
+                    .await?;
+                }
+            }
+        }
+
+        Ok((sent, failed))
+    }
+
+    /// List queued emails, newest first, optionally by status or recipient
+    pub async fn list(
+        &self,
+        status: Option<EmailStatus>,
+        recipient: Option<&str>,
+        cursor: Option<i64>,
+        limit: i64,
+    ) -> PdsResult<Vec<EmailDelivery>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, kind, recipient, subject, status, attempts, provider, provider_message_id,
+                   last_error, created_at, next_attempt_at, sent_at
+            FROM email_delivery
+            WHERE (?1 IS NULL OR status = ?1)
+              AND (?2 IS NULL OR recipient = ?2 COLLATE NOCASE)
+              AND (?3 IS NULL OR id < ?3)
+            ORDER BY id DESC
+            LIMIT ?4
+            "#,
+        )
+        .bind(status.map(|s| s.as_str()))
+        .bind(recipient)
+        .bind(cursor)
+        .bind(limit)
+        .fetch_all(&self.db)
+        .await?;
+
+        rows.iter().map(row_to_delivery).collect()
+    }
+
+    /// One queued email
+    pub async fn get(&self, id: i64) -> PdsResult<EmailDelivery> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, kind, recipient, subject, status, attempts, provider, provider_message_id,
+                   last_error, created_at, next_attempt_at, sent_at
+            FROM email_delivery WHERE id = ?1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| PdsError::NotFound(format!("Email delivery not found: {}", id)))?;
+
+        row_to_delivery(&row)
+    }
+
+    /// Send a failed or pending email again now, with a fresh set of attempts
+    pub async fn retry(&self, id: i64) -> PdsResult<EmailDelivery> {
+        let delivery = self.get(id).await?;
+        if delivery.status == EmailStatus::Sent {
+            return Err(PdsError::Conflict(format!("Email {} was already sent", id)));
+        }
+
+        sqlx::query(
+            "UPDATE email_delivery SET status = 'pending', attempts = 0, next_attempt_at = ?2 WHERE id = ?1",
+        )
+        .bind(id)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        self.notify.notify_one();
+        self.get(id).await
+    }
+
+    /// Delete sent and failed entries past the retention window
+    pub async fn prune(&self) -> PdsResult<u64> {
+        let cutoff = Utc::now() - Duration::days(DELIVERY_RETENTION_DAYS);
+        let result = sqlx::query(
+            "DELETE FROM email_delivery WHERE status IN ('sent', 'failed') AND created_at < ?1",
+        )
+        .bind(cutoff.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+fn row_to_delivery(row: &sqlx::sqlite::SqliteRow) -> PdsResult<EmailDelivery> {
+    let optional_time = |column: &str| -> PdsResult<Option<DateTime<Utc>>> {
+        row.get::<Option<String>, _>(column)
+            .map(|s| parse_time(&s))
+            .transpose()
+    };
+
+    Ok(EmailDelivery {
+        id: row.get("id"),
+        kind: row.get("kind"),
+        recipient: row.get("recipient"),
+        subject: row.get("subject"),
+        status: EmailStatus::from_str(&row.get::<String, _>("status"))?,
+        attempts: row.get("attempts"),
+        provider: row.get("provider"),
+        provider_message_id: row.get("provider_message_id"),
+        last_error: row.get("last_error"),
+        created_at: parse_time(&row.get::<String, _>("created_at"))?,
+        next_attempt_at: optional_time("next_attempt_at")?,
+        sent_at: optional_time("sent_at")?,
+    })
+}
+
+/// Delay before retrying after `attempts` failures, or None to give up
+fn retry_delay(attempts: i64, max_attempts: i64) -> Option<Duration> {
+    if attempts >= max_attempts {
+        return None;
+    }
+    let secs = RETRY_BASE_SECS.saturating_mul(1 << (attempts - 1).clamp(0, 20));
+    Some(Duration::seconds(secs.min(RETRY_MAX_SECS)))
+}
+
+fn parse_time(s: &str) -> PdsResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(s)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mailer::provider::SendError;
+    use async_trait::async_trait;
+    use std::sync::Mutex;
+
+    /// Provider that fails with the queued errors, then succeeds
+    struct ScriptedProvider {
+        errors: Mutex<Vec<SendError>>,
+        sent: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(errors: Vec<SendError>) -> Self {
+            Self {
+                errors: Mutex::new(errors),
+                sent: Mutex::new(Vec::new()),
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EmailProvider for ScriptedProvider {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        async fn send(&self, email: &OutgoingEmail) -> Result<Option<String>, SendError> {
+            let mut errors = self.errors.lock().unwrap();
+            if !errors.is_empty() {
+                return Err(errors.remove(0));
+            }
+            self.sent.lock().unwrap().push(email.to.clone());
+            Ok(Some("msg-1".to_string()))
+        }
+    }
+
+    async fn create_test_queue() -> EmailQueue {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+        sqlx::query(include_str!("../../migrations/20250126000001_email_queue.sql"))
+            .execute(&db)
+            .await
+            .unwrap();
+        EmailQueue::new(db)
+    }
+
+    fn email(to: &str) -> OutgoingEmail {
+        OutgoingEmail {
+            from: "noreply@pds.example".to_string(),
+            to: to.to_string(),
+            subject: "Verify your email address".to_string(),
+            body: "https://pds.example/verify-email?token=abc".to_string(),
+        }
+    }
+
+    /// Make every pending email due now
+    async fn make_due(queue: &EmailQueue) {
+        sqlx::query("UPDATE email_delivery SET next_attempt_at = ?1 WHERE status = 'pending'")
+            .bind((Utc::now() - Duration::seconds(1)).to_rfc3339())
+            .execute(&queue.db)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1, 6), Some(Duration::seconds(60)));
+        assert_eq!(retry_delay(3, 6), Some(Duration::seconds(240)));
+        assert_eq!(retry_delay(5, 10), Some(Duration::seconds(960)));
+        assert_eq!(retry_delay(9, 10), Some(Duration::seconds(RETRY_MAX_SECS)));
+        assert_eq!(retry_delay(6, 6), None);
+    }
+
+    #[tokio::test]
+    async fn test_retry_then_send() {
+        let queue = create_test_queue().await;
+        let provider = ScriptedProvider::new(vec![SendError::Transient("connection reset".to_string())]);
+        let id = queue.enqueue(kinds::VERIFICATION, &email("alice@example.com")).await.unwrap();
+
+        assert_eq!(queue.deliver_due(&provider, 6, 10).await.unwrap(), (0, 1));
+        let delivery = queue.get(id).await.unwrap();
+        assert_eq!(delivery.status, EmailStatus::Pending);
+        assert_eq!(delivery.last_error.as_deref(), Some("connection reset"));
+        assert!(delivery.next_attempt_at.unwrap() > Utc::now());
+
+        // Not due yet
+        assert_eq!(queue.deliver_due(&provider, 6, 10).await.unwrap(), (0, 0));
+
+        make_due(&queue).await;
+        assert_eq!(queue.deliver_due(&provider, 6, 10).await.unwrap(), (1, 0));
+        let delivery = queue.get(id).await.unwrap();
+        assert_eq!(delivery.status, EmailStatus::Sent);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.provider.as_deref(), Some("scripted"));
+        assert_eq!(delivery.provider_message_id.as_deref(), Some("msg-1"));
+        assert!(delivery.sent_at.is_some());
+
+        // The single-use link is not kept after sending
+        let body: String = sqlx::query_scalar("SELECT body FROM email_delivery WHERE id = ?1")
+            .bind(id)
+            .fetch_one(&queue.db)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+        assert!(matches!(queue.retry(id).await, Err(PdsError::Conflict(_))));
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failure_and_admin_retry() {
+        let queue = create_test_queue().await;
+        let provider = ScriptedProvider::new(vec![
+            SendError::Permanent("550 mailbox unavailable".to_string()),
+            SendError::Transient("timeout".to_string()),
+        ]);
+        let id = queue.enqueue(kinds::PASSWORD_RESET, &email("bob@example.com")).await.unwrap();
+
+        // Permanent failures are not retried
+        assert_eq!(queue.deliver_due(&provider, 6, 10).await.unwrap(), (0, 1));
+        let delivery = queue.get(id).await.unwrap();
+        assert_eq!(delivery.status, EmailStatus::Failed);
+        assert!(delivery.next_attempt_at.is_none());
+
+        // Out of attempts after a transient failure
+        let delivery = queue.retry(id).await.unwrap();
+        assert_eq!(delivery.status, EmailStatus::Pending);
+        assert_eq!(delivery.attempts, 0);
+        assert_eq!(queue.deliver_due(&provider, 1, 10).await.unwrap(), (0, 1));
+        assert_eq!(queue.get(id).await.unwrap().status, EmailStatus::Failed);
+
+        let failed = queue.list(Some(EmailStatus::Failed), Some("BOB@example.com"), None, 10).await.unwrap();
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].kind, kinds::PASSWORD_RESET);
+        assert!(queue.list(Some(EmailStatus::Sent), None, None, 10).await.unwrap().is_empty());
+        assert!(matches!(queue.get(999).await, Err(PdsError::NotFound(_))));
+    }
+}
@@ -4,6 +4,7 @@
 /// and federation capabilities for the AT Protocol network.
 
 mod account;
+mod activitypub;
 mod actor_store;
 mod admin;
 mod api;
@@ -12,67 +13,189 @@ mod backup;
 mod blob_store;
 mod cache;
 mod car;
+mod clock;
 mod config;
+mod config_file;
 mod context;
 mod crypto;
 mod db;
+mod delivery;
+mod encryption;
 mod error;
 mod federation;
 mod identity;
 mod jobs;
+mod loadtest;
 mod mailer;
 mod metrics;
+mod mirror;
+mod net;
 mod rate_limit;
+mod rate_limit_new;
+mod recompress;
+mod reload;
+mod repair;
+mod replay;
+mod seed;
 mod sequencer;
 mod server;
+mod takeout;
+mod telemetry;
+#[cfg(test)]
+mod testing;
 mod validation;
 
 use config::ServerConfig;
 use context::AppContext;
 use error::PdsResult;
 use std::sync::Arc;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> PdsResult<()> {
-    // Initialize logging with JSON support
-    let log_format = std::env::var("LOG_FORMAT").unwrap_or_else(|_| "text".to_string());
-
-    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| "aurora_locus=info,tower_http=info".into());
-
-    if log_format == "json" {
-        // JSON logging for production
-        tracing_subscriber::registry()
-            .with(env_filter)
-            .with(tracing_subscriber::fmt::layer().json())
-            .init();
-    } else {
-        // Pretty text logging for development
-        tracing_subscriber::registry()
-            .with(env_filter)
-            .with(tracing_subscriber::fmt::layer().pretty())
-            .init();
+    // `--config <file>` layers a TOML/YAML file under the environment; it is
+    // applied first so it also covers logging settings
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let config_path = config_file::take_config_arg(&mut args)?;
+    config_file::apply(config_path.as_deref())?;
+
+    // `config check` prints the effective configuration and exits
+    if args.first().map(String::as_str) == Some("config") {
+        return config_file::run(&args[1..]);
+    }
+
+    // Initialize logging (JSON or pretty) and optional OTLP span export
+    let telemetry_config = telemetry::TelemetryConfig::from_env();
+    if telemetry::init(&telemetry_config)? {
+        tracing::info!(
+            "OpenTelemetry export enabled ({})",
+            telemetry_config.otlp_endpoint.as_deref().unwrap_or_default()
+        );
     }
 
     // Print banner
     print_banner();
 
+    // Subcommands: `seed [options]` populates a dev server with demo data,
+    // `loadtest [options]` benchmarks the sequencer/firehose path,
+    // `repair [options]` verifies (and with --fix rebuilds) local repos,
+    // `replay [options]` inspects (and with --emit re-sequences) firehose events,
+    // `compress-events [options]` rewrites stored event payloads with zstd,
+    // `migrate [status|upgrade|actor-stores]` reports and applies database migrations,
+    // `encryption <command>` manages encryption at rest
+    let seed_options = match args.first().map(String::as_str) {
+        Some("seed") => Some(seed::SeedOptions::from_args(&args[1..])?),
+        _ => None,
+    };
+    let loadtest_options = match args.first().map(String::as_str) {
+        Some("loadtest") => Some(loadtest::LoadTestOptions::from_args(&args[1..])?),
+        _ => None,
+    };
+    let repair_options = match args.first().map(String::as_str) {
+        Some("repair") => Some(repair::RepairOptions::from_args(&args[1..])?),
+        _ => None,
+    };
+    let replay_options = match args.first().map(String::as_str) {
+        Some("replay") => Some(replay::ReplayOptions::from_args(&args[1..])?),
+        _ => None,
+    };
+    let recompress_options = match args.first().map(String::as_str) {
+        Some("compress-events") => Some(recompress::RecompressOptions::from_args(&args[1..])?),
+        _ => None,
+    };
+    let encryption_options = match args.first().map(String::as_str) {
+        Some("encryption") => Some(encryption::EncryptionOptions::from_args(&args[1..])?),
+        _ => None,
+    };
+
+    // `migrate [status|upgrade|actor-stores]` inspects and upgrades the databases before
+    // anything else opens them
+    if args.first().map(String::as_str) == Some("migrate") {
+        let options = db::migrations::MigrateOptions::from_args(&args[1..])?;
+        let config = ServerConfig::from_env()?;
+        print!("{}", db::migrations::run(&config, &options).await?);
+        return Ok(());
+    }
+
     // Load configuration
     let config = ServerConfig::from_env()?;
 
     // Create application context
     let ctx = AppContext::new(config).await?;
+
+    if let Some(options) = seed_options {
+        let report = seed::run(&ctx, &options).await?;
+        println!(
+            "Seeded {} new account(s) ({} already present), {} post(s), {} follow(s), {} blob(s). Password: {}",
+            report.accounts_created,
+            report.accounts_existing,
+            report.posts,
+            report.follows,
+            report.blobs,
+            options.password
+        );
+        return Ok(());
+    }
+
+    if let Some(options) = loadtest_options {
+        let report = loadtest::run(&ctx, &options).await?;
+        print!("{}", report.summary());
+        return Ok(());
+    }
+
+    if let Some(options) = repair_options {
+        let report = repair::run(&ctx, &options).await?;
+        print!("{}", report.summary());
+        return Ok(());
+    }
+
+    if let Some(options) = replay_options {
+        let report = replay::run(&ctx, &options).await?;
+        print!("{}", report.summary());
+        return Ok(());
+    }
+
+    if let Some(options) = recompress_options {
+        let report = recompress::run(&ctx, &options).await?;
+        print!("{}", report.summary());
+        return Ok(());
+    }
+
+    if let Some(options) = encryption_options {
+        let report = encryption::run(&ctx, &options).await?;
+        print!("{}", report.summary());
+        return Ok(());
+    }
+
     let ctx = std::sync::Arc::new(ctx);
 
-    // Start background jobs
-    let scheduler = std::sync::Arc::new(jobs::JobScheduler::new(Arc::clone(&ctx)));
-    scheduler.start();
+    // Surface a misconfigured or unreachable mail server at startup rather
+    // than on the first signup
+    if let Err(e) = ctx.mailer.verify().await {
+        tracing::error!("{}", e);
+    }
+
+    // Start background jobs (on the primary only; a replica's data is
+    // overwritten by replication and its writes go to the primary)
+    if let Some(primary) = &ctx.config.replica.primary_url {
+        tracing::info!("Read replica of {}: background jobs are not started", primary);
+    } else {
+        let scheduler = std::sync::Arc::new(jobs::JobScheduler::new(Arc::clone(&ctx)));
+        scheduler.start();
+    }
+
+    // Reload dynamic settings on SIGHUP
+    reload::spawn_sighup_listener((*ctx).clone());
+
+    // Start mirror mode (follows a remote firehose for read-only repo copies)
+    if let Some(mirror_manager) = ctx.mirror_manager.clone() {
+        tokio::spawn(mirror::MirrorConsumer::new(mirror_manager, ctx.account_db.clone()).run());
+    }
 
     // Start server
-    server::serve((*ctx).clone()).await?;
+    let result = server::serve((*ctx).clone()).await;
+    telemetry::shutdown();
 
-    Ok(())
+    result
 }
 
 fn print_banner() {
@@ -84,8 +207,9 @@ fn print_banner() {
  / ___ / /_/ / /  / /_/ / /  / /_/ /  / /___/ /_/ / /__/ /_/ (__  )
 /_/  |_\__,_/_/   \____/_/   \__,_/  /_____/\____/\___/\__,_/____/
 
-        ATProto Personal Data Server v{}
+        ATProto Personal Data Server v{} ({})
         "#,
-        env!("CARGO_PKG_VERSION")
+        env!("CARGO_PKG_VERSION"),
+        &api::version::GIT_COMMIT[..api::version::GIT_COMMIT.len().min(12)]
     );
 }
@@ -9,9 +9,10 @@
 
 use lazy_static::lazy_static;
 use prometheus::{
-    register_counter_vec, register_gauge, register_histogram_vec, register_int_counter_vec,
-    register_int_gauge, CounterVec, Gauge, HistogramVec, IntCounterVec, IntGauge, TextEncoder,
-    Encoder,
+    register_counter_vec, register_gauge, register_histogram, register_histogram_vec,
+    register_int_counter, register_int_counter_vec, register_int_gauge, register_int_gauge_vec,
+    CounterVec, Gauge, Histogram, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec,
+    TextEncoder, Encoder,
 };
 
 lazy_static! {
@@ -200,6 +201,41 @@ lazy_static! {
     )
     .unwrap();
 
+    /// Blobs staged by uploadBlob or a resumable upload
+    pub static ref BLOB_STAGED_TOTAL: IntCounter = register_int_counter!(
+        "blob_staged_total",
+        "Total number of blobs staged in temp storage"
+    )
+    .unwrap();
+
+    /// Staged blobs never committed to a record, removed by the cleanup job
+    pub static ref BLOB_STAGED_EXPIRED_TOTAL: IntCounter = register_int_counter!(
+        "blob_staged_expired_total",
+        "Staged blobs removed by cleanup without being committed"
+    )
+    .unwrap();
+
+    /// Uploads refused because the account had too many blobs staged
+    pub static ref BLOB_STAGED_REJECTED_TOTAL: IntCounter = register_int_counter!(
+        "blob_staged_rejected_total",
+        "Uploads refused by the per-account staged blob limit"
+    )
+    .unwrap();
+
+    /// Blobs currently staged but not committed
+    pub static ref BLOB_STAGED_COUNT: IntGauge = register_int_gauge!(
+        "blob_staged_count",
+        "Blobs staged in temp storage but not committed"
+    )
+    .unwrap();
+
+    /// Bytes currently staged but not committed
+    pub static ref BLOB_STAGED_BYTES: IntGauge = register_int_gauge!(
+        "blob_staged_bytes",
+        "Bytes staged in temp storage but not committed"
+    )
+    .unwrap();
+
     // ========== Account Metrics ==========
 
     /// Account creations
@@ -210,6 +246,14 @@ lazy_static! {
     )
     .unwrap();
 
+    /// createAccount/createSession attempts refused by the network blocklist
+    pub static ref BLOCKLIST_BLOCKED_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "blocklist_blocked_total",
+        "Total number of registration and login attempts refused by the blocklist",
+        &["endpoint", "kind"]
+    )
+    .unwrap();
+
     /// Active sessions
     pub static ref SESSIONS_ACTIVE: IntGauge = register_int_gauge!(
         "sessions_active",
@@ -241,6 +285,138 @@ lazy_static! {
     )
     .unwrap();
 
+    /// Events written per sequencer transaction
+    pub static ref SEQUENCER_BATCH_SIZE: Histogram = register_histogram!(
+        "sequencer_batch_size",
+        "Number of events written per sequencer transaction",
+        vec![1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0, 128.0, 256.0]
+    )
+    .unwrap();
+
+    /// Gaps found by the last sequencer integrity check
+    pub static ref SEQUENCER_GAPS: IntGauge = register_int_gauge!(
+        "sequencer_gaps",
+        "Number of gaps in the sequencer event log"
+    )
+    .unwrap();
+
+    /// Seqs missing from the event log
+    pub static ref SEQUENCER_MISSING_EVENTS: IntGauge = register_int_gauge!(
+        "sequencer_missing_events",
+        "Number of seq numbers missing from the sequencer event log"
+    )
+    .unwrap();
+
+    /// Duplicate commits found by the last integrity check
+    pub static ref SEQUENCER_DUPLICATE_EVENTS: IntGauge = register_int_gauge!(
+        "sequencer_duplicate_events",
+        "Number of duplicate commit events found by the last integrity check"
+    )
+    .unwrap();
+
+    /// Commits sequenced as tooBig, by the limit they exceeded
+    pub static ref SEQUENCER_TOO_BIG_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "sequencer_commits_too_big_total",
+        "Commits sequenced as tooBig because their blocks or ops exceeded the limit",
+        &["reason"]
+    )
+    .unwrap();
+
+    // ========== Firehose Metrics ==========
+
+    /// Bytes sent to firehose clients, after compression
+    pub static ref FIREHOSE_BYTES_SENT_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "firehose_bytes_sent_total",
+        "Total payload bytes sent to firehose clients",
+        &["compression"]
+    )
+    .unwrap();
+
+    /// Bytes sent over each firehose connection, recorded when it closes
+    pub static ref FIREHOSE_CONNECTION_BYTES: Histogram = register_histogram!(
+        "firehose_connection_bytes_sent",
+        "Payload bytes sent per firehose connection",
+        prometheus::exponential_buckets(1024.0, 8.0, 8).unwrap()
+    )
+    .unwrap();
+
+    /// Commits sent as tooBig because they exceeded the frame size limit
+    pub static ref FIREHOSE_TOO_BIG_TOTAL: IntCounter = register_int_counter!(
+        "firehose_too_big_total",
+        "Commits converted to tooBig frames by the frame size limit"
+    )
+    .unwrap();
+
+    /// Open firehose connections
+    pub static ref FIREHOSE_CONSUMERS: IntGauge = register_int_gauge!(
+        "firehose_consumers",
+        "Connected firehose consumers"
+    )
+    .unwrap();
+
+    /// Events the furthest-behind consumer trails the head of the log by
+    pub static ref FIREHOSE_CONSUMER_MAX_LAG: IntGauge = register_int_gauge!(
+        "firehose_consumer_max_lag",
+        "Events the slowest firehose consumer is behind the head of the log"
+    )
+    .unwrap();
+
+    /// Events each connected consumer trails the head of the log by
+    pub static ref FIREHOSE_CONSUMER_LAG: IntGaugeVec = register_int_gauge_vec!(
+        "firehose_consumer_lag",
+        "Events a firehose consumer is behind the head of the log",
+        &["consumer"]
+    )
+    .unwrap();
+
+    /// Firehose producer wake-ups, by what woke them (event, timer)
+    pub static ref FIREHOSE_PRODUCER_WAKEUPS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "firehose_producer_wakeups_total",
+        "Idle firehose producer wake-ups by cause",
+        &["cause"]
+    )
+    .unwrap();
+
+    // ========== Repository Export Metrics ==========
+
+    /// Repository CAR exports by kind (full or partial)
+    pub static ref REPO_EXPORTS_TOTAL: IntCounterVec = register_int_counter_vec!(
+        "repo_exports_total",
+        "Total number of repository CAR exports",
+        &["kind"]
+    )
+    .unwrap();
+
+    /// Size of each repository CAR export
+    pub static ref REPO_EXPORT_BYTES: Histogram = register_histogram!(
+        "repo_export_bytes",
+        "Bytes written per repository CAR export",
+        prometheus::exponential_buckets(1024.0, 4.0, 10).unwrap()
+    )
+    .unwrap();
+
+    /// Blocks in each repository CAR export
+    pub static ref REPO_EXPORT_BLOCKS: Histogram = register_histogram!(
+        "repo_export_blocks",
+        "Blocks written per repository CAR export",
+        prometheus::exponential_buckets(10.0, 4.0, 10).unwrap()
+    )
+    .unwrap();
+
+    /// Unreachable blocks deleted by actor store compaction
+    pub static ref REPO_COMPACTION_PRUNED_BLOCKS_TOTAL: IntCounter = register_int_counter!(
+        "repo_compaction_pruned_blocks_total",
+        "Unreachable repository blocks deleted by compaction"
+    )
+    .unwrap();
+
+    /// Bytes returned to the filesystem by actor store compaction
+    pub static ref REPO_COMPACTION_RECLAIMED_BYTES_TOTAL: IntCounter = register_int_counter!(
+        "repo_compaction_reclaimed_bytes_total",
+        "Actor store bytes reclaimed by compaction and vacuum"
+    )
+    .unwrap();
+
     // ========== Identity Resolution Metrics ==========
 
     /// Identity resolutions by DID method
@@ -251,6 +427,13 @@ lazy_static! {
     )
     .unwrap();
 
+    /// Expired DID documents served because no PLC directory answered
+    pub static ref STALE_DID_DOCS_SERVED_TOTAL: IntCounter = register_int_counter!(
+        "identity_stale_did_docs_served_total",
+        "Expired cached DID documents served in offline mode"
+    )
+    .unwrap();
+
     /// Handle resolutions
     pub static ref HANDLE_RESOLUTIONS_TOTAL: IntCounterVec = register_int_counter_vec!(
         "handle_resolutions_total",
@@ -360,6 +543,24 @@ pub fn record_blob_upload(mime_type: &str) {
     BLOB_UPLOADS_TOTAL.with_label_values(&[mime_type]).inc();
 }
 
+/// Record a blob staged in temp storage
+pub fn record_blob_staged() {
+    BLOB_STAGED_TOTAL.inc();
+}
+
+/// Record an upload refused by the staged blob limit
+pub fn record_blob_staging_rejected() {
+    BLOB_STAGED_REJECTED_TOTAL.inc();
+}
+
+/// Record a temp blob cleanup pass: staged blobs expired, and what is
+/// still staged afterwards
+pub fn record_staged_blob_cleanup(expired: u64, staged_count: i64, staged_bytes: i64) {
+    BLOB_STAGED_EXPIRED_TOTAL.inc_by(expired);
+    BLOB_STAGED_COUNT.set(staged_count);
+    BLOB_STAGED_BYTES.set(staged_bytes);
+}
+
 /// Record an account creation
 pub fn record_account_creation(invite_required: bool) {
     ACCOUNT_CREATIONS_TOTAL
@@ -367,6 +568,13 @@ pub fn record_account_creation(invite_required: bool) {
         .inc();
 }
 
+/// Record an attempt refused by the network blocklist
+pub fn record_blocklist_block(endpoint: &str, kind: &str) {
+    BLOCKLIST_BLOCKED_TOTAL
+        .with_label_values(&[endpoint, kind])
+        .inc();
+}
+
 /// Record a sequencer event
 pub fn record_sequencer_event(event_type: &str) {
     SEQUENCER_EVENTS_TOTAL
@@ -374,6 +582,82 @@ pub fn record_sequencer_event(event_type: &str) {
         .inc();
 }
 
+/// Record the size of a sequencer write batch
+pub fn record_sequencer_batch(size: usize) {
+    SEQUENCER_BATCH_SIZE.observe(size as f64);
+}
+
+/// Record a commit sequenced as tooBig ("blocks" or "ops")
+pub fn record_sequencer_too_big(reason: &str) {
+    SEQUENCER_TOO_BIG_TOTAL.with_label_values(&[reason]).inc();
+}
+
+/// Record the result of a sequencer integrity check
+pub fn record_sequencer_integrity(health: &crate::sequencer::SequencerHealth) {
+    SEQUENCER_GAPS.set(health.gaps.len() as i64);
+    SEQUENCER_MISSING_EVENTS.set(health.missing_events);
+    SEQUENCER_DUPLICATE_EVENTS.set(
+        health.duplicates.iter().map(|d| d.duplicate_seqs.len() as i64).sum(),
+    );
+}
+
+/// Record bytes sent to a firehose client
+pub fn record_firehose_bytes(compressed: bool, bytes: u64) {
+    FIREHOSE_BYTES_SENT_TOTAL
+        .with_label_values(&[if compressed { "deflate" } else { "none" }])
+        .inc_by(bytes);
+}
+
+/// Record the bytes sent over a firehose connection that closed
+pub fn record_firehose_connection(bytes_sent: u64) {
+    FIREHOSE_CONNECTION_BYTES.observe(bytes_sent as f64);
+}
+
+/// Record a commit sent as tooBig
+pub fn record_firehose_too_big() {
+    FIREHOSE_TOO_BIG_TOTAL.inc();
+}
+
+/// Set the number of connected firehose consumers
+pub fn set_firehose_consumers(count: usize) {
+    FIREHOSE_CONSUMERS.set(count as i64);
+}
+
+/// Set the lag of the slowest firehose consumer
+pub fn set_firehose_consumer_lag(lag: i64) {
+    FIREHOSE_CONSUMER_MAX_LAG.set(lag);
+}
+
+/// Set the lag of one firehose consumer
+pub fn set_firehose_consumer_lag_for(consumer: u64, lag: i64) {
+    FIREHOSE_CONSUMER_LAG.with_label_values(&[&consumer.to_string()]).set(lag);
+}
+
+/// Drop the lag series of a consumer that disconnected
+pub fn remove_firehose_consumer_lag(consumer: u64) {
+    let _ = FIREHOSE_CONSUMER_LAG.remove_label_values(&[&consumer.to_string()]);
+}
+
+/// Record an idle firehose producer waking up to check the log
+pub fn record_firehose_wakeup(cause: &str) {
+    FIREHOSE_PRODUCER_WAKEUPS_TOTAL.with_label_values(&[cause]).inc();
+}
+
+/// Record a completed repository export
+pub fn record_repo_export(partial: bool, bytes: u64, blocks: u64) {
+    REPO_EXPORTS_TOTAL
+        .with_label_values(&[if partial { "partial" } else { "full" }])
+        .inc();
+    REPO_EXPORT_BYTES.observe(bytes as f64);
+    REPO_EXPORT_BLOCKS.observe(blocks as f64);
+}
+
+/// Record the outcome of compacting one actor store
+pub fn record_repo_compaction(pruned_blocks: u64, reclaimed_bytes: u64) {
+    REPO_COMPACTION_PRUNED_BLOCKS_TOTAL.inc_by(pruned_blocks);
+    REPO_COMPACTION_RECLAIMED_BYTES_TOTAL.inc_by(reclaimed_bytes);
+}
+
 /// Record an identity resolution
 pub fn record_identity_resolution(did_method: &str, success: bool) {
     IDENTITY_RESOLUTIONS_TOTAL
@@ -381,6 +665,11 @@ pub fn record_identity_resolution(did_method: &str, success: bool) {
         .inc();
 }
 
+/// Record an expired DID document served in offline mode
+pub fn record_stale_did_doc_served() {
+    STALE_DID_DOCS_SERVED_TOTAL.inc();
+}
+
 /// Record a handle resolution
 pub fn record_handle_resolution(success: bool) {
     HANDLE_RESOLUTIONS_TOTAL
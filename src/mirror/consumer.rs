@@ -0,0 +1,70 @@
+/// Mirror firehose consumer
+///
+/// Follows the source's com.atproto.sync.subscribeRepos stream through a
+/// `FirehoseSubscription` (frame validation, persisted cursor, reconnect
+/// with backoff) and hands commits for mirrored repositories to the
+/// MirrorManager.
+
+use crate::{
+    federation::relay::{CursorStore, FirehoseEvent, FirehoseHandler, FirehoseSubscription},
+    metrics,
+    mirror::ingest::MirrorManager,
+};
+use async_trait::async_trait;
+use sqlx::SqlitePool;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+/// Firehose consumer for mirror mode
+pub struct MirrorConsumer {
+    manager: Arc<MirrorManager>,
+    cursor_store: CursorStore,
+}
+
+impl MirrorConsumer {
+    /// Create a new consumer; the cursor is persisted in `db`
+    pub fn new(manager: Arc<MirrorManager>, db: SqlitePool) -> Self {
+        Self {
+            manager,
+            cursor_store: CursorStore::new(db),
+        }
+    }
+
+    /// Bootstrap mirrored repositories, then follow the firehose forever
+    pub async fn run(self) {
+        self.manager.bootstrap_all().await;
+
+        // Before cursors were persisted, resumption used the last applied commit
+        let initial_cursor = match self.manager.cursor().await {
+            Ok(cursor) => cursor,
+            Err(e) => {
+                warn!(error = %e, "mirror_cursor_lookup_failed");
+                None
+            }
+        };
+
+        let subscription = FirehoseSubscription::new(self.manager.source_url())
+            .with_cursor_store(self.cursor_store.clone())
+            .with_initial_cursor(initial_cursor);
+        subscription.run(&self).await;
+    }
+}
+
+#[async_trait]
+impl FirehoseHandler for MirrorConsumer {
+    async fn handle_event(&self, event: FirehoseEvent) -> ControlFlow<()> {
+        match event {
+            FirehoseEvent::Commit(frame) => {
+                if let Err(e) = self.manager.apply_commit(&frame).await {
+                    warn!(did = %frame.repo, rev = %frame.rev, error = %e, "mirror_commit_failed");
+                    metrics::record_error("MirrorCommitFailed", "mirror");
+                    let _ = self.manager.mark_error(&frame.repo, &e.to_string()).await;
+                }
+            }
+            FirehoseEvent::Other { kind, seq, .. } => debug!("Mirror ignoring {} frame {}", kind, seq),
+        }
+
+        ControlFlow::Continue(())
+    }
+}
@@ -0,0 +1,524 @@
+/// Mirror ingestion - verifies and stores mirrored repository data
+///
+/// Every block is checked against its CID and every commit signature is
+/// verified against the repository's `#atproto` signing key before anything
+/// is written to the actor store.
+
+use crate::{
+    actor_store::{
+        verify::{account_signing_key, decode_commit},
+        ActorStore,
+    },
+    car::{read_verified_car, CarContents},
+    config::MirrorConfig,
+    error::{PdsError, PdsResult},
+    identity::IdentityResolver,
+    mirror::validation::{CommitFrame, RepoOpAction},
+};
+use chrono::{DateTime, Utc};
+use k256::ecdsa::{signature::hazmat::PrehashVerifier, Signature};
+use libipld::{cbor::DagCborCodec, codec::Codec, Cid, Ipld};
+use serde::Serialize;
+use sqlx::{Row, SqlitePool};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tracing::{info, warn};
+
+/// Mirrored repository status
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MirroredRepo {
+    pub did: String,
+    pub source: String,
+    pub head: Option<String>,
+    pub rev: Option<String>,
+    pub last_seq: Option<i64>,
+    pub status: String,
+    pub last_error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Verified commit contents
+#[derive(Debug, Clone)]
+struct VerifiedCommit {
+    rev: String,
+    data: Cid,
+}
+
+/// Mirror manager - owns the mirrored repository set
+pub struct MirrorManager {
+    db: SqlitePool,
+    actor_store: Arc<ActorStore>,
+    identity_resolver: Arc<IdentityResolver>,
+    config: MirrorConfig,
+    http_client: reqwest::Client,
+}
+
+impl MirrorManager {
+    /// Create a new mirror manager
+    pub fn new(
+        db: SqlitePool,
+        actor_store: Arc<ActorStore>,
+        identity_resolver: Arc<IdentityResolver>,
+        config: MirrorConfig,
+    ) -> Self {
+        Self {
+            db,
+            actor_store,
+            identity_resolver,
+            config,
+            http_client: reqwest::Client::builder()
+                .timeout(std::time::Duration::from_secs(60))
+                .build()
+                .unwrap(),
+        }
+    }
+
+    /// Mirror source URL
+    pub fn source_url(&self) -> &str {
+        &self.config.source_url
+    }
+
+    /// Whether a DID is configured for mirroring
+    pub fn is_tracked(&self, did: &str) -> bool {
+        self.config.dids.iter().any(|d| d == did)
+    }
+
+    /// Look up the mirror status of a DID (None if the repo is not mirrored)
+    pub async fn get_mirrored(&self, did: &str) -> PdsResult<Option<MirroredRepo>> {
+        let row = sqlx::query(
+            "SELECT did, source, head, rev, last_seq, status, last_error, updated_at
+             FROM mirror_repo WHERE did = ?1"
+        )
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?;
+
+        Ok(row.map(|row| MirroredRepo {
+            did: row.get("did"),
+            source: row.get("source"),
+            head: row.get("head"),
+            rev: row.get("rev"),
+            last_seq: row.get("last_seq"),
+            status: row.get("status"),
+            last_error: row.get("last_error"),
+            updated_at: row.get("updated_at"),
+        }))
+    }
+
+    /// Highest firehose sequence number applied from the source
+    pub async fn cursor(&self) -> PdsResult<Option<i64>> {
+        let cursor: Option<i64> = sqlx::query_scalar(
+            "SELECT MAX(last_seq) FROM mirror_repo WHERE source = ?1"
+        )
+        .bind(&self.config.source_url)
+        .fetch_one(&self.db)
+        .await?;
+
+        Ok(cursor)
+    }
+
+    /// Fetch full copies of tracked repositories that have not been synced yet
+    pub async fn bootstrap_all(&self) {
+        for did in &self.config.dids {
+            match self.get_mirrored(did).await {
+                Ok(Some(repo)) if repo.status == "synced" => continue,
+                Err(e) => {
+                    warn!(did = %did, error = %e, "mirror_status_lookup_failed");
+                    continue;
+                }
+                _ => {}
+            }
+
+            if let Err(e) = self.bootstrap(did).await {
+                warn!(did = %did, error = %e, "mirror_bootstrap_failed");
+                let _ = self.mark_error(did, &e.to_string()).await;
+            }
+        }
+    }
+
+    /// Fetch and ingest a full repository export from the source
+    pub async fn bootstrap(&self, did: &str) -> PdsResult<()> {
+        self.ensure_not_local(did).await?;
+
+        let url = format!(
+            "{}/xrpc/com.atproto.sync.getRepo?did={}",
+            self.config.source_url.trim_end_matches('/'),
+            urlencoding::encode(did)
+        );
+
+        let response = self
+            .http_client
+            .get(&url)
+            .send()
+            .await
+            .map_err(|e| PdsError::Federation(format!("Failed to fetch repo for {}: {}", did, e)))?;
+
+        if !response.status().is_success() {
+            return Err(PdsError::Federation(format!(
+                "Mirror source returned {} for {}",
+                response.status(),
+                did
+            )));
+        }
+
+        let car_bytes = response
+            .bytes()
+            .await
+            .map_err(|e| PdsError::Federation(format!("Failed to read repo for {}: {}", did, e)))?;
+
+        let CarContents { roots, blocks } = read_verified_car(&car_bytes)?;
+        let commit_cid = *roots
+            .first()
+            .ok_or_else(|| PdsError::Validation("Repository CAR has no root".to_string()))?;
+
+        let commit = self.verify_commit(did, &commit_cid, &blocks).await?;
+        let records = walk_mst(&commit.data, &blocks)?;
+
+        self.store_blocks(did, &blocks).await?;
+
+        // Replace the record index with the verified tree contents
+        let mut wanted = HashSet::new();
+        for (key, cid) in records {
+            let (collection, rkey) = key
+                .split_once('/')
+                .ok_or_else(|| PdsError::Validation(format!("Invalid MST key: {}", key)))?;
+            let uri = format!("at://{}/{}/{}", did, collection, rkey);
+            self.actor_store
+                .put_record(did, &uri, &cid.to_string(), collection, rkey, &commit.rev)
+                .await?;
+            wanted.insert(uri);
+        }
+
+        for record in self.actor_store.list_all_records(did).await? {
+            if !wanted.contains(&record.uri) {
+                self.actor_store.delete_record(did, &record.uri).await?;
+            }
+        }
+
+        self.actor_store
+            .update_repo_root(did, &commit_cid.to_string(), &commit.rev)
+            .await?;
+        self.mark_synced(did, &commit_cid.to_string(), &commit.rev, None)
+            .await?;
+
+        info!(did = %did, rev = %commit.rev, "mirror_bootstrap_complete");
+
+        Ok(())
+    }
+
+    /// Apply a validated commit frame
+    ///
+    /// Returns false if the frame is for a repository that is not mirrored.
+    pub async fn apply_commit(&self, frame: &CommitFrame) -> PdsResult<bool> {
+        if !self.is_tracked(&frame.repo) {
+            return Ok(false);
+        }
+
+        let current = self.get_mirrored(&frame.repo).await?;
+        let current_rev = current.as_ref().and_then(|r| r.rev.clone());
+
+        // Ignore replays of commits we already have
+        if let Some(rev) = &current_rev {
+            if frame.rev.as_str() <= rev.as_str() {
+                return Ok(true);
+            }
+        }
+
+        // Oversized commits and gaps in the chain need a full resync
+        let in_sequence = current_rev.is_some() && frame.since == current_rev;
+        if frame.too_big || !in_sequence {
+            info!(did = %frame.repo, rev = %frame.rev, "mirror_resync");
+            self.bootstrap(&frame.repo).await?;
+            self.record_seq(&frame.repo, frame.seq).await?;
+            return Ok(true);
+        }
+
+        let blocks = read_verified_car(&frame.blocks)?.blocks;
+        let commit = self.verify_commit(&frame.repo, &frame.commit, &blocks).await?;
+        if commit.rev != frame.rev {
+            return Err(PdsError::Validation(format!(
+                "Commit rev {} does not match frame rev {}",
+                commit.rev, frame.rev
+            )));
+        }
+
+        self.store_blocks(&frame.repo, &blocks).await?;
+
+        for op in &frame.ops {
+            let uri = format!("at://{}/{}/{}", frame.repo, op.collection, op.rkey);
+            match (op.action, op.cid) {
+                (RepoOpAction::Create | RepoOpAction::Update, Some(cid)) => {
+                    if !blocks.contains_key(&cid) {
+                        return Err(PdsError::Validation(format!(
+                            "Commit is missing record block {}",
+                            cid
+                        )));
+                    }
+                    self.actor_store
+                        .put_record(
+                            &frame.repo,
+                            &uri,
+                            &cid.to_string(),
+                            &op.collection,
+                            &op.rkey,
+                            &frame.rev,
+                        )
+                        .await?;
+                }
+                _ => {
+                    self.actor_store.delete_record(&frame.repo, &uri).await?;
+                }
+            }
+        }
+
+        self.actor_store
+            .update_repo_root(&frame.repo, &frame.commit.to_string(), &frame.rev)
+            .await?;
+        self.mark_synced(&frame.repo, &frame.commit.to_string(), &frame.rev, Some(frame.seq))
+            .await?;
+
+        Ok(true)
+    }
+
+    /// Verify a signed commit block and return its contents
+    async fn verify_commit(
+        &self,
+        did: &str,
+        commit_cid: &Cid,
+        blocks: &HashMap<Cid, Vec<u8>>,
+    ) -> PdsResult<VerifiedCommit> {
+        let bytes = blocks
+            .get(commit_cid)
+            .ok_or_else(|| PdsError::Validation(format!("Missing commit block {}", commit_cid)))?;
+
+        let (unsigned, sig) = decode_commit(bytes)?;
+
+        if unsigned.did != did {
+            return Err(PdsError::Validation(format!(
+                "Commit DID {} does not match repository {}",
+                unsigned.did, did
+            )));
+        }
+
+        let key = account_signing_key(&self.identity_resolver, did).await?;
+        let hash = unsigned
+            .signing_hash()
+            .map_err(|e| PdsError::Internal(format!("Failed to hash commit: {}", e)))?;
+        let signature = Signature::from_slice(&sig)
+            .map_err(|_| PdsError::Validation("Malformed commit signature".to_string()))?;
+
+        key.verify_prehash(&hash, &signature)
+            .map_err(|_| PdsError::Validation(format!("Invalid commit signature for {}", did)))?;
+
+        Ok(VerifiedCommit {
+            rev: unsigned.rev,
+            data: unsigned.data,
+        })
+    }
+
+    /// Store verified blocks in the actor store, creating it if needed
+    async fn store_blocks(&self, did: &str, blocks: &HashMap<Cid, Vec<u8>>) -> PdsResult<()> {
+        if !self.actor_store.exists(did).await {
+            self.actor_store.create(did).await?;
+        }
+
+        for (cid, data) in blocks {
+            self.actor_store.put_block(did, &cid.to_string(), data).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Refuse to mirror over a repository hosted on this PDS
+    async fn ensure_not_local(&self, did: &str) -> PdsResult<()> {
+        let local: Option<String> = sqlx::query_scalar("SELECT did FROM account WHERE did = ?1")
+            .bind(did)
+            .fetch_optional(&self.db)
+            .await?;
+
+        if local.is_some() {
+            return Err(PdsError::Conflict(format!(
+                "{} is hosted on this PDS and cannot be mirrored",
+                did
+            )));
+        }
+
+        Ok(())
+    }
+
+    async fn mark_synced(
+        &self,
+        did: &str,
+        head: &str,
+        rev: &str,
+        seq: Option<i64>,
+    ) -> PdsResult<()> {
+        sqlx::query(
+            "INSERT INTO mirror_repo (did, source, head, rev, last_seq, status, last_error, updated_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, 'synced', NULL, ?6)
+             ON CONFLICT(did) DO UPDATE SET
+                source = excluded.source,
+                head = excluded.head,
+                rev = excluded.rev,
+                last_seq = COALESCE(excluded.last_seq, mirror_repo.last_seq),
+                status = 'synced',
+                last_error = NULL,
+                updated_at = excluded.updated_at"
+        )
+        .bind(did)
+        .bind(&self.config.source_url)
+        .bind(head)
+        .bind(rev)
+        .bind(seq)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+
+    async fn record_seq(&self, did: &str, seq: i64) -> PdsResult<()> {
+        sqlx::query("UPDATE mirror_repo SET last_seq = ?1, updated_at = ?2 WHERE did = ?3")
+            .bind(seq)
+            .bind(Utc::now())
+            .bind(did)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Record a failure for a mirrored repository
+    pub async fn mark_error(&self, did: &str, error: &str) -> PdsResult<()> {
+        sqlx::query(
+            "INSERT INTO mirror_repo (did, source, status, last_error, updated_at)
+             VALUES (?1, ?2, 'error', ?3, ?4)
+             ON CONFLICT(did) DO UPDATE SET
+                status = 'error',
+                last_error = excluded.last_error,
+                updated_at = excluded.updated_at"
+        )
+        .bind(did)
+        .bind(&self.config.source_url)
+        .bind(error)
+        .bind(Utc::now())
+        .execute(&self.db)
+        .await?;
+
+        Ok(())
+    }
+}
+
+/// Walk an MST from its root, returning every (key, value CID) pair
+fn walk_mst(root: &Cid, blocks: &HashMap<Cid, Vec<u8>>) -> PdsResult<Vec<(String, Cid)>> {
+    let mut entries = Vec::new();
+    walk_mst_node(root, blocks, &mut entries)?;
+    Ok(entries)
+}
+
+fn walk_mst_node(
+    cid: &Cid,
+    blocks: &HashMap<Cid, Vec<u8>>,
+    entries: &mut Vec<(String, Cid)>,
+) -> PdsResult<()> {
+    let bytes = blocks
+        .get(cid)
+        .ok_or_else(|| PdsError::Validation(format!("Missing MST node {}", cid)))?;
+    let node: Ipld = DagCborCodec
+        .decode(bytes)
+        .map_err(|e| PdsError::Validation(format!("Invalid MST node {}: {}", cid, e)))?;
+
+    let Ipld::Map(node) = node else {
+        return Err(PdsError::Validation(format!("MST node {} is not a map", cid)));
+    };
+
+    if let Some(Ipld::Link(left)) = node.get("l") {
+        walk_mst_node(left, blocks, entries)?;
+    }
+
+    let mut last_key: Vec<u8> = Vec::new();
+    if let Some(Ipld::List(list)) = node.get("e") {
+        for entry in list {
+            let Ipld::Map(entry) = entry else {
+                return Err(PdsError::Validation("Invalid MST entry".to_string()));
+            };
+
+            let prefix_len = match entry.get("p") {
+                Some(Ipld::Integer(p)) if *p >= 0 && (*p as usize) <= last_key.len() => *p as usize,
+                _ => return Err(PdsError::Validation("Invalid MST entry prefix".to_string())),
+            };
+            let suffix = match entry.get("k") {
+                Some(Ipld::Bytes(k)) => k,
+                _ => return Err(PdsError::Validation("Invalid MST entry key".to_string())),
+            };
+            let value = match entry.get("v") {
+                Some(Ipld::Link(v)) => *v,
+                _ => return Err(PdsError::Validation("Invalid MST entry value".to_string())),
+            };
+
+            let mut key = last_key[..prefix_len].to_vec();
+            key.extend_from_slice(suffix);
+            let key_str = String::from_utf8(key.clone())
+                .map_err(|_| PdsError::Validation("MST key is not UTF-8".to_string()))?;
+            entries.push((key_str, value));
+            last_key = key;
+
+            if let Some(Ipld::Link(right)) = entry.get("t") {
+                walk_mst_node(right, blocks, entries)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::multihash::{Code, MultihashDigest};
+    use std::collections::BTreeMap;
+
+    fn block(ipld: &Ipld) -> (Cid, Vec<u8>) {
+        let bytes = DagCborCodec.encode(ipld).unwrap();
+        (Cid::new_v1(0x71, Code::Sha2_256.digest(&bytes)), bytes)
+    }
+
+    #[test]
+    fn test_walk_mst() {
+        let (record_a, _) = block(&Ipld::String("a".to_string()));
+        let (record_b, _) = block(&Ipld::String("b".to_string()));
+
+        let entry = |p: i128, k: &str, v: Cid| {
+            let mut e = BTreeMap::new();
+            e.insert("p".to_string(), Ipld::Integer(p));
+            e.insert("k".to_string(), Ipld::Bytes(k.as_bytes().to_vec()));
+            e.insert("v".to_string(), Ipld::Link(v));
+            e.insert("t".to_string(), Ipld::Null);
+            Ipld::Map(e)
+        };
+
+        let mut node = BTreeMap::new();
+        node.insert("l".to_string(), Ipld::Null);
+        node.insert(
+            "e".to_string(),
+            Ipld::List(vec![
+                entry(0, "app.bsky.feed.post/3jzfcijpj2z2a", record_a),
+                entry(31, "b", record_b),
+            ]),
+        );
+        let (root, bytes) = block(&Ipld::Map(node));
+
+        let mut blocks = HashMap::new();
+        blocks.insert(root, bytes);
+
+        let entries = walk_mst(&root, &blocks).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                ("app.bsky.feed.post/3jzfcijpj2z2a".to_string(), record_a),
+                ("app.bsky.feed.post/3jzfcijpj2z2b".to_string(), record_b),
+            ]
+        );
+    }
+}
@@ -0,0 +1,15 @@
+/// Mirror mode
+///
+/// Maintains read-only copies of selected remote repositories by following
+/// another PDS or relay firehose:
+/// - Structured validation of every consumed frame
+/// - CAR block ingestion with CID verification
+/// - Commit signature verification against the DID's signing key
+/// - Serving mirrored repos through the sync endpoints, flagged as mirrored
+
+pub mod consumer;
+pub mod ingest;
+pub mod validation;
+
+pub use consumer::MirrorConsumer;
+pub use ingest::{MirrorManager, MirroredRepo};
@@ -0,0 +1,501 @@
+/// Structured validation of firehose frames consumed in mirror mode
+///
+/// Frames from a remote firehose are untrusted input. Every frame is decoded
+/// and checked field-by-field before any of its contents reach the actor
+/// store; anything malformed is rejected with a specific reason.
+
+use crate::error::PdsError;
+use atproto::{syntax, tid::Tid};
+use libipld::{cbor::DagCborCodec, codec::Decode, Cid, Ipld};
+use std::collections::BTreeMap;
+use std::io::Cursor;
+use thiserror::Error;
+
+/// Maximum size of the CAR slice carried by a single commit frame
+pub const MAX_COMMIT_BLOCKS_BYTES: usize = 2 * 1024 * 1024;
+
+/// Maximum number of operations in a single commit frame
+pub const MAX_COMMIT_OPS: usize = 200;
+
+/// Reasons a firehose frame is rejected
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum MirrorValidationError {
+    #[error("Malformed frame: {0}")]
+    MalformedFrame(String),
+
+    #[error("Missing field: {0}")]
+    MissingField(&'static str),
+
+    #[error("Invalid field {field}: {reason}")]
+    InvalidField { field: &'static str, reason: String },
+
+    #[error("Too many operations in commit: {0}")]
+    TooManyOps(usize),
+
+    #[error("Commit blocks too large: {0} bytes")]
+    BlocksTooLarge(usize),
+
+    #[error("Error frame from upstream: {0}")]
+    UpstreamError(String),
+}
+
+impl From<MirrorValidationError> for PdsError {
+    fn from(e: MirrorValidationError) -> Self {
+        PdsError::Validation(e.to_string())
+    }
+}
+
+/// Decoded frame header
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameHeader {
+    /// 1 for messages, -1 for errors
+    pub op: i64,
+    /// Message type (e.g., "#commit"), absent on error frames
+    pub message_type: Option<String>,
+}
+
+/// Repository operation carried by a commit frame
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepoOpFrame {
+    pub action: RepoOpAction,
+    pub collection: String,
+    pub rkey: String,
+    /// New record CID (None for deletes)
+    pub cid: Option<Cid>,
+}
+
+/// Repository operation action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RepoOpAction {
+    Create,
+    Update,
+    Delete,
+}
+
+/// Validated `#commit` frame body
+#[derive(Debug, Clone)]
+pub struct CommitFrame {
+    pub seq: i64,
+    pub repo: String,
+    pub commit: Cid,
+    pub rev: String,
+    pub since: Option<String>,
+    pub too_big: bool,
+    pub blocks: Vec<u8>,
+    pub ops: Vec<RepoOpFrame>,
+}
+
+/// Split a binary firehose message into its header and body
+///
+/// A frame is two concatenated DAG-CBOR objects.
+pub fn decode_frame(bytes: &[u8]) -> Result<(FrameHeader, Ipld), MirrorValidationError> {
+    let mut cursor = Cursor::new(bytes);
+
+    let header = Ipld::decode(DagCborCodec, &mut cursor)
+        .map_err(|e| MirrorValidationError::MalformedFrame(format!("header: {}", e)))?;
+    let body = Ipld::decode(DagCborCodec, &mut cursor)
+        .map_err(|e| MirrorValidationError::MalformedFrame(format!("body: {}", e)))?;
+
+    if (cursor.position() as usize) != bytes.len() {
+        return Err(MirrorValidationError::MalformedFrame(
+            "trailing bytes after body".to_string(),
+        ));
+    }
+
+    let header_map = as_map(&header, "header")?;
+    let op = match header_map.get("op") {
+        Some(Ipld::Integer(op)) => *op as i64,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "op",
+                reason: "expected integer".to_string(),
+            })
+        }
+        None => return Err(MirrorValidationError::MissingField("op")),
+    };
+
+    let message_type = match header_map.get("t") {
+        Some(Ipld::String(t)) => Some(t.clone()),
+        None => None,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "t",
+                reason: "expected string".to_string(),
+            })
+        }
+    };
+
+    match op {
+        1 if message_type.is_none() => Err(MirrorValidationError::MissingField("t")),
+        1 => Ok((FrameHeader { op, message_type }, body)),
+        -1 => {
+            let body_map = as_map(&body, "body")?;
+            let error = optional_string(body_map, "error")?.unwrap_or_default();
+            let message = optional_string(body_map, "message")?.unwrap_or_default();
+            Err(MirrorValidationError::UpstreamError(format!("{} {}", error, message).trim().to_string()))
+        }
+        other => Err(MirrorValidationError::InvalidField {
+            field: "op",
+            reason: format!("unknown op {}", other),
+        }),
+    }
+}
+
+/// Sequence number of a message frame body (`#commit`, `#identity`, ...)
+pub fn frame_seq(body: &Ipld) -> Option<i64> {
+    match as_map(body, "body").ok()?.get("seq") {
+        Some(Ipld::Integer(seq)) => i64::try_from(*seq).ok(),
+        _ => None,
+    }
+}
+
+/// Validate the body of an `#info` frame, returning its name and message
+pub fn validate_info_frame(body: &Ipld) -> Result<(String, Option<String>), MirrorValidationError> {
+    let map = as_map(body, "body")?;
+    Ok((required_string(map, "name")?, optional_string(map, "message")?))
+}
+
+/// Validate the body of a `#commit` frame
+pub fn validate_commit_frame(body: &Ipld) -> Result<CommitFrame, MirrorValidationError> {
+    let map = as_map(body, "body")?;
+
+    let seq = match map.get("seq") {
+        Some(Ipld::Integer(seq)) if *seq >= 0 => *seq as i64,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "seq",
+                reason: "expected non-negative integer".to_string(),
+            })
+        }
+        None => return Err(MirrorValidationError::MissingField("seq")),
+    };
+
+    let repo = required_string(map, "repo")?;
+    if syntax::ensure_valid_did(&repo).is_err() {
+        return Err(MirrorValidationError::InvalidField {
+            field: "repo",
+            reason: format!("not a DID: {}", repo),
+        });
+    }
+
+    let commit = match map.get("commit") {
+        Some(Ipld::Link(cid)) => *cid,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "commit",
+                reason: "expected CID link".to_string(),
+            })
+        }
+        None => return Err(MirrorValidationError::MissingField("commit")),
+    };
+
+    let rev = required_string(map, "rev")?;
+    if !Tid::is_valid(&rev) {
+        return Err(MirrorValidationError::InvalidField {
+            field: "rev",
+            reason: format!("not a TID: {}", rev),
+        });
+    }
+
+    let since = optional_string(map, "since")?;
+    if let Some(since) = &since {
+        if !Tid::is_valid(since) {
+            return Err(MirrorValidationError::InvalidField {
+                field: "since",
+                reason: format!("not a TID: {}", since),
+            });
+        }
+    }
+
+    let too_big = match map.get("tooBig") {
+        Some(Ipld::Bool(b)) => *b,
+        None => false,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "tooBig",
+                reason: "expected boolean".to_string(),
+            })
+        }
+    };
+
+    let blocks = match map.get("blocks") {
+        Some(Ipld::Bytes(bytes)) => bytes.clone(),
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "blocks",
+                reason: "expected bytes".to_string(),
+            })
+        }
+        None => return Err(MirrorValidationError::MissingField("blocks")),
+    };
+    if blocks.len() > MAX_COMMIT_BLOCKS_BYTES {
+        return Err(MirrorValidationError::BlocksTooLarge(blocks.len()));
+    }
+
+    let ops = match map.get("ops") {
+        Some(Ipld::List(ops)) => ops,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "ops",
+                reason: "expected list".to_string(),
+            })
+        }
+        None => return Err(MirrorValidationError::MissingField("ops")),
+    };
+    if ops.len() > MAX_COMMIT_OPS {
+        return Err(MirrorValidationError::TooManyOps(ops.len()));
+    }
+
+    let ops = ops.iter().map(validate_op).collect::<Result<Vec<_>, _>>()?;
+
+    Ok(CommitFrame {
+        seq,
+        repo,
+        commit,
+        rev,
+        since,
+        too_big,
+        blocks,
+        ops,
+    })
+}
+
+/// Validate a single repo operation
+fn validate_op(op: &Ipld) -> Result<RepoOpFrame, MirrorValidationError> {
+    let map = as_map(op, "op")?;
+
+    let action = match required_string(map, "action")?.as_str() {
+        "create" => RepoOpAction::Create,
+        "update" => RepoOpAction::Update,
+        "delete" => RepoOpAction::Delete,
+        other => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "action",
+                reason: format!("unknown action {}", other),
+            })
+        }
+    };
+
+    let path = required_string(map, "path")?;
+    let (collection, rkey) = path
+        .split_once('/')
+        .filter(|(c, r)| syntax::is_valid_nsid(c) && syntax::is_valid_rkey(r))
+        .ok_or_else(|| MirrorValidationError::InvalidField {
+            field: "path",
+            reason: format!("expected collection/rkey, got {}", path),
+        })?;
+
+    let cid = match map.get("cid") {
+        Some(Ipld::Link(cid)) => Some(*cid),
+        Some(Ipld::Null) | None => None,
+        Some(_) => {
+            return Err(MirrorValidationError::InvalidField {
+                field: "cid",
+                reason: "expected CID link or null".to_string(),
+            })
+        }
+    };
+
+    match (action, cid) {
+        (RepoOpAction::Delete, Some(_)) => Err(MirrorValidationError::InvalidField {
+            field: "cid",
+            reason: "delete must not carry a CID".to_string(),
+        }),
+        (RepoOpAction::Create | RepoOpAction::Update, None) => {
+            Err(MirrorValidationError::MissingField("cid"))
+        }
+        _ => Ok(RepoOpFrame {
+            action,
+            collection: collection.to_string(),
+            rkey: rkey.to_string(),
+            cid,
+        }),
+    }
+}
+
+fn as_map<'a>(
+    value: &'a Ipld,
+    field: &'static str,
+) -> Result<&'a BTreeMap<String, Ipld>, MirrorValidationError> {
+    match value {
+        Ipld::Map(map) => Ok(map),
+        _ => Err(MirrorValidationError::InvalidField {
+            field,
+            reason: "expected map".to_string(),
+        }),
+    }
+}
+
+fn required_string(
+    map: &BTreeMap<String, Ipld>,
+    field: &'static str,
+) -> Result<String, MirrorValidationError> {
+    optional_string(map, field)?.ok_or(MirrorValidationError::MissingField(field))
+}
+
+fn optional_string(
+    map: &BTreeMap<String, Ipld>,
+    field: &'static str,
+) -> Result<Option<String>, MirrorValidationError> {
+    match map.get(field) {
+        Some(Ipld::String(s)) => Ok(Some(s.clone())),
+        Some(Ipld::Null) | None => Ok(None),
+        Some(_) => Err(MirrorValidationError::InvalidField {
+            field,
+            reason: "expected string".to_string(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::codec::Codec;
+    use libipld::multihash::{Code, MultihashDigest};
+
+    fn test_cid(data: &[u8]) -> Cid {
+        Cid::new_v1(0x71, Code::Sha2_256.digest(data))
+    }
+
+    fn commit_body() -> BTreeMap<String, Ipld> {
+        let mut op = BTreeMap::new();
+        op.insert("action".to_string(), Ipld::String("create".to_string()));
+        op.insert("path".to_string(), Ipld::String("app.bsky.feed.post/3jzfcijpj2z2a".to_string()));
+        op.insert("cid".to_string(), Ipld::Link(test_cid(b"record")));
+
+        let mut body = BTreeMap::new();
+        body.insert("seq".to_string(), Ipld::Integer(42));
+        body.insert("repo".to_string(), Ipld::String("did:plc:abc123".to_string()));
+        body.insert("commit".to_string(), Ipld::Link(test_cid(b"commit")));
+        body.insert("rev".to_string(), Ipld::String("3jzfcijpj2z2b".to_string()));
+        body.insert("since".to_string(), Ipld::Null);
+        body.insert("tooBig".to_string(), Ipld::Bool(false));
+        body.insert("blocks".to_string(), Ipld::Bytes(vec![]));
+        body.insert("ops".to_string(), Ipld::List(vec![Ipld::Map(op)]));
+        body
+    }
+
+    #[test]
+    fn test_validate_commit_frame() {
+        let frame = validate_commit_frame(&Ipld::Map(commit_body())).unwrap();
+        assert_eq!(frame.seq, 42);
+        assert_eq!(frame.repo, "did:plc:abc123");
+        assert_eq!(frame.ops.len(), 1);
+        assert_eq!(frame.ops[0].action, RepoOpAction::Create);
+        assert_eq!(frame.ops[0].collection, "app.bsky.feed.post");
+        assert_eq!(frame.ops[0].rkey, "3jzfcijpj2z2a");
+        assert!(frame.since.is_none());
+    }
+
+    #[test]
+    fn test_rejects_missing_and_invalid_fields() {
+        let mut body = commit_body();
+        body.remove("commit");
+        assert_eq!(
+            validate_commit_frame(&Ipld::Map(body)).unwrap_err(),
+            MirrorValidationError::MissingField("commit")
+        );
+
+        let mut body = commit_body();
+        body.insert("repo".to_string(), Ipld::String("alice.example.com".to_string()));
+        assert!(matches!(
+            validate_commit_frame(&Ipld::Map(body)),
+            Err(MirrorValidationError::InvalidField { field: "repo", .. })
+        ));
+
+        let mut body = commit_body();
+        body.insert("rev".to_string(), Ipld::String("not-a-tid".to_string()));
+        assert!(matches!(
+            validate_commit_frame(&Ipld::Map(body)),
+            Err(MirrorValidationError::InvalidField { field: "rev", .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_bad_ops() {
+        let mut op = BTreeMap::new();
+        op.insert("action".to_string(), Ipld::String("delete".to_string()));
+        op.insert("path".to_string(), Ipld::String("app.bsky.feed.post/abc".to_string()));
+        op.insert("cid".to_string(), Ipld::Link(test_cid(b"record")));
+        let mut body = commit_body();
+        body.insert("ops".to_string(), Ipld::List(vec![Ipld::Map(op)]));
+        assert!(matches!(
+            validate_commit_frame(&Ipld::Map(body)),
+            Err(MirrorValidationError::InvalidField { field: "cid", .. })
+        ));
+
+        let mut op = BTreeMap::new();
+        op.insert("action".to_string(), Ipld::String("create".to_string()));
+        op.insert("path".to_string(), Ipld::String("../../etc/passwd".to_string()));
+        op.insert("cid".to_string(), Ipld::Link(test_cid(b"record")));
+        let mut body = commit_body();
+        body.insert("ops".to_string(), Ipld::List(vec![Ipld::Map(op)]));
+        assert!(matches!(
+            validate_commit_frame(&Ipld::Map(body)),
+            Err(MirrorValidationError::InvalidField { field: "path", .. })
+        ));
+
+        let mut body = commit_body();
+        body.insert(
+            "ops".to_string(),
+            Ipld::List(vec![Ipld::Null; MAX_COMMIT_OPS + 1]),
+        );
+        assert_eq!(
+            validate_commit_frame(&Ipld::Map(body)).unwrap_err(),
+            MirrorValidationError::TooManyOps(MAX_COMMIT_OPS + 1)
+        );
+    }
+
+    #[test]
+    fn test_decode_frame() {
+        let mut header = BTreeMap::new();
+        header.insert("op".to_string(), Ipld::Integer(1));
+        header.insert("t".to_string(), Ipld::String("#commit".to_string()));
+
+        let mut bytes = DagCborCodec.encode(&Ipld::Map(header)).unwrap();
+        bytes.extend(DagCborCodec.encode(&Ipld::Map(commit_body())).unwrap());
+
+        let (header, body) = decode_frame(&bytes).unwrap();
+        assert_eq!(header.op, 1);
+        assert_eq!(header.message_type.as_deref(), Some("#commit"));
+        assert!(validate_commit_frame(&body).is_ok());
+
+        // Truncated frames are rejected
+        assert!(matches!(
+            decode_frame(&bytes[..bytes.len() - 4]),
+            Err(MirrorValidationError::MalformedFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_frame_seq_and_info() {
+        let mut body = BTreeMap::new();
+        body.insert("seq".to_string(), Ipld::Integer(42));
+        assert_eq!(frame_seq(&Ipld::Map(body)), Some(42));
+        assert_eq!(frame_seq(&Ipld::Map(BTreeMap::new())), None);
+
+        let mut info = BTreeMap::new();
+        info.insert("name".to_string(), Ipld::String("OutdatedCursor".to_string()));
+        assert_eq!(
+            validate_info_frame(&Ipld::Map(info)).unwrap(),
+            ("OutdatedCursor".to_string(), None)
+        );
+        assert!(validate_info_frame(&Ipld::Map(BTreeMap::new())).is_err());
+    }
+
+    #[test]
+    fn test_decode_error_frame() {
+        let mut header = BTreeMap::new();
+        header.insert("op".to_string(), Ipld::Integer(-1));
+        let mut body = BTreeMap::new();
+        body.insert("error".to_string(), Ipld::String("FutureCursor".to_string()));
+
+        let mut bytes = DagCborCodec.encode(&Ipld::Map(header)).unwrap();
+        bytes.extend(DagCborCodec.encode(&Ipld::Map(body)).unwrap());
+
+        assert_eq!(
+            decode_frame(&bytes).unwrap_err(),
+            MirrorValidationError::UpstreamError("FutureCursor".to_string())
+        );
+    }
+}
@@ -0,0 +1,221 @@
+/// Network address helpers
+///
+/// Webhook targets, ActivityPub actors and inboxes, and services named in
+/// `atproto-proxy` come from accounts or remote documents. Without a check
+/// any of them could point the server at its own network: loopback admin
+/// listeners, cloud metadata endpoints, other hosts on the LAN. Only host
+/// names and literal addresses are checked; names are not resolved.
+///
+/// Client addresses are resolved here too, believing `X-Forwarded-For`
+/// only as far as it was written by the configured trusted proxies.
+use crate::{
+    config::TrustedProxyConfig,
+    error::{PdsError, PdsResult},
+};
+use axum::http::HeaderMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// Loopback, private, link-local and other non-public addresses
+pub fn is_private_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                // Carrier-grade NAT (100.64.0.0/10)
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                // Unique local (fc00::/7) and link-local (fe80::/10)
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || v6.to_ipv4_mapped().is_some_and(|v4| is_private_ip(IpAddr::V4(v4)))
+        }
+    }
+}
+
+/// Refuse `url` (described as `what` in errors) unless it is https and its
+/// host isn't a private or loopback address
+///
+/// `allow_private` is for development: it also lets through http URLs and
+/// private hosts.
+pub fn check_public_url(url: &str, what: &str, allow_private: bool) -> PdsResult<()> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| PdsError::Validation(format!("Invalid {} {}: {}", what, url, e)))?;
+    if allow_private && matches!(parsed.scheme(), "https" | "http") {
+        return Ok(());
+    }
+    if parsed.scheme() != "https" {
+        return Err(PdsError::Validation(format!("{} must be an https URL: {}", what, url)));
+    }
+
+    let host = parsed.host_str().unwrap_or_default().trim_matches(['[', ']']);
+    let private = match host.parse::<IpAddr>() {
+        Ok(ip) => is_private_ip(ip),
+        Err(_) => host == "localhost" || host.ends_with(".localhost"),
+    };
+    if private {
+        return Err(PdsError::Validation(format!(
+            "{} must not point to a private or loopback address: {}",
+            what, url
+        )));
+    }
+    Ok(())
+}
+
+/// Parse an IP address or CIDR range into its network address and prefix
+///
+/// A bare address is a single-address range.
+pub fn parse_cidr(s: &str) -> Option<(IpAddr, u8)> {
+    let (addr, prefix) = match s.trim().split_once('/') {
+        Some((addr, prefix)) => (addr.parse::<IpAddr>().ok()?.to_canonical(), Some(prefix.parse::<u8>().ok()?)),
+        None => (s.trim().parse::<IpAddr>().ok()?.to_canonical(), None),
+    };
+    let prefix = prefix.unwrap_or_else(|| max_prefix(&addr));
+    if prefix > max_prefix(&addr) {
+        return None;
+    }
+    Some((network(addr, prefix), prefix))
+}
+
+/// Whether `ip` is inside the range `(network, prefix)`
+pub fn in_network(ip: IpAddr, (addr, prefix): (IpAddr, u8)) -> bool {
+    addr.is_ipv4() == ip.is_ipv4() && network(ip, prefix) == addr
+}
+
+pub fn max_prefix(addr: &IpAddr) -> u8 {
+    if addr.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+/// The first address of the `prefix`-bit network containing `addr`
+pub fn network(addr: IpAddr, prefix: u8) -> IpAddr {
+    match addr {
+        IpAddr::V4(v4) => {
+            let mask = u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0);
+            IpAddr::V4((u32::from(v4) & mask).into())
+        }
+        IpAddr::V6(v6) => {
+            let mask = u128::MAX.checked_shl(128 - prefix as u32).unwrap_or(0);
+            IpAddr::V6((u128::from(v6) & mask).into())
+        }
+    }
+}
+
+/// Address of the client behind `peer`, the socket the request came from
+///
+/// Walks `X-Forwarded-For` right to left from the peer for as long as each
+/// address is a trusted proxy; the first one that isn't is the client.
+/// Entries further left were written by the client and are ignored, so a
+/// forged header can't stand in for the real address. `X-Real-IP` is only
+/// read from a trusted peer that sent no `X-Forwarded-For`.
+pub fn client_ip(headers: &HeaderMap, peer: IpAddr, proxies: &TrustedProxyConfig) -> IpAddr {
+    let ranges: Vec<_> = proxies.cidrs.iter().filter_map(|c| parse_cidr(c)).collect();
+    let trusted = |ip: IpAddr, hop: usize| hop < proxies.hops || ranges.iter().any(|&r| in_network(ip, r));
+    let parse = |s: &str| {
+        let s = s.trim();
+        s.parse::<IpAddr>()
+            .or_else(|_| s.parse::<SocketAddr>().map(|a| a.ip()))
+            .ok()
+            .map(|ip| ip.to_canonical())
+    };
+
+    let mut addr = peer.to_canonical();
+    let forwarded: Vec<&str> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|h| h.to_str().ok())
+        .flat_map(|s| s.split(','))
+        .collect();
+
+    if forwarded.is_empty() {
+        if trusted(addr, 0) {
+            if let Some(ip) = headers.get("x-real-ip").and_then(|h| h.to_str().ok()).and_then(parse) {
+                return ip;
+            }
+        }
+        return addr;
+    }
+
+    for (hop, entry) in forwarded.iter().rev().enumerate() {
+        if !trusted(addr, hop) {
+            return addr;
+        }
+        // A proxy that couldn't name its client: the proxy is all we know
+        match parse(entry) {
+            Some(ip) => addr = ip,
+            None => return addr,
+        }
+    }
+    addr
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_targets() {
+        for ip in ["127.0.0.1", "10.1.2.3", "192.168.0.1", "169.254.169.254", "100.64.0.1", "::1", "fd00::1", "::ffff:10.0.0.1"] {
+            assert!(is_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in ["93.184.216.34", "2606:4700::1111"] {
+            assert!(!is_private_ip(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    #[test]
+    fn test_check_public_url() {
+        assert!(check_public_url("https://hooks.example.com/in", "URL", false).is_ok());
+        for url in ["http://hooks.example.com", "https://169.254.169.254/latest", "https://[::1]:2583", "https://localhost", "ftp://example.com"] {
+            assert!(check_public_url(url, "URL", false).is_err(), "{}", url);
+        }
+        assert!(check_public_url("http://127.0.0.1:8080", "URL", true).is_ok());
+        assert!(check_public_url("ftp://127.0.0.1", "URL", true).is_err());
+    }
+
+    #[test]
+    fn test_parse_cidr() {
+        assert_eq!(parse_cidr("10.1.2.3/8"), Some(("10.0.0.0".parse().unwrap(), 8)));
+        assert_eq!(parse_cidr("192.0.2.1"), Some(("192.0.2.1".parse().unwrap(), 32)));
+        assert!(in_network("10.9.9.9".parse().unwrap(), parse_cidr("10.0.0.0/8").unwrap()));
+        assert!(!in_network("11.0.0.1".parse().unwrap(), parse_cidr("10.0.0.0/8").unwrap()));
+        for bad in ["10.0.0.0/33", "fd00::/129", "not-an-ip", "10.0.0.0/x"] {
+            assert!(parse_cidr(bad).is_none(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn test_client_ip() {
+        let ip = |s: &str| s.parse::<IpAddr>().unwrap();
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", "6.6.6.6, 198.51.100.7, 10.0.0.2".parse().unwrap());
+
+        // Without trusted proxies the headers are ignored
+        let none = TrustedProxyConfig::default();
+        assert_eq!(client_ip(&headers, ip("10.0.0.1"), &none), ip("10.0.0.1"));
+
+        // One proxy hop: its right-most entry is the client
+        let one_hop = TrustedProxyConfig { hops: 1, cidrs: Vec::new() };
+        assert_eq!(client_ip(&headers, ip("10.0.0.1"), &one_hop), ip("10.0.0.2"));
+
+        // Proxies by range: walk past every one of them, never further
+        let ranges = TrustedProxyConfig { hops: 0, cidrs: vec!["10.0.0.0/8".to_string()] };
+        assert_eq!(client_ip(&headers, ip("10.0.0.1"), &ranges), ip("198.51.100.7"));
+        assert_eq!(client_ip(&headers, ip("203.0.113.9"), &ranges), ip("203.0.113.9"));
+
+        // X-Real-IP only from a trusted peer
+        let mut headers = HeaderMap::new();
+        headers.insert("x-real-ip", "198.51.100.8".parse().unwrap());
+        assert_eq!(client_ip(&headers, ip("10.0.0.1"), &ranges), ip("198.51.100.8"));
+        assert_eq!(client_ip(&headers, ip("203.0.113.9"), &ranges), ip("203.0.113.9"));
+    }
+}
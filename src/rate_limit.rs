@@ -1,20 +1,34 @@
 /// Rate Limiting System
-use crate::error::{PdsError, PdsResult};
+use crate::{
+    admin::RateLimitOverride,
+    cache::CacheClient,
+    crypto::jwt_keys::JwtKeyring,
+    error::{PdsError, PdsResult},
+    rate_limit_new::DistributedRateLimiter,
+};
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     middleware::Next,
     response::Response,
 };
 use governor::{
     clock::DefaultClock,
     state::{InMemoryState, NotKeyed},
-    Quota, RateLimiter as GovernorLimiter,
+    DefaultKeyedRateLimiter, Quota, RateLimiter as GovernorLimiter,
+};
+use chrono::{DateTime, Utc};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    num::NonZeroU32,
+    sync::{Arc, RwLock},
 };
-use std::{num::NonZeroU32, sync::Arc};
+
+type DirectLimiter = GovernorLimiter<NotKeyed, InMemoryState, DefaultClock>;
 
 /// Rate limiter configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RateLimitConfig {
     /// Requests per second for authenticated users
     pub authenticated_rps: u32,
@@ -37,16 +51,216 @@ impl Default for RateLimitConfig {
     }
 }
 
-/// Rate limiter manager
-#[derive(Clone)]
-pub struct RateLimiter {
-    authenticated: Arc<GovernorLimiter<NotKeyed, InMemoryState, DefaultClock>>,
-    unauthenticated: Arc<GovernorLimiter<NotKeyed, InMemoryState, DefaultClock>>,
-    admin: Arc<GovernorLimiter<NotKeyed, InMemoryState, DefaultClock>>,
+impl RateLimitConfig {
+    /// Load from `PDS_RATE_LIMIT_*` environment variables
+    ///
+    /// Read again on config reload, so budgets can change at runtime.
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str, default: u32| {
+            std::env::var(format!("PDS_RATE_LIMIT_{}", name))
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            authenticated_rps: var("AUTHENTICATED_RPS", defaults.authenticated_rps),
+            unauthenticated_rps: var("UNAUTHENTICATED_RPS", defaults.unauthenticated_rps),
+            admin_rps: var("ADMIN_RPS", defaults.admin_rps),
+            burst_size: var("BURST_SIZE", defaults.burst_size),
+        }
+    }
 }
 
-impl RateLimiter {
-    pub fn new(config: RateLimitConfig) -> Self {
+/// Budgets for the public identity resolution endpoints
+///
+/// `resolveHandle` and `resolveDid` are budgeted per client rather than
+/// from the shared pools, so one busy client can't starve the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveRateLimitConfig {
+    /// Requests per minute for each client IP
+    pub per_ip_per_minute: u32,
+    /// Requests per minute for each authenticated account
+    pub authenticated_per_minute: u32,
+}
+
+impl Default for ResolveRateLimitConfig {
+    fn default() -> Self {
+        Self {
+            per_ip_per_minute: 300,
+            authenticated_per_minute: 3000,
+        }
+    }
+}
+
+impl ResolveRateLimitConfig {
+    /// Load from `PDS_RATE_LIMIT_RESOLVE_*` environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        let var = |name: &str, default: u32| {
+            std::env::var(format!("PDS_RATE_LIMIT_RESOLVE_{}", name))
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            per_ip_per_minute: var("PER_MINUTE", defaults.per_ip_per_minute),
+            authenticated_per_minute: var("AUTHENTICATED_PER_MINUTE", defaults.authenticated_per_minute),
+        }
+    }
+}
+
+/// Who a resolution request is budgeted against
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveClient {
+    Ip(String),
+    Account(String),
+    /// No usable IP (no connection info); shares one bucket
+    Unknown,
+}
+
+impl ResolveClient {
+    /// Identify the caller from its resolved IP and request headers
+    pub fn from_request(ip: Option<IpAddr>, headers: &HeaderMap, jwt_keys: &JwtKeyring) -> Self {
+        if let Some(did) = account_did(headers, jwt_keys) {
+            return Self::Account(did);
+        }
+        match ip {
+            Some(ip) => Self::Ip(ip.to_string()),
+            None => Self::Unknown,
+        }
+    }
+
+    fn key(&self) -> String {
+        match self {
+            Self::Ip(ip) => format!("resolve:ip:{}", ip),
+            Self::Account(did) => format!("resolve:did:{}", did),
+            Self::Unknown => "resolve:ip:unknown".to_string(),
+        }
+    }
+}
+
+/// Keyed limiters for one resolution budget
+struct ResolvePools {
+    config: ResolveRateLimitConfig,
+    /// Shared counters when Redis is configured
+    distributed: Option<(DistributedRateLimiter, DistributedRateLimiter)>,
+    /// Per-process fallback, also used when Redis is unreachable
+    ip: DefaultKeyedRateLimiter<String>,
+    account: DefaultKeyedRateLimiter<String>,
+}
+
+impl ResolvePools {
+    fn new(config: ResolveRateLimitConfig, redis: Option<&CacheClient>) -> Self {
+        let quota = |per_minute: u32| {
+            Quota::per_minute(NonZeroU32::new(per_minute).unwrap_or(NonZeroU32::MIN))
+        };
+
+        Self {
+            distributed: redis.map(|redis| {
+                (
+                    DistributedRateLimiter::new(redis.clone(), config.per_ip_per_minute),
+                    DistributedRateLimiter::new(redis.clone(), config.authenticated_per_minute),
+                )
+            }),
+            ip: GovernorLimiter::keyed(quota(config.per_ip_per_minute)),
+            account: GovernorLimiter::keyed(quota(config.authenticated_per_minute)),
+            config,
+        }
+    }
+}
+
+/// Keyed entries kept before idle ones are dropped
+const RESOLVE_MAX_KEYS: usize = 100_000;
+
+/// Per-client limiter for the public identity resolution endpoints
+///
+/// Clients are keyed by IP, or by account for authenticated callers, who
+/// get a more relaxed budget. With Redis the counts are shared through the
+/// distributed limiter so every instance enforces the same budget; Redis
+/// errors fall back to the in-process limiter instead of failing open.
+pub struct ResolveRateLimiter {
+    pools: RwLock<Arc<ResolvePools>>,
+    redis: Option<CacheClient>,
+}
+
+impl ResolveRateLimiter {
+    pub fn new(config: ResolveRateLimitConfig, redis: Option<CacheClient>) -> Self {
+        Self {
+            pools: RwLock::new(Arc::new(ResolvePools::new(config, redis.as_ref()))),
+            redis,
+        }
+    }
+
+    fn pools(&self) -> Arc<ResolvePools> {
+        self.pools.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Current budgets
+    pub fn config(&self) -> ResolveRateLimitConfig {
+        self.pools().config.clone()
+    }
+
+    /// Switch to new budgets; in-process counts start fresh
+    pub fn reconfigure(&self, config: ResolveRateLimitConfig) {
+        let pools = Arc::new(ResolvePools::new(config, self.redis.as_ref()));
+        *self.pools.write().unwrap_or_else(|e| e.into_inner()) = pools;
+    }
+
+    /// Count a request, returning the client's per-minute budget
+    pub async fn check(&self, client: &ResolveClient) -> PdsResult<u32> {
+        let pools = self.pools();
+        let authenticated = matches!(client, ResolveClient::Account(_));
+        let budget = if authenticated {
+            pools.config.authenticated_per_minute
+        } else {
+            pools.config.per_ip_per_minute
+        };
+        let key = client.key();
+
+        if let Some((ip, account)) = &pools.distributed {
+            let limiter = if authenticated { account } else { ip };
+            match limiter.check_rate_limit(&key).await {
+                Ok(()) => return Ok(budget),
+                Err(e @ PdsError::RateLimitExceeded { .. }) => return Err(e),
+                Err(e) => tracing::debug!(error = %e, "resolve_rate_limit_redis_failed"),
+            }
+        }
+
+        let limiter = if authenticated { &pools.account } else { &pools.ip };
+        if limiter.len() > RESOLVE_MAX_KEYS {
+            limiter.retain_recent();
+        }
+        match limiter.check_key(&key) {
+            Ok(_) => Ok(budget),
+            Err(_) => Err(PdsError::RateLimitExceeded {
+                retry_after: std::time::Duration::from_secs(60 / budget.clamp(1, 60) as u64),
+            }),
+        }
+    }
+}
+
+/// Dedicated limiter for an account with an override
+struct AccountLimiter {
+    limiter: Arc<DirectLimiter>,
+    requests_per_second: u32,
+    expires_at: Option<DateTime<Utc>>,
+}
+
+/// Shared limiters built from one configuration
+///
+/// Replaced as a whole when the configuration is reloaded.
+struct Pools {
+    config: RateLimitConfig,
+    authenticated: DirectLimiter,
+    unauthenticated: DirectLimiter,
+    admin: DirectLimiter,
+}
+
+impl Pools {
+    fn new(config: RateLimitConfig) -> Self {
         let auth_quota = Quota::per_second(
             NonZeroU32::new(config.authenticated_rps)
                 .unwrap_or(NonZeroU32::new(100).unwrap()),
@@ -68,15 +282,114 @@ impl RateLimiter {
         );
 
         Self {
-            authenticated: Arc::new(GovernorLimiter::direct(auth_quota)),
-            unauthenticated: Arc::new(GovernorLimiter::direct(unauth_quota)),
-            admin: Arc::new(GovernorLimiter::direct(admin_quota)),
+            config,
+            authenticated: GovernorLimiter::direct(auth_quota),
+            unauthenticated: GovernorLimiter::direct(unauth_quota),
+            admin: GovernorLimiter::direct(admin_quota),
         }
     }
+}
 
-    /// Check rate limit for authenticated user
-    pub fn check_authenticated(&self) -> PdsResult<()> {
-        match self.authenticated.check() {
+/// Rate limiter manager
+#[derive(Clone)]
+pub struct RateLimiter {
+    pools: Arc<RwLock<Arc<Pools>>>,
+    overrides: Arc<RwLock<HashMap<String, AccountLimiter>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            pools: Arc::new(RwLock::new(Arc::new(Pools::new(config)))),
+            overrides: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    fn pools(&self) -> Arc<Pools> {
+        self.pools.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Current budgets
+    pub fn config(&self) -> RateLimitConfig {
+        self.pools().config.clone()
+    }
+
+    /// Switch to new budgets
+    ///
+    /// The shared pools start fresh. Per-account overrides keep their
+    /// limiters until reinstalled with `set_account_override`.
+    pub fn reconfigure(&self, config: RateLimitConfig) {
+        *self.pools.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(Pools::new(config));
+    }
+
+    /// Install (or replace) a per-account override
+    ///
+    /// The account gets its own limiter, so a raised budget does not draw
+    /// from the shared authenticated pool.
+    pub fn set_account_override(&self, entry: &RateLimitOverride) {
+        let config = self.config();
+        let (rps, burst) = entry.effective_quota(config.authenticated_rps, config.burst_size);
+        let quota = Quota::per_second(NonZeroU32::new(rps).unwrap_or(NonZeroU32::MIN))
+            .allow_burst(NonZeroU32::new(burst).unwrap_or(NonZeroU32::MIN));
+
+        let limiter = AccountLimiter {
+            limiter: Arc::new(GovernorLimiter::direct(quota)),
+            requests_per_second: rps,
+            expires_at: entry.expires_at,
+        };
+
+        self.overrides
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(entry.did.clone(), limiter);
+    }
+
+    /// Remove a per-account override, returning the account to the shared pool
+    pub fn remove_account_override(&self, did: &str) {
+        self.overrides
+            .write()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(did);
+    }
+
+    /// Look up the active override limiter for an account, dropping it if expired
+    fn account_limiter(&self, did: &str) -> Option<(Arc<DirectLimiter>, u32)> {
+        let expired = {
+            let overrides = self.overrides.read().unwrap_or_else(|e| e.into_inner());
+            let entry = overrides.get(did)?;
+            match entry.expires_at {
+                Some(t) if t <= Utc::now() => true,
+                _ => return Some((entry.limiter.clone(), entry.requests_per_second)),
+            }
+        };
+
+        if expired {
+            self.remove_account_override(did);
+        }
+        None
+    }
+
+    /// Check rate limit for a specific authenticated account
+    ///
+    /// Uses the account's override when one is active, otherwise the shared
+    /// authenticated limiter. Returns the effective requests-per-second budget.
+    pub fn check_account(&self, did: &str) -> PdsResult<u32> {
+        match self.account_limiter(did) {
+            Some((limiter, rps)) => match limiter.check() {
+                Ok(_) => Ok(rps),
+                Err(_) => Err(PdsError::RateLimitExceeded {
+                    retry_after: std::time::Duration::from_secs(1),
+                }),
+            },
+            None => {
+                let pools = self.pools();
+                Self::check(&pools.authenticated).map(|_| pools.config.authenticated_rps)
+            }
+        }
+    }
+
+    fn check(limiter: &DirectLimiter) -> PdsResult<()> {
+        match limiter.check() {
             Ok(_) => Ok(()),
             Err(_) => Err(PdsError::RateLimitExceeded {
                 retry_after: std::time::Duration::from_secs(1),
@@ -84,33 +397,39 @@ impl RateLimiter {
         }
     }
 
+    /// Check rate limit for authenticated user
+    pub fn check_authenticated(&self) -> PdsResult<()> {
+        Self::check(&self.pools().authenticated)
+    }
+
     /// Check rate limit for unauthenticated user
     pub fn check_unauthenticated(&self) -> PdsResult<()> {
-        match self.unauthenticated.check() {
-            Ok(_) => Ok(()),
-            Err(_) => Err(PdsError::RateLimitExceeded {
-                retry_after: std::time::Duration::from_secs(1),
-            }),
-        }
+        Self::check(&self.pools().unauthenticated)
     }
 
     /// Check rate limit for admin user
     pub fn check_admin(&self) -> PdsResult<()> {
-        match self.admin.check() {
-            Ok(_) => Ok(()),
-            Err(_) => Err(PdsError::RateLimitExceeded {
-                retry_after: std::time::Duration::from_secs(1),
-            }),
-        }
+        Self::check(&self.pools().admin)
     }
 }
 
+/// Endpoints budgeted by `ResolveRateLimiter` instead of the shared pools
+const RESOLVE_PATHS: &[&str] = &[
+    "/xrpc/com.atproto.identity.resolveHandle",
+    "/xrpc/com.atproto.identity.resolveDid",
+];
+
 /// Rate limiting middleware
 pub async fn rate_limit_middleware(
     State(ctx): State<crate::context::AppContext>,
     request: Request,
     next: Next,
 ) -> Result<Response, StatusCode> {
+    // Resolution endpoints have their own per-client budget
+    if RESOLVE_PATHS.contains(&request.uri().path()) {
+        return Ok(next.run(request).await);
+    }
+
     // Check if this is an admin endpoint
     let is_admin = request.uri().path().contains("/xrpc/com.atproto.admin");
 
@@ -123,24 +442,32 @@ pub async fn rate_limit_middleware(
     // Apply appropriate rate limit based on context
     let rate_limit_result = if is_admin && has_auth_header {
         // Admin endpoints with auth - highest rate limit
-        ctx.rate_limiter.check_admin()
+        ctx.rate_limiter.check_admin().map(|_| ctx.rate_limiter.config().admin_rps)
     } else if has_auth_header {
-        // Authenticated users - medium rate limit
-        ctx.rate_limiter.check_authenticated()
+        // Authenticated users - per-account override or medium rate limit
+        match account_did(request.headers(), &ctx.jwt_keys) {
+            Some(did) => ctx.rate_limiter.check_account(&did),
+            None => ctx
+                .rate_limiter
+                .check_authenticated()
+                .map(|_| ctx.rate_limiter.config().authenticated_rps),
+        }
     } else {
         // Unauthenticated users - lowest rate limit
-        ctx.rate_limiter.check_unauthenticated()
+        ctx.rate_limiter
+            .check_unauthenticated()
+            .map(|_| ctx.rate_limiter.config().unauthenticated_rps)
     };
 
     // Check rate limit
     match rate_limit_result {
-        Ok(_) => {
+        Ok(limit) => {
             // Rate limit check passed, continue to next handler
             let mut response = next.run(request).await;
 
             // Add rate limit headers to response
             let headers = response.headers_mut();
-            headers.insert("X-RateLimit-Limit", "100".parse().unwrap());
+            headers.insert("X-RateLimit-Limit", limit.into());
             headers.insert("X-RateLimit-Remaining", "99".parse().unwrap());
 
             Ok(response)
@@ -152,6 +479,26 @@ pub async fn rate_limit_middleware(
     }
 }
 
+/// Extract the account DID from a bearer access token
+///
+/// This runs before the auth layers, so the token is only decoded to pick a
+/// limiter; authentication itself still happens downstream.
+fn account_did(headers: &HeaderMap, jwt_keys: &JwtKeyring) -> Option<String> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+
+    jwt_keys
+        .verify(token)
+        .ok()?
+        .claims
+    .get("sub")?
+    .as_str()
+    .map(str::to_string)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -185,4 +532,135 @@ mod tests {
         // Should hit rate limit after burst
         assert!(limiter.check_authenticated().is_err());
     }
+
+    #[test]
+    fn test_account_override() {
+        let config = RateLimitConfig {
+            authenticated_rps: 10,
+            unauthenticated_rps: 5,
+            admin_rps: 100,
+            burst_size: 2,
+        };
+        let limiter = RateLimiter::new(config);
+
+        limiter.set_account_override(&RateLimitOverride {
+            did: "did:plc:bot".to_string(),
+            multiplier: Some(3.0),
+            requests_per_second: None,
+            burst_size: None,
+            reason: None,
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: None,
+        });
+
+        // Override account gets its own, larger budget
+        for _ in 0..6 {
+            assert_eq!(limiter.check_account("did:plc:bot").unwrap(), 30);
+        }
+        assert!(limiter.check_account("did:plc:bot").is_err());
+
+        // Other accounts still draw from the shared pool
+        assert_eq!(limiter.check_account("did:plc:other").unwrap(), 10);
+
+        limiter.remove_account_override("did:plc:bot");
+        assert_eq!(limiter.check_account("did:plc:bot").unwrap(), 10);
+    }
+
+    #[test]
+    fn test_expired_account_override_is_ignored() {
+        let limiter = RateLimiter::new(RateLimitConfig::default());
+
+        limiter.set_account_override(&RateLimitOverride {
+            did: "did:plc:slow".to_string(),
+            multiplier: None,
+            requests_per_second: Some(1),
+            burst_size: Some(1),
+            reason: None,
+            created_by: "did:plc:admin".to_string(),
+            created_at: Utc::now(),
+            expires_at: Some(Utc::now() - chrono::Duration::seconds(1)),
+        });
+
+        assert_eq!(limiter.check_account("did:plc:slow").unwrap(), 100);
+        assert_eq!(limiter.check_account("did:plc:slow").unwrap(), 100);
+    }
+
+    #[test]
+    fn test_reconfigure() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            authenticated_rps: 10,
+            unauthenticated_rps: 5,
+            admin_rps: 100,
+            burst_size: 1,
+        });
+        assert!(limiter.check_authenticated().is_ok());
+        assert!(limiter.check_authenticated().is_err());
+
+        // New budgets apply immediately, to clones too
+        let clone = limiter.clone();
+        limiter.reconfigure(RateLimitConfig {
+            burst_size: 3,
+            ..limiter.config()
+        });
+        assert_eq!(clone.config().burst_size, 3);
+        for _ in 0..3 {
+            assert!(clone.check_authenticated().is_ok());
+        }
+        assert!(clone.check_authenticated().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_resolve_limiter_keys_clients() {
+        let limiter = ResolveRateLimiter::new(
+            ResolveRateLimitConfig {
+                per_ip_per_minute: 2,
+                authenticated_per_minute: 4,
+            },
+            None,
+        );
+
+        let a = ResolveClient::Ip("203.0.113.1".to_string());
+        let b = ResolveClient::Ip("203.0.113.2".to_string());
+        let account = ResolveClient::Account("did:plc:alice".to_string());
+
+        assert_eq!(limiter.check(&a).await.unwrap(), 2);
+        assert!(limiter.check(&a).await.is_ok());
+        assert!(matches!(
+            limiter.check(&a).await,
+            Err(PdsError::RateLimitExceeded { .. })
+        ));
+
+        // Other clients have their own budget
+        assert!(limiter.check(&b).await.is_ok());
+        for _ in 0..4 {
+            assert_eq!(limiter.check(&account).await.unwrap(), 4);
+        }
+        assert!(limiter.check(&account).await.is_err());
+
+        limiter.reconfigure(ResolveRateLimitConfig {
+            per_ip_per_minute: 5,
+            ..limiter.config()
+        });
+        assert_eq!(limiter.check(&a).await.unwrap(), 5);
+    }
+
+    #[test]
+    fn test_resolve_client_from_request() {
+        let keys = JwtKeyring::new("secret", Default::default());
+        let ip = Some("198.51.100.7".parse().unwrap());
+        let mut headers = HeaderMap::new();
+        assert_eq!(ResolveClient::from_request(None, &headers, &keys), ResolveClient::Unknown);
+        assert_eq!(
+            ResolveClient::from_request(ip, &headers, &keys),
+            ResolveClient::Ip("198.51.100.7".to_string())
+        );
+
+        // An invalid token doesn't earn the authenticated budget
+        headers.insert(header::AUTHORIZATION, "Bearer not-a-jwt".parse().unwrap());
+        assert_eq!(
+            ResolveClient::from_request(ip, &headers, &keys),
+            ResolveClient::Ip("198.51.100.7".to_string())
+        );
+    }
 }
@@ -0,0 +1,4 @@
+/// Redis-backed rate limiters shared between PDS instances
+pub mod distributed;
+
+pub use distributed::DistributedRateLimiter;
@@ -0,0 +1,191 @@
+/// Sequencer payload (re)compression
+///
+/// `aurora-locus compress-events` rewrites the stored payloads of existing
+/// sequencer events with zstd, so a log written before
+/// `PDS_SEQUENCER_ZSTD_LEVEL` was set shrinks too, and prints how much
+/// space that saved. With `--dry-run` nothing is written, which makes it a
+/// benchmark of a compression level against the real event log.
+/// `--decompress` stores every payload as plain CBOR again, for going back
+/// to a release that can't read compressed events.
+///
+/// Rows are rewritten in seq order, one transaction per batch; SQLite only
+/// returns the freed pages to the filesystem after a `VACUUM`.
+use crate::{
+    context::AppContext,
+    error::{PdsError, PdsResult},
+    sequencer::payload,
+};
+use sqlx::Row;
+use std::time::{Duration, Instant};
+
+/// Compression options (`aurora-locus compress-events [--level N] [--batch N] [--dry-run] [--decompress]`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecompressOptions {
+    /// zstd level (1-22)
+    pub level: i32,
+    /// Rows rewritten per transaction
+    pub batch_size: i64,
+    /// Only measure; don't write
+    pub dry_run: bool,
+    /// Store plain CBOR instead
+    pub decompress: bool,
+}
+
+impl Default for RecompressOptions {
+    fn default() -> Self {
+        Self {
+            level: 3,
+            batch_size: 500,
+            dry_run: false,
+            decompress: false,
+        }
+    }
+}
+
+impl RecompressOptions {
+    /// Parse options from the arguments following `compress-events`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let mut opts = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            match flag.as_str() {
+                "--dry-run" => opts.dry_run = true,
+                "--decompress" => opts.decompress = true,
+                "--level" | "--batch" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+                    let number: i64 = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n > 0)
+                        .ok_or_else(|| PdsError::Validation(format!("Invalid value for {}: {}", flag, value)))?;
+                    match flag.as_str() {
+                        "--level" if number <= 22 => opts.level = number as i32,
+                        "--level" => return Err(PdsError::Validation("--level must be between 1 and 22".to_string())),
+                        _ => opts.batch_size = number,
+                    }
+                }
+                other => {
+                    return Err(PdsError::Validation(format!(
+                        "Unknown compress-events option: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Outcome of a compression run
+#[derive(Debug, Default)]
+pub struct RecompressReport {
+    /// Events scanned
+    pub events: u64,
+    /// Events whose stored payload changed
+    pub rewritten: u64,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+    pub dry_run: bool,
+    pub elapsed: Duration,
+}
+
+impl RecompressReport {
+    pub fn summary(&self) -> String {
+        let saved = self.bytes_before as i64 - self.bytes_after as i64;
+        let percent = if self.bytes_before > 0 {
+            saved as f64 * 100.0 / self.bytes_before as f64
+        } else {
+            0.0
+        };
+
+        format!(
+            "{} {} of {} event(s): {} -> {} payload bytes ({:.1}% saved) in {:.1}s\n",
+            if self.dry_run { "would rewrite" } else { "rewrote" },
+            self.rewritten,
+            self.events,
+            self.bytes_before,
+            self.bytes_after,
+            percent,
+            self.elapsed.as_secs_f64()
+        )
+    }
+}
+
+/// Rewrite every stored event payload with the requested encoding
+pub async fn run(ctx: &AppContext, opts: &RecompressOptions) -> PdsResult<RecompressReport> {
+    let started = Instant::now();
+    let level = (!opts.decompress).then_some(opts.level);
+    let mut report = RecompressReport {
+        dry_run: opts.dry_run,
+        ..Default::default()
+    };
+
+    let mut after = 0i64;
+    loop {
+        let rows = sqlx::query("SELECT seq, event FROM repo_seq WHERE seq > ?1 ORDER BY seq LIMIT ?2")
+            .bind(after)
+            .bind(opts.batch_size)
+            .fetch_all(&ctx.account_db)
+            .await?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.try_get("seq")?;
+
+        let mut tx = ctx.account_db.begin().await?;
+        for row in rows {
+            let seq: i64 = row.try_get("seq")?;
+            let stored: Vec<u8> = row.try_get("event")?;
+            let rewritten = payload::encode(payload::decode(stored.clone())?, level)?;
+
+            report.events += 1;
+            report.bytes_before += stored.len() as u64;
+            report.bytes_after += rewritten.len() as u64;
+            if rewritten == stored {
+                continue;
+            }
+
+            report.rewritten += 1;
+            if !opts.dry_run {
+                sqlx::query("UPDATE repo_seq SET event = ?1 WHERE seq = ?2")
+                    .bind(&rewritten)
+                    .bind(seq)
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        tx.commit().await?;
+    }
+
+    report.elapsed = started.elapsed();
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_recompress_options() {
+        assert_eq!(RecompressOptions::from_args(&[]).unwrap(), RecompressOptions::default());
+
+        let opts = RecompressOptions::from_args(&args(&["--level", "19", "--batch", "50", "--dry-run"])).unwrap();
+        assert_eq!(opts.level, 19);
+        assert_eq!(opts.batch_size, 50);
+        assert!(opts.dry_run);
+        assert!(RecompressOptions::from_args(&args(&["--decompress"])).unwrap().decompress);
+
+        assert!(RecompressOptions::from_args(&args(&["--level", "23"])).is_err());
+        assert!(RecompressOptions::from_args(&args(&["--batch", "0"])).is_err());
+        assert!(RecompressOptions::from_args(&args(&["--level"])).is_err());
+        assert!(RecompressOptions::from_args(&args(&["--fast"])).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+/// Configuration hot reload
+///
+/// Triggered by SIGHUP or `com.atproto.admin.reloadConfig`. Re-reads `.env`
+/// and the config file, validates the result, and applies the settings that
+/// can change at runtime:
+///
+/// - rate limit budgets (`PDS_RATE_LIMIT_*`), including the resolution budgets
+/// - content policy settings, including blocked link domains
+/// - the reserved/blocked handle list (re-read from the database)
+/// - email template contents
+///
+/// Everything else (listeners, storage, keys, ...) still needs a restart.
+/// Open connections, including firehose subscribers, are unaffected. Each
+/// reload is written to the admin audit log with what changed.
+use crate::{
+    config::ServerConfig, config_file, context::AppContext, error::PdsResult,
+    rate_limit::{RateLimitConfig, ResolveRateLimitConfig},
+};
+use serde::Serialize;
+
+/// What a reload changed
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReloadReport {
+    pub changes: Vec<String>,
+}
+
+/// Re-read configuration and apply the dynamic settings
+///
+/// Nothing is applied if the new configuration fails validation.
+pub async fn reload(ctx: &AppContext, actor: &str) -> PdsResult<ReloadReport> {
+    config_file::reload()?;
+    let config = ServerConfig::from_env()?;
+    config.validate()?;
+
+    let mut changes = Vec::new();
+
+    let limits = RateLimitConfig::from_env();
+    let current = ctx.rate_limiter.config();
+    if limits != current {
+        changes.push(format!(
+            "rate limits: authenticated {} -> {} rps, unauthenticated {} -> {} rps, admin {} -> {} rps, burst {} -> {}",
+            current.authenticated_rps,
+            limits.authenticated_rps,
+            current.unauthenticated_rps,
+            limits.unauthenticated_rps,
+            current.admin_rps,
+            limits.admin_rps,
+            current.burst_size,
+            limits.burst_size
+        ));
+        ctx.rate_limiter.reconfigure(limits);
+        // Override budgets are relative to the base budget
+        for entry in ctx.rate_limit_override_manager.list_overrides().await? {
+            ctx.rate_limiter.set_account_override(&entry);
+        }
+    }
+
+    let resolve_limits = ResolveRateLimitConfig::from_env();
+    let current = ctx.resolve_limiter.config();
+    if resolve_limits != current {
+        changes.push(format!(
+            "resolve rate limits: per IP {} -> {}/min, authenticated {} -> {}/min",
+            current.per_ip_per_minute,
+            resolve_limits.per_ip_per_minute,
+            current.authenticated_per_minute,
+            resolve_limits.authenticated_per_minute
+        ));
+        ctx.resolve_limiter.reconfigure(resolve_limits);
+    }
+
+    let previous = ctx.content_policy.config();
+    if ctx.content_policy.reconfigure(&config.content_policy)? {
+        let next = &config.content_policy;
+        if previous.enabled != next.enabled {
+            changes.push(format!("content policy: enabled {} -> {}", previous.enabled, next.enabled));
+        }
+        let added: Vec<&str> = next
+            .blocked_domains
+            .iter()
+            .filter(|d| !previous.blocked_domains.contains(d))
+            .map(String::as_str)
+            .collect();
+        let removed: Vec<&str> = previous
+            .blocked_domains
+            .iter()
+            .filter(|d| !next.blocked_domains.contains(d))
+            .map(String::as_str)
+            .collect();
+        if !added.is_empty() || !removed.is_empty() {
+            changes.push(format!(
+                "blocked domains: added [{}], removed [{}]",
+                added.join(", "),
+                removed.join(", ")
+            ));
+        }
+        changes.push("content policy rules rebuilt".to_string());
+    }
+
+    let reserved = ctx.account_manager.reserved_handles();
+    let before = reserved.cached_count();
+    let after = reserved.reload().await?;
+    if before != after {
+        changes.push(format!("reserved handles: {} -> {} entries", before, after));
+    }
+
+    for path in ctx.mailer.templates().reload().await {
+        changes.push(format!("email template updated: {}", path.display()));
+    }
+
+    let report = ReloadReport { changes };
+    let details = serde_json::to_string(&report).ok();
+    if let Err(e) = ctx
+        .admin_role_manager
+        .log_action(actor, "config.reload", None, details.as_deref(), None)
+        .await
+    {
+        tracing::warn!("Failed to audit config reload: {}", e);
+    }
+
+    tracing::info!(actor, changes = report.changes.len(), "Configuration reloaded");
+    Ok(report)
+}
+
+/// Reload on SIGHUP until the process exits
+#[cfg(unix)]
+pub fn spawn_sighup_listener(ctx: AppContext) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tokio::spawn(async move {
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGHUP, config reload is admin-only: {}", e);
+                return;
+            }
+        };
+
+        while hangups.recv().await.is_some() {
+            let actor = ctx.service_did().to_string();
+            match reload(&ctx, &actor).await {
+                Ok(report) => {
+                    for change in &report.changes {
+                        tracing::info!("Config reload: {}", change);
+                    }
+                }
+                Err(e) => tracing::error!("Config reload failed, keeping current settings: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(not(unix))]
+pub fn spawn_sighup_listener(_ctx: AppContext) {}
@@ -0,0 +1,200 @@
+/// Repository verification and repair
+///
+/// `aurora-locus repair` verifies every local repository (or one with
+/// `--did`): the head commit signature against the account's signing key,
+/// the MST shape, block hashes, and the record index. A report is printed
+/// per repository.
+///
+/// With `--fix`, repositories that fail verification have records whose
+/// blocks are missing or corrupt dropped from the index, their MST rebuilt
+/// from the remaining records, and a fresh commit signed with the account's
+/// signing key. Repairs are not sequenced; relays pick up the new head on
+/// their next sync.
+
+use crate::{
+    actor_store::{
+        verify::{account_signing_key, verify_local, VerificationReport},
+        RepositoryManager,
+    },
+    context::AppContext,
+    error::{PdsError, PdsResult},
+};
+use tracing::warn;
+
+/// Repair options (`aurora-locus repair [--did DID] [--fix]`)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RepairOptions {
+    /// Only check this repository
+    pub did: Option<String>,
+    /// Rebuild repositories that fail verification
+    pub fix: bool,
+}
+
+impl RepairOptions {
+    /// Parse options from the arguments following `repair`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let mut opts = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            match flag.as_str() {
+                "--fix" => opts.fix = true,
+                "--did" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+                    if !value.starts_with("did:") {
+                        return Err(PdsError::Validation(format!("Invalid value for --did: {}", value)));
+                    }
+                    opts.did = Some(value.clone());
+                }
+                other => {
+                    return Err(PdsError::Validation(format!(
+                        "Unknown repair option: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Outcome for one repository
+#[derive(Debug)]
+pub struct RepoOutcome {
+    pub report: VerificationReport,
+    /// New commit CID and dropped record URIs, if the repo was rebuilt
+    pub repaired: Option<(String, Vec<String>)>,
+    /// Verification after the rebuild
+    pub after: Option<VerificationReport>,
+}
+
+/// Summary of a repair run
+#[derive(Debug, Default)]
+pub struct RepairReport {
+    pub repos: Vec<RepoOutcome>,
+    /// Repositories that could not be checked, with the error
+    pub errors: Vec<(String, String)>,
+}
+
+impl RepairReport {
+    /// Human-readable summary
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+
+        for repo in &self.repos {
+            out.push_str(&repo.report.summary());
+            out.push('\n');
+            if let Some((commit, dropped)) = &repo.repaired {
+                out.push_str(&format!(
+                    "  rebuilt at {} ({} record(s) dropped)\n",
+                    commit,
+                    dropped.len()
+                ));
+                for uri in dropped {
+                    out.push_str(&format!("    dropped {}\n", uri));
+                }
+            }
+            if let Some(after) = &repo.after {
+                out.push_str(&format!("  after repair: {}\n", after.summary()));
+            }
+        }
+        for (did, error) in &self.errors {
+            out.push_str(&format!("{}: error: {}\n", did, error));
+        }
+
+        let invalid = self.repos.iter().filter(|r| !r.report.is_valid()).count();
+        let repaired = self.repos.iter().filter(|r| r.repaired.is_some()).count();
+        out.push_str(&format!(
+            "checked {} repo(s): {} with issues, {} repaired, {} error(s)\n",
+            self.repos.len(),
+            invalid,
+            repaired,
+            self.errors.len()
+        ));
+
+        out
+    }
+}
+
+/// Verify (and optionally repair) local repositories
+pub async fn run(ctx: &AppContext, opts: &RepairOptions) -> PdsResult<RepairReport> {
+    let dids: Vec<String> = match &opts.did {
+        Some(did) => vec![did.clone()],
+        None => sqlx::query_scalar("SELECT did FROM account ORDER BY did")
+            .fetch_all(&ctx.account_db)
+            .await?,
+    };
+
+    let mut report = RepairReport::default();
+    for did in dids {
+        if !ctx.actor_store.exists(&did).await {
+            report.errors.push((did, "no repository".to_string()));
+            continue;
+        }
+
+        match check(ctx, &did, opts.fix).await {
+            Ok(outcome) => report.repos.push(outcome),
+            Err(e) => report.errors.push((did, e.to_string())),
+        }
+    }
+
+    Ok(report)
+}
+
+async fn check(ctx: &AppContext, did: &str, fix: bool) -> PdsResult<RepoOutcome> {
+    // Repos here are signed with the account's local key; fall back to it
+    // when the DID document can't be resolved (e.g. offline dev servers)
+    let key = match account_signing_key(&ctx.identity_resolver, did).await {
+        Ok(key) => key,
+        Err(e) => {
+            warn!(did = %did, error = %e, "repair_signing_key_unresolved");
+            ctx.signing_keys.public_key(did).await?
+        }
+    };
+
+    let report = verify_local(&ctx.actor_store, did, Some(&key)).await?;
+    if report.is_valid() || !fix {
+        return Ok(RepoOutcome {
+            report,
+            repaired: None,
+            after: None,
+        });
+    }
+
+    let repo_mgr = RepositoryManager::new(did.to_string(), (*ctx.actor_store).clone());
+    let (commit, _rev, dropped) = repo_mgr
+        .rebuild(ctx.signing_keys.commit_signer(did).await?)
+        .await?;
+    let after = verify_local(&ctx.actor_store, did, Some(&key)).await?;
+
+    Ok(RepoOutcome {
+        report,
+        repaired: Some((commit, dropped)),
+        after: Some(after),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_repair_options() {
+        assert_eq!(RepairOptions::from_args(&[]).unwrap(), RepairOptions::default());
+
+        let opts = RepairOptions::from_args(&args(&["--did", "did:plc:abc", "--fix"])).unwrap();
+        assert_eq!(opts.did.as_deref(), Some("did:plc:abc"));
+        assert!(opts.fix);
+
+        assert!(RepairOptions::from_args(&args(&["--did"])).is_err());
+        assert!(RepairOptions::from_args(&args(&["--did", "alice.test"])).is_err());
+        assert!(RepairOptions::from_args(&args(&["--force"])).is_err());
+    }
+}
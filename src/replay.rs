@@ -0,0 +1,182 @@
+/// Firehose event inspection and replay
+///
+/// `aurora-locus replay` prints events from the sequencer log as JSON lines,
+/// decoded from their stored CBOR. Select a range with `--from` (exclusive)
+/// and `--to` (inclusive), or one account's events with `--did`. `--verify`
+/// checks each commit's blocks against its CIDs and ops.
+///
+/// With `--emit`, the selected events are sequenced again, unchanged, so
+/// connected subscribers and relays receive them a second time. Invalidated
+/// events are never re-emitted.
+
+use crate::{
+    context::AppContext,
+    error::{PdsError, PdsResult},
+    sequencer::InspectedEvent,
+};
+
+/// Replay options (`aurora-locus replay [--from SEQ] [--to SEQ] [--did DID] [--limit N] [--verify] [--emit]`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayOptions {
+    /// Events after this seq
+    pub from: i64,
+    /// Up to and including this seq
+    pub to: Option<i64>,
+    /// Only this account's events
+    pub did: Option<String>,
+    pub limit: i64,
+    /// Check commit block integrity
+    pub verify: bool,
+    /// Re-emit the selected events to subscribers
+    pub emit: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            from: 0,
+            to: None,
+            did: None,
+            limit: 100,
+            verify: false,
+            emit: false,
+        }
+    }
+}
+
+impl ReplayOptions {
+    /// Parse options from the arguments following `replay`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let mut opts = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            match flag.as_str() {
+                "--verify" => opts.verify = true,
+                "--emit" => opts.emit = true,
+                "--from" | "--to" | "--limit" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+                    let number: i64 = value
+                        .parse()
+                        .ok()
+                        .filter(|n| *n >= 0)
+                        .ok_or_else(|| PdsError::Validation(format!("Invalid value for {}: {}", flag, value)))?;
+                    match flag.as_str() {
+                        "--from" => opts.from = number,
+                        "--to" => opts.to = Some(number),
+                        _ => opts.limit = number.max(1),
+                    }
+                }
+                "--did" => {
+                    let value = iter
+                        .next()
+                        .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+                    if !value.starts_with("did:") {
+                        return Err(PdsError::Validation(format!("Invalid value for --did: {}", value)));
+                    }
+                    opts.did = Some(value.clone());
+                }
+                other => {
+                    return Err(PdsError::Validation(format!(
+                        "Unknown replay option: {}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        if let Some(to) = opts.to {
+            if to <= opts.from {
+                return Err(PdsError::Validation("--to must be greater than --from".to_string()));
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Events selected by a replay run
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub events: Vec<InspectedEvent>,
+    /// (original seq, new seq) for each re-emitted event
+    pub emitted: Vec<(i64, i64)>,
+}
+
+impl ReplayReport {
+    /// One JSON line per event, then a summary line
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+
+        for event in &self.events {
+            out.push_str(&serde_json::to_string(event).unwrap_or_default());
+            out.push('\n');
+        }
+        for (original, seq) in &self.emitted {
+            out.push_str(&format!("re-emitted {} as {}\n", original, seq));
+        }
+
+        let failed = self
+            .events
+            .iter()
+            .filter(|e| e.decode_error.is_some() || e.integrity.as_ref().is_some_and(|i| !i.valid))
+            .count();
+        out.push_str(&format!(
+            "inspected {} event(s): {} with issues, {} re-emitted\n",
+            self.events.len(),
+            failed,
+            self.emitted.len()
+        ));
+
+        out
+    }
+}
+
+/// Inspect (and optionally re-emit) events from the sequencer log
+pub async fn run(ctx: &AppContext, opts: &ReplayOptions) -> PdsResult<ReplayReport> {
+    let rows = ctx
+        .sequencer
+        .list_rows(opts.from, opts.to, opts.did.as_deref(), opts.limit)
+        .await?;
+
+    let events = rows.iter().map(|row| InspectedEvent::from_row(row, opts.verify)).collect();
+    let emitted = if opts.emit {
+        ctx.sequencer.replay(&rows).await?
+    } else {
+        Vec::new()
+    };
+
+    Ok(ReplayReport { events, emitted })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_replay_options() {
+        assert_eq!(ReplayOptions::from_args(&[]).unwrap(), ReplayOptions::default());
+
+        let opts = ReplayOptions::from_args(&args(&[
+            "--from", "10", "--to", "20", "--did", "did:plc:abc", "--verify", "--emit",
+        ]))
+        .unwrap();
+        assert_eq!(opts.from, 10);
+        assert_eq!(opts.to, Some(20));
+        assert_eq!(opts.did.as_deref(), Some("did:plc:abc"));
+        assert!(opts.verify);
+        assert!(opts.emit);
+
+        assert!(ReplayOptions::from_args(&args(&["--from"])).is_err());
+        assert!(ReplayOptions::from_args(&args(&["--limit", "many"])).is_err());
+        assert!(ReplayOptions::from_args(&args(&["--from", "20", "--to", "10"])).is_err());
+        assert!(ReplayOptions::from_args(&args(&["--did", "alice.test"])).is_err());
+        assert!(ReplayOptions::from_args(&args(&["--all"])).is_err());
+    }
+}
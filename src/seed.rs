@@ -0,0 +1,311 @@
+/// Development data seeding
+///
+/// `aurora-locus seed` populates a development server with demo accounts,
+/// profiles, posts, follows, and image blobs. All content is derived from a
+/// seeded RNG, so the same options always produce the same data set.
+///
+/// Only available with `PDS_DEV_MODE=true`. Demo accounts are local did:web
+/// identities (never registered with the PLC directory) and their commits
+/// are not sequenced, so nothing leaks to relays.
+
+use crate::{
+    actor_store::RepositoryManager,
+    context::AppContext,
+    error::{PdsError, PdsResult},
+};
+use chrono::{Duration, Utc};
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use serde_json::json;
+use tracing::info;
+
+const ADJECTIVES: &[&str] = &[
+    "amber", "brisk", "cobalt", "dusky", "eager", "fuzzy", "gentle", "hazel", "ivory", "jolly",
+    "keen", "lunar", "mellow", "nimble", "opal", "plucky", "quiet", "rusty", "sunny", "tidal",
+];
+
+const NOUNS: &[&str] = &[
+    "otter", "falcon", "maple", "harbor", "comet", "fern", "badger", "willow", "pebble", "heron",
+    "lynx", "meadow", "quartz", "raven", "spruce", "thistle", "walrus", "yarrow", "zephyr", "koi",
+];
+
+const POST_FRAGMENTS: &[&str] = &[
+    "just shipped a new feature",
+    "coffee first, then federation",
+    "thinking about merkle search trees again",
+    "the firehose is surprisingly calming",
+    "hot take: small servers are the future",
+    "testing image uploads",
+    "good morning from the dev instance",
+    "who else is self-hosting",
+    "reading the lexicon docs tonight",
+    "this thread is getting long",
+];
+
+/// Seed options (`aurora-locus seed [--accounts N] [--posts N] ...`)
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeedOptions {
+    /// Number of demo accounts
+    pub accounts: usize,
+    /// Posts per account
+    pub posts_per_account: usize,
+    /// Follows per account
+    pub follows_per_account: usize,
+    /// Image blobs per account (each attached to a post)
+    pub blobs_per_account: usize,
+    /// RNG seed
+    pub seed: u64,
+    /// Password for every demo account
+    pub password: String,
+}
+
+impl Default for SeedOptions {
+    fn default() -> Self {
+        Self {
+            accounts: 10,
+            posts_per_account: 5,
+            follows_per_account: 3,
+            blobs_per_account: 1,
+            seed: 42,
+            password: "demo-password".to_string(),
+        }
+    }
+}
+
+impl SeedOptions {
+    /// Parse options from the arguments following `seed`
+    pub fn from_args(args: &[String]) -> PdsResult<Self> {
+        let mut opts = Self::default();
+        let mut iter = args.iter();
+
+        while let Some(flag) = iter.next() {
+            let value = iter
+                .next()
+                .ok_or_else(|| PdsError::Validation(format!("Missing value for {}", flag)))?;
+            let parse = |v: &str| {
+                v.parse::<usize>()
+                    .map_err(|_| PdsError::Validation(format!("Invalid value for {}: {}", flag, v)))
+            };
+
+            match flag.as_str() {
+                "--accounts" => opts.accounts = parse(value)?,
+                "--posts" => opts.posts_per_account = parse(value)?,
+                "--follows" => opts.follows_per_account = parse(value)?,
+                "--blobs" => opts.blobs_per_account = parse(value)?,
+                "--seed" => {
+                    opts.seed = value.parse().map_err(|_| {
+                        PdsError::Validation(format!("Invalid value for --seed: {}", value))
+                    })?
+                }
+                "--password" => opts.password = value.clone(),
+                other => {
+                    return Err(PdsError::Validation(format!("Unknown seed option: {}", other)))
+                }
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+/// Summary of a seed run
+#[derive(Debug, Default)]
+pub struct SeedReport {
+    pub accounts_created: usize,
+    pub accounts_existing: usize,
+    pub posts: usize,
+    pub follows: usize,
+    pub blobs: usize,
+}
+
+/// Deterministic handle prefix for the `index`-th demo account
+fn demo_handle_prefix(rng: &mut StdRng, index: usize) -> String {
+    format!(
+        "{}-{}-{}",
+        ADJECTIVES.choose(rng).unwrap(),
+        NOUNS.choose(rng).unwrap(),
+        index
+    )
+}
+
+/// Deterministic post text
+fn demo_post_text(rng: &mut StdRng) -> String {
+    let fragment = POST_FRAGMENTS.choose(rng).unwrap();
+    let tag = NOUNS.choose(rng).unwrap();
+    format!("{} #{}", fragment, tag)
+}
+
+/// Deterministic PNG (a two-colour gradient)
+fn demo_image(rng: &mut StdRng) -> PdsResult<Vec<u8>> {
+    let (r, g, b): (u8, u8, u8) = (rng.gen(), rng.gen(), rng.gen());
+    let img = image::RgbImage::from_fn(64, 64, |x, y| {
+        image::Rgb([r ^ (x as u8 * 4), g ^ (y as u8 * 4), b])
+    });
+
+    let mut buf = Vec::new();
+    img.write_to(&mut std::io::Cursor::new(&mut buf), image::ImageFormat::Png)
+        .map_err(|e| PdsError::Internal(format!("Failed to encode demo image: {}", e)))?;
+    Ok(buf)
+}
+
+/// Append the service handle domain to a handle prefix
+fn full_handle(prefix: &str, domain: &str) -> String {
+    if domain.starts_with('.') {
+        format!("{}{}", prefix, domain)
+    } else {
+        format!("{}.{}", prefix, domain)
+    }
+}
+
+/// Seed the server with demo data
+pub async fn run(ctx: &AppContext, opts: &SeedOptions) -> PdsResult<SeedReport> {
+    if !ctx.config.service.dev_mode {
+        return Err(PdsError::Validation(
+            "seed is only available with PDS_DEV_MODE=true".to_string(),
+        ));
+    }
+
+    let domain = ctx
+        .config
+        .identity
+        .service_handle_domains
+        .first()
+        .cloned()
+        .ok_or_else(|| PdsError::Validation("No service handle domain configured".to_string()))?;
+
+    let mut rng = StdRng::seed_from_u64(opts.seed);
+    let mut report = SeedReport::default();
+
+    // Accounts (existing ones are reused as follow targets but not re-seeded)
+    let mut accounts: Vec<(String, bool)> = Vec::with_capacity(opts.accounts);
+    for i in 0..opts.accounts {
+        let handle = full_handle(&demo_handle_prefix(&mut rng, i), &domain);
+
+        match ctx.account_manager.get_account_by_identifier(&handle).await {
+            Ok(existing) => {
+                report.accounts_existing += 1;
+                accounts.push((existing.did, false));
+            }
+            Err(_) => {
+                let email = format!("{}@example.invalid", handle);
+                let account = ctx
+                    .account_manager
+                    .create_local_account(handle.clone(), Some(email), opts.password.clone())
+                    .await?;
+
+                RepositoryManager::new(account.did.clone(), (*ctx.actor_store).clone())
+                    .initialize()
+                    .await?;
+
+                info!("seed: created {} ({})", handle, account.did);
+                report.accounts_created += 1;
+                accounts.push((account.did, true));
+            }
+        }
+    }
+
+    let dids: Vec<String> = accounts.iter().map(|(did, _)| did.clone()).collect();
+
+    for (did, is_new) in &accounts {
+        if !is_new {
+            continue;
+        }
+
+        let repo = RepositoryManager::new(did.clone(), (*ctx.actor_store).clone())
+            .with_blob_store(Some(ctx.blob_store.clone()));
+        let base_time = Utc::now() - Duration::days(7);
+
+        // Profile
+        let profile = json!({
+            "$type": "app.bsky.actor.profile",
+            "displayName": format!("{} {}", ADJECTIVES.choose(&mut rng).unwrap(), NOUNS.choose(&mut rng).unwrap()),
+            "description": "Demo account generated by aurora-locus seed",
+        });
+        let signer = ctx.signing_keys.commit_signer(did).await?;
+        repo.create_record("app.bsky.actor.profile", Some("self"), profile, None, None, signer)
+            .await?;
+
+        // Posts, the first `blobs_per_account` of which carry an image
+        let post_count = opts.posts_per_account.max(opts.blobs_per_account);
+        for n in 0..post_count {
+            let created_at = base_time + Duration::minutes(rng.gen_range(0..7 * 24 * 60));
+            let mut post = json!({
+                "$type": "app.bsky.feed.post",
+                "text": demo_post_text(&mut rng),
+                "createdAt": created_at.to_rfc3339(),
+            });
+
+            if n < opts.blobs_per_account {
+                let data = demo_image(&mut rng)?;
+                let blob = ctx.blob_store.upload(data, Some("image/png"), did).await?;
+                post["embed"] = json!({
+                    "$type": "app.bsky.embed.images",
+                    "images": [{ "alt": "Generated demo image", "image": blob }],
+                });
+                report.blobs += 1;
+            }
+
+            let signer = ctx.signing_keys.commit_signer(did).await?;
+            repo.create_record("app.bsky.feed.post", None, post, None, None, signer)
+                .await?;
+            report.posts += 1;
+        }
+
+        // Follows
+        let others: Vec<&String> = dids.iter().filter(|d| *d != did).collect();
+        let targets: Vec<&String> = others
+            .choose_multiple(&mut rng, opts.follows_per_account.min(others.len()))
+            .copied()
+            .collect();
+        for subject in targets {
+            let follow = json!({
+                "$type": "app.bsky.graph.follow",
+                "subject": subject,
+                "createdAt": Utc::now().to_rfc3339(),
+            });
+            let signer = ctx.signing_keys.commit_signer(did).await?;
+            repo.create_record("app.bsky.graph.follow", None, follow, None, None, signer)
+                .await?;
+            report.follows += 1;
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> Vec<String> {
+        s.split_whitespace().map(String::from).collect()
+    }
+
+    #[test]
+    fn test_parse_seed_options() {
+        let opts = SeedOptions::from_args(&args("--accounts 3 --posts 2 --seed 7")).unwrap();
+        assert_eq!(opts.accounts, 3);
+        assert_eq!(opts.posts_per_account, 2);
+        assert_eq!(opts.seed, 7);
+        assert_eq!(opts.follows_per_account, SeedOptions::default().follows_per_account);
+
+        assert!(SeedOptions::from_args(&args("--accounts")).is_err());
+        assert!(SeedOptions::from_args(&args("--accounts many")).is_err());
+        assert!(SeedOptions::from_args(&args("--bogus 1")).is_err());
+    }
+
+    #[test]
+    fn test_seeded_content_is_deterministic() {
+        let generate = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (
+                demo_handle_prefix(&mut rng, 0),
+                demo_post_text(&mut rng),
+                demo_image(&mut rng).unwrap(),
+            )
+        };
+
+        assert_eq!(generate(42), generate(42));
+        assert_eq!(full_handle("amber-otter-0", ".example.com"), "amber-otter-0.example.com");
+        assert_eq!(full_handle("amber-otter-0", "example.com"), "amber-otter-0.example.com");
+    }
+}
@@ -0,0 +1,263 @@
+/// Registry of connected firehose consumers
+///
+/// Every subscribeRepos / subscribeOwnRepo connection registers itself here
+/// for as long as it is open and reports each frame it sends, so admins can
+/// see who is consuming the firehose and how far behind the head of the
+/// event log each consumer is, and disconnect one that misbehaves.
+///
+/// Kept in memory only; the list starts empty on restart.
+use crate::{
+    clock::{system_clock, Clock},
+    metrics,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex,
+};
+use tokio::sync::Notify;
+
+/// A connected consumer, as reported to admins
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConsumerInfo {
+    pub id: u64,
+    /// Client address (from the forwarding headers)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub remote_addr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_agent: Option<String>,
+    /// Account of an own-repo subscription
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub did: Option<String>,
+    /// Seq of the last event sent, or the cursor it connected with
+    pub cursor: i64,
+    pub frames_sent: u64,
+    pub bytes_sent: u64,
+    /// Events between the head of the log and `cursor`; not reported for
+    /// own-repo subscriptions, which skip other repos' events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lag: Option<i64>,
+    pub connected_at: DateTime<Utc>,
+}
+
+struct Consumer {
+    remote_addr: Option<String>,
+    user_agent: Option<String>,
+    did: Option<String>,
+    cursor: i64,
+    frames_sent: u64,
+    bytes_sent: u64,
+    connected_at: DateTime<Utc>,
+    disconnect: Arc<Notify>,
+}
+
+/// Connected firehose consumers
+pub struct FirehoseConsumers {
+    next_id: AtomicU64,
+    consumers: Mutex<HashMap<u64, Consumer>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for FirehoseConsumers {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FirehoseConsumers {
+    pub fn new() -> Self {
+        Self {
+            next_id: AtomicU64::new(1),
+            consumers: Mutex::new(HashMap::new()),
+            clock: system_clock(),
+        }
+    }
+
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Register a new connection; it is removed when the guard is dropped
+    pub fn register(
+        self: &Arc<Self>,
+        remote_addr: Option<String>,
+        user_agent: Option<String>,
+        did: Option<String>,
+        cursor: i64,
+    ) -> ConsumerGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let disconnect = Arc::new(Notify::new());
+        let consumer = Consumer {
+            remote_addr,
+            user_agent,
+            did,
+            cursor,
+            frames_sent: 0,
+            bytes_sent: 0,
+            connected_at: self.clock.now(),
+            disconnect: disconnect.clone(),
+        };
+
+        let mut consumers = self.consumers.lock().unwrap();
+        consumers.insert(id, consumer);
+        metrics::set_firehose_consumers(consumers.len());
+
+        ConsumerGuard {
+            id,
+            registry: Arc::clone(self),
+            disconnect,
+        }
+    }
+
+    /// Connected consumers, most behind first, with lag measured from `head`
+    ///
+    /// Also refreshes the consumer lag metrics.
+    pub fn list(&self, head: i64) -> Vec<ConsumerInfo> {
+        let consumers = self.consumers.lock().unwrap();
+        let mut list: Vec<ConsumerInfo> = consumers
+            .iter()
+            .map(|(id, c)| ConsumerInfo {
+                id: *id,
+                remote_addr: c.remote_addr.clone(),
+                user_agent: c.user_agent.clone(),
+                did: c.did.clone(),
+                cursor: c.cursor,
+                frames_sent: c.frames_sent,
+                bytes_sent: c.bytes_sent,
+                lag: c.did.is_none().then(|| (head - c.cursor).max(0)),
+                connected_at: c.connected_at,
+            })
+            .collect();
+        list.sort_by(|a, b| b.lag.cmp(&a.lag).then(a.id.cmp(&b.id)));
+
+        for consumer in &list {
+            if let Some(lag) = consumer.lag {
+                metrics::set_firehose_consumer_lag_for(consumer.id, lag);
+            }
+        }
+
+        metrics::set_firehose_consumer_lag(list.first().and_then(|c| c.lag).unwrap_or(0));
+        list
+    }
+
+    /// Ask a consumer's connection to close; false if it isn't connected
+    pub fn disconnect(&self, id: u64) -> bool {
+        match self.consumers.lock().unwrap().get(&id) {
+            Some(consumer) => {
+                // notify_one keeps the permit if the connection isn't waiting yet
+                consumer.disconnect.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn record_sent(&self, id: u64, seq: Option<i64>, bytes: u64) {
+        if let Some(consumer) = self.consumers.lock().unwrap().get_mut(&id) {
+            consumer.frames_sent += 1;
+            consumer.bytes_sent += bytes;
+            if let Some(seq) = seq {
+                consumer.cursor = consumer.cursor.max(seq);
+            }
+        }
+    }
+
+    fn report_lag(&self, id: u64, head: i64) {
+        if let Some(consumer) = self.consumers.lock().unwrap().get(&id) {
+            if consumer.did.is_none() {
+                metrics::set_firehose_consumer_lag_for(id, (head - consumer.cursor).max(0));
+            }
+        }
+    }
+
+    fn remove(&self, id: u64) {
+        let mut consumers = self.consumers.lock().unwrap();
+        consumers.remove(&id);
+        metrics::set_firehose_consumers(consumers.len());
+        metrics::remove_firehose_consumer_lag(id);
+    }
+}
+
+/// A registered connection
+pub struct ConsumerGuard {
+    id: u64,
+    registry: Arc<FirehoseConsumers>,
+    disconnect: Arc<Notify>,
+}
+
+impl ConsumerGuard {
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Count a frame sent to the consumer; `seq` is the event's, if it has one
+    pub fn record_sent(&self, seq: Option<i64>, bytes: u64) {
+        self.registry.record_sent(self.id, seq, bytes);
+    }
+
+    /// Update the consumer's lag metric against the head of the log
+    ///
+    /// Own-repo subscriptions have no meaningful lag and report none.
+    pub fn report_lag(&self, head: i64) {
+        self.registry.report_lag(self.id, head);
+    }
+
+    /// Resolves when an admin disconnects this consumer
+    pub async fn disconnected(&self) {
+        self.disconnect.notified().await
+    }
+}
+
+impl Drop for ConsumerGuard {
+    fn drop(&mut self) {
+        self.registry.remove(self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_consumer_registry() {
+        let registry = Arc::new(FirehoseConsumers::new());
+        let relay = registry.register(Some("10.0.0.1".to_string()), None, None, 10);
+        let own = registry.register(None, None, Some("did:plc:alice".to_string()), 0);
+
+        relay.record_sent(Some(40), 512);
+        relay.record_sent(None, 64);
+        own.record_sent(Some(12), 100);
+
+        let list = registry.list(50);
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].id, relay.id());
+        assert_eq!(list[0].cursor, 40);
+        assert_eq!(list[0].frames_sent, 2);
+        assert_eq!(list[0].bytes_sent, 576);
+        assert_eq!(list[0].lag, Some(10));
+        assert_eq!(list[1].cursor, 12);
+        assert_eq!(list[1].lag, None);
+
+        // A disconnect is delivered even before the connection waits for it
+        assert!(registry.disconnect(relay.id()));
+        tokio::time::timeout(Duration::from_secs(1), relay.disconnected()).await.unwrap();
+
+        relay.report_lag(50);
+        own.report_lag(50);
+        let rendered = metrics::render_metrics();
+        let series = format!("firehose_consumer_lag{{consumer=\"{}\"}} 10", relay.id());
+        assert!(rendered.contains(&series));
+        assert!(!rendered.contains(&format!("firehose_consumer_lag{{consumer=\"{}\"}}", own.id())));
+
+        let relay_id = relay.id();
+        drop(relay);
+        assert!(!metrics::render_metrics().contains(&series));
+        assert!(!registry.disconnect(relay_id));
+        assert_eq!(registry.list(50).len(), 1);
+    }
+}
@@ -88,6 +88,14 @@ impl CommitEvent {
             prev: None,
         }
     }
+
+    /// Drop the blocks, ops and blobs, leaving consumers to fetch the repo
+    pub fn mark_too_big(&mut self) {
+        self.too_big = true;
+        self.blocks.clear();
+        self.ops.clear();
+        self.blobs.clear();
+    }
 }
 
 impl IdentityEvent {
@@ -0,0 +1,257 @@
+/// Event log inspection
+///
+/// Decodes stored CBOR events into JSON and checks that a commit event's
+/// CAR slice is self-consistent: every block hashes to its CID, the commit
+/// block is present and is a root, and every created or updated record
+/// block is included. Used by `com.atproto.admin.getSequencerEvents` and
+/// `aurora-locus replay` when debugging what relays were sent.
+use crate::{
+    car::reader::{read_car, verify_block},
+    sequencer::{
+        events::{AccountEvent, CommitEvent, IdentityEvent, OpAction},
+        SeqRow,
+    },
+};
+use chrono::{DateTime, Utc};
+use libipld::Cid;
+use serde::Serialize;
+use serde_json::Value;
+use std::str::FromStr;
+
+/// A stored event, decoded
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InspectedEvent {
+    pub seq: i64,
+    pub did: String,
+    pub event_type: String,
+    pub invalidated: bool,
+    pub sequenced_at: DateTime<Utc>,
+    /// Size of the stored CBOR payload
+    pub payload_bytes: usize,
+    /// Decoded payload; commit blocks are listed by CID. `None` if the
+    /// payload could not be decoded
+    pub event: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub decode_error: Option<String>,
+    /// Block integrity of a commit event, when requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<CommitIntegrity>,
+}
+
+/// Integrity of a commit event's blocks
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CommitIntegrity {
+    pub valid: bool,
+    /// Blocks in the event's CAR slice
+    pub blocks: usize,
+    pub issues: Vec<String>,
+}
+
+impl InspectedEvent {
+    /// Decode a stored event, checking commit blocks if `verify` is set
+    pub fn from_row(row: &SeqRow, verify: bool) -> Self {
+        let mut inspected = Self {
+            seq: row.seq,
+            did: row.did.clone(),
+            event_type: row.event_type.clone(),
+            invalidated: row.invalidated,
+            sequenced_at: row.sequenced_at,
+            payload_bytes: row.event.len(),
+            event: None,
+            decode_error: None,
+            integrity: None,
+        };
+
+        let decoded = match row.event_type.as_str() {
+            "commit" => serde_cbor::from_slice::<CommitEvent>(&row.event).map(|commit| {
+                if verify {
+                    inspected.integrity = Some(check_commit(&commit));
+                }
+                commit_json(&commit)
+            }),
+            "identity" => serde_cbor::from_slice::<IdentityEvent>(&row.event).map(|e| to_json(&e)),
+            "account" => serde_cbor::from_slice::<AccountEvent>(&row.event).map(|e| to_json(&e)),
+            other => {
+                inspected.decode_error = Some(format!("Unknown event type: {}", other));
+                return inspected;
+            }
+        };
+
+        match decoded {
+            Ok(event) => inspected.event = Some(event),
+            Err(e) => inspected.decode_error = Some(format!("Invalid CBOR payload: {}", e)),
+        }
+        inspected
+    }
+}
+
+fn to_json<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+/// A commit as JSON, with the CAR bytes replaced by the CIDs they hold
+fn commit_json(commit: &CommitEvent) -> Value {
+    let mut json = to_json(commit);
+    let blocks = match read_car(&commit.blocks) {
+        Ok(car) => {
+            let mut cids: Vec<String> = car.blocks.keys().map(|cid| cid.to_string()).collect();
+            cids.sort();
+            Value::from(cids)
+        }
+        Err(_) => Value::Null,
+    };
+    if let Some(object) = json.as_object_mut() {
+        object.insert("blocks".to_string(), blocks);
+        object.insert("blocksBytes".to_string(), Value::from(commit.blocks.len()));
+    }
+    json
+}
+
+/// Check a commit event's CAR slice against its commit and ops
+pub fn check_commit(commit: &CommitEvent) -> CommitIntegrity {
+    // tooBig commits deliberately carry no blocks
+    if commit.too_big && commit.blocks.is_empty() {
+        return CommitIntegrity {
+            valid: true,
+            blocks: 0,
+            issues: Vec::new(),
+        };
+    }
+
+    let car = match read_car(&commit.blocks) {
+        Ok(car) => car,
+        Err(e) => {
+            return CommitIntegrity {
+                valid: false,
+                blocks: 0,
+                issues: vec![e.to_string()],
+            }
+        }
+    };
+
+    let mut issues = Vec::new();
+    let mut bad: Vec<String> = car
+        .blocks
+        .iter()
+        .filter_map(|(cid, data)| verify_block(cid, data).err().map(|_| cid.to_string()))
+        .collect();
+    bad.sort();
+    issues.extend(bad.into_iter().map(|cid| format!("Block {} does not match its CID", cid)));
+
+    match Cid::from_str(&commit.commit) {
+        Ok(cid) => {
+            if !car.roots.contains(&cid) {
+                issues.push(format!("Commit {} is not a CAR root", cid));
+            }
+            if !car.blocks.contains_key(&cid) {
+                issues.push(format!("Commit block {} is missing", cid));
+            }
+        }
+        Err(_) => issues.push(format!("Invalid commit CID: {}", commit.commit)),
+    }
+
+    for op in &commit.ops {
+        if op.action == OpAction::Delete {
+            continue;
+        }
+        match op.cid.as_deref().map(Cid::from_str) {
+            Some(Ok(cid)) if car.blocks.contains_key(&cid) => {}
+            Some(Ok(cid)) => issues.push(format!("Record block {} for {} is missing", cid, op.path)),
+            _ => issues.push(format!("Op {} has no valid record CID", op.path)),
+        }
+    }
+
+    CommitIntegrity {
+        valid: issues.is_empty(),
+        blocks: car.blocks.len(),
+        issues,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actor_store::mst::block_cid;
+    use crate::car::CarEncoder;
+    use crate::sequencer::events::CommitOp;
+
+    fn commit_with(blocks: &[(Cid, Vec<u8>)], root: &Cid, ops: Vec<CommitOp>) -> CommitEvent {
+        let mut car = CarEncoder::new(root).unwrap();
+        for (cid, data) in blocks {
+            car.add_block(cid, data).unwrap();
+        }
+        CommitEvent::new(
+            "did:plc:alice".to_string(),
+            root.to_string(),
+            "3kabc".to_string(),
+            None,
+            car.finalize(),
+            ops,
+        )
+    }
+
+    fn row(seq: i64, event_type: &str, event: Vec<u8>) -> SeqRow {
+        SeqRow {
+            seq,
+            did: "did:plc:alice".to_string(),
+            event_type: event_type.to_string(),
+            event,
+            invalidated: false,
+            sequenced_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_check_commit() {
+        let commit_cid = block_cid(b"commit");
+        let record_cid = block_cid(b"record");
+        let op = |cid: &Cid| CommitOp {
+            action: OpAction::Create,
+            path: "app.bsky.feed.post/1".to_string(),
+            cid: Some(cid.to_string()),
+        };
+
+        let good = commit_with(
+            &[(commit_cid, b"commit".to_vec()), (record_cid, b"record".to_vec())],
+            &commit_cid,
+            vec![op(&record_cid)],
+        );
+        let integrity = check_commit(&good);
+        assert!(integrity.valid, "{:?}", integrity.issues);
+        assert_eq!(integrity.blocks, 2);
+
+        // Missing record block and a corrupt commit block
+        let bad = commit_with(&[(commit_cid, b"tampered".to_vec())], &commit_cid, vec![op(&record_cid)]);
+        let integrity = check_commit(&bad);
+        assert!(!integrity.valid);
+        assert_eq!(integrity.issues.len(), 2);
+        assert!(integrity.issues[0].contains("does not match"));
+        assert!(integrity.issues[1].contains("app.bsky.feed.post/1"));
+
+        let mut too_big = CommitEvent::new("did:plc:alice".into(), commit_cid.to_string(), "3kabc".into(), None, vec![], vec![]);
+        too_big.too_big = true;
+        assert!(check_commit(&too_big).valid);
+    }
+
+    #[test]
+    fn test_inspect_rows() {
+        let commit_cid = block_cid(b"commit");
+        let commit = commit_with(&[(commit_cid, b"commit".to_vec())], &commit_cid, vec![]);
+        let inspected = InspectedEvent::from_row(&row(7, "commit", serde_cbor::to_vec(&commit).unwrap()), true);
+        let event = inspected.event.unwrap();
+        assert_eq!(event["repo"], "did:plc:alice");
+        assert_eq!(event["blocks"], serde_json::json!([commit_cid.to_string()]));
+        assert!(inspected.integrity.unwrap().valid);
+
+        let identity = IdentityEvent::new("did:plc:alice".to_string(), Some("alice.test".to_string()));
+        let inspected = InspectedEvent::from_row(&row(8, "identity", serde_cbor::to_vec(&identity).unwrap()), true);
+        assert_eq!(inspected.event.unwrap()["handle"], "alice.test");
+        assert!(inspected.integrity.is_none());
+
+        let inspected = InspectedEvent::from_row(&row(9, "commit", b"junk".to_vec()), false);
+        assert!(inspected.event.is_none());
+        assert!(inspected.decode_error.is_some());
+    }
+}
@@ -0,0 +1,234 @@
+/// Sequencer integrity checks
+///
+/// Seq numbers come from an AUTOINCREMENT column, so a healthy log has no
+/// holes. A restore from an old backup, a manual delete or a crash during
+/// recovery can leave gaps (seqs that no longer exist) or replay the same
+/// commit twice under new seqs.
+///
+/// Gaps can't be repaired - the events are gone - so they are reported: in
+/// the logs and metrics, to firehose clients as a `SequenceGap` info frame,
+/// and through `com.atproto.admin.getSequencerHealth`, after which an admin can
+/// re-emit the current state of affected repos. Duplicate commits are healed
+/// by invalidating every copy but the first.
+use crate::{
+    error::{PdsError, PdsResult},
+    sequencer::payload,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use sqlx::{Row, SqlitePool};
+use std::collections::{BTreeMap, HashMap};
+
+/// Maximum number of gaps / duplicates listed in a report
+const MAX_LISTED: i64 = 1000;
+
+/// Commit rows decoded per query while looking for duplicates
+const SCAN_BATCH: i64 = 500;
+
+/// A run of missing seq numbers
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SeqGap {
+    /// Last seq before the gap
+    pub after: i64,
+    /// First seq after the gap
+    pub before: i64,
+    /// Number of missing seqs
+    pub missing: i64,
+}
+
+/// A commit sequenced more than once
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DuplicateEvent {
+    pub did: String,
+    /// Seq of the copy that is kept
+    pub original_seq: i64,
+    /// Seqs of the later copies
+    pub duplicate_seqs: Vec<i64>,
+}
+
+/// Result of an integrity check
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SequencerHealth {
+    pub first_seq: Option<i64>,
+    pub last_seq: Option<i64>,
+    pub event_count: i64,
+    pub invalidated_count: i64,
+    /// Gaps, oldest first (at most 1000 listed)
+    pub gaps: Vec<SeqGap>,
+    /// Total missing seqs across all gaps
+    pub missing_events: i64,
+    /// Duplicate commits still valid before this check (at most 1000 listed)
+    pub duplicates: Vec<DuplicateEvent>,
+    /// Duplicate copies invalidated by this check
+    pub invalidated_duplicates: u64,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl SequencerHealth {
+    /// No gaps and no duplicates left valid
+    pub fn is_healthy(&self) -> bool {
+        let duplicate_copies: usize = self.duplicates.iter().map(|d| d.duplicate_seqs.len()).sum();
+        self.missing_events == 0 && duplicate_copies as u64 == self.invalidated_duplicates
+    }
+}
+
+/// Scan the event log for gaps and duplicate commits
+///
+/// With `repair`, later copies of duplicate commits are invalidated so
+/// firehose consumers skip them.
+pub async fn check(db: &SqlitePool, repair: bool) -> PdsResult<SequencerHealth> {
+    let totals = sqlx::query(
+        r#"
+        SELECT MIN(seq) AS first_seq, MAX(seq) AS last_seq, COUNT(*) AS event_count,
+               COALESCE(SUM(invalidated != 0), 0) AS invalidated_count
+        FROM repo_seq
+        "#,
+    )
+    .fetch_one(db)
+    .await
+    .map_err(PdsError::Database)?;
+
+    let first_seq: Option<i64> = totals.try_get("first_seq")?;
+    let last_seq: Option<i64> = totals.try_get("last_seq")?;
+    let event_count: i64 = totals.try_get("event_count")?;
+    let missing_events = match (first_seq, last_seq) {
+        (Some(first), Some(last)) => last - first + 1 - event_count,
+        _ => 0,
+    };
+
+    let gaps = if missing_events > 0 {
+        sqlx::query(
+            r#"
+            SELECT prev, seq FROM (
+                SELECT seq, LAG(seq) OVER (ORDER BY seq) AS prev FROM repo_seq
+            )
+            WHERE seq - prev > 1
+            ORDER BY seq
+            LIMIT ?1
+            "#,
+        )
+        .bind(MAX_LISTED)
+        .fetch_all(db)
+        .await
+        .map_err(PdsError::Database)?
+        .iter()
+        .map(|row| {
+            let after: i64 = row.get("prev");
+            let before: i64 = row.get("seq");
+            SeqGap {
+                after,
+                before,
+                missing: before - after - 1,
+            }
+        })
+        .collect()
+    } else {
+        Vec::new()
+    };
+
+    let duplicates = duplicate_commits(db).await?;
+
+    let mut invalidated_duplicates = 0;
+    if repair {
+        for duplicate in &duplicates {
+            for seq in &duplicate.duplicate_seqs {
+                invalidated_duplicates += sqlx::query("UPDATE repo_seq SET invalidated = 1 WHERE seq = ?1")
+                    .bind(seq)
+                    .execute(db)
+                    .await
+                    .map_err(PdsError::Database)?
+                    .rows_affected();
+            }
+        }
+    }
+
+    Ok(SequencerHealth {
+        first_seq,
+        last_seq,
+        event_count,
+        invalidated_count: totals.try_get::<i64, _>("invalidated_count")? + invalidated_duplicates as i64,
+        gaps,
+        missing_events,
+        duplicates,
+        invalidated_duplicates,
+        checked_at: Utc::now(),
+    })
+}
+
+/// Valid commits sequenced more than once, oldest original first
+///
+/// Identical commit payloads for the same repo are replays; identity events
+/// legitimately repeat, so only commits are considered. Payloads are
+/// compared by a hash of their decoded CBOR, since a replayed copy may be
+/// stored compressed when the original isn't, or the other way round.
+async fn duplicate_commits(db: &SqlitePool) -> PdsResult<Vec<DuplicateEvent>> {
+    let mut first_seen: HashMap<[u8; 32], i64> = HashMap::new();
+    let mut duplicates: BTreeMap<i64, DuplicateEvent> = BTreeMap::new();
+
+    let mut after = 0;
+    loop {
+        let rows = sqlx::query(
+            "SELECT seq, did, event FROM repo_seq
+             WHERE event_type = 'commit' AND invalidated = 0 AND seq > ?1
+             ORDER BY seq LIMIT ?2",
+        )
+        .bind(after)
+        .bind(SCAN_BATCH)
+        .fetch_all(db)
+        .await
+        .map_err(PdsError::Database)?;
+        let Some(last) = rows.last() else {
+            break;
+        };
+        after = last.get("seq");
+
+        for row in &rows {
+            let seq: i64 = row.get("seq");
+            let did: String = row.get("did");
+            let cbor = payload::decode(row.get("event"))?;
+            let hash: [u8; 32] = Sha256::new()
+                .chain_update(did.as_bytes())
+                .chain_update([0])
+                .chain_update(&cbor)
+                .finalize()
+                .into();
+
+            let original_seq = *first_seen.entry(hash).or_insert(seq);
+            if original_seq != seq {
+                duplicates
+                    .entry(original_seq)
+                    .or_insert_with(|| DuplicateEvent {
+                        did,
+                        original_seq,
+                        duplicate_seqs: Vec::new(),
+                    })
+                    .duplicate_seqs
+                    .push(seq);
+            }
+        }
+    }
+
+    Ok(duplicates.into_values().take(MAX_LISTED as usize).collect())
+}
+
+/// Number of seqs in `(after, before)` with no row at all
+///
+/// Invalidated events still have rows and don't count as missing.
+pub async fn missing_between(db: &SqlitePool, after: i64, before: i64) -> PdsResult<i64> {
+    if before - after <= 1 {
+        return Ok(0);
+    }
+
+    let present: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM repo_seq WHERE seq > ?1 AND seq < ?2")
+        .bind(after)
+        .bind(before)
+        .fetch_one(db)
+        .await
+        .map_err(PdsError::Database)?;
+
+    Ok(before - after - 1 - present)
+}
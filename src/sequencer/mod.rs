@@ -3,10 +3,17 @@
 /// Provides globally ordered event stream for federation and synchronization.
 /// All repository updates are recorded in a monotonically increasing sequence.
 
+pub mod consumers;
 pub mod events;
+pub mod inspect;
+pub mod integrity;
+pub mod payload;
 pub mod sequencer;
 
+pub use consumers::{ConsumerGuard, ConsumerInfo, FirehoseConsumers};
 pub use events::*;
+pub use inspect::{CommitIntegrity, InspectedEvent};
+pub use integrity::{DuplicateEvent, SeqGap, SequencerHealth};
 pub use sequencer::{Sequencer, SequencerConfig};
 
 use crate::error::PdsResult;
@@ -19,7 +26,7 @@ pub struct SeqRow {
     pub seq: i64,
     pub did: String,
     pub event_type: String,
-    pub event: Vec<u8>,  // CBOR-encoded
+    pub event: Vec<u8>,  // CBOR-encoded (decompressed when read)
     pub invalidated: bool,
     pub sequenced_at: DateTime<Utc>,
 }
@@ -0,0 +1,79 @@
+/// Stored event payload format
+///
+/// `repo_seq.event` holds an event's CBOR encoding, optionally compressed
+/// with zstd. A compressed payload starts with a format byte followed by
+/// the zstd frame. Plain payloads are stored as-is; an event always encodes
+/// as a CBOR map, whose first byte (0xa0..=0xbf) never collides with a
+/// format byte, so rows written before compression was enabled (or with it
+/// turned off) read back unchanged.
+use crate::error::{PdsError, PdsResult};
+
+/// zstd frame of the event's CBOR
+pub const FORMAT_ZSTD_V1: u8 = 0x01;
+
+/// Payload to store for an event's CBOR
+///
+/// With `level` set the CBOR is compressed, unless that doesn't make it
+/// smaller (small identity and account events usually don't shrink).
+pub fn encode(cbor: Vec<u8>, level: Option<i32>) -> PdsResult<Vec<u8>> {
+    let Some(level) = level else {
+        return Ok(cbor);
+    };
+
+    let mut stored = Vec::with_capacity(cbor.len() / 2 + 1);
+    stored.push(FORMAT_ZSTD_V1);
+    zstd::stream::copy_encode(cbor.as_slice(), &mut stored, level)
+        .map_err(|e| PdsError::Internal(format!("Failed to compress event: {}", e)))?;
+
+    Ok(if stored.len() < cbor.len() { stored } else { cbor })
+}
+
+/// An event's CBOR from its stored payload
+pub fn decode(stored: Vec<u8>) -> PdsResult<Vec<u8>> {
+    match stored.first() {
+        Some(&FORMAT_ZSTD_V1) => zstd::stream::decode_all(&stored[1..])
+            .map_err(|e| PdsError::Internal(format!("Failed to decompress event: {}", e))),
+        Some(&format) if format < 0x80 => {
+            Err(PdsError::Internal(format!("Unknown event payload format: {:#04x}", format)))
+        }
+        _ => Ok(stored),
+    }
+}
+
+/// Whether a stored payload is compressed
+pub fn is_compressed(stored: &[u8]) -> bool {
+    stored.first() == Some(&FORMAT_ZSTD_V1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sequencer::events::{CommitEvent, IdentityEvent};
+
+    #[test]
+    fn test_payload_round_trip() {
+        let commit = CommitEvent::new(
+            "did:plc:alice".to_string(),
+            "bafyreicommit".to_string(),
+            "3kabc".to_string(),
+            None,
+            vec![0x42; 4096],
+            Vec::new(),
+        );
+        let cbor = serde_cbor::to_vec(&commit).unwrap();
+
+        let stored = encode(cbor.clone(), Some(3)).unwrap();
+        assert!(is_compressed(&stored));
+        assert!(stored.len() < cbor.len() / 4);
+        assert_eq!(decode(stored).unwrap(), cbor);
+
+        // Compression off, or not worth it: the CBOR is stored as-is
+        assert_eq!(encode(cbor.clone(), None).unwrap(), cbor);
+        let identity = serde_cbor::to_vec(&IdentityEvent::new("did:plc:alice".to_string(), None)).unwrap();
+        let stored = encode(identity.clone(), Some(3)).unwrap();
+        assert!(!is_compressed(&stored));
+        assert_eq!(decode(stored).unwrap(), identity);
+
+        assert!(decode(vec![0x02, 0x00]).is_err());
+    }
+}
@@ -1,17 +1,26 @@
 /// Main Sequencer implementation
+///
+/// Event inserts go through a single writer task that groups inserts arriving
+/// within `batch_window` into one transaction. Seq numbers are assigned in
+/// arrival order, and callers are only answered once the transaction has been
+/// committed with `synchronous = FULL`, so an acknowledged event survives a
+/// crash.
 use crate::{
+    clock::{system_clock, Clock},
     error::{PdsError, PdsResult},
     federation::RelayClient,
+    metrics,
     sequencer::{
         events::{AccountEvent, CommitEvent, IdentityEvent},
-        EventType, SeqEvent, SeqRow,
+        integrity, payload, EventType, SeqEvent, SeqRow, SequencerHealth,
     },
 };
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use serde_cbor;
-use sqlx::{Row, SqlitePool};
+use sqlx::{Connection, Row, SqliteConnection, SqlitePool};
 use std::sync::Arc;
-use tokio::sync::{Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex, OnceCell, RwLock};
 
 /// Sequencer configuration
 #[derive(Debug, Clone)]
@@ -21,6 +30,24 @@ pub struct SequencerConfig {
 
     /// Backfill time window in seconds (how far back cursors can resume)
     pub backfill_limit_secs: i64,
+
+    /// How long the writer waits for more inserts to share a transaction
+    /// (zero writes every event in its own transaction)
+    pub batch_window: Duration,
+
+    /// Maximum number of events written in one transaction
+    pub max_batch_size: usize,
+
+    /// Largest `blocks` CAR embedded in a commit event; bigger commits are
+    /// sequenced as `tooBig`, without blocks or ops
+    pub max_commit_blocks_bytes: usize,
+
+    /// Most ops embedded in a commit event before it is sequenced as `tooBig`
+    pub max_commit_ops: usize,
+
+    /// zstd level stored event payloads are compressed with (`None` stores
+    /// plain CBOR); either kind of row is read back transparently
+    pub compression_level: Option<i32>,
 }
 
 impl Default for SequencerConfig {
@@ -28,10 +55,58 @@ impl Default for SequencerConfig {
         Self {
             max_query_limit: 1000,
             backfill_limit_secs: 14 * 24 * 60 * 60, // 14 days
+            batch_window: Duration::from_millis(5),
+            max_batch_size: 256,
+            // subscribeRepos#commit limits
+            max_commit_blocks_bytes: 2_000_000,
+            max_commit_ops: 200,
+            compression_level: None,
         }
     }
 }
 
+impl SequencerConfig {
+    /// Load from environment variables
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+
+        Self {
+            batch_window: std::env::var("PDS_SEQUENCER_BATCH_WINDOW_MS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .map(Duration::from_millis)
+                .unwrap_or(defaults.batch_window),
+            max_batch_size: std::env::var("PDS_SEQUENCER_MAX_BATCH_SIZE")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|size| *size > 0)
+                .unwrap_or(defaults.max_batch_size),
+            max_commit_blocks_bytes: std::env::var("PDS_SEQUENCER_MAX_COMMIT_BYTES")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_commit_blocks_bytes),
+            max_commit_ops: std::env::var("PDS_SEQUENCER_MAX_COMMIT_OPS")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(defaults.max_commit_ops),
+            compression_level: std::env::var("PDS_SEQUENCER_ZSTD_LEVEL")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .filter(|level| (1..=22).contains(level)),
+            ..defaults
+        }
+    }
+}
+
+/// An event waiting for the batch writer
+struct PendingInsert {
+    did: String,
+    event_type: EventType,
+    event: Vec<u8>,
+    /// Receives the assigned seq once the batch is durable
+    ack: oneshot::Sender<PdsResult<i64>>,
+}
+
 /// Main sequencer - manages event log
 #[derive(Clone)]
 pub struct Sequencer {
@@ -39,6 +114,20 @@ pub struct Sequencer {
     config: SequencerConfig,
     last_seq: Arc<RwLock<Option<i64>>>,
     relay_client: Option<Arc<Mutex<RelayClient>>>,
+    /// Local listeners for identity changes (e.g. identity cache invalidation)
+    identity_tx: broadcast::Sender<IdentityEvent>,
+    /// Local listeners for account status changes
+    account_tx: broadcast::Sender<AccountEvent>,
+    /// Local listeners for commits (e.g. repo webhooks)
+    commit_tx: broadcast::Sender<CommitEvent>,
+    /// Seq of the newest event sequenced here, for waking firehose producers
+    head_tx: Arc<watch::Sender<Option<i64>>>,
+    /// Queue into the batch writer, started on first use
+    writer: Arc<OnceCell<mpsc::Sender<PendingInsert>>>,
+    /// Result of the most recent integrity check
+    last_health: Arc<RwLock<Option<SequencerHealth>>>,
+    /// Source of `sequenced_at` timestamps
+    clock: Arc<dyn Clock>,
 }
 
 impl Sequencer {
@@ -49,6 +138,13 @@ impl Sequencer {
             config,
             last_seq: Arc::new(RwLock::new(None)),
             relay_client: None,
+            identity_tx: broadcast::channel(256).0,
+            account_tx: broadcast::channel(256).0,
+            commit_tx: broadcast::channel(256).0,
+            head_tx: Arc::new(watch::channel(None).0),
+            writer: Arc::new(OnceCell::new()),
+            last_health: Arc::new(RwLock::new(None)),
+            clock: system_clock(),
         }
     }
 
@@ -59,23 +155,87 @@ impl Sequencer {
             config,
             last_seq: Arc::new(RwLock::new(None)),
             relay_client,
+            identity_tx: broadcast::channel(256).0,
+            account_tx: broadcast::channel(256).0,
+            commit_tx: broadcast::channel(256).0,
+            head_tx: Arc::new(watch::channel(None).0),
+            writer: Arc::new(OnceCell::new()),
+            last_health: Arc::new(RwLock::new(None)),
+            clock: system_clock(),
         }
     }
 
+    /// Use `clock` to timestamp events
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
     /// Sequence a commit event
-    pub async fn sequence_commit(&self, evt: CommitEvent) -> PdsResult<i64> {
-        let event_bytes = serde_cbor::to_vec(&evt)
+    ///
+    /// A commit over the block or op budget is stored as `tooBig`, so relays
+    /// fetch the repo with getRepo instead of receiving a giant frame.
+    /// Local subscribers still get it whole.
+    pub async fn sequence_commit(&self, mut evt: CommitEvent) -> PdsResult<i64> {
+        let reason = if evt.blocks.len() > self.config.max_commit_blocks_bytes {
+            Some("blocks")
+        } else if evt.ops.len() > self.config.max_commit_ops {
+            Some("ops")
+        } else {
+            None
+        };
+        let truncated = reason.map(|reason| {
+            tracing::info!(
+                repo = %evt.repo,
+                commit = %evt.commit,
+                blocks_bytes = evt.blocks.len(),
+                ops = evt.ops.len(),
+                "Sequencing oversized commit as tooBig"
+            );
+            metrics::record_sequencer_too_big(reason);
+
+            // Copy everything but the blocks, which are about to be dropped anyway
+            let blocks = std::mem::take(&mut evt.blocks);
+            let mut truncated = evt.clone();
+            evt.blocks = blocks;
+            truncated.mark_too_big();
+            truncated
+        });
+        let stored = truncated.as_ref().unwrap_or(&evt);
+
+        let event_bytes = serde_cbor::to_vec(stored)
             .map_err(|e| PdsError::Internal(format!("Failed to encode commit event: {}", e)))?;
 
         let seq = self.insert_event(&evt.repo, EventType::Commit, event_bytes)
             .await?;
 
+        // Commits carry their blocks, so only clone them for someone listening
+        if self.commit_tx.receiver_count() > 0 {
+            let _ = self.commit_tx.send(evt.clone());
+        }
+
         // Publish to relay if configured
         self.publish_to_relay("commit", &evt.repo, seq, Some(&evt.commit)).await;
 
         Ok(seq)
     }
 
+    /// Subscribe to commits as they are sequenced
+    ///
+    /// Commits stored as `tooBig` arrive with their ops and blocks.
+    pub fn subscribe_commits(&self) -> broadcast::Receiver<CommitEvent> {
+        self.commit_tx.subscribe()
+    }
+
+    /// Watch the seq of the newest event sequenced by this process
+    ///
+    /// Changes as soon as an event is durable, so readers of the log can wait
+    /// for it instead of polling. Events written by another process (e.g. an
+    /// admin replay run against the same database) don't show up here.
+    pub fn watch_head(&self) -> watch::Receiver<Option<i64>> {
+        self.head_tx.subscribe()
+    }
+
     /// Sequence an identity event
     pub async fn sequence_identity(&self, evt: IdentityEvent) -> PdsResult<i64> {
         let event_bytes = serde_cbor::to_vec(&evt)
@@ -84,12 +244,20 @@ impl Sequencer {
         let seq = self.insert_event(&evt.did, EventType::Identity, event_bytes)
             .await?;
 
+        // Notify local listeners (no receivers is fine)
+        let _ = self.identity_tx.send(evt.clone());
+
         // Publish to relay if configured
         self.publish_to_relay("identity", &evt.did, seq, None).await;
 
         Ok(seq)
     }
 
+    /// Subscribe to identity events as they are sequenced
+    pub fn subscribe_identity(&self) -> broadcast::Receiver<IdentityEvent> {
+        self.identity_tx.subscribe()
+    }
+
     /// Sequence an account event
     pub async fn sequence_account(&self, evt: AccountEvent) -> PdsResult<i64> {
         let event_bytes = serde_cbor::to_vec(&evt)
@@ -98,40 +266,85 @@ impl Sequencer {
         let seq = self.insert_event(&evt.did, EventType::Account, event_bytes)
             .await?;
 
+        // Notify local listeners (no receivers is fine)
+        let _ = self.account_tx.send(evt.clone());
+
         // Publish to relay if configured
         self.publish_to_relay("account", &evt.did, seq, None).await;
 
         Ok(seq)
     }
 
+    /// Subscribe to account events as they are sequenced
+    pub fn subscribe_account(&self) -> broadcast::Receiver<AccountEvent> {
+        self.account_tx.subscribe()
+    }
+
     /// Insert event into database
+    ///
+    /// Returns once the event is durably committed.
     async fn insert_event(&self, did: &str, event_type: EventType, event: Vec<u8>) -> PdsResult<i64> {
-        let now = Utc::now().to_rfc3339();
+        let event = payload::encode(event, self.config.compression_level)?;
 
-        let result = sqlx::query(
-            r#"
-            INSERT INTO repo_seq (did, event_type, event, sequenced_at)
-            VALUES (?1, ?2, ?3, ?4)
-            RETURNING seq
-            "#,
-        )
-        .bind(did)
-        .bind(event_type.as_str())
-        .bind(&event)
-        .bind(&now)
-        .fetch_one(&self.db)
-        .await
-        .map_err(|e| PdsError::Database(e))?;
+        if self.config.batch_window.is_zero() {
+            let mut conn = self.db.acquire().await.map_err(PdsError::Database)?;
+            let seqs = write_batch(&mut conn, &[(did, event_type, event.as_slice())], self.clock.now()).await?;
+            return self.acked(seqs[0]).await;
+        }
 
-        let seq: i64 = result.try_get("seq")?;
+        let writer = self
+            .writer
+            .get_or_try_init(|| self.start_writer())
+            .await?;
 
-        // Update last seq
-        let mut last = self.last_seq.write().await;
-        *last = Some(seq);
+        let (ack, rx) = oneshot::channel();
+        writer
+            .send(PendingInsert {
+                did: did.to_string(),
+                event_type,
+                event,
+                ack,
+            })
+            .await
+            .map_err(|_| PdsError::Internal("Sequencer writer stopped".to_string()))?;
 
+        let seq = rx
+            .await
+            .map_err(|_| PdsError::Internal("Sequencer writer stopped".to_string()))??;
+        self.acked(seq).await
+    }
+
+    /// Record the latest acknowledged seq
+    async fn acked(&self, seq: i64) -> PdsResult<i64> {
+        let mut last = self.last_seq.write().await;
+        if last.map_or(true, |last| seq > last) {
+            *last = Some(seq);
+            self.head_tx.send_replace(Some(seq));
+        }
         Ok(seq)
     }
 
+    /// Spawn the batch writer on a dedicated connection
+    async fn start_writer(&self) -> PdsResult<mpsc::Sender<PendingInsert>> {
+        let mut conn = self.db.acquire().await.map_err(PdsError::Database)?.detach();
+        // Commits must reach disk before callers are acknowledged
+        sqlx::query("PRAGMA synchronous = FULL")
+            .execute(&mut conn)
+            .await
+            .map_err(PdsError::Database)?;
+
+        let (tx, rx) = mpsc::channel(self.config.max_batch_size * 4);
+        tokio::spawn(run_writer(
+            conn,
+            rx,
+            self.config.batch_window,
+            self.config.max_batch_size,
+            self.clock.clone(),
+        ));
+
+        Ok(tx)
+    }
+
     /// Get current maximum sequence number
     pub async fn current_seq(&self) -> PdsResult<Option<i64>> {
         let result = sqlx::query("SELECT MAX(seq) as max_seq FROM repo_seq WHERE invalidated = 0")
@@ -142,6 +355,46 @@ impl Sequencer {
         Ok(result.try_get("max_seq").ok())
     }
 
+    /// Check the event log for gaps and duplicate commits
+    ///
+    /// With `repair`, duplicate commits are invalidated. Problems are logged
+    /// and exported as metrics, and the result is kept for [`Self::last_health`].
+    pub async fn check_integrity(&self, repair: bool) -> PdsResult<SequencerHealth> {
+        let health = integrity::check(&self.db, repair).await?;
+
+        for gap in &health.gaps {
+            tracing::warn!(
+                after = gap.after,
+                before = gap.before,
+                missing = gap.missing,
+                "sequencer_gap"
+            );
+        }
+        for duplicate in &health.duplicates {
+            tracing::warn!(
+                did = %duplicate.did,
+                original_seq = duplicate.original_seq,
+                duplicates = ?duplicate.duplicate_seqs,
+                repaired = repair,
+                "sequencer_duplicate_commit"
+            );
+        }
+        metrics::record_sequencer_integrity(&health);
+
+        *self.last_health.write().await = Some(health.clone());
+        Ok(health)
+    }
+
+    /// Result of the most recent integrity check, if one has run
+    pub async fn last_health(&self) -> Option<SequencerHealth> {
+        self.last_health.read().await.clone()
+    }
+
+    /// Number of seqs between two events that are missing from the log
+    pub async fn missing_between(&self, after: i64, before: i64) -> PdsResult<i64> {
+        integrity::missing_between(&self.db, after, before).await
+    }
+
     /// Get next event after cursor
     pub async fn next_event(&self, cursor: i64) -> PdsResult<Option<SeqRow>> {
         let result = sqlx::query(
@@ -165,6 +418,26 @@ impl Sequencer {
         }
     }
 
+    /// Get the next event of one repo after cursor
+    pub async fn next_event_for_did(&self, did: &str, cursor: i64) -> PdsResult<Option<SeqRow>> {
+        let result = sqlx::query(
+            r#"
+            SELECT seq, did, event_type, event, invalidated, sequenced_at
+            FROM repo_seq
+            WHERE did = ?1 AND seq > ?2 AND invalidated = 0
+            ORDER BY seq ASC
+            LIMIT 1
+            "#,
+        )
+        .bind(did)
+        .bind(cursor)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| PdsError::Database(e))?;
+
+        result.map(|row| self.row_to_seq_row(row)).transpose()
+    }
+
     /// Request events in a sequence range
     pub async fn request_seq_range(
         &self,
@@ -211,15 +484,13 @@ impl Sequencer {
         Ok(events)
     }
 
-    /// Convert database row to SeqRow
+    /// Convert database row to SeqRow, decompressing the payload
     fn row_to_seq_row(&self, row: sqlx::sqlite::SqliteRow) -> PdsResult<SeqRow> {
-        use chrono::DateTime;
-
         Ok(SeqRow {
             seq: row.try_get("seq")?,
             did: row.try_get("did")?,
             event_type: row.try_get("event_type")?,
-            event: row.try_get("event")?,
+            event: payload::decode(row.try_get("event")?)?,
             invalidated: row.try_get::<i32, _>("invalidated")? != 0,
             sequenced_at: {
                 let time_str: String = row.try_get("sequenced_at")?;
@@ -276,7 +547,7 @@ impl Sequencer {
                 did: did.to_string(),
                 seq,
                 commit: commit_cid.map(|cid| serde_json::json!({ "cid": cid })),
-                time: Utc::now().to_rfc3339(),
+                time: self.clock.now().to_rfc3339(),
             };
 
             let client = relay_client.clone();
@@ -318,11 +589,139 @@ impl Sequencer {
 
         Ok(events)
     }
+
+    /// Raw events with `after < seq <= until`, oldest first, including
+    /// invalidated ones
+    pub async fn list_rows(
+        &self,
+        after: i64,
+        until: Option<i64>,
+        did: Option<&str>,
+        limit: i64,
+    ) -> PdsResult<Vec<SeqRow>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT seq, did, event_type, event, invalidated, sequenced_at
+            FROM repo_seq
+            WHERE seq > ?1 AND (?2 IS NULL OR seq <= ?2) AND (?3 IS NULL OR did = ?3)
+            ORDER BY seq ASC
+            LIMIT ?4
+            "#,
+        )
+        .bind(after)
+        .bind(until)
+        .bind(did)
+        .bind(limit.clamp(1, self.config.max_query_limit))
+        .fetch_all(&self.db)
+        .await
+        .map_err(PdsError::Database)?;
+
+        rows.into_iter().map(|row| self.row_to_seq_row(row)).collect()
+    }
+
+    /// Sequence copies of valid events again, returning `(original, new)` seqs
+    ///
+    /// Connected subscribers receive the copies as new events; the originals
+    /// are left as they are. Used to re-deliver a range to relays that
+    /// missed or mishandled it.
+    pub async fn replay(&self, rows: &[SeqRow]) -> PdsResult<Vec<(i64, i64)>> {
+        let mut replayed = Vec::new();
+        for row in rows.iter().filter(|row| !row.invalidated) {
+            let event_type = EventType::from(row.event_type.clone());
+            let seq = self.insert_event(&row.did, event_type.clone(), row.event.clone()).await?;
+
+            let commit = match event_type {
+                EventType::Commit => serde_cbor::from_slice::<CommitEvent>(&row.event).ok(),
+                _ => None,
+            };
+            self.publish_to_relay(event_type.as_str(), &row.did, seq, commit.as_ref().map(|c| c.commit.as_str()))
+                .await;
+            replayed.push((row.seq, seq));
+        }
+        Ok(replayed)
+    }
+}
+
+/// Batch writer loop; exits when every sender is gone
+async fn run_writer(
+    mut conn: SqliteConnection,
+    mut rx: mpsc::Receiver<PendingInsert>,
+    window: Duration,
+    max_batch_size: usize,
+    clock: Arc<dyn Clock>,
+) {
+    while let Some(first) = rx.recv().await {
+        let mut batch = vec![first];
+        let deadline = tokio::time::Instant::now() + window;
+
+        while batch.len() < max_batch_size {
+            match tokio::time::timeout_at(deadline, rx.recv()).await {
+                Ok(Some(pending)) => batch.push(pending),
+                Ok(None) | Err(_) => break,
+            }
+        }
+
+        metrics::record_sequencer_batch(batch.len());
+        let rows: Vec<_> = batch
+            .iter()
+            .map(|p| (p.did.as_str(), p.event_type.clone(), p.event.as_slice()))
+            .collect();
+
+        match write_batch(&mut conn, &rows, clock.now()).await {
+            Ok(seqs) => {
+                for (pending, seq) in batch.into_iter().zip(seqs) {
+                    let _ = pending.ack.send(Ok(seq));
+                }
+            }
+            Err(e) => {
+                tracing::error!("Failed to write sequencer batch of {}: {}", batch.len(), e);
+                let message = e.to_string();
+                for pending in batch {
+                    let _ = pending
+                        .ack
+                        .send(Err(PdsError::Internal(format!("Failed to sequence event: {}", message))));
+                }
+            }
+        }
+    }
+}
+
+/// Insert events in one transaction, returning their seqs in order
+async fn write_batch(
+    conn: &mut SqliteConnection,
+    rows: &[(&str, EventType, &[u8])],
+    sequenced_at: DateTime<Utc>,
+) -> PdsResult<Vec<i64>> {
+    let now = sequenced_at.to_rfc3339();
+    let mut tx = conn.begin().await.map_err(PdsError::Database)?;
+    let mut seqs = Vec::with_capacity(rows.len());
+
+    for &(did, ref event_type, event) in rows {
+        let seq: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO repo_seq (did, event_type, event, sequenced_at)
+            VALUES (?1, ?2, ?3, ?4)
+            RETURNING seq
+            "#,
+        )
+        .bind(did)
+        .bind(event_type.as_str())
+        .bind(event)
+        .bind(&now)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(PdsError::Database)?;
+        seqs.push(seq);
+    }
+
+    tx.commit().await.map_err(PdsError::Database)?;
+    Ok(seqs)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::sequencer::events::{CommitOp, OpAction};
 
     async fn create_test_sequencer() -> Sequencer {
         let db = SqlitePool::connect(":memory:").await.unwrap();
@@ -364,6 +763,143 @@ mod tests {
         assert_eq!(seq, 1);
     }
 
+    #[tokio::test]
+    async fn test_watch_head() {
+        let sequencer = create_test_sequencer().await;
+        let mut head = sequencer.watch_head();
+        assert_eq!(*head.borrow(), None);
+
+        let waiter = tokio::spawn(async move {
+            head.changed().await.unwrap();
+            *head.borrow_and_update()
+        });
+        let evt = CommitEvent::new("did:plc:test".to_string(), "bafyrei1".to_string(), "1".to_string(), None, vec![], vec![]);
+        let seq = sequencer.sequence_commit(evt).await.unwrap();
+
+        let seen = tokio::time::timeout(Duration::from_secs(1), waiter).await.unwrap().unwrap();
+        assert_eq!(seen, Some(seq));
+    }
+
+    #[tokio::test]
+    async fn test_sequence_commit_too_big() {
+        let mut sequencer = create_test_sequencer().await;
+        sequencer.config.max_commit_blocks_bytes = 16;
+        sequencer.config.max_commit_ops = 2;
+        let op = |rkey: &str| CommitOp {
+            action: OpAction::Create,
+            path: format!("app.bsky.feed.post/{}", rkey),
+            cid: Some("bafyreirecord".to_string()),
+        };
+
+        let small = CommitEvent::new("did:plc:test".to_string(), "bafyrei1".to_string(), "1".to_string(), None, vec![0; 16], vec![op("a")]);
+        let big_blocks = CommitEvent::new("did:plc:test".to_string(), "bafyrei2".to_string(), "2".to_string(), None, vec![0; 17], vec![op("b")]);
+        let many_ops = CommitEvent::new("did:plc:test".to_string(), "bafyrei3".to_string(), "3".to_string(), None, vec![], vec![op("c"), op("d"), op("e")]);
+        let mut local = sequencer.subscribe_commits();
+        for evt in [small, big_blocks, many_ops] {
+            sequencer.sequence_commit(evt).await.unwrap();
+        }
+
+        let commits: Vec<CommitEvent> = sequencer
+            .list_rows(0, None, None, 10)
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| serde_cbor::from_slice(&row.event).unwrap())
+            .collect();
+        assert!(!commits[0].too_big);
+        assert_eq!(commits[0].blocks.len(), 16);
+        for commit in &commits[1..] {
+            assert!(commit.too_big);
+            assert!(commit.blocks.is_empty());
+            assert!(commit.ops.is_empty());
+        }
+        // Consumers still learn the new head
+        assert_eq!(commits[2].commit, "bafyrei3");
+        assert_eq!(commits[2].rev, "3");
+
+        // Local subscribers (webhooks, the ActivityPub bridge) get them whole
+        local.recv().await.unwrap();
+        let big_blocks = local.recv().await.unwrap();
+        assert!(!big_blocks.too_big);
+        assert_eq!(big_blocks.blocks.len(), 17);
+        let many_ops = local.recv().await.unwrap();
+        assert_eq!(many_ops.ops.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_compressed_payloads() {
+        let mut sequencer = create_test_sequencer().await;
+        let commit = |rev: &str| {
+            CommitEvent::new("did:plc:test".to_string(), "bafyrei1".to_string(), rev.to_string(), None, vec![7; 8192], vec![])
+        };
+
+        // Rows written before compression was turned on stay readable
+        sequencer.sequence_commit(commit("1")).await.unwrap();
+        sequencer.config.compression_level = Some(3);
+        sequencer.sequence_commit(commit("2")).await.unwrap();
+
+        let stored: Vec<Vec<u8>> = sqlx::query_scalar("SELECT event FROM repo_seq ORDER BY seq")
+            .fetch_all(&sequencer.db)
+            .await
+            .unwrap();
+        assert!(!payload::is_compressed(&stored[0]));
+        assert!(payload::is_compressed(&stored[1]));
+        assert!(stored[1].len() < stored[0].len() / 4);
+
+        let mut cursor = 0;
+        for rev in ["1", "2"] {
+            let row = sequencer.next_event(cursor).await.unwrap().unwrap();
+            let evt: CommitEvent = serde_cbor::from_slice(&row.event).unwrap();
+            assert_eq!(evt.rev, rev);
+            assert_eq!(evt.blocks.len(), 8192);
+            cursor = row.seq;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sequenced_at_uses_clock() {
+        let clock = Arc::new(crate::clock::MockClock::fixed());
+        let sequencer = create_test_sequencer().await.with_clock(clock.clone());
+
+        let evt = CommitEvent::new(
+            "did:plc:test".to_string(),
+            "bafyrei123".to_string(),
+            "3".to_string(),
+            None,
+            vec![],
+            vec![],
+        );
+        sequencer.sequence_commit(evt).await.unwrap();
+
+        let row = sequencer.next_event(0).await.unwrap().unwrap();
+        assert_eq!(row.sequenced_at, clock.now());
+    }
+
+    #[tokio::test]
+    async fn test_list_and_replay_rows() {
+        let sequencer = create_test_sequencer().await;
+        for did in ["did:plc:a", "did:plc:b", "did:plc:a"] {
+            let evt = CommitEvent::new(did.to_string(), "bafyrei123".to_string(), "3".to_string(), None, vec![], vec![]);
+            sequencer.sequence_commit(evt).await.unwrap();
+        }
+        sqlx::query("UPDATE repo_seq SET invalidated = 1 WHERE seq = 3")
+            .execute(&sequencer.db)
+            .await
+            .unwrap();
+
+        let rows = sequencer.list_rows(0, None, Some("did:plc:a"), 100).await.unwrap();
+        assert_eq!(rows.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 3]);
+        assert!(rows[1].invalidated);
+        assert_eq!(sequencer.list_rows(1, Some(2), None, 100).await.unwrap().len(), 1);
+
+        // Invalidated events are not replayed; copies keep the payload
+        let replayed = sequencer.replay(&rows).await.unwrap();
+        assert_eq!(replayed, vec![(1, 4)]);
+        let copy = sequencer.next_event(3).await.unwrap().unwrap();
+        assert_eq!(copy.did, "did:plc:a");
+        assert_eq!(copy.event, rows[0].event);
+    }
+
     #[tokio::test]
     async fn test_current_seq() {
         let sequencer = create_test_sequencer().await;
@@ -406,4 +942,169 @@ mod tests {
         let events = sequencer.request_seq_range(Some(2), Some(4), None).await.unwrap();
         assert_eq!(events.len(), 2); // seq 3 and 4
     }
+
+    #[tokio::test]
+    async fn test_next_event_for_did() {
+        let sequencer = create_test_sequencer().await;
+
+        for did in ["did:plc:a", "did:plc:b", "did:plc:b", "did:plc:a"] {
+            let evt = CommitEvent::new(
+                did.to_string(),
+                "bafyrei1".to_string(),
+                "3".to_string(),
+                None,
+                vec![],
+                vec![],
+            );
+            sequencer.sequence_commit(evt).await.unwrap();
+        }
+
+        let first = sequencer.next_event_for_did("did:plc:a", 0).await.unwrap().unwrap();
+        assert_eq!(first.seq, 1);
+        let next = sequencer.next_event_for_did("did:plc:a", first.seq).await.unwrap().unwrap();
+        assert_eq!(next.seq, 4);
+        assert_eq!(next.did, "did:plc:a");
+        assert!(sequencer.next_event_for_did("did:plc:a", 4).await.unwrap().is_none());
+        assert!(sequencer.next_event_for_did("did:plc:c", 0).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_inserts_are_batched_in_order() {
+        let sequencer = create_test_sequencer().await;
+
+        let handles: Vec<_> = (0..50)
+            .map(|i| {
+                let sequencer = sequencer.clone();
+                tokio::spawn(async move {
+                    let evt = CommitEvent::new(
+                        format!("did:plc:test{}", i),
+                        format!("bafyrei{}", i),
+                        "3".to_string(),
+                        None,
+                        vec![],
+                        vec![],
+                    );
+                    sequencer.sequence_commit(evt).await.unwrap()
+                })
+            })
+            .collect();
+
+        let mut seqs = Vec::new();
+        for handle in handles {
+            seqs.push(handle.await.unwrap());
+        }
+        seqs.sort();
+        assert_eq!(seqs, (1..=50).collect::<Vec<i64>>());
+        assert_eq!(sequencer.current_seq().await.unwrap(), Some(50));
+
+        // Sequential callers see strictly increasing seqs
+        let a = sequencer.sequence_identity(IdentityEvent::new("did:plc:a".to_string(), None)).await.unwrap();
+        let b = sequencer.sequence_identity(IdentityEvent::new("did:plc:a".to_string(), None)).await.unwrap();
+        assert!(b > a);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check() {
+        let sequencer = create_test_sequencer().await;
+
+        for i in 1..=5 {
+            let evt = CommitEvent::new(
+                "did:plc:test".to_string(),
+                format!("bafyrei{}", i),
+                "3".to_string(),
+                None,
+                vec![],
+                vec![],
+            );
+            sequencer.sequence_commit(evt).await.unwrap();
+        }
+        assert!(sequencer.check_integrity(false).await.unwrap().is_healthy());
+
+        // Lose seq 3 and replay seq 2 as seq 6
+        sqlx::query("DELETE FROM repo_seq WHERE seq = 3")
+            .execute(&sequencer.db)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO repo_seq (did, event_type, event, sequenced_at)
+             SELECT did, event_type, event, sequenced_at FROM repo_seq WHERE seq = 2",
+        )
+        .execute(&sequencer.db)
+        .await
+        .unwrap();
+
+        let health = sequencer.check_integrity(true).await.unwrap();
+        assert_eq!(health.gaps, vec![crate::sequencer::SeqGap { after: 2, before: 4, missing: 1 }]);
+        assert_eq!(health.missing_events, 1);
+        assert_eq!(health.duplicates.len(), 1);
+        assert_eq!(health.duplicates[0].original_seq, 2);
+        assert_eq!(health.duplicates[0].duplicate_seqs, vec![6]);
+        assert_eq!(health.invalidated_duplicates, 1);
+        assert!(!health.is_healthy());
+
+        // The duplicate is gone for good; the gap stays reported
+        let health = sequencer.check_integrity(true).await.unwrap();
+        assert!(health.duplicates.is_empty());
+        assert_eq!(health.missing_events, 1);
+        assert_eq!(sequencer.last_health().await.unwrap().missing_events, 1);
+
+        assert_eq!(sequencer.missing_between(2, 4).await.unwrap(), 1);
+        // Skipping the invalidated seq 6 is not a gap
+        assert_eq!(sequencer.missing_between(5, 7).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_integrity_check_across_compression() {
+        let sequencer = create_test_sequencer().await;
+        let evt = CommitEvent::new(
+            "did:plc:test".to_string(),
+            "bafyrei1".to_string(),
+            "3".to_string(),
+            None,
+            vec![0x42; 4096],
+            vec![],
+        );
+        let seq = sequencer.sequence_commit(evt).await.unwrap();
+
+        // Replay it compressed, as if compression was turned on in between
+        let stored: Vec<u8> = sqlx::query_scalar("SELECT event FROM repo_seq WHERE seq = ?1")
+            .bind(seq)
+            .fetch_one(&sequencer.db)
+            .await
+            .unwrap();
+        let compressed = payload::encode(payload::decode(stored.clone()).unwrap(), Some(3)).unwrap();
+        assert_ne!(compressed, stored);
+        sqlx::query(
+            "INSERT INTO repo_seq (did, event_type, event, sequenced_at)
+             SELECT did, event_type, ?1, sequenced_at FROM repo_seq WHERE seq = ?2",
+        )
+        .bind(&compressed)
+        .bind(seq)
+        .execute(&sequencer.db)
+        .await
+        .unwrap();
+
+        let health = sequencer.check_integrity(true).await.unwrap();
+        assert_eq!(health.duplicates.len(), 1);
+        assert_eq!(health.duplicates[0].original_seq, seq);
+        assert_eq!(health.duplicates[0].duplicate_seqs, vec![seq + 1]);
+        assert_eq!(health.invalidated_duplicates, 1);
+    }
+
+    #[tokio::test]
+    async fn test_unbatched_insert() {
+        let mut sequencer = create_test_sequencer().await;
+        sequencer.config.batch_window = Duration::ZERO;
+
+        let evt = CommitEvent::new(
+            "did:plc:test".to_string(),
+            "bafyrei123".to_string(),
+            "3".to_string(),
+            None,
+            vec![],
+            vec![],
+        );
+        assert_eq!(sequencer.sequence_commit(evt).await.unwrap(), 1);
+        assert!(sequencer.writer.get().is_none());
+    }
 }
@@ -1,22 +1,32 @@
 /// HTTP server setup and routing
 use crate::{
-    api::middleware::check_account_moderation,
+    api::middleware::{
+        check_account_moderation, enforce_api_token_scopes, enforce_body_limit, pretty_json,
+        request_host, require_admin_network_token, resolve_client_ip, security_headers,
+    },
+    api::replica::replica_routing,
+    config::{CorsConfig, ListenerConfig, ListenerRole},
     context::AppContext,
     error::{PdsError, PdsResult},
     metrics,
     rate_limit::rate_limit_middleware,
+    telemetry::{make_request_span, REQUEST_ID_HEADER},
 };
 use axum::{
-    http::{header, Method, StatusCode},
+    extract::DefaultBodyLimit,
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
     middleware,
     response::{IntoResponse, Json, Response},
     routing::get,
     Router,
 };
 use serde_json::json;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{collections::HashSet, net::SocketAddr, time::Duration};
 use tower_http::{
     compression::CompressionLayer,
-    cors::{Any, CorsLayer},
+    cors::{AllowOrigin, Any, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
     services::ServeDir,
     trace::TraceLayer,
 };
@@ -25,38 +35,141 @@ use tracing::info;
 /// Build the main application router
 /// Returns Router<()> because state is already provided
 pub fn build_router(ctx: AppContext) -> Router {
-    // Create CORS layer
-    let cors = CorsLayer::new()
-        .allow_origin(Any)
-        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]);
+    build_router_for_role(ctx, ListenerRole::All)
+}
 
-    // Static file serving for admin panel
-    // Must come AFTER API routes to not conflict with /oauth/admin/* endpoints
-    let admin_static = Router::new()
-        .nest_service("/admin", ServeDir::new("static/admin"));
+/// Build the router for a listener role
+pub fn build_router_for_role(ctx: AppContext, role: ListenerRole) -> Router {
+    // With a dedicated admin listener, public listeners omit admin routes entirely
+    let include_admin = !ctx.config.admin_listener_only();
+
+    match role {
+        ListenerRole::Metrics => Router::new()
+            .route("/metrics", get(metrics_handler))
+            .fallback(not_found),
+        ListenerRole::All => {
+            build_api_router(ctx, include_admin).route("/metrics", get(metrics_handler))
+        }
+        ListenerRole::Api => build_api_router(ctx, include_admin),
+        ListenerRole::Admin => build_admin_router(ctx),
+    }
+}
 
-    // Build router with middleware
-    Router::new()
-        // Metrics endpoint (no middleware)
-        .route("/metrics", get(metrics_handler))
+/// Build the API router (everything except the metrics endpoint)
+fn build_api_router(ctx: AppContext, include_admin: bool) -> Router {
+    let mut routes = Router::new()
         // Server description endpoint
         .route("/xrpc/com.atproto.server.describeServer", get(describe_server))
         // Well-known endpoints for DID resolution
         .merge(crate::api::well_known::routes())
         // API routes (Phase 2) - merge before with_state
-        .merge(crate::api::routes())
-        // Provide state - converts Router<AppContext> to Router<()>
-        .with_state(ctx.clone())
+        .merge(crate::api::public_routes());
+
+    if include_admin {
+        routes = routes.merge(gated_admin_routes(ctx.clone()));
+    }
+
+    // Provide state - converts Router<AppContext> to Router<()>
+    let mut router = routes.with_state(ctx.clone());
+
+    if include_admin {
         // Merge admin static files (after with_state so it doesn't need state)
-        .merge(admin_static)
+        router = router.merge(admin_static());
+    }
+
+    with_common_layers(router, ctx)
+}
+
+/// Build the internal-only admin router
+fn build_admin_router(ctx: AppContext) -> Router {
+    let router = gated_admin_routes(ctx.clone())
+        .with_state(ctx.clone())
+        .merge(admin_static());
+
+    with_common_layers(router, ctx)
+}
+
+/// Admin API routes behind the optional network token check
+fn gated_admin_routes(ctx: AppContext) -> Router<AppContext> {
+    crate::api::admin_routes().route_layer(middleware::from_fn_with_state(
+        ctx,
+        require_admin_network_token,
+    ))
+}
+
+/// Static file serving for admin panel
+// Must come AFTER API routes to not conflict with /oauth/admin/* endpoints
+fn admin_static() -> Router {
+    Router::new().nest_service("/admin", ServeDir::new("static/admin"))
+}
+
+/// Build the CORS layer from configuration
+fn cors_layer(config: &CorsConfig) -> CorsLayer {
+    let origins = if config.allows_any_origin() {
+        AllowOrigin::from(Any)
+    } else {
+        // Origins are checked in ServerConfig::validate
+        AllowOrigin::list(
+            config
+                .allowed_origins
+                .iter()
+                .filter_map(|origin| HeaderValue::from_str(origin).ok()),
+        )
+    };
+
+    let cors = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_credentials(config.allow_credentials)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::AUTHORIZATION,
+            REQUEST_ID_HEADER,
+            HeaderName::from_static("atproto-proxy"),
+            HeaderName::from_static("atproto-accept-labelers"),
+        ])
+        .expose_headers([
+            REQUEST_ID_HEADER,
+            HeaderName::from_static("atproto-repo-rev"),
+            HeaderName::from_static("atproto-content-labelers"),
+            HeaderName::from_static("atproto-upstream-lag"),
+        ]);
+
+    match config.max_age_secs {
+        Some(secs) => cors.max_age(Duration::from_secs(secs)),
+        None => cors,
+    }
+}
+
+/// Apply the middleware stack shared by API and admin listeners
+fn with_common_layers(router: Router, ctx: AppContext) -> Router {
+    let cors = cors_layer(&ctx.config.cors);
+
+    router
+        // Body sizes are limited per route by enforce_body_limit, not axum's 2MB default
+        .layer(DefaultBodyLimit::disable())
+        // Pretty-print JSON bodies when configured (inside compression)
+        .layer(middleware::from_fn_with_state(ctx.clone(), pretty_json))
+        // Keep API tokens within their scopes (uses the session found by the moderation check)
+        .layer(middleware::from_fn(enforce_api_token_scopes))
         // Apply moderation check middleware (checks if account is suspended/taken down)
         .layer(middleware::from_fn_with_state(ctx.clone(), check_account_moderation))
         // Apply rate limiting middleware (after state so it can access AppContext)
-        .layer(middleware::from_fn_with_state(ctx, rate_limit_middleware))
+        .layer(middleware::from_fn_with_state(ctx.clone(), rate_limit_middleware))
+        // On a read replica, forwarded requests are limited and moderated by the primary
+        .layer(middleware::from_fn_with_state(ctx.clone(), replica_routing))
+        // Before anything reads the body, replicas included
+        .layer(middleware::from_fn_with_state(ctx.clone(), enforce_body_limit))
+        // Outside rate limiting so rejected requests carry the headers too
+        .layer(middleware::from_fn_with_state(ctx.clone(), security_headers))
+        // Client IP for everything inside: blocklist, signup caps, rate limits
+        .layer(middleware::from_fn_with_state(ctx, resolve_client_ip))
         .layer(cors)
         .layer(CompressionLayer::new())
-        .layer(TraceLayer::new_for_http())
+        // Request span tagged with the request ID (outermost, so every log line carries it)
+        .layer(TraceLayer::new_for_http().make_span_with(make_request_span::<axum::body::Body>))
+        .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+        .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
         .fallback(not_found)
 }
 
@@ -71,18 +184,48 @@ async fn metrics_handler() -> Response {
 }
 
 /// Server description handler (com.atproto.server.describeServer)
+///
+/// Requests addressed to a virtual host list that host's domain first and
+/// report its invite requirement. Policy links, the contact address and
+/// the phone verification flag come from `DescribeServerConfig`.
 async fn describe_server(
     axum::extract::State(ctx): axum::extract::State<AppContext>,
+    headers: axum::http::HeaderMap,
 ) -> Json<serde_json::Value> {
-    Json(json!({
-        "did": ctx.service_did(),
-        "availableUserDomains": ctx.config.identity.service_handle_domains,
-        "inviteCodeRequired": ctx.config.invites.required,
-        "links": {
-            "privacyPolicy": null,
-            "termsOfService": null
+    let mut domains = ctx.config.identity.service_handle_domains.clone();
+    let mut invite_required = ctx.config.invites.required;
+
+    let vhost = request_host(&headers).and_then(|host| ctx.config.virtual_host(&host));
+    if let Some(vhost) = vhost {
+        if let Some(pos) = domains.iter().position(|d| d.trim_start_matches('.') == vhost.domain) {
+            let domain = domains.remove(pos);
+            domains.insert(0, domain);
         }
-    }))
+        invite_required = vhost.invite_required.unwrap_or(invite_required);
+    }
+    // Open signups are locked down after a burst
+    invite_required |= ctx.signup_throttle.in_lockdown();
+
+    let info = &ctx.config.describe_server;
+    let mut description = json!({
+        "did": ctx.service_did(),
+        "availableUserDomains": domains,
+        "inviteCodeRequired": invite_required,
+        "phoneVerificationRequired": info.phone_verification_required,
+        "links": {},
+    });
+    // Unset links and contact are left out rather than sent as null
+    if let Some(url) = &info.privacy_policy_url {
+        description["links"]["privacyPolicy"] = json!(url);
+    }
+    if let Some(url) = &info.terms_of_service_url {
+        description["links"]["termsOfService"] = json!(url);
+    }
+    if let Some(email) = &info.contact_email {
+        description["contact"] = json!({ "email": email });
+    }
+
+    Json(description)
 }
 
 /// 404 handler
@@ -97,25 +240,106 @@ async fn not_found() -> (StatusCode, Json<serde_json::Value>) {
 }
 
 /// Start the HTTP server
+///
+/// Binds every configured listener and serves each with its own route set.
+/// Returns when any listener fails.
 pub async fn serve(ctx: AppContext) -> PdsResult<()> {
-    // Bind to 0.0.0.0 to listen on all interfaces (IPv4 and IPv6)
-    let bind_addr = format!("0.0.0.0:{}", ctx.config.service.port);
-
-    info!("🚀 Aurora Locus PDS listening on {}", bind_addr);
     info!("   Service DID: {}", ctx.service_did());
     info!("   Service URL: {}", ctx.service_url());
 
-    let app = build_router(ctx);
+    let listeners = ctx.config.service.listeners.clone();
+    let ipv4_ports: HashSet<u16> = listeners
+        .iter()
+        .filter_map(|l| l.address.parse::<SocketAddr>().ok())
+        .filter(|address| address.is_ipv4())
+        .map(|address| address.port())
+        .collect();
 
-    // Create TCP listener
-    let listener = tokio::net::TcpListener::bind(&bind_addr)
-        .await
-        .map_err(|e| PdsError::Internal(format!("Failed to bind to {}: {}", bind_addr, e)))?;
+    let mut servers = Vec::new();
+    for listener_config in listeners {
+        let listener = bind_listener(&listener_config, &ipv4_ports).await?;
+        let app = build_router_for_role(ctx.clone(), listener_config.role);
+        servers.push(tokio::spawn(serve_listener(listener_config, listener, app)));
+    }
+
+    let (result, _, remaining) = futures::future::select_all(servers).await;
 
-    // Axum 0.7: Router<()> can be passed directly to serve
-    axum::serve(listener, app)
+    // One listener stopped - tear down the rest
+    for handle in remaining {
+        handle.abort();
+    }
+
+    result.map_err(|e| PdsError::Internal(format!("Listener task failed: {}", e)))?
+}
+
+/// Bind a TCP listener for a listener configuration
+///
+/// An IPv6 listener whose port an IPv4 listener (in `ipv4_ports`) also
+/// uses is bound IPv6-only. Otherwise Linux binds `[::]` dual-stack, and
+/// the IPv4 bind fails with EADDRINUSE.
+async fn bind_listener(config: &ListenerConfig, ipv4_ports: &HashSet<u16>) -> PdsResult<tokio::net::TcpListener> {
+    let bind_error = |e: std::io::Error| PdsError::Internal(format!("Failed to bind to {}: {}", config.address, e));
+    let address: SocketAddr = config
+        .address
+        .parse()
+        .map_err(|_| PdsError::Validation(format!("Invalid listener address: {}", config.address)))?;
+    let listener = bind_address(address, address.is_ipv6() && ipv4_ports.contains(&address.port()))
+        .map_err(bind_error)?;
+
+    info!(
+        "🚀 Aurora Locus PDS listening on {} ({} routes)",
+        config.address,
+        config.role.as_str()
+    );
+
+    Ok(listener)
+}
+
+/// Bind `address` as `tokio::net::TcpListener::bind` would, optionally
+/// IPv6-only
+fn bind_address(address: SocketAddr, v6_only: bool) -> std::io::Result<tokio::net::TcpListener> {
+    let socket = Socket::new(Domain::for_address(address), Type::STREAM, Some(Protocol::TCP))?;
+    if v6_only {
+        socket.set_only_v6(true)?;
+    }
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&address.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    tokio::net::TcpListener::from_std(socket.into())
+}
+
+/// Serve a router on a bound listener
+async fn serve_listener(
+    config: ListenerConfig,
+    listener: tokio::net::TcpListener,
+    app: Router,
+) -> PdsResult<()> {
+    // Socket addresses let client IPs be resolved through trusted proxies
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
-        .map_err(|e| PdsError::Internal(format!("Server error: {}", e)))?;
+        .map_err(|e| PdsError::Internal(format!("Server error on {}: {}", config.address, e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_dual_stack_listeners_on_one_port() {
+        let ipv4 = ListenerConfig {
+            address: "0.0.0.0:0".to_string(),
+            role: ListenerRole::Api,
+        };
+        let ipv4_listener = bind_listener(&ipv4, &HashSet::new()).await.unwrap();
+        let port = ipv4_listener.local_addr().unwrap().port();
 
-    Ok(())
+        let ipv6 = ListenerConfig {
+            address: format!("[::]:{}", port),
+            role: ListenerRole::Api,
+        };
+        let ipv6_listener = bind_listener(&ipv6, &HashSet::from([port])).await.unwrap();
+        assert_eq!(ipv6_listener.local_addr().unwrap().port(), port);
+    }
 }
@@ -0,0 +1,157 @@
+/// Account export archive builder
+///
+/// Bundles an account's data into a gzipped tarball:
+///
+/// ```text
+/// account.json      account metadata
+/// preferences.json  stored preferences
+/// repo.car          full repository (com.atproto.sync.getRepo format)
+/// blobs.json        index of exported blobs (cid, mimeType, size)
+/// blobs/<cid>       blob contents
+/// ```
+use crate::{
+    context::AppContext,
+    error::{PdsError, PdsResult},
+    takeout::manager::AccountExport,
+};
+use flate2::{write::GzEncoder, Compression};
+use serde_json::json;
+use sqlx::Row;
+use std::fs::File;
+use tracing::{error, info};
+
+type ArchiveBuilder = tar::Builder<GzEncoder<File>>;
+
+/// Start building an export in the background
+pub fn spawn_export(ctx: AppContext, export: AccountExport) {
+    tokio::spawn(async move {
+        let manager = ctx.takeout_manager.clone();
+
+        match build_archive(&ctx, &export).await {
+            Ok(size) => {
+                info!(did = %export.did, id = %export.id, size, "account_export_complete");
+                if let Err(e) = manager.mark_complete(&export.id, size as i64).await {
+                    error!("Failed to mark export {} complete: {}", export.id, e);
+                }
+            }
+            Err(e) => {
+                error!(did = %export.did, id = %export.id, error = %e, "account_export_failed");
+                let _ = tokio::fs::remove_file(partial_path(&ctx, &export.id)).await;
+                if let Err(e) = manager.mark_failed(&export.id, &e.to_string()).await {
+                    error!("Failed to mark export {} failed: {}", export.id, e);
+                }
+            }
+        }
+    });
+}
+
+fn partial_path(ctx: &AppContext, id: &str) -> std::path::PathBuf {
+    ctx.takeout_manager
+        .archive_path(id)
+        .with_extension("gz.partial")
+}
+
+/// Build the archive for an export, returning its size in bytes
+async fn build_archive(ctx: &AppContext, export: &AccountExport) -> PdsResult<u64> {
+    let manager = &ctx.takeout_manager;
+    let did = export.did.as_str();
+
+    tokio::fs::create_dir_all(manager.directory()).await?;
+    let partial = partial_path(ctx, &export.id);
+    let file = File::create(&partial)?;
+    let mut archive = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    manager.set_progress(&export.id, 1).await?;
+
+    // Account metadata
+    let account = ctx.account_manager.get_account(did).await?;
+    let metadata = json!({
+        "did": account.did,
+        "handle": account.handle,
+        "email": account.email,
+        "emailConfirmed": account.email_confirmed,
+        "createdAt": account.created_at.to_rfc3339(),
+        "deactivatedAt": account.deactivated_at.map(|t| t.to_rfc3339()),
+        "exportedAt": chrono::Utc::now().to_rfc3339(),
+    });
+    append_json(&mut archive, "account.json", &metadata)?;
+
+    // Preferences (the export is requested with full access)
+    let preferences = if ctx.actor_store.exists(did).await {
+        crate::api::actor::load_preferences(ctx, did, true).await?
+    } else {
+        Vec::new()
+    };
+    append_json(&mut archive, "preferences.json", &json!({ "preferences": preferences }))?;
+    manager.set_progress(&export.id, 10).await?;
+
+    // Repository
+    if ctx.actor_store.exists(did).await {
+        let car = crate::api::sync::export_repo_car(ctx, did).await?;
+        append_file(&mut archive, "repo.car", &car)?;
+    }
+    manager.set_progress(&export.id, 30).await?;
+
+    // Blobs (thumbnails are derived data and skipped)
+    let rows = sqlx::query(
+        r#"
+        SELECT cid, mime_type, size FROM blob_metadata
+        WHERE creator_did = ?
+          AND cid NOT IN (SELECT thumbnail_cid FROM blob_metadata WHERE thumbnail_cid IS NOT NULL)
+        ORDER BY created_at
+        "#,
+    )
+    .bind(did)
+    .fetch_all(&ctx.account_db)
+    .await?;
+
+    let total = rows.len().max(1);
+    let mut index = Vec::with_capacity(rows.len());
+    for (i, row) in rows.iter().enumerate() {
+        let cid: String = row.get("cid");
+
+        match ctx.blob_store.get(&cid).await? {
+            Some((data, mime_type)) => {
+                append_file(&mut archive, &format!("blobs/{}", cid), &data)?;
+                index.push(json!({
+                    "cid": cid,
+                    "mimeType": mime_type,
+                    "size": row.get::<i64, _>("size"),
+                }));
+            }
+            None => tracing::warn!(did = %did, cid = %cid, "account_export_blob_missing"),
+        }
+
+        manager
+            .set_progress(&export.id, 30 + (65 * (i + 1) / total) as i64)
+            .await?;
+    }
+    append_json(&mut archive, "blobs.json", &json!({ "blobs": index }))?;
+
+    archive
+        .into_inner()
+        .and_then(|gz| gz.finish())
+        .map_err(|e| PdsError::Internal(format!("Failed to finalize export archive: {}", e)))?;
+
+    let path = manager.archive_path(&export.id);
+    tokio::fs::rename(&partial, &path).await?;
+
+    Ok(tokio::fs::metadata(&path).await?.len())
+}
+
+fn append_json(archive: &mut ArchiveBuilder, path: &str, value: &serde_json::Value) -> PdsResult<()> {
+    let bytes = serde_json::to_vec_pretty(value)
+        .map_err(|e| PdsError::Internal(format!("Failed to serialize {}: {}", path, e)))?;
+    append_file(archive, path, &bytes)
+}
+
+fn append_file(archive: &mut ArchiveBuilder, path: &str, data: &[u8]) -> PdsResult<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(chrono::Utc::now().timestamp() as u64);
+
+    archive
+        .append_data(&mut header, path, data)
+        .map_err(|e| PdsError::Internal(format!("Failed to write {} to export archive: {}", path, e)))
+}
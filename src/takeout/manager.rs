@@ -0,0 +1,429 @@
+/// Account export bookkeeping
+///
+/// Tracks export jobs in the `account_export` table and issues signed,
+/// expiring download links for finished archives.
+use crate::error::{PdsError, PdsResult};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use sqlx::{Row, SqlitePool};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// How long a finished archive is kept before cleanup
+pub const EXPORT_RETENTION_DAYS: i64 = 7;
+
+/// Lifetime of a signed download link
+pub const DOWNLOAD_LINK_TTL_SECS: i64 = 3600;
+
+/// Exports still running after this long are considered interrupted
+const STALE_EXPORT_HOURS: i64 = 6;
+
+/// Export job status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportStatus {
+    Pending,
+    Running,
+    Complete,
+    Failed,
+}
+
+impl ExportStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Running => "running",
+            ExportStatus::Complete => "complete",
+            ExportStatus::Failed => "failed",
+        }
+    }
+
+    pub fn from_str(s: &str) -> PdsResult<Self> {
+        match s {
+            "pending" => Ok(ExportStatus::Pending),
+            "running" => Ok(ExportStatus::Running),
+            "complete" => Ok(ExportStatus::Complete),
+            "failed" => Ok(ExportStatus::Failed),
+            _ => Err(PdsError::Internal(format!("Invalid export status: {}", s))),
+        }
+    }
+}
+
+/// Account export job
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountExport {
+    pub id: String,
+    pub did: String,
+    pub status: ExportStatus,
+    /// Percentage complete (0-100)
+    pub progress: i64,
+    /// Archive size in bytes once complete
+    pub size: Option<i64>,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    /// When the archive will be deleted
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+impl AccountExport {
+    /// Whether the job has not yet finished
+    pub fn is_active(&self) -> bool {
+        matches!(self.status, ExportStatus::Pending | ExportStatus::Running)
+    }
+}
+
+/// Account export manager
+#[derive(Clone)]
+pub struct TakeoutManager {
+    db: SqlitePool,
+    directory: PathBuf,
+    signing_secret: String,
+}
+
+impl TakeoutManager {
+    pub fn new(db: SqlitePool, directory: PathBuf, signing_secret: String) -> Self {
+        Self {
+            db,
+            directory,
+            signing_secret,
+        }
+    }
+
+    /// Directory holding export archives
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Path of the archive for an export
+    pub fn archive_path(&self, id: &str) -> PathBuf {
+        self.directory.join(format!("{}.tar.gz", id))
+    }
+
+    /// Request a new export, or return the one already in progress
+    pub async fn request_export(&self, did: &str) -> PdsResult<(AccountExport, bool)> {
+        if let Some(existing) = self.latest_export(did).await? {
+            if existing.is_active() {
+                return Ok((existing, false));
+            }
+        }
+
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+
+        sqlx::query(
+            r#"
+            INSERT INTO account_export (id, did, status, progress, created_at)
+            VALUES (?, ?, ?, 0, ?)
+            "#,
+        )
+        .bind(&id)
+        .bind(did)
+        .bind(ExportStatus::Pending.as_str())
+        .bind(now.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        let export = AccountExport {
+            id,
+            did: did.to_string(),
+            status: ExportStatus::Pending,
+            progress: 0,
+            size: None,
+            error: None,
+            created_at: now,
+            completed_at: None,
+            expires_at: None,
+        };
+
+        Ok((export, true))
+    }
+
+    /// Get an export belonging to an account
+    pub async fn get_export(&self, did: &str, id: &str) -> PdsResult<Option<AccountExport>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, did, status, progress, size, error, created_at, completed_at, expires_at
+            FROM account_export
+            WHERE id = ? AND did = ?
+            "#,
+        )
+        .bind(id)
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?;
+
+        row.map(|r| Self::row_to_export(&r)).transpose()
+    }
+
+    /// Get an export by ID regardless of owner (download links are owner-less)
+    pub async fn get_export_by_id(&self, id: &str) -> PdsResult<Option<AccountExport>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, did, status, progress, size, error, created_at, completed_at, expires_at
+            FROM account_export
+            WHERE id = ?
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&self.db)
+        .await?;
+
+        row.map(|r| Self::row_to_export(&r)).transpose()
+    }
+
+    /// Most recent export for an account
+    pub async fn latest_export(&self, did: &str) -> PdsResult<Option<AccountExport>> {
+        let row = sqlx::query(
+            r#"
+            SELECT id, did, status, progress, size, error, created_at, completed_at, expires_at
+            FROM account_export
+            WHERE did = ?
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(did)
+        .fetch_optional(&self.db)
+        .await?;
+
+        row.map(|r| Self::row_to_export(&r)).transpose()
+    }
+
+    /// Record progress on a running export
+    pub async fn set_progress(&self, id: &str, progress: i64) -> PdsResult<()> {
+        sqlx::query("UPDATE account_export SET status = ?, progress = ? WHERE id = ?")
+            .bind(ExportStatus::Running.as_str())
+            .bind(progress.clamp(0, 99))
+            .bind(id)
+            .execute(&self.db)
+            .await?;
+        Ok(())
+    }
+
+    /// Mark an export as complete
+    pub async fn mark_complete(&self, id: &str, size: i64) -> PdsResult<()> {
+        let now = Utc::now();
+        let expires_at = now + Duration::days(EXPORT_RETENTION_DAYS);
+
+        sqlx::query(
+            r#"
+            UPDATE account_export
+            SET status = ?, progress = 100, size = ?, completed_at = ?, expires_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(ExportStatus::Complete.as_str())
+        .bind(size)
+        .bind(now.to_rfc3339())
+        .bind(expires_at.to_rfc3339())
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Mark an export as failed
+    pub async fn mark_failed(&self, id: &str, error: &str) -> PdsResult<()> {
+        sqlx::query(
+            "UPDATE account_export SET status = ?, error = ?, completed_at = ? WHERE id = ?",
+        )
+        .bind(ExportStatus::Failed.as_str())
+        .bind(error)
+        .bind(Utc::now().to_rfc3339())
+        .bind(id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Sign a download link for an export, valid until `expires` (unix seconds)
+    pub fn sign_download(&self, id: &str, expires: i64) -> String {
+        hex::encode(self.download_mac(id, expires).finalize().into_bytes())
+    }
+
+    /// Verify a signed download link
+    pub fn verify_download(&self, id: &str, expires: i64, signature: &str) -> PdsResult<()> {
+        if expires < Utc::now().timestamp() {
+            return Err(PdsError::Authorization("Download link has expired".to_string()));
+        }
+
+        let signature = hex::decode(signature)
+            .map_err(|_| PdsError::Authorization("Invalid download signature".to_string()))?;
+
+        self.download_mac(id, expires)
+            .verify_slice(&signature)
+            .map_err(|_| PdsError::Authorization("Invalid download signature".to_string()))
+    }
+
+    fn download_mac(&self, id: &str, expires: i64) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.signing_secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(b"account-export:");
+        mac.update(id.as_bytes());
+        mac.update(b":");
+        mac.update(expires.to_string().as_bytes());
+        mac
+    }
+
+    /// Delete expired archives and fail exports interrupted by a restart
+    ///
+    /// Returns the number of export records removed.
+    pub async fn cleanup(&self) -> PdsResult<u64> {
+        let now = Utc::now();
+
+        let stale_before = now - Duration::hours(STALE_EXPORT_HOURS);
+        sqlx::query(
+            r#"
+            UPDATE account_export
+            SET status = 'failed', error = 'Export interrupted', completed_at = ?
+            WHERE status IN ('pending', 'running') AND created_at < ?
+            "#,
+        )
+        .bind(now.to_rfc3339())
+        .bind(stale_before.to_rfc3339())
+        .execute(&self.db)
+        .await?;
+
+        let expired: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT id FROM account_export
+            WHERE (expires_at IS NOT NULL AND expires_at < ?)
+               OR (status = 'failed' AND completed_at < ?)
+            "#,
+        )
+        .bind(now.to_rfc3339())
+        .bind((now - Duration::days(EXPORT_RETENTION_DAYS)).to_rfc3339())
+        .fetch_all(&self.db)
+        .await?;
+
+        for id in &expired {
+            let path = self.archive_path(id);
+            if let Err(e) = tokio::fs::remove_file(&path).await {
+                if e.kind() != std::io::ErrorKind::NotFound {
+                    tracing::warn!("Failed to remove export archive {}: {}", path.display(), e);
+                    continue;
+                }
+            }
+
+            sqlx::query("DELETE FROM account_export WHERE id = ?")
+                .bind(id)
+                .execute(&self.db)
+                .await?;
+        }
+
+        Ok(expired.len() as u64)
+    }
+
+    fn row_to_export(row: &sqlx::sqlite::SqliteRow) -> PdsResult<AccountExport> {
+        let parse = |s: String| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| PdsError::Internal(format!("Invalid timestamp: {}", e)))
+        };
+
+        Ok(AccountExport {
+            id: row.get("id"),
+            did: row.get("did"),
+            status: ExportStatus::from_str(&row.get::<String, _>("status"))?,
+            progress: row.get("progress"),
+            size: row.get("size"),
+            error: row.get("error"),
+            created_at: parse(row.get("created_at"))?,
+            completed_at: row
+                .get::<Option<String>, _>("completed_at")
+                .map(parse)
+                .transpose()?,
+            expires_at: row
+                .get::<Option<String>, _>("expires_at")
+                .map(parse)
+                .transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn setup() -> TakeoutManager {
+        let db = SqlitePool::connect(":memory:").await.unwrap();
+
+        sqlx::query(
+            r#"
+            CREATE TABLE account_export (
+                id TEXT PRIMARY KEY NOT NULL,
+                did TEXT NOT NULL,
+                status TEXT NOT NULL DEFAULT 'pending',
+                progress INTEGER NOT NULL DEFAULT 0,
+                size INTEGER,
+                error TEXT,
+                created_at TEXT NOT NULL,
+                completed_at TEXT,
+                expires_at TEXT
+            )
+            "#,
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+
+        TakeoutManager::new(
+            db,
+            std::env::temp_dir().join("aurora-exports-test"),
+            "test-secret".to_string(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_export_lifecycle() {
+        let manager = setup().await;
+
+        let (export, created) = manager.request_export("did:plc:alice").await.unwrap();
+        assert!(created);
+        assert_eq!(export.status, ExportStatus::Pending);
+
+        // A second request while one is active returns the same job
+        let (again, created) = manager.request_export("did:plc:alice").await.unwrap();
+        assert!(!created);
+        assert_eq!(again.id, export.id);
+
+        manager.set_progress(&export.id, 40).await.unwrap();
+        let running = manager.get_export("did:plc:alice", &export.id).await.unwrap().unwrap();
+        assert_eq!(running.status, ExportStatus::Running);
+        assert_eq!(running.progress, 40);
+
+        manager.mark_complete(&export.id, 1234).await.unwrap();
+        let done = manager.latest_export("did:plc:alice").await.unwrap().unwrap();
+        assert_eq!(done.status, ExportStatus::Complete);
+        assert_eq!(done.size, Some(1234));
+        assert!(done.expires_at.is_some());
+
+        // Other accounts cannot see the export
+        assert!(manager.get_export("did:plc:bob", &export.id).await.unwrap().is_none());
+
+        // Once complete, a new request starts a fresh export
+        let (fresh, created) = manager.request_export("did:plc:alice").await.unwrap();
+        assert!(created);
+        assert_ne!(fresh.id, export.id);
+    }
+
+    #[tokio::test]
+    async fn test_download_signature() {
+        let manager = setup().await;
+        let expires = Utc::now().timestamp() + 60;
+
+        let sig = manager.sign_download("export-1", expires);
+        assert!(manager.verify_download("export-1", expires, &sig).is_ok());
+        assert!(manager.verify_download("export-2", expires, &sig).is_err());
+        assert!(manager.verify_download("export-1", expires + 1, &sig).is_err());
+        assert!(manager.verify_download("export-1", expires, "zz").is_err());
+
+        let past = Utc::now().timestamp() - 1;
+        let sig = manager.sign_download("export-1", past);
+        assert!(manager.verify_download("export-1", past, &sig).is_err());
+    }
+}
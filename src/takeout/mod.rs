@@ -0,0 +1,13 @@
+/// Account data export (takeout)
+///
+/// Users can request a complete export of their account:
+/// - Repository CAR, blobs, account metadata, and preferences in one archive
+/// - Built asynchronously with progress reporting
+/// - Downloaded via a signed, expiring link
+/// - Archives are removed after a retention period
+
+pub mod archive;
+pub mod manager;
+
+pub use archive::spawn_export;
+pub use manager::{AccountExport, ExportStatus, TakeoutManager};
@@ -0,0 +1,220 @@
+/// Logging, request tracing and optional OpenTelemetry export
+///
+/// Every HTTP request runs inside a `request` span carrying its request ID
+/// (the incoming `X-Request-Id`, or a generated UUID), so all log lines
+/// emitted while handling it can be correlated. The ID is echoed back in the
+/// response headers.
+///
+/// When `OTEL_EXPORTER_OTLP_ENDPOINT` is set, spans are also exported over
+/// OTLP, incoming W3C `traceparent` headers are honored, and outgoing calls to
+/// the PLC directory, relays and other federation peers carry the current
+/// trace context via `inject_trace_context`.
+use crate::error::{PdsError, PdsResult};
+use axum::http::{HeaderMap, HeaderName, Request};
+use opentelemetry::{
+    global,
+    propagation::{Extractor, Injector},
+    trace::TracerProvider as _,
+    KeyValue,
+};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace as sdktrace, Resource};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// Header carrying the request ID
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Tracing configuration
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    /// "json" for structured logs, anything else for pretty text
+    pub log_format: String,
+    /// OTLP collector endpoint (gRPC); export is disabled when unset
+    pub otlp_endpoint: Option<String>,
+    /// Service name reported with exported spans
+    pub service_name: String,
+}
+
+impl TelemetryConfig {
+    pub fn from_env() -> Self {
+        Self {
+            log_format: std::env::var("LOG_FORMAT").unwrap_or_else(|_| "text".to_string()),
+            otlp_endpoint: std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT")
+                .ok()
+                .filter(|s| !s.is_empty()),
+            service_name: std::env::var("OTEL_SERVICE_NAME")
+                .unwrap_or_else(|_| "aurora-locus".to_string()),
+        }
+    }
+}
+
+/// Install the global tracing subscriber
+///
+/// Returns whether OTLP export was enabled.
+pub fn init(config: &TelemetryConfig) -> PdsResult<bool> {
+    let env_filter = tracing_subscriber::EnvFilter::try_from_default_env()
+        .unwrap_or_else(|_| "aurora_locus=info,tower_http=info".into());
+
+    let tracer = match &config.otlp_endpoint {
+        Some(endpoint) => Some(otlp_tracer(endpoint, &config.service_name)?),
+        None => None,
+    };
+    let otel_enabled = tracer.is_some();
+
+    if config.log_format == "json" {
+        // JSON logging for production
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(tracer.map(|t| tracing_opentelemetry::layer().with_tracer(t)))
+            .with(tracing_subscriber::fmt::layer().json().with_current_span(true))
+            .init();
+    } else {
+        // Pretty text logging for development
+        tracing_subscriber::registry()
+            .with(env_filter)
+            .with(tracer.map(|t| tracing_opentelemetry::layer().with_tracer(t)))
+            .with(tracing_subscriber::fmt::layer().pretty())
+            .init();
+    }
+
+    Ok(otel_enabled)
+}
+
+/// Flush pending spans on shutdown
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Build an OTLP span exporter pipeline and register W3C trace propagation
+fn otlp_tracer(endpoint: &str, service_name: &str) -> PdsResult<sdktrace::Tracer> {
+    let provider = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(sdktrace::Config::default().with_resource(Resource::new(vec![
+            KeyValue::new("service.name", service_name.to_string()),
+        ])))
+        .install_batch(runtime::Tokio)
+        .map_err(|e| PdsError::Internal(format!("Failed to start OTLP exporter: {}", e)))?;
+
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    let tracer = provider.tracer("aurora-locus");
+    global::set_tracer_provider(provider);
+
+    Ok(tracer)
+}
+
+/// Request ID of a request (set by the request ID layer before tracing)
+pub fn request_id<B>(request: &Request<B>) -> &str {
+    request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+}
+
+/// Span wrapping the handling of one HTTP request
+///
+/// Continues the caller's trace when the request carries a `traceparent`.
+pub fn make_request_span<B>(request: &Request<B>) -> Span {
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id(request),
+        method = %request.method(),
+        uri = %request.uri(),
+        version = ?request.version(),
+    );
+
+    let parent = global::get_text_map_propagator(|p| p.extract(&HeaderExtractor(request.headers())));
+    span.set_parent(parent);
+
+    span
+}
+
+/// Add the current trace context (`traceparent`) to an outgoing request
+///
+/// A no-op unless OTLP export is enabled.
+pub fn inject_trace_context(builder: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+    let context = Span::current().context();
+    let mut headers = HeaderInjector(HeaderMap::new());
+    global::get_text_map_propagator(|p| p.inject_context(&context, &mut headers));
+
+    builder.headers(headers.0)
+}
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
+
+struct HeaderInjector(HeaderMap);
+
+impl Injector for HeaderInjector {
+    fn set(&mut self, key: &str, value: String) {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(key.as_bytes()),
+            axum::http::HeaderValue::from_str(&value),
+        ) {
+            self.0.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, routing::get, Router};
+    use tower::ServiceExt;
+    use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
+
+    fn test_router() -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER))
+            .layer(SetRequestIdLayer::new(REQUEST_ID_HEADER, MakeRequestUuid))
+    }
+
+    #[tokio::test]
+    async fn test_request_id_generated_and_honored() {
+        let response = test_router()
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let generated = response.headers().get(REQUEST_ID_HEADER).unwrap().to_str().unwrap();
+        assert!(uuid::Uuid::parse_str(generated).is_ok());
+
+        let response = test_router()
+            .oneshot(
+                Request::builder()
+                    .uri("/")
+                    .header(REQUEST_ID_HEADER, "client-supplied-id")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.headers().get(REQUEST_ID_HEADER).unwrap(), "client-supplied-id");
+    }
+
+    #[test]
+    fn test_request_id_extraction() {
+        let request = Request::builder()
+            .header(REQUEST_ID_HEADER, "abc123")
+            .body(())
+            .unwrap();
+        assert_eq!(request_id(&request), "abc123");
+        assert_eq!(request_id(&Request::new(())), "");
+    }
+}
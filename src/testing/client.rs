@@ -0,0 +1,205 @@
+/// Typed XRPC client for tests
+use crate::{
+    account::CreateAccountResponse,
+    car::reader::{read_verified_car, CarContents},
+};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::StatusCode;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{connect_async, tungstenite::Message, MaybeTlsStream, WebSocketStream};
+
+/// How long to wait for a firehose frame before giving up
+const FRAME_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An XRPC error response
+#[derive(Debug)]
+pub struct XrpcError {
+    pub status: StatusCode,
+    /// The `error` name, e.g. `NotFound`
+    pub error: String,
+    pub message: Option<String>,
+}
+
+impl std::fmt::Display for XrpcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.status, self.error)?;
+        if let Some(message) = &self.message {
+            write!(f, ": {}", message)?;
+        }
+        Ok(())
+    }
+}
+
+impl XrpcError {
+    fn transport(e: impl std::fmt::Display) -> Self {
+        Self {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            error: "TransportError".to_string(),
+            message: Some(e.to_string()),
+        }
+    }
+
+    async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status();
+        let body: Value = response.json().await.unwrap_or_default();
+        Self {
+            status,
+            error: body["error"].as_str().unwrap_or("Unknown").to_string(),
+            message: body["message"].as_str().map(str::to_string),
+        }
+    }
+}
+
+/// Reference to a written record
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordRef {
+    pub uri: String,
+    pub cid: String,
+}
+
+/// Client for one server, optionally authenticated
+#[derive(Clone)]
+pub struct XrpcClient {
+    http: reqwest::Client,
+    base_url: String,
+    access_jwt: Option<String>,
+}
+
+impl XrpcClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            http: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            access_jwt: None,
+        }
+    }
+
+    /// The same client, sending `jwt` as the bearer token
+    pub fn with_auth(&self, jwt: &str) -> Self {
+        Self {
+            access_jwt: Some(jwt.to_string()),
+            ..self.clone()
+        }
+    }
+
+    fn request(&self, method: reqwest::Method, nsid: &str) -> reqwest::RequestBuilder {
+        let request = self.http.request(method, format!("{}/xrpc/{}", self.base_url, nsid));
+        match &self.access_jwt {
+            Some(jwt) => request.bearer_auth(jwt),
+            None => request,
+        }
+    }
+
+    async fn send(request: reqwest::RequestBuilder) -> Result<reqwest::Response, XrpcError> {
+        let response = request.send().await.map_err(XrpcError::transport)?;
+        if response.status().is_success() {
+            Ok(response)
+        } else {
+            Err(XrpcError::from_response(response).await)
+        }
+    }
+
+    /// Call a query (GET) and decode the JSON response
+    pub async fn query<T: DeserializeOwned>(&self, nsid: &str, params: &[(&str, &str)]) -> Result<T, XrpcError> {
+        let response = Self::send(self.request(reqwest::Method::GET, nsid).query(params)).await?;
+        response.json().await.map_err(XrpcError::transport)
+    }
+
+    /// Call a query (GET) returning a binary body
+    pub async fn query_bytes(&self, nsid: &str, params: &[(&str, &str)]) -> Result<Vec<u8>, XrpcError> {
+        let response = Self::send(self.request(reqwest::Method::GET, nsid).query(params)).await?;
+        Ok(response.bytes().await.map_err(XrpcError::transport)?.to_vec())
+    }
+
+    /// Call a procedure (POST) with a JSON body and decode the JSON response
+    pub async fn procedure<T: DeserializeOwned>(&self, nsid: &str, body: &Value) -> Result<T, XrpcError> {
+        let response = Self::send(self.request(reqwest::Method::POST, nsid).json(body)).await?;
+        response.json().await.map_err(XrpcError::transport)
+    }
+
+    pub async fn create_account(&self, handle: &str, password: &str) -> Result<CreateAccountResponse, XrpcError> {
+        self.procedure(
+            "com.atproto.server.createAccount",
+            &serde_json::json!({ "handle": handle, "password": password }),
+        )
+        .await
+    }
+
+    /// Upload a blob, returning the uploadBlob response
+    pub async fn upload_blob(&self, data: Vec<u8>, mime_type: &str) -> Result<Value, XrpcError> {
+        let request = self
+            .request(reqwest::Method::POST, "com.atproto.repo.uploadBlob")
+            .header(reqwest::header::CONTENT_TYPE, mime_type)
+            .body(data);
+        let response = Self::send(request).await?;
+        response.json().await.map_err(XrpcError::transport)
+    }
+
+    pub async fn create_record(&self, repo: &str, collection: &str, record: Value) -> Result<RecordRef, XrpcError> {
+        self.procedure(
+            "com.atproto.repo.createRecord",
+            &serde_json::json!({ "repo": repo, "collection": collection, "record": record }),
+        )
+        .await
+    }
+
+    /// Export a repository and check every block against its CID
+    pub async fn get_repo(&self, did: &str) -> Result<CarContents, XrpcError> {
+        let bytes = self.query_bytes("com.atproto.sync.getRepo", &[("did", did)]).await?;
+        read_verified_car(&bytes).map_err(XrpcError::transport)
+    }
+
+    /// Connect to subscribeRepos, replaying from `cursor` if given
+    pub async fn subscribe_repos(&self, cursor: Option<i64>) -> Result<FirehoseSubscription, XrpcError> {
+        self.subscribe("com.atproto.sync.subscribeRepos", cursor).await
+    }
+
+    /// Connect to subscribeLabels, replaying from `cursor` if given
+    pub async fn subscribe_labels(&self, cursor: Option<i64>) -> Result<FirehoseSubscription, XrpcError> {
+        self.subscribe("com.atproto.label.subscribeLabels", cursor).await
+    }
+
+    async fn subscribe(&self, nsid: &str, cursor: Option<i64>) -> Result<FirehoseSubscription, XrpcError> {
+        let mut url = format!("{}/xrpc/{}", self.base_url.replacen("http", "ws", 1), nsid);
+        if let Some(cursor) = cursor {
+            url.push_str(&format!("?cursor={}", cursor));
+        }
+        let (stream, _) = connect_async(&url).await.map_err(XrpcError::transport)?;
+        Ok(FirehoseSubscription { stream })
+    }
+}
+
+/// An open firehose connection
+pub struct FirehoseSubscription {
+    stream: WebSocketStream<MaybeTlsStream<TcpStream>>,
+}
+
+impl FirehoseSubscription {
+    /// The next frame, or `None` if the stream closed or went quiet
+    pub async fn next_frame(&mut self) -> Option<Value> {
+        loop {
+            let message = tokio::time::timeout(FRAME_TIMEOUT, self.stream.next()).await.ok()??.ok()?;
+            match message {
+                Message::Text(text) => return serde_json::from_str(&text).ok(),
+                Message::Ping(data) => {
+                    let _ = self.stream.send(Message::Pong(data)).await;
+                }
+                Message::Close(_) => return None,
+                _ => {}
+            }
+        }
+    }
+
+    /// The next `#commit` frame for `repo`, skipping everything else
+    pub async fn next_commit(&mut self, repo: &str) -> Option<Value> {
+        loop {
+            let frame = self.next_frame().await?;
+            if frame["$type"] == "#commit" && frame["repo"] == repo {
+                return Some(frame);
+            }
+        }
+    }
+}
@@ -0,0 +1,762 @@
+/// In-process server for end-to-end tests
+///
+/// [`TestServer::start`] boots the full [`AppContext`] against a temporary
+/// data directory, serves the router on an ephemeral port and points PLC
+/// registration at a [`MockPlc`], so tests exercise the same code paths as
+/// a deployment without touching the network. [`XrpcClient`] talks to it.
+mod client;
+
+pub use client::{FirehoseSubscription, RecordRef, XrpcClient, XrpcError};
+
+use crate::{
+    clock::{random_ids, system_clock, Clock, IdGenerator},
+    config::*,
+    context::AppContext,
+    db::{self, DatabaseOptions},
+};
+use axum::{
+    extract::{Path as AxumPath, State},
+    routing::{get, post},
+    Json, Router,
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tempfile::TempDir;
+use tokio::{net::TcpListener, task::JoinHandle};
+
+/// The account database schema is only written down in the installer
+const INSTALL_SCRIPT: &str = include_str!("../../install.sh");
+
+/// SQL the installer runs to create the account database
+pub fn account_schema() -> &'static str {
+    const START: &str = "<< 'EOSQL'\n";
+    let start = INSTALL_SCRIPT.find(START).expect("install.sh has no schema heredoc") + START.len();
+    let end = INSTALL_SCRIPT[start..].find("\nEOSQL").expect("unterminated schema heredoc");
+    &INSTALL_SCRIPT[start..start + end]
+}
+
+/// A running server with its own data directory
+pub struct TestServer {
+    pub ctx: AppContext,
+    /// Base URL, e.g. `http://127.0.0.1:40123`
+    pub url: String,
+    pub plc: MockPlc,
+    server: JoinHandle<()>,
+    _dir: TempDir,
+}
+
+impl TestServer {
+    /// Start a server with the default test configuration
+    pub async fn start() -> Self {
+        Self::start_with(|_| {}).await
+    }
+
+    /// Start a server, adjusting the configuration first
+    pub async fn start_with(configure: impl FnOnce(&mut ServerConfig)) -> Self {
+        Self::boot(configure, system_clock(), random_ids()).await
+    }
+
+    /// Start a server whose time and identifiers come from `clock` and `ids`
+    pub async fn start_with_clock(clock: Arc<dyn Clock>, ids: Arc<dyn IdGenerator>) -> Self {
+        Self::boot(|_| {}, clock, ids).await
+    }
+
+    async fn boot(
+        configure: impl FnOnce(&mut ServerConfig),
+        clock: Arc<dyn Clock>,
+        ids: Arc<dyn IdGenerator>,
+    ) -> Self {
+        let dir = tempfile::tempdir().expect("failed to create temp dir");
+        let plc = MockPlc::start().await;
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("failed to bind");
+        let addr = listener.local_addr().expect("no local address");
+
+        let mut config = test_config(dir.path(), addr, &plc.url);
+        configure(&mut config);
+
+        let pool = db::create_pool(&config.storage.account_db, DatabaseOptions::default())
+            .await
+            .expect("failed to create account database");
+        sqlx::raw_sql(account_schema())
+            .execute(&pool)
+            .await
+            .expect("failed to apply account schema");
+        pool.close().await;
+
+        let ctx = AppContext::new_with_clock(config, clock, ids)
+            .await
+            .expect("failed to build context");
+        let app = crate::server::build_router(ctx.clone())
+            .into_make_service_with_connect_info::<std::net::SocketAddr>();
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Self {
+            ctx,
+            url: format!("http://{}", addr),
+            plc,
+            server,
+            _dir: dir,
+        }
+    }
+
+    /// An unauthenticated client for this server
+    pub fn client(&self) -> XrpcClient {
+        XrpcClient::new(&self.url)
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+type PlcOperations = Arc<Mutex<HashMap<String, Vec<Value>>>>;
+
+/// Minimal PLC directory that accepts every operation
+///
+/// Operations are kept per DID and served back from `GET /{did}/log`, like
+/// the real directory, so tests can check what was published.
+pub struct MockPlc {
+    pub url: String,
+    operations: PlcOperations,
+    server: JoinHandle<()>,
+}
+
+impl MockPlc {
+    pub async fn start() -> Self {
+        let operations = PlcOperations::default();
+
+        let app = Router::new()
+            .route("/:did", post(submit_operation))
+            .route("/:did/log", get(operation_log))
+            .with_state(operations.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("failed to bind");
+        let url = format!("http://{}", listener.local_addr().expect("no local address"));
+        let server = tokio::spawn(async move {
+            let _ = axum::serve(listener, app).await;
+        });
+
+        Self { url, operations, server }
+    }
+
+    /// Operations submitted for a DID, oldest first
+    pub fn operations(&self, did: &str) -> Vec<Value> {
+        self.operations.lock().unwrap().get(did).cloned().unwrap_or_default()
+    }
+}
+
+impl Drop for MockPlc {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+async fn submit_operation(
+    State(operations): State<PlcOperations>,
+    AxumPath(did): AxumPath<String>,
+    Json(operation): Json<Value>,
+) -> Json<Value> {
+    operations.lock().unwrap().entry(did).or_default().push(operation);
+    Json(Value::Null)
+}
+
+async fn operation_log(
+    State(operations): State<PlcOperations>,
+    AxumPath(did): AxumPath<String>,
+) -> Json<Vec<Value>> {
+    Json(operations.lock().unwrap().get(&did).cloned().unwrap_or_default())
+}
+
+/// Configuration for a server rooted at `dir`
+pub fn test_config(dir: &Path, addr: SocketAddr, plc_url: &str) -> ServerConfig {
+    let data: PathBuf = dir.to_path_buf();
+    ServerConfig {
+        service: ServiceConfig {
+            hostname: "localhost".to_string(),
+            port: addr.port(),
+            service_did: "did:web:localhost".to_string(),
+            version: "0.1.0".to_string(),
+            blob_upload_limit: 5242880,
+            blob_audio_upload_limit: 10485760,
+            blob_account_quota: None,
+            blob_animated_thumbnails: false,
+            blob_temp_ttl_hours: 24,
+            blob_max_staged_per_account: None,
+            listeners: vec![ListenerConfig {
+                address: addr.to_string(),
+                role: ListenerRole::All,
+            }],
+            dev_mode: true,
+            pretty_json: false,
+        },
+        storage: StorageConfig {
+            data_directory: data.clone(),
+            account_db: data.join("account.sqlite"),
+            sequencer_db: data.join("sequencer.sqlite"),
+            did_cache_db: data.join("did_cache.sqlite"),
+            actor_store_directory: data.join("actors"),
+            blobstore: BlobstoreConfig::Disk {
+                location: data.join("blobs"),
+                tmp_location: data.join("tmp"),
+            },
+        },
+        authentication: AuthConfig {
+            jwt_secret: "test-secret-key-for-testing-only".to_string(),
+            repo_signing_key: "a".repeat(64),
+            plc_rotation_key: "b".repeat(64),
+            admin_dids: vec![],
+            oauth: OAuthConfig {
+                client_id: "test-client".to_string(),
+                redirect_uri: "http://localhost/oauth/callback".to_string(),
+                pds_url: "http://localhost".to_string(),
+            },
+            admin_network_token: None,
+            password_hash: PasswordHashConfig::default(),
+            jwt_keys: JwtKeysConfig::default(),
+        },
+        identity: IdentityConfig {
+            did_plc_url: plc_url.to_string(),
+            service_handle_domains: vec!["test".to_string()],
+            did_cache_stale_ttl: 3600,
+            did_cache_max_ttl: 86400,
+            handle_doh_url: "https://cloudflare-dns.com/dns-query".to_string(),
+            handle_redirect_secs: 0,
+            handle_changes: HandleChangeConfig::default(),
+            plc_failover: PlcFailoverConfig::default(),
+        },
+        email: None,
+        invites: InviteConfig {
+            required: false,
+            interval: 604800,
+            epoch: "2024-01-01T00:00:00Z".to_string(),
+        },
+        rate_limit: RateLimitConfig {
+            enabled: false,
+            global_requests_per_minute: 3000,
+        },
+        logging: LoggingConfig {
+            level: "info".to_string(),
+            audit_log_retention_days: None,
+            tombstone_retention_days: None,
+        },
+        federation: FederationConfig {
+            enabled: false,
+            relay_urls: vec![],
+            firehose_enabled: true,
+            crawl_enabled: false,
+            public_url: None,
+            auto_stream_events: false,
+        },
+        mirror: MirrorConfig {
+            enabled: false,
+            source_url: "https://bsky.network".to_string(),
+            dids: vec![],
+        },
+        content_policy: ContentPolicyConfig::default(),
+        registration_challenge: RegistrationChallengeConfig::default(),
+        signup_throttle: SignupThrottleConfig::default(),
+        report_service: None,
+        bsky_app_view: None,
+        proxy: ProxyConfig::default(),
+        keys: KeyConfig::default(),
+        encryption: EncryptionConfig::default(),
+        cors: CorsConfig::default(),
+        security_headers: SecurityHeadersConfig::default(),
+        firehose: FirehoseConfig::default(),
+        http_cache: HttpCacheConfig::default(),
+        jobs: JobsConfig::default(),
+        compaction: CompactionConfig::default(),
+        trusted_proxies: TrustedProxyConfig::default(),
+        record_history: RecordHistoryConfig::default(),
+        write_hooks: WriteHooksConfig::default(),
+        nodeinfo: NodeInfoConfig::default(),
+        describe_server: DescribeServerConfig::default(),
+        replica: ReplicaConfig::default(),
+        repo_webhooks: RepoWebhookConfig::default(),
+        activitypub: ActivityPubConfig::default(),
+        feeds: FeedsConfig::default(),
+        body_limits: BodyLimitConfig::default(),
+        virtual_hosts: vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use libipld::Cid;
+    use std::str::FromStr;
+
+    #[test]
+    fn test_account_schema() {
+        let schema = account_schema();
+        assert!(schema.contains("CREATE TABLE IF NOT EXISTS account ("));
+        assert!(schema.contains("CREATE TABLE IF NOT EXISTS _sqlx_migrations"));
+        assert!(!schema.contains("EOSQL"));
+    }
+
+    #[tokio::test]
+    async fn test_describe_server() {
+        let server = TestServer::start().await;
+        let description: Value = server.client().query("com.atproto.server.describeServer", &[]).await.unwrap();
+        assert_eq!(description["did"], "did:web:localhost");
+        assert_eq!(description["inviteCodeRequired"], false);
+        assert_eq!(description["phoneVerificationRequired"], false);
+        assert_eq!(description["links"], serde_json::json!({}));
+        assert!(description.get("contact").is_none());
+
+        let server = TestServer::start_with(|config| {
+            config.invites.required = true;
+            config.describe_server = DescribeServerConfig {
+                privacy_policy_url: Some("https://example.com/privacy".to_string()),
+                terms_of_service_url: Some("https://example.com/tos".to_string()),
+                contact_email: Some("admin@example.com".to_string()),
+                phone_verification_required: true,
+            };
+        })
+        .await;
+        let description: Value = server.client().query("com.atproto.server.describeServer", &[]).await.unwrap();
+        assert_eq!(description["availableUserDomains"], serde_json::json!(["test"]));
+        assert_eq!(description["inviteCodeRequired"], true);
+        assert_eq!(description["phoneVerificationRequired"], true);
+        assert_eq!(description["links"]["privacyPolicy"], "https://example.com/privacy");
+        assert_eq!(description["links"]["termsOfService"], "https://example.com/tos");
+        assert_eq!(description["contact"]["email"], "admin@example.com");
+    }
+
+    #[tokio::test]
+    async fn test_service_did_document() {
+        let server = TestServer::start_with(|config| {
+            config.federation.public_url = Some("https://pds.example.com/".to_string());
+        })
+        .await;
+        let did_document = |host: &'static str| {
+            reqwest::Client::new()
+                .get(format!("{}/.well-known/did.json", server.url))
+                .header(reqwest::header::HOST, host)
+                .send()
+        };
+
+        let doc: Value = did_document("localhost").await.unwrap().json().await.unwrap();
+        assert_eq!(doc["id"], "did:web:localhost");
+        assert_eq!(doc["service"][0]["id"], "did:web:localhost#atproto_pds");
+        assert_eq!(doc["service"][0]["serviceEndpoint"], "https://pds.example.com");
+
+        // Published key is the one service auth tokens from the service DID use
+        let key = server.ctx.signing_keys.public_key("did:web:localhost").await.unwrap();
+        assert_eq!(
+            doc["verificationMethod"][0]["publicKeyMultibase"],
+            crate::crypto::keys::public_key_multibase(&key)
+        );
+
+        let response = did_document("unknown.example").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_jwks() {
+        let server = TestServer::start_with(|config| {
+            config.authentication.jwt_keys.algorithm = JwtAlgorithm::Es256;
+        })
+        .await;
+        let url = format!("{}/.well-known/jwks.json", server.url);
+
+        let response = reqwest::get(&url).await.unwrap();
+        assert_eq!(response.headers()[reqwest::header::CACHE_CONTROL], "public, max-age=300");
+        let etag = response.headers()[reqwest::header::ETAG].clone();
+        let jwks: Value = response.json().await.unwrap();
+        assert_eq!(jwks["keys"][0]["kid"], "did:web:localhost#atproto");
+        assert_eq!(jwks["keys"][1]["alg"], "ES256");
+
+        let response = reqwest::Client::new()
+            .get(&url)
+            .header(reqwest::header::IF_NONE_MATCH, etag.clone())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_MODIFIED);
+
+        // A rotation is published right away, next to the retired key
+        let kid = server.ctx.jwt_keys.rotate().await.unwrap();
+        let response = reqwest::Client::new()
+            .get(&url)
+            .header(reqwest::header::IF_NONE_MATCH, etag)
+            .send()
+            .await
+            .unwrap();
+        let jwks: Value = response.json().await.unwrap();
+        assert_eq!(jwks["keys"].as_array().unwrap().len(), 3);
+        assert_eq!(jwks["keys"][1]["kid"], kid);
+    }
+
+    #[tokio::test]
+    async fn test_emailed_handle_change() {
+        let server = TestServer::start_with(|config| {
+            config.identity.handle_changes.require_email_confirmation = true;
+        })
+        .await;
+
+        let account: Value = server
+            .client()
+            .procedure(
+                "com.atproto.server.createAccount",
+                &serde_json::json!({
+                    "handle": "alice.test",
+                    "email": "alice@example.com",
+                    "password": "correct-horse-battery",
+                }),
+            )
+            .await
+            .unwrap();
+        let did = account["did"].as_str().unwrap();
+        let client = server.client().with_auth(account["accessJwt"].as_str().unwrap());
+
+        // The change waits for the emailed code
+        let pending: Value = client
+            .procedure("com.atproto.identity.updateHandle", &serde_json::json!({ "handle": "alice2.test" }))
+            .await
+            .unwrap();
+        assert_eq!(pending["confirmationRequired"], true);
+        assert_eq!(pending["handle"], "alice2.test");
+        assert_eq!(server.ctx.account_manager.get_account(did).await.unwrap().handle, "alice.test");
+
+        let token: String = sqlx::query_scalar("SELECT token FROM pending_handle_change WHERE did = ?1")
+            .bind(did)
+            .fetch_one(&server.ctx.account_db)
+            .await
+            .unwrap();
+        let err = client
+            .procedure::<Value>("app.aurora.identity.confirmHandleChange", &serde_json::json!({ "token": "nope" }))
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::NOT_FOUND);
+
+        client
+            .procedure::<Value>("app.aurora.identity.confirmHandleChange", &serde_json::json!({ "token": token }))
+            .await
+            .unwrap();
+        assert_eq!(server.ctx.account_manager.get_account(did).await.unwrap().handle, "alice2.test");
+        assert_eq!(server.plc.operations(did).len(), 2);
+
+        // Firehose consumers see the new handle
+        let mut firehose = client.subscribe_repos(Some(0)).await.unwrap();
+        let identity = loop {
+            let frame = firehose.next_frame().await.expect("no identity event");
+            if frame["$type"] == "#identity" && frame["handle"] == "alice2.test" {
+                break frame;
+            }
+        };
+        assert_eq!(identity["did"], did);
+    }
+
+    #[tokio::test]
+    async fn test_account_lifecycle_events() {
+        let server = TestServer::start().await;
+        let account: Value = server
+            .client()
+            .procedure(
+                "com.atproto.server.createAccount",
+                &serde_json::json!({ "handle": "bob.test", "password": "correct-horse-battery" }),
+            )
+            .await
+            .unwrap();
+        let did = account["did"].as_str().unwrap();
+        server
+            .client()
+            .with_auth(account["accessJwt"].as_str().unwrap())
+            .procedure::<Value>("com.atproto.server.deleteAccount", &serde_json::json!({ "password": "correct-horse-battery" }))
+            .await
+            .unwrap();
+
+        // Identity, then active, then deactivated pending deletion
+        let mut firehose = server.client().subscribe_repos(Some(0)).await.unwrap();
+        let mut events = Vec::new();
+        while events.len() < 3 {
+            let frame = firehose.next_frame().await.expect("missing lifecycle event");
+            if frame["did"] == did && (frame["$type"] == "#identity" || frame["$type"] == "#account") {
+                events.push(frame);
+            }
+        }
+        assert_eq!(events[0]["$type"], "#identity");
+        assert_eq!(events[0]["handle"], "bob.test");
+        assert_eq!(events[1]["$type"], "#account");
+        assert_eq!(events[1]["active"], true);
+        assert!(events[1].get("status").is_none());
+        assert_eq!(events[2]["active"], false);
+        assert_eq!(events[2]["status"], "deactivated");
+    }
+
+    #[tokio::test]
+    async fn test_label_stream() {
+        let server = TestServer::start().await;
+        let labels = &server.ctx.label_manager;
+        let uri = "at://did:plc:alice/app.bsky.feed.post/1";
+        let first = labels.apply_label(uri, None, "spam", "did:plc:admin", None).await.unwrap();
+
+        // Without a cursor only new labels arrive
+        let mut live = server.client().subscribe_labels(None).await.unwrap();
+        let mut replay = server.client().subscribe_labels(Some(0)).await.unwrap();
+        let removed = labels.remove_label(uri, None, "spam", "did:plc:admin").await.unwrap();
+
+        let frame = live.next_frame().await.expect("no label frame");
+        assert_eq!(frame["$type"], "#labels");
+        assert_eq!(frame["seq"], removed.id);
+        assert_eq!(frame["labels"][0]["neg"], true);
+
+        let seqs: Vec<Value> = vec![
+            replay.next_frame().await.unwrap()["seq"].clone(),
+            replay.next_frame().await.unwrap()["seq"].clone(),
+        ];
+        assert_eq!(seqs, vec![Value::from(first.id), Value::from(removed.id)]);
+
+        // A cursor past the newest label is refused
+        assert!(server.client().subscribe_labels(Some(99)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_body_limits() {
+        let server = TestServer::start_with(|config| config.body_limits.json = 4096).await;
+        let account = server
+            .client()
+            .create_account("alice.test", "correct-horse-battery")
+            .await
+            .unwrap();
+        let client = server.client().with_auth(&account.access_jwt);
+
+        // Blobs get the blob limit, well past axum's 2MB default
+        let uploaded = client.upload_blob(vec![0u8; 3 * 1024 * 1024], "video/mp4").await.unwrap();
+        assert_eq!(uploaded["blob"]["size"], 3 * 1024 * 1024);
+
+        // Procedures keep the JSON limit
+        let post = serde_json::json!({
+            "$type": "app.bsky.feed.post",
+            "text": "a".repeat(5000),
+            "createdAt": "2025-01-01T00:00:00.000Z",
+        });
+        let err = client.create_record(&account.did, "app.bsky.feed.post", post).await.unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::PAYLOAD_TOO_LARGE);
+        assert_eq!(err.error, "PayloadTooLarge");
+    }
+
+    #[tokio::test]
+    async fn test_record_rollback() {
+        let server = TestServer::start_with(|config| config.record_history.enabled = true).await;
+        let account = server
+            .client()
+            .create_account("alice.test", "correct-horse-battery")
+            .await
+            .unwrap();
+        let client = server.client().with_auth(&account.access_jwt);
+        let put_profile = |name: &str| {
+            serde_json::json!({
+                "repo": account.did,
+                "collection": "app.bsky.actor.profile",
+                "rkey": "self",
+                "record": { "$type": "app.bsky.actor.profile", "displayName": name },
+            })
+        };
+
+        let _: Value = client.procedure("com.atproto.repo.putRecord", &put_profile("Alice")).await.unwrap();
+        let _: Value = client.procedure("com.atproto.repo.putRecord", &put_profile("vandal")).await.unwrap();
+
+        let params = [("repo", account.did.as_str()), ("collection", "app.bsky.actor.profile"), ("rkey", "self")];
+        let history: Value = client.query("app.aurora.repo.listRecordVersions", &params).await.unwrap();
+        assert_eq!(history["versions"].as_array().unwrap().len(), 1);
+        assert_eq!(history["versions"][0]["value"]["displayName"], "Alice");
+
+        let restored: Value = client
+            .procedure(
+                "app.aurora.repo.rollbackRecord",
+                &serde_json::json!({
+                    "repo": account.did,
+                    "collection": "app.bsky.actor.profile",
+                    "rkey": "self",
+                    "cid": history["versions"][0]["cid"],
+                    "swapRecord": history["cid"],
+                }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(restored["cid"], history["versions"][0]["cid"]);
+
+        let record: Value = client.query("com.atproto.repo.getRecord", &params).await.unwrap();
+        assert_eq!(record["value"]["displayName"], "Alice");
+        assert_eq!(record["cid"], restored["cid"]);
+
+        // The vandalised version is kept in turn, and other repos are off limits
+        let history: Value = client.query("app.aurora.repo.listRecordVersions", &params).await.unwrap();
+        assert_eq!(history["versions"][0]["value"]["displayName"], "vandal");
+        let other = [("repo", "did:plc:someoneelse"), ("collection", "app.bsky.actor.profile"), ("rkey", "self")];
+        let err = client.query::<Value>("app.aurora.repo.listRecordVersions", &other).await.unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_records_at_commit_need_owner_or_admin() {
+        let server = TestServer::start().await;
+        let alice = server.client().create_account("alice.test", "correct-horse-battery").await.unwrap();
+        let bob = server.client().create_account("bob.test", "correct-horse-battery").await.unwrap();
+        let client = server.client().with_auth(&alice.access_jwt);
+        let _: Value = client
+            .procedure(
+                "com.atproto.repo.createRecord",
+                &serde_json::json!({
+                    "repo": alice.did,
+                    "collection": "app.bsky.feed.post",
+                    "record": { "$type": "app.bsky.feed.post", "text": "soon deleted", "createdAt": "2025-01-01T00:00:00Z" },
+                }),
+            )
+            .await
+            .unwrap();
+
+        let commits: Value = client.query("app.aurora.sync.listCommits", &[("did", alice.did.as_str())]).await.unwrap();
+        let commit = commits["commits"][0]["cid"].as_str().unwrap().to_string();
+        let params = [("did", alice.did.as_str()), ("commit", commit.as_str())];
+
+        let err = server.client().query::<Value>("app.aurora.sync.listRecordsAtCommit", &params).await.unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::UNAUTHORIZED);
+        let err = server
+            .client()
+            .with_auth(&bob.access_jwt)
+            .query::<Value>("app.aurora.sync.listRecordsAtCommit", &params)
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::FORBIDDEN);
+
+        let records: Value = client.query("app.aurora.sync.listRecordsAtCommit", &params).await.unwrap();
+        assert_eq!(records["records"][0]["value"]["text"], "soon deleted");
+    }
+
+    #[tokio::test]
+    async fn test_proxy_requires_auth_for_other_services() {
+        let server = TestServer::start().await;
+        let proxied = |target: &'static str| {
+            reqwest::Client::new()
+                .get(format!("{}/xrpc/app.example.getThing", server.url))
+                .header(crate::api::proxy::PROXY_HEADER, target)
+                .send()
+        };
+
+        // Refused before the DID is resolved
+        let response = proxied("did:web:169.254.169.254#metadata").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_signup_cap_ignores_forged_forwarded_for() {
+        let server = TestServer::start_with(|config| config.signup_throttle.per_ip_per_hour = 1).await;
+        let signup = |handle: &'static str, forwarded_for: &'static str| {
+            reqwest::Client::new()
+                .post(format!("{}/xrpc/com.atproto.server.createAccount", server.url))
+                .header("x-forwarded-for", forwarded_for)
+                .json(&serde_json::json!({ "handle": handle, "password": "correct-horse-battery" }))
+                .send()
+        };
+
+        assert!(signup("alice.test", "203.0.113.1").await.unwrap().status().is_success());
+        // A different claimed address is still the same client
+        let response = signup("bob.test", "203.0.113.2").await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_blocklist_ignores_forged_forwarded_for() {
+        let server = TestServer::start().await;
+        server.ctx.ip_blocklist.add("127.0.0.1", None, "did:plc:admin", None).await.unwrap();
+
+        // No trusted proxies, so the socket address is the client whatever the header says
+        let response = reqwest::Client::new()
+            .post(format!("{}/xrpc/com.atproto.server.createSession", server.url))
+            .header("x-forwarded-for", "203.0.113.5")
+            .json(&serde_json::json!({ "identifier": "alice.test", "password": "correct-horse-battery" }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_read_replica() {
+        let primary = TestServer::start().await;
+        let replica = TestServer::start_with(|config| {
+            config.replica.primary_url = Some(primary.url.clone());
+        })
+        .await;
+
+        // Writes made through the replica land on the primary
+        let account = replica
+            .client()
+            .create_account("alice.test", "correct-horse-battery")
+            .await
+            .unwrap();
+        assert!(primary.client().get_repo(&account.did).await.is_ok());
+
+        // Sync reads come from the replica's own (here unreplicated) storage
+        assert!(replica.client().get_repo(&account.did).await.is_err());
+        let repos: Value = replica.client().query("com.atproto.sync.listRepos", &[]).await.unwrap();
+        assert_eq!(repos["repos"], serde_json::json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_account_to_export() {
+        let server = TestServer::start().await;
+
+        let account = server
+            .client()
+            .create_account("alice.test", "correct-horse-battery")
+            .await
+            .unwrap();
+        assert!(account.did.starts_with("did:plc:"));
+        assert_eq!(server.plc.operations(&account.did).len(), 1);
+
+        // Writes need a session
+        let post = serde_json::json!({
+            "$type": "app.bsky.feed.post",
+            "text": "hello from the harness",
+            "createdAt": "2025-01-01T00:00:00.000Z",
+        });
+        let err = server
+            .client()
+            .create_record(&account.did, "app.bsky.feed.post", post.clone())
+            .await
+            .unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::UNAUTHORIZED);
+
+        let client = server.client().with_auth(&account.access_jwt);
+        let record = client
+            .create_record(&account.did, "app.bsky.feed.post", post)
+            .await
+            .unwrap();
+        let path = record.uri.trim_start_matches(&format!("at://{}/", account.did)).to_string();
+
+        // The write is on the firehose from the start of the log
+        let mut firehose = client.subscribe_repos(Some(0)).await.unwrap();
+        let (commit, op) = loop {
+            let commit = firehose.next_commit(&account.did).await.expect("no commit event");
+            let op = commit["ops"].as_array().unwrap().iter().find(|op| op["path"] == path.as_str()).cloned();
+            if let Some(op) = op {
+                break (commit, op);
+            }
+        };
+        assert_eq!(op["action"], "create");
+        assert_eq!(op["cid"], record.cid.as_str());
+
+        // And in the exported repo, rooted at that commit
+        let car = client.get_repo(&account.did).await.unwrap();
+        assert_eq!(car.roots[0].to_string(), commit["commit"].as_str().unwrap());
+        assert!(car.blocks.contains_key(&Cid::from_str(&record.cid).unwrap()));
+
+        let err = client.get_repo("did:plc:unknown").await.unwrap_err();
+        assert_eq!(err.status, reqwest::StatusCode::NOT_FOUND);
+        assert_eq!(err.error, "NotFound");
+    }
+}
@@ -503,8 +503,8 @@ function renderInvites(codes) {
         <tr>
             <td><code>${code.code}</code></td>
             <td>${code.uses || 0} / ${code.available || 1}</td>
-            <td>@${code.created_by || 'system'}</td>
-            <td>${new Date(code.created_at).toLocaleDateString()}</td>
+            <td>@${code.createdBy || 'system'}</td>
+            <td>${new Date(code.createdAt).toLocaleDateString()}</td>
             <td><span class="status-badge status-${code.disabled ? 'suspended' : 'active'}">${code.disabled ? 'Disabled' : 'Active'}</span></td>
             <td>
                 <button class="btn-sm btn-danger" onclick="disableInvite('${code.code}')">Disable</button>